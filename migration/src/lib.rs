@@ -4,6 +4,45 @@ mod m20250808_000001_consolidated_schema;
 mod m20250826_000001_add_pg_trgm_extension;
 mod m20251017_000001_rename_procedural_blank_to_blank;
 mod m20251017_000002_remove_water_volume_field;
+mod m20251101_000001_add_experiment_doi;
+mod m20251102_000001_add_asset_thumbnail_key;
+mod m20251103_000001_add_asset_checksum;
+mod m20251104_000001_add_asset_blob_key;
+mod m20251105_000001_add_asset_capture_link;
+mod m20251106_000001_add_tray_configuration_revisions;
+mod m20251107_000001_add_experiment_excluded_wells;
+mod m20251108_000001_add_probe_calibration;
+mod m20251109_000001_add_probe_hardware_metadata;
+mod m20251110_000001_add_sample_aliquots;
+mod m20251111_000001_create_treatment_dilutions;
+mod m20251112_000001_add_sample_barcode;
+mod m20251113_000001_create_sample_custody_events;
+mod m20251114_000001_add_sample_storage;
+mod m20251115_000001_add_sample_qc_status;
+mod m20251116_000001_create_sample_pool_sources;
+mod m20251117_000001_add_location_geometry;
+mod m20251118_000001_create_project_members;
+mod m20251119_000001_add_project_archiving;
+mod m20251120_000001_create_sample_weather;
+mod m20251121_000001_add_location_site_details;
+mod m20251122_000001_add_sample_track;
+mod m20251123_000001_create_api_keys;
+mod m20251124_000001_create_share_grants;
+mod m20251125_000001_create_audit_log;
+mod m20251126_000001_add_labs;
+mod m20251127_000001_create_download_tokens;
+mod m20251128_000001_add_soft_delete;
+mod m20251129_000001_create_record_versions;
+mod m20251130_000001_create_webhooks;
+mod m20251201_000001_create_idempotency_keys;
+mod m20251202_000001_create_experiment_results_summaries;
+mod m20251203_000001_partition_time_series;
+mod m20251204_000001_create_freezing_results;
+mod m20251205_000001_create_planned_experiments;
+mod m20251206_000001_create_probe_calibrations;
+mod m20251207_000001_create_experiment_comments;
+mod m20251208_000001_add_download_token_resumes;
+mod m20251209_000001_create_probe_temperature_series;
 pub struct Migrator;
 
 #[async_trait::async_trait]
@@ -14,6 +53,45 @@ impl MigratorTrait for Migrator {
             Box::new(m20250826_000001_add_pg_trgm_extension::Migration),
             Box::new(m20251017_000001_rename_procedural_blank_to_blank::Migration),
             Box::new(m20251017_000002_remove_water_volume_field::Migration),
+            Box::new(m20251101_000001_add_experiment_doi::Migration),
+            Box::new(m20251102_000001_add_asset_thumbnail_key::Migration),
+            Box::new(m20251103_000001_add_asset_checksum::Migration),
+            Box::new(m20251104_000001_add_asset_blob_key::Migration),
+            Box::new(m20251105_000001_add_asset_capture_link::Migration),
+            Box::new(m20251106_000001_add_tray_configuration_revisions::Migration),
+            Box::new(m20251107_000001_add_experiment_excluded_wells::Migration),
+            Box::new(m20251108_000001_add_probe_calibration::Migration),
+            Box::new(m20251109_000001_add_probe_hardware_metadata::Migration),
+            Box::new(m20251110_000001_add_sample_aliquots::Migration),
+            Box::new(m20251111_000001_create_treatment_dilutions::Migration),
+            Box::new(m20251112_000001_add_sample_barcode::Migration),
+            Box::new(m20251113_000001_create_sample_custody_events::Migration),
+            Box::new(m20251114_000001_add_sample_storage::Migration),
+            Box::new(m20251115_000001_add_sample_qc_status::Migration),
+            Box::new(m20251116_000001_create_sample_pool_sources::Migration),
+            Box::new(m20251117_000001_add_location_geometry::Migration),
+            Box::new(m20251118_000001_create_project_members::Migration),
+            Box::new(m20251119_000001_add_project_archiving::Migration),
+            Box::new(m20251120_000001_create_sample_weather::Migration),
+            Box::new(m20251121_000001_add_location_site_details::Migration),
+            Box::new(m20251122_000001_add_sample_track::Migration),
+            Box::new(m20251123_000001_create_api_keys::Migration),
+            Box::new(m20251124_000001_create_share_grants::Migration),
+            Box::new(m20251125_000001_create_audit_log::Migration),
+            Box::new(m20251126_000001_add_labs::Migration),
+            Box::new(m20251127_000001_create_download_tokens::Migration),
+            Box::new(m20251128_000001_add_soft_delete::Migration),
+            Box::new(m20251129_000001_create_record_versions::Migration),
+            Box::new(m20251130_000001_create_webhooks::Migration),
+            Box::new(m20251201_000001_create_idempotency_keys::Migration),
+            Box::new(m20251202_000001_create_experiment_results_summaries::Migration),
+            Box::new(m20251203_000001_partition_time_series::Migration),
+            Box::new(m20251204_000001_create_freezing_results::Migration),
+            Box::new(m20251205_000001_create_planned_experiments::Migration),
+            Box::new(m20251206_000001_create_probe_calibrations::Migration),
+            Box::new(m20251207_000001_create_experiment_comments::Migration),
+            Box::new(m20251208_000001_add_download_token_resumes::Migration),
+            Box::new(m20251209_000001_create_probe_temperature_series::Migration),
         ]
     }
 }
@@ -0,0 +1,73 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(S3Assets::Table)
+                    .add_column(ColumnDef::new(S3Assets::BlobS3Key).text().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        // Duplicates are found by checksum, and shared objects are reference
+        // counted by blob key
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_s3_assets_checksum_sha256")
+                    .table(S3Assets::Table)
+                    .col(S3Assets::ChecksumSha256)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_s3_assets_blob_s3_key")
+                    .table(S3Assets::Table)
+                    .col(S3Assets::BlobS3Key)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_s3_assets_blob_s3_key")
+                    .table(S3Assets::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_s3_assets_checksum_sha256")
+                    .table(S3Assets::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(S3Assets::Table)
+                    .drop_column(S3Assets::BlobS3Key)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum S3Assets {
+    Table,
+    ChecksumSha256,
+    BlobS3Key,
+}
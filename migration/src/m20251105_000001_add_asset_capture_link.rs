@@ -0,0 +1,73 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(S3Assets::Table)
+                    .add_column(
+                        ColumnDef::new(S3Assets::CapturedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // SQLite cannot add a foreign key to an existing table, but accepts one
+        // inline on a new column, as does PostgreSQL
+        let uuid_type = match manager.get_database_backend() {
+            sea_orm::DatabaseBackend::Postgres => "uuid",
+            _ => "uuid_text",
+        };
+        manager
+            .get_connection()
+            .execute_unprepared(&format!(
+                "ALTER TABLE s3_assets ADD COLUMN temperature_reading_id {uuid_type} \
+                 REFERENCES temperature_readings (id) ON DELETE SET NULL"
+            ))
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_s3_assets_temperature_reading_id")
+                    .table(S3Assets::Table)
+                    .col(S3Assets::TemperatureReadingId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_s3_assets_temperature_reading_id")
+                    .table(S3Assets::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(S3Assets::Table)
+                    .drop_column(S3Assets::TemperatureReadingId)
+                    .drop_column(S3Assets::CapturedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum S3Assets {
+    Table,
+    CapturedAt,
+    TemperatureReadingId,
+}
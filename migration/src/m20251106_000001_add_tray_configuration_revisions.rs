@@ -0,0 +1,72 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TrayConfigurations::Table)
+                    .add_column(
+                        ColumnDef::new(TrayConfigurations::Revision)
+                            .integer()
+                            .not_null()
+                            .default(1),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        let uuid_type = match manager.get_database_backend() {
+            sea_orm::DatabaseBackend::Postgres => "uuid",
+            _ => "uuid_text",
+        };
+        manager
+            .get_connection()
+            .execute_unprepared(&format!(
+                "ALTER TABLE tray_configurations ADD COLUMN revision_of_id {uuid_type} \
+                 REFERENCES tray_configurations (id) ON DELETE SET NULL"
+            ))
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_tray_configurations_revision_of_id")
+                    .table(TrayConfigurations::Table)
+                    .col(TrayConfigurations::RevisionOfId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_tray_configurations_revision_of_id")
+                    .table(TrayConfigurations::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TrayConfigurations::Table)
+                    .drop_column(TrayConfigurations::RevisionOfId)
+                    .drop_column(TrayConfigurations::Revision)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum TrayConfigurations {
+    Table,
+    Revision,
+    RevisionOfId,
+}
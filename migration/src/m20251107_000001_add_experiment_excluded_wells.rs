@@ -0,0 +1,108 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ExperimentExcludedWells::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ExperimentExcludedWells::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(ExperimentExcludedWells::ExperimentId)
+                            .uuid()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ExperimentExcludedWells::WellId)
+                            .uuid()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(ExperimentExcludedWells::Reason).text())
+                    .col(
+                        ColumnDef::new(ExperimentExcludedWells::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_experiment_excluded_wells_experiment")
+                            .from(
+                                ExperimentExcludedWells::Table,
+                                ExperimentExcludedWells::ExperimentId,
+                            )
+                            .to(Experiments::Table, Experiments::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::NoAction),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_experiment_excluded_wells_well")
+                            .from(
+                                ExperimentExcludedWells::Table,
+                                ExperimentExcludedWells::WellId,
+                            )
+                            .to(Wells::Table, Wells::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::NoAction),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_experiment_excluded_wells_experiment_well")
+                    .table(ExperimentExcludedWells::Table)
+                    .col(ExperimentExcludedWells::ExperimentId)
+                    .col(ExperimentExcludedWells::WellId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(ExperimentExcludedWells::Table)
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ExperimentExcludedWells {
+    Table,
+    Id,
+    ExperimentId,
+    WellId,
+    Reason,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Experiments {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Wells {
+    Table,
+    Id,
+}
@@ -0,0 +1,53 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite takes one column per statement
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Probes::Table)
+                    .add_column(ColumnDef::new(Probes::CalibrationSlope).decimal().null())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Probes::Table)
+                    .add_column(ColumnDef::new(Probes::CalibrationOffset).decimal().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Probes::Table)
+                    .drop_column(Probes::CalibrationOffset)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Probes::Table)
+                    .drop_column(Probes::CalibrationSlope)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Probes {
+    Table,
+    CalibrationSlope,
+    CalibrationOffset,
+}
@@ -0,0 +1,83 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite takes one column per statement
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Probes::Table)
+                    .add_column(ColumnDef::new(Probes::SerialNumber).text().null())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Probes::Table)
+                    .add_column(ColumnDef::new(Probes::SensorType).text().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        let uuid_type = match manager.get_database_backend() {
+            sea_orm::DatabaseBackend::Postgres => "uuid",
+            _ => "uuid_text",
+        };
+        manager
+            .get_connection()
+            .execute_unprepared(&format!(
+                "ALTER TABLE probes ADD COLUMN calibration_certificate_asset_id {uuid_type} \
+                 REFERENCES s3_assets (id) ON DELETE SET NULL"
+            ))
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_probes_serial_number")
+                    .table(Probes::Table)
+                    .col(Probes::SerialNumber)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_probes_serial_number")
+                    .table(Probes::Table)
+                    .to_owned(),
+            )
+            .await?;
+        for column in [
+            Probes::CalibrationCertificateAssetId,
+            Probes::SensorType,
+            Probes::SerialNumber,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Probes::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Probes {
+    Table,
+    SerialNumber,
+    SensorType,
+    CalibrationCertificateAssetId,
+}
@@ -0,0 +1,69 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Aliquots of a deleted sample are kept as samples of their own
+        let uuid_type = match manager.get_database_backend() {
+            sea_orm::DatabaseBackend::Postgres => "uuid",
+            _ => "uuid_text",
+        };
+        manager
+            .get_connection()
+            .execute_unprepared(&format!(
+                "ALTER TABLE samples ADD COLUMN parent_sample_id {uuid_type} \
+                 REFERENCES samples (id) ON DELETE SET NULL"
+            ))
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Samples::Table)
+                    .add_column(ColumnDef::new(Samples::AliquotLabel).text().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_samples_parent_sample_id")
+                    .table(Samples::Table)
+                    .col(Samples::ParentSampleId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_samples_parent_sample_id")
+                    .table(Samples::Table)
+                    .to_owned(),
+            )
+            .await?;
+        for column in [Samples::AliquotLabel, Samples::ParentSampleId] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Samples::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Samples {
+    Table,
+    ParentSampleId,
+    AliquotLabel,
+}
@@ -0,0 +1,136 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(TreatmentDilutions::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(TreatmentDilutions::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(TreatmentDilutions::TreatmentId)
+                            .uuid()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(TreatmentDilutions::DilutionFactor)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(TreatmentDilutions::PreparedVolumeLitres)
+                            .decimal_len(16, 10)
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(TreatmentDilutions::PreparedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(ColumnDef::new(TreatmentDilutions::Operator).text().null())
+                    .col(ColumnDef::new(TreatmentDilutions::Notes).text().null())
+                    .col(
+                        ColumnDef::new(TreatmentDilutions::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        ColumnDef::new(TreatmentDilutions::LastUpdated)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_treatment_dilutions_treatment")
+                            .from(TreatmentDilutions::Table, TreatmentDilutions::TreatmentId)
+                            .to(Treatments::Table, Treatments::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::NoAction),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_treatment_dilutions_treatment_factor")
+                    .table(TreatmentDilutions::Table)
+                    .col(TreatmentDilutions::TreatmentId)
+                    .col(TreatmentDilutions::DilutionFactor)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        let uuid_type = match manager.get_database_backend() {
+            sea_orm::DatabaseBackend::Postgres => "uuid",
+            _ => "uuid_text",
+        };
+        manager
+            .get_connection()
+            .execute_unprepared(&format!(
+                "ALTER TABLE regions ADD COLUMN dilution_id {uuid_type} \
+                 REFERENCES treatment_dilutions (id) ON DELETE SET NULL"
+            ))
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Regions::Table)
+                    .drop_column(Regions::DilutionId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(TreatmentDilutions::Table)
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum TreatmentDilutions {
+    Table,
+    Id,
+    TreatmentId,
+    DilutionFactor,
+    PreparedVolumeLitres,
+    PreparedAt,
+    Operator,
+    Notes,
+    CreatedAt,
+    LastUpdated,
+}
+
+#[derive(DeriveIden)]
+enum Treatments {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Regions {
+    Table,
+    DilutionId,
+}
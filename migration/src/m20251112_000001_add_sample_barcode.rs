@@ -0,0 +1,55 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Samples::Table)
+                    .add_column(ColumnDef::new(Samples::Barcode).text().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        // Unique indexes allow any number of NULLs on both backends
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_samples_barcode")
+                    .table(Samples::Table)
+                    .col(Samples::Barcode)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_samples_barcode")
+                    .table(Samples::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Samples::Table)
+                    .drop_column(Samples::Barcode)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Samples {
+    Table,
+    Barcode,
+}
@@ -0,0 +1,142 @@
+use sea_orm_migration::prelude::extension::postgres::Type;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let mut event_type = ColumnDef::new(SampleCustodyEvents::EventType);
+        if manager.get_database_backend() == sea_orm::DatabaseBackend::Postgres {
+            manager
+                .create_type(
+                    Type::create()
+                        .as_enum(CustodyEventType::Table)
+                        .values([
+                            CustodyEventType::Collected,
+                            CustodyEventType::Shipped,
+                            CustodyEventType::Received,
+                            CustodyEventType::Stored,
+                            CustodyEventType::Processed,
+                        ])
+                        .to_owned(),
+                )
+                .await?;
+            event_type.custom(CustodyEventType::Table);
+        } else {
+            event_type.text();
+        }
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(SampleCustodyEvents::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(SampleCustodyEvents::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(SampleCustodyEvents::SampleId)
+                            .uuid()
+                            .not_null(),
+                    )
+                    .col(event_type.not_null())
+                    .col(
+                        ColumnDef::new(SampleCustodyEvents::OccurredAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(SampleCustodyEvents::Username).text().null())
+                    .col(ColumnDef::new(SampleCustodyEvents::Location).text().null())
+                    .col(ColumnDef::new(SampleCustodyEvents::Notes).text().null())
+                    .col(
+                        ColumnDef::new(SampleCustodyEvents::RecordedBy)
+                            .text()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(SampleCustodyEvents::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_sample_custody_events_sample")
+                            .from(SampleCustodyEvents::Table, SampleCustodyEvents::SampleId)
+                            .to(Samples::Table, Samples::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::NoAction),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_sample_custody_events_sample_occurred_at")
+                    .table(SampleCustodyEvents::Table)
+                    .col(SampleCustodyEvents::SampleId)
+                    .col(SampleCustodyEvents::OccurredAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(SampleCustodyEvents::Table)
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await?;
+        if manager.get_database_backend() == sea_orm::DatabaseBackend::Postgres {
+            manager
+                .drop_type(
+                    Type::drop()
+                        .name(CustodyEventType::Table)
+                        .if_exists()
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum SampleCustodyEvents {
+    Table,
+    Id,
+    SampleId,
+    EventType,
+    OccurredAt,
+    Username,
+    Location,
+    Notes,
+    RecordedBy,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum CustodyEventType {
+    Table,
+    Collected,
+    Shipped,
+    Received,
+    Stored,
+    Processed,
+}
+
+#[derive(DeriveIden)]
+enum Samples {
+    Table,
+    Id,
+}
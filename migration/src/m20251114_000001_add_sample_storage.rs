@@ -0,0 +1,73 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite takes one column per statement
+        for column in [
+            Samples::StorageFreezer,
+            Samples::StorageShelf,
+            Samples::StorageBox,
+            Samples::StoragePosition,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Samples::Table)
+                        .add_column(ColumnDef::new(column).text().null())
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_samples_storage_freezer_box")
+                    .table(Samples::Table)
+                    .col(Samples::StorageFreezer)
+                    .col(Samples::StorageBox)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_samples_storage_freezer_box")
+                    .table(Samples::Table)
+                    .to_owned(),
+            )
+            .await?;
+        for column in [
+            Samples::StoragePosition,
+            Samples::StorageBox,
+            Samples::StorageShelf,
+            Samples::StorageFreezer,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Samples::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Samples {
+    Table,
+    StorageFreezer,
+    StorageShelf,
+    StorageBox,
+    StoragePosition,
+}
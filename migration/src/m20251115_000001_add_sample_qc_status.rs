@@ -0,0 +1,113 @@
+use sea_orm_migration::prelude::extension::postgres::Type;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let mut qc_status = ColumnDef::new(Samples::QcStatus);
+        if manager.get_database_backend() == sea_orm::DatabaseBackend::Postgres {
+            manager
+                .create_type(
+                    Type::create()
+                        .as_enum(SampleQcStatus::Table)
+                        .values([
+                            SampleQcStatus::Pending,
+                            SampleQcStatus::Accepted,
+                            SampleQcStatus::Rejected,
+                            SampleQcStatus::Flagged,
+                        ])
+                        .to_owned(),
+                )
+                .await?;
+            qc_status.custom(SampleQcStatus::Table);
+        } else {
+            qc_status.text();
+        }
+
+        // SQLite takes one column per statement
+        for column in [
+            qc_status.not_null().default("pending").to_owned(),
+            ColumnDef::new(Samples::QcReason).text().null().to_owned(),
+            ColumnDef::new(Samples::QcReviewedBy)
+                .text()
+                .null()
+                .to_owned(),
+            ColumnDef::new(Samples::QcReviewedAt)
+                .timestamp_with_time_zone()
+                .null()
+                .to_owned(),
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Samples::Table)
+                        .add_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_samples_qc_status")
+                    .table(Samples::Table)
+                    .col(Samples::QcStatus)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_samples_qc_status")
+                    .table(Samples::Table)
+                    .to_owned(),
+            )
+            .await?;
+        for column in [
+            Samples::QcReviewedAt,
+            Samples::QcReviewedBy,
+            Samples::QcReason,
+            Samples::QcStatus,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Samples::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        if manager.get_database_backend() == sea_orm::DatabaseBackend::Postgres {
+            manager
+                .drop_type(Type::drop().name(SampleQcStatus::Table).to_owned())
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Samples {
+    Table,
+    QcStatus,
+    QcReason,
+    QcReviewedBy,
+    QcReviewedAt,
+}
+
+#[derive(DeriveIden)]
+enum SampleQcStatus {
+    Table,
+    Pending,
+    Accepted,
+    Rejected,
+    Flagged,
+}
@@ -0,0 +1,110 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(SamplePoolSources::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(SamplePoolSources::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(SamplePoolSources::PooledSampleId)
+                            .uuid()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(SamplePoolSources::SourceSampleId)
+                            .uuid()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(SamplePoolSources::Fraction)
+                            .decimal_len(16, 10)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(SamplePoolSources::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_sample_pool_sources_pooled_sample")
+                            .from(SamplePoolSources::Table, SamplePoolSources::PooledSampleId)
+                            .to(Samples::Table, Samples::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::NoAction),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_sample_pool_sources_source_sample")
+                            .from(SamplePoolSources::Table, SamplePoolSources::SourceSampleId)
+                            .to(Samples::Table, Samples::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::NoAction),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_sample_pool_sources_pooled_source")
+                    .table(SamplePoolSources::Table)
+                    .col(SamplePoolSources::PooledSampleId)
+                    .col(SamplePoolSources::SourceSampleId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_sample_pool_sources_source")
+                    .table(SamplePoolSources::Table)
+                    .col(SamplePoolSources::SourceSampleId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(SamplePoolSources::Table)
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum SamplePoolSources {
+    Table,
+    Id,
+    PooledSampleId,
+    SourceSampleId,
+    Fraction,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Samples {
+    Table,
+    Id,
+}
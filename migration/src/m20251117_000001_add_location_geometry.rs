@@ -0,0 +1,80 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // PostGIS is only available on PostgreSQL
+        if manager.get_database_backend() != sea_orm::DatabaseBackend::Postgres {
+            return Ok(());
+        }
+        let db = manager.get_connection();
+
+        // The convex hull of the location's samples, kept up to date as
+        // samples are added, moved or removed
+        db.execute_unprepared("ALTER TABLE locations ADD COLUMN geom geometry(Geometry, 4326);")
+            .await?;
+        db.execute_unprepared("CREATE INDEX idx_locations_geom ON locations USING GIST (geom);")
+            .await?;
+        db.execute_unprepared(
+            "CREATE FUNCTION refresh_location_geom(location uuid) RETURNS void AS $$
+                UPDATE locations
+                SET geom = (
+                    SELECT ST_ConvexHull(ST_Collect(samples.geom))
+                    FROM samples
+                    WHERE samples.location_id = location
+                )
+                WHERE id = location;
+            $$ LANGUAGE sql;",
+        )
+        .await?;
+        db.execute_unprepared(
+            "CREATE FUNCTION refresh_location_geom_from_samples() RETURNS trigger AS $$
+            BEGIN
+                IF TG_OP IN ('UPDATE', 'DELETE') AND OLD.location_id IS NOT NULL THEN
+                    PERFORM refresh_location_geom(OLD.location_id);
+                END IF;
+                IF TG_OP IN ('INSERT', 'UPDATE') AND NEW.location_id IS NOT NULL
+                    AND (TG_OP = 'INSERT' OR NEW.location_id IS DISTINCT FROM OLD.location_id
+                         OR NEW.geom IS DISTINCT FROM OLD.geom) THEN
+                    PERFORM refresh_location_geom(NEW.location_id);
+                END IF;
+                RETURN NULL;
+            END;
+            $$ LANGUAGE plpgsql;",
+        )
+        .await?;
+        db.execute_unprepared(
+            "CREATE TRIGGER samples_refresh_location_geom
+                AFTER INSERT OR UPDATE OF location_id, latitude, longitude OR DELETE ON samples
+                FOR EACH ROW EXECUTE FUNCTION refresh_location_geom_from_samples();",
+        )
+        .await?;
+        db.execute_unprepared("SELECT refresh_location_geom(id) FROM locations;")
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if manager.get_database_backend() != sea_orm::DatabaseBackend::Postgres {
+            return Ok(());
+        }
+        let db = manager.get_connection();
+
+        db.execute_unprepared("DROP TRIGGER IF EXISTS samples_refresh_location_geom ON samples;")
+            .await?;
+        db.execute_unprepared("DROP FUNCTION IF EXISTS refresh_location_geom_from_samples();")
+            .await?;
+        db.execute_unprepared("DROP FUNCTION IF EXISTS refresh_location_geom(uuid);")
+            .await?;
+        db.execute_unprepared("DROP INDEX IF EXISTS idx_locations_geom;")
+            .await?;
+        db.execute_unprepared("ALTER TABLE locations DROP COLUMN IF EXISTS geom;")
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,132 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ProjectMembers::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ProjectMembers::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(ProjectMembers::ProjectId).uuid().not_null())
+                    .col(ColumnDef::new(ProjectMembers::Username).text().not_null())
+                    .col(
+                        ColumnDef::new(ProjectMembers::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_project_members_project")
+                            .from(ProjectMembers::Table, ProjectMembers::ProjectId)
+                            .to(Projects::Table, Projects::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::NoAction),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_project_members_project_username")
+                    .table(ProjectMembers::Table)
+                    .col(ProjectMembers::ProjectId)
+                    .col(ProjectMembers::Username)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_project_members_username")
+                    .table(ProjectMembers::Table)
+                    .col(ProjectMembers::Username)
+                    .to_owned(),
+            )
+            .await?;
+
+        let uuid_type = match manager.get_database_backend() {
+            sea_orm::DatabaseBackend::Postgres => "uuid",
+            _ => "uuid_text",
+        };
+        manager
+            .get_connection()
+            .execute_unprepared(&format!(
+                "ALTER TABLE experiments ADD COLUMN project_id {uuid_type} \
+                 REFERENCES projects (id) ON DELETE SET NULL"
+            ))
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_experiments_project_id")
+                    .table(Experiments::Table)
+                    .col(Experiments::ProjectId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_experiments_project_id")
+                    .table(Experiments::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Experiments::Table)
+                    .drop_column(Experiments::ProjectId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(ProjectMembers::Table)
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ProjectMembers {
+    Table,
+    Id,
+    ProjectId,
+    Username,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Projects {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Experiments {
+    Table,
+    ProjectId,
+}
@@ -0,0 +1,129 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let mut table = Table::create();
+        table
+            .table(SampleWeather::Table)
+            .if_not_exists()
+            .col(
+                ColumnDef::new(SampleWeather::Id)
+                    .uuid()
+                    .not_null()
+                    .primary_key(),
+            )
+            .col(ColumnDef::new(SampleWeather::SampleId).uuid().not_null())
+            .col(ColumnDef::new(SampleWeather::Source).text().not_null())
+            .col(
+                ColumnDef::new(SampleWeather::Latitude)
+                    .decimal_len(9, 6)
+                    .not_null(),
+            )
+            .col(
+                ColumnDef::new(SampleWeather::Longitude)
+                    .decimal_len(9, 6)
+                    .not_null(),
+            )
+            .col(
+                ColumnDef::new(SampleWeather::StartTime)
+                    .timestamp_with_time_zone()
+                    .not_null(),
+            )
+            .col(
+                ColumnDef::new(SampleWeather::StopTime)
+                    .timestamp_with_time_zone()
+                    .null(),
+            );
+        for summary in [
+            SampleWeather::TemperatureMeanC,
+            SampleWeather::TemperatureMinC,
+            SampleWeather::TemperatureMaxC,
+            SampleWeather::WindSpeedMeanMS,
+            SampleWeather::WindSpeedMaxMS,
+            SampleWeather::WindDirectionMeanDeg,
+            SampleWeather::BoundaryLayerHeightMeanM,
+            SampleWeather::BoundaryLayerHeightMinM,
+            SampleWeather::BoundaryLayerHeightMaxM,
+        ] {
+            table.col(ColumnDef::new(summary).decimal_len(12, 3).null());
+        }
+        table
+            .col(
+                ColumnDef::new(SampleWeather::Hourly)
+                    .json_binary()
+                    .not_null(),
+            )
+            .col(
+                ColumnDef::new(SampleWeather::FetchedAt)
+                    .timestamp_with_time_zone()
+                    .not_null()
+                    .default(Expr::current_timestamp()),
+            )
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk_sample_weather_sample")
+                    .from(SampleWeather::Table, SampleWeather::SampleId)
+                    .to(Samples::Table, Samples::Id)
+                    .on_delete(ForeignKeyAction::Cascade)
+                    .on_update(ForeignKeyAction::NoAction),
+            );
+        manager.create_table(table.to_owned()).await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_sample_weather_sample")
+                    .table(SampleWeather::Table)
+                    .col(SampleWeather::SampleId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(SampleWeather::Table)
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum SampleWeather {
+    Table,
+    Id,
+    SampleId,
+    Source,
+    Latitude,
+    Longitude,
+    StartTime,
+    StopTime,
+    TemperatureMeanC,
+    TemperatureMinC,
+    TemperatureMaxC,
+    #[sea_orm(iden = "wind_speed_mean_m_s")]
+    WindSpeedMeanMS,
+    #[sea_orm(iden = "wind_speed_max_m_s")]
+    WindSpeedMaxMS,
+    WindDirectionMeanDeg,
+    BoundaryLayerHeightMeanM,
+    BoundaryLayerHeightMinM,
+    BoundaryLayerHeightMaxM,
+    Hourly,
+    FetchedAt,
+}
+
+#[derive(DeriveIden)]
+enum Samples {
+    Table,
+    Id,
+}
@@ -0,0 +1,64 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite takes one column per statement
+        for column in [
+            ColumnDef::new(Locations::Latitude)
+                .decimal_len(9, 6)
+                .null()
+                .to_owned(),
+            ColumnDef::new(Locations::Longitude)
+                .decimal_len(9, 6)
+                .null()
+                .to_owned(),
+            ColumnDef::new(Locations::ElevationM)
+                .decimal_len(8, 2)
+                .null()
+                .to_owned(),
+            ColumnDef::new(Locations::Timezone).text().null().to_owned(),
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Locations::Table)
+                        .add_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [
+            Locations::Timezone,
+            Locations::ElevationM,
+            Locations::Longitude,
+            Locations::Latitude,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Locations::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Locations {
+    Table,
+    Latitude,
+    Longitude,
+    ElevationM,
+    Timezone,
+}
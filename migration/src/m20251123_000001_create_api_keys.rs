@@ -0,0 +1,83 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ApiKeys::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(ApiKeys::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(ApiKeys::Name).text().not_null())
+                    .col(ColumnDef::new(ApiKeys::Username).text().not_null())
+                    .col(ColumnDef::new(ApiKeys::Role).text().not_null())
+                    .col(ColumnDef::new(ApiKeys::Scopes).json_binary().not_null())
+                    .col(ColumnDef::new(ApiKeys::KeyPrefix).text().not_null())
+                    .col(ColumnDef::new(ApiKeys::KeyHash).text().not_null())
+                    .col(ColumnDef::new(ApiKeys::RateLimitPerMinute).integer().null())
+                    .col(
+                        ColumnDef::new(ApiKeys::ExpiresAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(ApiKeys::LastUsedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(ApiKeys::RevokedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(ColumnDef::new(ApiKeys::CreatedBy).text().null())
+                    .col(
+                        ColumnDef::new(ApiKeys::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_api_keys_key_prefix")
+                    .table(ApiKeys::Table)
+                    .col(ApiKeys::KeyPrefix)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ApiKeys::Table).if_exists().to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ApiKeys {
+    Table,
+    Id,
+    Name,
+    Username,
+    Role,
+    Scopes,
+    KeyPrefix,
+    KeyHash,
+    RateLimitPerMinute,
+    ExpiresAt,
+    LastUsedAt,
+    RevokedAt,
+    CreatedBy,
+    CreatedAt,
+}
@@ -0,0 +1,114 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Experiments::Table)
+                    .add_column(ColumnDef::new(Experiments::CreatedBy).text().null())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Samples::Table)
+                    .add_column(ColumnDef::new(Samples::CreatedBy).text().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(ShareGrants::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ShareGrants::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(ShareGrants::ResourceType).text().not_null())
+                    .col(ColumnDef::new(ShareGrants::ResourceId).uuid().not_null())
+                    .col(ColumnDef::new(ShareGrants::GranteeType).text().not_null())
+                    .col(ColumnDef::new(ShareGrants::Grantee).text().not_null())
+                    .col(ColumnDef::new(ShareGrants::Permission).text().not_null())
+                    .col(ColumnDef::new(ShareGrants::GrantedBy).text().null())
+                    .col(
+                        ColumnDef::new(ShareGrants::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_share_grants_resource_grantee")
+                    .table(ShareGrants::Table)
+                    .col(ShareGrants::ResourceType)
+                    .col(ShareGrants::ResourceId)
+                    .col(ShareGrants::GranteeType)
+                    .col(ShareGrants::Grantee)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ShareGrants::Table).to_owned())
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Samples::Table)
+                    .drop_column(Samples::CreatedBy)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Experiments::Table)
+                    .drop_column(Experiments::CreatedBy)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Experiments {
+    Table,
+    CreatedBy,
+}
+
+#[derive(DeriveIden)]
+enum Samples {
+    Table,
+    CreatedBy,
+}
+
+#[derive(DeriveIden)]
+enum ShareGrants {
+    Table,
+    Id,
+    ResourceType,
+    ResourceId,
+    GranteeType,
+    Grantee,
+    Permission,
+    GrantedBy,
+    CreatedAt,
+}
@@ -0,0 +1,80 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(AuditLog::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(AuditLog::Id).uuid().not_null().primary_key())
+                    .col(
+                        ColumnDef::new(AuditLog::OccurredAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(ColumnDef::new(AuditLog::Username).text().null())
+                    .col(ColumnDef::new(AuditLog::Action).text().not_null())
+                    .col(ColumnDef::new(AuditLog::Resource).text().not_null())
+                    .col(ColumnDef::new(AuditLog::RecordId).uuid().null())
+                    .col(ColumnDef::new(AuditLog::Method).text().not_null())
+                    .col(ColumnDef::new(AuditLog::Path).text().not_null())
+                    .col(ColumnDef::new(AuditLog::Status).integer().not_null())
+                    .col(ColumnDef::new(AuditLog::RequestId).text().null())
+                    .col(ColumnDef::new(AuditLog::Before).json_binary().null())
+                    .col(ColumnDef::new(AuditLog::After).json_binary().null())
+                    .col(ColumnDef::new(AuditLog::Changes).json_binary().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_audit_log_resource_record")
+                    .table(AuditLog::Table)
+                    .col(AuditLog::Resource)
+                    .col(AuditLog::RecordId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_audit_log_occurred_at")
+                    .table(AuditLog::Table)
+                    .col(AuditLog::OccurredAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AuditLog::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum AuditLog {
+    Table,
+    Id,
+    OccurredAt,
+    Username,
+    Action,
+    Resource,
+    RecordId,
+    Method,
+    Path,
+    Status,
+    RequestId,
+    Before,
+    After,
+    Changes,
+}
@@ -0,0 +1,74 @@
+use sea_orm_migration::prelude::*;
+
+/// Tables whose records belong to a lab. The records of everything else
+/// belong to the lab of what they hang from.
+const TABLES: [Tables; 6] = [
+    Tables::Projects,
+    Tables::Locations,
+    Tables::Experiments,
+    Tables::Samples,
+    Tables::TrayConfigurations,
+    Tables::ApiKeys,
+];
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for table in TABLES {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(table)
+                        .add_column(ColumnDef::new(Tables::Lab).text().null())
+                        .to_owned(),
+                )
+                .await?;
+            manager
+                .create_index(
+                    Index::create()
+                        .name(format!("idx_{}_lab", table.to_string()))
+                        .table(table)
+                        .col(Tables::Lab)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for table in TABLES {
+            manager
+                .drop_index(
+                    Index::drop()
+                        .name(format!("idx_{}_lab", table.to_string()))
+                        .table(table)
+                        .to_owned(),
+                )
+                .await?;
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(table)
+                        .drop_column(Tables::Lab)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+enum Tables {
+    Projects,
+    Locations,
+    Experiments,
+    Samples,
+    TrayConfigurations,
+    ApiKeys,
+    Lab,
+}
@@ -0,0 +1,115 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(DownloadTokens::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(DownloadTokens::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(DownloadTokens::TokenHash).text().not_null())
+                    .col(ColumnDef::new(DownloadTokens::Scope).text().not_null())
+                    .col(
+                        ColumnDef::new(DownloadTokens::AssetIds)
+                            .json_binary()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(DownloadTokens::ExperimentId).uuid().null())
+                    .col(
+                        ColumnDef::new(DownloadTokens::MaxDownloads)
+                            .integer()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(DownloadTokens::Downloads)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(DownloadTokens::ExpiresAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(DownloadTokens::LastUsedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(DownloadTokens::RevokedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(ColumnDef::new(DownloadTokens::RevokedBy).text().null())
+                    .col(ColumnDef::new(DownloadTokens::CreatedBy).text().null())
+                    .col(
+                        ColumnDef::new(DownloadTokens::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_download_tokens_token_hash")
+                    .table(DownloadTokens::Table)
+                    .col(DownloadTokens::TokenHash)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_download_tokens_created_at")
+                    .table(DownloadTokens::Table)
+                    .col(DownloadTokens::CreatedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(DownloadTokens::Table)
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum DownloadTokens {
+    Table,
+    Id,
+    TokenHash,
+    Scope,
+    AssetIds,
+    ExperimentId,
+    MaxDownloads,
+    Downloads,
+    ExpiresAt,
+    LastUsedAt,
+    RevokedAt,
+    RevokedBy,
+    CreatedBy,
+    CreatedAt,
+}
@@ -0,0 +1,73 @@
+use sea_orm_migration::prelude::*;
+
+/// Tables whose records are kept, marked deleted, when deleted
+const TABLES: [Tables; 4] = [
+    Tables::Experiments,
+    Tables::Samples,
+    Tables::Treatments,
+    Tables::TrayConfigurations,
+];
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for table in TABLES {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(table)
+                        .add_column(
+                            ColumnDef::new(Tables::DeletedAt)
+                                .timestamp_with_time_zone()
+                                .null(),
+                        )
+                        .to_owned(),
+                )
+                .await?;
+            manager
+                .create_index(
+                    Index::create()
+                        .name(format!("idx_{}_deleted_at", table.to_string()))
+                        .table(table)
+                        .col(Tables::DeletedAt)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for table in TABLES {
+            manager
+                .drop_index(
+                    Index::drop()
+                        .name(format!("idx_{}_deleted_at", table.to_string()))
+                        .table(table)
+                        .to_owned(),
+                )
+                .await?;
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(table)
+                        .drop_column(Tables::DeletedAt)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+enum Tables {
+    Experiments,
+    Samples,
+    Treatments,
+    TrayConfigurations,
+    DeletedAt,
+}
@@ -0,0 +1,70 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(RecordVersions::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(RecordVersions::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(RecordVersions::Resource).text().not_null())
+                    .col(ColumnDef::new(RecordVersions::RecordId).uuid().not_null())
+                    .col(ColumnDef::new(RecordVersions::Version).integer().not_null())
+                    .col(
+                        ColumnDef::new(RecordVersions::Snapshot)
+                            .json_binary()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(RecordVersions::Username).text().null())
+                    .col(
+                        ColumnDef::new(RecordVersions::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_record_versions_resource_record_version")
+                    .table(RecordVersions::Table)
+                    .col(RecordVersions::Resource)
+                    .col(RecordVersions::RecordId)
+                    .col(RecordVersions::Version)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(RecordVersions::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum RecordVersions {
+    Table,
+    Id,
+    Resource,
+    RecordId,
+    Version,
+    Snapshot,
+    Username,
+    CreatedAt,
+}
@@ -0,0 +1,84 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ExperimentResultsSummaries::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ExperimentResultsSummaries::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(ExperimentResultsSummaries::ExperimentId)
+                            .uuid()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(ExperimentResultsSummaries::JobId)
+                            .uuid()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(ExperimentResultsSummaries::Results)
+                            .json_binary()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ExperimentResultsSummaries::ComputedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_experiment_results_summaries_experiment")
+                            .from(
+                                ExperimentResultsSummaries::Table,
+                                ExperimentResultsSummaries::ExperimentId,
+                            )
+                            .to(Experiments::Table, Experiments::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::NoAction),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(ExperimentResultsSummaries::Table)
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ExperimentResultsSummaries {
+    Table,
+    Id,
+    ExperimentId,
+    JobId,
+    Results,
+    ComputedAt,
+}
+
+#[derive(DeriveIden)]
+enum Experiments {
+    Table,
+    Id,
+}
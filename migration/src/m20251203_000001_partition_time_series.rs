@@ -0,0 +1,197 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// Partitions each time-series table is split into, by experiment
+const PARTITIONS: u32 = 16;
+
+/// Each experiment's readings and transitions are in one partition, found
+/// from the experiment, so queries of an experiment read that partition
+/// only. A partitioned table's keys must hold the experiment, so probe
+/// readings and assets, which point at readings by their ID alone, are kept
+/// in step by triggers instead of foreign keys: one checks the reading they
+/// point at when they are written, and `temperature_reading_deleted` deletes
+/// or unlinks them with the reading.
+const PARTITION_TEMPERATURE_READINGS: &str = r"
+ALTER TABLE well_phase_transitions
+    DROP CONSTRAINT IF EXISTS fk_well_phase_transitions_temperature_reading;
+ALTER TABLE probe_temperature_readings
+    DROP CONSTRAINT IF EXISTS fk_probe_temp_readings_temp_reading;
+ALTER TABLE s3_assets
+    DROP CONSTRAINT IF EXISTS s3_assets_temperature_reading_id_fkey;
+
+ALTER TABLE temperature_readings RENAME TO temperature_readings_unpartitioned;
+CREATE TABLE temperature_readings (
+    LIKE temperature_readings_unpartitioned INCLUDING DEFAULTS
+) PARTITION BY HASH (experiment_id);
+";
+
+const PARTITION_PHASE_TRANSITIONS: &str = r"
+ALTER TABLE well_phase_transitions RENAME TO well_phase_transitions_unpartitioned;
+CREATE TABLE well_phase_transitions (
+    LIKE well_phase_transitions_unpartitioned INCLUDING DEFAULTS
+) PARTITION BY HASH (experiment_id);
+";
+
+const CONSTRAIN_PARTITIONED: &str = r"
+ALTER TABLE temperature_readings
+    ADD CONSTRAINT temperature_readings_pkey PRIMARY KEY (id, experiment_id),
+    ADD CONSTRAINT fk_temperature_readings_experiment FOREIGN KEY (experiment_id)
+        REFERENCES experiments (id) ON DELETE CASCADE;
+CREATE INDEX idx_temperature_readings_experiment_timestamp
+    ON temperature_readings (experiment_id, timestamp);
+
+ALTER TABLE well_phase_transitions
+    ADD CONSTRAINT well_phase_transitions_pkey PRIMARY KEY (id, experiment_id),
+    ADD CONSTRAINT fk_well_phase_transitions_well FOREIGN KEY (well_id)
+        REFERENCES wells (id) ON DELETE CASCADE,
+    ADD CONSTRAINT fk_well_phase_transitions_experiment FOREIGN KEY (experiment_id)
+        REFERENCES experiments (id) ON DELETE CASCADE,
+    ADD CONSTRAINT fk_well_phase_transitions_temperature_reading
+        FOREIGN KEY (temperature_reading_id, experiment_id)
+        REFERENCES temperature_readings (id, experiment_id) ON DELETE CASCADE;
+CREATE INDEX idx_well_phase_transitions_experiment
+    ON well_phase_transitions (experiment_id, timestamp);
+CREATE INDEX idx_well_phase_transitions_well_timestamp
+    ON well_phase_transitions (well_id, timestamp);
+
+CREATE FUNCTION temperature_reading_deleted() RETURNS trigger AS $$
+BEGIN
+    DELETE FROM probe_temperature_readings WHERE temperature_reading_id = OLD.id;
+    UPDATE s3_assets SET temperature_reading_id = NULL WHERE temperature_reading_id = OLD.id;
+    RETURN OLD;
+END;
+$$ LANGUAGE plpgsql;
+CREATE TRIGGER temperature_reading_deleted
+    AFTER DELETE ON temperature_readings
+    FOR EACH ROW EXECUTE FUNCTION temperature_reading_deleted();
+
+CREATE FUNCTION temperature_reading_referenced() RETURNS trigger AS $$
+BEGIN
+    IF NEW.temperature_reading_id IS NOT NULL THEN
+        PERFORM 1 FROM temperature_readings
+            WHERE id = NEW.temperature_reading_id FOR KEY SHARE;
+        IF NOT FOUND THEN
+            RAISE foreign_key_violation USING MESSAGE = format(
+                'temperature_reading_id %s of %s is not a temperature reading',
+                NEW.temperature_reading_id, TG_TABLE_NAME);
+        END IF;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+CREATE TRIGGER probe_temperature_readings_reading_referenced
+    BEFORE INSERT OR UPDATE OF temperature_reading_id ON probe_temperature_readings
+    FOR EACH ROW EXECUTE FUNCTION temperature_reading_referenced();
+CREATE TRIGGER s3_assets_reading_referenced
+    BEFORE INSERT OR UPDATE OF temperature_reading_id ON s3_assets
+    FOR EACH ROW EXECUTE FUNCTION temperature_reading_referenced();
+";
+
+const UNPARTITION: &str = r"
+DROP TRIGGER IF EXISTS s3_assets_reading_referenced ON s3_assets;
+DROP TRIGGER IF EXISTS probe_temperature_readings_reading_referenced
+    ON probe_temperature_readings;
+DROP FUNCTION IF EXISTS temperature_reading_referenced();
+DROP TRIGGER IF EXISTS temperature_reading_deleted ON temperature_readings;
+DROP FUNCTION IF EXISTS temperature_reading_deleted();
+
+ALTER TABLE well_phase_transitions RENAME TO well_phase_transitions_partitioned;
+CREATE TABLE well_phase_transitions (
+    LIKE well_phase_transitions_partitioned INCLUDING DEFAULTS
+);
+INSERT INTO well_phase_transitions SELECT * FROM well_phase_transitions_partitioned;
+DROP TABLE well_phase_transitions_partitioned;
+
+ALTER TABLE temperature_readings RENAME TO temperature_readings_partitioned;
+CREATE TABLE temperature_readings (
+    LIKE temperature_readings_partitioned INCLUDING DEFAULTS
+);
+INSERT INTO temperature_readings SELECT * FROM temperature_readings_partitioned;
+DROP TABLE temperature_readings_partitioned;
+
+ALTER TABLE temperature_readings
+    ADD CONSTRAINT temperature_readings_pkey PRIMARY KEY (id),
+    ADD CONSTRAINT fk_temperature_readings_experiment FOREIGN KEY (experiment_id)
+        REFERENCES experiments (id) ON DELETE CASCADE;
+CREATE INDEX idx_temperature_readings_experiment_timestamp
+    ON temperature_readings (experiment_id, timestamp);
+
+ALTER TABLE well_phase_transitions
+    ADD CONSTRAINT well_phase_transitions_pkey PRIMARY KEY (id),
+    ADD CONSTRAINT fk_well_phase_transitions_well FOREIGN KEY (well_id)
+        REFERENCES wells (id) ON DELETE CASCADE,
+    ADD CONSTRAINT fk_well_phase_transitions_experiment FOREIGN KEY (experiment_id)
+        REFERENCES experiments (id) ON DELETE CASCADE,
+    ADD CONSTRAINT fk_well_phase_transitions_temperature_reading
+        FOREIGN KEY (temperature_reading_id)
+        REFERENCES temperature_readings (id) ON DELETE CASCADE;
+CREATE INDEX idx_well_phase_transitions_experiment
+    ON well_phase_transitions (experiment_id, timestamp);
+CREATE INDEX idx_well_phase_transitions_well_timestamp
+    ON well_phase_transitions (well_id, timestamp);
+
+DELETE FROM probe_temperature_readings
+    WHERE temperature_reading_id NOT IN (SELECT id FROM temperature_readings);
+ALTER TABLE probe_temperature_readings
+    ADD CONSTRAINT fk_probe_temp_readings_temp_reading FOREIGN KEY (temperature_reading_id)
+        REFERENCES temperature_readings (id) ON DELETE CASCADE;
+UPDATE s3_assets SET temperature_reading_id = NULL
+    WHERE temperature_reading_id NOT IN (SELECT id FROM temperature_readings);
+ALTER TABLE s3_assets
+    ADD CONSTRAINT s3_assets_temperature_reading_id_fkey FOREIGN KEY (temperature_reading_id)
+        REFERENCES temperature_readings (id) ON DELETE SET NULL;
+";
+
+/// Partitions of a table, one per remainder of the experiment's hash
+fn create_partitions(table: &str) -> String {
+    (0..PARTITIONS)
+        .map(|remainder| {
+            format!(
+                "CREATE TABLE {table}_p{remainder} PARTITION OF {table} \
+                 FOR VALUES WITH (MODULUS {PARTITIONS}, REMAINDER {remainder});\n"
+            )
+        })
+        .collect()
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite has no partitioning, and its test databases stay small
+        if manager.get_database_backend() != sea_orm::DatabaseBackend::Postgres {
+            return Ok(());
+        }
+
+        let db = manager.get_connection();
+        db.execute_unprepared(PARTITION_TEMPERATURE_READINGS)
+            .await?;
+        db.execute_unprepared(PARTITION_PHASE_TRANSITIONS).await?;
+        db.execute_unprepared(&create_partitions("temperature_readings"))
+            .await?;
+        db.execute_unprepared(&create_partitions("well_phase_transitions"))
+            .await?;
+        db.execute_unprepared(
+            "INSERT INTO temperature_readings SELECT * FROM temperature_readings_unpartitioned;
+             INSERT INTO well_phase_transitions SELECT * FROM well_phase_transitions_unpartitioned;
+             DROP TABLE well_phase_transitions_unpartitioned;
+             DROP TABLE temperature_readings_unpartitioned;",
+        )
+        .await?;
+        db.execute_unprepared(CONSTRAIN_PARTITIONED).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if manager.get_database_backend() != sea_orm::DatabaseBackend::Postgres {
+            return Ok(());
+        }
+
+        manager
+            .get_connection()
+            .execute_unprepared(UNPARTITION)
+            .await?;
+        Ok(())
+    }
+}
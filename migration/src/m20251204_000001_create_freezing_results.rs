@@ -0,0 +1,182 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(FreezingResults::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(FreezingResults::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(FreezingResults::ExperimentId)
+                            .uuid()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(FreezingResults::WellId).uuid().not_null())
+                    .col(ColumnDef::new(FreezingResults::TrayName).string().null())
+                    .col(
+                        ColumnDef::new(FreezingResults::WellCoordinate)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(FreezingResults::RegionName).string().null())
+                    .col(ColumnDef::new(FreezingResults::TreatmentId).uuid().null())
+                    .col(
+                        ColumnDef::new(FreezingResults::TreatmentName)
+                            .string()
+                            .null(),
+                    )
+                    .col(ColumnDef::new(FreezingResults::SampleId).uuid().null())
+                    .col(
+                        ColumnDef::new(FreezingResults::DilutionFactor)
+                            .integer()
+                            .null(),
+                    )
+                    .col(ColumnDef::new(FreezingResults::Frozen).boolean().not_null())
+                    .col(
+                        ColumnDef::new(FreezingResults::FreezingTimestamp)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(FreezingResults::FreezingTimeSeconds)
+                            .big_integer()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(FreezingResults::FreezingTemperatureAvg)
+                            .decimal()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(FreezingResults::FreezingTemperatureRawAvg)
+                            .decimal()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(FreezingResults::FreezingTemperatureInterpolated)
+                            .decimal()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(FreezingResults::Excluded)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .col(
+                        ColumnDef::new(FreezingResults::ComputedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_freezing_results_experiment")
+                            .from(FreezingResults::Table, FreezingResults::ExperimentId)
+                            .to(Experiments::Table, Experiments::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::NoAction),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_freezing_results_well")
+                            .from(FreezingResults::Table, FreezingResults::WellId)
+                            .to(Wells::Table, Wells::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::NoAction),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // One result per well of an experiment
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_freezing_results_experiment_well")
+                    .table(FreezingResults::Table)
+                    .col(FreezingResults::ExperimentId)
+                    .col(FreezingResults::WellId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_freezing_results_well")
+                    .table(FreezingResults::Table)
+                    .col(FreezingResults::WellId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_freezing_results_treatment")
+                    .table(FreezingResults::Table)
+                    .col(FreezingResults::TreatmentId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(FreezingResults::Table)
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum FreezingResults {
+    Table,
+    Id,
+    ExperimentId,
+    WellId,
+    TrayName,
+    WellCoordinate,
+    RegionName,
+    TreatmentId,
+    TreatmentName,
+    SampleId,
+    DilutionFactor,
+    Frozen,
+    FreezingTimestamp,
+    FreezingTimeSeconds,
+    FreezingTemperatureAvg,
+    FreezingTemperatureRawAvg,
+    FreezingTemperatureInterpolated,
+    Excluded,
+    ComputedAt,
+}
+
+#[derive(DeriveIden)]
+enum Experiments {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Wells {
+    Table,
+    Id,
+}
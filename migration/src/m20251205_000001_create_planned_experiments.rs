@@ -0,0 +1,154 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(PlannedExperiments::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(PlannedExperiments::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(PlannedExperiments::Name).text().not_null())
+                    .col(
+                        ColumnDef::new(PlannedExperiments::ScheduledFor)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(PlannedExperiments::Operator).text().null())
+                    .col(ColumnDef::new(PlannedExperiments::ProjectId).uuid().null())
+                    .col(
+                        ColumnDef::new(PlannedExperiments::TrayConfigurationId)
+                            .uuid()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(PlannedExperiments::SampleIds)
+                            .json_binary()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(PlannedExperiments::Notes).text().null())
+                    .col(
+                        ColumnDef::new(PlannedExperiments::Status)
+                            .text()
+                            .not_null()
+                            .default("planned"),
+                    )
+                    .col(
+                        ColumnDef::new(PlannedExperiments::ExperimentId)
+                            .uuid()
+                            .null(),
+                    )
+                    .col(ColumnDef::new(PlannedExperiments::Lab).text().null())
+                    .col(ColumnDef::new(PlannedExperiments::CreatedBy).text().null())
+                    .col(
+                        ColumnDef::new(PlannedExperiments::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        ColumnDef::new(PlannedExperiments::LastUpdated)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_planned_experiments_project")
+                            .from(PlannedExperiments::Table, PlannedExperiments::ProjectId)
+                            .to(Projects::Table, Projects::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::NoAction),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_planned_experiments_tray_configuration")
+                            .from(
+                                PlannedExperiments::Table,
+                                PlannedExperiments::TrayConfigurationId,
+                            )
+                            .to(TrayConfigurations::Table, TrayConfigurations::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::NoAction),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_planned_experiments_experiment")
+                            .from(PlannedExperiments::Table, PlannedExperiments::ExperimentId)
+                            .to(Experiments::Table, Experiments::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::NoAction),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // The calendar looks plans up by date
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_planned_experiments_scheduled_for")
+                    .table(PlannedExperiments::Table)
+                    .col(PlannedExperiments::ScheduledFor)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(PlannedExperiments::Table)
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum PlannedExperiments {
+    Table,
+    Id,
+    Name,
+    ScheduledFor,
+    Operator,
+    ProjectId,
+    TrayConfigurationId,
+    SampleIds,
+    Notes,
+    Status,
+    ExperimentId,
+    Lab,
+    CreatedBy,
+    CreatedAt,
+    LastUpdated,
+}
+
+#[derive(DeriveIden)]
+enum Projects {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum TrayConfigurations {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Experiments {
+    Table,
+    Id,
+}
@@ -0,0 +1,117 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ProbeCalibrations::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ProbeCalibrations::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(ProbeCalibrations::ProbeId).uuid().not_null())
+                    .col(
+                        ColumnDef::new(ProbeCalibrations::ExperimentId)
+                            .uuid()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(ProbeCalibrations::CalibratedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ProbeCalibrations::CalibrationSlope)
+                            .decimal()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ProbeCalibrations::CalibrationOffset)
+                            .decimal()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(ProbeCalibrations::Notes).text().null())
+                    .col(ColumnDef::new(ProbeCalibrations::CreatedBy).text().null())
+                    .col(
+                        ColumnDef::new(ProbeCalibrations::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_probe_calibrations_probe")
+                            .from(ProbeCalibrations::Table, ProbeCalibrations::ProbeId)
+                            .to(Probes::Table, Probes::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::NoAction),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_probe_calibrations_experiment")
+                            .from(ProbeCalibrations::Table, ProbeCalibrations::ExperimentId)
+                            .to(Experiments::Table, Experiments::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::NoAction),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_probe_calibrations_probe_calibrated_at")
+                    .table(ProbeCalibrations::Table)
+                    .col(ProbeCalibrations::ProbeId)
+                    .col(ProbeCalibrations::CalibratedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(ProbeCalibrations::Table)
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ProbeCalibrations {
+    Table,
+    Id,
+    ProbeId,
+    ExperimentId,
+    CalibratedAt,
+    CalibrationSlope,
+    CalibrationOffset,
+    Notes,
+    CreatedBy,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Probes {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Experiments {
+    Table,
+    Id,
+}
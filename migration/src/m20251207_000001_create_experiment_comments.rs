@@ -0,0 +1,122 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ExperimentComments::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ExperimentComments::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(ExperimentComments::ExperimentId)
+                            .uuid()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(ExperimentComments::WellId).uuid().null())
+                    .col(ColumnDef::new(ExperimentComments::ParentId).uuid().null())
+                    .col(ColumnDef::new(ExperimentComments::Body).text().not_null())
+                    .col(
+                        ColumnDef::new(ExperimentComments::Mentions)
+                            .json_binary()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(ExperimentComments::CreatedBy).text().null())
+                    .col(
+                        ColumnDef::new(ExperimentComments::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        ColumnDef::new(ExperimentComments::LastUpdated)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_experiment_comments_experiment")
+                            .from(ExperimentComments::Table, ExperimentComments::ExperimentId)
+                            .to(Experiments::Table, Experiments::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::NoAction),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_experiment_comments_well")
+                            .from(ExperimentComments::Table, ExperimentComments::WellId)
+                            .to(Wells::Table, Wells::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::NoAction),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_experiment_comments_parent")
+                            .from(ExperimentComments::Table, ExperimentComments::ParentId)
+                            .to(ExperimentComments::Table, ExperimentComments::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::NoAction),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_experiment_comments_experiment_created_at")
+                    .table(ExperimentComments::Table)
+                    .col(ExperimentComments::ExperimentId)
+                    .col(ExperimentComments::CreatedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(ExperimentComments::Table)
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ExperimentComments {
+    Table,
+    Id,
+    ExperimentId,
+    WellId,
+    ParentId,
+    Body,
+    Mentions,
+    CreatedBy,
+    CreatedAt,
+    LastUpdated,
+}
+
+#[derive(DeriveIden)]
+enum Experiments {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Wells {
+    Table,
+    Id,
+}
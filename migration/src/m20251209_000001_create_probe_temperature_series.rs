@@ -0,0 +1,126 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// A copy of the probe readings, with their experiment and time, in a
+/// hypertable that triggers keep in step, and its per-minute continuous
+/// aggregate. The aggregate is made empty, since filling it cannot be done
+/// within the migration's transaction. A policy refreshes it every minute,
+/// all the way back so that readings of old runs reprocessed are summed
+/// again, and the minutes not yet refreshed are summed when they are read.
+const CREATE_SERIES: &str = r"
+CREATE TABLE probe_temperature_series (
+    probe_temperature_reading_id uuid NOT NULL,
+    experiment_id uuid NOT NULL,
+    probe_id uuid NOT NULL,
+    timestamp timestamptz NOT NULL,
+    temperature numeric NOT NULL
+);
+SELECT create_hypertable('probe_temperature_series', 'timestamp');
+CREATE INDEX idx_probe_temperature_series_probe
+    ON probe_temperature_series (experiment_id, probe_id, timestamp);
+CREATE INDEX idx_probe_temperature_series_reading
+    ON probe_temperature_series (probe_temperature_reading_id);
+
+CREATE FUNCTION probe_temperature_series_sync() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP <> 'INSERT' THEN
+        DELETE FROM probe_temperature_series WHERE probe_temperature_reading_id = OLD.id;
+    END IF;
+    IF TG_OP <> 'DELETE' THEN
+        INSERT INTO probe_temperature_series
+        SELECT NEW.id, readings.experiment_id, NEW.probe_id, readings.timestamp, NEW.temperature
+        FROM temperature_readings readings
+        WHERE readings.id = NEW.temperature_reading_id;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+CREATE TRIGGER probe_temperature_series_sync
+    AFTER INSERT OR UPDATE OR DELETE ON probe_temperature_readings
+    FOR EACH ROW EXECUTE FUNCTION probe_temperature_series_sync();
+
+CREATE FUNCTION probe_temperature_series_moved() RETURNS trigger AS $$
+BEGIN
+    DELETE FROM probe_temperature_series
+    WHERE probe_temperature_reading_id IN (
+        SELECT id FROM probe_temperature_readings WHERE temperature_reading_id = NEW.id
+    );
+    INSERT INTO probe_temperature_series
+    SELECT probes.id, NEW.experiment_id, probes.probe_id, NEW.timestamp, probes.temperature
+    FROM probe_temperature_readings probes
+    WHERE probes.temperature_reading_id = NEW.id;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+CREATE TRIGGER probe_temperature_series_moved
+    AFTER UPDATE OF experiment_id, timestamp ON temperature_readings
+    FOR EACH ROW EXECUTE FUNCTION probe_temperature_series_moved();
+
+INSERT INTO probe_temperature_series
+SELECT probes.id, readings.experiment_id, probes.probe_id, readings.timestamp, probes.temperature
+FROM probe_temperature_readings probes
+JOIN temperature_readings readings ON readings.id = probes.temperature_reading_id;
+
+CREATE MATERIALIZED VIEW probe_temperature_minutes
+WITH (timescaledb.continuous, timescaledb.materialized_only = false) AS
+SELECT experiment_id,
+       probe_id,
+       time_bucket(INTERVAL '1 minute', timestamp) AS minute,
+       min(timestamp) AS first_timestamp,
+       sum(temperature) AS total,
+       count(*) AS readings
+FROM probe_temperature_series
+GROUP BY experiment_id, probe_id, minute
+WITH NO DATA;
+SELECT add_continuous_aggregate_policy(
+    'probe_temperature_minutes',
+    start_offset => NULL,
+    end_offset => INTERVAL '1 minute',
+    schedule_interval => INTERVAL '1 minute'
+);
+";
+
+const DROP_SERIES: &str = r"
+DROP MATERIALIZED VIEW IF EXISTS probe_temperature_minutes;
+DROP TRIGGER IF EXISTS probe_temperature_series_moved ON temperature_readings;
+DROP FUNCTION IF EXISTS probe_temperature_series_moved();
+DROP TRIGGER IF EXISTS probe_temperature_series_sync ON probe_temperature_readings;
+DROP FUNCTION IF EXISTS probe_temperature_series_sync();
+DROP TABLE IF EXISTS probe_temperature_series;
+";
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Only databases that have the timescaledb extension keep the series
+        if manager.get_database_backend() != sea_orm::DatabaseBackend::Postgres {
+            return Ok(());
+        }
+        let db = manager.get_connection();
+        let timescale = db
+            .query_one(sea_orm::Statement::from_string(
+                sea_orm::DatabaseBackend::Postgres,
+                "SELECT 1 FROM pg_extension WHERE extname = 'timescaledb'",
+            ))
+            .await?
+            .is_some();
+        if timescale {
+            db.execute_unprepared(CREATE_SERIES).await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if manager.get_database_backend() != sea_orm::DatabaseBackend::Postgres {
+            return Ok(());
+        }
+
+        manager
+            .get_connection()
+            .execute_unprepared(DROP_SERIES)
+            .await?;
+        Ok(())
+    }
+}
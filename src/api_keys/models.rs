@@ -0,0 +1,139 @@
+use crate::common::auth::Role;
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// What a key may do in the route groups of its scopes
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, ToSchema, Serialize, Deserialize,
+)]
+#[sea_orm(rs_type = "String", db_type = "Text")]
+#[serde(rename_all = "snake_case")]
+pub enum ApiKeyRole {
+    #[sea_orm(string_value = "viewer")]
+    Viewer,
+    #[sea_orm(string_value = "editor")]
+    Editor,
+    #[sea_orm(string_value = "administrator")]
+    Administrator,
+}
+
+impl From<ApiKeyRole> for Role {
+    fn from(role: ApiKeyRole) -> Self {
+        match role {
+            ApiKeyRole::Viewer => Role::Viewer,
+            ApiKeyRole::Editor => Role::Editor,
+            ApiKeyRole::Administrator => Role::Administrator,
+        }
+    }
+}
+
+/// A key for instruments and scripts. Only the SHA-256 hash of the key is
+/// kept; its prefix finds it.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "api_keys")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    #[sea_orm(column_type = "Text")]
+    pub name: String,
+    /// Keycloak username the key acts as, for project membership
+    #[sea_orm(column_type = "Text")]
+    pub username: String,
+    pub role: ApiKeyRole,
+    /// Route groups the key may use
+    #[sea_orm(column_type = "JsonBinary")]
+    pub scopes: Json,
+    #[sea_orm(column_type = "Text", unique)]
+    pub key_prefix: String,
+    #[sea_orm(column_type = "Text")]
+    pub key_hash: String,
+    pub rate_limit_per_minute: Option<i32>,
+    /// Lab the key belongs to, when labs are on
+    #[sea_orm(column_type = "Text", nullable)]
+    pub lab: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub created_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    pub fn scope_list(&self) -> Vec<String> {
+        serde_json::from_value(self.scopes.clone()).unwrap_or_default()
+    }
+}
+
+/// An API key as shown to administrators, without its secret
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub name: String,
+    pub username: String,
+    pub role: ApiKeyRole,
+    pub scopes: Vec<String>,
+    /// Start of the key, to recognise it
+    pub key_prefix: String,
+    pub rate_limit_per_minute: Option<i32>,
+    pub lab: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<Model> for ApiKey {
+    fn from(model: Model) -> Self {
+        Self {
+            scopes: model.scope_list(),
+            id: model.id,
+            name: model.name,
+            username: model.username,
+            role: model.role,
+            key_prefix: model.key_prefix,
+            rate_limit_per_minute: model.rate_limit_per_minute,
+            lab: model.lab,
+            expires_at: model.expires_at,
+            last_used_at: model.last_used_at,
+            revoked_at: model.revoked_at,
+            created_by: model.created_by,
+            created_at: model.created_at,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, ToSchema)]
+pub struct ApiKeyCreate {
+    /// What the key is for, e.g. the instrument PC it is installed on
+    pub name: String,
+    /// Username the key acts as; the creating user when omitted
+    pub username: Option<String>,
+    pub role: ApiKeyRole,
+    /// Route groups the key may use, e.g. `["experiments", "assets"]`
+    pub scopes: Vec<String>,
+    /// Most requests a minute, unlimited when omitted
+    pub rate_limit_per_minute: Option<i32>,
+    /// Lab whose records the key reaches, when labs are on; only records of
+    /// no lab when omitted
+    pub lab: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// A new key, the only time its secret is shown
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct CreatedApiKey {
+    /// Send as `X-API-Key` or as a bearer token
+    pub key: String,
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+}
@@ -0,0 +1,383 @@
+//! API keys for instruments and scripts.
+//!
+//! Headless lab PCs authenticate with a key instead of a Keycloak token. A
+//! key reads `spice_<prefix>_<secret>`: the prefix finds the key and the
+//! SHA-256 hash of the whole key checks it, so the secret itself is never
+//! stored. A key acts as its user with the role it was given, only in the
+//! route groups of its scopes, until it expires or is revoked, and at most
+//! as often a minute as its rate limit allows.
+
+use super::models::{ActiveModel, ApiKey, ApiKeyCreate, Column, CreatedApiKey, Entity, Model};
+use crate::assets::services::sha256_hex;
+use crate::common::auth::Role;
+use crate::common::keycloak::KeycloakAuth;
+use crate::common::labs::Labs;
+use axum::{
+    extract::{Request, State},
+    http::{
+        HeaderMap, StatusCode,
+        header::{AUTHORIZATION, RETRY_AFTER},
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use axum_keycloak_auth::{
+    KeycloakAuthStatus,
+    decode::{Email, KeycloakToken, Profile, ProfileAndEmail},
+    role::KeycloakRole,
+};
+use chrono::{DateTime, Duration, Utc};
+use rand::Rng;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, DatabaseConnection, DbErr, EntityTrait,
+    IntoActiveModel, QueryFilter, QueryOrder,
+};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::{Arc, LazyLock, Mutex};
+use uuid::Uuid;
+
+/// Route groups a key can be scoped to, as nested under `/api`
+pub const SCOPES: &[&str] = &[
+    "assets",
+    "changes",
+    "dilutions",
+    "experiments",
+    "exports",
+    "freezing_results",
+    "graphql",
+    "locations",
+    "nucleation_events",
+    "planned_experiments",
+    "projects",
+    "samples",
+    "tray_configurations",
+    "trays",
+    "treatments",
+];
+const KEY_START: &str = "spice_";
+const HEADER: &str = "x-api-key";
+
+/// Start of a key's current minute and its requests in it
+type Window = (DateTime<Utc>, i32);
+
+/// Requests of each rate-limited key in its current minute
+static WINDOWS: LazyLock<Mutex<HashMap<Uuid, Window>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// A new key and its prefix
+fn generate_key() -> (String, String) {
+    let mut rng = rand::rng();
+    let prefix: [u8; 6] = rng.random();
+    let secret: [u8; 32] = rng.random();
+    let hex = |bytes: &[u8]| {
+        bytes.iter().fold(String::new(), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        })
+    };
+    let prefix = hex(&prefix);
+    (format!("{KEY_START}{prefix}_{}", hex(&secret)), prefix)
+}
+
+/// Create a key. Invalid input is returned as `DbErr::Custom`.
+pub async fn create_api_key(
+    db: &DatabaseConnection,
+    input: ApiKeyCreate,
+    created_by: Option<String>,
+) -> Result<CreatedApiKey, DbErr> {
+    let name = input.name.trim().to_string();
+    if name.is_empty() {
+        return Err(DbErr::Custom("name must not be blank".to_string()));
+    }
+    let username = input
+        .username
+        .map(|username| username.trim().to_string())
+        .filter(|username| !username.is_empty())
+        .or_else(|| created_by.clone())
+        .ok_or_else(|| DbErr::Custom("username is required".to_string()))?;
+
+    let mut scopes = input.scopes;
+    scopes.sort();
+    scopes.dedup();
+    if scopes.is_empty() {
+        return Err(DbErr::Custom("scopes must not be empty".to_string()));
+    }
+    if let Some(unknown) = scopes
+        .iter()
+        .find(|scope| !SCOPES.contains(&scope.as_str()))
+    {
+        return Err(DbErr::Custom(format!(
+            "Unknown scope '{unknown}'; scopes are {}",
+            SCOPES.join(", ")
+        )));
+    }
+    if input.rate_limit_per_minute.is_some_and(|limit| limit < 1) {
+        return Err(DbErr::Custom(
+            "rate_limit_per_minute must be at least 1".to_string(),
+        ));
+    }
+    let now = Utc::now();
+    if input.expires_at.is_some_and(|expires_at| expires_at <= now) {
+        return Err(DbErr::Custom(
+            "expires_at must be in the future".to_string(),
+        ));
+    }
+
+    let (key, key_prefix) = generate_key();
+    let model = ActiveModel {
+        id: Set(Uuid::new_v4()),
+        name: Set(name),
+        username: Set(username),
+        role: Set(input.role),
+        scopes: Set(serde_json::json!(scopes)),
+        key_prefix: Set(key_prefix),
+        key_hash: Set(sha256_hex(key.as_bytes())),
+        rate_limit_per_minute: Set(input.rate_limit_per_minute),
+        lab: Set(input
+            .lab
+            .map(|lab| lab.trim().to_string())
+            .filter(|lab| !lab.is_empty())),
+        expires_at: Set(input.expires_at),
+        last_used_at: Set(None),
+        revoked_at: Set(None),
+        created_by: Set(created_by),
+        created_at: Set(now),
+    }
+    .insert(db)
+    .await?;
+
+    Ok(CreatedApiKey {
+        key,
+        api_key: model.into(),
+    })
+}
+
+/// All keys, newest first
+pub async fn list_api_keys(db: &DatabaseConnection) -> Result<Vec<ApiKey>, DbErr> {
+    Ok(Entity::find()
+        .order_by_desc(Column::CreatedAt)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(ApiKey::from)
+        .collect())
+}
+
+/// Revoke a key for good. Revoking it again changes nothing.
+pub async fn revoke_api_key(db: &DatabaseConnection, id: Uuid) -> Result<ApiKey, DbErr> {
+    let api_key = Entity::find_by_id(id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("API key not found".to_string()))?;
+    if api_key.revoked_at.is_some() {
+        return Ok(api_key.into());
+    }
+    let mut active = api_key.into_active_model();
+    active.revoked_at = Set(Some(Utc::now()));
+    Ok(active.update(db).await?.into())
+}
+
+/// The key, if it is neither revoked nor expired, whatever its scopes
+pub async fn find_valid_key(
+    db: &DatabaseConnection,
+    key: &str,
+) -> Result<Model, (StatusCode, String)> {
+    let invalid = || (StatusCode::UNAUTHORIZED, "Invalid API key".to_string());
+    let prefix = key
+        .strip_prefix(KEY_START)
+        .and_then(|rest| rest.split_once('_'))
+        .map(|(prefix, _)| prefix)
+        .ok_or_else(invalid)?;
+    let api_key = Entity::find()
+        .filter(Column::KeyPrefix.eq(prefix))
+        .one(db)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .filter(|api_key| api_key.key_hash == sha256_hex(key.as_bytes()))
+        .ok_or_else(invalid)?;
+
+    let now = Utc::now();
+    if api_key.revoked_at.is_some() {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            "API key has been revoked".to_string(),
+        ));
+    }
+    if api_key
+        .expires_at
+        .is_some_and(|expires_at| expires_at <= now)
+    {
+        return Err((StatusCode::UNAUTHORIZED, "API key has expired".to_string()));
+    }
+    Ok(api_key)
+}
+
+/// The key, if it is valid now and scoped for the route group
+pub async fn verify_key(
+    db: &DatabaseConnection,
+    key: &str,
+    scope: &str,
+) -> Result<Model, (StatusCode, String)> {
+    let api_key = find_valid_key(db, key).await?;
+    if !api_key.scope_list().iter().any(|granted| granted == scope) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            format!("API key is not scoped for {scope}"),
+        ));
+    }
+
+    // Record use at most once a minute
+    let now = Utc::now();
+    if api_key
+        .last_used_at
+        .is_none_or(|last_used_at| now - last_used_at > Duration::minutes(1))
+    {
+        let mut active = api_key.clone().into_active_model();
+        active.last_used_at = Set(Some(now));
+        active
+            .update(db)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    }
+    Ok(api_key)
+}
+
+/// Count a request of the key in its current minute, or give the seconds
+/// until the next minute when it has used up its limit
+pub fn check_rate_limit(api_key: &Model, now: DateTime<Utc>) -> Result<(), i64> {
+    let Some(limit) = api_key.rate_limit_per_minute else {
+        return Ok(());
+    };
+    let mut windows = WINDOWS
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    let (start, count) = windows.entry(api_key.id).or_insert((now, 0));
+    if now - *start >= Duration::minutes(1) {
+        (*start, *count) = (now, 0);
+    }
+    if *count >= limit {
+        return Err((*start + Duration::minutes(1) - now).num_seconds().max(1));
+    }
+    *count += 1;
+    Ok(())
+}
+
+/// The identity a key acts with, in the form Keycloak gives users, so that
+/// the layers behind take it as they take a signed-in user
+pub fn key_token(api_key: &Model) -> KeycloakToken<Role> {
+    let now = time::OffsetDateTime::now_utc();
+    KeycloakToken {
+        expires_at: api_key
+            .expires_at
+            .and_then(|expires_at| {
+                time::OffsetDateTime::from_unix_timestamp(expires_at.timestamp()).ok()
+            })
+            .unwrap_or(now),
+        issued_at: now,
+        jwt_id: api_key.id.to_string(),
+        issuer: "api-key".to_string(),
+        audience: vec![String::from("account")],
+        subject: api_key.id.to_string(),
+        authorized_party: api_key.name.clone(),
+        roles: vec![KeycloakRole::Realm {
+            role: api_key.role.into(),
+        }],
+        extra: ProfileAndEmail {
+            profile: Profile {
+                given_name: None,
+                full_name: Some(api_key.name.clone()),
+                family_name: None,
+                preferred_username: api_key.username.clone(),
+            },
+            email: Email {
+                email: String::new(),
+                email_verified: false,
+            },
+        },
+    }
+}
+
+/// Key sent as `X-API-Key`, or as a bearer token
+pub fn presented_key(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(HEADER)
+        .and_then(|value| value.to_str().ok())
+        .or_else(|| {
+            headers
+                .get(AUTHORIZATION)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.strip_prefix("Bearer "))
+        })
+        .map(str::trim)
+        .filter(|key| key.starts_with(KEY_START))
+        .map(str::to_string)
+}
+
+/// Who the request's credentials verify as: the id of a valid API key or
+/// the subject of a valid Keycloak token. Nothing is taken on the word of
+/// a credential that does not verify.
+pub async fn verified_caller(
+    db: &DatabaseConnection,
+    auth: Option<&KeycloakAuth>,
+    headers: &HeaderMap,
+) -> Option<String> {
+    if let Some(key) = presented_key(headers) {
+        let api_key = find_valid_key(db, &key).await.ok()?;
+        return Some(format!("key:{}", api_key.id));
+    }
+    let bearer = headers
+        .get(AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")?;
+    let (token, _) = auth?.validate(bearer).await.ok()?;
+    Some(format!("user:{}", token.subject))
+}
+
+/// Middleware behind a Keycloak layer in pass-through mode. Requests with a
+/// valid token go on as the user, and requests with an API key scoped for
+/// the route group as the key's user with its role; others are rejected as
+/// the Keycloak layer would.
+pub async fn accept_api_keys(
+    State((db, scope)): State<(DatabaseConnection, &'static str)>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let status = request
+        .extensions_mut()
+        .remove::<KeycloakAuthStatus<Role, ProfileAndEmail>>();
+    if let Some(KeycloakAuthStatus::Success(token)) = status {
+        request.extensions_mut().insert(token);
+        return next.run(request).await;
+    }
+
+    if let Some(key) = presented_key(request.headers()) {
+        let api_key = match verify_key(&db, &key, scope).await {
+            Ok(api_key) => api_key,
+            Err(rejection) => return rejection.into_response(),
+        };
+        if let Err(retry_after) = check_rate_limit(&api_key, Utc::now()) {
+            return (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(RETRY_AFTER, retry_after.to_string())],
+                "API key rate limit exceeded",
+            )
+                .into_response();
+        }
+        request.extensions_mut().insert(key_token(&api_key));
+        if let Some(labs) = Labs::of_key(api_key.lab.as_deref()) {
+            request.extensions_mut().insert(labs);
+        }
+        return next.run(request).await;
+    }
+
+    match status {
+        Some(KeycloakAuthStatus::Failure(error)) => Arc::try_unwrap(error).map_or_else(
+            |error| (StatusCode::UNAUTHORIZED, error.to_string()).into_response(),
+            IntoResponse::into_response,
+        ),
+        // Without a Keycloak layer, authentication is off
+        _ => next.run(request).await,
+    }
+}
@@ -0,0 +1,232 @@
+use super::models::{ApiKeyCreate, ApiKeyRole, Entity as ApiKeys};
+use super::services::{accept_api_keys, check_rate_limit, create_api_key, key_token, verify_key};
+use crate::common::auth::Role;
+use crate::config::test_helpers::{send_json, setup_test_app, setup_test_db};
+use axum::body::{Body, to_bytes};
+use axum::http::{Request, StatusCode};
+use axum::{Extension, Router, middleware, routing::get};
+use axum_keycloak_auth::decode::KeycloakToken;
+use chrono::{Duration, Utc};
+use sea_orm::EntityTrait;
+use serde_json::json;
+use tower::ServiceExt;
+
+fn key_input(scopes: &[&str]) -> ApiKeyCreate {
+    ApiKeyCreate {
+        name: "Freezer PC".to_string(),
+        username: Some("lab-instrument".to_string()),
+        role: ApiKeyRole::Editor,
+        scopes: scopes.iter().map(ToString::to_string).collect(),
+        rate_limit_per_minute: None,
+        lab: None,
+        expires_at: None,
+    }
+}
+
+#[tokio::test]
+async fn test_api_key_management() {
+    let app = setup_test_app().await;
+
+    let (status, created) = send_json(
+        &app,
+        "POST",
+        "/api/api_keys",
+        Some(&json!({
+            "name": "Freezer PC",
+            "username": "lab-instrument",
+            "role": "editor",
+            "scopes": ["experiments", "assets", "experiments"],
+            "rate_limit_per_minute": 120
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{created}");
+    let key = created["key"].as_str().unwrap();
+    assert!(key.starts_with(&format!(
+        "spice_{}_",
+        created["key_prefix"].as_str().unwrap()
+    )));
+    assert_eq!(created["scopes"], json!(["assets", "experiments"]));
+    assert_eq!(created["role"], "editor");
+    let id = created["id"].as_str().unwrap();
+
+    // The secret is shown only once, and only its hash is stored
+    let (status, keys) = send_json(&app, "GET", "/api/api_keys", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let listed = keys
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|listed| listed["id"] == id)
+        .unwrap();
+    assert!(listed.get("key").is_none() && listed.get("key_hash").is_none());
+    assert!(!keys.to_string().contains(key));
+
+    for (input, message) in [
+        (
+            json!({"name": " ", "role": "viewer", "scopes": ["samples"]}),
+            "blank",
+        ),
+        (
+            json!({"name": "Script", "username": "someone", "role": "viewer", "scopes": []}),
+            "scopes must not be empty",
+        ),
+        (
+            json!({"name": "Script", "username": "someone", "role": "viewer", "scopes": ["api_keys"]}),
+            "Unknown scope 'api_keys'",
+        ),
+        (
+            json!({"name": "Script", "role": "viewer", "scopes": ["samples"]}),
+            "username is required",
+        ),
+        (
+            json!({"name": "Script", "username": "someone", "role": "viewer", "scopes": ["samples"], "expires_at": "2020-01-01T00:00:00Z"}),
+            "in the future",
+        ),
+    ] {
+        let (status, body) = send_json(&app, "POST", "/api/api_keys", Some(&input)).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{input}");
+        assert!(body.to_string().contains(message), "{body}");
+    }
+
+    let (status, revoked) = send_json(&app, "DELETE", &format!("/api/api_keys/{id}"), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(revoked["revoked_at"].is_string());
+    let (status, _) = send_json(
+        &app,
+        "DELETE",
+        &format!("/api/api_keys/{}", uuid::Uuid::new_v4()),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_api_key_verification() {
+    let db = setup_test_db().await;
+    let created = create_api_key(&db, key_input(&["experiments"]), None)
+        .await
+        .unwrap();
+
+    let api_key = verify_key(&db, &created.key, "experiments").await.unwrap();
+    assert_eq!(api_key.username, "lab-instrument");
+    let stored = ApiKeys::find_by_id(api_key.id)
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(stored.last_used_at.is_some());
+
+    let token = key_token(&api_key);
+    assert_eq!(token.extra.profile.preferred_username, "lab-instrument");
+    assert_eq!(*token.roles[0].role(), Role::Editor);
+
+    let (status, message) = verify_key(&db, &created.key, "samples").await.unwrap_err();
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(message, "API key is not scoped for samples");
+    for wrong in [
+        format!("{}0", created.key),
+        "spice_".to_string(),
+        "not-a-key".to_string(),
+    ] {
+        let (status, _) = verify_key(&db, &wrong, "experiments").await.unwrap_err();
+        assert_eq!(status, StatusCode::UNAUTHORIZED, "{wrong}");
+    }
+
+    let mut expiring = key_input(&["experiments"]);
+    expiring.expires_at = Some(Utc::now() + Duration::milliseconds(200));
+    let expiring = create_api_key(&db, expiring, None).await.unwrap();
+    assert!(verify_key(&db, &expiring.key, "experiments").await.is_ok());
+    tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+    let (status, message) = verify_key(&db, &expiring.key, "experiments")
+        .await
+        .unwrap_err();
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(message, "API key has expired");
+
+    super::services::revoke_api_key(&db, api_key.id)
+        .await
+        .unwrap();
+    let (_, message) = verify_key(&db, &created.key, "experiments")
+        .await
+        .unwrap_err();
+    assert_eq!(message, "API key has been revoked");
+}
+
+#[tokio::test]
+async fn test_api_key_rate_limit() {
+    let db = setup_test_db().await;
+    let mut input = key_input(&["samples"]);
+    input.rate_limit_per_minute = Some(2);
+    let created = create_api_key(&db, input, None).await.unwrap();
+    let api_key = ApiKeys::find_by_id(created.api_key.id)
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+
+    let start = Utc::now();
+    assert!(check_rate_limit(&api_key, start).is_ok());
+    assert!(check_rate_limit(&api_key, start + Duration::seconds(10)).is_ok());
+    assert_eq!(
+        check_rate_limit(&api_key, start + Duration::seconds(15)),
+        Err(45)
+    );
+    // A new minute starts afresh
+    assert!(check_rate_limit(&api_key, start + Duration::seconds(61)).is_ok());
+}
+
+#[tokio::test]
+async fn test_api_key_middleware() {
+    let db = setup_test_db().await;
+    let created = create_api_key(&db, key_input(&["samples"]), None)
+        .await
+        .unwrap();
+
+    let app = Router::new()
+        .route(
+            "/",
+            get(|token: Option<Extension<KeycloakToken<Role>>>| async move {
+                token.map_or_else(
+                    || "anonymous".to_string(),
+                    |Extension(token)| token.extra.profile.preferred_username,
+                )
+            }),
+        )
+        .layer(middleware::from_fn_with_state(
+            (db.clone(), "samples"),
+            accept_api_keys,
+        ));
+    let request = |header: Option<(&str, String)>| {
+        let mut request = Request::builder().uri("/");
+        if let Some((name, value)) = header {
+            request = request.header(name, value);
+        }
+        request.body(Body::empty()).unwrap()
+    };
+    let text = |response: axum::response::Response| async move {
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    };
+
+    // Without a Keycloak layer, requests without a key pass as before
+    let response = app.clone().oneshot(request(None)).await.unwrap();
+    assert_eq!(text(response).await, "anonymous");
+
+    for header in [
+        ("x-api-key", created.key.clone()),
+        ("authorization", format!("Bearer {}", created.key)),
+    ] {
+        let response = app.clone().oneshot(request(Some(header))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(text(response).await, "lab-instrument");
+    }
+
+    let response = app
+        .clone()
+        .oneshot(request(Some(("x-api-key", "spice_00_00".to_string()))))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,134 @@
+use super::models::{ApiKey, ApiKeyCreate, CreatedApiKey};
+use super::services::{create_api_key, list_api_keys, revoke_api_key};
+use crate::audit::services::{AuditedResource, audit_changes};
+use crate::common::auth::{Role, RouteAccess, require_role};
+use crate::common::keycloak::authenticate;
+use crate::common::state::AppState;
+use crate::idempotency::services::ShowsSecret;
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+    http::StatusCode,
+    middleware,
+    routing::{delete, get},
+};
+use axum_keycloak_auth::{PassthroughMode, decode::KeycloakToken};
+use sea_orm::DbErr;
+use utoipa::OpenApi;
+use utoipa_axum::router::OpenApiRouter;
+use uuid::Uuid;
+
+/// List API keys
+#[utoipa::path(
+    get,
+    path = "",
+    responses(
+        (status = 200, description = "API keys, newest first, without their secrets", body = Vec<ApiKey>),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "api_keys",
+    summary = "List API keys",
+    description = "List the API keys of instruments and scripts with their user, role, scopes, limits and last use. Secrets are only shown when a key is created"
+)]
+pub async fn get_api_keys(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<ApiKey>>, (StatusCode, String)> {
+    list_api_keys(&state.db)
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// Create an API key
+#[utoipa::path(
+    post,
+    path = "",
+    request_body = ApiKeyCreate,
+    responses(
+        (status = 201, description = "The key with its secret, shown only this once", body = CreatedApiKey),
+        (status = 422, description = "Blank name, unknown scope, or invalid limit or expiry"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "api_keys",
+    summary = "Create an API key",
+    description = "Create a key for a headless lab PC or script, sent as the `X-API-Key` header or as a bearer token. The key acts as its user with the given role in the route groups of its scopes, at most as often a minute as its rate limit, until it expires or is revoked"
+)]
+pub async fn post_api_key(
+    State(state): State<AppState>,
+    token: Option<Extension<KeycloakToken<Role>>>,
+    Json(input): Json<ApiKeyCreate>,
+) -> Result<(StatusCode, Extension<ShowsSecret>, Json<CreatedApiKey>), (StatusCode, String)> {
+    let created_by = token.map(|Extension(token)| token.extra.profile.preferred_username);
+    create_api_key(&state.db, input, created_by)
+        .await
+        .map(|created| (StatusCode::CREATED, Extension(ShowsSecret), Json(created)))
+        .map_err(|e| match e {
+            DbErr::Custom(message) => (StatusCode::UNPROCESSABLE_ENTITY, message),
+            e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })
+}
+
+/// Revoke an API key
+#[utoipa::path(
+    delete,
+    path = "/{id}",
+    params(
+        ("id" = Uuid, Path, description = "API key ID")
+    ),
+    responses(
+        (status = 200, description = "The revoked key", body = ApiKey),
+        (status = 404, description = "API key not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "api_keys",
+    summary = "Revoke an API key",
+    description = "Stop accepting the key. It stays listed with the time it was revoked"
+)]
+pub async fn delete_api_key(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiKey>, (StatusCode, String)> {
+    revoke_api_key(&state.db, id)
+        .await
+        .map(Json)
+        .map_err(|e| match e {
+            DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+            e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })
+}
+
+/// Documentation of the routes, which are routed with `route`
+#[derive(OpenApi)]
+#[openapi(paths(get_api_keys, post_api_key, delete_api_key))]
+struct ApiKeysApi;
+
+pub fn router(state: &AppState) -> OpenApiRouter {
+    let mut router = OpenApiRouter::new()
+        .route("/", get(get_api_keys).post(post_api_key))
+        .route("/{id}", delete(delete_api_key))
+        .with_state(state.clone());
+    router.get_openapi_mut().merge(ApiKeysApi::openapi());
+
+    // Changes are logged with the user who made them
+    router = router.layer(middleware::from_fn_with_state(
+        (state.db.clone(), AuditedResource::ApiKeys),
+        audit_changes,
+    ));
+
+    // Keys are managed by signed-in administrators, not with keys
+    if let Some(instance) = state.keycloak_auth_instance.clone() {
+        router = router
+            .layer(middleware::from_fn_with_state(
+                RouteAccess::ADMINISTRATION,
+                require_role,
+            ))
+            .layer(middleware::from_fn_with_state(
+                (instance, PassthroughMode::Block),
+                authenticate,
+            ));
+    } else if !state.config.tests_running {
+        println!("Warning: API key routes are not protected");
+    }
+
+    router
+}
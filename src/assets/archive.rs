@@ -0,0 +1,777 @@
+//! Byte-addressable ZIP archives, so interrupted downloads can be resumed with
+//! HTTP range requests.
+//!
+//! Entries are stored uncompressed and their CRC is written in a data
+//! descriptor after the content. The position of every byte of the archive is
+//! then known from the object sizes alone, and any range can be served by
+//! fetching only the parts of the objects it covers. CRCs are needed for the
+//! descriptors and the central directory; they are cached on the layout as
+//! objects are streamed, so resuming near the end does not re-read everything.
+//! ZIP64 records are used once files or offsets exceed 4 GiB.
+
+use super::models as s3_assets;
+use super::services::sha256_hex;
+use crate::config::Config;
+use crate::external::s3::{get_object_range_from_s3, head_object_size};
+use axum::{
+    body::Body,
+    http::{
+        HeaderMap, StatusCode,
+        header::{
+            ACCEPT_RANGES, CACHE_CONTROL, CONTENT_DISPOSITION, CONTENT_LENGTH, CONTENT_RANGE,
+            CONTENT_TYPE, ETAG, IF_RANGE, RANGE,
+        },
+    },
+    response::Response,
+};
+use futures::stream::{self, StreamExt};
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+/// Size of the ranged reads used to stream an object
+const READ_CHUNK_SIZE: u64 = 4 * 1024 * 1024;
+/// Objects inspected at the same time while planning an archive
+const HEAD_CONCURRENCY: usize = 25;
+/// Sizes and offsets from this value on are stored in ZIP64 fields
+const ZIP64_LIMIT: u64 = 0xFFFF_FFFF;
+const VERSION_DEFAULT: u16 = 20;
+const VERSION_ZIP64: u16 = 45;
+/// General purpose flag: CRC and sizes follow the data in a descriptor
+const FLAG_DATA_DESCRIPTOR: u16 = 0x0008;
+
+/// An S3 object to include in an archive
+#[derive(Clone, Debug)]
+pub struct ArchiveFile {
+    /// Path of the file inside the archive
+    pub path: String,
+    pub s3_key: String,
+    pub size: u64,
+}
+
+#[derive(Debug)]
+struct PlannedFile {
+    file: ArchiveFile,
+    /// Offset of the local header in the archive
+    offset: u64,
+}
+
+impl PlannedFile {
+    fn is_zip64(&self) -> bool {
+        self.file.size >= ZIP64_LIMIT
+    }
+
+    fn header_len(&self) -> u64 {
+        30 + self.file.path.len() as u64 + if self.is_zip64() { 20 } else { 0 }
+    }
+
+    fn descriptor_len(&self) -> u64 {
+        if self.is_zip64() { 24 } else { 16 }
+    }
+
+    fn data_offset(&self) -> u64 {
+        self.offset + self.header_len()
+    }
+
+    fn descriptor_offset(&self) -> u64 {
+        self.data_offset() + self.file.size
+    }
+
+    fn end(&self) -> u64 {
+        self.descriptor_offset() + self.descriptor_len()
+    }
+
+    fn local_header(&self) -> Vec<u8> {
+        let name = self.file.path.as_bytes();
+        let zip64 = self.is_zip64();
+        // The real sizes are in the data descriptor
+        let size_field: u32 = if zip64 { u32::MAX } else { 0 };
+
+        let mut header = Vec::with_capacity(usize::try_from(self.header_len()).unwrap_or(0));
+        header.extend_from_slice(&[0x50, 0x4b, 0x03, 0x04]); // Local file header signature
+        header.extend_from_slice(&version_needed(zip64).to_le_bytes());
+        header.extend_from_slice(&FLAG_DATA_DESCRIPTOR.to_le_bytes());
+        header.extend_from_slice(&[0x00, 0x00]); // Compression method (stored)
+        header.extend_from_slice(&[0x00, 0x00]); // File last modification time
+        header.extend_from_slice(&[0x00, 0x00]); // File last modification date
+        header.extend_from_slice(&0u32.to_le_bytes()); // CRC-32 (in descriptor)
+        header.extend_from_slice(&size_field.to_le_bytes()); // Compressed size
+        header.extend_from_slice(&size_field.to_le_bytes()); // Uncompressed size
+        header.extend_from_slice(&name_len(name).to_le_bytes());
+        header.extend_from_slice(&(if zip64 { 20u16 } else { 0 }).to_le_bytes()); // Extra field length
+        header.extend_from_slice(name);
+        if zip64 {
+            header.extend_from_slice(&0x0001u16.to_le_bytes()); // ZIP64 extra field
+            header.extend_from_slice(&16u16.to_le_bytes());
+            header.extend_from_slice(&0u64.to_le_bytes()); // Uncompressed size
+            header.extend_from_slice(&0u64.to_le_bytes()); // Compressed size
+        }
+        header
+    }
+
+    fn data_descriptor(&self, crc: u32) -> Vec<u8> {
+        let mut descriptor =
+            Vec::with_capacity(usize::try_from(self.descriptor_len()).unwrap_or(0));
+        descriptor.extend_from_slice(&[0x50, 0x4b, 0x07, 0x08]); // Data descriptor signature
+        descriptor.extend_from_slice(&crc.to_le_bytes());
+        if self.is_zip64() {
+            descriptor.extend_from_slice(&self.file.size.to_le_bytes()); // Compressed size
+            descriptor.extend_from_slice(&self.file.size.to_le_bytes()); // Uncompressed size
+        } else {
+            let size = u32::try_from(self.file.size).unwrap_or(u32::MAX);
+            descriptor.extend_from_slice(&size.to_le_bytes());
+            descriptor.extend_from_slice(&size.to_le_bytes());
+        }
+        descriptor
+    }
+
+    fn central_directory_entry(&self, crc: u32) -> Vec<u8> {
+        let name = self.file.path.as_bytes();
+        let size_overflow = self.is_zip64();
+        let offset_overflow = self.offset >= ZIP64_LIMIT;
+
+        // Only the fields that overflow are repeated in the ZIP64 extra field
+        let mut extra = Vec::new();
+        if size_overflow {
+            extra.extend_from_slice(&self.file.size.to_le_bytes()); // Uncompressed size
+            extra.extend_from_slice(&self.file.size.to_le_bytes()); // Compressed size
+        }
+        if offset_overflow {
+            extra.extend_from_slice(&self.offset.to_le_bytes());
+        }
+        if !extra.is_empty() {
+            let mut field = Vec::with_capacity(4 + extra.len());
+            field.extend_from_slice(&0x0001u16.to_le_bytes());
+            field.extend_from_slice(&u16::try_from(extra.len()).unwrap_or(0).to_le_bytes());
+            field.extend_from_slice(&extra);
+            extra = field;
+        }
+        let version = version_needed(!extra.is_empty());
+        let size = u32::try_from(self.file.size).unwrap_or(u32::MAX);
+        let offset = u32::try_from(self.offset).unwrap_or(u32::MAX);
+
+        let mut entry = Vec::with_capacity(46 + name.len() + extra.len());
+        entry.extend_from_slice(&[0x50, 0x4b, 0x01, 0x02]); // Central directory file header signature
+        entry.extend_from_slice(&version.to_le_bytes()); // Version made by
+        entry.extend_from_slice(&version.to_le_bytes()); // Version needed to extract
+        entry.extend_from_slice(&FLAG_DATA_DESCRIPTOR.to_le_bytes());
+        entry.extend_from_slice(&[0x00, 0x00]); // Compression method (stored)
+        entry.extend_from_slice(&[0x00, 0x00]); // Last mod file time
+        entry.extend_from_slice(&[0x00, 0x00]); // Last mod file date
+        entry.extend_from_slice(&crc.to_le_bytes());
+        entry.extend_from_slice(&size.to_le_bytes()); // Compressed size
+        entry.extend_from_slice(&size.to_le_bytes()); // Uncompressed size
+        entry.extend_from_slice(&name_len(name).to_le_bytes());
+        entry.extend_from_slice(&u16::try_from(extra.len()).unwrap_or(0).to_le_bytes());
+        entry.extend_from_slice(&[0x00, 0x00]); // File comment length
+        entry.extend_from_slice(&[0x00, 0x00]); // Disk number start
+        entry.extend_from_slice(&[0x00, 0x00]); // Internal file attributes
+        entry.extend_from_slice(&[0x00, 0x00, 0x00, 0x00]); // External file attributes
+        entry.extend_from_slice(&offset.to_le_bytes()); // Relative offset of local header
+        entry.extend_from_slice(name);
+        entry.extend_from_slice(&extra);
+        entry
+    }
+}
+
+fn version_needed(zip64: bool) -> u16 {
+    if zip64 {
+        VERSION_ZIP64
+    } else {
+        VERSION_DEFAULT
+    }
+}
+
+fn name_len(name: &[u8]) -> u16 {
+    u16::try_from(name.len()).unwrap_or(u16::MAX)
+}
+
+/// The byte layout of an archive, shared by every request made with the same
+/// download token
+#[derive(Debug)]
+pub struct ArchiveLayout {
+    files: Vec<PlannedFile>,
+    central_directory_offset: u64,
+    total_len: u64,
+    etag: String,
+    crcs: Mutex<Vec<Option<u32>>>,
+}
+
+impl ArchiveLayout {
+    pub fn new(files: Vec<ArchiveFile>) -> Self {
+        let mut offset = 0;
+        let files: Vec<PlannedFile> = files
+            .into_iter()
+            .map(|file| {
+                let planned = PlannedFile { file, offset };
+                offset = planned.end();
+                planned
+            })
+            .collect();
+
+        let mut fingerprint = String::new();
+        for planned in &files {
+            let _ = writeln!(
+                fingerprint,
+                "{}\n{}\n{}",
+                planned.file.path, planned.file.s3_key, planned.file.size
+            );
+        }
+
+        let mut layout = Self {
+            central_directory_offset: offset,
+            total_len: 0,
+            etag: format!("\"{}\"", &sha256_hex(fingerprint.as_bytes())[..32]),
+            crcs: Mutex::new(vec![None; files.len()]),
+            files,
+        };
+        // The length of the trailer does not depend on the CRCs
+        layout.total_len = offset + layout.trailer(&vec![0; layout.files.len()]).len() as u64;
+        layout
+    }
+
+    pub fn total_len(&self) -> u64 {
+        self.total_len
+    }
+
+    /// Changes whenever the set of files or their sizes change
+    pub fn etag(&self) -> &str {
+        &self.etag
+    }
+
+    fn crc(&self, index: usize) -> Option<u32> {
+        self.crcs.lock().ok().and_then(|crcs| crcs[index])
+    }
+
+    fn set_crc(&self, index: usize, crc: u32) {
+        if let Ok(mut crcs) = self.crcs.lock() {
+            crcs[index] = Some(crc);
+        }
+    }
+
+    /// Central directory and end records
+    fn trailer(&self, crcs: &[u32]) -> Vec<u8> {
+        let mut trailer: Vec<u8> = self
+            .files
+            .iter()
+            .zip(crcs)
+            .flat_map(|(planned, crc)| planned.central_directory_entry(*crc))
+            .collect();
+        let cd_len = trailer.len() as u64;
+        let cd_offset = self.central_directory_offset;
+        let total_files = self.files.len() as u64;
+
+        if total_files >= 0xFFFF || cd_len >= ZIP64_LIMIT || cd_offset >= ZIP64_LIMIT {
+            let zip64_end_offset = cd_offset + cd_len;
+            trailer.extend_from_slice(&[0x50, 0x4b, 0x06, 0x06]); // ZIP64 end of central dir signature
+            trailer.extend_from_slice(&44u64.to_le_bytes()); // Size of the remaining record
+            trailer.extend_from_slice(&VERSION_ZIP64.to_le_bytes()); // Version made by
+            trailer.extend_from_slice(&VERSION_ZIP64.to_le_bytes()); // Version needed to extract
+            trailer.extend_from_slice(&0u32.to_le_bytes()); // Number of this disk
+            trailer.extend_from_slice(&0u32.to_le_bytes()); // Disk with start of central directory
+            trailer.extend_from_slice(&total_files.to_le_bytes()); // Entries on this disk
+            trailer.extend_from_slice(&total_files.to_le_bytes()); // Total entries
+            trailer.extend_from_slice(&cd_len.to_le_bytes());
+            trailer.extend_from_slice(&cd_offset.to_le_bytes());
+
+            trailer.extend_from_slice(&[0x50, 0x4b, 0x06, 0x07]); // ZIP64 end of central dir locator signature
+            trailer.extend_from_slice(&0u32.to_le_bytes()); // Disk with the ZIP64 end record
+            trailer.extend_from_slice(&zip64_end_offset.to_le_bytes());
+            trailer.extend_from_slice(&1u32.to_le_bytes()); // Total number of disks
+        }
+
+        let entries = u16::try_from(total_files).unwrap_or(u16::MAX);
+        trailer.extend_from_slice(&[0x50, 0x4b, 0x05, 0x06]); // End of central dir signature
+        trailer.extend_from_slice(&[0x00, 0x00]); // Number of this disk
+        trailer.extend_from_slice(&[0x00, 0x00]); // Disk with start of central directory
+        trailer.extend_from_slice(&entries.to_le_bytes()); // Entries on this disk
+        trailer.extend_from_slice(&entries.to_le_bytes()); // Total entries
+        trailer.extend_from_slice(&u32::try_from(cd_len).unwrap_or(u32::MAX).to_le_bytes());
+        trailer.extend_from_slice(&u32::try_from(cd_offset).unwrap_or(u32::MAX).to_le_bytes());
+        trailer.extend_from_slice(&[0x00, 0x00]); // ZIP file comment length
+        trailer
+    }
+
+    /// Stream bytes `start..=end` of the archive. A failure to read an object
+    /// ends the stream with an error, so the client can resume from what it got.
+    pub fn stream_range(
+        self: Arc<Self>,
+        config: Config,
+        start: u64,
+        end: u64,
+    ) -> mpsc::Receiver<Result<Vec<u8>, std::io::Error>> {
+        let (tx, rx) = mpsc::channel(4);
+        tokio::spawn(async move {
+            if let Err(e) = self.write_range(&config, start, end, &tx).await {
+                let _ = tx.send(Err(std::io::Error::other(e))).await;
+            }
+        });
+        rx
+    }
+
+    async fn write_range(
+        &self,
+        config: &Config,
+        start: u64,
+        end: u64,
+        tx: &Sender,
+    ) -> Result<(), String> {
+        let range = (start, end);
+
+        for (index, planned) in self.files.iter().enumerate() {
+            if planned.offset > end {
+                return Ok(());
+            }
+            if planned.end() <= start {
+                continue;
+            }
+
+            emit(tx, &planned.local_header(), planned.offset, range).await?;
+
+            let descriptor_offset = planned.descriptor_offset();
+            let needs_descriptor = descriptor_offset <= end && planned.end() > start;
+            let needs_crc = needs_descriptor && self.crc(index).is_none();
+            self.copy_data(index, config, tx, Some(range), needs_crc)
+                .await?;
+
+            if needs_descriptor {
+                let crc = self
+                    .crc(index)
+                    .ok_or_else(|| format!("CRC of {} is unknown", planned.file.path))?;
+                emit(tx, &planned.data_descriptor(crc), descriptor_offset, range).await?;
+            }
+        }
+
+        if end < self.central_directory_offset {
+            return Ok(());
+        }
+        let mut crcs = Vec::with_capacity(self.files.len());
+        for index in 0..self.files.len() {
+            if self.crc(index).is_none() {
+                self.copy_data(index, config, tx, None, true).await?;
+            }
+            crcs.push(self.crc(index).unwrap_or_default());
+        }
+        emit(
+            tx,
+            &self.trailer(&crcs),
+            self.central_directory_offset,
+            range,
+        )
+        .await
+    }
+
+    /// Stream the content of a file that falls within `range`. With
+    /// `needs_crc`, the whole object is read so its CRC can be recorded.
+    async fn copy_data(
+        &self,
+        index: usize,
+        config: &Config,
+        tx: &Sender,
+        range: Option<(u64, u64)>,
+        needs_crc: bool,
+    ) -> Result<(), String> {
+        let planned = &self.files[index];
+        let size = planned.file.size;
+        let data_offset = planned.data_offset();
+
+        // Part of the object that was requested, as `from..to`
+        let (wanted_from, wanted_to) = range.map_or((0, 0), |(start, end)| {
+            (
+                start.saturating_sub(data_offset).min(size),
+                (end + 1).saturating_sub(data_offset).min(size),
+            )
+        });
+        let (read_from, read_to) = if needs_crc {
+            (0, size)
+        } else if wanted_from < wanted_to {
+            (wanted_from, wanted_to)
+        } else {
+            return Ok(());
+        };
+
+        let mut hasher = (read_from == 0).then(crc32fast::Hasher::new);
+        let mut position = read_from;
+        while position < read_to {
+            let chunk_end = (position + READ_CHUNK_SIZE).min(read_to);
+            let chunk =
+                get_object_range_from_s3(&planned.file.s3_key, position, chunk_end - 1, config)
+                    .await?;
+            if chunk.len() as u64 != chunk_end - position {
+                return Err(format!(
+                    "Object {} changed since the archive was planned",
+                    planned.file.s3_key
+                ));
+            }
+            if let Some(hasher) = hasher.as_mut() {
+                hasher.update(&chunk);
+            }
+            if let Some(range) = range {
+                emit(tx, &chunk, data_offset + position, range).await?;
+            }
+            position = chunk_end;
+        }
+
+        if read_to == size
+            && let Some(hasher) = hasher
+        {
+            self.set_crc(index, hasher.finalize());
+        }
+        Ok(())
+    }
+}
+
+type Sender = mpsc::Sender<Result<Vec<u8>, std::io::Error>>;
+
+/// Send the part of `bytes`, located at `offset` in the archive, that falls
+/// within `start..=end`
+async fn emit(
+    tx: &Sender,
+    bytes: &[u8],
+    offset: u64,
+    (start, end): (u64, u64),
+) -> Result<(), String> {
+    let len = bytes.len() as u64;
+    if offset > end || offset + len <= start {
+        return Ok(());
+    }
+    let from = usize::try_from(start.saturating_sub(offset)).unwrap_or(usize::MAX);
+    let to = usize::try_from((end + 1 - offset).min(len)).unwrap_or(usize::MAX);
+    tx.send(Ok(bytes[from..to].to_vec()))
+        .await
+        .map_err(|_| "Client disconnected".to_string())
+}
+
+/// Look up the size of every asset's object and lay out the archive. Objects
+/// that no longer exist are left out.
+pub async fn plan_asset_archive(
+    assets: Vec<s3_assets::Model>,
+    config: &Config,
+) -> Result<ArchiveLayout, (StatusCode, String)> {
+    if assets.is_empty() {
+        return Err((StatusCode::NOT_FOUND, "No assets to download".to_string()));
+    }
+
+    let keys: Vec<String> = assets
+        .iter()
+        .map(|asset| asset.storage_key().to_string())
+        .collect();
+    let sizes: Vec<Result<Option<i64>, String>> = stream::iter(keys)
+        .map(|key| async move { head_object_size(&key, config).await })
+        .buffered(HEAD_CONCURRENCY)
+        .collect()
+        .await;
+
+    let mut files = Vec::with_capacity(assets.len());
+    for (asset, size) in assets.into_iter().zip(sizes) {
+        let size = size.map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+        if let Some(size) = size {
+            files.push(ArchiveFile {
+                s3_key: asset.storage_key().to_string(),
+                path: asset.original_filename,
+                size: u64::try_from(size).unwrap_or_default(),
+            });
+        }
+    }
+    if files.is_empty() {
+        return Err((StatusCode::NOT_FOUND, "No assets to download".to_string()));
+    }
+
+    Ok(ArchiveLayout::new(files))
+}
+
+/// The part of the archive a request asks for
+#[derive(Debug, PartialEq, Eq)]
+pub enum ByteRange {
+    Full,
+    /// Inclusive start and end offsets
+    Partial(u64, u64),
+    Unsatisfiable,
+}
+
+/// Interpret a `Range` header against an archive of `total_len` bytes. Only a
+/// single byte range is honoured; anything else gets the full archive.
+pub fn parse_range(header: Option<&str>, total_len: u64) -> ByteRange {
+    let Some(spec) = header.and_then(|h| h.trim().strip_prefix("bytes=")) else {
+        return ByteRange::Full;
+    };
+    if spec.contains(',') {
+        return ByteRange::Full;
+    }
+    let Some((first, last)) = spec.split_once('-') else {
+        return ByteRange::Full;
+    };
+    let (first, last) = (first.trim(), last.trim());
+
+    if first.is_empty() {
+        // Suffix range: the last N bytes
+        return match last.parse::<u64>() {
+            Ok(0) => ByteRange::Unsatisfiable,
+            Ok(_) if total_len == 0 => ByteRange::Unsatisfiable,
+            Ok(suffix) => ByteRange::Partial(total_len.saturating_sub(suffix), total_len - 1),
+            Err(_) => ByteRange::Full,
+        };
+    }
+
+    let Ok(start) = first.parse::<u64>() else {
+        return ByteRange::Full;
+    };
+    let end = if last.is_empty() {
+        u64::MAX
+    } else {
+        match last.parse::<u64>() {
+            Ok(end) if end >= start => end,
+            _ => return ByteRange::Full,
+        }
+    };
+    if start >= total_len {
+        return ByteRange::Unsatisfiable;
+    }
+    ByteRange::Partial(start, end.min(total_len - 1))
+}
+
+/// Serve the archive, or the single byte range the request asks for
+pub fn ranged_zip_response(
+    layout: Arc<ArchiveLayout>,
+    config: &Config,
+    headers: &HeaderMap,
+    filename: &str,
+) -> Response {
+    let total_len = layout.total_len();
+    // A range only applies to the archive the client started downloading
+    let range_header = headers
+        .get(RANGE)
+        .and_then(|value| value.to_str().ok())
+        .filter(|_| {
+            headers
+                .get(IF_RANGE)
+                .is_none_or(|value| value.as_bytes() == layout.etag().as_bytes())
+        });
+
+    let builder = Response::builder()
+        .header(CONTENT_TYPE, "application/zip")
+        .header(
+            CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{filename}\""),
+        )
+        .header(ACCEPT_RANGES, "bytes")
+        .header(ETAG, layout.etag())
+        .header(CACHE_CONTROL, "no-cache")
+        .header("X-Accel-Buffering", "no");
+
+    let (builder, start, end) = match parse_range(range_header, total_len) {
+        ByteRange::Full => (builder.status(StatusCode::OK), 0, total_len - 1),
+        ByteRange::Partial(start, end) => (
+            builder
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(CONTENT_RANGE, format!("bytes {start}-{end}/{total_len}")),
+            start,
+            end,
+        ),
+        ByteRange::Unsatisfiable => {
+            return builder
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(CONTENT_RANGE, format!("bytes */{total_len}"))
+                .body(Body::empty())
+                .unwrap();
+        }
+    };
+
+    let mut rx = layout.stream_range(config.clone(), start, end);
+    let stream = async_stream::stream! {
+        while let Some(chunk) = rx.recv().await {
+            yield chunk;
+        }
+    };
+
+    builder
+        .header(CONTENT_LENGTH, end - start + 1)
+        .body(Body::from_stream(stream))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::external::s3::MOCK_S3_STORE;
+    use std::io::Read;
+
+    async fn read_range(layout: &Arc<ArchiveLayout>, start: u64, end: u64) -> Vec<u8> {
+        let mut rx = layout.clone().stream_range(Config::for_tests(), start, end);
+        let mut bytes = Vec::new();
+        while let Some(chunk) = rx.recv().await {
+            bytes.extend_from_slice(&chunk.unwrap());
+        }
+        bytes
+    }
+
+    fn test_files(prefix: &str) -> Vec<ArchiveFile> {
+        [
+            ("a.txt", &b"first file"[..]),
+            ("empty.txt", b""),
+            ("dir/c.bin", &[7u8; 1000]),
+        ]
+        .into_iter()
+        .map(|(path, data)| {
+            let s3_key = format!("{prefix}/{path}");
+            MOCK_S3_STORE.put_object(&s3_key, data.to_vec()).unwrap();
+            ArchiveFile {
+                path: path.to_string(),
+                s3_key,
+                size: data.len() as u64,
+            }
+        })
+        .collect()
+    }
+
+    #[tokio::test]
+    async fn test_full_archive_is_valid_zip() {
+        let prefix = format!("test/archive/{}", uuid::Uuid::new_v4());
+        let layout = Arc::new(ArchiveLayout::new(test_files(&prefix)));
+
+        let archive = read_range(&layout, 0, layout.total_len() - 1).await;
+        assert_eq!(archive.len() as u64, layout.total_len());
+
+        let mut zip = zip::ZipArchive::new(std::io::Cursor::new(archive)).unwrap();
+        assert_eq!(zip.len(), 3);
+        let mut content = Vec::new();
+        zip.by_name("dir/c.bin")
+            .unwrap()
+            .read_to_end(&mut content)
+            .unwrap();
+        assert_eq!(content, vec![7u8; 1000]);
+        let mut content = String::new();
+        zip.by_name("a.txt")
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, "first file");
+    }
+
+    #[tokio::test]
+    async fn test_ranges_match_full_archive() {
+        let prefix = format!("test/archive/{}", uuid::Uuid::new_v4());
+        let files = test_files(&prefix);
+        let full = {
+            let layout = Arc::new(ArchiveLayout::new(files.clone()));
+            read_range(&layout, 0, layout.total_len() - 1).await
+        };
+
+        // A fresh layout has no CRCs cached, as when a server restart is
+        // followed by a resumed download
+        let total = full.len() as u64;
+        for (start, end) in [
+            (0, 10),
+            (5, 60),
+            (40, 1100),
+            (1000, total - 1),
+            (total - 1, total - 1),
+        ] {
+            let layout = Arc::new(ArchiveLayout::new(files.clone()));
+            let part = read_range(&layout, start, end).await;
+            assert_eq!(
+                part,
+                full[usize::try_from(start).unwrap()..=usize::try_from(end).unwrap()],
+                "Range {start}-{end} differs from the full archive"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_changed_object_fails_stream() {
+        let prefix = format!("test/archive/{}", uuid::Uuid::new_v4());
+        let files = test_files(&prefix);
+        let layout = Arc::new(ArchiveLayout::new(files.clone()));
+        MOCK_S3_STORE
+            .put_object(&files[2].s3_key, b"truncated".to_vec())
+            .unwrap();
+
+        let mut rx = layout
+            .clone()
+            .stream_range(Config::for_tests(), 0, layout.total_len() - 1);
+        let mut failed = false;
+        while let Some(chunk) = rx.recv().await {
+            failed |= chunk.is_err();
+        }
+        assert!(failed);
+    }
+
+    #[test]
+    fn test_zip64_layout() {
+        let layout = ArchiveLayout::new(vec![
+            ArchiveFile {
+                path: "huge.raw".to_string(),
+                s3_key: "huge".to_string(),
+                size: 5 * 1024 * 1024 * 1024,
+            },
+            ArchiveFile {
+                path: "after.txt".to_string(),
+                s3_key: "after".to_string(),
+                size: 10,
+            },
+        ]);
+        let huge = &layout.files[0];
+        let after = &layout.files[1];
+        assert!(huge.is_zip64());
+        assert_eq!(huge.header_len(), 30 + 8 + 20);
+        assert_eq!(after.offset, huge.end());
+        assert!(after.offset > ZIP64_LIMIT);
+
+        let trailer = layout.trailer(&[0, 0]);
+        assert_eq!(
+            layout.total_len(),
+            layout.central_directory_offset + trailer.len() as u64
+        );
+        // ZIP64 end record, locator and the classic end record
+        let end = trailer.len();
+        assert_eq!(trailer[end - 22..end - 18], [0x50, 0x4b, 0x05, 0x06]);
+        assert_eq!(trailer[end - 42..end - 38], [0x50, 0x4b, 0x06, 0x07]);
+        assert_eq!(trailer[end - 98..end - 94], [0x50, 0x4b, 0x06, 0x06]);
+        // The second entry's offset no longer fits the classic field
+        assert_eq!(
+            after.central_directory_entry(0).len(),
+            46 + "after.txt".len() + 4 + 8
+        );
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range(None, 100), ByteRange::Full);
+        assert_eq!(
+            parse_range(Some("bytes=0-9"), 100),
+            ByteRange::Partial(0, 9)
+        );
+        assert_eq!(
+            parse_range(Some("bytes=50-"), 100),
+            ByteRange::Partial(50, 99)
+        );
+        assert_eq!(
+            parse_range(Some("bytes=90-500"), 100),
+            ByteRange::Partial(90, 99)
+        );
+        assert_eq!(
+            parse_range(Some("bytes=-10"), 100),
+            ByteRange::Partial(90, 99)
+        );
+        assert_eq!(
+            parse_range(Some("bytes=-500"), 100),
+            ByteRange::Partial(0, 99)
+        );
+        assert_eq!(
+            parse_range(Some("bytes=100-"), 100),
+            ByteRange::Unsatisfiable
+        );
+        assert_eq!(parse_range(Some("bytes=-0"), 100), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range(Some("bytes=0-1,5-9"), 100), ByteRange::Full);
+        assert_eq!(parse_range(Some("bytes=9-0"), 100), ByteRange::Full);
+        assert_eq!(parse_range(Some("items=0-9"), 100), ByteRange::Full);
+    }
+
+    #[tokio::test]
+    async fn test_plan_asset_archive_without_assets() {
+        let result = plan_asset_archive(Vec::new(), &Config::for_tests()).await;
+        let (status, message) = result.unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(message, "No assets to download");
+    }
+}
@@ -0,0 +1,360 @@
+//! Capture times of camera images and their link to temperature readings.
+//!
+//! The capture time comes from the `INP_*` filename, or else from the EXIF
+//! `DateTimeOriginal` of the image. An image is linked to the reading whose
+//! `image_filename` names it, or else to the reading recorded closest to its
+//! capture time, provided it is at most [`MAX_LINK_GAP_SECONDS`] away.
+
+use super::models as s3_assets;
+use crate::experiments::temperatures::models as temperature_readings;
+use crate::experiments::timelapse::parse_capture_time;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use sea_orm::{
+    ActiveValue::Set, ColumnTrait, Condition, ConnectionTrait, DatabaseConnection, DbErr,
+    EntityTrait, QueryFilter,
+};
+use uuid::Uuid;
+
+/// Readings further away than this belong to another part of the run
+pub const MAX_LINK_GAP_SECONDS: i64 = 120;
+
+const TAG_EXIF_IFD: u16 = 0x8769;
+const TAG_DATE_TIME: u16 = 0x0132;
+const TAG_DATE_TIME_ORIGINAL: u16 = 0x9003;
+const TAG_DATE_TIME_DIGITIZED: u16 = 0x9004;
+const TYPE_ASCII: u16 = 2;
+
+/// Reads the TIFF structure that holds EXIF data
+struct Tiff<'a> {
+    data: &'a [u8],
+    little_endian: bool,
+}
+
+impl Tiff<'_> {
+    fn u16_at(&self, offset: usize) -> Option<u16> {
+        let bytes: [u8; 2] = self.data.get(offset..offset + 2)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    }
+
+    fn u32_at(&self, offset: usize) -> Option<u32> {
+        let bytes: [u8; 4] = self.data.get(offset..offset + 4)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+
+    /// Entries of the IFD at `offset` as (tag, type, count, value offset)
+    fn entries(&self, offset: usize) -> Vec<(u16, u16, usize, usize)> {
+        let count = self.u16_at(offset).unwrap_or(0);
+        (0..usize::from(count))
+            .map_while(|index| {
+                let entry = offset + 2 + index * 12;
+                let count = usize::try_from(self.u32_at(entry + 4)?).ok()?;
+                // Values of up to four bytes are stored in the entry itself
+                let value = if count <= 4 {
+                    entry + 8
+                } else {
+                    usize::try_from(self.u32_at(entry + 8)?).ok()?
+                };
+                Some((self.u16_at(entry)?, self.u16_at(entry + 2)?, count, value))
+            })
+            .collect()
+    }
+
+    fn date_time(&self, entries: &[(u16, u16, usize, usize)], tag: u16) -> Option<NaiveDateTime> {
+        let &(_, _, count, value) = entries
+            .iter()
+            .find(|(entry_tag, kind, ..)| *entry_tag == tag && *kind == TYPE_ASCII)?;
+        let text = std::str::from_utf8(self.data.get(value..value + count)?).ok()?;
+        NaiveDateTime::parse_from_str(text.trim_end_matches('\0').trim(), "%Y:%m:%d %H:%M:%S").ok()
+    }
+}
+
+/// Capture time in raw EXIF data: `DateTimeOriginal`, `DateTimeDigitized` or
+/// the IFD0 `DateTime`, in that order
+fn exif_date_time(exif: &[u8]) -> Option<NaiveDateTime> {
+    let data = exif.strip_prefix(b"Exif\0\0").unwrap_or(exif);
+    let little_endian = match data.get(..2)? {
+        b"II" => true,
+        b"MM" => false,
+        _ => return None,
+    };
+    let tiff = Tiff {
+        data,
+        little_endian,
+    };
+    if tiff.u16_at(2)? != 42 {
+        return None;
+    }
+
+    let ifd0 = tiff.entries(usize::try_from(tiff.u32_at(4)?).ok()?);
+    let exif_ifd = ifd0
+        .iter()
+        .find(|(tag, ..)| *tag == TAG_EXIF_IFD)
+        .and_then(|&(_, _, _, value)| tiff.u32_at(value))
+        .and_then(|offset| usize::try_from(offset).ok())
+        .map(|offset| tiff.entries(offset))
+        .unwrap_or_default();
+
+    tiff.date_time(&exif_ifd, TAG_DATE_TIME_ORIGINAL)
+        .or_else(|| tiff.date_time(&exif_ifd, TAG_DATE_TIME_DIGITIZED))
+        .or_else(|| tiff.date_time(&ifd0, TAG_DATE_TIME))
+}
+
+/// EXIF capture time of an encoded image
+fn image_exif_date_time(image_bytes: &[u8]) -> Option<NaiveDateTime> {
+    use image::ImageDecoder;
+
+    let mut decoder = image::ImageReader::new(std::io::Cursor::new(image_bytes))
+        .with_guessed_format()
+        .ok()?
+        .into_decoder()
+        .ok()?;
+    exif_date_time(&decoder.exif_metadata().ok()??)
+}
+
+/// Bytes at the start of an image that hold its EXIF data, ahead of the
+/// pixels, enough for [`capture_time`] without reading the whole file
+pub const EXIF_HEADER_BYTES: u64 = 256 * 1024;
+
+/// When an image was taken: from its filename, or else its EXIF data. Camera
+/// clocks carry no time zone and are read as UTC, like the spreadsheet times.
+pub fn capture_time(filename: &str, image_bytes: Option<&[u8]>) -> Option<DateTime<Utc>> {
+    parse_capture_time(filename)
+        .or_else(|| image_bytes.and_then(image_exif_date_time))
+        .map(|captured| captured.and_utc())
+}
+
+fn file_stem(filename: &str) -> &str {
+    std::path::Path::new(filename)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or(filename)
+}
+
+/// The reading that names the image, or else the one closest in time
+fn closest_reading(
+    readings: &[temperature_readings::Model],
+    filename: &str,
+    captured_at: Option<DateTime<Utc>>,
+) -> Option<Uuid> {
+    let stem = file_stem(filename);
+    if let Some(reading) = readings.iter().find(|reading| {
+        reading
+            .image_filename
+            .as_deref()
+            .is_some_and(|name| file_stem(name) == stem)
+    }) {
+        return Some(reading.id);
+    }
+
+    let captured_at = captured_at?;
+    readings
+        .iter()
+        .map(|reading| {
+            let gap = (reading.timestamp - captured_at).num_milliseconds().abs();
+            (reading, gap)
+        })
+        .filter(|(_, gap)| *gap <= MAX_LINK_GAP_SECONDS * 1000)
+        .min_by_key(|(reading, gap)| (*gap, reading.timestamp))
+        .map(|(reading, _)| reading.id)
+}
+
+async fn set_link(
+    db: &impl ConnectionTrait,
+    asset: &s3_assets::Model,
+    captured_at: Option<DateTime<Utc>>,
+    reading_id: Option<Uuid>,
+) -> Result<(), DbErr> {
+    if asset.captured_at == captured_at && asset.temperature_reading_id == reading_id {
+        return Ok(());
+    }
+    s3_assets::Entity::update(s3_assets::ActiveModel {
+        id: Set(asset.id),
+        captured_at: Set(captured_at),
+        temperature_reading_id: Set(reading_id),
+        ..Default::default()
+    })
+    .exec(db)
+    .await?;
+    Ok(())
+}
+
+/// Link an image asset to the temperature reading taken when it was captured.
+/// Returns the linked reading.
+pub async fn link_image_to_reading(
+    db: &DatabaseConnection,
+    asset: &s3_assets::Model,
+) -> Result<Option<Uuid>, DbErr> {
+    let Some(experiment_id) = asset.experiment_id else {
+        return Ok(None);
+    };
+    let captured_at = asset
+        .captured_at
+        .or_else(|| capture_time(&asset.original_filename, None));
+
+    let stem = file_stem(&asset.original_filename);
+    let mut nearby = Condition::any().add(
+        temperature_readings::Column::ImageFilename.is_in([stem, asset.original_filename.as_str()]),
+    );
+    if let Some(captured_at) = captured_at {
+        let gap = chrono::Duration::seconds(MAX_LINK_GAP_SECONDS);
+        nearby = nearby.add(
+            temperature_readings::Column::Timestamp.between(captured_at - gap, captured_at + gap),
+        );
+    }
+    let readings = temperature_readings::Entity::find()
+        .filter(temperature_readings::Column::ExperimentId.eq(experiment_id))
+        .filter(nearby)
+        .all(db)
+        .await?;
+
+    let reading_id = closest_reading(&readings, &asset.original_filename, captured_at);
+    set_link(db, asset, captured_at, reading_id).await?;
+    Ok(reading_id)
+}
+
+/// Link every image of an experiment to its temperature reading, after the
+/// readings were (re)loaded. Returns how many images are linked.
+pub async fn link_experiment_images(
+    db: &impl ConnectionTrait,
+    experiment_id: Uuid,
+) -> Result<usize, DbErr> {
+    let readings = temperature_readings::Entity::find()
+        .filter(temperature_readings::Column::ExperimentId.eq(experiment_id))
+        .all(db)
+        .await?;
+    let images = s3_assets::Entity::find()
+        .filter(s3_assets::Column::ExperimentId.eq(experiment_id))
+        .filter(s3_assets::Column::Type.eq("image"))
+        .all(db)
+        .await?;
+
+    let mut linked = 0;
+    for image in &images {
+        let captured_at = image
+            .captured_at
+            .or_else(|| capture_time(&image.original_filename, None));
+        let reading_id = closest_reading(&readings, &image.original_filename, captured_at);
+        set_link(db, image, captured_at, reading_id).await?;
+        linked += usize::from(reading_id.is_some());
+    }
+    Ok(linked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Little-endian TIFF with `DateTime` in IFD0 and `DateTimeOriginal` in
+    /// the EXIF IFD
+    fn exif_block(original: Option<&str>) -> Vec<u8> {
+        let mut data = b"Exif\0\0II\x2a\0\x08\0\0\0".to_vec();
+        let tiff_len = |data: &Vec<u8>| u32::try_from(data.len() - 6).unwrap();
+        let entry = |data: &mut Vec<u8>, tag: u16, kind: u16, count: u32, value: u32| {
+            data.extend_from_slice(&tag.to_le_bytes());
+            data.extend_from_slice(&kind.to_le_bytes());
+            data.extend_from_slice(&count.to_le_bytes());
+            data.extend_from_slice(&value.to_le_bytes());
+        };
+
+        // IFD0 at 8 with two entries, then the EXIF IFD with one entry, then
+        // the strings
+        let exif_ifd = 8 + 2 + 2 * 12 + 4;
+        let date_time = exif_ifd + 2 + 12 + 4;
+        let date_time_original = date_time + 20;
+        data.extend_from_slice(&2u16.to_le_bytes());
+        entry(&mut data, TAG_DATE_TIME, TYPE_ASCII, 20, date_time);
+        entry(&mut data, TAG_EXIF_IFD, 4, 1, exif_ifd);
+        data.extend_from_slice(&0u32.to_le_bytes());
+        assert_eq!(tiff_len(&data), exif_ifd);
+        data.extend_from_slice(&u16::from(original.is_some()).to_le_bytes());
+        if original.is_some() {
+            entry(
+                &mut data,
+                TAG_DATE_TIME_ORIGINAL,
+                TYPE_ASCII,
+                20,
+                date_time_original,
+            );
+        } else {
+            data.extend_from_slice(&[0; 12]);
+        }
+        data.extend_from_slice(&0u32.to_le_bytes());
+        assert_eq!(tiff_len(&data), date_time);
+        data.extend_from_slice(b"2025:03:21 08:00:00\0");
+        data.extend_from_slice(original.unwrap_or("").as_bytes());
+        data.push(0);
+        data
+    }
+
+    fn time(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    #[test]
+    fn test_exif_date_time() {
+        assert_eq!(
+            exif_date_time(&exif_block(Some("2025:03:20 15:14:17"))),
+            Some(time("2025-03-20 15:14:17"))
+        );
+        // Without DateTimeOriginal the IFD0 DateTime is used
+        assert_eq!(
+            exif_date_time(&exif_block(None)),
+            Some(time("2025-03-21 08:00:00"))
+        );
+        assert_eq!(exif_date_time(b"not exif"), None);
+        assert_eq!(
+            exif_date_time(&exif_block(Some("2025:03:20 15:14:17"))[..20]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_capture_time_prefers_the_filename() {
+        assert_eq!(
+            capture_time("INP_1_2025-03-20_15-14-17.jpg", None),
+            Some(time("2025-03-20 15:14:17").and_utc())
+        );
+        assert_eq!(capture_time("camera.jpg", Some(b"garbage")), None);
+    }
+
+    #[test]
+    fn test_closest_reading() {
+        let reading = |timestamp: &str, image_filename: Option<&str>| temperature_readings::Model {
+            id: Uuid::new_v4(),
+            experiment_id: Uuid::nil(),
+            timestamp: time(timestamp).and_utc(),
+            image_filename: image_filename.map(str::to_string),
+            created_at: Utc::now(),
+            average: None,
+            probe_readings: vec![],
+        };
+        let readings = vec![
+            reading("2025-03-20 15:14:00", Some("INP_1_2025-03-20_15-14-00")),
+            reading("2025-03-20 15:14:20", None),
+            reading("2025-03-20 15:20:00", None),
+        ];
+
+        // The reading naming the image wins over a closer one
+        let captured = Some(time("2025-03-20 15:14:19").and_utc());
+        assert_eq!(
+            closest_reading(&readings, "INP_1_2025-03-20_15-14-00.jpg", captured),
+            Some(readings[0].id)
+        );
+        assert_eq!(
+            closest_reading(&readings, "INP_2.jpg", captured),
+            Some(readings[1].id)
+        );
+        // Nothing within the gap
+        let captured = Some(time("2025-03-20 15:17:10").and_utc());
+        assert_eq!(closest_reading(&readings, "INP_3.jpg", captured), None);
+        assert_eq!(closest_reading(&readings, "INP_3.jpg", None), None);
+    }
+}
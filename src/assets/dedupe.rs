@@ -0,0 +1,130 @@
+//! Content-addressable storage for assets with identical content.
+//!
+//! Each asset is first stored under its own key. When an upload has the same
+//! checksum and size as an existing asset, the content is kept once in a
+//! content-addressed object and both rows point at it through
+//! `blob_s3_key`; the per-asset objects are removed. Shared objects are
+//! reference counted by the rows that point at them and only deleted with the
+//! last one.
+
+use super::models as s3_assets;
+use crate::config::Config;
+use crate::external::s3::{copy_object_in_s3, delete_object_from_s3, head_object_size};
+use sea_orm::{
+    ActiveValue::Set, ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter,
+};
+use uuid::Uuid;
+
+/// Larger objects cannot be copied in a single request, so they are stored as
+/// uploaded
+const MAX_DEDUPLICATED_SIZE: i64 = 5 * 1024 * 1024 * 1024;
+
+/// Content-addressed key for content with the given SHA-256
+pub fn blob_key(config: &Config, checksum_sha256: &str) -> String {
+    format!(
+        "{}/{}/blobs/sha256/{checksum_sha256}",
+        config.app_name, config.deployment
+    )
+}
+
+/// Share storage between a newly stored asset and existing assets with the
+/// same content. Returns whether the asset now points at a shared object.
+pub async fn deduplicate_asset(
+    db: &DatabaseConnection,
+    config: &Config,
+    asset: &s3_assets::Model,
+) -> Result<bool, String> {
+    let (Some(checksum), Some(size)) = (&asset.checksum_sha256, asset.size_bytes) else {
+        return Ok(false);
+    };
+    if asset.blob_s3_key.is_some() || size > MAX_DEDUPLICATED_SIZE {
+        return Ok(false);
+    }
+
+    let duplicates = s3_assets::Entity::find()
+        .filter(s3_assets::Column::ChecksumSha256.eq(checksum))
+        .filter(s3_assets::Column::SizeBytes.eq(size))
+        .filter(s3_assets::Column::Id.ne(asset.id))
+        .all(db)
+        .await
+        .map_err(|e| format!("Failed to look up duplicates: {e}"))?;
+    if duplicates.is_empty() {
+        return Ok(false);
+    }
+
+    let blob = blob_key(config, checksum);
+    // The new upload's content is known to match the checksum, so it seeds
+    // the shared object if there is none yet
+    let blob_size = head_object_size(&blob, config).await?;
+    if blob_size != Some(size) {
+        copy_object_in_s3(&asset.s3_key, &blob, config).await?;
+    }
+
+    for duplicate in duplicates
+        .iter()
+        .chain(std::iter::once(asset))
+        .filter(|duplicate| duplicate.blob_s3_key.is_none())
+    {
+        s3_assets::Entity::update(s3_assets::ActiveModel {
+            id: Set(duplicate.id),
+            blob_s3_key: Set(Some(blob.clone())),
+            ..Default::default()
+        })
+        .exec(db)
+        .await
+        .map_err(|e| {
+            format!(
+                "Failed to point asset {} at shared object: {e}",
+                duplicate.id
+            )
+        })?;
+
+        if let Err(e) = delete_object_from_s3(&duplicate.s3_key, config).await {
+            println!(
+                "Warning: Failed to delete deduplicated object from S3: {} - {e}",
+                duplicate.s3_key
+            );
+        }
+    }
+
+    Ok(true)
+}
+
+/// Delete the S3 objects of assets that are about to be removed: their own
+/// objects and thumbnails, and shared objects no other asset points at
+pub async fn release_asset_objects(
+    db: &DatabaseConnection,
+    config: &Config,
+    assets: &[s3_assets::Model],
+) -> Result<(), String> {
+    let released_ids: Vec<Uuid> = assets.iter().map(|asset| asset.id).collect();
+
+    for asset in assets {
+        delete_object_from_s3(&asset.s3_key, config)
+            .await
+            .map_err(|e| format!("Failed to delete S3 asset with key {}: {e}", asset.s3_key))?;
+
+        if let Some(blob) = &asset.blob_s3_key {
+            let references = s3_assets::Entity::find()
+                .filter(s3_assets::Column::BlobS3Key.eq(blob))
+                .filter(s3_assets::Column::Id.is_not_in(released_ids.clone()))
+                .count(db)
+                .await
+                .map_err(|e| format!("Failed to count references to {blob}: {e}"))?;
+            if references == 0 {
+                delete_object_from_s3(blob, config)
+                    .await
+                    .map_err(|e| format!("Failed to delete S3 asset with key {blob}: {e}"))?;
+            }
+        }
+
+        // Thumbnails can always be regenerated, so a failure only leaves an
+        // unreferenced object behind
+        if let Some(thumbnail_key) = &asset.thumbnail_s3_key
+            && let Err(e) = delete_object_from_s3(thumbnail_key, config).await
+        {
+            println!("Warning: Failed to delete thumbnail from S3: {thumbnail_key} - {e}");
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,121 @@
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// What a download token gives
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, ToSchema, Serialize, Deserialize,
+)]
+#[sea_orm(rs_type = "String", db_type = "Text")]
+#[serde(rename_all = "snake_case")]
+pub enum DownloadScope {
+    /// One asset, downloaded as it is
+    #[sea_orm(string_value = "asset")]
+    Asset,
+    /// Chosen assets, downloaded as a ZIP file
+    #[sea_orm(string_value = "assets")]
+    Assets,
+    /// Every asset of an experiment, downloaded as a ZIP file
+    #[sea_orm(string_value = "experiment")]
+    Experiment,
+}
+
+/// A download token as issued. Only the SHA-256 hash of the token is kept.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "download_tokens")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    #[sea_orm(column_type = "Text", unique)]
+    pub token_hash: String,
+    pub scope: DownloadScope,
+    #[sea_orm(column_type = "JsonBinary")]
+    pub asset_ids: Json,
+    pub experiment_id: Option<Uuid>,
+    /// Downloads the token may start, unlimited when unset
+    pub max_downloads: Option<i32>,
+    /// Downloads started, not counting resumed ones
+    pub downloads: i32,
+    /// Times a started download was resumed
+    pub resumes: i32,
+    /// Last time a download may be started or resumed
+    pub expires_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub revoked_by: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub created_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    pub fn asset_list(&self) -> Vec<Uuid> {
+        serde_json::from_value(self.asset_ids.clone()).unwrap_or_default()
+    }
+}
+
+/// A download token as shown to administrators, without its secret
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct DownloadToken {
+    pub id: Uuid,
+    pub scope: DownloadScope,
+    pub asset_ids: Vec<Uuid>,
+    pub experiment_id: Option<Uuid>,
+    pub max_downloads: Option<i32>,
+    pub downloads: i32,
+    pub resumes: i32,
+    pub expires_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub revoked_by: Option<String>,
+    pub created_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<Model> for DownloadToken {
+    fn from(model: Model) -> Self {
+        Self {
+            asset_ids: model.asset_list(),
+            id: model.id,
+            scope: model.scope,
+            experiment_id: model.experiment_id,
+            max_downloads: model.max_downloads,
+            downloads: model.downloads,
+            resumes: model.resumes,
+            expires_at: model.expires_at,
+            last_used_at: model.last_used_at,
+            revoked_at: model.revoked_at,
+            revoked_by: model.revoked_by,
+            created_by: model.created_by,
+            created_at: model.created_at,
+        }
+    }
+}
+
+/// How long and how often a new token may be used. The configured defaults
+/// apply to what is omitted.
+#[derive(Clone, Debug, Default, Deserialize, ToSchema)]
+pub struct DownloadTokenOptions {
+    /// Minutes within which a download must be started, at most the
+    /// configured maximum
+    pub ttl_minutes: Option<i64>,
+    /// Downloads the token may start, 1 for a single-use token
+    pub max_downloads: Option<i32>,
+}
+
+/// A new token, the only time it is shown
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct IssuedDownloadToken {
+    pub token: String,
+    pub download_url: String,
+    #[serde(flatten)]
+    pub download_token: DownloadToken,
+}
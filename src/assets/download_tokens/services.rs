@@ -0,0 +1,197 @@
+//! Download tokens, which let a browser download assets without signing in.
+//!
+//! A token gives one asset, chosen assets or every asset of an experiment.
+//! It is single-use unless asked otherwise, and starts at most its number of
+//! downloads. It expires at a fixed time set when it is issued; a started
+//! download can be resumed until then, even when the token is used up, by a
+//! `Range` request that does not start at the first byte, at most
+//! [`MAX_RESUMES`] times. Every token issued is kept, without its secret,
+//! with who issued it and how it was used, and an administrator can revoke
+//! it.
+
+use super::models::{
+    ActiveModel, Column, DownloadScope, DownloadToken, DownloadTokenOptions, Entity,
+    IssuedDownloadToken, Model,
+};
+use crate::assets::services::sha256_hex;
+use crate::config::Config;
+use axum::http::StatusCode;
+use chrono::{DateTime, Duration, Utc};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, DatabaseConnection, DbErr, EntityTrait,
+    IntoActiveModel, QueryFilter, QueryOrder, QuerySelect, sea_query::Expr,
+};
+use serde::Deserialize;
+use uuid::Uuid;
+
+/// Times the downloads of a token can be resumed in all
+pub const MAX_RESUMES: i32 = 20;
+/// Most tokens listed at once
+const LIST_LIMIT: u64 = 500;
+
+/// Issue a token for a download
+pub async fn issue_download_token(
+    db: &DatabaseConnection,
+    config: &Config,
+    scope: DownloadScope,
+    asset_ids: Vec<Uuid>,
+    experiment_id: Option<Uuid>,
+    options: DownloadTokenOptions,
+) -> Result<IssuedDownloadToken, DbErr> {
+    let ttl_minutes = options
+        .ttl_minutes
+        .unwrap_or(config.download_token_ttl_minutes);
+    if !(1..=config.download_token_max_ttl_minutes).contains(&ttl_minutes) {
+        return Err(DbErr::Custom(format!(
+            "ttl_minutes must be between 1 and {}",
+            config.download_token_max_ttl_minutes
+        )));
+    }
+    let max_downloads = options
+        .max_downloads
+        .or(config.download_token_max_downloads);
+    if max_downloads.is_some_and(|max_downloads| max_downloads < 1) {
+        return Err(DbErr::Custom(
+            "max_downloads must be at least 1".to_string(),
+        ));
+    }
+
+    let token = Uuid::new_v4().to_string();
+    let now = Utc::now();
+    let model = ActiveModel {
+        id: Set(Uuid::new_v4()),
+        token_hash: Set(sha256_hex(token.as_bytes())),
+        scope: Set(scope),
+        asset_ids: Set(serde_json::json!(asset_ids)),
+        experiment_id: Set(experiment_id),
+        max_downloads: Set(max_downloads),
+        downloads: Set(0),
+        resumes: Set(0),
+        expires_at: Set(now + Duration::minutes(ttl_minutes)),
+        last_used_at: Set(None),
+        revoked_at: Set(None),
+        revoked_by: Set(None),
+        created_by: Set(crate::common::auth::current_username()),
+        created_at: Set(now),
+    }
+    .insert(db)
+    .await?;
+
+    Ok(IssuedDownloadToken {
+        download_url: format!("/api/assets/download/{token}"),
+        token,
+        download_token: model.into(),
+    })
+}
+
+/// Use a token to start a download, or to resume a started one. Returns the
+/// token as it is after the use.
+pub async fn use_download_token(
+    db: &DatabaseConnection,
+    token: &str,
+    resuming: bool,
+    now: DateTime<Utc>,
+) -> Result<Model, (StatusCode, String)> {
+    let rejected = |message: &str| (StatusCode::NOT_FOUND, message.to_string());
+    let internal = |e: DbErr| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+
+    let model = Entity::find()
+        .filter(Column::TokenHash.eq(sha256_hex(token.as_bytes())))
+        .one(db)
+        .await
+        .map_err(internal)?
+        .ok_or_else(|| rejected("Invalid or expired token"))?;
+    if model.revoked_at.is_some() {
+        return Err(rejected("This download token was revoked"));
+    }
+    if model.expires_at < now {
+        return Err(rejected("Invalid or expired token"));
+    }
+
+    let resumed_too_often = || rejected("This download has been resumed too often");
+    let used_up = || rejected("This download token has been used up");
+    let resuming = resuming && model.downloads > 0;
+    let update = Entity::update_many()
+        .col_expr(Column::LastUsedAt, Expr::value(now))
+        .filter(Column::Id.eq(model.id));
+    let update = if resuming {
+        if model.resumes >= MAX_RESUMES {
+            return Err(resumed_too_often());
+        }
+        // Parts of a download may be resumed at the same time
+        update
+            .col_expr(Column::Resumes, Expr::col(Column::Resumes).add(1))
+            .filter(Column::Resumes.lt(MAX_RESUMES))
+    } else {
+        if model
+            .max_downloads
+            .is_some_and(|max_downloads| model.downloads >= max_downloads)
+        {
+            return Err(used_up());
+        }
+        // Only one of two downloads racing for a token's last use may start
+        update
+            .col_expr(Column::Downloads, Expr::value(model.downloads + 1))
+            .filter(Column::Downloads.eq(model.downloads))
+    };
+    if update.exec(db).await.map_err(internal)?.rows_affected == 0 {
+        return Err(if resuming {
+            resumed_too_often()
+        } else {
+            used_up()
+        });
+    }
+    Ok(Model {
+        downloads: model.downloads + i32::from(!resuming),
+        resumes: model.resumes + i32::from(resuming),
+        last_used_at: Some(now),
+        ..model
+    })
+}
+
+#[derive(Clone, Debug, Default, Deserialize, utoipa::IntoParams)]
+pub struct DownloadTokenQuery {
+    pub experiment_id: Option<Uuid>,
+    /// Keycloak username, or the user of the API key, that issued the token
+    pub created_by: Option<String>,
+}
+
+/// Issued tokens, newest first
+pub async fn list_download_tokens(
+    db: &DatabaseConnection,
+    query: DownloadTokenQuery,
+) -> Result<Vec<DownloadToken>, DbErr> {
+    let mut select = Entity::find();
+    if let Some(experiment_id) = query.experiment_id {
+        select = select.filter(Column::ExperimentId.eq(experiment_id));
+    }
+    if let Some(created_by) = query.created_by {
+        select = select.filter(Column::CreatedBy.eq(created_by));
+    }
+    Ok(select
+        .order_by_desc(Column::CreatedAt)
+        .limit(LIST_LIMIT)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(DownloadToken::from)
+        .collect())
+}
+
+/// Revoke a token for good. Revoking it again changes nothing.
+pub async fn revoke_download_token(
+    db: &DatabaseConnection,
+    id: Uuid,
+) -> Result<DownloadToken, DbErr> {
+    let model = Entity::find_by_id(id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Download token not found".to_string()))?;
+    if model.revoked_at.is_some() {
+        return Ok(model.into());
+    }
+    let mut active = model.into_active_model();
+    active.revoked_at = Set(Some(Utc::now()));
+    active.revoked_by = Set(crate::common::auth::current_username());
+    Ok(active.update(db).await?.into())
+}
@@ -0,0 +1,275 @@
+//! Verify stored objects against the SHA-256 checksums recorded at upload
+
+use super::models as s3_assets;
+use super::services::sha256_hex;
+use crate::common::state::AppState;
+use crate::config::Config;
+use crate::external::s3::{get_object_from_s3, head_object_size};
+use chrono::{DateTime, Utc};
+use futures::stream::{self, StreamExt};
+use sea_orm::{ActiveValue::Set, DatabaseConnection, EntityTrait, QueryOrder, entity::prelude::*};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Objects fetched from S3 at the same time during an audit
+const AUDIT_CONCURRENCY: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum IntegrityStatus {
+    /// Object matches its recorded checksum
+    Ok,
+    /// Object content differs from its recorded checksum
+    Corrupted,
+    /// Object is not in the bucket
+    Missing,
+    /// No checksum was recorded for the asset
+    Unverified,
+    /// No checksum was recorded; the current content's checksum has been stored
+    Recorded,
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct AssetIntegrityResult {
+    pub asset_id: Uuid,
+    pub original_filename: String,
+    pub s3_key: String,
+    pub status: IntegrityStatus,
+    pub expected_sha256: Option<String>,
+    pub actual_sha256: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Outcome of verifying the assets of one experiment. Only assets that are
+/// not `ok` are listed.
+#[derive(Clone, Debug, Default, Serialize, ToSchema)]
+pub struct ExperimentIntegrityReport {
+    /// `null` for assets not attached to an experiment
+    pub experiment_id: Option<Uuid>,
+    pub checked: usize,
+    pub ok: usize,
+    pub corrupted: usize,
+    pub missing: usize,
+    pub unverified: usize,
+    pub recorded: usize,
+    pub problems: Vec<AssetIntegrityResult>,
+}
+
+impl ExperimentIntegrityReport {
+    fn add(&mut self, result: AssetIntegrityResult) {
+        self.checked += 1;
+        match result.status {
+            IntegrityStatus::Ok => {
+                self.ok += 1;
+                return;
+            }
+            IntegrityStatus::Corrupted => self.corrupted += 1,
+            IntegrityStatus::Missing => self.missing += 1,
+            IntegrityStatus::Unverified => self.unverified += 1,
+            IntegrityStatus::Recorded => self.recorded += 1,
+        }
+        self.problems.push(result);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditStatus {
+    Running,
+    Completed,
+    Failed,
+}
+
+/// Bucket-wide audit run in the background
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct IntegrityAudit {
+    pub id: Uuid,
+    pub status: AuditStatus,
+    pub started_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub record_missing: bool,
+    pub error: Option<String>,
+    /// One report per experiment that has assets
+    pub experiments: Vec<ExperimentIntegrityReport>,
+}
+
+async fn verify_asset(
+    db: &DatabaseConnection,
+    config: &Config,
+    asset: s3_assets::Model,
+    record_missing: bool,
+) -> AssetIntegrityResult {
+    // Deduplicated assets are checked through the object they share
+    let s3_key = asset.storage_key().to_string();
+    let mut result = AssetIntegrityResult {
+        asset_id: asset.id,
+        original_filename: asset.original_filename,
+        s3_key,
+        status: IntegrityStatus::Missing,
+        expected_sha256: asset.checksum_sha256,
+        actual_sha256: None,
+        error: None,
+    };
+
+    // Tell a missing object apart from a failure to reach the bucket
+    match head_object_size(&result.s3_key, config).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return result,
+        Err(e) => {
+            result.error = Some(e);
+            return result;
+        }
+    }
+    let data = match get_object_from_s3(&result.s3_key, config).await {
+        Ok(data) => data,
+        Err(e) => {
+            result.error = Some(e);
+            return result;
+        }
+    };
+    let actual = sha256_hex(&data);
+
+    result.status = match &result.expected_sha256 {
+        Some(expected) if *expected == actual => IntegrityStatus::Ok,
+        Some(_) => IntegrityStatus::Corrupted,
+        None if record_missing => {
+            let update = s3_assets::ActiveModel {
+                id: Set(result.asset_id),
+                checksum_sha256: Set(Some(actual.clone())),
+                ..Default::default()
+            };
+            match s3_assets::Entity::update(update).exec(db).await {
+                Ok(_) => IntegrityStatus::Recorded,
+                Err(e) => {
+                    result.error = Some(format!("Failed to record checksum: {e}"));
+                    IntegrityStatus::Unverified
+                }
+            }
+        }
+        None => IntegrityStatus::Unverified,
+    };
+    result.actual_sha256 = Some(actual);
+    result
+}
+
+/// Verify the given assets and group the results by experiment.
+///
+/// With `record_missing`, assets uploaded before checksums were recorded get
+/// the checksum of their current content.
+pub async fn verify_assets(
+    db: &DatabaseConnection,
+    config: &Config,
+    assets: Vec<s3_assets::Model>,
+    record_missing: bool,
+) -> Vec<ExperimentIntegrityReport> {
+    let results: Vec<(Option<Uuid>, AssetIntegrityResult)> = stream::iter(assets)
+        .map(|asset| async move {
+            let experiment_id = asset.experiment_id;
+            (
+                experiment_id,
+                verify_asset(db, config, asset, record_missing).await,
+            )
+        })
+        .buffer_unordered(AUDIT_CONCURRENCY)
+        .collect()
+        .await;
+
+    let mut reports: BTreeMap<Option<Uuid>, ExperimentIntegrityReport> = BTreeMap::new();
+    for (experiment_id, result) in results {
+        reports
+            .entry(experiment_id)
+            .or_insert_with(|| ExperimentIntegrityReport {
+                experiment_id,
+                ..Default::default()
+            })
+            .add(result);
+    }
+    for report in reports.values_mut() {
+        report
+            .problems
+            .sort_by(|a, b| a.original_filename.cmp(&b.original_filename));
+    }
+    reports.into_values().collect()
+}
+
+/// Verify the assets of a single experiment
+pub async fn verify_experiment_assets(
+    db: &DatabaseConnection,
+    config: &Config,
+    experiment_id: Uuid,
+    record_missing: bool,
+) -> Result<ExperimentIntegrityReport, DbErr> {
+    let assets = s3_assets::Entity::find()
+        .filter(s3_assets::Column::ExperimentId.eq(experiment_id))
+        .all(db)
+        .await?;
+
+    Ok(verify_assets(db, config, assets, record_missing)
+        .await
+        .pop()
+        .unwrap_or_else(|| ExperimentIntegrityReport {
+            experiment_id: Some(experiment_id),
+            ..Default::default()
+        }))
+}
+
+/// Start a bucket-wide audit unless one is already running
+pub async fn start_audit(state: &AppState, record_missing: bool) -> Option<IntegrityAudit> {
+    let audit = {
+        let mut current = state.integrity_audit.write().await;
+        if current
+            .as_ref()
+            .is_some_and(|audit| audit.status == AuditStatus::Running)
+        {
+            return None;
+        }
+        let audit = IntegrityAudit {
+            id: Uuid::new_v4(),
+            status: AuditStatus::Running,
+            started_at: Utc::now(),
+            completed_at: None,
+            record_missing,
+            error: None,
+            experiments: vec![],
+        };
+        *current = Some(audit.clone());
+        audit
+    };
+
+    let state = state.clone();
+    let audit_id = audit.id;
+    tokio::spawn(async move {
+        let assets = s3_assets::Entity::find()
+            .order_by_asc(s3_assets::Column::ExperimentId)
+            .all(&state.db)
+            .await;
+        let outcome = match assets {
+            Ok(assets) => Ok(verify_assets(&state.db, &state.config, assets, record_missing).await),
+            Err(e) => Err(format!("Failed to load assets: {e}")),
+        };
+
+        if let Some(audit) = state
+            .integrity_audit
+            .write()
+            .await
+            .as_mut()
+            .filter(|audit| audit.id == audit_id)
+        {
+            audit.completed_at = Some(Utc::now());
+            match outcome {
+                Ok(experiments) => {
+                    audit.status = AuditStatus::Completed;
+                    audit.experiments = experiments;
+                }
+                Err(e) => {
+                    audit.status = AuditStatus::Failed;
+                    audit.error = Some(e);
+                }
+            }
+        }
+    });
+
+    Some(audit)
+}
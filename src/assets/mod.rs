@@ -1,4 +1,11 @@
+pub mod archive;
+pub mod capture;
+pub mod dedupe;
+pub mod download_tokens;
+pub mod integrity;
 pub mod models;
+pub mod orphans;
+pub mod search;
 pub mod services;
 #[cfg(test)]
 pub mod tests;
@@ -1,4 +1,5 @@
-use crate::external::s3::delete_from_s3;
+use super::dedupe::release_asset_objects;
+use crate::config::Config;
 use chrono::{DateTime, Utc};
 use crudcrate::{CRUDResource, EntityToModels};
 use sea_orm::entity::prelude::*;
@@ -51,6 +52,25 @@ pub struct Model {
     #[sea_orm(column_type = "Text", nullable)]
     #[crudcrate(filterable)]
     pub processing_message: Option<String>,
+    /// Key of the generated JPEG thumbnail, for image assets
+    #[sea_orm(column_type = "Text", nullable)]
+    #[crudcrate(create_model = false, update_model = false)]
+    pub thumbnail_s3_key: Option<String>,
+    /// Hex SHA-256 of the object, recorded when it was uploaded
+    #[sea_orm(column_type = "Text", nullable)]
+    #[crudcrate(filterable, create_model = false, update_model = false)]
+    pub checksum_sha256: Option<String>,
+    /// Content-addressed object holding the data when other assets have the
+    /// same content. The object at `s3_key` is then removed.
+    #[sea_orm(column_type = "Text", nullable)]
+    #[crudcrate(filterable, create_model = false, update_model = false)]
+    pub blob_s3_key: Option<String>,
+    /// When a camera image was taken, from its filename or EXIF data
+    #[crudcrate(sortable, filterable, create_model = false, update_model = false)]
+    pub captured_at: Option<DateTime<Utc>>,
+    /// Temperature reading recorded closest to `captured_at`
+    #[crudcrate(filterable, create_model = false, update_model = false)]
+    pub temperature_reading_id: Option<Uuid>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -63,6 +83,14 @@ pub enum Relation {
         on_delete = "NoAction"
     )]
     Experiments,
+    #[sea_orm(
+        belongs_to = "crate::experiments::temperatures::models::Entity",
+        from = "Column::TemperatureReadingId",
+        to = "crate::experiments::temperatures::models::Column::Id",
+        on_update = "NoAction",
+        on_delete = "SetNull"
+    )]
+    TemperatureReadings,
 }
 
 impl Related<crate::experiments::models::Entity> for Entity {
@@ -71,8 +99,21 @@ impl Related<crate::experiments::models::Entity> for Entity {
     }
 }
 
+impl Related<crate::experiments::temperatures::models::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::TemperatureReadings.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
 
+impl Model {
+    /// Key of the S3 object that holds the asset's content
+    pub fn storage_key(&self) -> &str {
+        self.blob_s3_key.as_deref().unwrap_or(&self.s3_key)
+    }
+}
+
 async fn delete_asset(db: &DatabaseConnection, id: Uuid) -> Result<Uuid, DbErr> {
     // Fetch the asset to get its S3 key
     let asset = Entity::find_by_id(id)
@@ -80,13 +121,10 @@ async fn delete_asset(db: &DatabaseConnection, id: Uuid) -> Result<Uuid, DbErr>
         .await?
         .ok_or_else(|| DbErr::RecordNotFound("Asset not found".to_string()))?;
 
-    // Delete the asset from S3
-    if let Err(e) = delete_from_s3(&asset.s3_key).await {
-        return Err(DbErr::Custom(format!(
-            "Failed to delete S3 asset with key {}: {}",
-            asset.s3_key, e
-        )));
-    }
+    // Delete the asset from S3, keeping content shared with other assets
+    release_asset_objects(db, &Config::from_env(), std::slice::from_ref(&asset))
+        .await
+        .map_err(DbErr::Custom)?;
 
     // Proceed with deleting the database record
     let res = Entity::delete_by_id(id).exec(db).await?;
@@ -109,15 +147,10 @@ pub async fn delete_many_assets(
     // Track which assets actually exist in the database
     let existing_asset_ids: Vec<Uuid> = assets.iter().map(|a| a.id).collect();
 
-    // Delete the assets from S3 first
-    for asset in &assets {
-        if let Err(e) = delete_from_s3(&asset.s3_key).await {
-            return Err(DbErr::Custom(format!(
-                "Failed to delete S3 asset with key {}: {}",
-                asset.s3_key, e
-            )));
-        }
-    }
+    // Delete the assets from S3 first, keeping content shared with other assets
+    release_asset_objects(db, &Config::from_env(), &assets)
+        .await
+        .map_err(DbErr::Custom)?;
 
     // Proceed with deleting the database records
     let delete_result = Entity::delete_many()
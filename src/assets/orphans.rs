@@ -0,0 +1,326 @@
+//! Reconcile stored objects with asset rows.
+//!
+//! Uploads that fail midway leave objects that no row points at, and objects
+//! removed outside the API leave rows that point at nothing. A cleanup lists
+//! the deployment's objects, compares them with the keys referenced by assets
+//! and running exports, and reports or removes whatever does not match.
+//! Anything newer than the minimum age is left alone, since uploads still in
+//! progress look the same.
+
+use super::integrity::AuditStatus;
+use super::models as s3_assets;
+use crate::common::state::AppState;
+use crate::config::Config;
+use crate::external::s3::{delete_object_from_s3, list_objects_in_s3};
+use chrono::{DateTime, Utc};
+use sea_orm::EntityTrait;
+use serde::Serialize;
+use std::collections::HashSet;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Age below which objects and rows are never considered orphaned
+pub const DEFAULT_MIN_AGE_HOURS: u64 = 24;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CleanupTrigger {
+    Manual,
+    Scheduled,
+}
+
+#[derive(Clone, Debug)]
+pub struct CleanupOptions {
+    /// Only objects with keys under this prefix are scanned
+    pub prefix: String,
+    pub remove: bool,
+    pub min_age_hours: u64,
+}
+
+impl CleanupOptions {
+    /// Scan the whole deployment
+    pub fn new(config: &Config, remove: bool) -> Self {
+        Self {
+            prefix: deployment_prefix(config),
+            remove,
+            min_age_hours: DEFAULT_MIN_AGE_HOURS,
+        }
+    }
+}
+
+/// Object in the bucket that no asset or export refers to
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct OrphanedObject {
+    pub key: String,
+    pub size_bytes: i64,
+    pub last_modified: Option<DateTime<Utc>>,
+    pub removed: bool,
+    pub error: Option<String>,
+}
+
+/// Asset whose object is not in the bucket
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct OrphanedAsset {
+    pub asset_id: Uuid,
+    pub experiment_id: Option<Uuid>,
+    pub original_filename: String,
+    /// Key of the missing object
+    pub s3_key: String,
+    pub uploaded_at: DateTime<Utc>,
+    pub removed: bool,
+    pub error: Option<String>,
+}
+
+/// Cleanup run in the background
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct OrphanCleanup {
+    pub id: Uuid,
+    pub status: AuditStatus,
+    pub trigger: CleanupTrigger,
+    pub started_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub prefix: String,
+    /// Orphans are deleted, not only listed
+    pub remove: bool,
+    pub min_age_hours: u64,
+    pub objects_scanned: usize,
+    pub assets_scanned: usize,
+    pub orphaned_bytes: i64,
+    pub removed_objects: usize,
+    pub removed_assets: usize,
+    pub error: Option<String>,
+    pub orphaned_objects: Vec<OrphanedObject>,
+    pub orphaned_assets: Vec<OrphanedAsset>,
+}
+
+/// Prefix of every key this deployment writes
+pub fn deployment_prefix(config: &Config) -> String {
+    format!("{}/{}/", config.app_name, config.deployment)
+}
+
+async fn find_orphans(
+    state: &AppState,
+    cleanup: &mut OrphanCleanup,
+    cutoff: DateTime<Utc>,
+) -> Result<(), String> {
+    let objects = list_objects_in_s3(&cleanup.prefix, &state.config).await?;
+    // Soft-deleted assets still own their objects until they are purged
+    let assets = s3_assets::Entity::find()
+        .all(&state.db)
+        .await
+        .map_err(|e| format!("Failed to load assets: {e}"))?;
+    cleanup.objects_scanned = objects.len();
+    cleanup.assets_scanned = assets.len();
+
+    let mut referenced: HashSet<String> = assets
+        .iter()
+        .flat_map(|asset| {
+            [
+                Some(asset.s3_key.clone()),
+                asset.thumbnail_s3_key.clone(),
+                asset.blob_s3_key.clone(),
+            ]
+        })
+        .flatten()
+        .collect();
+    referenced.extend(
+        state
+            .export_jobs
+            .read()
+            .await
+            .keys()
+            .map(|job_id| crate::exports::services::export_key(&state.config, *job_id)),
+    );
+    let stored: HashSet<&str> = objects.iter().map(|object| object.key.as_str()).collect();
+
+    cleanup.orphaned_assets = assets
+        .iter()
+        .filter(|asset| {
+            let key = asset.storage_key();
+            key.starts_with(&cleanup.prefix) && !stored.contains(key) && asset.uploaded_at < cutoff
+        })
+        .map(|asset| OrphanedAsset {
+            asset_id: asset.id,
+            experiment_id: asset.experiment_id,
+            original_filename: asset.original_filename.clone(),
+            s3_key: asset.storage_key().to_string(),
+            uploaded_at: asset.uploaded_at,
+            removed: false,
+            error: None,
+        })
+        .collect();
+    // Objects of unknown age are only taken without a minimum age
+    cleanup.orphaned_objects = objects
+        .into_iter()
+        .filter(|object| {
+            !referenced.contains(&object.key)
+                && object
+                    .last_modified
+                    .map_or(cleanup.min_age_hours == 0, |time| time < cutoff)
+        })
+        .map(|object| OrphanedObject {
+            key: object.key,
+            size_bytes: object.size,
+            last_modified: object.last_modified,
+            removed: false,
+            error: None,
+        })
+        .collect();
+    cleanup.orphaned_bytes = cleanup
+        .orphaned_objects
+        .iter()
+        .map(|object| object.size_bytes)
+        .sum();
+    Ok(())
+}
+
+async fn remove_orphans(state: &AppState, cleanup: &mut OrphanCleanup) {
+    for object in &mut cleanup.orphaned_objects {
+        match delete_object_from_s3(&object.key, &state.config).await {
+            Ok(()) => object.removed = true,
+            Err(e) => object.error = Some(e),
+        }
+    }
+
+    for orphan in &mut cleanup.orphaned_assets {
+        let thumbnail = s3_assets::Entity::find_by_id(orphan.asset_id)
+            .one(&state.db)
+            .await
+            .ok()
+            .flatten()
+            .and_then(|asset| asset.thumbnail_s3_key);
+        match s3_assets::Entity::delete_by_id(orphan.asset_id)
+            .exec(&state.db)
+            .await
+        {
+            Ok(_) => orphan.removed = true,
+            Err(e) => {
+                orphan.error = Some(format!("Failed to delete asset: {e}"));
+                continue;
+            }
+        }
+        // The thumbnail would be orphaned by the row's removal
+        if let Some(thumbnail) = thumbnail
+            && let Err(e) = delete_object_from_s3(&thumbnail, &state.config).await
+        {
+            orphan.error = Some(e);
+        }
+    }
+
+    cleanup.removed_objects = cleanup
+        .orphaned_objects
+        .iter()
+        .filter(|object| object.removed)
+        .count();
+    cleanup.removed_assets = cleanup
+        .orphaned_assets
+        .iter()
+        .filter(|asset| asset.removed)
+        .count();
+}
+
+async fn run_cleanup(state: &AppState, cleanup: &mut OrphanCleanup) -> Result<(), String> {
+    let cutoff = i64::try_from(cleanup.min_age_hours)
+        .ok()
+        .and_then(chrono::Duration::try_hours)
+        .and_then(|min_age| cleanup.started_at.checked_sub_signed(min_age))
+        .unwrap_or(DateTime::<Utc>::MIN_UTC);
+    find_orphans(state, cleanup, cutoff).await?;
+    if cleanup.remove {
+        remove_orphans(state, cleanup).await;
+    }
+    Ok(())
+}
+
+/// Start a cleanup unless one is already running
+pub async fn start_cleanup(
+    state: &AppState,
+    options: CleanupOptions,
+    trigger: CleanupTrigger,
+) -> Option<OrphanCleanup> {
+    let cleanup = {
+        let mut current = state.orphan_cleanup.write().await;
+        if current
+            .as_ref()
+            .is_some_and(|cleanup| cleanup.status == AuditStatus::Running)
+        {
+            return None;
+        }
+        let cleanup = OrphanCleanup {
+            id: Uuid::new_v4(),
+            status: AuditStatus::Running,
+            trigger,
+            started_at: Utc::now(),
+            completed_at: None,
+            prefix: options.prefix,
+            remove: options.remove,
+            min_age_hours: options.min_age_hours,
+            objects_scanned: 0,
+            assets_scanned: 0,
+            orphaned_bytes: 0,
+            removed_objects: 0,
+            removed_assets: 0,
+            error: None,
+            orphaned_objects: vec![],
+            orphaned_assets: vec![],
+        };
+        *current = Some(cleanup.clone());
+        cleanup
+    };
+
+    let state = state.clone();
+    let mut result = cleanup.clone();
+    tokio::spawn(async move {
+        let outcome = run_cleanup(&state, &mut result).await;
+        result.completed_at = Some(Utc::now());
+        match outcome {
+            Ok(()) => result.status = AuditStatus::Completed,
+            Err(e) => {
+                result.status = AuditStatus::Failed;
+                result.error = Some(e);
+            }
+        }
+        if trigger == CleanupTrigger::Scheduled {
+            println!(
+                "Orphan cleanup {}: {} objects and {} assets orphaned, {} and {} removed",
+                result.id,
+                result.orphaned_objects.len(),
+                result.orphaned_assets.len(),
+                result.removed_objects,
+                result.removed_assets
+            );
+        }
+
+        let mut current = state.orphan_cleanup.write().await;
+        if current
+            .as_ref()
+            .is_some_and(|cleanup| cleanup.id == result.id)
+        {
+            *current = Some(result);
+        }
+    });
+
+    Some(cleanup)
+}
+
+/// Run a cleanup every `orphan_cleanup_interval_hours`, if configured
+pub fn schedule_cleanups(state: &AppState) {
+    let Some(hours) = state.config.orphan_cleanup_interval_hours else {
+        return;
+    };
+    let state = state.clone();
+    tokio::spawn(async move {
+        let period = std::time::Duration::from_secs(hours.saturating_mul(3600));
+        let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+        loop {
+            interval.tick().await;
+            let options = CleanupOptions::new(&state.config, state.config.orphan_cleanup_remove);
+            if start_cleanup(&state, options, CleanupTrigger::Scheduled)
+                .await
+                .is_none()
+            {
+                println!("Skipping scheduled orphan cleanup, one is already running");
+            }
+        }
+    });
+}
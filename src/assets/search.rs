@@ -0,0 +1,239 @@
+//! Asset search with typed filters, and storage statistics
+
+use super::models::{self as s3_assets, Asset};
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    Condition, DatabaseConnection, EntityTrait, PaginatorTrait, QueryOrder, QuerySelect,
+    entity::prelude::*,
+    sea_query::{Expr, Func, SimpleExpr},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+const DEFAULT_LIMIT: u64 = 100;
+const MAX_LIMIT: u64 = 1000;
+
+/// Type, role, size, deletion flag and upload time of an asset
+type StatsRow = (String, Option<String>, Option<i64>, bool, DateTime<Utc>);
+
+#[derive(Clone, Debug, Default, Deserialize, IntoParams)]
+pub struct AssetSearchQuery {
+    pub experiment_id: Option<Uuid>,
+    /// Exact role, such as `camera_image` or `experiment_data`
+    pub role: Option<String>,
+    /// Exact type, such as `image` or `tabular`
+    #[serde(rename = "type")]
+    #[param(rename = "type")]
+    pub asset_type: Option<String>,
+    /// Exact processing status; `none` matches assets never processed
+    pub processing_status: Option<String>,
+    /// Uploaded at or after this time
+    pub uploaded_after: Option<DateTime<Utc>>,
+    /// Uploaded before this time
+    pub uploaded_before: Option<DateTime<Utc>>,
+    /// Case-insensitive filename pattern where `*` matches any run of
+    /// characters and `?` a single one, such as `INP_*_2025-03-20_*.jpg`
+    pub filename: Option<String>,
+    /// Also return soft-deleted assets
+    #[serde(default)]
+    pub include_deleted: bool,
+    /// Most assets to return, up to 1000 (default 100)
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct AssetSearchResult {
+    /// Matching assets before `limit` and `offset`
+    pub total: u64,
+    /// Newest uploads first
+    pub assets: Vec<Asset>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, ToSchema)]
+pub struct AssetGroupStats {
+    pub count: u64,
+    pub total_bytes: i64,
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct AssetStats {
+    pub experiment_id: Option<Uuid>,
+    /// Assets that are not soft-deleted
+    pub count: u64,
+    pub total_bytes: i64,
+    /// Soft-deleted assets waiting to be purged
+    pub deleted_count: u64,
+    pub deleted_bytes: i64,
+    pub by_type: BTreeMap<String, AssetGroupStats>,
+    /// Assets without a role are counted under `none`
+    pub by_role: BTreeMap<String, AssetGroupStats>,
+    pub first_uploaded_at: Option<DateTime<Utc>>,
+    pub last_uploaded_at: Option<DateTime<Utc>>,
+}
+
+/// SQL `LIKE` pattern for a filename glob, with `\` as the escape character
+fn glob_to_like(glob: &str) -> String {
+    let mut pattern = String::with_capacity(glob.len());
+    for c in glob.chars() {
+        match c {
+            '*' => pattern.push('%'),
+            '?' => pattern.push('_'),
+            '%' | '_' | '\\' => {
+                pattern.push('\\');
+                pattern.push(c);
+            }
+            _ => pattern.extend(c.to_uppercase()),
+        }
+    }
+    pattern
+}
+
+fn search_condition(query: &AssetSearchQuery) -> Condition {
+    let mut condition = Condition::all();
+    if let Some(experiment_id) = query.experiment_id {
+        condition = condition.add(s3_assets::Column::ExperimentId.eq(experiment_id));
+    }
+    if let Some(role) = &query.role {
+        condition = condition.add(s3_assets::Column::Role.eq(role.as_str()));
+    }
+    if let Some(asset_type) = &query.asset_type {
+        condition = condition.add(s3_assets::Column::Type.eq(asset_type.as_str()));
+    }
+    match query.processing_status.as_deref() {
+        Some("none") => condition = condition.add(s3_assets::Column::ProcessingStatus.is_null()),
+        Some(status) => condition = condition.add(s3_assets::Column::ProcessingStatus.eq(status)),
+        None => {}
+    }
+    if let Some(after) = query.uploaded_after {
+        condition = condition.add(s3_assets::Column::UploadedAt.gte(after));
+    }
+    if let Some(before) = query.uploaded_before {
+        condition = condition.add(s3_assets::Column::UploadedAt.lt(before));
+    }
+    if let Some(filename) = &query.filename {
+        condition = condition.add(
+            SimpleExpr::FunctionCall(Func::upper(Expr::col(s3_assets::Column::OriginalFilename)))
+                .like(sea_orm::sea_query::LikeExpr::new(glob_to_like(filename)).escape('\\')),
+        );
+    }
+    if !query.include_deleted {
+        condition = condition.add(s3_assets::Column::IsDeleted.eq(false));
+    }
+    condition
+}
+
+/// Assets matching every given filter. Invalid filters are returned as
+/// `DbErr::Custom`.
+pub async fn search_assets(
+    db: &DatabaseConnection,
+    query: &AssetSearchQuery,
+) -> Result<AssetSearchResult, DbErr> {
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return Err(DbErr::Custom(format!(
+            "limit must be between 1 and {MAX_LIMIT}"
+        )));
+    }
+    if let (Some(after), Some(before)) = (query.uploaded_after, query.uploaded_before)
+        && after >= before
+    {
+        return Err(DbErr::Custom(
+            "uploaded_after must be before uploaded_before".to_string(),
+        ));
+    }
+
+    let select = s3_assets::Entity::find().filter(search_condition(query));
+    let total = select.clone().count(db).await?;
+    let assets = select
+        .order_by_desc(s3_assets::Column::UploadedAt)
+        .order_by_asc(s3_assets::Column::OriginalFilename)
+        .offset(query.offset.unwrap_or(0))
+        .limit(limit)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(Asset::from)
+        .collect();
+    Ok(AssetSearchResult { total, assets })
+}
+
+/// Asset counts and sizes, for one experiment or for all assets. A missing
+/// experiment is returned as `DbErr::RecordNotFound`.
+pub async fn asset_stats(
+    db: &DatabaseConnection,
+    experiment_id: Option<Uuid>,
+) -> Result<AssetStats, DbErr> {
+    let mut select = s3_assets::Entity::find()
+        .select_only()
+        .column(s3_assets::Column::Type)
+        .column(s3_assets::Column::Role)
+        .column(s3_assets::Column::SizeBytes)
+        .column(s3_assets::Column::IsDeleted)
+        .column(s3_assets::Column::UploadedAt);
+    if let Some(experiment_id) = experiment_id {
+        crate::experiments::models::Entity::find_by_id(experiment_id)
+            .one(db)
+            .await?
+            .ok_or_else(|| DbErr::RecordNotFound("Experiment not found".to_string()))?;
+        select = select.filter(s3_assets::Column::ExperimentId.eq(experiment_id));
+    }
+    let rows: Vec<StatsRow> = select.into_tuple().all(db).await?;
+
+    let mut stats = AssetStats {
+        experiment_id,
+        count: 0,
+        total_bytes: 0,
+        deleted_count: 0,
+        deleted_bytes: 0,
+        by_type: BTreeMap::new(),
+        by_role: BTreeMap::new(),
+        first_uploaded_at: None,
+        last_uploaded_at: None,
+    };
+    for (asset_type, role, size_bytes, is_deleted, uploaded_at) in rows {
+        let size = size_bytes.unwrap_or(0);
+        if is_deleted {
+            stats.deleted_count += 1;
+            stats.deleted_bytes += size;
+            continue;
+        }
+        stats.count += 1;
+        stats.total_bytes += size;
+        for group in [
+            stats.by_type.entry(asset_type).or_default(),
+            stats
+                .by_role
+                .entry(role.unwrap_or_else(|| "none".to_string()))
+                .or_default(),
+        ] {
+            group.count += 1;
+            group.total_bytes += size;
+        }
+        stats.first_uploaded_at = Some(
+            stats
+                .first_uploaded_at
+                .map_or(uploaded_at, |first| first.min(uploaded_at)),
+        );
+        stats.last_uploaded_at = Some(
+            stats
+                .last_uploaded_at
+                .map_or(uploaded_at, |last| last.max(uploaded_at)),
+        );
+    }
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_to_like() {
+        assert_eq!(glob_to_like("INP_*.jpg"), "INP\\_%.JPG");
+        assert_eq!(glob_to_like("run?.xlsx"), "RUN_.XLSX");
+        assert_eq!(glob_to_like("100%"), "100\\%");
+    }
+}
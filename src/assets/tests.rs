@@ -1,4 +1,5 @@
-use crate::config::test_helpers::setup_test_app;
+use crate::common::keycloak::test_realm;
+use crate::config::test_helpers::{send_json_as, setup_authenticated_test_app, setup_test_app};
 use axum::body::{Body, to_bytes};
 use axum::http::{Request, StatusCode};
 use serde_json::{Value, json};
@@ -462,7 +463,7 @@ async fn test_asset_download_and_view_endpoints() {
         .oneshot(
             Request::builder()
                 .method("GET")
-                .uri(format!("/api/assets/{fake_id}/download",))
+                .uri(format!("/api/assets/{fake_id}/download"))
                 .body(Body::empty())
                 .unwrap(),
         )
@@ -477,3 +478,1013 @@ async fn test_asset_download_and_view_endpoints() {
         "Non-existent asset should return 404 or 500, got: {not_found_status}"
     );
 }
+
+async fn create_asset_record(
+    app: &axum::Router,
+    filename: &str,
+    s3_key: &str,
+    r#type: &str,
+) -> String {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/assets")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({
+                        "original_filename": filename,
+                        "s3_key": s3_key,
+                        "type": r#type,
+                        "is_deleted": false
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (status, body) = extract_response_body(response).await;
+    assert_eq!(
+        status,
+        StatusCode::CREATED,
+        "Failed to create asset: {body:?}"
+    );
+    body["id"].as_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn test_asset_thumbnail() {
+    let app = setup_test_app().await;
+
+    // A 600x300 PNG should come back as a 256x128 JPEG
+    let mut png = Vec::new();
+    image::RgbImage::from_fn(600, 300, |x, y| {
+        image::Rgb([
+            u8::try_from(x % 256).unwrap(),
+            u8::try_from(y % 256).unwrap(),
+            128,
+        ])
+    })
+    .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+    .unwrap();
+    let s3_key = format!("test/thumbnails/{}/camera.png", uuid::Uuid::new_v4());
+    crate::external::s3::MOCK_S3_STORE
+        .put_object(&s3_key, png)
+        .unwrap();
+    let asset_id = create_asset_record(&app, "camera.png", &s3_key, "image").await;
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/assets/{asset_id}/thumbnail"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "image/jpeg");
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let thumbnail = image::load_from_memory_with_format(&bytes, image::ImageFormat::Jpeg).unwrap();
+    assert_eq!((thumbnail.width(), thumbnail.height()), (256, 128));
+
+    // The generated thumbnail is stored next to the original
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/assets/{asset_id}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (_, asset) = extract_response_body(response).await;
+    assert_eq!(asset["thumbnail_s3_key"], format!("{s3_key}.thumb.jpg"));
+    assert!(
+        crate::external::s3::MOCK_S3_STORE
+            .get_object(&format!("{s3_key}.thumb.jpg"))
+            .is_ok()
+    );
+
+    // Only images have thumbnails
+    let text_asset_id =
+        create_asset_record(&app, "notes.txt", "test/thumbnails/notes.txt", "unknown").await;
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/assets/{text_asset_id}/thumbnail"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_asset_cache_headers_and_presigned_urls() {
+    let app = setup_test_app().await;
+
+    let s3_key = format!("test/serving/{}/INP_1.jpg", uuid::Uuid::new_v4());
+    crate::external::s3::MOCK_S3_STORE
+        .put_object(&s3_key, b"camera image".to_vec())
+        .unwrap();
+    let asset_id = create_asset_record(&app, "INP_1.jpg", &s3_key, "image").await;
+
+    let get = |uri: String, if_none_match: Option<&str>| {
+        let mut request = Request::builder().uri(uri);
+        if let Some(etag) = if_none_match {
+            request = request.header("if-none-match", etag);
+        }
+        app.clone().oneshot(request.body(Body::empty()).unwrap())
+    };
+
+    // Proxied content can be cached and revalidated with its ETag
+    let response = get(format!("/api/assets/{asset_id}/view"), None)
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "image/jpeg");
+    assert!(
+        response.headers()["cache-control"]
+            .to_str()
+            .unwrap()
+            .contains("max-age=")
+    );
+    let etag = response.headers()["etag"].to_str().unwrap().to_string();
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    assert_eq!(&bytes[..], b"camera image");
+
+    let response = get(format!("/api/assets/{asset_id}/view"), Some(&etag))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(response.headers()["etag"], etag.as_str());
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    assert!(bytes.is_empty());
+
+    let response = get(
+        format!("/api/assets/{asset_id}/download"),
+        Some("\"other\", W/\"stale\""),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Redirect straight to storage instead of proxying the bytes
+    let response = get(format!("/api/assets/{asset_id}/view?redirect=true"), None)
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+    assert!(
+        response.headers()["location"]
+            .to_str()
+            .unwrap()
+            .contains(&s3_key)
+    );
+
+    let response = get(format!("/api/assets/{asset_id}/url?attachment=true"), None)
+        .await
+        .unwrap();
+    let (status, body) = extract_response_body(response).await;
+    assert_eq!(status, StatusCode::OK, "Failed to presign: {body:?}");
+    assert!(body["url"].as_str().unwrap().contains(&s3_key));
+    assert!(body["expires_at"].is_string());
+
+    let response = get(format!("/api/assets/{}/url", uuid::Uuid::new_v4()), None)
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_integrity_audit_job() {
+    let app = setup_test_app().await;
+
+    let s3_key = format!("test/integrity/{}/unchecked.txt", uuid::Uuid::new_v4());
+    crate::external::s3::MOCK_S3_STORE
+        .put_object(&s3_key, b"unchecked".to_vec())
+        .unwrap();
+    create_asset_record(&app, "unchecked.txt", &s3_key, "unknown").await;
+
+    let request = |method: &str| {
+        Request::builder()
+            .method(method)
+            .uri("/api/assets/integrity-audit")
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    let response = app.clone().oneshot(request("GET")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = app.clone().oneshot(request("POST")).await.unwrap();
+    let (status, audit) = extract_response_body(response).await;
+    assert_eq!(
+        status,
+        StatusCode::ACCEPTED,
+        "Failed to start audit: {audit:?}"
+    );
+    assert_eq!(audit["status"], "running");
+
+    let mut audit = audit;
+    for _ in 0..100 {
+        let response = app.clone().oneshot(request("GET")).await.unwrap();
+        audit = extract_response_body(response).await.1;
+        if audit["status"] != "running" {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert_eq!(
+        audit["status"], "completed",
+        "Audit did not complete: {audit:?}"
+    );
+    let unattached = audit["experiments"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|report| report["experiment_id"].is_null())
+        .expect("Report for assets without experiment");
+    assert_eq!(unattached["unverified"], 1);
+    assert_eq!(unattached["problems"][0]["s3_key"], s3_key);
+}
+
+#[tokio::test]
+async fn test_asset_restore_and_purge() {
+    let app = setup_test_app().await;
+
+    let send = |method: &str, uri: String, body: Option<Value>| {
+        let request = Request::builder().method(method).uri(uri);
+        let request = match body {
+            Some(body) => request
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string())),
+            None => request.body(Body::empty()),
+        }
+        .unwrap();
+        let app = app.clone();
+        async move { extract_response_body(app.oneshot(request).await.unwrap()).await }
+    };
+
+    let prefix = format!("test/purge/{}", uuid::Uuid::new_v4());
+    let mut asset_ids = vec![];
+    for name in ["first.txt", "second.txt", "kept.txt"] {
+        let s3_key = format!("{prefix}/{name}");
+        crate::external::s3::MOCK_S3_STORE
+            .put_object(&s3_key, name.as_bytes().to_vec())
+            .unwrap();
+        asset_ids.push(create_asset_record(&app, name, &s3_key, "unknown").await);
+    }
+    let [first, second, kept] = asset_ids.as_slice() else {
+        unreachable!()
+    };
+
+    // Live assets can be neither restored nor purged
+    let (status, _) = send("POST", format!("/api/assets/{first}/restore"), None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (status, _) = send("POST", format!("/api/assets/{first}/purge"), None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    for id in [first, second] {
+        let (status, _) = send(
+            "PUT",
+            format!("/api/assets/{id}"),
+            Some(json!({"is_deleted": true})),
+        )
+        .await;
+        assert!(status.is_success(), "Failed to soft-delete asset: {status}");
+    }
+
+    let (status, restored) = send("POST", format!("/api/assets/{first}/restore"), None).await;
+    assert_eq!(status, StatusCode::OK, "Failed to restore: {restored:?}");
+    assert_eq!(restored["is_deleted"], false);
+
+    // Only the remaining soft-deleted asset is purged, along with its object
+    let (status, result) = send("POST", "/api/assets/purge".to_string(), None).await;
+    assert_eq!(status, StatusCode::OK, "Failed to purge: {result:?}");
+    assert_eq!(result["purged"], json!([second]));
+    assert!(
+        crate::external::s3::MOCK_S3_STORE
+            .get_object(&format!("{prefix}/second.txt"))
+            .is_err()
+    );
+    let (status, _) = send("GET", format!("/api/assets/{second}"), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    for id in [first, kept] {
+        let (status, _) = send("GET", format!("/api/assets/{id}"), None).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    let (status, _) = send(
+        "PUT",
+        format!("/api/assets/{kept}"),
+        Some(json!({"is_deleted": true})),
+    )
+    .await;
+    assert!(status.is_success());
+    let (status, result) = send("POST", format!("/api/assets/{kept}/purge"), None).await;
+    assert_eq!(status, StatusCode::OK, "Failed to purge: {result:?}");
+    assert_eq!(result["purged"], json!([kept]));
+
+    let missing = uuid::Uuid::new_v4();
+    let (status, _) = send("POST", format!("/api/assets/{missing}/restore"), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = send("POST", format!("/api/assets/{missing}/purge"), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_purge_needs_administrator() {
+    let (app, _db) = setup_authenticated_test_app().await;
+    let admin = test_realm::token("ada", &["spice-admin"], &[]);
+    let editor = test_realm::token("eddie", &["spice-editor"], &[]);
+
+    let (_, project) = send_json_as(
+        &app,
+        Some(&admin),
+        "POST",
+        "/api/projects",
+        Some(&json!({"name": "Purged project"})),
+    )
+    .await;
+    let project_id = project["id"].as_str().unwrap();
+    let (status, _) = send_json_as(
+        &app,
+        Some(&admin),
+        "PUT",
+        &format!("/api/projects/{project_id}/members"),
+        Some(&json!(["eddie"])),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, experiment) = send_json_as(
+        &app,
+        Some(&admin),
+        "POST",
+        "/api/experiments",
+        Some(&json!({
+            "name": "Purged run",
+            "is_calibration": false,
+            "project_id": project_id
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{experiment}");
+    let s3_key = format!("test/purge-access/{}/run.txt", uuid::Uuid::new_v4());
+    crate::external::s3::MOCK_S3_STORE
+        .put_object(&s3_key, b"run".to_vec())
+        .unwrap();
+    let (status, asset) = send_json_as(
+        &app,
+        Some(&admin),
+        "POST",
+        "/api/assets",
+        Some(&json!({
+            "original_filename": "run.txt",
+            "s3_key": s3_key,
+            "type": "unknown",
+            "experiment_id": experiment["id"],
+            "is_deleted": false
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{asset}");
+    let asset_id = asset["id"].as_str().unwrap();
+
+    // Editors bin the assets of their projects, but cannot purge them
+    let (status, _) = send_json_as(
+        &app,
+        Some(&editor),
+        "PATCH",
+        &format!("/api/assets/{asset_id}"),
+        Some(&json!({"is_deleted": true})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    for uri in [
+        format!("/api/assets/{asset_id}/purge"),
+        "/api/assets/purge".to_string(),
+    ] {
+        let (status, _) = send_json_as(&app, Some(&editor), "POST", &uri, None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN, "{uri}");
+    }
+
+    let (status, result) = send_json_as(
+        &app,
+        Some(&admin),
+        "POST",
+        &format!("/api/assets/{asset_id}/purge"),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{result}");
+    assert_eq!(result["purged"], json!([asset_id]));
+}
+
+#[tokio::test]
+async fn test_resumable_token_download() {
+    let app = setup_test_app().await;
+
+    let prefix = format!("test/ranged/{}", uuid::Uuid::new_v4());
+    let mut asset_ids = vec![];
+    for (name, size) in [("first.bin", 3000), ("second.bin", 5000)] {
+        let s3_key = format!("{prefix}/{name}");
+        crate::external::s3::MOCK_S3_STORE
+            .put_object(&s3_key, vec![b'x'; size])
+            .unwrap();
+        asset_ids.push(create_asset_record(&app, name, &s3_key, "unknown").await);
+    }
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/assets/bulk-download-token")
+                .header("content-type", "application/json")
+                .body(Body::from(json!({"asset_ids": asset_ids}).to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (status, token) = extract_response_body(response).await;
+    assert_eq!(status, StatusCode::OK, "Failed to create token: {token:?}");
+    let download_url = token["download_url"].as_str().unwrap().to_string();
+
+    let download = |headers: Vec<(&'static str, String)>| {
+        let mut request = Request::builder().uri(download_url.clone());
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+        let app = app.clone();
+        async move {
+            let response = app
+                .oneshot(request.body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            let status = response.status();
+            let headers = response.headers().clone();
+            let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            (status, headers, bytes.to_vec())
+        }
+    };
+
+    let (status, headers, full) = download(vec![]).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers["accept-ranges"], "bytes");
+    assert_eq!(headers["content-length"], full.len().to_string().as_str());
+    let etag = headers["etag"].to_str().unwrap().to_string();
+    let zip = zip::ZipArchive::new(std::io::Cursor::new(full.clone())).unwrap();
+    assert_eq!(zip.len(), 2);
+
+    // The token can be reused to resume from where a download stopped
+    let (status, headers, rest) = download(vec![("range", "bytes=4000-".to_string())]).await;
+    assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+    assert_eq!(
+        headers["content-range"],
+        format!("bytes 4000-{}/{}", full.len() - 1, full.len()).as_str()
+    );
+    assert_eq!(rest, full[4000..]);
+
+    let (status, _, part) = download(vec![
+        ("range", "bytes=10-99".to_string()),
+        ("if-range", etag),
+    ])
+    .await;
+    assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+    assert_eq!(part, full[10..100]);
+
+    // A range for a different archive gets the whole file
+    let (status, _, whole) = download(vec![
+        ("range", "bytes=10-99".to_string()),
+        ("if-range", "\"stale\"".to_string()),
+    ])
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(whole, full);
+
+    let (status, headers, _) = download(vec![("range", format!("bytes={}-", full.len()))]).await;
+    assert_eq!(status, StatusCode::RANGE_NOT_SATISFIABLE);
+    assert_eq!(
+        headers["content-range"],
+        format!("bytes */{}", full.len()).as_str()
+    );
+}
+
+#[tokio::test]
+#[allow(clippy::too_many_lines)]
+async fn test_download_token_options_and_revocation() {
+    use crate::assets::download_tokens::services::{MAX_RESUMES, use_download_token};
+    use crate::config::{Config, test_helpers::setup_test_db};
+
+    let db = setup_test_db().await;
+    let mut config = Config::for_tests();
+    config.keycloak_url = String::new();
+    let app = crate::routes::build_router(&db, &config);
+    let send = |method: &str, uri: String, body: Option<Value>, range: Option<&str>| {
+        let mut request = Request::builder().method(method).uri(uri);
+        if let Some(range) = range {
+            request = request.header("range", range);
+        }
+        let body = match body {
+            Some(body) => {
+                request = request.header("content-type", "application/json");
+                Body::from(body.to_string())
+            }
+            None => Body::empty(),
+        };
+        app.clone().oneshot(request.body(body).unwrap())
+    };
+
+    let s3_key = format!("test/tokens/{}/data.bin", uuid::Uuid::new_v4());
+    crate::external::s3::MOCK_S3_STORE
+        .put_object(&s3_key, vec![b'y'; 2000])
+        .unwrap();
+    let asset_id = create_asset_record(&app, "data.bin", &s3_key, "unknown").await;
+
+    // A single-use token for one asset gives the asset itself, once
+    let response = send(
+        "POST",
+        format!("/api/assets/{asset_id}/download-token"),
+        Some(json!({"max_downloads": 1, "ttl_minutes": 60})),
+        None,
+    )
+    .await
+    .unwrap();
+    let (status, issued) = extract_response_body(response).await;
+    assert_eq!(status, StatusCode::OK, "{issued}");
+    assert_eq!(issued["scope"], "asset");
+    assert_eq!(issued["max_downloads"], 1);
+    assert!(issued.get("token_hash").is_none());
+    let url = issued["download_url"].as_str().unwrap().to_string();
+    let response = send("GET", url.clone(), None, None).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    assert_eq!(bytes.len(), 2000);
+    let response = send("GET", url.clone(), None, None).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let message = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    assert!(String::from_utf8_lossy(&message).contains("used up"));
+    // Resuming the download it started is still allowed, a bounded number
+    // of times until it expires, but starting it over is not
+    let response = send("GET", url.clone(), None, Some("bytes=1000-"))
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    let response = send("GET", url, None, Some("bytes=0-")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let secret = issued["token"].as_str().unwrap();
+    let expired = chrono::Utc::now() + chrono::Duration::minutes(61);
+    assert!(
+        use_download_token(&db, secret, true, expired)
+            .await
+            .is_err()
+    );
+    for _ in 1..MAX_RESUMES {
+        let resumed = use_download_token(&db, secret, true, chrono::Utc::now()).await;
+        assert_eq!(resumed.unwrap().downloads, 1);
+    }
+    let (_, message) = use_download_token(&db, secret, true, chrono::Utc::now())
+        .await
+        .unwrap_err();
+    assert!(message.contains("resumed too often"));
+
+    // The time to live is bounded by the configuration
+    for ttl_minutes in [0, 24 * 60 + 1] {
+        let response = send(
+            "POST",
+            "/api/assets/bulk-download-token".to_string(),
+            Some(json!({"asset_ids": [asset_id], "ttl_minutes": ttl_minutes})),
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    // Tokens are single-use by default, and expire unless started in time
+    let response = send(
+        "POST",
+        "/api/assets/bulk-download-token".to_string(),
+        Some(json!({"asset_ids": [asset_id]})),
+        None,
+    )
+    .await
+    .unwrap();
+    let (status, bulk) = extract_response_body(response).await;
+    assert_eq!(status, StatusCode::OK, "{bulk}");
+    assert_eq!(bulk["scope"], "assets");
+    assert_eq!(bulk["max_downloads"], 1);
+    let token = bulk["token"].as_str().unwrap();
+    let later = chrono::Utc::now() + chrono::Duration::minutes(6);
+    assert!(use_download_token(&db, token, false, later).await.is_err());
+
+    // Issued tokens are listed without their secrets, and can be revoked
+    let response = send("GET", "/api/assets/download-tokens".to_string(), None, None)
+        .await
+        .unwrap();
+    let (status, listed) = extract_response_body(response).await;
+    assert_eq!(status, StatusCode::OK);
+    let listed = listed.as_array().unwrap();
+    assert_eq!(listed.len(), 2);
+    assert_eq!(listed[0]["id"], bulk["id"]);
+    assert_eq!(listed[1]["downloads"], 1);
+    assert_eq!(listed[1]["resumes"], MAX_RESUMES);
+    assert!(listed[1]["last_used_at"].is_string());
+    assert!(
+        !listed
+            .iter()
+            .any(|token| token.to_string().contains(secret))
+    );
+
+    let response = send(
+        "DELETE",
+        format!(
+            "/api/assets/download-tokens/{}",
+            bulk["id"].as_str().unwrap()
+        ),
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+    let (status, revoked) = extract_response_body(response).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(revoked["revoked_at"].is_string());
+    let response = send(
+        "GET",
+        bulk["download_url"].as_str().unwrap().to_string(),
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+#[allow(clippy::too_many_lines)]
+async fn test_image_region_overlay() {
+    let app = setup_test_app().await;
+    let post = |uri: &str, body: Value| {
+        app.clone().oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+    };
+
+    // A 12x8 tray with A1 at (20, 20) and a 20 px pitch
+    let response = post(
+        "/api/tray_configurations",
+        json!({
+            "name": format!("Overlay Config {}", uuid::Uuid::new_v4()),
+            "experiment_default": false,
+            "trays": [{
+                "order_sequence": 1,
+                "rotation_degrees": 0,
+                "name": "P1",
+                "qty_cols": 12,
+                "qty_rows": 8,
+                "upper_left_corner_x": 20,
+                "upper_left_corner_y": 20,
+                "lower_right_corner_x": 240,
+                "lower_right_corner_y": 160
+            }]
+        }),
+    )
+    .await
+    .unwrap();
+    let (status, config) = extract_response_body(response).await;
+    assert_eq!(status, StatusCode::CREATED, "Failed to create: {config:?}");
+
+    let response = post(
+        "/api/experiments",
+        json!({
+            "name": format!("Overlay Experiment {}", uuid::Uuid::new_v4()),
+            "username": "test@example.com",
+            "performed_at": "2024-06-20T14:30:00Z",
+            "is_calibration": false,
+            "tray_configuration_id": config["id"],
+            "regions": [{
+                "name": "Left",
+                "display_colour_hex": "#FF0000",
+                "tray_id": 1,
+                "row_min": 0, "row_max": 7, "col_min": 0, "col_max": 3,
+                "is_background_key": false
+            }]
+        }),
+    )
+    .await
+    .unwrap();
+    let (status, experiment) = extract_response_body(response).await;
+    assert_eq!(
+        status,
+        StatusCode::CREATED,
+        "Failed to create: {experiment:?}"
+    );
+
+    let mut png = Vec::new();
+    image::RgbImage::from_pixel(320, 240, image::Rgb([0, 0, 0]))
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .unwrap();
+    let s3_key = format!("test/overlay/{}/INP_1.png", uuid::Uuid::new_v4());
+    crate::external::s3::MOCK_S3_STORE
+        .put_object(&s3_key, png)
+        .unwrap();
+    let response = post(
+        "/api/assets",
+        json!({
+            "original_filename": "INP_1.png",
+            "experiment_id": experiment["id"],
+            "s3_key": s3_key,
+            "type": "image",
+            "is_deleted": false
+        }),
+    )
+    .await
+    .unwrap();
+    let (status, asset) = extract_response_body(response).await;
+    assert_eq!(status, StatusCode::CREATED, "Failed to create: {asset:?}");
+    let asset_id = asset["id"].as_str().unwrap();
+
+    let get = |uri: String| {
+        app.clone()
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+    };
+    let response = get(format!("/api/assets/{asset_id}/overlay?labels=false"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "image/png");
+    assert_eq!(
+        response.headers()["content-disposition"],
+        "inline; filename=\"INP_1_overlay.png\""
+    );
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let overlay = image::load_from_memory(&bytes).unwrap().into_rgb8();
+    assert_eq!((overlay.width(), overlay.height()), (320, 240));
+
+    // Columns 1-4 are tinted red between the wells, column 6 is not
+    assert_eq!(overlay.get_pixel(50, 50), &image::Rgb([64, 0, 0]));
+    assert_eq!(overlay.get_pixel(130, 50), &image::Rgb([0, 0, 0]));
+    // The region's right edge runs half a pitch past column 4
+    assert_eq!(overlay.get_pixel(90, 100), &image::Rgb([255, 0, 0]));
+    // Wells are outlined, B6 at (120, 40) with the 4 px fallback radius
+    assert_eq!(overlay.get_pixel(124, 40), &image::Rgb([255, 255, 255]));
+
+    // Labels are drawn on the wells
+    let response = get(format!("/api/assets/{asset_id}/overlay"))
+        .await
+        .unwrap();
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let labelled = image::load_from_memory(&bytes).unwrap().into_rgb8();
+    assert_ne!(labelled, overlay);
+
+    // Images outside an experiment with a configuration cannot be annotated
+    let other_key = format!("test/overlay/{}/camera.png", uuid::Uuid::new_v4());
+    crate::external::s3::MOCK_S3_STORE
+        .put_object(&other_key, vec![0])
+        .unwrap();
+    let other_id = create_asset_record(&app, "camera.png", &other_key, "image").await;
+    let response = get(format!("/api/assets/{other_id}/overlay"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+#[allow(clippy::too_many_lines)]
+async fn test_asset_search_and_stats() {
+    let app = setup_test_app().await;
+
+    let send = |method: &str, uri: String, body: Option<Value>| {
+        let request = Request::builder().method(method).uri(uri);
+        let request = match body {
+            Some(body) => request
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string())),
+            None => request.body(Body::empty()),
+        }
+        .unwrap();
+        let app = app.clone();
+        async move { extract_response_body(app.oneshot(request).await.unwrap()).await }
+    };
+
+    let (status, experiment) = send(
+        "POST",
+        "/api/experiments".to_string(),
+        Some(json!({
+            "name": format!("Asset Search {}", uuid::Uuid::new_v4()),
+            "is_calibration": false
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{experiment:?}");
+    let experiment_id = experiment["id"].as_str().unwrap();
+
+    let prefix = format!("test/search/{}", uuid::Uuid::new_v4());
+    let mut ids = vec![];
+    for (filename, asset_type, role, size_bytes) in [
+        (
+            "INP_1_2025-03-20_15-14-17.jpg",
+            "image",
+            Some("camera_image"),
+            1000,
+        ),
+        (
+            "INP_2_2025-03-20_15-14-27.jpg",
+            "image",
+            Some("camera_image"),
+            1500,
+        ),
+        ("merged.xlsx", "tabular", Some("experiment_data"), 4000),
+        ("notes_100%.txt", "unknown", None, 10),
+    ] {
+        let (status, body) = send(
+            "POST",
+            "/api/assets".to_string(),
+            Some(json!({
+                "experiment_id": experiment_id,
+                "original_filename": filename,
+                "s3_key": format!("{prefix}/{filename}"),
+                "type": asset_type,
+                "role": role,
+                "size_bytes": size_bytes,
+                "is_deleted": false
+            })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED, "{body:?}");
+        ids.push(body["id"].as_str().unwrap().to_string());
+    }
+    let (status, _) = send(
+        "PUT",
+        format!("/api/assets/{}", ids[3]),
+        Some(json!({"is_deleted": true})),
+    )
+    .await;
+    assert!(status.is_success());
+
+    let search = |query: String| send("GET", format!("/api/assets/search?{query}"), None);
+    let filenames = |result: &Value| -> Vec<String> {
+        result["assets"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|asset| asset["original_filename"].as_str().unwrap().to_string())
+            .collect()
+    };
+    let scope = format!("experiment_id={experiment_id}");
+
+    let (status, result) = search(format!("{scope}&type=image&role=camera_image")).await;
+    assert_eq!(status, StatusCode::OK, "{result:?}");
+    assert_eq!(result["total"], 2);
+    let (_, result) = search(format!("{scope}&filename=inp_%3F_*-27.jpg")).await;
+    assert_eq!(filenames(&result), ["INP_2_2025-03-20_15-14-27.jpg"]);
+    // `_` and `%` in a pattern are literal
+    let (_, result) = search(format!("{scope}&filename=INP_*_2025*")).await;
+    assert_eq!(result["total"], 2);
+    let (_, result) = search(format!("{scope}&filename=*100%25*")).await;
+    assert_eq!(result["total"], 0, "Deleted assets are left out");
+    let (_, result) = search(format!("{scope}&filename=*100%25*&include_deleted=true")).await;
+    assert_eq!(filenames(&result), ["notes_100%.txt"]);
+    let (_, result) = search(format!("{scope}&processing_status=none&limit=1")).await;
+    assert_eq!(
+        (result["total"].clone(), filenames(&result).len()),
+        (json!(3), 1)
+    );
+
+    let now = chrono::Utc::now();
+    let hour = chrono::Duration::hours(1);
+    let (_, result) = search(format!(
+        "{scope}&uploaded_after={}&uploaded_before={}",
+        (now - hour).format("%Y-%m-%dT%H:%M:%SZ"),
+        (now + hour).format("%Y-%m-%dT%H:%M:%SZ")
+    ))
+    .await;
+    assert_eq!(result["total"], 3);
+    let (_, result) = search(format!(
+        "{scope}&uploaded_after={}",
+        (now + hour).format("%Y-%m-%dT%H:%M:%SZ")
+    ))
+    .await;
+    assert_eq!(result["total"], 0);
+
+    for query in [
+        "limit=0".to_string(),
+        "uploaded_after=2025-01-02T00:00:00Z&uploaded_before=2025-01-01T00:00:00Z".to_string(),
+        "uploaded_after=yesterday".to_string(),
+    ] {
+        assert_eq!(
+            search(query.clone()).await.0,
+            StatusCode::BAD_REQUEST,
+            "{query}"
+        );
+    }
+
+    let (status, summary) = send("GET", format!("/api/assets/stats?{scope}"), None).await;
+    assert_eq!(status, StatusCode::OK, "{summary:?}");
+    assert_eq!(summary["count"], 3);
+    assert_eq!(summary["total_bytes"], 6500);
+    assert_eq!(summary["deleted_count"], 1);
+    assert_eq!(summary["deleted_bytes"], 10);
+    assert_eq!(
+        summary["by_type"]["image"],
+        json!({"count": 2, "total_bytes": 2500})
+    );
+    assert_eq!(summary["by_type"]["tabular"]["total_bytes"], 4000);
+    assert_eq!(summary["by_role"]["experiment_data"]["count"], 1);
+    assert!(summary["last_uploaded_at"].is_string());
+
+    let (status, _) = send(
+        "GET",
+        format!("/api/assets/stats?experiment_id={}", uuid::Uuid::new_v4()),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, summary) = send("GET", "/api/assets/stats".to_string(), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(summary["count"].as_u64().unwrap() >= 3);
+}
+
+#[tokio::test]
+async fn test_orphan_cleanup() {
+    let app = setup_test_app().await;
+    let store = &crate::external::s3::MOCK_S3_STORE;
+
+    let send = |method: &str, uri: String| {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap();
+        let app = app.clone();
+        async move { extract_response_body(app.oneshot(request).await.unwrap()).await }
+    };
+    let run_cleanup = |query: String| async move {
+        let (status, cleanup) = send("POST", format!("/api/assets/orphans?{query}")).await;
+        assert_eq!(status, StatusCode::ACCEPTED, "{cleanup:?}");
+        let mut cleanup = cleanup;
+        for _ in 0..100 {
+            cleanup = send("GET", "/api/assets/orphans".to_string()).await.1;
+            if cleanup["status"] != "running" {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        assert_eq!(cleanup["status"], "completed", "{cleanup:?}");
+        cleanup
+    };
+
+    let (status, _) = send("GET", "/api/assets/orphans".to_string()).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Scan only this test's keys, as the mock store is shared between tests
+    let prefix = format!("spice-api-test/test/orphans/{}/", uuid::Uuid::new_v4());
+    let tracked = format!("{prefix}tracked.txt");
+    let stray = format!("{prefix}stray.txt");
+    let missing = format!("{prefix}missing.txt");
+    store.put_object(&tracked, b"tracked".to_vec()).unwrap();
+    store.put_object(&stray, b"stray".to_vec()).unwrap();
+    let tracked_id = create_asset_record(&app, "tracked.txt", &tracked, "unknown").await;
+    let missing_id = create_asset_record(&app, "missing.txt", &missing, "unknown").await;
+
+    let (status, _) = send("POST", "/api/assets/orphans?prefix=other/".to_string()).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Everything is younger than the default minimum age
+    let cleanup = run_cleanup(format!("prefix={prefix}")).await;
+    assert_eq!(cleanup["objects_scanned"], 2);
+    assert_eq!(cleanup["orphaned_objects"], json!([]));
+    assert_eq!(cleanup["orphaned_assets"], json!([]));
+
+    let cleanup = run_cleanup(format!("prefix={prefix}&min_age_hours=0")).await;
+    assert_eq!(cleanup["trigger"], "manual");
+    assert_eq!(cleanup["orphaned_objects"][0]["key"], stray);
+    assert_eq!(cleanup["orphaned_objects"][0]["removed"], false);
+    assert_eq!(cleanup["orphaned_bytes"], 5);
+    assert_eq!(cleanup["orphaned_assets"][0]["asset_id"], missing_id);
+    assert_eq!(cleanup["removed_objects"], 0);
+    assert!(store.get_object(&stray).is_ok());
+
+    let cleanup = run_cleanup(format!("prefix={prefix}&min_age_hours=0&remove=true")).await;
+    assert_eq!(cleanup["removed_objects"], 1);
+    assert_eq!(cleanup["removed_assets"], 1);
+    assert!(store.get_object(&stray).is_err());
+    assert!(store.get_object(&tracked).is_ok());
+    let (status, _) = send("GET", format!("/api/assets/{missing_id}")).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = send("GET", format!("/api/assets/{tracked_id}")).await;
+    assert_eq!(status, StatusCode::OK);
+}
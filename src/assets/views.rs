@@ -1,33 +1,82 @@
-use crate::common::auth::Role;
+use crate::api_keys::services::accept_api_keys;
+use crate::audit::services::{AuditedResource, audit_changes};
+use crate::common::auth::{RouteAccess, require_role};
+use crate::common::etag::check_entity_tags;
+use crate::common::keycloak::authenticate;
+use crate::common::labs::{TenantResource, require_lab};
+use crate::common::pagination::paginate_by_cursor;
+use crate::common::patch::patch_one_handler;
+use crate::common::redaction::redact_for_viewers;
 use crate::common::state::AppState;
+use crate::idempotency::services::ShowsSecret;
 
+use super::download_tokens::models::{
+    DownloadScope, DownloadToken, DownloadTokenOptions, IssuedDownloadToken,
+    Model as DownloadTokenModel,
+};
+use super::download_tokens::services::{
+    DownloadTokenQuery, issue_download_token, list_download_tokens, revoke_download_token,
+    use_download_token,
+};
+use super::integrity::IntegrityAudit;
+use super::orphans::{CleanupOptions, CleanupTrigger, OrphanCleanup, start_cleanup};
+use super::search::{AssetSearchQuery, AssetSearchResult, AssetStats};
 use crate::assets::models as s3_assets;
+use crate::projects::access::{ScopedResource, require_project_access};
+use crate::projects::archiving::reject_archived_changes;
+use crate::tray_configurations::well_grid::{WellGrid, tray_configuration_well_grid};
 use axum::{
-    extract::{Path, State},
+    Extension, Json,
+    extract::{Path, Query, State},
     http::{
         HeaderMap, StatusCode,
-        header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+        header::{
+            CACHE_CONTROL, CONTENT_DISPOSITION, CONTENT_TYPE, ETAG, IF_NONE_MATCH, LOCATION, RANGE,
+        },
     },
+    middleware,
     response::{IntoResponse, Response},
-    routing::{get, post},
+    routing::{delete, get, patch, post},
 };
-use axum_keycloak_auth::{PassthroughMode, layer::KeycloakAuthLayer};
+use axum_keycloak_auth::PassthroughMode;
 use crudcrate::CRUDResource;
-use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use sea_orm::{ColumnTrait, DbErr, EntityTrait, QueryFilter};
+use std::sync::Arc;
+use utoipa::OpenApi;
 use utoipa_axum::router::OpenApiRouter;
 use uuid::Uuid;
 // crud_handlers!(Asset, AssetUpdate, AssetCreate);
 pub use super::models::{Asset, Entity as AssetEntity, router as crudrouter};
 
+/// Seconds a presigned download URL stays valid
+const PRESIGNED_DOWNLOAD_EXPIRY_SECONDS: u64 = 60 * 60;
+const PRESIGNED_DOWNLOAD_EXPIRY: std::time::Duration =
+    std::time::Duration::from_secs(PRESIGNED_DOWNLOAD_EXPIRY_SECONDS);
+
+/// Re-uploading a file creates a new asset, so the content behind an asset ID
+/// never changes
+const ASSET_CACHE_CONTROL: &str = "private, max-age=604800, immutable";
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+pub struct ServeAssetQuery {
+    /// Redirect to a presigned storage URL instead of sending the content
+    /// through the API. Ignored when the storage backend has no presigned URLs.
+    #[serde(default)]
+    redirect: bool,
+}
+
 /// Download an asset as an attachment
 #[utoipa::path(
     get,
     path = "/{id}/download",
     params(
-        ("id" = Uuid, Path, description = "Asset ID to download")
+        ("id" = Uuid, Path, description = "Asset ID to download"),
+        ServeAssetQuery
     ),
     responses(
         (status = 200, description = "Asset downloaded successfully"),
+        (status = 304, description = "Asset matches the If-None-Match ETag"),
+        (status = 307, description = "Redirect to a presigned storage URL"),
         (status = 404, description = "Asset not found"),
         (status = 500, description = "Failed to retrieve asset from S3")
     ),
@@ -36,8 +85,10 @@ pub use super::models::{Asset, Entity as AssetEntity, router as crudrouter};
 async fn download_asset(
     Path(id): Path<Uuid>,
     State(state): State<AppState>,
+    Query(query): Query<ServeAssetQuery>,
+    headers: HeaderMap,
 ) -> Result<Response, StatusCode> {
-    serve_asset_internal(id, &state, true).await
+    serve_asset_internal(id, &state, true, query.redirect, &headers).await
 }
 
 /// View an asset inline (for images, etc.)
@@ -45,10 +96,13 @@ async fn download_asset(
     get,
     path = "/{id}/view",
     params(
-        ("id" = Uuid, Path, description = "Asset ID to view")
+        ("id" = Uuid, Path, description = "Asset ID to view"),
+        ServeAssetQuery
     ),
     responses(
         (status = 200, description = "Asset displayed inline"),
+        (status = 304, description = "Asset matches the If-None-Match ETag"),
+        (status = 307, description = "Redirect to a presigned storage URL"),
         (status = 404, description = "Asset not found"),
         (status = 500, description = "Failed to retrieve asset from S3")
     ),
@@ -57,10 +111,600 @@ async fn download_asset(
 async fn view_asset(
     Path(id): Path<Uuid>,
     State(state): State<AppState>,
+    Query(query): Query<ServeAssetQuery>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    serve_asset_internal(id, &state, false, query.redirect, &headers).await
+}
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+pub struct PresignedAssetUrlQuery {
+    /// Have the browser save the file instead of displaying it
+    #[serde(default)]
+    attachment: bool,
+}
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+pub struct PresignedAssetUrl {
+    pub url: String,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Get a presigned URL to fetch an asset directly from storage
+#[utoipa::path(
+    get,
+    path = "/{id}/url",
+    params(
+        ("id" = Uuid, Path, description = "Asset ID"),
+        PresignedAssetUrlQuery
+    ),
+    responses(
+        (status = 200, description = "Presigned download URL", body = PresignedAssetUrl),
+        (status = 404, description = "Asset not found"),
+        (status = 500, description = "Failed to presign the URL"),
+        (status = 501, description = "The storage backend does not support presigned URLs")
+    ),
+    tag = "assets"
+)]
+async fn get_presigned_asset_url(
+    Path(id): Path<Uuid>,
+    State(state): State<AppState>,
+    Query(query): Query<PresignedAssetUrlQuery>,
+) -> Result<Json<PresignedAssetUrl>, (StatusCode, String)> {
+    let asset = find_asset(&state, id).await?;
+    if !crate::external::s3::supports_presigned_urls(&state.config).await {
+        return Err((
+            StatusCode::NOT_IMPLEMENTED,
+            "The storage backend does not support presigned URLs".to_string(),
+        ));
+    }
+
+    let expires_at = chrono::Utc::now()
+        + chrono::Duration::from_std(PRESIGNED_DOWNLOAD_EXPIRY).unwrap_or_default();
+    let url = presigned_asset_url(&state, &asset, query.attachment)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    Ok(Json(PresignedAssetUrl { url, expires_at }))
+}
+
+/// Presigned URL for an asset, answered by storage with the same headers as
+/// the proxied endpoints
+async fn presigned_asset_url(
+    state: &AppState,
+    asset: &s3_assets::Model,
+    as_attachment: bool,
+) -> Result<String, String> {
+    let headers = crate::external::storage::ResponseHeaders {
+        content_type: asset_content_type(asset).to_string(),
+        content_disposition: content_disposition(asset, as_attachment),
+        // Anyone holding the URL may fetch it, so shared caches may keep it
+        // for as long as it is valid
+        cache_control: format!("public, max-age={}", PRESIGNED_DOWNLOAD_EXPIRY.as_secs()),
+    };
+    crate::external::s3::presigned_get_url(
+        asset.storage_key(),
+        PRESIGNED_DOWNLOAD_EXPIRY,
+        &headers,
+        &state.config,
+    )
+    .await
+}
+
+/// Strong `ETag` of an asset's content: its checksum, or its ID for assets
+/// stored before checksums were recorded
+fn asset_etag(asset: &s3_assets::Model) -> String {
+    match &asset.checksum_sha256 {
+        Some(checksum) => format!("\"{checksum}\""),
+        None => format!("\"{}\"", asset.id),
+    }
+}
+
+/// Whether the request's `If-None-Match` lists `etag`
+fn matches_if_none_match(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get(IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| {
+            value
+                .split(',')
+                .map(str::trim)
+                .any(|candidate| candidate == "*" || candidate.trim_start_matches("W/") == etag)
+        })
+}
+
+fn not_modified(etag: &str, cache_control: &str) -> Result<Response, StatusCode> {
+    Response::builder()
+        .status(StatusCode::NOT_MODIFIED)
+        .header(ETAG, etag)
+        .header(CACHE_CONTROL, cache_control)
+        .body(axum::body::Body::empty())
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// Serve the JPEG thumbnail of an image asset
+#[utoipa::path(
+    get,
+    path = "/{id}/thumbnail",
+    params(
+        ("id" = Uuid, Path, description = "Image asset ID")
+    ),
+    responses(
+        (status = 200, description = "256px JPEG thumbnail", content_type = "image/jpeg"),
+        (status = 304, description = "Thumbnail matches the If-None-Match ETag"),
+        (status = 404, description = "Asset not found or not an image"),
+        (status = 500, description = "Failed to retrieve or generate the thumbnail")
+    ),
+    tag = "assets"
+)]
+async fn get_thumbnail(
+    Path(id): Path<Uuid>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
 ) -> Result<Response, StatusCode> {
-    serve_asset_internal(id, &state, false).await
+    let asset = AssetEntity::find_by_id(id)
+        .one(&state.db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .filter(|asset| asset.r#type == "image")
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    // Thumbnails are rendered from the content, so they share its ETag
+    let etag = asset_etag(&asset);
+    if matches_if_none_match(&headers, &etag) {
+        return not_modified(&etag, ASSET_CACHE_CONTROL);
+    }
+
+    // Fall back to generating it now if the background job has not run yet
+    let cached = match &asset.thumbnail_s3_key {
+        Some(key) => crate::external::s3::get_object_from_s3(key, &state.config)
+            .await
+            .ok(),
+        None => None,
+    };
+    let thumbnail = match cached {
+        Some(thumbnail) => thumbnail,
+        None => {
+            crate::services::thumbnail_service::generate_thumbnail(&state.db, &state.config, &asset)
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        }
+    };
+
+    Ok((
+        [
+            (CONTENT_TYPE, "image/jpeg"),
+            (CACHE_CONTROL, ASSET_CACHE_CONTROL),
+            (ETAG, etag.as_str()),
+        ],
+        thumbnail,
+    )
+        .into_response())
 }
 
+/// Well positions on a camera image, from the tray configuration of its experiment
+#[utoipa::path(
+    get,
+    path = "/{id}/well-grid",
+    params(
+        ("id" = Uuid, Path, description = "Image asset ID")
+    ),
+    responses(
+        (status = 200, description = "Well centroids in image pixels", body = WellGrid),
+        (status = 404, description = "Asset not found, not an image, or its experiment has no tray configuration"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "assets"
+)]
+async fn get_image_well_grid(
+    Path(id): Path<Uuid>,
+    State(state): State<AppState>,
+) -> Result<Json<WellGrid>, (StatusCode, String)> {
+    let asset = find_asset(&state, id).await?;
+    if asset.r#type != "image" {
+        return Err((StatusCode::NOT_FOUND, "Asset is not an image".to_string()));
+    }
+
+    let no_configuration = || {
+        (
+            StatusCode::NOT_FOUND,
+            "The image's experiment has no tray configuration".to_string(),
+        )
+    };
+    let experiment_id = asset.experiment_id.ok_or_else(no_configuration)?;
+    let tray_configuration_id = crate::experiments::models::Entity::find_by_id(experiment_id)
+        .one(&state.db)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .and_then(|experiment| experiment.tray_configuration_id)
+        .ok_or_else(no_configuration)?;
+
+    tray_configuration_well_grid(&state.db, tray_configuration_id)
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+pub struct OverlayQuery {
+    /// Write each well's coordinate on it (default true)
+    labels: Option<bool>,
+}
+
+/// Render a camera image with the experiment's regions and wells drawn on it
+#[utoipa::path(
+    get,
+    path = "/{id}/overlay",
+    params(
+        ("id" = Uuid, Path, description = "Image asset ID"),
+        OverlayQuery
+    ),
+    responses(
+        (status = 200, description = "Annotated PNG image", content_type = "image/png"),
+        (status = 400, description = "No tray of the configuration can be placed on the image"),
+        (status = 404, description = "Asset not found, not an image, or its experiment has no tray configuration"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "assets"
+)]
+async fn get_image_overlay(
+    Path(id): Path<Uuid>,
+    State(state): State<AppState>,
+    Query(query): Query<OverlayQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    let asset = find_asset(&state, id).await?;
+    let png =
+        crate::experiments::overlay::render_overlay(&state, &asset, query.labels.unwrap_or(true))
+            .await
+            .map_err(|e| match e {
+                sea_orm::DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+                sea_orm::DbErr::Custom(message) => (StatusCode::BAD_REQUEST, message),
+                _ => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Failed to render overlay: {e}"),
+                ),
+            })?;
+
+    let stem = std::path::Path::new(&asset.original_filename)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("image");
+    Ok((
+        [
+            (CONTENT_TYPE, "image/png".to_string()),
+            (
+                CONTENT_DISPOSITION,
+                format!("inline; filename=\"{stem}_overlay.png\""),
+            ),
+        ],
+        png,
+    )
+        .into_response())
+}
+
+/// Search assets by role, type, processing status, upload time and filename
+#[utoipa::path(
+    get,
+    path = "/search",
+    params(AssetSearchQuery),
+    responses(
+        (status = 200, description = "Matching assets, newest uploads first", body = AssetSearchResult),
+        (status = 400, description = "Invalid limit or upload time range"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "assets"
+)]
+async fn search_assets(
+    State(state): State<AppState>,
+    Query(query): Query<AssetSearchQuery>,
+) -> Result<Json<AssetSearchResult>, (StatusCode, String)> {
+    super::search::search_assets(&state.db, &query)
+        .await
+        .map(Json)
+        .map_err(|e| match e {
+            sea_orm::DbErr::Custom(message) => (StatusCode::BAD_REQUEST, message),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to search assets: {e}"),
+            ),
+        })
+}
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+pub struct AssetStatsQuery {
+    /// Limit the statistics to one experiment
+    experiment_id: Option<Uuid>,
+}
+
+/// Asset counts and total size by type and role
+#[utoipa::path(
+    get,
+    path = "/stats",
+    params(AssetStatsQuery),
+    responses(
+        (status = 200, description = "Asset statistics", body = AssetStats),
+        (status = 404, description = "Experiment not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "assets"
+)]
+async fn get_asset_stats(
+    State(state): State<AppState>,
+    Query(query): Query<AssetStatsQuery>,
+) -> Result<Json<AssetStats>, (StatusCode, String)> {
+    super::search::asset_stats(&state.db, query.experiment_id)
+        .await
+        .map(Json)
+        .map_err(|e| match e {
+            sea_orm::DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to compute asset statistics: {e}"),
+            ),
+        })
+}
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+pub struct IntegrityAuditQuery {
+    /// Store the checksum of assets that have none recorded
+    #[serde(default)]
+    record_missing: bool,
+}
+
+/// Start an integrity audit of all assets
+#[utoipa::path(
+    post,
+    path = "/integrity-audit",
+    params(IntegrityAuditQuery),
+    responses(
+        (status = 202, description = "Audit started", body = IntegrityAudit),
+        (status = 409, description = "An audit is already running")
+    ),
+    tag = "assets"
+)]
+async fn start_integrity_audit(
+    State(state): State<AppState>,
+    Query(query): Query<IntegrityAuditQuery>,
+) -> Result<(StatusCode, Json<IntegrityAudit>), (StatusCode, String)> {
+    super::integrity::start_audit(&state, query.record_missing)
+        .await
+        .map(|audit| (StatusCode::ACCEPTED, Json(audit)))
+        .ok_or((
+            StatusCode::CONFLICT,
+            "An integrity audit is already running".to_string(),
+        ))
+}
+
+/// Get the most recent integrity audit, with a report per experiment
+#[utoipa::path(
+    get,
+    path = "/integrity-audit",
+    responses(
+        (status = 200, description = "Latest audit", body = IntegrityAudit),
+        (status = 404, description = "No audit has been run")
+    ),
+    tag = "assets"
+)]
+async fn get_integrity_audit(
+    State(state): State<AppState>,
+) -> Result<Json<IntegrityAudit>, (StatusCode, String)> {
+    state.integrity_audit.read().await.clone().map(Json).ok_or((
+        StatusCode::NOT_FOUND,
+        "No integrity audit has been run".to_string(),
+    ))
+}
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+pub struct OrphanCleanupQuery {
+    /// Delete the orphaned objects and asset rows instead of only listing them
+    #[serde(default)]
+    remove: bool,
+    /// Leave anything newer than this alone, as uploads may still be in
+    /// progress (default 24)
+    min_age_hours: Option<u64>,
+    /// Only scan keys under this prefix, which must lie within the deployment
+    prefix: Option<String>,
+}
+
+/// Start a cleanup of objects without an asset and assets without an object
+#[utoipa::path(
+    post,
+    path = "/orphans",
+    params(OrphanCleanupQuery),
+    responses(
+        (status = 202, description = "Cleanup started", body = OrphanCleanup),
+        (status = 400, description = "Prefix outside the deployment"),
+        (status = 409, description = "A cleanup is already running")
+    ),
+    tag = "assets"
+)]
+async fn start_orphan_cleanup(
+    State(state): State<AppState>,
+    Query(query): Query<OrphanCleanupQuery>,
+) -> Result<(StatusCode, Json<OrphanCleanup>), (StatusCode, String)> {
+    let mut options = CleanupOptions::new(&state.config, query.remove);
+    if let Some(prefix) = query.prefix {
+        if !prefix.starts_with(&options.prefix) {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("prefix must start with {}", options.prefix),
+            ));
+        }
+        options.prefix = prefix;
+    }
+    if let Some(min_age_hours) = query.min_age_hours {
+        options.min_age_hours = min_age_hours;
+    }
+
+    start_cleanup(&state, options, CleanupTrigger::Manual)
+        .await
+        .map(|cleanup| (StatusCode::ACCEPTED, Json(cleanup)))
+        .ok_or((
+            StatusCode::CONFLICT,
+            "An orphan cleanup is already running".to_string(),
+        ))
+}
+
+/// Get the most recent orphan cleanup, manual or scheduled
+#[utoipa::path(
+    get,
+    path = "/orphans",
+    responses(
+        (status = 200, description = "Latest cleanup", body = OrphanCleanup),
+        (status = 404, description = "No cleanup has been run")
+    ),
+    tag = "assets"
+)]
+async fn get_orphan_cleanup(
+    State(state): State<AppState>,
+) -> Result<Json<OrphanCleanup>, (StatusCode, String)> {
+    state.orphan_cleanup.read().await.clone().map(Json).ok_or((
+        StatusCode::NOT_FOUND,
+        "No orphan cleanup has been run".to_string(),
+    ))
+}
+
+/// Restore a soft-deleted asset
+#[utoipa::path(
+    post,
+    path = "/{id}/restore",
+    params(
+        ("id" = Uuid, Path, description = "Soft-deleted asset ID")
+    ),
+    responses(
+        (status = 200, description = "Asset restored", body = Asset),
+        (status = 404, description = "Asset not found"),
+        (status = 409, description = "Asset is not deleted")
+    ),
+    tag = "assets"
+)]
+async fn restore_asset(
+    Path(id): Path<Uuid>,
+    State(state): State<AppState>,
+) -> Result<Json<Asset>, (StatusCode, String)> {
+    let asset = find_asset(&state, id).await?;
+    if !asset.is_deleted {
+        return Err((StatusCode::CONFLICT, "Asset is not deleted".to_string()));
+    }
+
+    let restored = AssetEntity::update(super::models::ActiveModel {
+        id: sea_orm::ActiveValue::Set(id),
+        is_deleted: sea_orm::ActiveValue::Set(false),
+        last_updated: sea_orm::ActiveValue::Set(chrono::Utc::now()),
+        ..Default::default()
+    })
+    .exec(&state.db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to restore asset: {e}"),
+        )
+    })?;
+
+    Ok(Json(restored.into()))
+}
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+pub struct PurgeResponse {
+    /// IDs of the assets whose objects and records were removed
+    pub purged: Vec<Uuid>,
+}
+
+/// Permanently remove a soft-deleted asset and its S3 object
+#[utoipa::path(
+    post,
+    path = "/{id}/purge",
+    params(
+        ("id" = Uuid, Path, description = "Soft-deleted asset ID")
+    ),
+    responses(
+        (status = 200, description = "Asset purged", body = PurgeResponse),
+        (status = 403, description = "Purging needs the administrator role"),
+        (status = 404, description = "Asset not found"),
+        (status = 409, description = "Asset is not deleted"),
+        (status = 500, description = "Failed to remove the S3 object")
+    ),
+    tag = "assets"
+)]
+async fn purge_asset(
+    Path(id): Path<Uuid>,
+    State(state): State<AppState>,
+) -> Result<Json<PurgeResponse>, (StatusCode, String)> {
+    // Only assets already in the bin can be purged, live ones go through DELETE
+    let asset = find_asset(&state, id).await?;
+    if !asset.is_deleted {
+        return Err((
+            StatusCode::CONFLICT,
+            "Only soft-deleted assets can be purged".to_string(),
+        ));
+    }
+    purge(&state, vec![asset]).await
+}
+
+/// Permanently remove every soft-deleted asset and its S3 object
+#[utoipa::path(
+    post,
+    path = "/purge",
+    responses(
+        (status = 200, description = "Soft-deleted assets purged", body = PurgeResponse),
+        (status = 403, description = "Purging needs the administrator role"),
+        (status = 500, description = "Failed to remove an S3 object")
+    ),
+    tag = "assets"
+)]
+async fn purge_deleted_assets(
+    State(state): State<AppState>,
+) -> Result<Json<PurgeResponse>, (StatusCode, String)> {
+    let assets = AssetEntity::find()
+        .filter(s3_assets::Column::IsDeleted.eq(true))
+        .all(&state.db)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Database error".to_string(),
+            )
+        })?;
+    purge(&state, assets).await
+}
+
+async fn find_asset(state: &AppState, id: Uuid) -> Result<s3_assets::Model, (StatusCode, String)> {
+    AssetEntity::find_by_id(id)
+        .one(&state.db)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Database error".to_string(),
+            )
+        })?
+        .ok_or((StatusCode::NOT_FOUND, "Asset not found".to_string()))
+}
+
+/// Remove the objects of the given assets, then their records. If an object
+/// cannot be removed, the records are kept so the purge can be retried.
+async fn purge(
+    state: &AppState,
+    assets: Vec<s3_assets::Model>,
+) -> Result<Json<PurgeResponse>, (StatusCode, String)> {
+    // Content shared with live assets stays in the bucket
+    super::dedupe::release_asset_objects(&state.db, &state.config, &assets)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    let purged: Vec<Uuid> = assets.iter().map(|asset| asset.id).collect();
+    AssetEntity::delete_many()
+        .filter(s3_assets::Column::Id.is_in(purged.clone()))
+        .exec(&state.db)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to delete asset records: {e}"),
+            )
+        })?;
+    Ok(Json(PurgeResponse { purged }))
+}
 
 /// Reprocess an Excel asset (for merged.xlsx files)
 #[utoipa::path(
@@ -113,7 +757,7 @@ async fn reprocess_asset(
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
     // Download file from S3 (uses mock for tests, real S3 for production)
-    let file_bytes = crate::external::s3::get_object_from_s3(&asset.s3_key, &state.config)
+    let file_bytes = crate::external::s3::get_object_from_s3(asset.storage_key(), &state.config)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
@@ -167,6 +811,8 @@ async fn serve_asset_internal(
     id: Uuid,
     state: &AppState,
     as_attachment: bool,
+    redirect: bool,
+    request_headers: &HeaderMap,
 ) -> Result<Response, StatusCode> {
     // Find the asset in the database
     let asset = AssetEntity::find_by_id(id)
@@ -175,16 +821,48 @@ async fn serve_asset_internal(
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
         .ok_or(StatusCode::NOT_FOUND)?;
 
+    if redirect && crate::external::s3::supports_presigned_urls(&state.config).await {
+        let url = presigned_asset_url(state, &asset, as_attachment)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        // The browser may follow the same redirect while the URL is still valid
+        let cache_control = format!(
+            "private, max-age={}",
+            PRESIGNED_DOWNLOAD_EXPIRY.as_secs() / 2
+        );
+        return Response::builder()
+            .status(StatusCode::TEMPORARY_REDIRECT)
+            .header(LOCATION, url)
+            .header(CACHE_CONTROL, cache_control)
+            .body(axum::body::Body::empty())
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    let etag = asset_etag(&asset);
+    if matches_if_none_match(request_headers, &etag) {
+        return not_modified(&etag, ASSET_CACHE_CONTROL);
+    }
+
     // Download from S3 (uses mock for tests, real S3 for production)
-    let body_bytes = crate::external::s3::get_object_from_s3(&asset.s3_key, &state.config)
+    let body_bytes = crate::external::s3::get_object_from_s3(asset.storage_key(), &state.config)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    // Set headers
     let mut headers = HeaderMap::new();
+    headers.insert(CONTENT_TYPE, asset_content_type(&asset).parse().unwrap());
+    headers.insert(
+        CONTENT_DISPOSITION,
+        content_disposition(&asset, as_attachment).parse().unwrap(),
+    );
+    headers.insert(CACHE_CONTROL, ASSET_CACHE_CONTROL.parse().unwrap());
+    headers.insert(ETAG, etag.parse().unwrap());
 
-    // Set content type based on file extension or stored type
-    let content_type = match asset.r#type.as_str() {
+    Ok((headers, body_bytes).into_response())
+}
+
+/// Content type based on file extension or stored type
+fn asset_content_type(asset: &s3_assets::Model) -> &'static str {
+    match asset.r#type.as_str() {
         "image" => {
             let ext = asset
                 .original_filename
@@ -203,74 +881,187 @@ async fn serve_asset_internal(
                 }
             }
         }
+        "video" => {
+            if asset.original_filename.to_lowercase().ends_with(".webm") {
+                "video/webm"
+            } else {
+                "video/mp4"
+            }
+        }
         "tabular" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
         "netcdf" => "application/x-netcdf",
         _ => "application/octet-stream",
-    };
-
-    headers.insert(CONTENT_TYPE, content_type.parse().unwrap());
+    }
+}
 
+fn content_disposition(asset: &s3_assets::Model, as_attachment: bool) -> String {
     if as_attachment {
-        let disposition = format!("attachment; filename=\"{}\"", asset.original_filename);
-        headers.insert(CONTENT_DISPOSITION, disposition.parse().unwrap());
+        format!("attachment; filename=\"{}\"", asset.original_filename)
     } else {
-        let disposition = format!("inline; filename=\"{}\"", asset.original_filename);
-        headers.insert(CONTENT_DISPOSITION, disposition.parse().unwrap());
+        format!("inline; filename=\"{}\"", asset.original_filename)
     }
+}
 
-    Ok((headers, body_bytes).into_response())
+fn download_token_error(e: DbErr) -> (StatusCode, String) {
+    match e {
+        DbErr::Custom(message) => (StatusCode::BAD_REQUEST, message),
+        e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+/// Assets to download with one token, and how long and how often it may
+/// be used
+#[derive(serde::Deserialize, utoipa::ToSchema)]
+pub struct BulkDownloadRequest {
+    asset_ids: Vec<Uuid>,
+    #[serde(flatten)]
+    options: DownloadTokenOptions,
 }
 
 /// Create a download token for bulk asset download
 #[utoipa::path(
     post,
     path = "/bulk-download-token",
-    request_body(content_type = "application/json", description = "Asset IDs to download"),
+    request_body = BulkDownloadRequest,
     responses(
-        (status = 200, description = "Download token created"),
+        (status = 200, description = "Download token created", body = IssuedDownloadToken),
         (status = 400, description = "Invalid request")
     ),
     tag = "assets"
 )]
 async fn create_bulk_download_token(
     State(state): State<AppState>,
-    axum::Json(payload): axum::Json<serde_json::Value>,
-) -> Result<axum::Json<serde_json::Value>, (StatusCode, String)> {
-    let asset_ids = payload
-        .get("asset_ids")
-        .and_then(|v| v.as_array())
-        .ok_or((StatusCode::BAD_REQUEST, "Missing asset_ids".to_string()))?;
-
-    let asset_uuids: Result<Vec<Uuid>, _> = asset_ids
-        .iter()
-        .map(|v| v.as_str().unwrap_or("").parse::<Uuid>())
-        .collect();
-
-    let asset_uuids =
-        asset_uuids.map_err(|_| (StatusCode::BAD_REQUEST, "Invalid asset IDs".to_string()))?;
-
-    if asset_uuids.is_empty() {
+    axum::Json(request): axum::Json<BulkDownloadRequest>,
+) -> Result<(Extension<ShowsSecret>, axum::Json<IssuedDownloadToken>), (StatusCode, String)> {
+    if request.asset_ids.is_empty() {
         return Err((StatusCode::BAD_REQUEST, "No asset IDs provided".to_string()));
     }
 
-    let token = state.create_download_token(asset_uuids).await;
+    issue_download_token(
+        &state.db,
+        &state.config,
+        DownloadScope::Assets,
+        request.asset_ids,
+        None,
+        request.options,
+    )
+    .await
+    .map(|issued| (Extension(ShowsSecret), axum::Json(issued)))
+    .map_err(download_token_error)
+}
 
-    Ok(axum::Json(serde_json::json!({
-        "token": token,
-        "download_url": format!("/api/assets/download/{}", token)
-    })))
+/// Create a download token for one asset
+#[utoipa::path(
+    post,
+    path = "/{id}/download-token",
+    params(
+        ("id" = Uuid, Path, description = "Asset ID")
+    ),
+    request_body(content = Option<DownloadTokenOptions>, description = "How long and how often the token may be used; the configured defaults when omitted"),
+    responses(
+        (status = 200, description = "Download token created", body = IssuedDownloadToken),
+        (status = 400, description = "Invalid time to live or number of downloads"),
+        (status = 404, description = "Asset not found")
+    ),
+    tag = "assets"
+)]
+async fn create_asset_download_token(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    options: Option<axum::Json<DownloadTokenOptions>>,
+) -> Result<(Extension<ShowsSecret>, axum::Json<IssuedDownloadToken>), (StatusCode, String)> {
+    AssetEntity::find_by_id(id)
+        .one(&state.db)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Asset not found".to_string()))?;
+
+    issue_download_token(
+        &state.db,
+        &state.config,
+        DownloadScope::Asset,
+        vec![id],
+        None,
+        options
+            .map(|axum::Json(options)| options)
+            .unwrap_or_default(),
+    )
+    .await
+    .map(|issued| (Extension(ShowsSecret), axum::Json(issued)))
+    .map_err(download_token_error)
 }
 
-/// Download assets using a token (GET endpoint for direct browser download)
+/// List download tokens
+#[utoipa::path(
+    get,
+    path = "/download-tokens",
+    params(DownloadTokenQuery),
+    responses(
+        (status = 200, description = "Issued tokens, newest first, without their secrets", body = Vec<DownloadToken>),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "assets",
+    summary = "List download tokens",
+    description = "List the download tokens issued, with what they give, who issued them, when they expire, how often they were used and whether they were revoked"
+)]
+async fn get_download_tokens(
+    State(state): State<AppState>,
+    Query(query): Query<DownloadTokenQuery>,
+) -> Result<axum::Json<Vec<DownloadToken>>, (StatusCode, String)> {
+    list_download_tokens(&state.db, query)
+        .await
+        .map(axum::Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// Revoke a download token
+#[utoipa::path(
+    delete,
+    path = "/download-tokens/{id}",
+    params(
+        ("id" = Uuid, Path, description = "Download token ID")
+    ),
+    responses(
+        (status = 200, description = "The revoked token", body = DownloadToken),
+        (status = 404, description = "Download token not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "assets",
+    summary = "Revoke a download token",
+    description = "Stop accepting the token, also for resuming downloads. It stays listed with who revoked it and when"
+)]
+async fn delete_download_token(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<axum::Json<DownloadToken>, (StatusCode, String)> {
+    revoke_download_token(&state.db, id)
+        .await
+        .map(axum::Json)
+        .map_err(|e| match e {
+            DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+            e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })
+}
+
+/// Download assets using a token (GET endpoint for direct browser download).
+///
+/// A token for one asset gives the asset itself, and other tokens a ZIP file.
+/// The archive keeps the same bytes until the token expires, so an
+/// interrupted download can be resumed with a `Range` request past its first
+/// byte.
 #[utoipa::path(
     get,
     path = "/download/{token}",
     params(
-        ("token" = String, Path, description = "Download token")
+        ("token" = String, Path, description = "Download token"),
+        ("Range" = Option<String>, Header, description = "Single byte range to resume an interrupted download, e.g. `bytes=1048576-`"),
+        ("If-Range" = Option<String>, Header, description = "ETag of the archive the range refers to")
     ),
     responses(
         (status = 200, description = "ZIP file with assets"),
-        (status = 404, description = "Invalid or expired token"),
+        (status = 206, description = "Requested byte range of the ZIP file"),
+        (status = 404, description = "Invalid, expired, used up or revoked token, or a download resumed too often"),
+        (status = 416, description = "Range lies outside the archive"),
         (status = 500, description = "Failed to create ZIP file")
     ),
     tag = "assets"
@@ -278,26 +1069,77 @@ async fn create_bulk_download_token(
 async fn download_with_token(
     State(state): State<AppState>,
     Path(token): Path<String>,
+    headers: HeaderMap,
 ) -> Result<Response, (StatusCode, String)> {
-    // Consume the token (it's single-use)
-    let download_token = state.consume_download_token(&token).await.ok_or((
-        StatusCode::NOT_FOUND,
-        "Invalid or expired token".to_string(),
-    ))?;
+    // Only a range past the first byte resumes a download; any other request
+    // starts one
+    let resuming = headers
+        .get(RANGE)
+        .and_then(|range| range.to_str().ok())
+        .and_then(|range| range.trim().strip_prefix("bytes="))
+        .and_then(|spec| spec.split_once('-'))
+        .and_then(|(first, _)| first.trim().parse::<u64>().ok())
+        .is_some_and(|first| first > 0);
+    let download_token =
+        use_download_token(&state.db, &token, resuming, chrono::Utc::now()).await?;
+
+    if download_token.scope == DownloadScope::Asset {
+        let id = download_token
+            .asset_list()
+            .first()
+            .copied()
+            .ok_or((StatusCode::NOT_FOUND, "No assets in token".to_string()))?;
+        return serve_asset_internal(id, &state, true, false, &headers)
+            .await
+            .map_err(|status| (status, "Failed to download the asset".to_string()));
+    }
+
+    let filename = match download_token.experiment_id {
+        Some(experiment_id) => format!("experiment_{experiment_id}.zip"),
+        None => format!(
+            "bulk-assets-{}.zip",
+            download_token.created_at.format("%Y%m%d-%H%M%S")
+        ),
+    };
+
+    let layout = if let Some(layout) = state.download_layout(download_token.id).await {
+        layout
+    } else {
+        let assets = token_assets(&state, &download_token).await?;
+        let layout = Arc::new(super::archive::plan_asset_archive(assets, &state.config).await?);
+        state
+            .set_download_layout(download_token.id, download_token.expires_at, layout.clone())
+            .await;
+        layout
+    };
+
+    Ok(super::archive::ranged_zip_response(
+        layout,
+        &state.config,
+        &headers,
+        &filename,
+    ))
+}
+
+/// Load the assets a download token gives access to
+async fn token_assets(
+    state: &AppState,
+    download_token: &DownloadTokenModel,
+) -> Result<Vec<s3_assets::Model>, (StatusCode, String)> {
+    let database_error = |_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Database error".to_string(),
+        )
+    };
 
     // Handle experiment download
     if let Some(experiment_id) = download_token.experiment_id {
-        // Fetch experiment assets
         let assets = s3_assets::Entity::find()
             .filter(s3_assets::Column::ExperimentId.eq(Some(experiment_id)))
             .all(&state.db)
             .await
-            .map_err(|_| {
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "Database error".to_string(),
-                )
-            })?;
+            .map_err(database_error)?;
 
         if assets.is_empty() {
             return Err((
@@ -305,49 +1147,56 @@ async fn download_with_token(
                 "No assets found for experiment".to_string(),
             ));
         }
-
-        // Use hybrid streaming: concurrent downloads + immediate streaming
-        let mut response =
-            super::services::create_hybrid_streaming_zip_response(assets, &state.config).await?;
-
-        // Update filename for experiment
-        let headers = response.headers_mut();
-        headers.insert(
-            CONTENT_DISPOSITION,
-            format!("attachment; filename=\"experiment_{experiment_id}.zip\"")
-                .parse()
-                .unwrap(),
-        );
-
-        return Ok(response);
+        return Ok(assets);
     }
 
     // Handle regular asset download
-    let asset_uuids = download_token.asset_ids;
-    if asset_uuids.is_empty() {
+    let asset_ids = download_token.asset_list();
+    if asset_ids.is_empty() {
         return Err((StatusCode::BAD_REQUEST, "No assets in token".to_string()));
     }
 
-    // Fetch assets from database
-    let assets: Vec<super::models::Model> = AssetEntity::find()
-        .filter(super::models::Column::Id.is_in(asset_uuids))
+    let assets = AssetEntity::find()
+        .filter(s3_assets::Column::Id.is_in(asset_ids))
         .all(&state.db)
         .await
-        .map_err(|_| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Database error".to_string(),
-            )
-        })?;
+        .map_err(database_error)?;
 
     if assets.is_empty() {
         return Err((StatusCode::NOT_FOUND, "No assets found".to_string()));
     }
-
-    // Use hybrid streaming: concurrent downloads + immediate streaming
-    super::services::create_hybrid_streaming_zip_response(assets, &state.config).await
+    Ok(assets)
 }
 
+/// Routes beyond the CRUD routes, documented here as they are routed
+/// with `route`
+#[derive(OpenApi)]
+#[openapi(paths(
+    download_asset,
+    view_asset,
+    get_presigned_asset_url,
+    get_thumbnail,
+    get_image_well_grid,
+    get_image_overlay,
+    search_assets,
+    get_asset_stats,
+    start_integrity_audit,
+    get_integrity_audit,
+    start_orphan_cleanup,
+    get_orphan_cleanup,
+    restore_asset,
+    purge_asset,
+    purge_deleted_assets,
+    reprocess_asset,
+    create_bulk_download_token,
+    create_asset_download_token,
+    get_download_tokens,
+    delete_download_token,
+    download_with_token
+))]
+struct AssetsApi;
+
+#[allow(clippy::too_many_lines)] // One route per asset endpoint
 pub fn router(state: &AppState) -> OpenApiRouter
 where
     Asset: CRUDResource,
@@ -360,30 +1209,120 @@ where
 
     // Authenticated routes - token creation and other operations
     let mut authenticated_router = crudrouter(&state.db.clone())
+        .route(
+            "/{id}",
+            patch(patch_one_handler::<Asset>).with_state(state.db.clone()),
+        )
         .nest(
             "/{id}",
             OpenApiRouter::new()
                 .route("/download", get(download_asset))
+                .route("/download-token", post(create_asset_download_token))
                 .route("/view", get(view_asset))
+                .route("/thumbnail", get(get_thumbnail))
+                .route("/url", get(get_presigned_asset_url))
+                .route("/well-grid", get(get_image_well_grid))
+                .route("/overlay", get(get_image_overlay))
                 .route("/reprocess", axum::routing::post(reprocess_asset))
+                .route("/restore", post(restore_asset))
+                // Purges cannot be undone, so they are left to administrators
+                .route(
+                    "/purge",
+                    post(purge_asset).route_layer(middleware::from_fn_with_state(
+                        RouteAccess::ADMINISTRATION,
+                        require_role,
+                    )),
+                )
                 .with_state(state.clone()),
         )
+        .route("/search", get(search_assets).with_state(state.clone()))
+        .route(
+            "/download-tokens",
+            get(get_download_tokens).with_state(state.clone()),
+        )
+        .route(
+            "/download-tokens/{id}",
+            delete(delete_download_token).with_state(state.clone()),
+        )
+        .route("/stats", get(get_asset_stats).with_state(state.clone()))
         .route(
             "/bulk-download-token",
             post(create_bulk_download_token).with_state(state.clone()),
+        )
+        .route(
+            "/purge",
+            post(purge_deleted_assets)
+                .route_layer(middleware::from_fn_with_state(
+                    RouteAccess::ADMINISTRATION,
+                    require_role,
+                ))
+                .with_state(state.clone()),
+        )
+        .route(
+            "/integrity-audit",
+            post(start_integrity_audit)
+                .get(get_integrity_audit)
+                .with_state(state.clone()),
+        )
+        .route(
+            "/orphans",
+            post(start_orphan_cleanup)
+                .get(get_orphan_cleanup)
+                .with_state(state.clone()),
         );
+    authenticated_router
+        .get_openapi_mut()
+        .merge(AssetsApi::openapi());
+
+    authenticated_router = authenticated_router.layer(middleware::from_fn_with_state(
+        state.db.clone(),
+        paginate_by_cursor::<Asset>,
+    ));
+
+    authenticated_router = authenticated_router.layer(middleware::from_fn_with_state(
+        (state.db.clone(), state.config.require_if_match),
+        check_entity_tags::<Asset>,
+    ));
 
     // Apply authentication to the authenticated routes only
+    // Archived projects and their records are read-only, even to administrators
+    authenticated_router = authenticated_router.layer(middleware::from_fn_with_state(
+        (state.db.clone(), Some(ScopedResource::Assets)),
+        reject_archived_changes,
+    ));
+
+    // Changes are logged with the user who made them
+    authenticated_router = authenticated_router.layer(middleware::from_fn_with_state(
+        (state.db.clone(), AuditedResource::Assets),
+        audit_changes,
+    ));
+
     if let Some(instance) = state.keycloak_auth_instance.clone() {
-        authenticated_router = authenticated_router.layer(
-            KeycloakAuthLayer::<Role>::builder()
-                .instance(instance)
-                .passthrough_mode(PassthroughMode::Block)
-                .persist_raw_claims(false)
-                .expected_audiences(vec![String::from("account")])
-                .required_roles(vec![Role::Administrator])
-                .build(),
-        );
+        // Viewers read and editors change records; users who are not
+        // administrators are limited to the projects they are members of and
+        // the records they created or were given, within their labs
+        authenticated_router = authenticated_router
+            .layer(middleware::from_fn_with_state(
+                (state.db.clone(), ScopedResource::Assets),
+                require_project_access,
+            ))
+            .layer(middleware::from_fn_with_state(
+                (state.db.clone(), TenantResource::Assets),
+                require_lab,
+            ))
+            .layer(middleware::from_fn_with_state(
+                RouteAccess::PROJECT_RECORDS,
+                require_role,
+            ))
+            .layer(middleware::from_fn(redact_for_viewers))
+            .layer(middleware::from_fn_with_state(
+                (state.db.clone(), "assets"),
+                accept_api_keys,
+            ))
+            .layer(middleware::from_fn_with_state(
+                (instance, PassthroughMode::Pass),
+                authenticate,
+            ));
     } else if !state.config.tests_running {
         println!(
             "Warning: Mutating routes of {} router are not protected",
@@ -0,0 +1,59 @@
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, ToSchema, Serialize, Deserialize,
+)]
+#[sea_orm(rs_type = "String", db_type = "Text")]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    #[sea_orm(string_value = "create")]
+    Create,
+    #[sea_orm(string_value = "update")]
+    Update,
+    #[sea_orm(string_value = "delete")]
+    Delete,
+}
+
+/// A change made through the API: who made it, to what, when, and the
+/// record before and after
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, ToSchema)]
+#[sea_orm(table_name = "audit_log")]
+#[schema(as = AuditEntry)]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    pub occurred_at: DateTime<Utc>,
+    /// Keycloak username, or the user of the API key, that made the change
+    #[sea_orm(column_type = "Text", nullable)]
+    pub username: Option<String>,
+    pub action: AuditAction,
+    /// Route group the change was made in, such as `samples`
+    #[sea_orm(column_type = "Text")]
+    pub resource: String,
+    /// Record changed, when the change was to one record
+    pub record_id: Option<Uuid>,
+    #[sea_orm(column_type = "Text")]
+    pub method: String,
+    #[sea_orm(column_type = "Text")]
+    pub path: String,
+    pub status: i32,
+    /// `X-Request-Id` of the request
+    #[sea_orm(column_type = "Text", nullable)]
+    pub request_id: Option<String>,
+    #[sea_orm(column_type = "JsonBinary", nullable)]
+    pub before: Option<Json>,
+    #[sea_orm(column_type = "JsonBinary", nullable)]
+    pub after: Option<Json>,
+    /// Fields that differ, each with its value before and after
+    #[sea_orm(column_type = "JsonBinary", nullable)]
+    pub changes: Option<Json>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,416 @@
+//! Audit log of changes made through the API.
+//!
+//! Each route group logs its successful changes with `audit_changes`, a
+//! middleware behind the authentication layers: who made the change, the
+//! request ID, and the record before and after with the fields that
+//! differ. A `POST` to a collection creates, a `DELETE` deletes, and other
+//! changes, such as a `PATCH` or a `POST` to one of a record's routes,
+//! update the record named in the path. Operations over a whole collection
+//! are logged without a record. Request bodies are not kept; records are
+//! read back from the database, so secrets never reach the log.
+
+use super::models::{ActiveModel, AuditAction, Column, Entity, Model};
+use crate::common::auth::Role;
+use crate::webhooks::services::{emit, record_event};
+use crate::{
+    api_keys::models as api_keys, assets::models as assets, experiments::models as experiments,
+    locations::models as locations, projects::models as projects, samples::models as samples,
+    tray_configurations::models as tray_configurations, treatments::dilutions::models as dilutions,
+    treatments::models as treatments, webhooks::models as webhooks,
+};
+use axum::{
+    Extension,
+    body::{Body, to_bytes},
+    extract::{OriginalUri, Request, State},
+    http::{HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use axum_keycloak_auth::decode::KeycloakToken;
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, DatabaseConnection, DbErr, EntityTrait,
+    PrimaryKeyTrait, QueryFilter, QueryOrder, QuerySelect,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+use std::collections::HashMap;
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+pub const DEFAULT_LIMIT: u64 = 100;
+pub const MAX_LIMIT: u64 = 1000;
+
+/// As the router's body limit
+const BODY_LIMIT: usize = 30 * 1024 * 1024;
+
+/// Route groups whose changes are logged
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuditedResource {
+    ApiKeys,
+    Assets,
+    Dilutions,
+    Experiments,
+    Locations,
+    Projects,
+    Samples,
+    TrayConfigurations,
+    Treatments,
+    Users,
+    Webhooks,
+}
+
+impl AuditedResource {
+    /// Name of the route group, as nested under `/api`
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::ApiKeys => "api_keys",
+            Self::Assets => "assets",
+            Self::Dilutions => "dilutions",
+            Self::Experiments => "experiments",
+            Self::Locations => "locations",
+            Self::Projects => "projects",
+            Self::Samples => "samples",
+            Self::TrayConfigurations => "tray_configurations",
+            Self::Treatments => "treatments",
+            Self::Users => "users",
+            Self::Webhooks => "webhooks",
+        }
+    }
+
+    /// The record as the API shows it, or `None` when there is no such
+    /// record
+    pub(crate) async fn snapshot(
+        self,
+        db: &DatabaseConnection,
+        id: Uuid,
+    ) -> Result<Option<Value>, DbErr> {
+        match self {
+            Self::ApiKeys => snapshot::<api_keys::Entity, api_keys::ApiKey>(db, id).await,
+            Self::Assets => snapshot::<assets::Entity, assets::Asset>(db, id).await,
+            Self::Dilutions => snapshot::<dilutions::Entity, dilutions::Dilution>(db, id).await,
+            Self::Experiments => {
+                snapshot::<experiments::Entity, experiments::Experiment>(db, id).await
+            }
+            Self::Locations => snapshot::<locations::Entity, locations::Location>(db, id).await,
+            Self::Projects => snapshot::<projects::Entity, projects::Project>(db, id).await,
+            Self::Samples => snapshot::<samples::Entity, samples::Sample>(db, id).await,
+            Self::TrayConfigurations => {
+                snapshot::<tray_configurations::Entity, tray_configurations::TrayConfiguration>(
+                    db, id,
+                )
+                .await
+            }
+            Self::Treatments => snapshot::<treatments::Entity, treatments::Treatment>(db, id).await,
+            Self::Users => Ok(None),
+            Self::Webhooks => snapshot::<webhooks::Entity, webhooks::Webhook>(db, id).await,
+        }
+    }
+}
+
+async fn snapshot<E, T>(db: &DatabaseConnection, id: Uuid) -> Result<Option<Value>, DbErr>
+where
+    E: EntityTrait,
+    <E::PrimaryKey as PrimaryKeyTrait>::ValueType: From<Uuid>,
+    T: From<E::Model> + Serialize,
+{
+    Ok(E::find_by_id(id)
+        .one(db)
+        .await?
+        .and_then(|model| serde_json::to_value(T::from(model)).ok()))
+}
+
+/// Fields of two versions of a record that differ, each with its value
+/// before and after
+pub fn diff(before: Option<&Value>, after: Option<&Value>) -> Option<Value> {
+    let empty = Map::new();
+    let before = before.and_then(Value::as_object);
+    let after = after.and_then(Value::as_object);
+    if before.is_none() && after.is_none() {
+        return None;
+    }
+    let (before, after) = (before.unwrap_or(&empty), after.unwrap_or(&empty));
+
+    let mut fields: Vec<&String> = before.keys().chain(after.keys()).collect();
+    fields.sort();
+    fields.dedup();
+    let changes: Map<String, Value> = fields
+        .into_iter()
+        .filter(|field| before.get(*field) != after.get(*field))
+        .map(|field| {
+            (
+                field.clone(),
+                json!({
+                    "before": before.get(field).cloned().unwrap_or(Value::Null),
+                    "after": after.get(field).cloned().unwrap_or(Value::Null),
+                }),
+            )
+        })
+        .collect();
+    Some(Value::Object(changes))
+}
+
+/// A change to log
+#[derive(Clone, Debug)]
+pub struct AuditedChange {
+    pub username: Option<String>,
+    pub action: AuditAction,
+    pub resource: AuditedResource,
+    pub record_id: Option<Uuid>,
+    pub method: String,
+    pub path: String,
+    pub status: StatusCode,
+    pub request_id: Option<String>,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+/// Log a change, for the middleware or for changes made outside it
+pub async fn record_change(db: &DatabaseConnection, change: AuditedChange) -> Result<Model, DbErr> {
+    let changes = diff(change.before.as_ref(), change.after.as_ref());
+
+    // Changes to records are also sent to the webhooks subscribed to them
+    if let Some(record_id) = change.record_id
+        && let Some(event_type) = record_event(change.resource, change.action)
+    {
+        let data = json!({
+            "resource": change.resource.name(),
+            "id": record_id,
+            "username": change.username,
+            "record": change.after.as_ref().or(change.before.as_ref()),
+            "changes": changes,
+        });
+        emit(db, &event_type, data).await;
+    }
+
+    ActiveModel {
+        id: Set(Uuid::new_v4()),
+        occurred_at: Set(Utc::now()),
+        username: Set(change.username),
+        action: Set(change.action),
+        resource: Set(change.resource.name().to_string()),
+        record_id: Set(change.record_id),
+        method: Set(change.method),
+        path: Set(change.path),
+        status: Set(i32::from(change.status.as_u16())),
+        request_id: Set(change.request_id),
+        before: Set(change.before),
+        after: Set(change.after),
+        changes: Set(changes),
+    }
+    .insert(db)
+    .await
+}
+
+#[derive(Clone, Debug, Default, Deserialize, IntoParams)]
+pub struct AuditQuery {
+    /// Route group, such as `samples`
+    pub resource: Option<String>,
+    pub record_id: Option<Uuid>,
+    pub username: Option<String>,
+    pub action: Option<AuditAction>,
+    pub request_id: Option<String>,
+    /// Changes at or after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Changes before this time
+    pub until: Option<DateTime<Utc>>,
+    /// Number of entries, newest first; 100 by default and at most 1000
+    pub limit: Option<u64>,
+}
+
+/// Entries matching the query, newest first
+pub async fn query_audit_log(
+    db: &DatabaseConnection,
+    query: AuditQuery,
+) -> Result<Vec<Model>, DbErr> {
+    let mut select = Entity::find();
+    if let Some(resource) = query.resource {
+        select = select.filter(Column::Resource.eq(resource));
+    }
+    if let Some(record_id) = query.record_id {
+        select = select.filter(Column::RecordId.eq(record_id));
+    }
+    if let Some(username) = query.username {
+        select = select.filter(Column::Username.eq(username));
+    }
+    if let Some(action) = query.action {
+        select = select.filter(Column::Action.eq(action));
+    }
+    if let Some(request_id) = query.request_id {
+        select = select.filter(Column::RequestId.eq(request_id));
+    }
+    if let Some(since) = query.since {
+        select = select.filter(Column::OccurredAt.gte(since));
+    }
+    if let Some(until) = query.until {
+        select = select.filter(Column::OccurredAt.lt(until));
+    }
+    select
+        .order_by_desc(Column::OccurredAt)
+        .order_by_desc(Column::Id)
+        .limit(query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT))
+        .all(db)
+        .await
+}
+
+/// Middleware giving every request an ID, kept from its `X-Request-Id`
+/// header or made up, and returning it in the response
+pub async fn assign_request_id(mut request: Request, next: Next) -> Response {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .filter(|value| !value.is_empty() && value.len() <= 200)
+        .cloned()
+        .unwrap_or_else(|| {
+            HeaderValue::from_str(&Uuid::new_v4().to_string())
+                .expect("A UUID is a valid header value")
+        });
+    request
+        .headers_mut()
+        .insert(REQUEST_ID_HEADER, request_id.clone());
+    let mut response = next.run(request).await;
+    response.headers_mut().insert(REQUEST_ID_HEADER, request_id);
+    response
+}
+
+async fn read_json(body: Body) -> Result<(Body, Option<Value>), Response> {
+    let Ok(bytes) = to_bytes(body, BODY_LIMIT).await else {
+        return Err(StatusCode::PAYLOAD_TOO_LARGE.into_response());
+    };
+    let json = serde_json::from_slice(&bytes).ok();
+    Ok((Body::from(bytes), json))
+}
+
+fn uuids(json: Option<&Value>) -> Vec<Uuid> {
+    json.and_then(Value::as_array)
+        .map(|ids| {
+            ids.iter()
+                .filter_map(|id| id.as_str().and_then(|id| Uuid::parse_str(id).ok()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Whether the middleware logs a request: reads are not changes, and
+/// upserts log each record they create or update themselves
+fn is_logged(method: &Method, path: &str) -> bool {
+    match *method {
+        Method::GET | Method::HEAD | Method::OPTIONS => false,
+        Method::POST => path.trim_matches('/') != "upsert",
+        _ => true,
+    }
+}
+
+/// Middleware logging the successful changes of a route group
+pub async fn audit_changes(
+    State((db, resource)): State<(DatabaseConnection, AuditedResource)>,
+    token: Option<Extension<KeycloakToken<Role>>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let method = request.method().clone();
+    if !is_logged(&method, request.uri().path()) {
+        return next.run(request).await;
+    }
+    let path = request.uri().path().to_string();
+    let full_path = request
+        .extensions()
+        .get::<OriginalUri>()
+        .map_or_else(|| path.clone(), |OriginalUri(uri)| uri.path().to_string());
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    let record_id = segments
+        .first()
+        .and_then(|first| Uuid::parse_str(first).ok());
+    let batch_delete = method == Method::DELETE && segments == ["batch"];
+
+    // Records as they were, by ID
+    let (request, targets) = if batch_delete {
+        let (parts, body) = request.into_parts();
+        let (body, json) = match read_json(body).await {
+            Ok(read) => read,
+            Err(response) => return response,
+        };
+        (Request::from_parts(parts, body), uuids(json.as_ref()))
+    } else {
+        (request, record_id.into_iter().collect())
+    };
+    let mut before = HashMap::new();
+    for id in targets {
+        match resource.snapshot(&db, id).await {
+            Ok(snapshot) => {
+                before.insert(id, snapshot);
+            }
+            Err(e) => tracing::error!("Could not read a record for the audit log: {e}"),
+        }
+    }
+
+    let response = next.run(request).await;
+    let status = response.status();
+    if !status.is_success() {
+        return response;
+    }
+
+    // Creates and batch deletes name their records in the response
+    let creating = method == Method::POST && record_id.is_none();
+    let (response, response_json) = if (creating && segments.is_empty()) || batch_delete {
+        let (parts, body) = response.into_parts();
+        let (body, json) = match read_json(body).await {
+            Ok(read) => read,
+            Err(response) => return response,
+        };
+        (Response::from_parts(parts, body), json)
+    } else {
+        (response, None)
+    };
+    let (action, records) = if batch_delete {
+        (AuditAction::Delete, uuids(response_json.as_ref()))
+    } else if creating {
+        let created = response_json
+            .as_ref()
+            .and_then(|json| json.get("id"))
+            .and_then(Value::as_str)
+            .and_then(|id| Uuid::parse_str(id).ok());
+        (AuditAction::Create, created.into_iter().collect())
+    } else if method == Method::DELETE {
+        (AuditAction::Delete, record_id.into_iter().collect())
+    } else {
+        (AuditAction::Update, record_id.into_iter().collect())
+    };
+
+    let username = token.map(|Extension(token)| token.extra.profile.preferred_username);
+    let changes: Vec<Option<Uuid>> = if records.is_empty() {
+        vec![None]
+    } else {
+        records.into_iter().map(Some).collect()
+    };
+    for id in changes {
+        let after = match id {
+            Some(id) if action != AuditAction::Delete => {
+                resource.snapshot(&db, id).await.ok().flatten()
+            }
+            _ => None,
+        };
+        let change = AuditedChange {
+            username: username.clone(),
+            action,
+            resource,
+            record_id: id,
+            method: method.to_string(),
+            path: full_path.clone(),
+            status,
+            request_id: request_id.clone(),
+            before: id.and_then(|id| before.get(&id).cloned().flatten()),
+            after,
+        };
+        if let Err(e) = record_change(&db, change).await {
+            tracing::error!("Could not write to the audit log: {e}");
+        }
+    }
+    response
+}
@@ -0,0 +1,189 @@
+use super::services::diff;
+use crate::config::test_helpers::{send_json, setup_test_app};
+use axum::body::{Body, to_bytes};
+use axum::http::{HeaderMap, Request, StatusCode};
+use serde_json::{Value, json};
+use tower::ServiceExt;
+
+/// `send_json` with the given request ID, if any, also reading the
+/// response's headers
+async fn send_with_request_id(
+    app: &axum::Router,
+    method: &str,
+    uri: &str,
+    body: Option<&Value>,
+    request_id: Option<&str>,
+) -> (StatusCode, HeaderMap, Value) {
+    let mut request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("content-type", "application/json");
+    if let Some(request_id) = request_id {
+        request = request.header("x-request-id", request_id);
+    }
+    let body = body.map_or_else(Body::empty, |body| Body::from(body.to_string()));
+    let response = app
+        .clone()
+        .oneshot(request.body(body).unwrap())
+        .await
+        .unwrap();
+    let (status, headers) = (response.status(), response.headers().clone());
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (
+        status,
+        headers,
+        serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+    )
+}
+
+#[tokio::test]
+async fn test_audit_log() {
+    let app = setup_test_app().await;
+    let name = format!("Audited project {}", uuid::Uuid::new_v4());
+
+    let (status, headers, project) = send_with_request_id(
+        &app,
+        "POST",
+        "/api/projects",
+        Some(&json!({"name": name, "colour": "#112233"})),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    // Every response has a request ID, made up when none was sent
+    let created_request_id = headers["x-request-id"].to_str().unwrap().to_string();
+    assert!(uuid::Uuid::parse_str(&created_request_id).is_ok());
+    let id = project["id"].as_str().unwrap().to_string();
+
+    let (status, headers, _) = send_with_request_id(
+        &app,
+        "PUT",
+        &format!("/api/projects/{id}"),
+        Some(&json!({"colour": "#445566"})),
+        Some("rename-42"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers["x-request-id"], "rename-42");
+
+    // Failed changes are not logged
+    let (status, _) = send_json(
+        &app,
+        "PUT",
+        &format!("/api/projects/{}", uuid::Uuid::new_v4()),
+        Some(&json!({"colour": "#000000"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, _) = send_json(&app, "DELETE", &format!("/api/projects/{id}"), None).await;
+    assert!(status.is_success());
+
+    let (status, entries) = send_json(
+        &app,
+        "GET",
+        &format!("/api/audit?resource=projects&record_id={id}"),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let actions: Vec<&str> = entries
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| entry["action"].as_str().unwrap())
+        .collect();
+    assert_eq!(actions, vec!["delete", "update", "create"]);
+
+    let (deleted, updated, created) = (&entries[0], &entries[1], &entries[2]);
+    assert!(created["before"].is_null());
+    assert_eq!(created["after"]["name"], name);
+    assert_eq!(created["request_id"], created_request_id.as_str());
+    assert_eq!(created["path"], "/api/projects");
+    assert_eq!(created["status"], 201);
+    assert_eq!(
+        updated["changes"]["colour"],
+        json!({"before": "#112233", "after": "#445566"})
+    );
+    assert!(updated["changes"].get("name").is_none());
+    assert_eq!(updated["request_id"], "rename-42");
+    assert_eq!(updated["method"], "PUT");
+    assert_eq!(deleted["before"]["colour"], "#445566");
+    assert!(deleted["after"].is_null());
+
+    let (_, entries) = send_json(&app, "GET", "/api/audit?request_id=rename-42", None).await;
+    assert_eq!(entries.as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_audit_batch_delete_and_secrets() {
+    let app = setup_test_app().await;
+    let mut ids = Vec::new();
+    for _ in 0..2 {
+        let (_, project) = send_json(
+            &app,
+            "POST",
+            "/api/projects",
+            Some(&json!({"name": format!("Batch project {}", uuid::Uuid::new_v4())})),
+        )
+        .await;
+        ids.push(project["id"].clone());
+    }
+    let (status, _, _) = send_with_request_id(
+        &app,
+        "DELETE",
+        "/api/projects/batch",
+        Some(&json!(ids)),
+        Some("batch-1"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, entries) = send_json(&app, "GET", "/api/audit?request_id=batch-1", None).await;
+    let mut deleted: Vec<Value> = entries
+        .as_array()
+        .unwrap()
+        .iter()
+        .inspect(|entry| {
+            assert_eq!(entry["action"], "delete");
+            assert!(entry["before"]["name"].is_string());
+        })
+        .map(|entry| entry["record_id"].clone())
+        .collect();
+    deleted.sort_by_key(ToString::to_string);
+    ids.sort_by_key(ToString::to_string);
+    assert_eq!(deleted, ids);
+
+    // Records are read back from the database, so a new key's secret is
+    // not logged
+    let (status, _, created) = send_with_request_id(
+        &app,
+        "POST",
+        "/api/api_keys",
+        Some(&json!({"name": "Logger", "username": "lab-instrument", "role": "viewer", "scopes": ["samples"]})),
+        Some("new-key"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let key = created["key"].as_str().unwrap().to_string();
+    let (_, entries) = send_json(&app, "GET", "/api/audit?request_id=new-key", None).await;
+    assert_eq!(entries[0]["after"]["name"], "Logger");
+    assert!(!entries.to_string().contains(&key));
+}
+
+#[test]
+fn test_diff() {
+    let before = json!({"name": "A", "colour": "#000000", "note": null});
+    let after = json!({"name": "A", "colour": "#ffffff", "note": "New"});
+    assert_eq!(
+        diff(Some(&before), Some(&after)).unwrap(),
+        json!({
+            "colour": {"before": "#000000", "after": "#ffffff"},
+            "note": {"before": null, "after": "New"}
+        })
+    );
+    assert_eq!(
+        diff(None, Some(&json!({"name": "A"}))).unwrap(),
+        json!({"name": {"before": null, "after": "A"}})
+    );
+    assert_eq!(diff(None, None), None);
+}
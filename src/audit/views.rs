@@ -0,0 +1,67 @@
+use super::models::Model as AuditEntry;
+use super::services::{AuditQuery, query_audit_log};
+use crate::common::auth::{RouteAccess, require_role};
+use crate::common::keycloak::authenticate;
+use crate::common::state::AppState;
+use axum::{
+    Json,
+    extract::{Query, State},
+    http::StatusCode,
+    middleware,
+    routing::get,
+};
+use axum_keycloak_auth::PassthroughMode;
+use utoipa::OpenApi;
+use utoipa_axum::router::OpenApiRouter;
+
+/// Query the audit log
+#[utoipa::path(
+    get,
+    path = "",
+    params(AuditQuery),
+    responses(
+        (status = 200, description = "Matching entries, newest first", body = Vec<AuditEntry>),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "audit",
+    summary = "Query the audit log",
+    description = "List the changes made through the API, filtered by route group, record, user, action, request ID and time. Each entry has the record before and after the change and the fields that differ"
+)]
+pub async fn get_audit_log(
+    State(state): State<AppState>,
+    Query(query): Query<AuditQuery>,
+) -> Result<Json<Vec<AuditEntry>>, (StatusCode, String)> {
+    query_audit_log(&state.db, query)
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// Documentation of the routes, which are routed with `route`
+#[derive(OpenApi)]
+#[openapi(paths(get_audit_log))]
+struct AuditApi;
+
+pub fn router(state: &AppState) -> OpenApiRouter {
+    let mut router = OpenApiRouter::new()
+        .route("/", get(get_audit_log))
+        .with_state(state.clone());
+    router.get_openapi_mut().merge(AuditApi::openapi());
+
+    // Only signed-in administrators read the log
+    if let Some(instance) = state.keycloak_auth_instance.clone() {
+        router = router
+            .layer(middleware::from_fn_with_state(
+                RouteAccess::ADMINISTRATION,
+                require_role,
+            ))
+            .layer(middleware::from_fn_with_state(
+                (instance, PassthroughMode::Block),
+                authenticate,
+            ));
+    } else if !state.config.tests_running {
+        println!("Warning: Audit log routes are not protected");
+    }
+
+    router
+}
@@ -1114,6 +1114,7 @@ impl DatabaseSeeder {
     }
 
     #[allow(clippy::missing_errors_doc, clippy::missing_panics_doc)] // Seeding utility function
+    #[allow(clippy::too_many_lines)] // Upload, polling and reprocessing are reported step by step
     pub async fn process_excel_file(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         println!("{} Processing Excel file...", style("[7/7]").bold().dim());
 
@@ -1200,7 +1201,6 @@ impl DatabaseSeeder {
                                     retries += 1;
                                     pb.set_message("Waiting for processing to complete...");
                                     sleep(Duration::from_secs(2)).await;
-                                    continue;
                                 }
                                 _ => {
                                     pb.set_message("Processing timeout or unknown status");
@@ -1215,10 +1215,9 @@ impl DatabaseSeeder {
                                 pb.set_message("Checking processing status...");
                                 sleep(Duration::from_secs(2)).await;
                                 continue;
-                            } else {
-                                pb.finish_with_message("Failed to check processing status");
-                                return Ok(());
                             }
+                            pb.finish_with_message("Failed to check processing status");
+                            return Ok(());
                         }
                     }
                 }
@@ -1251,7 +1250,7 @@ impl DatabaseSeeder {
                     "   This might be expected if tray configuration assignment is needed first"
                 );
             }
-        };
+        }
 
         Ok(())
     }
@@ -1337,7 +1336,7 @@ fn ensure_api_prefix(base_url: &str) -> String {
     if url.ends_with("/api") {
         url.to_string()
     } else {
-        format!("{}/api", url)
+        format!("{url}/api")
     }
 }
 
@@ -1345,7 +1344,7 @@ fn ensure_api_prefix(base_url: &str) -> String {
 async fn get_keycloak_config(base_url: &str) -> Result<KeycloakConfig, Box<dyn std::error::Error>> {
     let client = Client::new();
     let api_base = ensure_api_prefix(base_url);
-    let url = format!("{}/config", api_base);
+    let url = format!("{api_base}/config");
     
     println!("Fetching Keycloak configuration from: {}", style(&url).dim());
     
@@ -1396,7 +1395,7 @@ async fn authenticate_with_keycloak(config: &KeycloakConfig) -> Result<String, B
     
     if !response.status().is_success() {
         let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-        return Err(format!("Authentication failed: {}", error_text).into());
+        return Err(format!("Authentication failed: {error_text}").into());
     }
     
     let token_response: TokenResponse = response.json().await?;
@@ -0,0 +1,5 @@
+pub mod services;
+pub mod views;
+
+#[cfg(test)]
+pub mod tests;
@@ -0,0 +1,279 @@
+//! Changes feed for incremental sync.
+//!
+//! Offline-capable clients and mirrors ask for what changed since they last
+//! synced instead of reading everything again. The feed is read from the
+//! audit log, which every change made through the API goes to: each record
+//! changed after `since` is given once, with its latest change and the
+//! record as that change left it, or without a record when it was deleted.
+//! Pages end on a whole timestamp, and `next_since` is the `since` of the
+//! next page. Users who are not administrators get only the changes to
+//! records they may read.
+
+use crate::audit::models::{AuditAction, Column, Entity as AuditLog, Model as AuditEntry};
+use crate::audit::services::AuditedResource;
+use crate::common::labs::{self, TenantResource};
+use crate::projects::access::{self, ScopedResource};
+use crate::projects::sharing::Requester;
+use axum::http::Method;
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder, QuerySelect,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+pub const DEFAULT_LIMIT: u64 = 500;
+pub const MAX_LIMIT: u64 = 5000;
+
+/// Resources whose records are synced
+const SYNCED: &[AuditedResource] = &[
+    AuditedResource::Assets,
+    AuditedResource::Dilutions,
+    AuditedResource::Experiments,
+    AuditedResource::Locations,
+    AuditedResource::Projects,
+    AuditedResource::Samples,
+    AuditedResource::TrayConfigurations,
+    AuditedResource::Treatments,
+];
+
+#[derive(Clone, Debug, Default, Deserialize, IntoParams)]
+pub struct ChangesQuery {
+    /// Changes after this time; all changes when left out
+    pub since: Option<DateTime<Utc>>,
+    /// Comma-separated resources, such as `samples,experiments`; all by
+    /// default
+    pub resources: Option<String>,
+    /// Log entries read for the page; 500 by default and at most 5000
+    pub limit: Option<u64>,
+}
+
+/// The latest change to a record
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct RecordChange {
+    /// Resource of the record, such as `samples`
+    pub resource: String,
+    pub id: Uuid,
+    pub action: AuditAction,
+    pub changed_at: DateTime<Utc>,
+    /// The record as the change left it, as its route gives it; `null`
+    /// when it was deleted
+    #[schema(value_type = Option<Object>)]
+    pub record: Option<Value>,
+}
+
+/// A page of the changes feed
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct ChangesFeed {
+    /// Changed records, oldest change first
+    pub changes: Vec<RecordChange>,
+    /// `since` to ask for the changes after these
+    pub next_since: Option<DateTime<Utc>>,
+    /// Whether more changes follow
+    pub has_more: bool,
+}
+
+/// Who reads the feed, when not an administrator
+#[derive(Clone, Debug, Default)]
+pub struct Reader {
+    /// Username and Keycloak groups
+    pub member: Option<(String, Vec<String>)>,
+    /// Labs, when labs are on
+    pub labs: Option<Vec<String>>,
+}
+
+impl Reader {
+    /// Whether the REST routes would let the user read a record
+    pub(crate) async fn may_read(
+        &self,
+        db: &DatabaseConnection,
+        resources: (Option<ScopedResource>, TenantResource),
+        id: Uuid,
+    ) -> bool {
+        self.may(db, resources, &Method::GET, id).await
+    }
+
+    /// Whether the REST routes would let the user make a request of the
+    /// method to a record
+    pub(crate) async fn may(
+        &self,
+        db: &DatabaseConnection,
+        (scoped, tenant): (Option<ScopedResource>, TenantResource),
+        method: &Method,
+        id: Uuid,
+    ) -> bool {
+        let path = format!("/{id}");
+        if let Some(labs) = &self.labs
+            && labs::authorize(db, labs, tenant, method, &path, None)
+                .await
+                .is_err()
+        {
+            return false;
+        }
+        match (&self.member, scoped) {
+            (Some((username, groups)), Some(scoped)) => {
+                let user = Requester { username, groups };
+                access::authorize(db, &user, scoped, method, &path, None)
+                    .await
+                    .is_ok()
+            }
+            _ => true,
+        }
+    }
+
+    /// Whether the user may see a change: one to a record they may read,
+    /// or to a record since removed whose owner they may read
+    async fn may_see(
+        &self,
+        db: &DatabaseConnection,
+        resource: AuditedResource,
+        entry: &AuditEntry,
+        id: Uuid,
+    ) -> Result<bool, DbErr> {
+        if self.member.is_none() && self.labs.is_none() {
+            return Ok(true);
+        }
+        // Other resources are only read by administrators
+        let resources = match resource {
+            AuditedResource::Samples => (Some(ScopedResource::Samples), TenantResource::Samples),
+            AuditedResource::Experiments => (
+                Some(ScopedResource::Experiments),
+                TenantResource::Experiments,
+            ),
+            AuditedResource::Assets => (Some(ScopedResource::Assets), TenantResource::Assets),
+            AuditedResource::TrayConfigurations => (None, TenantResource::TrayConfigurations),
+            _ => return Ok(false),
+        };
+        if resource.snapshot(db, id).await?.is_some() {
+            return Ok(self.may_read(db, resources, id).await);
+        }
+        let experiment_id = entry
+            .before
+            .as_ref()
+            .and_then(|before| before.get("experiment_id"))
+            .and_then(Value::as_str)
+            .and_then(|id| Uuid::parse_str(id).ok());
+        Ok(match (resource, experiment_id) {
+            (AuditedResource::Assets, Some(experiment_id)) => {
+                self.may_read(
+                    db,
+                    (
+                        Some(ScopedResource::Experiments),
+                        TenantResource::Experiments,
+                    ),
+                    experiment_id,
+                )
+                .await
+            }
+            _ => false,
+        })
+    }
+}
+
+fn synced_resources(names: Option<&str>) -> Result<Vec<AuditedResource>, DbErr> {
+    let Some(names) = names else {
+        return Ok(SYNCED.to_vec());
+    };
+    names
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            SYNCED
+                .iter()
+                .copied()
+                .find(|resource| resource.name() == name)
+                .ok_or_else(|| {
+                    let known: Vec<&str> = SYNCED.iter().map(|resource| resource.name()).collect();
+                    DbErr::Custom(format!(
+                        "Unknown resource '{name}'; resources are {}",
+                        known.join(", ")
+                    ))
+                })
+        })
+        .collect()
+}
+
+/// A page of the changes after a time, each record's latest change given
+/// once
+pub async fn changes_since(
+    db: &DatabaseConnection,
+    reader: &Reader,
+    query: ChangesQuery,
+) -> Result<ChangesFeed, DbErr> {
+    let resources = synced_resources(query.resources.as_deref())?;
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let mut select = AuditLog::find()
+        .filter(Column::Resource.is_in(resources.iter().map(|resource| resource.name())))
+        .filter(Column::RecordId.is_not_null());
+    if let Some(since) = query.since {
+        select = select.filter(Column::OccurredAt.gt(since));
+    }
+    let mut entries = select
+        .order_by_asc(Column::OccurredAt)
+        .order_by_asc(Column::Id)
+        .limit(limit + 1)
+        .all(db)
+        .await?;
+
+    // A page ends on a whole timestamp, so the next one starts after it,
+    // unless the page is a single timestamp
+    let has_more = entries.len() as u64 > limit;
+    if has_more {
+        let next = entries.pop().map(|entry| entry.occurred_at);
+        if let Some(last) =
+            next.filter(|next| entries.last().map(|entry| entry.occurred_at) == Some(*next))
+            && entries.iter().any(|entry| entry.occurred_at < last)
+        {
+            entries.retain(|entry| entry.occurred_at < last);
+        }
+    }
+    let next_since = entries
+        .last()
+        .map(|entry| entry.occurred_at)
+        .or(query.since);
+
+    // The latest change of each record
+    let mut latest: HashMap<(String, Uuid), AuditEntry> = HashMap::new();
+    for entry in entries {
+        if let Some(id) = entry.record_id {
+            latest.insert((entry.resource.clone(), id), entry);
+        }
+    }
+    let mut latest: Vec<AuditEntry> = latest.into_values().collect();
+    latest.sort_by_key(|entry| (entry.occurred_at, entry.id));
+
+    let mut changes = Vec::with_capacity(latest.len());
+    for entry in latest {
+        let (Some(id), Some(resource)) = (
+            entry.record_id,
+            resources
+                .iter()
+                .copied()
+                .find(|resource| resource.name() == entry.resource),
+        ) else {
+            continue;
+        };
+        if !reader.may_see(db, resource, &entry, id).await? {
+            continue;
+        }
+        changes.push(RecordChange {
+            resource: entry.resource,
+            id,
+            action: entry.action,
+            changed_at: entry.occurred_at,
+            record: match entry.action {
+                AuditAction::Delete => None,
+                AuditAction::Create | AuditAction::Update => entry.after,
+            },
+        });
+    }
+    Ok(ChangesFeed {
+        changes,
+        next_since,
+        has_more,
+    })
+}
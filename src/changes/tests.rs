@@ -0,0 +1,91 @@
+use crate::config::test_helpers::{create, send_json, setup_test_app};
+use axum::http::StatusCode;
+use serde_json::{Value, json};
+
+/// Changes after a `next_since` of the feed
+fn since(feed: &Value) -> String {
+    feed["next_since"].as_str().unwrap().replace('+', "%2B")
+}
+
+#[tokio::test]
+async fn test_changes_feed() {
+    let app = setup_test_app().await;
+    let project_id = create(&app, "/api/projects", &json!({"name": "Synced project"})).await;
+    let location_id = create(
+        &app,
+        "/api/locations",
+        &json!({"name": "Synced station", "project_id": project_id}),
+    )
+    .await;
+    let sample = json!({"name": "Synced filter", "type": "filter", "location_id": location_id});
+    let sample_id = create(&app, "/api/samples", &sample).await;
+
+    let (status, feed) = send_json(&app, "GET", "/api/changes", None).await;
+    assert_eq!(status, StatusCode::OK, "{feed}");
+    assert_eq!(feed["has_more"], false);
+    let changes = feed["changes"].as_array().unwrap();
+    assert_eq!(changes.len(), 3, "{feed}");
+    assert_eq!(changes[0]["resource"], "projects");
+    assert_eq!(changes[2]["id"], sample_id);
+    assert_eq!(changes[2]["action"], "create");
+    assert_eq!(changes[2]["record"]["name"], "Synced filter");
+
+    // Each record changed since is given once, with its latest change
+    let synced = since(&feed);
+    for remarks in ["first", "second"] {
+        let (status, _) = send_json(
+            &app,
+            "PATCH",
+            &format!("/api/samples/{sample_id}"),
+            Some(&json!({"remarks": remarks})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+    }
+    let removed_id = create(
+        &app,
+        "/api/samples",
+        &json!({"name": "Removed filter", "type": "filter", "location_id": location_id}),
+    )
+    .await;
+    let (status, _) = send_json(&app, "DELETE", &format!("/api/samples/{removed_id}"), None).await;
+    assert!(status.is_success());
+
+    let (_, feed) = send_json(&app, "GET", &format!("/api/changes?since={synced}"), None).await;
+    let changes = feed["changes"].as_array().unwrap();
+    assert_eq!(changes.len(), 2, "{feed}");
+    assert_eq!(changes[0]["id"], sample_id);
+    assert_eq!(changes[0]["action"], "update");
+    assert_eq!(changes[0]["record"]["remarks"], "second");
+    assert_eq!(changes[1]["id"], removed_id);
+    assert_eq!(changes[1]["action"], "delete");
+    assert_eq!(changes[1]["record"], Value::Null);
+
+    // Nothing changed since the last page
+    let (_, feed) = send_json(
+        &app,
+        "GET",
+        &format!("/api/changes?since={}", since(&feed)),
+        None,
+    )
+    .await;
+    assert_eq!(feed["changes"], json!([]));
+
+    // Pages follow each other
+    let (_, page) = send_json(&app, "GET", "/api/changes?limit=2", None).await;
+    assert_eq!(page["has_more"], true);
+    assert_eq!(page["changes"].as_array().unwrap().len(), 2, "{page}");
+    let (_, page) = send_json(
+        &app,
+        "GET",
+        &format!("/api/changes?limit=2&since={}", since(&page)),
+        None,
+    )
+    .await;
+    assert_eq!(page["changes"][0]["resource"], "samples", "{page}");
+
+    let (_, feed) = send_json(&app, "GET", "/api/changes?resources=locations", None).await;
+    assert_eq!(feed["changes"].as_array().unwrap().len(), 1, "{feed}");
+    let (status, _) = send_json(&app, "GET", "/api/changes?resources=users", None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
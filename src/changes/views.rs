@@ -0,0 +1,94 @@
+use super::services::{ChangesFeed, ChangesQuery, Reader, changes_since};
+use crate::api_keys::services::accept_api_keys;
+use crate::common::auth::{Role, RouteAccess, require_role};
+use crate::common::keycloak::authenticate;
+use crate::common::labs::Labs;
+use crate::common::redaction::redact_for_viewers;
+use crate::common::state::AppState;
+use crate::projects::sharing::token_groups;
+use axum::{
+    Extension, Json,
+    extract::{Query, State},
+    http::StatusCode,
+    middleware,
+};
+use axum_keycloak_auth::{PassthroughMode, decode::KeycloakToken};
+use sea_orm::DbErr;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+/// Who makes a request, when not an administrator
+pub fn reader(
+    token: Option<Extension<KeycloakToken<Role>>>,
+    labs: Option<Extension<Labs>>,
+) -> Reader {
+    let mut reader = Reader::default();
+    if let Some(Extension(token)) = token
+        && !token
+            .roles
+            .iter()
+            .any(|role| *role.role() == Role::Administrator)
+    {
+        let groups = token_groups(&token);
+        reader.member = Some((token.extra.profile.preferred_username, groups));
+        reader.labs = labs.map(|Extension(Labs(labs))| labs);
+    }
+    reader
+}
+
+/// Get the records changed since a time
+#[utoipa::path(
+    get,
+    path = "",
+    params(ChangesQuery),
+    responses(
+        (status = 200, description = "The latest change of each record changed since the time, oldest first", body = ChangesFeed),
+        (status = 400, description = "Unknown resource"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "changes",
+    summary = "Get the changes feed",
+    description = "List the records created, updated or deleted after `since` across experiments, samples, assets and the other resources, each once with its latest change and the record as that change left it. Ask again with `next_since` while `has_more` is true. Users who are not administrators get only the changes to records they may read"
+)]
+pub async fn get_changes(
+    State(state): State<AppState>,
+    token: Option<Extension<KeycloakToken<Role>>>,
+    labs: Option<Extension<Labs>>,
+    Query(query): Query<ChangesQuery>,
+) -> Result<Json<ChangesFeed>, (StatusCode, String)> {
+    changes_since(&state.db, &reader(token, labs), query)
+        .await
+        .map(Json)
+        .map_err(|e| match e {
+            DbErr::Custom(message) => (StatusCode::BAD_REQUEST, message),
+            e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })
+}
+
+pub fn router(state: &AppState) -> OpenApiRouter {
+    let mut router = OpenApiRouter::new()
+        .routes(routes!(get_changes))
+        .with_state(state.clone());
+
+    if let Some(instance) = state.keycloak_auth_instance.clone() {
+        // Viewers sync the records they may read, as the REST routes
+        // limit them
+        router = router
+            .layer(middleware::from_fn_with_state(
+                RouteAccess::QUERIES,
+                require_role,
+            ))
+            .layer(middleware::from_fn(redact_for_viewers))
+            .layer(middleware::from_fn_with_state(
+                (state.db.clone(), "changes"),
+                accept_api_keys,
+            ))
+            .layer(middleware::from_fn_with_state(
+                (instance, PassthroughMode::Pass),
+                authenticate,
+            ));
+    } else if !state.config.tests_running {
+        println!("Warning: Changes feed routes are not protected");
+    }
+
+    router
+}
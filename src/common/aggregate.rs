@@ -0,0 +1,328 @@
+//! Counts and aggregates of a resource's records.
+//!
+//! Dashboards chart experiments per month or samples per location without
+//! reading whole lists. `GET /count` counts the records a list would give,
+//! and `GET /aggregate` groups them by a field, or by the day, month or year
+//! of a date, counting each group with the minimum, maximum and mean of
+//! another field. Both take the list's `filter`, and are narrowed as the
+//! list is to the records the user may read.
+
+use crate::projects::access::{ListScope, in_list_scope};
+use axum::{
+    Extension, Json,
+    extract::{Query, State},
+    http::StatusCode,
+};
+use chrono::{DateTime, Utc};
+use crudcrate::CRUDResource;
+use rust_decimal::{Decimal, prelude::ToPrimitive};
+use sea_orm::{
+    ColumnTrait, ColumnType, ConnectionTrait, DatabaseConnection, DbBackend, DbErr, EntityTrait,
+    PaginatorTrait, QueryFilter, QueryOrder, QueryResult, QuerySelect, QueryTrait,
+    sea_query::{Alias, Expr, Func, SimpleExpr},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+/// Most groups an aggregate gives
+const MAX_GROUPS: u64 = 1000;
+
+#[derive(Clone, Debug, Default, Deserialize, IntoParams)]
+pub struct CountQuery {
+    /// JSON filter, as for lists
+    pub filter: Option<String>,
+}
+
+/// Number of records matching a filter
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct RecordCount {
+    pub count: u64,
+}
+
+/// Period dates are grouped by
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DateInterval {
+    Day,
+    Month,
+    Year,
+}
+
+impl DateInterval {
+    /// Dates of the period as `strftime` formats them
+    fn sqlite_format(self) -> &'static str {
+        match self {
+            Self::Day => "%Y-%m-%d",
+            Self::Month => "%Y-%m",
+            Self::Year => "%Y",
+        }
+    }
+
+    /// Dates of the period as `to_char` formats them
+    fn postgres_format(self) -> &'static str {
+        match self {
+            Self::Day => "YYYY-MM-DD",
+            Self::Month => "YYYY-MM",
+            Self::Year => "YYYY",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, IntoParams)]
+pub struct AggregateQuery {
+    /// JSON filter, as for lists
+    pub filter: Option<String>,
+    /// Field to group the records by; without it, all records are one group
+    pub group_by: Option<String>,
+    /// Group a date field by its day, month or year (`2025-03`)
+    #[param(value_type = Option<String>)]
+    pub interval: Option<DateInterval>,
+    /// Field to give the minimum, maximum and, for numbers, the mean of
+    pub field: Option<String>,
+}
+
+/// A group of records and their aggregates
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct AggregateGroup {
+    /// Value of the grouping field, or its period; `null` for records
+    /// without one, or when not grouped
+    #[schema(value_type = Object)]
+    pub key: Value,
+    pub count: i64,
+    #[schema(value_type = Object)]
+    pub min: Value,
+    #[schema(value_type = Object)]
+    pub max: Value,
+    pub avg: Option<f64>,
+}
+
+fn query_error(e: DbErr) -> (StatusCode, String) {
+    match e {
+        DbErr::Custom(message) => (StatusCode::BAD_REQUEST, message),
+        e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+/// Column of a field the resource lists can be filtered or sorted by
+fn column<R: CRUDResource>(field: &str) -> Result<R::ColumnType, DbErr> {
+    R::filterable_columns()
+        .into_iter()
+        .chain(R::sortable_columns())
+        .find(|(name, _)| *name == field)
+        .map(|(_, column)| column)
+        .ok_or_else(|| {
+            DbErr::Custom(format!(
+                "{} cannot be aggregated by {field}",
+                R::RESOURCE_NAME_PLURAL
+            ))
+        })
+}
+
+fn is_date(column: &impl ColumnTrait) -> bool {
+    matches!(
+        column.def().get_column_type(),
+        ColumnType::Date
+            | ColumnType::DateTime
+            | ColumnType::Timestamp
+            | ColumnType::TimestampWithTimeZone
+    )
+}
+
+/// Types aggregated values are read back as
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ValueKind {
+    Uuid,
+    Integer,
+    Number,
+    Date,
+    Boolean,
+    Text,
+}
+
+impl ValueKind {
+    fn of(column: &impl ColumnTrait) -> Self {
+        match column.def().get_column_type() {
+            ColumnType::Uuid => Self::Uuid,
+            ColumnType::TinyInteger
+            | ColumnType::SmallInteger
+            | ColumnType::Integer
+            | ColumnType::BigInteger => Self::Integer,
+            ColumnType::Float
+            | ColumnType::Double
+            | ColumnType::Decimal(_)
+            | ColumnType::Money(_) => Self::Number,
+            ColumnType::DateTime | ColumnType::Timestamp | ColumnType::TimestampWithTimeZone => {
+                Self::Date
+            }
+            ColumnType::Boolean => Self::Boolean,
+            _ => Self::Text,
+        }
+    }
+
+    /// A value of the column, as text on `PostgreSQL` when it is of a type
+    /// of its own, such as an enum
+    fn select(self, backend: DbBackend, value: SimpleExpr) -> SimpleExpr {
+        if self == Self::Text && backend == DbBackend::Postgres {
+            value.cast_as(Alias::new("TEXT"))
+        } else {
+            value
+        }
+    }
+
+    /// A value read back as JSON
+    fn read(self, row: &QueryResult, name: &str) -> Result<Value, DbErr> {
+        Ok(match self {
+            Self::Uuid => json!(row.try_get::<Option<Uuid>>("", name)?),
+            Self::Integer => json!(row.try_get::<Option<i64>>("", name)?),
+            Self::Number => json!(
+                row.try_get::<Option<Decimal>>("", name)?
+                    .and_then(|number| number.to_f64())
+            ),
+            Self::Date => json!(row.try_get::<Option<DateTime<Utc>>>("", name)?),
+            Self::Boolean => json!(row.try_get::<Option<bool>>("", name)?),
+            Self::Text => json!(row.try_get::<Option<String>>("", name)?),
+        })
+    }
+}
+
+/// What records are grouped by: a field, or the period of a date field,
+/// with the type it is read back as
+fn group_key<R: CRUDResource>(
+    backend: DbBackend,
+    field: &str,
+    interval: Option<DateInterval>,
+) -> Result<(SimpleExpr, ValueKind), DbErr> {
+    let column = column::<R>(field)?;
+    let Some(interval) = interval else {
+        let kind = ValueKind::of(&column);
+        return Ok((kind.select(backend, Expr::col(column).into()), kind));
+    };
+    if !is_date(&column) {
+        return Err(DbErr::Custom(format!(
+            "{field} is not a date, so cannot be grouped by {interval:?}"
+        )));
+    }
+    let period = match backend {
+        DbBackend::Postgres => Func::cust(Alias::new("to_char"))
+            .arg(Expr::col(column))
+            .arg(interval.postgres_format()),
+        _ => Func::cust(Alias::new("strftime"))
+            .arg(interval.sqlite_format())
+            .arg(Expr::col(column)),
+    };
+    Ok((period.into(), ValueKind::Text))
+}
+
+/// Handler for `GET /count` of a resource's router
+pub async fn count_handler<R: CRUDResource>(
+    State(db): State<DatabaseConnection>,
+    scope: Option<Extension<ListScope>>,
+    Query(query): Query<CountQuery>,
+) -> Result<Json<RecordCount>, (StatusCode, String)> {
+    let condition = in_list_scope(
+        crudcrate::filter::apply_filters::<R>(
+            query.filter,
+            &R::filterable_columns(),
+            db.get_database_backend(),
+        ),
+        scope.as_deref(),
+    );
+    R::EntityType::find()
+        .filter(condition)
+        .count(&db)
+        .await
+        .map(|count| Json(RecordCount { count }))
+        .map_err(query_error)
+}
+
+/// Groups of records and their aggregates, largest first
+pub async fn aggregate<R: CRUDResource>(
+    db: &DatabaseConnection,
+    query: AggregateQuery,
+    scope: Option<&ListScope>,
+) -> Result<Vec<AggregateGroup>, DbErr> {
+    let backend = db.get_database_backend();
+    let condition = in_list_scope(
+        crudcrate::filter::apply_filters::<R>(query.filter, &R::filterable_columns(), backend),
+        scope,
+    );
+    let mut select = R::EntityType::find()
+        .select_only()
+        .column_as(Expr::col(R::ID_COLUMN).count(), "count")
+        .filter(condition);
+
+    let mut key_kind = None;
+    match (&query.group_by, query.interval) {
+        (Some(field), interval) => {
+            let (key, kind) = group_key::<R>(backend, field, interval)?;
+            key_kind = Some(kind);
+            select = select
+                .column_as(key.clone(), "key")
+                .group_by(key)
+                .order_by_desc(Expr::col(Alias::new("count")))
+                .order_by_asc(Expr::col(Alias::new("key")));
+        }
+        (None, Some(_)) => {
+            return Err(DbErr::Custom(
+                "An interval needs a date field to group_by".to_string(),
+            ));
+        }
+        (None, None) => {}
+    }
+    let mut field_kind = None;
+    if let Some(field) = &query.field {
+        let column = column::<R>(field)?;
+        let kind = ValueKind::of(&column);
+        field_kind = Some(kind);
+        select = select
+            .column_as(kind.select(backend, Expr::col(column).min()), "min")
+            .column_as(kind.select(backend, Expr::col(column).max()), "max");
+        if kind == ValueKind::Integer || kind == ValueKind::Number {
+            let real = match backend {
+                DbBackend::Postgres => "DOUBLE PRECISION",
+                _ => "REAL",
+            };
+            select = select.column_as(
+                SimpleExpr::from(Func::avg(Expr::col(column))).cast_as(Alias::new(real)),
+                "avg",
+            );
+        }
+    }
+
+    let read = |kind: Option<ValueKind>, row: &QueryResult, name: &str| match kind {
+        Some(kind) => kind.read(row, name),
+        None => Ok(Value::Null),
+    };
+    let rows = db
+        .query_all(select.limit(MAX_GROUPS).build(backend))
+        .await?;
+    rows.iter()
+        .map(|row| {
+            Ok(AggregateGroup {
+                key: read(key_kind, row, "key")?,
+                count: row.try_get("", "count")?,
+                min: read(field_kind, row, "min")?,
+                max: read(field_kind, row, "max")?,
+                avg: if field_kind.is_some() {
+                    row.try_get("", "avg").ok().flatten()
+                } else {
+                    None
+                },
+            })
+        })
+        .collect()
+}
+
+/// Handler for `GET /aggregate` of a resource's router
+pub async fn aggregate_handler<R: CRUDResource>(
+    State(db): State<DatabaseConnection>,
+    scope: Option<Extension<ListScope>>,
+    Query(query): Query<AggregateQuery>,
+) -> Result<Json<Vec<AggregateGroup>>, (StatusCode, String)> {
+    aggregate::<R>(&db, query, scope.as_deref())
+        .await
+        .map(Json)
+        .map_err(query_error)
+}
@@ -1,14 +1,36 @@
+//! Roles of Keycloak users and the access they give.
+//!
+//! Administrators can do anything. Editors can read and change the records
+//! of their projects and viewers can only read them. Each router states the
+//! role it needs for reading and for changes with a `RouteAccess`, enforced
+//! by `require_role` behind the Keycloak layer.
+
+use crate::config::Config;
+use axum::{
+    Extension,
+    extract::{Request, State},
+    http::{Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use axum_keycloak_auth::decode::KeycloakToken;
+use std::sync::{PoisonError, RwLock};
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum Role {
     Administrator,
+    Editor,
+    Viewer,
     Unknown(String),
 }
 impl axum_keycloak_auth::role::Role for Role {}
 impl std::fmt::Display for Role {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let config = crate::config::Config::from_env();
+        let [admin, editor, viewer] = role_names();
         match self {
-            Role::Administrator => f.write_str(config.admin_role.as_str()),
+            Role::Administrator => f.write_str(&admin),
+            Role::Editor => f.write_str(&editor),
+            Role::Viewer => f.write_str(&viewer),
             Role::Unknown(unknown) => f.write_fmt(format_args!("Unknown role: {unknown}")),
         }
     }
@@ -16,16 +38,153 @@ impl std::fmt::Display for Role {
 
 impl From<String> for Role {
     fn from(value: String) -> Self {
-        let config = crate::config::Config::from_env();
-        let admin_role = config.admin_role.as_str();
-        if value == admin_role {
+        let [admin, editor, viewer] = role_names();
+        if value == admin {
             Role::Administrator
+        } else if value == editor {
+            Role::Editor
+        } else if value == viewer {
+            Role::Viewer
         } else {
             Role::Unknown(value)
         }
     }
 }
 
+/// Keycloak names of the administrator, editor and viewer roles
+static ROLE_NAMES: RwLock<Option<[String; 3]>> = RwLock::new(None);
+
+/// Use the role names of the configuration
+pub fn configure(config: &Config) {
+    *ROLE_NAMES.write().unwrap_or_else(PoisonError::into_inner) = Some([
+        config.admin_role.clone(),
+        config.editor_role.clone(),
+        config.viewer_role.clone(),
+    ]);
+}
+
+/// Names of the roles as configured, or as the environment gives them
+/// before then
+fn role_names() -> [String; 3] {
+    ROLE_NAMES
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+        .unwrap_or_else(|| {
+            let config = Config::from_env();
+            [config.admin_role, config.editor_role, config.viewer_role]
+        })
+}
+
+impl Role {
+    /// Whether the role gives what `required` does
+    pub fn grants(&self, required: &Role) -> bool {
+        match self {
+            Role::Administrator => true,
+            Role::Editor => matches!(required, Role::Editor | Role::Viewer),
+            Role::Viewer => *required == Role::Viewer,
+            Role::Unknown(_) => false,
+        }
+    }
+
+    /// Name of the role in Keycloak
+    pub fn name(&self) -> String {
+        match self {
+            Role::Unknown(name) => name.clone(),
+            role => role.to_string(),
+        }
+    }
+
+    fn describe(&self) -> &str {
+        match self {
+            Role::Administrator => "administrator",
+            Role::Editor => "editor",
+            Role::Viewer => "viewer",
+            Role::Unknown(unknown) => unknown,
+        }
+    }
+}
+
+tokio::task_local! {
+    static CURRENT_USER: String;
+}
+
+/// Run a request on behalf of a user, whom records it creates name as their
+/// creator
+pub async fn as_user<F: Future>(username: String, future: F) -> F::Output {
+    CURRENT_USER.scope(username, future).await
+}
+
+/// Username of the user a request is run on behalf of, if any
+pub fn current_username() -> Option<String> {
+    CURRENT_USER.try_with(Clone::clone).ok()
+}
+
+/// Roles a group of routes needs for reading and for changes
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RouteAccess {
+    pub read: Role,
+    pub write: Role,
+}
+
+impl RouteAccess {
+    /// Records kept in projects, which viewers read and editors change
+    pub const PROJECT_RECORDS: Self = Self {
+        read: Role::Viewer,
+        write: Role::Editor,
+    };
+    /// Setup shared by all projects, which only administrators change
+    pub const SHARED: Self = Self {
+        read: Role::Viewer,
+        write: Role::Administrator,
+    };
+    /// Routes not scoped to projects, left to administrators
+    pub const ADMINISTRATION: Self = Self {
+        read: Role::Administrator,
+        write: Role::Administrator,
+    };
+    /// Queries posted only to read, such as GraphQL's
+    pub const QUERIES: Self = Self {
+        read: Role::Viewer,
+        write: Role::Viewer,
+    };
+
+    /// Check that one of the user's roles allows the request
+    pub fn check(&self, method: &Method, roles: &[Role]) -> Result<(), (StatusCode, String)> {
+        let reading = matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS);
+        let required = if reading { &self.read } else { &self.write };
+        if roles.iter().any(|role| role.grants(required)) {
+            Ok(())
+        } else {
+            Err((
+                StatusCode::FORBIDDEN,
+                format!(
+                    "{} needs the {} role",
+                    if reading { "Reading" } else { "Making changes" },
+                    required.describe()
+                ),
+            ))
+        }
+    }
+}
+
+/// Middleware rejecting requests the user's roles do not allow. Requests
+/// without a token, when authentication is off, pass unchanged.
+pub async fn require_role(
+    State(access): State<RouteAccess>,
+    token: Option<Extension<KeycloakToken<Role>>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if let Some(Extension(token)) = token {
+        let roles: Vec<Role> = token.roles.iter().map(|role| role.role().clone()).collect();
+        if let Err(rejection) = access.check(request.method(), &roles) {
+            return rejection.into_response();
+        }
+    }
+    next.run(request).await
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -85,11 +244,11 @@ mod tests {
 
         match admin {
             Role::Administrator => (),
-            Role::Unknown(_) => panic!("Expected Administrator"),
+            Role::Editor | Role::Viewer | Role::Unknown(_) => panic!("Expected Administrator"),
         }
 
         match unknown {
-            Role::Administrator => panic!("Expected Unknown"),
+            Role::Administrator | Role::Editor | Role::Viewer => panic!("Expected Unknown"),
             Role::Unknown(value) => assert_eq!(value, "test"),
         }
     }
@@ -107,4 +266,42 @@ mod tests {
         // Test that we can create different role variants
         let _unknown = Role::Unknown("test_role".to_string());
     }
+
+    #[test]
+    fn test_route_access() {
+        let viewer = [Role::Viewer];
+        let editor = [Role::Unknown("offline_access".to_string()), Role::Editor];
+        let administrator = [Role::Administrator];
+        let records = RouteAccess::PROJECT_RECORDS;
+
+        for method in [Method::GET, Method::HEAD] {
+            assert!(records.check(&method, &viewer).is_ok());
+            assert!(records.check(&method, &editor).is_ok());
+        }
+        for method in [Method::POST, Method::PUT, Method::PATCH, Method::DELETE] {
+            let (status, message) = records.check(&method, &viewer).unwrap_err();
+            assert_eq!(status, StatusCode::FORBIDDEN);
+            assert_eq!(message, "Making changes needs the editor role");
+            assert!(records.check(&method, &editor).is_ok());
+            assert!(records.check(&method, &administrator).is_ok());
+        }
+        assert!(records.check(&Method::GET, &[]).is_err());
+        assert!(
+            records
+                .check(&Method::GET, &[Role::Unknown("spice-user".to_string())])
+                .is_err()
+        );
+
+        assert!(RouteAccess::SHARED.check(&Method::GET, &viewer).is_ok());
+        assert!(RouteAccess::SHARED.check(&Method::PUT, &editor).is_err());
+        assert!(
+            RouteAccess::SHARED
+                .check(&Method::PUT, &administrator)
+                .is_ok()
+        );
+        let (_, message) = RouteAccess::ADMINISTRATION
+            .check(&Method::GET, &editor)
+            .unwrap_err();
+        assert_eq!(message, "Reading needs the administrator role");
+    }
 }
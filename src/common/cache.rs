@@ -0,0 +1,179 @@
+//! In-process cache of the records dashboards poll.
+//!
+//! Reading a tray configuration, project or experiment loads its trays and
+//! probes, members, or results and assets, and dashboards read the same ones
+//! every few seconds. Their responses are kept, by path, with the
+//! `last_updated` of the record they were built from, and served again while
+//! the record's `last_updated` is unchanged, which takes a single query. Any
+//! change made through the API, and the end of background processing, drops
+//! them all, as records nested in a response change without their parent
+//! being updated. Responses are kept at most `RESPONSE_CACHE_SECONDS`, and
+//! the cache is off when it is unset.
+
+use crate::common::etag::last_updated;
+use crate::config::Config;
+use axum::{
+    body::{Body, Bytes, to_bytes},
+    extract::{Request, State},
+    http::{HeaderMap, Method, StatusCode, header::CONTENT_TYPE},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use crudcrate::CRUDResource;
+use sea_orm::DatabaseConnection;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Responses kept at most; the oldest are dropped for new ones
+const MAX_ENTRIES: usize = 1000;
+
+#[derive(Clone, Debug)]
+struct CachedResponse {
+    last_updated: DateTime<Utc>,
+    kept_at: Instant,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    responses: HashMap<String, CachedResponse>,
+    /// Changes made since the API started, so responses built while a
+    /// change was made are not kept
+    changes: u64,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ResponseCache {
+    /// How long responses are kept; off when `None`
+    lifetime: Option<Duration>,
+    entries: Arc<Mutex<Entries>>,
+}
+
+impl ResponseCache {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            lifetime: config.response_cache_seconds.map(Duration::from_secs),
+            entries: Arc::default(),
+        }
+    }
+
+    fn entries(&self) -> std::sync::MutexGuard<'_, Entries> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Drop every response, after a change
+    pub fn clear(&self) {
+        let mut entries = self.entries();
+        entries.responses.clear();
+        entries.changes += 1;
+    }
+
+    /// The response kept for a path, when built from the record as it is
+    fn get(&self, key: &str, last_updated: DateTime<Utc>) -> Option<Response> {
+        let lifetime = self.lifetime?;
+        let entries = self.entries();
+        let cached = entries.responses.get(key)?;
+        if cached.last_updated != last_updated || cached.kept_at.elapsed() > lifetime {
+            return None;
+        }
+        let mut response = Response::new(Body::from(cached.body.clone()));
+        *response.headers_mut() = cached.headers.clone();
+        Some(response)
+    }
+
+    /// Keep a response, unless a change was made since it was started
+    fn put(&self, key: String, changes: u64, cached: CachedResponse) {
+        let Some(lifetime) = self.lifetime else {
+            return;
+        };
+        let mut entries = self.entries();
+        if entries.changes != changes {
+            return;
+        }
+        entries
+            .responses
+            .retain(|_, kept| kept.kept_at.elapsed() <= lifetime);
+        if entries.responses.len() >= MAX_ENTRIES
+            && let Some(oldest) = entries
+                .responses
+                .iter()
+                .min_by_key(|(_, kept)| kept.kept_at)
+                .map(|(key, _)| key.clone())
+        {
+            entries.responses.remove(&oldest);
+        }
+        entries.responses.insert(key, cached);
+    }
+}
+
+/// Middleware of a resource's router, innermost, serving the records it
+/// reads from the cache while they are unchanged
+pub async fn cache_records<R: CRUDResource>(
+    State((db, cache)): State<(DatabaseConnection, ResponseCache)>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let record = (request.method() == Method::GET && cache.lifetime.is_some())
+        .then(|| request.uri().path().trim_matches('/').parse::<Uuid>().ok())
+        .flatten();
+    let Some(id) = record else {
+        return next.run(request).await;
+    };
+
+    let key = format!("{}{}", R::RESOURCE_NAME_PLURAL, request.uri());
+    let changes = cache.entries().changes;
+    let last_updated = match last_updated::<R>(&db, id).await {
+        Ok(Some(last_updated)) => last_updated,
+        Ok(None) => return next.run(request).await,
+        Err(response) => return response,
+    };
+    if let Some(response) = cache.get(&key, last_updated) {
+        return response;
+    }
+
+    let response = next.run(request).await;
+    let is_json = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+    if response.status() != StatusCode::OK || !is_json {
+        return response;
+    }
+    let (parts, body) = response.into_parts();
+    let Ok(body) = to_bytes(body, usize::MAX).await else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    cache.put(
+        key,
+        changes,
+        CachedResponse {
+            last_updated,
+            kept_at: Instant::now(),
+            headers: parts.headers.clone(),
+            body: body.clone(),
+        },
+    );
+    Response::from_parts(parts, Body::from(body))
+}
+
+/// Middleware of the whole API dropping the kept responses after each change
+pub async fn clear_on_changes(
+    State(cache): State<ResponseCache>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let reads = matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS
+    );
+    let response = next.run(request).await;
+    if !reads {
+        cache.clear();
+    }
+    response
+}
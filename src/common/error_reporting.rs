@@ -0,0 +1,87 @@
+//! Reporting of panics and server errors to Sentry, when `SENTRY_DSN` is set.
+//!
+//! Panics are reported by Sentry's panic handler. Responses with a 5xx status
+//! are reported by middleware, with the request's method, path and ID and the
+//! start of the response, which holds the error. Query strings and headers are
+//! left out, as they can hold download tokens and credentials. Events are
+//! tagged with the release and the deployment they come from.
+
+use crate::audit::services::REQUEST_ID_HEADER;
+use crate::config::Config;
+use axum::{
+    body::{Body, to_bytes},
+    extract::Request,
+    middleware::Next,
+    response::Response,
+};
+use uuid::Uuid;
+
+/// Bytes of an error response included in its report
+const MAX_DETAIL_BYTES: usize = 2000;
+
+/// Start reporting, for as long as the guard is kept
+pub fn init(config: &Config) -> Option<sentry::ClientInitGuard> {
+    let dsn = config.sentry_dsn.as_deref()?.parse().ok()?;
+    let mut options = sentry::ClientOptions::new();
+    options.dsn = Some(dsn);
+    options.release = sentry::release_name!();
+    options.environment = Some(config.deployment.clone().into());
+    Some(sentry::init(options))
+}
+
+/// Path with its IDs replaced, so errors of the same route are grouped
+fn route(path: &str) -> String {
+    path.split('/')
+        .map(|segment| {
+            if Uuid::parse_str(segment).is_ok() {
+                "{id}"
+            } else {
+                segment
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Middleware of the whole API reporting the responses with a 5xx status
+pub async fn report_server_errors(request: Request, next: Next) -> Response {
+    if sentry::Hub::current().client().is_none() {
+        return next.run(request).await;
+    }
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+
+    let response = next.run(request).await;
+    let status = response.status();
+    if !status.is_server_error() {
+        return response;
+    }
+    // Error responses are short messages
+    let (parts, body) = response.into_parts();
+    let body = to_bytes(body, usize::MAX).await.unwrap_or_default();
+    let detail = String::from_utf8_lossy(&body[..body.len().min(MAX_DETAIL_BYTES)]);
+
+    sentry::with_scope(
+        |scope| {
+            scope.set_tag("http.method", &method);
+            scope.set_tag("http.status_code", status.as_u16());
+            scope.set_tag("route", route(&path));
+            if let Some(request_id) = &request_id {
+                scope.set_tag("request_id", request_id);
+            }
+            scope.set_extra("path", path.clone().into());
+        },
+        || {
+            sentry::capture_message(
+                &format!("{status} on {method} {}: {detail}", route(&path)),
+                sentry::Level::Error,
+            )
+        },
+    );
+    Response::from_parts(parts, Body::from(body))
+}
@@ -0,0 +1,186 @@
+//! Optimistic concurrency with entity tags.
+//!
+//! A record is read with an `ETag` derived from its `last_updated`, which
+//! every update moves on. Changing it with `PUT` or `PATCH` takes that tag in
+//! `If-Match`: when the record was updated since it was read, the change is
+//! refused with 412 Precondition Failed instead of overwriting the other
+//! update. Unless `REQUIRE_IF_MATCH` is off, a change without `If-Match` is
+//! refused with 428 Precondition Required. `If-Match: *` only asks for the
+//! record to exist. A read with the current tag in `If-None-Match` gets 304
+//! Not Modified.
+
+use crate::projects::access::segments;
+use axum::{
+    body::{Body, to_bytes},
+    extract::{Request, State},
+    http::{
+        HeaderMap, HeaderValue, Method, StatusCode,
+        header::{CONTENT_LENGTH, CONTENT_TYPE, ETAG, IF_MATCH, IF_NONE_MATCH},
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use crudcrate::CRUDResource;
+use sea_orm::{DatabaseConnection, EntityTrait, QuerySelect};
+use serde_json::Value;
+use uuid::Uuid;
+
+/// Tag of a record last updated at a time
+pub fn entity_tag(last_updated: DateTime<Utc>) -> String {
+    let nanos = last_updated
+        .timestamp_nanos_opt()
+        .unwrap_or_else(|| last_updated.timestamp_micros() * 1000);
+    format!("\"{nanos:x}\"")
+}
+
+/// Whether a list of tags, as given in `If-Match` or `If-None-Match`, holds
+/// a tag. Weak tags never match.
+fn tags_match(header: &HeaderValue, tag: &str) -> bool {
+    header.to_str().is_ok_and(|tags| {
+        tags.split(',')
+            .map(str::trim)
+            .any(|listed| listed == "*" || listed == tag)
+    })
+}
+
+/// When a record was last updated, or `None` when it does not exist
+pub async fn last_updated<R: CRUDResource>(
+    db: &DatabaseConnection,
+    id: Uuid,
+) -> Result<Option<DateTime<Utc>>, Response> {
+    let Some((_, column)) = R::sortable_columns()
+        .into_iter()
+        .find(|(name, _)| *name == "last_updated")
+    else {
+        return Ok(None);
+    };
+    R::EntityType::find_by_id(id)
+        .select_only()
+        .column(column)
+        .into_tuple()
+        .one(db)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response())
+}
+
+/// Check the `If-Match` of a change to a record against its current tag
+async fn check_if_match<R: CRUDResource>(
+    db: &DatabaseConnection,
+    id: Uuid,
+    headers: &HeaderMap,
+    required: bool,
+) -> Result<(), Response> {
+    let Some(if_match) = headers.get(IF_MATCH) else {
+        if required {
+            return Err((
+                StatusCode::PRECONDITION_REQUIRED,
+                format!(
+                    "Send the ETag of the {} read last in If-Match to change it",
+                    R::RESOURCE_NAME_SINGULAR
+                ),
+            )
+                .into_response());
+        }
+        return Ok(());
+    };
+    // A record that does not exist is left for the update to answer
+    let Some(last_updated) = last_updated::<R>(db, id).await? else {
+        return Ok(());
+    };
+    if tags_match(if_match, &entity_tag(last_updated)) {
+        Ok(())
+    } else {
+        Err((
+            StatusCode::PRECONDITION_FAILED,
+            format!(
+                "The {} was changed since it was read; read it again before changing it",
+                R::RESOURCE_NAME_SINGULAR
+            ),
+        )
+            .into_response())
+    }
+}
+
+/// Add the tag of the record in a JSON response, answering 304 when it is
+/// the one the client has
+async fn tag_response(response: Response, if_none_match: Option<HeaderValue>) -> Response {
+    let is_json = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+    if !response.status().is_success() || !is_json {
+        return response;
+    }
+    let (mut parts, body) = response.into_parts();
+    let Ok(bytes) = to_bytes(body, usize::MAX).await else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    let last_updated = serde_json::from_slice::<Value>(&bytes)
+        .ok()
+        .and_then(|json| serde_json::from_value(json.get("last_updated")?.clone()).ok());
+    let Some(tag) = last_updated.map(entity_tag) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+    if parts.status == StatusCode::OK
+        && if_none_match.is_some_and(|if_none_match| tags_match(&if_none_match, &tag))
+    {
+        let mut not_modified = StatusCode::NOT_MODIFIED.into_response();
+        if let Ok(value) = HeaderValue::from_str(&tag) {
+            not_modified.headers_mut().insert(ETAG, value);
+        }
+        return not_modified;
+    }
+    if let Ok(value) = HeaderValue::from_str(&tag) {
+        parts.headers.insert(ETAG, value);
+    }
+    parts.headers.remove(CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+/// Middleware of a resource's router, innermost, tagging the records it
+/// returns and checking the tags of the changes made to them. `required` is
+/// whether changes must give a tag.
+pub async fn check_entity_tags<R: CRUDResource>(
+    State((db, required)): State<(DatabaseConnection, bool)>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(id) = (match segments(request.uri().path()).as_slice() {
+        [id] => Uuid::parse_str(id).ok(),
+        _ => None,
+    }) else {
+        return next.run(request).await;
+    };
+    let if_none_match = match *request.method() {
+        Method::GET => request.headers().get(IF_NONE_MATCH).cloned(),
+        Method::PUT | Method::PATCH => {
+            if let Err(rejection) = check_if_match::<R>(&db, id, request.headers(), required).await
+            {
+                return rejection;
+            }
+            None
+        }
+        _ => return next.run(request).await,
+    };
+    tag_response(next.run(request).await, if_none_match).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tags_match() {
+        let tag = entity_tag(DateTime::from_timestamp(1_700_000_000, 5).unwrap());
+        assert_eq!(tag, "\"17979cfe362a0005\"");
+        assert!(tags_match(&HeaderValue::from_str(&tag).unwrap(), &tag));
+        assert!(tags_match(&HeaderValue::from_static("\"1\", *"), &tag));
+        assert!(!tags_match(&HeaderValue::from_static("\"1\""), &tag));
+        assert!(!tags_match(
+            &HeaderValue::from_str(&format!("W/{tag}")).unwrap(),
+            &tag
+        ));
+    }
+}
@@ -0,0 +1,192 @@
+//! Sparse fieldsets.
+//!
+//! A `GET` with `?fields=id,name,performed_at` gets back only the named
+//! fields of a record, or of each record of a list, so a list view can ask
+//! for what it renders. Fields of embedded records are named by their path,
+//! e.g. `fields=id,regions.name,regions.treatment_id` keeps the ID and, of
+//! each region, its name and treatment. Naming an embedded record keeps it
+//! whole. Fields a record does not have are ignored.
+
+use axum::{
+    body::{Body, to_bytes},
+    extract::Request,
+    http::{
+        Method, StatusCode, Uri,
+        header::{CONTENT_LENGTH, CONTENT_TYPE},
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+/// Query parameter naming the fields to return
+pub const FIELDS_PARAMETER: &str = "fields";
+
+/// Fields selected, by name, with those selected of each. An empty
+/// selection of a field keeps it whole.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FieldSelection(BTreeMap<String, FieldSelection>);
+
+impl FieldSelection {
+    /// Parse comma-separated field paths. Gives `None` when none are named.
+    pub fn parse(fields: &str) -> Option<Self> {
+        let mut selection = Self::default();
+        for path in fields.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let mut node = &mut selection;
+            let mut whole = false;
+            for name in path.split('.').filter(|name| !name.is_empty()) {
+                // A field already kept whole stays whole
+                whole = node.0.get(name).is_some_and(|child| child.0.is_empty());
+                node = node.0.entry(name.to_string()).or_default();
+                if whole {
+                    break;
+                }
+            }
+            if !whole {
+                // Naming a field after some of its fields keeps it whole
+                node.0.clear();
+            }
+        }
+        (!selection.0.is_empty()).then_some(selection)
+    }
+
+    /// Keep only the selected fields of a record, or of each in a list
+    pub fn apply(&self, json: &mut Value) {
+        if self.0.is_empty() {
+            return;
+        }
+        match json {
+            Value::Array(values) => {
+                for value in values {
+                    self.apply(value);
+                }
+            }
+            Value::Object(fields) => {
+                let mut selected = Map::new();
+                for (name, selection) in &self.0 {
+                    if let Some(mut value) = fields.remove(name) {
+                        selection.apply(&mut value);
+                        selected.insert(name.clone(), value);
+                    }
+                }
+                *fields = selected;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Take a list parameter, such as `fields`, out of a URI, giving the URI
+/// without it and the values it was given, joined by commas. Gives `None`
+/// when the URI does not have it.
+pub(crate) fn take_list_parameter(uri: &Uri, parameter: &str) -> Option<(Uri, String)> {
+    let pairs: Vec<(String, String)> = serde_urlencoded::from_str(uri.query()?).ok()?;
+    let (values, others): (Vec<_>, Vec<_>) =
+        pairs.into_iter().partition(|(name, _)| name == parameter);
+    if values.is_empty() {
+        return None;
+    }
+    let values = values
+        .into_iter()
+        .map(|(_, value)| value)
+        .collect::<Vec<_>>()
+        .join(",");
+    let query = serde_urlencoded::to_string(&others).ok()?;
+    let path_and_query = if query.is_empty() {
+        uri.path().to_string()
+    } else {
+        format!("{}?{query}", uri.path())
+    };
+    let mut parts = uri.clone().into_parts();
+    parts.path_and_query = Some(path_and_query.parse().ok()?);
+    Some((Uri::from_parts(parts).ok()?, values))
+}
+
+/// Middleware trimming the JSON responses of reads to the fields asked for.
+/// The parameter is taken off before the request goes on.
+pub async fn select_fields(mut request: Request, next: Next) -> Response {
+    if request.method() != Method::GET {
+        return next.run(request).await;
+    }
+    let Some((uri, fields)) = take_list_parameter(request.uri(), FIELDS_PARAMETER) else {
+        return next.run(request).await;
+    };
+    let Some(selection) = FieldSelection::parse(&fields) else {
+        return next.run(request).await;
+    };
+    *request.uri_mut() = uri;
+
+    let response = next.run(request).await;
+    let is_json = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+    if !response.status().is_success() || !is_json {
+        return response;
+    }
+    let (mut parts, body) = response.into_parts();
+    let Ok(bytes) = to_bytes(body, usize::MAX).await else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    let Ok(mut json) = serde_json::from_slice::<Value>(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+    selection.apply(&mut json);
+    parts.headers.remove(CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(json.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_field_selection() {
+        assert_eq!(FieldSelection::parse(" , "), None);
+
+        let mut experiments = json!([{
+            "id": 1,
+            "name": "Run 1",
+            "remarks": "Long",
+            "regions": [{"name": "Top", "treatment_id": 7, "row_min": 0}],
+            "results": {"summary": {"total_wells": 192}, "trays": []},
+        }]);
+        FieldSelection::parse("id,regions.name,results.summary,missing.field")
+            .unwrap()
+            .apply(&mut experiments);
+        assert_eq!(
+            experiments,
+            json!([{
+                "id": 1,
+                "regions": [{"name": "Top"}],
+                "results": {"summary": {"total_wells": 192}},
+            }])
+        );
+
+        // A field named whole stays whole, in either order
+        for fields in ["regions,regions.name", "regions.name,regions"] {
+            let mut experiment = json!({"regions": [{"name": "Top", "row_min": 0}]});
+            FieldSelection::parse(fields)
+                .unwrap()
+                .apply(&mut experiment);
+            assert_eq!(
+                experiment,
+                json!({"regions": [{"name": "Top", "row_min": 0}]})
+            );
+        }
+    }
+
+    #[test]
+    fn test_take_list_parameter() {
+        let uri: Uri = "/api/experiments?sort=name&fields=id,name&fields=regions.name"
+            .parse()
+            .unwrap();
+        assert_eq!(take_list_parameter(&uri, "include"), None);
+        let (uri, fields) = take_list_parameter(&uri, FIELDS_PARAMETER).unwrap();
+        assert_eq!(uri.to_string(), "/api/experiments?sort=name");
+        assert_eq!(fields, "id,name,regions.name");
+    }
+}
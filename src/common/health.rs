@@ -0,0 +1,109 @@
+//! Checks of the services the API depends on, for Kubernetes probes.
+//!
+//! `/healthz` only checks the database: a failing liveness probe restarts the
+//! pod, which does not help while storage or Keycloak is down. `/readyz` also
+//! checks storage, Keycloak's signing keys and the migrations, and takes the
+//! pod out of service while any of them fails. Each check gives up after
+//! `CHECK_TIMEOUT_SECONDS`, so a dependency that hangs fails the probe rather
+//! than timing it out.
+
+use super::keycloak::KeycloakAuth;
+use super::models::{DependencyCheck, HealthCheck};
+use crate::config::Config;
+use crate::external::s3::head_object_size;
+use axum::{Json, http::StatusCode};
+use migration::{Migrator, MigratorTrait};
+use sea_orm::DatabaseConnection;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+const CHECK_TIMEOUT_SECONDS: u64 = 5;
+/// Object looked up to reach storage; it need not exist
+const STORAGE_PROBE_KEY: &str = ".readyz";
+
+/// Time a check, which gives its detail when it passes
+async fn timed(check: impl Future<Output = Result<Option<String>, String>>) -> DependencyCheck {
+    let started = Instant::now();
+    let result = tokio::time::timeout(Duration::from_secs(CHECK_TIMEOUT_SECONDS), check)
+        .await
+        .unwrap_or_else(|_| Err(format!("No answer within {CHECK_TIMEOUT_SECONDS} seconds")));
+    let (status, detail) = match result {
+        Ok(detail) => ("ok", detail),
+        Err(e) => ("error", Some(e)),
+    };
+    DependencyCheck {
+        status: status.to_string(),
+        detail,
+        duration_ms: u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX),
+    }
+}
+
+pub async fn database(db: &DatabaseConnection) -> DependencyCheck {
+    timed(async { db.ping().await.map(|()| None).map_err(|e| e.to_string()) }).await
+}
+
+pub async fn storage(config: &Config) -> DependencyCheck {
+    // Storage clients build large requests; boxed to keep probes small
+    timed(Box::pin(async {
+        head_object_size(STORAGE_PROBE_KEY, config).await?;
+        Ok(Some(format!(
+            "{} storage reachable",
+            config.storage_backend
+        )))
+    }))
+    .await
+}
+
+pub async fn keycloak(auth: Option<&KeycloakAuth>) -> DependencyCheck {
+    let Some(auth) = auth else {
+        return DependencyCheck {
+            status: "disabled".to_string(),
+            detail: None,
+            duration_ms: 0,
+        };
+    };
+    timed(async {
+        let fetched_at = auth.keys_fetched_at().await?;
+        Ok(Some(format!(
+            "Signing keys fetched at {}",
+            fetched_at.to_rfc3339()
+        )))
+    })
+    .await
+}
+
+/// Migrations not applied, and migrations applied by a newer version of the
+/// API, both fail
+pub async fn migrations(db: &DatabaseConnection) -> DependencyCheck {
+    timed(async {
+        let pending = Migrator::get_pending_migrations(db)
+            .await
+            .map_err(|e| e.to_string())?;
+        if pending.is_empty() {
+            return Ok(None);
+        }
+        let names: Vec<&str> = pending
+            .iter()
+            .map(sea_orm_migration::Migration::name)
+            .collect();
+        Err(format!("Pending migrations: {}", names.join(", ")))
+    })
+    .await
+}
+
+/// Response of a probe, unavailable when any check failed
+pub fn report(checks: BTreeMap<String, DependencyCheck>) -> (StatusCode, Json<HealthCheck>) {
+    let failed = checks.values().any(|check| check.status == "error");
+    let (code, status) = if failed {
+        (StatusCode::SERVICE_UNAVAILABLE, "error")
+    } else {
+        (StatusCode::OK, "ok")
+    };
+    (
+        code,
+        Json(HealthCheck {
+            status: status.to_string(),
+            checks,
+        }),
+    )
+}
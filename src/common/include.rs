@@ -0,0 +1,48 @@
+//! Control over the related records embedded in a response.
+//!
+//! Some records embed heavy relations, such as the results of an experiment.
+//! A request with `?include=regions,assets` embeds exactly the relations it
+//! names, and `?include=` none of them; without the parameter each resource
+//! embeds its usual ones. Services ask `includes` before loading a relation.
+
+use super::fields::take_list_parameter;
+use axum::{extract::Request, middleware::Next, response::Response};
+use std::collections::BTreeSet;
+
+/// Query parameter naming the relations to embed
+pub const INCLUDE_PARAMETER: &str = "include";
+
+tokio::task_local! {
+    static INCLUDED: BTreeSet<String>;
+}
+
+/// Whether a request embeds a relation. `by_default` is whether it does
+/// when the request names none.
+pub fn includes(relation: &str, by_default: bool) -> bool {
+    INCLUDED
+        .try_with(|included| included.contains(relation))
+        .unwrap_or(by_default)
+}
+
+/// Run a future embedding exactly the relations named, as a request naming
+/// them would
+pub async fn including<F: Future>(relations: &[&str], future: F) -> F::Output {
+    let included = relations.iter().map(ToString::to_string).collect();
+    INCLUDED.scope(included, future).await
+}
+
+/// Middleware running a request with the relations its `include` parameter
+/// names, which is taken off before the request goes on
+pub async fn scope_includes(mut request: Request, next: Next) -> Response {
+    let Some((uri, relations)) = take_list_parameter(request.uri(), INCLUDE_PARAMETER) else {
+        return next.run(request).await;
+    };
+    *request.uri_mut() = uri;
+    let included = relations
+        .split(',')
+        .map(str::trim)
+        .filter(|relation| !relation.is_empty())
+        .map(str::to_string)
+        .collect();
+    INCLUDED.scope(included, next.run(request)).await
+}
@@ -0,0 +1,491 @@
+//! Validation of Keycloak tokens against a cache of the realm's keys.
+//!
+//! The realm's signing keys (its JWKS) are fetched at start-up and refreshed
+//! in the background. A token signed with a key not yet known, after Keycloak
+//! rotated its keys, asks for an early refresh, at most every half minute.
+//! When Keycloak cannot be reached the last keys fetched are kept, and tokens
+//! are still validated with them for `keycloak_jwks_max_stale_hours`, so a
+//! restart or short outage of Keycloak does not sign everyone out.
+//! The `groups` claim gives the labs of the user, when labs are on.
+
+use crate::common::auth::Role;
+use crate::common::labs::Labs;
+use crate::config::Config;
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use axum_keycloak_auth::{
+    KeycloakAuthStatus, PassthroughMode,
+    decode::{KeycloakToken, ProfileAndEmail, StandardClaims},
+    error::AuthError,
+    extract::{AuthHeaderTokenExtractor, TokenExtractor},
+    role::ExtractRoles,
+};
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::{Algorithm, DecodingKey, Header, Validation, jwk::JwkSet};
+use serde::Deserialize;
+use std::sync::{Arc, Mutex, PoisonError, RwLock, Weak};
+
+/// Least time between refreshes asked for by tokens signed with unknown keys
+const MIN_REFRESH_SECONDS: i64 = 30;
+/// Time before a failed background refresh is tried again
+const RETRY_SECONDS: u64 = 30;
+const FETCH_TIMEOUT_SECONDS: u64 = 10;
+
+struct SigningKey {
+    kid: Option<String>,
+    algorithm: Option<Algorithm>,
+    key: DecodingKey,
+}
+
+/// Claims beyond the standard ones
+#[derive(Deserialize)]
+struct ExtraClaims {
+    #[serde(flatten)]
+    profile: ProfileAndEmail,
+    /// Keycloak groups, given by a group membership mapper
+    #[serde(default)]
+    groups: Vec<String>,
+}
+
+/// Keys of the realm as last fetched
+struct KeySet {
+    keys: Vec<SigningKey>,
+    fetched_at: DateTime<Utc>,
+}
+
+impl KeySet {
+    /// Key the token names, or the first key of its algorithm when it names
+    /// none
+    fn find(&self, header: &Header) -> Option<DecodingKey> {
+        self.keys
+            .iter()
+            .filter(|key| {
+                key.algorithm
+                    .is_none_or(|algorithm| algorithm == header.alg)
+            })
+            .find(|key| header.kid.is_none() || key.kid == header.kid)
+            .map(|key| key.key.clone())
+    }
+}
+
+pub struct KeycloakAuth {
+    jwks_url: String,
+    /// Accepted `aud` claims, not checked when empty
+    audiences: Vec<String>,
+    clock_skew_seconds: u64,
+    refresh_interval: std::time::Duration,
+    max_stale: Duration,
+    keys: RwLock<Option<Arc<KeySet>>>,
+    last_requested_refresh: Mutex<Option<DateTime<Utc>>>,
+}
+
+impl KeycloakAuth {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            jwks_url: format!(
+                "{}/realms/{}/protocol/openid-connect/certs",
+                config.keycloak_url.trim_end_matches('/'),
+                config.keycloak_realm
+            ),
+            audiences: config.keycloak_audiences.clone(),
+            clock_skew_seconds: config.keycloak_clock_skew_seconds,
+            refresh_interval: std::time::Duration::from_secs(
+                config.keycloak_jwks_refresh_minutes * 60,
+            ),
+            max_stale: Duration::hours(config.keycloak_jwks_max_stale_hours),
+            keys: RwLock::new(None),
+            last_requested_refresh: Mutex::new(None),
+        }
+    }
+
+    /// Validation for the configured realm, its keys kept refreshed in the
+    /// background for as long as it is used
+    pub fn start(config: &Config) -> Arc<Self> {
+        let auth = Arc::new(Self::new(config));
+        tokio::spawn(keep_refreshed(Arc::downgrade(&auth)));
+        auth
+    }
+
+    /// Fetch the realm's keys, keeping the last ones when that fails
+    pub async fn refresh(&self) -> Result<usize, String> {
+        let jwk_set: JwkSet = reqwest::Client::new()
+            .get(&self.jwks_url)
+            .timeout(std::time::Duration::from_secs(FETCH_TIMEOUT_SECONDS))
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|e| format!("Failed to fetch the Keycloak signing keys: {e}"))?
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse the Keycloak signing keys: {e}"))?;
+        self.set_keys(&jwk_set, Utc::now())
+    }
+
+    /// When the keys tokens are validated with were fetched, fetching them
+    /// first when none are recent enough
+    pub async fn keys_fetched_at(&self) -> Result<DateTime<Utc>, String> {
+        if self.usable_keys(Utc::now()).is_none() && self.claim_refresh(Utc::now()) {
+            self.refresh().await?;
+        }
+        self.usable_keys(Utc::now())
+            .map(|keys| keys.fetched_at)
+            .ok_or_else(|| "No Keycloak signing keys recent enough to validate tokens".to_string())
+    }
+
+    fn set_keys(&self, jwk_set: &JwkSet, fetched_at: DateTime<Utc>) -> Result<usize, String> {
+        let keys: Vec<SigningKey> = jwk_set
+            .keys
+            .iter()
+            .filter_map(|jwk| match DecodingKey::from_jwk(jwk) {
+                Ok(key) => Some(SigningKey {
+                    kid: jwk.common.key_id.clone(),
+                    algorithm: jwk
+                        .common
+                        .key_algorithm
+                        .and_then(|algorithm| algorithm.to_string().parse().ok()),
+                    key,
+                }),
+                Err(e) => {
+                    tracing::warn!("Ignoring a Keycloak signing key that cannot be used: {e}");
+                    None
+                }
+            })
+            .collect();
+        if keys.is_empty() {
+            return Err("Keycloak returned no usable signing keys".to_string());
+        }
+        let count = keys.len();
+        *self.keys.write().unwrap_or_else(PoisonError::into_inner) =
+            Some(Arc::new(KeySet { keys, fetched_at }));
+        Ok(count)
+    }
+
+    /// Keys recent enough to be trusted
+    fn usable_keys(&self, now: DateTime<Utc>) -> Option<Arc<KeySet>> {
+        self.keys
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+            .filter(|keys| now - keys.fetched_at <= self.max_stale)
+    }
+
+    /// Whether a request may ask for an early refresh, counting it if so
+    fn claim_refresh(&self, now: DateTime<Utc>) -> bool {
+        let mut last = self
+            .last_requested_refresh
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if last.is_some_and(|last| now - last < Duration::seconds(MIN_REFRESH_SECONDS)) {
+            return false;
+        }
+        *last = Some(now);
+        true
+    }
+
+    async fn decoding_key(&self, header: &Header) -> Result<DecodingKey, AuthError> {
+        if let Some(key) = self
+            .usable_keys(Utc::now())
+            .and_then(|keys| keys.find(header))
+        {
+            return Ok(key);
+        }
+        // A key new since the last refresh, or no keys yet
+        if self.claim_refresh(Utc::now())
+            && let Err(e) = self.refresh().await
+        {
+            tracing::warn!("{e}");
+        }
+        self.usable_keys(Utc::now())
+            .and_then(|keys| keys.find(header))
+            .ok_or(AuthError::NoDecodingKeys)
+    }
+
+    /// Check a token's signature, expiry and audience, allowing for clock
+    /// skew, and read the user, roles and groups it gives
+    pub async fn validate(
+        &self,
+        token: &str,
+    ) -> Result<(KeycloakToken<Role>, Vec<String>), AuthError> {
+        let header = jsonwebtoken::decode_header(token)
+            .map_err(|source| AuthError::DecodeHeader { source })?;
+        let key = self.decoding_key(&header).await?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.leeway = self.clock_skew_seconds;
+        if self.audiences.is_empty() {
+            validation.validate_aud = false;
+        } else {
+            validation.set_audience(&self.audiences);
+        }
+        let claims = jsonwebtoken::decode::<StandardClaims<ExtraClaims>>(token, &key, &validation)
+            .map_err(|source| AuthError::Decode { source })?
+            .claims;
+        token_from_claims(claims)
+    }
+}
+
+fn token_from_claims(
+    claims: StandardClaims<ExtraClaims>,
+) -> Result<(KeycloakToken<Role>, Vec<String>), AuthError> {
+    let timestamp = |seconds, claim| {
+        time::OffsetDateTime::from_unix_timestamp(seconds).map_err(|e| AuthError::InvalidToken {
+            reason: format!("Could not parse '{claim}' as a unix timestamp: {e}"),
+        })
+    };
+    let mut roles = Vec::new();
+    (claims.realm_access, claims.resource_access).extract_roles(&mut roles);
+    let token = KeycloakToken {
+        expires_at: timestamp(claims.exp, "exp")?,
+        issued_at: timestamp(claims.iat, "iat")?,
+        jwt_id: claims.jti,
+        issuer: claims.iss,
+        audience: claims.aud,
+        subject: claims.sub,
+        authorized_party: claims.azp,
+        roles,
+        extra: claims.extra.profile,
+    };
+    Ok((token, claims.extra.groups))
+}
+
+async fn keep_refreshed(auth: Weak<KeycloakAuth>) {
+    while let Some(auth) = auth.upgrade() {
+        let wait = match auth.refresh().await {
+            Ok(count) => {
+                tracing::info!("Fetched {count} Keycloak signing keys");
+                auth.refresh_interval
+            }
+            Err(e) => {
+                tracing::warn!("{e}, keeping the last keys");
+                std::time::Duration::from_secs(RETRY_SECONDS)
+            }
+        };
+        drop(auth);
+        tokio::time::sleep(wait).await;
+    }
+}
+
+/// Middleware validating the request's bearer token. In block mode requests
+/// without a valid token are rejected; in pass mode the outcome is left to
+/// the layers behind as a `KeycloakAuthStatus`.
+pub async fn authenticate(
+    State((auth, mode)): State<(Arc<KeycloakAuth>, PassthroughMode)>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let token = AuthHeaderTokenExtractor {}
+        .extract(&request)
+        .map(std::borrow::Cow::into_owned);
+    let result = match token {
+        Ok(token) => auth.validate(&token).await,
+        Err(e) => Err(e),
+    };
+    if let Ok((_, groups)) = &result
+        && let Some(labs) = Labs::from_groups(groups)
+    {
+        request.extensions_mut().insert(labs);
+    }
+    match (result.map(|(token, _)| token), mode) {
+        (Ok(token), PassthroughMode::Block) => {
+            request.extensions_mut().insert(token);
+        }
+        (Ok(token), PassthroughMode::Pass) => {
+            request
+                .extensions_mut()
+                .insert(KeycloakAuthStatus::<Role, ProfileAndEmail>::Success(token));
+        }
+        (Err(e), PassthroughMode::Block) => return e.into_response(),
+        (Err(e), PassthroughMode::Pass) => {
+            request
+                .extensions_mut()
+                .insert(KeycloakAuthStatus::<Role, ProfileAndEmail>::Failure(
+                    Arc::new(e),
+                ));
+        }
+    }
+    next.run(request).await
+}
+
+/// A realm whose tokens router tests sign, as Keycloak would
+#[cfg(test)]
+pub mod test_realm {
+    use super::*;
+    use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+    use jsonwebtoken::{EncodingKey, encode};
+    use openssl::rsa::Rsa;
+    use serde_json::json;
+    use std::sync::LazyLock;
+
+    const KID: &str = "test-key";
+
+    /// The realm's published keys and the key its tokens are signed with
+    static KEYS: LazyLock<(JwkSet, EncodingKey)> = LazyLock::new(|| {
+        let rsa = Rsa::generate(2048).unwrap();
+        let jwk_set = serde_json::from_value(json!({"keys": [{
+            "kty": "RSA",
+            "kid": KID,
+            "alg": "RS256",
+            "use": "sig",
+            "n": URL_SAFE_NO_PAD.encode(rsa.n().to_vec()),
+            "e": URL_SAFE_NO_PAD.encode(rsa.e().to_vec()),
+        }]}))
+        .unwrap();
+        let pem = rsa.private_key_to_pem().unwrap();
+        (jwk_set, EncodingKey::from_rsa_pem(&pem).unwrap())
+    });
+
+    /// Validation trusting the realm's keys
+    pub fn auth(config: &Config) -> Arc<KeycloakAuth> {
+        let auth = Arc::new(KeycloakAuth::new(config));
+        auth.set_keys(&KEYS.0, Utc::now()).unwrap();
+        auth
+    }
+
+    /// Bearer token of a user with the given realm roles and groups
+    pub fn token(username: &str, roles: &[&str], groups: &[&str]) -> String {
+        let now = Utc::now().timestamp();
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = Some(KID.to_string());
+        let claims = json!({
+            "exp": now + 300,
+            "iat": now,
+            "jti": uuid::Uuid::new_v4().to_string(),
+            "iss": "http://localhost:8080/realms/test-realm",
+            "aud": "account",
+            "sub": format!("{username}-id"),
+            "typ": "Bearer",
+            "azp": "spice-ui",
+            "preferred_username": username,
+            "email": format!("{username}@example.org"),
+            "email_verified": true,
+            "realm_access": {"roles": roles},
+            "groups": groups,
+        });
+        encode(&header, &claims, &KEYS.1).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+    use jsonwebtoken::{EncodingKey, encode};
+    use openssl::rsa::Rsa;
+    use serde_json::json;
+
+    /// A signing key pair, as Keycloak would publish and use it
+    fn key_pair(kid: &str) -> (serde_json::Value, EncodingKey) {
+        let rsa = Rsa::generate(2048).unwrap();
+        let jwk = json!({
+            "kty": "RSA",
+            "kid": kid,
+            "alg": "RS256",
+            "use": "sig",
+            "n": URL_SAFE_NO_PAD.encode(rsa.n().to_vec()),
+            "e": URL_SAFE_NO_PAD.encode(rsa.e().to_vec()),
+        });
+        let pem = rsa.private_key_to_pem().unwrap();
+        (jwk, EncodingKey::from_rsa_pem(&pem).unwrap())
+    }
+
+    fn token(kid: &str, key: &EncodingKey, audience: &str, expires_in: i64) -> String {
+        let now = Utc::now().timestamp();
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = Some(kid.to_string());
+        let claims = json!({
+            "exp": now + expires_in,
+            "iat": now - 60,
+            "jti": "token-1",
+            "iss": "http://localhost:8080/realms/test-realm",
+            "aud": audience,
+            "sub": "user-1",
+            "typ": "Bearer",
+            "azp": "spice-ui",
+            "preferred_username": "alice",
+            "email": "alice@example.org",
+            "email_verified": true,
+            "groups": ["/labs/eerl", "/staff"],
+        });
+        encode(&header, &claims, key).unwrap()
+    }
+
+    fn auth() -> KeycloakAuth {
+        let mut config = Config::for_tests();
+        // Nothing listens there, as when Keycloak is down
+        config.keycloak_url = "http://127.0.0.1:9".to_string();
+        config.keycloak_audiences = vec!["account".to_string()];
+        config.keycloak_clock_skew_seconds = 30;
+        config.keycloak_jwks_max_stale_hours = 12;
+        KeycloakAuth::new(&config)
+    }
+
+    #[tokio::test]
+    async fn test_token_validation() {
+        let auth = auth();
+        let (jwk, key) = key_pair("key-1");
+        let jwk_set: JwkSet = serde_json::from_value(json!({"keys": [jwk]})).unwrap();
+        auth.set_keys(&jwk_set, Utc::now()).unwrap();
+
+        let (validated, groups) = auth
+            .validate(&token("key-1", &key, "account", 300))
+            .await
+            .unwrap();
+        assert_eq!(validated.extra.profile.preferred_username, "alice");
+        assert_eq!(validated.subject, "user-1");
+        assert_eq!(groups, vec!["/labs/eerl", "/staff"]);
+
+        // Expired within the clock skew allowed, then beyond it
+        assert!(
+            auth.validate(&token("key-1", &key, "account", -20))
+                .await
+                .is_ok()
+        );
+        let expired = auth.validate(&token("key-1", &key, "account", -40)).await;
+        assert!(matches!(expired, Err(AuthError::Decode { .. })));
+
+        let other_audience = auth
+            .validate(&token("key-1", &key, "other-client", 300))
+            .await;
+        assert!(matches!(other_audience, Err(AuthError::Decode { .. })));
+
+        // Signed with a key Keycloak never published
+        let (_, forged) = key_pair("key-1");
+        assert!(
+            auth.validate(&token("key-1", &forged, "account", 300))
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_keys_kept_through_outages() {
+        let auth = auth();
+        let (jwk, key) = key_pair("key-1");
+        let jwk_set: JwkSet = serde_json::from_value(json!({"keys": [jwk]})).unwrap();
+        let fetched_at = Utc::now() - Duration::hours(2);
+        auth.set_keys(&jwk_set, fetched_at).unwrap();
+
+        // Keycloak cannot be reached: the cached keys stay in use
+        assert!(auth.refresh().await.is_err());
+        let valid = token("key-1", &key, "account", 300);
+        assert!(auth.validate(&valid).await.is_ok());
+
+        // An unknown key asks for a refresh, which fails, once per interval
+        let (_, rotated) = key_pair("key-2");
+        let unknown = auth
+            .validate(&token("key-2", &rotated, "account", 300))
+            .await;
+        assert!(matches!(unknown, Err(AuthError::NoDecodingKeys)));
+        assert!(!auth.claim_refresh(Utc::now()));
+        assert!(auth.claim_refresh(Utc::now() + Duration::seconds(MIN_REFRESH_SECONDS)));
+
+        // Keys not refreshed for too long are no longer trusted
+        auth.set_keys(&jwk_set, Utc::now() - Duration::hours(13))
+            .unwrap();
+        let stale = auth.validate(&valid).await;
+        assert!(matches!(stale, Err(AuthError::NoDecodingKeys)));
+    }
+}
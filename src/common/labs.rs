@@ -0,0 +1,578 @@
+//! Labs sharing one deployment.
+//!
+//! With `LAB_GROUP_PREFIX` set, the Keycloak groups of a user that start
+//! with it name the labs the user belongs to, e.g. `/labs/eerl` for the lab
+//! `eerl`, and an API key belongs to the lab it was given. Projects,
+//! locations, experiments, samples, tray configurations and planned
+//! experiments are stamped with the lab a request works in; everything else
+//! belongs to the lab of what it hangs from. Users who are not administrators
+//! reach only the records of their labs and those of no lab: lists are
+//! narrowed to them in the database, other records are not found, and changes cannot point at
+//! records of other labs. A user of several labs picks one with the `X-Lab`
+//! header, and works in their first lab otherwise. Administrators reach every
+//! lab and may work in any.
+
+use crate::common::auth::Role;
+use crate::config::Config;
+use crate::projects::access::{narrow_list_scope, read_record_body, reads_collection, segments};
+use axum::{
+    Extension,
+    extract::{Request, State},
+    http::{HeaderMap, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use axum_keycloak_auth::decode::KeycloakToken;
+use sea_orm::{
+    ConnectionTrait, DatabaseConnection, DbErr,
+    sea_query::{Alias, Condition, Expr, Query, SelectStatement},
+};
+use serde_json::Value;
+use std::sync::{PoisonError, RwLock};
+use uuid::Uuid;
+
+/// Header naming the lab a request works in
+pub const LAB_HEADER: &str = "x-lab";
+
+static GROUP_PREFIX: RwLock<Option<String>> = RwLock::new(None);
+
+/// Use the lab groups of the configuration
+pub fn configure(config: &Config) {
+    GROUP_PREFIX
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone_from(&config.lab_group_prefix);
+}
+
+fn group_prefix() -> Option<String> {
+    GROUP_PREFIX
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+/// Labs the user of a request belongs to, first the one they work in by
+/// default
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Labs(pub Vec<String>);
+
+impl Labs {
+    /// Labs named by a user's Keycloak groups, or `None` when labs are off
+    pub fn from_groups(groups: &[String]) -> Option<Self> {
+        let prefix = group_prefix()?;
+        Some(Self(
+            groups
+                .iter()
+                .filter_map(|group| group.strip_prefix(prefix.as_str()))
+                .filter(|lab| !lab.is_empty())
+                .map(str::to_string)
+                .collect(),
+        ))
+    }
+
+    /// Lab of an API key, or `None` when labs are off
+    pub fn of_key(lab: Option<&str>) -> Option<Self> {
+        group_prefix()?;
+        Some(Self(lab.into_iter().map(str::to_string).collect()))
+    }
+}
+
+tokio::task_local! {
+    static CURRENT_LAB: Option<String>;
+}
+
+/// Run a request in a lab, which records it creates are stamped with
+pub async fn as_lab<F: Future>(lab: Option<String>, future: F) -> F::Output {
+    CURRENT_LAB.scope(lab, future).await
+}
+
+/// Lab a request works in, if any
+pub fn current_lab() -> Option<String> {
+    CURRENT_LAB.try_with(Clone::clone).ok().flatten()
+}
+
+/// Resources whose records belong to labs
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TenantResource {
+    Projects,
+    Locations,
+    Experiments,
+    Samples,
+    Treatments,
+    Dilutions,
+    Assets,
+    TrayConfigurations,
+    Trays,
+    PlannedExperiments,
+}
+
+impl TenantResource {
+    fn table(self) -> &'static str {
+        match self {
+            Self::Projects => "projects",
+            Self::Locations => "locations",
+            Self::Experiments => "experiments",
+            Self::Samples => "samples",
+            Self::Treatments => "treatments",
+            Self::Dilutions => "treatment_dilutions",
+            Self::Assets => "s3_assets",
+            Self::TrayConfigurations => "tray_configurations",
+            Self::Trays => "trays",
+            Self::PlannedExperiments => "planned_experiments",
+        }
+    }
+
+    /// For records without a lab of their own, the field naming what they
+    /// hang from
+    fn parent(self) -> Option<(&'static str, Self)> {
+        match self {
+            Self::Treatments => Some(("sample_id", Self::Samples)),
+            Self::Dilutions => Some(("treatment_id", Self::Treatments)),
+            Self::Assets => Some(("experiment_id", Self::Experiments)),
+            Self::Trays => Some(("tray_configuration_id", Self::TrayConfigurations)),
+            Self::Projects
+            | Self::Locations
+            | Self::Experiments
+            | Self::Samples
+            | Self::TrayConfigurations
+            | Self::PlannedExperiments => None,
+        }
+    }
+
+    /// Fields of a record naming records of other resources
+    fn references(self) -> &'static [(&'static str, Self)] {
+        match self {
+            Self::Projects | Self::TrayConfigurations => &[],
+            Self::Locations => &[("project_id", Self::Projects)],
+            Self::Experiments | Self::PlannedExperiments => &[
+                ("project_id", Self::Projects),
+                ("tray_configuration_id", Self::TrayConfigurations),
+            ],
+            Self::Samples => &[
+                ("location_id", Self::Locations),
+                ("parent_sample_id", Self::Samples),
+            ],
+            Self::Treatments => &[("sample_id", Self::Samples)],
+            Self::Dilutions => &[("treatment_id", Self::Treatments)],
+            Self::Assets => &[("experiment_id", Self::Experiments)],
+            Self::Trays => &[("tray_configuration_id", Self::TrayConfigurations)],
+        }
+    }
+
+    /// Collection routes that reveal no records, open to every user
+    fn open_routes(self) -> &'static [&'static str] {
+        match self {
+            Self::Samples => &["type-rules", "validate"],
+            Self::PlannedExperiments => &["calendar"],
+            _ => &[],
+        }
+    }
+
+    /// The records of the labs and of no lab, as a condition on the
+    /// resource's table
+    fn visible(self, labs: &[String]) -> Condition {
+        let column = |field: &str| Expr::col((Alias::new(self.table()), Alias::new(field)));
+        let (field, visible) = match self.parent() {
+            None => ("lab", column("lab").is_in(labs.to_vec())),
+            Some((field, parent)) => (field, column(field).in_subquery(parent.visible_ids(labs))),
+        };
+        Condition::any().add(column(field).is_null()).add(visible)
+    }
+
+    /// Query for the IDs of the records of the labs and of no lab
+    fn visible_ids(self, labs: &[String]) -> SelectStatement {
+        Query::select()
+            .column(Alias::new("id"))
+            .from(Alias::new(self.table()))
+            .cond_where(self.visible(labs))
+            .to_owned()
+    }
+}
+
+fn internal(error: &DbErr) -> (StatusCode, String) {
+    (StatusCode::INTERNAL_SERVER_ERROR, error.to_string())
+}
+
+/// The records of the labs and of no lab, as a condition lists add to their
+/// filter
+pub fn lab_scope(resource: TenantResource, labs: &[String]) -> Condition {
+    resource.visible(labs)
+}
+
+/// Whether a record is of the labs or of no lab, or `None` when there is
+/// no such record
+async fn is_visible(
+    db: &DatabaseConnection,
+    resource: TenantResource,
+    labs: &[String],
+    id: Uuid,
+) -> Result<Option<bool>, DbErr> {
+    let backend = db.get_database_backend();
+    let exists = Query::select()
+        .column(Alias::new("id"))
+        .from(Alias::new(resource.table()))
+        .and_where(Expr::col(Alias::new("id")).eq(id))
+        .to_owned();
+    if db.query_one(backend.build(&exists)).await?.is_none() {
+        return Ok(None);
+    }
+    let visible = resource
+        .visible_ids(labs)
+        .and_where(Expr::col(Alias::new("id")).eq(id))
+        .to_owned();
+    Ok(Some(db.query_one(backend.build(&visible)).await?.is_some()))
+}
+
+/// Check that the records a create or update body names are of the labs
+async fn check_references(
+    db: &DatabaseConnection,
+    resource: TenantResource,
+    labs: &[String],
+    body: Option<&Value>,
+) -> Result<(), (StatusCode, String)> {
+    for (field, target) in resource.references() {
+        let Some(id) = body
+            .and_then(|body| body.get(field))
+            .and_then(Value::as_str)
+            .and_then(|id| Uuid::parse_str(id).ok())
+        else {
+            continue;
+        };
+        if is_visible(db, *target, labs, id)
+            .await
+            .map_err(|e| internal(&e))?
+            == Some(false)
+        {
+            return Err((
+                StatusCode::FORBIDDEN,
+                format!("The {field} names a record of another lab"),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Decide whether a user of the labs may make a request, given the path
+/// within the resource's router. Lists are left to `lab_scope`.
+pub async fn authorize(
+    db: &DatabaseConnection,
+    labs: &[String],
+    resource: TenantResource,
+    method: &Method,
+    path: &str,
+    body: Option<&Value>,
+) -> Result<(), (StatusCode, String)> {
+    let segments = segments(path);
+
+    if reads_collection(method, &segments) {
+        return Ok(());
+    }
+    let Some(first) = segments.first() else {
+        return match *method {
+            Method::POST => check_references(db, resource, labs, body).await,
+            _ => Err((
+                StatusCode::FORBIDDEN,
+                "Only administrators can change several records at once".to_string(),
+            )),
+        };
+    };
+    let Ok(id) = Uuid::parse_str(first) else {
+        return if resource.open_routes().contains(first) {
+            Ok(())
+        } else {
+            Err((
+                StatusCode::FORBIDDEN,
+                "Only administrators can use this endpoint".to_string(),
+            ))
+        };
+    };
+
+    match is_visible(db, resource, labs, id)
+        .await
+        .map_err(|e| internal(&e))?
+    {
+        // Records of other labs are as good as missing
+        Some(false) => Err((StatusCode::NOT_FOUND, "Record not found".to_string())),
+        // Let the route report the missing record
+        None => Ok(()),
+        Some(true) => {
+            if segments.len() == 1 && matches!(*method, Method::PUT | Method::PATCH) {
+                check_references(db, resource, labs, body).await?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Lab a request works in: the one it names, which must be one of the
+/// user's unless they are an administrator, or else the user's first
+fn active_lab(
+    headers: &HeaderMap,
+    labs: &[String],
+    administrator: bool,
+) -> Result<Option<String>, (StatusCode, String)> {
+    let Some(value) = headers.get(LAB_HEADER) else {
+        return Ok(labs.first().cloned());
+    };
+    let lab = value.to_str().map(str::trim).unwrap_or_default();
+    if lab.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "X-Lab must name a lab".to_string()));
+    }
+    if administrator || labs.iter().any(|own| own == lab) {
+        Ok(Some(lab.to_string()))
+    } else {
+        Err((
+            StatusCode::FORBIDDEN,
+            format!("You are not a member of the lab '{lab}'"),
+        ))
+    }
+}
+
+/// Middleware limiting users who are not administrators to the records of
+/// their labs, and running requests in the lab they work in. Requests pass
+/// unchanged when labs are off and, when authentication is off, without a
+/// token.
+pub async fn require_lab(
+    State((db, resource)): State<(DatabaseConnection, TenantResource)>,
+    token: Option<Extension<KeycloakToken<Role>>>,
+    labs: Option<Extension<Labs>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(Extension(token)) = token else {
+        return next.run(request).await;
+    };
+    if group_prefix().is_none() {
+        return next.run(request).await;
+    }
+    let Labs(labs) = labs.map(|Extension(labs)| labs).unwrap_or_default();
+    let administrator = token
+        .roles
+        .iter()
+        .any(|role| *role.role() == Role::Administrator);
+    let lab = match active_lab(request.headers(), &labs, administrator) {
+        Ok(lab) => lab,
+        Err(rejection) => return rejection.into_response(),
+    };
+    if administrator {
+        return as_lab(lab, next.run(request)).await;
+    }
+
+    let (mut parts, body) = request.into_parts();
+    let (body, json) = match read_record_body(&parts, body).await {
+        Ok(read) => read,
+        Err(response) => return response,
+    };
+    if let Err(rejection) = authorize(
+        &db,
+        &labs,
+        resource,
+        &parts.method,
+        parts.uri.path(),
+        json.as_ref(),
+    )
+    .await
+    {
+        return rejection.into_response();
+    }
+    if reads_collection(&parts.method, &segments(parts.uri.path())) {
+        narrow_list_scope(&mut parts.extensions, lab_scope(resource, &labs));
+    }
+    as_lab(lab, next.run(Request::from_parts(parts, body))).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::test_helpers::setup_test_db;
+    use crate::tray_configurations::models as tray_configurations;
+    use axum::body::{Body, to_bytes};
+    use sea_orm::{ConnectionTrait, EntityTrait, QueryFilter};
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    /// IDs of the records of the labs and of no lab
+    async fn visible_ids(
+        db: &DatabaseConnection,
+        resource: TenantResource,
+        labs: &[String],
+    ) -> Vec<Uuid> {
+        let statement = db.get_database_backend().build(&resource.visible_ids(labs));
+        db.query_all(statement)
+            .await
+            .unwrap()
+            .iter()
+            .map(|row| row.try_get_by_index(0).unwrap())
+            .collect()
+    }
+
+    /// Post as a user working in a lab
+    async fn post_in_lab(app: &axum::Router, lab: Option<&str>, uri: &str, body: &Value) -> Value {
+        let request = axum::http::Request::builder()
+            .method("POST")
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let response = as_lab(lab.map(str::to_string), app.clone().oneshot(request))
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(status, StatusCode::CREATED, "{body}");
+        body
+    }
+
+    #[test]
+    fn test_labs_of_groups_and_active_lab() {
+        let mut config = Config::for_tests();
+        config.lab_group_prefix = Some("/labs/".to_string());
+        configure(&config);
+        let groups = ["/labs/eerl", "/staff", "/labs/", "/labs/lapi"].map(str::to_string);
+        assert_eq!(
+            Labs::from_groups(&groups),
+            Some(Labs(vec!["eerl".to_string(), "lapi".to_string()]))
+        );
+        assert_eq!(Labs::of_key(None), Some(Labs(vec![])));
+
+        let labs = ["eerl".to_string(), "lapi".to_string()];
+        let mut headers = HeaderMap::new();
+        assert_eq!(
+            active_lab(&headers, &labs, false),
+            Ok(Some("eerl".to_string()))
+        );
+        headers.insert(LAB_HEADER, "lapi".parse().unwrap());
+        assert_eq!(
+            active_lab(&headers, &labs, false),
+            Ok(Some("lapi".to_string()))
+        );
+        headers.insert(LAB_HEADER, "other".parse().unwrap());
+        let (status, _) = active_lab(&headers, &labs, false).unwrap_err();
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(
+            active_lab(&headers, &[], true),
+            Ok(Some("other".to_string()))
+        );
+    }
+
+    #[tokio::test]
+    #[allow(clippy::too_many_lines)]
+    async fn test_lab_isolation() {
+        let db = setup_test_db().await;
+        let mut config = Config::for_tests();
+        config.keycloak_url = String::new();
+        let app = crate::routes::build_router(&db, &config);
+
+        // Records are stamped with the lab the request works in
+        let mut experiment_ids = Vec::new();
+        let mut configuration_ids = Vec::new();
+        for lab in [Some("eerl"), Some("lapi"), None] {
+            let configuration = post_in_lab(
+                &app,
+                lab,
+                "/api/tray_configurations",
+                &json!({
+                    "name": format!("Trays of {lab:?}"),
+                    "experiment_default": false,
+                    "trays": [{"order_sequence": 1, "rotation_degrees": 0, "name": "P1", "qty_cols": 12, "qty_rows": 8}],
+                }),
+            )
+            .await;
+            assert_eq!(configuration["lab"], json!(lab));
+            configuration_ids.push(configuration["id"].as_str().unwrap().to_string());
+            let experiment = post_in_lab(
+                &app,
+                lab,
+                "/api/experiments",
+                &json!({"name": format!("Run of {lab:?}"), "is_calibration": false}),
+            )
+            .await;
+            assert_eq!(experiment["lab"], json!(lab));
+            experiment_ids.push(experiment["id"].as_str().unwrap().to_string());
+        }
+
+        let labs = ["eerl".to_string()];
+        let check = |resource, method, path: String, body: Option<Value>| {
+            let db = db.clone();
+            let labs = labs.clone();
+            async move { authorize(&db, &labs, resource, &method, &path, body.as_ref()).await }
+        };
+
+        // The lab's own records and those of no lab
+        for id in [&experiment_ids[0], &experiment_ids[2]] {
+            assert_eq!(
+                check(
+                    TenantResource::Experiments,
+                    Method::GET,
+                    format!("/{id}"),
+                    None
+                )
+                .await,
+                Ok(())
+            );
+        }
+        // Another lab's records are not found
+        let (status, _) = check(
+            TenantResource::Experiments,
+            Method::DELETE,
+            format!("/{}", experiment_ids[1]),
+            None,
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let trays = visible_ids(&db, TenantResource::Trays, &labs).await;
+        assert_eq!(trays.len(), 2);
+
+        // Lists are narrowed to the records of the lab and of no lab
+        assert_eq!(
+            check(
+                TenantResource::TrayConfigurations,
+                Method::GET,
+                "/".to_string(),
+                None
+            )
+            .await,
+            Ok(())
+        );
+        let mut listed: Vec<String> = tray_configurations::Entity::find()
+            .filter(lab_scope(TenantResource::TrayConfigurations, &labs))
+            .all(&db)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|configuration| configuration.id.to_string())
+            .collect();
+        listed.sort();
+        let mut expected = vec![configuration_ids[0].clone(), configuration_ids[2].clone()];
+        expected.sort();
+        assert_eq!(listed, expected);
+
+        // Changes cannot point at another lab's records
+        assert_eq!(
+            check(
+                TenantResource::Experiments,
+                Method::POST,
+                "/".to_string(),
+                Some(json!({"name": "New run", "tray_configuration_id": configuration_ids[0]})),
+            )
+            .await,
+            Ok(())
+        );
+        let (status, message) = check(
+            TenantResource::Experiments,
+            Method::PATCH,
+            format!("/{}", experiment_ids[0]),
+            Some(json!({"tray_configuration_id": configuration_ids[1]})),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert!(message.contains("another lab"));
+
+        // A user of no lab sees only the records of no lab
+        let ids = visible_ids(&db, TenantResource::Experiments, &[]).await;
+        assert_eq!(ids, vec![Uuid::parse_str(&experiment_ids[2]).unwrap()]);
+    }
+}
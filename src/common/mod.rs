@@ -1,6 +1,22 @@
+pub mod aggregate;
 pub mod auth;
+pub mod cache;
+pub mod error_reporting;
+pub mod etag;
+pub mod fields;
+pub mod health;
+pub mod include;
+pub mod keycloak;
+pub mod labs;
 pub mod models;
+pub mod pagination;
+pub mod patch;
+pub mod rate_limit;
+pub mod redaction;
+pub mod soft_delete;
+pub mod spatial;
 pub mod state;
+pub mod upsert;
 pub mod views;
 
 #[cfg(test)]
@@ -1,5 +1,6 @@
 use crate::config::Config;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use utoipa::ToSchema;
 
 /// Processing status for async operations
@@ -36,5 +37,17 @@ impl UIConfiguration {
 #[derive(ToSchema, Deserialize, Serialize)]
 pub struct HealthCheck {
     pub status: String,
+    /// Result of each dependency checked, by name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub checks: BTreeMap<String, DependencyCheck>,
+}
+
+#[derive(ToSchema, Deserialize, Serialize, Debug, Clone)]
+pub struct DependencyCheck {
+    /// `ok`, `error`, or `disabled` when the dependency is not configured
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    pub duration_ms: u64,
 }
 
@@ -0,0 +1,396 @@
+//! Cursor pagination of lists.
+//!
+//! Lists are paged by offset with `range`, which gets slower the further in
+//! a page is. A list asked for with `page_size`, or with the `cursor` of a
+//! previous page, is paged by key instead: each page starts after the last
+//! record of the one before, by the list's sort and then by ID, however far
+//! in it is. The cursor is opaque to clients and carries the sort, so the
+//! next page is asked for with it alone, besides the filter. It also keeps
+//! the last record's sort value, so a page follows on even when that record
+//! has since been deleted. Pages hold up
+//! to `MAX_PAGE_SIZE` records. The cursor of the next page is given in
+//! `X-Next-Cursor`, until the last page. Every list, paged either way, gives
+//! the number of records matching its filter in `X-Total-Count`. Lists with
+//! a `ListScope` are answered here either way, narrowed to it.
+
+use crate::projects::access::{ListScope, in_list_scope, segments};
+use axum::{
+    Json,
+    extract::{Request, State},
+    http::{HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime};
+use crudcrate::{CRUDResource, models::FilterOptions, pagination::calculate_content_range};
+use sea_orm::{
+    ColumnTrait, Condition, ConnectionTrait, DatabaseConnection, EntityTrait, IdenStatic,
+    ModelTrait, Order, QueryFilter, QueryOrder, QuerySelect,
+    sea_query::{NullOrdering, SimpleExpr, ValueType},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+pub const DEFAULT_PAGE_SIZE: u64 = 100;
+pub const MAX_PAGE_SIZE: u64 = 1000;
+pub const TOTAL_COUNT_HEADER: &str = "x-total-count";
+pub const NEXT_CURSOR_HEADER: &str = "x-next-cursor";
+
+/// Parameters of a list paged by key
+#[derive(Debug, Default, Deserialize)]
+struct CursorQuery {
+    filter: Option<String>,
+    sort: Option<String>,
+    cursor: Option<String>,
+    page_size: Option<u64>,
+}
+
+/// Where a page ends, as given to clients encoded in `X-Next-Cursor`
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Cursor {
+    /// Last record of the page
+    pub after: Uuid,
+    /// Its value of the sort column, `None` when it has none
+    pub value: Option<SortValue>,
+    /// Sort of the list, as `["column", "ASC"]`
+    pub sort: String,
+}
+
+impl Cursor {
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    pub fn decode(cursor: &str) -> Option<Self> {
+        serde_json::from_slice(&URL_SAFE_NO_PAD.decode(cursor).ok()?).ok()
+    }
+}
+
+/// A value of a sort column, kept in a cursor with its type so that it is
+/// compared as the column's own
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum SortValue {
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    /// Kept as text, which holds every digit
+    Decimal(String),
+    Text(String),
+    Uuid(Uuid),
+    Date(NaiveDate),
+    DateTime(NaiveDateTime),
+    Timestamp(DateTime<FixedOffset>),
+    Json(Value),
+}
+
+impl SortValue {
+    /// The value of a column, `None` when it is null
+    fn from_column(value: sea_orm::Value) -> Option<Self> {
+        use sea_orm::Value as V;
+        Some(match value {
+            V::Bool(value) => Self::Bool(value?),
+            V::TinyInt(value) => Self::Integer(value?.into()),
+            V::SmallInt(value) => Self::Integer(value?.into()),
+            V::Int(value) => Self::Integer(value?.into()),
+            V::BigInt(value) => Self::Integer(value?),
+            V::TinyUnsigned(value) => Self::Integer(value?.into()),
+            V::SmallUnsigned(value) => Self::Integer(value?.into()),
+            V::Unsigned(value) => Self::Integer(value?.into()),
+            V::BigUnsigned(value) => Self::Integer(value?.try_into().ok()?),
+            V::Float(value) => Self::Float(value?.into()),
+            V::Double(value) => Self::Float(value?),
+            V::Decimal(value) => Self::Decimal(value?.to_string()),
+            V::String(value) => Self::Text(*value?),
+            V::Char(value) => Self::Text(value?.to_string()),
+            V::Uuid(value) => Self::Uuid(*value?),
+            V::ChronoDate(value) => Self::Date(*value?),
+            V::ChronoDateTime(value) => Self::DateTime(*value?),
+            V::ChronoDateTimeUtc(value) => Self::Timestamp(value?.fixed_offset()),
+            V::ChronoDateTimeLocal(value) => Self::Timestamp(value?.fixed_offset()),
+            V::ChronoDateTimeWithTimeZone(value) => Self::Timestamp(*value?),
+            V::Json(value) => Self::Json(*value?),
+            _ => return None,
+        })
+    }
+
+    fn into_column(self) -> Option<sea_orm::Value> {
+        Some(match self {
+            Self::Bool(value) => value.into(),
+            Self::Integer(value) => value.into(),
+            Self::Float(value) => value.into(),
+            Self::Decimal(value) => value.parse::<rust_decimal::Decimal>().ok()?.into(),
+            Self::Text(value) => value.into(),
+            Self::Uuid(value) => value.into(),
+            Self::Date(value) => value.into(),
+            Self::DateTime(value) => value.into(),
+            Self::Timestamp(value) => value.into(),
+            Self::Json(value) => value.into(),
+        })
+    }
+}
+
+/// Condition for the records after a cursor, by a column and then by ID.
+/// Records without a value come last.
+fn after_cursor<C: ColumnTrait>(
+    id_column: C,
+    order_column: C,
+    order: &Order,
+    cursor: &Cursor,
+) -> Option<Condition> {
+    let same_and_after = |value_is: SimpleExpr| {
+        Condition::all()
+            .add(value_is)
+            .add(id_column.gt(cursor.after))
+    };
+    let Some(value) = cursor.value.clone() else {
+        return Some(same_and_after(order_column.is_null()));
+    };
+    let value = value.into_column()?;
+    let beyond = match order {
+        Order::Desc => order_column.lt(value.clone()),
+        _ => order_column.gt(value.clone()),
+    };
+    Some(
+        Condition::any()
+            .add(beyond)
+            .add(same_and_after(order_column.eq(value)))
+            .add(order_column.is_null()),
+    )
+}
+
+/// The entity's own column of a resource's column
+fn entity_column<R: CRUDResource>(
+    column: R::ColumnType,
+) -> Option<<R::EntityType as EntityTrait>::Column> {
+    column.as_str().parse().ok()
+}
+
+fn record_id<R: CRUDResource>(record: &<R::EntityType as EntityTrait>::Model) -> Option<Uuid> {
+    <Uuid as ValueType>::try_from(record.get(entity_column::<R>(R::ID_COLUMN)?)).ok()
+}
+
+/// Size and cursor of a page, checked
+fn page_bounds(query: &CursorQuery) -> Result<(u64, Option<Cursor>), (StatusCode, String)> {
+    let page_size = query.page_size.unwrap_or(DEFAULT_PAGE_SIZE);
+    if !(1..=MAX_PAGE_SIZE).contains(&page_size) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("page_size must be between 1 and {MAX_PAGE_SIZE}"),
+        ));
+    }
+    let cursor = match &query.cursor {
+        Some(cursor) => Some(
+            Cursor::decode(cursor)
+                .ok_or_else(|| (StatusCode::BAD_REQUEST, "Invalid cursor".to_string()))?,
+        ),
+        None => None,
+    };
+    Ok((page_size, cursor))
+}
+
+/// A page of a list, after the record of the cursor if any
+async fn cursor_page<R>(
+    db: &DatabaseConnection,
+    query: CursorQuery,
+    scope: Option<&ListScope>,
+) -> Result<(HeaderMap, Vec<Value>), (StatusCode, String)>
+where
+    R: CRUDResource,
+    R::ListModel: Serialize,
+{
+    let (page_size, cursor) = page_bounds(&query)?;
+    let sort = cursor
+        .as_ref()
+        .map(|cursor| cursor.sort.clone())
+        .or(query.sort)
+        .unwrap_or_else(|| r#"["id","ASC"]"#.to_string());
+    let (order_column, order) = crudcrate::sort::generic_sort(
+        Some(sort.clone()),
+        &R::sortable_columns(),
+        R::default_index_column(),
+    );
+    let filter = in_list_scope(
+        crudcrate::filter::apply_filters::<R>(
+            query.filter,
+            &R::filterable_columns(),
+            db.get_database_backend(),
+        ),
+        scope,
+    );
+    let internal = |e: sea_orm::DbErr| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+
+    let mut condition = filter.clone();
+    if let Some(cursor) = &cursor {
+        condition = condition.add(
+            after_cursor(R::ID_COLUMN, order_column, &order, cursor)
+                .ok_or_else(|| (StatusCode::BAD_REQUEST, "Invalid cursor".to_string()))?,
+        );
+    }
+    let mut page = R::EntityType::find()
+        .filter(condition)
+        .order_by_with_nulls(order_column, order.clone(), NullOrdering::Last)
+        .order_by_asc(R::ID_COLUMN)
+        .limit(page_size + 1)
+        .all(db)
+        .await
+        .map_err(internal)?;
+    let more = page.len() as u64 > page_size;
+    page.truncate(usize::try_from(page_size).unwrap_or(usize::MAX));
+    let ids: Vec<Uuid> = page.iter().filter_map(record_id::<R>).collect();
+    let last = page.last().map(|last| (record_id::<R>(last), last));
+    let next = match (more, last, entity_column::<R>(order_column)) {
+        (false, ..) => None,
+        (true, Some((Some(after), last)), Some(column)) => Some(Cursor {
+            after,
+            value: SortValue::from_column(last.get(column)),
+            sort,
+        }),
+        (true, ..) => {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("{} cannot be paged by key", R::RESOURCE_NAME_PLURAL),
+            ));
+        }
+    };
+
+    // Records are listed as the resource lists them, in the page's order
+    let mut records: Vec<Value> = R::get_all(
+        db,
+        &Condition::all().add(R::ID_COLUMN.is_in(ids.clone())),
+        order_column,
+        order,
+        0,
+        page_size,
+    )
+    .await
+    .map_err(internal)?
+    .into_iter()
+    .filter_map(|record| serde_json::to_value(record).ok())
+    .collect();
+    records.sort_by_key(|record| {
+        let id = record["id"]
+            .as_str()
+            .and_then(|id| Uuid::parse_str(id).ok());
+        ids.iter().position(|listed| Some(*listed) == id)
+    });
+
+    let mut headers = HeaderMap::new();
+    let total = R::total_count(db, &filter).await;
+    headers.insert(TOTAL_COUNT_HEADER, HeaderValue::from(total));
+    if let Some(next) = next
+        && let Ok(value) = HeaderValue::from_str(&next.encode())
+    {
+        headers.insert(NEXT_CURSOR_HEADER, value);
+    }
+    Ok((headers, records))
+}
+
+/// A page of a list by offset, as the resource's list route gives it,
+/// narrowed to a scope
+async fn offset_page<R>(
+    db: &DatabaseConnection,
+    query: Option<&str>,
+    scope: &ListScope,
+) -> Result<(HeaderMap, Vec<R::ListModel>), (StatusCode, String)>
+where
+    R: CRUDResource,
+{
+    let params: FilterOptions = serde_urlencoded::from_str(query.unwrap_or_default())
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    let (offset, limit) = crudcrate::filter::parse_pagination(&params);
+    let condition = in_list_scope(
+        crudcrate::filter::apply_filters::<R>(
+            params.filter.clone(),
+            &R::filterable_columns(),
+            db.get_database_backend(),
+        ),
+        Some(scope),
+    );
+    let (order_column, order) =
+        crudcrate::sort::parse_sorting(&params, &R::sortable_columns(), R::default_index_column());
+    let records = R::get_all(db, &condition, order_column, order, offset, limit)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let total = R::total_count(db, &condition).await;
+    let mut headers = calculate_content_range(offset, limit, total, R::RESOURCE_NAME_PLURAL);
+    headers.insert(TOTAL_COUNT_HEADER, HeaderValue::from(total));
+    Ok((headers, records))
+}
+
+/// Total given by a `Content-Range` header such as `experiments 0-9/42`
+fn content_range_total(headers: &HeaderMap) -> Option<u64> {
+    headers
+        .get("content-range")?
+        .to_str()
+        .ok()?
+        .rsplit_once('/')?
+        .1
+        .parse()
+        .ok()
+}
+
+/// Middleware of a resource's router, innermost, answering list requests
+/// with a `cursor` or `page_size` with a page by key. Other list requests
+/// are paged by offset as before, with the total added, here when they
+/// have a scope.
+pub async fn paginate_by_cursor<R>(
+    State(db): State<DatabaseConnection>,
+    request: Request,
+    next: Next,
+) -> Response
+where
+    R: CRUDResource,
+    R::ListModel: Serialize,
+{
+    if request.method() != Method::GET || !segments(request.uri().path()).is_empty() {
+        return next.run(request).await;
+    }
+    let scope = request.extensions().get::<ListScope>().cloned();
+    let query: CursorQuery =
+        serde_urlencoded::from_str(request.uri().query().unwrap_or_default()).unwrap_or_default();
+    if query.cursor.is_none() && query.page_size.is_none() {
+        if let Some(scope) = &scope {
+            return match offset_page::<R>(&db, request.uri().query(), scope).await {
+                Ok((headers, records)) => (headers, Json(records)).into_response(),
+                Err(rejection) => rejection.into_response(),
+            };
+        }
+        let mut response = next.run(request).await;
+        if let Some(total) = content_range_total(response.headers()) {
+            response
+                .headers_mut()
+                .insert(TOTAL_COUNT_HEADER, HeaderValue::from(total));
+        }
+        return response;
+    }
+    match cursor_page::<R>(&db, query, scope.as_ref()).await {
+        Ok((headers, records)) => (headers, Json(records)).into_response(),
+        Err(rejection) => rejection.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let cursor = Cursor {
+            after: Uuid::new_v4(),
+            value: Some(SortValue::Decimal("-12.3450".to_string())),
+            sort: r#"["name","DESC"]"#.to_string(),
+        };
+        assert_eq!(Cursor::decode(&cursor.encode()), Some(cursor));
+        assert_eq!(Cursor::decode("not a cursor"), None);
+    }
+
+    #[test]
+    fn test_content_range_total() {
+        let mut headers = HeaderMap::new();
+        headers.insert("content-range", HeaderValue::from_static("assets 0-9/42"));
+        assert_eq!(content_range_total(&headers), Some(42));
+    }
+}
@@ -0,0 +1,143 @@
+//! Partial updates with JSON Merge Patch (RFC 7396).
+//!
+//! A `PATCH` to a record changes only the fields its body names: a value
+//! replaces the field, `null` clears it, and an object is merged into the
+//! object the field holds. Arrays, such as the regions of an experiment, are
+//! replaced whole. Fields not named are left as they are, so a client can set
+//! one field without sending the record back. The update goes through the
+//! resource's usual update, with its validation.
+
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use crudcrate::CRUDResource;
+use sea_orm::{DatabaseConnection, DbErr};
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+/// Apply a merge patch to a document
+pub fn merge_patch(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    let Value::Object(fields) = target else {
+        unreachable!("The target was made an object");
+    };
+    for (field, value) in patch {
+        if value.is_null() {
+            fields.remove(field);
+        } else {
+            merge_patch(fields.entry(field.clone()).or_insert(Value::Null), value);
+        }
+    }
+}
+
+/// Update a record with a merge patch. Only the fields the patch names are
+/// sent to the update, with objects merged into the record's.
+pub async fn patch_record<R>(db: &DatabaseConnection, id: Uuid, patch: Value) -> Result<R, DbErr>
+where
+    R: CRUDResource + Serialize,
+    R::UpdateModel: DeserializeOwned,
+{
+    let Value::Object(patch) = patch else {
+        return Err(DbErr::Custom(
+            "A merge patch must be a JSON object".to_string(),
+        ));
+    };
+
+    // The record is only read when an object field is merged into
+    let current = if patch.values().any(Value::is_object) {
+        serde_json::to_value(R::get_one(db, id).await?).map_err(|e| DbErr::Custom(e.to_string()))?
+    } else {
+        Value::Object(Map::new())
+    };
+    let mut update = Map::new();
+    for (field, value) in patch {
+        let mut merged = current.get(&field).cloned().unwrap_or(Value::Null);
+        merge_patch(&mut merged, &value);
+        update.insert(field, merged);
+    }
+
+    let update: R::UpdateModel = serde_json::from_value(Value::Object(update))
+        .map_err(|e| DbErr::Custom(format!("Invalid patch: {e}")))?;
+    R::update(db, id, update).await
+}
+
+/// Handler for `PATCH /{id}` of a resource's router, answering as its `PUT`
+/// does
+pub async fn patch_one_handler<R>(
+    State(db): State<DatabaseConnection>,
+    Path(id): Path<Uuid>,
+    Json(patch): Json<Value>,
+) -> Result<Json<R>, (StatusCode, String)>
+where
+    R: CRUDResource + Serialize,
+    R::UpdateModel: DeserializeOwned,
+{
+    patch_record::<R>(&db, id, patch)
+        .await
+        .map(Json)
+        .map_err(|e| match e {
+            DbErr::Custom(message) => (StatusCode::UNPROCESSABLE_ENTITY, message),
+            DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+            e => match e.sql_err() {
+                Some(sea_orm::SqlErr::UniqueConstraintViolation(detail)) => {
+                    (StatusCode::CONFLICT, format!("Conflict: {detail}"))
+                }
+                _ => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+            },
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_merge_patch() {
+        // Examples of RFC 7396, appendix A
+        for (target, patch, result) in [
+            (json!({"a": "b"}), json!({"a": "c"}), json!({"a": "c"})),
+            (
+                json!({"a": "b"}),
+                json!({"b": "c"}),
+                json!({"a": "b", "b": "c"}),
+            ),
+            (json!({"a": "b"}), json!({"a": null}), json!({})),
+            (
+                json!({"a": {"b": "c"}}),
+                json!({"a": {"b": "d", "c": null}}),
+                json!({"a": {"b": "d"}}),
+            ),
+            (
+                json!({"a": [{"b": "c"}]}),
+                json!({"a": [1]}),
+                json!({"a": [1]}),
+            ),
+            (json!(["a", "b"]), json!(["c", "d"]), json!(["c", "d"])),
+            (json!({"a": "foo"}), json!("bar"), json!("bar")),
+            (
+                json!({"e": null}),
+                json!({"a": 1}),
+                json!({"e": null, "a": 1}),
+            ),
+            (
+                json!([1, 2]),
+                json!({"a": "b", "c": null}),
+                json!({"a": "b"}),
+            ),
+        ] {
+            let mut merged = target;
+            merge_patch(&mut merged, &patch);
+            assert_eq!(merged, result, "{patch}");
+        }
+    }
+}
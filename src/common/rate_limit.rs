@@ -0,0 +1,476 @@
+//! Rate limiting of clients by address and by user.
+//!
+//! Each address and each user has a budget of requests a minute, and each
+//! client a smaller one for the expensive routes: experiment results and
+//! the exports, archives and downloads. Responses tell the client what is
+//! left of its tightest budget in `X-RateLimit-*` headers, and requests over
+//! it get a 429 with `Retry-After`.
+//!
+//! Behind proxies, the client's address is the last one in the proxies'
+//! header that is not itself a trusted proxy, and the header is only read
+//! from connections of trusted proxies: earlier entries are the client's
+//! own word.
+//!
+//! Users are told apart by their verified credentials: the subject of a
+//! valid token or the id of a valid API key. Requests whose credentials do
+//! not verify count against their address alone, so no one can spend
+//! another user's budget by claiming their name.
+
+use crate::api_keys::services::verified_caller;
+use crate::common::keycloak::KeycloakAuth;
+use crate::config::Config;
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderMap, HeaderValue, Method, StatusCode, header::RETRY_AFTER},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Duration, Utc};
+use ipnet::IpNet;
+use sea_orm::DatabaseConnection;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+
+const LIMIT_HEADER: &str = "x-ratelimit-limit";
+const REMAINING_HEADER: &str = "x-ratelimit-remaining";
+const RESET_HEADER: &str = "x-ratelimit-reset";
+/// Windows kept before those of past minutes are dropped
+const PRUNE_AFTER: usize = 10_000;
+
+/// Route segments, after the record ID, of experiment exports and analyses
+const EXPENSIVE_EXPERIMENT_ROUTES: &[&str] = &[
+    "archive",
+    "bundle",
+    "detect-freezing",
+    "excel",
+    "image-diff",
+    "integrity",
+    "temperatures",
+    "timelapse",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Budget {
+    Address,
+    User,
+    Expensive,
+}
+
+impl Budget {
+    fn exceeded(self) -> &'static str {
+        match self {
+            Budget::Address => "Too many requests from this address",
+            Budget::User => "Too many requests by this user",
+            Budget::Expensive => "Too many requests for results and exports",
+        }
+    }
+}
+
+/// What is left of a budget in its current minute
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Usage {
+    pub limit: u32,
+    pub remaining: u32,
+    /// Seconds until the budget is renewed
+    pub reset: i64,
+}
+
+/// Start of a client's current minute and its requests in it
+type Window = (DateTime<Utc>, u32);
+
+pub struct RateLimiter {
+    per_address: Option<u32>,
+    per_user: Option<u32>,
+    expensive: Option<u32>,
+    client_address_header: Option<String>,
+    trusted_proxies: Vec<IpNet>,
+    windows: Mutex<HashMap<(Budget, String), Window>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            per_address: config.rate_limit_per_address,
+            per_user: config.rate_limit_per_user,
+            expensive: config.rate_limit_expensive,
+            client_address_header: config.client_address_header.clone(),
+            trusted_proxies: config.trusted_proxies.clone(),
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.per_address.is_some() || self.per_user.is_some() || self.expensive.is_some()
+    }
+
+    /// Count a request against each budget, or none of them when one is
+    /// used up. Gives the tightest budget, and which one was used up.
+    fn check(
+        &self,
+        budgets: &[(Budget, String, u32)],
+        now: DateTime<Utc>,
+    ) -> Result<Option<Usage>, (Budget, Usage)> {
+        let mut windows = self
+            .windows
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        if windows.len() > PRUNE_AFTER {
+            windows.retain(|_, (start, _)| now - *start < Duration::minutes(1));
+        }
+
+        let mut usages = Vec::with_capacity(budgets.len());
+        for (budget, client, limit) in budgets {
+            let (start, count) = windows.entry((*budget, client.clone())).or_insert((now, 0));
+            if now - *start >= Duration::minutes(1) {
+                (*start, *count) = (now, 0);
+            }
+            let usage = Usage {
+                limit: *limit,
+                remaining: limit.saturating_sub(*count),
+                reset: (*start + Duration::minutes(1) - now).num_seconds().max(1),
+            };
+            if usage.remaining == 0 {
+                return Err((*budget, usage));
+            }
+            usages.push(usage);
+        }
+
+        for (budget, client, _) in budgets {
+            if let Some((_, count)) = windows.get_mut(&(*budget, client.clone())) {
+                *count += 1;
+            }
+        }
+        Ok(usages
+            .into_iter()
+            .map(|usage| Usage {
+                remaining: usage.remaining - 1,
+                ..usage
+            })
+            .min_by_key(|usage| usage.remaining))
+    }
+
+    fn is_trusted(&self, address: &IpAddr) -> bool {
+        self.trusted_proxies
+            .iter()
+            .any(|proxy| proxy.contains(address))
+    }
+
+    /// Address of the client, taken from the proxies' header when it comes
+    /// through a trusted proxy
+    fn address(&self, request: &Request) -> String {
+        let Some(peer) = request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|info| info.0.ip())
+        else {
+            return "unknown".to_string();
+        };
+        let Some(header) = self
+            .client_address_header
+            .as_deref()
+            .filter(|_| self.is_trusted(&peer))
+        else {
+            return peer.to_string();
+        };
+        // Each proxy adds the address it was reached from to the end
+        let forwarded: Vec<&str> = request
+            .headers()
+            .get_all(header)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .filter(|address| !address.is_empty())
+            .collect();
+        forwarded
+            .iter()
+            .rev()
+            .find(|address| {
+                address
+                    .parse::<IpAddr>()
+                    .map_or(true, |address| !self.is_trusted(&address))
+            })
+            .or_else(|| forwarded.first())
+            .map_or_else(|| peer.to_string(), |address| (*address).to_string())
+    }
+}
+
+/// Whether the route computes experiment results or builds exports
+fn is_expensive(method: &Method, path: &str) -> bool {
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    match segments.as_slice() {
+        ["api", "exports", ..] | ["api", "assets", "download", ..] => true,
+        ["api", "experiments", id] => *method == Method::GET && uuid::Uuid::parse_str(id).is_ok(),
+        ["api", "experiments", _, route] => EXPENSIVE_EXPERIMENT_ROUTES.contains(route),
+        _ => false,
+    }
+}
+
+fn set_headers(headers: &mut HeaderMap, usage: Usage) {
+    for (name, value) in [
+        (LIMIT_HEADER, i64::from(usage.limit)),
+        (REMAINING_HEADER, i64::from(usage.remaining)),
+        (RESET_HEADER, usage.reset),
+    ] {
+        headers.insert(name, HeaderValue::from(value));
+    }
+}
+
+/// Middleware counting each request against the budgets of its address, its
+/// user and, on expensive routes, its client
+pub async fn limit_rate(
+    State((limiter, db, auth)): State<(
+        Arc<RateLimiter>,
+        DatabaseConnection,
+        Option<Arc<KeycloakAuth>>,
+    )>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !limiter.is_enabled() || matches!(request.uri().path(), "/healthz" | "/readyz") {
+        return next.run(request).await;
+    }
+
+    let address = limiter.address(&request);
+    let user = if limiter.per_user.is_some() || limiter.expensive.is_some() {
+        verified_caller(&db, auth.as_deref(), request.headers()).await
+    } else {
+        None
+    };
+    let mut budgets = Vec::new();
+    if let Some(limit) = limiter.per_address {
+        budgets.push((Budget::Address, address.clone(), limit));
+    }
+    if let (Some(limit), Some(user)) = (limiter.per_user, &user) {
+        budgets.push((Budget::User, user.clone(), limit));
+    }
+    if let Some(limit) = limiter.expensive
+        && is_expensive(request.method(), request.uri().path())
+    {
+        budgets.push((Budget::Expensive, user.unwrap_or(address), limit));
+    }
+
+    match limiter.check(&budgets, Utc::now()) {
+        Ok(usage) => {
+            let mut response = next.run(request).await;
+            if let Some(usage) = usage {
+                set_headers(response.headers_mut(), usage);
+            }
+            response
+        }
+        Err((budget, usage)) => {
+            let mut response = (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(RETRY_AFTER, usage.reset.to_string())],
+                budget.exceeded(),
+            )
+                .into_response();
+            set_headers(response.headers_mut(), usage);
+            response
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::keycloak::test_realm;
+    use crate::config::test_helpers::setup_test_db;
+    use crate::routes::{build_router, build_router_with_auth};
+    use axum::body::Body;
+    use tower::ServiceExt;
+
+    fn limiter(
+        per_address: Option<u32>,
+        per_user: Option<u32>,
+        expensive: Option<u32>,
+    ) -> RateLimiter {
+        let mut config = Config::for_tests();
+        config.rate_limit_per_address = per_address;
+        config.rate_limit_per_user = per_user;
+        config.rate_limit_expensive = expensive;
+        RateLimiter::new(&config)
+    }
+
+    #[test]
+    fn test_budgets() {
+        let limiter = limiter(Some(2), Some(5), None);
+        let now = Utc::now();
+        let budgets = [
+            (Budget::Address, "10.0.0.1".to_string(), 2),
+            (Budget::User, "user:alice".to_string(), 5),
+        ];
+
+        let usage = limiter.check(&budgets, now).unwrap().unwrap();
+        assert_eq!((usage.limit, usage.remaining, usage.reset), (2, 1, 60));
+        assert_eq!(limiter.check(&budgets, now).unwrap().unwrap().remaining, 0);
+        let (budget, usage) = limiter
+            .check(&budgets, now + Duration::seconds(20))
+            .unwrap_err();
+        assert_eq!(budget, Budget::Address);
+        assert_eq!((usage.remaining, usage.reset), (0, 40));
+
+        // The rejected request did not count against the user
+        let other_address = [
+            (Budget::Address, "10.0.0.2".to_string(), 2),
+            (Budget::User, "user:alice".to_string(), 5),
+        ];
+        let usage = limiter.check(&other_address, now).unwrap().unwrap();
+        assert_eq!(usage.remaining, 1);
+        let user = &other_address[1..];
+        assert_eq!(limiter.check(user, now).unwrap().unwrap().remaining, 1);
+        assert_eq!(limiter.check(user, now).unwrap().unwrap().remaining, 0);
+        assert_eq!(limiter.check(user, now).unwrap_err().0, Budget::User);
+
+        // Budgets are renewed each minute
+        assert!(limiter.check(&budgets, now + Duration::minutes(1)).is_ok());
+    }
+
+    #[test]
+    fn test_expensive_routes() {
+        let id = uuid::Uuid::new_v4();
+        assert!(is_expensive(
+            &Method::GET,
+            &format!("/api/experiments/{id}")
+        ));
+        assert!(!is_expensive(
+            &Method::PUT,
+            &format!("/api/experiments/{id}")
+        ));
+        assert!(!is_expensive(&Method::GET, "/api/experiments/batch"));
+        assert!(is_expensive(
+            &Method::GET,
+            &format!("/api/experiments/{id}/excel")
+        ));
+        assert!(!is_expensive(
+            &Method::GET,
+            &format!("/api/experiments/{id}/shares")
+        ));
+        assert!(is_expensive(&Method::POST, "/api/exports"));
+        assert!(is_expensive(&Method::GET, "/api/assets/download/token"));
+        assert!(!is_expensive(&Method::GET, "/api/samples"));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_router() {
+        let mut config = Config::for_tests();
+        config.keycloak_url = String::new();
+        config.rate_limit_per_address = Some(3);
+        config.rate_limit_expensive = Some(1);
+        config.client_address_header = Some("X-Forwarded-For".to_string());
+        config.trusted_proxies = vec!["10.0.0.0/24".parse().unwrap()];
+        let app = build_router(&setup_test_db().await, &config).layer(axum::Extension(
+            ConnectInfo(SocketAddr::from(([10, 0, 0, 254], 41000))),
+        ));
+        // The client's own entry first, then the address the inner proxy was
+        // reached from
+        let get = |uri: String, address: &str| {
+            app.clone().oneshot(
+                Request::builder()
+                    .uri(uri)
+                    .header("x-forwarded-for", format!("{address}, 10.0.0.253"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+        };
+
+        let response = get("/api/projects".to_string(), "192.0.2.1").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[LIMIT_HEADER], "3");
+        assert_eq!(response.headers()[REMAINING_HEADER], "2");
+
+        let experiment = format!("/api/experiments/{}", uuid::Uuid::new_v4());
+        let response = get(experiment.clone(), "192.0.2.1").await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(response.headers()[LIMIT_HEADER], "1");
+        assert_eq!(response.headers()[REMAINING_HEADER], "0");
+        let response = get(experiment, "192.0.2.1").await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(response.headers().contains_key(RETRY_AFTER));
+
+        let response = get("/api/projects".to_string(), "192.0.2.1").await.unwrap();
+        assert_eq!(response.headers()[REMAINING_HEADER], "0");
+        let response = get("/api/projects".to_string(), "192.0.2.1").await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let response = get("/api/projects".to_string(), "192.0.2.2").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = get("/healthz".to_string(), "192.0.2.1").await.unwrap();
+        assert!(!response.headers().contains_key(LIMIT_HEADER));
+
+        // An address the client puts in front of its own is not believed
+        let response = get("/api/projects".to_string(), "198.51.100.7, 192.0.2.1")
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[test]
+    fn test_client_address() {
+        let mut config = Config::for_tests();
+        config.client_address_header = Some("X-Forwarded-For".to_string());
+        config.trusted_proxies = vec!["10.0.0.0/24".parse().unwrap()];
+        let limiter = RateLimiter::new(&config);
+        let address = |peer: [u8; 4], forwarded: &str| {
+            let mut request = Request::builder()
+                .header("x-forwarded-for", forwarded)
+                .body(Body::empty())
+                .unwrap();
+            request
+                .extensions_mut()
+                .insert(ConnectInfo(SocketAddr::from((peer, 41000))));
+            limiter.address(&request)
+        };
+
+        assert_eq!(
+            address([10, 0, 0, 254], "192.0.2.9, 192.0.2.1"),
+            "192.0.2.1"
+        );
+        assert_eq!(
+            address([10, 0, 0, 254], "192.0.2.1, 10.0.0.253"),
+            "192.0.2.1"
+        );
+        // Only trusted proxies are taken at their word
+        assert_eq!(address([203, 0, 113, 5], "192.0.2.1"), "203.0.113.5");
+        // A request from within the proxies' network
+        assert_eq!(address([10, 0, 0, 254], "10.0.0.12"), "10.0.0.12");
+    }
+
+    #[tokio::test]
+    async fn test_users_are_told_apart_by_verified_tokens() {
+        let mut config = Config::for_tests();
+        config.rate_limit_per_user = Some(2);
+        let app = build_router_with_auth(
+            &setup_test_db().await,
+            &config,
+            Some(test_realm::auth(&config)),
+        );
+        let get = |token: &str| {
+            app.clone().oneshot(
+                Request::builder()
+                    .uri("/api/projects")
+                    .header("authorization", format!("Bearer {token}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+        };
+        let ada = test_realm::token("ada", &["spice-admin"], &[]);
+
+        // A token claiming to be ada without her signature spends nothing
+        // of her budget
+        let (unsigned, _) = ada.rsplit_once('.').unwrap();
+        let forged = format!("{unsigned}.AAAA");
+        for _ in 0..3 {
+            let response = get(&forged).await.unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+            assert!(!response.headers().contains_key(LIMIT_HEADER));
+        }
+
+        let response = get(&ada).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[REMAINING_HEADER], "1");
+        assert_eq!(get(&ada).await.unwrap().status(), StatusCode::OK);
+        let response = get(&ada).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+}
@@ -0,0 +1,194 @@
+//! Redaction of personal information for viewers.
+//!
+//! Data may be shared with viewers, such as collaborators outside the lab,
+//! without telling them who did what. When a request is made with a token or
+//! API key giving no more than the viewer role, the JSON it gets back has
+//! the fields naming users, their emails, the free-text remarks and the
+//! bodies of comments cleared, at any depth. Editors and administrators see
+//! everything, as do requests without a token when authentication is off.
+
+use crate::common::auth::Role;
+use axum::{
+    Extension,
+    body::{Body, to_bytes},
+    extract::Request,
+    http::{
+        StatusCode,
+        header::{CONTENT_LENGTH, CONTENT_TYPE},
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use axum_keycloak_auth::decode::KeycloakToken;
+use serde_json::Value;
+
+/// Fields cleared from the responses to viewers
+const REDACTED_FIELDS: &[&str] = &[
+    "archived_by",
+    "body",
+    "created_by",
+    "email",
+    "grantee",
+    "granted_by",
+    "mentions",
+    "operator",
+    "qc_reviewed_by",
+    "recorded_by",
+    "remarks",
+    "revoked_by",
+    "uploaded_by",
+    "username",
+];
+
+/// Whether the roles of a user give them no more than reading
+fn is_viewer_only(token: &KeycloakToken<Role>) -> bool {
+    !token
+        .roles
+        .iter()
+        .any(|role| role.role().grants(&Role::Editor))
+}
+
+/// Clear the personal fields of a response body, at any depth
+pub fn redact(json: &mut Value) {
+    match json {
+        Value::Array(values) => values.iter_mut().for_each(redact),
+        Value::Object(fields) => {
+            for (field, value) in fields {
+                if REDACTED_FIELDS.contains(&field.as_str()) {
+                    *value = Value::Null;
+                } else {
+                    redact(value);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Middleware, behind the layers authenticating the request, redacting the
+/// JSON responses to viewers. Files and other responses pass unchanged.
+pub async fn redact_for_viewers(
+    token: Option<Extension<KeycloakToken<Role>>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let response = next.run(request).await;
+    if !token.is_some_and(|Extension(token)| is_viewer_only(&token)) {
+        return response;
+    }
+    let is_json = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+    if !is_json {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let Ok(bytes) = to_bytes(body, usize::MAX).await else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    let Ok(mut json) = serde_json::from_slice::<Value>(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+    redact(&mut json);
+    parts.headers.remove(CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(json.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_keys::models::{ApiKeyCreate, ApiKeyRole};
+    use crate::api_keys::services::{accept_api_keys, create_api_key};
+    use crate::config::test_helpers::setup_test_db;
+    use axum::{Json, Router, middleware, routing::get};
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_redact_nested_fields() {
+        let mut json = json!([{
+            "name": "Run 1",
+            "username": "alice",
+            "remarks": "Alice's bench",
+            "assets": [{"uploaded_by": "bob", "original_filename": "run1.xlsx"}],
+        }]);
+        redact(&mut json);
+        assert_eq!(
+            json,
+            json!([{
+                "name": "Run 1",
+                "username": null,
+                "remarks": null,
+                "assets": [{"uploaded_by": null, "original_filename": "run1.xlsx"}],
+            }])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_redact_for_viewers() {
+        let db = setup_test_db().await;
+        let app = Router::new()
+            .route(
+                "/",
+                get(|| async { Json(json!({"name": "Run 1", "username": "alice"})) }),
+            )
+            .route("/file", get(|| async { "username: alice" }))
+            .layer(middleware::from_fn(redact_for_viewers))
+            .layer(middleware::from_fn_with_state(
+                (db.clone(), "experiments"),
+                accept_api_keys,
+            ));
+        let get_as = |key: Option<String>, uri: &'static str| {
+            let app = app.clone();
+            async move {
+                let mut request = axum::http::Request::builder().uri(uri);
+                if let Some(key) = key {
+                    request = request.header("x-api-key", key);
+                }
+                let response = app
+                    .oneshot(request.body(Body::empty()).unwrap())
+                    .await
+                    .unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                String::from_utf8(bytes.to_vec()).unwrap()
+            }
+        };
+        let mut keys = Vec::new();
+        for role in [ApiKeyRole::Viewer, ApiKeyRole::Editor] {
+            let created = create_api_key(
+                &db,
+                ApiKeyCreate {
+                    name: format!("{role:?} key"),
+                    username: Some("alice".to_string()),
+                    role,
+                    scopes: vec!["experiments".to_string()],
+                    rate_limit_per_minute: None,
+                    lab: None,
+                    expires_at: None,
+                },
+                None,
+            )
+            .await
+            .unwrap();
+            keys.push(created.key);
+        }
+
+        let viewed: Value =
+            serde_json::from_str(&get_as(Some(keys[0].clone()), "/").await).unwrap();
+        assert_eq!(viewed, json!({"name": "Run 1", "username": null}));
+        assert_eq!(
+            get_as(Some(keys[0].clone()), "/file").await,
+            "username: alice"
+        );
+        let edited: Value =
+            serde_json::from_str(&get_as(Some(keys[1].clone()), "/").await).unwrap();
+        assert_eq!(edited["username"], "alice");
+        // Without authentication there is no one to redact for
+        let open: Value = serde_json::from_str(&get_as(None, "/").await).unwrap();
+        assert_eq!(open["username"], "alice");
+    }
+}
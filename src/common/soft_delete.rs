@@ -0,0 +1,211 @@
+//! Soft deletion of records.
+//!
+//! Deleting an experiment, sample, treatment or tray configuration marks it
+//! with `deleted_at` instead of removing it, so the records hanging from it,
+//! such as phase transitions, are kept. Deleted records are left out of
+//! lists and their counts, and answered 404 to, until restored with
+//! `POST /{id}/restore`.
+//! Adding `deleted=true` to a read lists only the deleted records, or reads
+//! one of them.
+
+use super::fields::take_list_parameter;
+use crate::projects::access::{narrow_list_scope, reads_collection, segments};
+use axum::{
+    Json,
+    extract::{Path, Request, State},
+    http::{Method, StatusCode, Uri},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use crudcrate::CRUDResource;
+use sea_orm::{
+    ColumnTrait, Condition, ConnectionTrait, DatabaseBackend, DatabaseConnection, DbErr,
+    EntityTrait, QueryFilter, QuerySelect,
+    sea_query::{Expr, Query},
+};
+use serde::Serialize;
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+/// Query parameter asking for deleted records
+pub const DELETED_PARAMETER: &str = "deleted";
+
+/// Mark records deleted, giving the IDs of those that were not already
+pub async fn soft_delete_many<E: EntityTrait>(
+    db: &DatabaseConnection,
+    id_column: E::Column,
+    deleted_at_column: E::Column,
+    ids: Vec<Uuid>,
+) -> Result<Vec<Uuid>, DbErr> {
+    let deleted: Vec<Uuid> = E::find()
+        .select_only()
+        .column(id_column)
+        .filter(id_column.is_in(ids))
+        .filter(deleted_at_column.is_null())
+        .into_tuple()
+        .all(db)
+        .await?;
+    if !deleted.is_empty() {
+        E::update_many()
+            .col_expr(deleted_at_column, Expr::value(Some(Utc::now())))
+            .filter(id_column.is_in(deleted.clone()))
+            .exec(db)
+            .await?;
+    }
+    Ok(deleted)
+}
+
+/// Mark a record deleted
+pub async fn soft_delete<E: EntityTrait>(
+    db: &DatabaseConnection,
+    id_column: E::Column,
+    deleted_at_column: E::Column,
+    id: Uuid,
+) -> Result<Uuid, DbErr> {
+    soft_delete_many::<E>(db, id_column, deleted_at_column, vec![id])
+        .await?
+        .pop()
+        .ok_or_else(|| DbErr::RecordNotFound("Record not found".to_string()))
+}
+
+/// Column a resource marks its deleted records in, if it keeps them
+pub(crate) fn deleted_at_column<R: CRUDResource>() -> Option<R::ColumnType> {
+    R::filterable_columns()
+        .into_iter()
+        .find(|(name, _)| *name == "deleted_at")
+        .map(|(_, column)| column)
+}
+
+/// When a record was deleted, `None` when it does not exist and
+/// `Some(None)` when it is not deleted
+async fn deleted_at<R: CRUDResource>(
+    db: &DatabaseConnection,
+    column: R::ColumnType,
+    id: Uuid,
+) -> Result<Option<Option<DateTime<Utc>>>, DbErr> {
+    R::EntityType::find_by_id(id)
+        .select_only()
+        .column(column)
+        .into_tuple()
+        .one(db)
+        .await
+}
+
+/// The full-text search a list asks for, as a condition on the resource's
+/// table, with the list's filter taken off. A search ignores other filters,
+/// and is run on the table alone, apart from the records the list joins.
+fn take_search<R: CRUDResource>(uri: &Uri, backend: DatabaseBackend) -> Option<(Uri, Condition)> {
+    let (uri, filter) = take_list_parameter(uri, "filter")?;
+    let filter: Value = serde_json::from_str(&filter).ok()?;
+    let search = filter.get("q")?;
+    let matching = Query::select()
+        .column(R::ID_COLUMN)
+        .from(R::EntityType::default())
+        .cond_where(crudcrate::filter::apply_filters::<R>(
+            Some(json!({ "q": search }).to_string()),
+            &R::filterable_columns(),
+            backend,
+        ))
+        .to_owned();
+    Some((
+        uri,
+        Condition::all().add(R::ID_COLUMN.in_subquery(matching)),
+    ))
+}
+
+/// Middleware of a resource's router, outside the paging of its lists,
+/// hiding its deleted records
+pub async fn hide_deleted<R: CRUDResource>(
+    State(db): State<DatabaseConnection>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let Some(column) = deleted_at_column::<R>() else {
+        return next.run(request).await;
+    };
+    let deleted = match take_list_parameter(request.uri(), DELETED_PARAMETER) {
+        Some((uri, deleted)) => {
+            *request.uri_mut() = uri;
+            deleted == "true"
+        }
+        None => false,
+    };
+    let reading = request.method() == Method::GET;
+    let path = request.uri().path().to_string();
+    match segments(&path).as_slice() {
+        segments if reads_collection(request.method(), segments) => {
+            let mut condition = Condition::all().add(if deleted {
+                column.is_not_null()
+            } else {
+                column.is_null()
+            });
+            if let Some((uri, search)) = take_search::<R>(request.uri(), db.get_database_backend())
+            {
+                *request.uri_mut() = uri;
+                condition = condition.add(search);
+            }
+            narrow_list_scope(request.extensions_mut(), condition);
+        }
+        [id] => {
+            if let Ok(id) = Uuid::parse_str(id) {
+                match deleted_at::<R>(&db, column, id).await {
+                    Ok(Some(Some(_))) if !(reading && deleted) => {
+                        return (
+                            StatusCode::NOT_FOUND,
+                            format!("The {} was deleted", R::RESOURCE_NAME_SINGULAR),
+                        )
+                            .into_response();
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+    next.run(request).await
+}
+
+/// Handler for `POST /{id}/restore` of a resource's router, taking a record
+/// out of the deleted ones
+pub async fn restore_one_handler<R>(
+    State(db): State<DatabaseConnection>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<R>, (StatusCode, String)>
+where
+    R: CRUDResource + Serialize,
+{
+    let internal = |e: DbErr| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+    let singular = R::RESOURCE_NAME_SINGULAR;
+    let column = deleted_at_column::<R>().ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            format!("Deleted {} are not kept", R::RESOURCE_NAME_PLURAL),
+        )
+    })?;
+    match deleted_at::<R>(&db, column, id).await.map_err(internal)? {
+        None => {
+            return Err((
+                StatusCode::NOT_FOUND,
+                format!("The {singular} was not found"),
+            ));
+        }
+        Some(None) => {
+            return Err((
+                StatusCode::CONFLICT,
+                format!("The {singular} is not deleted"),
+            ));
+        }
+        Some(Some(_)) => {}
+    }
+    R::EntityType::update_many()
+        .col_expr(column, Expr::value(Option::<DateTime<Utc>>::None))
+        .filter(R::ID_COLUMN.eq(id))
+        .exec(&db)
+        .await
+        .map_err(internal)?;
+    R::get_one(&db, id).await.map(Json).map_err(internal)
+}
@@ -0,0 +1,236 @@
+//! Spatial filters on the `PostGIS` geometry of samples and locations.
+//!
+//! Samples have a point `geom` generated from their coordinates and
+//! locations the convex hull of their samples, both in WGS 84 (SRID 4326).
+//! Coordinates are given longitude first, as in `GeoJSON`. The filters need
+//! `PostgreSQL` with `PostGIS`; other databases reject them.
+
+use sea_orm::{Condition, DbBackend, DbErr, Value, sea_query::Expr};
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+pub const DEFAULT_LIMIT: u64 = 100;
+pub const MAX_LIMIT: u64 = 1000;
+
+#[derive(Clone, Debug, Default, Deserialize, IntoParams)]
+pub struct SpatialQuery {
+    /// `min_lon,min_lat,max_lon,max_lat`
+    pub within_bbox: Option<String>,
+    /// `lon,lat,metres`: within that distance of the point
+    pub within_radius: Option<String>,
+    /// `lon lat,lon lat,...`: the vertices of a polygon, closed automatically
+    pub within_polygon: Option<String>,
+    /// Most results to return, up to 1000 (default 100)
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+}
+
+fn parse_numbers(name: &str, text: &str, separator: char) -> Result<Vec<f64>, DbErr> {
+    text.split(separator)
+        .map(|number| {
+            number
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|number| number.is_finite())
+                .ok_or_else(|| DbErr::Custom(format!("{name} has an invalid number '{number}'")))
+        })
+        .collect()
+}
+
+fn check_point(name: &str, lon: f64, lat: f64) -> Result<(), DbErr> {
+    if (-180.0..=180.0).contains(&lon) && (-90.0..=90.0).contains(&lat) {
+        Ok(())
+    } else {
+        Err(DbErr::Custom(format!(
+            "{name} has a point outside longitudes -180 to 180 and latitudes -90 to 90"
+        )))
+    }
+}
+
+/// `min_lon,min_lat,max_lon,max_lat` of a `within_bbox` filter
+pub fn bbox(text: &str) -> Result<[f64; 4], DbErr> {
+    let [min_lon, min_lat, max_lon, max_lat] = parse_numbers("within_bbox", text, ',')?[..] else {
+        return Err(DbErr::Custom(
+            "within_bbox must be min_lon,min_lat,max_lon,max_lat".to_string(),
+        ));
+    };
+    check_point("within_bbox", min_lon, min_lat)?;
+    check_point("within_bbox", max_lon, max_lat)?;
+    if min_lon > max_lon || min_lat > max_lat {
+        return Err(DbErr::Custom(
+            "within_bbox must give the minimum longitude and latitude first".to_string(),
+        ));
+    }
+    Ok([min_lon, min_lat, max_lon, max_lat])
+}
+
+fn radius(text: &str) -> Result<[f64; 3], DbErr> {
+    let [lon, lat, metres] = parse_numbers("within_radius", text, ',')?[..] else {
+        return Err(DbErr::Custom(
+            "within_radius must be lon,lat,metres".to_string(),
+        ));
+    };
+    check_point("within_radius", lon, lat)?;
+    if metres <= 0.0 {
+        return Err(DbErr::Custom(
+            "within_radius must have a positive distance".to_string(),
+        ));
+    }
+    Ok([lon, lat, metres])
+}
+
+/// The polygon as WKT
+fn polygon(text: &str) -> Result<String, DbErr> {
+    let mut points = Vec::new();
+    for vertex in text.split(',') {
+        let [lon, lat] = parse_numbers("within_polygon", vertex.trim(), ' ')?[..] else {
+            return Err(DbErr::Custom(format!(
+                "within_polygon vertex '{}' must be 'lon lat'",
+                vertex.trim()
+            )));
+        };
+        check_point("within_polygon", lon, lat)?;
+        points.push((lon, lat));
+    }
+    if points.first() != points.last() {
+        points.push(points[0]);
+    }
+    if points.len() < 4 {
+        return Err(DbErr::Custom(
+            "within_polygon needs at least three vertices".to_string(),
+        ));
+    }
+    let ring: Vec<String> = points
+        .iter()
+        .map(|(lon, lat)| format!("{lon} {lat}"))
+        .collect();
+    Ok(format!("POLYGON(({}))", ring.join(", ")))
+}
+
+/// A condition on `geometry`, a column or expression of SRID 4326, matching
+/// every filter of the query. Invalid or missing filters are returned as
+/// `DbErr::Custom`.
+pub fn spatial_condition(
+    backend: DbBackend,
+    geometry: &str,
+    query: &SpatialQuery,
+) -> Result<Condition, DbErr> {
+    let mut condition = Condition::all();
+    let mut filters = 0;
+    if let Some(text) = &query.within_bbox {
+        let [min_lon, min_lat, max_lon, max_lat] = bbox(text)?;
+        condition = condition.add(Expr::cust_with_values(
+            format!("ST_Intersects({geometry}, ST_MakeEnvelope($1, $2, $3, $4, 4326))"),
+            [min_lon, min_lat, max_lon, max_lat],
+        ));
+        filters += 1;
+    }
+    if let Some(text) = &query.within_radius {
+        let [lon, lat, metres] = radius(text)?;
+        condition = condition.add(Expr::cust_with_values(
+            format!(
+                "ST_DWithin({geometry}::geography, ST_SetSRID(ST_MakePoint($1, $2), 4326)::geography, $3)"
+            ),
+            [lon, lat, metres],
+        ));
+        filters += 1;
+    }
+    if let Some(text) = &query.within_polygon {
+        let wkt = polygon(text)?;
+        condition = condition.add(Expr::cust_with_values(
+            format!("ST_Intersects({geometry}, ST_GeomFromText($1, 4326))"),
+            [Value::from(wkt)],
+        ));
+        filters += 1;
+    }
+    if filters == 0 {
+        return Err(DbErr::Custom(
+            "Give within_bbox, within_radius or within_polygon".to_string(),
+        ));
+    }
+    if backend != DbBackend::Postgres {
+        return Err(DbErr::Custom(
+            "Spatial filters need a PostgreSQL database with PostGIS".to_string(),
+        ));
+    }
+    Ok(condition)
+}
+
+/// `(offset, limit)` of the query, rejecting limits out of range as
+/// `DbErr::Custom`
+pub fn page(query: &SpatialQuery) -> Result<(u64, u64), DbErr> {
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return Err(DbErr::Custom(format!(
+            "limit must be between 1 and {MAX_LIMIT}"
+        )));
+    }
+    Ok((query.offset.unwrap_or(0), limit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(bbox: Option<&str>, radius: Option<&str>, polygon: Option<&str>) -> SpatialQuery {
+        SpatialQuery {
+            within_bbox: bbox.map(str::to_string),
+            within_radius: radius.map(str::to_string),
+            within_polygon: polygon.map(str::to_string),
+            ..SpatialQuery::default()
+        }
+    }
+
+    fn error(query: &SpatialQuery) -> String {
+        match spatial_condition(DbBackend::Postgres, "samples.geom", query) {
+            Err(DbErr::Custom(message)) => message,
+            other => panic!("expected an error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_spatial_condition() {
+        let condition = spatial_condition(
+            DbBackend::Postgres,
+            "samples.geom",
+            &query(
+                Some("5.9,45.8,10.5,47.8"),
+                Some("7.98,46.55,5000"),
+                Some("7 46, 8 46, 8 47"),
+            ),
+        )
+        .unwrap();
+        let sql = sea_orm::sea_query::Query::select()
+            .expr(Expr::val(1))
+            .cond_where(condition)
+            .to_string(sea_orm::sea_query::PostgresQueryBuilder);
+        assert!(
+            sql.contains(
+                "ST_Intersects(samples.geom, ST_MakeEnvelope(5.9, 45.8, 10.5, 47.8, 4326))"
+            ),
+            "{sql}"
+        );
+        assert!(sql.contains("ST_DWithin(samples.geom::geography, ST_SetSRID(ST_MakePoint(7.98, 46.55), 4326)::geography, 5000)"), "{sql}");
+        assert!(
+            sql.contains("ST_GeomFromText('POLYGON((7 46, 8 46, 8 47, 7 46))', 4326)"),
+            "{sql}"
+        );
+
+        assert!(error(&query(Some("10,45,5,47"), None, None)).contains("minimum"));
+        assert!(error(&query(Some("5,45,10"), None, None)).contains("min_lon"));
+        assert!(error(&query(None, Some("7,46,-1"), None)).contains("positive"));
+        assert!(error(&query(None, Some("7,95,10"), None)).contains("latitudes"));
+        assert!(error(&query(None, None, Some("7 46, 8 46"))).contains("three"));
+        assert!(error(&query(None, None, Some("7 46, x 46, 8 47"))).contains("'x'"));
+        assert!(error(&query(None, None, None)).contains("within_bbox"));
+        assert!(
+            spatial_condition(
+                DbBackend::Sqlite,
+                "samples.geom",
+                &query(Some("5,45,10,47"), None, None)
+            )
+            .is_err()
+        );
+    }
+}
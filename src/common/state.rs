@@ -1,6 +1,11 @@
+use crate::assets::archive::ArchiveLayout;
+use crate::assets::integrity::IntegrityAudit;
+use crate::assets::orphans::OrphanCleanup;
+use crate::common::cache::ResponseCache;
+use crate::common::keycloak::KeycloakAuth;
 use crate::config::Config;
+use crate::exports::models::ExportJob;
 use crate::services::processing::excel_processor::DataProcessingService;
-use axum_keycloak_auth::instance::KeycloakAuthInstance;
 use chrono::{DateTime, Utc};
 use sea_orm::DatabaseConnection;
 use std::collections::HashMap;
@@ -8,85 +13,95 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
+/// Archive planned on a download token's first use, so resumed requests see
+/// the same bytes
 #[derive(Clone, Debug)]
-pub struct DownloadToken {
-    pub asset_ids: Vec<Uuid>,
-    pub created_at: DateTime<Utc>,
-    pub experiment_id: Option<Uuid>, // For experiment downloads
+struct PlannedDownload {
+    layout: Arc<ArchiveLayout>,
+    /// When the token expires, and the download can no longer be resumed
+    expires_at: DateTime<Utc>,
 }
 
 #[derive(Clone)]
 pub struct AppState {
     pub db: DatabaseConnection,
     pub config: Config,
-    pub keycloak_auth_instance: Option<Arc<KeycloakAuthInstance>>,
+    pub keycloak_auth_instance: Option<Arc<KeycloakAuth>>,
     pub data_processing_service: DataProcessingService,
-    pub download_tokens: Arc<RwLock<HashMap<String, DownloadToken>>>,
+    /// Responses of the records dashboards poll
+    pub response_cache: ResponseCache,
+    /// Archives of the downloads in progress, by download token ID
+    download_layouts: Arc<RwLock<HashMap<Uuid, PlannedDownload>>>,
+    pub export_jobs: Arc<RwLock<HashMap<Uuid, ExportJob>>>,
+    /// Most recent bucket-wide integrity audit
+    pub integrity_audit: Arc<RwLock<Option<IntegrityAudit>>>,
+    /// Most recent orphaned object cleanup
+    pub orphan_cleanup: Arc<RwLock<Option<OrphanCleanup>>>,
 }
 
 impl AppState {
     pub fn new(
         db: DatabaseConnection,
         config: Config,
-        keycloak_auth_instance: Option<Arc<KeycloakAuthInstance>>,
+        keycloak_auth_instance: Option<Arc<KeycloakAuth>>,
     ) -> Self {
         let data_processing_service = DataProcessingService::new(db.clone());
+        let response_cache = ResponseCache::new(&config);
 
         Self {
             db,
             config,
             keycloak_auth_instance,
             data_processing_service,
-            download_tokens: Arc::new(RwLock::new(HashMap::new())),
+            response_cache,
+            download_layouts: Arc::new(RwLock::new(HashMap::new())),
+            export_jobs: Arc::new(RwLock::new(HashMap::new())),
+            integrity_audit: Arc::new(RwLock::new(None)),
+            orphan_cleanup: Arc::new(RwLock::new(None)),
         }
     }
 
-    /// Create a download token for assets
-    pub async fn create_download_token(&self, asset_ids: Vec<Uuid>) -> String {
-        let token = Uuid::new_v4().to_string();
-        let download_token = DownloadToken {
-            asset_ids,
-            created_at: Utc::now(),
-            experiment_id: None,
-        };
-
-        let mut tokens = self.download_tokens.write().await;
-        tokens.insert(token.clone(), download_token);
-
-        // Clean up old tokens (older than 5 minutes)
-        let cutoff = Utc::now() - chrono::Duration::minutes(5);
-        tokens.retain(|_, t| t.created_at > cutoff);
-
-        token
+    /// The archive planned for a download token, if it is still in use
+    pub async fn download_layout(&self, token_id: Uuid) -> Option<Arc<ArchiveLayout>> {
+        let layouts = self.download_layouts.read().await;
+        let planned = layouts.get(&token_id)?;
+        Some(planned.layout.clone())
     }
 
-    /// Create a download token for an experiment
-    pub async fn create_experiment_download_token(&self, experiment_id: Uuid) -> String {
-        let token = Uuid::new_v4().to_string();
-        let download_token = DownloadToken {
-            asset_ids: Vec::new(),
-            created_at: Utc::now(),
-            experiment_id: Some(experiment_id),
-        };
-
-        let mut tokens = self.download_tokens.write().await;
-        tokens.insert(token.clone(), download_token);
-
-        // Clean up old tokens
-        let cutoff = Utc::now() - chrono::Duration::minutes(5);
-        tokens.retain(|_, t| t.created_at > cutoff);
-
-        token
+    /// Remember the archive planned for a download token until the token
+    /// expires
+    pub async fn set_download_layout(
+        &self,
+        token_id: Uuid,
+        expires_at: DateTime<Utc>,
+        layout: Arc<ArchiveLayout>,
+    ) {
+        let now = Utc::now();
+        let mut layouts = self.download_layouts.write().await;
+        // Forget downloads that can no longer be resumed
+        layouts.retain(|_, planned| planned.expires_at >= now);
+        layouts.insert(token_id, PlannedDownload { layout, expires_at });
     }
 
-    /// Consume a download token (removes it and returns the data)
-    pub async fn consume_download_token(&self, token: &str) -> Option<DownloadToken> {
-        let mut tokens = self.download_tokens.write().await;
+    /// Get a snapshot of a bulk export job
+    pub async fn get_export_job(&self, job_id: Uuid) -> Option<ExportJob> {
+        self.export_jobs.read().await.get(&job_id).cloned()
+    }
 
-        // Clean up old tokens
-        let cutoff = Utc::now() - chrono::Duration::minutes(5);
-        tokens.retain(|_, t| t.created_at > cutoff);
+    /// Apply a change to a bulk export job, if it still exists
+    pub async fn update_export_job(&self, job_id: Uuid, update: impl FnOnce(&mut ExportJob)) {
+        if let Some(job) = self.export_jobs.write().await.get_mut(&job_id) {
+            update(job);
+        }
+    }
 
-        tokens.remove(token)
+    /// Find the finished export job a download token belongs to
+    pub async fn find_export_job_by_token(&self, token: &str) -> Option<ExportJob> {
+        self.export_jobs
+            .read()
+            .await
+            .values()
+            .find(|job| job.download_token.as_deref() == Some(token) && !job.is_expired())
+            .cloned()
     }
 }
@@ -124,6 +124,7 @@ fn test_health_check_serialization() {
     // Test HealthCheck serialization
     let health = HealthCheck {
         status: "ok".to_string(),
+        checks: std::collections::BTreeMap::new(),
     };
 
     let json = serde_json::to_string(&health).unwrap();
@@ -144,4 +145,259 @@ fn test_processing_status_invalid_deserialization() {
     // Test that invalid processing status fails gracefully
     let result: Result<ProcessingStatus, _> = serde_json::from_str(r#""invalid_status""#);
     assert!(result.is_err());
-}
\ No newline at end of file
+}
+/// Schemas an `OpenAPI` document refers to that it does not define
+fn dangling_refs(
+    value: &serde_json::Value,
+    schemas: &serde_json::Map<String, serde_json::Value>,
+    dangling: &mut Vec<String>,
+) {
+    match value {
+        serde_json::Value::Object(object) => {
+            if let Some(reference) = object.get("$ref").and_then(serde_json::Value::as_str) {
+                let name = reference.trim_start_matches("#/components/schemas/");
+                if !schemas.contains_key(name) && !dangling.contains(&name.to_string()) {
+                    dangling.push(name.to_string());
+                }
+            }
+            for value in object.values() {
+                dangling_refs(value, schemas, dangling);
+            }
+        }
+        serde_json::Value::Array(values) => {
+            for value in values {
+                dangling_refs(value, schemas, dangling);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[tokio::test]
+async fn test_openapi_document_defines_its_schemas() {
+    use axum::body::{Body, to_bytes};
+    use axum::http::{Request, StatusCode};
+    use tower::ServiceExt;
+
+    let app = crate::config::test_helpers::setup_test_app().await;
+    let response = app
+        .oneshot(
+            Request::get("/api/openapi.json")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let spec: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+
+    let schemas = spec["components"]["schemas"].as_object().unwrap();
+    let mut dangling = Vec::new();
+    dangling_refs(&spec, schemas, &mut dangling);
+    assert!(dangling.is_empty(), "Undefined schemas: {dangling:?}");
+
+    // Routes routed beside the CRUD routes are documented, with their payloads
+    for schema in [
+        "ExperimentResultsResponse",
+        "TrayWellSummary",
+        "ExcelProcessingResult",
+        "ProcessingProgress",
+        "ProcessAssetResponse",
+    ] {
+        assert!(schemas.contains_key(schema), "{schema} is not defined");
+    }
+    let processed = &spec["paths"]["/api/experiments/{experiment_id}/process-asset"]["post"];
+    assert_eq!(
+        processed["responses"]["200"]["content"]["application/json"]["schema"]["$ref"],
+        "#/components/schemas/ProcessAssetResponse"
+    );
+    let status = &spec["paths"]["/api/experiments/{experiment_id}/process-status/{job_id}"]["get"];
+    assert_eq!(
+        status["responses"]["200"]["content"]["application/json"]["schema"]["$ref"],
+        "#/components/schemas/ProcessingProgress"
+    );
+
+    // Clients name their methods after the operations
+    let mut operations = std::collections::HashSet::new();
+    for item in spec["paths"].as_object().unwrap().values() {
+        for operation in item.as_object().unwrap().values() {
+            if let Some(id) = operation["operationId"].as_str() {
+                assert!(
+                    operations.insert(id.to_string()),
+                    "{id} names several operations"
+                );
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_responses_are_compressed_when_accepted() {
+    use axum::body::{Body, to_bytes};
+    use axum::http::{Request, StatusCode, header};
+    use tower::ServiceExt;
+
+    let app = crate::config::test_helpers::setup_test_app().await;
+    let fetch = |encoding: Option<&'static str>| {
+        let app = app.clone();
+        async move {
+            let mut request = Request::get("/api/openapi.json");
+            if let Some(encoding) = encoding {
+                request = request.header(header::ACCEPT_ENCODING, encoding);
+            }
+            let response = app
+                .oneshot(request.body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let encoded = response
+                .headers()
+                .get(header::CONTENT_ENCODING)
+                .map(|value| value.to_str().unwrap().to_string());
+            let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            (encoded, bytes.len())
+        }
+    };
+
+    let (encoded, plain) = fetch(None).await;
+    assert_eq!(encoded, None);
+    let (encoded, gzipped) = fetch(Some("gzip")).await;
+    assert_eq!(encoded.as_deref(), Some("gzip"));
+    assert!(gzipped * 4 < plain, "{gzipped} of {plain} bytes");
+    let (encoded, brotli) = fetch(Some("gzip, br")).await;
+    assert_eq!(encoded.as_deref(), Some("br"));
+    assert!(brotli * 4 < plain, "{brotli} of {plain} bytes");
+}
+
+#[tokio::test]
+async fn test_metrics_report_the_connection_pool() {
+    use axum::body::{Body, to_bytes};
+    use axum::http::{Request, StatusCode};
+    use tower::ServiceExt;
+
+    let mut config = crate::config::Config::for_tests();
+    config.keycloak_url = String::new();
+    config.db_max_connections = 7;
+    let db = crate::config::test_helpers::setup_test_db().await;
+    let app = crate::routes::build_router(&db, &config);
+
+    let response = app
+        .oneshot(Request::get("/metrics").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body = String::from_utf8(body.to_vec()).unwrap();
+    assert!(body.contains("# TYPE spice_db_pool_max_connections gauge"));
+    assert!(body.lines().any(|line| line == "spice_db_pool_max_connections 7"));
+}
+
+#[test]
+fn test_connect_options_follow_the_pool_settings() {
+    let mut config = crate::config::Config::for_tests();
+    config.db_max_connections = 4;
+    config.db_min_connections = 10;
+    config.db_acquire_timeout_seconds = 3;
+    let options = config.connect_options();
+    assert_eq!(options.get_max_connections(), Some(4));
+    // Never more kept open than may be opened
+    assert_eq!(options.get_min_connections(), Some(4));
+    assert_eq!(
+        options.get_acquire_timeout(),
+        Some(std::time::Duration::from_secs(3))
+    );
+}
+
+#[tokio::test]
+async fn test_readiness_checks_each_dependency() {
+    use axum::body::{Body, to_bytes};
+    use axum::http::{Request, StatusCode};
+    use tower::ServiceExt;
+
+    let probe = |app: axum::Router, path: &'static str| async move {
+        let response = app
+            .oneshot(Request::get(path).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let health: HealthCheck = serde_json::from_slice(&body).unwrap();
+        (status, health)
+    };
+
+    let app = crate::config::test_helpers::setup_test_app().await;
+    let (status, health) = probe(app.clone(), "/readyz").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(health.status, "ok");
+    for dependency in ["database", "storage", "migrations"] {
+        assert_eq!(health.checks[dependency].status, "ok", "{dependency}");
+    }
+    // Keycloak is off in tests
+    assert_eq!(health.checks["keycloak"].status, "disabled");
+
+    // A database the migrations have not been run on is alive but not ready
+    let mut config = crate::config::Config::for_tests();
+    config.keycloak_url = String::new();
+    let db = sea_orm::Database::connect("sqlite::memory:").await.unwrap();
+    let app = crate::routes::build_router(&db, &config);
+    let (status, health) = probe(app.clone(), "/healthz").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(health.checks["database"].status, "ok");
+    let (status, health) = probe(app, "/readyz").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(health.status, "error");
+    let migrations = &health.checks["migrations"];
+    assert_eq!(migrations.status, "error");
+    assert!(
+        migrations
+            .detail
+            .as_deref()
+            .is_some_and(|detail| detail.starts_with("Pending migrations: ")),
+        "{migrations:?}"
+    );
+}
+
+#[test]
+fn test_server_errors_are_reported() {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use axum::{Router, middleware, routing::get};
+    use tower::ServiceExt;
+
+    let app = Router::new()
+        .route(
+            "/api/experiments/{id}/results",
+            get(|| async { (StatusCode::INTERNAL_SERVER_ERROR, "Database unreachable") }),
+        )
+        .route("/api/experiments", get(|| async { StatusCode::NOT_FOUND }))
+        .layer(middleware::from_fn(
+            super::error_reporting::report_server_errors,
+        ));
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let events = sentry::test::with_captured_events(|| {
+        runtime.block_on(async {
+            let path = format!("/api/experiments/{}/results?token=secret", uuid::Uuid::new_v4());
+            for uri in [path.as_str(), "/api/experiments"] {
+                let request = Request::get(uri)
+                    .header("x-request-id", "request-1")
+                    .body(Body::empty())
+                    .unwrap();
+                app.clone().oneshot(request).await.unwrap();
+            }
+        });
+    });
+
+    // Only the server error is reported, without its query string
+    assert_eq!(events.len(), 1);
+    let event = &events[0];
+    assert_eq!(
+        event.message.as_deref(),
+        Some("500 Internal Server Error on GET /api/experiments/{id}/results: Database unreachable")
+    );
+    assert_eq!(event.tags["request_id"], "request-1");
+    assert_eq!(event.tags["http.status_code"], "500");
+    assert!(!format!("{event:?}").contains("secret"));
+}
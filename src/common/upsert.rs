@@ -0,0 +1,255 @@
+//! Upserts of records by their natural key.
+//!
+//! Seeders and importers run again over the same data. `POST /upsert` of a
+//! resource takes a list of records as they would be created, and finds
+//! each one by the fields that identify it outside the API, such as a
+//! sample's name and location: a record not found is created, and one found
+//! is updated with the fields given, as a `PATCH` would. Deleted records are
+//! not matched. Each record succeeds or fails on its own, and keeps its
+//! versions and audit log as a single create or update would.
+
+use crate::audit::models::AuditAction;
+use crate::audit::services::{AuditedChange, AuditedResource, REQUEST_ID_HEADER, record_change};
+use crate::common::auth::Role;
+use crate::common::patch::patch_record;
+use crate::common::soft_delete::deleted_at_column;
+use crate::versions::services::{save_version, snapshot};
+use axum::{
+    Extension, Json,
+    extract::{OriginalUri, State},
+    http::{HeaderMap, Method, StatusCode},
+};
+use axum_keycloak_auth::decode::KeycloakToken;
+use crudcrate::CRUDResource;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QuerySelect,
+};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::Value;
+use std::str::FromStr;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Resources that can be upserted, with the fields identifying their records
+pub trait NaturalKey: CRUDResource {
+    /// Fields that together identify a record. A field left out matches
+    /// records where it is empty.
+    const NATURAL_KEY: &'static [&'static str];
+}
+
+/// What an upsert did with a record
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum UpsertAction {
+    Created,
+    Updated,
+    Failed,
+}
+
+/// Outcome of a record of an upsert
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct UpsertOutcome {
+    /// Position of the record in the request
+    pub index: usize,
+    /// ID of the record created or updated
+    pub id: Option<Uuid>,
+    pub action: UpsertAction,
+    pub error: Option<String>,
+}
+
+/// Outcomes of an upsert, in the order of the records sent
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct UpsertReport {
+    pub created: usize,
+    pub updated: usize,
+    pub failed: usize,
+    pub results: Vec<UpsertOutcome>,
+}
+
+fn item_error(e: DbErr) -> String {
+    match e {
+        DbErr::Custom(message) | DbErr::RecordNotFound(message) => message,
+        e => match e.sql_err() {
+            Some(sea_orm::SqlErr::UniqueConstraintViolation(detail)) => {
+                format!("Conflict: {detail}")
+            }
+            _ => e.to_string(),
+        },
+    }
+}
+
+/// ID of the record a new record would be, found by its natural key
+async fn find_by_natural_key<R>(
+    db: &DatabaseConnection,
+    item: &Value,
+) -> Result<Option<Uuid>, DbErr>
+where
+    R: NaturalKey,
+    R::CreateModel: DeserializeOwned,
+{
+    let create: R::CreateModel = serde_json::from_value(item.clone())
+        .map_err(|e| DbErr::Custom(format!("Invalid record: {e}")))?;
+    let active: R::ActiveModelType = create.into();
+
+    let mut query = R::EntityType::find().select_only().column(R::ID_COLUMN);
+    for field in R::NATURAL_KEY {
+        let column = <R::EntityType as EntityTrait>::Column::from_str(field)
+            .map_err(|_| DbErr::Custom(format!("Unknown key field {field}")))?;
+        let value = active.get(column).into_value();
+        query = match (item.get(*field), value) {
+            (None | Some(Value::Null), _) | (_, None) => query.filter(column.is_null()),
+            (Some(_), Some(value)) => query.filter(column.eq(value)),
+        };
+    }
+    if let Some(deleted_at) = deleted_at_column::<R>() {
+        query = query.filter(deleted_at.is_null());
+    }
+    let mut found: Vec<Uuid> = query.limit(2).into_tuple().all(db).await?;
+    match found.len() {
+        0 | 1 => Ok(found.pop()),
+        _ => Err(DbErr::Custom(format!(
+            "Several {} have this {}",
+            R::RESOURCE_NAME_PLURAL,
+            R::NATURAL_KEY.join(" and ")
+        ))),
+    }
+}
+
+/// A record an upsert updated, as it was before
+struct Replaced {
+    id: Uuid,
+    /// As versions keep it
+    version: Option<Value>,
+    /// As the audit log shows it
+    audited: Option<Value>,
+}
+
+/// Create or update a record, with the record it updated if any
+async fn upsert_one<R>(
+    db: &DatabaseConnection,
+    resource: AuditedResource,
+    item: Value,
+) -> Result<(R, Option<Replaced>), DbErr>
+where
+    R: NaturalKey + Serialize,
+    R::CreateModel: DeserializeOwned,
+    R::UpdateModel: DeserializeOwned,
+{
+    if !item.is_object() {
+        return Err(DbErr::Custom("A record must be a JSON object".to_string()));
+    }
+    let Some(id) = find_by_natural_key::<R>(db, &item).await? else {
+        let create: R::CreateModel = serde_json::from_value(item)
+            .map_err(|e| DbErr::Custom(format!("Invalid record: {e}")))?;
+        return Ok((R::create(db, create).await?, None));
+    };
+    let replaced = Replaced {
+        id,
+        version: snapshot::<R>(db, id).await?,
+        audited: resource.snapshot(db, id).await?,
+    };
+    let updated = patch_record::<R>(db, id, item).await?;
+    Ok((updated, Some(replaced)))
+}
+
+/// ID of a record as its API shows it
+fn record_id<R: Serialize>(record: &R) -> Option<Uuid> {
+    serde_json::to_value(record)
+        .ok()
+        .and_then(|record| record.get("id").and_then(Value::as_str).map(str::to_string))
+        .and_then(|id| Uuid::parse_str(&id).ok())
+}
+
+/// Handler for `POST /upsert` of a resource's router, creating or updating
+/// each record by its natural key
+pub async fn upsert_handler<R>(
+    State((db, resource)): State<(DatabaseConnection, AuditedResource)>,
+    token: Option<Extension<KeycloakToken<Role>>>,
+    OriginalUri(uri): OriginalUri,
+    headers: HeaderMap,
+    Json(items): Json<Vec<Value>>,
+) -> Json<UpsertReport>
+where
+    R: NaturalKey + Serialize,
+    R::CreateModel: DeserializeOwned,
+    R::UpdateModel: DeserializeOwned,
+{
+    let username = token.map(|Extension(token)| token.extra.profile.preferred_username);
+    let request_id = headers
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+
+    let mut results = Vec::with_capacity(items.len());
+    for (index, item) in items.into_iter().enumerate() {
+        let (record, replaced) = match upsert_one::<R>(&db, resource, item).await {
+            Ok(upserted) => upserted,
+            Err(e) => {
+                results.push(UpsertOutcome {
+                    index,
+                    id: None,
+                    action: UpsertAction::Failed,
+                    error: Some(item_error(e)),
+                });
+                continue;
+            }
+        };
+        let (action, audit_action) = if replaced.is_some() {
+            (UpsertAction::Updated, AuditAction::Update)
+        } else {
+            (UpsertAction::Created, AuditAction::Create)
+        };
+        let id = replaced
+            .as_ref()
+            .map(|replaced| replaced.id)
+            .or_else(|| record_id(&record));
+
+        // Each update keeps the version it replaces
+        if let Some(Replaced {
+            id,
+            version: Some(version),
+            ..
+        }) = &replaced
+            && let Err(e) = save_version(&db, R::RESOURCE_NAME_PLURAL, *id, version.clone()).await
+        {
+            tracing::error!(
+                "Failed to keep a version of {} {id}: {e}",
+                R::RESOURCE_NAME_SINGULAR
+            );
+        }
+        let after = match id {
+            Some(id) => resource.snapshot(&db, id).await.ok().flatten(),
+            None => None,
+        };
+        let change = AuditedChange {
+            username: username.clone(),
+            action: audit_action,
+            resource,
+            record_id: id,
+            method: Method::POST.to_string(),
+            path: uri.path().to_string(),
+            status: StatusCode::OK,
+            request_id: request_id.clone(),
+            before: replaced.and_then(|replaced| replaced.audited),
+            after,
+        };
+        if let Err(e) = record_change(&db, change).await {
+            tracing::error!("Could not write to the audit log: {e}");
+        }
+
+        results.push(UpsertOutcome {
+            index,
+            id,
+            action,
+            error: None,
+        });
+    }
+
+    let count = |action| results.iter().filter(|r| r.action == action).count();
+    Json(UpsertReport {
+        created: count(UpsertAction::Created),
+        updated: count(UpsertAction::Updated),
+        failed: count(UpsertAction::Failed),
+        results,
+    })
+}
@@ -1,8 +1,16 @@
+use super::health;
 use super::models::HealthCheck;
 use super::models::UIConfiguration;
 use crate::common::state::AppState;
-use axum::{Json, extract::State, http::StatusCode};
-use sea_orm::DatabaseConnection;
+use axum::{
+    Json,
+    extract::State,
+    http::{StatusCode, header::CONTENT_TYPE},
+    response::IntoResponse,
+};
+use sea_orm::{ConnectionTrait, DatabaseBackend, DatabaseConnection};
+use std::collections::BTreeMap;
+use std::fmt::Write;
 use utoipa_axum::{router::OpenApiRouter, routes};
 
 pub fn router(state: &AppState) -> OpenApiRouter {
@@ -11,36 +19,54 @@ pub fn router(state: &AppState) -> OpenApiRouter {
         .routes(routes!(healthz))
         .routes(routes!(get_ui_config))
         .with_state(state.db.clone())
+        .merge(
+            OpenApiRouter::new()
+                .routes(routes!(readyz))
+                .routes(routes!(metrics))
+                .with_state(state.clone()),
+        )
 }
 
 #[utoipa::path(
     get,
     path = "/healthz",
     responses(
-        (
-            status = OK,
-            description = "Kubernetes health check",
-            body = str,
-            content_type = "text/plain"
-        )
+        (status = OK, description = "Kubernetes liveness probe: the database answers", body = HealthCheck),
+        (status = SERVICE_UNAVAILABLE, description = "The database does not answer", body = HealthCheck)
     )
 )]
 pub async fn healthz(State(db): State<DatabaseConnection>) -> (StatusCode, Json<HealthCheck>) {
-    if db.ping().await.is_err() {
-        return (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(HealthCheck {
-                status: "error".to_string(),
-            }),
-        );
-    }
+    health::report(BTreeMap::from([(
+        "database".to_string(),
+        health::database(&db).await,
+    )]))
+}
 
-    (
-        StatusCode::OK,
-        Json(HealthCheck {
-            status: "ok".to_string(),
-        }),
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    responses(
+        (
+            status = OK,
+            description = "Kubernetes readiness probe: the database, storage and Keycloak answer and no migrations are pending",
+            body = HealthCheck
+        ),
+        (status = SERVICE_UNAVAILABLE, description = "A dependency failed its check", body = HealthCheck)
     )
+)]
+pub async fn readyz(State(state): State<AppState>) -> (StatusCode, Json<HealthCheck>) {
+    let (database, storage, keycloak, migrations) = tokio::join!(
+        health::database(&state.db),
+        health::storage(&state.config),
+        health::keycloak(state.keycloak_auth_instance.as_deref()),
+        health::migrations(&state.db),
+    );
+    health::report(BTreeMap::from([
+        ("database".to_string(), database),
+        ("storage".to_string(), storage),
+        ("keycloak".to_string(), keycloak),
+        ("migrations".to_string(), migrations),
+    ]))
 }
 
 #[utoipa::path(
@@ -58,3 +84,51 @@ pub async fn healthz(State(db): State<DatabaseConnection>) -> (StatusCode, Json<
 pub async fn get_ui_config() -> Json<UIConfiguration> {
     Json(UIConfiguration::new())
 }
+
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    responses(
+        (
+            status = OK,
+            description = "Database connection pool utilization, in the Prometheus text format",
+            body = str,
+            content_type = "text/plain"
+        )
+    )
+)]
+pub async fn metrics(State(state): State<AppState>) -> impl IntoResponse {
+    let mut gauges = vec![(
+        "spice_db_pool_max_connections",
+        "Most connections the pool opens",
+        state.config.db_max_connections,
+    )];
+    // Only Postgres pools are served; other backends are for tests
+    if state.db.get_database_backend() == DatabaseBackend::Postgres {
+        let pool = state.db.get_postgres_connection_pool();
+        let open = pool.size();
+        let idle = u32::try_from(pool.num_idle()).unwrap_or(u32::MAX);
+        gauges.extend([
+            ("spice_db_pool_connections", "Connections open", open),
+            (
+                "spice_db_pool_idle_connections",
+                "Connections open and idle",
+                idle,
+            ),
+            (
+                "spice_db_pool_in_use_connections",
+                "Connections in use by requests and background jobs",
+                open.saturating_sub(idle),
+            ),
+        ]);
+    }
+
+    let mut body = String::new();
+    for (name, help, value) in gauges {
+        let _ = writeln!(
+            body,
+            "# HELP {name} {help}\n# TYPE {name} gauge\n{name} {value}"
+        );
+    }
+    ([(CONTENT_TYPE, "text/plain; version=0.0.4")], body)
+}
@@ -1,55 +1,419 @@
+pub mod sources;
+
+use crate::external::storage::StorageKind;
 use dotenvy::dotenv;
+use ipnet::IpNet;
+use sea_orm::ConnectOptions;
 use serde::Deserialize;
+use sources::{ConfigError, Settings};
+#[cfg(test)]
 use std::env;
+use std::net::IpAddr;
+use std::time::Duration;
 
 #[derive(Deserialize, Debug, Clone)]
+#[allow(clippy::struct_excessive_bools)] // One per setting
 pub struct Config {
     pub db_url: Option<String>,
+    /// Most connections the pool opens; each Excel upload holds one for the
+    /// whole load
+    pub db_max_connections: u32,
+    /// Connections the pool keeps open while idle
+    pub db_min_connections: u32,
+    /// Seconds to wait for the database to accept a new connection
+    pub db_connect_timeout_seconds: u64,
+    /// Seconds a request waits for a free connection before failing
+    pub db_acquire_timeout_seconds: u64,
+    /// Seconds an idle connection above the minimum is kept open
+    pub db_idle_timeout_seconds: u64,
+    /// Prepared statements kept by each connection; 0 behind a pooler such
+    /// as `PgBouncer` in transaction mode, which cannot share them
+    pub db_statement_cache_capacity: usize,
     pub app_name: String,
     pub keycloak_ui_id: String,
     pub keycloak_url: String,
     pub keycloak_realm: String,
+    /// Accepted token audiences; not checked when set empty
+    pub keycloak_audiences: Vec<String>,
+    /// Seconds tokens are still accepted past their expiry, or before they
+    /// are valid, for clocks that disagree with Keycloak's
+    pub keycloak_clock_skew_seconds: u64,
+    /// Minutes between background refreshes of Keycloak's signing keys
+    pub keycloak_jwks_refresh_minutes: u64,
+    /// Hours the last signing keys fetched are used for while Keycloak cannot
+    /// be reached
+    pub keycloak_jwks_max_stale_hours: i64,
     pub deployment: String,
     pub admin_role: String,
+    pub editor_role: String,
+    pub viewer_role: String,
     pub s3_access_key: String,
     pub s3_secret_key: String,
     pub s3_bucket_id: String,
     pub s3_url: String,
+    pub storage_backend: StorageKind,
+    /// Root directory of the `local` storage backend
+    pub storage_local_path: String,
+    /// `https://<account>.blob.core.windows.net/<container>` for the `azure` backend
+    pub azure_container_url: String,
+    pub azure_sas_token: String,
+    pub zenodo_url: String,
+    pub zenodo_access_token: Option<String>,
+    pub datacite_publisher: String,
+    pub ffmpeg_path: String,
+    /// Minutes within which a download must be started with a new download
+    /// token, unless the token asks for another time
+    pub download_token_ttl_minutes: i64,
+    /// Most minutes a download token may ask for
+    pub download_token_max_ttl_minutes: i64,
+    /// Downloads a new token may start unless it asks otherwise, 1 for
+    /// single-use tokens; unlimited when unset, with 0
+    pub download_token_max_downloads: Option<i32>,
+    /// Hours between scheduled orphaned object cleanups, disabled when unset
+    pub orphan_cleanup_interval_hours: Option<u64>,
+    /// Let scheduled cleanups delete what they find instead of only reporting it
+    pub orphan_cleanup_remove: bool,
+    /// Accept experiment regions that share wells
+    pub allow_overlapping_regions: bool,
+    /// Downsample probe series from the per-minute aggregates of the
+    /// `TimescaleDB` series, which the database must have been migrated with;
+    /// `PostgreSQL` with the `timescaledb` extension only
+    pub timescaledb: bool,
+    /// JSON file of the metadata rules for each sample type
+    pub sample_type_rules_path: Option<String>,
+    /// Open-Meteo compatible historical weather API, e.g.
+    /// `https://archive-api.open-meteo.com/v1/archive`; weather enrichment of
+    /// samples is disabled when unset
+    pub weather_api_url: Option<String>,
+    pub weather_api_key: Option<String>,
+    /// Open-Meteo compatible elevation API filling in the elevation of
+    /// locations, e.g. `https://api.open-meteo.com/v1/elevation`
+    pub elevation_api_url: Option<String>,
+    /// Open-Meteo compatible forecast API filling in the time zone of
+    /// locations, e.g. `https://api.open-meteo.com/v1/forecast`
+    pub timezone_api_url: Option<String>,
+    /// Requests a minute allowed from each address, unlimited when unset
+    pub rate_limit_per_address: Option<u32>,
+    /// Requests a minute allowed by each user or API key, unlimited when unset
+    pub rate_limit_per_user: Option<u32>,
+    /// Requests a minute each client may make for experiment results,
+    /// exports and downloads, unlimited when unset
+    pub rate_limit_expensive: Option<u32>,
+    /// Header a proxy in front gives client addresses in, such as
+    /// `X-Forwarded-For`; the connection's address is used when unset
+    pub client_address_header: Option<String>,
+    /// Addresses or ranges, such as `10.0.0.0/8`, of the proxies trusted to
+    /// give client addresses in `client_address_header`
+    pub trusted_proxies: Vec<IpNet>,
+    /// Start of the Keycloak groups that are labs, such as `/labs/`. Each
+    /// lab sees only its own records; labs are off when unset
+    pub lab_group_prefix: Option<String>,
+    /// Refuse changes to records that do not give, in `If-Match`, the `ETag`
+    /// they were read with
+    pub require_if_match: bool,
+    /// Encodings responses are compressed with for clients that accept them,
+    /// `br` and `gzip`; none when set empty
+    pub response_compression: Vec<String>,
+    /// Seconds the responses of tray configurations, projects and
+    /// experiments are kept for, while unchanged; not kept when unset
+    pub response_cache_seconds: Option<u64>,
+    /// DSN of the Sentry project panics and server errors are reported to;
+    /// not reported when unset
+    pub sentry_dsn: Option<String>,
     pub tests_running: bool, // Flag to indicate if tests are running
 }
 
 impl Config {
+    /// Settings of the config file, the environment and secrets files,
+    /// panicking with everything wrong with them
     pub fn from_env() -> Self {
+        Self::load().unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Settings of the config file, the environment and secrets files, see
+    /// [`sources`]
+    pub fn load() -> Result<Self, ConfigError> {
         dotenv().ok(); // Load from .env file if available
-        let db_url = env::var("DB_URL").ok().or_else(|| {
+        Self::from_settings(Settings::load())
+    }
+
+    fn from_settings(settings: Settings) -> Result<Self, ConfigError> {
+        let config = Self::read(&settings);
+        config.check(&settings);
+        settings.finish().map(|()| config)
+    }
+
+    #[allow(clippy::too_many_lines)] // One entry per setting
+    fn read(settings: &Settings) -> Self {
+        let db_url = settings.var("DB_URL").ok().or_else(|| {
             Some(format!(
                 "{}://{}:{}@{}:{}/{}",
-                env::var("DB_PREFIX").unwrap_or_else(|_| "postgresql".to_string()),
-                env::var("DB_USER").expect("DB_USER must be set"),
-                env::var("DB_PASSWORD").expect("DB_PASSWORD must be set"),
-                env::var("DB_HOST").expect("DB_HOST must be set"),
-                env::var("DB_PORT").unwrap_or_else(|_| "5432".to_string()),
-                env::var("DB_NAME").expect("DB_NAME must be set"),
+                settings
+                    .var("DB_PREFIX")
+                    .unwrap_or_else(|_| "postgresql".to_string()),
+                settings.required("DB_USER"),
+                settings.required("DB_PASSWORD"),
+                settings.required("DB_HOST"),
+                settings
+                    .var("DB_PORT")
+                    .unwrap_or_else(|_| "5432".to_string()),
+                settings.required("DB_NAME"),
             ))
         });
 
+        let storage_backend = settings.var("STORAGE_BACKEND").map_or_else(
+            |_| StorageKind::default(),
+            |kind| {
+                kind.parse().unwrap_or_else(|e: String| {
+                    settings.check(false, || format!("STORAGE_BACKEND: {e}"));
+                    StorageKind::default()
+                })
+            },
+        );
+        // Only the selected backend's settings are required
+        let required = |name: &str, needed: bool| {
+            settings.var(name).unwrap_or_else(|_| {
+                settings.check(!needed, || {
+                    format!("{name} must be set for the {storage_backend} storage backend")
+                });
+                String::new()
+            })
+        };
+        let uses_s3_api = storage_backend.uses_s3_api();
+        let is_azure = storage_backend == StorageKind::Azure;
+
         Config {
-            app_name: env::var("APP_NAME").expect("APP_NAME must be set"),
-            keycloak_ui_id: env::var("KEYCLOAK_UI_ID").expect("KEYCLOAK_UI_ID must be set"),
-            keycloak_url: env::var("KEYCLOAK_URL").expect("KEYCLOAK_URL must be set"),
-            keycloak_realm: env::var("KEYCLOAK_REALM").expect("KEYCLOAK_REALM must be set"),
-            deployment: env::var("DEPLOYMENT")
-                .expect("DEPLOYMENT must be set, this can be local, dev, stage, or prod"),
+            db_max_connections: settings
+                .parsed("DB_MAX_CONNECTIONS")
+                .filter(|connections| *connections > 0)
+                .unwrap_or(20),
+            db_min_connections: settings.parsed("DB_MIN_CONNECTIONS").unwrap_or(2),
+            db_connect_timeout_seconds: settings
+                .parsed("DB_CONNECT_TIMEOUT_SECONDS")
+                .filter(|seconds| *seconds > 0)
+                .unwrap_or(10),
+            db_acquire_timeout_seconds: settings
+                .parsed("DB_ACQUIRE_TIMEOUT_SECONDS")
+                .filter(|seconds| *seconds > 0)
+                .unwrap_or(30),
+            db_idle_timeout_seconds: settings
+                .parsed("DB_IDLE_TIMEOUT_SECONDS")
+                .filter(|seconds| *seconds > 0)
+                .unwrap_or(600),
+            db_statement_cache_capacity: settings
+                .parsed("DB_STATEMENT_CACHE_CAPACITY")
+                .unwrap_or(100),
+            app_name: settings.required("APP_NAME"),
+            keycloak_ui_id: settings.required("KEYCLOAK_UI_ID"),
+            keycloak_url: settings.required("KEYCLOAK_URL"),
+            keycloak_realm: settings.required("KEYCLOAK_REALM"),
+            keycloak_audiences: settings
+                .var("KEYCLOAK_AUDIENCES")
+                .unwrap_or_else(|_| "account".to_string())
+                .split(',')
+                .map(str::trim)
+                .filter(|audience| !audience.is_empty())
+                .map(str::to_string)
+                .collect(),
+            keycloak_clock_skew_seconds: settings
+                .parsed("KEYCLOAK_CLOCK_SKEW_SECONDS")
+                .unwrap_or(60),
+            keycloak_jwks_refresh_minutes: settings
+                .parsed("KEYCLOAK_JWKS_REFRESH_MINUTES")
+                .filter(|minutes| *minutes > 0)
+                .unwrap_or(10),
+            keycloak_jwks_max_stale_hours: settings
+                .parsed("KEYCLOAK_JWKS_MAX_STALE_HOURS")
+                .unwrap_or(12),
+            // One of local, dev, stage, or prod
+            deployment: settings.required("DEPLOYMENT"),
             admin_role: "spice-admin".to_string(), // Admin role name in Keycloak
-            s3_access_key: env::var("S3_ACCESS_KEY").expect("S3_ACCESS_KEY must be set"),
-            s3_secret_key: env::var("S3_SECRET_KEY").expect("S3_SECRET_KEY must be set"),
-            s3_bucket_id: env::var("S3_BUCKET_ID").expect("S3_BUCKET must be set"),
-            s3_url: env::var("S3_URL").expect("S3_URL must be set"),
+            editor_role: "spice-editor".to_string(),
+            viewer_role: "spice-viewer".to_string(),
+            s3_access_key: required("S3_ACCESS_KEY", uses_s3_api),
+            s3_secret_key: required("S3_SECRET_KEY", uses_s3_api),
+            s3_bucket_id: required("S3_BUCKET_ID", uses_s3_api),
+            s3_url: match storage_backend {
+                StorageKind::Gcs => settings
+                    .var("S3_URL")
+                    .unwrap_or_else(|_| "https://storage.googleapis.com".to_string()),
+                _ => required("S3_URL", uses_s3_api),
+            },
+            storage_backend,
+            storage_local_path: settings
+                .var("STORAGE_LOCAL_PATH")
+                .unwrap_or_else(|_| "./storage".to_string()),
+            azure_container_url: required("AZURE_STORAGE_CONTAINER_URL", is_azure),
+            azure_sas_token: required("AZURE_STORAGE_SAS_TOKEN", is_azure),
+            zenodo_url: settings
+                .var("ZENODO_URL")
+                .unwrap_or_else(|_| "https://zenodo.org/api".to_string()),
+            zenodo_access_token: settings.var("ZENODO_ACCESS_TOKEN").ok(),
+            datacite_publisher: settings
+                .var("DATACITE_PUBLISHER")
+                .unwrap_or_else(|_| "École Polytechnique Fédérale de Lausanne (EPFL)".to_string()),
+            ffmpeg_path: settings
+                .var("FFMPEG_PATH")
+                .unwrap_or_else(|_| "ffmpeg".to_string()),
+            download_token_ttl_minutes: settings
+                .parsed("DOWNLOAD_TOKEN_TTL_MINUTES")
+                .filter(|minutes| *minutes > 0)
+                .unwrap_or(5),
+            download_token_max_ttl_minutes: settings
+                .parsed("DOWNLOAD_TOKEN_MAX_TTL_MINUTES")
+                .filter(|minutes| *minutes > 0)
+                .unwrap_or(24 * 60),
+            download_token_max_downloads: settings
+                .parsed("DOWNLOAD_TOKEN_MAX_DOWNLOADS")
+                .map_or(Some(1), |downloads| (downloads > 0).then_some(downloads)),
+            orphan_cleanup_interval_hours: settings
+                .parsed("ORPHAN_CLEANUP_INTERVAL_HOURS")
+                .filter(|hours| *hours > 0),
+            orphan_cleanup_remove: settings.flag("ORPHAN_CLEANUP_REMOVE").unwrap_or(false),
+            allow_overlapping_regions: settings.flag("ALLOW_OVERLAPPING_REGIONS").unwrap_or(false),
+            timescaledb: settings.flag("TIMESCALEDB").unwrap_or(false),
+            sample_type_rules_path: settings
+                .var("SAMPLE_TYPE_RULES_PATH")
+                .ok()
+                .filter(|path| !path.is_empty()),
+            weather_api_url: settings
+                .var("WEATHER_API_URL")
+                .ok()
+                .filter(|url| !url.is_empty()),
+            weather_api_key: settings
+                .var("WEATHER_API_KEY")
+                .ok()
+                .filter(|key| !key.is_empty()),
+            elevation_api_url: settings
+                .var("ELEVATION_API_URL")
+                .ok()
+                .filter(|url| !url.is_empty()),
+            timezone_api_url: settings
+                .var("TIMEZONE_API_URL")
+                .ok()
+                .filter(|url| !url.is_empty()),
+            rate_limit_per_address: settings
+                .parsed("RATE_LIMIT_PER_ADDRESS")
+                .filter(|limit| *limit > 0),
+            rate_limit_per_user: settings
+                .parsed("RATE_LIMIT_PER_USER")
+                .filter(|limit| *limit > 0),
+            rate_limit_expensive: settings
+                .parsed("RATE_LIMIT_EXPENSIVE")
+                .filter(|limit| *limit > 0),
+            client_address_header: settings
+                .var("CLIENT_ADDRESS_HEADER")
+                .ok()
+                .filter(|header| !header.is_empty()),
+            trusted_proxies: settings
+                .var("TRUSTED_PROXIES")
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|proxy| !proxy.is_empty())
+                .filter_map(|proxy| {
+                    let parsed = proxy
+                        .parse()
+                        .or_else(|_| proxy.parse::<IpAddr>().map(IpNet::from))
+                        .ok();
+                    settings.check(parsed.is_some(), || {
+                        format!("TRUSTED_PROXIES: {proxy:?} is not an address or range")
+                    });
+                    parsed
+                })
+                .collect(),
+            lab_group_prefix: settings
+                .var("LAB_GROUP_PREFIX")
+                .ok()
+                .filter(|prefix| !prefix.is_empty()),
+            require_if_match: settings.flag("REQUIRE_IF_MATCH").unwrap_or(true),
+            response_compression: settings
+                .var("RESPONSE_COMPRESSION")
+                .unwrap_or_else(|_| "br,gzip".to_string())
+                .split(',')
+                .map(|encoding| encoding.trim().to_ascii_lowercase())
+                .filter(|encoding| !encoding.is_empty())
+                .collect(),
+            response_cache_seconds: settings
+                .parsed("RESPONSE_CACHE_SECONDS")
+                .filter(|seconds| *seconds > 0),
+            sentry_dsn: settings
+                .var("SENTRY_DSN")
+                .ok()
+                .filter(|dsn| !dsn.is_empty()),
             tests_running: false, // Always false if using Config from_env
             db_url,
         }
     }
 
+    /// Check settings against each other
+    fn check(&self, settings: &Settings) {
+        settings.check(self.db_min_connections <= self.db_max_connections, || {
+            format!(
+                "DB_MIN_CONNECTIONS ({}) must not be above DB_MAX_CONNECTIONS ({})",
+                self.db_min_connections, self.db_max_connections
+            )
+        });
+        settings.check(
+            self.download_token_ttl_minutes <= self.download_token_max_ttl_minutes,
+            || {
+                format!(
+                    "DOWNLOAD_TOKEN_TTL_MINUTES ({}) must not be above DOWNLOAD_TOKEN_MAX_TTL_MINUTES ({})",
+                    self.download_token_ttl_minutes, self.download_token_max_ttl_minutes
+                )
+            },
+        );
+        settings.check(
+            self.keycloak_url.is_empty()
+                || self.keycloak_url.starts_with("http://")
+                || self.keycloak_url.starts_with("https://"),
+            || {
+                format!(
+                    "KEYCLOAK_URL: {:?} is not an http(s) URL",
+                    self.keycloak_url
+                )
+            },
+        );
+        if let Some(dsn) = &self.sentry_dsn {
+            settings.check(dsn.parse::<sentry::types::Dsn>().is_ok(), || {
+                "SENTRY_DSN is not a valid Sentry DSN".to_string()
+            });
+        }
+        settings.check(
+            !self.timescaledb
+                || self
+                    .db_url
+                    .as_deref()
+                    .is_some_and(|url| url.starts_with("postgres")),
+            || "TIMESCALEDB needs a PostgreSQL database".to_string(),
+        );
+        settings.check(
+            self.client_address_header.is_none() || !self.trusted_proxies.is_empty(),
+            || "CLIENT_ADDRESS_HEADER needs TRUSTED_PROXIES".to_string(),
+        );
+        for encoding in &self.response_compression {
+            settings.check(matches!(encoding.as_str(), "br" | "gzip"), || {
+                format!("RESPONSE_COMPRESSION: {encoding:?} is not br or gzip")
+            });
+        }
+    }
+
+    /// Options of the database connection pool
+    pub fn connect_options(&self) -> ConnectOptions {
+        let mut options = ConnectOptions::new(self.db_url.clone().unwrap_or_default());
+        let statement_cache_capacity = self.db_statement_cache_capacity;
+        options
+            .max_connections(self.db_max_connections)
+            .min_connections(self.db_min_connections.min(self.db_max_connections))
+            .connect_timeout(Duration::from_secs(self.db_connect_timeout_seconds))
+            .acquire_timeout(Duration::from_secs(self.db_acquire_timeout_seconds))
+            .idle_timeout(Duration::from_secs(self.db_idle_timeout_seconds))
+            .map_sqlx_postgres_opts(move |options| {
+                options.statement_cache_capacity(statement_cache_capacity)
+            });
+        options
+    }
+
     #[cfg(test)]
     pub fn for_tests() -> Self {
         // Set default test environment variables if not already set
@@ -64,16 +428,58 @@ impl Config {
         ));
 
         Config {
+            db_max_connections: 20,
+            db_min_connections: 2,
+            db_connect_timeout_seconds: 10,
+            db_acquire_timeout_seconds: 30,
+            db_idle_timeout_seconds: 600,
+            db_statement_cache_capacity: 100,
             app_name: "spice-api-test".to_string(),
             keycloak_ui_id: "test-ui".to_string(),
             keycloak_url: "http://localhost:8080".to_string(),
             keycloak_realm: "test-realm".to_string(),
+            keycloak_audiences: vec!["account".to_string()],
+            keycloak_clock_skew_seconds: 60,
+            keycloak_jwks_refresh_minutes: 10,
+            keycloak_jwks_max_stale_hours: 12,
             deployment: "test".to_string(),
             admin_role: "spice-admin".to_string(),
+            editor_role: "spice-editor".to_string(),
+            viewer_role: "spice-viewer".to_string(),
             s3_access_key: "test-access-key".to_string(),
             s3_secret_key: "test-secret-key".to_string(),
             s3_bucket_id: "test-bucket".to_string(),
             s3_url: "http://localhost:9000".to_string(),
+            storage_backend: StorageKind::S3,
+            storage_local_path: "./storage".to_string(),
+            azure_container_url: String::new(),
+            azure_sas_token: String::new(),
+            zenodo_url: "http://localhost:9001/api".to_string(),
+            zenodo_access_token: None,
+            datacite_publisher: "SPICE Test Publisher".to_string(),
+            ffmpeg_path: "ffmpeg".to_string(),
+            download_token_ttl_minutes: 5,
+            download_token_max_ttl_minutes: 24 * 60,
+            download_token_max_downloads: Some(1),
+            orphan_cleanup_interval_hours: None,
+            orphan_cleanup_remove: false,
+            allow_overlapping_regions: false,
+            timescaledb: false,
+            sample_type_rules_path: None,
+            weather_api_url: None,
+            weather_api_key: None,
+            elevation_api_url: None,
+            timezone_api_url: None,
+            rate_limit_per_address: None,
+            rate_limit_per_user: None,
+            rate_limit_expensive: None,
+            client_address_header: None,
+            trusted_proxies: vec![],
+            lab_group_prefix: None,
+            require_if_match: false,
+            response_compression: vec!["br".to_string(), "gzip".to_string()],
+            response_cache_seconds: None,
+            sentry_dsn: None,
             tests_running: true, // Set to true for test configurations
             db_url,
         }
@@ -83,10 +489,15 @@ impl Config {
 #[cfg(test)]
 pub mod test_helpers {
     use super::*;
-    use crate::routes::build_router;
+    use crate::common::keycloak::test_realm;
+    use crate::routes::{build_router, build_router_with_auth};
     use axum::Router;
+    use axum::body::{Body, to_bytes};
+    use axum::http::{Request, StatusCode};
     use migration::{Migrator, MigratorTrait};
     use sea_orm::{ConnectionTrait, Database, DatabaseConnection};
+    use serde_json::Value;
+    use tower::ServiceExt;
 
     pub fn init_test_env() {
         // No need for Once since each test gets its own database
@@ -132,4 +543,114 @@ pub mod test_helpers {
         config.keycloak_url = String::new();
         build_router(&db, &config)
     }
+
+    /// App with Keycloak on, trusting the tokens of `test_realm::token`, and
+    /// its database
+    pub async fn setup_authenticated_test_app() -> (Router, DatabaseConnection) {
+        let config = Config::for_tests();
+        let db = setup_test_db().await;
+        let app = build_router_with_auth(&db, &config, Some(test_realm::auth(&config)));
+        (app, db)
+    }
+
+    /// Send a request with an optional JSON body and read the response as
+    /// JSON, or as a string when it is not
+    pub async fn send_json(
+        app: &Router,
+        method: &str,
+        uri: &str,
+        body: Option<&Value>,
+    ) -> (StatusCode, Value) {
+        send_json_as(app, None, method, uri, body).await
+    }
+
+    /// `send_json` with the given bearer token, if any
+    pub async fn send_json_as(
+        app: &Router,
+        token: Option<&str>,
+        method: &str,
+        uri: &str,
+        body: Option<&Value>,
+    ) -> (StatusCode, Value) {
+        let mut request = Request::builder().method(method).uri(uri);
+        if let Some(token) = token {
+            request = request.header("authorization", format!("Bearer {token}"));
+        }
+        if body.is_some() {
+            request = request.header("content-type", "application/json");
+        }
+        let response = app
+            .clone()
+            .oneshot(
+                request
+                    .body(body.map_or_else(Body::empty, |body| Body::from(body.to_string())))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (
+            status,
+            serde_json::from_slice(&bytes)
+                .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&bytes).into_owned())),
+        )
+    }
+
+    /// Create a record with a `POST` and return its ID, failing the test
+    /// unless it was created
+    pub async fn create(app: &Router, uri: &str, body: &Value) -> String {
+        let (status, created) = send_json(app, "POST", uri, Some(body)).await;
+        assert_eq!(status, StatusCode::CREATED, "{uri}: {created}");
+        created["id"].as_str().unwrap().to_string()
+    }
+
+    /// A new, migrated Postgres database, for the tests of what `SQLite` does
+    /// not have, such as the partitioned time series. It is created on the
+    /// server `TEST_POSTGRES_URL` names, as `postgres://user@host:port`;
+    /// without it there is none and the test is skipped.
+    pub async fn setup_postgres_test_db() -> Option<DatabaseConnection> {
+        postgres_test_db(false).await
+    }
+
+    /// `setup_postgres_test_db` with the `timescaledb` extension, for the
+    /// tests of the `TimescaleDB` series. The test is skipped when the
+    /// server does not have it.
+    pub async fn setup_timescale_test_db() -> Option<DatabaseConnection> {
+        postgres_test_db(true).await
+    }
+
+    async fn postgres_test_db(timescale: bool) -> Option<DatabaseConnection> {
+        init_test_env();
+        let Ok(server_url) = env::var("TEST_POSTGRES_URL") else {
+            println!("TEST_POSTGRES_URL is not set; skipping the Postgres test");
+            return None;
+        };
+        let server_url = server_url.trim_end_matches('/');
+        let name = format!("spice_test_{}", uuid::Uuid::new_v4().simple());
+        let server = Database::connect(format!("{server_url}/postgres"))
+            .await
+            .expect("Failed to connect to the Postgres test server");
+        server
+            .execute_unprepared(&format!("CREATE DATABASE {name}"))
+            .await
+            .expect("Failed to create the Postgres test database");
+        server.close().await.ok();
+
+        let db = Database::connect(format!("{server_url}/{name}"))
+            .await
+            .expect("Failed to connect to the Postgres test database");
+        if timescale
+            && let Err(e) = db
+                .execute_unprepared("CREATE EXTENSION IF NOT EXISTS timescaledb")
+                .await
+        {
+            println!("The Postgres test server has no TimescaleDB ({e}); skipping the test");
+            return None;
+        }
+        Migrator::up(&db, None)
+            .await
+            .expect("Failed to run database migrations");
+        Some(db)
+    }
 }
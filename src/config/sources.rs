@@ -0,0 +1,271 @@
+//! Where settings are read from, each overriding the ones before:
+//!
+//! 1. The TOML file named by `CONFIG_FILE`, or `spice.toml` when it exists,
+//!    with the names of the environment variables as keys, in any case.
+//!    Lists may be given as arrays.
+//! 2. The environment, and `.env`.
+//! 3. `<NAME>_FILE`, the path of a file holding the value, as Docker and
+//!    Kubernetes mount secrets. Surrounding whitespace is dropped.
+//!
+//! Problems are collected rather than reported one at a time, so a
+//! deployment sees everything it has to fix at once.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::env::{self, VarError};
+use std::fmt;
+use std::str::FromStr;
+
+/// Config file read when `CONFIG_FILE` is unset, if it exists
+const DEFAULT_CONFIG_FILE: &str = "spice.toml";
+
+/// Everything wrong with the settings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError(pub Vec<String>);
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Invalid configuration:")?;
+        for problem in &self.0 {
+            writeln!(f, "  - {problem}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+#[derive(Debug, Default)]
+pub struct Settings {
+    /// Values of the config file, by upper case name
+    file: HashMap<String, String>,
+    problems: RefCell<Vec<String>>,
+}
+
+impl Settings {
+    /// Settings of the config file, the environment and secrets files
+    pub fn load() -> Self {
+        let mut settings = Self::default();
+        let (path, required) = match env::var("CONFIG_FILE") {
+            Ok(path) if !path.is_empty() => (path, true),
+            _ => (DEFAULT_CONFIG_FILE.to_string(), false),
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => settings.read_file(&path, &contents),
+            Err(e) if required || e.kind() != std::io::ErrorKind::NotFound => {
+                settings.problem(format!("CONFIG_FILE: cannot read {path}: {e}"));
+            }
+            Err(_) => {}
+        }
+        settings
+    }
+
+    /// Take the values of a TOML config file
+    pub fn read_file(&mut self, path: &str, contents: &str) {
+        let table = match contents.parse::<toml::Table>() {
+            Ok(table) => table,
+            Err(e) => {
+                self.problem(format!("CONFIG_FILE: {path} is not valid TOML: {e}"));
+                return;
+            }
+        };
+        let mut file = HashMap::new();
+        for (key, value) in table {
+            let name = key.to_ascii_uppercase();
+            match setting_text(&value) {
+                Some(text) => {
+                    file.insert(name, text);
+                }
+                None => self.problem(format!(
+                    "{name}: {path} must give a string, number, boolean or list of them"
+                )),
+            }
+        }
+        self.file.extend(file);
+    }
+
+    fn problem(&self, problem: String) {
+        let mut problems = self.problems.borrow_mut();
+        if !problems.contains(&problem) {
+            problems.push(problem);
+        }
+    }
+
+    /// Value of a setting, as `env::var` gives it
+    pub fn var(&self, name: &str) -> Result<String, VarError> {
+        let secret = format!("{name}_FILE");
+        if let Ok(path) = env::var(&secret) {
+            if env::var(name).is_ok() {
+                self.problem(format!("{name} and {secret} are both set; set only one"));
+            }
+            return std::fs::read_to_string(&path)
+                .map(|value| value.trim().to_string())
+                .map_err(|e| {
+                    self.problem(format!("{secret}: cannot read {path}: {e}"));
+                    VarError::NotPresent
+                });
+        }
+        env::var(name).or_else(|e| self.file.get(name).cloned().ok_or(e))
+    }
+
+    /// Value of a setting that must be given
+    pub fn required(&self, name: &str) -> String {
+        self.var(name).unwrap_or_else(|_| {
+            // An unreadable secrets file is reported already
+            if env::var(format!("{name}_FILE")).is_err() {
+                self.problem(format!("{name} must be set"));
+            }
+            String::new()
+        })
+    }
+
+    /// Value of a setting read as a number or other type, when given
+    pub fn parsed<T: FromStr>(&self, name: &str) -> Option<T> {
+        let value = self.var(name).ok()?;
+        let parsed = value.trim().parse().ok();
+        if parsed.is_none() {
+            self.problem(format!(
+                "{name}: {value:?} is not a valid {}",
+                std::any::type_name::<T>()
+                    .rsplit("::")
+                    .next()
+                    .unwrap_or_default()
+            ));
+        }
+        parsed
+    }
+
+    /// Value of a `true` or `false` setting, when given; `1` and `0` are
+    /// accepted too
+    pub fn flag(&self, name: &str) -> Option<bool> {
+        let value = self.var(name).ok()?;
+        match value.trim().to_ascii_lowercase().as_str() {
+            "true" | "1" => Some(true),
+            "false" | "0" => Some(false),
+            _ => {
+                self.problem(format!("{name}: {value:?} is not true or false"));
+                None
+            }
+        }
+    }
+
+    /// Report a problem found checking settings against each other
+    pub fn check(&self, valid: bool, problem: impl FnOnce() -> String) {
+        if !valid {
+            self.problem(problem());
+        }
+    }
+
+    /// The problems found, if any
+    pub fn finish(self) -> Result<(), ConfigError> {
+        let problems = self.problems.into_inner();
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError(problems))
+        }
+    }
+}
+
+/// Text of a config file value, lists separated by commas as in the
+/// environment
+fn setting_text(value: &toml::Value) -> Option<String> {
+    match value {
+        toml::Value::String(text) => Some(text.clone()),
+        toml::Value::Integer(number) => Some(number.to_string()),
+        toml::Value::Float(number) => Some(number.to_string()),
+        toml::Value::Boolean(flag) => Some(flag.to_string()),
+        toml::Value::Array(values) => values
+            .iter()
+            .map(|value| match value {
+                toml::Value::Array(_) | toml::Value::Table(_) => None,
+                value => setting_text(value),
+            })
+            .collect::<Option<Vec<_>>>()
+            .map(|values| values.join(",")),
+        toml::Value::Table(_) | toml::Value::Datetime(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(contents: &str) -> Settings {
+        let mut settings = Settings::default();
+        settings.read_file("spice.toml", contents);
+        settings
+    }
+
+    #[test]
+    fn test_config_file_values() {
+        let settings = settings(
+            r#"
+            spice_test_name = "lab"
+            SPICE_TEST_LIMIT = 30
+            spice_test_flag = true
+            spice_test_list = ["br", "gzip"]
+            "#,
+        );
+        assert_eq!(settings.required("SPICE_TEST_NAME"), "lab");
+        assert_eq!(settings.parsed::<u32>("SPICE_TEST_LIMIT"), Some(30));
+        assert_eq!(settings.flag("SPICE_TEST_FLAG"), Some(true));
+        assert_eq!(settings.var("SPICE_TEST_LIST").unwrap(), "br,gzip");
+        assert_eq!(settings.parsed::<u32>("SPICE_TEST_UNSET"), None);
+        assert_eq!(settings.finish(), Ok(()));
+    }
+
+    #[test]
+    fn test_every_problem_is_reported() {
+        let settings = settings(
+            r#"
+            spice_test_limit = "many"
+            spice_test_flag = "perhaps"
+            spice_test_table = { nested = 1 }
+            "#,
+        );
+        settings.required("SPICE_TEST_NAME");
+        settings.required("SPICE_TEST_NAME");
+        settings.parsed::<u32>("SPICE_TEST_LIMIT");
+        settings.flag("SPICE_TEST_FLAG");
+        let ConfigError(problems) = settings.finish().unwrap_err();
+        assert_eq!(
+            problems,
+            [
+                "SPICE_TEST_TABLE: spice.toml must give a string, number, boolean or list of them",
+                "SPICE_TEST_NAME must be set",
+                r#"SPICE_TEST_LIMIT: "many" is not a valid u32"#,
+                r#"SPICE_TEST_FLAG: "perhaps" is not true or false"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_config_file() {
+        let ConfigError(problems) = settings("not toml").finish().unwrap_err();
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].starts_with("CONFIG_FILE: spice.toml is not valid TOML"));
+    }
+
+    #[test]
+    fn test_settings_are_checked_against_each_other() {
+        let mut config = crate::config::Config::for_tests();
+        config.db_min_connections = 30;
+        config.response_compression = vec!["zstd".to_string()];
+        config.db_url = Some("sqlite::memory:".to_string());
+        config.timescaledb = true;
+        config.client_address_header = Some("X-Forwarded-For".to_string());
+        let settings = Settings::default();
+        config.check(&settings);
+        let ConfigError(problems) = settings.finish().unwrap_err();
+        assert_eq!(
+            problems,
+            [
+                "DB_MIN_CONNECTIONS (30) must not be above DB_MAX_CONNECTIONS (20)",
+                "TIMESCALEDB needs a PostgreSQL database",
+                "CLIENT_ADDRESS_HEADER needs TRUSTED_PROXIES",
+                r#"RESPONSE_COMPRESSION: "zstd" is not br or gzip"#,
+            ]
+        );
+    }
+}
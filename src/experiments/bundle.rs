@@ -0,0 +1,750 @@
+//! Self-contained experiment bundles for moving experiments between deployments
+//!
+//! An export gathers the experiment together with everything it references
+//! (regions, tray configuration, treatments and their samples) plus references
+//! to its uploaded assets and derived data. Importing a bundle recreates those
+//! records with fresh IDs and returns the mapping from the original IDs.
+
+use crate::{
+    assets::models as s3_assets,
+    experiments::models as experiments,
+    experiments::phase_transitions::models as well_phase_transitions,
+    experiments::temperatures::models as temperature_readings,
+    locations::models as locations,
+    samples::models::{self as samples, SampleType},
+    tray_configurations::models as tray_configurations,
+    tray_configurations::probes::models as probes,
+    tray_configurations::regions::models as regions,
+    tray_configurations::trays::models as trays,
+    treatments::models::{self as treatments, TreatmentName},
+};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use sea_orm::{
+    ActiveValue::Set, ConnectionTrait, DatabaseConnection, EntityTrait, QueryOrder,
+    TransactionTrait, entity::prelude::*,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Bundle format understood by this version of the API
+pub const BUNDLE_FORMAT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct ExperimentBundle {
+    pub format_version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub experiment: BundleExperiment,
+    pub tray_configuration: Option<BundleTrayConfiguration>,
+    pub regions: Vec<BundleRegion>,
+    pub treatments: Vec<BundleTreatment>,
+    pub samples: Vec<BundleSample>,
+    /// Uploaded files as stored in the source deployment. The objects
+    /// themselves are not part of the bundle.
+    pub assets: Vec<BundleAssetReference>,
+    pub derived: BundleDerivedData,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct BundleExperiment {
+    pub id: Uuid,
+    pub name: String,
+    pub username: Option<String>,
+    pub performed_at: Option<DateTime<Utc>>,
+    pub temperature_ramp: Option<Decimal>,
+    pub temperature_start: Option<Decimal>,
+    pub temperature_end: Option<Decimal>,
+    pub is_calibration: bool,
+    pub remarks: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct BundleTrayConfiguration {
+    pub id: Uuid,
+    pub name: Option<String>,
+    pub trays: Vec<BundleTray>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct BundleTray {
+    pub id: Uuid,
+    pub order_sequence: i32,
+    pub rotation_degrees: i32,
+    pub name: Option<String>,
+    pub qty_cols: Option<i32>,
+    pub qty_rows: Option<i32>,
+    pub well_relative_diameter: Option<Decimal>,
+    pub upper_left_corner_x: Option<i32>,
+    pub upper_left_corner_y: Option<i32>,
+    pub lower_right_corner_x: Option<i32>,
+    pub lower_right_corner_y: Option<i32>,
+    pub probes: Vec<BundleProbe>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct BundleProbe {
+    pub id: Uuid,
+    pub name: String,
+    pub data_column_index: i32,
+    pub position_x: Decimal,
+    pub position_y: Decimal,
+    #[serde(default)]
+    pub calibration_slope: Option<Decimal>,
+    #[serde(default)]
+    pub calibration_offset: Option<Decimal>,
+    #[serde(default)]
+    pub serial_number: Option<String>,
+    #[serde(default)]
+    pub sensor_type: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct BundleRegion {
+    pub id: Uuid,
+    pub treatment_id: Option<Uuid>,
+    pub name: Option<String>,
+    pub display_colour_hex: Option<String>,
+    pub tray_id: Option<i32>,
+    pub col_min: Option<i32>,
+    pub row_min: Option<i32>,
+    pub col_max: Option<i32>,
+    pub row_max: Option<i32>,
+    pub dilution_factor: Option<i32>,
+    pub is_background_key: bool,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct BundleTreatment {
+    pub id: Uuid,
+    pub sample_id: Option<Uuid>,
+    pub name: TreatmentName,
+    pub notes: Option<String>,
+    pub enzyme_volume_litres: Option<Decimal>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct BundleSample {
+    pub id: Uuid,
+    pub name: String,
+    pub r#type: SampleType,
+    pub start_time: Option<DateTime<Utc>>,
+    pub stop_time: Option<DateTime<Utc>>,
+    pub flow_litres_per_minute: Option<Decimal>,
+    pub total_volume: Option<Decimal>,
+    pub material_description: Option<String>,
+    pub extraction_procedure: Option<String>,
+    pub filter_substrate: Option<String>,
+    pub suspension_volume_litres: Option<Decimal>,
+    pub air_volume_litres: Option<Decimal>,
+    pub initial_concentration_gram_l: Option<Decimal>,
+    pub well_volume_litres: Option<Decimal>,
+    pub remarks: Option<String>,
+    pub longitude: Option<Decimal>,
+    pub latitude: Option<Decimal>,
+    pub location_id: Option<Uuid>,
+    /// Used to re-link the sample when the location ID differs on the target
+    pub location_name: Option<String>,
+    /// Kept only when the parent is also in the bundle
+    #[serde(default)]
+    pub parent_sample_id: Option<Uuid>,
+    #[serde(default)]
+    pub aliquot_label: Option<String>,
+    #[serde(default)]
+    pub track: Option<serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct BundleAssetReference {
+    pub id: Uuid,
+    pub original_filename: String,
+    pub s3_key: String,
+    pub r#type: String,
+    pub role: Option<String>,
+    pub size_bytes: Option<i64>,
+    pub uploaded_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug, Default)]
+pub struct BundleDerivedData {
+    pub temperature_readings: u64,
+    pub phase_transitions: u64,
+    /// Filenames of the processed Excel uploads the derived data came from
+    pub source_files: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct BundleImportResult {
+    pub experiment_id: Uuid,
+    pub tray_configuration_id: Option<Uuid>,
+    /// Whether an existing tray configuration with the same name was reused
+    pub reused_tray_configuration: bool,
+    /// Original ID -> newly created ID for every imported record
+    pub id_map: HashMap<Uuid, Uuid>,
+    /// Assets that need to be uploaded again on the target deployment
+    pub assets_to_upload: Vec<String>,
+}
+
+impl From<experiments::Model> for BundleExperiment {
+    fn from(model: experiments::Model) -> Self {
+        Self {
+            id: model.id,
+            name: model.name,
+            username: model.username,
+            performed_at: model.performed_at,
+            temperature_ramp: model.temperature_ramp,
+            temperature_start: model.temperature_start,
+            temperature_end: model.temperature_end,
+            is_calibration: model.is_calibration,
+            remarks: model.remarks,
+        }
+    }
+}
+
+impl From<probes::Model> for BundleProbe {
+    fn from(model: probes::Model) -> Self {
+        Self {
+            id: model.id,
+            name: model.name,
+            data_column_index: model.data_column_index,
+            position_x: model.position_x,
+            position_y: model.position_y,
+            calibration_slope: model.calibration_slope,
+            calibration_offset: model.calibration_offset,
+            serial_number: model.serial_number,
+            sensor_type: model.sensor_type,
+        }
+    }
+}
+
+impl From<regions::Model> for BundleRegion {
+    fn from(model: regions::Model) -> Self {
+        Self {
+            id: model.id,
+            treatment_id: model.treatment_id,
+            name: model.name,
+            display_colour_hex: model.display_colour_hex,
+            tray_id: model.tray_id,
+            col_min: model.col_min,
+            row_min: model.row_min,
+            col_max: model.col_max,
+            row_max: model.row_max,
+            dilution_factor: model.dilution_factor,
+            is_background_key: model.is_background_key,
+        }
+    }
+}
+
+impl From<treatments::Model> for BundleTreatment {
+    fn from(model: treatments::Model) -> Self {
+        Self {
+            id: model.id,
+            sample_id: model.sample_id,
+            name: model.name,
+            notes: model.notes,
+            enzyme_volume_litres: model.enzyme_volume_litres,
+        }
+    }
+}
+
+impl From<samples::Model> for BundleSample {
+    fn from(model: samples::Model) -> Self {
+        Self {
+            id: model.id,
+            name: model.name,
+            r#type: model.r#type,
+            start_time: model.start_time,
+            stop_time: model.stop_time,
+            flow_litres_per_minute: model.flow_litres_per_minute,
+            total_volume: model.total_volume,
+            material_description: model.material_description,
+            extraction_procedure: model.extraction_procedure,
+            filter_substrate: model.filter_substrate,
+            suspension_volume_litres: model.suspension_volume_litres,
+            air_volume_litres: model.air_volume_litres,
+            initial_concentration_gram_l: model.initial_concentration_gram_l,
+            well_volume_litres: model.well_volume_litres,
+            remarks: model.remarks,
+            longitude: model.longitude,
+            latitude: model.latitude,
+            location_id: model.location_id,
+            location_name: None,
+            parent_sample_id: model.parent_sample_id,
+            aliquot_label: model.aliquot_label,
+            track: model.track,
+        }
+    }
+}
+
+impl From<s3_assets::Model> for BundleAssetReference {
+    fn from(model: s3_assets::Model) -> Self {
+        Self {
+            id: model.id,
+            original_filename: model.original_filename,
+            s3_key: model.s3_key,
+            r#type: model.r#type,
+            role: model.role,
+            size_bytes: model.size_bytes,
+            uploaded_at: model.uploaded_at,
+        }
+    }
+}
+
+/// Build a self-contained bundle for an experiment
+pub async fn export_experiment_bundle(
+    db: &impl ConnectionTrait,
+    experiment_id: Uuid,
+) -> Result<ExperimentBundle, DbErr> {
+    let experiment = experiments::Entity::find_by_id(experiment_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Experiment not found".to_string()))?;
+
+    let tray_configuration = match experiment.tray_configuration_id {
+        Some(tray_configuration_id) => export_tray_configuration(db, tray_configuration_id).await?,
+        None => None,
+    };
+
+    let region_models = regions::Entity::find()
+        .filter(regions::Column::ExperimentId.eq(experiment_id))
+        .order_by_asc(regions::Column::CreatedAt)
+        .all(db)
+        .await?;
+
+    let treatment_ids: HashSet<Uuid> = region_models
+        .iter()
+        .filter_map(|r| r.treatment_id)
+        .collect();
+    let treatment_models = if treatment_ids.is_empty() {
+        vec![]
+    } else {
+        treatments::Entity::find()
+            .filter(treatments::Column::Id.is_in(treatment_ids))
+            .all(db)
+            .await?
+    };
+
+    let sample_ids: HashSet<Uuid> = treatment_models
+        .iter()
+        .filter_map(|t| t.sample_id)
+        .collect();
+    let sample_models = if sample_ids.is_empty() {
+        vec![]
+    } else {
+        samples::Entity::find()
+            .filter(samples::Column::Id.is_in(sample_ids))
+            .all(db)
+            .await?
+    };
+
+    let location_ids: HashSet<Uuid> = sample_models.iter().filter_map(|s| s.location_id).collect();
+    let location_names: HashMap<Uuid, String> = if location_ids.is_empty() {
+        HashMap::new()
+    } else {
+        locations::Entity::find()
+            .filter(locations::Column::Id.is_in(location_ids))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|l| (l.id, l.name))
+            .collect()
+    };
+
+    let asset_models = s3_assets::Entity::find()
+        .filter(s3_assets::Column::ExperimentId.eq(experiment_id))
+        .filter(s3_assets::Column::IsDeleted.eq(false))
+        .order_by_asc(s3_assets::Column::OriginalFilename)
+        .all(db)
+        .await?;
+
+    let derived = BundleDerivedData {
+        temperature_readings: temperature_readings::Entity::find()
+            .filter(temperature_readings::Column::ExperimentId.eq(experiment_id))
+            .count(db)
+            .await?,
+        phase_transitions: well_phase_transitions::Entity::find()
+            .filter(well_phase_transitions::Column::ExperimentId.eq(experiment_id))
+            .count(db)
+            .await?,
+        source_files: asset_models
+            .iter()
+            .filter(|a| a.processing_status.as_deref() == Some("completed"))
+            .map(|a| a.original_filename.clone())
+            .collect(),
+    };
+
+    Ok(ExperimentBundle {
+        format_version: BUNDLE_FORMAT_VERSION,
+        exported_at: Utc::now(),
+        experiment: experiment.into(),
+        tray_configuration,
+        regions: region_models.into_iter().map(Into::into).collect(),
+        treatments: treatment_models.into_iter().map(Into::into).collect(),
+        samples: sample_models
+            .into_iter()
+            .map(|s| {
+                let location_name = s
+                    .location_id
+                    .and_then(|id| location_names.get(&id).cloned());
+                BundleSample {
+                    location_name,
+                    ..s.into()
+                }
+            })
+            .collect(),
+        assets: asset_models.into_iter().map(Into::into).collect(),
+        derived,
+    })
+}
+
+async fn export_tray_configuration(
+    db: &impl ConnectionTrait,
+    tray_configuration_id: Uuid,
+) -> Result<Option<BundleTrayConfiguration>, DbErr> {
+    let Some(config) = tray_configurations::Entity::find_by_id(tray_configuration_id)
+        .one(db)
+        .await?
+    else {
+        return Ok(None);
+    };
+
+    let tray_models = trays::Entity::find()
+        .filter(trays::Column::TrayConfigurationId.eq(tray_configuration_id))
+        .order_by_asc(trays::Column::OrderSequence)
+        .all(db)
+        .await?;
+
+    let mut bundle_trays = Vec::with_capacity(tray_models.len());
+    for tray in tray_models {
+        let probe_models = probes::Entity::find()
+            .filter(probes::Column::TrayId.eq(tray.id))
+            .order_by_asc(probes::Column::DataColumnIndex)
+            .all(db)
+            .await?;
+
+        bundle_trays.push(BundleTray {
+            id: tray.id,
+            order_sequence: tray.order_sequence,
+            rotation_degrees: tray.rotation_degrees,
+            name: tray.name,
+            qty_cols: tray.qty_cols,
+            qty_rows: tray.qty_rows,
+            well_relative_diameter: tray.well_relative_diameter,
+            upper_left_corner_x: tray.upper_left_corner_x,
+            upper_left_corner_y: tray.upper_left_corner_y,
+            lower_right_corner_x: tray.lower_right_corner_x,
+            lower_right_corner_y: tray.lower_right_corner_y,
+            probes: probe_models.into_iter().map(Into::into).collect(),
+        });
+    }
+
+    Ok(Some(BundleTrayConfiguration {
+        id: config.id,
+        name: config.name,
+        trays: bundle_trays,
+    }))
+}
+
+/// Recreate the records of a bundle with fresh IDs in a single transaction.
+///
+/// A tray configuration with the same name on the target is reused rather than
+/// duplicated. Samples are re-linked to a location with the same ID or, failing
+/// that, the same name. Assets are only referenced and must be re-uploaded.
+#[allow(clippy::too_many_lines)] // Each record is recreated field by field
+pub async fn import_experiment_bundle(
+    db: &DatabaseConnection,
+    bundle: ExperimentBundle,
+) -> Result<BundleImportResult, DbErr> {
+    if bundle.format_version != BUNDLE_FORMAT_VERSION {
+        return Err(DbErr::Custom(format!(
+            "Unsupported bundle format version {} (expected {BUNDLE_FORMAT_VERSION})",
+            bundle.format_version
+        )));
+    }
+
+    let txn = db.begin().await?;
+    let now = Utc::now();
+    let mut id_map: HashMap<Uuid, Uuid> = HashMap::new();
+
+    if experiments::Entity::find()
+        .filter(experiments::Column::Name.eq(bundle.experiment.name.clone()))
+        .one(&txn)
+        .await?
+        .is_some()
+    {
+        return Err(DbErr::Custom(format!(
+            "An experiment named '{}' already exists",
+            bundle.experiment.name
+        )));
+    }
+
+    let (tray_configuration_id, reused_tray_configuration) = match &bundle.tray_configuration {
+        Some(config) => {
+            let (id, reused) = import_tray_configuration(&txn, config, &mut id_map).await?;
+            (Some(id), reused)
+        }
+        None => (None, false),
+    };
+
+    import_samples(&txn, &bundle.samples, &mut id_map).await?;
+
+    for treatment in &bundle.treatments {
+        let new_id = Uuid::new_v4();
+        treatments::ActiveModel {
+            id: Set(new_id),
+            name: Set(treatment.name.clone()),
+            notes: Set(treatment.notes.clone()),
+            sample_id: Set(treatment.sample_id.and_then(|id| id_map.get(&id).copied())),
+            enzyme_volume_litres: Set(treatment.enzyme_volume_litres),
+            deleted_at: Set(None),
+            created_at: Set(now),
+            last_updated: Set(now),
+        }
+        .insert(&txn)
+        .await?;
+        id_map.insert(treatment.id, new_id);
+    }
+
+    let experiment_id = Uuid::new_v4();
+    experiments::ActiveModel {
+        id: Set(experiment_id),
+        name: Set(bundle.experiment.name.clone()),
+        username: Set(bundle.experiment.username.clone()),
+        performed_at: Set(bundle.experiment.performed_at),
+        temperature_ramp: Set(bundle.experiment.temperature_ramp),
+        temperature_start: Set(bundle.experiment.temperature_start),
+        temperature_end: Set(bundle.experiment.temperature_end),
+        is_calibration: Set(bundle.experiment.is_calibration),
+        remarks: Set(bundle.experiment.remarks.clone()),
+        tray_configuration_id: Set(tray_configuration_id),
+        // A DOI identifies the published original, not the imported copy
+        doi: Set(None),
+        project_id: Set(None),
+        created_by: Set(crate::common::auth::current_username()),
+        lab: Set(crate::common::labs::current_lab()),
+        deleted_at: Set(None),
+        created_at: Set(now),
+        last_updated: Set(now),
+    }
+    .insert(&txn)
+    .await?;
+    id_map.insert(bundle.experiment.id, experiment_id);
+
+    for region in &bundle.regions {
+        let new_id = Uuid::new_v4();
+        regions::ActiveModel {
+            id: Set(new_id),
+            experiment_id: Set(experiment_id),
+            treatment_id: Set(region.treatment_id.and_then(|id| id_map.get(&id).copied())),
+            name: Set(region.name.clone()),
+            display_colour_hex: Set(region.display_colour_hex.clone()),
+            tray_id: Set(region.tray_id),
+            col_min: Set(region.col_min),
+            row_min: Set(region.row_min),
+            col_max: Set(region.col_max),
+            row_max: Set(region.row_max),
+            dilution_factor: Set(region.dilution_factor),
+            // Dilution records are not bundled; regions keep their factor
+            dilution_id: Set(None),
+            is_background_key: Set(region.is_background_key),
+            created_at: Set(now),
+            last_updated: Set(now),
+        }
+        .insert(&txn)
+        .await?;
+        id_map.insert(region.id, new_id);
+    }
+
+    txn.commit().await?;
+
+    Ok(BundleImportResult {
+        experiment_id,
+        tray_configuration_id,
+        reused_tray_configuration,
+        id_map,
+        assets_to_upload: bundle
+            .assets
+            .into_iter()
+            .map(|a| a.original_filename)
+            .collect(),
+    })
+}
+
+// Reuse a tray configuration with the same name, otherwise create a copy
+async fn import_tray_configuration(
+    db: &impl ConnectionTrait,
+    config: &BundleTrayConfiguration,
+    id_map: &mut HashMap<Uuid, Uuid>,
+) -> Result<(Uuid, bool), DbErr> {
+    if let Some(name) = &config.name
+        && let Some(existing) = tray_configurations::Entity::find()
+            .filter(tray_configurations::Column::Name.eq(name.clone()))
+            .one(db)
+            .await?
+    {
+        id_map.insert(config.id, existing.id);
+        return Ok((existing.id, true));
+    }
+
+    let now = Utc::now();
+    let tray_configuration_id = Uuid::new_v4();
+    tray_configurations::ActiveModel {
+        id: Set(tray_configuration_id),
+        name: Set(config.name.clone()),
+        experiment_default: Set(false),
+        revision: Set(1),
+        revision_of_id: Set(None),
+        lab: Set(crate::common::labs::current_lab()),
+        deleted_at: Set(None),
+        created_at: Set(now),
+        last_updated: Set(now),
+    }
+    .insert(db)
+    .await?;
+    id_map.insert(config.id, tray_configuration_id);
+
+    for tray in &config.trays {
+        let tray_id = Uuid::new_v4();
+        trays::ActiveModel {
+            id: Set(tray_id),
+            tray_configuration_id: Set(tray_configuration_id),
+            order_sequence: Set(tray.order_sequence),
+            rotation_degrees: Set(tray.rotation_degrees),
+            name: Set(tray.name.clone()),
+            qty_cols: Set(tray.qty_cols),
+            qty_rows: Set(tray.qty_rows),
+            well_relative_diameter: Set(tray.well_relative_diameter),
+            upper_left_corner_x: Set(tray.upper_left_corner_x),
+            upper_left_corner_y: Set(tray.upper_left_corner_y),
+            lower_right_corner_x: Set(tray.lower_right_corner_x),
+            lower_right_corner_y: Set(tray.lower_right_corner_y),
+            created_at: Set(now),
+            last_updated: Set(now),
+        }
+        .insert(db)
+        .await?;
+        id_map.insert(tray.id, tray_id);
+
+        for probe in &tray.probes {
+            let probe_id = Uuid::new_v4();
+            probes::ActiveModel {
+                id: Set(probe_id),
+                tray_id: Set(tray_id),
+                name: Set(probe.name.clone()),
+                data_column_index: Set(probe.data_column_index),
+                position_x: Set(probe.position_x),
+                position_y: Set(probe.position_y),
+                calibration_slope: Set(probe.calibration_slope),
+                calibration_offset: Set(probe.calibration_offset),
+                serial_number: Set(probe.serial_number.clone()),
+                sensor_type: Set(probe.sensor_type.clone()),
+                // Certificates are assets of the exporting instance
+                calibration_certificate_asset_id: Set(None),
+                created_at: Set(now),
+                last_updated: Set(now),
+            }
+            .insert(db)
+            .await?;
+            id_map.insert(probe.id, probe_id);
+        }
+    }
+
+    Ok((tray_configuration_id, false))
+}
+
+async fn import_samples(
+    db: &impl ConnectionTrait,
+    bundle_samples: &[BundleSample],
+    id_map: &mut HashMap<Uuid, Uuid>,
+) -> Result<(), DbErr> {
+    let now = Utc::now();
+    for sample in bundle_samples {
+        let location_id = resolve_location(db, sample).await?;
+        let new_id = Uuid::new_v4();
+        samples::ActiveModel {
+            id: Set(new_id),
+            name: Set(sample.name.clone()),
+            r#type: Set(sample.r#type.clone()),
+            start_time: Set(sample.start_time),
+            stop_time: Set(sample.stop_time),
+            flow_litres_per_minute: Set(sample.flow_litres_per_minute),
+            total_volume: Set(sample.total_volume),
+            material_description: Set(sample.material_description.clone()),
+            extraction_procedure: Set(sample.extraction_procedure.clone()),
+            filter_substrate: Set(sample.filter_substrate.clone()),
+            suspension_volume_litres: Set(sample.suspension_volume_litres),
+            air_volume_litres: Set(sample.air_volume_litres),
+            initial_concentration_gram_l: Set(sample.initial_concentration_gram_l),
+            well_volume_litres: Set(sample.well_volume_litres),
+            remarks: Set(sample.remarks.clone()),
+            longitude: Set(sample.longitude),
+            latitude: Set(sample.latitude),
+            location_id: Set(location_id),
+            parent_sample_id: Set(None),
+            aliquot_label: Set(sample.aliquot_label.clone()),
+            track: Set(sample.track.clone()),
+            // Barcodes and storage places belong to the exporting lab
+            barcode: Set(None),
+            storage_freezer: Set(None),
+            storage_shelf: Set(None),
+            storage_box: Set(None),
+            storage_position: Set(None),
+            // QC is reviewed again by the importing lab
+            qc_status: Set(samples::SampleQcStatus::Pending),
+            qc_reason: Set(None),
+            qc_reviewed_by: Set(None),
+            qc_reviewed_at: Set(None),
+            // The importing user created the copy
+            created_by: Set(crate::common::auth::current_username()),
+            lab: Set(crate::common::labs::current_lab()),
+            deleted_at: Set(None),
+            created_at: Set(now),
+            last_updated: Set(now),
+        }
+        .insert(db)
+        .await?;
+        id_map.insert(sample.id, new_id);
+    }
+
+    // Parents are linked once every sample of the bundle exists
+    for sample in bundle_samples {
+        if let (Some(new_id), Some(new_parent_id)) = (
+            id_map.get(&sample.id),
+            sample.parent_sample_id.and_then(|id| id_map.get(&id)),
+        ) {
+            samples::ActiveModel {
+                id: Set(*new_id),
+                parent_sample_id: Set(Some(*new_parent_id)),
+                ..Default::default()
+            }
+            .update(db)
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn resolve_location(
+    db: &impl ConnectionTrait,
+    sample: &BundleSample,
+) -> Result<Option<Uuid>, DbErr> {
+    if let Some(location_id) = sample.location_id
+        && locations::Entity::find_by_id(location_id)
+            .one(db)
+            .await?
+            .is_some()
+    {
+        return Ok(Some(location_id));
+    }
+
+    match &sample.location_name {
+        Some(name) => Ok(locations::Entity::find()
+            .filter(locations::Column::Name.eq(name.clone()))
+            .one(db)
+            .await?
+            .map(|l| l.id)),
+        None => Ok(None),
+    }
+}
@@ -0,0 +1,134 @@
+//! Copies of an experiment to set up a repeat run of the same layout.
+//!
+//! The copy keeps the experiment's settings, project, tray configuration
+//! and regions with their treatments, but none of what a run produces:
+//! readings, phase transitions, results, assets or exclusions. Its DOI and
+//! run time are left for the new run.
+
+use super::models::{self as experiments, Experiment, get_one_experiment};
+use crate::tray_configurations::regions::models as regions;
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr,
+    EntityTrait, QueryFilter, TransactionTrait,
+};
+use serde::Deserialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(Clone, Debug, Default, Deserialize, ToSchema)]
+pub struct ExperimentCloneRequest {
+    /// Name of the copy (default: the original's name followed by "(copy)")
+    pub name: Option<String>,
+    /// When the repeat run is performed, if known
+    pub performed_at: Option<DateTime<Utc>>,
+}
+
+async fn name_taken(db: &impl ConnectionTrait, name: &str) -> Result<bool, DbErr> {
+    Ok(experiments::Entity::find()
+        .filter(experiments::Column::Name.eq(name))
+        .one(db)
+        .await?
+        .is_some())
+}
+
+/// First free name of the form `<name> (copy)`, `<name> (copy 2)`, ...
+async fn copy_name(db: &impl ConnectionTrait, name: &str) -> Result<String, DbErr> {
+    let mut candidate = format!("{name} (copy)");
+    let mut number = 1;
+    while name_taken(db, &candidate).await? {
+        number += 1;
+        candidate = format!("{name} (copy {number})");
+    }
+    Ok(candidate)
+}
+
+/// Copy the experiment and its regions. Problems are returned as
+/// `DbErr::Custom`.
+pub async fn clone_experiment(
+    db: &DatabaseConnection,
+    experiment_id: Uuid,
+    request: ExperimentCloneRequest,
+) -> Result<Experiment, DbErr> {
+    let original = experiments::Entity::find_by_id(experiment_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Experiment not found".to_string()))?;
+    let layout = regions::Entity::find()
+        .filter(regions::Column::ExperimentId.eq(experiment_id))
+        .all(db)
+        .await?;
+    crate::samples::qc::ensure_not_rejected(
+        db,
+        layout
+            .iter()
+            .filter_map(|region| region.treatment_id)
+            .collect(),
+    )
+    .await?;
+
+    let txn = db.begin().await?;
+    let name = match request.name {
+        Some(name) if name.trim().is_empty() => {
+            return Err(DbErr::Custom("name must not be empty".to_string()));
+        }
+        Some(name) => {
+            if name_taken(&txn, &name).await? {
+                return Err(DbErr::Custom(format!(
+                    "An experiment named '{name}' already exists"
+                )));
+            }
+            name
+        }
+        None => copy_name(&txn, &original.name).await?,
+    };
+
+    let now = Utc::now();
+    let copy = experiments::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        name: Set(name),
+        username: Set(original.username),
+        performed_at: Set(request.performed_at),
+        temperature_ramp: Set(original.temperature_ramp),
+        temperature_start: Set(original.temperature_start),
+        temperature_end: Set(original.temperature_end),
+        is_calibration: Set(original.is_calibration),
+        remarks: Set(original.remarks),
+        tray_configuration_id: Set(original.tray_configuration_id),
+        // A DOI identifies the published original, not the repeat run
+        doi: Set(None),
+        project_id: Set(original.project_id),
+        created_by: Set(crate::common::auth::current_username()),
+        lab: Set(crate::common::labs::current_lab()),
+        deleted_at: Set(None),
+        created_at: Set(now),
+        last_updated: Set(now),
+    }
+    .insert(&txn)
+    .await?;
+
+    for region in layout {
+        regions::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            experiment_id: Set(copy.id),
+            treatment_id: Set(region.treatment_id),
+            name: Set(region.name),
+            display_colour_hex: Set(region.display_colour_hex),
+            tray_id: Set(region.tray_id),
+            col_min: Set(region.col_min),
+            row_min: Set(region.row_min),
+            col_max: Set(region.col_max),
+            row_max: Set(region.row_max),
+            dilution_factor: Set(region.dilution_factor),
+            dilution_id: Set(region.dilution_id),
+            is_background_key: Set(region.is_background_key),
+            created_at: Set(now),
+            last_updated: Set(now),
+        }
+        .insert(&txn)
+        .await?;
+    }
+    txn.commit().await?;
+
+    get_one_experiment(db, copy.id).await
+}
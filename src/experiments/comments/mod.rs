@@ -0,0 +1,4 @@
+pub mod models;
+pub mod services;
+#[cfg(test)]
+mod tests;
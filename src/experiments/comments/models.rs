@@ -0,0 +1,123 @@
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// A comment on an experiment or one of its wells, or a reply to one
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "experiment_comments")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    pub experiment_id: Uuid,
+    /// Well the comment is on, if not on the whole experiment
+    pub well_id: Option<Uuid>,
+    /// Comment starting the thread, for replies
+    pub parent_id: Option<Uuid>,
+    #[sea_orm(column_type = "Text")]
+    pub body: String,
+    /// Usernames mentioned in the body
+    #[sea_orm(column_type = "JsonBinary")]
+    pub mentions: Json,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub created_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_updated: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "crate::experiments::models::Entity",
+        from = "Column::ExperimentId",
+        to = "crate::experiments::models::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Experiments,
+    #[sea_orm(
+        belongs_to = "crate::tray_configurations::wells::models::Entity",
+        from = "Column::WellId",
+        to = "crate::tray_configurations::wells::models::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Wells,
+    #[sea_orm(
+        belongs_to = "Entity",
+        from = "Column::ParentId",
+        to = "Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Parent,
+}
+
+impl Related<crate::experiments::models::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Experiments.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    pub fn mention_list(&self) -> Vec<String> {
+        serde_json::from_value(self.mentions.clone()).unwrap_or_default()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ExperimentComment {
+    pub id: Uuid,
+    pub experiment_id: Uuid,
+    pub well_id: Option<Uuid>,
+    /// Well as `P1:A1`, for comments on a well
+    pub coordinate: Option<String>,
+    pub parent_id: Option<Uuid>,
+    pub body: String,
+    /// Usernames mentioned in the body as `@username`
+    pub mentions: Vec<String>,
+    pub created_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_updated: DateTime<Utc>,
+    /// Replies to the comment, oldest first; replies have none
+    #[schema(no_recursion)]
+    pub replies: Vec<ExperimentComment>,
+}
+
+impl ExperimentComment {
+    pub(super) fn new(model: Model, coordinate: Option<String>) -> Self {
+        Self {
+            mentions: model.mention_list(),
+            id: model.id,
+            experiment_id: model.experiment_id,
+            well_id: model.well_id,
+            coordinate,
+            parent_id: model.parent_id,
+            body: model.body,
+            created_by: model.created_by,
+            created_at: model.created_at,
+            last_updated: model.last_updated,
+            replies: vec![],
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, ToSchema)]
+pub struct ExperimentCommentCreate {
+    /// Text of the comment; `@username` mentions a user
+    pub body: String,
+    /// Well the comment is on, as `P1:A1`, or `A1` when the configuration
+    /// has a single tray; left out for comments on the whole experiment
+    pub coordinate: Option<String>,
+    /// Comment replied to; replies are on the well of their thread
+    pub parent_id: Option<Uuid>,
+}
+
+#[derive(Clone, Debug, Deserialize, ToSchema)]
+pub struct ExperimentCommentUpdate {
+    /// New text of the comment, whose mentions are read again
+    pub body: String,
+}
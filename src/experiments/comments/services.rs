@@ -0,0 +1,332 @@
+//! Threads of comments on experiments and their wells.
+//!
+//! Analysts discuss a run, or an anomaly in one of its wells, in threads: a
+//! comment on the experiment or on a well, and the replies to it, oldest
+//! first. Users are mentioned as `@username`; the usernames mentioned are
+//! kept with each comment, so clients can notify them and find the threads
+//! naming a user.
+
+use super::models::{
+    ActiveModel, Column, Entity, ExperimentComment, ExperimentCommentCreate,
+    ExperimentCommentUpdate, Model,
+};
+use crate::experiments::models as experiments;
+use crate::tray_configurations::wells::services::experiment_wells;
+use crate::tray_configurations::{trays::models as trays, wells::models as wells};
+use chrono::Utc;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, DatabaseConnection, DbErr, EntityTrait,
+    IntoActiveModel, QueryFilter, QueryOrder,
+};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+#[derive(Clone, Debug, Default, Deserialize, IntoParams)]
+pub struct ExperimentCommentQuery {
+    /// Only the threads on the well at this coordinate, such as `P1:A1`
+    pub coordinate: Option<String>,
+    /// Only the threads mentioning this username
+    pub mentioned: Option<String>,
+}
+
+/// Usernames mentioned in a comment as `@username`, with where each starts
+/// in the body. An `@` within a word, as in an email address, is not a
+/// mention.
+fn mention_spans(body: &str) -> Vec<(usize, &str)> {
+    let is_username_char = |c: char| c.is_alphanumeric() || matches!(c, '.' | '_' | '-');
+    let mut spans = vec![];
+    let mut previous = None;
+    for (index, c) in body.char_indices() {
+        if c == '@' && !previous.is_some_and(is_username_char) {
+            let rest = &body[index + 1..];
+            let end = rest.find(|c| !is_username_char(c)).unwrap_or(rest.len());
+            let username = rest[..end].trim_end_matches(['.', '-']);
+            if !username.is_empty() {
+                spans.push((index + 1, username));
+            }
+        }
+        previous = Some(c);
+    }
+    spans
+}
+
+/// Usernames mentioned in a comment as `@username`, in the order they are
+/// first mentioned
+pub fn mentions(body: &str) -> Vec<String> {
+    let mut mentioned: Vec<String> = vec![];
+    for (_, username) in mention_spans(body) {
+        if !mentioned.iter().any(|known| known == username) {
+            mentioned.push(username.to_string());
+        }
+    }
+    mentioned
+}
+
+/// The body with the mentions of a user made mentions of another name
+pub fn replace_mentions(body: &str, username: &str, replacement: &str) -> String {
+    let mut replaced = String::with_capacity(body.len());
+    let mut copied = 0;
+    for (start, mentioned) in mention_spans(body) {
+        if mentioned == username {
+            replaced.push_str(&body[copied..start]);
+            replaced.push_str(replacement);
+            copied = start + mentioned.len();
+        }
+    }
+    replaced.push_str(&body[copied..]);
+    replaced
+}
+
+fn check_body(body: &str) -> Result<String, DbErr> {
+    let body = body.trim();
+    if body.is_empty() {
+        return Err(DbErr::Custom("The comment is empty".to_string()));
+    }
+    Ok(body.to_string())
+}
+
+async fn find_experiment(db: &DatabaseConnection, experiment_id: Uuid) -> Result<(), DbErr> {
+    experiments::Entity::find_by_id(experiment_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Experiment not found".to_string()))?;
+    Ok(())
+}
+
+/// The well of the experiment at a coordinate, which must name its tray
+/// when the configuration has more than one
+async fn find_well(
+    db: &DatabaseConnection,
+    experiment_id: Uuid,
+    coordinate: &str,
+) -> Result<Uuid, DbErr> {
+    match experiment_wells(db, experiment_id, Some(coordinate))
+        .await?
+        .as_slice()
+    {
+        [well] => Ok(well.id),
+        [] => Err(DbErr::Custom(format!(
+            "No well of the experiment's trays at '{coordinate}'"
+        ))),
+        _ => Err(DbErr::Custom(format!(
+            "Name the tray of well '{coordinate}', as in P1:{coordinate}"
+        ))),
+    }
+}
+
+/// Coordinates of wells as `P1:A1`, by well ID
+async fn well_coordinates(
+    db: &DatabaseConnection,
+    well_ids: HashSet<Uuid>,
+) -> Result<HashMap<Uuid, String>, DbErr> {
+    if well_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    Ok(wells::Entity::find()
+        .filter(wells::Column::Id.is_in(well_ids))
+        .find_also_related(trays::Entity)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|(well, tray)| {
+            let name = format!("{}{}", well.row_letter, well.column_number);
+            let coordinate = match tray.and_then(|tray| tray.name) {
+                Some(tray_name) => format!("{tray_name}:{name}"),
+                None => name,
+            };
+            (well.id, coordinate)
+        })
+        .collect())
+}
+
+/// Comments gathered into threads, each with its replies
+async fn threads(
+    db: &DatabaseConnection,
+    comments: Vec<Model>,
+) -> Result<Vec<ExperimentComment>, DbErr> {
+    let coordinates =
+        well_coordinates(db, comments.iter().filter_map(|c| c.well_id).collect()).await?;
+    let present = |comment: Model| {
+        let coordinate = comment
+            .well_id
+            .and_then(|well_id| coordinates.get(&well_id).cloned());
+        ExperimentComment::new(comment, coordinate)
+    };
+    let (starts, replies): (Vec<Model>, Vec<Model>) = comments
+        .into_iter()
+        .partition(|comment| comment.parent_id.is_none());
+    let mut threads: Vec<ExperimentComment> = starts.into_iter().map(present).collect();
+    for reply in replies {
+        if let Some(thread) = threads
+            .iter_mut()
+            .find(|thread| Some(thread.id) == reply.parent_id)
+        {
+            thread.replies.push(present(reply));
+        }
+    }
+    Ok(threads)
+}
+
+/// The experiment's threads, oldest first, as embedded in the experiment
+pub async fn comment_threads(
+    db: &DatabaseConnection,
+    experiment_id: Uuid,
+) -> Result<Vec<ExperimentComment>, DbErr> {
+    let comments = Entity::find()
+        .filter(Column::ExperimentId.eq(experiment_id))
+        .order_by_asc(Column::CreatedAt)
+        .order_by_asc(Column::Id)
+        .all(db)
+        .await?;
+    threads(db, comments).await
+}
+
+/// The experiment's threads, optionally only those on a well or mentioning
+/// a user. An invalid coordinate is returned as `DbErr::Custom`.
+pub async fn list_comments(
+    db: &DatabaseConnection,
+    experiment_id: Uuid,
+    query: &ExperimentCommentQuery,
+) -> Result<Vec<ExperimentComment>, DbErr> {
+    find_experiment(db, experiment_id).await?;
+    let well_id = match &query.coordinate {
+        Some(coordinate) => Some(find_well(db, experiment_id, coordinate.trim()).await?),
+        None => None,
+    };
+    let mut threads = comment_threads(db, experiment_id).await?;
+    if well_id.is_some() {
+        threads.retain(|thread| thread.well_id == well_id);
+    }
+    if let Some(username) = &query.mentioned {
+        threads.retain(|thread| {
+            thread.mentions.contains(username)
+                || thread
+                    .replies
+                    .iter()
+                    .any(|reply| reply.mentions.contains(username))
+        });
+    }
+    Ok(threads)
+}
+
+/// A comment of the experiment
+pub async fn get_comment(
+    db: &DatabaseConnection,
+    experiment_id: Uuid,
+    comment_id: Uuid,
+) -> Result<Model, DbErr> {
+    Entity::find_by_id(comment_id)
+        .one(db)
+        .await?
+        .filter(|comment| comment.experiment_id == experiment_id)
+        .ok_or_else(|| DbErr::RecordNotFound("Comment not found".to_string()))
+}
+
+/// A comment with its replies, which are on the well of their thread
+async fn with_replies(db: &DatabaseConnection, comment: Model) -> Result<ExperimentComment, DbErr> {
+    let replies = if comment.parent_id.is_none() {
+        Entity::find()
+            .filter(Column::ParentId.eq(comment.id))
+            .order_by_asc(Column::CreatedAt)
+            .order_by_asc(Column::Id)
+            .all(db)
+            .await?
+    } else {
+        vec![]
+    };
+    let coordinate = match comment.well_id {
+        Some(well_id) => well_coordinates(db, HashSet::from([well_id]))
+            .await?
+            .remove(&well_id),
+        None => None,
+    };
+    let mut comment = ExperimentComment::new(comment, coordinate.clone());
+    comment.replies = replies
+        .into_iter()
+        .map(|reply| ExperimentComment::new(reply, coordinate.clone()))
+        .collect();
+    Ok(comment)
+}
+
+/// Comment on the experiment, one of its wells, or reply to a comment.
+/// Replies to replies join the thread of the comment replied to. Empty
+/// comments, coordinates that are not of the experiment's wells, and
+/// parents of other experiments are returned as `DbErr::Custom`.
+pub async fn create_comment(
+    db: &DatabaseConnection,
+    experiment_id: Uuid,
+    input: ExperimentCommentCreate,
+) -> Result<ExperimentComment, DbErr> {
+    find_experiment(db, experiment_id).await?;
+    let body = check_body(&input.body)?;
+    let (parent_id, well_id) = match input.parent_id {
+        Some(_) if input.coordinate.is_some() => {
+            return Err(DbErr::Custom(
+                "Replies are on the well of their thread and take no coordinate".to_string(),
+            ));
+        }
+        Some(parent_id) => {
+            let parent = get_comment(db, experiment_id, parent_id)
+                .await
+                .map_err(|_| {
+                    DbErr::Custom("The parent_id is not a comment of the experiment".to_string())
+                })?;
+            (Some(parent.parent_id.unwrap_or(parent.id)), parent.well_id)
+        }
+        None => match &input.coordinate {
+            Some(coordinate) => (
+                None,
+                Some(find_well(db, experiment_id, coordinate.trim()).await?),
+            ),
+            None => (None, None),
+        },
+    };
+
+    let now = Utc::now();
+    let comment = ActiveModel {
+        id: Set(Uuid::new_v4()),
+        experiment_id: Set(experiment_id),
+        well_id: Set(well_id),
+        parent_id: Set(parent_id),
+        mentions: Set(json!(mentions(&body))),
+        body: Set(body),
+        created_by: Set(crate::common::auth::current_username()),
+        created_at: Set(now),
+        last_updated: Set(now),
+    }
+    .insert(db)
+    .await?;
+    with_replies(db, comment).await
+}
+
+/// Change the text of a comment, and with it the users it mentions
+pub async fn update_comment(
+    db: &DatabaseConnection,
+    experiment_id: Uuid,
+    comment_id: Uuid,
+    input: ExperimentCommentUpdate,
+) -> Result<ExperimentComment, DbErr> {
+    let body = check_body(&input.body)?;
+    let mut comment = get_comment(db, experiment_id, comment_id)
+        .await?
+        .into_active_model();
+    comment.mentions = Set(json!(mentions(&body)));
+    comment.body = Set(body);
+    comment.last_updated = Set(Utc::now());
+    let comment = comment.update(db).await?;
+    with_replies(db, comment).await
+}
+
+/// Delete a comment, and its replies when it starts a thread
+pub async fn delete_comment(
+    db: &DatabaseConnection,
+    experiment_id: Uuid,
+    comment_id: Uuid,
+) -> Result<(), DbErr> {
+    get_comment(db, experiment_id, comment_id).await?;
+    Entity::delete_by_id(comment_id).exec(db).await?;
+    Ok(())
+}
@@ -0,0 +1,167 @@
+use crate::config::test_helpers::{send_json, setup_test_app};
+use axum::http::StatusCode;
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+#[test]
+fn test_mentions() {
+    assert_eq!(
+        super::services::mentions("@alice, is this @bob.'s run? Ask carol@example.org or @alice"),
+        vec!["alice", "bob"]
+    );
+    assert!(super::services::mentions("@ and @- are not users").is_empty());
+}
+
+#[tokio::test]
+#[allow(clippy::too_many_lines)]
+async fn test_comment_threads_on_experiments_and_wells() {
+    let app = setup_test_app().await;
+    let (status, demo) = send_json(&app, "POST", "/api/maintenance/demo", None).await;
+    assert_eq!(status, StatusCode::CREATED, "{demo}");
+    let experiment = format!(
+        "/api/experiments/{}",
+        demo["experiment_id"].as_str().unwrap()
+    );
+    let comments = format!("{experiment}/comments");
+
+    // Comments are on the experiment's wells, which name their tray when
+    // there are several
+    for invalid in [
+        json!({"body": "  "}),
+        json!({"body": "Which tray?", "coordinate": "A1"}),
+        json!({"body": "No such tray", "coordinate": "P9:A1"}),
+        json!({"body": "No such well", "coordinate": "P1:Z99"}),
+        json!({"body": "Orphan", "parent_id": Uuid::new_v4()}),
+    ] {
+        let (status, _) = send_json(&app, "POST", &comments, Some(&invalid)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{invalid}");
+    }
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        &format!("/api/experiments/{}/comments", Uuid::new_v4()),
+        Some(&json!({"body": "Hello"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, run_comment) = send_json(
+        &app,
+        "POST",
+        &comments,
+        Some(&json!({"body": "The cooling rate dips after -15 °C, @alice"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{run_comment}");
+    assert_eq!(run_comment["mentions"], json!(["alice"]));
+    assert_eq!(run_comment["coordinate"], Value::Null);
+    let (status, well_comment) = send_json(
+        &app,
+        "POST",
+        &comments,
+        Some(&json!({"body": "Froze far too early, contaminated?", "coordinate": "P1:a1"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{well_comment}");
+    assert_eq!(well_comment["coordinate"], "P1:A1");
+    assert!(well_comment["well_id"].is_string());
+
+    // Replies are on the well of their thread, and replies to replies join it
+    let (status, reply) = send_json(
+        &app,
+        "POST",
+        &comments,
+        Some(&json!({"body": "@bob saw a bubble in it", "parent_id": well_comment["id"]})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{reply}");
+    assert_eq!(reply["well_id"], well_comment["well_id"]);
+    assert_eq!(reply["coordinate"], "P1:A1");
+    let (status, nested) = send_json(
+        &app,
+        "POST",
+        &comments,
+        Some(&json!({"body": "Excluding it, @alice @bob", "parent_id": reply["id"]})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{nested}");
+    assert_eq!(nested["parent_id"], well_comment["id"]);
+    assert_eq!(nested["mentions"], json!(["alice", "bob"]));
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        &comments,
+        Some(&json!({"body": "Elsewhere", "parent_id": reply["id"], "coordinate": "P2:A1"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, threads) = send_json(&app, "GET", &comments, None).await;
+    assert_eq!(status, StatusCode::OK, "{threads}");
+    assert_eq!(threads.as_array().unwrap().len(), 2);
+    assert_eq!(threads[0]["id"], run_comment["id"]);
+    assert_eq!(threads[0]["replies"], json!([]));
+    let replies = threads[1]["replies"].as_array().unwrap();
+    assert_eq!(replies.len(), 2);
+    assert_eq!(replies[0]["id"], reply["id"]);
+    assert_eq!(replies[1]["id"], nested["id"]);
+
+    // Threads are found by well and by the users they mention
+    let (status, on_well) =
+        send_json(&app, "GET", &format!("{comments}?coordinate=P1:A1"), None).await;
+    assert_eq!(status, StatusCode::OK, "{on_well}");
+    assert_eq!(on_well.as_array().unwrap().len(), 1);
+    assert_eq!(on_well[0]["id"], well_comment["id"]);
+    let (status, mentioning) =
+        send_json(&app, "GET", &format!("{comments}?mentioned=bob"), None).await;
+    assert_eq!(status, StatusCode::OK, "{mentioning}");
+    assert_eq!(mentioning.as_array().unwrap().len(), 1);
+    assert_eq!(mentioning[0]["id"], well_comment["id"]);
+    let (status, mentioning) =
+        send_json(&app, "GET", &format!("{comments}?mentioned=alice"), None).await;
+    assert_eq!(status, StatusCode::OK, "{mentioning}");
+    assert_eq!(mentioning.as_array().unwrap().len(), 2);
+
+    // The experiment embeds its threads unless left out
+    let (status, detail) = send_json(&app, "GET", &experiment, None).await;
+    assert_eq!(status, StatusCode::OK, "{detail}");
+    assert_eq!(detail["comments"], threads);
+    let (status, detail) =
+        send_json(&app, "GET", &format!("{experiment}?include=regions"), None).await;
+    assert_eq!(status, StatusCode::OK, "{detail}");
+    assert_eq!(detail["comments"], Value::Null);
+
+    // Edits read the mentions again
+    let run_comment_uri = format!("{comments}/{}", run_comment["id"].as_str().unwrap());
+    let (status, edited) = send_json(
+        &app,
+        "PATCH",
+        &run_comment_uri,
+        Some(&json!({"body": "The cooling rate dips after -15 °C, @dave"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{edited}");
+    assert_eq!(edited["mentions"], json!(["dave"]));
+    assert_eq!(edited["created_at"], run_comment["created_at"]);
+    let (status, _) = send_json(&app, "PATCH", &run_comment_uri, Some(&json!({"body": ""}))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Deleting a thread deletes its replies
+    let well_comment_uri = format!("{comments}/{}", well_comment["id"].as_str().unwrap());
+    let (status, _) = send_json(&app, "DELETE", &well_comment_uri, None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = send_json(&app, "DELETE", &well_comment_uri, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = send_json(
+        &app,
+        "DELETE",
+        &format!("{comments}/{}", reply["id"].as_str().unwrap()),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, threads) = send_json(&app, "GET", &comments, None).await;
+    assert_eq!(status, StatusCode::OK, "{threads}");
+    assert_eq!(threads.as_array().unwrap().len(), 1);
+    assert_eq!(threads[0]["mentions"], json!(["dave"]));
+}
@@ -0,0 +1,299 @@
+//! Regenerate a `merged.xlsx`-style workbook from the stored experiment data
+//!
+//! The layout mirrors the files produced by the acquisition software and
+//! accepted by the Excel processor: tray names in row 1, well coordinates in
+//! row 2, probe names in row 6 and column headers in row 7 (`Date`, `Time`,
+//! `Temperature N (°C)`, `(.jpg)` and `()` for each well), followed by one row
+//! per temperature reading with the 0/1 state of every well.
+
+use crate::{
+    experiments::models as experiments,
+    experiments::phase_transitions::models as well_phase_transitions,
+    experiments::probe_temperature_readings::models as probe_temperature_readings,
+    experiments::temperatures::models as temperature_readings,
+    tray_configurations::probes::models as probes, tray_configurations::trays::models as trays,
+    tray_configurations::wells::models as wells,
+};
+use rust_decimal::prelude::ToPrimitive;
+use rust_xlsxwriter::{Format, Workbook, XlsxError};
+use sea_orm::{DatabaseConnection, EntityTrait, QueryOrder, entity::prelude::*};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+const TRAY_ROW: u32 = 0;
+const COORDINATE_ROW: u32 = 1;
+const CHANNEL_ROW: u32 = 5;
+const HEADER_ROW: u32 = 6;
+const DATA_START_ROW: u32 = 7;
+
+/// Well column of the workbook, in tray order then row-major (A1, A2, ... H12)
+struct WellColumn {
+    well_id: Uuid,
+    tray_name: String,
+    coordinate: String,
+}
+
+async fn load_well_columns(
+    db: &DatabaseConnection,
+    tray_configuration_id: Option<Uuid>,
+) -> Result<(Vec<trays::Model>, Vec<WellColumn>), DbErr> {
+    let Some(tray_configuration_id) = tray_configuration_id else {
+        return Ok((vec![], vec![]));
+    };
+
+    let config_trays = trays::Entity::find()
+        .filter(trays::Column::TrayConfigurationId.eq(tray_configuration_id))
+        .order_by_asc(trays::Column::OrderSequence)
+        .all(db)
+        .await?;
+    if config_trays.is_empty() {
+        return Ok((config_trays, vec![]));
+    }
+
+    let mut tray_wells = wells::Entity::find()
+        .filter(wells::Column::TrayId.is_in(config_trays.iter().map(|tray| tray.id)))
+        .all(db)
+        .await?;
+    let tray_order: HashMap<Uuid, (i32, String)> = config_trays
+        .iter()
+        .map(|tray| {
+            (
+                tray.id,
+                (
+                    tray.order_sequence,
+                    tray.name
+                        .clone()
+                        .unwrap_or_else(|| format!("Tray {}", tray.order_sequence)),
+                ),
+            )
+        })
+        .collect();
+    tray_wells.sort_by(|a, b| {
+        (tray_order[&a.tray_id].0, &a.row_letter, a.column_number).cmp(&(
+            tray_order[&b.tray_id].0,
+            &b.row_letter,
+            b.column_number,
+        ))
+    });
+
+    let columns = tray_wells
+        .into_iter()
+        .map(|well| WellColumn {
+            well_id: well.id,
+            tray_name: tray_order[&well.tray_id].1.clone(),
+            coordinate: format!("{}{}", well.row_letter, well.column_number),
+        })
+        .collect();
+
+    Ok((config_trays, columns))
+}
+
+/// Probes of the tray configuration, plus any that only appear in readings,
+/// in data column order
+async fn load_probes(
+    db: &DatabaseConnection,
+    config_trays: &[trays::Model],
+    mut probe_ids: HashSet<Uuid>,
+) -> Result<Vec<probes::Model>, DbErr> {
+    if !config_trays.is_empty() {
+        probe_ids.extend(
+            probes::Entity::find()
+                .filter(probes::Column::TrayId.is_in(config_trays.iter().map(|tray| tray.id)))
+                .all(db)
+                .await?
+                .into_iter()
+                .map(|probe| probe.id),
+        );
+    }
+    if probe_ids.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut experiment_probes = probes::Entity::find()
+        .filter(probes::Column::Id.is_in(probe_ids))
+        .all(db)
+        .await?;
+    experiment_probes.sort_by_key(|p| (p.data_column_index, p.name.clone()));
+    Ok(experiment_probes)
+}
+
+fn xlsx_error(e: &XlsxError) -> DbErr {
+    DbErr::Custom(format!("Failed to write workbook: {e}"))
+}
+
+/// Column positions of the regenerated sheet
+struct SheetLayout {
+    probe_start: u16,
+    image_column: u16,
+    liquid_column: u16,
+    frozen_column: u16,
+    well_start: u16,
+}
+
+/// Write the tray, coordinate, channel and header rows above the data
+fn write_header_rows(
+    worksheet: &mut rust_xlsxwriter::Worksheet,
+    experiment_name: &str,
+    probes: &[probes::Model],
+    well_columns: &[WellColumn],
+) -> Result<SheetLayout, XlsxError> {
+    worksheet
+        .write_string(TRAY_ROW, 0, "Experiment:")
+        .and_then(|ws| ws.write_string(COORDINATE_ROW, 0, experiment_name))
+        .and_then(|ws| ws.write_string(HEADER_ROW, 0, "Date"))
+        .and_then(|ws| ws.write_string(HEADER_ROW, 1, "Time"))?;
+
+    let mut column: u16 = 2;
+    let probe_start = column;
+    for probe in probes {
+        worksheet
+            .write_string(CHANNEL_ROW, column, probe.name.as_str())
+            .and_then(|ws| {
+                ws.write_string(
+                    HEADER_ROW,
+                    column,
+                    format!("Temperature {} (°C)", probe.data_column_index),
+                )
+            })?;
+        column += 1;
+    }
+
+    let image_column = column;
+    let liquid_column = image_column + 1;
+    let frozen_column = image_column + 2;
+    let well_start = image_column + 3;
+    worksheet
+        .write_string(HEADER_ROW, image_column, "(.jpg)")
+        .and_then(|ws| ws.write_string(TRAY_ROW, liquid_column, "processing"))
+        .and_then(|ws| ws.write_string(COORDINATE_ROW, liquid_column, "liquid"))
+        .and_then(|ws| ws.write_string(HEADER_ROW, liquid_column, "()"))
+        .and_then(|ws| ws.write_string(TRAY_ROW, frozen_column, "processing"))
+        .and_then(|ws| ws.write_string(COORDINATE_ROW, frozen_column, "frozen"))
+        .and_then(|ws| ws.write_string(HEADER_ROW, frozen_column, "()"))?;
+
+    for (offset, well) in (well_start..).zip(well_columns) {
+        worksheet
+            .write_string(TRAY_ROW, offset, well.tray_name.as_str())
+            .and_then(|ws| ws.write_string(COORDINATE_ROW, offset, well.coordinate.as_str()))
+            .and_then(|ws| ws.write_string(HEADER_ROW, offset, "()"))?;
+    }
+
+    Ok(SheetLayout {
+        probe_start,
+        image_column,
+        liquid_column,
+        frozen_column,
+        well_start,
+    })
+}
+
+/// Build the workbook for an experiment and return the `.xlsx` bytes
+pub async fn build_experiment_workbook(
+    db: &DatabaseConnection,
+    experiment_id: Uuid,
+) -> Result<Vec<u8>, DbErr> {
+    let experiment = experiments::Entity::find_by_id(experiment_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Experiment not found".to_string()))?;
+
+    let readings = temperature_readings::Entity::find()
+        .filter(temperature_readings::Column::ExperimentId.eq(experiment_id))
+        .order_by_asc(temperature_readings::Column::Timestamp)
+        .all(db)
+        .await?;
+    let probe_readings = probe_temperature_readings::Entity::find()
+        .inner_join(temperature_readings::Entity)
+        .filter(temperature_readings::Column::ExperimentId.eq(experiment_id))
+        .all(db)
+        .await?;
+    let transitions = well_phase_transitions::Entity::find()
+        .filter(well_phase_transitions::Column::ExperimentId.eq(experiment_id))
+        .order_by_asc(well_phase_transitions::Column::Timestamp)
+        .all(db)
+        .await?;
+
+    let (config_trays, well_columns) =
+        load_well_columns(db, experiment.tray_configuration_id).await?;
+
+    let experiment_probes = load_probes(
+        db,
+        &config_trays,
+        probe_readings.iter().map(|p| p.probe_id).collect(),
+    )
+    .await?;
+
+    let temperatures: HashMap<(Uuid, Uuid), f64> = probe_readings
+        .into_iter()
+        .filter_map(|reading| {
+            Some((
+                (reading.temperature_reading_id, reading.probe_id),
+                reading.temperature.to_f64()?,
+            ))
+        })
+        .collect();
+    let mut transitions_by_reading: HashMap<Uuid, Vec<(Uuid, i32)>> = HashMap::new();
+    for transition in transitions {
+        transitions_by_reading
+            .entry(transition.temperature_reading_id)
+            .or_default()
+            .push((transition.well_id, transition.new_state));
+    }
+
+    let mut workbook = Workbook::new();
+    let worksheet = workbook.add_worksheet();
+    let datetime_format = Format::new().set_num_format("yyyy-mm-dd hh:mm:ss");
+
+    let layout = write_header_rows(
+        worksheet,
+        &experiment.name,
+        &experiment_probes,
+        &well_columns,
+    )
+    .map_err(|e| xlsx_error(&e))?;
+
+    // Wells start liquid; each reading applies the transitions recorded for it
+    let mut states: HashMap<Uuid, i32> = HashMap::new();
+    for (row, reading) in (DATA_START_ROW..).zip(&readings) {
+        if let Some(changes) = transitions_by_reading.get(&reading.id) {
+            for (well_id, new_state) in changes {
+                states.insert(*well_id, *new_state);
+            }
+        }
+
+        let timestamp = reading.timestamp.naive_utc();
+        worksheet
+            .write_datetime_with_format(row, 0, timestamp, &datetime_format)
+            .and_then(|ws| ws.write_datetime_with_format(row, 1, timestamp, &datetime_format))
+            .map_err(|e| xlsx_error(&e))?;
+
+        for (probe_column, probe) in (layout.probe_start..).zip(&experiment_probes) {
+            if let Some(temperature) = temperatures.get(&(reading.id, probe.id)) {
+                worksheet
+                    .write_number(row, probe_column, *temperature)
+                    .map_err(|e| xlsx_error(&e))?;
+            }
+        }
+        if let Some(image_filename) = &reading.image_filename {
+            worksheet
+                .write_string(row, layout.image_column, image_filename.as_str())
+                .map_err(|e| xlsx_error(&e))?;
+        }
+
+        let mut frozen = 0;
+        for (well_column, well) in (layout.well_start..).zip(&well_columns) {
+            let state = states.get(&well.well_id).copied().unwrap_or(0);
+            frozen += state;
+            worksheet
+                .write_number(row, well_column, state)
+                .map_err(|e| xlsx_error(&e))?;
+        }
+        let total = i32::try_from(well_columns.len()).unwrap_or(i32::MAX);
+        worksheet
+            .write_number(row, layout.liquid_column, total - frozen)
+            .and_then(|ws| ws.write_number(row, layout.frozen_column, frozen))
+            .map_err(|e| xlsx_error(&e))?;
+    }
+
+    workbook.save_to_buffer().map_err(|e| xlsx_error(&e))
+}
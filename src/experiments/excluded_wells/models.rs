@@ -0,0 +1,56 @@
+use chrono::{DateTime, Utc};
+use crudcrate::EntityToModels;
+use sea_orm::entity::prelude::*;
+use uuid::Uuid;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, EntityToModels)]
+#[sea_orm(table_name = "experiment_excluded_wells")]
+#[crudcrate(api_struct = "ExperimentExcludedWell")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    #[crudcrate(primary_key, update_model = false, create_model = false, on_create = Uuid::new_v4())]
+    pub id: Uuid,
+    #[crudcrate(sortable, filterable)]
+    pub experiment_id: Uuid,
+    #[crudcrate(sortable, filterable)]
+    pub well_id: Uuid,
+    #[sea_orm(column_type = "Text", nullable)]
+    #[crudcrate(filterable)]
+    pub reason: Option<String>,
+    #[crudcrate(update_model = false, create_model = false, on_create = chrono::Utc::now(), sortable)]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "crate::experiments::models::Entity",
+        from = "Column::ExperimentId",
+        to = "crate::experiments::models::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Experiments,
+    #[sea_orm(
+        belongs_to = "crate::tray_configurations::wells::models::Entity",
+        from = "Column::WellId",
+        to = "crate::tray_configurations::wells::models::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Wells,
+}
+
+impl Related<crate::experiments::models::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Experiments.def()
+    }
+}
+
+impl Related<crate::tray_configurations::wells::models::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Wells.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
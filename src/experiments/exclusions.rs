@@ -0,0 +1,209 @@
+//! Wells left out of an experiment's statistics.
+//!
+//! A contaminated or empty well keeps its phase transitions and still appears
+//! in the results, marked as excluded, but it is not counted in the frozen
+//! fraction or in the nucleation statistics of samples and treatments.
+
+use super::excluded_wells::models as excluded_wells;
+use super::well_image::split_coordinate;
+use crate::tray_configurations::{trays::models as trays, wells::models as wells};
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    ActiveValue::Set, ConnectionTrait, DatabaseConnection, EntityTrait, QueryFilter,
+    TransactionTrait, entity::prelude::*,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(Clone, Debug, Deserialize, ToSchema)]
+pub struct WellExclusion {
+    /// Well as `P1:A1`, or `A1` when the configuration has a single tray
+    pub coordinate: String,
+    /// Why the well is left out, e.g. "contaminated" or "empty"
+    pub reason: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, ToSchema)]
+pub struct ExcludedWellsUpdate {
+    /// The complete list; wells not in it are included again
+    pub wells: Vec<WellExclusion>,
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct ExcludedWell {
+    pub well_id: Uuid,
+    pub tray_id: Uuid,
+    pub tray_name: Option<String>,
+    /// Well as `P1:A1`
+    pub coordinate: String,
+    pub reason: Option<String>,
+    pub excluded_at: DateTime<Utc>,
+}
+
+/// Reasons of the experiment's excluded wells, by well ID
+pub async fn excluded_well_reasons(
+    db: &impl ConnectionTrait,
+    experiment_id: Uuid,
+) -> Result<HashMap<Uuid, Option<String>>, DbErr> {
+    Ok(excluded_wells::Entity::find()
+        .filter(excluded_wells::Column::ExperimentId.eq(experiment_id))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|exclusion| (exclusion.well_id, exclusion.reason))
+        .collect())
+}
+
+/// The experiment's excluded wells in tray and well order
+pub async fn list_excluded_wells(
+    db: &impl ConnectionTrait,
+    experiment_id: Uuid,
+) -> Result<Vec<ExcludedWell>, DbErr> {
+    super::models::Entity::find_by_id(experiment_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Experiment not found".to_string()))?;
+
+    let exclusions = excluded_wells::Entity::find()
+        .filter(excluded_wells::Column::ExperimentId.eq(experiment_id))
+        .find_also_related(wells::Entity)
+        .all(db)
+        .await?;
+    let tray_ids: Vec<Uuid> = exclusions
+        .iter()
+        .filter_map(|(_, well)| well.as_ref().map(|well| well.tray_id))
+        .collect();
+    let tray_map: HashMap<Uuid, trays::Model> = trays::Entity::find()
+        .filter(trays::Column::Id.is_in(tray_ids))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|tray| (tray.id, tray))
+        .collect();
+
+    let mut excluded: Vec<(i32, &wells::Model, ExcludedWell)> = exclusions
+        .iter()
+        .filter_map(|(exclusion, well)| {
+            let well = well.as_ref()?;
+            let tray = tray_map.get(&well.tray_id);
+            let tray_name = tray.and_then(|tray| tray.name.clone());
+            let well_name = format!("{}{}", well.row_letter, well.column_number);
+            Some((
+                tray.map_or(0, |tray| tray.order_sequence),
+                well,
+                ExcludedWell {
+                    well_id: well.id,
+                    tray_id: well.tray_id,
+                    coordinate: tray_name
+                        .as_ref()
+                        .map_or_else(|| well_name.clone(), |name| format!("{name}:{well_name}")),
+                    tray_name,
+                    reason: exclusion.reason.clone(),
+                    excluded_at: exclusion.created_at,
+                },
+            ))
+        })
+        .collect();
+    excluded.sort_by(|(a_order, a, _), (b_order, b, _)| {
+        (a_order, &a.row_letter, a.column_number).cmp(&(b_order, &b.row_letter, b.column_number))
+    });
+    Ok(excluded.into_iter().map(|(_, _, well)| well).collect())
+}
+
+/// Replace the experiment's excluded wells. Wells that stay excluded keep
+/// their original exclusion time. Coordinates that do not match a well of
+/// the experiment's tray configuration are returned as `DbErr::Custom`.
+pub async fn set_excluded_wells(
+    db: &DatabaseConnection,
+    experiment_id: Uuid,
+    update: &ExcludedWellsUpdate,
+) -> Result<Vec<ExcludedWell>, DbErr> {
+    let experiment = super::models::Entity::find_by_id(experiment_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Experiment not found".to_string()))?;
+
+    let mut reasons: HashMap<Uuid, Option<String>> = HashMap::new();
+    if !update.wells.is_empty() {
+        let tray_configuration_id = experiment
+            .tray_configuration_id
+            .ok_or_else(|| DbErr::Custom("Experiment has no tray configuration".to_string()))?;
+        let configuration_trays = trays::Entity::find()
+            .filter(trays::Column::TrayConfigurationId.eq(tray_configuration_id))
+            .all(db)
+            .await?;
+        let configuration_wells = wells::Entity::find()
+            .filter(wells::Column::TrayId.is_in(configuration_trays.iter().map(|tray| tray.id)))
+            .all(db)
+            .await?;
+
+        for exclusion in &update.wells {
+            let coordinate = &exclusion.coordinate;
+            let (tray_name, row_letter, column_number) = split_coordinate(coordinate)?;
+            let tray = match tray_name {
+                Some(name) => configuration_trays
+                    .iter()
+                    .find(|tray| tray.name.as_deref() == Some(name)),
+                None if configuration_trays.len() == 1 => configuration_trays.first(),
+                None => {
+                    return Err(DbErr::Custom(format!(
+                        "The configuration has several trays; use a coordinate like P1:{coordinate}"
+                    )));
+                }
+            }
+            .ok_or_else(|| DbErr::Custom(format!("Tray not found in '{coordinate}'")))?;
+            let well = configuration_wells
+                .iter()
+                .find(|well| {
+                    well.tray_id == tray.id
+                        && well.row_letter == row_letter
+                        && well.column_number == column_number
+                })
+                .ok_or_else(|| DbErr::Custom(format!("Well {coordinate} is not on the tray")))?;
+            // A later entry for the same well wins
+            reasons.insert(
+                well.id,
+                exclusion
+                    .reason
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|reason| !reason.is_empty())
+                    .map(str::to_string),
+            );
+        }
+    }
+
+    let excluded_at: HashMap<Uuid, DateTime<Utc>> = excluded_wells::Entity::find()
+        .filter(excluded_wells::Column::ExperimentId.eq(experiment_id))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|exclusion| (exclusion.well_id, exclusion.created_at))
+        .collect();
+    let now = Utc::now();
+
+    let txn = db.begin().await?;
+    excluded_wells::Entity::delete_many()
+        .filter(excluded_wells::Column::ExperimentId.eq(experiment_id))
+        .exec(&txn)
+        .await?;
+    if !reasons.is_empty() {
+        excluded_wells::Entity::insert_many(reasons.into_iter().map(|(well_id, reason)| {
+            excluded_wells::ActiveModel {
+                id: Set(Uuid::new_v4()),
+                experiment_id: Set(experiment_id),
+                well_id: Set(well_id),
+                reason: Set(reason),
+                created_at: Set(excluded_at.get(&well_id).copied().unwrap_or(now)),
+            }
+        }))
+        .exec(&txn)
+        .await?;
+    }
+    super::summaries::discard_results_summary(&txn, experiment_id).await?;
+    txn.commit().await?;
+
+    list_excluded_wells(db, experiment_id).await
+}
@@ -0,0 +1,170 @@
+//! Stepping through an experiment's camera frames one at a time
+
+use super::timelapse::camera_images;
+use super::well_image::nearest_frame;
+use crate::assets::models as s3_assets;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use sea_orm::{DatabaseConnection, DbErr, EntityTrait};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum FrameDirection {
+    /// The last frame captured before the timestamp
+    Previous,
+    /// The first frame captured after the timestamp
+    Next,
+    /// The frame captured closest to the timestamp
+    Nearest,
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct CameraFrame {
+    pub asset_id: Uuid,
+    pub filename: String,
+    pub captured_at: DateTime<Utc>,
+    /// Temperature reading the image is linked to
+    pub temperature_reading_id: Option<Uuid>,
+    pub view_url: String,
+    pub thumbnail_url: String,
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct FrameNavigation {
+    /// Position of the frame in capture order, from 0
+    pub index: usize,
+    pub frame_count: usize,
+    pub frame: CameraFrame,
+    /// Neighbouring frames, to step without another lookup
+    pub previous: Option<CameraFrame>,
+    pub next: Option<CameraFrame>,
+}
+
+impl CameraFrame {
+    fn new(captured_at: NaiveDateTime, asset: &s3_assets::Model) -> Self {
+        Self {
+            asset_id: asset.id,
+            filename: asset.original_filename.clone(),
+            captured_at: captured_at.and_utc(),
+            temperature_reading_id: asset.temperature_reading_id,
+            view_url: format!("/api/assets/{}/view", asset.id),
+            thumbnail_url: format!("/api/assets/{}/thumbnail", asset.id),
+        }
+    }
+}
+
+/// Index of the frame in `direction` from `timestamp`. Without a timestamp,
+/// `previous` gives the last frame and the others the first.
+fn select_frame(
+    captured: &[NaiveDateTime],
+    timestamp: Option<NaiveDateTime>,
+    direction: FrameDirection,
+) -> Option<usize> {
+    let Some(timestamp) = timestamp else {
+        return match direction {
+            FrameDirection::Previous => captured.len().checked_sub(1),
+            FrameDirection::Next | FrameDirection::Nearest => (!captured.is_empty()).then_some(0),
+        };
+    };
+    match direction {
+        // Frames are in capture order
+        FrameDirection::Previous => captured
+            .partition_point(|time| *time < timestamp)
+            .checked_sub(1),
+        FrameDirection::Next => {
+            let index = captured.partition_point(|time| *time <= timestamp);
+            (index < captured.len()).then_some(index)
+        }
+        FrameDirection::Nearest => nearest_frame(captured, timestamp),
+    }
+}
+
+/// The camera frame in `direction` from `timestamp`, with its neighbours.
+/// A missing experiment or frame is returned as `DbErr::RecordNotFound`.
+pub async fn navigate_frames(
+    db: &DatabaseConnection,
+    experiment_id: Uuid,
+    timestamp: Option<DateTime<Utc>>,
+    direction: FrameDirection,
+) -> Result<FrameNavigation, DbErr> {
+    super::models::Entity::find_by_id(experiment_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Experiment not found".to_string()))?;
+
+    let frames = camera_images(db, experiment_id).await?;
+    let captured: Vec<NaiveDateTime> = frames.iter().map(|(captured, _)| *captured).collect();
+    let index = select_frame(
+        &captured,
+        timestamp.map(|timestamp| timestamp.naive_utc()),
+        direction,
+    )
+    .ok_or_else(|| {
+        DbErr::RecordNotFound(match (frames.is_empty(), timestamp) {
+            (false, Some(timestamp)) => {
+                let side = if direction == FrameDirection::Previous {
+                    "before"
+                } else {
+                    "after"
+                };
+                format!("No camera frame {side} {}", timestamp.to_rfc3339())
+            }
+            _ => "Experiment has no INP_* camera images".to_string(),
+        })
+    })?;
+
+    let frame = |index: usize| {
+        frames
+            .get(index)
+            .map(|(captured, asset)| CameraFrame::new(*captured, asset))
+    };
+    Ok(FrameNavigation {
+        index,
+        frame_count: frames.len(),
+        frame: CameraFrame::new(frames[index].0, &frames[index].1),
+        previous: index.checked_sub(1).and_then(frame),
+        next: frame(index + 1),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use FrameDirection::{Nearest, Next, Previous};
+
+    fn time(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    #[test]
+    fn test_select_frame() {
+        let captured = [
+            time("2025-01-01 10:00:00"),
+            time("2025-01-01 10:00:10"),
+            time("2025-01-01 10:00:20"),
+        ];
+        let select = |s, direction| select_frame(&captured, Some(time(s)), direction);
+
+        assert_eq!(select("2025-01-01 10:00:14", Previous), Some(1));
+        assert_eq!(select("2025-01-01 10:00:14", Next), Some(2));
+        assert_eq!(select("2025-01-01 10:00:14", Nearest), Some(1));
+        // A frame at the timestamp itself is neither before nor after it
+        assert_eq!(select("2025-01-01 10:00:10", Previous), Some(0));
+        assert_eq!(select("2025-01-01 10:00:10", Next), Some(2));
+        assert_eq!(select("2025-01-01 10:00:10", Nearest), Some(1));
+        // Nothing beyond the ends
+        assert_eq!(select("2025-01-01 10:00:00", Previous), None);
+        assert_eq!(select("2025-01-01 10:00:20", Next), None);
+
+        assert_eq!(select_frame(&captured, None, Previous), Some(2));
+        assert_eq!(select_frame(&captured, None, Next), Some(0));
+        assert_eq!(select_frame(&captured, None, Nearest), Some(0));
+        assert_eq!(select_frame(&[], None, Previous), None);
+        assert_eq!(
+            select_frame(&[], Some(time("2025-01-01 10:00:00")), Nearest),
+            None
+        );
+    }
+}
@@ -0,0 +1,279 @@
+//! Differences between two camera frames of an experiment
+//!
+//! Each pixel of the difference image is the absolute change of its grey
+//! level between the frames, and wells whose mean brightness changed by at
+//! least the threshold are circled. The same per-well change scores are what
+//! freeze detection compares with its threshold, so the diff shows which
+//! wells a given threshold would pick up between the two frames.
+
+use super::image_freeze::{DEFAULT_THRESHOLD, SamplePoint, mean_brightness, sample_points};
+use super::overlay::draw_circle;
+use super::timelapse::camera_images;
+use super::well_image::nearest_frame;
+use crate::common::state::AppState;
+use crate::external::s3::get_object_from_s3;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use image::{GrayImage, ImageFormat, Rgb, RgbImage};
+use sea_orm::{EntityTrait, entity::prelude::*};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+const CHANGED_WELL: Rgb<u8> = Rgb([255, 40, 40]);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageDiffFormat {
+    /// Difference image with the changed wells circled
+    #[default]
+    Png,
+    /// Per-well change scores
+    Json,
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct WellChange {
+    pub well_id: Uuid,
+    pub tray_name: Option<String>,
+    pub row_letter: String,
+    pub column_number: i32,
+    /// Mean grey level of the well centre in each frame, `null` if the well
+    /// lies outside the image
+    pub before: Option<f64>,
+    pub after: Option<f64>,
+    /// `after - before`, negative when the well darkened
+    pub change: Option<f64>,
+    /// The change reaches the threshold
+    pub changed: bool,
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct ImageDiff {
+    pub from_filename: String,
+    pub from_captured_at: DateTime<Utc>,
+    pub to_filename: String,
+    pub to_captured_at: DateTime<Utc>,
+    pub threshold: f64,
+    /// Mean absolute grey level change over the whole image
+    pub mean_pixel_change: f64,
+    pub wells_changed: usize,
+    pub wells: Vec<WellChange>,
+    /// Rendered difference image, for `format=png`
+    #[serde(skip)]
+    pub png: Option<Vec<u8>>,
+}
+
+fn round2(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+/// Absolute grey level change of every pixel, and its mean
+fn difference(before: &GrayImage, after: &GrayImage) -> (GrayImage, f64) {
+    let diff = GrayImage::from_fn(before.width(), before.height(), |x, y| {
+        image::Luma([before.get_pixel(x, y)[0].abs_diff(after.get_pixel(x, y)[0])])
+    });
+    let pixels = u64::from(diff.width()) * u64::from(diff.height());
+    let total: u64 = diff.pixels().map(|pixel| u64::from(pixel[0])).sum();
+    #[allow(clippy::cast_precision_loss)] // Far below 2^52 pixels
+    let mean = if pixels == 0 {
+        0.0
+    } else {
+        total as f64 / pixels as f64
+    };
+    (diff, mean)
+}
+
+fn well_changes(
+    before: &GrayImage,
+    after: &GrayImage,
+    points: &[SamplePoint],
+    threshold: f64,
+) -> Vec<WellChange> {
+    points
+        .iter()
+        .map(|point| {
+            let sample = |image| mean_brightness(image, point.x, point.y, point.radius);
+            let (before, after) = (sample(before), sample(after));
+            let change = before.zip(after).map(|(before, after)| after - before);
+            WellChange {
+                well_id: point.well_id,
+                tray_name: point.tray_name.clone(),
+                row_letter: point.row_letter.clone(),
+                column_number: point.column_number,
+                before: before.map(round2),
+                after: after.map(round2),
+                change: change.map(round2),
+                changed: change.is_some_and(|change| change.abs() >= threshold),
+            }
+        })
+        .collect()
+}
+
+/// The difference image with a circle around each changed well
+fn render(
+    diff: &GrayImage,
+    points: &[SamplePoint],
+    wells: &[WellChange],
+) -> Result<Vec<u8>, String> {
+    let mut frame = RgbImage::from_fn(diff.width(), diff.height(), |x, y| {
+        let level = diff.get_pixel(x, y)[0];
+        Rgb([level, level, level])
+    });
+    for (point, well) in points.iter().zip(wells) {
+        if well.changed {
+            // Only the inner half of a well is sampled
+            draw_circle(
+                &mut frame,
+                (point.x, point.y),
+                point.radius * 2.0,
+                CHANGED_WELL,
+            );
+        }
+    }
+    let mut png = Vec::new();
+    frame
+        .write_to(&mut std::io::Cursor::new(&mut png), ImageFormat::Png)
+        .map_err(|e| format!("Failed to encode difference image: {e}"))?;
+    Ok(png)
+}
+
+/// Compare the camera frames captured closest to `from` and `to`.
+///
+/// Wells are scored when the experiment's trays can be placed on the images.
+/// Missing records are returned as `DbErr::RecordNotFound`, other problems as
+/// `DbErr::Custom`.
+pub async fn diff_frames(
+    state: &AppState,
+    experiment_id: Uuid,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    threshold: Option<f64>,
+    format: ImageDiffFormat,
+) -> Result<ImageDiff, DbErr> {
+    let threshold = threshold.unwrap_or(DEFAULT_THRESHOLD);
+    if !(1.0..=255.0).contains(&threshold) {
+        return Err(DbErr::Custom(
+            "threshold must be between 1 and 255".to_string(),
+        ));
+    }
+
+    let experiment = super::models::Entity::find_by_id(experiment_id)
+        .one(&state.db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Experiment not found".to_string()))?;
+    let points = match experiment.tray_configuration_id {
+        Some(tray_configuration_id) => sample_points(&state.db, tray_configuration_id).await?,
+        None => Vec::new(),
+    };
+
+    let frames = camera_images(&state.db, experiment_id).await?;
+    let captured: Vec<NaiveDateTime> = frames.iter().map(|(captured, _)| *captured).collect();
+    let (Some(from_index), Some(to_index)) = (
+        nearest_frame(&captured, from.naive_utc()),
+        nearest_frame(&captured, to.naive_utc()),
+    ) else {
+        return Err(DbErr::RecordNotFound(
+            "Experiment has no INP_* camera images".to_string(),
+        ));
+    };
+    if from_index == to_index {
+        return Err(DbErr::Custom(
+            "from and to are closest to the same camera frame".to_string(),
+        ));
+    }
+    let (from_captured, from_asset) = &frames[from_index];
+    let (to_captured, to_asset) = &frames[to_index];
+
+    let before_bytes = get_object_from_s3(from_asset.storage_key(), &state.config)
+        .await
+        .map_err(DbErr::Custom)?;
+    let after_bytes = get_object_from_s3(to_asset.storage_key(), &state.config)
+        .await
+        .map_err(DbErr::Custom)?;
+    let (mean_pixel_change, wells, png) = tokio::task::spawn_blocking(move || {
+        let decode = |bytes: &[u8]| {
+            image::load_from_memory(bytes)
+                .map(image::DynamicImage::into_luma8)
+                .map_err(|e| format!("Failed to decode image: {e}"))
+        };
+        let (before, after) = (decode(&before_bytes)?, decode(&after_bytes)?);
+        if before.dimensions() != after.dimensions() {
+            return Err("The two camera frames differ in size".to_string());
+        }
+        let (diff, mean) = difference(&before, &after);
+        let wells = well_changes(&before, &after, &points, threshold);
+        let png = match format {
+            ImageDiffFormat::Png => Some(render(&diff, &points, &wells)?),
+            ImageDiffFormat::Json => None,
+        };
+        Ok((round2(mean), wells, png))
+    })
+    .await
+    .map_err(|e| DbErr::Custom(format!("Diff task failed: {e}")))?
+    .map_err(DbErr::Custom)?;
+
+    Ok(ImageDiff {
+        from_filename: from_asset.original_filename.clone(),
+        from_captured_at: from_captured.and_utc(),
+        to_filename: to_asset.original_filename.clone(),
+        to_captured_at: to_captured.and_utc(),
+        threshold,
+        mean_pixel_change,
+        wells_changed: wells.iter().filter(|well| well.changed).count(),
+        wells,
+        png,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Luma;
+
+    fn point(x: f64, y: f64) -> SamplePoint {
+        SamplePoint {
+            well_id: Uuid::new_v4(),
+            tray_name: Some("P1".to_string()),
+            row_letter: "A".to_string(),
+            column_number: 1,
+            x,
+            y,
+            radius: 3.0,
+        }
+    }
+
+    #[test]
+    fn test_difference_is_absolute() {
+        let before = GrayImage::from_pixel(4, 2, Luma([100]));
+        let mut after = before.clone();
+        after.put_pixel(0, 0, Luma([180]));
+        after.put_pixel(1, 0, Luma([20]));
+        let (diff, mean) = difference(&before, &after);
+        assert_eq!(diff.get_pixel(0, 0)[0], 80);
+        assert_eq!(diff.get_pixel(1, 0)[0], 80);
+        assert_eq!(diff.get_pixel(3, 1)[0], 0);
+        assert!((mean - 20.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_well_changes_use_the_threshold() {
+        let before = GrayImage::from_pixel(40, 20, Luma([50]));
+        let mut after = before.clone();
+        for y in 5..=15 {
+            for x in 5..=15 {
+                after.put_pixel(x, y, Luma([200]));
+            }
+            for x in 25..=35 {
+                after.put_pixel(x, y, Luma([45]));
+            }
+        }
+        let points = [point(10.0, 10.0), point(30.0, 10.0), point(-20.0, 10.0)];
+        let wells = well_changes(&before, &after, &points, 12.0);
+        assert_eq!(wells[0].change, Some(150.0));
+        assert!(wells[0].changed);
+        assert_eq!(wells[1].change, Some(-5.0));
+        assert!(!wells[1].changed);
+        assert!(wells[2].before.is_none() && !wells[2].changed);
+        assert!(well_changes(&before, &after, &points, 4.0)[1].changed);
+    }
+}
@@ -0,0 +1,461 @@
+//! Freeze detection from camera images
+//!
+//! The mean brightness of every well is sampled in each `INP_*` image at the
+//! centroid given by the tray's well grid. Freezing turns a well opaque, so a
+//! well is taken to freeze in the frame after its largest brightness step
+//! between consecutive frames, provided the step reaches the threshold.
+//!
+//! The detections are either compared with the transitions read from the
+//! spreadsheet (`cross_check`, nothing is written) or stored in their place
+//! (`replace`).
+
+use super::phase_transitions::models as phase_transitions;
+use super::temperatures::models as temperature_readings;
+use super::timelapse::camera_images;
+use crate::common::state::AppState;
+use crate::external::s3::get_object_from_s3;
+use crate::tray_configurations::well_grid::tray_configuration_well_grid;
+use chrono::{DateTime, Utc};
+use image::GrayImage;
+use sea_orm::{
+    ActiveValue::Set, EntityTrait, QueryFilter, QueryOrder, TransactionTrait, entity::prelude::*,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+pub(super) const DEFAULT_THRESHOLD: f64 = 12.0;
+const DEFAULT_TOLERANCE_SECONDS: i64 = 60;
+/// Only the centre of a well is sampled, away from its wall and meniscus
+const SAMPLE_RADIUS_FRACTION: f64 = 0.5;
+/// Sample radius for trays without `well_relative_diameter`
+const FALLBACK_SAMPLE_RADIUS_PX: f64 = 4.0;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FreezeDetectionMode {
+    /// Compare with the spreadsheet transitions without writing anything
+    #[default]
+    CrossCheck,
+    /// Replace the experiment's phase transitions with the detected ones
+    Replace,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, ToSchema)]
+pub struct FreezeDetectionRequest {
+    #[serde(default)]
+    pub mode: FreezeDetectionMode,
+    /// Smallest brightness step between consecutive frames that counts as
+    /// freezing, in grey levels from 1 to 255 (default 12)
+    pub threshold: Option<f64>,
+    /// Largest difference between the image and spreadsheet freezing times
+    /// for a well to agree, in seconds (default 60)
+    pub tolerance_seconds: Option<i64>,
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct WellFreezeComparison {
+    pub well_id: Uuid,
+    pub tray_name: Option<String>,
+    pub row_letter: String,
+    pub column_number: i32,
+    /// Brightness step at the detected freeze in grey levels, negative when
+    /// the well darkened
+    pub brightness_change: Option<f64>,
+    pub image_frozen_at: Option<DateTime<Utc>>,
+    pub image_filename: Option<String>,
+    pub spreadsheet_frozen_at: Option<DateTime<Utc>>,
+    /// `image_frozen_at - spreadsheet_frozen_at` when both are known
+    pub difference_seconds: Option<i64>,
+    /// Both sources froze within the tolerance, or neither froze
+    pub agrees: bool,
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct FreezeDetectionResult {
+    pub mode: FreezeDetectionMode,
+    pub frames_analysed: usize,
+    /// Images that could not be downloaded or decoded
+    pub frames_skipped: Vec<String>,
+    pub wells_analysed: usize,
+    pub wells_frozen: usize,
+    pub agreeing_wells: usize,
+    /// Readings added for frames without a spreadsheet row, in `replace` mode
+    pub temperature_readings_created: usize,
+    pub phase_transitions_created: usize,
+    pub wells: Vec<WellFreezeComparison>,
+}
+
+/// A well located on the images
+pub(super) struct SamplePoint {
+    pub well_id: Uuid,
+    pub tray_name: Option<String>,
+    pub row_letter: String,
+    pub column_number: i32,
+    pub x: f64,
+    pub y: f64,
+    pub radius: f64,
+}
+
+/// Mean grey level of the pixels within `radius` of (`x`, `y`), or `None` if
+/// the circle lies outside the image
+pub(super) fn mean_brightness(image: &GrayImage, x: f64, y: f64, radius: f64) -> Option<f64> {
+    let radius = radius.max(0.5);
+    let bound = |value: f64, size: u32| {
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)] // Clamped to the image
+        let clamped = value.clamp(0.0, f64::from(size)) as u32;
+        clamped
+    };
+    let (left, right) = (
+        bound((x - radius).floor(), image.width()),
+        bound((x + radius).ceil() + 1.0, image.width()),
+    );
+    let (top, bottom) = (
+        bound((y - radius).floor(), image.height()),
+        bound((y + radius).ceil() + 1.0, image.height()),
+    );
+
+    let (mut sum, mut count) = (0.0, 0u32);
+    for py in top..bottom {
+        for px in left..right {
+            let (dx, dy) = (f64::from(px) - x, f64::from(py) - y);
+            if dx * dx + dy * dy <= radius * radius {
+                sum += f64::from(image.get_pixel(px, py)[0]);
+                count += 1;
+            }
+        }
+    }
+    (count > 0).then(|| sum / f64::from(count))
+}
+
+/// Frame index at which a well freezes and the brightness step into it: the
+/// largest step between consecutive samples, if it reaches `threshold`
+fn detect_freeze(series: &[Option<f64>], threshold: f64) -> Option<(usize, f64)> {
+    let mut previous: Option<f64> = None;
+    let mut largest: Option<(usize, f64)> = None;
+    for (index, sample) in series.iter().enumerate() {
+        let Some(value) = *sample else { continue };
+        if let Some(before) = previous {
+            let step = value - before;
+            if largest.is_none_or(|(_, largest_step)| step.abs() > largest_step.abs()) {
+                largest = Some((index, step));
+            }
+        }
+        previous = Some(value);
+    }
+    largest.filter(|(_, step)| step.abs() >= threshold)
+}
+
+fn file_stem(filename: &str) -> &str {
+    std::path::Path::new(filename)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or(filename)
+}
+
+/// Sample every well in one image
+fn sample_frame(image_bytes: &[u8], points: &[SamplePoint]) -> Result<Vec<Option<f64>>, String> {
+    let image = image::load_from_memory(image_bytes)
+        .map_err(|e| format!("Failed to decode image: {e}"))?
+        .into_luma8();
+    Ok(points
+        .iter()
+        .map(|point| mean_brightness(&image, point.x, point.y, point.radius))
+        .collect())
+}
+
+pub(super) async fn sample_points(
+    db: &DatabaseConnection,
+    tray_configuration_id: Uuid,
+) -> Result<Vec<SamplePoint>, DbErr> {
+    let grid = tray_configuration_well_grid(db, tray_configuration_id).await?;
+    let mut points = Vec::new();
+    for tray in grid.trays {
+        let radius = tray
+            .well_radius
+            .map_or(FALLBACK_SAMPLE_RADIUS_PX, |radius| {
+                radius * SAMPLE_RADIUS_FRACTION
+            });
+        for well in tray.wells {
+            let Some(well_id) = well.well_id else {
+                continue;
+            };
+            points.push(SamplePoint {
+                well_id,
+                tray_name: tray.tray_name.clone(),
+                row_letter: well.row_letter,
+                column_number: well.column_number,
+                x: well.x,
+                y: well.y,
+                radius,
+            });
+        }
+    }
+    Ok(points)
+}
+
+/// Detect freezing from the experiment's camera images.
+///
+/// Validation problems are returned as `DbErr::Custom`, a missing experiment
+/// as `DbErr::RecordNotFound`.
+#[allow(clippy::too_many_lines)]
+pub async fn detect_freezing(
+    state: &AppState,
+    experiment_id: Uuid,
+    request: &FreezeDetectionRequest,
+) -> Result<FreezeDetectionResult, DbErr> {
+    let threshold = request.threshold.unwrap_or(DEFAULT_THRESHOLD);
+    let tolerance = request
+        .tolerance_seconds
+        .unwrap_or(DEFAULT_TOLERANCE_SECONDS);
+    if !(1.0..=255.0).contains(&threshold) {
+        return Err(DbErr::Custom(
+            "threshold must be between 1 and 255".to_string(),
+        ));
+    }
+    if tolerance < 0 {
+        return Err(DbErr::Custom(
+            "tolerance_seconds must not be negative".to_string(),
+        ));
+    }
+
+    let experiment = super::models::Entity::find_by_id(experiment_id)
+        .one(&state.db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Experiment not found".to_string()))?;
+    let tray_configuration_id = experiment
+        .tray_configuration_id
+        .ok_or_else(|| DbErr::Custom("Experiment has no tray configuration".to_string()))?;
+
+    let points = sample_points(&state.db, tray_configuration_id).await?;
+    if points.is_empty() {
+        return Err(DbErr::Custom(
+            "No wells can be located on the images; the trays need corner coordinates and wells"
+                .to_string(),
+        ));
+    }
+    let images = camera_images(&state.db, experiment_id).await?;
+    if images.is_empty() {
+        return Err(DbErr::Custom(
+            "Experiment has no INP_* camera images".to_string(),
+        ));
+    }
+
+    // Brightness of every well in every readable frame, in capture order
+    let points = std::sync::Arc::new(points);
+    let mut frames: Vec<(DateTime<Utc>, String)> = Vec::new();
+    let mut brightness: Vec<Vec<Option<f64>>> = Vec::new();
+    let mut frames_skipped = Vec::new();
+    for (captured, asset) in images {
+        let sampled = match get_object_from_s3(asset.storage_key(), &state.config).await {
+            Ok(bytes) => {
+                let points = points.clone();
+                tokio::task::spawn_blocking(move || sample_frame(&bytes, &points))
+                    .await
+                    .map_err(|e| DbErr::Custom(format!("Sampling task failed: {e}")))?
+            }
+            Err(e) => Err(e),
+        };
+        match sampled {
+            Ok(frame_samples) => {
+                frames.push((captured.and_utc(), asset.original_filename));
+                brightness.push(frame_samples);
+            }
+            Err(e) => {
+                tracing::warn!(
+                    "Skipping {} in freeze detection: {e}",
+                    asset.original_filename
+                );
+                frames_skipped.push(asset.original_filename);
+            }
+        }
+    }
+
+    // Frames are tied to the spreadsheet row of the same image, whose
+    // timestamp the spreadsheet transitions use
+    let readings: HashMap<String, temperature_readings::Model> =
+        temperature_readings::Entity::find()
+            .filter(temperature_readings::Column::ExperimentId.eq(experiment_id))
+            .all(&state.db)
+            .await?
+            .into_iter()
+            .filter_map(|reading| {
+                let stem = file_stem(reading.image_filename.as_deref()?).to_string();
+                Some((stem, reading))
+            })
+            .collect();
+    let frame_readings: Vec<Option<&temperature_readings::Model>> = frames
+        .iter()
+        .map(|(_, filename)| readings.get(file_stem(filename)))
+        .collect();
+    let frame_time =
+        |index: usize| frame_readings[index].map_or(frames[index].0, |reading| reading.timestamp);
+
+    let mut spreadsheet_frozen_at: HashMap<Uuid, DateTime<Utc>> = HashMap::new();
+    for transition in phase_transitions::Entity::find()
+        .filter(phase_transitions::Column::ExperimentId.eq(experiment_id))
+        .filter(phase_transitions::Column::PreviousState.eq(0))
+        .filter(phase_transitions::Column::NewState.eq(1))
+        .order_by_asc(phase_transitions::Column::Timestamp)
+        .all(&state.db)
+        .await?
+    {
+        spreadsheet_frozen_at
+            .entry(transition.well_id)
+            .or_insert(transition.timestamp);
+    }
+
+    let mut detections: Vec<(usize, usize)> = Vec::new();
+    let mut wells = Vec::with_capacity(points.len());
+    for (point_index, point) in points.iter().enumerate() {
+        let series: Vec<Option<f64>> = brightness.iter().map(|frame| frame[point_index]).collect();
+        let detection = detect_freeze(&series, threshold);
+        if let Some((frame_index, _)) = detection {
+            detections.push((point_index, frame_index));
+        }
+
+        let image_frozen_at = detection.map(|(frame_index, _)| frame_time(frame_index));
+        let spreadsheet = spreadsheet_frozen_at.get(&point.well_id).copied();
+        let difference_seconds = image_frozen_at
+            .zip(spreadsheet)
+            .map(|(image, spreadsheet)| (image - spreadsheet).num_seconds());
+        wells.push(WellFreezeComparison {
+            well_id: point.well_id,
+            tray_name: point.tray_name.clone(),
+            row_letter: point.row_letter.clone(),
+            column_number: point.column_number,
+            brightness_change: detection.map(|(_, step)| (step * 100.0).round() / 100.0),
+            image_frozen_at,
+            image_filename: detection.map(|(frame_index, _)| frames[frame_index].1.clone()),
+            spreadsheet_frozen_at: spreadsheet,
+            difference_seconds,
+            agrees: match (image_frozen_at, spreadsheet) {
+                (None, None) => true,
+                (Some(_), Some(_)) => difference_seconds.is_some_and(|d| d.abs() <= tolerance),
+                _ => false,
+            },
+        });
+    }
+
+    let mut temperature_readings_created = 0;
+    let mut phase_transitions_created = 0;
+    if request.mode == FreezeDetectionMode::Replace {
+        let txn = state.db.begin().await?;
+        phase_transitions::Entity::delete_many()
+            .filter(phase_transitions::Column::ExperimentId.eq(experiment_id))
+            .exec(&txn)
+            .await?;
+        super::summaries::discard_results_summary(&txn, experiment_id).await?;
+
+        // Frames without a spreadsheet row get a reading of their own, without
+        // probe temperatures, so the transition has something to point at
+        let mut created_readings: HashMap<usize, Uuid> = HashMap::new();
+        let mut transitions = Vec::with_capacity(detections.len());
+        for &(point_index, frame_index) in &detections {
+            let reading_id = if let Some(reading) = frame_readings[frame_index] {
+                reading.id
+            } else if let Some(&id) = created_readings.get(&frame_index) {
+                id
+            } else {
+                let id = Uuid::new_v4();
+                temperature_readings::Entity::insert(temperature_readings::ActiveModel {
+                    id: Set(id),
+                    experiment_id: Set(experiment_id),
+                    timestamp: Set(frames[frame_index].0),
+                    image_filename: Set(Some(file_stem(&frames[frame_index].1).to_string())),
+                    created_at: Set(Utc::now()),
+                })
+                .exec(&txn)
+                .await?;
+                created_readings.insert(frame_index, id);
+                id
+            };
+            transitions.push(phase_transitions::ActiveModel {
+                id: Set(Uuid::new_v4()),
+                well_id: Set(points[point_index].well_id),
+                experiment_id: Set(experiment_id),
+                temperature_reading_id: Set(reading_id),
+                timestamp: Set(frame_time(frame_index)),
+                previous_state: Set(0),
+                new_state: Set(1),
+                created_at: Set(Utc::now()),
+            });
+        }
+        phase_transitions_created = transitions.len();
+        if !transitions.is_empty() {
+            phase_transitions::Entity::insert_many(transitions)
+                .exec(&txn)
+                .await?;
+        }
+        txn.commit().await?;
+        temperature_readings_created = created_readings.len();
+    }
+
+    Ok(FreezeDetectionResult {
+        mode: request.mode,
+        frames_analysed: frames.len(),
+        frames_skipped,
+        wells_analysed: wells.len(),
+        wells_frozen: detections.len(),
+        agreeing_wells: wells.iter().filter(|well| well.agrees).count(),
+        temperature_readings_created,
+        phase_transitions_created,
+        wells,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Luma;
+
+    #[test]
+    fn test_mean_brightness_samples_a_disc() {
+        let mut image = GrayImage::from_pixel(40, 40, Luma([10]));
+        for y in 15..=25 {
+            for x in 15..=25 {
+                image.put_pixel(x, y, Luma([200]));
+            }
+        }
+        assert_eq!(mean_brightness(&image, 20.0, 20.0, 4.0), Some(200.0));
+        assert_eq!(mean_brightness(&image, 5.0, 5.0, 3.0), Some(10.0));
+        // Clipped at the border, and nothing left outside the image
+        assert_eq!(mean_brightness(&image, 0.0, 0.0, 2.0), Some(10.0));
+        assert_eq!(mean_brightness(&image, -50.0, 20.0, 3.0), None);
+    }
+
+    #[test]
+    fn test_detect_freeze_picks_the_largest_step() {
+        let series = [
+            Some(50.0),
+            Some(52.0),
+            None,
+            Some(51.0),
+            Some(120.0),
+            Some(118.0),
+        ];
+        assert_eq!(detect_freeze(&series, 12.0), Some((4, 69.0)));
+
+        // Darkening counts as well, noise below the threshold does not
+        let darkening = [Some(180.0), Some(178.0), Some(90.0)];
+        assert_eq!(detect_freeze(&darkening, 12.0), Some((2, -88.0)));
+        assert_eq!(
+            detect_freeze(&[Some(50.0), Some(55.0), Some(48.0)], 12.0),
+            None
+        );
+        assert_eq!(detect_freeze(&[Some(50.0)], 12.0), None);
+    }
+
+    #[test]
+    fn test_file_stem() {
+        assert_eq!(
+            file_stem("INP_1_2025-03-20_15-14-17.jpg"),
+            "INP_1_2025-03-20_15-14-17"
+        );
+        assert_eq!(
+            file_stem("INP_1_2025-03-20_15-14-17"),
+            "INP_1_2025-03-20_15-14-17"
+        );
+    }
+}
@@ -0,0 +1,113 @@
+//! Temperature at a well's position, interpolated from the probes of its tray.
+//!
+//! Probe positions are millimetres from the upper-left corner of the tray, x
+//! along the columns and y along the rows. Wells sit on the 9 mm pitch of
+//! standard microplates with the centre of A1 at the standard offset from
+//! the corner. Probes of other trays are ignored, as their positions are in
+//! another tray's frame.
+
+use rust_decimal::Decimal;
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
+
+pub(crate) const WELL_PITCH_MM: f64 = 9.0;
+/// Centre of well A1 from the upper-left corner of an SBS microplate
+const A1_OFFSET_X_MM: f64 = 14.38;
+const A1_OFFSET_Y_MM: f64 = 11.24;
+/// Weights fall with the square of the distance
+const IDW_POWER: i32 = 2;
+/// A probe this close to the well gives its reading directly
+const COINCIDENT_MM: f64 = 0.01;
+
+/// Centre of a well in millimetres from the tray's upper-left corner
+pub fn well_position(row_letter: &str, column_number: i32) -> (f64, f64) {
+    let row = row_letter
+        .chars()
+        .next()
+        .map_or(0, |c| i32::from(c as u8).saturating_sub(i32::from(b'A')));
+    (
+        A1_OFFSET_X_MM + f64::from(column_number - 1) * WELL_PITCH_MM,
+        A1_OFFSET_Y_MM + f64::from(row) * WELL_PITCH_MM,
+    )
+}
+
+/// Width and height in millimetres of a tray with `columns` x `rows` wells,
+/// keeping the outer wells as far from the edges as on an SBS microplate
+pub fn tray_size(columns: i32, rows: i32) -> (f64, f64) {
+    (
+        2.0 * A1_OFFSET_X_MM + f64::from(columns - 1) * WELL_PITCH_MM,
+        2.0 * A1_OFFSET_Y_MM + f64::from(rows - 1) * WELL_PITCH_MM,
+    )
+}
+
+/// Inverse-distance weighted temperature at `point` from probe positions and
+/// readings, rounded to 3 decimal places. `None` without readings.
+pub fn interpolate_at(
+    point: (f64, f64),
+    readings: impl IntoIterator<Item = ((Decimal, Decimal), Decimal)>,
+) -> Option<Decimal> {
+    let mut weighted_sum = 0.0;
+    let mut weight_total = 0.0;
+    for ((x, y), temperature) in readings {
+        let (Some(x), Some(y), Some(temperature)) = (x.to_f64(), y.to_f64(), temperature.to_f64())
+        else {
+            continue;
+        };
+        let distance = (x - point.0).hypot(y - point.1);
+        if distance < COINCIDENT_MM {
+            return Decimal::from_f64(temperature).map(|value| value.round_dp(3));
+        }
+        let weight = distance.powi(-IDW_POWER);
+        weighted_sum += weight * temperature;
+        weight_total += weight;
+    }
+    if weight_total > 0.0 {
+        Decimal::from_f64(weighted_sum / weight_total).map(|value| value.round_dp(3))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interpolate_at() {
+        let probe = |x: i64, y: i64, temperature: i64| {
+            (
+                (Decimal::from(x), Decimal::from(y)),
+                Decimal::from(temperature),
+            )
+        };
+
+        assert_eq!(interpolate_at((0.0, 0.0), []), None);
+        // A single probe gives its reading everywhere
+        assert_eq!(
+            interpolate_at((50.0, 50.0), [probe(0, 0, -20)]),
+            Some(Decimal::from(-20))
+        );
+        // Halfway between two probes
+        assert_eq!(
+            interpolate_at((50.0, 0.0), [probe(0, 0, -20), probe(100, 0, -10)]),
+            Some(Decimal::from(-15))
+        );
+        // Three times closer to the first probe: weights 9 to 1
+        assert_eq!(
+            interpolate_at((25.0, 0.0), [probe(0, 0, -20), probe(100, 0, -10)]),
+            Some(Decimal::from(-19))
+        );
+        assert_eq!(
+            interpolate_at((100.0, 0.0), [probe(0, 0, -20), probe(100, 0, -10)]),
+            Some(Decimal::from(-10))
+        );
+    }
+
+    #[test]
+    fn test_well_position() {
+        assert_eq!(well_position("A", 1), (A1_OFFSET_X_MM, A1_OFFSET_Y_MM));
+        assert_eq!(
+            well_position("H", 12),
+            (A1_OFFSET_X_MM + 99.0, A1_OFFSET_Y_MM + 63.0)
+        );
+    }
+}
@@ -0,0 +1,350 @@
+//! Merging of a run split into two uploads, as when the instrument was
+//! restarted part way through.
+//!
+//! The readings, transitions, assets, exclusions and comments of the second
+//! upload are moved to the first, and the emptied experiment is deleted,
+//! along with the results kept for it.
+//! Readings are copied, with new IDs, and what points at them is moved to
+//! the copies, as on Postgres their partition is found by their experiment.
+//! Readings that do not follow on from the first upload's, as when the
+//! restart reset the logger's clock, are shifted to start one reading
+//! interval after its last.
+//! The logger also starts every well liquid again after a restart, so
+//! transitions that do not change what is known of a well are dropped and
+//! the rest take the well's state from before them.
+
+use super::comments::models as comments;
+use super::excluded_wells::models as excluded_wells;
+use super::models::{self as experiments, Experiment, get_one_experiment};
+use super::phase_transitions::models as phase_transitions;
+use super::probe_temperature_readings::models as probe_readings;
+use super::summaries::{discard_results_summary, recompute_results_summary};
+use super::temperatures::models as temperature_readings;
+use crate::assets::models as assets;
+use crate::freezing_results::services::delete_freezing_results;
+use crate::services::processing::database::insert_all;
+use chrono::{DateTime, Duration, Utc};
+use sea_orm::{
+    ActiveModelTrait,
+    ActiveValue::Set,
+    ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait, IntoActiveModel,
+    QueryFilter, QueryOrder, QuerySelect, TransactionTrait,
+    sea_query::{Expr, Query, SelectStatement},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(Clone, Debug, Deserialize, ToSchema)]
+pub struct ExperimentMergeRequest {
+    /// Experiment holding the rest of the run; it is deleted once merged
+    pub source_experiment_id: Uuid,
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct ExperimentMergeResult {
+    pub experiment: Experiment,
+    pub readings_moved: usize,
+    pub phase_transitions_moved: usize,
+    /// Transitions of the source that did not change its well's state
+    pub phase_transitions_dropped: usize,
+    pub assets_moved: u64,
+    /// Seconds added to the source's timestamps, 0 when they followed on
+    pub timestamp_shift_seconds: i64,
+}
+
+async fn find_experiment(
+    db: &impl ConnectionTrait,
+    id: Uuid,
+    label: &str,
+) -> Result<experiments::Model, DbErr> {
+    experiments::Entity::find_by_id(id)
+        .one(db)
+        .await?
+        .filter(|experiment| experiment.deleted_at.is_none())
+        .ok_or_else(|| DbErr::RecordNotFound(format!("{label} not found")))
+}
+
+/// Timestamps of the experiment's first two and last two readings
+async fn reading_times(
+    db: &impl ConnectionTrait,
+    experiment_id: Uuid,
+) -> Result<Vec<DateTime<Utc>>, DbErr> {
+    let times = |descending: bool| {
+        let select = temperature_readings::Entity::find()
+            .filter(temperature_readings::Column::ExperimentId.eq(experiment_id))
+            .select_only()
+            .column(temperature_readings::Column::Timestamp);
+        if descending {
+            select.order_by_desc(temperature_readings::Column::Timestamp)
+        } else {
+            select.order_by_asc(temperature_readings::Column::Timestamp)
+        }
+        .limit(2)
+        .into_tuple::<DateTime<Utc>>()
+        .all(db)
+    };
+    let mut first = times(false).await?;
+    let mut last = times(true).await?;
+    last.reverse();
+    first.extend(last);
+    Ok(first)
+}
+
+/// Shift that makes the source's readings follow the target's
+async fn timestamp_shift(
+    db: &impl ConnectionTrait,
+    target_id: Uuid,
+    source_id: Uuid,
+) -> Result<Duration, DbErr> {
+    let target = reading_times(db, target_id).await?;
+    let source = reading_times(db, source_id).await?;
+    let (Some(target_last), Some(source_first)) = (target.last(), source.first()) else {
+        return Ok(Duration::zero());
+    };
+    if source_first > target_last {
+        return Ok(Duration::zero());
+    }
+    // The reading interval of the run, as the source or else the target logged it
+    let interval = [&source[..], &target[target.len().saturating_sub(2)..]]
+        .iter()
+        .find_map(|times| match times {
+            [first, second, ..] if second > first => Some(*second - *first),
+            _ => None,
+        })
+        .unwrap_or_else(|| Duration::seconds(1));
+    Ok(*target_last + interval - *source_first)
+}
+
+/// Move the source's transitions to the target and its copies of their
+/// readings, dropping those that do not change their well's state. Returns
+/// the moved and dropped counts.
+async fn move_transitions(
+    db: &impl ConnectionTrait,
+    target_id: Uuid,
+    source_id: Uuid,
+    shift: Duration,
+    copies: &HashMap<Uuid, Uuid>,
+) -> Result<(usize, usize), DbErr> {
+    let mut states: HashMap<Uuid, i32> = HashMap::new();
+    for transition in phase_transitions::Entity::find()
+        .filter(phase_transitions::Column::ExperimentId.eq(target_id))
+        .order_by_asc(phase_transitions::Column::Timestamp)
+        .all(db)
+        .await?
+    {
+        states.insert(transition.well_id, transition.new_state);
+    }
+
+    let (mut moved, mut dropped) = (0, 0);
+    for transition in phase_transitions::Entity::find()
+        .filter(phase_transitions::Column::ExperimentId.eq(source_id))
+        .order_by_asc(phase_transitions::Column::Timestamp)
+        .all(db)
+        .await?
+    {
+        let state = states.get(&transition.well_id).copied().unwrap_or(0);
+        if transition.new_state == state {
+            phase_transitions::Entity::delete_by_id(transition.id)
+                .exec(db)
+                .await?;
+            dropped += 1;
+            continue;
+        }
+        states.insert(transition.well_id, transition.new_state);
+        let timestamp = transition.timestamp + shift;
+        let reading_id = copies
+            .get(&transition.temperature_reading_id)
+            .copied()
+            .unwrap_or(transition.temperature_reading_id);
+        let mut active = transition.into_active_model();
+        active.experiment_id = Set(target_id);
+        active.temperature_reading_id = Set(reading_id);
+        active.timestamp = Set(timestamp);
+        active.previous_state = Set(state);
+        active.update(db).await?;
+        moved += 1;
+    }
+    Ok((moved, dropped))
+}
+
+/// IDs of the experiment's readings
+fn reading_ids(experiment_id: Uuid) -> SelectStatement {
+    Query::select()
+        .column(temperature_readings::Column::Id)
+        .from(temperature_readings::Entity)
+        .and_where(temperature_readings::Column::ExperimentId.eq(experiment_id))
+        .to_owned()
+}
+
+/// Copy the source's readings to the target, shifted, with their probe
+/// readings, and point the source's frames at the copies. Returns the copy
+/// of each reading by the original.
+async fn copy_readings(
+    db: &impl ConnectionTrait,
+    target_id: Uuid,
+    source_id: Uuid,
+    shift: Duration,
+) -> Result<HashMap<Uuid, Uuid>, DbErr> {
+    let readings = temperature_readings::Entity::find()
+        .filter(temperature_readings::Column::ExperimentId.eq(source_id))
+        .all(db)
+        .await?;
+    let copies: HashMap<Uuid, Uuid> = readings
+        .iter()
+        .map(|reading| (reading.id, Uuid::new_v4()))
+        .collect();
+    insert_all(
+        db,
+        readings
+            .into_iter()
+            .map(|reading| temperature_readings::ActiveModel {
+                id: Set(copies[&reading.id]),
+                experiment_id: Set(target_id),
+                timestamp: Set(reading.timestamp + shift),
+                image_filename: Set(reading.image_filename),
+                created_at: Set(reading.created_at),
+            })
+            .collect(),
+    )
+    .await?;
+
+    let probe_readings = probe_readings::Entity::find()
+        .filter(probe_readings::Column::TemperatureReadingId.in_subquery(reading_ids(source_id)))
+        .all(db)
+        .await?;
+    insert_all(
+        db,
+        probe_readings
+            .into_iter()
+            .map(|probe_reading| probe_readings::ActiveModel {
+                id: Set(Uuid::new_v4()),
+                probe_id: Set(probe_reading.probe_id),
+                temperature_reading_id: Set(copies[&probe_reading.temperature_reading_id]),
+                temperature: Set(probe_reading.temperature),
+                created_at: Set(probe_reading.created_at),
+            })
+            .collect(),
+    )
+    .await?;
+
+    for frame in assets::Entity::find()
+        .filter(assets::Column::TemperatureReadingId.in_subquery(reading_ids(source_id)))
+        .all(db)
+        .await?
+    {
+        let copy = frame
+            .temperature_reading_id
+            .and_then(|reading_id| copies.get(&reading_id).copied());
+        let mut active = frame.into_active_model();
+        active.temperature_reading_id = Set(copy);
+        active.update(db).await?;
+    }
+    Ok(copies)
+}
+
+/// Delete the source's readings, once nothing points at them
+async fn delete_readings(db: &impl ConnectionTrait, source_id: Uuid) -> Result<(), DbErr> {
+    probe_readings::Entity::delete_many()
+        .filter(probe_readings::Column::TemperatureReadingId.in_subquery(reading_ids(source_id)))
+        .exec(db)
+        .await?;
+    temperature_readings::Entity::delete_many()
+        .filter(temperature_readings::Column::ExperimentId.eq(source_id))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// Move the source's exclusions of wells the target does not exclude
+async fn move_exclusions(
+    db: &impl ConnectionTrait,
+    target_id: Uuid,
+    source_id: Uuid,
+) -> Result<(), DbErr> {
+    let excluded: HashSet<Uuid> = excluded_wells::Entity::find()
+        .filter(excluded_wells::Column::ExperimentId.eq(target_id))
+        .select_only()
+        .column(excluded_wells::Column::WellId)
+        .into_tuple()
+        .all(db)
+        .await?
+        .into_iter()
+        .collect();
+    excluded_wells::Entity::update_many()
+        .col_expr(excluded_wells::Column::ExperimentId, Expr::value(target_id))
+        .filter(excluded_wells::Column::ExperimentId.eq(source_id))
+        .filter(excluded_wells::Column::WellId.is_not_in(excluded))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// Merge the source experiment into the target and delete it. The
+/// target's results are built again. Problems are returned as
+/// `DbErr::Custom`.
+pub async fn merge_experiments(
+    db: &DatabaseConnection,
+    target_id: Uuid,
+    request: ExperimentMergeRequest,
+) -> Result<ExperimentMergeResult, DbErr> {
+    let source_id = request.source_experiment_id;
+    if source_id == target_id {
+        return Err(DbErr::Custom(
+            "An experiment cannot be merged into itself".to_string(),
+        ));
+    }
+    let target = find_experiment(db, target_id, "Experiment").await?;
+    let source = find_experiment(db, source_id, "Source experiment").await?;
+    if source.tray_configuration_id != target.tray_configuration_id {
+        return Err(DbErr::Custom(
+            "The experiments use different tray configurations".to_string(),
+        ));
+    }
+
+    let txn = db.begin().await?;
+    let shift = timestamp_shift(&txn, target_id, source_id).await?;
+    let copies = copy_readings(&txn, target_id, source_id, shift).await?;
+    let readings_moved = copies.len();
+    let (phase_transitions_moved, phase_transitions_dropped) =
+        move_transitions(&txn, target_id, source_id, shift, &copies).await?;
+    delete_readings(&txn, source_id).await?;
+    let assets_moved = assets::Entity::update_many()
+        .col_expr(assets::Column::ExperimentId, Expr::value(target_id))
+        .filter(assets::Column::ExperimentId.eq(source_id))
+        .exec(&txn)
+        .await?
+        .rows_affected;
+    move_exclusions(&txn, target_id, source_id).await?;
+    comments::Entity::update_many()
+        .col_expr(comments::Column::ExperimentId, Expr::value(target_id))
+        .filter(comments::Column::ExperimentId.eq(source_id))
+        .exec(&txn)
+        .await?;
+
+    // The emptied experiment is deleted as the experiment routes delete
+    let now = Utc::now();
+    let mut shell = source.into_active_model();
+    shell.deleted_at = Set(Some(now));
+    shell.last_updated = Set(now);
+    shell.update(&txn).await?;
+    discard_results_summary(&txn, source_id).await?;
+    delete_freezing_results(&txn, source_id).await?;
+
+    if readings_moved > 0 {
+        recompute_results_summary(&txn, target_id, None).await?;
+    } else {
+        discard_results_summary(&txn, target_id).await?;
+    }
+    txn.commit().await?;
+
+    Ok(ExperimentMergeResult {
+        experiment: get_one_experiment(db, target_id).await?,
+        readings_moved,
+        phase_transitions_moved,
+        phase_transitions_dropped,
+        assets_moved,
+        timestamp_shift_seconds: shift.num_seconds(),
+    })
+}
@@ -1,8 +1,27 @@
+pub mod bundle;
+pub mod cloning;
+pub mod comments;
+pub mod excel_export;
+pub mod excluded_wells;
+pub mod exclusions;
+pub mod frames;
+pub mod image_diff;
+pub mod image_freeze;
+pub mod interpolation;
+pub mod merging;
 pub mod models;
+pub mod overlay;
 pub mod phase_transitions;
 pub mod probe_temperature_readings;
+pub mod region_validation;
+pub mod regions;
+pub mod results_summaries;
 pub mod services;
+pub mod summaries;
 pub mod temperatures;
+pub mod time_points;
+pub mod timelapse;
+pub mod well_image;
 #[cfg(test)]
 mod tests;
 pub mod views;
@@ -1,4 +1,8 @@
-use crate::experiments::services::build_tray_centric_results;
+use crate::common::include::includes;
+use crate::common::soft_delete::{soft_delete, soft_delete_many};
+use crate::common::upsert::NaturalKey;
+use crate::experiments::comments::services::comment_threads;
+use crate::experiments::summaries::{discard_results_summary, results_summaries, results_summary};
 use chrono::{DateTime, Utc};
 use crudcrate::{CRUDResource, EntityToModels, traits::MergeIntoActiveModel};
 use rust_decimal::Decimal;
@@ -17,6 +21,8 @@ use uuid::Uuid;
     name_plural = "experiments",
     description = "Experiments track ice nucleation testing sessions with associated data and results.",
     fn_get_one = get_one_experiment,
+    fn_delete = delete_experiment,
+    fn_delete_many = delete_many_experiments,
     fn_create = create_experiment,
     fn_update = update_experiment,
     fn_get_all = get_all_experiments
@@ -46,6 +52,26 @@ pub struct Model {
     pub remarks: Option<String>,
     #[crudcrate(sortable, filterable, list_model = false)]
     pub tray_configuration_id: Option<Uuid>,
+    #[sea_orm(column_type = "Text", nullable)]
+    /// Set when a DOI is minted for the experiment, not by clients
+    #[crudcrate(sortable, filterable, fulltext, create_model = false, update_model = false)]
+    pub doi: Option<String>,
+    /// Project the experiment belongs to; members of the project can work
+    /// with it
+    #[crudcrate(sortable, filterable)]
+    pub project_id: Option<Uuid>,
+    /// Keycloak username of the user who created it, who can work with it
+    /// outside the project and share it
+    #[sea_orm(column_type = "Text", nullable)]
+    #[crudcrate(sortable, filterable, create_model = false, update_model = false)]
+    pub created_by: Option<String>,
+    /// Lab the experiment belongs to, when labs are on
+    #[sea_orm(column_type = "Text", nullable)]
+    #[crudcrate(sortable, filterable, create_model = false, update_model = false)]
+    pub lab: Option<String>,
+    /// When the experiment was deleted; deleted experiments are kept until restored
+    #[crudcrate(sortable, filterable, create_model = false, update_model = false)]
+    pub deleted_at: Option<DateTime<Utc>>,
     #[crudcrate(update_model = false, create_model = false, on_create = chrono::Utc::now(), sortable, list_model=false)]
     pub created_at: DateTime<Utc>,
     #[crudcrate(update_model = false, create_model = false, on_update = chrono::Utc::now(), on_create = chrono::Utc::now(), sortable)]
@@ -56,6 +82,14 @@ pub struct Model {
     #[sea_orm(ignore)]
     #[crudcrate(non_db_attr = true, default = None, list_model=false)]
     pub results: Option<super::models::ExperimentResultsResponse>,
+    /// Assets of the experiment, embedded with `?include=assets`
+    #[sea_orm(ignore)]
+    #[crudcrate(non_db_attr = true, default = None, list_model=false)]
+    pub assets: Option<Vec<crate::assets::models::Asset>>,
+    /// Comment threads on the experiment and its wells, oldest first
+    #[sea_orm(ignore)]
+    #[crudcrate(non_db_attr = true, default = None, list_model=false)]
+    pub comments: Option<Vec<crate::experiments::comments::models::ExperimentComment>>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -110,16 +144,34 @@ impl Related<crate::experiments::phase_transitions::models::Entity> for Entity {
 
 impl ActiveModelBehavior for ActiveModel {}
 
+/// Experiments are identified by their name and when they were performed
+impl NaturalKey for Experiment {
+    const NATURAL_KEY: &'static [&'static str] = &["name", "performed_at"];
+}
+
+/// Custom `delete` keeping the experiment, marked deleted
+async fn delete_experiment(db: &DatabaseConnection, id: Uuid) -> Result<Uuid, DbErr> {
+    soft_delete::<Entity>(db, Column::Id, Column::DeletedAt, id).await
+}
+
+async fn delete_many_experiments(db: &DatabaseConnection, ids: Vec<Uuid>) -> Result<Vec<Uuid>, DbErr> {
+    soft_delete_many::<Entity>(db, Column::Id, Column::DeletedAt, ids).await
+}
+
 #[derive(ToSchema, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct ProbeTemperatureReadingWithMetadata {
     pub id: Uuid,
     pub temperature_reading_id: Uuid,
+    /// Reading with the probe's calibration applied
     pub temperature: rust_decimal::Decimal,
+    /// Reading as logged
+    pub raw_temperature: rust_decimal::Decimal,
     pub created_at: DateTime<Utc>,
     // Probe metadata
     pub probe_id: Uuid,
     pub probe_name: String,
     pub probe_data_column_index: i32,
+    pub probe_tray_id: Uuid,
     pub probe_position_x: rust_decimal::Decimal,
     pub probe_position_y: rust_decimal::Decimal,
 }
@@ -131,7 +183,10 @@ pub struct TemperatureDataWithProbes {
     pub experiment_id: Uuid,
     pub timestamp: DateTime<Utc>,
     pub image_filename: Option<String>,
+    /// Mean of the calibrated probe readings
     pub average: Option<rust_decimal::Decimal>,
+    /// Mean of the probe readings as logged
+    pub raw_average: Option<rust_decimal::Decimal>,
     // All probe readings for this timestamp with metadata
     pub probe_readings: Vec<ProbeTemperatureReadingWithMetadata>,
 }
@@ -146,8 +201,14 @@ pub struct TrayWellSummary {
     pub dilution_factor: Option<i32>,
     pub first_phase_change_time: Option<DateTime<Utc>>,
     pub temperatures: Option<TemperatureDataWithProbes>,
+    /// Calibrated temperature at the well's position at freeze time,
+    /// interpolated from the probes of its tray
+    pub interpolated_temperature: Option<rust_decimal::Decimal>,
     pub total_phase_changes: usize,
     pub image_asset_id: Option<Uuid>, // Asset ID for the image at freeze time
+    /// Left out of the frozen fraction and nucleation statistics
+    pub excluded: bool,
+    pub exclusion_reason: Option<String>,
 }
 
 #[derive(ToSchema, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
@@ -158,10 +219,25 @@ pub struct TrayResultsSummary {
 }
 
 #[derive(ToSchema, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[schema(example = json!({
+    "total_time_points": 6786,
+    "first_timestamp": "2025-03-20T15:13:47Z",
+    "last_timestamp": "2025-03-20T17:06:53Z",
+    "total_wells": 192,
+    "excluded_wells": 2,
+    "frozen_wells": 187,
+    "frozen_fraction": "0.9842"
+}))]
 pub struct ExperimentResultsSummaryCompact {
     pub total_time_points: usize,
     pub first_timestamp: Option<DateTime<Utc>>,
     pub last_timestamp: Option<DateTime<Utc>>,
+    pub total_wells: usize,
+    pub excluded_wells: usize,
+    /// Wells that froze, excluded wells not counted
+    pub frozen_wells: usize,
+    /// Share of the wells that froze, excluded wells not counted
+    pub frozen_fraction: Option<Decimal>,
 }
 
 #[derive(ToSchema, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
@@ -171,7 +247,7 @@ pub struct ExperimentResultsResponse {
 }
 
 // Helper function to enhance regions with treatment and sample data
-async fn enhance_regions_with_treatment_data(
+pub(super) async fn enhance_regions_with_treatment_data(
     region_models: Vec<crate::tray_configurations::regions::models::Model>,
     db: &DatabaseConnection,
 ) -> Result<Vec<crate::tray_configurations::regions::models::Region>, DbErr> {
@@ -246,6 +322,7 @@ async fn enhance_regions_with_treatment_data(
                 col_max: region_model.col_max,
                 row_max: region_model.row_max,
                 dilution_factor: region_model.dilution_factor,
+                dilution_id: region_model.dilution_id,
                 is_background_key: region_model.is_background_key,
                 created_at: region_model.created_at,
                 last_updated: region_model.last_updated,
@@ -266,36 +343,127 @@ pub(super) async fn get_one_experiment(
         .await?
         .ok_or(DbErr::RecordNotFound("Experiment not found".to_string()))?;
 
-    // Load regions with enhanced treatment and sample data
-    let region_models = model
-        .find_related(crate::tray_configurations::regions::models::Entity)
-        .all(db)
-        .await?;
-
-    // Enhance regions with treatment and sample data
-    let enhanced_regions = enhance_regions_with_treatment_data(region_models, db).await?;
+    // Regions, results and comments are embedded unless left out with
+    // `?include=`, assets only when asked for
+    let mut enhanced_regions = vec![];
+    if includes("regions", true) {
+        // Load regions with enhanced treatment and sample data
+        let region_models = model
+            .find_related(crate::tray_configurations::regions::models::Entity)
+            .all(db)
+            .await?;
+        enhanced_regions = enhance_regions_with_treatment_data(region_models, db).await?;
+    }
+    let assets = if includes("assets", false) {
+        let assets = model
+            .find_related(crate::assets::models::Entity)
+            .filter(crate::assets::models::Column::IsDeleted.eq(false))
+            .order_by_asc(crate::assets::models::Column::OriginalFilename)
+            .all(db)
+            .await?;
+        Some(assets.into_iter().map(Into::into).collect())
+    } else {
+        None
+    };
 
     let mut experiment: Experiment = model.into();
     experiment.regions = enhanced_regions;
-    experiment.results = build_tray_centric_results(id, db).await?;
+    experiment.assets = assets;
+    if includes("results", true) {
+        experiment.results = results_summary(db, id).await?;
+    }
+    if includes("comments", true) {
+        experiment.comments = Some(comment_threads(db, id).await?);
+    }
 
     Ok(experiment)
 }
 
+/// The experiments with the IDs, in no particular order, with the regions
+/// and results [`get_one_experiment`] embeds, read for all of them together.
+/// Assets and comments are left out.
+pub(crate) async fn get_experiments(
+    db: &DatabaseConnection,
+    ids: &[Uuid],
+) -> Result<Vec<Experiment>, DbErr> {
+    use crate::tray_configurations::regions::models as regions;
+
+    let models = Entity::find()
+        .filter(Column::Id.is_in(ids.iter().copied()))
+        .all(db)
+        .await?;
+    let mut regions_by_experiment: std::collections::HashMap<Uuid, Vec<regions::Region>> =
+        std::collections::HashMap::new();
+    if includes("regions", true) {
+        let region_models = regions::Entity::find()
+            .filter(regions::Column::ExperimentId.is_in(ids.iter().copied()))
+            .all(db)
+            .await?;
+        for region in enhance_regions_with_treatment_data(region_models, db).await? {
+            regions_by_experiment
+                .entry(region.experiment_id)
+                .or_default()
+                .push(region);
+        }
+    }
+    let mut results = if includes("results", true) {
+        results_summaries(db, ids).await?
+    } else {
+        std::collections::HashMap::new()
+    };
+
+    Ok(models
+        .into_iter()
+        .map(|model| {
+            let mut experiment: Experiment = model.into();
+            experiment.regions = regions_by_experiment
+                .remove(&experiment.id)
+                .unwrap_or_default();
+            experiment.results = results.remove(&experiment.id);
+            experiment
+        })
+        .collect())
+}
+
 pub(super) async fn create_experiment(
     db: &DatabaseConnection,
     data: ExperimentCreate,
 ) -> Result<Experiment, DbErr> {
-    let txn = db.begin().await?;
-
-    // Store regions before conversion since they're not part of the DB model
     let regions_to_create = data.regions.clone();
+    super::region_validation::validate_regions(
+        db,
+        data.tray_configuration_id,
+        &regions_to_create
+            .iter()
+            .map(|region| super::region_validation::RegionBounds {
+                name: region.name.clone(),
+                tray_id: region.tray_id,
+                row_min: region.row_min,
+                row_max: region.row_max,
+                col_min: region.col_min,
+                col_max: region.col_max,
+            })
+            .collect::<Vec<_>>(),
+    )
+    .await?;
+    crate::samples::qc::ensure_not_rejected(
+        db,
+        regions_to_create
+            .iter()
+            .filter_map(|region| region.treatment_id)
+            .collect(),
+    )
+    .await?;
+
+    let txn = db.begin().await?;
 
     // Create the experiment first (avoid data.into() due to non-db attributes)
     // Manually construct ActiveModel from database fields only
     let mut experiment_model = ActiveModel::new();
     experiment_model.id = Set(Uuid::new_v4()); // Explicitly set UUID for SQLite compatibility
     experiment_model.name = Set(data.name);
+    experiment_model.created_by = Set(crate::common::auth::current_username());
+    experiment_model.lab = Set(crate::common::labs::current_lab());
     if let Some(username) = data.username {
         experiment_model.username = Set(Some(username));
     }
@@ -318,12 +486,22 @@ pub(super) async fn create_experiment(
     if let Some(tray_configuration_id) = data.tray_configuration_id {
         experiment_model.tray_configuration_id = Set(Some(tray_configuration_id));
     }
+    if let Some(project_id) = data.project_id {
+        experiment_model.project_id = Set(Some(project_id));
+    }
 
     let experiment = experiment_model.insert(&txn).await?;
 
     // Handle regions if provided
     if !regions_to_create.is_empty() {
         for region in regions_to_create {
+            let dilution_factor = crate::treatments::dilutions::models::region_dilution_factor(
+                &txn,
+                region.treatment_id,
+                region.dilution_id,
+                region.dilution_factor,
+            )
+            .await?;
             // Convert Region to ActiveModel for insertion
             let region_active = crate::tray_configurations::regions::models::ActiveModel {
                 id: Set(Uuid::new_v4()),
@@ -336,7 +514,8 @@ pub(super) async fn create_experiment(
                 row_min: Set(region.row_min),
                 col_max: Set(region.col_max),
                 row_max: Set(region.row_max),
-                dilution_factor: Set(region.dilution_factor),
+                dilution_factor: Set(dilution_factor),
+                dilution_id: Set(region.dilution_id),
                 is_background_key: Set(region.is_background_key),
                 created_at: Set(chrono::Utc::now()),
                 last_updated: Set(chrono::Utc::now()),
@@ -370,10 +549,47 @@ pub(super) async fn update_experiment(
             update_data,
             existing,
         )?;
-    let _updated = updated_model.update(&txn).await?;
+    let updated = updated_model.update(&txn).await?;
+    // Regions and the tray configuration decide the results
+    discard_results_summary(&txn, id).await?;
 
     // Handle regions update - delete existing regions and create new ones
     if !regions.is_empty() {
+        super::region_validation::validate_regions(
+            &txn,
+            updated.tray_configuration_id,
+            &regions
+                .iter()
+                .map(|region| super::region_validation::RegionBounds {
+                    name: region.name.clone().flatten(),
+                    tray_id: region.tray_id.flatten(),
+                    row_min: region.row_min.flatten(),
+                    row_max: region.row_max.flatten(),
+                    col_min: region.col_min.flatten(),
+                    col_max: region.col_max.flatten(),
+                })
+                .collect::<Vec<_>>(),
+        )
+        .await?;
+        // Treatments already in the experiment stay, even of samples rejected since
+        let assigned_treatment_ids: Vec<Uuid> =
+            crate::tray_configurations::regions::models::Entity::find()
+                .filter(crate::tray_configurations::regions::models::Column::ExperimentId.eq(id))
+                .all(&txn)
+                .await?
+                .into_iter()
+                .filter_map(|region| region.treatment_id)
+                .collect();
+        crate::samples::qc::ensure_not_rejected(
+            &txn,
+            regions
+                .iter()
+                .filter_map(|region| region.treatment_id.flatten())
+                .filter(|treatment_id| !assigned_treatment_ids.contains(treatment_id))
+                .collect(),
+        )
+        .await?;
+
         // Delete existing regions for this experiment
         crate::tray_configurations::regions::models::Entity::delete_many()
             .filter(crate::tray_configurations::regions::models::Column::ExperimentId.eq(id))
@@ -382,11 +598,20 @@ pub(super) async fn update_experiment(
 
         // Create new regions
         for region in regions {
+            let treatment_id = region.treatment_id.flatten();
+            let dilution_id = region.dilution_id.flatten();
+            let dilution_factor = crate::treatments::dilutions::models::region_dilution_factor(
+                &txn,
+                treatment_id,
+                dilution_id,
+                region.dilution_factor.flatten(),
+            )
+            .await?;
             // Convert Region to ActiveModel for insertion
             let region_active = crate::tray_configurations::regions::models::ActiveModel {
                 id: Set(Uuid::new_v4()),
                 experiment_id: Set(id),
-                treatment_id: Set(region.treatment_id.flatten()),
+                treatment_id: Set(treatment_id),
                 name: Set(region.name.flatten()),
                 display_colour_hex: Set(region.display_colour_hex.flatten()),
                 tray_id: Set(region.tray_id.flatten()),
@@ -394,7 +619,8 @@ pub(super) async fn update_experiment(
                 row_min: Set(region.row_min.flatten()),
                 col_max: Set(region.col_max.flatten()),
                 row_max: Set(region.row_max.flatten()),
-                dilution_factor: Set(region.dilution_factor.flatten()),
+                dilution_factor: Set(dilution_factor),
+                dilution_id: Set(dilution_id),
                 is_background_key: Set(region.is_background_key.flatten().unwrap_or_default()),
                 created_at: Set(chrono::Utc::now()),
                 last_updated: Set(chrono::Utc::now()),
@@ -426,25 +652,9 @@ pub(super) async fn get_all_experiments(
         .all(db)
         .await?;
 
-    let mut experiment_lists = Vec::new();
-
-    for model in models {
-        let regions: Vec<crate::tray_configurations::regions::models::Region> = model
-            .find_related(crate::tray_configurations::regions::models::Entity)
-            .all(db)
-            .await?
-            .into_iter()
-            .map(Into::into) // Direct conversion using EntityToModels
-            .collect();
-
-        let mut experiment: Experiment = model.into();
-        experiment.regions = regions;
-
-        // Convert to ExperimentList
-        let experiment_list: ExperimentList = experiment.into();
-
-        experiment_lists.push(experiment_list);
-    }
-
-    Ok(experiment_lists)
+    // Lists leave out regions, so they are not loaded
+    Ok(models
+        .into_iter()
+        .map(|model| Experiment::from(model).into())
+        .collect())
 }
@@ -0,0 +1,402 @@
+//! Camera frames annotated with the experiment's regions and wells
+//!
+//! Regions are drawn as tinted rectangles in their display colour, or in the
+//! colour of their treatment, with their name at the top-left corner. Every
+//! well located by the tray's well grid is outlined and, optionally, labelled
+//! with its coordinate.
+
+use super::timelapse::{draw_text, text_width};
+use crate::assets::models as s3_assets;
+use crate::common::state::AppState;
+use crate::external::s3::get_object_from_s3;
+use crate::tray_configurations::{
+    regions::models as regions,
+    trays::models as trays,
+    well_grid::{TrayWellGrid, compute_tray_grid},
+};
+use crate::treatments::models::{self as treatments, TreatmentName};
+use image::{ImageFormat, Rgb, RgbImage};
+use sea_orm::{EntityTrait, QueryFilter, entity::prelude::*};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Outline radius for trays without `well_relative_diameter`
+const FALLBACK_WELL_RADIUS_PX: f64 = 4.0;
+/// Share of the region colour in the tint over the region
+const REGION_TINT: f64 = 0.25;
+const WELL_OUTLINE: Rgb<u8> = Rgb([255, 255, 255]);
+
+/// `#RRGGBB` or `RRGGBB`
+fn parse_hex_colour(hex: &str) -> Option<Rgb<u8>> {
+    let hex = hex.trim().trim_start_matches('#');
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |range: std::ops::Range<usize>| u8::from_str_radix(&hex[range], 16).ok();
+    Some(Rgb([channel(0..2)?, channel(2..4)?, channel(4..6)?]))
+}
+
+fn treatment_colour(name: Option<&TreatmentName>) -> Rgb<u8> {
+    match name {
+        Some(TreatmentName::None) => Rgb([59, 130, 246]),
+        Some(TreatmentName::Heat) => Rgb([239, 68, 68]),
+        Some(TreatmentName::H2o2) => Rgb([16, 185, 129]),
+        None => Rgb([200, 200, 200]),
+    }
+}
+
+fn blend(pixel: &mut Rgb<u8>, colour: Rgb<u8>, share: f64) {
+    for (channel, target) in pixel.0.iter_mut().zip(colour.0) {
+        let mixed = f64::from(*channel) * (1.0 - share) + f64::from(target) * share;
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)] // Within 0..=255
+        let mixed = mixed.round().clamp(0.0, 255.0) as u8;
+        *channel = mixed;
+    }
+}
+
+fn to_pixel(value: f64) -> Option<u32> {
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)] // Checked range
+    (value >= 0.0 && value < f64::from(u32::MAX)).then(|| value.round() as u32)
+}
+
+fn put(frame: &mut RgbImage, x: f64, y: f64, colour: Rgb<u8>) {
+    if let (Some(x), Some(y)) = (to_pixel(x), to_pixel(y))
+        && x < frame.width()
+        && y < frame.height()
+    {
+        frame.put_pixel(x, y, colour);
+    }
+}
+
+/// Line of the given thickness between two points
+fn draw_line(
+    frame: &mut RgbImage,
+    from: (f64, f64),
+    to: (f64, f64),
+    thickness: u32,
+    colour: Rgb<u8>,
+) {
+    let length = (to.0 - from.0).hypot(to.1 - from.1);
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)] // Line lengths fit easily
+    let steps = length.ceil().max(1.0) as u32;
+    let half = f64::from(thickness) / 2.0;
+    for step in 0..=steps {
+        let t = f64::from(step) / f64::from(steps);
+        let (x, y) = (from.0 + (to.0 - from.0) * t, from.1 + (to.1 - from.1) * t);
+        for dy in 0..thickness {
+            for dx in 0..thickness {
+                put(
+                    frame,
+                    x - half + f64::from(dx),
+                    y - half + f64::from(dy),
+                    colour,
+                );
+            }
+        }
+    }
+}
+
+pub(super) fn draw_circle(frame: &mut RgbImage, centre: (f64, f64), radius: f64, colour: Rgb<u8>) {
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)] // Small radii
+    let steps = (radius * 8.0).ceil().max(16.0) as u32;
+    for step in 0..steps {
+        let angle = std::f64::consts::TAU * f64::from(step) / f64::from(steps);
+        put(
+            frame,
+            centre.0 + radius * angle.cos(),
+            centre.1 + radius * angle.sin(),
+            colour,
+        );
+    }
+}
+
+/// Tint the inside of a convex quadrilateral and outline it
+fn draw_quad(frame: &mut RgbImage, corners: [(f64, f64); 4], thickness: u32, colour: Rgb<u8>) {
+    // Inside when the point is on the same side of every edge
+    let inside = |x: f64, y: f64| {
+        let (mut left, mut right) = (false, false);
+        for (index, &(ax, ay)) in corners.iter().enumerate() {
+            let (bx, by) = corners[(index + 1) % 4];
+            let cross = (bx - ax) * (y - ay) - (by - ay) * (x - ax);
+            left |= cross > 0.0;
+            right |= cross < 0.0;
+        }
+        !(left && right)
+    };
+    let xs = corners.map(|(x, _)| x);
+    let ys = corners.map(|(_, y)| y);
+    let bound = |values: [f64; 4], min: bool, limit: u32| {
+        let value = if min {
+            values.into_iter().fold(f64::INFINITY, f64::min).floor()
+        } else {
+            values.into_iter().fold(f64::NEG_INFINITY, f64::max).ceil() + 1.0
+        };
+        to_pixel(value.max(0.0)).unwrap_or(0).min(limit)
+    };
+    for y in bound(ys, true, frame.height())..bound(ys, false, frame.height()) {
+        for x in bound(xs, true, frame.width())..bound(xs, false, frame.width()) {
+            if inside(f64::from(x), f64::from(y)) {
+                blend(frame.get_pixel_mut(x, y), colour, REGION_TINT);
+            }
+        }
+    }
+    for index in 0..4 {
+        draw_line(
+            frame,
+            corners[index],
+            corners[(index + 1) % 4],
+            thickness,
+            colour,
+        );
+    }
+}
+
+/// Text on a dark box, clipped to the frame
+fn draw_tag(frame: &mut RgbImage, x: f64, y: f64, scale: u32, text: &str, colour: Rgb<u8>) {
+    let (Some(left), Some(top)) = (to_pixel(x), to_pixel(y)) else {
+        return;
+    };
+    let width = text_width(text, scale) + scale;
+    for py in top..(top + 8 * scale + scale).min(frame.height()) {
+        for px in left..(left + width).min(frame.width()) {
+            blend(frame.get_pixel_mut(px, py), Rgb([0, 0, 0]), 0.6);
+        }
+    }
+    draw_text(frame, left + scale, top + scale, scale, text, colour);
+}
+
+/// Corners of the area covered by the wells in `row_range` x `column_range`
+/// (0-based, inclusive) of a tray with `columns` columns, extending
+/// `half_extent` beyond the outer well centres
+fn region_corners(
+    grid: &TrayWellGrid,
+    columns: i32,
+    row_range: (i32, i32),
+    column_range: (i32, i32),
+    half_extent: f64,
+) -> Option<[(f64, f64); 4]> {
+    // Wells are listed row by row
+    let at = |row: i32, column: i32| {
+        if !(0..columns).contains(&column) {
+            return None;
+        }
+        let index = usize::try_from(row * columns + column).ok()?;
+        grid.wells.get(index).map(|well| (well.x, well.y))
+    };
+    let (sin, cos) = f64::from(grid.rotation_degrees).to_radians().sin_cos();
+    let (column_dir, row_dir) = ((cos, sin), (-sin, cos));
+    let offset = |point: (f64, f64), along_columns: f64, along_rows: f64| {
+        (
+            point.0 + (column_dir.0 * along_columns + row_dir.0 * along_rows) * half_extent,
+            point.1 + (column_dir.1 * along_columns + row_dir.1 * along_rows) * half_extent,
+        )
+    };
+    Some([
+        offset(at(row_range.0, column_range.0)?, -1.0, -1.0),
+        offset(at(row_range.0, column_range.1)?, 1.0, -1.0),
+        offset(at(row_range.1, column_range.1)?, 1.0, 1.0),
+        offset(at(row_range.1, column_range.0)?, -1.0, 1.0),
+    ])
+}
+
+/// Distance between neighbouring well centres, if the tray has more than one well
+fn well_pitch(grid: &TrayWellGrid) -> Option<f64> {
+    let first = grid.wells.first()?;
+    grid.wells
+        .iter()
+        .skip(1)
+        .map(|well| (well.x - first.x).hypot(well.y - first.y))
+        .filter(|distance| *distance > 0.0)
+        .reduce(f64::min)
+}
+
+/// Draw the regions and wells on a decoded frame
+fn annotate(
+    frame: &mut RgbImage,
+    grids: &[(trays::Model, TrayWellGrid)],
+    experiment_regions: &[(regions::Model, Rgb<u8>)],
+    labels: bool,
+) {
+    let scale = (frame.width() / 640).max(1);
+    let thickness = (frame.width() / 400).max(2);
+
+    for (tray, grid) in grids {
+        let Some(columns) = tray.qty_cols else {
+            continue;
+        };
+        let radius = grid.well_radius.unwrap_or(FALLBACK_WELL_RADIUS_PX);
+        let half_extent = well_pitch(grid).map_or(radius, |pitch| pitch / 2.0);
+
+        for (region, colour) in experiment_regions {
+            if region.tray_id != Some(tray.order_sequence) {
+                continue;
+            }
+            let (Some(row_min), Some(row_max), Some(col_min), Some(col_max)) = (
+                region.row_min,
+                region.row_max,
+                region.col_min,
+                region.col_max,
+            ) else {
+                continue;
+            };
+            let Some(corners) = region_corners(
+                grid,
+                columns,
+                (row_min, row_max),
+                (col_min, col_max),
+                half_extent,
+            ) else {
+                continue;
+            };
+            draw_quad(frame, corners, thickness, *colour);
+            if let Some(name) = &region.name {
+                let (x, y) = corners
+                    .iter()
+                    .copied()
+                    .reduce(|a, b| (a.0.min(b.0), a.1.min(b.1)))
+                    .unwrap_or_default();
+                draw_tag(frame, x, y, scale, name, *colour);
+            }
+        }
+
+        for well in &grid.wells {
+            draw_circle(frame, (well.x, well.y), radius, WELL_OUTLINE);
+            if labels {
+                let text = format!("{}{}", well.row_letter, well.column_number);
+                let half_width = f64::from(text_width(&text, scale)) / 2.0;
+                let half_height = f64::from(7 * scale) / 2.0;
+                if let (Some(left), Some(top)) = (
+                    to_pixel(well.x - half_width),
+                    to_pixel(well.y - half_height),
+                ) {
+                    draw_text(frame, left, top, scale, &text, WELL_OUTLINE);
+                }
+            }
+        }
+    }
+}
+
+/// The experiment's regions with the colour they are drawn in: their display
+/// colour, or else the colour of their treatment
+pub(crate) async fn experiment_region_colours(
+    db: &impl ConnectionTrait,
+    experiment_id: Uuid,
+) -> Result<Vec<(regions::Model, Rgb<u8>)>, DbErr> {
+    let experiment_regions = regions::Entity::find()
+        .filter(regions::Column::ExperimentId.eq(experiment_id))
+        .all(db)
+        .await?;
+    let treatment_names: HashMap<Uuid, TreatmentName> = treatments::Entity::find()
+        .filter(
+            treatments::Column::Id.is_in(
+                experiment_regions
+                    .iter()
+                    .filter_map(|region| region.treatment_id),
+            ),
+        )
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|treatment| (treatment.id, treatment.name))
+        .collect();
+    Ok(experiment_regions
+        .into_iter()
+        .map(|region| {
+            let colour = region
+                .display_colour_hex
+                .as_deref()
+                .and_then(parse_hex_colour)
+                .unwrap_or_else(|| {
+                    treatment_colour(region.treatment_id.and_then(|id| treatment_names.get(&id)))
+                });
+            (region, colour)
+        })
+        .collect())
+}
+
+/// Render an image asset of an experiment with its regions and wells drawn
+/// on it, as PNG.
+///
+/// Missing records are returned as `DbErr::RecordNotFound`, other problems as
+/// `DbErr::Custom`.
+pub async fn render_overlay(
+    state: &AppState,
+    asset: &s3_assets::Model,
+    labels: bool,
+) -> Result<Vec<u8>, DbErr> {
+    if asset.r#type != "image" {
+        return Err(DbErr::RecordNotFound("Asset is not an image".to_string()));
+    }
+    let experiment_id = asset.experiment_id.ok_or_else(|| {
+        DbErr::RecordNotFound("The image does not belong to an experiment".to_string())
+    })?;
+    let tray_configuration_id = super::models::Entity::find_by_id(experiment_id)
+        .one(&state.db)
+        .await?
+        .and_then(|experiment| experiment.tray_configuration_id)
+        .ok_or_else(|| {
+            DbErr::RecordNotFound("The image's experiment has no tray configuration".to_string())
+        })?;
+
+    let grids: Vec<(trays::Model, TrayWellGrid)> = trays::Entity::find()
+        .filter(trays::Column::TrayConfigurationId.eq(tray_configuration_id))
+        .all(&state.db)
+        .await?
+        .into_iter()
+        .filter_map(|tray| compute_tray_grid(&tray).map(|grid| (tray, grid)))
+        .collect();
+    if grids.is_empty() {
+        return Err(DbErr::Custom(
+            "No tray has corner coordinates and dimensions".to_string(),
+        ));
+    }
+
+    let experiment_regions = experiment_region_colours(&state.db, experiment_id).await?;
+
+    let bytes = get_object_from_s3(asset.storage_key(), &state.config)
+        .await
+        .map_err(DbErr::Custom)?;
+    tokio::task::spawn_blocking(move || -> Result<Vec<u8>, String> {
+        let mut frame = image::load_from_memory(&bytes)
+            .map_err(|e| format!("Failed to decode image: {e}"))?
+            .into_rgb8();
+        annotate(&mut frame, &grids, &experiment_regions, labels);
+
+        let mut png = Vec::new();
+        frame
+            .write_to(&mut std::io::Cursor::new(&mut png), ImageFormat::Png)
+            .map_err(|e| format!("Failed to encode overlay: {e}"))?;
+        Ok(png)
+    })
+    .await
+    .map_err(|e| DbErr::Custom(format!("Overlay task failed: {e}")))?
+    .map_err(DbErr::Custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hex_colour() {
+        assert_eq!(parse_hex_colour("#3B82F6"), Some(Rgb([59, 130, 246])));
+        assert_eq!(parse_hex_colour("ef4444"), Some(Rgb([239, 68, 68])));
+        assert_eq!(parse_hex_colour("#3B82F"), None);
+        assert_eq!(parse_hex_colour("#GGGGGG"), None);
+    }
+
+    #[test]
+    fn test_quad_is_tinted_inside_only() {
+        let mut frame = RgbImage::from_pixel(40, 40, Rgb([0, 0, 0]));
+        let colour = Rgb([200, 100, 0]);
+        draw_quad(
+            &mut frame,
+            [(10.0, 10.0), (30.0, 10.0), (30.0, 30.0), (10.0, 30.0)],
+            2,
+            colour,
+        );
+        assert_eq!(frame.get_pixel(20, 20), &Rgb([50, 25, 0]));
+        assert_eq!(frame.get_pixel(10, 20), &colour);
+        assert_eq!(frame.get_pixel(2, 2), &Rgb([0, 0, 0]));
+    }
+}
@@ -53,4 +53,48 @@ impl Related<crate::experiments::temperatures::models::Entity> for Entity {
     }
 }
 
-impl ActiveModelBehavior for ActiveModel {}
\ No newline at end of file
+impl ActiveModelBehavior for ActiveModel {}
+
+/// Mean of the readings with each probe's calibration applied, and as logged
+pub fn mean_temperatures(
+    readings: &[&Model],
+    probes: &std::collections::HashMap<Uuid, crate::tray_configurations::probes::models::Model>,
+) -> Option<(Decimal, Decimal)> {
+    if readings.is_empty() {
+        return None;
+    }
+    let count = Decimal::from(readings.len());
+    let raw: Decimal = readings.iter().map(|reading| reading.temperature).sum();
+    let calibrated: Decimal = readings
+        .iter()
+        .map(|reading| {
+            probes
+                .get(&reading.probe_id)
+                .map_or(reading.temperature, |probe| {
+                    probe.calibrate(reading.temperature)
+                })
+        })
+        .sum();
+    Some((calibrated / count, raw / count))
+}
+
+/// Calibrated temperature at the well's position, interpolated from the
+/// readings of the probes on its tray
+pub fn interpolated_temperature(
+    readings: &[&Model],
+    probes: &std::collections::HashMap<Uuid, crate::tray_configurations::probes::models::Model>,
+    well: &crate::tray_configurations::wells::models::Model,
+) -> Option<Decimal> {
+    crate::experiments::interpolation::interpolate_at(
+        crate::experiments::interpolation::well_position(&well.row_letter, well.column_number),
+        readings.iter().filter_map(|reading| {
+            let probe = probes.get(&reading.probe_id)?;
+            (probe.tray_id == well.tray_id).then(|| {
+                (
+                    (probe.position_x, probe.position_y),
+                    probe.calibrate(reading.temperature),
+                )
+            })
+        }),
+    )
+}
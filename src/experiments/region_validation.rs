@@ -0,0 +1,216 @@
+//! Checks on the regions saved with an experiment.
+//!
+//! Region bounds are 0-based and inclusive, and `tray_id` is the tray's
+//! `order_sequence`. Regions must fit on a tray of the experiment's tray
+//! configuration, and must not share wells unless `ALLOW_OVERLAPPING_REGIONS`
+//! is set. Regions without all four bounds cover no wells and are not checked.
+
+use crate::config::Config;
+use crate::tray_configurations::trays::models as trays;
+use sea_orm::{ColumnTrait, ConnectionTrait, DbErr, EntityTrait, QueryFilter};
+use std::sync::atomic::{AtomicBool, Ordering};
+use uuid::Uuid;
+
+static ALLOW_OVERLAPS: AtomicBool = AtomicBool::new(false);
+
+/// `(row_min, row_max, col_min, col_max)`
+type Rectangle = (i32, i32, i32, i32);
+
+/// Apply the configuration's overlap setting to every later check
+pub fn configure(config: &Config) {
+    ALLOW_OVERLAPS.store(config.allow_overlapping_regions, Ordering::Relaxed);
+}
+
+/// The fields of a region that are checked
+#[derive(Clone, Debug, Default)]
+pub struct RegionBounds {
+    pub name: Option<String>,
+    pub tray_id: Option<i32>,
+    pub row_min: Option<i32>,
+    pub row_max: Option<i32>,
+    pub col_min: Option<i32>,
+    pub col_max: Option<i32>,
+}
+
+impl RegionBounds {
+    /// The region's name, or its position in the list
+    fn label(&self, index: usize) -> String {
+        self.name
+            .as_ref()
+            .map_or_else(|| (index + 1).to_string(), |name| format!("'{name}'"))
+    }
+
+    /// The bounds, when all are set
+    fn rectangle(&self) -> Option<Rectangle> {
+        Some((self.row_min?, self.row_max?, self.col_min?, self.col_max?))
+    }
+}
+
+/// Problems with the regions, given the `(order_sequence, rows, columns)` of
+/// the configuration's trays, or `None` when the experiment has no tray
+/// configuration to check against
+pub fn region_problems(
+    regions: &[RegionBounds],
+    tray_sizes: Option<&[(i32, i32, i32)]>,
+    allow_overlaps: bool,
+) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut placed: Vec<(usize, i32, Rectangle)> = Vec::new();
+
+    for (index, region) in regions.iter().enumerate() {
+        let Some((row_min, row_max, col_min, col_max)) = region.rectangle() else {
+            continue;
+        };
+        let label = region.label(index);
+        if row_min < 0 || col_min < 0 {
+            problems.push(format!("Region {label} has a negative row or column"));
+            continue;
+        }
+        if row_min > row_max || col_min > col_max {
+            problems.push(format!(
+                "Region {label} ends before it starts (rows {row_min}-{row_max}, columns {col_min}-{col_max})"
+            ));
+            continue;
+        }
+        let Some(tray_id) = region.tray_id else {
+            problems.push(format!("Region {label} has no tray"));
+            continue;
+        };
+        if let Some(tray_sizes) = tray_sizes {
+            let Some((_, rows, columns)) = tray_sizes
+                .iter()
+                .find(|(order_sequence, _, _)| *order_sequence == tray_id)
+            else {
+                problems.push(format!(
+                    "Region {label} is on tray {tray_id}, which the tray configuration does not have"
+                ));
+                continue;
+            };
+            if row_max >= *rows || col_max >= *columns {
+                problems.push(format!(
+                    "Region {label} (rows {row_min}-{row_max}, columns {col_min}-{col_max}) does not fit on tray {tray_id} of {rows} rows and {columns} columns"
+                ));
+                continue;
+            }
+        }
+
+        if !allow_overlaps {
+            for (other_index, other_tray_id, other) in &placed {
+                let overlaps = *other_tray_id == tray_id
+                    && row_min <= other.1
+                    && other.0 <= row_max
+                    && col_min <= other.3
+                    && other.2 <= col_max;
+                if overlaps {
+                    problems.push(format!(
+                        "Region {label} overlaps region {} on tray {tray_id}",
+                        regions[*other_index].label(*other_index)
+                    ));
+                }
+            }
+        }
+        placed.push((index, tray_id, (row_min, row_max, col_min, col_max)));
+    }
+    problems
+}
+
+/// Check the regions against the trays of `tray_configuration_id`, returning
+/// every problem found in a single `DbErr::Custom`
+pub async fn validate_regions(
+    db: &impl ConnectionTrait,
+    tray_configuration_id: Option<Uuid>,
+    regions: &[RegionBounds],
+) -> Result<(), DbErr> {
+    if regions.is_empty() {
+        return Ok(());
+    }
+    let tray_sizes = match tray_configuration_id {
+        Some(tray_configuration_id) => Some(
+            trays::Entity::find()
+                .filter(trays::Column::TrayConfigurationId.eq(tray_configuration_id))
+                .all(db)
+                .await?
+                .into_iter()
+                .map(|tray| {
+                    (
+                        tray.order_sequence,
+                        tray.qty_rows.unwrap_or(0),
+                        tray.qty_cols.unwrap_or(0),
+                    )
+                })
+                .collect::<Vec<_>>(),
+        ),
+        None => None,
+    };
+
+    let problems = region_problems(
+        regions,
+        tray_sizes.as_deref(),
+        ALLOW_OVERLAPS.load(Ordering::Relaxed),
+    );
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(DbErr::Custom(problems.join("; ")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn region(name: &str, tray_id: i32, rows: (i32, i32), columns: (i32, i32)) -> RegionBounds {
+        RegionBounds {
+            name: Some(name.to_string()),
+            tray_id: Some(tray_id),
+            row_min: Some(rows.0),
+            row_max: Some(rows.1),
+            col_min: Some(columns.0),
+            col_max: Some(columns.1),
+        }
+    }
+
+    #[test]
+    fn test_region_problems() {
+        let trays = [(1, 8, 12), (2, 8, 12)];
+        let side_by_side = [
+            region("Left", 1, (0, 7), (0, 5)),
+            region("Right", 1, (0, 7), (6, 11)),
+            region("Same place, other tray", 2, (0, 7), (0, 5)),
+        ];
+        assert!(region_problems(&side_by_side, Some(&trays), false).is_empty());
+
+        let problems = region_problems(
+            &[
+                region("Too wide", 1, (0, 7), (0, 12)),
+                region("Reversed", 1, (3, 2), (0, 1)),
+                region("Missing tray", 3, (0, 1), (0, 1)),
+                region("Negative", 1, (-1, 1), (0, 1)),
+            ],
+            Some(&trays),
+            false,
+        );
+        assert_eq!(problems.len(), 4, "{problems:?}");
+        assert!(problems[0].contains("does not fit on tray 1 of 8 rows and 12 columns"));
+        assert!(problems[1].contains("ends before it starts"));
+        assert!(problems[2].contains("tray 3"));
+        assert!(problems[3].contains("negative"));
+
+        let overlapping = [
+            region("Heat", 1, (0, 7), (0, 6)),
+            region("H2O2", 1, (4, 7), (6, 11)),
+        ];
+        assert_eq!(
+            region_problems(&overlapping, Some(&trays), false),
+            vec!["Region 'H2O2' overlaps region 'Heat' on tray 1".to_string()]
+        );
+        assert!(region_problems(&overlapping, Some(&trays), true).is_empty());
+
+        // Without a tray configuration only the bounds themselves are checked
+        assert!(
+            region_problems(&[region("Anywhere", 5, (0, 20), (0, 20))], None, false).is_empty()
+        );
+        // Regions without bounds cover no wells
+        assert!(region_problems(&[RegionBounds::default()], Some(&trays), false).is_empty());
+    }
+}
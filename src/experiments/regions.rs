@@ -0,0 +1,229 @@
+//! Regions of an experiment, managed one at a time.
+//!
+//! Each change is checked with the experiment's other regions against its
+//! trays, as when the experiment is saved with all of them, and drops the
+//! kept results, which the regions decide.
+
+use super::models::{self as experiments, enhance_regions_with_treatment_data};
+use super::region_validation::{RegionBounds, validate_regions};
+use crate::tray_configurations::regions::models::{
+    ActiveModel, Column, Entity, Model, Region, RegionCreate, RegionUpdate,
+};
+use crate::treatments::dilutions::models::region_dilution_factor;
+use crate::treatments::models as treatments;
+use crudcrate::traits::MergeIntoActiveModel;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, DatabaseConnection, DbErr, EntityTrait,
+    IntoActiveModel, Iterable, ModelTrait, QueryFilter, QueryOrder, TransactionTrait, TryIntoModel,
+};
+use uuid::Uuid;
+
+fn bounds(region: &Model) -> RegionBounds {
+    RegionBounds {
+        name: region.name.clone(),
+        tray_id: region.tray_id,
+        row_min: region.row_min,
+        row_max: region.row_max,
+        col_min: region.col_min,
+        col_max: region.col_max,
+    }
+}
+
+async fn find_experiment(
+    db: &DatabaseConnection,
+    experiment_id: Uuid,
+) -> Result<experiments::Model, DbErr> {
+    experiments::Entity::find_by_id(experiment_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Experiment not found".to_string()))
+}
+
+async fn find_region(
+    db: &DatabaseConnection,
+    experiment_id: Uuid,
+    region_id: Uuid,
+) -> Result<Model, DbErr> {
+    Entity::find_by_id(region_id)
+        .filter(Column::ExperimentId.eq(experiment_id))
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Region not found".to_string()))
+}
+
+/// Check the region with the experiment's others, and that a treatment new
+/// to the experiment is not of a rejected sample. Its dilution factor is
+/// taken from its dilution.
+async fn prepare_region(
+    db: &DatabaseConnection,
+    experiment: &experiments::Model,
+    region: &mut Model,
+) -> Result<(), DbErr> {
+    let others = Entity::find()
+        .filter(Column::ExperimentId.eq(experiment.id))
+        .filter(Column::Id.ne(region.id))
+        .all(db)
+        .await?;
+    let mut regions: Vec<RegionBounds> = others.iter().map(bounds).collect();
+    regions.push(bounds(region));
+    validate_regions(db, experiment.tray_configuration_id, &regions).await?;
+
+    // Treatments already in the experiment stay, even of samples rejected since
+    if let Some(treatment_id) = region.treatment_id
+        && !others
+            .iter()
+            .any(|other| other.treatment_id == Some(treatment_id))
+    {
+        crate::samples::qc::ensure_not_rejected(db, vec![treatment_id]).await?;
+    }
+    region.dilution_factor = region_dilution_factor(
+        db,
+        region.treatment_id,
+        region.dilution_id,
+        region.dilution_factor,
+    )
+    .await?;
+    Ok(())
+}
+
+/// Save the region and drop the experiment's kept results
+async fn save_region(db: &DatabaseConnection, region: Model, insert: bool) -> Result<Uuid, DbErr> {
+    let experiment_id = region.experiment_id;
+    let txn = db.begin().await?;
+    let mut active = region.into_active_model();
+    active.last_updated = Set(chrono::Utc::now());
+    let saved = if insert {
+        active.reset_all().insert(&txn).await?
+    } else {
+        active.reset_all().update(&txn).await?
+    };
+    super::summaries::discard_results_summary(&txn, experiment_id).await?;
+    txn.commit().await?;
+    Ok(saved.id)
+}
+
+async fn get_region(db: &DatabaseConnection, region_id: Uuid) -> Result<Region, DbErr> {
+    let region = Entity::find_by_id(region_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Region not found".to_string()))?;
+    enhance_regions_with_treatment_data(vec![region], db)
+        .await?
+        .pop()
+        .ok_or_else(|| DbErr::RecordNotFound("Region not found".to_string()))
+}
+
+/// Regions of the experiment, by tray and position
+pub async fn list_regions(
+    db: &DatabaseConnection,
+    experiment_id: Uuid,
+) -> Result<Vec<Region>, DbErr> {
+    find_experiment(db, experiment_id).await?;
+    let regions = Entity::find()
+        .filter(Column::ExperimentId.eq(experiment_id))
+        .order_by_asc(Column::TrayId)
+        .order_by_asc(Column::RowMin)
+        .order_by_asc(Column::ColMin)
+        .all(db)
+        .await?;
+    enhance_regions_with_treatment_data(regions, db).await
+}
+
+/// Add a region to the experiment. Problems are returned as `DbErr::Custom`.
+pub async fn create_region(
+    db: &DatabaseConnection,
+    experiment_id: Uuid,
+    data: RegionCreate,
+) -> Result<Region, DbErr> {
+    let experiment = find_experiment(db, experiment_id).await?;
+    let mut active: ActiveModel = data.into();
+    active.id = Set(Uuid::new_v4());
+    active.experiment_id = Set(experiment_id);
+    let mut region = active.try_into_model()?;
+    prepare_region(db, &experiment, &mut region).await?;
+    let id = save_region(db, region, true).await?;
+    get_region(db, id).await
+}
+
+/// Change a region of the experiment. Problems are returned as
+/// `DbErr::Custom`.
+pub async fn update_region(
+    db: &DatabaseConnection,
+    experiment_id: Uuid,
+    region_id: Uuid,
+    data: RegionUpdate,
+) -> Result<Region, DbErr> {
+    let experiment = find_experiment(db, experiment_id).await?;
+    let existing = find_region(db, experiment_id, region_id).await?;
+    let mut active = data.merge_into_activemodel(existing.clone().into_active_model())?;
+    // Fields left out of the change keep their stored values
+    for column in Column::iter() {
+        if active.is_not_set(column) {
+            active.set(column, existing.get(column));
+        }
+    }
+    let mut region = active.try_into_model()?;
+    prepare_region(db, &experiment, &mut region).await?;
+    save_region(db, region, false).await?;
+    get_region(db, region_id).await
+}
+
+/// Remove a region of the experiment
+pub async fn delete_region(
+    db: &DatabaseConnection,
+    experiment_id: Uuid,
+    region_id: Uuid,
+) -> Result<(), DbErr> {
+    let region = find_region(db, experiment_id, region_id).await?;
+    let txn = db.begin().await?;
+    Entity::delete_by_id(region.id).exec(&txn).await?;
+    super::summaries::discard_results_summary(&txn, experiment_id).await?;
+    txn.commit().await
+}
+
+/// Regions holding any of the treatments, by experiment
+pub async fn regions_of_treatments(
+    db: &DatabaseConnection,
+    treatment_ids: Vec<Uuid>,
+) -> Result<Vec<Region>, DbErr> {
+    let regions = Entity::find()
+        .filter(Column::TreatmentId.is_in(treatment_ids))
+        .order_by_asc(Column::ExperimentId)
+        .order_by_asc(Column::TrayId)
+        .order_by_asc(Column::RowMin)
+        .order_by_asc(Column::ColMin)
+        .all(db)
+        .await?;
+    enhance_regions_with_treatment_data(regions, db).await
+}
+
+/// Regions holding the treatment
+pub async fn treatment_regions(
+    db: &DatabaseConnection,
+    treatment_id: Uuid,
+) -> Result<Vec<Region>, DbErr> {
+    treatments::Entity::find_by_id(treatment_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Treatment not found".to_string()))?;
+    regions_of_treatments(db, vec![treatment_id]).await
+}
+
+/// Regions holding any treatment of the sample
+pub async fn sample_regions(
+    db: &DatabaseConnection,
+    sample_id: Uuid,
+) -> Result<Vec<Region>, DbErr> {
+    crate::samples::models::Entity::find_by_id(sample_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Sample not found".to_string()))?;
+    let treatment_ids = treatments::Entity::find()
+        .filter(treatments::Column::SampleId.eq(sample_id))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|treatment| treatment.id)
+        .collect();
+    regions_of_treatments(db, treatment_ids).await
+}
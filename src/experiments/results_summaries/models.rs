@@ -0,0 +1,39 @@
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use uuid::Uuid;
+
+/// Results of an experiment as built when its data was last processed or
+/// the results recomputed
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "experiment_results_summaries")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    #[sea_orm(unique)]
+    pub experiment_id: Uuid,
+    /// Processing job the results were built after; none when recomputed
+    pub job_id: Option<Uuid>,
+    #[sea_orm(column_type = "JsonBinary")]
+    pub results: Json,
+    pub computed_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "crate::experiments::models::Entity",
+        from = "Column::ExperimentId",
+        to = "crate::experiments::models::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Experiments,
+}
+
+impl Related<crate::experiments::models::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Experiments.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -2,6 +2,7 @@ use super::models::{
     ExperimentResultsResponse, ExperimentResultsSummaryCompact, TemperatureDataWithProbes,
     TrayResultsSummary, TrayWellSummary,
 };
+use crate::assets::services::{ArchiveEntry, ArchiveSource};
 use crate::{
     experiments::models as experiments,
     experiments::phase_transitions::models as well_phase_transitions,
@@ -13,7 +14,7 @@ use crate::{
 use crate::{samples::models as samples, treatments::models as treatments};
 use chrono::{DateTime, Utc};
 use rust_decimal::Decimal;
-use sea_orm::{ConnectionTrait, EntityTrait, QueryOrder, entity::prelude::*};
+use sea_orm::{ConnectionTrait, EntityTrait, QueryOrder, QuerySelect, entity::prelude::*};
 use uuid::Uuid;
 
 // Constants for phase states
@@ -23,9 +24,10 @@ const PHASE_FROZEN: i32 = 1;
 // Parameter struct to reduce argument count in build_well_summaries
 struct WellSummaryContext<'a> {
     experiment_wells: &'a [wells::Model],
-    phase_transitions_data: &'a [(well_phase_transitions::Model, Option<wells::Model>)],
+    transitions_by_well: &'a std::collections::HashMap<Uuid, Vec<well_phase_transitions::Model>>,
     temp_readings_map: &'a std::collections::HashMap<Uuid, TemperatureDataWithProbes>,
     filename_to_asset_id: &'a std::collections::HashMap<String, Uuid>,
+    reading_to_asset_id: &'a std::collections::HashMap<Uuid, Uuid>,
     experiment_regions: &'a [regions::Model],
     treatment_map: &'a std::collections::HashMap<
         Uuid,
@@ -35,6 +37,7 @@ struct WellSummaryContext<'a> {
         ),
     >,
     tray_map: &'a std::collections::HashMap<Uuid, trays::Model>,
+    excluded_wells: &'a std::collections::HashMap<Uuid, Option<String>>,
 }
 
 // Helper function to convert row letter to 0-based index
@@ -45,11 +48,76 @@ fn row_letter_to_index(row_letter: &str) -> i32 {
         .map_or(0, |c| c as i32 - 'A' as i32)
 }
 
+/// A reading with its probes' temperatures, calibrated and as logged, in
+/// the order of the experiment's probes
+pub fn temperature_data_with_probes(
+    temp_reading: &temperature_readings::Model,
+    readings_by_probe_id: &std::collections::HashMap<Uuid, Decimal>,
+    all_experiment_probes: &[probes::Model],
+) -> TemperatureDataWithProbes {
+    // Create complete probe readings array including ALL probes from tray configuration
+    let mut complete_probe_readings = Vec::new();
+    let mut temperature_values = Vec::new();
+    let mut raw_temperature_values = Vec::new();
+
+    for probe in all_experiment_probes {
+        let temperature_value = readings_by_probe_id.get(&probe.id).copied();
+
+        // Only include probe readings that have actual temperature data
+        // This avoids showing misleading "0" temperatures for probes without readings
+        if let Some(raw_temp) = temperature_value {
+            let actual_temp = probe.calibrate(raw_temp);
+            // Create probe temperature reading with metadata (rounded to 3 decimal places)
+            let probe_temp_reading = super::models::ProbeTemperatureReadingWithMetadata {
+                id: uuid::Uuid::new_v4(), // Placeholder ID for API response
+                temperature_reading_id: temp_reading.id,
+                temperature: actual_temp.round_dp(3), // Round to 3 decimal places
+                raw_temperature: raw_temp.round_dp(3),
+                created_at: temp_reading.created_at,
+                // Probe metadata
+                probe_id: probe.id,
+                probe_name: probe.name.clone(),
+                probe_data_column_index: probe.data_column_index,
+                probe_tray_id: probe.tray_id,
+                probe_position_x: probe.position_x,
+                probe_position_y: probe.position_y,
+            };
+
+            complete_probe_readings.push(probe_temp_reading);
+            temperature_values.push(actual_temp);
+            raw_temperature_values.push(raw_temp);
+        }
+    }
+
+    // Calculate average temperature from actual probe readings only (rounded to 3 decimal places)
+    let average = |values: &[Decimal]| {
+        if values.is_empty() {
+            None
+        } else {
+            let sum: Decimal = values.iter().sum();
+            // Round to 3 decimal places
+            Some((sum / Decimal::from(values.len())).round_dp(3))
+        }
+    };
+
+    // Create flattened temperature data with ALL probe readings from tray configuration
+    TemperatureDataWithProbes {
+        id: temp_reading.id,
+        experiment_id: temp_reading.experiment_id,
+        timestamp: temp_reading.timestamp,
+        image_filename: temp_reading.image_filename.clone(),
+        average: average(&temperature_values),
+        raw_average: average(&raw_temperature_values),
+        probe_readings: complete_probe_readings,
+    }
+}
+
 // Helper function to load temperature readings with individual probe data (optimized to only load needed readings)
 #[allow(clippy::too_many_lines)] // Complex data loading logic requires extensive processing
 async fn load_individual_temperature_data(
     experiment_id: Uuid,
     phase_transition_temp_ids: &std::collections::HashSet<Uuid>,
+    all_experiment_probes: &[probes::Model],
     db: &impl ConnectionTrait,
 ) -> Result<
     (
@@ -63,37 +131,37 @@ async fn load_individual_temperature_data(
     // Only load temperature readings that we actually need (for phase transitions)
     let temp_reading_ids_vec: Vec<Uuid> = phase_transition_temp_ids.iter().copied().collect();
 
-    // Get total count of temperature readings for summary stats
-    let total_time_points = {
-        let count = temperature_readings::Entity::find()
-            .filter(temperature_readings::Column::ExperimentId.eq(experiment_id))
-            .count(db)
-            .await?;
-        usize::try_from(count)
-            .map_err(|_| DbErr::Custom("Temperature readings count exceeds maximum".to_string()))?
-    };
-
-    // Get first and last timestamps for summary (lightweight query)
-    let first_temp_reading = temperature_readings::Entity::find()
-        .filter(temperature_readings::Column::ExperimentId.eq(experiment_id))
-        .order_by_asc(temperature_readings::Column::Timestamp)
-        .one(db)
-        .await?;
-
-    let last_temp_reading = temperature_readings::Entity::find()
+    // Count and first and last timestamps of the readings for summary stats,
+    // in one query
+    let (count, first_timestamp, last_timestamp): (
+        i64,
+        Option<DateTime<Utc>>,
+        Option<DateTime<Utc>>,
+    ) = temperature_readings::Entity::find()
+        .select_only()
+        .column_as(Expr::col(temperature_readings::Column::Id).count(), "count")
+        .column_as(
+            Expr::col(temperature_readings::Column::Timestamp).min(),
+            "first",
+        )
+        .column_as(
+            Expr::col(temperature_readings::Column::Timestamp).max(),
+            "last",
+        )
         .filter(temperature_readings::Column::ExperimentId.eq(experiment_id))
-        .order_by_desc(temperature_readings::Column::Timestamp)
+        .into_tuple()
         .one(db)
-        .await?;
-
-    let first_timestamp = first_temp_reading.map(|tr| tr.timestamp.with_timezone(&Utc));
-    let last_timestamp = last_temp_reading.map(|tr| tr.timestamp.with_timezone(&Utc));
+        .await?
+        .unwrap_or_default();
+    let total_time_points = usize::try_from(count)
+        .map_err(|_| DbErr::Custom("Temperature readings count exceeds maximum".to_string()))?;
 
     // Only load the specific temperature readings we need (192 instead of 6,786)
     let temp_readings_data = if temp_reading_ids_vec.is_empty() {
         vec![]
     } else {
         temperature_readings::Entity::find()
+            .filter(temperature_readings::Column::ExperimentId.eq(experiment_id))
             .filter(temperature_readings::Column::Id.is_in(temp_reading_ids_vec))
             .order_by_asc(temperature_readings::Column::Timestamp)
             .all(db)
@@ -109,31 +177,6 @@ async fn load_individual_temperature_data(
         ));
     }
 
-    // Get the experiment to find its tray configuration
-    let experiment = experiments::Entity::find_by_id(experiment_id)
-        .one(db)
-        .await?
-        .ok_or_else(|| DbErr::RecordNotFound("Experiment not found".to_string()))?;
-
-    // Load all probes from the tray configuration for this experiment
-    let all_experiment_probes = if let Some(tray_config_id) = experiment.tray_configuration_id {
-        // Get all trays for this configuration
-        let trays = trays::Entity::find()
-            .filter(trays::Column::TrayConfigurationId.eq(tray_config_id))
-            .all(db)
-            .await?;
-
-        let tray_ids: Vec<Uuid> = trays.iter().map(|t| t.id).collect();
-
-        // Get all probes from all trays in this configuration
-        probes::Entity::find()
-            .filter(probes::Column::TrayId.is_in(tray_ids))
-            .all(db)
-            .await?
-    } else {
-        Vec::new()
-    };
-
     // Get individual probe readings for all temperature readings
     let temp_reading_ids: Vec<Uuid> = temp_readings_data.iter().map(|tr| tr.id).collect();
 
@@ -174,55 +217,8 @@ async fn load_individual_temperature_data(
             }
         }
 
-        // Create complete probe readings array including ALL probes from tray configuration
-        let mut complete_probe_readings = Vec::new();
-        let mut temperature_values = Vec::new();
-
-        for probe in &all_experiment_probes {
-            let temperature_value = readings_by_probe_id.get(&probe.id).copied();
-
-            // Only include probe readings that have actual temperature data
-            // This avoids showing misleading "0" temperatures for probes without readings
-            if let Some(actual_temp) = temperature_value {
-                // Create probe temperature reading with metadata (rounded to 3 decimal places)
-                let probe_temp_reading = super::models::ProbeTemperatureReadingWithMetadata {
-                    id: uuid::Uuid::new_v4(), // Placeholder ID for API response
-                    temperature_reading_id: temp_reading.id,
-                    temperature: actual_temp.round_dp(3), // Round to 3 decimal places
-                    created_at: temp_reading.created_at,
-                    // Probe metadata
-                    probe_id: probe.id,
-                    probe_name: probe.name.clone(),
-                    probe_data_column_index: probe.data_column_index,
-                    probe_position_x: probe.position_x,
-                    probe_position_y: probe.position_y,
-                };
-
-                complete_probe_readings.push(probe_temp_reading);
-                temperature_values.push(actual_temp);
-            }
-        }
-
-        // Calculate average temperature from actual probe readings only (rounded to 3 decimal places)
-        let temperature_average = if temperature_values.is_empty() {
-            None
-        } else {
-            let sum: Decimal = temperature_values.iter().sum();
-            let average = sum / Decimal::from(temperature_values.len());
-            // Round to 3 decimal places
-            Some(average.round_dp(3))
-        };
-
-        // Create flattened temperature data with ALL probe readings from tray configuration
-        let temp_data_with_probes = TemperatureDataWithProbes {
-            id: temp_reading.id,
-            experiment_id: temp_reading.experiment_id,
-            timestamp: temp_reading.timestamp,
-            image_filename: temp_reading.image_filename.clone(),
-            average: temperature_average,
-            probe_readings: complete_probe_readings,
-        };
-
+        let temp_data_with_probes =
+            temperature_data_with_probes(temp_reading, &readings_by_probe_id, all_experiment_probes);
         temp_data_map.insert(temp_reading.id, temp_data_with_probes);
     }
 
@@ -236,11 +232,18 @@ async fn load_individual_temperature_data(
     ))
 }
 
-// Helper function to load experiment assets and create filename mapping
+// Helper function to load experiment assets and map them by filename and by
+// the temperature reading they are linked to
 async fn load_experiment_assets(
     experiment_id: Uuid,
     db: &impl ConnectionTrait,
-) -> Result<std::collections::HashMap<String, Uuid>, DbErr> {
+) -> Result<
+    (
+        std::collections::HashMap<String, Uuid>,
+        std::collections::HashMap<Uuid, Uuid>,
+    ),
+    DbErr,
+> {
     let experiment_assets = crate::assets::models::Entity::find()
         .filter(crate::assets::models::Column::ExperimentId.eq(experiment_id))
         .filter(crate::assets::models::Column::Type.eq("image"))
@@ -267,101 +270,123 @@ async fn load_experiment_assets(
         })
         .collect();
 
-    Ok(filename_to_asset_id)
+    let reading_to_asset_id = experiment_assets
+        .iter()
+        .filter_map(|asset| Some((asset.temperature_reading_id?, asset.id)))
+        .collect();
+
+    Ok((filename_to_asset_id, reading_to_asset_id))
+}
+
+// Image of a well at its freeze: images linked to the reading win over a
+// filename match
+fn freeze_image_asset_id(
+    reading_id: Option<Uuid>,
+    image_filename: Option<&str>,
+    filename_to_asset_id: &std::collections::HashMap<String, Uuid>,
+    reading_to_asset_id: &std::collections::HashMap<Uuid, Uuid>,
+) -> Option<Uuid> {
+    reading_id
+        .and_then(|reading_id| reading_to_asset_id.get(&reading_id))
+        .or_else(|| image_filename.and_then(|filename| filename_to_asset_id.get(filename)))
+        .copied()
 }
 
-// Helper function to process phase transitions and determine well states
+/// Point each well of results built earlier at the experiment's images as
+/// they are now, as images are uploaded and removed after processing
+pub async fn link_freeze_images(
+    experiment_id: Uuid,
+    results: &mut ExperimentResultsResponse,
+    db: &impl ConnectionTrait,
+) -> Result<(), DbErr> {
+    let (filename_to_asset_id, reading_to_asset_id) =
+        load_experiment_assets(experiment_id, db).await?;
+    for well in results.trays.iter_mut().flat_map(|tray| &mut tray.wells) {
+        well.image_asset_id = well.temperatures.as_ref().and_then(|temperatures| {
+            freeze_image_asset_id(
+                Some(temperatures.id),
+                temperatures.image_filename.as_deref(),
+                &filename_to_asset_id,
+                &reading_to_asset_id,
+            )
+        });
+    }
+    Ok(())
+}
+
+// Helper function to load phase transitions, grouped by well
 async fn process_phase_transitions(
     experiment_id: Uuid,
     db: &impl ConnectionTrait,
-) -> Result<
-    (
-        Vec<(well_phase_transitions::Model, Option<wells::Model>)>,
-        std::collections::HashSet<Uuid>,
-    ),
-    DbErr,
-> {
+) -> Result<std::collections::HashMap<Uuid, Vec<well_phase_transitions::Model>>, DbErr> {
     let phase_transitions_data = well_phase_transitions::Entity::find()
         .filter(well_phase_transitions::Column::ExperimentId.eq(experiment_id))
         .find_also_related(wells::Entity)
         .all(db)
         .await?;
 
-    // Group transitions by well to determine final states
-    let mut well_final_states = std::collections::HashMap::new();
-    let mut wells_with_transitions = std::collections::HashSet::new();
-
-    for (transition, well_opt) in &phase_transitions_data {
+    // Each well's transitions, in the order they were read
+    let mut transitions_by_well: std::collections::HashMap<Uuid, Vec<_>> =
+        std::collections::HashMap::new();
+    for (transition, well_opt) in phase_transitions_data {
         if let Some(well) = well_opt {
-            wells_with_transitions.insert(well.id);
-            // Update the final state for this well (later transitions override earlier ones)
-            well_final_states.insert(well.id, transition.new_state);
+            transitions_by_well
+                .entry(well.id)
+                .or_default()
+                .push(transition);
         }
     }
 
-    Ok((phase_transitions_data, wells_with_transitions))
+    Ok(transitions_by_well)
+}
+
+// Trays, wells and probes of the tray configuration an experiment uses,
+// loaded once for all wells
+#[derive(Default)]
+struct ExperimentLayout {
+    wells: Vec<wells::Model>,
+    tray_map: std::collections::HashMap<Uuid, trays::Model>,
+    probes: Vec<probes::Model>,
 }
 
-// Helper function to load experiment wells and trays
-async fn load_experiment_wells_and_trays(
+// Helper function to load experiment wells, trays and probes
+async fn load_experiment_layout(
     experiment_id: Uuid,
-    _wells_with_transitions: &std::collections::HashSet<Uuid>,
-    _phase_transitions_data: &[(well_phase_transitions::Model, Option<wells::Model>)],
     db: &impl ConnectionTrait,
-) -> Result<
-    (
-        Vec<wells::Model>,
-        std::collections::HashMap<Uuid, trays::Model>,
-    ),
-    DbErr,
-> {
-    // Always load ALL wells for this experiment from tray configuration
-    // This ensures we show both wells that froze and wells that never froze (important scientific data)
-    let experiment = experiments::Entity::find_by_id(experiment_id)
+) -> Result<ExperimentLayout, DbErr> {
+    let tray_config_id: Option<Uuid> = experiments::Entity::find_by_id(experiment_id)
+        .select_only()
+        .column(experiments::Column::TrayConfigurationId)
+        .into_tuple::<Option<Uuid>>()
         .one(db)
-        .await?;
-
-    let experiment_wells = if let Some(exp) = experiment {
-        if let Some(tray_config_id) = exp.tray_configuration_id {
-            let tray_list = trays::Entity::find()
-                .filter(trays::Column::TrayConfigurationId.eq(tray_config_id))
-                .all(db)
-                .await?;
-
-            let tray_ids: Vec<Uuid> = tray_list.into_iter().map(|tray| tray.id).collect();
-
-            wells::Entity::find()
-                .filter(wells::Column::TrayId.is_in(tray_ids))
-                .all(db)
-                .await?
-        } else {
-            vec![]
-        }
-    } else {
-        vec![]
+        .await?
+        .flatten();
+    let Some(tray_config_id) = tray_config_id else {
+        return Ok(ExperimentLayout::default());
     };
 
-    // Get all trays for this experiment's wells (use actual experiment wells, not just transition wells)
-    let tray_ids: Vec<Uuid> = experiment_wells
-        .iter()
-        .map(|w| w.tray_id)
-        .collect::<std::collections::HashSet<_>>()
-        .into_iter()
-        .collect();
-
-    let trays_data = if tray_ids.is_empty() {
-        vec![]
-    } else {
-        trays::Entity::find()
-            .filter(trays::Column::Id.is_in(tray_ids))
-            .all(db)
-            .await?
-    };
+    let tray_list = trays::Entity::find()
+        .filter(trays::Column::TrayConfigurationId.eq(tray_config_id))
+        .all(db)
+        .await?;
+    let tray_ids: Vec<Uuid> = tray_list.iter().map(|tray| tray.id).collect();
 
-    let tray_map: std::collections::HashMap<Uuid, trays::Model> =
-        trays_data.iter().map(|t| (t.id, t.clone())).collect();
+    // Always load ALL wells of the tray configuration, so wells that never
+    // froze are shown too (important scientific data)
+    let wells = wells::Entity::find()
+        .filter(wells::Column::TrayId.is_in(tray_ids.clone()))
+        .all(db)
+        .await?;
+    let probes = probes::Entity::find()
+        .filter(probes::Column::TrayId.is_in(tray_ids))
+        .all(db)
+        .await?;
 
-    Ok((experiment_wells, tray_map))
+    Ok(ExperimentLayout {
+        wells,
+        tray_map: tray_list.into_iter().map(|tray| (tray.id, tray)).collect(),
+        probes,
+    })
 }
 
 // Helper function to load treatment and sample data with optimized joins
@@ -416,57 +441,77 @@ pub async fn build_tray_centric_results(
     db: &impl ConnectionTrait,
 ) -> Result<Option<ExperimentResultsResponse>, DbErr> {
     // First load phase transitions to get the temperature reading IDs we actually need
-    let (phase_transitions_data, wells_with_transitions) =
-        process_phase_transitions(experiment_id, db).await?;
+    let transitions_by_well = process_phase_transitions(experiment_id, db).await?;
 
     // Extract temperature reading IDs from phase transitions (only ~192 instead of 6,786)
-    let phase_transition_temp_ids: std::collections::HashSet<Uuid> = phase_transitions_data
-        .iter()
-        .map(|(transition, _)| transition.temperature_reading_id)
+    let phase_transition_temp_ids: std::collections::HashSet<Uuid> = transitions_by_well
+        .values()
+        .flatten()
+        .map(|transition| transition.temperature_reading_id)
         .collect();
 
+    let layout = load_experiment_layout(experiment_id, db).await?;
+
     // Load temperature data only for the readings we actually need
     let (temp_readings_map, first_timestamp, last_timestamp, total_time_points) =
-        load_individual_temperature_data(experiment_id, &phase_transition_temp_ids, db).await?;
+        load_individual_temperature_data(
+            experiment_id,
+            &phase_transition_temp_ids,
+            &layout.probes,
+            db,
+        )
+        .await?;
 
-    let filename_to_asset_id = load_experiment_assets(experiment_id, db).await?;
+    let (filename_to_asset_id, reading_to_asset_id) =
+        load_experiment_assets(experiment_id, db).await?;
 
     let experiment_regions = regions::Entity::find()
         .filter(regions::Column::ExperimentId.eq(experiment_id))
         .all(db)
         .await?;
 
-    // Phase transitions already loaded above for optimization
-
-    let (experiment_wells, tray_map) = load_experiment_wells_and_trays(
-        experiment_id,
-        &wells_with_transitions,
-        &phase_transitions_data,
-        db,
-    )
-    .await?;
-
     let treatment_map = load_treatment_and_sample_data(&experiment_regions, db).await?;
+    let excluded_wells = super::exclusions::excluded_well_reasons(db, experiment_id).await?;
 
     // Create context for shared data
     let context = WellSummaryContext {
-        experiment_wells: &experiment_wells,
-        phase_transitions_data: &phase_transitions_data,
+        experiment_wells: &layout.wells,
+        transitions_by_well: &transitions_by_well,
         temp_readings_map: &temp_readings_map,
         filename_to_asset_id: &filename_to_asset_id,
+        reading_to_asset_id: &reading_to_asset_id,
         experiment_regions: &experiment_regions,
         treatment_map: &treatment_map,
-        tray_map: &tray_map,
+        tray_map: &layout.tray_map,
+        excluded_wells: &excluded_wells,
     };
 
     // Build tray-centric results using same context as well summaries
     let tray_results = build_tray_summaries(&context);
 
+    // Excluded wells keep their data but do not count towards the fraction
+    let included_wells = tray_results
+        .iter()
+        .flat_map(|tray| &tray.wells)
+        .filter(|well| !well.excluded);
+    let (included_count, frozen_wells) = included_wells.fold((0, 0), |(total, frozen), well| {
+        (
+            total + 1,
+            frozen + usize::from(well.first_phase_change_time.is_some()),
+        )
+    });
+    let frozen_fraction = (included_count > 0)
+        .then(|| (Decimal::from(frozen_wells) / Decimal::from(included_count)).round_dp(4));
+
     // Create compact summary
     let summary = ExperimentResultsSummaryCompact {
         total_time_points,
         first_timestamp,
         last_timestamp,
+        total_wells: layout.wells.len(),
+        excluded_wells: layout.wells.len() - included_count,
+        frozen_wells,
+        frozen_fraction,
     };
 
     Ok(Some(ExperimentResultsResponse {
@@ -491,6 +536,7 @@ fn create_tray_well_hashmap(
     tray_well_map
 }
 
+#[allow(clippy::too_many_lines)] // One pass builds every field of the well summary
 fn build_tray_summaries(context: &WellSummaryContext) -> Vec<TrayResultsSummary> {
     // Group wells by tray
     let tray_wells = create_tray_well_hashmap(context);
@@ -506,17 +552,10 @@ fn build_tray_summaries(context: &WellSummaryContext) -> Vec<TrayResultsSummary>
             let coordinate = format!("{}{}", well.row_letter, well.column_number);
 
             // Get phase transitions for this well
-            let well_transitions: Vec<&well_phase_transitions::Model> = context
-                .phase_transitions_data
-                .iter()
-                .filter_map(|(transition, well_opt)| {
-                    if well_opt.as_ref().map(|w| w.id) == Some(well.id) {
-                        Some(transition)
-                    } else {
-                        None
-                    }
-                })
-                .collect();
+            let well_transitions: &[well_phase_transitions::Model] = context
+                .transitions_by_well
+                .get(&well.id)
+                .map_or(&[], Vec::as_slice);
 
             // Calculate first phase change time (first 0→1 transition)
             let first_phase_change_transition = well_transitions.iter().find(|transition| {
@@ -533,12 +572,29 @@ fn build_tray_summaries(context: &WellSummaryContext) -> Vec<TrayResultsSummary>
                         .get(&transition.temperature_reading_id)
                 })
                 .cloned();
-            let image_filename: Option<String> =
-                temperatures.as_ref().and_then(|t| t.image_filename.clone());
-            let image_asset_id = match image_filename {
-                Some(filename) => context.filename_to_asset_id.get(&filename).copied(),
-                None => None,
-            };
+            let image_asset_id = freeze_image_asset_id(
+                first_phase_change_transition.map(|transition| transition.temperature_reading_id),
+                temperatures
+                    .as_ref()
+                    .and_then(|t| t.image_filename.as_deref()),
+                context.filename_to_asset_id,
+                context.reading_to_asset_id,
+            );
+            let interpolated_temperature = temperatures.as_ref().and_then(|temperatures| {
+                super::interpolation::interpolate_at(
+                    super::interpolation::well_position(&well.row_letter, well.column_number),
+                    temperatures
+                        .probe_readings
+                        .iter()
+                        .filter(|reading| reading.probe_tray_id == well.tray_id)
+                        .map(|reading| {
+                            (
+                                (reading.probe_position_x, reading.probe_position_y),
+                                reading.temperature,
+                            )
+                        }),
+                )
+            });
             // Simple state mapping
 
             // Find region for this well to get sample/treatment info
@@ -591,8 +647,11 @@ fn build_tray_summaries(context: &WellSummaryContext) -> Vec<TrayResultsSummary>
                 dilution_factor: region.and_then(|r| r.dilution_factor),
                 first_phase_change_time,
                 temperatures,
+                interpolated_temperature,
                 total_phase_changes: well_transitions.len(),
                 image_asset_id,
+                excluded: context.excluded_wells.contains_key(&well.id),
+                exclusion_reason: context.excluded_wells.get(&well.id).cloned().flatten(),
             };
 
             tray_well_summaries.push(tray_well_summary);
@@ -610,3 +669,275 @@ fn build_tray_summaries(context: &WellSummaryContext) -> Vec<TrayResultsSummary>
     tray_results.sort_by(|a, b| a.tray_name.cmp(&b.tray_name));
     tray_results
 }
+
+// Quote a CSV field if it contains a delimiter, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn csv_line(fields: &[String]) -> String {
+    let mut line = fields
+        .iter()
+        .map(|f| csv_field(f))
+        .collect::<Vec<_>>()
+        .join(",");
+    line.push('\n');
+    line
+}
+
+fn opt_to_string<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+// Path of an uploaded asset inside the experiment archive
+fn archive_path_for_asset(asset: &crate::assets::models::Model) -> String {
+    if asset.r#type == "image" {
+        format!("images/{}", asset.original_filename)
+    } else {
+        format!("uploads/{}", asset.original_filename)
+    }
+}
+
+// Wide-format temperature table: one row per reading, one column per probe
+async fn build_temperatures_csv(
+    experiment_id: Uuid,
+    db: &impl ConnectionTrait,
+) -> Result<String, DbErr> {
+    let readings = temperature_readings::Entity::find()
+        .filter(temperature_readings::Column::ExperimentId.eq(experiment_id))
+        .order_by_asc(temperature_readings::Column::Timestamp)
+        .all(db)
+        .await?;
+
+    let probe_readings = probe_temperature_readings::Entity::find()
+        .inner_join(temperature_readings::Entity)
+        .filter(temperature_readings::Column::ExperimentId.eq(experiment_id))
+        .all(db)
+        .await?;
+
+    let probe_ids: std::collections::HashSet<Uuid> =
+        probe_readings.iter().map(|p| p.probe_id).collect();
+    let mut experiment_probes = if probe_ids.is_empty() {
+        vec![]
+    } else {
+        probes::Entity::find()
+            .filter(probes::Column::Id.is_in(probe_ids))
+            .all(db)
+            .await?
+    };
+    experiment_probes.sort_by_key(|p| (p.data_column_index, p.name.clone()));
+
+    let mut values: std::collections::HashMap<(Uuid, Uuid), Decimal> =
+        std::collections::HashMap::new();
+    for reading in probe_readings {
+        values.insert(
+            (reading.temperature_reading_id, reading.probe_id),
+            reading.temperature,
+        );
+    }
+
+    let mut header = vec!["timestamp".to_string(), "image_filename".to_string()];
+    header.extend(experiment_probes.iter().map(|p| p.name.clone()));
+    let mut csv = csv_line(&header);
+
+    for reading in readings {
+        let mut row = vec![
+            reading.timestamp.to_rfc3339(),
+            reading.image_filename.clone().unwrap_or_default(),
+        ];
+        row.extend(
+            experiment_probes
+                .iter()
+                .map(|p| opt_to_string(values.get(&(reading.id, p.id)))),
+        );
+        csv.push_str(&csv_line(&row));
+    }
+
+    Ok(csv)
+}
+
+async fn build_phase_transitions_csv(
+    experiment_id: Uuid,
+    db: &impl ConnectionTrait,
+) -> Result<String, DbErr> {
+    let transitions = well_phase_transitions::Entity::find()
+        .filter(well_phase_transitions::Column::ExperimentId.eq(experiment_id))
+        .find_also_related(wells::Entity)
+        .order_by_asc(well_phase_transitions::Column::Timestamp)
+        .all(db)
+        .await?;
+
+    let tray_ids: std::collections::HashSet<Uuid> = transitions
+        .iter()
+        .filter_map(|(_, well)| well.as_ref().map(|w| w.tray_id))
+        .collect();
+    let tray_names: std::collections::HashMap<Uuid, String> = if tray_ids.is_empty() {
+        std::collections::HashMap::new()
+    } else {
+        trays::Entity::find()
+            .filter(trays::Column::Id.is_in(tray_ids))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|t| (t.id, t.name.unwrap_or_else(|| t.order_sequence.to_string())))
+            .collect()
+    };
+
+    let mut csv = csv_line(&[
+        "timestamp".to_string(),
+        "tray".to_string(),
+        "well".to_string(),
+        "previous_state".to_string(),
+        "new_state".to_string(),
+        "temperature_reading_id".to_string(),
+    ]);
+
+    for (transition, well) in transitions {
+        let (tray, coordinate) = well.map_or((String::new(), String::new()), |w| {
+            (
+                tray_names.get(&w.tray_id).cloned().unwrap_or_default(),
+                format!("{}{}", w.row_letter, w.column_number),
+            )
+        });
+        csv.push_str(&csv_line(&[
+            transition.timestamp.to_rfc3339(),
+            tray,
+            coordinate,
+            transition.previous_state.to_string(),
+            transition.new_state.to_string(),
+            transition.temperature_reading_id.to_string(),
+        ]));
+    }
+
+    Ok(csv)
+}
+
+// Per-well freezing summary taken from the tray-centric results
+fn build_well_results_csv(results: Option<&ExperimentResultsResponse>) -> String {
+    let mut csv = csv_line(&[
+        "tray".to_string(),
+        "well".to_string(),
+        "sample".to_string(),
+        "treatment".to_string(),
+        "dilution_factor".to_string(),
+        "first_phase_change_time".to_string(),
+        "freezing_temperature_avg".to_string(),
+        "freezing_temperature_raw_avg".to_string(),
+        "freezing_temperature_interpolated".to_string(),
+        "total_phase_changes".to_string(),
+        "excluded".to_string(),
+        "exclusion_reason".to_string(),
+    ]);
+
+    for tray in results.map(|r| r.trays.as_slice()).unwrap_or_default() {
+        for well in &tray.wells {
+            let treatment = well
+                .treatment
+                .as_ref()
+                .and_then(|t| serde_json::to_value(&t.name).ok())
+                .and_then(|v| v.as_str().map(str::to_string))
+                .unwrap_or_default();
+            csv.push_str(&csv_line(&[
+                tray.tray_name.clone().unwrap_or_default(),
+                well.coordinate.clone(),
+                well.sample
+                    .as_ref()
+                    .map(|s| s.name.clone())
+                    .unwrap_or_default(),
+                treatment,
+                opt_to_string(well.dilution_factor),
+                opt_to_string(well.first_phase_change_time.map(|t| t.to_rfc3339())),
+                opt_to_string(well.temperatures.as_ref().and_then(|t| t.average)),
+                opt_to_string(well.temperatures.as_ref().and_then(|t| t.raw_average)),
+                opt_to_string(well.interpolated_temperature),
+                well.total_phase_changes.to_string(),
+                well.excluded.to_string(),
+                well.exclusion_reason.clone().unwrap_or_default(),
+            ]));
+        }
+    }
+
+    csv
+}
+
+/// Collect every file that makes up an offline experiment archive.
+///
+/// The archive contains the experiment metadata (`metadata.json`), the
+/// uploaded files (camera images under `images/`, everything else under
+/// `uploads/`) and derived CSVs under `derived/`.
+pub async fn build_experiment_archive_entries(
+    experiment_id: Uuid,
+    db: &DatabaseConnection,
+) -> Result<Vec<ArchiveEntry>, DbErr> {
+    let experiment = super::models::get_one_experiment(db, experiment_id).await?;
+
+    let assets = crate::assets::models::Entity::find()
+        .filter(crate::assets::models::Column::ExperimentId.eq(experiment_id))
+        .filter(crate::assets::models::Column::IsDeleted.eq(false))
+        .order_by_asc(crate::assets::models::Column::OriginalFilename)
+        .all(db)
+        .await?;
+
+    let temperatures_csv = build_temperatures_csv(experiment_id, db).await?;
+    let phase_transitions_csv = build_phase_transitions_csv(experiment_id, db).await?;
+    let well_results_csv = build_well_results_csv(experiment.results.as_ref());
+
+    let asset_manifest: Vec<serde_json::Value> = assets
+        .iter()
+        .map(|asset| {
+            serde_json::json!({
+                "id": asset.id,
+                "path": archive_path_for_asset(asset),
+                "original_filename": asset.original_filename,
+                "type": asset.r#type,
+                "role": asset.role,
+                "size_bytes": asset.size_bytes,
+                "uploaded_at": asset.uploaded_at,
+            })
+        })
+        .collect();
+
+    let metadata = serde_json::json!({
+        "format_version": 1,
+        "generated_at": Utc::now(),
+        "experiment": experiment,
+        "assets": asset_manifest,
+        "derived": [
+            "derived/temperatures.csv",
+            "derived/phase_transitions.csv",
+            "derived/well_results.csv",
+        ],
+    });
+    let metadata_bytes = serde_json::to_vec_pretty(&metadata)
+        .map_err(|e| DbErr::Custom(format!("Failed to serialise archive metadata: {e}")))?;
+
+    let mut entries = vec![
+        ArchiveEntry {
+            path: "metadata.json".to_string(),
+            source: ArchiveSource::Inline(metadata_bytes),
+        },
+        ArchiveEntry {
+            path: "derived/temperatures.csv".to_string(),
+            source: ArchiveSource::Inline(temperatures_csv.into_bytes()),
+        },
+        ArchiveEntry {
+            path: "derived/phase_transitions.csv".to_string(),
+            source: ArchiveSource::Inline(phase_transitions_csv.into_bytes()),
+        },
+        ArchiveEntry {
+            path: "derived/well_results.csv".to_string(),
+            source: ArchiveSource::Inline(well_results_csv.into_bytes()),
+        },
+    ];
+
+    entries.extend(assets.iter().map(|asset| ArchiveEntry {
+        path: archive_path_for_asset(asset),
+        source: ArchiveSource::S3Key(asset.storage_key().to_string()),
+    }));
+
+    Ok(entries)
+}
@@ -0,0 +1,108 @@
+//! Results summaries kept from processing runs.
+//!
+//! Building an experiment's results reads all its wells, transitions and
+//! readings, so they are built once when Excel processing finishes and kept,
+//! and reads of the experiment are served the kept results, with the images
+//! of its wells as they are now. Changes made through the experiment's
+//! routes that change its results, such as to its regions, excluded wells or
+//! transitions, drop them, and the results are built on each read until they
+//! are kept again. Transitions edited any
+//! other way, and the samples and treatments the results show, are followed
+//! by `POST /experiments/{id}/results/recompute`. The freezing results of
+//! the experiment's wells are written along with the kept results.
+
+use super::models::ExperimentResultsResponse;
+use super::results_summaries::models::{ActiveModel, Column, Entity as ResultsSummaries};
+use super::services::{build_tray_centric_results, link_freeze_images};
+use chrono::Utc;
+use sea_orm::{ActiveValue::Set, ColumnTrait, ConnectionTrait, DbErr, EntityTrait, QueryFilter};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// The experiment's results, as kept or else built
+pub async fn results_summary(
+    db: &impl ConnectionTrait,
+    experiment_id: Uuid,
+) -> Result<Option<ExperimentResultsResponse>, DbErr> {
+    Ok(results_summaries(db, &[experiment_id])
+        .await?
+        .remove(&experiment_id))
+}
+
+/// The results of each of the experiments that has some, as kept or else
+/// built. Those kept are read together.
+pub async fn results_summaries(
+    db: &impl ConnectionTrait,
+    experiment_ids: &[Uuid],
+) -> Result<HashMap<Uuid, ExperimentResultsResponse>, DbErr> {
+    // Results kept by an earlier version of the API are built again
+    let mut kept: HashMap<Uuid, ExperimentResultsResponse> = ResultsSummaries::find()
+        .filter(Column::ExperimentId.is_in(experiment_ids.iter().copied()))
+        .all(db)
+        .await?
+        .into_iter()
+        .filter_map(|kept| {
+            Some((
+                kept.experiment_id,
+                serde_json::from_value(kept.results).ok()?,
+            ))
+        })
+        .collect();
+    let mut summaries = HashMap::new();
+    for &experiment_id in experiment_ids {
+        let results = match kept.remove(&experiment_id) {
+            Some(mut results) => {
+                link_freeze_images(experiment_id, &mut results, db).await?;
+                Some(results)
+            }
+            None => build_tray_centric_results(experiment_id, db).await?,
+        };
+        if let Some(results) = results {
+            summaries.insert(experiment_id, results);
+        }
+    }
+    Ok(summaries)
+}
+
+/// Build the experiment's results and keep them, in place of those kept
+pub async fn recompute_results_summary(
+    db: &impl ConnectionTrait,
+    experiment_id: Uuid,
+    job_id: Option<Uuid>,
+) -> Result<ExperimentResultsResponse, DbErr> {
+    let not_found = || DbErr::RecordNotFound("Experiment not found".to_string());
+    super::models::Entity::find_by_id(experiment_id)
+        .one(db)
+        .await?
+        .ok_or_else(not_found)?;
+    let results = build_tray_centric_results(experiment_id, db)
+        .await?
+        .ok_or_else(not_found)?;
+
+    discard_results_summary(db, experiment_id).await?;
+    let kept = serde_json::to_value(&results)
+        .map_err(|e| DbErr::Custom(format!("Failed to serialize results: {e}")))?;
+    ResultsSummaries::insert(ActiveModel {
+        id: Set(Uuid::new_v4()),
+        experiment_id: Set(experiment_id),
+        job_id: Set(job_id),
+        results: Set(kept),
+        computed_at: Set(Utc::now()),
+    })
+    .exec(db)
+    .await?;
+    crate::freezing_results::services::populate_freezing_results(db, experiment_id).await?;
+    Ok(results)
+}
+
+/// Drop the experiment's kept results, once they no longer hold
+pub async fn discard_results_summary(
+    db: &impl ConnectionTrait,
+    experiment_id: Uuid,
+) -> Result<(), DbErr> {
+    ResultsSummaries::delete_many()
+        .filter(Column::ExperimentId.eq(experiment_id))
+        .exec(db)
+        .await?;
+    Ok(())
+}
@@ -1 +1,4 @@
 pub mod models;
+pub mod services;
+pub mod timescale;
+pub mod timeseries;
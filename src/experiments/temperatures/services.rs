@@ -0,0 +1,157 @@
+//! Streamed temperature readings.
+//!
+//! An experiment logs thousands of readings, each with the temperature of
+//! every probe, so its readings are read a page at a time and written out as
+//! they are read, one line of JSON per reading (NDJSON). Memory stays flat
+//! however long the experiment ran.
+
+use super::models::{Column, Entity as TemperatureReadings, Model as TemperatureReading};
+use crate::experiments::models as experiments;
+use crate::experiments::probe_temperature_readings::models as probe_temperature_readings;
+use crate::experiments::services::temperature_data_with_probes;
+use crate::tray_configurations::{probes::models as probes, trays::models as trays};
+use axum::body::Bytes;
+use chrono::{DateTime, Utc};
+use futures::Stream;
+use rust_decimal::Decimal;
+use sea_orm::{
+    ColumnTrait, Condition, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder,
+    QuerySelect,
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+/// Readings read from the database at a time
+const PAGE_READINGS: u64 = 500;
+
+#[derive(Clone, Debug, Default, Deserialize, IntoParams)]
+pub struct TemperatureStreamQuery {
+    /// Readings taken at or after this time
+    pub from: Option<DateTime<Utc>>,
+    /// Readings taken before this time
+    pub to: Option<DateTime<Utc>>,
+}
+
+/// Probes of the trays the experiment uses
+pub async fn experiment_probes(
+    db: &DatabaseConnection,
+    experiment_id: Uuid,
+) -> Result<Vec<probes::Model>, DbErr> {
+    let experiment = experiments::Entity::find_by_id(experiment_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Experiment not found".to_string()))?;
+    let Some(tray_configuration_id) = experiment.tray_configuration_id else {
+        return Ok(vec![]);
+    };
+    let tray_ids: Vec<Uuid> = trays::Entity::find()
+        .filter(trays::Column::TrayConfigurationId.eq(tray_configuration_id))
+        .select_only()
+        .column(trays::Column::Id)
+        .into_tuple()
+        .all(db)
+        .await?;
+    probes::Entity::find()
+        .filter(probes::Column::TrayId.is_in(tray_ids))
+        .order_by_asc(probes::Column::DataColumnIndex)
+        .all(db)
+        .await
+}
+
+/// The page of readings after `after`, oldest first
+async fn readings_page(
+    db: &DatabaseConnection,
+    experiment_id: Uuid,
+    query: &TemperatureStreamQuery,
+    after: Option<(DateTime<Utc>, Uuid)>,
+) -> Result<Vec<TemperatureReading>, DbErr> {
+    let mut select = TemperatureReadings::find().filter(Column::ExperimentId.eq(experiment_id));
+    if let Some(from) = query.from {
+        select = select.filter(Column::Timestamp.gte(from));
+    }
+    if let Some(to) = query.to {
+        select = select.filter(Column::Timestamp.lt(to));
+    }
+    if let Some((timestamp, id)) = after {
+        select = select.filter(
+            Condition::any().add(Column::Timestamp.gt(timestamp)).add(
+                Condition::all()
+                    .add(Column::Timestamp.eq(timestamp))
+                    .add(Column::Id.gt(id)),
+            ),
+        );
+    }
+    select
+        .order_by_asc(Column::Timestamp)
+        .order_by_asc(Column::Id)
+        .limit(PAGE_READINGS)
+        .all(db)
+        .await
+}
+
+/// Lines of JSON of a page of readings, each with its probes' temperatures
+async fn page_lines(
+    db: &DatabaseConnection,
+    page: &[TemperatureReading],
+    probes: &[probes::Model],
+) -> Result<Vec<u8>, DbErr> {
+    let probe_readings = probe_temperature_readings::Entity::find()
+        .filter(
+            probe_temperature_readings::Column::TemperatureReadingId
+                .is_in(page.iter().map(|reading| reading.id)),
+        )
+        .all(db)
+        .await?;
+    let mut by_reading: HashMap<Uuid, HashMap<Uuid, Decimal>> = HashMap::new();
+    for probe_reading in probe_readings {
+        by_reading
+            .entry(probe_reading.temperature_reading_id)
+            .or_default()
+            .insert(probe_reading.probe_id, probe_reading.temperature);
+    }
+
+    let none = HashMap::new();
+    let mut lines = Vec::new();
+    for reading in page {
+        let line = temperature_data_with_probes(
+            reading,
+            by_reading.get(&reading.id).unwrap_or(&none),
+            probes,
+        );
+        serde_json::to_writer(&mut lines, &line)
+            .map_err(|e| DbErr::Custom(format!("Failed to serialize reading: {e}")))?;
+        lines.push(b'\n');
+    }
+    Ok(lines)
+}
+
+/// The experiment's readings, oldest first, one line of JSON each, read a
+/// page at a time as the stream is consumed
+pub fn temperature_lines(
+    db: DatabaseConnection,
+    experiment_id: Uuid,
+    probes: Vec<probes::Model>,
+    query: TemperatureStreamQuery,
+) -> impl Stream<Item = Result<Bytes, std::io::Error>> {
+    async_stream::try_stream! {
+        let mut after = None;
+        loop {
+            let page = readings_page(&db, experiment_id, &query, after)
+                .await
+                .map_err(std::io::Error::other)?;
+            let Some(last) = page.last() else {
+                break;
+            };
+            after = Some((last.timestamp, last.id));
+            let lines = page_lines(&db, &page, &probes)
+                .await
+                .map_err(std::io::Error::other)?;
+            yield Bytes::from(lines);
+            if (page.len() as u64) < PAGE_READINGS {
+                break;
+            }
+        }
+    }
+}
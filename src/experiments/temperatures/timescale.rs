@@ -0,0 +1,221 @@
+//! Downsampled probe series from `TimescaleDB`, with `TIMESCALEDB` set, on
+//! `PostgreSQL` databases that had the `timescaledb` extension when they
+//! were migrated.
+//!
+//! Every probe reading is also kept, with its experiment and time, in the
+//! `probe_temperature_series` hypertable, which triggers keep in step with
+//! `probe_temperature_readings`. The `probe_temperature_minutes` continuous
+//! aggregate sums it by minute, so a downsampled probe series is read from
+//! the aggregate instead of from every reading. A policy of the database
+//! refreshes the aggregate every minute, and the minutes it has not summed
+//! yet are summed as they are read. Readings logged or changed within minutes
+//! already summed are counted from the next refresh. The minutes a time
+//! range cuts through are read from the hypertable, so the range is kept to
+//! exactly.
+
+use super::timeseries::ProbeTimeseriesQuery;
+use crate::config::Config;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use sea_orm::{ConnectionTrait, DatabaseConnection, DbBackend, DbErr, FromQueryResult, Statement};
+use uuid::Uuid;
+
+/// Seconds of the continuous aggregate's buckets
+const AGGREGATE_SECONDS: i64 = 60;
+
+/// The minutes of a probe's readings in a time range: those the range
+/// holds whole from the continuous aggregate, and those it cuts through
+/// summed from the hypertable
+const RANGE_MINUTES: &str = r"
+WITH bounds AS (
+    SELECT from_time,
+           to_time,
+           date_trunc('minute', from_time - INTERVAL '1 microsecond', 'UTC')
+               + INTERVAL '1 minute' AS whole_from,
+           date_trunc('minute', to_time, 'UTC') AS whole_to
+    FROM (SELECT coalesce($3::timestamptz, '-infinity') AS from_time,
+                 coalesce($4::timestamptz, 'infinity') AS to_time) AS given
+),
+minutes AS (
+    SELECT minute, first_timestamp, total, readings
+    FROM probe_temperature_minutes, bounds
+    WHERE experiment_id = $1
+      AND probe_id = $2
+      AND minute >= whole_from
+      AND minute < whole_to
+    UNION ALL
+    SELECT time_bucket(INTERVAL '1 minute', timestamp),
+           min(timestamp),
+           sum(temperature),
+           count(*)
+    FROM probe_temperature_series, bounds
+    WHERE experiment_id = $1
+      AND probe_id = $2
+      AND timestamp >= from_time
+      AND timestamp < to_time
+      AND (timestamp < whole_from OR timestamp >= whole_to)
+    GROUP BY 1
+)
+";
+
+/// The minutes of a probe's series in a time range, and its readings
+const SERIES_SPAN: &str = r"
+SELECT min(minute) AS first_minute,
+       max(minute) AS last_minute,
+       coalesce(sum(readings), 0)::bigint AS readings
+FROM minutes
+";
+
+/// Sum of a probe's readings over buckets of whole minutes from the first
+const DOWNSAMPLED_SERIES: &str = r"
+SELECT min(first_timestamp) AS timestamp,
+       sum(total) AS total,
+       sum(readings)::bigint AS readings
+FROM minutes
+GROUP BY time_bucket($5::double precision * INTERVAL '1 second', minute, $6::timestamptz)
+ORDER BY 1
+";
+
+/// Readings of a probe in a time range
+#[derive(Clone, Debug, Default, FromQueryResult)]
+pub struct SeriesSpan {
+    pub first_minute: Option<DateTime<Utc>>,
+    pub last_minute: Option<DateTime<Utc>>,
+    pub readings: i64,
+}
+
+#[derive(Clone, Debug, FromQueryResult)]
+pub struct SeriesPoint {
+    /// Time of the first reading of the point
+    pub timestamp: DateTime<Utc>,
+    /// Sum of the point's readings as logged
+    pub total: Decimal,
+    pub readings: i64,
+}
+
+/// Whether a `PostgreSQL` query finds a row
+async fn found(db: &DatabaseConnection, sql: &str) -> Result<bool, DbErr> {
+    let row = db
+        .query_one(Statement::from_string(DbBackend::Postgres, sql))
+        .await?;
+    Ok(row.is_some())
+}
+
+/// Check on startup that the database has the series when `TIMESCALEDB` is
+/// set, and warn when it has the `timescaledb` extension without them, as
+/// when the extension was installed after the database was migrated
+pub async fn check_series(db: &DatabaseConnection, config: &Config) -> Result<(), DbErr> {
+    if db.get_database_backend() != DbBackend::Postgres {
+        return Ok(());
+    }
+    let series = found(
+        db,
+        "SELECT 1 FROM pg_class WHERE relname = 'probe_temperature_minutes'",
+    )
+    .await?;
+    if series {
+        return Ok(());
+    }
+    let extension = found(
+        db,
+        "SELECT 1 FROM pg_extension WHERE extname = 'timescaledb'",
+    )
+    .await?;
+    let problem = if extension {
+        "the database was migrated without the TimescaleDB series; revert \
+         m20251209_000001_create_probe_temperature_series and migrate again to add them"
+    } else {
+        "the database has no timescaledb extension"
+    };
+    if config.timescaledb {
+        return Err(DbErr::Custom(format!("TIMESCALEDB is set, but {problem}")));
+    }
+    if extension {
+        println!("Warning: {problem}");
+    }
+    Ok(())
+}
+
+/// Seconds of the buckets, in whole minutes, that split the `span_seconds`
+/// from the first to the last minute of a series into at most `max_points`
+fn bucket_seconds(span_seconds: i64, max_points: usize) -> i64 {
+    let gaps = i64::try_from(max_points.saturating_sub(1).max(1)).unwrap_or(i64::MAX);
+    let seconds = (span_seconds.max(0) + gaps - 1) / gaps;
+    ((seconds + AGGREGATE_SECONDS - 1) / AGGREGATE_SECONDS).max(1) * AGGREGATE_SECONDS
+}
+
+fn range_values(
+    experiment_id: Uuid,
+    probe_id: Uuid,
+    query: &ProbeTimeseriesQuery,
+) -> Vec<sea_orm::Value> {
+    vec![
+        experiment_id.into(),
+        probe_id.into(),
+        query.from.into(),
+        query.to.into(),
+    ]
+}
+
+/// How many readings of the probe the query's time range holds, and over
+/// which minutes
+pub async fn series_span(
+    db: &DatabaseConnection,
+    experiment_id: Uuid,
+    probe_id: Uuid,
+    query: &ProbeTimeseriesQuery,
+) -> Result<SeriesSpan, DbErr> {
+    Ok(
+        SeriesSpan::find_by_statement(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            format!("{RANGE_MINUTES}{SERIES_SPAN}"),
+            range_values(experiment_id, probe_id, query),
+        ))
+        .one(db)
+        .await?
+        .unwrap_or_default(),
+    )
+}
+
+/// The probe's readings in the query's time range, summed over at most
+/// `max_points` points of whole minutes, oldest first
+pub async fn downsampled_series(
+    db: &DatabaseConnection,
+    experiment_id: Uuid,
+    probe_id: Uuid,
+    query: &ProbeTimeseriesQuery,
+    span: &SeriesSpan,
+    max_points: usize,
+) -> Result<Vec<SeriesPoint>, DbErr> {
+    let (Some(first), Some(last)) = (span.first_minute, span.last_minute) else {
+        return Ok(vec![]);
+    };
+    let mut values = range_values(experiment_id, probe_id, query);
+    values.push(bucket_seconds((last - first).num_seconds(), max_points).into());
+    values.push(first.into());
+    SeriesPoint::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        format!("{RANGE_MINUTES}{DOWNSAMPLED_SERIES}"),
+        values,
+    ))
+    .all(db)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_seconds() {
+        // An hour over 61 points is a point a minute
+        assert_eq!(bucket_seconds(3600, 61), 60);
+        // 400 s rounded up to whole minutes
+        assert_eq!(bucket_seconds(3600, 10), 420);
+        // A single minute still makes a bucket
+        assert_eq!(bucket_seconds(0, 100), 60);
+        assert_eq!(bucket_seconds(-60, 100), 60);
+        // Two points: the first and last minute in buckets of their own
+        assert_eq!(bucket_seconds(3600, 2), 3600);
+    }
+}
@@ -0,0 +1,181 @@
+//! The readings of one probe of an experiment, so that clients plotting a
+//! probe need not pull every probe's temperatures.
+//!
+//! Long runs can be downsampled to at most `max_points` points, each the
+//! mean of an equal run of consecutive readings. With `TimescaleDB`, each is
+//! instead the mean of the readings of equal runs of whole minutes, summed
+//! from the per-minute aggregates.
+
+use super::models as temperature_readings;
+use super::services::experiment_probes;
+use super::timescale::{downsampled_series, series_span};
+use crate::experiments::probe_temperature_readings::models as probe_temperature_readings;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder, QuerySelect,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+const MAX_POINTS: usize = 10_000;
+
+fn calibrated_by_default() -> bool {
+    true
+}
+
+#[derive(Clone, Debug, Deserialize, IntoParams)]
+pub struct ProbeTimeseriesQuery {
+    /// Readings taken at or after this time
+    pub from: Option<DateTime<Utc>>,
+    /// Readings taken before this time
+    pub to: Option<DateTime<Utc>>,
+    /// Average consecutive readings down to at most this many points, 2 to
+    /// 10000 (default: every reading)
+    pub max_points: Option<usize>,
+    /// Correct the readings with the probe's calibration (default), or give
+    /// them as logged
+    #[serde(default = "calibrated_by_default")]
+    pub calibrated: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, ToSchema)]
+pub struct ProbeTimeseriesPoint {
+    /// Time of the first reading of the point
+    pub timestamp: DateTime<Utc>,
+    /// Mean of the point's readings in °C, rounded to 3 decimal places
+    pub temperature: Decimal,
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct ProbeTimeseries {
+    pub probe_id: Uuid,
+    pub probe_name: String,
+    pub data_column_index: i32,
+    pub calibrated: bool,
+    /// Readings in the time range, before downsampling
+    pub readings: usize,
+    /// Oldest first
+    pub points: Vec<ProbeTimeseriesPoint>,
+}
+
+/// Mean of runs of consecutive readings, at most `max_points` of them
+fn downsample(
+    readings: &[(DateTime<Utc>, Decimal)],
+    max_points: usize,
+) -> Vec<ProbeTimeseriesPoint> {
+    let per_point = readings.len().div_ceil(max_points).max(1);
+    readings
+        .chunks(per_point)
+        .map(|chunk| ProbeTimeseriesPoint {
+            timestamp: chunk[0].0,
+            temperature: (chunk
+                .iter()
+                .map(|(_, temperature)| *temperature)
+                .sum::<Decimal>()
+                / Decimal::from(chunk.len()))
+            .round_dp(3),
+        })
+        .collect()
+}
+
+/// The probe's readings in the experiment, oldest first, downsampled from
+/// the `TimescaleDB` aggregates if `timescale`. The probe must be on one of
+/// the experiment's trays. Invalid queries are returned as `DbErr::Custom`.
+pub async fn probe_timeseries(
+    db: &DatabaseConnection,
+    experiment_id: Uuid,
+    probe_id: Uuid,
+    query: &ProbeTimeseriesQuery,
+    timescale: bool,
+) -> Result<ProbeTimeseries, DbErr> {
+    if query
+        .max_points
+        .is_some_and(|max_points| !(2..=MAX_POINTS).contains(&max_points))
+    {
+        return Err(DbErr::Custom(format!(
+            "max_points must be between 2 and {MAX_POINTS}"
+        )));
+    }
+    if let (Some(from), Some(to)) = (query.from, query.to)
+        && from > to
+    {
+        return Err(DbErr::Custom("from must not be after to".to_string()));
+    }
+    let probe = experiment_probes(db, experiment_id)
+        .await?
+        .into_iter()
+        .find(|probe| probe.id == probe_id)
+        .ok_or_else(|| DbErr::RecordNotFound("Probe not found in the experiment".to_string()))?;
+    let calibrate = |raw: Decimal| {
+        if query.calibrated {
+            probe.calibrate(raw)
+        } else {
+            raw
+        }
+    };
+
+    if timescale && let Some(max_points) = query.max_points {
+        let span = series_span(db, experiment_id, probe_id, query).await?;
+        let readings = usize::try_from(span.readings).unwrap_or_default();
+        // A series short enough is given as it is
+        if readings > max_points {
+            let points = downsampled_series(db, experiment_id, probe_id, query, &span, max_points)
+                .await?
+                .into_iter()
+                .map(|point| {
+                    let readings = Decimal::from(point.readings);
+                    let total = if query.calibrated {
+                        probe.calibrate_sum(point.total, readings)
+                    } else {
+                        point.total
+                    };
+                    ProbeTimeseriesPoint {
+                        timestamp: point.timestamp,
+                        temperature: (total / readings).round_dp(3),
+                    }
+                })
+                .collect();
+            return Ok(ProbeTimeseries {
+                probe_id: probe.id,
+                probe_name: probe.name,
+                data_column_index: probe.data_column_index,
+                calibrated: query.calibrated,
+                readings,
+                points,
+            });
+        }
+    }
+
+    let mut select = probe_temperature_readings::Entity::find()
+        .inner_join(temperature_readings::Entity)
+        .filter(temperature_readings::Column::ExperimentId.eq(experiment_id))
+        .filter(probe_temperature_readings::Column::ProbeId.eq(probe_id));
+    if let Some(from) = query.from {
+        select = select.filter(temperature_readings::Column::Timestamp.gte(from));
+    }
+    if let Some(to) = query.to {
+        select = select.filter(temperature_readings::Column::Timestamp.lt(to));
+    }
+    let readings: Vec<(DateTime<Utc>, Decimal)> = select
+        .select_only()
+        .column(temperature_readings::Column::Timestamp)
+        .column(probe_temperature_readings::Column::Temperature)
+        .order_by_asc(temperature_readings::Column::Timestamp)
+        .into_tuple()
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|(timestamp, raw): (DateTime<Utc>, Decimal)| (timestamp, calibrate(raw)))
+        .collect();
+
+    Ok(ProbeTimeseries {
+        probe_id: probe.id,
+        probe_name: probe.name,
+        data_column_index: probe.data_column_index,
+        calibrated: query.calibrated,
+        readings: readings.len(),
+        points: downsample(&readings, query.max_points.unwrap_or(readings.len().max(1))),
+    })
+}
@@ -1,4 +1,4 @@
-use crate::config::test_helpers::setup_test_app;
+use crate::config::test_helpers::{send_json, setup_test_app};
 use axum::Router;
 use axum::body::Body;
 use axum::body::to_bytes;
@@ -814,12 +814,7 @@ async fn test_experiment_process_status_endpoint() {
         .unwrap();
 
     let (status_status, _status_body) = extract_response_body(status_response).await;
-
-    if status_status == StatusCode::NOT_FOUND {
-        //     "✅ Process status endpoint accessible - correctly returns 404 for non-existent job"
-        // );
-    } else if status_status == StatusCode::OK {
-    }
+    assert_eq!(status_status, StatusCode::NOT_FOUND);
 }
 
 /// Validate experiment results structure
@@ -1271,232 +1266,1359 @@ async fn test_asset_upload_no_file() {
     );
 }
 
-/// Helper function to create test image data (small PNG-like binary data)
-fn create_test_image_data() -> Vec<u8> {
-    // Simple binary data that looks like a PNG file
-    vec![
-        0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, // PNG signature
-        0x00, 0x00, 0x00, 0x0D, 0x49, 0x48, 0x44, 0x52, // IHDR chunk
-        0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, // 1x1 pixel
-        0x08, 0x02, 0x00, 0x00, 0x00, 0x90, 0x77, 0x53, // RGB, no interlace
-        0xDE, 0x00, 0x00, 0x00, 0x0C, 0x49, 0x44, 0x41, // IDAT chunk
-        0x54, 0x08, 0x99, 0x01, 0x01, 0x00, 0x00, 0x00, // Compressed data
-        0x00, 0x00, 0x02, 0x00, 0x01, 0xE5, 0x27, 0xDE, // Checksum
-        0xFC, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4E, // IEND chunk
-        0x44, 0xAE, 0x42, 0x60, 0x82,
-    ]
+async fn post_json_with_headers(
+    app: &Router,
+    uri: &str,
+    body: &Value,
+    headers: &[(&str, &str)],
+) -> (StatusCode, Value) {
+    let mut request = Request::builder()
+        .method("POST")
+        .uri(uri)
+        .header("content-type", "application/json");
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    let response = app
+        .clone()
+        .oneshot(request.body(Body::from(body.to_string())).unwrap())
+        .await
+        .unwrap();
+    extract_response_body(response).await
 }
 
-// Expected values from the merged.xlsx file based on previous analysis
-const EXPECTED_TOTAL_WELLS: u64 = 192; // 96 wells per tray × 2 trays
-const EXPECTED_TOTAL_TIME_POINTS: u64 = 6786;
+#[tokio::test]
+async fn test_presigned_upload_flow() {
+    let app = setup_test_app().await;
+    let experiment = create_test_experiment(&app).await.unwrap();
+    let experiment_id = experiment["id"].as_str().unwrap();
 
-/// Comprehensive validation data derived from merged.csv analysis
-/// This represents the exact phase transitions that should occur when processing the Excel file
-pub struct WellTransitionData {
-    pub tray: &'static str,        // "P1" or "P2"
-    pub coordinate: &'static str,  // "A1", "B2", etc.
-    pub freeze_time: &'static str, // "2025-03-20 16:19:47" - for reference
-    pub temp_probe_1: f64,         // Temperature at freeze time
+    let (status, uploads) = post_json_with_headers(
+        &app,
+        &format!("/api/experiments/{experiment_id}/upload-urls"),
+        &json!({"files": [
+            {"filename": "INP_49640_2025-03-20_15-14-17.jpg", "content_type": "image/jpeg"},
+            {"filename": "notes.txt"}
+        ]}),
+        &[],
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "Failed to presign: {uploads:?}");
+    let uploads = uploads.as_array().unwrap();
+    assert_eq!(uploads.len(), 2);
+    let image_upload = &uploads[0];
+    let s3_key = image_upload["s3_key"].as_str().unwrap();
+    assert!(s3_key.contains(experiment_id));
+    assert!(s3_key.ends_with("/INP_49640_2025-03-20_15-14-17.jpg"));
+    assert!(
+        image_upload["upload_url"]
+            .as_str()
+            .unwrap()
+            .contains(s3_key)
+    );
+    assert_eq!(image_upload["upload_headers"]["Content-Type"], "image/jpeg");
+    assert!(image_upload["expires_at"].is_string());
+
+    // Registering before the client has uploaded anything is rejected
+    let (status, _) = post_json_with_headers(
+        &app,
+        &format!("/api/experiments/{experiment_id}/uploads/register"),
+        &json!({"s3_key": s3_key}),
+        &[],
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Simulate the browser's direct PUT to S3
+    let image = create_test_image_data();
+    crate::external::s3::MOCK_S3_STORE
+        .put_object(s3_key, image.clone())
+        .unwrap();
+
+    let (status, registered) = post_json_with_headers(
+        &app,
+        &format!("/api/experiments/{experiment_id}/uploads/register"),
+        &json!({"s3_key": s3_key}),
+        &[],
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "Failed to register: {registered:?}");
+    assert_eq!(registered["filename"], "INP_49640_2025-03-20_15-14-17.jpg");
+    assert_eq!(registered["size"], image.len());
+    assert_eq!(registered["auto_processed"], false);
+
+    let asset_id = registered["id"].as_str().unwrap();
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/assets/{asset_id}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (status, asset) = extract_response_body(response).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(asset["s3_key"], s3_key);
+    assert_eq!(asset["experiment_id"], experiment_id);
+    assert_eq!(asset["role"], "camera_image");
+    assert_eq!(
+        asset["checksum_sha256"],
+        crate::assets::services::sha256_hex(&image)
+    );
+
+    // The same upload cannot be registered twice
+    let (status, _) = post_json_with_headers(
+        &app,
+        &format!("/api/experiments/{experiment_id}/uploads/register"),
+        &json!({"s3_key": s3_key}),
+        &[],
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    // Requesting a URL for an existing file needs the overwrite header
+    let request = json!({"files": [{"filename": "INP_49640_2025-03-20_15-14-17.jpg"}]});
+    let uri = format!("/api/experiments/{experiment_id}/upload-urls");
+    let (status, _) = post_json_with_headers(&app, &uri, &request, &[]).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (status, _) =
+        post_json_with_headers(&app, &uri, &request, &[("x-allow-overwrite", "true")]).await;
+    assert_eq!(status, StatusCode::OK);
 }
 
-/// Expected phase transitions extracted from merged.csv analysis
-/// Complete list of all 192 well transitions with exact timestamps and average temperatures
-/// Data generated from systematic CSV analysis of all phase changes (0→1)
-pub const EXPECTED_TRANSITIONS: &[WellTransitionData] = &[
-    WellTransitionData {
-        tray: "P1",
-        coordinate: "A1",
-        freeze_time: "2025-03-20 16:49:38",
-        temp_probe_1: -27.543,
-    },
-    WellTransitionData {
-        tray: "P1",
-        coordinate: "A10",
-        freeze_time: "2025-03-20 16:35:04",
-        temp_probe_1: -22.863,
-    },
-    WellTransitionData {
-        tray: "P1",
-        coordinate: "A11",
-        freeze_time: "2025-03-20 16:32:47",
-        temp_probe_1: -22.151,
-    },
-    WellTransitionData {
-        tray: "P1",
-        coordinate: "A12",
-        freeze_time: "2025-03-20 16:34:09",
-        temp_probe_1: -22.588,
-    },
-    WellTransitionData {
-        tray: "P1",
-        coordinate: "A2",
-        freeze_time: "2025-03-20 16:48:49",
-        temp_probe_1: -27.278,
-    },
-    WellTransitionData {
-        tray: "P1",
-        coordinate: "A3",
-        freeze_time: "2025-03-20 16:50:39",
-        temp_probe_1: -27.856,
-    },
-    WellTransitionData {
-        tray: "P1",
-        coordinate: "A4",
-        freeze_time: "2025-03-20 16:50:58",
-        temp_probe_1: -27.955,
-    },
-    WellTransitionData {
-        tray: "P1",
-        coordinate: "A5",
-        freeze_time: "2025-03-20 16:45:19",
-        temp_probe_1: -26.121,
-    },
-    WellTransitionData {
-        tray: "P1",
-        coordinate: "A6",
-        freeze_time: "2025-03-20 16:51:07",
-        temp_probe_1: -28.003,
-    },
-    WellTransitionData {
-        tray: "P1",
-        coordinate: "A7",
-        freeze_time: "2025-03-20 16:33:57",
-        temp_probe_1: -22.522,
-    },
-    WellTransitionData {
-        tray: "P1",
-        coordinate: "A8",
-        freeze_time: "2025-03-20 16:34:41",
-        temp_probe_1: -22.753,
-    },
-    WellTransitionData {
-        tray: "P1",
-        coordinate: "A9",
-        freeze_time: "2025-03-20 16:42:06",
-        temp_probe_1: -25.078,
-    },
-    WellTransitionData {
-        tray: "P1",
-        coordinate: "B1",
-        freeze_time: "2025-03-20 16:42:35",
-        temp_probe_1: -25.232,
-    },
-    WellTransitionData {
-        tray: "P1",
-        coordinate: "B10",
-        freeze_time: "2025-03-20 16:42:44",
-        temp_probe_1: -25.278,
-    },
-    WellTransitionData {
-        tray: "P1",
-        coordinate: "B11",
-        freeze_time: "2025-03-20 16:43:21",
-        temp_probe_1: -25.469,
-    },
-    WellTransitionData {
-        tray: "P1",
-        coordinate: "B12",
-        freeze_time: "2025-03-20 16:33:20",
-        temp_probe_1: -22.322,
-    },
-    WellTransitionData {
-        tray: "P1",
-        coordinate: "B2",
-        freeze_time: "2025-03-20 16:46:10",
-        temp_probe_1: -26.419,
-    },
-    WellTransitionData {
-        tray: "P1",
-        coordinate: "B3",
-        freeze_time: "2025-03-20 16:52:23",
-        temp_probe_1: -28.435,
-    },
-    WellTransitionData {
-        tray: "P1",
-        coordinate: "B4",
-        freeze_time: "2025-03-20 16:46:09",
-        temp_probe_1: -26.412,
-    },
-    WellTransitionData {
-        tray: "P1",
-        coordinate: "B5",
-        freeze_time: "2025-03-20 16:40:09",
-        temp_probe_1: -24.460,
-    },
-    WellTransitionData {
-        tray: "P1",
-        coordinate: "B6",
-        freeze_time: "2025-03-20 16:46:35",
-        temp_probe_1: -26.555,
-    },
-    WellTransitionData {
-        tray: "P1",
-        coordinate: "B7",
-        freeze_time: "2025-03-20 16:46:33",
-        temp_probe_1: -26.550,
-    },
-    WellTransitionData {
-        tray: "P1",
-        coordinate: "B8",
-        freeze_time: "2025-03-20 16:46:30",
-        temp_probe_1: -26.531,
-    },
-    WellTransitionData {
-        tray: "P1",
-        coordinate: "B9",
-        freeze_time: "2025-03-20 16:42:04",
-        temp_probe_1: -25.065,
-    },
-    WellTransitionData {
-        tray: "P1",
-        coordinate: "C1",
-        freeze_time: "2025-03-20 16:50:22",
-        temp_probe_1: -27.769,
-    },
-    WellTransitionData {
-        tray: "P1",
-        coordinate: "C10",
-        freeze_time: "2025-03-20 16:31:47",
-        temp_probe_1: -21.823,
-    },
-    WellTransitionData {
-        tray: "P1",
-        coordinate: "C11",
-        freeze_time: "2025-03-20 16:36:24",
-        temp_probe_1: -23.257,
-    },
-    WellTransitionData {
-        tray: "P1",
-        coordinate: "C12",
-        freeze_time: "2025-03-20 16:36:50",
-        temp_probe_1: -23.392,
-    },
-    WellTransitionData {
-        tray: "P1",
-        coordinate: "C2",
-        freeze_time: "2025-03-20 16:46:24",
-        temp_probe_1: -26.500,
-    },
-    WellTransitionData {
-        tray: "P1",
-        coordinate: "C3",
-        freeze_time: "2025-03-20 16:49:16",
-        temp_probe_1: -27.425,
-    },
-    WellTransitionData {
-        tray: "P1",
-        coordinate: "C4",
-        freeze_time: "2025-03-20 16:47:00",
-        temp_probe_1: -26.678,
-    },
-    WellTransitionData {
-        tray: "P1",
-        coordinate: "C5",
-        freeze_time: "2025-03-20 16:40:48",
-        temp_probe_1: -24.659,
-    },
-    WellTransitionData {
+#[tokio::test]
+async fn test_presigned_upload_validation() {
+    let app = setup_test_app().await;
+    let experiment = create_test_experiment(&app).await.unwrap();
+    let experiment_id = experiment["id"].as_str().unwrap();
+    let other_id = create_experiment_via_api(&app).await.unwrap();
+
+    let uri = format!("/api/experiments/{experiment_id}/upload-urls");
+    for files in [
+        json!([]),
+        json!([{"filename": "../escape.jpg"}]),
+        json!([{"filename": ""}]),
+    ] {
+        let (status, _) = post_json_with_headers(&app, &uri, &json!({"files": files}), &[]).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "Accepted {files}");
+    }
+
+    let (status, _) = post_json_with_headers(
+        &app,
+        &format!("/api/experiments/{}/upload-urls", uuid::Uuid::new_v4()),
+        &json!({"files": [{"filename": "a.jpg"}]}),
+        &[],
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Keys issued for one experiment cannot be registered on another
+    let (_, uploads) = post_json_with_headers(
+        &app,
+        &format!("/api/experiments/{other_id}/upload-urls"),
+        &json!({"files": [{"filename": "a.jpg"}]}),
+        &[],
+    )
+    .await;
+    let s3_key = uploads[0]["s3_key"].as_str().unwrap();
+    crate::external::s3::MOCK_S3_STORE
+        .put_object(s3_key, vec![1, 2, 3])
+        .unwrap();
+    let (status, _) = post_json_with_headers(
+        &app,
+        &format!("/api/experiments/{experiment_id}/uploads/register"),
+        &json!({"s3_key": s3_key}),
+        &[],
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+async fn create_camera_image_asset(app: &Router, experiment_id: &str, filename: &str) {
+    let image = image::RgbImage::from_pixel(320, 240, image::Rgb([30, 60, 90]));
+    register_camera_image(app, experiment_id, filename, &image).await;
+}
+
+async fn register_camera_image(
+    app: &Router,
+    experiment_id: &str,
+    filename: &str,
+    image: &image::RgbImage,
+) {
+    let mut png = Vec::new();
+    image
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .unwrap();
+    let s3_key = format!("test/{experiment_id}/{filename}");
+    crate::external::s3::MOCK_S3_STORE
+        .put_object(&s3_key, png)
+        .unwrap();
+
+    let (status, body) = post_json_with_headers(
+        app,
+        "/api/assets",
+        &json!({
+            "experiment_id": experiment_id,
+            "original_filename": filename,
+            "s3_key": s3_key,
+            "type": "image",
+            "role": "camera_image",
+            "is_deleted": false
+        }),
+        &[],
+    )
+    .await;
+    assert_eq!(
+        status,
+        StatusCode::CREATED,
+        "Failed to create asset: {body:?}"
+    );
+}
+
+#[tokio::test]
+async fn test_experiment_timelapse() {
+    let app = setup_test_app().await;
+    let experiment = create_test_experiment(&app).await.unwrap();
+    let experiment_id = experiment["id"].as_str().unwrap();
+    let uri = format!("/api/experiments/{experiment_id}/timelapse");
+
+    // Nothing to stitch yet
+    let (status, _) = post_json_with_headers(&app, &uri, &json!({}), &[]).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let response = app
+        .clone()
+        .oneshot(Request::builder().uri(&uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    create_camera_image_asset(&app, experiment_id, "INP_1_2025-03-20_15-14-27.png").await;
+    create_camera_image_asset(&app, experiment_id, "INP_1_2025-03-20_15-14-17.png").await;
+
+    let (status, _) = post_json_with_headers(&app, &uri, &json!({"fps": 0}), &[]).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, asset) =
+        post_json_with_headers(&app, &uri, &json!({"format": "mp4", "fps": 2}), &[]).await;
+    assert_eq!(status, StatusCode::ACCEPTED, "Failed to start: {asset:?}");
+    assert_eq!(asset["type"], "video");
+    assert_eq!(asset["role"], "timelapse");
+    assert_eq!(asset["original_filename"], "timelapse.mp4");
+    assert_eq!(asset["processing_status"], "processing");
+
+    let asset_id = asset["id"].as_str().unwrap();
+    let mut asset = asset.clone();
+    for _ in 0..200 {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/api/assets/{asset_id}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        asset = extract_response_body(response).await.1;
+        if asset["processing_status"] != "processing" {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("{uri}?format=mp4"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    // Encoding needs ffmpeg, which is not necessarily installed where tests run
+    match asset["processing_status"].as_str() {
+        Some("completed") => {
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.headers()["content-type"], "video/mp4");
+            let video = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            assert_eq!(asset["size_bytes"], video.len());
+            assert!(
+                asset["processing_message"]
+                    .as_str()
+                    .unwrap()
+                    .contains("Rendered 2 frames")
+            );
+        }
+        Some("error") => {
+            assert_eq!(response.status(), StatusCode::CONFLICT);
+            assert!(asset["processing_message"].is_string());
+        }
+        other => panic!("Time-lapse did not finish: {other:?}"),
+    }
+}
+
+#[tokio::test]
+#[allow(clippy::too_many_lines)]
+async fn test_image_freeze_detection() {
+    let app = setup_test_app().await;
+
+    // P1 is placed on the images with A1 at (20, 20) and a 10 px pitch
+    let (status, tray_config) = post_json_with_headers(
+        &app,
+        "/api/tray_configurations",
+        &json!({
+            "name": format!("Freeze Detection Config {}", uuid::Uuid::new_v4()),
+            "experiment_default": false,
+            "trays": [
+                {
+                    "order_sequence": 1,
+                    "rotation_degrees": 0,
+                    "name": "P1",
+                    "qty_cols": 12,
+                    "qty_rows": 8,
+                    "well_relative_diameter": 2.5,
+                    "upper_left_corner_x": 20,
+                    "upper_left_corner_y": 20,
+                    "lower_right_corner_x": 130,
+                    "lower_right_corner_y": 90
+                },
+                {
+                    "order_sequence": 2,
+                    "rotation_degrees": 0,
+                    "name": "P2",
+                    "qty_cols": 12,
+                    "qty_rows": 8,
+                    "well_relative_diameter": 2.5
+                }
+            ]
+        }),
+        &[],
+    )
+    .await;
+    assert_eq!(
+        status,
+        StatusCode::CREATED,
+        "Failed to create: {tray_config:?}"
+    );
+    let experiment_id = create_test_experiment_via_api(&app, tray_config["id"].as_str().unwrap())
+        .await
+        .unwrap();
+    let uri = format!("/api/experiments/{experiment_id}/detect-freezing");
+
+    // The wells are created from the spreadsheet
+    let (status, _) = post_json_with_headers(&app, &uri, &json!({}), &[]).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    process_excel_file_via_api(&app, &experiment_id)
+        .await
+        .unwrap();
+
+    let (status, _) = post_json_with_headers(&app, &uri, &json!({}), &[]).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "No camera images yet");
+
+    // Well A1 of P1 turns white in the third frame
+    let liquid = image::RgbImage::from_pixel(320, 240, image::Rgb([40, 40, 40]));
+    let mut frozen = liquid.clone();
+    for y in 17..=23 {
+        for x in 17..=23 {
+            frozen.put_pixel(x, y, image::Rgb([230, 230, 230]));
+        }
+    }
+    register_camera_image(
+        &app,
+        &experiment_id,
+        "INP_7_2025-01-01_10-00-00.png",
+        &liquid,
+    )
+    .await;
+    register_camera_image(
+        &app,
+        &experiment_id,
+        "INP_7_2025-01-01_10-00-10.png",
+        &liquid,
+    )
+    .await;
+    register_camera_image(
+        &app,
+        &experiment_id,
+        "INP_7_2025-01-01_10-00-20.png",
+        &frozen,
+    )
+    .await;
+    register_camera_image(
+        &app,
+        &experiment_id,
+        "INP_7_2025-01-01_10-00-30.png",
+        &frozen,
+    )
+    .await;
+
+    let (status, _) = post_json_with_headers(&app, &uri, &json!({"threshold": 0}), &[]).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = post_json_with_headers(
+        &app,
+        &format!("/api/experiments/{}/detect-freezing", uuid::Uuid::new_v4()),
+        &json!({}),
+        &[],
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, result) = post_json_with_headers(&app, &uri, &json!({}), &[]).await;
+    assert_eq!(status, StatusCode::OK, "Detection failed: {result:?}");
+    assert_eq!(result["mode"], "cross_check");
+    assert_eq!(result["frames_analysed"], 4);
+    assert_eq!(result["wells_analysed"], 96, "Only P1 has corners");
+    assert_eq!(result["wells_frozen"], 1);
+    assert_eq!(result["phase_transitions_created"], 0);
+    let well_result = |result: &Value, row: &str, column: i64| {
+        result["wells"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|well| well["row_letter"] == row && well["column_number"] == column)
+            .unwrap()
+            .clone()
+    };
+    let a1 = well_result(&result, "A", 1);
+    assert_eq!(a1["image_frozen_at"], "2025-01-01T10:00:20Z");
+    assert_eq!(a1["image_filename"], "INP_7_2025-01-01_10-00-20.png");
+    assert_eq!(a1["brightness_change"], 190.0);
+    assert!(a1["spreadsheet_frozen_at"].is_string());
+    assert_eq!(a1["agrees"], false);
+    assert!(well_result(&result, "A", 2)["image_frozen_at"].is_null());
+
+    assert_eq!(
+        result["agreeing_wells"], 0,
+        "Every well froze in the spreadsheet"
+    );
+
+    let (status, result) =
+        post_json_with_headers(&app, &uri, &json!({"mode": "replace"}), &[]).await;
+    assert_eq!(status, StatusCode::OK, "Detection failed: {result:?}");
+    assert_eq!(result["phase_transitions_created"], 1);
+    // The images have no spreadsheet rows
+    assert_eq!(result["temperature_readings_created"], 1);
+
+    // The stored transitions now match the images
+    let (_, result) = post_json_with_headers(&app, &uri, &json!({}), &[]).await;
+    assert_eq!(well_result(&result, "A", 1)["difference_seconds"], 0);
+    assert!(well_result(&result, "A", 2)["spreadsheet_frozen_at"].is_null());
+    assert_eq!(result["agreeing_wells"], 96);
+}
+
+#[tokio::test]
+async fn test_well_image_crop() {
+    let app = setup_test_app().await;
+
+    let (status, tray_config) = post_json_with_headers(
+        &app,
+        "/api/tray_configurations",
+        &json!({
+            "name": format!("Well Image Config {}", uuid::Uuid::new_v4()),
+            "experiment_default": false,
+            "trays": [{
+                "order_sequence": 1,
+                "rotation_degrees": 0,
+                "name": "P1",
+                "qty_cols": 12,
+                "qty_rows": 8,
+                "upper_left_corner_x": 20,
+                "upper_left_corner_y": 20,
+                "lower_right_corner_x": 130,
+                "lower_right_corner_y": 90
+            }]
+        }),
+        &[],
+    )
+    .await;
+    assert_eq!(
+        status,
+        StatusCode::CREATED,
+        "Failed to create: {tray_config:?}"
+    );
+    let experiment_id = create_test_experiment_via_api(&app, tray_config["id"].as_str().unwrap())
+        .await
+        .unwrap();
+
+    // Well B2 at (30, 30) is white in the second frame
+    let dark = image::RgbImage::from_pixel(320, 240, image::Rgb([40, 40, 40]));
+    let mut bright = dark.clone();
+    for y in 25..=35 {
+        for x in 25..=35 {
+            bright.put_pixel(x, y, image::Rgb([230, 230, 230]));
+        }
+    }
+    register_camera_image(&app, &experiment_id, "INP_3_2025-01-01_10-00-00.png", &dark).await;
+    register_camera_image(
+        &app,
+        &experiment_id,
+        "INP_3_2025-01-01_10-00-10.png",
+        &bright,
+    )
+    .await;
+
+    let get = |uri: String| {
+        app.clone()
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+    };
+    let base = format!("/api/experiments/{experiment_id}/wells");
+
+    let response = get(format!(
+        "{base}/P1:B2/image?size=20&timestamp=2025-01-01T10:00:02Z"
+    ))
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "image/png");
+    assert_eq!(
+        response.headers()["content-disposition"],
+        "inline; filename=\"P1_B2_INP_3_2025-01-01_10-00-00.png\""
+    );
+    let png = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let crop = image::load_from_memory(&png).unwrap().into_rgb8();
+    assert_eq!((crop.width(), crop.height()), (20, 20));
+    assert_eq!(crop.get_pixel(10, 10)[0], 40);
+
+    // Without a timestamp or a freeze the last frame is used, and the tray
+    // name can be left out with a single tray
+    let response = get(format!("{base}/b2/image?size=20")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let png = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let crop = image::load_from_memory(&png).unwrap().into_rgb8();
+    assert_eq!(crop.get_pixel(10, 10)[0], 230);
+    assert_eq!(crop.get_pixel(0, 0)[0], 40);
+
+    for (uri, expected) in [
+        (
+            format!("{base}/P1:B2/image?size=4"),
+            StatusCode::BAD_REQUEST,
+        ),
+        (format!("{base}/P1:22/image"), StatusCode::BAD_REQUEST),
+        (format!("{base}/P9:A1/image"), StatusCode::NOT_FOUND),
+        (format!("{base}/P1:Z1/image"), StatusCode::NOT_FOUND),
+        (
+            format!("/api/experiments/{}/wells/A1/image", uuid::Uuid::new_v4()),
+            StatusCode::NOT_FOUND,
+        ),
+    ] {
+        assert_eq!(get(uri.clone()).await.unwrap().status(), expected, "{uri}");
+    }
+}
+
+#[tokio::test]
+#[allow(clippy::too_many_lines)]
+async fn test_image_diff_between_frames() {
+    let app = setup_test_app().await;
+
+    let (status, tray_config) = post_json_with_headers(
+        &app,
+        "/api/tray_configurations",
+        &json!({
+            "name": format!("Image Diff Config {}", uuid::Uuid::new_v4()),
+            "experiment_default": false,
+            "trays": [
+                {
+                    "order_sequence": 1,
+                    "rotation_degrees": 0,
+                    "name": "P1",
+                    "qty_cols": 12,
+                    "qty_rows": 8,
+                    "upper_left_corner_x": 20,
+                    "upper_left_corner_y": 20,
+                    "lower_right_corner_x": 130,
+                    "lower_right_corner_y": 90
+                },
+                {
+                    "order_sequence": 2,
+                    "rotation_degrees": 0,
+                    "name": "P2",
+                    "qty_cols": 12,
+                    "qty_rows": 8
+                }
+            ]
+        }),
+        &[],
+    )
+    .await;
+    assert_eq!(
+        status,
+        StatusCode::CREATED,
+        "Failed to create: {tray_config:?}"
+    );
+    let experiment_id = create_test_experiment_via_api(&app, tray_config["id"].as_str().unwrap())
+        .await
+        .unwrap();
+    process_excel_file_via_api(&app, &experiment_id)
+        .await
+        .unwrap();
+
+    // Well B2 at (30, 30) turns white, well A3 at (40, 20) darkens slightly
+    let before = image::RgbImage::from_pixel(320, 240, image::Rgb([40, 40, 40]));
+    let mut after = before.clone();
+    for y in 25..=35 {
+        for x in 25..=35 {
+            after.put_pixel(x, y, image::Rgb([230, 230, 230]));
+        }
+    }
+    for y in 16..=24 {
+        for x in 36..=44 {
+            after.put_pixel(x, y, image::Rgb([32, 32, 32]));
+        }
+    }
+    register_camera_image(
+        &app,
+        &experiment_id,
+        "INP_5_2025-01-01_10-00-00.png",
+        &before,
+    )
+    .await;
+    register_camera_image(
+        &app,
+        &experiment_id,
+        "INP_5_2025-01-01_10-00-10.png",
+        &after,
+    )
+    .await;
+
+    let get = |query: &str| {
+        app.clone().oneshot(
+            Request::builder()
+                .uri(format!(
+                    "/api/experiments/{experiment_id}/image-diff?{query}"
+                ))
+                .body(Body::empty())
+                .unwrap(),
+        )
+    };
+    let times = "from=2025-01-01T10:00:01Z&to=2025-01-01T10:00:09Z";
+
+    let (status, diff) =
+        extract_response_body(get(&format!("{times}&format=json")).await.unwrap()).await;
+    assert_eq!(status, StatusCode::OK, "{diff:?}");
+    assert_eq!(diff["from_filename"], "INP_5_2025-01-01_10-00-00.png");
+    assert_eq!(diff["to_captured_at"], "2025-01-01T10:00:10Z");
+    assert_eq!(diff["threshold"], 12.0);
+    assert_eq!(diff["wells_changed"], 1);
+    assert!(diff.get("png").is_none());
+    let well = |diff: &Value, row: &str, column: i64| {
+        diff["wells"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|well| {
+                well["tray_name"] == "P1"
+                    && well["row_letter"] == row
+                    && well["column_number"] == column
+            })
+            .unwrap()
+            .clone()
+    };
+    let b2 = well(&diff, "B", 2);
+    assert_eq!(
+        (b2["before"].clone(), b2["after"].clone()),
+        (json!(40.0), json!(230.0))
+    );
+    assert_eq!(b2["change"], 190.0);
+    assert_eq!(b2["changed"], true);
+    assert_eq!(well(&diff, "A", 3)["change"], -8.0);
+    assert_eq!(well(&diff, "A", 3)["changed"], false);
+
+    // A lower threshold picks up the darkening well too
+    let (_, diff) = extract_response_body(
+        get(&format!("{times}&format=json&threshold=5"))
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(diff["wells_changed"], 2);
+
+    // The difference image circles the changed well
+    let response = get(times).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "image/png");
+    assert_eq!(
+        response.headers()["content-disposition"],
+        "inline; filename=\"INP_5_2025-01-01_10-00-00_vs_INP_5_2025-01-01_10-00-10.png\""
+    );
+    let png = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let image = image::load_from_memory(&png).unwrap().into_rgb8();
+    assert_eq!(image.get_pixel(30, 30).0, [190, 190, 190]);
+    assert_eq!(image.get_pixel(200, 200).0, [0, 0, 0]);
+    // The fallback sample radius is 4 px, so the circle has a radius of 8
+    assert_eq!(image.get_pixel(38, 30).0, [255, 40, 40]);
+
+    for (query, expected) in [
+        (
+            "from=2025-01-01T10:00:01Z&to=2025-01-01T10:00:02Z",
+            StatusCode::BAD_REQUEST,
+        ),
+        (
+            &format!("{times}&threshold=0") as &str,
+            StatusCode::BAD_REQUEST,
+        ),
+        ("from=2025-01-01T10:00:01Z", StatusCode::BAD_REQUEST),
+    ] {
+        assert_eq!(get(query).await.unwrap().status(), expected, "{query}");
+    }
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!(
+                    "/api/experiments/{}/image-diff?{times}",
+                    uuid::Uuid::new_v4()
+                ))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_camera_frame_navigation() {
+    let app = setup_test_app().await;
+    let experiment = create_test_experiment(&app).await.unwrap();
+    let experiment_id = experiment["id"].as_str().unwrap();
+    for filename in [
+        "INP_8_2025-01-01_10-00-20.png",
+        "INP_8_2025-01-01_10-00-00.png",
+        "INP_8_2025-01-01_10-00-10.png",
+        "overview.png",
+    ] {
+        create_camera_image_asset(&app, experiment_id, filename).await;
+    }
+
+    let get = |query: &str| {
+        let uri = format!("/api/experiments/{experiment_id}/frames/{query}");
+        let app = app.clone();
+        async move {
+            let response = app
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            extract_response_body(response).await
+        }
+    };
+
+    let (status, nearest) = get("nearest?timestamp=2025-01-01T10:00:08Z").await;
+    assert_eq!(status, StatusCode::OK, "{nearest:?}");
+    assert_eq!(nearest["index"], 1);
+    assert_eq!(nearest["frame_count"], 3);
+    assert_eq!(
+        nearest["frame"]["filename"],
+        "INP_8_2025-01-01_10-00-10.png"
+    );
+    assert_eq!(nearest["frame"]["captured_at"], "2025-01-01T10:00:10Z");
+    let asset_id = nearest["frame"]["asset_id"].as_str().unwrap();
+    assert_eq!(
+        nearest["frame"]["view_url"],
+        format!("/api/assets/{asset_id}/view")
+    );
+    assert_eq!(
+        nearest["previous"]["filename"],
+        "INP_8_2025-01-01_10-00-00.png"
+    );
+    assert_eq!(nearest["next"]["filename"], "INP_8_2025-01-01_10-00-20.png");
+
+    // Stepping from a frame's own time moves to its neighbour
+    let (_, next) = get("next?timestamp=2025-01-01T10:00:10Z").await;
+    assert_eq!(next["index"], 2);
+    assert!(next["next"].is_null());
+    let (_, previous) = get("previous?timestamp=2025-01-01T10:00:10Z").await;
+    assert_eq!(previous["index"], 0);
+    assert!(previous["previous"].is_null());
+
+    // Without a timestamp: the first and the last frame
+    assert_eq!(get("next").await.1["index"], 0);
+    assert_eq!(get("previous").await.1["index"], 2);
+
+    let (status, message) = get("next?timestamp=2025-01-01T10:00:20Z").await;
+    assert_eq!(status, StatusCode::NOT_FOUND, "{message:?}");
+    assert_eq!(get("sideways").await.0, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        get("next?timestamp=yesterday").await.0,
+        StatusCode::BAD_REQUEST
+    );
+
+    let empty = create_experiment_via_api(&app).await.unwrap();
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/experiments/{empty}/frames/nearest"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_uploaded_images_link_to_the_closest_reading() {
+    let app = setup_test_app().await;
+    let tray_config: Value =
+        serde_json::from_str(&create_test_tray_config_with_trays(&app, "Image Link Config").await)
+            .unwrap();
+    let experiment_id = create_test_experiment_via_api(&app, tray_config["id"].as_str().unwrap())
+        .await
+        .unwrap();
+    // The camera counter differs from the spreadsheet's image names, so only
+    // the capture time can match them
+    let upload = |filename: &'static str| {
+        let app = app.clone();
+        let experiment_id = experiment_id.clone();
+        async move {
+            let mut png = Vec::new();
+            image::RgbImage::from_pixel(8, 8, image::Rgb([10, 20, 30]))
+                .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+                .unwrap();
+            let boundary = "capture_boundary";
+            let mut body = format!(
+                "--{boundary}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{filename}\"\r\nContent-Type: image/png\r\n\r\n"
+            )
+            .into_bytes();
+            body.extend_from_slice(&png);
+            body.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .method("POST")
+                        .uri(format!("/api/experiments/{experiment_id}/uploads"))
+                        .header(
+                            "content-type",
+                            format!("multipart/form-data; boundary={boundary}"),
+                        )
+                        .body(Body::from(body))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            let (status, uploaded) = extract_response_body(response).await;
+            assert_eq!(status, StatusCode::OK, "Upload failed: {uploaded:?}");
+            uploaded["id"].clone()
+        }
+    };
+    let get_asset = |id: Value| {
+        let app = app.clone();
+        let uri = format!("/api/assets/{}", id.as_str().unwrap());
+        async move {
+            let response = app
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            extract_response_body(response).await.1
+        }
+    };
+
+    // Uploaded before the readings exist, then linked by the processing
+    let early = get_asset(upload("INP_99999_2025-03-20_16-49-38.png").await).await;
+    assert_eq!(early["captured_at"], "2025-03-20T16:49:38Z");
+    assert!(early["temperature_reading_id"].is_null());
+    process_excel_file_via_api(&app, &experiment_id)
+        .await
+        .unwrap();
+    let early = get_asset(early["id"].clone()).await;
+    assert!(early["temperature_reading_id"].is_string());
+
+    // Uploaded after, and linked on upload
+    let late = get_asset(upload("INP_99999_2025-03-20_16-35-04.png").await).await;
+    assert!(late["temperature_reading_id"].is_string());
+    assert_ne!(
+        late["temperature_reading_id"],
+        early["temperature_reading_id"]
+    );
+
+    // Hours away from any reading, and without a capture time at all
+    let unlinked = get_asset(upload("INP_99999_2025-03-21_09-00-00.png").await).await;
+    assert!(unlinked["captured_at"].is_string());
+    assert!(unlinked["temperature_reading_id"].is_null());
+    let undated = get_asset(upload("overview.png").await).await;
+    assert!(undated["captured_at"].is_null());
+    assert!(undated["temperature_reading_id"].is_null());
+
+    // The results show the linked image at the freeze
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/experiments/{experiment_id}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (_, experiment) = extract_response_body(response).await;
+    let well = |coordinate: &str| {
+        experiment["results"]["trays"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|tray| tray["tray_name"] == "P1")
+            .flat_map(|tray| tray["wells"].as_array().unwrap())
+            .find(|well| well["coordinate"] == coordinate)
+            .unwrap()
+            .clone()
+    };
+    // P1 A1 freezes at 16:49:38 and A10 at 16:35:04
+    assert_eq!(well("A1")["image_asset_id"], early["id"]);
+    assert_eq!(well("A10")["image_asset_id"], late["id"]);
+}
+
+async fn upload_text_file(
+    app: &Router,
+    experiment_id: &str,
+    filename: &str,
+    content: &str,
+) -> Value {
+    let boundary = "integrity_boundary";
+    let body = format!(
+        "--{boundary}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{filename}\"\r\nContent-Type: text/plain\r\n\r\n{content}\r\n--{boundary}--\r\n"
+    );
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(format!("/api/experiments/{experiment_id}/uploads"))
+                .header(
+                    "content-type",
+                    format!("multipart/form-data; boundary={boundary}"),
+                )
+                .body(Body::from(body))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (status, uploaded) = extract_response_body(response).await;
+    assert_eq!(status, StatusCode::OK, "Upload failed: {uploaded:?}");
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/assets/{}", uploaded["id"].as_str().unwrap()))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    extract_response_body(response).await.1
+}
+
+async fn get_integrity_report(app: &Router, experiment_id: &str, query: &str) -> Value {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/experiments/{experiment_id}/integrity{query}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (status, report) = extract_response_body(response).await;
+    assert_eq!(status, StatusCode::OK, "Integrity check failed: {report:?}");
+    report
+}
+
+#[tokio::test]
+async fn test_experiment_integrity_report() {
+    let app = setup_test_app().await;
+    let experiment = create_test_experiment(&app).await.unwrap();
+    let experiment_id = experiment["id"].as_str().unwrap();
+
+    let intact = upload_text_file(&app, experiment_id, "intact.txt", "intact content").await;
+    assert_eq!(
+        intact["checksum_sha256"],
+        crate::assets::services::sha256_hex(b"intact content")
+    );
+    let corrupted = upload_text_file(&app, experiment_id, "corrupted.txt", "original").await;
+    let missing = upload_text_file(&app, experiment_id, "missing.txt", "soon gone").await;
+
+    let report = get_integrity_report(&app, experiment_id, "").await;
+    assert_eq!(report["checked"], 3);
+    assert_eq!(report["ok"], 3);
+    assert_eq!(report["problems"], json!([]));
+
+    let store = &crate::external::s3::MOCK_S3_STORE;
+    store
+        .put_object(corrupted["s3_key"].as_str().unwrap(), b"tampered".to_vec())
+        .unwrap();
+    store
+        .delete_object(missing["s3_key"].as_str().unwrap())
+        .unwrap();
+
+    // An asset registered without a checksum can only be recorded, not verified
+    let legacy_key = format!("test/{experiment_id}/legacy.txt");
+    store.put_object(&legacy_key, b"legacy".to_vec()).unwrap();
+    let (status, _) = post_json_with_headers(
+        &app,
+        "/api/assets",
+        &json!({
+            "experiment_id": experiment_id,
+            "original_filename": "legacy.txt",
+            "s3_key": legacy_key,
+            "type": "unknown",
+            "is_deleted": false
+        }),
+        &[],
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let report = get_integrity_report(&app, experiment_id, "").await;
+    assert_eq!(report["checked"], 4);
+    assert_eq!(report["ok"], 1);
+    assert_eq!(report["corrupted"], 1);
+    assert_eq!(report["missing"], 1);
+    assert_eq!(report["unverified"], 1);
+    let problems: HashMap<&str, &str> = report["problems"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| {
+            (
+                p["original_filename"].as_str().unwrap(),
+                p["status"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(problems["corrupted.txt"], "corrupted");
+    assert_eq!(problems["missing.txt"], "missing");
+    assert_eq!(problems["legacy.txt"], "unverified");
+
+    let report = get_integrity_report(&app, experiment_id, "?record_missing=true").await;
+    assert_eq!(report["recorded"], 1);
+    let report = get_integrity_report(&app, experiment_id, "").await;
+    assert_eq!(report["ok"], 2);
+    assert_eq!(report["unverified"], 0);
+}
+
+#[tokio::test]
+async fn test_identical_uploads_share_storage() {
+    let app = setup_test_app().await;
+    let first_experiment = create_test_experiment(&app).await.unwrap();
+    let first_experiment = first_experiment["id"].as_str().unwrap();
+    let second_experiment = create_experiment_via_api(&app).await.unwrap();
+
+    let content = format!("shared image set {}", uuid::Uuid::new_v4());
+    let first = upload_text_file(&app, first_experiment, "shared.txt", &content).await;
+    assert!(first["blob_s3_key"].is_null());
+    let second = upload_text_file(&app, &second_experiment, "copy.txt", &content).await;
+    let other = upload_text_file(&app, &second_experiment, "other.txt", "different").await;
+    assert!(other["blob_s3_key"].is_null());
+
+    // Both assets now point at one content-addressed object
+    let store = &crate::external::s3::MOCK_S3_STORE;
+    let blob = second["blob_s3_key"].as_str().unwrap().to_string();
+    assert!(blob.ends_with(&crate::assets::services::sha256_hex(content.as_bytes())));
+    assert!(store.get_object(&blob).is_ok());
+    for asset in [&first, &second] {
+        assert!(store.get_object(asset["s3_key"].as_str().unwrap()).is_err());
+    }
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/assets/{}", first["id"].as_str().unwrap()))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (_, first) = extract_response_body(response).await;
+    assert_eq!(first["blob_s3_key"], blob);
+
+    for asset in [&first, &second] {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri(format!(
+                        "/api/assets/{}/download",
+                        asset["id"].as_str().unwrap()
+                    ))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(bytes, content.as_bytes());
+    }
+
+    // The shared object is kept until its last asset is removed
+    let purge = |id: String| {
+        let app = app.clone();
+        async move {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .method("PUT")
+                        .uri(format!("/api/assets/{id}"))
+                        .header("content-type", "application/json")
+                        .body(Body::from(json!({"is_deleted": true}).to_string()))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert!(response.status().is_success());
+            let response = app
+                .oneshot(
+                    Request::builder()
+                        .method("POST")
+                        .uri(format!("/api/assets/{id}/purge"))
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+    };
+    purge(first["id"].as_str().unwrap().to_string()).await;
+    assert!(store.get_object(&blob).is_ok());
+    purge(second["id"].as_str().unwrap().to_string()).await;
+    assert!(store.get_object(&blob).is_err());
+}
+
+/// Helper function to create test image data (small PNG-like binary data)
+fn create_test_image_data() -> Vec<u8> {
+    // Simple binary data that looks like a PNG file
+    vec![
+        0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, // PNG signature
+        0x00, 0x00, 0x00, 0x0D, 0x49, 0x48, 0x44, 0x52, // IHDR chunk
+        0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, // 1x1 pixel
+        0x08, 0x02, 0x00, 0x00, 0x00, 0x90, 0x77, 0x53, // RGB, no interlace
+        0xDE, 0x00, 0x00, 0x00, 0x0C, 0x49, 0x44, 0x41, // IDAT chunk
+        0x54, 0x08, 0x99, 0x01, 0x01, 0x00, 0x00, 0x00, // Compressed data
+        0x00, 0x00, 0x02, 0x00, 0x01, 0xE5, 0x27, 0xDE, // Checksum
+        0xFC, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4E, // IEND chunk
+        0x44, 0xAE, 0x42, 0x60, 0x82,
+    ]
+}
+
+// Expected values from the merged.xlsx file based on previous analysis
+const EXPECTED_TOTAL_WELLS: u64 = 192; // 96 wells per tray × 2 trays
+const EXPECTED_TOTAL_TIME_POINTS: u64 = 6786;
+
+/// Comprehensive validation data derived from merged.csv analysis
+/// This represents the exact phase transitions that should occur when processing the Excel file
+pub struct WellTransitionData {
+    pub tray: &'static str,        // "P1" or "P2"
+    pub coordinate: &'static str,  // "A1", "B2", etc.
+    pub freeze_time: &'static str, // "2025-03-20 16:19:47" - for reference
+    pub temp_probe_1: f64,         // Temperature at freeze time
+}
+
+/// Expected phase transitions extracted from merged.csv analysis
+/// Complete list of all 192 well transitions with exact timestamps and average temperatures
+/// Data generated from systematic CSV analysis of all phase changes (0→1)
+pub const EXPECTED_TRANSITIONS: &[WellTransitionData] = &[
+    WellTransitionData {
+        tray: "P1",
+        coordinate: "A1",
+        freeze_time: "2025-03-20 16:49:38",
+        temp_probe_1: -27.543,
+    },
+    WellTransitionData {
+        tray: "P1",
+        coordinate: "A10",
+        freeze_time: "2025-03-20 16:35:04",
+        temp_probe_1: -22.863,
+    },
+    WellTransitionData {
+        tray: "P1",
+        coordinate: "A11",
+        freeze_time: "2025-03-20 16:32:47",
+        temp_probe_1: -22.151,
+    },
+    WellTransitionData {
+        tray: "P1",
+        coordinate: "A12",
+        freeze_time: "2025-03-20 16:34:09",
+        temp_probe_1: -22.588,
+    },
+    WellTransitionData {
+        tray: "P1",
+        coordinate: "A2",
+        freeze_time: "2025-03-20 16:48:49",
+        temp_probe_1: -27.278,
+    },
+    WellTransitionData {
+        tray: "P1",
+        coordinate: "A3",
+        freeze_time: "2025-03-20 16:50:39",
+        temp_probe_1: -27.856,
+    },
+    WellTransitionData {
+        tray: "P1",
+        coordinate: "A4",
+        freeze_time: "2025-03-20 16:50:58",
+        temp_probe_1: -27.955,
+    },
+    WellTransitionData {
+        tray: "P1",
+        coordinate: "A5",
+        freeze_time: "2025-03-20 16:45:19",
+        temp_probe_1: -26.121,
+    },
+    WellTransitionData {
+        tray: "P1",
+        coordinate: "A6",
+        freeze_time: "2025-03-20 16:51:07",
+        temp_probe_1: -28.003,
+    },
+    WellTransitionData {
+        tray: "P1",
+        coordinate: "A7",
+        freeze_time: "2025-03-20 16:33:57",
+        temp_probe_1: -22.522,
+    },
+    WellTransitionData {
+        tray: "P1",
+        coordinate: "A8",
+        freeze_time: "2025-03-20 16:34:41",
+        temp_probe_1: -22.753,
+    },
+    WellTransitionData {
+        tray: "P1",
+        coordinate: "A9",
+        freeze_time: "2025-03-20 16:42:06",
+        temp_probe_1: -25.078,
+    },
+    WellTransitionData {
+        tray: "P1",
+        coordinate: "B1",
+        freeze_time: "2025-03-20 16:42:35",
+        temp_probe_1: -25.232,
+    },
+    WellTransitionData {
+        tray: "P1",
+        coordinate: "B10",
+        freeze_time: "2025-03-20 16:42:44",
+        temp_probe_1: -25.278,
+    },
+    WellTransitionData {
+        tray: "P1",
+        coordinate: "B11",
+        freeze_time: "2025-03-20 16:43:21",
+        temp_probe_1: -25.469,
+    },
+    WellTransitionData {
+        tray: "P1",
+        coordinate: "B12",
+        freeze_time: "2025-03-20 16:33:20",
+        temp_probe_1: -22.322,
+    },
+    WellTransitionData {
+        tray: "P1",
+        coordinate: "B2",
+        freeze_time: "2025-03-20 16:46:10",
+        temp_probe_1: -26.419,
+    },
+    WellTransitionData {
+        tray: "P1",
+        coordinate: "B3",
+        freeze_time: "2025-03-20 16:52:23",
+        temp_probe_1: -28.435,
+    },
+    WellTransitionData {
+        tray: "P1",
+        coordinate: "B4",
+        freeze_time: "2025-03-20 16:46:09",
+        temp_probe_1: -26.412,
+    },
+    WellTransitionData {
+        tray: "P1",
+        coordinate: "B5",
+        freeze_time: "2025-03-20 16:40:09",
+        temp_probe_1: -24.460,
+    },
+    WellTransitionData {
+        tray: "P1",
+        coordinate: "B6",
+        freeze_time: "2025-03-20 16:46:35",
+        temp_probe_1: -26.555,
+    },
+    WellTransitionData {
+        tray: "P1",
+        coordinate: "B7",
+        freeze_time: "2025-03-20 16:46:33",
+        temp_probe_1: -26.550,
+    },
+    WellTransitionData {
+        tray: "P1",
+        coordinate: "B8",
+        freeze_time: "2025-03-20 16:46:30",
+        temp_probe_1: -26.531,
+    },
+    WellTransitionData {
+        tray: "P1",
+        coordinate: "B9",
+        freeze_time: "2025-03-20 16:42:04",
+        temp_probe_1: -25.065,
+    },
+    WellTransitionData {
+        tray: "P1",
+        coordinate: "C1",
+        freeze_time: "2025-03-20 16:50:22",
+        temp_probe_1: -27.769,
+    },
+    WellTransitionData {
+        tray: "P1",
+        coordinate: "C10",
+        freeze_time: "2025-03-20 16:31:47",
+        temp_probe_1: -21.823,
+    },
+    WellTransitionData {
+        tray: "P1",
+        coordinate: "C11",
+        freeze_time: "2025-03-20 16:36:24",
+        temp_probe_1: -23.257,
+    },
+    WellTransitionData {
+        tray: "P1",
+        coordinate: "C12",
+        freeze_time: "2025-03-20 16:36:50",
+        temp_probe_1: -23.392,
+    },
+    WellTransitionData {
+        tray: "P1",
+        coordinate: "C2",
+        freeze_time: "2025-03-20 16:46:24",
+        temp_probe_1: -26.500,
+    },
+    WellTransitionData {
+        tray: "P1",
+        coordinate: "C3",
+        freeze_time: "2025-03-20 16:49:16",
+        temp_probe_1: -27.425,
+    },
+    WellTransitionData {
+        tray: "P1",
+        coordinate: "C4",
+        freeze_time: "2025-03-20 16:47:00",
+        temp_probe_1: -26.678,
+    },
+    WellTransitionData {
+        tray: "P1",
+        coordinate: "C5",
+        freeze_time: "2025-03-20 16:40:48",
+        temp_probe_1: -24.659,
+    },
+    WellTransitionData {
         tray: "P1",
         coordinate: "C6",
         freeze_time: "2025-03-20 16:36:39",
@@ -2458,494 +3580,1875 @@ pub const EXPECTED_TRANSITIONS: &[WellTransitionData] = &[
     },
 ];
 
-/// Create a tray configuration with embedded trays (post-flattening structure)
-async fn create_test_tray_config_with_trays(app: &Router, name: &str) -> String {
-    let tray_config_data = json!({
-        "name": name,
-        "experiment_default": false,
-        "trays": [
-            {
-                "order_sequence": 1,
-                "rotation_degrees": 0,
-                "name": "P1",
-                "qty_cols": 8,
-                "qty_rows": 12,
-                "well_relative_diameter": 2.5
-            },
-            {
-                "order_sequence": 2,
-                "rotation_degrees": 0,
-                "name": "P2",
-                "qty_cols": 8,
-                "qty_rows": 12,
-                "well_relative_diameter": 2.5
-            }
-        ]
+/// Create a tray configuration with embedded trays (post-flattening structure)
+async fn create_test_tray_config_with_trays(app: &Router, name: &str) -> String {
+    let tray_config_data = json!({
+        "name": name,
+        "experiment_default": false,
+        "trays": [
+            {
+                "order_sequence": 1,
+                "rotation_degrees": 0,
+                "name": "P1",
+                "qty_cols": 8,
+                "qty_rows": 12,
+                "well_relative_diameter": 2.5
+            },
+            {
+                "order_sequence": 2,
+                "rotation_degrees": 0,
+                "name": "P2",
+                "qty_cols": 8,
+                "qty_rows": 12,
+                "well_relative_diameter": 2.5
+            }
+        ]
+    });
+
+    //     "🏗️ Creating tray configuration '{}' with embedded P1/P2 trays: {}",
+    //     name, tray_config_data
+    // );
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/tray_configurations")
+                .header("content-type", "application/json")
+                .body(Body::from(tray_config_data.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body_str = String::from_utf8(body.to_vec()).unwrap();
+
+    assert_eq!(
+        status,
+        StatusCode::CREATED,
+        "Failed to create tray config. Status: {status}, Body: {body_str}"
+    );
+
+    body_str
+}
+
+/// Upload Excel file via API with proper multipart support
+async fn upload_excel_file(app: &Router, experiment_id: &str) -> Value {
+    // Use the new upload + reprocess flow
+    match process_excel_file_via_api(app, experiment_id).await {
+        Ok(processing_result) => {
+            // Convert processing result to the format expected by tests
+            let body_str = serde_json::to_string(&processing_result).unwrap_or_default();
+            json!({
+                "status_code": 200,
+                "body": body_str
+            })
+        }
+        Err(e) => {
+            json!({
+                "status_code": 500,
+                "body": format!("Upload and processing failed: {}", e)
+            })
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_comprehensive_excel_validation_with_specific_transitions() {
+    let app = setup_test_app().await;
+
+    // Step 1: Create experiment with proper tray configuration
+    let tray_config_response =
+        create_test_tray_config_with_trays(&app, "Comprehensive Test Config").await;
+    let tray_config: Value = serde_json::from_str(&tray_config_response).unwrap();
+
+    let tray_config_id = tray_config["id"].as_str().unwrap();
+
+    let experiment_payload = serde_json::json!({
+        "name": "Comprehensive Validation Test",
+        "remarks": "Testing specific well transitions from merged.csv",
+        "tray_configuration_id": tray_config_id,
+        "is_calibration": false
+    });
+
+    let experiment_response = app
+        .clone()
+        .oneshot(
+            axum::http::Request::builder()
+                .method(axum::http::Method::POST)
+                .uri("/api/experiments")
+                .header("content-type", "application/json")
+                .body(axum::body::Body::from(experiment_payload.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let exp_status = experiment_response.status();
+    let exp_body = axum::body::to_bytes(experiment_response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let exp_body_str = String::from_utf8(exp_body.to_vec()).unwrap();
+
+    assert!(
+        !(exp_status != StatusCode::OK && exp_status != StatusCode::CREATED),
+        "Failed to create experiment. Status: {exp_status}, Body: {exp_body_str}"
+    );
+
+    assert_eq!(exp_status, 201);
+    let experiment: Value = serde_json::from_str(&exp_body_str).unwrap();
+    let experiment_id = experiment["id"].as_str().unwrap();
+
+    // Step 2: Upload Excel file and process
+    let upload_result = upload_excel_file(&app, experiment_id).await;
+
+    assert!(
+        upload_result["body"]
+            .as_str()
+            .unwrap()
+            .contains("completed")
+    );
+
+    // Step 3: Fetch experiment results with comprehensive validation
+    let results_response = app
+        .clone()
+        .oneshot(
+            axum::http::Request::builder()
+                .method(axum::http::Method::GET)
+                .uri(format!("/api/experiments/{experiment_id}"))
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(results_response.status(), 200);
+    let results_body = axum::body::to_bytes(results_response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let experiment_with_results: Value = serde_json::from_slice(&results_body).unwrap();
+    let results = &experiment_with_results["results"];
+
+    // Step 4: Validate high-level counts
+    validate_experiment_totals(results);
+
+    // Step 5: Validate specific well transitions
+    validate_specific_well_transitions(&experiment_with_results);
+
+    // Step 6: Validate temperature data accuracy
+    validate_temperature_readings(&experiment_with_results);
+
+    // Step 7: Validate timing accuracy
+    validate_experiment_timing(results);
+}
+
+fn validate_experiment_totals(results: &Value) {
+    // Calculate totals from tray data
+    let mut total_wells = 0;
+    let mut wells_with_data = 0;
+    let mut wells_frozen = 0;
+
+    if let Some(trays) = results["trays"].as_array() {
+        for tray in trays {
+            if let Some(wells) = tray["wells"].as_array() {
+                for well in wells {
+                    total_wells += 1;
+
+                    // Count as having data if it has phase change time
+                    if well.get("first_phase_change_time").is_some() {
+                        wells_with_data += 1;
+                        // If it has a phase change time, it froze
+                        wells_frozen += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    let total_time_points = results["summary"]["total_time_points"]
+        .as_u64()
+        .unwrap_or(0);
+
+    assert_eq!(
+        total_wells,
+        { EXPECTED_TOTAL_WELLS },
+        "Total wells should be {EXPECTED_TOTAL_WELLS}, got {total_wells}"
+    );
+    assert_eq!(
+        wells_with_data,
+        { EXPECTED_TOTAL_WELLS },
+        "Wells with data should be {EXPECTED_TOTAL_WELLS}, got {wells_with_data}"
+    );
+    assert_eq!(
+        wells_frozen,
+        { EXPECTED_TOTAL_WELLS },
+        "All wells should be frozen, got {wells_frozen}"
+    );
+    assert_eq!(
+        total_time_points,
+        { EXPECTED_TOTAL_TIME_POINTS },
+        "Time points should be {EXPECTED_TOTAL_TIME_POINTS}, got {total_time_points}"
+    );
+}
+
+fn validate_specific_well_transitions(experiment: &Value) {
+    // Extract all wells from all trays in the new format
+    let mut all_wells = Vec::new();
+    if let Some(trays) = experiment["results"]["trays"].as_array() {
+        for tray in trays {
+            if let Some(wells) = tray["wells"].as_array() {
+                all_wells.extend(wells.iter());
+            }
+        }
+    }
+
+    // Create lookup map by tray and coordinate
+    let mut well_lookup: HashMap<String, &Value> = HashMap::new();
+    if let Some(trays) = experiment["results"]["trays"].as_array() {
+        for tray in trays {
+            let tray_name = tray["tray_name"].as_str().unwrap_or("unknown");
+            if let Some(wells) = tray["wells"].as_array() {
+                for well in wells {
+                    let coordinate = well["coordinate"].as_str().unwrap_or("unknown");
+                    let key = format!("{tray_name}_{coordinate}");
+                    well_lookup.insert(key, well);
+                }
+            }
+        }
+    }
+
+    // Validate each expected transition
+    for expected in EXPECTED_TRANSITIONS {
+        let key = format!("{}_{}", expected.tray, expected.coordinate);
+        let well = well_lookup
+            .get(&key)
+            .unwrap_or_else(|| panic!("Could not find well {key}"));
+
+        // Validate well has a freeze time
+        let freeze_time = well["first_phase_change_time"]
+            .as_str()
+            .unwrap_or_else(|| panic!("Well {key} should have first_phase_change_time"));
+
+        // Validate well is frozen (has first_phase_change_time means it froze)
+        assert!(
+            well.get("first_phase_change_time").is_some(),
+            "Well {key} should be frozen (have first_phase_change_time)"
+        );
+
+        // Validate temperature probes (optional)
+        let temp_probes = &well["first_phase_change_temperature_probes"];
+        if !temp_probes.is_null() {
+            assert!(
+                temp_probes.is_object(),
+                "Well {key} temperature probe data should be an object if present"
+            );
+        }
+
+        // Parse API timestamp (ISO 8601 format)
+        let api_time = DateTime::parse_from_rfc3339(freeze_time)
+            .expect("Failed to parse API timestamp")
+            .naive_utc();
+
+        // Parse expected timestamp (space-separated format)
+        let expected_time =
+            NaiveDateTime::parse_from_str(expected.freeze_time, "%Y-%m-%d %H:%M:%S")
+                .expect("Failed to parse expected timestamp");
+
+        // Allow 1 second tolerance to handle .999 millisecond differences
+        let diff = (api_time - expected_time).num_milliseconds().abs();
+        assert!(
+            diff <= 1000,
+            "Well {} freeze time difference too large: expected {}, got {} (diff: {}ms)",
+            key,
+            expected.freeze_time,
+            freeze_time,
+            diff
+        );
+
+        // Temperature validation - only if probe data is available
+        if !temp_probes.is_null() && temp_probes.is_object() {
+            // Temperature values are stored as strings (Decimal), need to parse them
+            if let Some(probe1_temp_str) = temp_probes["probe_1"].as_str()
+                && let Ok(probe1_temp) = probe1_temp_str.parse::<f64>()
+            {
+                // Allow tolerance for difference between averaged (CSV analysis) and single probe (API) temperatures
+                // Since CSV analysis used 8-probe averages but API uses individual probe readings
+                let temp_diff = (probe1_temp - expected.temp_probe_1).abs();
+                assert!(
+                    temp_diff < 1.0,
+                    "Well {} probe 1 temperature should be ~{}°C, got {}°C (diff: {}°C)",
+                    key,
+                    expected.temp_probe_1,
+                    probe1_temp,
+                    temp_diff
+                );
+            }
+        }
+    }
+}
+
+fn validate_temperature_readings(_experiment: &Value) {
+
+    // Temperature validation would require time series data
+    // For now, validate that temperature probe structure exists
+}
+
+fn validate_experiment_timing(results: &Value) {
+    let first_timestamp = results["summary"]["first_timestamp"]
+        .as_str()
+        .expect("Should have first_timestamp");
+    let _last_timestamp = results["summary"]["last_timestamp"]
+        .as_str()
+        .expect("Should have last_timestamp");
+
+    // Validate experiment start time matches expected
+    assert!(
+        first_timestamp.contains("2025-03-20"),
+        "Experiment should start on 2025-03-20, got {first_timestamp}"
+    );
+    assert!(
+        first_timestamp.contains("15:13"),
+        "Experiment should start around 15:13, got {first_timestamp}"
+    );
+
+    // Calculate duration (should be about 1 hour 6 minutes based on CSV)
+    // This is a rough validation - exact timing depends on processing
+}
+
+#[tokio::test]
+async fn test_well_coordinate_mapping_accuracy() {
+    let app = setup_test_app().await;
+
+    // Create experiment and upload
+    let tray_config_response = create_test_tray_config_with_trays(&app, "Coordinate Test").await;
+    let tray_config: Value = serde_json::from_str(&tray_config_response).unwrap();
+    let tray_config_id = tray_config["id"].as_str().unwrap();
+
+    let experiment_payload = serde_json::json!({
+        "name": "Coordinate Mapping Test",
+        "tray_configuration_id": tray_config_id,
+        "is_calibration": false
+    });
+
+    let experiment_response = app
+        .clone()
+        .oneshot(
+            axum::http::Request::builder()
+                .method(axum::http::Method::POST)
+                .uri("/api/experiments")
+                .header("content-type", "application/json")
+                .body(axum::body::Body::from(experiment_payload.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let experiment_body = axum::body::to_bytes(experiment_response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let experiment: Value = serde_json::from_slice(&experiment_body).unwrap();
+    let experiment_id = experiment["id"].as_str().unwrap();
+
+    let _upload_result = upload_excel_file(&app, experiment_id).await;
+
+    // Fetch results and validate coordinate mappings
+    let results_response = app
+        .clone()
+        .oneshot(
+            axum::http::Request::builder()
+                .method(axum::http::Method::GET)
+                .uri(format!("/api/experiments/{experiment_id}"))
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let results_body = axum::body::to_bytes(results_response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let experiment_with_results: Value = serde_json::from_slice(&results_body).unwrap();
+    // Extract all wells from all trays in the new format
+    let mut all_wells = Vec::new();
+    if let Some(trays) = experiment_with_results["results"]["trays"].as_array() {
+        for tray in trays {
+            if let Some(wells) = tray["wells"].as_array() {
+                all_wells.extend(wells.iter());
+            }
+        }
+    }
+
+    // Validate that we have exactly 192 wells with proper coordinates
+    assert_eq!(all_wells.len(), 192, "Should have exactly 192 wells");
+
+    let mut p1_wells = 0;
+    let mut p2_wells = 0;
+    let mut coordinate_set = std::collections::HashSet::new();
+
+    // Iterate through trays and their wells to get tray context
+    if let Some(trays) = experiment_with_results["results"]["trays"].as_array() {
+        for tray in trays {
+            let tray_name = tray["tray_name"].as_str().unwrap_or("unknown");
+            if let Some(wells) = tray["wells"].as_array() {
+                for well in wells {
+                    let coordinate = well["coordinate"].as_str().unwrap_or("unknown");
+
+                    match tray_name {
+                        "P1" => p1_wells += 1,
+                        "P2" => p2_wells += 1,
+                        _ => panic!("Unexpected tray name: {tray_name}"),
+                    }
+
+                    // Validate coordinate format (A1-H12)
+                    assert!(
+                        coordinate.len() >= 2 && coordinate.len() <= 3,
+                        "Coordinate {coordinate} should be 2-3 characters"
+                    );
+                    assert!(
+                        coordinate.chars().next().unwrap().is_ascii_uppercase(),
+                        "Coordinate {coordinate} should start with A-H"
+                    );
+
+                    // Add to set to check for duplicates within tray
+                    let full_coord = format!("{tray_name}_{coordinate}");
+                    assert!(
+                        coordinate_set.insert(full_coord.clone()),
+                        "Duplicate coordinate found: {full_coord}"
+                    );
+                }
+            }
+        }
+    }
+
+    assert_eq!(p1_wells, 96, "Should have 96 P1 wells, got {p1_wells}");
+    assert_eq!(p2_wells, 96, "Should have 96 P2 wells, got {p2_wells}");
+}
+
+/// Test image-temperature correlation in results summary
+#[tokio::test]
+async fn test_image_temperature_correlation() {
+    let app = setup_test_app().await;
+
+    // Create experiment
+    let experiment_id = create_experiment_via_api(&app).await.unwrap();
+
+    // Create a simple tray configuration manually (skip complex create function for now)
+    let tray_config_id = create_simple_tray_config(&app).await.unwrap();
+    assign_tray_config_to_experiment_via_api(&app, &experiment_id, &tray_config_id).await;
+
+    // Verify experiment can be retrieved (temperature readings are created through Excel processing)
+    let experiment_response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri(format!("/api/experiments/{experiment_id}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(experiment_response.status(), StatusCode::OK);
+    let body_bytes = to_bytes(experiment_response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let experiment_data: Value = serde_json::from_slice(&body_bytes).unwrap();
+
+    // Verify the experiment exists and has the expected structure
+    assert_eq!(experiment_data["id"].as_str().unwrap(), experiment_id);
+}
+
+/// Helper function to create a test tray configuration with trays and probes
+async fn create_test_tray_configuration_with_probes(app: &Router) -> Result<String, String> {
+    // 1. Create base tray configuration
+    let tray_config_response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/tray_configurations")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({
+                        "name": "Excel Test Configuration",
+                        "experiment_default": false
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    if tray_config_response.status() != StatusCode::CREATED {
+        return Err(format!(
+            "Failed to create tray configuration: {}",
+            tray_config_response.status()
+        ));
+    }
+
+    let tray_config_body = to_bytes(tray_config_response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let tray_config: Value = serde_json::from_slice(&tray_config_body).unwrap();
+    let tray_config_id = tray_config["id"].as_str().unwrap();
+
+    // 2. Update with trays and probes
+    let update_response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri(format!("/api/tray_configurations/{tray_config_id}"))
+                .header("content-type", "application/json")
+                .body(Body::from(json!({
+                    "name": "Excel Test Configuration with Trays",
+                    "experiment_default": false,
+                    "trays": [
+                        {
+                            "name": "P1",
+                            "rotation_degrees": 90,
+                            "well_relative_diameter": 6.4,
+                            "qty_cols": 12,
+                            "qty_rows": 8,
+                            "probe_locations": [
+                                {"name": "Probe 1", "data_column_index": 1, "position_x": 22.1, "position_y": 77.6},
+                                {"name": "Probe 2", "data_column_index": 2, "position_x": 47.1, "position_y": 20},
+                                {"name": "Probe 3", "data_column_index": 3, "position_x": 113, "position_y": 19.5},
+                                {"name": "Probe 4", "data_column_index": 4, "position_x": 143.5, "position_y": 79.5}
+                            ],
+                            "upper_left_corner_x": 416,
+                            "upper_left_corner_y": 75,
+                            "lower_right_corner_x": 135,
+                            "lower_right_corner_y": 542,
+                            "order_sequence": 1
+                        },
+                        {
+                            "name": "P2",
+                            "rotation_degrees": 270,
+                            "well_relative_diameter": 6.4,
+                            "qty_cols": 12,
+                            "qty_rows": 8,
+                            "probe_locations": [
+                                {"name": "Probe 5", "data_column_index": 5, "position_x": 140.8, "position_y": 80},
+                                {"name": "Probe 6", "data_column_index": 6, "position_x": 103.1, "position_y": 21.9},
+                                {"name": "Probe 7", "data_column_index": 7, "position_x": 48.1, "position_y": 22.4},
+                                {"name": "Probe 8", "data_column_index": 8, "position_x": 7.2, "position_y": 93.3}
+                            ],
+                            "upper_left_corner_x": 536,
+                            "upper_left_corner_y": 529,
+                            "lower_right_corner_x": 823,
+                            "lower_right_corner_y": 67,
+                            "order_sequence": 2
+                        }
+                    ]
+                }).to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    if update_response.status() != StatusCode::OK {
+        return Err(format!(
+            "Failed to update tray configuration: {}",
+            update_response.status()
+        ));
+    }
+
+    Ok(tray_config_id.to_string())
+}
+
+/// Helper function to create a test experiment
+async fn create_test_experiment_via_api(
+    app: &Router,
+    tray_config_id: &str,
+) -> Result<String, String> {
+    let experiment_response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/experiments")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({
+                        "name": "Excel Processing API Integration Test",
+                        "username": "test_user@example.com",
+                        "performed_at": "2025-01-01T00:00:00Z",
+                        "is_calibration": false,
+                        "remarks": "Testing Excel upload via API",
+                        "tray_configuration_id": tray_config_id
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    if experiment_response.status() != StatusCode::CREATED {
+        return Err(format!(
+            "Failed to create experiment: {}",
+            experiment_response.status()
+        ));
+    }
+
+    let experiment_body = to_bytes(experiment_response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let experiment: Value = serde_json::from_slice(&experiment_body).unwrap();
+    let experiment_id = experiment["id"].as_str().unwrap();
+
+    Ok(experiment_id.to_string())
+}
+
+/// Helper function to process Excel file via API using upload + reprocess flow
+async fn process_excel_file_via_api(app: &Router, experiment_id: &str) -> Result<Value, String> {
+    // Load test Excel file
+    let excel_path = "src/experiments/test_resources/merged.xlsx";
+    let excel_data = fs::read(excel_path).map_err(|e| format!("Failed to read Excel file: {e}"))?;
+
+    // Create multipart form data (binary safe)
+    let boundary = "test-boundary-12345";
+    let mut multipart_body = Vec::new();
+
+    multipart_body.extend_from_slice(format!(
+        "--{boundary}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"merged.xlsx\"\r\nContent-Type: application/vnd.openxmlformats-officedocument.spreadsheetml.sheet\r\n\r\n"
+    ).as_bytes());
+    multipart_body.extend_from_slice(&excel_data);
+    multipart_body.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+
+    // 1. Upload the file via API
+    let upload_response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(format!("/api/experiments/{experiment_id}/uploads"))
+                .header(
+                    "content-type",
+                    format!("multipart/form-data; boundary={boundary}"),
+                )
+                .body(Body::from(multipart_body))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let status = upload_response.status();
+    let upload_body = to_bytes(upload_response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+
+    if status != StatusCode::OK {
+        let error_text = String::from_utf8_lossy(&upload_body);
+        return Err(format!(
+            "Excel upload failed with status {status}: {error_text}"
+        ));
+    }
+
+    let upload_result: Value = serde_json::from_slice(&upload_body)
+        .map_err(|e| format!("Failed to parse upload result: {e}"))?;
+
+    // Extract asset ID from upload response
+    let asset_id = upload_result.get("id")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| "No asset ID in upload response".to_string())?;
+
+    // 2. Reprocess the asset to trigger Excel processing
+    let reprocess_response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(format!("/api/assets/{asset_id}/reprocess"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let status = reprocess_response.status();
+    let reprocess_body = to_bytes(reprocess_response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+
+    if status != StatusCode::OK {
+        let error_text = String::from_utf8_lossy(&reprocess_body);
+        return Err(format!(
+            "Excel reprocessing failed with status {status}: {error_text}"
+        ));
+    }
+
+    let processing_result: Value = serde_json::from_slice(&reprocess_body)
+        .map_err(|e| format!("Failed to parse reprocessing result: {e}"))?;
+
+    Ok(processing_result)
+}
+
+/// Helper function to validate Excel processing results
+fn validate_excel_processing_results(processing_result: &Value) -> Result<(), String> {
+    let success = processing_result["success"].as_bool().unwrap_or(false);
+    let empty_errors = vec![];
+    let errors = processing_result["errors"]
+        .as_array()
+        .unwrap_or(&empty_errors);
+
+    let temp_readings_created = processing_result["temperature_readings_created"]
+        .as_u64()
+        .unwrap_or(0);
+    let _probe_readings_created = processing_result["probe_temperature_readings_created"]
+        .as_u64()
+        .unwrap_or(0);
+    let phase_transitions_created = processing_result["phase_transitions_created"]
+        .as_u64()
+        .unwrap_or(0);
+
+    let has_reasonable_data = temp_readings_created > 5000 && phase_transitions_created > 0;
+
+    if !has_reasonable_data {
+        return Err(format!(
+            "Processing failed without reasonable data. Success: {success}, Errors: {errors:?}"
+        ));
+    }
+
+    // Verify expected data volumes
+    if temp_readings_created <= 6000 {
+        return Err(format!(
+            "Expected >6000 temperature readings, got {temp_readings_created}"
+        ));
+    }
+    if phase_transitions_created == 0 {
+        return Err("Expected phase transitions, got 0".to_string());
+    }
+
+    //     "   ✅ Core Excel processing working via API (temp readings: {}, phase transitions: {})",
+    //     temp_readings_created, phase_transitions_created
+    // );
+
+    Ok(())
+}
+
+/// Helper function to verify experiment results are accessible via API
+async fn verify_experiment_results_api(app: &Router, experiment_id: &str) -> Result<(), String> {
+    let results_response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri(format!("/api/experiments/{experiment_id}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    if results_response.status() != StatusCode::OK {
+        return Err(format!(
+            "Failed to retrieve experiment results: {}",
+            results_response.status()
+        ));
+    }
+
+    let results_body = to_bytes(results_response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let experiment_data: Value = serde_json::from_slice(&results_body)
+        .map_err(|e| format!("Failed to parse experiment data: {e}"))?;
+
+    // Check that results include the processed data
+    if let Some(results) = experiment_data.get("results")
+        && let Some(summary) = results.get("summary")
+    {
+        let total_time_points = summary["total_time_points"].as_u64().unwrap_or(0);
+        if total_time_points <= 6000 {
+            return Err(format!(
+                "Results should show >6000 time points, got {total_time_points}"
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// API Integration test for Excel processing endpoint with real Excel file
+/// This tests the full HTTP request/response cycle through /api/experiments/{id}/process-excel
+#[tokio::test]
+async fn test_excel_processing_api_integration() {
+    let app = setup_test_app().await;
+
+    // 1. Setup: Create tray configuration with trays and probes
+    let tray_config_id = create_test_tray_configuration_with_probes(&app)
+        .await
+        .expect("Failed to create tray configuration");
+
+    // 2. Setup: Create experiment
+    let experiment_id = create_test_experiment_via_api(&app, &tray_config_id)
+        .await
+        .expect("Failed to create experiment");
+
+    // 3. Process: Upload and process Excel file
+    let processing_result = process_excel_file_via_api(&app, &experiment_id)
+        .await
+        .expect("Failed to process Excel file");
+
+    // 4. Validate: Check processing results
+    validate_excel_processing_results(&processing_result)
+        .expect("Processing results validation failed");
+
+    // 5. Validate: Check that results are accessible via API
+    verify_experiment_results_api(&app, &experiment_id)
+        .await
+        .expect("Failed to verify experiment results API");
+}
+
+#[tokio::test]
+async fn test_experiment_results_take_few_queries() {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let mut config = crate::config::Config::for_tests();
+    config.keycloak_url = String::new();
+    let mut db = crate::config::test_helpers::setup_test_db().await;
+    let queries = Arc::new(AtomicUsize::new(0));
+    let counter = queries.clone();
+    db.set_metric_callback(move |_| {
+        counter.fetch_add(1, Ordering::SeqCst);
     });
+    let app = crate::routes::build_router(&db, &config);
 
-    //     "🏗️ Creating tray configuration '{}' with embedded P1/P2 trays: {}",
-    //     name, tray_config_data
-    // );
+    let tray_config_id = create_test_tray_configuration_with_probes(&app)
+        .await
+        .unwrap();
+    let experiment_id = create_test_experiment_via_api(&app, &tray_config_id)
+        .await
+        .unwrap();
+    process_excel_file_via_api(&app, &experiment_id)
+        .await
+        .unwrap();
+
+    // The results of all wells are read in a handful of queries, however
+    // many wells and readings there are
+    let before = queries.load(Ordering::SeqCst);
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/experiments/{experiment_id}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (status, experiment) = extract_response_body(response).await;
+    let issued = queries.load(Ordering::SeqCst) - before;
+    assert_eq!(status, StatusCode::OK);
+    let wells: usize = experiment["results"]["trays"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|tray| tray["wells"].as_array().unwrap().len())
+        .sum();
+    assert_eq!(wells, 192);
+    assert!(issued <= 16, "{issued} queries to read an experiment");
+}
+
+#[tokio::test]
+async fn test_results_kept_after_processing() {
+    use crate::experiments::phase_transitions::models as phase_transitions;
+    use crate::experiments::results_summaries::models as results_summaries;
+    use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+
+    let mut config = crate::config::Config::for_tests();
+    config.keycloak_url = String::new();
+    let db = crate::config::test_helpers::setup_test_db().await;
+    let app = crate::routes::build_router(&db, &config);
+    let tray_config_id = create_test_tray_configuration_with_probes(&app)
+        .await
+        .unwrap();
+    let experiment_id = create_test_experiment_via_api(&app, &tray_config_id)
+        .await
+        .unwrap();
+    let processed = process_excel_file_via_api(&app, &experiment_id)
+        .await
+        .unwrap();
+    let id = uuid::Uuid::parse_str(&experiment_id).unwrap();
+    let kept = results_summaries::Entity::find()
+        .filter(results_summaries::Column::ExperimentId.eq(id))
+        .one(&db)
+        .await
+        .unwrap()
+        .expect("Results are kept when processing finishes");
+    assert_eq!(kept.job_id.unwrap().to_string(), processed["job_id"]);
+
+    let frozen_wells =
+        |experiment: &Value| experiment["results"]["summary"]["frozen_wells"].clone();
+    let experiment = get_experiment_data(&app, &experiment_id).await;
+    assert_eq!(experiment["results"], kept.results);
+    assert!(frozen_wells(&experiment).as_u64().unwrap() > 0);
+
+    // Transitions edited outside the API show once the results are recomputed
+    phase_transitions::Entity::delete_many()
+        .filter(phase_transitions::Column::ExperimentId.eq(id))
+        .exec(&db)
+        .await
+        .unwrap();
+    let experiment = get_experiment_data(&app, &experiment_id).await;
+    assert!(frozen_wells(&experiment).as_u64().unwrap() > 0);
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(format!(
+                    "/api/experiments/{experiment_id}/results/recompute"
+                ))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (status, results) = extract_response_body(response).await;
+    assert_eq!(status, StatusCode::OK, "{results}");
+    assert_eq!(results["summary"]["frozen_wells"], 0);
+    let experiment = get_experiment_data(&app, &experiment_id).await;
+    assert_eq!(frozen_wells(&experiment), 0);
+
+    // Changes through the API drop the kept results
+    let (status, _) =
+        put_excluded_wells(&app, &experiment_id, json!([{"coordinate": "P1:A1"}])).await;
+    assert_eq!(status, StatusCode::OK);
+    let kept = results_summaries::Entity::find()
+        .filter(results_summaries::Column::ExperimentId.eq(id))
+        .one(&db)
+        .await
+        .unwrap();
+    assert!(kept.is_none());
+    let experiment = get_experiment_data(&app, &experiment_id).await;
+    assert_eq!(experiment["results"]["summary"]["excluded_wells"], 1);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(format!(
+                    "/api/experiments/{}/results/recompute",
+                    uuid::Uuid::new_v4()
+                ))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+/// Events of a server-sent event stream, as their names and JSON data
+fn parse_events(body: &str) -> Vec<(String, Value)> {
+    body.split("\n\n")
+        .filter_map(|block| {
+            let name = block.lines().find_map(|line| line.strip_prefix("event: "))?;
+            let data = block.lines().find_map(|line| line.strip_prefix("data: "))?;
+            Some((name.to_string(), serde_json::from_str(data).ok()?))
+        })
+        .collect()
+}
+
+#[tokio::test]
+#[allow(clippy::too_many_lines)] // Processing in the foreground, then followed in the background
+async fn test_processing_progress_stream() {
+    let app = setup_test_app().await;
+    let tray_config_id = create_test_tray_configuration_with_probes(&app)
+        .await
+        .expect("Failed to create tray configuration");
+    let experiment_id = create_test_experiment_via_api(&app, &tray_config_id)
+        .await
+        .expect("Failed to create experiment");
+    let processed = process_excel_file_via_api(&app, &experiment_id)
+        .await
+        .expect("Failed to process Excel file");
+
+    // A finished job's progress is kept
+    let job_id = processed["job_id"].as_str().unwrap();
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!(
+                    "/api/experiments/{experiment_id}/process-status/{job_id}"
+                ))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (status, progress) = extract_response_body(response).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(progress["status"], "completed");
+    assert_eq!(progress["percent_complete"], 100);
+    assert_eq!(
+        progress["temperature_readings_written"],
+        processed["temperature_readings_created"]
+    );
+
+    // Processing in the background is followed on its stream to the end
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!(
+                    "/api/assets?filter=%7B%22experiment_id%22%3A%22{experiment_id}%22%7D"
+                ))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (_, assets) = extract_response_body(response).await;
+    let asset_id = assets[0]["id"].as_str().unwrap();
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(format!("/api/experiments/{experiment_id}/process-asset"))
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({"assetId": asset_id, "background": true}).to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (status, started) = extract_response_body(response).await;
+    assert_eq!(status, StatusCode::ACCEPTED, "{started}");
+    let job_id = started["jobId"].as_str().unwrap();
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!(
+                    "/api/experiments/{experiment_id}/process-status/{job_id}/stream"
+                ))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "text/event-stream");
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let events = parse_events(&String::from_utf8_lossy(&body));
+    let (last, progress) = events.last().unwrap();
+    assert_eq!(last, "completed", "{events:?}");
+    assert_eq!(progress["percent_complete"], 100);
+    assert!(progress["rows_parsed"].as_u64().unwrap() > 0);
+    assert_eq!(
+        progress["temperature_readings_written"],
+        processed["temperature_readings_created"]
+    );
+    assert!(
+        events[..events.len() - 1]
+            .iter()
+            .all(|(name, _)| name == "progress")
+    );
+
+    // Jobs are only reached through their experiment
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!(
+                    "/api/experiments/{}/process-status/{job_id}/stream",
+                    uuid::Uuid::new_v4()
+                ))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_temperatures_are_streamed_as_lines() {
+    let app = setup_test_app().await;
+    let tray_config_id = create_test_tray_configuration_with_probes(&app)
+        .await
+        .expect("Failed to create tray configuration");
+    let experiment_id = create_test_experiment_via_api(&app, &tray_config_id)
+        .await
+        .expect("Failed to create experiment");
+    let processed = process_excel_file_via_api(&app, &experiment_id)
+        .await
+        .expect("Failed to process Excel file");
+
+    let stream = |query: String| {
+        let app = app.clone();
+        let experiment_id = experiment_id.clone();
+        async move {
+            let response = app
+                .oneshot(
+                    Request::builder()
+                        .uri(format!("/api/experiments/{experiment_id}/temperatures{query}"))
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.headers()["content-type"], "application/x-ndjson");
+            let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            String::from_utf8(body.to_vec())
+                .unwrap()
+                .lines()
+                .map(|line| serde_json::from_str::<Value>(line).unwrap())
+                .collect::<Vec<_>>()
+        }
+    };
+
+    // Every reading, across pages, oldest first
+    let readings = stream(String::new()).await;
+    assert_eq!(
+        readings.len() as u64,
+        processed["temperature_readings_created"].as_u64().unwrap()
+    );
+    let timestamps: Vec<&str> = readings
+        .iter()
+        .map(|reading| reading["timestamp"].as_str().unwrap())
+        .collect();
+    assert!(timestamps.windows(2).all(|pair| pair[0] <= pair[1]));
+    let ids: std::collections::HashSet<&str> = readings
+        .iter()
+        .map(|reading| reading["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(ids.len(), readings.len());
+    assert_eq!(readings[0]["experiment_id"], experiment_id.as_str());
+    assert!(!readings[0]["probe_readings"].as_array().unwrap().is_empty());
+    assert!(readings[0]["average"].is_string() || readings[0]["average"].is_number());
+
+    // Readings from a time on
+    let from = timestamps[100].replace('+', "%2B");
+    let later = stream(format!("?from={from}")).await;
+    assert_eq!(later.len(), readings.len() - 100);
+    assert_eq!(later[0]["id"], readings[100]["id"]);
 
     let response = app
         .clone()
         .oneshot(
             Request::builder()
-                .method("POST")
-                .uri("/api/tray_configurations")
-                .header("content-type", "application/json")
-                .body(Body::from(tray_config_data.to_string()))
+                .uri(format!(
+                    "/api/experiments/{}/temperatures",
+                    uuid::Uuid::new_v4()
+                ))
+                .body(Body::empty())
                 .unwrap(),
         )
         .await
         .unwrap();
-
-    let status = response.status();
-    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
-        .await
-        .unwrap();
-    let body_str = String::from_utf8(body.to_vec()).unwrap();
-
-    assert_eq!(
-        status,
-        StatusCode::CREATED,
-        "Failed to create tray config. Status: {status}, Body: {body_str}"
-    );
-
-    body_str
-}
-
-/// Upload Excel file via API with proper multipart support
-async fn upload_excel_file(app: &Router, experiment_id: &str) -> Value {
-    // Use the new upload + reprocess flow
-    match process_excel_file_via_api(app, experiment_id).await {
-        Ok(processing_result) => {
-            // Convert processing result to the format expected by tests
-            let body_str = serde_json::to_string(&processing_result).unwrap_or_default();
-            json!({
-                "status_code": 200,
-                "body": body_str
-            })
-        }
-        Err(e) => {
-            json!({
-                "status_code": 500,
-                "body": format!("Upload and processing failed: {}", e)
-            })
-        }
-    }
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
 }
 
+/// Test to verify sample well filtering issue - samples should not include wells from other treatments
+/// This test confirms the bug where each treatment gets assigned all 192 wells instead of just its region wells
 #[tokio::test]
-async fn test_comprehensive_excel_validation_with_specific_transitions() {
+async fn test_sample_well_filtering_after_excel_processing() {
     let app = setup_test_app().await;
 
-    // Step 1: Create experiment with proper tray configuration
-    let tray_config_response =
-        create_test_tray_config_with_trays(&app, "Comprehensive Test Config").await;
-    let tray_config: Value = serde_json::from_str(&tray_config_response).unwrap();
+    // 1. Setup: Create tray configuration with trays and probes
+    let tray_config_id = create_test_tray_configuration_with_probes(&app)
+        .await
+        .expect("Failed to create tray configuration");
 
-    let tray_config_id = tray_config["id"].as_str().unwrap();
+    // 2. Setup: Create experiment
+    let experiment_id = create_test_experiment_via_api(&app, &tray_config_id)
+        .await
+        .expect("Failed to create experiment");
 
-    let experiment_payload = serde_json::json!({
-        "name": "Comprehensive Validation Test",
-        "remarks": "Testing specific well transitions from merged.csv",
-        "tray_configuration_id": tray_config_id,
-        "is_calibration": false
-    });
+    // 3. Create sample and treatments to be used in regions
+    let sample_id = create_test_sample_and_treatments(&app)
+        .await
+        .expect("Failed to create sample and treatments");
 
-    let experiment_response = app
-        .clone()
-        .oneshot(
-            axum::http::Request::builder()
-                .method(axum::http::Method::POST)
-                .uri("/api/experiments")
-                .header("content-type", "application/json")
-                .body(axum::body::Body::from(experiment_payload.to_string()))
-                .unwrap(),
-        )
+    // 4. Add regions to the experiment by updating it
+    update_experiment_with_regions(&app, &experiment_id, &sample_id)
         .await
-        .unwrap();
+        .expect("Failed to add regions to experiment");
 
-    let exp_status = experiment_response.status();
-    let exp_body = axum::body::to_bytes(experiment_response.into_body(), usize::MAX)
+    // 5. Process: Upload and process Excel file
+    let _processing_result = process_excel_file_via_api(&app, &experiment_id)
         .await
-        .unwrap();
-    let exp_body_str = String::from_utf8(exp_body.to_vec()).unwrap();
+        .expect("Failed to process Excel file");
 
-    assert!(
-        !(exp_status != StatusCode::OK && exp_status != StatusCode::CREATED),
-        "Failed to create experiment. Status: {exp_status}, Body: {exp_body_str}"
-    );
+    // 6. Get experiment details to find regions and samples
+    let experiment_data = get_experiment_data(&app, &experiment_id).await;
 
-    assert_eq!(exp_status, 201);
-    let experiment: Value = serde_json::from_str(&exp_body_str).unwrap();
-    let experiment_id = experiment["id"].as_str().unwrap();
+    // 7. Find the sample used in the experiment (should be the first sample from first region)
+    let regions = experiment_data["regions"].as_array().unwrap();
+    assert!(!regions.is_empty(), "Experiment should have regions");
 
-    // Step 2: Upload Excel file and process
-    let upload_result = upload_excel_file(&app, experiment_id).await;
+    let first_region = &regions[0];
+    let sample_id = first_region["treatment"]["sample"]["id"]
+        .as_str()
+        .expect("Should have sample ID in first region");
 
-    assert!(
-        upload_result["body"]
-            .as_str()
-            .unwrap()
-            .contains("completed")
-    );
+    // 8. Test: Get sample data via API
+    let sample_data = get_sample_data(&app, sample_id).await;
 
-    // Step 3: Fetch experiment results with comprehensive validation
-    let results_response = app
+    // 9. Validate: Check treatment well counts
+    validate_treatment_well_counts(&sample_data);
+}
+
+async fn put_excluded_wells(
+    app: &Router,
+    experiment_id: &str,
+    wells: Value,
+) -> (StatusCode, Value) {
+    let response = app
         .clone()
         .oneshot(
-            axum::http::Request::builder()
-                .method(axum::http::Method::GET)
-                .uri(format!("/api/experiments/{experiment_id}"))
-                .body(axum::body::Body::empty())
+            Request::builder()
+                .method("PUT")
+                .uri(format!("/api/experiments/{experiment_id}/excluded-wells"))
+                .header("content-type", "application/json")
+                .body(Body::from(json!({ "wells": wells }).to_string()))
                 .unwrap(),
         )
         .await
         .unwrap();
-
-    assert_eq!(results_response.status(), 200);
-    let results_body = axum::body::to_bytes(results_response.into_body(), usize::MAX)
-        .await
-        .unwrap();
-    let experiment_with_results: Value = serde_json::from_slice(&results_body).unwrap();
-    let results = &experiment_with_results["results"];
-
-    // Step 4: Validate high-level counts
-    validate_experiment_totals(results);
-
-    // Step 5: Validate specific well transitions
-    validate_specific_well_transitions(&experiment_with_results);
-
-    // Step 6: Validate temperature data accuracy
-    validate_temperature_readings(&experiment_with_results);
-
-    // Step 7: Validate timing accuracy
-    validate_experiment_timing(results);
+    extract_response_body(response).await
 }
 
-fn validate_experiment_totals(results: &Value) {
-    // Calculate totals from tray data
-    let mut total_wells = 0;
-    let mut wells_with_data = 0;
-    let mut wells_frozen = 0;
-
-    if let Some(trays) = results["trays"].as_array() {
-        for tray in trays {
-            if let Some(wells) = tray["wells"].as_array() {
-                for well in wells {
-                    total_wells += 1;
-
-                    // Count as having data if it has phase change time
-                    if well.get("first_phase_change_time").is_some() {
-                        wells_with_data += 1;
-                        // If it has a phase change time, it froze
-                        wells_frozen += 1;
-                    }
-                }
-            }
-        }
-    }
+#[tokio::test]
+#[allow(clippy::too_many_lines)]
+async fn test_excluded_wells_left_out_of_statistics() {
+    let app = setup_test_app().await;
+    let tray_config_id = create_test_tray_configuration_with_probes(&app)
+        .await
+        .expect("Failed to create tray configuration");
+    let experiment_id = create_test_experiment_via_api(&app, &tray_config_id)
+        .await
+        .expect("Failed to create experiment");
+    let sample_id = create_test_sample_and_treatments(&app)
+        .await
+        .expect("Failed to create sample and treatments");
+    update_experiment_with_regions(&app, &experiment_id, &sample_id)
+        .await
+        .expect("Failed to add regions to experiment");
+    process_excel_file_via_api(&app, &experiment_id)
+        .await
+        .expect("Failed to process Excel file");
 
-    let total_time_points = results["summary"]["total_time_points"]
-        .as_u64()
-        .unwrap_or(0);
+    let before = get_experiment_data(&app, &experiment_id).await;
+    let summary = &before["results"]["summary"];
+    assert_eq!(summary["total_wells"], 192);
+    assert_eq!(summary["excluded_wells"], 0);
+    let frozen_before = summary["frozen_wells"].as_u64().unwrap();
+
+    // Both wells are in the heat treated region of P1
+    let (status, excluded) = put_excluded_wells(
+        &app,
+        &experiment_id,
+        json!([
+            {"coordinate": "P1:A5", "reason": "contaminated"},
+            {"coordinate": "P1:b5"}
+        ]),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{excluded}");
+    let excluded = excluded.as_array().unwrap();
+    assert_eq!(excluded.len(), 2);
+    assert_eq!(excluded[0]["coordinate"], "P1:A5");
+    assert_eq!(excluded[0]["reason"], "contaminated");
+    assert_eq!(excluded[1]["coordinate"], "P1:B5");
+    assert!(excluded[1]["reason"].is_null());
+
+    // The wells keep their data but are marked and not counted
+    let after = get_experiment_data(&app, &experiment_id).await;
+    let summary = &after["results"]["summary"];
+    assert_eq!(summary["total_wells"], 192);
+    assert_eq!(summary["excluded_wells"], 2);
+    let frozen_after = summary["frozen_wells"].as_u64().unwrap();
+    let p1 = after["results"]["trays"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|tray| tray["tray_name"] == "P1")
+        .unwrap();
+    let well = |coordinate: &str| {
+        p1["wells"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|well| well["coordinate"] == coordinate)
+            .unwrap()
+            .clone()
+    };
+    let a5 = well("A5");
+    assert_eq!(a5["excluded"], true);
+    assert_eq!(a5["exclusion_reason"], "contaminated");
+    assert_eq!(well("B5")["excluded"], true);
+    assert_eq!(well("C5")["excluded"], false);
+    let excluded_frozen = ["A5", "B5"]
+        .iter()
+        .filter(|coordinate| !well(coordinate)["first_phase_change_time"].is_null())
+        .count() as u64;
+    assert_eq!(frozen_after, frozen_before - excluded_frozen);
+    let fraction: f64 = summary["frozen_fraction"]
+        .as_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    #[allow(clippy::cast_precision_loss)]
+    let expected = frozen_after as f64 / 190.0;
+    assert!((fraction - expected).abs() < 1e-4);
 
+    // Treatment statistics skip the excluded events
+    let sample_data = get_sample_data(&app, &sample_id).await;
+    let heat = sample_data["treatments"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|treatment| treatment["name"] == "heat")
+        .unwrap();
+    let results = heat["experimental_results"].as_array().unwrap();
     assert_eq!(
-        total_wells,
-        { EXPECTED_TOTAL_WELLS },
-        "Total wells should be {EXPECTED_TOTAL_WELLS}, got {total_wells}"
-    );
-    assert_eq!(
-        wells_with_data,
-        { EXPECTED_TOTAL_WELLS },
-        "Wells with data should be {EXPECTED_TOTAL_WELLS}, got {wells_with_data}"
-    );
-    assert_eq!(
-        wells_frozen,
-        { EXPECTED_TOTAL_WELLS },
-        "All wells should be frozen, got {wells_frozen}"
+        results
+            .iter()
+            .filter(|event| event["excluded"] == true)
+            .count(),
+        2
     );
+    let listed = results.len();
+    assert_eq!(heat["statistics"]["total_wells"], listed - 2);
     assert_eq!(
-        total_time_points,
-        { EXPECTED_TOTAL_TIME_POINTS },
-        "Time points should be {EXPECTED_TOTAL_TIME_POINTS}, got {total_time_points}"
+        heat["dilution_summaries"][0]["statistics"]["total_wells"],
+        listed - 2
     );
-}
 
-fn validate_specific_well_transitions(experiment: &Value) {
-    // Extract all wells from all trays in the new format
-    let mut all_wells = Vec::new();
-    if let Some(trays) = experiment["results"]["trays"].as_array() {
-        for tray in trays {
-            if let Some(wells) = tray["wells"].as_array() {
-                all_wells.extend(wells.iter());
-            }
-        }
+    // Invalid coordinates are rejected without changing the list
+    for wells in [
+        json!([{"coordinate": "A5"}]),
+        json!([{"coordinate": "P9:A1"}]),
+        json!([{"coordinate": "P1:Z99"}]),
+        json!([{"coordinate": "P1:"}]),
+    ] {
+        let (status, _) = put_excluded_wells(&app, &experiment_id, wells).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
     }
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/experiments/{experiment_id}/excluded-wells"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (status, listed) = extract_response_body(response).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(listed.as_array().unwrap().len(), 2);
+
+    // Keeping a well keeps its exclusion time; an empty list includes all again
+    let (_, kept) = put_excluded_wells(
+        &app,
+        &experiment_id,
+        json!([{"coordinate": "P1:A5", "reason": "empty"}]),
+    )
+    .await;
+    assert_eq!(kept[0]["reason"], "empty");
+    assert_eq!(kept[0]["excluded_at"], excluded[0]["excluded_at"]);
+    let (status, cleared) = put_excluded_wells(&app, &experiment_id, json!([])).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(cleared.as_array().unwrap().is_empty());
+    let summary = &get_experiment_data(&app, &experiment_id).await["results"]["summary"];
+    assert_eq!(summary["excluded_wells"], 0);
+    assert_eq!(summary["frozen_wells"].as_u64().unwrap(), frozen_before);
+
+    let (status, _) = put_excluded_wells(&app, &uuid::Uuid::new_v4().to_string(), json!([])).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_interpolated_well_temperatures() {
+    let app = setup_test_app().await;
+    let tray_config_id = create_test_tray_configuration_with_probes(&app)
+        .await
+        .expect("Failed to create tray configuration");
+    let experiment_id = create_test_experiment_via_api(&app, &tray_config_id)
+        .await
+        .expect("Failed to create experiment");
+    let sample_id = create_test_sample_and_treatments(&app)
+        .await
+        .expect("Failed to create sample and treatments");
+    update_experiment_with_regions(&app, &experiment_id, &sample_id)
+        .await
+        .expect("Failed to add regions to experiment");
+    process_excel_file_via_api(&app, &experiment_id)
+        .await
+        .expect("Failed to process Excel file");
 
-    // Create lookup map by tray and coordinate
-    let mut well_lookup: HashMap<String, &Value> = HashMap::new();
-    if let Some(trays) = experiment["results"]["trays"].as_array() {
-        for tray in trays {
-            let tray_name = tray["tray_name"].as_str().unwrap_or("unknown");
-            if let Some(wells) = tray["wells"].as_array() {
-                for well in wells {
-                    let coordinate = well["coordinate"].as_str().unwrap_or("unknown");
-                    let key = format!("{tray_name}_{coordinate}");
-                    well_lookup.insert(key, well);
-                }
+    let decimal = |value: &Value| -> f64 { value.as_str().unwrap().parse().unwrap() };
+    let experiment = get_experiment_data(&app, &experiment_id).await;
+    let mut frozen_wells = 0;
+    for tray in experiment["results"]["trays"].as_array().unwrap() {
+        for well in tray["wells"].as_array().unwrap() {
+            if well["temperatures"].is_null() {
+                assert!(well["interpolated_temperature"].is_null());
+                continue;
             }
+            frozen_wells += 1;
+            // Only the probes of the well's own tray are used, and a weighted
+            // mean stays within their readings
+            let tray_readings: Vec<f64> = well["temperatures"]["probe_readings"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .filter(|reading| reading["probe_tray_id"] == tray["tray_id"])
+                .map(|reading| decimal(&reading["temperature"]))
+                .collect();
+            assert_eq!(tray_readings.len(), 4, "{}", well["coordinate"]);
+            let interpolated = decimal(&well["interpolated_temperature"]);
+            let min = tray_readings.iter().copied().fold(f64::INFINITY, f64::min);
+            let max = tray_readings
+                .iter()
+                .copied()
+                .fold(f64::NEG_INFINITY, f64::max);
+            assert!(
+                (min - 1e-3..=max + 1e-3).contains(&interpolated),
+                "{} interpolated {interpolated} outside {min}..{max}",
+                well["coordinate"]
+            );
         }
     }
+    assert!(frozen_wells > 0);
 
-    // Validate each expected transition
-    for expected in EXPECTED_TRANSITIONS {
-        let key = format!("{}_{}", expected.tray, expected.coordinate);
-        let well = well_lookup
-            .get(&key)
-            .unwrap_or_else(|| panic!("Could not find well {key}"));
-
-        // Validate well has a freeze time
-        let freeze_time = well["first_phase_change_time"]
-            .as_str()
-            .unwrap_or_else(|| panic!("Well {key} should have first_phase_change_time"));
-
-        // Validate well is frozen (has first_phase_change_time means it froze)
+    let sample_data = get_sample_data(&app, &sample_id).await;
+    let events: Vec<&Value> = sample_data["treatments"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .flat_map(|treatment| treatment["experimental_results"].as_array().unwrap())
+        .collect();
+    assert!(!events.is_empty());
+    for event in events {
         assert!(
-            well.get("first_phase_change_time").is_some(),
-            "Well {key} should be frozen (have first_phase_change_time)"
+            event["nucleation_temperature_interpolated_celsius"].is_string(),
+            "{event}"
         );
+    }
+}
 
-        // Validate temperature probes (optional)
-        let temp_probes = &well["first_phase_change_temperature_probes"];
-        if !temp_probes.is_null() {
-            assert!(
-                temp_probes.is_object(),
-                "Well {key} temperature probe data should be an object if present"
-            );
-        }
-
-        // Parse API timestamp (ISO 8601 format)
-        let api_time = DateTime::parse_from_rfc3339(freeze_time)
-            .expect("Failed to parse API timestamp")
-            .naive_utc();
+async fn get_experiment_data(app: &Router, experiment_id: &str) -> Value {
+    let experiment_response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri(format!("/api/experiments/{experiment_id}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
 
-        // Parse expected timestamp (space-separated format)
-        let expected_time =
-            NaiveDateTime::parse_from_str(expected.freeze_time, "%Y-%m-%d %H:%M:%S")
-                .expect("Failed to parse expected timestamp");
+    assert_eq!(experiment_response.status(), StatusCode::OK);
+    let experiment_body = to_bytes(experiment_response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&experiment_body).unwrap()
+}
 
-        // Allow 1 second tolerance to handle .999 millisecond differences
-        let diff = (api_time - expected_time).num_milliseconds().abs();
-        assert!(
-            diff <= 1000,
-            "Well {} freeze time difference too large: expected {}, got {} (diff: {}ms)",
-            key,
-            expected.freeze_time,
-            freeze_time,
-            diff
-        );
+async fn get_sample_data(app: &Router, sample_id: &str) -> Value {
+    let sample_response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri(format!("/api/samples/{sample_id}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
 
-        // Temperature validation - only if probe data is available
-        if !temp_probes.is_null() && temp_probes.is_object() {
-            // Temperature values are stored as strings (Decimal), need to parse them
-            if let Some(probe1_temp_str) = temp_probes["probe_1"].as_str()
-                && let Ok(probe1_temp) = probe1_temp_str.parse::<f64>()
-            {
-                // Allow tolerance for difference between averaged (CSV analysis) and single probe (API) temperatures
-                // Since CSV analysis used 8-probe averages but API uses individual probe readings
-                let temp_diff = (probe1_temp - expected.temp_probe_1).abs();
-                assert!(
-                    temp_diff < 1.0,
-                    "Well {} probe 1 temperature should be ~{}°C, got {}°C (diff: {}°C)",
-                    key,
-                    expected.temp_probe_1,
-                    probe1_temp,
-                    temp_diff
-                );
-            }
-        }
-    }
+    assert_eq!(sample_response.status(), StatusCode::OK);
+    let sample_body = to_bytes(sample_response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&sample_body).unwrap()
 }
 
-fn validate_temperature_readings(_experiment: &Value) {
+fn validate_treatment_well_counts(sample_data: &Value) {
+    let treatments = sample_data["treatments"].as_array().unwrap();
+    assert!(!treatments.is_empty(), "Sample should have treatments");
 
-    // Temperature validation would require time series data
-    // For now, validate that temperature probe structure exists
-}
+    for treatment in treatments {
+        let treatment_name = treatment["name"].as_str().unwrap();
+        let experimental_results = treatment["experimental_results"].as_array().unwrap();
+        let dilution_summaries = treatment["dilution_summaries"].as_array().unwrap();
 
-fn validate_experiment_timing(results: &Value) {
-    let first_timestamp = results["summary"]["first_timestamp"]
-        .as_str()
-        .expect("Should have first_timestamp");
-    let _last_timestamp = results["summary"]["last_timestamp"]
-        .as_str()
-        .expect("Should have last_timestamp");
+        match treatment_name {
+            "none" => {
+                let mut total_wells_from_summaries = 0;
+                for summary in dilution_summaries {
+                    let wells_count: i32 = summary["statistics"]["total_wells"]
+                        .as_i64()
+                        .unwrap()
+                        .try_into()
+                        .unwrap();
 
-    // Validate experiment start time matches expected
-    assert!(
-        first_timestamp.contains("2025-03-20"),
-        "Experiment should start on 2025-03-20, got {first_timestamp}"
-    );
-    assert!(
-        first_timestamp.contains("15:13"),
-        "Experiment should start around 15:13, got {first_timestamp}"
-    );
+                    total_wells_from_summaries += wells_count;
 
-    // Calculate duration (should be about 1 hour 6 minutes based on CSV)
-    // This is a rough validation - exact timing depends on processing
+                    let dilution_factor = summary["dilution_factor"].as_i64().unwrap();
+                    match dilution_factor {
+                        1 => assert_eq!(
+                            wells_count, 32,
+                            "None treatment at 1x dilution should have 32 wells, got {wells_count}"
+                        ),
+                        10 => assert_eq!(
+                            wells_count, 48,
+                            "None treatment at 10x dilution should have 48 wells, got {wells_count}"
+                        ),
+                        100 => assert_eq!(
+                            wells_count, 48,
+                            "None treatment at 100x dilution should have 48 wells, got {wells_count}"
+                        ),
+                        _ => panic!("Unexpected dilution factor: {}", dilution_factor),
+                    }
+                }
+                assert_eq!(
+                    total_wells_from_summaries, 128,
+                    "None treatment should have 128 total wells (32+48+48), got {total_wells_from_summaries}"
+                );
+                assert_eq!(
+                    experimental_results.len(),
+                    128,
+                    "None treatment should have 128 experimental results, got {}",
+                    experimental_results.len()
+                );
+            }
+            "heat" => {
+                assert_eq!(
+                    experimental_results.len(),
+                    32,
+                    "Heat treatment should have 32 experimental results, got {}",
+                    experimental_results.len()
+                );
+                assert_eq!(
+                    dilution_summaries.len(),
+                    1,
+                    "Heat treatment should have 1 dilution summary, got {}",
+                    dilution_summaries.len()
+                );
+                let summary = &dilution_summaries[0];
+                let wells_count: i32 = summary["statistics"]["total_wells"]
+                    .as_i64()
+                    .unwrap()
+                    .try_into()
+                    .unwrap();
+                assert_eq!(
+                    wells_count, 32,
+                    "Heat treatment should have 32 wells, got {wells_count}"
+                );
+            }
+            "h2o2" => {
+                assert_eq!(
+                    experimental_results.len(),
+                    32,
+                    "H2O2 treatment should have 32 experimental results, got {}",
+                    experimental_results.len()
+                );
+                assert_eq!(
+                    dilution_summaries.len(),
+                    1,
+                    "H2O2 treatment should have 1 dilution summary, got {}",
+                    dilution_summaries.len()
+                );
+                let summary = &dilution_summaries[0];
+                let wells_count: i32 = summary["statistics"]["total_wells"]
+                    .as_i64()
+                    .unwrap()
+                    .try_into()
+                    .unwrap();
+                assert_eq!(
+                    wells_count, 32,
+                    "H2O2 treatment should have 32 wells, got {wells_count}"
+                );
+            }
+            _ => panic!("Unexpected treatment name: {}", treatment_name),
+        }
+    }
 }
 
-#[tokio::test]
-async fn test_well_coordinate_mapping_accuracy() {
-    let app = setup_test_app().await;
-
-    // Create experiment and upload
-    let tray_config_response = create_test_tray_config_with_trays(&app, "Coordinate Test").await;
-    let tray_config: Value = serde_json::from_str(&tray_config_response).unwrap();
-    let tray_config_id = tray_config["id"].as_str().unwrap();
+/// Helper function to create sample and treatments (mimics seeder structure)
+#[allow(clippy::too_many_lines)]
+async fn create_test_sample_and_treatments(app: &Router) -> Result<String, String> {
+    // 1. First create a project
+    let project_response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/projects")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({
+                        "name": "Test Arctic Research Project",
+                        "description": "Test project for sample well filtering",
+                        "colour": "#3B82F6"
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
 
-    let experiment_payload = serde_json::json!({
-        "name": "Coordinate Mapping Test",
-        "tray_configuration_id": tray_config_id,
-        "is_calibration": false
-    });
+    let project_body = to_bytes(project_response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let project: Value = serde_json::from_slice(&project_body).unwrap();
+    let project_id = project["id"].as_str().unwrap();
 
-    let experiment_response = app
+    // 2. Create a location
+    let location_response = app
         .clone()
         .oneshot(
-            axum::http::Request::builder()
-                .method(axum::http::Method::POST)
-                .uri("/api/experiments")
+            Request::builder()
+                .method("POST")
+                .uri("/api/locations")
                 .header("content-type", "application/json")
-                .body(axum::body::Body::from(experiment_payload.to_string()))
+                .body(Body::from(
+                    json!({
+                        "name": "Utqiagvik Research Station",
+                        "comment": "Arctic atmospheric research facility",
+                        "project_id": project_id
+                    })
+                    .to_string(),
+                ))
                 .unwrap(),
         )
         .await
         .unwrap();
 
-    let experiment_body = axum::body::to_bytes(experiment_response.into_body(), usize::MAX)
+    let location_body = to_bytes(location_response.into_body(), usize::MAX)
         .await
         .unwrap();
-    let experiment: Value = serde_json::from_slice(&experiment_body).unwrap();
-    let experiment_id = experiment["id"].as_str().unwrap();
-
-    let _upload_result = upload_excel_file(&app, experiment_id).await;
+    let location: Value = serde_json::from_slice(&location_body).unwrap();
+    let location_id = location["id"].as_str().unwrap();
 
-    // Fetch results and validate coordinate mappings
-    let results_response = app
+    // 3. Create a sample
+    let sample_response = app
         .clone()
         .oneshot(
-            axum::http::Request::builder()
-                .method(axum::http::Method::GET)
-                .uri(format!("/api/experiments/{experiment_id}"))
-                .body(axum::body::Body::empty())
+            Request::builder()
+                .method("POST")
+                .uri("/api/samples")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({
+                        "name": "Utqiagvik Resea PM10 Aerosol Filter 2025-08-25 S001",
+                        "type": "filter",
+                        "start_time": "2025-08-25T06:00:00Z",
+                        "stop_time": "2025-08-25T08:00:00Z",
+                        "flow_litres_per_minute": "10.3358743102",
+                        "total_volume": "1740.1531513381",
+                        "filter_substrate": "PTFE",
+                        "suspension_volume_litres": "0.011491431795436186",
+                        "well_volume_litres": "0.00005",
+                        "remarks": "Environmental sample S001 collected from Utqiagvik Research Station region on 2025-08-25. Sample type: filter. GPS: 71.320002°, -156.743158°",
+                        "location_id": location_id
+                    })
+                    .to_string(),
+                ))
                 .unwrap(),
         )
         .await
         .unwrap();
 
-    let results_body = axum::body::to_bytes(results_response.into_body(), usize::MAX)
+    let sample_body = to_bytes(sample_response.into_body(), usize::MAX)
         .await
         .unwrap();
-    let experiment_with_results: Value = serde_json::from_slice(&results_body).unwrap();
-    // Extract all wells from all trays in the new format
-    let mut all_wells = Vec::new();
-    if let Some(trays) = experiment_with_results["results"]["trays"].as_array() {
-        for tray in trays {
-            if let Some(wells) = tray["wells"].as_array() {
-                all_wells.extend(wells.iter());
-            }
-        }
-    }
-
-    // Validate that we have exactly 192 wells with proper coordinates
-    assert_eq!(all_wells.len(), 192, "Should have exactly 192 wells");
+    let sample: Value = serde_json::from_slice(&sample_body).unwrap();
+    let sample_id = sample["id"].as_str().unwrap();
 
-    let mut p1_wells = 0;
-    let mut p2_wells = 0;
-    let mut coordinate_set = std::collections::HashSet::new();
+    // 4. Create treatments for this sample (none, heat, h2o2)
+    let treatments = vec![
+        json!({
+            "name": "none",
+            "notes": "Untreated control sample - baseline ice nucleation activity",
+            "sample_id": sample_id
+        }),
+        json!({
+            "name": "heat",
+            "notes": "Heat treatment at 95°C for 20 minutes - removes heat-labile biological INPs",
+            "sample_id": sample_id
+        }),
+        json!({
+            "name": "h2o2",
+            "notes": "Hydrogen peroxide treatment - removes organic components including biological INPs",
+            "enzyme_volume_litres": "0.0002358673",
+            "sample_id": sample_id
+        }),
+    ];
 
-    // Iterate through trays and their wells to get tray context
-    if let Some(trays) = experiment_with_results["results"]["trays"].as_array() {
-        for tray in trays {
-            let tray_name = tray["tray_name"].as_str().unwrap_or("unknown");
-            if let Some(wells) = tray["wells"].as_array() {
-                for well in wells {
-                    let coordinate = well["coordinate"].as_str().unwrap_or("unknown");
+    for treatment in treatments {
+        let _treatment_response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/treatments")
+                    .header("content-type", "application/json")
+                    .body(Body::from(treatment.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+    }
 
-                    match tray_name {
-                        "P1" => p1_wells += 1,
-                        "P2" => p2_wells += 1,
-                        _ => panic!("Unexpected tray name: {tray_name}"),
-                    }
+    Ok(sample_id.to_string())
+}
 
-                    // Validate coordinate format (A1-H12)
-                    assert!(
-                        coordinate.len() >= 2 && coordinate.len() <= 3,
-                        "Coordinate {coordinate} should be 2-3 characters"
-                    );
-                    assert!(
-                        coordinate.chars().next().unwrap().is_ascii_uppercase(),
-                        "Coordinate {coordinate} should start with A-H"
-                    );
+/// Helper function to update experiment with regions (uses exact seeder structure)
+#[allow(clippy::too_many_lines)]
+async fn update_experiment_with_regions(
+    app: &Router,
+    experiment_id: &str,
+    sample_id: &str,
+) -> Result<(), String> {
+    // Get the current experiment
+    let experiment_response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri(format!("/api/experiments/{experiment_id}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
 
-                    // Add to set to check for duplicates within tray
-                    let full_coord = format!("{tray_name}_{coordinate}");
-                    assert!(
-                        coordinate_set.insert(full_coord.clone()),
-                        "Duplicate coordinate found: {full_coord}"
-                    );
-                }
-            }
-        }
-    }
+    let experiment_body = to_bytes(experiment_response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let mut experiment_data: Value = serde_json::from_slice(&experiment_body).unwrap();
 
-    assert_eq!(p1_wells, 96, "Should have 96 P1 wells, got {p1_wells}");
-    assert_eq!(p2_wells, 96, "Should have 96 P2 wells, got {p2_wells}");
-}
+    // Get treatments for the sample to link to regions
+    let sample_response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri(format!("/api/samples/{sample_id}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
 
-/// Test image-temperature correlation in results summary
-#[tokio::test]
-async fn test_image_temperature_correlation() {
-    let app = setup_test_app().await;
+    let sample_body = to_bytes(sample_response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let sample_data: Value = serde_json::from_slice(&sample_body).unwrap();
+    let treatments = sample_data["treatments"].as_array().unwrap();
 
-    // Create experiment
-    let experiment_id = create_experiment_via_api(&app).await.unwrap();
+    // Find treatment IDs
+    let mut treatment_map = std::collections::HashMap::new();
+    for treatment in treatments {
+        let name = treatment["name"].as_str().unwrap();
+        let id = treatment["id"].as_str().unwrap();
+        treatment_map.insert(name, id);
+    }
 
-    // Create a simple tray configuration manually (skip complex create function for now)
-    let tray_config_id = create_simple_tray_config(&app).await.unwrap();
-    assign_tray_config_to_experiment_via_api(&app, &experiment_id, &tray_config_id).await;
+    // Add regions to the experiment (exact same structure as seeder)
+    experiment_data["regions"] = json!([
+        {
+            "treatment_id": treatment_map.get("none").unwrap(),
+            "name": "Untreated Samples - P1",
+            "display_colour_hex": "#3B82F6",
+            "tray_id": 1,
+            "col_min": 0, "col_max": 3, "row_min": 0, "row_max": 7,
+            "dilution_factor": 1,
+            "is_background_key": false
+        },
+        {
+            "treatment_id": treatment_map.get("heat").unwrap(),
+            "name": "Heat Treated - P1",
+            "display_colour_hex": "#EF4444",
+            "tray_id": 1,
+            "col_min": 4, "col_max": 7, "row_min": 0, "row_max": 7,
+            "dilution_factor": 1,
+            "is_background_key": false
+        },
+        {
+            "treatment_id": treatment_map.get("h2o2").unwrap(),
+            "name": "H2O2 Treated - P1",
+            "display_colour_hex": "#10B981",
+            "tray_id": 1,
+            "col_min": 8, "col_max": 11, "row_min": 0, "row_max": 7,
+            "dilution_factor": 1,
+            "is_background_key": false
+        },
+        {
+            "treatment_id": treatment_map.get("none").unwrap(),
+            "name": "Dilution Series 1:10 - P2",
+            "display_colour_hex": "#8B5CF6",
+            "tray_id": 2,
+            "col_min": 0, "col_max": 5, "row_min": 0, "row_max": 7,
+            "dilution_factor": 10,
+            "is_background_key": false
+        },
+        {
+            "treatment_id": treatment_map.get("none").unwrap(),
+            "name": "Dilution Series 1:100 - P2",
+            "display_colour_hex": "#F59E0B",
+            "tray_id": 2,
+            "col_min": 6, "col_max": 11, "row_min": 0, "row_max": 7,
+            "dilution_factor": 100,
+            "is_background_key": false
+        }
+    ]);
 
-    // Verify experiment can be retrieved (temperature readings are created through Excel processing)
-    let experiment_response = app
+    // Update the experiment with regions
+    let update_response = app
         .clone()
         .oneshot(
             Request::builder()
-                .method("GET")
+                .method("PUT")
                 .uri(format!("/api/experiments/{experiment_id}"))
-                .body(Body::empty())
+                .header("content-type", "application/json")
+                .body(Body::from(experiment_data.to_string()))
                 .unwrap(),
         )
         .await
         .unwrap();
-    assert_eq!(experiment_response.status(), StatusCode::OK);
-    let body_bytes = to_bytes(experiment_response.into_body(), usize::MAX)
-        .await
-        .unwrap();
-    let experiment_data: Value = serde_json::from_slice(&body_bytes).unwrap();
-
-    // Verify the experiment exists and has the expected structure
-    assert_eq!(experiment_data["id"].as_str().unwrap(), experiment_id);
-}
 
+    if update_response.status() != StatusCode::OK {
+        let error_body = to_bytes(update_response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let error_text = String::from_utf8_lossy(&error_body);
+        return Err(format!(
+            "Failed to update experiment with regions: {error_text}"
+        ));
+    }
 
+    Ok(())
+}
 
-/// Helper function to create a test tray configuration with trays and probes
-async fn create_test_tray_configuration_with_probes(app: &Router) -> Result<String, String> {
-    // 1. Create base tray configuration
+/// Helper function to create a simple tray configuration
+async fn create_simple_tray_configuration(app: &Router, name: &str) -> Result<String, String> {
     let tray_config_response = app
         .clone()
         .oneshot(
@@ -2955,7 +5458,7 @@ async fn create_test_tray_configuration_with_probes(app: &Router) -> Result<Stri
                 .header("content-type", "application/json")
                 .body(Body::from(
                     json!({
-                        "name": "Excel Test Configuration",
+                        "name": name,
                         "experiment_default": false
                     })
                     .to_string(),
@@ -2978,76 +5481,21 @@ async fn create_test_tray_configuration_with_probes(app: &Router) -> Result<Stri
     let tray_config: Value = serde_json::from_slice(&tray_config_body).unwrap();
     let tray_config_id = tray_config["id"].as_str().unwrap();
 
-    // 2. Update with trays and probes
-    let update_response = app
-        .clone()
-        .oneshot(
-            Request::builder()
-                .method("PUT")
-                .uri(format!("/api/tray_configurations/{tray_config_id}"))
-                .header("content-type", "application/json")
-                .body(Body::from(json!({
-                    "name": "Excel Test Configuration with Trays",
-                    "experiment_default": false,
-                    "trays": [
-                        {
-                            "name": "P1",
-                            "rotation_degrees": 90,
-                            "well_relative_diameter": 6.4,
-                            "qty_cols": 12,
-                            "qty_rows": 8,
-                            "probe_locations": [
-                                {"name": "Probe 1", "data_column_index": 1, "position_x": 22.1, "position_y": 77.6},
-                                {"name": "Probe 2", "data_column_index": 2, "position_x": 47.1, "position_y": 20},
-                                {"name": "Probe 3", "data_column_index": 3, "position_x": 113, "position_y": 19.5},
-                                {"name": "Probe 4", "data_column_index": 4, "position_x": 143.5, "position_y": 79.5}
-                            ],
-                            "upper_left_corner_x": 416,
-                            "upper_left_corner_y": 75,
-                            "lower_right_corner_x": 135,
-                            "lower_right_corner_y": 542,
-                            "order_sequence": 1
-                        },
-                        {
-                            "name": "P2",
-                            "rotation_degrees": 270,
-                            "well_relative_diameter": 6.4,
-                            "qty_cols": 12,
-                            "qty_rows": 8,
-                            "probe_locations": [
-                                {"name": "Probe 5", "data_column_index": 5, "position_x": 140.8, "position_y": 80},
-                                {"name": "Probe 6", "data_column_index": 6, "position_x": 103.1, "position_y": 21.9},
-                                {"name": "Probe 7", "data_column_index": 7, "position_x": 48.1, "position_y": 22.4},
-                                {"name": "Probe 8", "data_column_index": 8, "position_x": 7.2, "position_y": 93.3}
-                            ],
-                            "upper_left_corner_x": 536,
-                            "upper_left_corner_y": 529,
-                            "lower_right_corner_x": 823,
-                            "lower_right_corner_y": 67,
-                            "order_sequence": 2
-                        }
-                    ]
-                }).to_string()))
-                .unwrap(),
-        )
-        .await
-        .unwrap();
-
-    if update_response.status() != StatusCode::OK {
-        return Err(format!(
-            "Failed to update tray configuration: {}",
-            update_response.status()
-        ));
-    }
-
     Ok(tray_config_id.to_string())
 }
 
-/// Helper function to create a test experiment
-async fn create_test_experiment_via_api(
-    app: &Router,
-    tray_config_id: &str,
-) -> Result<String, String> {
+/// API Integration test for experiment results endpoint after Excel processing
+/// This tests retrieving processed experiment results via /api/experiments/{id}
+#[tokio::test]
+async fn test_experiment_results_api_integration() {
+    let app = setup_test_app().await;
+
+    // 1. Create simple test setup via API
+    let tray_config_id = create_simple_tray_configuration(&app, "Results Test Configuration")
+        .await
+        .expect("Failed to create tray configuration");
+
+    // 2. Create experiment via API with custom name
     let experiment_response = app
         .clone()
         .oneshot(
@@ -3057,11 +5505,11 @@ async fn create_test_experiment_via_api(
                 .header("content-type", "application/json")
                 .body(Body::from(
                     json!({
-                        "name": "Excel Processing API Integration Test",
+                        "name": "Results API Integration Test",
                         "username": "test_user@example.com",
                         "performed_at": "2025-01-01T00:00:00Z",
                         "is_calibration": false,
-                        "remarks": "Testing Excel upload via API",
+                        "remarks": "Testing results API endpoint",
                         "tray_configuration_id": tray_config_id
                     })
                     .to_string(),
@@ -3071,1152 +5519,2604 @@ async fn create_test_experiment_via_api(
         .await
         .unwrap();
 
-    if experiment_response.status() != StatusCode::CREATED {
-        return Err(format!(
-            "Failed to create experiment: {}",
-            experiment_response.status()
-        ));
-    }
+    assert!(
+        (experiment_response.status() == StatusCode::CREATED),
+        "Failed to create experiment: {}",
+        experiment_response.status()
+    );
 
     let experiment_body = to_bytes(experiment_response.into_body(), usize::MAX)
         .await
         .unwrap();
     let experiment: Value = serde_json::from_slice(&experiment_body).unwrap();
-    let experiment_id = experiment["id"].as_str().unwrap();
+    let experiment_id = experiment["id"].as_str().unwrap().to_string();
 
-    Ok(experiment_id.to_string())
-}
+    // 3. Test retrieving experiment without processed data
+    let empty_results_response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri(format!("/api/experiments/{experiment_id}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
 
-/// Helper function to process Excel file via API using upload + reprocess flow
-async fn process_excel_file_via_api(app: &Router, experiment_id: &str) -> Result<Value, String> {
-    // Load test Excel file
-    let excel_path = "src/experiments/test_resources/merged.xlsx";
-    let excel_data = fs::read(excel_path).map_err(|e| format!("Failed to read Excel file: {e}"))?;
+    assert_eq!(empty_results_response.status(), StatusCode::OK);
+    let empty_results_body = to_bytes(empty_results_response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let empty_experiment_data: Value = serde_json::from_slice(&empty_results_body).unwrap();
 
-    // Create multipart form data (binary safe)
-    let boundary = "test-boundary-12345";
-    let mut multipart_body = Vec::new();
+    // Verify experiment data structure
+    assert_eq!(empty_experiment_data["id"].as_str().unwrap(), experiment_id);
+    assert_eq!(
+        empty_experiment_data["name"].as_str().unwrap(),
+        "Results API Integration Test"
+    );
 
-    multipart_body.extend_from_slice(format!(
-        "--{boundary}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"merged.xlsx\"\r\nContent-Type: application/vnd.openxmlformats-officedocument.spreadsheetml.sheet\r\n\r\n"
-    ).as_bytes());
-    multipart_body.extend_from_slice(&excel_data);
-    multipart_body.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+    // Check results structure (should be null or empty for experiment without data)
+    let results = empty_experiment_data.get("results");
+    if let Some(results_val) = results
+        && !results_val.is_null()
+    {
+        // If results exist, they should have the expected structure
+        assert!(
+            results_val.get("summary").is_some(),
+            "Results should have summary"
+        );
+        assert!(
+            results_val.get("trays").is_some(),
+            "Results should have trays array"
+        );
+    }
 
-    // 1. Upload the file via API
-    let upload_response = app
+    // 4. Test API error handling
+    let nonexistent_id = "00000000-0000-0000-0000-000000000000";
+    let not_found_response = app
         .clone()
         .oneshot(
             Request::builder()
-                .method("POST")
-                .uri(format!("/api/experiments/{experiment_id}/uploads"))
-                .header(
-                    "content-type",
-                    format!("multipart/form-data; boundary={boundary}"),
-                )
-                .body(Body::from(multipart_body))
+                .method("GET")
+                .uri(format!("/api/experiments/{nonexistent_id}"))
+                .body(Body::empty())
                 .unwrap(),
         )
         .await
         .unwrap();
 
-    let status = upload_response.status();
-    let upload_body = to_bytes(upload_response.into_body(), usize::MAX)
+    assert_eq!(not_found_response.status(), StatusCode::NOT_FOUND);
+
+    // 5. Test invalid UUID handling
+    let invalid_uuid_response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/api/experiments/invalid-uuid")
+                .body(Body::empty())
+                .unwrap(),
+        )
         .await
         .unwrap();
 
-    if status != StatusCode::OK {
-        let error_text = String::from_utf8_lossy(&upload_body);
-        return Err(format!(
-            "Excel upload failed with status {status}: {error_text}"
-        ));
-    }
+    assert_eq!(invalid_uuid_response.status(), StatusCode::BAD_REQUEST);
+}
 
-    let upload_result: Value = serde_json::from_slice(&upload_body)
-        .map_err(|e| format!("Failed to parse upload result: {e}"))?;
+#[tokio::test]
+async fn test_seeder_experiment_structure() {
+    let app = setup_test_app().await;
 
-    // Extract asset ID from upload response
-    let asset_id = upload_result.get("id")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| "No asset ID in upload response".to_string())?;
+    // Test the exact structure our seeder is trying to create
+    let seeder_experiment = json!({
+        "name": "Arctic Aerosol INP Characterization Exp139",
+        "username": "researcher@eerl.lab",
+        "temperature_ramp": -1.0,
+        "temperature_start": 5.0,
+        "temperature_end": -25.0,
+        "is_calibration": false,
+        "remarks": "Comprehensive characterization of Arctic atmospheric aerosol samples using SPICE droplet freezing technique",
+        "performed_at": "2025-08-15T10:30:00.000Z"
+    });
 
-    // 2. Reprocess the asset to trigger Excel processing
-    let reprocess_response = app
+    let create_response = app
         .clone()
         .oneshot(
             Request::builder()
                 .method("POST")
-                .uri(format!("/api/assets/{asset_id}/reprocess"))
-                .body(Body::empty())
+                .uri("/api/experiments")
+                .header("content-type", "application/json")
+                .body(Body::from(seeder_experiment.to_string()))
                 .unwrap(),
         )
         .await
         .unwrap();
 
-    let status = reprocess_response.status();
-    let reprocess_body = to_bytes(reprocess_response.into_body(), usize::MAX)
-        .await
-        .unwrap();
-
-    if status != StatusCode::OK {
-        let error_text = String::from_utf8_lossy(&reprocess_body);
-        return Err(format!(
-            "Excel reprocessing failed with status {status}: {error_text}"
-        ));
-    }
+    let (create_status, create_body) = extract_response_body(create_response).await;
 
-    let processing_result: Value = serde_json::from_slice(&reprocess_body)
-        .map_err(|e| format!("Failed to parse reprocessing result: {e}"))?;
+    println!("SEEDER EXPERIMENT TEST - Status: {create_status}");
+    println!("SEEDER EXPERIMENT TEST - Body: {create_body:?}");
 
-    Ok(processing_result)
+    assert_eq!(
+        create_status,
+        StatusCode::CREATED,
+        "Seeder experiment payload should create successfully. Status: {create_status}, Body: {create_body:?}"
+    );
 }
 
-/// Helper function to validate Excel processing results
-fn validate_excel_processing_results(processing_result: &Value) -> Result<(), String> {
-    let success = processing_result["success"].as_bool().unwrap_or(false);
-    let empty_errors = vec![];
-    let errors = processing_result["errors"]
-        .as_array()
-        .unwrap_or(&empty_errors);
-
-    let temp_readings_created = processing_result["temperature_readings_created"]
-        .as_u64()
-        .unwrap_or(0);
-    let _probe_readings_created = processing_result["probe_temperature_readings_created"]
-        .as_u64()
-        .unwrap_or(0);
-    let phase_transitions_created = processing_result["phase_transitions_created"]
-        .as_u64()
-        .unwrap_or(0);
-
-    let has_reasonable_data = temp_readings_created > 5000 && phase_transitions_created > 0;
-
-    if !has_reasonable_data {
-        return Err(format!(
-            "Processing failed without reasonable data. Success: {success}, Errors: {errors:?}"
-        ));
-    }
-
-    // Verify expected data volumes
-    if temp_readings_created <= 6000 {
-        return Err(format!(
-            "Expected >6000 temperature readings, got {temp_readings_created}"
-        ));
-    }
-    if phase_transitions_created == 0 {
-        return Err("Expected phase transitions, got 0".to_string());
-    }
-
-    //     "   ✅ Core Excel processing working via API (temp readings: {}, phase transitions: {})",
-    //     temp_readings_created, phase_transitions_created
-    // );
+#[tokio::test]
+async fn test_seeder_sample_with_coordinates() {
+    let app = setup_test_app().await;
 
-    Ok(())
-}
+    // Test the enhanced sample structure with GPS coordinates
+    let seeder_sample = json!({
+        "name": "Utqiagvik Research PM10 Aerosol Filter 2025-08-15",
+        "type": "filter",
+        "latitude": "71.3230",
+        "longitude": "-156.7660",
+        "remarks": "Environmental sample collected from Utqiagvik Research Station on 2025-08-15. Sample type: filter. GPS: 71.3230°, -156.7660°"
+    });
 
-/// Helper function to verify experiment results are accessible via API
-async fn verify_experiment_results_api(app: &Router, experiment_id: &str) -> Result<(), String> {
-    let results_response = app
+    let create_response = app
         .clone()
         .oneshot(
             Request::builder()
-                .method("GET")
-                .uri(format!("/api/experiments/{experiment_id}"))
-                .body(Body::empty())
+                .method("POST")
+                .uri("/api/samples")
+                .header("content-type", "application/json")
+                .body(Body::from(seeder_sample.to_string()))
                 .unwrap(),
         )
         .await
         .unwrap();
 
-    if results_response.status() != StatusCode::OK {
-        return Err(format!(
-            "Failed to retrieve experiment results: {}",
-            results_response.status()
-        ));
-    }
+    let (create_status, create_body) = extract_response_body(create_response).await;
 
-    let results_body = to_bytes(results_response.into_body(), usize::MAX)
-        .await
-        .unwrap();
-    let experiment_data: Value = serde_json::from_slice(&results_body)
-        .map_err(|e| format!("Failed to parse experiment data: {e}"))?;
+    println!("SEEDER SAMPLE GPS TEST - Status: {create_status}");
+    println!("SEEDER SAMPLE GPS TEST - Body: {create_body:?}");
 
-    // Check that results include the processed data
-    if let Some(results) = experiment_data.get("results")
-        && let Some(summary) = results.get("summary")
-    {
-        let total_time_points = summary["total_time_points"].as_u64().unwrap_or(0);
-        if total_time_points <= 6000 {
-            return Err(format!(
-                "Results should show >6000 time points, got {total_time_points}"
-            ));
-        }
-    }
+    assert_eq!(
+        create_status,
+        StatusCode::CREATED,
+        "Seeder sample with GPS should create successfully. Status: {create_status}, Body: {create_body:?}"
+    );
 
-    Ok(())
+    // Verify GPS coordinates are stored - note: Decimal type may truncate trailing zeros
+    let latitude = create_body["latitude"].as_str().unwrap();
+    let longitude = create_body["longitude"].as_str().unwrap();
+
+    // Check that coordinates have at least 3 decimal places and are within expected range
+    assert!(
+        latitude.starts_with("71.32"),
+        "Latitude should be around 71.32, got: {latitude}"
+    );
+    assert!(
+        longitude.starts_with("-156.76"),
+        "Longitude should be around -156.76, got: {longitude}"
+    );
 }
 
-/// API Integration test for Excel processing endpoint with real Excel file
-/// This tests the full HTTP request/response cycle through /api/experiments/{id}/process-excel
+/// Test temperature readings at specific timestamps, especially during phase changes
+/// Verifies that all 8 probes return correct temperature data with metadata
+#[allow(clippy::too_many_lines)]
 #[tokio::test]
-async fn test_excel_processing_api_integration() {
+async fn test_temperature_readings_during_phase_changes() {
+    println!("🌡️ Testing temperature readings during phase changes");
+
     let app = setup_test_app().await;
 
-    // 1. Setup: Create tray configuration with trays and probes
+    // 1. Create tray configuration with full probe setup (8 probes)
     let tray_config_id = create_test_tray_configuration_with_probes(&app)
         .await
-        .expect("Failed to create tray configuration");
+        .expect("Failed to create tray configuration with probes");
 
-    // 2. Setup: Create experiment
+    // 2. Create experiment
     let experiment_id = create_test_experiment_via_api(&app, &tray_config_id)
         .await
         .expect("Failed to create experiment");
 
-    // 3. Process: Upload and process Excel file
-    let processing_result = process_excel_file_via_api(&app, &experiment_id)
+    // 3. Process Excel file to get temperature and phase change data
+    let _processing_result = process_excel_file_via_api(&app, &experiment_id)
         .await
         .expect("Failed to process Excel file");
 
-    // 4. Validate: Check processing results
-    validate_excel_processing_results(&processing_result)
-        .expect("Processing results validation failed");
+    // 4. Get experiment results with temperature data
+    let results_response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri(format!("/api/experiments/{experiment_id}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
 
-    // 5. Validate: Check that results are accessible via API
-    verify_experiment_results_api(&app, &experiment_id)
+    assert_eq!(results_response.status(), StatusCode::OK);
+    let results_body = to_bytes(results_response.into_body(), usize::MAX)
         .await
-        .expect("Failed to verify experiment results API");
-}
+        .unwrap();
+    let experiment_data: Value =
+        serde_json::from_slice(&results_body).expect("Failed to parse experiment results");
 
-/// Test to verify sample well filtering issue - samples should not include wells from other treatments
-/// This test confirms the bug where each treatment gets assigned all 192 wells instead of just its region wells
-#[tokio::test]
-async fn test_sample_well_filtering_after_excel_processing() {
-    let app = setup_test_app().await;
+    // 5. Validate overall structure
+    let results = experiment_data
+        .get("results")
+        .expect("Experiment should have results")
+        .as_object()
+        .expect("Results should be an object");
 
-    // 1. Setup: Create tray configuration with trays and probes
-    let tray_config_id = create_test_tray_configuration_with_probes(&app)
-        .await
-        .expect("Failed to create tray configuration");
+    let trays = results
+        .get("trays")
+        .expect("Results should have trays")
+        .as_array()
+        .expect("Trays should be an array");
 
-    // 2. Setup: Create experiment
-    let experiment_id = create_test_experiment_via_api(&app, &tray_config_id)
-        .await
-        .expect("Failed to create experiment");
+    assert!(!trays.is_empty(), "Should have at least one tray");
 
-    // 3. Create sample and treatments to be used in regions
-    let sample_id = create_test_sample_and_treatments(&app)
-        .await
-        .expect("Failed to create sample and treatments");
+    // 6. Find wells with phase changes and temperature data
+    let mut wells_with_phase_changes = 0;
+    let mut wells_with_temperatures = 0;
+    let mut total_probe_readings_checked = 0;
 
-    // 4. Add regions to the experiment by updating it
-    update_experiment_with_regions(&app, &experiment_id, &sample_id)
-        .await
-        .expect("Failed to add regions to experiment");
+    for tray in trays {
+        let wells = tray
+            .get("wells")
+            .expect("Tray should have wells")
+            .as_array()
+            .expect("Wells should be an array");
 
-    // 5. Process: Upload and process Excel file
-    let _processing_result = process_excel_file_via_api(&app, &experiment_id)
-        .await
-        .expect("Failed to process Excel file");
+        for well in wells {
+            // Check if well has phase change time
+            if let Some(_phase_change_time) = well.get("first_phase_change_time") {
+                wells_with_phase_changes += 1;
 
-    // 6. Get experiment details to find regions and samples
-    let experiment_data = get_experiment_data(&app, &experiment_id).await;
+                // Check if well has temperature data at phase change
+                if let Some(temperatures) = well.get("temperatures") {
+                    wells_with_temperatures += 1;
 
-    // 7. Find the sample used in the experiment (should be the first sample from first region)
-    let regions = experiment_data["regions"].as_array().unwrap();
-    assert!(!regions.is_empty(), "Experiment should have regions");
+                    // Validate temperature structure
+                    assert!(
+                        temperatures.get("id").is_some(),
+                        "Temperature reading should have id"
+                    );
+                    assert!(
+                        temperatures.get("timestamp").is_some(),
+                        "Temperature reading should have timestamp"
+                    );
+                    assert!(
+                        temperatures.get("average").is_some(),
+                        "Temperature reading should have average"
+                    );
 
-    let first_region = &regions[0];
-    let sample_id = first_region["treatment"]["sample"]["id"]
-        .as_str()
-        .expect("Should have sample ID in first region");
+                    // Validate probe readings array
+                    let probe_readings = temperatures
+                        .get("probe_readings")
+                        .expect("Temperature reading should have probe_readings")
+                        .as_array()
+                        .expect("probe_readings should be an array");
 
-    // 8. Test: Get sample data via API
-    let sample_data = get_sample_data(&app, sample_id).await;
+                    println!(
+                        "   Well {}:{} has {} probe readings at phase change",
+                        well["row_letter"].as_str().unwrap_or("?"),
+                        well["column_number"].as_i64().unwrap_or(0),
+                        probe_readings.len()
+                    );
 
-    // 9. Validate: Check treatment well counts
-    validate_treatment_well_counts(&sample_data);
-}
+                    // Validate each probe reading
+                    for probe_reading in probe_readings {
+                        total_probe_readings_checked += 1;
 
-async fn get_experiment_data(app: &Router, experiment_id: &str) -> Value {
-    let experiment_response = app
-        .clone()
-        .oneshot(
-            Request::builder()
-                .method("GET")
-                .uri(format!("/api/experiments/{experiment_id}"))
-                .body(Body::empty())
-                .unwrap(),
-        )
-        .await
-        .unwrap();
+                        // Check probe reading structure
+                        assert!(
+                            probe_reading.get("id").is_some(),
+                            "Probe reading should have id"
+                        );
+                        assert!(
+                            probe_reading.get("probe_id").is_some(),
+                            "Probe reading should have probe_id"
+                        );
+                        assert!(
+                            probe_reading.get("temperature").is_some(),
+                            "Probe reading should have temperature"
+                        );
 
-    assert_eq!(experiment_response.status(), StatusCode::OK);
-    let experiment_body = to_bytes(experiment_response.into_body(), usize::MAX)
-        .await
-        .unwrap();
-    serde_json::from_slice(&experiment_body).unwrap()
-}
+                        // Check probe metadata (new feature)
+                        assert!(
+                            probe_reading.get("probe_name").is_some(),
+                            "Probe reading should have probe_name"
+                        );
+                        assert!(
+                            probe_reading.get("probe_data_column_index").is_some(),
+                            "Probe reading should have probe_data_column_index"
+                        );
+                        assert!(
+                            probe_reading.get("probe_position_x").is_some(),
+                            "Probe reading should have probe_position_x"
+                        );
+                        assert!(
+                            probe_reading.get("probe_position_y").is_some(),
+                            "Probe reading should have probe_position_y"
+                        );
 
-async fn get_sample_data(app: &Router, sample_id: &str) -> Value {
-    let sample_response = app
-        .clone()
-        .oneshot(
-            Request::builder()
-                .method("GET")
-                .uri(format!("/api/samples/{sample_id}"))
-                .body(Body::empty())
-                .unwrap(),
-        )
-        .await
-        .unwrap();
+                        // Validate temperature value (should be formatted to 3 decimal places)
+                        let temperature_str = probe_reading
+                            .get("temperature")
+                            .and_then(|t| t.as_str())
+                            .expect("Temperature should be a string");
 
-    assert_eq!(sample_response.status(), StatusCode::OK);
-    let sample_body = to_bytes(sample_response.into_body(), usize::MAX)
-        .await
-        .unwrap();
-    serde_json::from_slice(&sample_body).unwrap()
-}
+                        // Parse temperature and check it's reasonable
+                        let temperature: f64 = temperature_str
+                            .parse()
+                            .expect("Temperature should be parseable as float");
 
-fn validate_treatment_well_counts(sample_data: &Value) {
-    let treatments = sample_data["treatments"].as_array().unwrap();
-    assert!(!treatments.is_empty(), "Sample should have treatments");
+                        // During phase changes, temperatures should be below 0°C and above -30°C (reasonable range)
+                        assert!(
+                            (-30.0..=5.0).contains(&temperature),
+                            "Temperature {temperature} should be in reasonable range (-30°C to 5°C) during phase change"
+                        );
 
-    for treatment in treatments {
-        let treatment_name = treatment["name"].as_str().unwrap();
-        let experimental_results = treatment["experimental_results"].as_array().unwrap();
-        let dilution_summaries = treatment["dilution_summaries"].as_array().unwrap();
+                        // Validate probe metadata values
+                        let probe_name = probe_reading
+                            .get("probe_name")
+                            .and_then(|n| n.as_str())
+                            .expect("Probe name should be a string");
+                        assert!(
+                            probe_name.starts_with("Probe"),
+                            "Probe name should start with 'Probe', got: {probe_name}"
+                        );
 
-        match treatment_name {
-            "none" => {
-                let mut total_wells_from_summaries = 0;
-                for summary in dilution_summaries {
-                    let wells_count: i32 = summary["statistics"]["total_wells"]
-                        .as_i64()
-                        .unwrap()
-                        .try_into()
-                        .unwrap();
+                        let data_column_index = probe_reading
+                            .get("probe_data_column_index")
+                            .and_then(serde_json::Value::as_i64)
+                            .expect("Data column index should be an integer");
+                        assert!(
+                            (1..=8).contains(&data_column_index),
+                            "Data column index should be 1-8, got: {data_column_index}"
+                        );
+                    }
 
-                    total_wells_from_summaries += wells_count;
+                    // Validate average temperature
+                    if let Some(average_str) = temperatures.get("average").and_then(|a| a.as_str())
+                    {
+                        let average: f64 = average_str
+                            .parse()
+                            .expect("Average temperature should be parseable as float");
+                        assert!(
+                            (-30.0..=5.0).contains(&average),
+                            "Average temperature {average} should be in reasonable range during phase change"
+                        );
+                    }
 
-                    let dilution_factor = summary["dilution_factor"].as_i64().unwrap();
-                    match dilution_factor {
-                        1 => assert_eq!(
-                            wells_count, 32,
-                            "None treatment at 1x dilution should have 32 wells, got {wells_count}"
-                        ),
-                        10 => assert_eq!(
-                            wells_count, 48,
-                            "None treatment at 10x dilution should have 48 wells, got {wells_count}"
-                        ),
-                        100 => assert_eq!(
-                            wells_count, 48,
-                            "None treatment at 100x dilution should have 48 wells, got {wells_count}"
-                        ),
-                        _ => panic!("Unexpected dilution factor: {}", dilution_factor),
+                    // Stop after checking a few wells to avoid test timeout
+                    if wells_with_temperatures >= 3 {
+                        break;
                     }
                 }
-                assert_eq!(
-                    total_wells_from_summaries, 128,
-                    "None treatment should have 128 total wells (32+48+48), got {total_wells_from_summaries}"
-                );
-                assert_eq!(
-                    experimental_results.len(),
-                    128,
-                    "None treatment should have 128 experimental results, got {}",
-                    experimental_results.len()
-                );
-            }
-            "heat" => {
-                assert_eq!(
-                    experimental_results.len(),
-                    32,
-                    "Heat treatment should have 32 experimental results, got {}",
-                    experimental_results.len()
-                );
-                assert_eq!(
-                    dilution_summaries.len(),
-                    1,
-                    "Heat treatment should have 1 dilution summary, got {}",
-                    dilution_summaries.len()
-                );
-                let summary = &dilution_summaries[0];
-                let wells_count: i32 = summary["statistics"]["total_wells"]
-                    .as_i64()
-                    .unwrap()
-                    .try_into()
-                    .unwrap();
-                assert_eq!(
-                    wells_count, 32,
-                    "Heat treatment should have 32 wells, got {wells_count}"
-                );
-            }
-            "h2o2" => {
-                assert_eq!(
-                    experimental_results.len(),
-                    32,
-                    "H2O2 treatment should have 32 experimental results, got {}",
-                    experimental_results.len()
-                );
-                assert_eq!(
-                    dilution_summaries.len(),
-                    1,
-                    "H2O2 treatment should have 1 dilution summary, got {}",
-                    dilution_summaries.len()
-                );
-                let summary = &dilution_summaries[0];
-                let wells_count: i32 = summary["statistics"]["total_wells"]
-                    .as_i64()
-                    .unwrap()
-                    .try_into()
-                    .unwrap();
-                assert_eq!(
-                    wells_count, 32,
-                    "H2O2 treatment should have 32 wells, got {wells_count}"
-                );
             }
-            _ => panic!("Unexpected treatment name: {}", treatment_name),
+        }
+        if wells_with_temperatures >= 3 {
+            break;
         }
     }
+
+    // 7. Validate test results
+    assert!(
+        wells_with_phase_changes > 0,
+        "Should have found wells with phase changes"
+    );
+    assert!(
+        wells_with_temperatures > 0,
+        "Should have found wells with temperature data at phase change"
+    );
+    assert!(
+        total_probe_readings_checked > 0,
+        "Should have checked probe readings"
+    );
+
+    println!("✅ Temperature validation completed:");
+    println!("   - Wells with phase changes: {wells_with_phase_changes}");
+    println!("   - Wells with temperature data: {wells_with_temperatures}");
+    println!("   - Probe readings validated: {total_probe_readings_checked}");
+
+    // 8. Additional validation: Check that we have reasonable number of probe readings
+    // Since we're looking at phase change times, and each well should have readings from multiple probes
+    let expected_min_probe_readings = wells_with_temperatures * 3; // At least 3 probes per well with data
+    assert!(
+        total_probe_readings_checked >= expected_min_probe_readings,
+        "Expected at least {expected_min_probe_readings} probe readings ({wells_with_temperatures}+ wells × 3+ probes), got {total_probe_readings_checked}"
+    );
 }
 
-/// Helper function to create sample and treatments (mimics seeder structure)
-#[allow(clippy::too_many_lines)]
-async fn create_test_sample_and_treatments(app: &Router) -> Result<String, String> {
-    // 1. First create a project
-    let project_response = app
+/// Upload a small non-Excel file to an experiment via the multipart endpoint
+async fn upload_plain_file(app: &Router, experiment_id: &str, filename: &str, content: &[u8]) {
+    let boundary = "archive-test-boundary";
+    let mut multipart_body = Vec::new();
+    multipart_body.extend_from_slice(
+        format!(
+            "--{boundary}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{filename}\"\r\nContent-Type: application/octet-stream\r\n\r\n"
+        )
+        .as_bytes(),
+    );
+    multipart_body.extend_from_slice(content);
+    multipart_body.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+
+    let response = app
         .clone()
         .oneshot(
             Request::builder()
                 .method("POST")
-                .uri("/api/projects")
-                .header("content-type", "application/json")
-                .body(Body::from(
-                    json!({
-                        "name": "Test Arctic Research Project",
-                        "description": "Test project for sample well filtering",
-                        "colour": "#3B82F6"
-                    })
-                    .to_string(),
-                ))
+                .uri(format!("/api/experiments/{experiment_id}/uploads"))
+                .header(
+                    "content-type",
+                    format!("multipart/form-data; boundary={boundary}"),
+                )
+                .body(Body::from(multipart_body))
                 .unwrap(),
         )
         .await
         .unwrap();
 
-    let project_body = to_bytes(project_response.into_body(), usize::MAX)
-        .await
-        .unwrap();
-    let project: Value = serde_json::from_slice(&project_body).unwrap();
-    let project_id = project["id"].as_str().unwrap();
+    assert_eq!(
+        response.status(),
+        StatusCode::OK,
+        "Upload of {filename} failed"
+    );
+}
 
-    // 2. Create a location
-    let location_response = app
+/// Download the experiment archive and open it as a ZIP
+async fn download_experiment_archive(
+    app: &Router,
+    experiment_id: &str,
+) -> zip::ZipArchive<std::io::Cursor<Vec<u8>>> {
+    let response = app
         .clone()
         .oneshot(
             Request::builder()
-                .method("POST")
-                .uri("/api/locations")
-                .header("content-type", "application/json")
-                .body(Body::from(
-                    json!({
-                        "name": "Utqiagvik Research Station",
-                        "comment": "Arctic atmospheric research facility",
-                        "project_id": project_id
-                    })
-                    .to_string(),
-                ))
+                .method("GET")
+                .uri(format!("/api/experiments/{experiment_id}/archive"))
+                .body(Body::empty())
                 .unwrap(),
         )
         .await
         .unwrap();
 
-    let location_body = to_bytes(location_response.into_body(), usize::MAX)
-        .await
-        .unwrap();
-    let location: Value = serde_json::from_slice(&location_body).unwrap();
-    let location_id = location["id"].as_str().unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "application/zip"
+    );
+    let disposition = response
+        .headers()
+        .get("content-disposition")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+    assert!(disposition.contains(&format!("experiment_{experiment_id}_archive.zip")));
+
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    zip::ZipArchive::new(std::io::Cursor::new(body.to_vec())).expect("Archive is not a valid ZIP")
+}
 
-    // 3. Create a sample
-    let sample_response = app
-        .clone()
-        .oneshot(
-            Request::builder()
-                .method("POST")
-                .uri("/api/samples")
-                .header("content-type", "application/json")
-                .body(Body::from(
-                    json!({
-                        "name": "Utqiagvik Resea PM10 Aerosol Filter 2025-08-25 S001",
-                        "type": "filter",
-                        "start_time": "2025-08-25T06:00:00Z",
-                        "stop_time": "2025-08-25T08:00:00Z",
-                        "flow_litres_per_minute": "10.3358743102",
-                        "total_volume": "1740.1531513381",
-                        "filter_substrate": "PTFE",
-                        "suspension_volume_litres": "0.011491431795436186",
-                        "well_volume_litres": "0.00005",
-                        "remarks": "Environmental sample S001 collected from Utqiagvik Research Station region on 2025-08-25. Sample type: filter. GPS: 71.320002°, -156.743158°",
-                        "location_id": location_id
-                    })
-                    .to_string(),
-                ))
-                .unwrap(),
-        )
-        .await
-        .unwrap();
+fn read_archive_file(
+    archive: &mut zip::ZipArchive<std::io::Cursor<Vec<u8>>>,
+    name: &str,
+) -> Vec<u8> {
+    use std::io::Read;
+
+    let mut file = archive
+        .by_name(name)
+        .unwrap_or_else(|_| panic!("{name} missing from archive"));
+    let mut content = Vec::new();
+    file.read_to_end(&mut content).unwrap();
+    content
+}
 
-    let sample_body = to_bytes(sample_response.into_body(), usize::MAX)
-        .await
-        .unwrap();
-    let sample: Value = serde_json::from_slice(&sample_body).unwrap();
-    let sample_id = sample["id"].as_str().unwrap();
+#[tokio::test]
+async fn test_experiment_archive_contains_metadata_uploads_and_images() {
+    let app = setup_test_app().await;
 
-    // 4. Create treatments for this sample (none, heat, h2o2)
-    let treatments = vec![
-        json!({
-            "name": "none",
-            "notes": "Untreated control sample - baseline ice nucleation activity",
-            "sample_id": sample_id
-        }),
-        json!({
-            "name": "heat",
-            "notes": "Heat treatment at 95°C for 20 minutes - removes heat-labile biological INPs",
-            "sample_id": sample_id
-        }),
-        json!({
-            "name": "h2o2",
-            "notes": "Hydrogen peroxide treatment - removes organic components including biological INPs",
-            "enzyme_volume_litres": "0.0002358673",
-            "sample_id": sample_id
-        }),
-    ];
+    let experiment = create_test_experiment(&app).await.unwrap();
+    let experiment_id = experiment["id"].as_str().unwrap();
 
-    for treatment in treatments {
-        let _treatment_response = app
-            .clone()
-            .oneshot(
-                Request::builder()
-                    .method("POST")
-                    .uri("/api/treatments")
-                    .header("content-type", "application/json")
-                    .body(Body::from(treatment.to_string()))
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
+    let image_content = b"fake camera image bytes";
+    let notes_content = b"instrument notes";
+    upload_plain_file(
+        &app,
+        experiment_id,
+        "INP_00001_2025-01-01_10-00-00.jpg",
+        image_content,
+    )
+    .await;
+    upload_plain_file(&app, experiment_id, "notes.txt", notes_content).await;
+
+    let mut archive = download_experiment_archive(&app, experiment_id).await;
+
+    let names: Vec<String> = archive.file_names().map(str::to_string).collect();
+    for expected in [
+        "metadata.json",
+        "derived/temperatures.csv",
+        "derived/phase_transitions.csv",
+        "derived/well_results.csv",
+        "images/INP_00001_2025-01-01_10-00-00.jpg",
+        "uploads/notes.txt",
+    ] {
+        assert!(
+            names.iter().any(|n| n == expected),
+            "Expected {expected} in archive, got {names:?}"
+        );
     }
 
-    Ok(sample_id.to_string())
+    assert_eq!(
+        read_archive_file(&mut archive, "images/INP_00001_2025-01-01_10-00-00.jpg"),
+        image_content
+    );
+    assert_eq!(
+        read_archive_file(&mut archive, "uploads/notes.txt"),
+        notes_content
+    );
+
+    let metadata: Value =
+        serde_json::from_slice(&read_archive_file(&mut archive, "metadata.json")).unwrap();
+    assert_eq!(metadata["format_version"], 1);
+    assert_eq!(metadata["experiment"]["id"], experiment_id);
+    assert_eq!(metadata["experiment"]["name"], "Test Experiment");
+    assert_eq!(metadata["assets"].as_array().unwrap().len(), 2);
+
+    // No processed data yet, so derived tables only carry their headers
+    let temperatures =
+        String::from_utf8(read_archive_file(&mut archive, "derived/temperatures.csv")).unwrap();
+    assert_eq!(temperatures, "timestamp,image_filename\n");
 }
 
-/// Helper function to update experiment with regions (uses exact seeder structure)
-#[allow(clippy::too_many_lines)]
-async fn update_experiment_with_regions(
-    app: &Router,
-    experiment_id: &str,
-    sample_id: &str,
-) -> Result<(), String> {
-    // Get the current experiment
-    let experiment_response = app
-        .clone()
-        .oneshot(
-            Request::builder()
-                .method("GET")
-                .uri(format!("/api/experiments/{experiment_id}"))
-                .body(Body::empty())
-                .unwrap(),
-        )
-        .await
-        .unwrap();
+#[tokio::test]
+async fn test_experiment_archive_includes_processed_data() {
+    let app = setup_test_app().await;
 
-    let experiment_body = to_bytes(experiment_response.into_body(), usize::MAX)
+    let tray_config_id = create_test_tray_configuration_with_probes(&app)
         .await
-        .unwrap();
-    let mut experiment_data: Value = serde_json::from_slice(&experiment_body).unwrap();
+        .expect("Failed to create tray configuration");
+    let experiment_id = create_test_experiment_via_api(&app, &tray_config_id)
+        .await
+        .expect("Failed to create experiment");
+    process_excel_file_via_api(&app, &experiment_id)
+        .await
+        .expect("Failed to process Excel file");
 
-    // Get treatments for the sample to link to regions
-    let sample_response = app
-        .clone()
+    let mut archive = download_experiment_archive(&app, &experiment_id).await;
+
+    let original = fs::read("src/experiments/test_resources/merged.xlsx").unwrap();
+    assert_eq!(
+        read_archive_file(&mut archive, "uploads/merged.xlsx"),
+        original
+    );
+
+    let temperatures =
+        String::from_utf8(read_archive_file(&mut archive, "derived/temperatures.csv")).unwrap();
+    let mut lines = temperatures.lines();
+    let header = lines.next().unwrap();
+    assert!(header.starts_with("timestamp,image_filename,"));
+    assert!(
+        header.split(',').count() > 2,
+        "Probe columns missing from header: {header}"
+    );
+    assert!(
+        lines.count() > 100,
+        "Expected a temperature row per time point"
+    );
+
+    let transitions = String::from_utf8(read_archive_file(
+        &mut archive,
+        "derived/phase_transitions.csv",
+    ))
+    .unwrap();
+    assert!(transitions.starts_with("timestamp,tray,well,previous_state,new_state,"));
+    assert!(transitions.lines().count() > 1);
+
+    let well_results =
+        String::from_utf8(read_archive_file(&mut archive, "derived/well_results.csv")).unwrap();
+    assert!(well_results.lines().count() > 1);
+}
+
+#[tokio::test]
+async fn test_experiment_archive_not_found() {
+    let app = setup_test_app().await;
+
+    let response = app
         .oneshot(
             Request::builder()
                 .method("GET")
-                .uri(format!("/api/samples/{sample_id}"))
+                .uri(format!("/api/experiments/{}/archive", uuid::Uuid::new_v4()))
                 .body(Body::empty())
                 .unwrap(),
         )
         .await
         .unwrap();
 
-    let sample_body = to_bytes(sample_response.into_body(), usize::MAX)
-        .await
-        .unwrap();
-    let sample_data: Value = serde_json::from_slice(&sample_body).unwrap();
-    let treatments = sample_data["treatments"].as_array().unwrap();
-
-    // Find treatment IDs
-    let mut treatment_map = std::collections::HashMap::new();
-    for treatment in treatments {
-        let name = treatment["name"].as_str().unwrap();
-        let id = treatment["id"].as_str().unwrap();
-        treatment_map.insert(name, id);
-    }
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
 
-    // Add regions to the experiment (exact same structure as seeder)
-    experiment_data["regions"] = json!([
-        {
-            "treatment_id": treatment_map.get("none").unwrap(),
-            "name": "Untreated Samples - P1",
-            "display_colour_hex": "#3B82F6",
-            "tray_id": 1,
-            "col_min": 0, "col_max": 3, "row_min": 0, "row_max": 7,
-            "dilution_factor": 1,
-            "is_background_key": false
-        },
-        {
-            "treatment_id": treatment_map.get("heat").unwrap(),
-            "name": "Heat Treated - P1",
-            "display_colour_hex": "#EF4444",
-            "tray_id": 1,
-            "col_min": 4, "col_max": 7, "row_min": 0, "row_max": 7,
-            "dilution_factor": 1,
-            "is_background_key": false
-        },
-        {
-            "treatment_id": treatment_map.get("h2o2").unwrap(),
-            "name": "H2O2 Treated - P1",
-            "display_colour_hex": "#10B981",
-            "tray_id": 1,
-            "col_min": 8, "col_max": 11, "row_min": 0, "row_max": 7,
-            "dilution_factor": 1,
-            "is_background_key": false
-        },
-        {
-            "treatment_id": treatment_map.get("none").unwrap(),
-            "name": "Dilution Series 1:10 - P2",
-            "display_colour_hex": "#8B5CF6",
-            "tray_id": 2,
-            "col_min": 0, "col_max": 5, "row_min": 0, "row_max": 7,
-            "dilution_factor": 10,
-            "is_background_key": false
-        },
-        {
-            "treatment_id": treatment_map.get("none").unwrap(),
-            "name": "Dilution Series 1:100 - P2",
-            "display_colour_hex": "#F59E0B",
-            "tray_id": 2,
-            "col_min": 6, "col_max": 11, "row_min": 0, "row_max": 7,
-            "dilution_factor": 100,
-            "is_background_key": false
-        }
-    ]);
+/// Create an experiment with tray configuration, sample, treatments and regions
+async fn create_experiment_with_regions_for_bundle(app: &Router) -> String {
+    let tray_config_id = create_test_tray_configuration_with_probes(app)
+        .await
+        .expect("Failed to create tray configuration");
+    let experiment_id = create_test_experiment_via_api(app, &tray_config_id)
+        .await
+        .expect("Failed to create experiment");
+    let sample_id = create_test_sample_and_treatments(app)
+        .await
+        .expect("Failed to create sample");
+    update_experiment_with_regions(app, &experiment_id, &sample_id)
+        .await
+        .expect("Failed to add regions");
+    experiment_id
+}
 
-    // Update the experiment with regions
-    let update_response = app
+async fn export_bundle(app: &Router, experiment_id: &str) -> (StatusCode, Value) {
+    let response = app
         .clone()
         .oneshot(
             Request::builder()
-                .method("PUT")
-                .uri(format!("/api/experiments/{experiment_id}"))
-                .header("content-type", "application/json")
-                .body(Body::from(experiment_data.to_string()))
+                .method("GET")
+                .uri(format!("/api/experiments/{experiment_id}/bundle"))
+                .body(Body::empty())
                 .unwrap(),
         )
         .await
         .unwrap();
-
-    if update_response.status() != StatusCode::OK {
-        let error_body = to_bytes(update_response.into_body(), usize::MAX)
-            .await
-            .unwrap();
-        let error_text = String::from_utf8_lossy(&error_body);
-        return Err(format!(
-            "Failed to update experiment with regions: {error_text}"
-        ));
-    }
-
-    Ok(())
+    extract_response_body(response).await
 }
 
-/// Helper function to create a simple tray configuration
-async fn create_simple_tray_configuration(app: &Router, name: &str) -> Result<String, String> {
-    let tray_config_response = app
+async fn import_bundle(app: &Router, bundle: &Value) -> (StatusCode, Value) {
+    let response = app
         .clone()
         .oneshot(
             Request::builder()
                 .method("POST")
-                .uri("/api/tray_configurations")
+                .uri("/api/experiments/import")
                 .header("content-type", "application/json")
-                .body(Body::from(
-                    json!({
-                        "name": name,
-                        "experiment_default": false
-                    })
-                    .to_string(),
-                ))
+                .body(Body::from(bundle.to_string()))
                 .unwrap(),
         )
         .await
         .unwrap();
+    extract_response_body(response).await
+}
 
-    if tray_config_response.status() != StatusCode::CREATED {
-        return Err(format!(
-            "Failed to create tray configuration: {}",
-            tray_config_response.status()
-        ));
+#[tokio::test]
+async fn test_experiment_bundle_export_contents() {
+    let app = setup_test_app().await;
+    let experiment_id = create_experiment_with_regions_for_bundle(&app).await;
+
+    let (status, bundle) = export_bundle(&app, &experiment_id).await;
+    assert_eq!(status, StatusCode::OK, "Export failed: {bundle}");
+
+    assert_eq!(bundle["format_version"], 1);
+    assert_eq!(bundle["experiment"]["id"], experiment_id);
+    assert_eq!(
+        bundle["experiment"]["name"],
+        "Excel Processing API Integration Test"
+    );
+
+    let tray_configuration = &bundle["tray_configuration"];
+    assert!(tray_configuration.is_object());
+    let trays = tray_configuration["trays"].as_array().unwrap();
+    assert!(!trays.is_empty());
+    assert!(
+        trays
+            .iter()
+            .any(|t| !t["probes"].as_array().unwrap().is_empty()),
+        "Probe layout should be part of the bundle"
+    );
+
+    let regions = bundle["regions"].as_array().unwrap();
+    let treatments = bundle["treatments"].as_array().unwrap();
+    let samples = bundle["samples"].as_array().unwrap();
+    assert!(!regions.is_empty());
+    assert!(!treatments.is_empty());
+    assert_eq!(samples.len(), 1);
+    assert_eq!(samples[0]["location_name"], "Utqiagvik Research Station");
+
+    // Every region treatment must be resolvable inside the bundle
+    for region in regions {
+        if let Some(treatment_id) = region["treatment_id"].as_str() {
+            assert!(treatments.iter().any(|t| t["id"] == treatment_id));
+        }
     }
 
-    let tray_config_body = to_bytes(tray_config_response.into_body(), usize::MAX)
-        .await
+    assert!(bundle["assets"].as_array().unwrap().is_empty());
+    assert_eq!(bundle["derived"]["temperature_readings"], 0);
+}
+
+#[tokio::test]
+async fn test_experiment_bundle_import_into_other_deployment() {
+    let source = setup_test_app().await;
+    let experiment_id = create_experiment_with_regions_for_bundle(&source).await;
+    let (_, bundle) = export_bundle(&source, &experiment_id).await;
+
+    // A fresh app has its own in-memory database, like a separate deployment
+    let target = setup_test_app().await;
+    let (status, result) = import_bundle(&target, &bundle).await;
+    assert_eq!(status, StatusCode::CREATED, "Import failed: {result}");
+
+    let new_experiment_id = result["experiment_id"].as_str().unwrap();
+    assert_ne!(new_experiment_id, experiment_id);
+    assert_eq!(result["reused_tray_configuration"], false);
+
+    let id_map = result["id_map"].as_object().unwrap();
+    assert_eq!(id_map[&experiment_id], new_experiment_id);
+    for region in bundle["regions"].as_array().unwrap() {
+        assert!(id_map.contains_key(region["id"].as_str().unwrap()));
+    }
+
+    let experiment = get_experiment_data(&target, new_experiment_id).await;
+    assert_eq!(experiment["name"], bundle["experiment"]["name"]);
+    assert_eq!(
+        experiment["tray_configuration_id"],
+        result["tray_configuration_id"]
+    );
+
+    let imported_regions = experiment["regions"].as_array().unwrap();
+    assert_eq!(
+        imported_regions.len(),
+        bundle["regions"].as_array().unwrap().len()
+    );
+    for region in imported_regions {
+        if let Some(treatment_id) = region["treatment_id"].as_str() {
+            assert!(
+                id_map.values().any(|v| v == treatment_id),
+                "Region treatment {treatment_id} was not remapped"
+            );
+        }
+    }
+
+    // The location does not exist on the target, so the sample is left unlinked
+    let new_sample_id = id_map[bundle["samples"][0]["id"].as_str().unwrap()]
+        .as_str()
         .unwrap();
-    let tray_config: Value = serde_json::from_slice(&tray_config_body).unwrap();
-    let tray_config_id = tray_config["id"].as_str().unwrap();
+    let sample = get_sample_data(&target, new_sample_id).await;
+    assert_eq!(sample["name"], bundle["samples"][0]["name"]);
+    assert!(sample["location_id"].is_null());
+}
 
-    Ok(tray_config_id.to_string())
+#[tokio::test]
+async fn test_experiment_bundle_import_reuses_tray_configuration() {
+    let app = setup_test_app().await;
+    let experiment_id = create_experiment_with_regions_for_bundle(&app).await;
+    let (_, mut bundle) = export_bundle(&app, &experiment_id).await;
+
+    // Importing the unchanged bundle collides with the original experiment
+    let (status, _) = import_bundle(&app, &bundle).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    bundle["experiment"]["name"] = json!("Imported copy");
+    let (status, result) = import_bundle(&app, &bundle).await;
+    assert_eq!(status, StatusCode::CREATED, "Import failed: {result}");
+    assert_eq!(result["reused_tray_configuration"], true);
+    assert_eq!(
+        result["tray_configuration_id"],
+        bundle["tray_configuration"]["id"]
+    );
 }
 
-/// API Integration test for experiment results endpoint after Excel processing
-/// This tests retrieving processed experiment results via /api/experiments/{id}
 #[tokio::test]
-async fn test_experiment_results_api_integration() {
+async fn test_experiment_bundle_rejects_unknown_format_version() {
     let app = setup_test_app().await;
+    let experiment_id = create_experiment_with_regions_for_bundle(&app).await;
+    let (_, mut bundle) = export_bundle(&app, &experiment_id).await;
 
-    // 1. Create simple test setup via API
-    let tray_config_id = create_simple_tray_configuration(&app, "Results Test Configuration")
-        .await
-        .expect("Failed to create tray configuration");
+    bundle["format_version"] = json!(99);
+    bundle["experiment"]["name"] = json!("Future bundle");
+    let (status, _) = import_bundle(&app, &bundle).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
 
-    // 2. Create experiment via API with custom name
-    let experiment_response = app
+#[tokio::test]
+async fn test_experiment_bundle_export_not_found() {
+    let app = setup_test_app().await;
+    let (status, _) = export_bundle(&app, &uuid::Uuid::new_v4().to_string()).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+async fn get_experiment_datacite(app: &Router, experiment_id: &str) -> (StatusCode, Value) {
+    let response = app
         .clone()
         .oneshot(
             Request::builder()
-                .method("POST")
-                .uri("/api/experiments")
-                .header("content-type", "application/json")
-                .body(Body::from(
-                    json!({
-                        "name": "Results API Integration Test",
-                        "username": "test_user@example.com",
-                        "performed_at": "2025-01-01T00:00:00Z",
-                        "is_calibration": false,
-                        "remarks": "Testing results API endpoint",
-                        "tray_configuration_id": tray_config_id
-                    })
-                    .to_string(),
-                ))
+                .method("GET")
+                .uri(format!("/api/experiments/{experiment_id}/datacite"))
+                .body(Body::empty())
                 .unwrap(),
         )
         .await
         .unwrap();
+    extract_response_body(response).await
+}
 
-    assert!(
-        (experiment_response.status() == StatusCode::CREATED),
-        "Failed to create experiment: {}",
-        experiment_response.status()
-    );
-
-    let experiment_body = to_bytes(experiment_response.into_body(), usize::MAX)
-        .await
-        .unwrap();
-    let experiment: Value = serde_json::from_slice(&experiment_body).unwrap();
-    let experiment_id = experiment["id"].as_str().unwrap().to_string();
-
-    // 3. Test retrieving experiment without processed data
-    let empty_results_response = app
-        .clone()
+async fn request_zenodo_deposition(app: &Router, experiment_id: &str) -> StatusCode {
+    app.clone()
         .oneshot(
             Request::builder()
-                .method("GET")
-                .uri(format!("/api/experiments/{experiment_id}"))
+                .method("POST")
+                .uri(format!("/api/experiments/{experiment_id}/zenodo-deposition"))
                 .body(Body::empty())
                 .unwrap(),
         )
         .await
-        .unwrap();
+        .unwrap()
+        .status()
+}
 
-    assert_eq!(empty_results_response.status(), StatusCode::OK);
-    let empty_results_body = to_bytes(empty_results_response.into_body(), usize::MAX)
-        .await
-        .unwrap();
-    let empty_experiment_data: Value = serde_json::from_slice(&empty_results_body).unwrap();
+#[tokio::test]
+async fn test_experiment_datacite_metadata() {
+    let app = setup_test_app().await;
+    let experiment_id = create_experiment_with_regions_for_bundle(&app).await;
 
-    // Verify experiment data structure
-    assert_eq!(empty_experiment_data["id"].as_str().unwrap(), experiment_id);
+    let (status, metadata) = get_experiment_datacite(&app, &experiment_id).await;
+    assert_eq!(status, StatusCode::OK, "DataCite request failed: {metadata}");
+
+    assert_eq!(metadata["types"]["resourceTypeGeneral"], "Dataset");
     assert_eq!(
-        empty_experiment_data["name"].as_str().unwrap(),
-        "Results API Integration Test"
+        metadata["titles"][0]["title"],
+        "Excel Processing API Integration Test"
     );
+    assert_eq!(metadata["creators"][0]["name"], "test_user@example.com");
+    assert_eq!(metadata["creators"][0]["nameType"], "Personal");
+    assert_eq!(metadata["publisher"], "SPICE Test Publisher");
+    assert!(metadata.get("doi").is_none(), "No DOI has been assigned yet");
 
-    // Check results structure (should be null or empty for experiment without data)
-    let results = empty_experiment_data.get("results");
-    if let Some(results_val) = results
-        && !results_val.is_null()
-    {
-        // If results exist, they should have the expected structure
-        assert!(
-            results_val.get("summary").is_some(),
-            "Results should have summary"
-        );
-        assert!(
-            results_val.get("trays").is_some(),
-            "Results should have trays array"
-        );
-    }
+    let dates = metadata["dates"].as_array().unwrap();
+    assert!(dates.contains(&json!({"date": "2025-01-01", "dateType": "Created"})));
+    assert!(dates.contains(&json!({"date": "2025-08-25/2025-08-25", "dateType": "Collected"})));
 
-    // 4. Test API error handling
-    let nonexistent_id = "00000000-0000-0000-0000-000000000000";
-    let not_found_response = app
+    let subjects: Vec<&str> = metadata["subjects"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|s| s["subject"].as_str())
+        .collect();
+    assert!(subjects.contains(&"Ice nucleation"));
+    assert!(subjects.contains(&"filter sample"));
+    assert!(subjects.contains(&"heat treatment"));
+
+    assert_eq!(
+        metadata["geoLocations"][0]["geoLocationPlace"],
+        "Utqiagvik Research Station"
+    );
+    assert_eq!(metadata["descriptions"][0]["descriptionType"], "Abstract");
+    assert_eq!(
+        metadata["descriptions"][0]["description"],
+        "Testing Excel upload via API"
+    );
+}
+
+#[tokio::test]
+async fn test_experiment_datacite_not_found() {
+    let app = setup_test_app().await;
+    let (status, _) = get_experiment_datacite(&app, &uuid::Uuid::new_v4().to_string()).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_zenodo_deposition_requires_configuration_and_no_existing_doi() {
+    use sea_orm::{ActiveModelTrait, ActiveValue::Set};
+
+    let db = crate::config::test_helpers::setup_test_db().await;
+    let mut config = crate::config::Config::for_tests();
+    config.keycloak_url = String::new();
+    let app = crate::routes::build_router(&db, &config);
+    let experiment_id = create_experiment_with_regions_for_bundle(&app).await;
+
+    // Tests run without a Zenodo access token
+    assert_eq!(
+        request_zenodo_deposition(&app, &experiment_id).await,
+        StatusCode::SERVICE_UNAVAILABLE
+    );
+
+    // Clients cannot set the DOI themselves
+    let response = app
         .clone()
         .oneshot(
             Request::builder()
-                .method("GET")
-                .uri(format!("/api/experiments/{nonexistent_id}"))
-                .body(Body::empty())
+                .method("PUT")
+                .uri(format!("/api/experiments/{experiment_id}"))
+                .header("content-type", "application/json")
+                .body(Body::from(json!({"doi": "10.5281/zenodo.1234567"}).to_string()))
                 .unwrap(),
         )
         .await
         .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let (_, metadata) = get_experiment_datacite(&app, &experiment_id).await;
+    assert!(metadata.get("doi").is_none(), "{metadata}");
+
+    // An experiment that has a DOI is not deposited again
+    crate::experiments::models::ActiveModel {
+        id: Set(experiment_id.parse().unwrap()),
+        doi: Set(Some("10.5281/zenodo.1234567".to_string())),
+        ..Default::default()
+    }
+    .update(&db)
+    .await
+    .unwrap();
+    let (_, metadata) = get_experiment_datacite(&app, &experiment_id).await;
+    assert_eq!(metadata["doi"], "10.5281/zenodo.1234567");
 
-    assert_eq!(not_found_response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        request_zenodo_deposition(&app, &experiment_id).await,
+        StatusCode::CONFLICT
+    );
+    assert_eq!(
+        request_zenodo_deposition(&app, &uuid::Uuid::new_v4().to_string()).await,
+        StatusCode::NOT_FOUND
+    );
+}
 
-    // 5. Test invalid UUID handling
-    let invalid_uuid_response = app
+/// Final 0/1 state of every well column ("Tray:A1" -> state) and the number of data rows
+fn final_well_states(xlsx: Vec<u8>) -> (HashMap<String, i32>, usize) {
+    use crate::services::processing::{
+        structure::parse_excel_structure,
+        utils::{extract_integer, load_excel},
+    };
+
+    let rows = load_excel(xlsx).expect("Workbook should be readable");
+    let structure = parse_excel_structure(&rows).expect("Workbook should have the merged layout");
+    let data_rows = &rows[structure.data_start_row..];
+    let last_row = data_rows.last().expect("Workbook should have data rows");
+
+    let states = structure
+        .well_columns
+        .iter()
+        .map(|(well_key, &col)| {
+            let state = extract_integer(&last_row[col])
+                .unwrap_or_else(|| panic!("Unexpected well state for {well_key}"));
+            (well_key.clone(), state)
+        })
+        .collect();
+    (states, data_rows.len())
+}
+
+#[tokio::test]
+async fn test_experiment_excel_export_round_trip() {
+    let app = setup_test_app().await;
+
+    let tray_config_id = create_test_tray_configuration_with_probes(&app)
+        .await
+        .expect("Failed to create tray configuration");
+    let experiment_id = create_test_experiment_via_api(&app, &tray_config_id)
+        .await
+        .expect("Failed to create experiment");
+    process_excel_file_via_api(&app, &experiment_id)
+        .await
+        .expect("Failed to process Excel file");
+
+    let response = app
         .clone()
         .oneshot(
             Request::builder()
                 .method("GET")
-                .uri("/api/experiments/invalid-uuid")
+                .uri(format!("/api/experiments/{experiment_id}/excel"))
                 .body(Body::empty())
                 .unwrap(),
         )
         .await
         .unwrap();
-
-    assert_eq!(invalid_uuid_response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()["content-type"],
+        "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"
+    );
+    let exported = to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap()
+        .to_vec();
+
+    // The regenerated workbook can be read back by the Excel processor and
+    // reproduces the well states of the original upload
+    let original = fs::read("src/experiments/test_resources/merged.xlsx").unwrap();
+    let (original_states, original_rows) = final_well_states(original);
+    let (exported_states, exported_rows) = final_well_states(exported.clone());
+    assert_eq!(exported_states.len(), 192);
+    assert_eq!(exported_states, original_states);
+    assert_eq!(exported_rows, original_rows);
+
+    let rows = crate::services::processing::utils::load_excel(exported).unwrap();
+    let structure = crate::services::processing::structure::parse_excel_structure(&rows).unwrap();
+    assert_eq!(structure.probe_columns.len(), 8);
+    assert!(structure.image_col.is_some());
 }
 
 #[tokio::test]
-async fn test_seeder_experiment_structure() {
+async fn test_experiment_excel_export_not_found() {
     let app = setup_test_app().await;
-
-    // Test the exact structure our seeder is trying to create
-    let seeder_experiment = json!({
-        "name": "Arctic Aerosol INP Characterization Exp139",
-        "username": "researcher@eerl.lab",
-        "temperature_ramp": -1.0,
-        "temperature_start": 5.0,
-        "temperature_end": -25.0,
-        "is_calibration": false,
-        "remarks": "Comprehensive characterization of Arctic atmospheric aerosol samples using SPICE droplet freezing technique",
-        "performed_at": "2025-08-15T10:30:00.000Z"
-    });
-
-    let create_response = app
-        .clone()
+    let response = app
         .oneshot(
             Request::builder()
-                .method("POST")
-                .uri("/api/experiments")
-                .header("content-type", "application/json")
-                .body(Body::from(seeder_experiment.to_string()))
+                .method("GET")
+                .uri(format!("/api/experiments/{}/excel", uuid::Uuid::new_v4()))
+                .body(Body::empty())
                 .unwrap(),
         )
-        .await
-        .unwrap();
-
-    let (create_status, create_body) = extract_response_body(create_response).await;
-
-    println!("SEEDER EXPERIMENT TEST - Status: {create_status}");
-    println!("SEEDER EXPERIMENT TEST - Body: {create_body:?}");
-
-    assert_eq!(
-        create_status,
-        StatusCode::CREATED,
-        "Seeder experiment payload should create successfully. Status: {create_status}, Body: {create_body:?}"
-    );
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
 }
 
-#[tokio::test]
-async fn test_seeder_sample_with_coordinates() {
-    let app = setup_test_app().await;
-
-    // Test the enhanced sample structure with GPS coordinates
-    let seeder_sample = json!({
-        "name": "Utqiagvik Research PM10 Aerosol Filter 2025-08-15",
-        "type": "filter",
-        "latitude": "71.3230",
-        "longitude": "-156.7660",
-        "remarks": "Environmental sample collected from Utqiagvik Research Station on 2025-08-15. Sample type: filter. GPS: 71.3230°, -156.7660°"
-    });
-
-    let create_response = app
+async fn put_experiment_regions(
+    app: &Router,
+    experiment_id: &str,
+    regions: Value,
+) -> (StatusCode, String) {
+    let response = app
         .clone()
         .oneshot(
             Request::builder()
-                .method("POST")
-                .uri("/api/samples")
+                .method("PUT")
+                .uri(format!("/api/experiments/{experiment_id}"))
                 .header("content-type", "application/json")
-                .body(Body::from(seeder_sample.to_string()))
+                .body(Body::from(json!({ "regions": regions }).to_string()))
                 .unwrap(),
         )
         .await
         .unwrap();
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
 
-    let (create_status, create_body) = extract_response_body(create_response).await;
-
-    println!("SEEDER SAMPLE GPS TEST - Status: {create_status}");
-    println!("SEEDER SAMPLE GPS TEST - Body: {create_body:?}");
-
-    assert_eq!(
-        create_status,
-        StatusCode::CREATED,
-        "Seeder sample with GPS should create successfully. Status: {create_status}, Body: {create_body:?}"
-    );
-
-    // Verify GPS coordinates are stored - note: Decimal type may truncate trailing zeros
-    let latitude = create_body["latitude"].as_str().unwrap();
-    let longitude = create_body["longitude"].as_str().unwrap();
-
-    // Check that coordinates have at least 3 decimal places and are within expected range
+#[tokio::test]
+async fn test_experiment_region_validation() {
+    let app = setup_test_app().await;
+    let tray_config: Value =
+        serde_json::from_str(&create_test_tray_config_with_trays(&app, "Region bounds").await)
+            .unwrap();
+    let experiment_id = create_experiment_via_api(&app).await.unwrap();
+    assign_tray_config_to_experiment_via_api(
+        &app,
+        &experiment_id,
+        tray_config["id"].as_str().unwrap(),
+    )
+    .await;
+
+    // Both trays have 12 rows of 8 columns
+    let (status, body) = put_experiment_regions(
+        &app,
+        &experiment_id,
+        json!([
+            {"name": "Top", "tray_id": 1, "row_min": 0, "row_max": 5, "col_min": 0, "col_max": 7},
+            {"name": "Bottom", "tray_id": 1, "row_min": 6, "row_max": 11, "col_min": 0, "col_max": 7},
+            {"name": "Top", "tray_id": 2, "row_min": 0, "row_max": 5, "col_min": 0, "col_max": 7}
+        ]),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    let (status, body) = put_experiment_regions(
+        &app,
+        &experiment_id,
+        json!([
+            {"name": "Wide", "tray_id": 1, "row_min": 0, "row_max": 5, "col_min": 0, "col_max": 11}
+        ]),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
     assert!(
-        latitude.starts_with("71.32"),
-        "Latitude should be around 71.32, got: {latitude}"
+        body.contains(
+            "Region 'Wide' (rows 0-5, columns 0-11) does not fit on tray 1 of 12 rows and 8 columns"
+        ),
+        "{body}"
     );
+
+    let (status, body) = put_experiment_regions(
+        &app,
+        &experiment_id,
+        json!([
+            {"name": "Top", "tray_id": 1, "row_min": 0, "row_max": 6, "col_min": 0, "col_max": 7},
+            {"name": "Bottom", "tray_id": 1, "row_min": 6, "row_max": 11, "col_min": 0, "col_max": 7},
+            {"name": "Elsewhere", "tray_id": 3, "row_min": 0, "row_max": 1, "col_min": 0, "col_max": 1}
+        ]),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
     assert!(
-        longitude.starts_with("-156.76"),
-        "Longitude should be around -156.76, got: {longitude}"
+        body.contains("Region 'Bottom' overlaps region 'Top' on tray 1"),
+        "{body}"
     );
+    assert!(body.contains("Region 'Elsewhere' is on tray 3"), "{body}");
+
+    // Rejected updates leave the saved regions untouched
+    let experiment = get_experiment_data(&app, &experiment_id).await;
+    assert_eq!(experiment["regions"].as_array().unwrap().len(), 3);
 }
 
-/// Test temperature readings at specific timestamps, especially during phase changes
-/// Verifies that all 8 probes return correct temperature data with metadata
-#[allow(clippy::too_many_lines)]
 #[tokio::test]
-async fn test_temperature_readings_during_phase_changes() {
-    println!("🌡️ Testing temperature readings during phase changes");
-
+async fn test_sample_rollup_includes_aliquot_results() {
     let app = setup_test_app().await;
-
-    // 1. Create tray configuration with full probe setup (8 probes)
     let tray_config_id = create_test_tray_configuration_with_probes(&app)
         .await
-        .expect("Failed to create tray configuration with probes");
-
-    // 2. Create experiment
+        .expect("Failed to create tray configuration");
     let experiment_id = create_test_experiment_via_api(&app, &tray_config_id)
         .await
         .expect("Failed to create experiment");
-
-    // 3. Process Excel file to get temperature and phase change data
-    let _processing_result = process_excel_file_via_api(&app, &experiment_id)
+    let aliquot_id = create_test_sample_and_treatments(&app)
+        .await
+        .expect("Failed to create sample and treatments");
+    update_experiment_with_regions(&app, &experiment_id, &aliquot_id)
+        .await
+        .expect("Failed to add regions to experiment");
+    process_excel_file_via_api(&app, &experiment_id)
         .await
         .expect("Failed to process Excel file");
 
-    // 4. Get experiment results with temperature data
-    let results_response = app
+    let send = |method: &str, uri: String, data: Value| {
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(Body::from(data.to_string()))
+            .unwrap()
+    };
+    let response = app
+        .clone()
+        .oneshot(send(
+            "POST",
+            "/api/samples".to_string(),
+            json!({"name": "Whole filter", "type": "filter"}),
+        ))
+        .await
+        .unwrap();
+    let (status, parent) = extract_response_body(response).await;
+    assert_eq!(status, StatusCode::CREATED, "{parent}");
+    let parent_id = parent["id"].as_str().unwrap();
+
+    // Treatments are replaced on update, so the aliquot's are sent back as they are
+    let aliquot = get_sample_data(&app, &aliquot_id).await;
+    let treatments: Vec<Value> = aliquot["treatments"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|treatment| json!({"id": treatment["id"]}))
+        .collect();
+    let aliquot_events: usize = aliquot["treatments"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|treatment| treatment["experimental_results"].as_array().unwrap().len())
+        .sum();
+    assert!(aliquot_events > 0);
+    let response = app
+        .clone()
+        .oneshot(send(
+            "PUT",
+            format!("/api/samples/{aliquot_id}"),
+            json!({"parent_sample_id": parent_id, "aliquot_label": "1/2", "treatments": treatments}),
+        ))
+        .await
+        .unwrap();
+    let (status, body) = extract_response_body(response).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    let response = app
         .clone()
         .oneshot(
             Request::builder()
-                .method("GET")
-                .uri(format!("/api/experiments/{experiment_id}"))
+                .uri(format!("/api/samples/{parent_id}/rollup"))
                 .body(Body::empty())
                 .unwrap(),
         )
         .await
         .unwrap();
+    let (status, rollup) = extract_response_body(response).await;
+    assert_eq!(status, StatusCode::OK, "{rollup}");
+    let samples = rollup["samples"].as_array().unwrap();
+    assert_eq!(samples.len(), 2);
+    assert_eq!(samples[0]["sample"]["id"], parent_id);
+    assert!(
+        samples[0]["experimental_results"]
+            .as_array()
+            .unwrap()
+            .is_empty()
+    );
+    assert_eq!(samples[1]["sample"]["aliquot_label"], "1/2");
+    assert_eq!(
+        samples[1]["experimental_results"].as_array().unwrap().len(),
+        aliquot_events
+    );
+    assert_eq!(rollup["statistics"]["total_wells"], aliquot_events);
+    assert!(!rollup["dilution_summaries"].as_array().unwrap().is_empty());
+}
 
-    assert_eq!(results_response.status(), StatusCode::OK);
-    let results_body = to_bytes(results_response.into_body(), usize::MAX)
+#[tokio::test]
+#[allow(clippy::too_many_lines)]
+async fn test_rejected_samples_cannot_be_assigned_to_regions() {
+    let app = setup_test_app().await;
+    let send = |method: &str, uri: String, data: Value| {
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(Body::from(data.to_string()))
+            .unwrap()
+    };
+    let response = app
+        .clone()
+        .oneshot(send(
+            "POST",
+            "/api/samples".to_string(),
+            json!({"name": "Contaminated filter", "type": "filter", "treatments": [{"name": "none"}]}),
+        ))
         .await
         .unwrap();
-    let experiment_data: Value =
-        serde_json::from_slice(&results_body).expect("Failed to parse experiment results");
-
-    // 5. Validate overall structure
-    let results = experiment_data
-        .get("results")
-        .expect("Experiment should have results")
-        .as_object()
-        .expect("Results should be an object");
-
-    let trays = results
-        .get("trays")
-        .expect("Results should have trays")
-        .as_array()
-        .expect("Trays should be an array");
-
-    assert!(!trays.is_empty(), "Should have at least one tray");
+    let (status, sample) = extract_response_body(response).await;
+    assert_eq!(status, StatusCode::CREATED, "{sample}");
+    assert_eq!(sample["qc_status"], "pending");
+    let sample_id = sample["id"].as_str().unwrap();
+    let treatment_id = sample["treatments"][0]["id"].as_str().unwrap();
+    let region = json!([{"name": "Untreated", "treatment_id": treatment_id}]);
 
-    // 6. Find wells with phase changes and temperature data
-    let mut wells_with_phase_changes = 0;
-    let mut wells_with_temperatures = 0;
-    let mut total_probe_readings_checked = 0;
+    let experiment_id = create_experiment_via_api(&app).await.unwrap();
+    let (status, body) = put_experiment_regions(&app, &experiment_id, region.clone()).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
 
-    for tray in trays {
-        let wells = tray
-            .get("wells")
-            .expect("Tray should have wells")
-            .as_array()
-            .expect("Wells should be an array");
+    // Rejecting or flagging needs a reason
+    let response = app
+        .clone()
+        .oneshot(send(
+            "PUT",
+            format!("/api/samples/{sample_id}/qc"),
+            json!({"status": "rejected", "reason": " "}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = app
+        .clone()
+        .oneshot(send(
+            "PUT",
+            format!("/api/samples/{sample_id}/qc"),
+            json!({"status": "rejected", "reason": "Field blank above threshold"}),
+        ))
+        .await
+        .unwrap();
+    let (status, reviewed) = extract_response_body(response).await;
+    assert_eq!(status, StatusCode::OK, "{reviewed}");
+    assert_eq!(reviewed["qc_status"], "rejected");
+    assert_eq!(reviewed["qc_reason"], "Field blank above threshold");
+    assert!(reviewed["qc_reviewed_at"].is_string());
 
-        for well in wells {
-            // Check if well has phase change time
-            if let Some(_phase_change_time) = well.get("first_phase_change_time") {
-                wells_with_phase_changes += 1;
+    // The QC status is only set through a review
+    let response = app
+        .clone()
+        .oneshot(send(
+            "PUT",
+            format!("/api/samples/{sample_id}"),
+            json!({"qc_status": "accepted", "remarks": "Rechecked", "treatments": [{"id": treatment_id}]}),
+        ))
+        .await
+        .unwrap();
+    let (status, updated) = extract_response_body(response).await;
+    assert_eq!(status, StatusCode::OK, "{updated}");
+    assert_eq!(updated["qc_status"], "rejected");
 
-                // Check if well has temperature data at phase change
-                if let Some(temperatures) = well.get("temperatures") {
-                    wells_with_temperatures += 1;
+    // Regions already using the sample are kept
+    let (status, body) = put_experiment_regions(&app, &experiment_id, region.clone()).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
 
-                    // Validate temperature structure
-                    assert!(
-                        temperatures.get("id").is_some(),
-                        "Temperature reading should have id"
-                    );
-                    assert!(
-                        temperatures.get("timestamp").is_some(),
-                        "Temperature reading should have timestamp"
-                    );
-                    assert!(
-                        temperatures.get("average").is_some(),
-                        "Temperature reading should have average"
-                    );
+    let response = app
+        .clone()
+        .oneshot(send(
+            "POST",
+            "/api/experiments".to_string(),
+            json!({"name": "Rerun without the rejected filter", "is_calibration": false}),
+        ))
+        .await
+        .unwrap();
+    let (status, other_experiment) = extract_response_body(response).await;
+    assert_eq!(status, StatusCode::CREATED, "{other_experiment}");
+    let other_experiment_id = other_experiment["id"].as_str().unwrap();
+    let (status, body) = put_experiment_regions(&app, other_experiment_id, region.clone()).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(
+        body.contains(
+            "Rejected samples cannot be assigned to experiment regions: 'Contaminated filter'"
+        ),
+        "{body}"
+    );
+    let response = app
+        .clone()
+        .oneshot(send(
+            "POST",
+            "/api/experiments".to_string(),
+            json!({"name": "New run", "is_calibration": false, "regions": region}),
+        ))
+        .await
+        .unwrap();
+    assert!(!response.status().is_success());
 
-                    // Validate probe readings array
-                    let probe_readings = temperatures
-                        .get("probe_readings")
-                        .expect("Temperature reading should have probe_readings")
-                        .as_array()
-                        .expect("probe_readings should be an array");
+    // Flagged samples can still be used
+    let response = app
+        .clone()
+        .oneshot(send(
+            "PUT",
+            format!("/api/samples/{sample_id}/qc"),
+            json!({"status": "flagged", "reason": "Check the blank again"}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let (status, body) = put_experiment_regions(&app, other_experiment_id, region).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+}
 
-                    println!(
-                        "   Well {}:{} has {} probe readings at phase change",
-                        well["row_letter"].as_str().unwrap_or("?"),
-                        well["column_number"].as_i64().unwrap_or(0),
-                        probe_readings.len()
-                    );
+async fn patch_experiment(
+    app: &Router,
+    experiment_id: &str,
+    content_type: &str,
+    patch: &Value,
+) -> (StatusCode, Value) {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PATCH")
+                .uri(format!("/api/experiments/{experiment_id}"))
+                .header("content-type", content_type)
+                .body(Body::from(patch.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (
+        status,
+        serde_json::from_slice(&body)
+            .unwrap_or_else(|_| json!(String::from_utf8_lossy(&body).to_string())),
+    )
+}
 
-                    // Validate each probe reading
-                    for probe_reading in probe_readings {
-                        total_probe_readings_checked += 1;
+#[tokio::test]
+async fn test_experiment_merge_patch() {
+    let app = setup_test_app().await;
+    let experiment_id = create_experiment_via_api(&app).await.unwrap();
+    let tray_config_id = create_simple_tray_config(&app).await.unwrap();
 
-                        // Check probe reading structure
-                        assert!(
-                            probe_reading.get("id").is_some(),
-                            "Probe reading should have id"
-                        );
-                        assert!(
-                            probe_reading.get("probe_id").is_some(),
-                            "Probe reading should have probe_id"
-                        );
-                        assert!(
-                            probe_reading.get("temperature").is_some(),
-                            "Probe reading should have temperature"
-                        );
+    // Only the named field changes
+    let (status, experiment) = patch_experiment(
+        &app,
+        &experiment_id,
+        "application/merge-patch+json",
+        &json!({"tray_configuration_id": tray_config_id}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{experiment}");
+    assert_eq!(experiment["tray_configuration_id"], tray_config_id);
+    assert_eq!(experiment["username"], "test@example.com");
+    assert_eq!(experiment["performed_at"], "2024-06-20T14:30:00Z");
+
+    // Null clears a field
+    let (status, experiment) = patch_experiment(
+        &app,
+        &experiment_id,
+        "application/json",
+        &json!({"username": null, "remarks": "Rerun"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{experiment}");
+    assert_eq!(experiment["username"], Value::Null);
+    assert_eq!(experiment["remarks"], "Rerun");
+    assert_eq!(experiment["tray_configuration_id"], tray_config_id);
+
+    let (status, _) = patch_experiment(&app, &experiment_id, "application/json", &json!([])).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let (status, _) = patch_experiment(
+        &app,
+        &experiment_id,
+        "application/json",
+        &json!({"is_calibration": "yes"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let (status, _) = patch_experiment(
+        &app,
+        &uuid::Uuid::new_v4().to_string(),
+        "application/json",
+        &json!({"remarks": "Lost"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
 
-                        // Check probe metadata (new feature)
-                        assert!(
-                            probe_reading.get("probe_name").is_some(),
-                            "Probe reading should have probe_name"
-                        );
-                        assert!(
-                            probe_reading.get("probe_data_column_index").is_some(),
-                            "Probe reading should have probe_data_column_index"
-                        );
-                        assert!(
-                            probe_reading.get("probe_position_x").is_some(),
-                            "Probe reading should have probe_position_x"
-                        );
-                        assert!(
-                            probe_reading.get("probe_position_y").is_some(),
-                            "Probe reading should have probe_position_y"
-                        );
+#[tokio::test]
+async fn test_experiment_sparse_fieldsets() {
+    let app = setup_test_app().await;
+    let experiment_id = create_experiment_via_api(&app).await.unwrap();
+    let get = |uri: String| {
+        let app = app.clone();
+        async move {
+            let response = app
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            serde_json::from_slice::<Value>(&body).unwrap()
+        }
+    };
 
-                        // Validate temperature value (should be formatted to 3 decimal places)
-                        let temperature_str = probe_reading
-                            .get("temperature")
-                            .and_then(|t| t.as_str())
-                            .expect("Temperature should be a string");
+    let query = serde_urlencoded::to_string([
+        ("fields", "id,name,performed_at".to_string()),
+        ("filter", json!({"id": experiment_id}).to_string()),
+    ])
+    .unwrap();
+    let experiments = get(format!("/api/experiments?{query}")).await;
+    assert_eq!(
+        experiments,
+        json!([{
+            "id": experiment_id,
+            "name": "Test Image Correlation Experiment",
+            "performed_at": "2024-06-20T14:30:00Z",
+        }])
+    );
 
-                        // Parse temperature and check it's reasonable
-                        let temperature: f64 = temperature_str
-                            .parse()
-                            .expect("Temperature should be parseable as float");
+    let experiment = get(format!(
+        "/api/experiments/{experiment_id}?fields=name,regions.name"
+    ))
+    .await;
+    assert_eq!(
+        experiment,
+        json!({"name": "Test Image Correlation Experiment", "regions": []})
+    );
+}
 
-                        // During phase changes, temperatures should be below 0°C and above -30°C (reasonable range)
-                        assert!(
-                            (-30.0..=5.0).contains(&temperature),
-                            "Temperature {temperature} should be in reasonable range (-30°C to 5°C) during phase change"
-                        );
+#[tokio::test]
+async fn test_experiment_relation_includes() {
+    let app = setup_test_app().await;
+    let experiment_id = create_experiment_via_api(&app).await.unwrap();
+    let get = |query: &'static str| {
+        let app = app.clone();
+        let uri = format!("/api/experiments/{experiment_id}{query}");
+        async move {
+            let response = app
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            serde_json::from_slice::<Value>(&body).unwrap()
+        }
+    };
 
-                        // Validate probe metadata values
-                        let probe_name = probe_reading
-                            .get("probe_name")
-                            .and_then(|n| n.as_str())
-                            .expect("Probe name should be a string");
-                        assert!(
-                            probe_name.starts_with("Probe"),
-                            "Probe name should start with 'Probe', got: {probe_name}"
-                        );
+    // Regions and results are embedded by default, assets are not
+    let experiment = get("").await;
+    assert!(experiment["results"].is_object(), "{experiment}");
+    assert_eq!(experiment["assets"], Value::Null);
 
-                        let data_column_index = probe_reading
-                            .get("probe_data_column_index")
-                            .and_then(serde_json::Value::as_i64)
-                            .expect("Data column index should be an integer");
-                        assert!(
-                            (1..=8).contains(&data_column_index),
-                            "Data column index should be 1-8, got: {data_column_index}"
-                        );
-                    }
+    let experiment = get("?include=regions,assets").await;
+    assert_eq!(experiment["results"], Value::Null);
+    assert_eq!(experiment["assets"], json!([]));
 
-                    // Validate average temperature
-                    if let Some(average_str) = temperatures.get("average").and_then(|a| a.as_str())
-                    {
-                        let average: f64 = average_str
-                            .parse()
-                            .expect("Average temperature should be parseable as float");
-                        assert!(
-                            (-30.0..=5.0).contains(&average),
-                            "Average temperature {average} should be in reasonable range during phase change"
-                        );
-                    }
+    let experiment = get("?include=").await;
+    assert_eq!(experiment["results"], Value::Null);
+    assert_eq!(experiment["assets"], Value::Null);
+    assert_eq!(experiment["name"], "Test Image Correlation Experiment");
+}
 
-                    // Stop after checking a few wells to avoid test timeout
-                    if wells_with_temperatures >= 3 {
-                        break;
-                    }
-                }
-            }
+#[tokio::test]
+async fn test_experiment_entity_tags() {
+    let mut config = crate::config::Config::for_tests();
+    config.keycloak_url = String::new();
+    config.require_if_match = true;
+    let db = crate::config::test_helpers::setup_test_db().await;
+    let app = crate::routes::build_router(&db, &config);
+    let experiment_id = create_experiment_via_api(&app).await.unwrap();
+    let send = |method: &str, header: Option<(&str, String)>, body: Option<Value>| {
+        let mut request = Request::builder()
+            .method(method)
+            .uri(format!("/api/experiments/{experiment_id}"))
+            .header("content-type", "application/json");
+        if let Some((name, value)) = header {
+            request = request.header(name, value);
         }
-        if wells_with_temperatures >= 3 {
-            break;
+        let request = request
+            .body(body.map_or_else(Body::empty, |body| Body::from(body.to_string())))
+            .unwrap();
+        let app = app.clone();
+        async move {
+            let response = app.oneshot(request).await.unwrap();
+            let tag = response
+                .headers()
+                .get("etag")
+                .map(|tag| tag.to_str().unwrap().to_string());
+            (response.status(), tag)
+        }
+    };
+
+    let (status, tag) = send("GET", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let tag = tag.expect("Records are read with their ETag");
+    let (status, _) = send("GET", Some(("if-none-match", tag.clone())), None).await;
+    assert_eq!(status, StatusCode::NOT_MODIFIED);
+
+    // Changes must give the tag, and only the current one is accepted
+    let change = json!({"remarks": "First edit"});
+    let (status, _) = send("PATCH", None, Some(change.clone())).await;
+    assert_eq!(status, StatusCode::PRECONDITION_REQUIRED);
+    let (status, new_tag) = send("PATCH", Some(("if-match", tag.clone())), Some(change)).await;
+    assert_eq!(status, StatusCode::OK);
+    let new_tag = new_tag.unwrap();
+    assert_ne!(new_tag, tag);
+
+    // A client still holding the old tag cannot overwrite the edit
+    let stale = json!({"remarks": "Second edit"});
+    let (status, _) = send("PATCH", Some(("if-match", tag)), Some(stale.clone())).await;
+    assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+    let (status, _) = send("PUT", Some(("if-match", new_tag)), Some(stale)).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send("PATCH", Some(("if-match", "*".to_string())), Some(json!({}))).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_experiment_soft_delete_and_restore() {
+    let app = setup_test_app().await;
+    let experiment_id = create_experiment_via_api(&app).await.unwrap();
+    let send = |method: &str, uri: String| {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap();
+        let app = app.clone();
+        async move {
+            let response = app.oneshot(request).await.unwrap();
+            let status = response.status();
+            let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
         }
+    };
+    let listed = |experiments: &Value| {
+        experiments
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|experiment| experiment["id"] == experiment_id.as_str())
+    };
+
+    let (status, _) = send("DELETE", format!("/api/experiments/{experiment_id}")).await;
+    assert!(status.is_success(), "{status}");
+
+    // The deleted experiment is out of sight, but kept
+    let (status, _) = send("GET", format!("/api/experiments/{experiment_id}")).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = send("DELETE", format!("/api/experiments/{experiment_id}")).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (_, experiments) = send("GET", "/api/experiments".to_string()).await;
+    assert!(!listed(&experiments));
+    let (_, experiments) = send("GET", "/api/experiments?deleted=true".to_string()).await;
+    assert!(listed(&experiments));
+    let (status, experiment) = send(
+        "GET",
+        format!("/api/experiments/{experiment_id}?deleted=true"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(experiment["deleted_at"].is_string());
+
+    let (status, experiment) =
+        send("POST", format!("/api/experiments/{experiment_id}/restore")).await;
+    assert_eq!(status, StatusCode::OK, "{experiment}");
+    assert_eq!(experiment["deleted_at"], Value::Null);
+    let (status, _) = send("POST", format!("/api/experiments/{experiment_id}/restore")).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (_, experiments) = send("GET", "/api/experiments".to_string()).await;
+    assert!(listed(&experiments));
+    let (_, experiments) = send("GET", "/api/experiments?deleted=true".to_string()).await;
+    assert!(!listed(&experiments));
+}
+
+#[tokio::test]
+async fn test_experiment_upsert_by_name_and_date() {
+    let app = setup_test_app().await;
+    let experiments = json!([{
+        "name": "Upserted experiment",
+        "performed_at": "2025-03-04T10:00:00Z",
+        "is_calibration": false,
+        "remarks": "first import"
+    }]);
+
+    let upsert = "/api/experiments/upsert";
+    let (status, first) = post_json_with_headers(&app, upsert, &experiments, &[]).await;
+    assert_eq!(status, StatusCode::OK, "{first}");
+    assert_eq!(first["results"][0]["action"], "created", "{first}");
+
+    let mut experiments = experiments;
+    experiments[0]["remarks"] = json!("second import");
+    let (status, second) = post_json_with_headers(&app, upsert, &experiments, &[]).await;
+    assert_eq!(status, StatusCode::OK, "{second}");
+    assert_eq!(second["updated"], 1, "{second}");
+    assert_eq!(second["results"][0]["id"], first["results"][0]["id"]);
+
+    // Another date is another experiment, whose name is already taken
+    experiments[0]["performed_at"] = json!("2025-03-05T10:00:00Z");
+    let (_, third) = post_json_with_headers(&app, upsert, &experiments, &[]).await;
+    assert_eq!(third["failed"], 1, "{third}");
+    assert!(third["results"][0]["error"].is_string());
+}
+
+#[tokio::test]
+async fn test_experiments_per_month() {
+    let app = setup_test_app().await;
+    for (name, performed_at) in [
+        ("March experiment 1", "2025-03-04T10:00:00Z"),
+        ("March experiment 2", "2025-03-20T10:00:00Z"),
+        ("April experiment", "2025-04-02T10:00:00Z"),
+    ] {
+        let (status, body) = post_json_with_headers(
+            &app,
+            "/api/experiments",
+            &json!({"name": name, "performed_at": performed_at, "is_calibration": false}),
+            &[],
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED, "{body}");
     }
 
-    // 7. Validate test results
-    assert!(
-        wells_with_phase_changes > 0,
-        "Should have found wells with phase changes"
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/experiments/aggregate?group_by=performed_at&interval=month")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (status, months) = extract_response_body(response).await;
+    assert_eq!(status, StatusCode::OK, "{months}");
+    assert_eq!(
+        months,
+        json!([
+            {"key": "2025-03", "count": 2, "min": null, "max": null, "avg": null},
+            {"key": "2025-04", "count": 1, "min": null, "max": null, "avg": null},
+        ])
     );
-    assert!(
-        wells_with_temperatures > 0,
-        "Should have found wells with temperature data at phase change"
+}
+
+#[tokio::test]
+#[allow(clippy::too_many_lines)]
+async fn test_regions_are_managed_one_at_a_time() {
+    let app = setup_test_app().await;
+    let (status, demo) = send_json(&app, "POST", "/api/maintenance/demo", None).await;
+    assert_eq!(status, StatusCode::CREATED, "{demo}");
+    let experiment_id = demo["experiment_id"].as_str().unwrap();
+    let uri = format!("/api/experiments/{experiment_id}/regions");
+
+    let (status, regions) = send_json(&app, "GET", &uri, None).await;
+    assert_eq!(status, StatusCode::OK, "{regions}");
+    let regions = regions.as_array().unwrap().clone();
+    assert_eq!(regions.len(), 4);
+    let untreated = regions
+        .iter()
+        .find(|region| region["name"] == "Untreated")
+        .unwrap();
+    assert_eq!(untreated["treatment"]["name"], "none");
+
+    // Checked with the experiment's other regions
+    let overlapping = json!({
+        "name": "Overlapping",
+        "tray_id": 1,
+        "row_min": 0,
+        "row_max": 1,
+        "col_min": 0,
+        "col_max": 1,
+        "is_background_key": false
+    });
+    let (status, error) = send_json(&app, "POST", &uri, Some(&overlapping)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{error}");
+
+    let untreated_uri = format!("{uri}/{}", untreated["id"].as_str().unwrap());
+    let (status, _) = send_json(&app, "DELETE", &untreated_uri, None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let experiment_uri = format!("/api/experiments/{experiment_id}");
+    let (_, experiment) = send_json(&app, "GET", &experiment_uri, None).await;
+    assert_eq!(experiment["regions"].as_array().unwrap().len(), 3);
+
+    // The freed wells take the snow sample
+    let bulk_id = demo["sample_ids"][1].as_str().unwrap();
+    let (_, bulk) = send_json(&app, "GET", &format!("/api/samples/{bulk_id}"), None).await;
+    let bulk_treatment = bulk["treatments"][0]["id"].clone();
+    let mut snow = overlapping.clone();
+    snow["name"] = json!("Snow");
+    snow["treatment_id"] = bulk_treatment.clone();
+    let (status, created) = send_json(&app, "POST", &uri, Some(&snow)).await;
+    assert_eq!(status, StatusCode::CREATED, "{created}");
+    assert_eq!(created["experiment_id"], experiment_id);
+    assert_eq!(created["treatment"]["sample"]["id"], bulk_id);
+    let snow_uri = format!("{uri}/{}", created["id"].as_str().unwrap());
+
+    let (status, error) = send_json(&app, "PUT", &snow_uri, Some(&json!({"col_max": 20}))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{error}");
+    let (status, updated) = send_json(
+        &app,
+        "PUT",
+        &snow_uri,
+        Some(&json!({"name": "Fresh snow", "row_max": 3})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{updated}");
+    assert_eq!(updated["name"], "Fresh snow");
+    assert_eq!(updated["row_max"], 3);
+    assert_eq!(updated["col_max"], 1);
+    let (status, _) = send_json(
+        &app,
+        "PUT",
+        &format!(
+            "/api/experiments/{}/regions/{}",
+            uuid::Uuid::new_v4(),
+            created["id"].as_str().unwrap()
+        ),
+        Some(&json!({"name": "Elsewhere"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Regions by treatment and by sample
+    let (status, by_treatment) = send_json(
+        &app,
+        "GET",
+        &format!(
+            "/api/treatments/{}/regions",
+            bulk_treatment.as_str().unwrap()
+        ),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{by_treatment}");
+    assert_eq!(by_treatment.as_array().unwrap().len(), 1);
+    let filter_id = demo["sample_ids"][0].as_str().unwrap();
+    let (_, by_sample) = send_json(
+        &app,
+        "GET",
+        &format!("/api/samples/{filter_id}/regions"),
+        None,
+    )
+    .await;
+    let names: Vec<&str> = by_sample
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|region| region["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names.len(), 2, "{names:?}");
+    assert!(names.contains(&"Heat treated") && names.contains(&"H2O2 treated"));
+}
+
+#[tokio::test]
+async fn test_wells_are_listed_with_their_coordinates() {
+    let app = setup_test_app().await;
+    let (status, demo) = send_json(&app, "POST", "/api/maintenance/demo", None).await;
+    assert_eq!(status, StatusCode::CREATED, "{demo}");
+    let experiment_id = demo["experiment_id"].as_str().unwrap();
+    let uri = format!("/api/experiments/{experiment_id}/wells");
+
+    let (status, wells) = send_json(&app, "GET", &uri, None).await;
+    assert_eq!(status, StatusCode::OK, "{wells}");
+    assert_eq!(wells.as_array().unwrap().len(), 192);
+
+    // Without a tray name the coordinate is looked up on every tray
+    let (_, a1) = send_json(&app, "GET", &format!("{uri}?coordinate=a1"), None).await;
+    let a1 = a1.as_array().unwrap();
+    assert_eq!(a1.len(), 2);
+    assert_eq!(a1[0]["tray_name"], "P1");
+    assert_eq!(a1[1]["tray_name"], "P2");
+    assert!(a1.iter().all(|well| well["coordinate"] == "A1"));
+
+    let (_, p2) = send_json(&app, "GET", &format!("{uri}?coordinate=P2:H12"), None).await;
+    let p2 = p2.as_array().unwrap();
+    assert_eq!(p2.len(), 1);
+    assert_eq!(p2[0]["row_letter"], "H");
+    assert_eq!(p2[0]["column_number"], 12);
+    let (_, missing) = send_json(&app, "GET", &format!("{uri}?coordinate=P3:A1"), None).await;
+    assert_eq!(missing, json!([]));
+    let (status, _) = send_json(&app, "GET", &format!("{uri}?coordinate=12"), None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let tray_uri = format!("/api/trays/{}/wells", p2[0]["tray_id"].as_str().unwrap());
+    let (status, tray_wells) = send_json(&app, "GET", &tray_uri, None).await;
+    assert_eq!(status, StatusCode::OK, "{tray_wells}");
+    let tray_wells = tray_wells.as_array().unwrap();
+    assert_eq!(tray_wells.len(), 96);
+    assert_eq!(tray_wells[0]["coordinate"], "A1");
+    assert!(tray_wells.contains(&p2[0]));
+    let (status, _) = send_json(
+        &app,
+        "GET",
+        &format!("/api/trays/{}/wells", uuid::Uuid::new_v4()),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_time_points_are_listed_a_page_at_a_time() {
+    let app = setup_test_app().await;
+    let (status, demo) = send_json(&app, "POST", "/api/maintenance/demo", None).await;
+    assert_eq!(status, StatusCode::CREATED, "{demo}");
+    let experiment_id = demo["experiment_id"].as_str().unwrap();
+    let uri = format!("/api/experiments/{experiment_id}/time_points");
+
+    let (status, page) = send_json(&app, "GET", &uri, None).await;
+    assert_eq!(status, StatusCode::OK, "{page}");
+    assert_eq!(page["total"], 151);
+    let time_points = page["time_points"].as_array().unwrap();
+    assert_eq!(time_points.len(), 100);
+    let probe_readings = time_points[0]["probe_readings"].as_array().unwrap();
+    assert_eq!(probe_readings.len(), 8);
+    assert!(time_points[0].get("well_states").is_none());
+    assert!(time_points[0]["timestamp"].as_str() < time_points[1]["timestamp"].as_str());
+
+    let (_, last) = send_json(&app, "GET", &format!("{uri}?limit=10&offset=145"), None).await;
+    assert_eq!(last["time_points"].as_array().unwrap().len(), 6);
+
+    // Well states follow the transitions up to each time point
+    let frozen = |time_point: &Value| {
+        let wells = time_point["well_states"].as_array().unwrap();
+        assert_eq!(wells.len(), 192);
+        wells.iter().filter(|well| well["state"] == 1).count()
+    };
+    let (_, first) = send_json(
+        &app,
+        "GET",
+        &format!("{uri}?include_well_states=true&limit=1"),
+        None,
+    )
+    .await;
+    let (_, end) = send_json(
+        &app,
+        "GET",
+        &format!("{uri}?include_well_states=true&limit=1&offset=150"),
+        None,
+    )
+    .await;
+    assert_eq!(frozen(&first["time_points"][0]), 0);
+    assert!(frozen(&end["time_points"][0]) > 0);
+
+    let encode = |value: &Value| value.as_str().unwrap().replace('+', "%2B");
+    let from = encode(&time_points[10]["timestamp"]);
+    let to = encode(&time_points[20]["timestamp"]);
+    let (status, range) = send_json(&app, "GET", &format!("{uri}?from={from}&to={to}"), None).await;
+    assert_eq!(status, StatusCode::OK, "{range}");
+    assert_eq!(range["total"], 10);
+    assert_eq!(range["time_points"][0]["id"], time_points[10]["id"]);
+
+    let (status, _) = send_json(&app, "GET", &format!("{uri}?from={to}&to={from}"), None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = send_json(&app, "GET", &format!("{uri}?limit=0"), None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = send_json(
+        &app,
+        "GET",
+        &format!("/api/experiments/{}/time_points", uuid::Uuid::new_v4()),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_probe_timeseries_is_given_per_probe() {
+    let app = setup_test_app().await;
+    let (status, demo) = send_json(&app, "POST", "/api/maintenance/demo", None).await;
+    assert_eq!(status, StatusCode::CREATED, "{demo}");
+    let experiment_id = demo["experiment_id"].as_str().unwrap();
+    let (_, page) = send_json(
+        &app,
+        "GET",
+        &format!("/api/experiments/{experiment_id}/time_points?limit=1"),
+        None,
+    )
+    .await;
+    let first = &page["time_points"][0]["probe_readings"][2];
+    let uri = format!(
+        "/api/experiments/{experiment_id}/probes/{}/timeseries",
+        first["probe_id"].as_str().unwrap()
     );
-    assert!(
-        total_probe_readings_checked > 0,
-        "Should have checked probe readings"
+
+    let (status, series) = send_json(&app, "GET", &uri, None).await;
+    assert_eq!(status, StatusCode::OK, "{series}");
+    assert_eq!(series["probe_name"], first["probe_name"]);
+    assert_eq!(series["calibrated"], true);
+    assert_eq!(series["readings"], 151);
+    assert_eq!(series["points"].as_array().unwrap().len(), 151);
+    assert_eq!(series["points"][0]["temperature"], first["temperature"]);
+
+    let (_, raw) = send_json(&app, "GET", &format!("{uri}?calibrated=false"), None).await;
+    assert_eq!(raw["calibrated"], false);
+    assert_eq!(raw["points"][0]["temperature"], first["raw_temperature"]);
+
+    // Consecutive readings are averaged
+    let (_, downsampled) = send_json(&app, "GET", &format!("{uri}?max_points=10"), None).await;
+    assert_eq!(downsampled["readings"], 151);
+    let points = downsampled["points"].as_array().unwrap();
+    assert_eq!(points.len(), 10);
+    assert_eq!(points[0]["timestamp"], series["points"][0]["timestamp"]);
+    assert_eq!(points[1]["timestamp"], series["points"][16]["timestamp"]);
+
+    let (status, _) = send_json(&app, "GET", &format!("{uri}?max_points=1"), None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = send_json(
+        &app,
+        "GET",
+        &format!(
+            "/api/experiments/{experiment_id}/probes/{}/timeseries",
+            uuid::Uuid::new_v4()
+        ),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_experiment_is_cloned_without_its_data() {
+    let app = setup_test_app().await;
+    let (status, demo) = send_json(&app, "POST", "/api/maintenance/demo", None).await;
+    assert_eq!(status, StatusCode::CREATED, "{demo}");
+    let experiment_id = demo["experiment_id"].as_str().unwrap();
+    let uri = format!("/api/experiments/{experiment_id}");
+    let (_, original) = send_json(&app, "GET", &uri, None).await;
+    let uri = format!("{uri}/clone");
+
+    let (status, copy) = send_json(&app, "POST", &uri, None).await;
+    assert_eq!(status, StatusCode::CREATED, "{copy}");
+    let name = original["name"].as_str().unwrap();
+    assert_eq!(copy["name"], format!("{name} (copy)"));
+    assert_ne!(copy["id"], original["id"]);
+    for field in ["tray_configuration_id", "project_id", "temperature_ramp"] {
+        assert_eq!(copy[field], original[field], "{field}");
+    }
+    assert!(copy["performed_at"].is_null());
+    let treatments = |experiment: &Value| {
+        let mut treatments: Vec<(String, String)> = experiment["regions"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|region| {
+                (
+                    region["name"].as_str().unwrap().to_string(),
+                    region["treatment_id"].to_string(),
+                )
+            })
+            .collect();
+        treatments.sort();
+        treatments
+    };
+    assert_eq!(treatments(&copy), treatments(&original));
+
+    // Nothing the run produced is copied
+    let copy_id = copy["id"].as_str().unwrap();
+    let (_, time_points) = send_json(
+        &app,
+        "GET",
+        &format!("/api/experiments/{copy_id}/time_points"),
+        None,
+    )
+    .await;
+    assert_eq!(time_points["total"], 0);
+
+    let (_, second) = send_json(&app, "POST", &uri, Some(&json!({}))).await;
+    assert_eq!(second["name"], format!("{name} (copy 2)"));
+    let repeat = json!({"name": "Repeat run", "performed_at": "2025-03-01T09:00:00Z"});
+    let (status, named) = send_json(&app, "POST", &uri, Some(&repeat)).await;
+    assert_eq!(status, StatusCode::CREATED, "{named}");
+    assert_eq!(named["name"], "Repeat run");
+    let performed_at = named["performed_at"].as_str().unwrap();
+    assert!(performed_at.starts_with("2025-03-01T09:00:00"));
+    let (status, _) = send_json(&app, "POST", &uri, Some(&repeat)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        &format!("/api/experiments/{}/clone", uuid::Uuid::new_v4()),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+#[allow(clippy::too_many_lines)]
+async fn test_split_run_is_merged() {
+    use crate::experiments::phase_transitions::models as phase_transitions;
+    use crate::experiments::results_summaries::models as results_summaries;
+    use crate::experiments::temperatures::models as temperature_readings;
+    use crate::freezing_results::models as freezing_results;
+    use sea_orm::{
+        ActiveModelTrait, ActiveValue::Set, ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter,
+    };
+
+    let mut config = crate::config::Config::for_tests();
+    config.keycloak_url = String::new();
+    let db = crate::config::test_helpers::setup_test_db().await;
+    let app = crate::routes::build_router(&db, &config);
+    let (status, demo) = send_json(&app, "POST", "/api/maintenance/demo", None).await;
+    assert_eq!(status, StatusCode::CREATED, "{demo}");
+    let experiment_id: uuid::Uuid = demo["experiment_id"].as_str().unwrap().parse().unwrap();
+    let states_uri =
+        format!("/api/experiments/{experiment_id}/time_points?include_well_states=true&limit=1000");
+    let (_, before) = send_json(&app, "GET", &states_uri, None).await;
+    let before = before["time_points"].as_array().unwrap().clone();
+    let (_, source) = send_json(
+        &app,
+        "POST",
+        &format!("/api/experiments/{experiment_id}/clone"),
+        None,
+    )
+    .await;
+    let source_id: uuid::Uuid = source["id"].as_str().unwrap().parse().unwrap();
+
+    // Split the run after 76 readings, the second upload's clock starting again
+    let time = |time_point: &Value| -> DateTime<chrono::Utc> {
+        time_point["timestamp"].as_str().unwrap().parse().unwrap()
+    };
+    let (split, start) = (time(&before[76]), time(&before[0]));
+    let reset = split - start;
+    for reading in temperature_readings::Entity::find()
+        .filter(temperature_readings::Column::ExperimentId.eq(experiment_id))
+        .filter(temperature_readings::Column::Timestamp.gte(split))
+        .all(&db)
+        .await
+        .unwrap()
+    {
+        let timestamp = reading.timestamp - reset;
+        let mut reading: temperature_readings::ActiveModel = reading.into();
+        reading.experiment_id = Set(source_id);
+        reading.timestamp = Set(timestamp);
+        reading.update(&db).await.unwrap();
+    }
+    let mut frozen_before_split = None;
+    for transition in phase_transitions::Entity::find()
+        .filter(phase_transitions::Column::ExperimentId.eq(experiment_id))
+        .all(&db)
+        .await
+        .unwrap()
+    {
+        if transition.timestamp < split {
+            frozen_before_split.get_or_insert(transition);
+            continue;
+        }
+        let timestamp = transition.timestamp - reset;
+        let mut transition: phase_transitions::ActiveModel = transition.into();
+        transition.experiment_id = Set(source_id);
+        transition.timestamp = Set(timestamp);
+        transition.update(&db).await.unwrap();
+    }
+    // The restarted logger reports a well frozen before the split as freezing again
+    let frozen_before_split = frozen_before_split.unwrap();
+    let source_start = temperature_readings::Entity::find()
+        .filter(temperature_readings::Column::ExperimentId.eq(source_id))
+        .filter(temperature_readings::Column::Timestamp.eq(start))
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    phase_transitions::ActiveModel {
+        id: Set(uuid::Uuid::new_v4()),
+        well_id: Set(frozen_before_split.well_id),
+        experiment_id: Set(source_id),
+        temperature_reading_id: Set(source_start.id),
+        timestamp: Set(start),
+        previous_state: Set(0),
+        new_state: Set(1),
+        created_at: Set(chrono::Utc::now()),
+    }
+    .insert(&db)
+    .await
+    .unwrap();
+    for id in [experiment_id, source_id] {
+        let uri = format!("/api/experiments/{id}/results/recompute");
+        let (status, _) = send_json(&app, "POST", &uri, None).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+    let db = &db;
+    let results_of = |id: uuid::Uuid| async move {
+        let summaries = results_summaries::Entity::find()
+            .filter(results_summaries::Column::ExperimentId.eq(id))
+            .count(db)
+            .await
+            .unwrap();
+        let freezing = freezing_results::Entity::find()
+            .filter(freezing_results::Column::ExperimentId.eq(id))
+            .count(db)
+            .await
+            .unwrap();
+        (summaries, freezing)
+    };
+    let (_, wells) = results_of(experiment_id).await;
+    assert!(wells > 0);
+    assert_eq!(results_of(source_id).await, (1, wells));
+
+    let uri = format!("/api/experiments/{experiment_id}/merge");
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        &uri,
+        Some(&json!({"source_experiment_id": experiment_id})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, merged) = send_json(
+        &app,
+        "POST",
+        &uri,
+        Some(&json!({"source_experiment_id": source_id})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{merged}");
+    assert_eq!(merged["readings_moved"], 75);
+    assert_eq!(merged["phase_transitions_dropped"], 1);
+    assert_eq!(merged["timestamp_shift_seconds"], reset.num_seconds());
+    assert_eq!(merged["experiment"]["id"], experiment_id.to_string());
+
+    // The results are kept for the merged run alone
+    assert_eq!(results_of(experiment_id).await, (1, wells));
+    assert_eq!(results_of(source_id).await, (0, 0));
+
+    // The run reads as it was logged, and the shell is gone
+    let (_, after) = send_json(&app, "GET", &states_uri, None).await;
+    let after = after["time_points"].as_array().unwrap();
+    assert_eq!(after.len(), before.len());
+    for (before, after) in before.iter().zip(after) {
+        assert_eq!(before["timestamp"], after["timestamp"]);
+        assert_eq!(before["well_states"], after["well_states"]);
+    }
+    let (status, _) = send_json(&app, "GET", &format!("/api/experiments/{source_id}"), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        &uri,
+        Some(&json!({"source_experiment_id": source_id})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+/// Readings are found by their experiment on Postgres, where it picks their
+/// partition, so merging copies them and keeps what points at them
+#[tokio::test]
+#[allow(clippy::too_many_lines)]
+async fn test_split_run_is_merged_on_postgres() {
+    use crate::assets::models as assets;
+    use crate::experiments::phase_transitions::models as phase_transitions;
+    use crate::experiments::probe_temperature_readings::models as probe_readings;
+    use crate::experiments::temperatures::models as temperature_readings;
+    use sea_orm::{
+        ActiveModelTrait, ActiveValue::Set, ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter,
+        QueryOrder,
+    };
+
+    let Some(db) = crate::config::test_helpers::setup_postgres_test_db().await else {
+        return;
+    };
+    let mut config = crate::config::Config::for_tests();
+    config.keycloak_url = String::new();
+    let app = crate::routes::build_router(&db, &config);
+    let (status, demo) = send_json(&app, "POST", "/api/maintenance/demo", None).await;
+    assert_eq!(status, StatusCode::CREATED, "{demo}");
+    let source_id: uuid::Uuid = demo["experiment_id"].as_str().unwrap().parse().unwrap();
+    let (status, target) = send_json(
+        &app,
+        "POST",
+        "/api/experiments",
+        Some(&json!({
+            "name": "First upload",
+            "is_calibration": false,
+            "tray_configuration_id": demo["tray_configuration_id"],
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{target}");
+    let target_id: uuid::Uuid = target["id"].as_str().unwrap().parse().unwrap();
+
+    let readings_of = |experiment_id: uuid::Uuid| {
+        temperature_readings::Entity::find()
+            .filter(temperature_readings::Column::ExperimentId.eq(experiment_id))
+            .order_by_asc(temperature_readings::Column::Timestamp)
+            .all(&db)
+    };
+    let probe_readings_of = |readings: &[temperature_readings::Model]| {
+        probe_readings::Entity::find()
+            .filter(
+                probe_readings::Column::TemperatureReadingId
+                    .is_in(readings.iter().map(|reading| reading.id)),
+            )
+            .count(&db)
+    };
+    let source_readings = readings_of(source_id).await.unwrap();
+    let probe_reading_count = probe_readings_of(&source_readings).await.unwrap();
+    assert!(probe_reading_count > 0);
+    let transition_count = phase_transitions::Entity::find()
+        .filter(phase_transitions::Column::ExperimentId.eq(source_id))
+        .count(&db)
+        .await
+        .unwrap();
+    assert!(transition_count > 0);
+
+    // A camera frame taken at one of the readings
+    let framed = &source_readings[10];
+    let now = chrono::Utc::now();
+    let frame = assets::ActiveModel {
+        id: Set(uuid::Uuid::new_v4()),
+        experiment_id: Set(Some(source_id)),
+        original_filename: Set("frame_0010.jpg".to_string()),
+        s3_key: Set(format!("test/{source_id}/frame_0010.jpg")),
+        uploaded_at: Set(now),
+        is_deleted: Set(false),
+        created_at: Set(now),
+        last_updated: Set(now),
+        r#type: Set("image".to_string()),
+        captured_at: Set(Some(framed.timestamp)),
+        temperature_reading_id: Set(Some(framed.id)),
+        ..Default::default()
+    }
+    .insert(&db)
+    .await
+    .unwrap();
+
+    let (status, merged) = send_json(
+        &app,
+        "POST",
+        &format!("/api/experiments/{target_id}/merge"),
+        Some(&json!({"source_experiment_id": source_id})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{merged}");
+    assert_eq!(merged["readings_moved"], source_readings.len());
+    assert_eq!(merged["phase_transitions_moved"], transition_count);
+
+    // The readings keep their probe readings, transitions and frames
+    let merged_readings = readings_of(target_id).await.unwrap();
+    assert_eq!(merged_readings.len(), source_readings.len());
+    assert!(readings_of(source_id).await.unwrap().is_empty());
+    assert_eq!(
+        probe_readings_of(&merged_readings).await.unwrap(),
+        probe_reading_count
     );
+    for transition in phase_transitions::Entity::find()
+        .filter(phase_transitions::Column::ExperimentId.eq(target_id))
+        .all(&db)
+        .await
+        .unwrap()
+    {
+        assert!(
+            merged_readings
+                .iter()
+                .any(|reading| reading.id == transition.temperature_reading_id)
+        );
+    }
+    let frame = assets::Entity::find_by_id(frame.id)
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(frame.experiment_id, Some(target_id));
+    let reading = merged_readings
+        .iter()
+        .find(|reading| Some(reading.id) == frame.temperature_reading_id)
+        .expect("The frame lost its reading");
+    assert_eq!(reading.timestamp, framed.timestamp);
+}
 
-    println!("✅ Temperature validation completed:");
-    println!("   - Wells with phase changes: {wells_with_phase_changes}");
-    println!("   - Wells with temperature data: {wells_with_temperatures}");
-    println!("   - Probe readings validated: {total_probe_readings_checked}");
+/// On Postgres, readings and transitions are kept in partitions by
+/// experiment, which go with it, and what points at readings by their ID
+/// alone must point at one. Reverting the partitioning keeps them.
+#[tokio::test]
+#[allow(clippy::too_many_lines)]
+async fn test_time_series_are_partitioned_on_postgres() {
+    use crate::assets::models as assets;
+    use crate::experiments::models as experiments;
+    use crate::experiments::phase_transitions::models as phase_transitions;
+    use crate::experiments::probe_temperature_readings::models as probe_readings;
+    use crate::experiments::temperatures::models as temperature_readings;
+    use crate::tray_configurations::regions::models as regions;
+    use migration::{Migrator, MigratorTrait};
+    use sea_orm::{
+        ActiveModelTrait, ActiveValue::Set, ColumnTrait, ConnectionTrait, DatabaseConnection,
+        DbBackend, EntityTrait, PaginatorTrait, QueryFilter, Statement,
+    };
+
+    /// `p` for a partitioned table, `r` for a plain one
+    async fn kind_of(db: &DatabaseConnection, table: &str) -> String {
+        db.query_one(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            "SELECT relkind::text AS kind FROM pg_class WHERE relname = $1",
+            [table.into()],
+        ))
+        .await
+        .unwrap()
+        .unwrap()
+        .try_get("", "kind")
+        .unwrap()
+    }
 
-    // 8. Additional validation: Check that we have reasonable number of probe readings
-    // Since we're looking at phase change times, and each well should have readings from multiple probes
-    let expected_min_probe_readings = wells_with_temperatures * 3; // At least 3 probes per well with data
-    assert!(
-        total_probe_readings_checked >= expected_min_probe_readings,
-        "Expected at least {expected_min_probe_readings} probe readings ({wells_with_temperatures}+ wells × 3+ probes), got {total_probe_readings_checked}"
+    /// Readings, probe readings and transitions of the experiment
+    async fn counts_of(
+        db: &DatabaseConnection,
+        experiment_id: uuid::Uuid,
+        reading_ids: &[uuid::Uuid],
+    ) -> (u64, u64, u64) {
+        let readings = temperature_readings::Entity::find()
+            .filter(temperature_readings::Column::ExperimentId.eq(experiment_id))
+            .count(db)
+            .await
+            .unwrap();
+        let probe_readings = probe_readings::Entity::find()
+            .filter(probe_readings::Column::TemperatureReadingId.is_in(reading_ids.to_vec()))
+            .count(db)
+            .await
+            .unwrap();
+        let transitions = phase_transitions::Entity::find()
+            .filter(phase_transitions::Column::ExperimentId.eq(experiment_id))
+            .count(db)
+            .await
+            .unwrap();
+        (readings, probe_readings, transitions)
+    }
+
+    let Some(db) = crate::config::test_helpers::setup_postgres_test_db().await else {
+        return;
+    };
+    let mut config = crate::config::Config::for_tests();
+    config.keycloak_url = String::new();
+    let app = crate::routes::build_router(&db, &config);
+    let (status, demo) = send_json(&app, "POST", "/api/maintenance/demo", None).await;
+    assert_eq!(status, StatusCode::CREATED, "{demo}");
+    let experiment_id: uuid::Uuid = demo["experiment_id"].as_str().unwrap().parse().unwrap();
+    let reading_ids: Vec<uuid::Uuid> = temperature_readings::Entity::find()
+        .filter(temperature_readings::Column::ExperimentId.eq(experiment_id))
+        .all(&db)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|reading| reading.id)
+        .collect();
+    let counts = counts_of(&db, experiment_id, &reading_ids).await;
+    assert!(counts.0 > 0 && counts.1 > 0 && counts.2 > 0, "{counts:?}");
+
+    // The experiment's readings and transitions are all in one partition
+    for table in ["temperature_readings", "well_phase_transitions"] {
+        assert_eq!(kind_of(&db, table).await, "p");
+        let partitions = db
+            .query_all(Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                format!(
+                    "SELECT DISTINCT tableoid::regclass::text AS partition FROM {table} \
+                     WHERE experiment_id = $1"
+                ),
+                [experiment_id.into()],
+            ))
+            .await
+            .unwrap();
+        assert_eq!(partitions.len(), 1, "{table}");
+        let partition: String = partitions[0].try_get("", "partition").unwrap();
+        assert!(partition.starts_with(&format!("{table}_p")), "{partition}");
+    }
+
+    // Probe readings and assets cannot point at a reading that is not there
+    let probe_reading = probe_readings::Entity::find()
+        .filter(probe_readings::Column::TemperatureReadingId.eq(reading_ids[0]))
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    let dangling = probe_readings::ActiveModel {
+        id: Set(uuid::Uuid::new_v4()),
+        probe_id: Set(probe_reading.probe_id),
+        temperature_reading_id: Set(uuid::Uuid::new_v4()),
+        temperature: Set(probe_reading.temperature),
+        created_at: Set(probe_reading.created_at),
+    };
+    assert!(dangling.insert(&db).await.is_err());
+    let now = chrono::Utc::now();
+    let frame = assets::ActiveModel {
+        id: Set(uuid::Uuid::new_v4()),
+        original_filename: Set("frame_0000.jpg".to_string()),
+        s3_key: Set(format!("test/{experiment_id}/frame_0000.jpg")),
+        uploaded_at: Set(now),
+        is_deleted: Set(false),
+        created_at: Set(now),
+        last_updated: Set(now),
+        r#type: Set("image".to_string()),
+        temperature_reading_id: Set(Some(reading_ids[0])),
+        ..Default::default()
+    }
+    .insert(&db)
+    .await
+    .unwrap();
+    let mut moved = assets::ActiveModel::from(frame.clone());
+    moved.temperature_reading_id = Set(Some(uuid::Uuid::new_v4()));
+    assert!(moved.update(&db).await.is_err());
+
+    // Reverting the partitioning keeps the rows, and applying it again too
+    let steps = Migrator::migrations()
+        .iter()
+        .rev()
+        .position(|migration| migration.name() == "m20251203_000001_partition_time_series")
+        .unwrap()
+        + 1;
+    Migrator::down(&db, Some(u32::try_from(steps).unwrap()))
+        .await
+        .unwrap();
+    assert_eq!(kind_of(&db, "temperature_readings").await, "r");
+    assert_eq!(kind_of(&db, "well_phase_transitions").await, "r");
+    assert_eq!(counts_of(&db, experiment_id, &reading_ids).await, counts);
+    Migrator::up(&db, None).await.unwrap();
+    assert_eq!(kind_of(&db, "temperature_readings").await, "p");
+    assert_eq!(kind_of(&db, "well_phase_transitions").await, "p");
+    assert_eq!(counts_of(&db, experiment_id, &reading_ids).await, counts);
+
+    // Deleting the experiment deletes its time series, and unlinks frames.
+    // Its regions are not deleted with it, so they go first.
+    regions::Entity::delete_many()
+        .filter(regions::Column::ExperimentId.eq(experiment_id))
+        .exec(&db)
+        .await
+        .unwrap();
+    experiments::Entity::delete_by_id(experiment_id)
+        .exec(&db)
+        .await
+        .unwrap();
+    assert_eq!(counts_of(&db, experiment_id, &reading_ids).await, (0, 0, 0));
+    let frame = assets::Entity::find_by_id(frame.id)
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(frame.temperature_reading_id, None);
+}
+
+/// With `TimescaleDB`, downsampled probe series are summed from the
+/// per-minute aggregates, keeping to the time range to the reading, and
+/// counting readings logged late once the aggregates are refreshed
+#[tokio::test]
+#[allow(clippy::too_many_lines)]
+async fn test_probe_series_is_downsampled_on_timescaledb() {
+    use crate::experiments::probe_temperature_readings::models as probe_readings;
+    use crate::experiments::temperatures::models as temperature_readings;
+    use crate::tray_configurations::probes::models as probes;
+    use chrono::{Duration, SecondsFormat, Utc};
+    use rust_decimal::Decimal;
+    use sea_orm::{
+        ActiveModelTrait, ActiveValue::Set, ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter,
+        QueryOrder, QuerySelect,
+    };
+
+    let Some(db) = crate::config::test_helpers::setup_timescale_test_db().await else {
+        return;
+    };
+    let mut config = crate::config::Config::for_tests();
+    config.keycloak_url = String::new();
+    config.timescaledb = true;
+    crate::experiments::temperatures::timescale::check_series(&db, &config)
+        .await
+        .unwrap();
+    let app = crate::routes::build_router(&db, &config);
+    let (status, demo) = send_json(&app, "POST", "/api/maintenance/demo", None).await;
+    assert_eq!(status, StatusCode::CREATED, "{demo}");
+    let experiment_id: uuid::Uuid = demo["experiment_id"].as_str().unwrap().parse().unwrap();
+    let (_, page) = send_json(
+        &app,
+        "GET",
+        &format!("/api/experiments/{experiment_id}/time_points?limit=1"),
+        None,
+    )
+    .await;
+    let first = &page["time_points"][0];
+    let started: DateTime<Utc> = first["timestamp"].as_str().unwrap().parse().unwrap();
+    let probe_id: uuid::Uuid = first["probe_readings"][2]["probe_id"]
+        .as_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    let probe = probes::Entity::find_by_id(probe_id)
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+
+    // A range cutting through a minute at either end
+    let from = started + Duration::seconds(95);
+    let to = started + Duration::seconds(20 * 60 + 25);
+    let uri = format!(
+        "/api/experiments/{experiment_id}/probes/{probe_id}/timeseries?from={}&to={}&max_points=4",
+        from.to_rfc3339_opts(SecondsFormat::Secs, true),
+        to.to_rfc3339_opts(SecondsFormat::Secs, true),
     );
+    let calibrated_readings = || async {
+        probe_readings::Entity::find()
+            .inner_join(temperature_readings::Entity)
+            .filter(temperature_readings::Column::ExperimentId.eq(experiment_id))
+            .filter(probe_readings::Column::ProbeId.eq(probe_id))
+            .filter(temperature_readings::Column::Timestamp.gte(from))
+            .filter(temperature_readings::Column::Timestamp.lt(to))
+            .select_only()
+            .column(temperature_readings::Column::Timestamp)
+            .column(probe_readings::Column::Temperature)
+            .order_by_asc(temperature_readings::Column::Timestamp)
+            .into_tuple::<(DateTime<Utc>, Decimal)>()
+            .all(&db)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(timestamp, raw)| (timestamp, probe.calibrate(raw)))
+            .collect::<Vec<_>>()
+    };
+    // Each point is the mean of the calibrated readings from it to the next
+    let check = |series: &Value, readings: &[(DateTime<Utc>, Decimal)]| {
+        assert_eq!(series["readings"], readings.len(), "{series}");
+        let points = series["points"].as_array().unwrap();
+        assert!((2..=4).contains(&points.len()), "{series}");
+        let starts: Vec<DateTime<Utc>> = points
+            .iter()
+            .map(|point| point["timestamp"].as_str().unwrap().parse().unwrap())
+            .collect();
+        assert_eq!(starts[0], readings[0].0);
+        for (index, point) in points.iter().enumerate() {
+            let of_point: Vec<Decimal> = readings
+                .iter()
+                .filter(|(timestamp, _)| {
+                    *timestamp >= starts[index]
+                        && starts.get(index + 1).is_none_or(|next| timestamp < next)
+                })
+                .map(|(_, temperature)| *temperature)
+                .collect();
+            let mean =
+                (of_point.iter().sum::<Decimal>() / Decimal::from(of_point.len())).round_dp(3);
+            assert_eq!(point["temperature"], json!(mean), "{series}");
+        }
+    };
+
+    let (status, series) = send_json(&app, "GET", &uri, None).await;
+    assert_eq!(status, StatusCode::OK, "{series}");
+    check(&series, &calibrated_readings().await);
+
+    // A reading logged late, within minutes already summed
+    let late = temperature_readings::ActiveModel {
+        id: Set(uuid::Uuid::new_v4()),
+        experiment_id: Set(experiment_id),
+        timestamp: Set(started + Duration::seconds(5 * 60 + 5)),
+        created_at: Set(Utc::now()),
+        ..Default::default()
+    }
+    .insert(&db)
+    .await
+    .unwrap();
+    probe_readings::ActiveModel {
+        id: Set(uuid::Uuid::new_v4()),
+        probe_id: Set(probe_id),
+        temperature_reading_id: Set(late.id),
+        temperature: Set(Decimal::from(-40)),
+        created_at: Set(Utc::now()),
+    }
+    .insert(&db)
+    .await
+    .unwrap();
+    let readings = calibrated_readings().await;
+    assert_eq!(series["readings"], readings.len() - 1);
+    // As the refresh policy does
+    db.execute_unprepared(
+        "CALL refresh_continuous_aggregate('probe_temperature_minutes', NULL, NULL)",
+    )
+    .await
+    .unwrap();
+    let (status, series) = send_json(&app, "GET", &uri, None).await;
+    assert_eq!(status, StatusCode::OK, "{series}");
+    check(&series, &readings);
 }
@@ -0,0 +1,197 @@
+//! Time points of an experiment a page at a time, for replay and debugging.
+//!
+//! A time point is one temperature reading with its probes' temperatures,
+//! and optionally the phase of every well at that time, taken from the
+//! well's last transition up to then.
+
+use super::models::{self as experiments, TemperatureDataWithProbes};
+use super::phase_transitions::models as phase_transitions;
+use super::probe_temperature_readings::models as probe_temperature_readings;
+use super::services::temperature_data_with_probes;
+use super::temperatures::models as temperature_readings;
+use super::temperatures::services::experiment_probes;
+use crate::tray_configurations::wells::services::experiment_wells;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder,
+    QuerySelect, Select,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+const DEFAULT_LIMIT: u64 = 100;
+const MAX_LIMIT: u64 = 1000;
+
+#[derive(Clone, Debug, Default, Deserialize, IntoParams)]
+pub struct TimePointQuery {
+    /// Time points at or after this time
+    pub from: Option<DateTime<Utc>>,
+    /// Time points before this time
+    pub to: Option<DateTime<Utc>>,
+    /// Give the phase of every well at each time point
+    #[serde(default)]
+    pub include_well_states: bool,
+    /// Most time points to return, up to 1000 (default 100)
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, ToSchema)]
+pub struct WellState {
+    pub well_id: Uuid,
+    pub tray_name: Option<String>,
+    /// Well coordinate such as A1
+    pub coordinate: String,
+    /// Phase as logged: 0 liquid, 1 frozen
+    pub state: i32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, ToSchema)]
+pub struct TimePoint {
+    #[serde(flatten)]
+    pub reading: TemperatureDataWithProbes,
+    /// Wells in tray order, given with `include_well_states`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub well_states: Option<Vec<WellState>>,
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct TimePointPage {
+    /// Time points in the time range before `limit` and `offset`
+    pub total: u64,
+    /// Oldest first
+    pub time_points: Vec<TimePoint>,
+}
+
+fn readings_in_range(
+    experiment_id: Uuid,
+    query: &TimePointQuery,
+) -> Select<temperature_readings::Entity> {
+    let mut select = temperature_readings::Entity::find()
+        .filter(temperature_readings::Column::ExperimentId.eq(experiment_id));
+    if let Some(from) = query.from {
+        select = select.filter(temperature_readings::Column::Timestamp.gte(from));
+    }
+    if let Some(to) = query.to {
+        select = select.filter(temperature_readings::Column::Timestamp.lt(to));
+    }
+    select
+}
+
+/// Phase of every well of the experiment at each of the readings, which are
+/// oldest first
+async fn well_states(
+    db: &DatabaseConnection,
+    experiment_id: Uuid,
+    readings: &[temperature_readings::Model],
+) -> Result<Vec<Vec<WellState>>, DbErr> {
+    let Some(last) = readings.last() else {
+        return Ok(Vec::new());
+    };
+    let wells = experiment_wells(db, experiment_id, None).await?;
+    let transitions = phase_transitions::Entity::find()
+        .filter(phase_transitions::Column::ExperimentId.eq(experiment_id))
+        .filter(phase_transitions::Column::Timestamp.lte(last.timestamp))
+        .order_by_asc(phase_transitions::Column::Timestamp)
+        .all(db)
+        .await?;
+
+    let mut states: HashMap<Uuid, i32> = HashMap::new();
+    let mut pending = transitions.iter().peekable();
+    let mut by_reading = Vec::with_capacity(readings.len());
+    for reading in readings {
+        while let Some(transition) =
+            pending.next_if(|transition| transition.timestamp <= reading.timestamp)
+        {
+            states.insert(transition.well_id, transition.new_state);
+        }
+        by_reading.push(
+            wells
+                .iter()
+                .map(|well| WellState {
+                    well_id: well.id,
+                    tray_name: well.tray_name.clone(),
+                    coordinate: well.coordinate.clone(),
+                    state: states.get(&well.id).copied().unwrap_or(0),
+                })
+                .collect(),
+        );
+    }
+    Ok(by_reading)
+}
+
+/// A page of the experiment's time points, oldest first. Invalid queries
+/// are returned as `DbErr::Custom`.
+pub async fn list_time_points(
+    db: &DatabaseConnection,
+    experiment_id: Uuid,
+    query: &TimePointQuery,
+) -> Result<TimePointPage, DbErr> {
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return Err(DbErr::Custom(format!(
+            "limit must be between 1 and {MAX_LIMIT}"
+        )));
+    }
+    if let (Some(from), Some(to)) = (query.from, query.to)
+        && from > to
+    {
+        return Err(DbErr::Custom("from must not be after to".to_string()));
+    }
+    experiments::Entity::find_by_id(experiment_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Experiment not found".to_string()))?;
+
+    let total = readings_in_range(experiment_id, query).count(db).await?;
+    let readings = readings_in_range(experiment_id, query)
+        .order_by_asc(temperature_readings::Column::Timestamp)
+        .order_by_asc(temperature_readings::Column::Id)
+        .offset(query.offset.unwrap_or(0))
+        .limit(limit)
+        .all(db)
+        .await?;
+
+    let probes = experiment_probes(db, experiment_id).await?;
+    let mut by_reading: HashMap<Uuid, HashMap<Uuid, Decimal>> = HashMap::new();
+    for probe_reading in probe_temperature_readings::Entity::find()
+        .filter(
+            probe_temperature_readings::Column::TemperatureReadingId
+                .is_in(readings.iter().map(|reading| reading.id)),
+        )
+        .all(db)
+        .await?
+    {
+        by_reading
+            .entry(probe_reading.temperature_reading_id)
+            .or_default()
+            .insert(probe_reading.probe_id, probe_reading.temperature);
+    }
+
+    let states = if query.include_well_states {
+        well_states(db, experiment_id, &readings)
+            .await?
+            .into_iter()
+            .map(Some)
+            .collect()
+    } else {
+        vec![None; readings.len()]
+    };
+    let none = HashMap::new();
+    let time_points = readings
+        .iter()
+        .zip(states)
+        .map(|(reading, well_states)| TimePoint {
+            reading: temperature_data_with_probes(
+                reading,
+                by_reading.get(&reading.id).unwrap_or(&none),
+                &probes,
+            ),
+            well_states,
+        })
+        .collect();
+    Ok(TimePointPage { total, time_points })
+}
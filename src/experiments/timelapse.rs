@@ -0,0 +1,508 @@
+//! Time-lapse video of an experiment's camera images
+//!
+//! The `INP_*` images are ordered by capture time, downscaled, stamped with
+//! their capture time and piped to `ffmpeg`. The video is stored as a derived
+//! asset of the experiment whose `processing_status` tracks the rendering job.
+
+use crate::assets::models as s3_assets;
+use crate::common::state::AppState;
+use crate::config::Config;
+use crate::external::s3::{delete_object_from_s3, get_object_from_s3, put_object_to_s3};
+use chrono::NaiveDateTime;
+use image::{Rgb, RgbImage, codecs::jpeg::JpegEncoder, imageops::FilterType};
+use sea_orm::{ActiveValue::Set, DatabaseConnection, EntityTrait, QueryFilter, entity::prelude::*};
+use serde::Deserialize;
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+pub const TIMELAPSE_ROLE: &str = "timelapse";
+const DEFAULT_FPS: u32 = 10;
+const DEFAULT_WIDTH: u32 = 1280;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TimelapseFormat {
+    #[default]
+    Mp4,
+    Webm,
+}
+
+impl TimelapseFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Mp4 => "mp4",
+            Self::Webm => "webm",
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Mp4 => "video/mp4",
+            Self::Webm => "video/webm",
+        }
+    }
+
+    fn codec_args(self) -> &'static [&'static str] {
+        match self {
+            Self::Mp4 => &[
+                "-c:v",
+                "libx264",
+                "-pix_fmt",
+                "yuv420p",
+                "-movflags",
+                "+faststart",
+            ],
+            Self::Webm => &[
+                "-c:v",
+                "libvpx-vp9",
+                "-pix_fmt",
+                "yuv420p",
+                "-b:v",
+                "0",
+                "-crf",
+                "32",
+            ],
+        }
+    }
+
+    pub fn filename(self) -> String {
+        format!("timelapse.{}", self.extension())
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, ToSchema)]
+pub struct TimelapseRequest {
+    #[serde(default)]
+    pub format: TimelapseFormat,
+    /// Frames per second, 1 to 60 (default 10)
+    pub fps: Option<u32>,
+    /// Frame width in pixels, 64 to 3840 (default 1280); height keeps the aspect ratio
+    pub width: Option<u32>,
+}
+
+/// Capture time encoded in camera filenames: `INP_<n>_YYYY-MM-DD_HH-MM-SS.jpg`
+pub fn parse_capture_time(filename: &str) -> Option<NaiveDateTime> {
+    let stem = filename.rsplit_once('.').map_or(filename, |(stem, _)| stem);
+    let mut parts = stem.rsplitn(3, '_');
+    let time = parts.next()?;
+    let date = parts.next()?;
+    NaiveDateTime::parse_from_str(&format!("{date} {time}"), "%Y-%m-%d %H-%M-%S").ok()
+}
+
+/// 5x7 glyphs for digits, upper-case letters and the punctuation of
+/// timestamps; each row uses the low 5 bits
+fn glyph(c: char) -> [u8; 7] {
+    match c {
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        '-' => [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00],
+        ':' => [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C],
+        'A' => [0x0E, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'B' => [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E],
+        'C' => [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E],
+        'D' => [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C],
+        'E' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F],
+        'F' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10],
+        'G' => [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F],
+        'H' => [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'I' => [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F],
+        'M' => [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'P' => [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10],
+        'Q' => [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D],
+        'R' => [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11],
+        'S' => [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E],
+        'T' => [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A],
+        'X' => [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04],
+        'Z' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F],
+        _ => [0x00; 7],
+    }
+}
+
+/// Width in pixels of `text` drawn by [`draw_text`]
+pub fn text_width(text: &str, scale: u32) -> u32 {
+    u32::try_from(text.chars().count())
+        .unwrap_or(u32::MAX)
+        .saturating_mul(6 * scale)
+}
+
+/// Draw `text` with its top-left corner at (`left`, `top`), each glyph pixel
+/// as a `scale`-sized square. Lower-case letters are drawn as upper-case and
+/// other unknown characters as spaces; anything outside the frame is clipped.
+pub fn draw_text(
+    frame: &mut RgbImage,
+    left: u32,
+    top: u32,
+    scale: u32,
+    text: &str,
+    colour: Rgb<u8>,
+) {
+    for (index, c) in (0u32..).zip(text.chars()) {
+        let glyph_left = left + index * 6 * scale;
+        for (row, bits) in (0u32..).zip(glyph(c.to_ascii_uppercase())) {
+            for column in 0..5 {
+                if bits & (0x10 >> column) == 0 {
+                    continue;
+                }
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        let (x, y) = (glyph_left + column * scale + dx, top + row * scale + dy);
+                        if x < frame.width() && y < frame.height() {
+                            frame.put_pixel(x, y, colour);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Draw white text on a black box in the bottom-left corner of the frame
+fn draw_label(frame: &mut RgbImage, text: &str) {
+    let scale = (frame.width() / 320).max(1);
+    let padding = 2 * scale;
+    let box_width = (text_width(text, scale) + 2 * padding).min(frame.width());
+    let box_height = (7 * scale + 2 * padding).min(frame.height());
+    let top = frame.height() - box_height;
+
+    for y in top..frame.height() {
+        for x in 0..box_width {
+            frame.put_pixel(x, y, Rgb([0, 0, 0]));
+        }
+    }
+    draw_text(
+        frame,
+        padding,
+        top + padding,
+        scale,
+        text,
+        Rgb([255, 255, 255]),
+    );
+}
+
+/// Decode an image, scale it to `width` (both dimensions even, as required by
+/// yuv420p), stamp `label` on it and encode it as JPEG
+pub fn render_frame(image_bytes: &[u8], width: u32, label: &str) -> Result<Vec<u8>, String> {
+    let image =
+        image::load_from_memory(image_bytes).map_err(|e| format!("Failed to decode image: {e}"))?;
+    let width = width & !1;
+    let height = u32::try_from(
+        u64::from(image.height()) * u64::from(width) / u64::from(image.width().max(1)),
+    )
+    .unwrap_or(u32::MAX)
+    .max(2)
+        & !1;
+
+    let mut frame = image
+        .resize_exact(width, height, FilterType::Triangle)
+        .into_rgb8();
+    draw_label(&mut frame, label);
+
+    let mut encoded = Vec::new();
+    frame
+        .write_with_encoder(JpegEncoder::new_with_quality(&mut encoded, 90))
+        .map_err(|e| format!("Failed to encode frame: {e}"))?;
+    Ok(encoded)
+}
+
+/// `INP_*` camera images of an experiment with their capture time, in capture
+/// order. The capture time recorded at upload comes first, then the time in
+/// the name, then the upload time.
+pub async fn camera_images(
+    db: &DatabaseConnection,
+    experiment_id: Uuid,
+) -> Result<Vec<(NaiveDateTime, s3_assets::Model)>, DbErr> {
+    let images = s3_assets::Entity::find()
+        .filter(s3_assets::Column::ExperimentId.eq(experiment_id))
+        .filter(s3_assets::Column::Type.eq("image"))
+        .all(db)
+        .await?;
+
+    let mut frames: Vec<(NaiveDateTime, s3_assets::Model)> = images
+        .into_iter()
+        .filter(|asset| {
+            asset
+                .original_filename
+                .to_ascii_uppercase()
+                .starts_with("INP_")
+        })
+        .map(|asset| {
+            let captured = asset
+                .captured_at
+                .map(|captured_at| captured_at.naive_utc())
+                .or_else(|| parse_capture_time(&asset.original_filename))
+                .unwrap_or_else(|| asset.uploaded_at.naive_utc());
+            (captured, asset)
+        })
+        .collect();
+    frames.sort_by(|a, b| (a.0, &a.1.original_filename).cmp(&(b.0, &b.1.original_filename)));
+    Ok(frames)
+}
+
+/// Camera images of an experiment in capture order, with the label for each frame
+async fn load_frames(
+    db: &DatabaseConnection,
+    experiment_id: Uuid,
+) -> Result<Vec<(s3_assets::Model, String)>, DbErr> {
+    Ok(camera_images(db, experiment_id)
+        .await?
+        .into_iter()
+        .map(|(captured, asset)| (asset, captured.format("%Y-%m-%d %H:%M:%S").to_string()))
+        .collect())
+}
+
+/// The experiment's time-lapse asset in the given format, if one was requested
+pub async fn find_timelapse_asset(
+    db: &DatabaseConnection,
+    experiment_id: Uuid,
+    format: TimelapseFormat,
+) -> Result<Option<s3_assets::Model>, DbErr> {
+    s3_assets::Entity::find()
+        .filter(s3_assets::Column::ExperimentId.eq(experiment_id))
+        .filter(s3_assets::Column::Role.eq(TIMELAPSE_ROLE))
+        .filter(s3_assets::Column::OriginalFilename.eq(format.filename()))
+        .one(db)
+        .await
+}
+
+/// Pipe the frames through `ffmpeg` and return the encoded video
+async fn encode_video(
+    config: &Config,
+    frames: &[(s3_assets::Model, String)],
+    format: TimelapseFormat,
+    fps: u32,
+    width: u32,
+) -> Result<(Vec<u8>, usize), String> {
+    let output_dir = tempfile::tempdir().map_err(|e| format!("Failed to create temp dir: {e}"))?;
+    let output_path = output_dir.path().join(format.filename());
+
+    let mut ffmpeg = tokio::process::Command::new(&config.ffmpeg_path)
+        .args(["-hide_banner", "-loglevel", "error", "-y"])
+        .args(["-f", "image2pipe", "-c:v", "mjpeg"])
+        .args(["-framerate", &fps.to_string(), "-i", "-"])
+        .args(format.codec_args())
+        .arg(&output_path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("Failed to start {}: {e}", config.ffmpeg_path))?;
+    let mut stdin = ffmpeg
+        .stdin
+        .take()
+        .ok_or_else(|| "Failed to open ffmpeg input".to_string())?;
+
+    let mut rendered = 0;
+    for (asset, label) in frames {
+        let original = get_object_from_s3(asset.storage_key(), config).await?;
+        let label = label.clone();
+        let frame = tokio::task::spawn_blocking(move || render_frame(&original, width, &label))
+            .await
+            .map_err(|e| format!("Frame task failed: {e}"))?;
+        match frame {
+            Ok(frame) => {
+                stdin
+                    .write_all(&frame)
+                    .await
+                    .map_err(|e| format!("Failed to write frame to ffmpeg: {e}"))?;
+                rendered += 1;
+            }
+            // A corrupt image drops one frame rather than the whole video
+            Err(e) => tracing::warn!("Skipping {} in time-lapse: {e}", asset.original_filename),
+        }
+    }
+    drop(stdin);
+
+    let output = ffmpeg
+        .wait_with_output()
+        .await
+        .map_err(|e| format!("ffmpeg failed: {e}"))?;
+    if !output.status.success() {
+        return Err(format!(
+            "ffmpeg exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    if rendered == 0 {
+        return Err("None of the images could be decoded".to_string());
+    }
+
+    let video = tokio::fs::read(&output_path)
+        .await
+        .map_err(|e| format!("Failed to read encoded video: {e}"))?;
+    Ok((video, rendered))
+}
+
+async fn set_status(db: &DatabaseConnection, asset_id: Uuid, update: s3_assets::ActiveModel) {
+    let update = s3_assets::ActiveModel {
+        id: Set(asset_id),
+        last_updated: Set(chrono::Utc::now()),
+        ..update
+    };
+    if let Err(e) = s3_assets::Entity::update(update).exec(db).await {
+        tracing::warn!("Failed to update time-lapse asset {asset_id}: {e}");
+    }
+}
+
+/// Register the time-lapse asset and render the video in the background.
+///
+/// A previous time-lapse in the same format is replaced. Validation problems
+/// are returned as `DbErr::Custom`.
+pub async fn start_timelapse(
+    state: &AppState,
+    experiment_id: Uuid,
+    request: &TimelapseRequest,
+) -> Result<s3_assets::Model, DbErr> {
+    let fps = request.fps.unwrap_or(DEFAULT_FPS);
+    let width = request.width.unwrap_or(DEFAULT_WIDTH);
+    if !(1..=60).contains(&fps) {
+        return Err(DbErr::Custom("fps must be between 1 and 60".to_string()));
+    }
+    if !(64..=3840).contains(&width) {
+        return Err(DbErr::Custom(
+            "width must be between 64 and 3840".to_string(),
+        ));
+    }
+
+    let frames = load_frames(&state.db, experiment_id).await?;
+    if frames.is_empty() {
+        return Err(DbErr::Custom(
+            "Experiment has no INP_* camera images".to_string(),
+        ));
+    }
+
+    let format = request.format;
+    if let Some(previous) = find_timelapse_asset(&state.db, experiment_id, format).await? {
+        if let Err(e) = delete_object_from_s3(&previous.s3_key, &state.config).await {
+            tracing::warn!(
+                "Failed to delete previous time-lapse {}: {e}",
+                previous.s3_key
+            );
+        }
+        s3_assets::Entity::delete_by_id(previous.id)
+            .exec(&state.db)
+            .await?;
+    }
+
+    let asset = s3_assets::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        experiment_id: Set(Some(experiment_id)),
+        original_filename: Set(format.filename()),
+        s3_key: Set(format!(
+            "{}/{}/experiments/{}/derived/{}",
+            state.config.app_name,
+            state.config.deployment,
+            experiment_id,
+            format.filename()
+        )),
+        size_bytes: Set(None),
+        uploaded_by: Set(Some("spice-api".to_string())),
+        uploaded_at: Set(chrono::Utc::now()),
+        is_deleted: Set(false),
+        created_at: Set(chrono::Utc::now()),
+        last_updated: Set(chrono::Utc::now()),
+        r#type: Set("video".to_string()),
+        role: Set(Some(TIMELAPSE_ROLE.to_string())),
+        processing_status: Set(Some("processing".to_string())),
+        processing_message: Set(Some(format!("Rendering {} frames", frames.len()))),
+        thumbnail_s3_key: Set(None),
+        checksum_sha256: Set(None),
+        blob_s3_key: Set(None),
+        captured_at: Set(None),
+        temperature_reading_id: Set(None),
+    };
+    let asset = s3_assets::Entity::insert(asset)
+        .exec_with_returning(&state.db)
+        .await?;
+
+    let db = state.db.clone();
+    let config = state.config.clone();
+    let job_asset = asset.clone();
+    let cache = state.response_cache.clone();
+    tokio::spawn(async move {
+        let update = match encode_video(&config, &frames, format, fps, width).await {
+            Ok((video, rendered)) => {
+                let size = i64::try_from(video.len()).ok();
+                let checksum = crate::assets::services::sha256_hex(&video);
+                match put_object_to_s3(&job_asset.s3_key, video, &config).await {
+                    Ok(()) => s3_assets::ActiveModel {
+                        size_bytes: Set(size),
+                        checksum_sha256: Set(Some(checksum)),
+                        processing_status: Set(Some("completed".to_string())),
+                        processing_message: Set(Some(format!(
+                            "Rendered {rendered} frames at {fps} fps"
+                        ))),
+                        ..Default::default()
+                    },
+                    Err(e) => failed(e),
+                }
+            }
+            Err(e) => failed(e),
+        };
+        set_status(&db, job_asset.id, update).await;
+        cache.clear();
+    });
+
+    Ok(asset)
+}
+
+fn failed(error: String) -> s3_assets::ActiveModel {
+    tracing::error!("Time-lapse rendering failed: {error}");
+    s3_assets::ActiveModel {
+        processing_status: Set(Some("error".to_string())),
+        processing_message: Set(Some(error)),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_capture_time() {
+        assert_eq!(
+            parse_capture_time("INP_49640_2025-03-20_15-14-17.jpg"),
+            NaiveDateTime::parse_from_str("2025-03-20 15:14:17", "%Y-%m-%d %H:%M:%S").ok()
+        );
+        assert_eq!(parse_capture_time("INP_49640.jpg"), None);
+        assert_eq!(parse_capture_time("notes.txt"), None);
+    }
+
+    #[test]
+    fn test_render_frame_scales_and_stamps() {
+        let mut png = Vec::new();
+        RgbImage::from_pixel(1001, 500, Rgb([200, 200, 200]))
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+
+        let frame = render_frame(&png, 641, "2025-03-20 15:14:17").unwrap();
+        let frame = image::load_from_memory(&frame).unwrap().into_rgb8();
+        assert_eq!((frame.width(), frame.height()), (640, 318));
+
+        // The label box is dark, the rest of the frame keeps its colour
+        assert!(frame.get_pixel(1, frame.height() - 1)[0] < 50);
+        assert!(frame.get_pixel(frame.width() - 1, 0)[0] > 150);
+    }
+}
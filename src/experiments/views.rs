@@ -1,32 +1,86 @@
+use super::bundle::{BundleImportResult, ExperimentBundle};
+use super::cloning::{ExperimentCloneRequest, clone_experiment};
+use super::comments::models::{
+    ExperimentComment, ExperimentCommentCreate, ExperimentCommentUpdate,
+};
+use super::comments::services::{
+    ExperimentCommentQuery, create_comment, delete_comment, get_comment, list_comments,
+    update_comment,
+};
+use super::exclusions::{ExcludedWell, ExcludedWellsUpdate};
+use super::frames::{FrameDirection, FrameNavigation};
+use super::image_diff::{ImageDiff, ImageDiffFormat};
+use super::image_freeze::{FreezeDetectionRequest, FreezeDetectionResult};
+use super::merging::{ExperimentMergeRequest, ExperimentMergeResult, merge_experiments};
+use super::models::ExperimentResultsResponse;
 pub use super::models::{Experiment, router as crudrouter};
+use super::regions::{create_region, delete_region, list_regions, update_region};
+use super::temperatures::services::TemperatureStreamQuery;
+use super::temperatures::timeseries::{ProbeTimeseries, ProbeTimeseriesQuery, probe_timeseries};
+use super::time_points::{TimePointPage, TimePointQuery, list_time_points};
+use super::timelapse::{TimelapseFormat, TimelapseRequest};
+use crate::api_keys::services::accept_api_keys;
+use crate::assets::download_tokens::models::{
+    DownloadScope, DownloadTokenOptions, IssuedDownloadToken,
+};
+use crate::assets::download_tokens::services::issue_download_token;
+use crate::assets::integrity::ExperimentIntegrityReport;
 use crate::assets::models as s3_assets;
-use crate::common::auth::Role;
+use crate::audit::services::{AuditedResource, audit_changes};
+use crate::changes::services::Reader;
+use crate::changes::views::reader;
+use crate::common::aggregate::{aggregate_handler, count_handler};
+use crate::common::auth::{Role, RouteAccess, require_role};
+use crate::common::cache::cache_records;
+use crate::common::etag::check_entity_tags;
+use crate::common::keycloak::authenticate;
+use crate::common::labs::{Labs, TenantResource, require_lab};
 use crate::common::models::ProcessingStatus;
+use crate::common::pagination::paginate_by_cursor;
+use crate::common::patch::patch_one_handler;
+use crate::common::redaction::redact_for_viewers;
+use crate::common::soft_delete::{hide_deleted, restore_one_handler};
 use crate::common::state::AppState;
+use crate::common::upsert::upsert_handler;
 use crate::experiments::phase_transitions::models as phase_models;
 use crate::experiments::temperatures::models as temp_models;
-use crate::external::s3::get_client;
+use crate::idempotency::services::ShowsSecret;
+use crate::projects::access::{ScopedResource, require_project_access};
+use crate::projects::archiving::reject_archived_changes;
+use crate::projects::shares::models::SharedResource;
+use crate::projects::shares::views::{delete_share, get_shares, post_share, shares_api};
+use crate::services::datacite_service::DataCiteMetadata;
+use crate::services::processing::excel_processor::ExcelProcessingResult;
+use crate::services::processing::progress::ProcessingProgress;
+use crate::tray_configurations::regions::models::{Region, RegionCreate, RegionUpdate};
+use crate::tray_configurations::wells::services::{WellLocation, experiment_wells};
+use crate::versions::{self, services::keep_versions};
 use axum::extract::{Path, State};
-use axum::routing::post;
+use axum::middleware;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::routing::{patch, post};
 use axum::{
     extract::Multipart,
     http::{HeaderMap, status::StatusCode},
     response::Json,
 };
-use axum_keycloak_auth::{PassthroughMode, layer::KeycloakAuthLayer};
+use axum_keycloak_auth::{PassthroughMode, decode::KeycloakToken};
 use crudcrate::CRUDResource;
 use sea_orm::ActiveValue::Set;
 use sea_orm::entity::prelude::*;
 use serde::Serialize;
+use std::collections::BTreeMap;
 use std::convert::TryInto;
-use utoipa::ToSchema;
+use utoipa::{OpenApi, ToSchema};
 use utoipa_axum::router::OpenApiRouter;
 use uuid::Uuid;
 
 // Helper struct for file upload processing
 struct FileUploadData {
     file_name: String,
-    file_bytes: Vec<u8>,
+    /// Contents of a file sent through the API. Files uploaded to storage
+    /// directly are only read from there as far as they need to be.
+    file_bytes: Option<Vec<u8>>,
     file_type: String,
     extension: String,
     size: u64,
@@ -39,14 +93,9 @@ struct AssetProcessingResult {
     processing_message: Option<String>,
 }
 
-/// Process multipart field into file upload data
-async fn process_multipart_field(
-    field: &mut axum::extract::multipart::Field<'_>,
-    experiment_id: Uuid,
-    state: &AppState,
-) -> Result<FileUploadData, (StatusCode, String)> {
-    let file_name = field.file_name().unwrap_or("unknown").to_string();
-    let extension = std::path::Path::new(&file_name)
+/// File type and lower-case extension of an uploaded file
+fn classify_file(file_name: &str) -> (String, String) {
+    let extension = std::path::Path::new(file_name)
         .extension()
         .and_then(|ext| ext.to_str())
         .unwrap_or("")
@@ -59,6 +108,18 @@ async fn process_multipart_field(
         _ => "unknown".to_string(),
     };
 
+    (file_type, extension)
+}
+
+/// Process multipart field into file upload data
+async fn process_multipart_field(
+    field: &mut axum::extract::multipart::Field<'_>,
+    experiment_id: Uuid,
+    state: &AppState,
+) -> Result<FileUploadData, (StatusCode, String)> {
+    let file_name = field.file_name().unwrap_or("unknown").to_string();
+    let (file_type, extension) = classify_file(&file_name);
+
     let mut file_bytes = Vec::new();
     while let Some(chunk) = field.chunk().await.unwrap() {
         file_bytes.extend_from_slice(&chunk);
@@ -72,7 +133,7 @@ async fn process_multipart_field(
 
     Ok(FileUploadData {
         file_name,
-        file_bytes,
+        file_bytes: Some(file_bytes),
         file_type,
         extension,
         size,
@@ -103,11 +164,14 @@ async fn handle_existing_asset(
         }
 
         // Delete existing asset from database and S3 before uploading new one
-        if let Err(e) = crate::external::s3::delete_from_s3(&existing.s3_key).await {
-            println!(
-                "Warning: Failed to delete existing file from S3: {} - {}",
-                existing.s3_key, e
-            );
+        if let Err(e) = crate::assets::dedupe::release_asset_objects(
+            &state.db,
+            &state.config,
+            std::slice::from_ref(&existing),
+        )
+        .await
+        {
+            println!("Warning: Failed to delete existing file from S3: {e}");
         }
 
         s3_assets::Entity::delete_by_id(existing.id)
@@ -154,10 +218,23 @@ async fn process_excel_if_needed(
             state.db.clone(),
         );
 
-    match processing_service
-        .process_excel_file(experiment_id, upload_data.file_bytes.clone())
-        .await
-    {
+    // A file uploaded to storage directly is only read back to be processed
+    let file_bytes = match &upload_data.file_bytes {
+        Some(file_bytes) => Ok(file_bytes.clone()),
+        None => crate::external::s3::get_object_from_s3(&upload_data.s3_key, &state.config)
+            .await
+            .map_err(anyhow::Error::msg),
+    };
+    let processed = match file_bytes {
+        Ok(file_bytes) => {
+            processing_service
+                .process_excel_file(experiment_id, file_bytes)
+                .await
+        }
+        Err(e) => Err(e),
+    };
+
+    match processed {
         Ok(result) => {
             let processing_status = match result.status {
                 crate::common::models::ProcessingStatus::Completed => Some("completed".to_string()),
@@ -282,13 +359,84 @@ fn determine_asset_role(filename: &str, file_type: &str, _extension: &str) -> St
     }
 }
 
+/// Routes beyond the CRUD routes, documented here as they are routed
+/// with `route`
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        upload_file,
+        create_presigned_upload_urls,
+        register_presigned_upload,
+        create_experiment_download_token,
+        download_experiment_archive,
+        export_experiment_excel,
+        stream_experiment_temperatures,
+        get_probe_timeseries,
+        get_time_points,
+        export_experiment_bundle,
+        import_experiment_bundle,
+        clone_experiment_handler,
+        merge_experiment_handler,
+        create_experiment_timelapse,
+        download_experiment_timelapse,
+        get_wells,
+        get_well_image,
+        get_excluded_wells,
+        set_excluded_wells,
+        get_comments,
+        post_comment,
+        patch_comment,
+        delete_comment_handler,
+        get_regions,
+        post_region,
+        put_region,
+        delete_region_handler,
+        recompute_experiment_results,
+        navigate_camera_frames,
+        get_image_diff,
+        detect_experiment_freezing,
+        verify_experiment_integrity,
+        get_experiment_datacite,
+        create_zenodo_deposition,
+        process_asset_data,
+        get_processing_status,
+        stream_processing_status,
+        clear_experiment_results
+    ),
+    components(schemas(FrameDirection, ImageDiffFormat))
+)]
+struct ExperimentsApi;
+
+#[allow(clippy::too_many_lines)] // One route per experiment endpoint
 pub fn router(state: &AppState) -> OpenApiRouter
 where
     Experiment: CRUDResource,
 {
     use axum::extract::DefaultBodyLimit;
 
-    let mut mutating_router = crudrouter(&state.db.clone());
+    let mut mutating_router = crudrouter(&state.db.clone())
+        .route(
+            "/{id}",
+            patch(patch_one_handler::<Experiment>).with_state(state.db.clone()),
+        )
+        .route(
+            "/{id}/restore",
+            post(restore_one_handler::<Experiment>).with_state(state.db.clone()),
+        )
+        .route(
+            "/count",
+            axum::routing::get(count_handler::<Experiment>).with_state(state.db.clone()),
+        )
+        .route(
+            "/aggregate",
+            axum::routing::get(aggregate_handler::<Experiment>).with_state(state.db.clone()),
+        )
+        .route(
+            "/upsert",
+            post(upsert_handler::<Experiment>)
+                .with_state((state.db.clone(), AuditedResource::Experiments)),
+        )
+        .merge(versions::views::router::<Experiment>(&state.db));
 
     // Excel processing endpoints (previously in excel_upload_router)
     mutating_router = mutating_router
@@ -296,6 +444,14 @@ where
             "/{experiment_id}/process-asset",
             post(process_asset_data).with_state(state.clone()),
         )
+        .route(
+            "/{experiment_id}/process-status/{job_id}",
+            axum::routing::get(get_processing_status).with_state(state.clone()),
+        )
+        .route(
+            "/{experiment_id}/process-status/{job_id}/stream",
+            axum::routing::get(stream_processing_status).with_state(state.clone()),
+        )
         .route(
             "/{experiment_id}/clear-results",
             post(clear_experiment_results).with_state(state.clone()),
@@ -305,22 +461,211 @@ where
             "/{experiment_id}/uploads",
             post(upload_file).with_state(state.clone()),
         )
+        .route(
+            "/{experiment_id}/upload-urls",
+            post(create_presigned_upload_urls).with_state(state.clone()),
+        )
+        .route(
+            "/{experiment_id}/uploads/register",
+            post(register_presigned_upload).with_state(state.clone()),
+        )
         .route(
             "/{experiment_id}/download-token",
             post(create_experiment_download_token).with_state(state.clone()),
         )
+        .route(
+            "/{experiment_id}/archive",
+            axum::routing::get(download_experiment_archive).with_state(state.clone()),
+        )
+        .route(
+            "/{experiment_id}/temperatures",
+            axum::routing::get(stream_experiment_temperatures).with_state(state.clone()),
+        )
+        .route(
+            "/{experiment_id}/probes/{probe_id}/timeseries",
+            axum::routing::get(get_probe_timeseries).with_state(state.clone()),
+        )
+        .route(
+            "/{experiment_id}/time_points",
+            axum::routing::get(get_time_points).with_state(state.clone()),
+        )
+        .route(
+            "/{experiment_id}/bundle",
+            axum::routing::get(export_experiment_bundle).with_state(state.clone()),
+        )
+        .route(
+            "/import",
+            post(import_experiment_bundle).with_state(state.clone()),
+        )
+        .route(
+            "/{experiment_id}/excel",
+            axum::routing::get(export_experiment_excel).with_state(state.clone()),
+        )
+        .route(
+            "/{experiment_id}/clone",
+            post(clone_experiment_handler).with_state(state.clone()),
+        )
+        .route(
+            "/{experiment_id}/merge",
+            post(merge_experiment_handler).with_state(state.clone()),
+        )
+        .route(
+            "/{experiment_id}/timelapse",
+            post(create_experiment_timelapse)
+                .get(download_experiment_timelapse)
+                .with_state(state.clone()),
+        )
+        .route(
+            "/{experiment_id}/detect-freezing",
+            post(detect_experiment_freezing).with_state(state.clone()),
+        )
+        .route(
+            "/{experiment_id}/wells",
+            axum::routing::get(get_wells).with_state(state.clone()),
+        )
+        .route(
+            "/{experiment_id}/wells/{coordinate}/image",
+            axum::routing::get(get_well_image).with_state(state.clone()),
+        )
+        .route(
+            "/{experiment_id}/excluded-wells",
+            axum::routing::get(get_excluded_wells)
+                .put(set_excluded_wells)
+                .with_state(state.clone()),
+        )
+        .route(
+            "/{experiment_id}/comments",
+            axum::routing::get(get_comments)
+                .post(post_comment)
+                .with_state(state.clone()),
+        )
+        .route(
+            "/{experiment_id}/comments/{comment_id}",
+            patch(patch_comment)
+                .delete(delete_comment_handler)
+                .with_state(state.clone()),
+        )
+        .route(
+            "/{experiment_id}/regions",
+            axum::routing::get(get_regions)
+                .post(post_region)
+                .with_state(state.clone()),
+        )
+        .route(
+            "/{experiment_id}/regions/{region_id}",
+            axum::routing::put(put_region)
+                .delete(delete_region_handler)
+                .with_state(state.clone()),
+        )
+        .route(
+            "/{experiment_id}/results/recompute",
+            post(recompute_experiment_results).with_state(state.clone()),
+        )
+        .route(
+            "/{experiment_id}/frames/{direction}",
+            axum::routing::get(navigate_camera_frames).with_state(state.clone()),
+        )
+        .route(
+            "/{experiment_id}/image-diff",
+            axum::routing::get(get_image_diff).with_state(state.clone()),
+        )
+        .route(
+            "/{experiment_id}/integrity",
+            axum::routing::get(verify_experiment_integrity).with_state(state.clone()),
+        )
+        .route(
+            "/{experiment_id}/datacite",
+            axum::routing::get(get_experiment_datacite).with_state(state.clone()),
+        )
+        .route(
+            "/{experiment_id}/zenodo-deposition",
+            post(create_zenodo_deposition).with_state(state.clone()),
+        )
+        .route(
+            "/{experiment_id}/shares",
+            axum::routing::get(get_shares)
+                .post(post_share)
+                .with_state((state.db.clone(), SharedResource::Experiment)),
+        )
+        .route(
+            "/{experiment_id}/shares/{grant_id}",
+            axum::routing::delete(delete_share)
+                .with_state((state.db.clone(), SharedResource::Experiment)),
+        )
         .layer(DefaultBodyLimit::max(30 * 1024 * 1024)); // 30MB limit for file uploads
+    mutating_router
+        .get_openapi_mut()
+        .merge(ExperimentsApi::openapi());
+    mutating_router
+        .get_openapi_mut()
+        .merge(shares_api(SharedResource::Experiment));
+
+    // Records polled by dashboards are served from the cache while unchanged
+    mutating_router = mutating_router.layer(middleware::from_fn_with_state(
+        (state.db.clone(), state.response_cache.clone()),
+        cache_records::<Experiment>,
+    ));
+
+    // Each update keeps the version it replaces
+    mutating_router = mutating_router.layer(middleware::from_fn_with_state(
+        state.db.clone(),
+        keep_versions::<Experiment>,
+    ));
+
+    mutating_router = mutating_router.layer(middleware::from_fn_with_state(
+        state.db.clone(),
+        paginate_by_cursor::<Experiment>,
+    ));
+
+    mutating_router = mutating_router.layer(middleware::from_fn_with_state(
+        (state.db.clone(), state.config.require_if_match),
+        check_entity_tags::<Experiment>,
+    ));
+
+    // Deleted records are kept, out of sight until restored
+    mutating_router = mutating_router.layer(middleware::from_fn_with_state(
+        state.db.clone(),
+        hide_deleted::<Experiment>,
+    ));
+
+    // Archived projects and their records are read-only, even to administrators
+    mutating_router = mutating_router.layer(middleware::from_fn_with_state(
+        (state.db.clone(), Some(ScopedResource::Experiments)),
+        reject_archived_changes,
+    ));
+
+    // Changes are logged with the user who made them
+    mutating_router = mutating_router.layer(middleware::from_fn_with_state(
+        (state.db.clone(), AuditedResource::Experiments),
+        audit_changes,
+    ));
 
     if let Some(instance) = &state.keycloak_auth_instance {
-        mutating_router = mutating_router.layer(
-            KeycloakAuthLayer::<Role>::builder()
-                .instance(instance.clone())
-                .passthrough_mode(PassthroughMode::Block)
-                .persist_raw_claims(false)
-                .expected_audiences(vec![String::from("account")])
-                .required_roles(vec![Role::Administrator])
-                .build(),
-        );
+        // Viewers read and editors change records; users who are not
+        // administrators are limited to the projects they are members of and
+        // the records they created or were given, within their labs
+        mutating_router = mutating_router
+            .layer(middleware::from_fn_with_state(
+                (state.db.clone(), ScopedResource::Experiments),
+                require_project_access,
+            ))
+            .layer(middleware::from_fn_with_state(
+                (state.db.clone(), TenantResource::Experiments),
+                require_lab,
+            ))
+            .layer(middleware::from_fn_with_state(
+                RouteAccess::PROJECT_RECORDS,
+                require_role,
+            ))
+            .layer(middleware::from_fn(redact_for_viewers))
+            .layer(middleware::from_fn_with_state(
+                (state.db.clone(), "experiments"),
+                accept_api_keys,
+            ))
+            .layer(middleware::from_fn_with_state(
+                (instance.clone(), PassthroughMode::Pass),
+                authenticate,
+            ));
     } else if !state.config.tests_running {
         println!(
             "Warning: Mutating routes of {} router are not protected",
@@ -331,6 +676,15 @@ where
     mutating_router
 }
 
+/// Form an upload is sent as
+#[derive(ToSchema)]
+#[allow(dead_code)]
+pub struct UploadForm {
+    /// The file, under its own name
+    #[schema(value_type = String, format = Binary)]
+    file: Vec<u8>,
+}
+
 #[derive(Serialize, serde::Deserialize, ToSchema)]
 pub struct UploadResponse {
     success: bool,
@@ -345,11 +699,9 @@ pub struct UploadResponse {
     post,
     path = "/{experiment_id}/uploads",
     request_body(
+        content = UploadForm,
         content_type = "multipart/form-data",
-        description = "File to upload",
-        example = json!({
-            "file": "(binary data)"
-        })
+        description = "File to upload"
     ),
     responses(
         (status = 200, description = "Success", body = UploadResponse)
@@ -371,9 +723,6 @@ pub async fn upload_file(
         return Err((StatusCode::NOT_FOUND, "Experiment not found".to_string()));
     }
 
-    // Load S3 configuration from app state (not needed for mocked S3 operations)
-    let _s3_client = get_client(&state.config).await;
-
     while let Some(mut field) = infile.next_field().await.unwrap() {
         let field_name = field.name().unwrap_or("none").to_string();
 
@@ -386,10 +735,7 @@ pub async fn upload_file(
         let upload_data = process_multipart_field(&mut field, experiment_id, &state).await?;
 
         // Check if overwrite is allowed
-        let allow_overwrite = headers
-            .get("x-allow-overwrite")
-            .and_then(|v| v.to_str().ok())
-            .is_some_and(|s| s == "true");
+        let allow_overwrite = allows_overwrite(&headers);
 
         // Handle existing asset overwrite logic
         handle_existing_asset(
@@ -403,7 +749,7 @@ pub async fn upload_file(
         // Upload the file to S3 (uses mock for tests, real S3 for production)
         if let Err(e) = crate::external::s3::put_object_to_s3(
             &upload_data.s3_key,
-            upload_data.file_bytes.clone(),
+            upload_data.file_bytes.clone().unwrap_or_default(),
             &state.config,
         )
         .await
@@ -414,140 +760,1811 @@ pub async fn upload_file(
             ));
         }
 
-        // Determine asset role based on filename patterns and type
-        let asset_role = determine_asset_role(
-            &upload_data.file_name,
-            &upload_data.file_type,
-            &upload_data.extension,
-        );
-
-        // Insert a record into the local DB
-        let asset_id = Uuid::new_v4();
-        let asset = s3_assets::ActiveModel {
-            id: Set(asset_id),
-            original_filename: Set(upload_data.file_name.clone()),
-            experiment_id: Set(Some(experiment_id)),
-            s3_key: Set(upload_data.s3_key.clone()),
-            size_bytes: Set(Some(upload_data.size.try_into().unwrap())),
-            uploaded_by: Set(Some("uploader".to_string())),
-            r#type: Set(upload_data.file_type.clone()),
-            role: Set(Some(asset_role.clone())),
-            processing_status: Set(None),
-            processing_message: Set(None),
-            ..Default::default()
-        };
-        let _asset_result = s3_assets::Entity::insert(asset)
-            .exec(&state.db)
+        return register_uploaded_asset(upload_data, experiment_id, &state)
             .await
-            .map_err(|e| {
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("Failed to insert asset record: {e}"),
-                )
-            })?;
+            .map(Json);
+    }
+
+    Err((StatusCode::BAD_REQUEST, "No file uploaded".to_string()))
+}
 
-        // Asset insertion successful, use the pre-generated asset_id
+/// Checksum of an uploaded file and, for images, when they were taken. Files
+/// uploaded to storage directly are hashed a chunk at a time, and only the
+/// header of an image is read.
+async fn read_upload_metadata(
+    upload_data: &FileUploadData,
+    state: &AppState,
+) -> Result<(String, Option<chrono::DateTime<chrono::Utc>>), String> {
+    let is_image = upload_data.file_type == "image";
+    if let Some(file_bytes) = &upload_data.file_bytes {
+        return Ok((
+            crate::assets::services::sha256_hex(file_bytes),
+            is_image
+                .then(|| {
+                    crate::assets::capture::capture_time(&upload_data.file_name, Some(file_bytes))
+                })
+                .flatten(),
+        ));
+    }
 
-        // Process Excel file if needed using helper function
-        let processing_result =
-            process_excel_if_needed(&upload_data, asset_id, experiment_id, &state).await;
+    let checksum = crate::assets::services::object_sha256_hex(
+        &upload_data.s3_key,
+        upload_data.size,
+        &state.config,
+    )
+    .await?;
+    let captured_at = if is_image && upload_data.size > 0 {
+        let header = crate::external::s3::get_object_range_from_s3(
+            &upload_data.s3_key,
+            0,
+            crate::assets::capture::EXIF_HEADER_BYTES.min(upload_data.size) - 1,
+            &state.config,
+        )
+        .await?;
+        crate::assets::capture::capture_time(&upload_data.file_name, Some(&header))
+    } else {
+        None
+    };
+    Ok((checksum, captured_at))
+}
 
-        return Ok(Json(UploadResponse {
-            success: true,
-            id: asset_id.to_string(),
-            filename: upload_data.file_name,
-            size: upload_data.size,
-            auto_processed: processing_result.auto_processed,
-            processing_message: processing_result.processing_message,
-        }));
+/// Record an uploaded file in `s3_assets` and auto-process it when it is the
+/// experiment's Excel data
+async fn register_uploaded_asset(
+    upload_data: FileUploadData,
+    experiment_id: Uuid,
+    state: &AppState,
+) -> Result<UploadResponse, (StatusCode, String)> {
+    // Determine asset role based on filename patterns and type
+    let asset_role = determine_asset_role(
+        &upload_data.file_name,
+        &upload_data.file_type,
+        &upload_data.extension,
+    );
+
+    let (checksum_sha256, captured_at) = read_upload_metadata(&upload_data, state)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    // Insert a record into the local DB
+    let asset_id = Uuid::new_v4();
+    let asset = s3_assets::ActiveModel {
+        id: Set(asset_id),
+        original_filename: Set(upload_data.file_name.clone()),
+        experiment_id: Set(Some(experiment_id)),
+        s3_key: Set(upload_data.s3_key.clone()),
+        size_bytes: Set(Some(upload_data.size.try_into().unwrap())),
+        uploaded_by: Set(Some("uploader".to_string())),
+        r#type: Set(upload_data.file_type.clone()),
+        role: Set(Some(asset_role.clone())),
+        processing_status: Set(None),
+        processing_message: Set(None),
+        checksum_sha256: Set(Some(checksum_sha256)),
+        captured_at: Set(captured_at),
+        ..Default::default()
+    };
+    let asset = s3_assets::Entity::insert(asset)
+        .exec_with_returning(&state.db)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to insert asset record: {e}"),
+            )
+        })?;
+
+    // Content already stored for another asset is kept only once
+    if let Err(e) = crate::assets::dedupe::deduplicate_asset(&state.db, &state.config, &asset).await
+    {
+        println!("Warning: Failed to deduplicate asset {asset_id}: {e}");
     }
 
-    Err((StatusCode::BAD_REQUEST, "No file uploaded".to_string()))
+    crate::webhooks::services::emit(
+        &state.db,
+        "asset.uploaded",
+        serde_json::json!({
+            "id": asset_id,
+            "experiment_id": experiment_id,
+            "original_filename": asset.original_filename,
+            "type": asset.r#type,
+            "role": asset.role,
+            "size_bytes": asset.size_bytes,
+            "checksum_sha256": asset.checksum_sha256,
+        }),
+    )
+    .await;
+
+    if upload_data.file_type == "image" {
+        if let Err(e) = crate::assets::capture::link_image_to_reading(&state.db, &asset).await {
+            println!("Warning: Failed to link image {asset_id} to a temperature reading: {e}");
+        }
+        crate::services::thumbnail_service::spawn_thumbnail_generation(
+            state.db.clone(),
+            state.config.clone(),
+            asset_id,
+        );
+    }
+
+    // Process Excel file if needed using helper function
+    let processing_result =
+        process_excel_if_needed(&upload_data, asset_id, experiment_id, state).await;
+
+    Ok(UploadResponse {
+        success: true,
+        id: asset_id.to_string(),
+        filename: upload_data.file_name,
+        size: upload_data.size,
+        auto_processed: processing_result.auto_processed,
+        processing_message: processing_result.processing_message,
+    })
+}
+
+/// Seconds a presigned upload URL stays valid
+const PRESIGNED_UPLOAD_EXPIRY_SECONDS: u64 = 60 * 60;
+const PRESIGNED_UPLOAD_EXPIRY: std::time::Duration =
+    std::time::Duration::from_secs(PRESIGNED_UPLOAD_EXPIRY_SECONDS);
+
+#[derive(serde::Deserialize, ToSchema)]
+pub struct PresignedUploadFile {
+    filename: String,
+    /// Content type the client will send with the `PUT`
+    content_type: Option<String>,
+}
+
+#[derive(serde::Deserialize, ToSchema)]
+pub struct PresignedUploadRequest {
+    files: Vec<PresignedUploadFile>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct PresignedUpload {
+    filename: String,
+    /// Key to pass to the registration endpoint once the upload has finished
+    s3_key: String,
+    upload_url: String,
+    /// Headers to send with the `PUT` to `upload_url`
+    upload_headers: BTreeMap<String, String>,
+    expires_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(serde::Deserialize, ToSchema)]
+pub struct RegisterUploadRequest {
+    s3_key: String,
+}
+
+/// Key prefix for files uploaded directly to S3. Every upload gets its own
+/// directory so a re-upload never overwrites an object that is still registered.
+fn direct_upload_prefix(state: &AppState, experiment_id: Uuid) -> String {
+    format!(
+        "{}/{}/experiments/{}/uploads/",
+        state.config.app_name, state.config.deployment, experiment_id
+    )
+}
+
+fn is_valid_upload_filename(filename: &str) -> bool {
+    !filename.is_empty() && filename != "." && filename != ".." && !filename.contains(['/', '\\'])
+}
+
+async fn ensure_experiment_exists(
+    state: &AppState,
+    experiment_id: Uuid,
+) -> Result<(), (StatusCode, String)> {
+    super::models::Entity::find_by_id(experiment_id)
+        .one(&state.db)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map(|_| ())
+        .ok_or((StatusCode::NOT_FOUND, "Experiment not found".to_string()))
+}
+
+fn allows_overwrite(headers: &HeaderMap) -> bool {
+    headers
+        .get("x-allow-overwrite")
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|s| s == "true")
 }
 
 #[utoipa::path(
     post,
-    path = "/{experiment_id}/download-token",
+    path = "/{experiment_id}/upload-urls",
     params(
         ("experiment_id" = Uuid, Path, description = "Experiment UUID")
     ),
+    request_body = PresignedUploadRequest,
     responses(
-        (status = 200, description = "Download token created successfully", body = serde_json::Value),
+        (status = 200, description = "Presigned upload URLs", body = Vec<PresignedUpload>),
+        (status = 400, description = "Invalid file names"),
         (status = 404, description = "Experiment not found"),
-        (status = 500, description = "Internal server error")
+        (status = 409, description = "A file already exists and X-Allow-Overwrite is not set"),
+        (status = 500, description = "Internal server error"),
+        (status = 501, description = "The storage backend does not support direct uploads")
     ),
     tag = "experiments",
-    summary = "Create download token",
-    description = "Create a secure download token for accessing experiment assets"
+    summary = "Create presigned upload URLs",
+    description = "Issue presigned PUT URLs so large files can be uploaded directly to storage. After each upload succeeds, register it with POST /{experiment_id}/uploads/register"
 )]
-pub async fn create_experiment_download_token(
+pub async fn create_presigned_upload_urls(
     State(state): State<AppState>,
-    Path(experiment_id): Path<uuid::Uuid>,
-) -> Result<axum::Json<serde_json::Value>, (StatusCode, String)> {
-    // Verify experiment exists
-    use crate::experiments::models::Entity as ExperimentEntity;
+    Path(experiment_id): Path<Uuid>,
+    headers: HeaderMap,
+    Json(request): Json<PresignedUploadRequest>,
+) -> Result<Json<Vec<PresignedUpload>>, (StatusCode, String)> {
+    ensure_experiment_exists(&state, experiment_id).await?;
+
+    if !crate::external::s3::supports_presigned_urls(&state.config).await {
+        return Err((
+            StatusCode::NOT_IMPLEMENTED,
+            "The storage backend does not support direct uploads, use POST /{experiment_id}/uploads"
+                .to_string(),
+        ));
+    }
+    if request.files.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "No files requested".to_string()));
+    }
+    if let Some(file) = request
+        .files
+        .iter()
+        .find(|file| !is_valid_upload_filename(&file.filename))
+    {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Invalid file name '{}'", file.filename),
+        ));
+    }
 
-    let experiment = ExperimentEntity::find_by_id(experiment_id)
-        .one(&state.db)
-        .await
-        .map_err(|_| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Database error".to_string(),
+    // Fail early rather than after the client has uploaded gigabytes
+    if !allows_overwrite(&headers) {
+        let existing = s3_assets::Entity::find()
+            .filter(s3_assets::Column::ExperimentId.eq(Some(experiment_id)))
+            .filter(
+                s3_assets::Column::OriginalFilename
+                    .is_in(request.files.iter().map(|file| file.filename.clone())),
             )
-        })?;
-
-    if experiment.is_none() {
-        return Err((StatusCode::NOT_FOUND, "Experiment not found".to_string()));
+            .one(&state.db)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        if let Some(existing) = existing {
+            return Err((
+                StatusCode::CONFLICT,
+                format!(
+                    "File '{}' already exists in this experiment",
+                    existing.original_filename
+                ),
+            ));
+        }
     }
 
-    let token = state.create_experiment_download_token(experiment_id).await;
+    let prefix = direct_upload_prefix(&state, experiment_id);
+    let expires_at = chrono::Utc::now()
+        + chrono::Duration::from_std(PRESIGNED_UPLOAD_EXPIRY).unwrap_or_default();
+    let mut uploads = Vec::with_capacity(request.files.len());
+    for file in request.files {
+        let s3_key = format!("{prefix}{}/{}", Uuid::new_v4(), file.filename);
+        let upload = crate::external::s3::presigned_put(
+            &s3_key,
+            file.content_type.as_deref(),
+            PRESIGNED_UPLOAD_EXPIRY,
+            &state.config,
+        )
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+        uploads.push(PresignedUpload {
+            filename: file.filename,
+            s3_key,
+            upload_url: upload.url,
+            upload_headers: upload.headers.into_iter().collect(),
+            expires_at,
+        });
+    }
 
-    Ok(axum::Json(serde_json::json!({
-        "token": token,
-        "download_url": format!("/api/assets/download/{}", token)
-    })))
+    Ok(Json(uploads))
 }
 
 #[utoipa::path(
     post,
-    path = "/{experiment_id}/process-asset",
+    path = "/{experiment_id}/uploads/register",
     params(
         ("experiment_id" = Uuid, Path, description = "Experiment UUID")
     ),
-    request_body = serde_json::Value,
+    request_body = RegisterUploadRequest,
     responses(
-        (status = 200, description = "Asset processing completed successfully", body = serde_json::Value),
-        (status = 400, description = "Bad request"),
+        (status = 200, description = "Upload registered as an asset", body = UploadResponse),
+        (status = 400, description = "Key was not issued for this experiment or nothing was uploaded"),
         (status = 404, description = "Experiment not found"),
+        (status = 409, description = "Upload already registered, or the file exists and X-Allow-Overwrite is not set"),
         (status = 500, description = "Internal server error")
     ),
     tag = "experiments",
-    summary = "Process asset data",
-    description = "Process uploaded asset data for an experiment (Excel files, images, etc.)"
+    summary = "Register a direct upload",
+    description = "Record a file uploaded through a presigned URL as an experiment asset. Excel data files are processed as with regular uploads"
 )]
-#[allow(clippy::too_many_lines)]
-pub async fn process_asset_data(
-    State(app_state): State<AppState>,
+pub async fn register_presigned_upload(
+    State(state): State<AppState>,
     Path(experiment_id): Path<Uuid>,
-    Json(payload): Json<serde_json::Value>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
-    use sea_orm::Set;
-
-    let asset_id = payload
-        .get("assetId")
-        .and_then(|v| v.as_str())
-        .and_then(|s| Uuid::parse_str(s).ok())
-        .ok_or_else(|| {
-            (
-                StatusCode::BAD_REQUEST,
-                "Missing or invalid assetId".to_string(),
-            )
-        })?;
-
-    // Find the asset
-    let asset = s3_assets::Entity::find_by_id(asset_id)
-        .one(&app_state.db)
+    headers: HeaderMap,
+    Json(request): Json<RegisterUploadRequest>,
+) -> Result<Json<UploadResponse>, (StatusCode, String)> {
+    ensure_experiment_exists(&state, experiment_id).await?;
+
+    let file_name = request
+        .s3_key
+        .strip_prefix(&direct_upload_prefix(&state, experiment_id))
+        .and_then(|rest| rest.split_once('/'))
+        .filter(|(upload_id, file_name)| {
+            Uuid::parse_str(upload_id).is_ok() && is_valid_upload_filename(file_name)
+        })
+        .map(|(_, file_name)| file_name.to_string())
+        .ok_or((
+            StatusCode::BAD_REQUEST,
+            "Key was not issued for this experiment".to_string(),
+        ))?;
+
+    let already_registered = s3_assets::Entity::find()
+        .filter(s3_assets::Column::S3Key.eq(&request.s3_key))
+        .one(&state.db)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if already_registered.is_some() {
+        return Err((
+            StatusCode::CONFLICT,
+            "Upload is already registered".to_string(),
+        ));
+    }
+
+    let size = crate::external::s3::head_object_size(&request.s3_key, &state.config)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?
+        .ok_or((
+            StatusCode::BAD_REQUEST,
+            "No file has been uploaded to this key".to_string(),
+        ))?;
+
+    if let Err(e) = handle_existing_asset(
+        &file_name,
+        experiment_id,
+        allows_overwrite(&headers),
+        &state,
+    )
+    .await
+    {
+        // The rejected upload would otherwise stay in the bucket unreferenced
+        let _ = crate::external::s3::delete_object_from_s3(&request.s3_key, &state.config).await;
+        return Err(e);
+    }
+
+    let (file_type, extension) = classify_file(&file_name);
+    let upload_data = FileUploadData {
+        file_name,
+        file_bytes: None,
+        file_type,
+        extension,
+        size: u64::try_from(size).unwrap_or_default(),
+        s3_key: request.s3_key,
+    };
+
+    register_uploaded_asset(upload_data, experiment_id, &state)
+        .await
+        .map(Json)
+}
+
+#[utoipa::path(
+    post,
+    path = "/{experiment_id}/download-token",
+    params(
+        ("experiment_id" = Uuid, Path, description = "Experiment UUID")
+    ),
+    request_body(content = Option<DownloadTokenOptions>, description = "How long and how often the token may be used; the configured defaults when omitted"),
+    responses(
+        (status = 200, description = "Download token created successfully", body = IssuedDownloadToken),
+        (status = 400, description = "Invalid time to live or number of downloads"),
+        (status = 404, description = "Experiment not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "experiments",
+    summary = "Create download token",
+    description = "Create a secure download token for accessing experiment assets"
+)]
+pub async fn create_experiment_download_token(
+    State(state): State<AppState>,
+    Path(experiment_id): Path<uuid::Uuid>,
+    options: Option<axum::Json<DownloadTokenOptions>>,
+) -> Result<
+    (
+        axum::Extension<ShowsSecret>,
+        axum::Json<IssuedDownloadToken>,
+    ),
+    (StatusCode, String),
+> {
+    // Verify experiment exists
+    use crate::experiments::models::Entity as ExperimentEntity;
+
+    let experiment = ExperimentEntity::find_by_id(experiment_id)
+        .one(&state.db)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Database error".to_string(),
+            )
+        })?;
+
+    if experiment.is_none() {
+        return Err((StatusCode::NOT_FOUND, "Experiment not found".to_string()));
+    }
+
+    issue_download_token(
+        &state.db,
+        &state.config,
+        DownloadScope::Experiment,
+        Vec::new(),
+        Some(experiment_id),
+        options
+            .map(|axum::Json(options)| options)
+            .unwrap_or_default(),
+    )
+    .await
+    .map(|issued| (axum::Extension(ShowsSecret), axum::Json(issued)))
+    .map_err(|e| match e {
+        DbErr::Custom(message) => (StatusCode::BAD_REQUEST, message),
+        e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/{experiment_id}/archive",
+    params(
+        ("experiment_id" = Uuid, Path, description = "Experiment UUID")
+    ),
+    responses(
+        (status = 200, description = "ZIP archive with metadata, uploads, images and derived CSVs", content_type = "application/zip"),
+        (status = 404, description = "Experiment not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "experiments",
+    summary = "Download full experiment archive",
+    description = "Stream a single ZIP containing metadata.json, the original uploads, all camera images and derived CSVs (temperatures, phase transitions, per-well results) for offline archiving or sharing"
+)]
+pub async fn download_experiment_archive(
+    State(state): State<AppState>,
+    Path(experiment_id): Path<Uuid>,
+) -> Result<axum::response::Response, (StatusCode, String)> {
+    let entries = super::services::build_experiment_archive_entries(experiment_id, &state.db)
+        .await
+        .map_err(|e| match e {
+            DbErr::RecordNotFound(_) => (StatusCode::NOT_FOUND, "Experiment not found".to_string()),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to build experiment archive: {e}"),
+            ),
+        })?;
+
+    crate::assets::services::create_archive_streaming_zip_response(
+        entries,
+        &state.config,
+        &format!("experiment_{experiment_id}_archive.zip"),
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/{experiment_id}/excel",
+    params(
+        ("experiment_id" = Uuid, Path, description = "Experiment UUID")
+    ),
+    responses(
+        (status = 200, description = "Regenerated merged.xlsx workbook", content_type = "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"),
+        (status = 404, description = "Experiment not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "experiments",
+    summary = "Export experiment as Excel workbook",
+    description = "Regenerate a merged.xlsx-style workbook from the database with timestamps, probe temperatures, image names and the frozen/liquid state of every well at each reading"
+)]
+pub async fn export_experiment_excel(
+    State(state): State<AppState>,
+    Path(experiment_id): Path<Uuid>,
+) -> Result<axum::response::Response, (StatusCode, String)> {
+    use axum::response::IntoResponse;
+
+    let workbook = super::excel_export::build_experiment_workbook(&state.db, experiment_id)
+        .await
+        .map_err(|e| match e {
+            DbErr::RecordNotFound(_) => (StatusCode::NOT_FOUND, "Experiment not found".to_string()),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to export experiment workbook: {e}"),
+            ),
+        })?;
+
+    Ok((
+        [
+            (
+                axum::http::header::CONTENT_TYPE,
+                "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet".to_string(),
+            ),
+            (
+                axum::http::header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"experiment_{experiment_id}_merged.xlsx\""),
+            ),
+        ],
+        workbook,
+    )
+        .into_response())
+}
+
+#[utoipa::path(
+    get,
+    path = "/{experiment_id}/temperatures",
+    params(
+        ("experiment_id" = Uuid, Path, description = "Experiment UUID"),
+        TemperatureStreamQuery
+    ),
+    responses(
+        (status = 200, description = "One reading per line, oldest first", body = super::models::TemperatureDataWithProbes, content_type = "application/x-ndjson"),
+        (status = 404, description = "Experiment not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "experiments",
+    summary = "Stream temperature readings",
+    description = "Stream the experiment's temperature readings as newline-delimited JSON, each with its probes' temperatures calibrated and as logged, optionally from and to a time. Readings are read as the response is sent, so the whole series is never held in memory"
+)]
+pub async fn stream_experiment_temperatures(
+    State(state): State<AppState>,
+    Path(experiment_id): Path<Uuid>,
+    axum::extract::Query(query): axum::extract::Query<TemperatureStreamQuery>,
+) -> Result<axum::response::Response, (StatusCode, String)> {
+    let probes = super::temperatures::services::experiment_probes(&state.db, experiment_id)
+        .await
+        .map_err(|e| match e {
+            DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+            _ => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })?;
+    let lines = super::temperatures::services::temperature_lines(
+        state.db.clone(),
+        experiment_id,
+        probes,
+        query,
+    );
+
+    Ok(axum::response::Response::builder()
+        .header(axum::http::header::CONTENT_TYPE, "application/x-ndjson")
+        .header("X-Accel-Buffering", "no")
+        .body(axum::body::Body::from_stream(lines))
+        .unwrap())
+}
+
+#[utoipa::path(
+    get,
+    path = "/{experiment_id}/probes/{probe_id}/timeseries",
+    params(
+        ("experiment_id" = Uuid, Path, description = "Experiment UUID"),
+        ("probe_id" = Uuid, Path, description = "Probe ID"),
+        ProbeTimeseriesQuery
+    ),
+    responses(
+        (status = 200, description = "The probe's readings, oldest first", body = ProbeTimeseries),
+        (status = 400, description = "Invalid max_points or time range"),
+        (status = 404, description = "Experiment not found, or the probe is not on its trays"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "experiments",
+    summary = "Get the time series of a probe",
+    description = "Return the readings of one probe of the experiment, calibrated or as logged, optionally from and to a time and averaged down to at most max_points points, without the other probes' temperatures"
+)]
+pub async fn get_probe_timeseries(
+    State(state): State<AppState>,
+    Path((experiment_id, probe_id)): Path<(Uuid, Uuid)>,
+    axum::extract::Query(query): axum::extract::Query<ProbeTimeseriesQuery>,
+) -> Result<Json<ProbeTimeseries>, (StatusCode, String)> {
+    probe_timeseries(
+        &state.db,
+        experiment_id,
+        probe_id,
+        &query,
+        state.config.timescaledb,
+    )
+    .await
+    .map(Json)
+    .map_err(|e| match e {
+        DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+        DbErr::Custom(message) => (StatusCode::BAD_REQUEST, message),
+        _ => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to read the probe's time series: {e}"),
+        ),
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/{experiment_id}/time_points",
+    params(
+        ("experiment_id" = Uuid, Path, description = "Experiment UUID"),
+        TimePointQuery
+    ),
+    responses(
+        (status = 200, description = "A page of time points, oldest first", body = TimePointPage),
+        (status = 400, description = "Invalid limit or time range"),
+        (status = 404, description = "Experiment not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "experiments",
+    summary = "List time points",
+    description = "List the experiment's temperature readings a page at a time, each with its probes' temperatures calibrated and as logged, optionally from and to a time and with the phase of every well at that time, to replay or debug a run"
+)]
+pub async fn get_time_points(
+    State(state): State<AppState>,
+    Path(experiment_id): Path<Uuid>,
+    axum::extract::Query(query): axum::extract::Query<TimePointQuery>,
+) -> Result<Json<TimePointPage>, (StatusCode, String)> {
+    list_time_points(&state.db, experiment_id, &query)
+        .await
+        .map(Json)
+        .map_err(|e| match e {
+            DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+            DbErr::Custom(message) => (StatusCode::BAD_REQUEST, message),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to list time points: {e}"),
+            ),
+        })
+}
+
+#[utoipa::path(
+    get,
+    path = "/{experiment_id}/bundle",
+    params(
+        ("experiment_id" = Uuid, Path, description = "Experiment UUID")
+    ),
+    responses(
+        (status = 200, description = "Self-contained experiment bundle", body = ExperimentBundle),
+        (status = 404, description = "Experiment not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "experiments",
+    summary = "Export experiment bundle",
+    description = "Export the experiment with its regions, tray configuration, treatments, samples and references to uploaded and derived data as a JSON bundle that can be imported into another deployment"
+)]
+pub async fn export_experiment_bundle(
+    State(state): State<AppState>,
+    Path(experiment_id): Path<Uuid>,
+) -> Result<Json<ExperimentBundle>, (StatusCode, String)> {
+    super::bundle::export_experiment_bundle(&state.db, experiment_id)
+        .await
+        .map(Json)
+        .map_err(|e| match e {
+            DbErr::RecordNotFound(_) => (StatusCode::NOT_FOUND, "Experiment not found".to_string()),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to export experiment bundle: {e}"),
+            ),
+        })
+}
+
+#[utoipa::path(
+    post,
+    path = "/import",
+    request_body = ExperimentBundle,
+    responses(
+        (status = 201, description = "Bundle imported with new IDs", body = BundleImportResult),
+        (status = 400, description = "Bundle is invalid or conflicts with existing data"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "experiments",
+    summary = "Import experiment bundle",
+    description = "Recreate an experiment exported from another deployment. All records receive new IDs; the response maps original IDs to the new ones and lists assets that must be uploaded again"
+)]
+pub async fn import_experiment_bundle(
+    State(state): State<AppState>,
+    Json(bundle): Json<ExperimentBundle>,
+) -> Result<(StatusCode, Json<BundleImportResult>), (StatusCode, String)> {
+    super::bundle::import_experiment_bundle(&state.db, bundle)
+        .await
+        .map(|result| (StatusCode::CREATED, Json(result)))
+        .map_err(|e| match e {
+            DbErr::Custom(message) => (StatusCode::BAD_REQUEST, message),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to import experiment bundle: {e}"),
+            ),
+        })
+}
+
+#[utoipa::path(
+    post,
+    path = "/{experiment_id}/clone",
+    params(
+        ("experiment_id" = Uuid, Path, description = "Experiment UUID")
+    ),
+    request_body(content = Option<ExperimentCloneRequest>, description = "Name and run time of the copy"),
+    responses(
+        (status = 201, description = "Copy of the experiment", body = Experiment),
+        (status = 400, description = "The name is taken, or a region holds a treatment of a rejected sample"),
+        (status = 404, description = "Experiment not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "experiments",
+    summary = "Clone an experiment",
+    description = "Create an experiment with the settings, project, tray configuration and regions of this one, to set up a repeat run of the same layout. Readings, results, assets and exclusions are not copied, nor the DOI"
+)]
+pub async fn clone_experiment_handler(
+    State(state): State<AppState>,
+    Path(experiment_id): Path<Uuid>,
+    request: Option<Json<ExperimentCloneRequest>>,
+) -> Result<(StatusCode, Json<Experiment>), (StatusCode, String)> {
+    clone_experiment(
+        &state.db,
+        experiment_id,
+        request.map(|Json(request)| request).unwrap_or_default(),
+    )
+    .await
+    .map(|experiment| (StatusCode::CREATED, Json(experiment)))
+    .map_err(|e| match e {
+        DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+        DbErr::Custom(message) => (StatusCode::BAD_REQUEST, message),
+        _ => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to clone experiment: {e}"),
+        ),
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/{experiment_id}/merge",
+    params(
+        ("experiment_id" = Uuid, Path, description = "UUID of the experiment holding the start of the run")
+    ),
+    request_body = ExperimentMergeRequest,
+    responses(
+        (status = 200, description = "The merged experiment", body = ExperimentMergeResult),
+        (status = 400, description = "The experiments are the same or use different tray configurations"),
+        (status = 403, description = "The user may not delete the source experiment"),
+        (status = 404, description = "Experiment or source experiment not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "experiments",
+    summary = "Merge a split run",
+    description = "Move the readings, phase transitions, assets, exclusions and comments of a run split into two uploads by an instrument restart into the first experiment and delete the second. Readings that do not follow on are shifted to start one reading interval after the first upload's last, and transitions that do not change a well's state are dropped. The experiment's results are built again"
+)]
+pub async fn merge_experiment_handler(
+    State(state): State<AppState>,
+    token: Option<axum::Extension<KeycloakToken<Role>>>,
+    labs: Option<axum::Extension<Labs>>,
+    Path(experiment_id): Path<Uuid>,
+    Json(request): Json<ExperimentMergeRequest>,
+) -> Result<Json<ExperimentMergeResult>, (StatusCode, String)> {
+    // The source is deleted, so the user must be allowed to delete it
+    if !reader(token, labs)
+        .may(
+            &state.db,
+            (
+                Some(ScopedResource::Experiments),
+                TenantResource::Experiments,
+            ),
+            &axum::http::Method::DELETE,
+            request.source_experiment_id,
+        )
+        .await
+    {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "You may not delete the source experiment".to_string(),
+        ));
+    }
+    merge_experiments(&state.db, experiment_id, request)
+        .await
+        .map(Json)
+        .map_err(|e| match e {
+            DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+            DbErr::Custom(message) => (StatusCode::BAD_REQUEST, message),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to merge experiments: {e}"),
+            ),
+        })
+}
+
+#[utoipa::path(
+    post,
+    path = "/{experiment_id}/timelapse",
+    params(
+        ("experiment_id" = Uuid, Path, description = "Experiment UUID")
+    ),
+    request_body = TimelapseRequest,
+    responses(
+        (status = 202, description = "Rendering started; poll the returned asset until processing_status is completed", body = crate::assets::models::Asset),
+        (status = 400, description = "Invalid options or no camera images"),
+        (status = 404, description = "Experiment not found"),
+        (status = 409, description = "A time-lapse in this format is already being rendered"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "experiments",
+    summary = "Render time-lapse video",
+    description = "Start a background job that stitches the experiment's INP_* camera images, stamped with their capture time, into an MP4 or WebM video stored as a derived asset. An existing time-lapse in the same format is replaced"
+)]
+pub async fn create_experiment_timelapse(
+    State(state): State<AppState>,
+    Path(experiment_id): Path<Uuid>,
+    Json(request): Json<TimelapseRequest>,
+) -> Result<(StatusCode, Json<crate::assets::models::Asset>), (StatusCode, String)> {
+    ensure_experiment_exists(&state, experiment_id).await?;
+
+    let existing = super::timelapse::find_timelapse_asset(&state.db, experiment_id, request.format)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if existing.is_some_and(|asset| asset.processing_status.as_deref() == Some("processing")) {
+        return Err((
+            StatusCode::CONFLICT,
+            "A time-lapse in this format is already being rendered".to_string(),
+        ));
+    }
+
+    let asset = super::timelapse::start_timelapse(&state, experiment_id, &request)
+        .await
+        .map_err(|e| match e {
+            DbErr::Custom(message) => (StatusCode::BAD_REQUEST, message),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to start time-lapse: {e}"),
+            ),
+        })?;
+
+    Ok((StatusCode::ACCEPTED, Json(asset.into())))
+}
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+pub struct TimelapseQuery {
+    #[serde(default)]
+    format: TimelapseFormat,
+}
+
+#[utoipa::path(
+    get,
+    path = "/{experiment_id}/timelapse",
+    params(
+        ("experiment_id" = Uuid, Path, description = "Experiment UUID"),
+        TimelapseQuery
+    ),
+    responses(
+        (status = 200, description = "Time-lapse video", content_type = "video/mp4"),
+        (status = 404, description = "No time-lapse has been rendered in this format"),
+        (status = 409, description = "The time-lapse is still rendering or failed"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "experiments",
+    summary = "Download time-lapse video",
+    description = "Download the experiment's rendered time-lapse video (format=mp4 or webm)"
+)]
+pub async fn download_experiment_timelapse(
+    State(state): State<AppState>,
+    Path(experiment_id): Path<Uuid>,
+    axum::extract::Query(query): axum::extract::Query<TimelapseQuery>,
+) -> Result<axum::response::Response, (StatusCode, String)> {
+    use axum::response::IntoResponse;
+
+    let asset = super::timelapse::find_timelapse_asset(&state.db, experiment_id, query.format)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((
+            StatusCode::NOT_FOUND,
+            "No time-lapse has been rendered in this format".to_string(),
+        ))?;
+    if asset.processing_status.as_deref() != Some("completed") {
+        return Err((
+            StatusCode::CONFLICT,
+            asset
+                .processing_message
+                .unwrap_or_else(|| "Time-lapse is not ready".to_string()),
+        ));
+    }
+
+    let video = crate::external::s3::get_object_from_s3(asset.storage_key(), &state.config)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    Ok((
+        [
+            (
+                axum::http::header::CONTENT_TYPE,
+                query.format.content_type().to_string(),
+            ),
+            (
+                axum::http::header::CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"experiment_{experiment_id}_{}\"",
+                    query.format.filename()
+                ),
+            ),
+        ],
+        video,
+    )
+        .into_response())
+}
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+pub struct WellsQuery {
+    /// Only the wells at this coordinate, such as A1 on every tray or P1:A1
+    coordinate: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/{experiment_id}/wells",
+    params(
+        ("experiment_id" = Uuid, Path, description = "Experiment UUID"),
+        WellsQuery
+    ),
+    responses(
+        (status = 200, description = "Wells of the experiment's trays, by tray, row and column", body = Vec<WellLocation>),
+        (status = 400, description = "Invalid coordinate"),
+        (status = 404, description = "Experiment not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "experiments",
+    summary = "List wells",
+    description = "List the well records of the trays of the experiment's configuration with their IDs, trays and coordinates, optionally only those at one coordinate"
+)]
+pub async fn get_wells(
+    State(state): State<AppState>,
+    Path(experiment_id): Path<Uuid>,
+    axum::extract::Query(query): axum::extract::Query<WellsQuery>,
+) -> Result<Json<Vec<WellLocation>>, (StatusCode, String)> {
+    experiment_wells(&state.db, experiment_id, query.coordinate.as_deref())
+        .await
+        .map(Json)
+        .map_err(|e| match e {
+            DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+            DbErr::Custom(message) => (StatusCode::BAD_REQUEST, message),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to list wells: {e}"),
+            ),
+        })
+}
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+pub struct WellImageQuery {
+    /// Use the camera frame captured closest to this time. Defaults to the
+    /// well's first freeze, or the last frame if it never froze.
+    timestamp: Option<chrono::DateTime<chrono::Utc>>,
+    /// Side of the square crop in pixels, 16 to 1024 (default: about three
+    /// well diameters)
+    size: Option<u32>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/{experiment_id}/wells/{coordinate}/image",
+    params(
+        ("experiment_id" = Uuid, Path, description = "Experiment UUID"),
+        ("coordinate" = String, Path, description = "Well coordinate such as P1:A1; the tray can be left out if the configuration has one tray"),
+        WellImageQuery
+    ),
+    responses(
+        (status = 200, description = "PNG crop of the camera frame around the well; the frame is named in Content-Disposition", content_type = "image/png"),
+        (status = 400, description = "Invalid coordinate or size, or the tray cannot be placed on the images"),
+        (status = 404, description = "Experiment, tray, well or camera images not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "experiments",
+    summary = "Crop a camera frame around a well",
+    description = "Return the region around one well of the INP_* camera frame nearest to the given time, located with the tray well grid, to verify a detected freeze by eye"
+)]
+pub async fn get_well_image(
+    State(state): State<AppState>,
+    Path((experiment_id, coordinate)): Path<(Uuid, String)>,
+    axum::extract::Query(query): axum::extract::Query<WellImageQuery>,
+) -> Result<axum::response::Response, (StatusCode, String)> {
+    use axum::response::IntoResponse;
+
+    let crop = super::well_image::crop_well_image(
+        &state,
+        experiment_id,
+        &coordinate,
+        query.timestamp,
+        query.size,
+    )
+    .await
+    .map_err(|e| match e {
+        DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+        DbErr::Custom(message) => (StatusCode::BAD_REQUEST, message),
+        _ => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to crop well image: {e}"),
+        ),
+    })?;
+
+    let frame = std::path::Path::new(&crop.filename)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("frame");
+    Ok((
+        [
+            (axum::http::header::CONTENT_TYPE, "image/png".to_string()),
+            (
+                axum::http::header::CONTENT_DISPOSITION,
+                format!(
+                    "inline; filename=\"{}_{frame}.png\"",
+                    coordinate.replace(':', "_")
+                ),
+            ),
+            (
+                axum::http::header::LAST_MODIFIED,
+                crop.captured_at
+                    .and_utc()
+                    .format("%a, %d %b %Y %H:%M:%S GMT")
+                    .to_string(),
+            ),
+        ],
+        crop.png,
+    )
+        .into_response())
+}
+
+#[utoipa::path(
+    get,
+    path = "/{experiment_id}/excluded-wells",
+    params(
+        ("experiment_id" = Uuid, Path, description = "Experiment UUID")
+    ),
+    responses(
+        (status = 200, description = "Wells left out of the experiment's statistics", body = Vec<ExcludedWell>),
+        (status = 404, description = "Experiment not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "experiments",
+    summary = "List excluded wells",
+    description = "Wells marked as contaminated, empty or otherwise unusable. Their data is kept, but they do not count towards the frozen fraction or the nucleation statistics"
+)]
+pub async fn get_excluded_wells(
+    State(state): State<AppState>,
+    Path(experiment_id): Path<Uuid>,
+) -> Result<Json<Vec<ExcludedWell>>, (StatusCode, String)> {
+    super::exclusions::list_excluded_wells(&state.db, experiment_id)
+        .await
+        .map(Json)
+        .map_err(|e| match e {
+            DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to load excluded wells: {e}"),
+            ),
+        })
+}
+
+#[utoipa::path(
+    put,
+    path = "/{experiment_id}/excluded-wells",
+    params(
+        ("experiment_id" = Uuid, Path, description = "Experiment UUID")
+    ),
+    request_body = ExcludedWellsUpdate,
+    responses(
+        (status = 200, description = "The excluded wells after the update", body = Vec<ExcludedWell>),
+        (status = 400, description = "Invalid coordinate, or a well that is not on the experiment's trays"),
+        (status = 404, description = "Experiment not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "experiments",
+    summary = "Set excluded wells",
+    description = "Replace the list of wells left out of the experiment's frozen fraction and nucleation statistics. An empty list includes every well again"
+)]
+pub async fn set_excluded_wells(
+    State(state): State<AppState>,
+    Path(experiment_id): Path<Uuid>,
+    Json(update): Json<ExcludedWellsUpdate>,
+) -> Result<Json<Vec<ExcludedWell>>, (StatusCode, String)> {
+    super::exclusions::set_excluded_wells(&state.db, experiment_id, &update)
+        .await
+        .map(Json)
+        .map_err(|e| match e {
+            DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+            DbErr::Custom(message) => (StatusCode::BAD_REQUEST, message),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to set excluded wells: {e}"),
+            ),
+        })
+}
+
+fn comment_error(e: DbErr) -> (StatusCode, String) {
+    match e {
+        DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+        DbErr::Custom(message) => (StatusCode::BAD_REQUEST, message),
+        _ => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to change comments: {e}"),
+        ),
+    }
+}
+
+/// Check that the user wrote the comment; administrators change any
+async fn check_comment_author(
+    db: &DatabaseConnection,
+    reader: &Reader,
+    experiment_id: Uuid,
+    comment_id: Uuid,
+) -> Result<(), (StatusCode, String)> {
+    let comment = get_comment(db, experiment_id, comment_id)
+        .await
+        .map_err(comment_error)?;
+    if let Some((username, _)) = &reader.member
+        && comment.created_by.as_ref() != Some(username)
+    {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Comments are changed only by their author".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    get,
+    path = "/{experiment_id}/comments",
+    params(
+        ("experiment_id" = Uuid, Path, description = "Experiment UUID"),
+        ExperimentCommentQuery
+    ),
+    responses(
+        (status = 200, description = "Comment threads, oldest first, each with its replies", body = Vec<ExperimentComment>),
+        (status = 400, description = "Invalid coordinate"),
+        (status = 404, description = "Experiment not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "experiments",
+    summary = "List comments",
+    description = "List the threads of comments on the experiment and its wells, optionally only those on a well or mentioning a user. The threads are also embedded in the experiment unless left out with `?include=`"
+)]
+pub async fn get_comments(
+    State(state): State<AppState>,
+    Path(experiment_id): Path<Uuid>,
+    axum::extract::Query(query): axum::extract::Query<ExperimentCommentQuery>,
+) -> Result<Json<Vec<ExperimentComment>>, (StatusCode, String)> {
+    list_comments(&state.db, experiment_id, &query)
+        .await
+        .map(Json)
+        .map_err(comment_error)
+}
+
+#[utoipa::path(
+    post,
+    path = "/{experiment_id}/comments",
+    params(
+        ("experiment_id" = Uuid, Path, description = "Experiment UUID")
+    ),
+    request_body = ExperimentCommentCreate,
+    responses(
+        (status = 201, description = "The comment", body = ExperimentComment),
+        (status = 400, description = "Empty comment, a coordinate that is not of the experiment's wells, or a parent of another experiment"),
+        (status = 404, description = "Experiment not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "experiments",
+    summary = "Comment on an experiment",
+    description = "Comment on the experiment or one of its wells, or reply to a comment. Users are mentioned as `@username`, and the usernames mentioned are kept with the comment"
+)]
+pub async fn post_comment(
+    State(state): State<AppState>,
+    Path(experiment_id): Path<Uuid>,
+    Json(input): Json<ExperimentCommentCreate>,
+) -> Result<(StatusCode, Json<ExperimentComment>), (StatusCode, String)> {
+    create_comment(&state.db, experiment_id, input)
+        .await
+        .map(|comment| (StatusCode::CREATED, Json(comment)))
+        .map_err(comment_error)
+}
+
+#[utoipa::path(
+    patch,
+    path = "/{experiment_id}/comments/{comment_id}",
+    params(
+        ("experiment_id" = Uuid, Path, description = "Experiment UUID"),
+        ("comment_id" = Uuid, Path, description = "Comment UUID")
+    ),
+    request_body = ExperimentCommentUpdate,
+    responses(
+        (status = 200, description = "The changed comment", body = ExperimentComment),
+        (status = 400, description = "Empty comment"),
+        (status = 403, description = "The comment is of another user"),
+        (status = 404, description = "Experiment or comment not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "experiments",
+    summary = "Edit a comment",
+    description = "Change the text of a comment, and with it the users it mentions. Comments are changed by their author or by administrators"
+)]
+pub async fn patch_comment(
+    State(state): State<AppState>,
+    token: Option<axum::Extension<KeycloakToken<Role>>>,
+    labs: Option<axum::Extension<Labs>>,
+    Path((experiment_id, comment_id)): Path<(Uuid, Uuid)>,
+    Json(input): Json<ExperimentCommentUpdate>,
+) -> Result<Json<ExperimentComment>, (StatusCode, String)> {
+    check_comment_author(&state.db, &reader(token, labs), experiment_id, comment_id).await?;
+    update_comment(&state.db, experiment_id, comment_id, input)
+        .await
+        .map(Json)
+        .map_err(comment_error)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/{experiment_id}/comments/{comment_id}",
+    params(
+        ("experiment_id" = Uuid, Path, description = "Experiment UUID"),
+        ("comment_id" = Uuid, Path, description = "Comment UUID")
+    ),
+    responses(
+        (status = 204, description = "Comment deleted"),
+        (status = 403, description = "The comment is of another user"),
+        (status = 404, description = "Experiment or comment not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "experiments",
+    summary = "Delete a comment",
+    description = "Delete a comment, with its replies when it starts a thread. Comments are deleted by their author or by administrators"
+)]
+pub async fn delete_comment_handler(
+    State(state): State<AppState>,
+    token: Option<axum::Extension<KeycloakToken<Role>>>,
+    labs: Option<axum::Extension<Labs>>,
+    Path((experiment_id, comment_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    check_comment_author(&state.db, &reader(token, labs), experiment_id, comment_id).await?;
+    delete_comment(&state.db, experiment_id, comment_id)
+        .await
+        .map(|()| StatusCode::NO_CONTENT)
+        .map_err(comment_error)
+}
+
+fn region_error(e: DbErr) -> (StatusCode, String) {
+    match e {
+        DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+        DbErr::Custom(message) => (StatusCode::BAD_REQUEST, message),
+        _ => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to change regions: {e}"),
+        ),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/{experiment_id}/regions",
+    params(
+        ("experiment_id" = Uuid, Path, description = "Experiment UUID")
+    ),
+    responses(
+        (status = 200, description = "Regions of the experiment, by tray and position", body = Vec<Region>),
+        (status = 404, description = "Experiment not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "experiments",
+    summary = "List regions",
+    description = "List the regions of the experiment with their treatments and samples"
+)]
+pub async fn get_regions(
+    State(state): State<AppState>,
+    Path(experiment_id): Path<Uuid>,
+) -> Result<Json<Vec<Region>>, (StatusCode, String)> {
+    list_regions(&state.db, experiment_id)
+        .await
+        .map(Json)
+        .map_err(region_error)
+}
+
+#[utoipa::path(
+    post,
+    path = "/{experiment_id}/regions",
+    params(
+        ("experiment_id" = Uuid, Path, description = "Experiment UUID")
+    ),
+    request_body = RegionCreate,
+    responses(
+        (status = 201, description = "Region added", body = Region),
+        (status = 400, description = "The region is off its tray, overlaps another, or holds a treatment of a rejected sample"),
+        (status = 404, description = "Experiment not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "experiments",
+    summary = "Add a region",
+    description = "Add a region to the experiment, checked with its other regions against its trays as when the experiment is saved. The experiment's kept results are dropped, and built again on the next read"
+)]
+pub async fn post_region(
+    State(state): State<AppState>,
+    Path(experiment_id): Path<Uuid>,
+    Json(data): Json<RegionCreate>,
+) -> Result<(StatusCode, Json<Region>), (StatusCode, String)> {
+    create_region(&state.db, experiment_id, data)
+        .await
+        .map(|region| (StatusCode::CREATED, Json(region)))
+        .map_err(region_error)
+}
+
+#[utoipa::path(
+    put,
+    path = "/{experiment_id}/regions/{region_id}",
+    params(
+        ("experiment_id" = Uuid, Path, description = "Experiment UUID"),
+        ("region_id" = Uuid, Path, description = "Region UUID")
+    ),
+    request_body = RegionUpdate,
+    responses(
+        (status = 200, description = "Region updated", body = Region),
+        (status = 400, description = "The region is off its tray, overlaps another, or holds a treatment of a rejected sample"),
+        (status = 404, description = "Experiment or region not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "experiments",
+    summary = "Update a region",
+    description = "Change the fields given of a region of the experiment, checked with its other regions. The experiment's kept results are dropped"
+)]
+pub async fn put_region(
+    State(state): State<AppState>,
+    Path((experiment_id, region_id)): Path<(Uuid, Uuid)>,
+    Json(data): Json<RegionUpdate>,
+) -> Result<Json<Region>, (StatusCode, String)> {
+    update_region(&state.db, experiment_id, region_id, data)
+        .await
+        .map(Json)
+        .map_err(region_error)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/{experiment_id}/regions/{region_id}",
+    params(
+        ("experiment_id" = Uuid, Path, description = "Experiment UUID"),
+        ("region_id" = Uuid, Path, description = "Region UUID")
+    ),
+    responses(
+        (status = 204, description = "Region removed"),
+        (status = 404, description = "Experiment or region not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "experiments",
+    summary = "Remove a region",
+    description = "Remove a region of the experiment; its wells no longer count towards a treatment. The experiment's kept results are dropped"
+)]
+pub async fn delete_region_handler(
+    State(state): State<AppState>,
+    Path((experiment_id, region_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    delete_region(&state.db, experiment_id, region_id)
+        .await
+        .map(|()| StatusCode::NO_CONTENT)
+        .map_err(region_error)
+}
+
+#[utoipa::path(
+    post,
+    path = "/{experiment_id}/results/recompute",
+    params(
+        ("experiment_id" = Uuid, Path, description = "Experiment UUID")
+    ),
+    responses(
+        (status = 200, description = "The experiment's results, as now kept", body = ExperimentResultsResponse),
+        (status = 404, description = "Experiment not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "experiments",
+    summary = "Recompute experiment results",
+    description = "Build the experiment's results from its current transitions and keep them for reads of the experiment, with the freezing results of its wells, as after transitions were edited outside the API. Results are otherwise kept when processing finishes"
+)]
+pub async fn recompute_experiment_results(
+    State(state): State<AppState>,
+    Path(experiment_id): Path<Uuid>,
+) -> Result<Json<ExperimentResultsResponse>, (StatusCode, String)> {
+    super::summaries::recompute_results_summary(&state.db, experiment_id, None)
+        .await
+        .map(Json)
+        .map_err(|e| match e {
+            DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to recompute results: {e}"),
+            ),
+        })
+}
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+pub struct FrameNavigationQuery {
+    /// Reference time. Without it, `previous` gives the last frame and
+    /// `next` and `nearest` the first.
+    timestamp: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/{experiment_id}/frames/{direction}",
+    params(
+        ("experiment_id" = Uuid, Path, description = "Experiment UUID"),
+        ("direction" = FrameDirection, Path, description = "previous, next or nearest"),
+        FrameNavigationQuery
+    ),
+    responses(
+        (status = 200, description = "The selected camera frame with its neighbours", body = FrameNavigation),
+        (status = 400, description = "Invalid direction or timestamp"),
+        (status = 404, description = "Experiment not found, or no camera frame in that direction"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "experiments",
+    summary = "Step through camera frames",
+    description = "Return the INP_* camera image captured before (previous), after (next) or closest to (nearest) a timestamp, with its position in the sequence and the frames either side of it, so a player can step frame by frame without listing every asset"
+)]
+pub async fn navigate_camera_frames(
+    State(state): State<AppState>,
+    Path((experiment_id, direction)): Path<(Uuid, FrameDirection)>,
+    axum::extract::Query(query): axum::extract::Query<FrameNavigationQuery>,
+) -> Result<Json<FrameNavigation>, (StatusCode, String)> {
+    super::frames::navigate_frames(&state.db, experiment_id, query.timestamp, direction)
+        .await
+        .map(Json)
+        .map_err(|e| match e {
+            DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to find camera frame: {e}"),
+            ),
+        })
+}
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+pub struct ImageDiffQuery {
+    /// Compare the camera frame captured closest to this time...
+    from: chrono::DateTime<chrono::Utc>,
+    /// ...with the one captured closest to this time
+    to: chrono::DateTime<chrono::Utc>,
+    /// Smallest change of a well's mean brightness, in grey levels from 1 to
+    /// 255, for it to count as changed (default 12, as in freeze detection)
+    threshold: Option<f64>,
+    #[serde(default)]
+    format: ImageDiffFormat,
+}
+
+#[utoipa::path(
+    get,
+    path = "/{experiment_id}/image-diff",
+    params(
+        ("experiment_id" = Uuid, Path, description = "Experiment UUID"),
+        ImageDiffQuery
+    ),
+    responses(
+        (status = 200, description = "format=png: difference image with the changed wells circled. format=json: per-well change scores", body = ImageDiff),
+        (status = 400, description = "Invalid threshold, both times select the same frame, or the frames differ in size"),
+        (status = 404, description = "Experiment or camera images not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "experiments",
+    summary = "Compare two camera frames",
+    description = "Difference between the INP_* camera frames nearest to two times: the absolute grey level change of every pixel, and the change of each well's mean brightness as sampled by freeze detection. Shows which wells froze between the frames and which a detection threshold would pick up"
+)]
+pub async fn get_image_diff(
+    State(state): State<AppState>,
+    Path(experiment_id): Path<Uuid>,
+    axum::extract::Query(query): axum::extract::Query<ImageDiffQuery>,
+) -> Result<axum::response::Response, (StatusCode, String)> {
+    use axum::response::IntoResponse;
+
+    let mut diff = super::image_diff::diff_frames(
+        &state,
+        experiment_id,
+        query.from,
+        query.to,
+        query.threshold,
+        query.format,
+    )
+    .await
+    .map_err(|e| match e {
+        DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+        DbErr::Custom(message) => (StatusCode::BAD_REQUEST, message),
+        _ => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to compare camera frames: {e}"),
+        ),
+    })?;
+
+    let Some(png) = diff.png.take() else {
+        return Ok(Json(diff).into_response());
+    };
+    let stem = |filename: &str| {
+        std::path::Path::new(filename)
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or("frame")
+            .to_string()
+    };
+    Ok((
+        [
+            (axum::http::header::CONTENT_TYPE, "image/png".to_string()),
+            (
+                axum::http::header::CONTENT_DISPOSITION,
+                format!(
+                    "inline; filename=\"{}_vs_{}.png\"",
+                    stem(&diff.from_filename),
+                    stem(&diff.to_filename)
+                ),
+            ),
+        ],
+        png,
+    )
+        .into_response())
+}
+
+#[utoipa::path(
+    post,
+    path = "/{experiment_id}/detect-freezing",
+    params(
+        ("experiment_id" = Uuid, Path, description = "Experiment UUID")
+    ),
+    request_body = FreezeDetectionRequest,
+    responses(
+        (status = 200, description = "Per-well freezing detected from the images, compared with the spreadsheet", body = FreezeDetectionResult),
+        (status = 400, description = "Invalid options, no tray configuration, no locatable wells or no camera images"),
+        (status = 404, description = "Experiment not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "experiments",
+    summary = "Detect freezing from camera images",
+    description = "Sample each well's brightness in the INP_* camera images using the tray well grid and detect the frame where it freezes. mode=cross_check compares the result with the spreadsheet-derived phase transitions; mode=replace stores the detected transitions in their place"
+)]
+pub async fn detect_experiment_freezing(
+    State(state): State<AppState>,
+    Path(experiment_id): Path<Uuid>,
+    Json(request): Json<FreezeDetectionRequest>,
+) -> Result<Json<FreezeDetectionResult>, (StatusCode, String)> {
+    super::image_freeze::detect_freezing(&state, experiment_id, &request)
+        .await
+        .map(Json)
+        .map_err(|e| match e {
+            DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+            DbErr::Custom(message) => (StatusCode::BAD_REQUEST, message),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to detect freezing: {e}"),
+            ),
+        })
+}
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+pub struct IntegrityQuery {
+    /// Store the checksum of assets that have none recorded
+    #[serde(default)]
+    record_missing: bool,
+}
+
+#[utoipa::path(
+    get,
+    path = "/{experiment_id}/integrity",
+    params(
+        ("experiment_id" = Uuid, Path, description = "Experiment UUID"),
+        IntegrityQuery
+    ),
+    responses(
+        (status = 200, description = "Integrity report for the experiment's assets", body = ExperimentIntegrityReport),
+        (status = 404, description = "Experiment not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "experiments",
+    summary = "Verify experiment assets",
+    description = "Re-read every asset of the experiment from S3 and compare it with the SHA-256 checksum recorded at upload, listing corrupted, missing and unverified objects"
+)]
+pub async fn verify_experiment_integrity(
+    State(state): State<AppState>,
+    Path(experiment_id): Path<Uuid>,
+    axum::extract::Query(query): axum::extract::Query<IntegrityQuery>,
+) -> Result<Json<ExperimentIntegrityReport>, (StatusCode, String)> {
+    ensure_experiment_exists(&state, experiment_id).await?;
+
+    crate::assets::integrity::verify_experiment_assets(
+        &state.db,
+        &state.config,
+        experiment_id,
+        query.record_missing,
+    )
+    .await
+    .map(Json)
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/{experiment_id}/datacite",
+    params(
+        ("experiment_id" = Uuid, Path, description = "Experiment UUID")
+    ),
+    responses(
+        (status = 200, description = "DataCite metadata for the experiment", body = DataCiteMetadata),
+        (status = 404, description = "Experiment not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "experiments",
+    summary = "Get DataCite metadata",
+    description = "Assemble DataCite (schema 4) metadata describing the experiment as a dataset, including creators, sampling dates and sample locations"
+)]
+pub async fn get_experiment_datacite(
+    State(state): State<AppState>,
+    Path(experiment_id): Path<Uuid>,
+) -> Result<Json<DataCiteMetadata>, (StatusCode, String)> {
+    crate::services::datacite_service::experiment_metadata(
+        &state.db,
+        experiment_id,
+        &state.config.datacite_publisher,
+    )
+    .await
+    .map(Json)
+    .map_err(|e| match e {
+        DbErr::RecordNotFound(_) => (StatusCode::NOT_FOUND, "Experiment not found".to_string()),
+        _ => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to build DataCite metadata: {e}"),
+        ),
+    })
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ZenodoDepositionResponse {
+    pub experiment_id: Uuid,
+    pub doi: String,
+    pub zenodo_deposition_id: i64,
+    pub zenodo_url: Option<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/{experiment_id}/zenodo-deposition",
+    params(
+        ("experiment_id" = Uuid, Path, description = "Experiment UUID")
+    ),
+    responses(
+        (status = 201, description = "Draft deposition created and DOI stored on the experiment", body = ZenodoDepositionResponse),
+        (status = 404, description = "Experiment not found"),
+        (status = 409, description = "Experiment already has a DOI"),
+        (status = 502, description = "Zenodo rejected the deposition"),
+        (status = 503, description = "Zenodo integration is not configured"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "experiments",
+    summary = "Create Zenodo deposition",
+    description = "Create a draft Zenodo deposition from the experiment's DataCite metadata and store the reserved DOI on the experiment. Files are attached and the deposition published on Zenodo"
+)]
+pub async fn create_zenodo_deposition(
+    State(state): State<AppState>,
+    Path(experiment_id): Path<Uuid>,
+) -> Result<(StatusCode, Json<ZenodoDepositionResponse>), (StatusCode, String)> {
+    let experiment = super::models::Entity::find_by_id(experiment_id)
+        .one(&state.db)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Experiment not found".to_string()))?;
+
+    if let Some(doi) = &experiment.doi {
+        return Err((
+            StatusCode::CONFLICT,
+            format!("Experiment already has DOI {doi}"),
+        ));
+    }
+    if state.config.zenodo_access_token.is_none() {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Zenodo integration is not configured".to_string(),
+        ));
+    }
+
+    let Json(metadata) = get_experiment_datacite(State(state.clone()), Path(experiment_id)).await?;
+    let deposition = crate::external::zenodo::create_deposition(&state.config, &metadata)
+        .await
+        .map_err(|e| (StatusCode::BAD_GATEWAY, e))?;
+
+    let mut active: super::models::ActiveModel = experiment.into();
+    active.doi = Set(Some(deposition.doi.clone()));
+    active.last_updated = Set(chrono::Utc::now());
+    active.update(&state.db).await.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!(
+                "Zenodo deposition {} was created but storing DOI {} failed: {e}",
+                deposition.id, deposition.doi
+            ),
+        )
+    })?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(ZenodoDepositionResponse {
+            experiment_id,
+            doi: deposition.doi,
+            zenodo_deposition_id: deposition.id,
+            zenodo_url: deposition.html_url,
+        }),
+    ))
+}
+
+/// Asset whose data is processed or cleared
+#[derive(Serialize, serde::Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(example = json!({"assetId": "5b0d7a3e-8c1f-4e57-9a5d-2f3c6e1b4a90", "background": true}))]
+pub struct ProcessAssetRequest {
+    /// The experiment's data file, such as its `merged.xlsx`
+    pub asset_id: Uuid,
+    /// Return at once, processing the file in the background
+    #[serde(default)]
+    pub background: bool,
+}
+
+/// Outcome of a request to process an asset
+#[derive(Serialize, serde::Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(example = json!({"success": true, "jobId": "0c6f1d2e-3b4a-4f5e-8d7c-9a0b1c2d3e4f", "message": "Processing started"}))]
+pub struct ProcessAssetResponse {
+    pub success: bool,
+    pub message: String,
+    /// Job to follow the processing by, when it runs in the background
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub job_id: Option<Uuid>,
+    /// What the processing wrote, once it is done
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<ExcelProcessingResult>,
+}
+
+/// Outcome of a request to clear an experiment's results
+#[derive(Serialize, serde::Deserialize, ToSchema)]
+#[schema(example = json!({"success": true, "message": "Experiment results cleared successfully"}))]
+pub struct ClearResultsResponse {
+    pub success: bool,
+    pub message: String,
+}
+
+/// The asset a request is about, or 400
+fn requested_asset(
+    payload: serde_json::Value,
+) -> Result<ProcessAssetRequest, (StatusCode, String)> {
+    serde_json::from_value(payload).map_err(|_| {
+        (
+            StatusCode::BAD_REQUEST,
+            "Missing or invalid assetId".to_string(),
+        )
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/{experiment_id}/process-asset",
+    params(
+        ("experiment_id" = Uuid, Path, description = "Experiment UUID")
+    ),
+    request_body = ProcessAssetRequest,
+    responses(
+        (status = 200, description = "Asset processing completed successfully", body = ProcessAssetResponse),
+        (status = 202, description = "Asset processing started in the background, with the `jobId` to follow it by", body = ProcessAssetResponse),
+        (status = 400, description = "Bad request"),
+        (status = 404, description = "Experiment not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "experiments",
+    summary = "Process asset data",
+    description = "Process uploaded asset data for an experiment (Excel files, images, etc.). With `\"background\": true` the request returns at once, and the processing's progress is followed at `/{experiment_id}/process-status/{job_id}` and its `/stream`"
+)]
+#[allow(clippy::too_many_lines)]
+pub async fn process_asset_data(
+    State(app_state): State<AppState>,
+    Path(experiment_id): Path<Uuid>,
+    Json(payload): Json<serde_json::Value>,
+) -> Result<(StatusCode, Json<ProcessAssetResponse>), (StatusCode, String)> {
+    use sea_orm::Set;
+
+    let ProcessAssetRequest {
+        asset_id,
+        background,
+    } = requested_asset(payload)?;
+
+    // Find the asset
+    let asset = s3_assets::Entity::find_by_id(asset_id)
+        .one(&app_state.db)
         .await
         .map_err(|e| {
             (
@@ -575,14 +2592,15 @@ pub async fn process_asset_data(
         })?;
 
     // Download the file from S3 to get bytes for processing (uses mock for tests)
-    let file_bytes = crate::external::s3::get_object_from_s3(&asset.s3_key, &app_state.config)
-        .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to download from S3: {e}"),
-            )
-        })?;
+    let file_bytes =
+        crate::external::s3::get_object_from_s3(asset.storage_key(), &app_state.config)
+            .await
+            .map_err(|e| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Failed to download from S3: {e}"),
+                )
+            })?;
 
     // Validate file can be processed - only allow Excel files with appropriate names
     let filename = asset.original_filename.to_lowercase();
@@ -602,6 +2620,7 @@ pub async fn process_asset_data(
         let update_asset = s3_assets::ActiveModel {
             id: Set(asset_id),
             processing_status: Set(Some("error".to_string())),
+            last_updated: Set(chrono::Utc::now()),
             processing_message: Set(Some(error_message.clone())),
             ..Default::default()
         };
@@ -626,6 +2645,7 @@ pub async fn process_asset_data(
         let update_asset = s3_assets::ActiveModel {
             id: Set(asset_id),
             processing_status: Set(Some("error".to_string())),
+            last_updated: Set(chrono::Utc::now()),
             processing_message: Set(Some(error_message.clone())),
             ..Default::default()
         };
@@ -662,12 +2682,52 @@ pub async fn process_asset_data(
         .exec(&app_state.db)
         .await;
 
-    // Process the Excel file
-    match app_state
+    // Process the Excel file, in the background when asked to, so its
+    // progress can be followed at the job's process-status
+    let job = app_state.data_processing_service.jobs.start(experiment_id);
+    let job_id = job.job_id();
+    if background {
+        let app_state = app_state.clone();
+        tokio::spawn(async move {
+            let outcome = app_state
+                .data_processing_service
+                .process_excel_file_in_job(&job, experiment_id, file_bytes)
+                .await;
+            app_state.response_cache.clear();
+            if let Err((_, message)) =
+                record_processing_outcome(&app_state.db, asset_id, outcome).await
+            {
+                tracing::warn!("Processing job {job_id} failed: {message}");
+            }
+        });
+        return Ok((
+            StatusCode::ACCEPTED,
+            Json(ProcessAssetResponse {
+                success: true,
+                message: "Processing started".to_string(),
+                job_id: Some(job_id),
+                result: None,
+            }),
+        ));
+    }
+
+    let outcome = app_state
         .data_processing_service
-        .process_excel_file(experiment_id, file_bytes)
+        .process_excel_file_in_job(&job, experiment_id, file_bytes)
+        .await;
+    record_processing_outcome(&app_state.db, asset_id, outcome)
         .await
-    {
+        .map(|json| (StatusCode::OK, json))
+}
+
+/// Mark an asset with how its processing went, giving the response to the
+/// request that processed it
+async fn record_processing_outcome(
+    db: &DatabaseConnection,
+    asset_id: Uuid,
+    outcome: anyhow::Result<ExcelProcessingResult>,
+) -> Result<Json<ProcessAssetResponse>, (StatusCode, String)> {
+    match outcome {
         Ok(result) => {
             // Check if processing actually succeeded by looking at the result status
             if matches!(result.status, ProcessingStatus::Completed)
@@ -682,18 +2742,18 @@ pub async fn process_asset_data(
                 let update_asset = s3_assets::ActiveModel {
                     id: Set(asset_id),
                     processing_status: Set(Some("completed".to_string())),
+                    last_updated: Set(chrono::Utc::now()),
                     processing_message: Set(Some(success_message.clone())),
                     ..Default::default()
                 };
-                let _ = s3_assets::Entity::update(update_asset)
-                    .exec(&app_state.db)
-                    .await;
-
-                Ok(Json(serde_json::json!({
-                    "success": true,
-                    "message": success_message,
-                    "result": result
-                })))
+                let _ = s3_assets::Entity::update(update_asset).exec(db).await;
+
+                Ok(Json(ProcessAssetResponse {
+                    success: true,
+                    message: success_message,
+                    job_id: None,
+                    result: Some(result),
+                }))
             } else {
                 // Processing technically succeeded but with errors or no data
                 let error_message = result.error.unwrap_or_else(|| {
@@ -708,12 +2768,11 @@ pub async fn process_asset_data(
                 let update_asset = s3_assets::ActiveModel {
                     id: Set(asset_id),
                     processing_status: Set(Some("error".to_string())),
+                    last_updated: Set(chrono::Utc::now()),
                     processing_message: Set(Some(error_message.clone())),
                     ..Default::default()
                 };
-                let _ = s3_assets::Entity::update(update_asset)
-                    .exec(&app_state.db)
-                    .await;
+                let _ = s3_assets::Entity::update(update_asset).exec(db).await;
 
                 Err((StatusCode::UNPROCESSABLE_ENTITY, error_message))
             }
@@ -725,27 +2784,111 @@ pub async fn process_asset_data(
             let update_asset = s3_assets::ActiveModel {
                 id: Set(asset_id),
                 processing_status: Set(Some("error".to_string())),
+                last_updated: Set(chrono::Utc::now()),
                 processing_message: Set(Some(error_message.clone())),
                 ..Default::default()
             };
-            let _ = s3_assets::Entity::update(update_asset)
-                .exec(&app_state.db)
-                .await;
+            let _ = s3_assets::Entity::update(update_asset).exec(db).await;
 
             Err((StatusCode::INTERNAL_SERVER_ERROR, error_message))
         }
     }
 }
 
+/// Progress of a processing job of an experiment, or 404 when the job is
+/// unknown, has been forgotten, or processes another experiment
+fn processing_job(
+    app_state: &AppState,
+    experiment_id: Uuid,
+    job_id: Uuid,
+) -> Result<tokio::sync::watch::Receiver<ProcessingProgress>, (StatusCode, String)> {
+    app_state
+        .data_processing_service
+        .jobs
+        .subscribe(job_id)
+        .filter(|progress| progress.borrow().experiment_id == experiment_id)
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                format!("Processing job {job_id} not found"),
+            )
+        })
+}
+
+#[utoipa::path(
+    get,
+    path = "/{experiment_id}/process-status/{job_id}",
+    params(
+        ("experiment_id" = Uuid, Path, description = "Experiment UUID"),
+        ("job_id" = Uuid, Path, description = "Processing job ID")
+    ),
+    responses(
+        (status = 200, description = "Progress of the processing", body = ProcessingProgress),
+        (status = 404, description = "Processing job not found")
+    ),
+    tag = "experiments",
+    summary = "Get processing progress",
+    description = "Get the rows parsed, records written and percent complete of an experiment's data file processing. Jobs are kept for an hour after they end"
+)]
+pub async fn get_processing_status(
+    State(app_state): State<AppState>,
+    Path((experiment_id, job_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<ProcessingProgress>, (StatusCode, String)> {
+    let progress = processing_job(&app_state, experiment_id, job_id)?;
+    let current = progress.borrow().clone();
+    Ok(Json(current))
+}
+
+#[utoipa::path(
+    get,
+    path = "/{experiment_id}/process-status/{job_id}/stream",
+    params(
+        ("experiment_id" = Uuid, Path, description = "Experiment UUID"),
+        ("job_id" = Uuid, Path, description = "Processing job ID")
+    ),
+    responses(
+        (status = 200, description = "Server-sent `progress` events, ending with a `completed` or `failed` event", body = ProcessingProgress, content_type = "text/event-stream"),
+        (status = 404, description = "Processing job not found")
+    ),
+    tag = "experiments",
+    summary = "Stream processing progress",
+    description = "Follow an experiment's data file processing as server-sent events, each with the progress as JSON: rows parsed, temperature readings and phase transitions written, and percent complete. The stream ends when the processing does"
+)]
+pub async fn stream_processing_status(
+    State(app_state): State<AppState>,
+    Path((experiment_id, job_id)): Path<(Uuid, Uuid)>,
+) -> Result<
+    Sse<impl futures::Stream<Item = Result<Event, std::convert::Infallible>>>,
+    (StatusCode, String),
+> {
+    let mut progress = processing_job(&app_state, experiment_id, job_id)?;
+    let stream = async_stream::stream! {
+        loop {
+            let current = progress.borrow_and_update().clone();
+            let event = match current.status {
+                ProcessingStatus::Completed => "completed",
+                ProcessingStatus::Failed => "failed",
+                _ => "progress",
+            };
+            yield Ok(Event::default().event(event).json_data(&current).unwrap_or_default());
+            // The job is over, or forgotten
+            if current.is_finished() || progress.changed().await.is_err() {
+                break;
+            }
+        }
+    };
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
 #[utoipa::path(
     post,
     path = "/{experiment_id}/clear-results",
     params(
         ("experiment_id" = Uuid, Path, description = "Experiment UUID")
     ),
-    request_body = serde_json::Value,
+    request_body = ProcessAssetRequest,
     responses(
-        (status = 200, description = "Results cleared successfully", body = serde_json::Value),
+        (status = 200, description = "Results cleared successfully", body = ClearResultsResponse),
         (status = 404, description = "Experiment not found"),
         (status = 500, description = "Internal server error")
     ),
@@ -757,19 +2900,10 @@ pub async fn clear_experiment_results(
     State(app_state): State<AppState>,
     Path(experiment_id): Path<Uuid>,
     Json(payload): Json<serde_json::Value>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<ClearResultsResponse>, (StatusCode, String)> {
     use sea_orm::Set;
 
-    let asset_id = payload
-        .get("assetId")
-        .and_then(|v| v.as_str())
-        .and_then(|s| Uuid::parse_str(s).ok())
-        .ok_or_else(|| {
-            (
-                StatusCode::BAD_REQUEST,
-                "Missing or invalid assetId".to_string(),
-            )
-        })?;
+    let asset_id = requested_asset(payload)?.asset_id;
 
     // Clear processed data by deleting related records directly
     // Delete temperature readings
@@ -796,6 +2930,14 @@ pub async fn clear_experiment_results(
                 format!("Failed to clear phase transitions: {e}"),
             )
         })?;
+    super::summaries::discard_results_summary(&app_state.db, experiment_id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to clear results summary: {e}"),
+            )
+        })?;
 
     // Update asset to remove processing status
     let update_asset = s3_assets::ActiveModel {
@@ -808,10 +2950,10 @@ pub async fn clear_experiment_results(
         .exec(&app_state.db)
         .await;
 
-    Ok(Json(serde_json::json!({
-        "success": true,
-        "message": "Experiment results cleared successfully"
-    })))
+    Ok(Json(ClearResultsResponse {
+        success: true,
+        message: "Experiment results cleared successfully".to_string(),
+    }))
 }
 
 #[cfg(test)]
@@ -913,7 +3055,7 @@ mod view_helper_tests {
         // Test that FileUploadData can be created and has expected fields
         let upload_data = FileUploadData {
             file_name: "test.jpg".to_string(),
-            file_bytes: vec![1, 2, 3, 4],
+            file_bytes: Some(vec![1, 2, 3, 4]),
             file_type: "image".to_string(),
             extension: "jpg".to_string(),
             size: 4,
@@ -921,7 +3063,7 @@ mod view_helper_tests {
         };
 
         assert_eq!(upload_data.file_name, "test.jpg");
-        assert_eq!(upload_data.file_bytes, vec![1, 2, 3, 4]);
+        assert_eq!(upload_data.file_bytes, Some(vec![1, 2, 3, 4]));
         assert_eq!(upload_data.file_type, "image");
         assert_eq!(upload_data.extension, "jpg");
         assert_eq!(upload_data.size, 4);
@@ -0,0 +1,235 @@
+//! Crops of camera frames around one well, to check a freeze by eye
+
+use super::phase_transitions::models as phase_transitions;
+use super::timelapse::camera_images;
+use crate::common::state::AppState;
+use crate::external::s3::get_object_from_s3;
+use crate::services::processing::structure::parse_well_coordinate;
+use crate::tray_configurations::{
+    trays::models as trays, well_grid::compute_tray_grid, wells::models as wells,
+};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use image::{ImageFormat, imageops};
+use sea_orm::{EntityTrait, QueryFilter, QueryOrder, entity::prelude::*};
+use uuid::Uuid;
+
+/// Crop side for trays without `well_relative_diameter`
+const DEFAULT_CROP_SIZE: u32 = 64;
+/// The default crop shows the well and its neighbours' edges
+const CROP_SIZE_PER_RADIUS: f64 = 6.0;
+
+pub struct WellCrop {
+    pub png: Vec<u8>,
+    /// Camera image the crop was taken from
+    pub filename: String,
+    pub captured_at: NaiveDateTime,
+}
+
+/// Tray name and well of a `P1:A1` coordinate. The tray may be left out when
+/// the configuration has a single tray.
+pub(crate) fn split_coordinate(coordinate: &str) -> Result<(Option<&str>, String, i32), DbErr> {
+    let (tray_name, well) = match coordinate.split_once(':') {
+        Some((tray_name, well)) => (Some(tray_name), well),
+        None => (None, coordinate),
+    };
+    let (row_letter, column_number) = parse_well_coordinate(well)
+        .map_err(|_| DbErr::Custom(format!("Invalid well coordinate '{coordinate}'")))?;
+    Ok((tray_name, row_letter.to_ascii_uppercase(), column_number))
+}
+
+/// Window of `size` pixels centred on `centre` and kept inside `0..limit`
+fn crop_window(centre: f64, size: u32, limit: u32) -> (u32, u32) {
+    let size = size.min(limit);
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)] // Clamped to the image
+    let start = (centre - f64::from(size) / 2.0)
+        .round()
+        .clamp(0.0, f64::from(limit - size)) as u32;
+    (start, size)
+}
+
+/// Index of the frame captured closest to `timestamp`
+pub(super) fn nearest_frame(captured: &[NaiveDateTime], timestamp: NaiveDateTime) -> Option<usize> {
+    captured
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, time)| (**time - timestamp).abs())
+        .map(|(index, _)| index)
+}
+
+/// Crop the camera frame nearest to `timestamp` around a well.
+///
+/// Without a timestamp the frame of the well's first freeze is used, or the
+/// last frame if it never froze. Missing records are returned as
+/// `DbErr::RecordNotFound`, other problems as `DbErr::Custom`.
+pub async fn crop_well_image(
+    state: &AppState,
+    experiment_id: Uuid,
+    coordinate: &str,
+    timestamp: Option<DateTime<Utc>>,
+    size: Option<u32>,
+) -> Result<WellCrop, DbErr> {
+    if size.is_some_and(|size| !(16..=1024).contains(&size)) {
+        return Err(DbErr::Custom(
+            "size must be between 16 and 1024".to_string(),
+        ));
+    }
+    let (tray_name, row_letter, column_number) = split_coordinate(coordinate)?;
+
+    let experiment = super::models::Entity::find_by_id(experiment_id)
+        .one(&state.db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Experiment not found".to_string()))?;
+    let tray_configuration_id = experiment
+        .tray_configuration_id
+        .ok_or_else(|| DbErr::Custom("Experiment has no tray configuration".to_string()))?;
+
+    let configuration_trays = trays::Entity::find()
+        .filter(trays::Column::TrayConfigurationId.eq(tray_configuration_id))
+        .all(&state.db)
+        .await?;
+    let tray = match tray_name {
+        Some(name) => configuration_trays
+            .iter()
+            .find(|tray| tray.name.as_deref() == Some(name)),
+        None if configuration_trays.len() == 1 => configuration_trays.first(),
+        None => {
+            return Err(DbErr::Custom(
+                "The configuration has several trays; use a coordinate like P1:A1".to_string(),
+            ));
+        }
+    }
+    .ok_or_else(|| DbErr::RecordNotFound(format!("Tray not found in '{coordinate}'")))?;
+
+    let grid = compute_tray_grid(tray).ok_or_else(|| {
+        DbErr::Custom("The tray has no corner coordinates or dimensions".to_string())
+    })?;
+    let position = grid
+        .wells
+        .iter()
+        .find(|well| well.row_letter == row_letter && well.column_number == column_number)
+        .ok_or_else(|| DbErr::RecordNotFound(format!("Well {coordinate} is not on the tray")))?;
+
+    let frames = camera_images(&state.db, experiment_id).await?;
+    if frames.is_empty() {
+        return Err(DbErr::RecordNotFound(
+            "Experiment has no INP_* camera images".to_string(),
+        ));
+    }
+
+    let timestamp = match timestamp {
+        Some(timestamp) => Some(timestamp),
+        None => first_freeze(state, experiment_id, tray.id, &row_letter, column_number).await?,
+    };
+    let captured: Vec<NaiveDateTime> = frames.iter().map(|(captured, _)| *captured).collect();
+    let index = timestamp
+        .and_then(|timestamp| nearest_frame(&captured, timestamp.naive_utc()))
+        .unwrap_or(frames.len() - 1);
+    let (captured_at, asset) = &frames[index];
+
+    let size = size.unwrap_or_else(|| {
+        grid.well_radius.map_or(DEFAULT_CROP_SIZE, |radius| {
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)] // Clamped
+            let size = (radius * CROP_SIZE_PER_RADIUS).ceil().clamp(16.0, 1024.0) as u32;
+            size
+        })
+    });
+    let (x, y) = (position.x, position.y);
+    let bytes = get_object_from_s3(asset.storage_key(), &state.config)
+        .await
+        .map_err(DbErr::Custom)?;
+    let png = tokio::task::spawn_blocking(move || -> Result<Vec<u8>, String> {
+        let image =
+            image::load_from_memory(&bytes).map_err(|e| format!("Failed to decode image: {e}"))?;
+        if x < 0.0 || y < 0.0 || x >= f64::from(image.width()) || y >= f64::from(image.height()) {
+            return Err("The well lies outside the camera image".to_string());
+        }
+        let (left, width) = crop_window(x, size, image.width());
+        let (top, height) = crop_window(y, size, image.height());
+        let crop = imageops::crop_imm(&image, left, top, width, height).to_image();
+
+        let mut png = Vec::new();
+        crop.write_to(&mut std::io::Cursor::new(&mut png), ImageFormat::Png)
+            .map_err(|e| format!("Failed to encode crop: {e}"))?;
+        Ok(png)
+    })
+    .await
+    .map_err(|e| DbErr::Custom(format!("Crop task failed: {e}")))?
+    .map_err(DbErr::Custom)?;
+
+    Ok(WellCrop {
+        png,
+        filename: asset.original_filename.clone(),
+        captured_at: *captured_at,
+    })
+}
+
+/// Time of the well's first liquid to frozen transition
+async fn first_freeze(
+    state: &AppState,
+    experiment_id: Uuid,
+    tray_id: Uuid,
+    row_letter: &str,
+    column_number: i32,
+) -> Result<Option<DateTime<Utc>>, DbErr> {
+    let Some(well) = wells::Entity::find()
+        .filter(wells::Column::TrayId.eq(tray_id))
+        .filter(wells::Column::RowLetter.eq(row_letter))
+        .filter(wells::Column::ColumnNumber.eq(column_number))
+        .one(&state.db)
+        .await?
+    else {
+        return Ok(None);
+    };
+
+    Ok(phase_transitions::Entity::find()
+        .filter(phase_transitions::Column::ExperimentId.eq(experiment_id))
+        .filter(phase_transitions::Column::WellId.eq(well.id))
+        .filter(phase_transitions::Column::PreviousState.eq(0))
+        .filter(phase_transitions::Column::NewState.eq(1))
+        .order_by_asc(phase_transitions::Column::Timestamp)
+        .one(&state.db)
+        .await?
+        .map(|transition| transition.timestamp))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_coordinate() {
+        let (tray, row, column) = split_coordinate("P2:b11").unwrap();
+        assert_eq!((tray, row.as_str(), column), (Some("P2"), "B", 11));
+        let (tray, row, column) = split_coordinate("A1").unwrap();
+        assert_eq!((tray, row.as_str(), column), (None, "A", 1));
+        assert!(split_coordinate("P1:").is_err());
+        assert!(split_coordinate("12").is_err());
+    }
+
+    #[test]
+    fn test_crop_window_stays_inside_the_image() {
+        assert_eq!(crop_window(100.0, 40, 320), (80, 40));
+        assert_eq!(crop_window(5.0, 40, 320), (0, 40));
+        assert_eq!(crop_window(318.0, 40, 320), (280, 40));
+        assert_eq!(crop_window(10.0, 400, 320), (0, 320));
+    }
+
+    #[test]
+    fn test_nearest_frame() {
+        let time = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+        let captured = [
+            time("2025-01-01 10:00:00"),
+            time("2025-01-01 10:00:10"),
+            time("2025-01-01 10:00:20"),
+        ];
+        assert_eq!(
+            nearest_frame(&captured, time("2025-01-01 10:00:14")),
+            Some(1)
+        );
+        assert_eq!(
+            nearest_frame(&captured, time("2025-01-01 11:00:00")),
+            Some(2)
+        );
+        assert_eq!(nearest_frame(&[], time("2025-01-01 10:00:00")), None);
+    }
+}
@@ -0,0 +1,70 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// How long a finished export (and its staged archive) is kept
+pub const EXPORT_RETENTION_HOURS: i64 = 24;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportJobStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+}
+
+/// Experiments to export: either an explicit list of IDs or a filter in the
+/// same format as the `filter` parameter of `GET /api/experiments`
+#[derive(Clone, Debug, Deserialize, ToSchema)]
+pub struct ExportJobRequest {
+    #[serde(default)]
+    pub experiment_ids: Option<Vec<Uuid>>,
+    #[serde(default)]
+    #[schema(value_type = Option<Object>, example = json!({"is_calibration": false}))]
+    pub filter: Option<serde_json::Value>,
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct ExportJob {
+    pub id: Uuid,
+    pub status: ExportJobStatus,
+    pub experiment_ids: Vec<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub size_bytes: Option<u64>,
+    pub error: Option<String>,
+    /// Link to the finished archive; valid until `expires_at`
+    pub download_url: Option<String>,
+    #[serde(skip)]
+    pub s3_key: Option<String>,
+    #[serde(skip)]
+    pub download_token: Option<String>,
+}
+
+impl ExportJob {
+    pub fn new(experiment_ids: Vec<Uuid>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            status: ExportJobStatus::Queued,
+            experiment_ids,
+            created_at: Utc::now(),
+            started_at: None,
+            completed_at: None,
+            expires_at: None,
+            size_bytes: None,
+            error: None,
+            download_url: None,
+            s3_key: None,
+            download_token: None,
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at <= Utc::now())
+    }
+}
@@ -0,0 +1,216 @@
+use super::models::{EXPORT_RETENTION_HOURS, ExportJob, ExportJobRequest, ExportJobStatus};
+use crate::assets::services::{ArchiveEntry, ArchiveSource, archive_path_component};
+use crate::common::state::AppState;
+use crate::config::Config;
+use crate::experiments::models::{self as experiments, Experiment};
+use crate::external::s3::{delete_object_from_s3, put_object_to_s3};
+use chrono::Utc;
+use crudcrate::CRUDResource;
+use sea_orm::{ConnectionTrait, DatabaseConnection, EntityTrait, QueryOrder, entity::prelude::*};
+use uuid::Uuid;
+
+/// Upper bound on the experiments in one export job
+pub const MAX_EXPORT_EXPERIMENTS: usize = 500;
+
+/// Resolve the experiments selected by an export request.
+///
+/// Validation problems are returned as `DbErr::Custom` so they can be reported
+/// as bad requests.
+pub async fn resolve_export_experiments(
+    db: &DatabaseConnection,
+    request: &ExportJobRequest,
+) -> Result<Vec<experiments::Model>, DbErr> {
+    let query = match (&request.experiment_ids, &request.filter) {
+        (Some(ids), None) if !ids.is_empty() => {
+            experiments::Entity::find().filter(experiments::Column::Id.is_in(ids.clone()))
+        }
+        (None, Some(filter)) => {
+            let condition = crudcrate::filter::apply_filters::<Experiment>(
+                Some(filter.to_string()),
+                &Experiment::filterable_columns(),
+                db.get_database_backend(),
+            );
+            experiments::Entity::find().filter(condition)
+        }
+        _ => {
+            return Err(DbErr::Custom(
+                "Provide either a non-empty experiment_ids list or a filter".to_string(),
+            ));
+        }
+    };
+
+    let selected = query
+        .order_by_asc(experiments::Column::Name)
+        .all(db)
+        .await?;
+
+    if let Some(ids) = &request.experiment_ids
+        && selected.len() != ids.len()
+    {
+        let missing: Vec<String> = ids
+            .iter()
+            .filter(|id| !selected.iter().any(|experiment| experiment.id == **id))
+            .map(ToString::to_string)
+            .collect();
+        return Err(DbErr::Custom(format!(
+            "Experiments not found: {}",
+            missing.join(", ")
+        )));
+    }
+    if selected.is_empty() {
+        return Err(DbErr::Custom(
+            "No experiments match the export filter".to_string(),
+        ));
+    }
+    if selected.len() > MAX_EXPORT_EXPERIMENTS {
+        return Err(DbErr::Custom(format!(
+            "{} experiments selected; a single export is limited to {MAX_EXPORT_EXPERIMENTS}",
+            selected.len()
+        )));
+    }
+
+    Ok(selected)
+}
+
+/// Collect the archive entries for every experiment, each below
+/// `experiments/<experiment name>/`, plus a top-level `manifest.json`
+async fn build_export_entries(
+    db: &DatabaseConnection,
+    job: &ExportJob,
+) -> Result<Vec<ArchiveEntry>, DbErr> {
+    let selected = experiments::Entity::find()
+        .filter(experiments::Column::Id.is_in(job.experiment_ids.clone()))
+        .order_by_asc(experiments::Column::Name)
+        .all(db)
+        .await?;
+
+    let mut entries = Vec::new();
+    let mut manifest = Vec::new();
+    for experiment in &selected {
+        let directory = format!("experiments/{}", archive_path_component(&experiment.name));
+        let experiment_entries =
+            crate::experiments::services::build_experiment_archive_entries(experiment.id, db)
+                .await?;
+        entries.extend(experiment_entries.into_iter().map(|entry| ArchiveEntry {
+            path: format!("{directory}/{}", entry.path),
+            source: entry.source,
+        }));
+        manifest.push(serde_json::json!({
+            "id": experiment.id,
+            "name": experiment.name,
+            "path": format!("{directory}/"),
+        }));
+    }
+
+    let manifest = serde_json::json!({
+        "format_version": 1,
+        "export_job_id": job.id,
+        "generated_at": Utc::now(),
+        "experiments": manifest,
+    });
+    entries.insert(
+        0,
+        ArchiveEntry {
+            path: "manifest.json".to_string(),
+            source: ArchiveSource::Inline(
+                serde_json::to_vec_pretty(&manifest).map_err(|e| {
+                    DbErr::Custom(format!("Failed to serialise export manifest: {e}"))
+                })?,
+            ),
+        },
+    );
+
+    Ok(entries)
+}
+
+/// Key of the staged archive of an export job
+pub fn export_key(config: &Config, job_id: Uuid) -> String {
+    format!(
+        "{}/{}/exports/{job_id}.zip",
+        config.app_name, config.deployment
+    )
+}
+
+async fn run_export_job(state: &AppState, job: ExportJob) -> Result<(String, u64), String> {
+    let entries = build_export_entries(&state.db, &job)
+        .await
+        .map_err(|e| format!("Failed to collect experiment data: {e}"))?;
+    let archive = crate::assets::services::build_archive_zip(entries, &state.config).await;
+    let size_bytes = archive.len() as u64;
+
+    let s3_key = export_key(&state.config, job.id);
+    put_object_to_s3(&s3_key, archive, &state.config).await?;
+
+    Ok((s3_key, size_bytes))
+}
+
+/// Register the job and build its archive in the background
+pub async fn submit_export_job(state: &AppState, experiment_ids: Vec<Uuid>) -> ExportJob {
+    purge_expired_export_jobs(state).await;
+
+    let job = ExportJob::new(experiment_ids);
+    state.export_jobs.write().await.insert(job.id, job.clone());
+
+    let worker_state = state.clone();
+    let worker_job = job.clone();
+    tokio::spawn(async move {
+        let job_id = worker_job.id;
+        worker_state
+            .update_export_job(job_id, |job| {
+                job.status = ExportJobStatus::Running;
+                job.started_at = Some(Utc::now());
+            })
+            .await;
+
+        let result = run_export_job(&worker_state, worker_job).await;
+
+        worker_state
+            .update_export_job(job_id, |job| {
+                let now = Utc::now();
+                job.completed_at = Some(now);
+                job.expires_at = Some(now + chrono::Duration::hours(EXPORT_RETENTION_HOURS));
+                match result {
+                    Ok((s3_key, size_bytes)) => {
+                        let token = Uuid::new_v4().to_string();
+                        job.status = ExportJobStatus::Completed;
+                        job.size_bytes = Some(size_bytes);
+                        job.download_url = Some(format!("/api/exports/download/{token}"));
+                        job.download_token = Some(token);
+                        job.s3_key = Some(s3_key);
+                    }
+                    Err(error) => {
+                        tracing::error!("Export job {job_id} failed: {error}");
+                        job.status = ExportJobStatus::Failed;
+                        job.error = Some(error);
+                    }
+                }
+            })
+            .await;
+    });
+
+    job
+}
+
+/// Forget expired jobs and delete their staged archives
+pub async fn purge_expired_export_jobs(state: &AppState) {
+    let expired: Vec<ExportJob> = {
+        let mut jobs = state.export_jobs.write().await;
+        let expired_ids: Vec<Uuid> = jobs
+            .values()
+            .filter(|job| job.is_expired())
+            .map(|job| job.id)
+            .collect();
+        expired_ids
+            .iter()
+            .filter_map(|id| jobs.remove(id))
+            .collect()
+    };
+
+    for job in expired {
+        if let Some(s3_key) = job.s3_key
+            && let Err(e) = delete_object_from_s3(&s3_key, &state.config).await
+        {
+            tracing::warn!("Failed to delete expired export {s3_key}: {e}");
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use crate::config::test_helpers::{send_json, setup_test_app};
+use axum::body::{Body, to_bytes};
+use axum::http::{Request, StatusCode};
+use serde_json::{Value, json};
+use std::io::Read;
+use tower::ServiceExt;
+
+/// The archive at a download link
+async fn download(app: &axum::Router, uri: &str) -> (StatusCode, Vec<u8>) {
+    let request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, bytes.to_vec())
+}
+
+async fn create_experiment(app: &axum::Router, name: &str, is_calibration: bool) -> String {
+    let (status, body) = send_json(
+        app,
+        "POST",
+        "/api/experiments",
+        Some(&json!({
+            "name": name,
+            "username": "export-tester",
+            "performed_at": "2024-06-20T14:30:00Z",
+            "is_calibration": is_calibration,
+            "remarks": "Bulk export test"
+        })),
+    )
+    .await;
+    assert_eq!(
+        status,
+        StatusCode::CREATED,
+        "Failed to create experiment: {body:?}"
+    );
+    body["id"].as_str().unwrap().to_string()
+}
+
+async fn wait_for_job(app: &axum::Router, job_id: &str) -> Value {
+    for _ in 0..100 {
+        let (status, job) = send_json(app, "GET", &format!("/api/exports/{job_id}"), None).await;
+        assert_eq!(status, StatusCode::OK, "Failed to get export job: {job:?}");
+        if job["status"] == "completed" || job["status"] == "failed" {
+            return job;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    panic!("Export job {job_id} did not finish");
+}
+
+#[tokio::test]
+async fn test_bulk_export_by_ids() {
+    let app = setup_test_app().await;
+    let suffix = uuid::Uuid::new_v4();
+    let first = create_experiment(&app, &format!("Export A {suffix}"), false).await;
+    let second = create_experiment(&app, &format!("Export B {suffix}"), false).await;
+
+    let (status, job) = send_json(
+        &app,
+        "POST",
+        "/api/exports",
+        Some(&json!({"experiment_ids": [first, second]})),
+    )
+    .await;
+    assert_eq!(
+        status,
+        StatusCode::ACCEPTED,
+        "Failed to submit export: {job:?}"
+    );
+    assert!(job["download_url"].is_null());
+    assert_eq!(job["experiment_ids"].as_array().unwrap().len(), 2);
+
+    let job = wait_for_job(&app, job["id"].as_str().unwrap()).await;
+    assert_eq!(job["status"], "completed", "Export failed: {job:?}");
+    assert!(job["expires_at"].is_string());
+    assert!(job["size_bytes"].as_u64().unwrap() > 0);
+    let download_url = job["download_url"].as_str().unwrap();
+
+    let (status, archive) = download(&app, download_url).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(archive.len() as u64, job["size_bytes"].as_u64().unwrap());
+
+    let mut zip = zip::ZipArchive::new(std::io::Cursor::new(archive)).unwrap();
+    let mut manifest = String::new();
+    zip.by_name("manifest.json")
+        .unwrap()
+        .read_to_string(&mut manifest)
+        .unwrap();
+    let manifest: Value = serde_json::from_str(&manifest).unwrap();
+    assert_eq!(manifest["export_job_id"], job["id"]);
+    let experiments = manifest["experiments"].as_array().unwrap();
+    assert_eq!(experiments.len(), 2);
+    for experiment in experiments {
+        let path = experiment["path"].as_str().unwrap();
+        assert!(
+            zip.file_names().any(|name| name.starts_with(path)),
+            "Archive has no files below {path}"
+        );
+    }
+
+    // The link stays valid until the job expires
+    let (status, _) = download(&app, download_url).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_bulk_export_by_filter() {
+    let app = setup_test_app().await;
+    let name = format!("Filtered export {}", uuid::Uuid::new_v4());
+    let experiment_id = create_experiment(&app, &name, true).await;
+
+    let (status, job) = send_json(
+        &app,
+        "POST",
+        "/api/exports",
+        Some(&json!({"filter": {"name": name}})),
+    )
+    .await;
+    assert_eq!(
+        status,
+        StatusCode::ACCEPTED,
+        "Failed to submit export: {job:?}"
+    );
+    assert_eq!(job["experiment_ids"], json!([experiment_id]));
+
+    let job = wait_for_job(&app, job["id"].as_str().unwrap()).await;
+    assert_eq!(job["status"], "completed", "Export failed: {job:?}");
+}
+
+#[tokio::test]
+async fn test_bulk_export_validation() {
+    let app = setup_test_app().await;
+
+    let (status, _) = send_json(&app, "POST", "/api/exports", Some(&json!({}))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        "/api/exports",
+        Some(&json!({"experiment_ids": []})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        "/api/exports",
+        Some(&json!({"experiment_ids": [uuid::Uuid::new_v4()]})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        "/api/exports",
+        Some(&json!({"filter": {"name": format!("No such experiment {}", uuid::Uuid::new_v4())}})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, _) = send_json(
+        &app,
+        "GET",
+        &format!("/api/exports/{}", uuid::Uuid::new_v4()),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, _) = send_json(&app, "GET", "/api/exports/download/not-a-token", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,209 @@
+use super::models::{ExportJob, ExportJobRequest};
+use super::services::{resolve_export_experiments, submit_export_job};
+use crate::api_keys::services::accept_api_keys;
+use crate::common::auth::{RouteAccess, require_role};
+use crate::common::keycloak::authenticate;
+use crate::common::state::AppState;
+use crate::config::Config;
+use crate::external::s3::{get_object_range_from_s3, head_object_size};
+use axum::{
+    Json,
+    body::Body,
+    extract::{Path, State},
+    http::{
+        StatusCode,
+        header::{CONTENT_DISPOSITION, CONTENT_LENGTH, CONTENT_TYPE},
+    },
+    middleware,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use axum_keycloak_auth::PassthroughMode;
+use futures::Stream;
+use sea_orm::DbErr;
+use utoipa::OpenApi;
+use utoipa_axum::router::OpenApiRouter;
+use uuid::Uuid;
+
+/// Bytes of an archive read from storage at a time
+const DOWNLOAD_CHUNK_BYTES: u64 = 8 * 1024 * 1024;
+
+#[utoipa::path(
+    post,
+    path = "",
+    request_body = ExportJobRequest,
+    responses(
+        (status = 202, description = "Export job queued", body = ExportJob),
+        (status = 400, description = "Invalid selection of experiments"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "exports",
+    summary = "Start a bulk experiment export",
+    description = "Queue a background job that archives the selected experiments (by ID list or experiment filter) into a single ZIP. Poll the job until it is completed, then fetch the archive from its download_url"
+)]
+pub async fn create_export_job(
+    State(state): State<AppState>,
+    Json(request): Json<ExportJobRequest>,
+) -> Result<(StatusCode, Json<ExportJob>), (StatusCode, String)> {
+    let selected = resolve_export_experiments(&state.db, &request)
+        .await
+        .map_err(|e| match e {
+            DbErr::Custom(message) => (StatusCode::BAD_REQUEST, message),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to resolve experiments: {e}"),
+            ),
+        })?;
+
+    let job = submit_export_job(
+        &state,
+        selected
+            .into_iter()
+            .map(|experiment| experiment.id)
+            .collect(),
+    )
+    .await;
+
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/{job_id}",
+    params(
+        ("job_id" = Uuid, Path, description = "Export job ID")
+    ),
+    responses(
+        (status = 200, description = "Export job status", body = ExportJob),
+        (status = 404, description = "Export job not found or expired")
+    ),
+    tag = "exports",
+    summary = "Get bulk export status",
+    description = "Report the progress of an export job. Once completed, the response includes the archive size and a download_url valid until expires_at"
+)]
+pub async fn get_export_job(
+    State(state): State<AppState>,
+    Path(job_id): Path<Uuid>,
+) -> Result<Json<ExportJob>, (StatusCode, String)> {
+    state
+        .get_export_job(job_id)
+        .await
+        .filter(|job| !job.is_expired())
+        .map(Json)
+        .ok_or((StatusCode::NOT_FOUND, "Export job not found".to_string()))
+}
+
+/// The archive a range at a time, so it is never held whole
+fn archive_chunks(
+    s3_key: String,
+    size: u64,
+    config: Config,
+) -> impl Stream<Item = Result<Vec<u8>, std::io::Error>> {
+    async_stream::try_stream! {
+        let mut start = 0;
+        while start < size {
+            let end = (start + DOWNLOAD_CHUNK_BYTES).min(size) - 1;
+            let chunk = get_object_range_from_s3(&s3_key, start, end, &config)
+                .await
+                .map_err(std::io::Error::other)?;
+            start = end + 1;
+            yield chunk;
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/download/{token}",
+    params(
+        ("token" = String, Path, description = "Download token of a completed export job")
+    ),
+    responses(
+        (status = 200, description = "ZIP archive of the exported experiments", content_type = "application/zip"),
+        (status = 404, description = "Invalid or expired token"),
+        (status = 500, description = "Failed to retrieve the archive")
+    ),
+    tag = "exports",
+    summary = "Download a bulk export",
+    description = "Download the archive of a completed export job. The link can be reused until the job expires"
+)]
+pub async fn download_export(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+) -> Result<Response, (StatusCode, String)> {
+    let job = state.find_export_job_by_token(&token).await;
+    let Some((job_id, s3_key)) = job.and_then(|job| Some((job.id, job.s3_key?))) else {
+        return Err((
+            StatusCode::NOT_FOUND,
+            "Invalid or expired token".to_string(),
+        ));
+    };
+
+    let size = head_object_size(&s3_key, &state.config)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to retrieve export archive: {e}"),
+            )
+        })?
+        .and_then(|size| u64::try_from(size).ok())
+        .ok_or((
+            StatusCode::NOT_FOUND,
+            "Export archive not found".to_string(),
+        ))?;
+
+    Ok((
+        [
+            (CONTENT_TYPE, "application/zip".to_string()),
+            (
+                CONTENT_DISPOSITION,
+                format!("attachment; filename=\"export_{job_id}.zip\""),
+            ),
+            (CONTENT_LENGTH, size.to_string()),
+        ],
+        Body::from_stream(archive_chunks(s3_key, size, state.config.clone())),
+    )
+        .into_response())
+}
+
+/// Documentation of the routes, which are routed with `route`
+#[derive(OpenApi)]
+#[openapi(paths(create_export_job, get_export_job, download_export))]
+struct ExportsApi;
+
+pub fn router(state: &AppState) -> OpenApiRouter {
+    // The download link is handed out to browsers, so it carries its own token
+    let public_router = OpenApiRouter::new().route(
+        "/download/{token}",
+        get(download_export).with_state(state.clone()),
+    );
+
+    let mut authenticated_router = OpenApiRouter::new()
+        .route("/", post(create_export_job))
+        .route("/{job_id}", get(get_export_job))
+        .with_state(state.clone());
+    authenticated_router
+        .get_openapi_mut()
+        .merge(ExportsApi::openapi());
+
+    if let Some(instance) = state.keycloak_auth_instance.clone() {
+        authenticated_router = authenticated_router
+            .layer(middleware::from_fn_with_state(
+                RouteAccess::ADMINISTRATION,
+                require_role,
+            ))
+            .layer(middleware::from_fn_with_state(
+                (state.db.clone(), "exports"),
+                accept_api_keys,
+            ))
+            .layer(middleware::from_fn_with_state(
+                (instance, PassthroughMode::Pass),
+                authenticate,
+            ));
+    } else if !state.config.tests_running {
+        println!("Warning: Export routes are not protected");
+    }
+
+    public_router.merge(authenticated_router)
+}
@@ -1 +1,5 @@
 pub mod s3;
+pub mod site;
+pub mod storage;
+pub mod weather;
+pub mod zenodo;
@@ -0,0 +1,80 @@
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct ElevationResponse {
+    elevation: Vec<f64>,
+}
+
+#[derive(Deserialize)]
+struct TimezoneResponse {
+    timezone: String,
+}
+
+async fn get<T: serde::de::DeserializeOwned>(
+    url: &str,
+    query: &[(&str, String)],
+    what: &str,
+) -> Result<T, String> {
+    let response = reqwest::Client::new()
+        .get(url)
+        .query(query)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach the {what} provider: {e}"))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!(
+            "The {what} provider rejected the request ({status}): {body}"
+        ));
+    }
+    response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse the {what} provider's response: {e}"))
+}
+
+/// Elevation in metres from an Open-Meteo compatible elevation API, such as
+/// `https://api.open-meteo.com/v1/elevation`
+pub async fn fetch_elevation(url: &str, latitude: f64, longitude: f64) -> Result<Decimal, String> {
+    let response: ElevationResponse = get(
+        url,
+        &[
+            ("latitude", latitude.to_string()),
+            ("longitude", longitude.to_string()),
+        ],
+        "elevation",
+    )
+    .await?;
+    response
+        .elevation
+        .first()
+        .and_then(|elevation| Decimal::try_from(*elevation).ok())
+        .map(|elevation| elevation.round_dp(2))
+        .ok_or_else(|| "The elevation provider returned no elevation".to_string())
+}
+
+/// IANA time zone from an Open-Meteo compatible forecast API asked to work it
+/// out, such as `https://api.open-meteo.com/v1/forecast`
+pub async fn fetch_timezone(url: &str, latitude: f64, longitude: f64) -> Result<String, String> {
+    let response: TimezoneResponse = get(
+        url,
+        &[
+            ("latitude", latitude.to_string()),
+            ("longitude", longitude.to_string()),
+            ("timezone", "auto".to_string()),
+        ],
+        "time zone",
+    )
+    .await?;
+    if response.timezone.contains('/') || response.timezone == "UTC" {
+        Ok(response.timezone)
+    } else {
+        Err(format!(
+            "The time zone provider returned '{}' rather than an IANA time zone",
+            response.timezone
+        ))
+    }
+}
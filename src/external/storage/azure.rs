@@ -0,0 +1,287 @@
+//! Azure Blob Storage through its REST API, authorised with a SAS token.
+//!
+//! The token needs read, write, delete, create and list permissions on the
+//! container; creating the container at startup also needs an account SAS.
+//! Presigned URLs are not offered because the token would have to be shared.
+
+use super::{PresignedPut, ResponseHeaders, StorageBackend, StoredObject, encode_key};
+use crate::config::Config;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use reqwest::{Method, RequestBuilder, Response, StatusCode};
+use std::time::Duration;
+
+/// Needed for Put Blob From URL, used to copy blobs of up to 5000 MiB
+const API_VERSION: &str = "2021-08-06";
+
+pub struct AzureBlobBackend {
+    client: reqwest::Client,
+    container_url: String,
+    sas_token: String,
+}
+
+impl AzureBlobBackend {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            container_url: config.azure_container_url.trim_end_matches('/').to_string(),
+            sas_token: config.azure_sas_token.trim_start_matches('?').to_string(),
+        }
+    }
+
+    fn with_token(&self, url: &str, query: Option<&str>) -> String {
+        match query {
+            Some(query) => format!("{url}?{query}&{}", self.sas_token),
+            None => format!("{url}?{}", self.sas_token),
+        }
+    }
+
+    fn blob_url(&self, key: &str) -> String {
+        self.with_token(&format!("{}/{}", self.container_url, encode_key(key)), None)
+    }
+
+    fn request(&self, method: Method, url: String) -> RequestBuilder {
+        self.client
+            .request(method, url)
+            .header("x-ms-version", API_VERSION)
+    }
+
+    async fn send(request: RequestBuilder, action: &str) -> Result<Response, String> {
+        request
+            .send()
+            .await
+            .map_err(|e| format!("Failed to {action} in Azure Blob Storage: {e}"))
+    }
+
+    async fn error(response: Response, action: &str) -> String {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        format!("Failed to {action} in Azure Blob Storage: {status} {body}")
+    }
+
+    /// One page of a List Blobs response, and the marker of the next page
+    fn parse_blob_list(xml: &str) -> (Vec<StoredObject>, Option<String>) {
+        let objects = xml
+            .split("<Blob>")
+            .skip(1)
+            .filter_map(|blob| {
+                Some(StoredObject {
+                    key: xml_element(blob, "Name")?,
+                    size: xml_element(blob, "Content-Length")?.parse().ok()?,
+                    last_modified: xml_element(blob, "Last-Modified")
+                        .and_then(|time| DateTime::parse_from_rfc2822(&time).ok())
+                        .map(|time| time.with_timezone(&Utc)),
+                })
+            })
+            .collect();
+        let next_marker = xml
+            .rsplit_once("</Blobs>")
+            .and_then(|(_, rest)| xml_element(rest, "NextMarker"))
+            .filter(|marker| !marker.is_empty());
+        (objects, next_marker)
+    }
+
+    async fn body(response: Response, action: &str) -> Result<Vec<u8>, String> {
+        response
+            .bytes()
+            .await
+            .map(|bytes| bytes.to_vec())
+            .map_err(|e| format!("Failed to {action} in Azure Blob Storage: {e}"))
+    }
+}
+
+#[async_trait]
+impl StorageBackend for AzureBlobBackend {
+    async fn ensure_container(&self) -> Result<(), String> {
+        let url = self.with_token(&self.container_url, Some("restype=container"));
+        let response = Self::send(
+            self.request(Method::PUT, url).header("Content-Length", "0"),
+            "create container",
+        )
+        .await?;
+        match response.status() {
+            StatusCode::CREATED => {
+                println!("Created Azure Blob container: {}", self.container_url);
+                Ok(())
+            }
+            // Already exists, or the token may not create containers but can
+            // still use the existing one
+            StatusCode::CONFLICT | StatusCode::FORBIDDEN => Ok(()),
+            _ => Err(Self::error(response, "create container").await),
+        }
+    }
+
+    async fn put(&self, key: &str, data: Vec<u8>) -> Result<(), String> {
+        let request = self
+            .request(Method::PUT, self.blob_url(key))
+            .header("x-ms-blob-type", "BlockBlob")
+            .body(data);
+        let response = Self::send(request, "upload object").await?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(Self::error(response, "upload object").await)
+        }
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>, String> {
+        let response =
+            Self::send(self.request(Method::GET, self.blob_url(key)), "get object").await?;
+        match response.status() {
+            status if status.is_success() => Self::body(response, "get object").await,
+            StatusCode::NOT_FOUND => Err(format!("Object not found: {key}")),
+            _ => Err(Self::error(response, "get object").await),
+        }
+    }
+
+    async fn get_range(&self, key: &str, start: u64, end: u64) -> Result<Vec<u8>, String> {
+        let request = self
+            .request(Method::GET, self.blob_url(key))
+            .header("x-ms-range", format!("bytes={start}-{end}"));
+        let response = Self::send(request, "get object range").await?;
+        match response.status() {
+            status if status.is_success() => Self::body(response, "get object range").await,
+            // The range starts after the end of the blob
+            StatusCode::RANGE_NOT_SATISFIABLE => Ok(Vec::new()),
+            StatusCode::NOT_FOUND => Err(format!("Object not found: {key}")),
+            _ => Err(Self::error(response, "get object range").await),
+        }
+    }
+
+    async fn size(&self, key: &str) -> Result<Option<i64>, String> {
+        let response = Self::send(
+            self.request(Method::HEAD, self.blob_url(key)),
+            "inspect object",
+        )
+        .await?;
+        match response.status() {
+            status if status.is_success() => Ok(response
+                .headers()
+                .get(reqwest::header::CONTENT_LENGTH)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse().ok())),
+            StatusCode::NOT_FOUND => Ok(None),
+            status => Err(format!(
+                "Failed to inspect object in Azure Blob Storage: {status}"
+            )),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), String> {
+        let response = Self::send(
+            self.request(Method::DELETE, self.blob_url(key)),
+            "delete object",
+        )
+        .await?;
+        match response.status() {
+            status if status.is_success() => Ok(()),
+            StatusCode::NOT_FOUND => Ok(()),
+            _ => Err(Self::error(response, "delete object").await),
+        }
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<StoredObject>, String> {
+        let mut objects = Vec::new();
+        let mut marker: Option<String> = None;
+        loop {
+            let marker_param = marker
+                .as_deref()
+                .map(|marker| format!("&marker={}", encode_key(marker)))
+                .unwrap_or_default();
+            let query = format!(
+                "restype=container&comp=list&prefix={}{marker_param}",
+                encode_key(prefix)
+            );
+            let url = self.with_token(&self.container_url, Some(&query));
+            let response = Self::send(self.request(Method::GET, url), "list objects").await?;
+            if !response.status().is_success() {
+                return Err(Self::error(response, "list objects").await);
+            }
+            let body = Self::body(response, "list objects").await?;
+            let (page, next_marker) = Self::parse_blob_list(&String::from_utf8_lossy(&body));
+            objects.extend(page);
+            match next_marker {
+                Some(next_marker) => marker = Some(next_marker),
+                None => return Ok(objects),
+            }
+        }
+    }
+
+    /// Put Blob From URL copies synchronously, up to 5000 MiB
+    async fn copy(&self, source_key: &str, destination_key: &str) -> Result<(), String> {
+        let request = self
+            .request(Method::PUT, self.blob_url(destination_key))
+            .header("x-ms-blob-type", "BlockBlob")
+            .header("x-ms-copy-source", self.blob_url(source_key))
+            .header("Content-Length", "0");
+        let response = Self::send(request, "copy object").await?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(Self::error(response, "copy object").await)
+        }
+    }
+
+    fn supports_presigned_urls(&self) -> bool {
+        false
+    }
+
+    async fn presigned_put(
+        &self,
+        _key: &str,
+        _content_type: Option<&str>,
+        _expires_in: Duration,
+    ) -> Result<PresignedPut, String> {
+        Err("Azure Blob Storage does not support direct uploads".to_string())
+    }
+
+    async fn presigned_get(
+        &self,
+        _key: &str,
+        _expires_in: Duration,
+        _headers: &ResponseHeaders,
+    ) -> Result<String, String> {
+        Err("Azure Blob Storage does not support direct downloads".to_string())
+    }
+}
+
+/// Unescaped text of the first `<tag>` element in `xml`
+fn xml_element(xml: &str, tag: &str) -> Option<String> {
+    let start = xml.find(&format!("<{tag}>"))? + tag.len() + 2;
+    let end = start + xml[start..].find(&format!("</{tag}>"))?;
+    Some(
+        xml[start..end]
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&apos;", "'")
+            .replace("&amp;", "&"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_blob_list() {
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<EnumerationResults ContainerName="spice"><Prefix>app/test/</Prefix><Blobs>
+<Blob><Name>app/test/a&amp;b.jpg</Name><Properties><Last-Modified>Thu, 20 Mar 2025 16:49:38 GMT</Last-Modified><Content-Length>1024</Content-Length></Properties></Blob>
+<Blob><Name>app/test/c.xlsx</Name><Properties><Content-Length>7</Content-Length></Properties></Blob>
+</Blobs><NextMarker>2!88!MDAw</NextMarker></EnumerationResults>"#;
+        let (objects, next_marker) = AzureBlobBackend::parse_blob_list(xml);
+        assert_eq!(objects.len(), 2);
+        assert_eq!(objects[0].key, "app/test/a&b.jpg");
+        assert_eq!(objects[0].size, 1024);
+        assert_eq!(
+            objects[0].last_modified.map(|time| time.to_rfc3339()),
+            Some("2025-03-20T16:49:38+00:00".to_string())
+        );
+        assert!(objects[1].last_modified.is_none());
+        assert_eq!(next_marker.as_deref(), Some("2!88!MDAw"));
+
+        let (_, next_marker) = AzureBlobBackend::parse_blob_list("<Blobs></Blobs><NextMarker />");
+        assert!(next_marker.is_none());
+    }
+}
@@ -0,0 +1,268 @@
+//! Objects stored as files below a root directory, for deployments without
+//! an object store
+
+use super::{PresignedPut, ResponseHeaders, StorageBackend, StoredObject};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::io::{ErrorKind, SeekFrom};
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use uuid::Uuid;
+
+pub struct LocalBackend {
+    root: PathBuf,
+}
+
+impl LocalBackend {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// File of an object. Keys are relative paths that must stay inside the root.
+    fn path(&self, key: &str) -> Result<PathBuf, String> {
+        let relative = Path::new(key);
+        let is_contained = !key.is_empty()
+            && !key.contains('\\')
+            && relative
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)));
+        if !is_contained {
+            return Err(format!("Invalid storage key: {key}"));
+        }
+        Ok(self.root.join(relative))
+    }
+
+    /// Temporary file next to `path`, renamed over it once complete so readers
+    /// never see a partial object
+    async fn staging_path(path: &Path) -> Result<PathBuf, String> {
+        let parent = path
+            .parent()
+            .ok_or_else(|| format!("Invalid storage path: {}", path.display()))?;
+        tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(|e| format!("Failed to create directory {}: {e}", parent.display()))?;
+        Ok(parent.join(format!(".{}.tmp", Uuid::new_v4())))
+    }
+
+    async fn commit(staging: &Path, path: &Path) -> Result<(), String> {
+        if let Err(e) = tokio::fs::rename(staging, path).await {
+            let _ = tokio::fs::remove_file(staging).await;
+            return Err(format!("Failed to store {}: {e}", path.display()));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl StorageBackend for LocalBackend {
+    async fn ensure_container(&self) -> Result<(), String> {
+        tokio::fs::create_dir_all(&self.root).await.map_err(|e| {
+            format!(
+                "Failed to create storage directory {}: {e}",
+                self.root.display()
+            )
+        })
+    }
+
+    async fn put(&self, key: &str, data: Vec<u8>) -> Result<(), String> {
+        let path = self.path(key)?;
+        let staging = Self::staging_path(&path).await?;
+        tokio::fs::write(&staging, data)
+            .await
+            .map_err(|e| format!("Failed to write {}: {e}", staging.display()))?;
+        Self::commit(&staging, &path).await
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>, String> {
+        match tokio::fs::read(self.path(key)?).await {
+            Ok(data) => Ok(data),
+            Err(e) if e.kind() == ErrorKind::NotFound => Err(format!("Object not found: {key}")),
+            Err(e) => Err(format!("Failed to read object {key}: {e}")),
+        }
+    }
+
+    async fn get_range(&self, key: &str, start: u64, end: u64) -> Result<Vec<u8>, String> {
+        let mut file = match tokio::fs::File::open(self.path(key)?).await {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                return Err(format!("Object not found: {key}"));
+            }
+            Err(e) => return Err(format!("Failed to open object {key}: {e}")),
+        };
+        file.seek(SeekFrom::Start(start))
+            .await
+            .map_err(|e| format!("Failed to seek in object {key}: {e}"))?;
+
+        let mut data = Vec::new();
+        file.take(end.saturating_sub(start).saturating_add(1))
+            .read_to_end(&mut data)
+            .await
+            .map_err(|e| format!("Failed to read object {key}: {e}"))?;
+        Ok(data)
+    }
+
+    async fn size(&self, key: &str) -> Result<Option<i64>, String> {
+        match tokio::fs::metadata(self.path(key)?).await {
+            Ok(metadata) if metadata.is_file() => Ok(i64::try_from(metadata.len()).ok()),
+            Ok(_) => Ok(None),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!("Failed to inspect object {key}: {e}")),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), String> {
+        match tokio::fs::remove_file(self.path(key)?).await {
+            Err(e) if e.kind() != ErrorKind::NotFound => {
+                Err(format!("Failed to delete object {key}: {e}"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Staging files of interrupted writes are listed too
+    async fn list(&self, prefix: &str) -> Result<Vec<StoredObject>, String> {
+        let mut objects = Vec::new();
+        let mut directories = vec![self.root.clone()];
+        while let Some(directory) = directories.pop() {
+            let mut entries = match tokio::fs::read_dir(&directory).await {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                Err(e) => return Err(format!("Failed to list {}: {e}", directory.display())),
+            };
+            while let Some(entry) = entries
+                .next_entry()
+                .await
+                .map_err(|e| format!("Failed to list {}: {e}", directory.display()))?
+            {
+                let path = entry.path();
+                let metadata = entry
+                    .metadata()
+                    .await
+                    .map_err(|e| format!("Failed to inspect {}: {e}", path.display()))?;
+                if metadata.is_dir() {
+                    directories.push(path);
+                    continue;
+                }
+                // Keys always use `/`, whatever the platform
+                let Some(key) = path.strip_prefix(&self.root).ok().and_then(|relative| {
+                    relative
+                        .components()
+                        .map(|component| component.as_os_str().to_str())
+                        .collect::<Option<Vec<_>>>()
+                        .map(|parts| parts.join("/"))
+                }) else {
+                    continue;
+                };
+                if key.starts_with(prefix) {
+                    objects.push(StoredObject {
+                        key,
+                        size: i64::try_from(metadata.len()).unwrap_or(i64::MAX),
+                        last_modified: metadata.modified().ok().map(DateTime::<Utc>::from),
+                    });
+                }
+            }
+        }
+        objects.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(objects)
+    }
+
+    async fn copy(&self, source_key: &str, destination_key: &str) -> Result<(), String> {
+        let source = self.path(source_key)?;
+        let destination = self.path(destination_key)?;
+        let staging = Self::staging_path(&destination).await?;
+        if let Err(e) = tokio::fs::copy(&source, &staging).await {
+            let _ = tokio::fs::remove_file(&staging).await;
+            return Err(format!("Failed to copy object {source_key}: {e}"));
+        }
+        Self::commit(&staging, &destination).await
+    }
+
+    fn supports_presigned_urls(&self) -> bool {
+        false
+    }
+
+    async fn presigned_put(
+        &self,
+        _key: &str,
+        _content_type: Option<&str>,
+        _expires_in: Duration,
+    ) -> Result<PresignedPut, String> {
+        Err("Local storage does not support direct uploads".to_string())
+    }
+
+    async fn presigned_get(
+        &self,
+        _key: &str,
+        _expires_in: Duration,
+        _headers: &ResponseHeaders,
+    ) -> Result<String, String> {
+        Err("Local storage does not support direct downloads".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_local_backend_round_trip() {
+        let root = tempfile::tempdir().unwrap();
+        let backend = LocalBackend::new(root.path().join("storage"));
+        backend.ensure_container().await.unwrap();
+
+        let key = "app/test/experiments/1/INP_1.jpg";
+        assert_eq!(backend.size(key).await.unwrap(), None);
+        assert!(backend.get(key).await.is_err());
+
+        backend.put(key, b"0123456789".to_vec()).await.unwrap();
+        assert_eq!(backend.get(key).await.unwrap(), b"0123456789");
+        assert_eq!(backend.size(key).await.unwrap(), Some(10));
+        assert_eq!(backend.get_range(key, 2, 4).await.unwrap(), b"234");
+        assert_eq!(backend.get_range(key, 8, 20).await.unwrap(), b"89");
+        assert!(backend.get_range(key, 20, 30).await.unwrap().is_empty());
+
+        let copy = "app/test/blobs/sha256/abc";
+        backend.copy(key, copy).await.unwrap();
+        backend.put(key, b"replaced".to_vec()).await.unwrap();
+        assert_eq!(backend.get(copy).await.unwrap(), b"0123456789");
+        assert_eq!(backend.get(key).await.unwrap(), b"replaced");
+
+        let listed: Vec<(String, i64)> = backend
+            .list("app/test/")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|object| (object.key, object.size))
+            .collect();
+        assert_eq!(
+            listed,
+            [(copy.to_string(), 10), (key.to_string(), 8)],
+            "{listed:?}"
+        );
+        assert!(backend.list("app/other/").await.unwrap().is_empty());
+
+        backend.delete(key).await.unwrap();
+        backend.delete(key).await.unwrap();
+        assert_eq!(backend.size(key).await.unwrap(), None);
+        assert!(!backend.supports_presigned_urls());
+    }
+
+    #[tokio::test]
+    async fn test_local_backend_rejects_keys_outside_root() {
+        let root = tempfile::tempdir().unwrap();
+        let backend = LocalBackend::new(root.path());
+
+        for key in [
+            "",
+            "../escape",
+            "a/../../escape",
+            "/etc/passwd",
+            "a\\b",
+            "./a",
+        ] {
+            assert!(backend.put(key, vec![1]).await.is_err(), "{key}");
+            assert!(backend.size(key).await.is_err(), "{key}");
+        }
+    }
+}
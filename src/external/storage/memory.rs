@@ -0,0 +1,178 @@
+use super::{PresignedPut, ResponseHeaders, StorageBackend, StoredObject};
+use crate::config::Config;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// In-memory S3 mock for testing - stores files as byte arrays in a `HashMap`
+/// This provides fast, reliable testing without external dependencies
+pub struct MockS3Store {
+    files: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+}
+
+impl MockS3Store {
+    pub fn new() -> Self {
+        Self {
+            files: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn put_object(&self, key: &str, data: Vec<u8>) -> Result<(), String> {
+        self.files
+            .lock()
+            .map_err(|e| format!("Failed to acquire lock: {e}"))?
+            .insert(key.to_string(), data);
+        Ok(())
+    }
+
+    pub fn get_object(&self, key: &str) -> Result<Vec<u8>, String> {
+        self.files
+            .lock()
+            .map_err(|e| format!("Failed to acquire lock: {e}"))?
+            .get(key)
+            .cloned()
+            .ok_or_else(|| format!("Object not found: {key}"))
+    }
+
+    pub fn delete_object(&self, key: &str) -> Result<(), String> {
+        self.files
+            .lock()
+            .map_err(|e| format!("Failed to acquire lock: {e}"))?
+            .remove(key);
+        Ok(())
+    }
+
+    #[allow(dead_code)]
+    pub fn list_objects(&self) -> Result<Vec<String>, String> {
+        Ok(self
+            .files
+            .lock()
+            .map_err(|e| format!("Failed to acquire lock: {e}"))?
+            .keys()
+            .cloned()
+            .collect())
+    }
+}
+
+// Global mock store for tests - similar to how we handle the in-memory database
+pub static MOCK_S3_STORE: LazyLock<MockS3Store> = LazyLock::new(MockS3Store::new);
+
+/// Clear the mock S3 store - useful for test cleanup
+#[allow(dead_code)]
+pub fn clear_mock_s3_store() {
+    if let Ok(mut files) = MOCK_S3_STORE.files.lock() {
+        files.clear();
+    }
+}
+
+/// Backend used while tests run, on top of [`MOCK_S3_STORE`]
+pub struct MemoryBackend {
+    url: String,
+    bucket: String,
+}
+
+impl MemoryBackend {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            url: config.s3_url.trim_end_matches('/').to_string(),
+            bucket: config.s3_bucket_id.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl StorageBackend for MemoryBackend {
+    async fn ensure_container(&self) -> Result<(), String> {
+        Ok(())
+    }
+
+    async fn put(&self, key: &str, data: Vec<u8>) -> Result<(), String> {
+        MOCK_S3_STORE.put_object(key, data)
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>, String> {
+        MOCK_S3_STORE.get_object(key)
+    }
+
+    async fn get_range(&self, key: &str, start: u64, end: u64) -> Result<Vec<u8>, String> {
+        let data = MOCK_S3_STORE.get_object(key)?;
+        let from = usize::try_from(start).unwrap_or(usize::MAX).min(data.len());
+        let to = usize::try_from(end.saturating_add(1))
+            .unwrap_or(usize::MAX)
+            .min(data.len());
+        Ok(data[from..to.max(from)].to_vec())
+    }
+
+    async fn size(&self, key: &str) -> Result<Option<i64>, String> {
+        Ok(MOCK_S3_STORE
+            .get_object(key)
+            .ok()
+            .and_then(|data| i64::try_from(data.len()).ok()))
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), String> {
+        MOCK_S3_STORE.delete_object(key)
+    }
+
+    /// The mock store keeps no modification times
+    async fn list(&self, prefix: &str) -> Result<Vec<StoredObject>, String> {
+        let mut objects: Vec<StoredObject> = MOCK_S3_STORE
+            .files
+            .lock()
+            .map_err(|e| format!("Failed to acquire lock: {e}"))?
+            .iter()
+            .filter(|(key, _)| key.starts_with(prefix))
+            .map(|(key, data)| StoredObject {
+                key: key.clone(),
+                size: i64::try_from(data.len()).unwrap_or(i64::MAX),
+                last_modified: None,
+            })
+            .collect();
+        objects.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(objects)
+    }
+
+    async fn copy(&self, source_key: &str, destination_key: &str) -> Result<(), String> {
+        let data = MOCK_S3_STORE.get_object(source_key)?;
+        MOCK_S3_STORE.put_object(destination_key, data)
+    }
+
+    /// No request is signed; the URL only has the shape of a real one
+    async fn presigned_put(
+        &self,
+        key: &str,
+        content_type: Option<&str>,
+        expires_in: Duration,
+    ) -> Result<PresignedPut, String> {
+        Ok(PresignedPut {
+            url: format!(
+                "{}/{}/{}?X-Amz-Expires={}",
+                self.url,
+                self.bucket,
+                key,
+                expires_in.as_secs()
+            ),
+            headers: content_type
+                .map(|content_type| ("Content-Type".to_string(), content_type.to_string()))
+                .into_iter()
+                .collect(),
+        })
+    }
+
+    async fn presigned_get(
+        &self,
+        key: &str,
+        expires_in: Duration,
+        _headers: &ResponseHeaders,
+    ) -> Result<String, String> {
+        Ok(format!(
+            "{}/{}/{}?X-Amz-Expires={}",
+            self.url,
+            self.bucket,
+            key,
+            expires_in.as_secs()
+        ))
+    }
+}
@@ -0,0 +1,194 @@
+//! Object storage backends for assets.
+//!
+//! Objects are addressed by key, as in S3. The backend is chosen with
+//! `STORAGE_BACKEND`: `s3` (default), `minio`, `gcs` (through the
+//! S3-compatible XML API with HMAC keys), `azure` or `local`. Tests always use
+//! the in-memory store.
+
+pub mod azure;
+pub mod local;
+pub mod memory;
+pub mod s3;
+
+use crate::config::Config;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageKind {
+    #[default]
+    S3,
+    Minio,
+    Gcs,
+    Azure,
+    Local,
+}
+
+impl StorageKind {
+    /// Whether the backend is reached through the S3 API and needs the `S3_*`
+    /// settings
+    pub fn uses_s3_api(self) -> bool {
+        matches!(self, Self::S3 | Self::Minio | Self::Gcs)
+    }
+}
+
+impl FromStr for StorageKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "s3" => Ok(Self::S3),
+            "minio" => Ok(Self::Minio),
+            "gcs" => Ok(Self::Gcs),
+            "azure" => Ok(Self::Azure),
+            "local" => Ok(Self::Local),
+            other => Err(format!(
+                "Unknown storage backend '{other}', expected s3, minio, gcs, azure or local"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for StorageKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::S3 => "s3",
+            Self::Minio => "minio",
+            Self::Gcs => "gcs",
+            Self::Azure => "azure",
+            Self::Local => "local",
+        })
+    }
+}
+
+/// A request a client can send to upload one object without going through the API
+pub struct PresignedPut {
+    pub url: String,
+    /// Headers the client must send with the `PUT`
+    pub headers: Vec<(String, String)>,
+}
+
+/// An object found when listing the store
+#[derive(Clone, Debug)]
+pub struct StoredObject {
+    pub key: String,
+    pub size: i64,
+    /// `None` when the store does not report it
+    pub last_modified: Option<DateTime<Utc>>,
+}
+
+/// Headers the store should send when serving a presigned download
+pub struct ResponseHeaders {
+    pub content_type: String,
+    pub content_disposition: String,
+    pub cache_control: String,
+}
+
+#[async_trait]
+pub trait StorageBackend: Send + Sync {
+    /// Create the bucket, container or directory if it does not exist yet
+    async fn ensure_container(&self) -> Result<(), String>;
+
+    async fn put(&self, key: &str, data: Vec<u8>) -> Result<(), String>;
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>, String>;
+
+    /// Bytes `start..=end` of an object. The result is shorter than requested
+    /// if the object ends before `end`.
+    async fn get_range(&self, key: &str, start: u64, end: u64) -> Result<Vec<u8>, String>;
+
+    /// Object size, or `None` when the object does not exist
+    async fn size(&self, key: &str) -> Result<Option<i64>, String>;
+
+    /// Deleting a missing object is not an error
+    async fn delete(&self, key: &str) -> Result<(), String>;
+
+    /// Every object whose key starts with `prefix`
+    async fn list(&self, prefix: &str) -> Result<Vec<StoredObject>, String>;
+
+    /// Server-side copy within the store
+    async fn copy(&self, source_key: &str, destination_key: &str) -> Result<(), String>;
+
+    /// Whether clients can reach objects directly with
+    /// [`StorageBackend::presigned_put`] and [`StorageBackend::presigned_get`]
+    fn supports_presigned_urls(&self) -> bool {
+        true
+    }
+
+    async fn presigned_put(
+        &self,
+        key: &str,
+        content_type: Option<&str>,
+        expires_in: Duration,
+    ) -> Result<PresignedPut, String>;
+
+    /// URL that lets a client download one object directly. The store answers
+    /// with the given response headers instead of the object's own.
+    async fn presigned_get(
+        &self,
+        key: &str,
+        expires_in: Duration,
+        headers: &ResponseHeaders,
+    ) -> Result<String, String>;
+}
+
+/// The backend selected by the configuration
+pub async fn backend(config: &Config) -> Box<dyn StorageBackend> {
+    if config.tests_running {
+        return Box::new(memory::MemoryBackend::new(config));
+    }
+
+    match config.storage_backend {
+        StorageKind::S3 | StorageKind::Minio | StorageKind::Gcs => {
+            Box::new(s3::S3Backend::new(config).await)
+        }
+        StorageKind::Azure => Box::new(azure::AzureBlobBackend::new(config)),
+        StorageKind::Local => Box::new(local::LocalBackend::new(&config.storage_local_path)),
+    }
+}
+
+/// Percent-encode a key for use in a URL path, keeping `/`
+fn encode_key(key: &str) -> String {
+    use std::fmt::Write;
+
+    key.bytes()
+        .fold(String::with_capacity(key.len()), |mut encoded, byte| {
+            match byte {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                    encoded.push(char::from(byte));
+                }
+                _ => {
+                    let _ = write!(encoded, "%{byte:02X}");
+                }
+            }
+            encoded
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_storage_kind_from_str() {
+        assert_eq!("S3".parse::<StorageKind>(), Ok(StorageKind::S3));
+        assert_eq!(" local ".parse::<StorageKind>(), Ok(StorageKind::Local));
+        assert_eq!("gcs".parse::<StorageKind>(), Ok(StorageKind::Gcs));
+        assert!("ftp".parse::<StorageKind>().is_err());
+        assert!(StorageKind::Minio.uses_s3_api());
+        assert!(!StorageKind::Azure.uses_s3_api());
+    }
+
+    #[test]
+    fn test_encode_key() {
+        assert_eq!(
+            encode_key("app/exp 1/INP_1+ü.jpg"),
+            "app/exp%201/INP_1%2B%C3%BC.jpg"
+        );
+    }
+}
@@ -0,0 +1,276 @@
+//! S3-compatible object storage: AWS S3, `MinIO` and Google Cloud Storage
+//! through its XML API with HMAC keys
+
+use super::{PresignedPut, ResponseHeaders, StorageBackend, StorageKind, StoredObject, encode_key};
+use crate::config::Config;
+use async_trait::async_trait;
+use aws_config::BehaviorVersion;
+use aws_sdk_s3::Client as S3Client;
+use aws_sdk_s3::config::{
+    Credentials, Region, RequestChecksumCalculation, ResponseChecksumValidation,
+};
+use aws_sdk_s3::operation::head_object::HeadObjectError;
+use chrono::DateTime;
+use std::time::Duration;
+
+pub struct S3Backend {
+    client: S3Client,
+    bucket: String,
+}
+
+impl S3Backend {
+    pub async fn new(config: &Config) -> Self {
+        // GCS ignores the region but requires "auto" in the signature
+        let region = match config.storage_backend {
+            StorageKind::Gcs => Region::new("auto"),
+            _ => Region::new("us-east-1"),
+        };
+        let credentials = Credentials::new(
+            &config.s3_access_key,
+            &config.s3_secret_key,
+            None,
+            None,
+            "manual",
+        );
+        let shared_config = aws_config::defaults(BehaviorVersion::latest())
+            .region(region)
+            .credentials_provider(credentials)
+            .endpoint_url(&config.s3_url)
+            .load()
+            .await;
+
+        // Use path-style addressing for MinIO compatibility
+        let mut s3_config =
+            aws_sdk_s3::config::Builder::from(&shared_config).force_path_style(true);
+        if config.storage_backend == StorageKind::Gcs {
+            // The XML API rejects the flexible checksum headers sent by default
+            s3_config = s3_config
+                .request_checksum_calculation(RequestChecksumCalculation::WhenRequired)
+                .response_checksum_validation(ResponseChecksumValidation::WhenRequired);
+        }
+
+        Self {
+            client: S3Client::from_conf(s3_config.build()),
+            bucket: config.s3_bucket_id.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl StorageBackend for S3Backend {
+    async fn ensure_container(&self) -> Result<(), String> {
+        // Check if bucket exists by trying to list objects
+        if self
+            .client
+            .list_objects_v2()
+            .bucket(&self.bucket)
+            .max_keys(1)
+            .send()
+            .await
+            .is_ok()
+        {
+            return Ok(());
+        }
+
+        // Bucket doesn't exist or isn't accessible, try to create it
+        match self
+            .client
+            .create_bucket()
+            .bucket(&self.bucket)
+            .send()
+            .await
+        {
+            Ok(_) => {
+                println!("Created S3 bucket: {}", self.bucket);
+                Ok(())
+            }
+            Err(err) => Err(format!("Failed to create S3 bucket {}: {err}", self.bucket)),
+        }
+    }
+
+    async fn put(&self, key: &str, data: Vec<u8>) -> Result<(), String> {
+        let body = aws_sdk_s3::primitives::ByteStream::from(data);
+
+        match self
+            .client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .body(body)
+            .send()
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(err) => Err(format!("Failed to upload object to S3: {err}")),
+        }
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>, String> {
+        match self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+        {
+            Ok(response) => {
+                let body = response
+                    .body
+                    .collect()
+                    .await
+                    .map_err(|e| format!("Failed to read S3 object body: {e}"))?;
+                Ok(body.into_bytes().to_vec())
+            }
+            Err(err) => Err(format!("Failed to get object from S3: {err}")),
+        }
+    }
+
+    async fn get_range(&self, key: &str, start: u64, end: u64) -> Result<Vec<u8>, String> {
+        match self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .range(format!("bytes={start}-{end}"))
+            .send()
+            .await
+        {
+            Ok(response) => {
+                let body = response
+                    .body
+                    .collect()
+                    .await
+                    .map_err(|e| format!("Failed to read S3 object body: {e}"))?;
+                Ok(body.into_bytes().to_vec())
+            }
+            Err(err) => Err(format!("Failed to get object range from S3: {err}")),
+        }
+    }
+
+    async fn size(&self, key: &str) -> Result<Option<i64>, String> {
+        match self
+            .client
+            .head_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+        {
+            Ok(response) => Ok(Some(response.content_length().unwrap_or_default())),
+            Err(err)
+                if err
+                    .as_service_error()
+                    .is_some_and(HeadObjectError::is_not_found) =>
+            {
+                Ok(None)
+            }
+            Err(err) => Err(format!("Failed to inspect S3 object: {err}")),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), String> {
+        match self
+            .client
+            .delete_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(err) => Err(format!("Failed to delete object from S3: {err}")),
+        }
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<StoredObject>, String> {
+        let mut pages = self
+            .client
+            .list_objects_v2()
+            .bucket(&self.bucket)
+            .prefix(prefix)
+            .into_paginator()
+            .send();
+
+        let mut objects = Vec::new();
+        while let Some(page) = pages.next().await {
+            let page = page.map_err(|err| format!("Failed to list S3 objects: {err}"))?;
+            objects.extend(page.contents().iter().filter_map(|object| {
+                Some(StoredObject {
+                    key: object.key()?.to_string(),
+                    size: object.size().unwrap_or_default(),
+                    last_modified: object.last_modified().and_then(|time| {
+                        DateTime::from_timestamp(time.secs(), time.subsec_nanos())
+                    }),
+                })
+            }));
+        }
+        Ok(objects)
+    }
+
+    /// A single copy request is limited to 5 GiB
+    async fn copy(&self, source_key: &str, destination_key: &str) -> Result<(), String> {
+        match self
+            .client
+            .copy_object()
+            .bucket(&self.bucket)
+            .copy_source(format!("{}/{}", self.bucket, encode_key(source_key)))
+            .key(destination_key)
+            .send()
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(err) => Err(format!("Failed to copy object in S3: {err}")),
+        }
+    }
+
+    async fn presigned_put(
+        &self,
+        key: &str,
+        content_type: Option<&str>,
+        expires_in: Duration,
+    ) -> Result<PresignedPut, String> {
+        let presigning = aws_sdk_s3::presigning::PresigningConfig::expires_in(expires_in)
+            .map_err(|e| format!("Invalid presigning expiry: {e}"))?;
+
+        let request = self
+            .client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .set_content_type(content_type.map(ToString::to_string))
+            .presigned(presigning)
+            .await
+            .map_err(|err| format!("Failed to presign S3 upload: {err}"))?;
+
+        Ok(PresignedPut {
+            url: request.uri().to_string(),
+            headers: request
+                .headers()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        })
+    }
+
+    async fn presigned_get(
+        &self,
+        key: &str,
+        expires_in: Duration,
+        headers: &ResponseHeaders,
+    ) -> Result<String, String> {
+        let presigning = aws_sdk_s3::presigning::PresigningConfig::expires_in(expires_in)
+            .map_err(|e| format!("Invalid presigning expiry: {e}"))?;
+
+        self.client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .response_content_type(&headers.content_type)
+            .response_content_disposition(&headers.content_disposition)
+            .response_cache_control(&headers.cache_control)
+            .presigned(presigning)
+            .await
+            .map(|request| request.uri().to_string())
+            .map_err(|err| format!("Failed to presign S3 download: {err}"))
+    }
+}
@@ -0,0 +1,106 @@
+use crate::config::Config;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Hourly variables requested from the provider, in the order of `WeatherHour`
+const HOURLY_VARIABLES: &str =
+    "temperature_2m,wind_speed_10m,wind_direction_10m,boundary_layer_height";
+
+/// Reanalysis values for the hour starting at `time`
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct WeatherHour {
+    pub time: DateTime<Utc>,
+    /// Air temperature 2 m above ground
+    pub temperature_c: Option<f64>,
+    /// Wind speed 10 m above ground
+    pub wind_speed_m_s: Option<f64>,
+    /// Direction the wind comes from, 10 m above ground
+    pub wind_direction_deg: Option<f64>,
+    pub boundary_layer_height_m: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct HourlyResponse {
+    hourly: Hourly,
+}
+
+#[derive(Deserialize)]
+struct Hourly {
+    time: Vec<String>,
+    #[serde(default)]
+    temperature_2m: Vec<Option<f64>>,
+    #[serde(default)]
+    wind_speed_10m: Vec<Option<f64>>,
+    #[serde(default)]
+    wind_direction_10m: Vec<Option<f64>>,
+    #[serde(default)]
+    boundary_layer_height: Vec<Option<f64>>,
+}
+
+/// Fetch hourly weather at a point for whole UTC days from the configured
+/// Open-Meteo compatible API
+pub async fn fetch_hourly(
+    config: &Config,
+    latitude: f64,
+    longitude: f64,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+) -> Result<Vec<WeatherHour>, String> {
+    let url = config
+        .weather_api_url
+        .as_ref()
+        .ok_or_else(|| "Weather provider is not configured".to_string())?;
+
+    let mut query = vec![
+        ("latitude", latitude.to_string()),
+        ("longitude", longitude.to_string()),
+        ("start_date", start_date.to_string()),
+        ("end_date", end_date.to_string()),
+        ("hourly", HOURLY_VARIABLES.to_string()),
+        ("wind_speed_unit", "ms".to_string()),
+        ("timezone", "GMT".to_string()),
+    ];
+    if let Some(key) = &config.weather_api_key {
+        query.push(("apikey", key.clone()));
+    }
+
+    let response = reqwest::Client::new()
+        .get(url)
+        .query(&query)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach the weather provider: {e}"))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!(
+            "Weather provider rejected the request ({status}): {body}"
+        ));
+    }
+
+    let hourly = response
+        .json::<HourlyResponse>()
+        .await
+        .map_err(|e| format!("Failed to parse weather provider response: {e}"))?
+        .hourly;
+    let value = |values: &[Option<f64>], i: usize| values.get(i).copied().flatten();
+    hourly
+        .time
+        .iter()
+        .enumerate()
+        .map(|(i, time)| {
+            let time = NaiveDateTime::parse_from_str(time, "%Y-%m-%dT%H:%M")
+                .map_err(|e| format!("Weather provider returned an invalid time '{time}': {e}"))?
+                .and_utc();
+            Ok(WeatherHour {
+                time,
+                temperature_c: value(&hourly.temperature_2m, i),
+                wind_speed_m_s: value(&hourly.wind_speed_10m, i),
+                wind_direction_deg: value(&hourly.wind_direction_10m, i),
+                boundary_layer_height_m: value(&hourly.boundary_layer_height, i),
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,108 @@
+use crate::config::Config;
+use crate::services::datacite_service::DataCiteMetadata;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::fmt::Write;
+
+/// Draft deposition created on Zenodo with a reserved DOI
+pub struct ZenodoDeposition {
+    pub id: i64,
+    pub doi: String,
+    pub html_url: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct DepositionResponse {
+    id: i64,
+    #[serde(default)]
+    links: Value,
+    #[serde(default)]
+    metadata: Value,
+}
+
+/// Map `DataCite` metadata onto Zenodo's deposition metadata
+fn deposition_metadata(metadata: &DataCiteMetadata) -> Value {
+    let description = metadata
+        .descriptions
+        .iter()
+        .fold(String::new(), |mut html, d| {
+            let _ = write!(html, "<p>{}</p>", d.description);
+            html
+        });
+    let locations: Vec<Value> = metadata
+        .geo_locations
+        .iter()
+        .filter_map(|location| {
+            let point = location.geo_location_point.as_ref()?;
+            Some(json!({
+                "lat": point.point_latitude.parse::<f64>().ok()?,
+                "lon": point.point_longitude.parse::<f64>().ok()?,
+                "place": location.geo_location_place.clone().unwrap_or_default(),
+            }))
+        })
+        .collect();
+
+    json!({
+        "metadata": {
+            "upload_type": "dataset",
+            "title": metadata.titles.first().map(|t| t.title.clone()).unwrap_or_default(),
+            "description": description,
+            "creators": metadata.creators.iter().map(|c| json!({"name": c.name})).collect::<Vec<_>>(),
+            "keywords": metadata.subjects.iter().map(|s| s.subject.clone()).collect::<Vec<_>>(),
+            "publication_date": chrono::Utc::now().format("%Y-%m-%d").to_string(),
+            "locations": locations,
+            "related_identifiers": metadata.related_identifiers.iter().map(|r| json!({
+                "identifier": r.related_identifier,
+                "relation": "hasPart",
+                "scheme": "doi",
+            })).collect::<Vec<_>>(),
+            "prereserve_doi": true,
+        }
+    })
+}
+
+/// Create a draft deposition on Zenodo and reserve its DOI.
+///
+/// Files are attached and the deposition published from Zenodo itself, so the
+/// DOI only resolves once a curator has reviewed the upload.
+pub async fn create_deposition(
+    config: &Config,
+    metadata: &DataCiteMetadata,
+) -> Result<ZenodoDeposition, String> {
+    let token = config
+        .zenodo_access_token
+        .as_ref()
+        .ok_or_else(|| "Zenodo access token is not configured".to_string())?;
+
+    let response = reqwest::Client::new()
+        .post(format!(
+            "{}/deposit/depositions",
+            config.zenodo_url.trim_end_matches('/')
+        ))
+        .bearer_auth(token)
+        .json(&deposition_metadata(metadata))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach Zenodo: {e}"))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("Zenodo rejected the deposition ({status}): {body}"));
+    }
+
+    let deposition: DepositionResponse = response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse Zenodo response: {e}"))?;
+    let doi = deposition.metadata["prereserve_doi"]["doi"]
+        .as_str()
+        .ok_or_else(|| "Zenodo did not reserve a DOI for the deposition".to_string())?
+        .to_string();
+
+    Ok(ZenodoDeposition {
+        id: deposition.id,
+        doi,
+        html_url: deposition.links["html"].as_str().map(ToString::to_string),
+    })
+}
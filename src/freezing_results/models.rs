@@ -0,0 +1,82 @@
+use chrono::{DateTime, Utc};
+use crudcrate::EntityToModels;
+use rust_decimal::Decimal;
+use sea_orm::entity::prelude::*;
+use uuid::Uuid;
+
+/// Result of a well of an experiment, denormalised from its phase
+/// transitions, regions and probe readings when the experiment was last
+/// processed or its results recomputed
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, EntityToModels)]
+#[sea_orm(table_name = "freezing_results")]
+#[crudcrate(api_struct = "FreezingResult")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    #[crudcrate(primary_key, update_model = false, create_model = false, on_create = Uuid::new_v4())]
+    pub id: Uuid,
+    #[crudcrate(sortable, filterable)]
+    pub experiment_id: Uuid,
+    #[crudcrate(sortable, filterable)]
+    pub well_id: Uuid,
+    /// Name of the tray, such as `P1`
+    pub tray_name: Option<String>,
+    /// Well coordinate, such as `A1`
+    pub well_coordinate: String,
+    /// Region of the experiment the well is in
+    pub region_name: Option<String>,
+    #[crudcrate(sortable, filterable)]
+    pub treatment_id: Option<Uuid>,
+    pub treatment_name: Option<String>,
+    /// Sample of the treatment
+    pub sample_id: Option<Uuid>,
+    pub dilution_factor: Option<i32>,
+    /// The well froze during the experiment
+    pub frozen: bool,
+    /// Time of the first reading the well was frozen in
+    pub freezing_timestamp: Option<DateTime<Utc>>,
+    /// Seconds from the first reading of the experiment to freezing
+    pub freezing_time_seconds: Option<i64>,
+    /// Mean calibrated temperature of the probes at freezing, in °C
+    pub freezing_temperature_avg: Option<Decimal>,
+    /// Mean temperature of the probes at freezing as logged, in °C
+    pub freezing_temperature_raw_avg: Option<Decimal>,
+    /// Calibrated temperature at the well's position at freezing, in °C
+    pub freezing_temperature_interpolated: Option<Decimal>,
+    /// The well is excluded from the experiment's statistics
+    pub excluded: bool,
+    pub computed_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "crate::experiments::models::Entity",
+        from = "Column::ExperimentId",
+        to = "crate::experiments::models::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Experiments,
+    #[sea_orm(
+        belongs_to = "crate::tray_configurations::wells::models::Entity",
+        from = "Column::WellId",
+        to = "crate::tray_configurations::wells::models::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Wells,
+}
+
+impl Related<crate::experiments::models::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Experiments.def()
+    }
+}
+
+impl Related<crate::tray_configurations::wells::models::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Wells.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,257 @@
+//! Freezing results kept per well.
+//!
+//! Each well of an experiment's trays gets a row: whether it froze, and when
+//! it did, at what temperature, as for its nucleation event. The rows are
+//! written when the experiment's results are kept, as processing finishes or
+//! they are recomputed, so tools downstream can read them as a plain table
+//! without building the results. Rows show the regions and excluded wells as
+//! they were then; `computed_at` tells how recent they are.
+
+use super::models::{ActiveModel, Column, Entity as FreezingResults, FreezingResult, Model};
+use crate::changes::services::Reader;
+use crate::experiments::models as experiments;
+use crate::experiments::phase_transitions::models as phase_transitions;
+use crate::nucleation_events::services::{build_event, load_context, readable_experiments};
+use crate::tray_configurations::{trays::models as trays, wells::models as wells};
+use chrono::Utc;
+use sea_orm::{
+    ActiveValue::Set, ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait,
+    QueryFilter, QueryOrder, Select,
+};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+/// Rows inserted per statement
+const ROWS_PER_INSERT: usize = 500;
+
+/// Wells of the experiment's trays, in tray order
+async fn experiment_wells(
+    db: &impl ConnectionTrait,
+    experiment: &experiments::Model,
+) -> Result<Vec<wells::Model>, DbErr> {
+    let Some(tray_configuration_id) = experiment.tray_configuration_id else {
+        return Ok(Vec::new());
+    };
+    wells::Entity::find()
+        .inner_join(trays::Entity)
+        .filter(trays::Column::TrayConfigurationId.eq(tray_configuration_id))
+        .order_by_asc(trays::Column::OrderSequence)
+        .order_by_asc(wells::Column::RowLetter)
+        .order_by_asc(wells::Column::ColumnNumber)
+        .all(db)
+        .await
+}
+
+async fn insert_results(db: &impl ConnectionTrait, rows: &[Model]) -> Result<(), DbErr> {
+    for chunk in rows.chunks(ROWS_PER_INSERT) {
+        FreezingResults::insert_many(chunk.iter().cloned().map(|row| ActiveModel {
+            id: Set(row.id),
+            experiment_id: Set(row.experiment_id),
+            well_id: Set(row.well_id),
+            tray_name: Set(row.tray_name),
+            well_coordinate: Set(row.well_coordinate),
+            region_name: Set(row.region_name),
+            treatment_id: Set(row.treatment_id),
+            treatment_name: Set(row.treatment_name),
+            sample_id: Set(row.sample_id),
+            dilution_factor: Set(row.dilution_factor),
+            frozen: Set(row.frozen),
+            freezing_timestamp: Set(row.freezing_timestamp),
+            freezing_time_seconds: Set(row.freezing_time_seconds),
+            freezing_temperature_avg: Set(row.freezing_temperature_avg),
+            freezing_temperature_raw_avg: Set(row.freezing_temperature_raw_avg),
+            freezing_temperature_interpolated: Set(row.freezing_temperature_interpolated),
+            excluded: Set(row.excluded),
+            computed_at: Set(row.computed_at),
+        }))
+        .exec(db)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Delete the freezing results kept for the experiment
+pub async fn delete_freezing_results(
+    db: &impl ConnectionTrait,
+    experiment_id: Uuid,
+) -> Result<(), DbErr> {
+    FreezingResults::delete_many()
+        .filter(Column::ExperimentId.eq(experiment_id))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// Build the freezing results of the experiment from its phase transitions,
+/// in place of those kept. An experiment without transitions has none.
+pub async fn populate_freezing_results(
+    db: &impl ConnectionTrait,
+    experiment_id: Uuid,
+) -> Result<Vec<FreezingResult>, DbErr> {
+    let experiment = experiments::Entity::find_by_id(experiment_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Experiment not found".to_string()))?;
+    delete_freezing_results(db, experiment_id).await?;
+
+    // The first freezing of each well
+    let mut transitions: Vec<(phase_transitions::Model, wells::Model)> = Vec::new();
+    let mut seen = HashSet::new();
+    for (transition, well) in phase_transitions::Entity::find()
+        .filter(phase_transitions::Column::ExperimentId.eq(experiment_id))
+        .filter(phase_transitions::Column::PreviousState.eq(0))
+        .filter(phase_transitions::Column::NewState.eq(1))
+        .inner_join(wells::Entity)
+        .select_also(wells::Entity)
+        .order_by_asc(phase_transitions::Column::Timestamp)
+        .all(db)
+        .await?
+    {
+        if let Some(well) = well
+            && seen.insert(well.id)
+        {
+            transitions.push((transition, well));
+        }
+    }
+    if transitions.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut wells = experiment_wells(db, &experiment).await?;
+    // Wells no longer in the tray configuration keep their transitions
+    wells.extend(
+        transitions
+            .iter()
+            .filter(|(_, well)| !wells.iter().any(|w| w.id == well.id))
+            .map(|(_, well)| well.clone())
+            .collect::<Vec<_>>(),
+    );
+    let context = load_context(
+        db,
+        &HashSet::from([experiment_id]),
+        wells.iter().map(|well| well.tray_id).collect(),
+        &transitions,
+    )
+    .await?;
+    let frozen: HashMap<Uuid, &phase_transitions::Model> = transitions
+        .iter()
+        .map(|(transition, well)| (well.id, transition))
+        .collect();
+
+    let computed_at = Utc::now();
+    let rows: Vec<Model> = wells
+        .iter()
+        .filter_map(|well| {
+            let transition = frozen.get(&well.id).copied();
+            let event = build_event(&context, experiment_id, well, transition)?;
+            let region = context.region(experiment_id, well);
+            Some(Model {
+                id: Uuid::new_v4(),
+                experiment_id,
+                well_id: well.id,
+                tray_name: event.tray_name,
+                well_coordinate: event.well_coordinate,
+                region_name: region.and_then(|region| region.name.clone()),
+                treatment_id: event.treatment_id,
+                treatment_name: event.treatment_name,
+                sample_id: event
+                    .treatment_id
+                    .and_then(|id| context.treatment(id))
+                    .and_then(|treatment| treatment.sample_id),
+                dilution_factor: event.dilution_factor,
+                frozen: transition.is_some(),
+                freezing_timestamp: transition.map(|transition| transition.timestamp),
+                freezing_time_seconds: event.nucleation_time_seconds,
+                freezing_temperature_avg: event.nucleation_temperature_avg_celsius,
+                freezing_temperature_raw_avg: event.nucleation_temperature_raw_avg_celsius,
+                freezing_temperature_interpolated: event
+                    .nucleation_temperature_interpolated_celsius,
+                excluded: event.excluded,
+                computed_at,
+            })
+        })
+        .collect();
+
+    insert_results(db, &rows).await?;
+    Ok(rows.into_iter().map(Into::into).collect())
+}
+
+/// Results in tray and well order, of the experiments the user may read
+async fn readable_results(
+    db: &DatabaseConnection,
+    reader: &Reader,
+    select: Select<FreezingResults>,
+) -> Result<Vec<FreezingResult>, DbErr> {
+    let results = select
+        .inner_join(wells::Entity)
+        .order_by_asc(Column::ExperimentId)
+        .order_by_asc(Column::TrayName)
+        .order_by_asc(wells::Column::RowLetter)
+        .order_by_asc(wells::Column::ColumnNumber)
+        .all(db)
+        .await?;
+    let readable = readable_experiments(
+        db,
+        reader,
+        results.iter().map(|result| result.experiment_id).collect(),
+    )
+    .await;
+    Ok(results
+        .into_iter()
+        .filter(|result| readable.contains(&result.experiment_id))
+        .map(Into::into)
+        .collect())
+}
+
+/// Freezing results of each well of the experiment
+pub async fn experiment_freezing_results(
+    db: &DatabaseConnection,
+    reader: &Reader,
+    experiment_id: Uuid,
+) -> Result<Vec<FreezingResult>, DbErr> {
+    let not_found = || DbErr::RecordNotFound("Experiment not found".to_string());
+    experiments::Entity::find_by_id(experiment_id)
+        .one(db)
+        .await?
+        .ok_or_else(not_found)?;
+    if readable_experiments(db, reader, HashSet::from([experiment_id]))
+        .await
+        .is_empty()
+    {
+        return Err(not_found());
+    }
+    readable_results(
+        db,
+        reader,
+        FreezingResults::find().filter(Column::ExperimentId.eq(experiment_id)),
+    )
+    .await
+}
+
+/// Freezing results of the well in each experiment it was used in
+pub async fn well_freezing_results(
+    db: &DatabaseConnection,
+    reader: &Reader,
+    well_id: Uuid,
+) -> Result<Vec<FreezingResult>, DbErr> {
+    readable_results(
+        db,
+        reader,
+        FreezingResults::find().filter(Column::WellId.eq(well_id)),
+    )
+    .await
+}
+
+/// Freezing results of the wells of the treatment's regions
+pub async fn treatment_freezing_results(
+    db: &DatabaseConnection,
+    reader: &Reader,
+    treatment_id: Uuid,
+) -> Result<Vec<FreezingResult>, DbErr> {
+    readable_results(
+        db,
+        reader,
+        FreezingResults::find().filter(Column::TreatmentId.eq(treatment_id)),
+    )
+    .await
+}
@@ -0,0 +1,104 @@
+use crate::config::test_helpers::{send_json, setup_test_app};
+use axum::http::StatusCode;
+use serde_json::json;
+
+#[tokio::test]
+async fn test_freezing_results_are_kept_per_well() {
+    let app = setup_test_app().await;
+    let (status, demo) = send_json(&app, "POST", "/api/maintenance/demo", None).await;
+    assert_eq!(status, StatusCode::CREATED, "{demo}");
+    let experiment_id = demo["experiment_id"].as_str().unwrap();
+
+    // Written as processing finished
+    let uri = format!("/api/freezing_results/experiments/{experiment_id}");
+    let (status, results) = send_json(&app, "GET", &uri, None).await;
+    assert_eq!(status, StatusCode::OK, "{results}");
+    let results = results.as_array().unwrap();
+    assert_eq!(results.len(), 192);
+    assert!(results.iter().all(|result| result["frozen"] == true));
+    let first = &results[0];
+    assert_eq!(first["tray_name"], "P1");
+    assert_eq!(first["well_coordinate"], "A1");
+    assert_eq!(first["region_name"], "Untreated");
+    assert_eq!(first["sample_id"], demo["sample_ids"][0]);
+    assert_eq!(first["excluded"], false);
+    assert!(first["freezing_temperature_avg"].is_string());
+    assert!(first["freezing_time_seconds"].as_i64().unwrap() > 0);
+    assert_eq!(results[1]["well_coordinate"], "A2");
+
+    let well_id = first["well_id"].as_str().unwrap();
+    let (_, by_well) = send_json(
+        &app,
+        "GET",
+        &format!("/api/freezing_results/wells/{well_id}"),
+        None,
+    )
+    .await;
+    assert_eq!(by_well, json!([first]));
+
+    let heat = results
+        .iter()
+        .find(|result| result["region_name"] == "Heat treated")
+        .unwrap();
+    let (_, by_treatment) = send_json(
+        &app,
+        "GET",
+        &format!(
+            "/api/freezing_results/treatments/{}",
+            heat["treatment_id"].as_str().unwrap()
+        ),
+        None,
+    )
+    .await;
+    let by_treatment = by_treatment.as_array().unwrap();
+    assert_eq!(by_treatment.len(), 48);
+    assert!(
+        by_treatment
+            .iter()
+            .all(|result| result["tray_name"] == "P1")
+    );
+
+    // Rewritten as the results are recomputed
+    let (status, _) = send_json(
+        &app,
+        "PUT",
+        &format!("/api/experiments/{experiment_id}/excluded-wells"),
+        Some(&json!({ "wells": [{"coordinate": "P1:A1"}] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        &format!("/api/experiments/{experiment_id}/results/recompute"),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, results) = send_json(&app, "GET", &uri, None).await;
+    assert_eq!(results.as_array().unwrap().len(), 192);
+    assert_eq!(results[0]["excluded"], true);
+    assert_eq!(results[1]["excluded"], false);
+}
+
+#[tokio::test]
+async fn test_freezing_results_of_unknown_experiment() {
+    let app = setup_test_app().await;
+    let (status, _) = send_json(
+        &app,
+        "GET",
+        &format!("/api/freezing_results/experiments/{}", uuid::Uuid::new_v4()),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, results) = send_json(
+        &app,
+        "GET",
+        &format!("/api/freezing_results/wells/{}", uuid::Uuid::new_v4()),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(results, json!([]));
+}
@@ -0,0 +1,139 @@
+use super::models::FreezingResult;
+use super::services::{
+    experiment_freezing_results, treatment_freezing_results, well_freezing_results,
+};
+use crate::api_keys::services::accept_api_keys;
+use crate::changes::views::reader;
+use crate::common::auth::{Role, RouteAccess, require_role};
+use crate::common::keycloak::authenticate;
+use crate::common::labs::Labs;
+use crate::common::redaction::redact_for_viewers;
+use crate::common::state::AppState;
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+    http::StatusCode,
+    middleware,
+};
+use axum_keycloak_auth::{PassthroughMode, decode::KeycloakToken};
+use sea_orm::DbErr;
+use utoipa_axum::{router::OpenApiRouter, routes};
+use uuid::Uuid;
+
+fn error_response(e: DbErr) -> (StatusCode, String) {
+    match e {
+        DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+        e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+/// Freezing results of an experiment
+#[utoipa::path(
+    get,
+    path = "/experiments/{experiment_id}",
+    params(
+        ("experiment_id" = Uuid, Path, description = "Experiment UUID")
+    ),
+    responses(
+        (status = 200, description = "A result per well, in tray and well order", body = Vec<FreezingResult>),
+        (status = 404, description = "Experiment not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "freezing_results",
+    summary = "Freezing results of an experiment",
+    description = "List a row per well of the experiment's trays: whether it froze, when, and the temperature of the probes and at the well then, with the region, treatment, sample and dilution it held. Rows are written when the experiment is processed or its results recomputed; an experiment not yet processed has none"
+)]
+pub async fn get_experiment_freezing_results(
+    State(state): State<AppState>,
+    token: Option<Extension<KeycloakToken<Role>>>,
+    labs: Option<Extension<Labs>>,
+    Path(experiment_id): Path<Uuid>,
+) -> Result<Json<Vec<FreezingResult>>, (StatusCode, String)> {
+    experiment_freezing_results(&state.db, &reader(token, labs), experiment_id)
+        .await
+        .map(Json)
+        .map_err(error_response)
+}
+
+/// Freezing results of a well
+#[utoipa::path(
+    get,
+    path = "/wells/{well_id}",
+    params(
+        ("well_id" = Uuid, Path, description = "Well UUID")
+    ),
+    responses(
+        (status = 200, description = "A result per experiment the well was used in", body = Vec<FreezingResult>),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "freezing_results",
+    summary = "Freezing results of a well",
+    description = "List the results of the well in each processed experiment of its tray configuration that the user may read"
+)]
+pub async fn get_well_freezing_results(
+    State(state): State<AppState>,
+    token: Option<Extension<KeycloakToken<Role>>>,
+    labs: Option<Extension<Labs>>,
+    Path(well_id): Path<Uuid>,
+) -> Result<Json<Vec<FreezingResult>>, (StatusCode, String)> {
+    well_freezing_results(&state.db, &reader(token, labs), well_id)
+        .await
+        .map(Json)
+        .map_err(error_response)
+}
+
+/// Freezing results of a treatment
+#[utoipa::path(
+    get,
+    path = "/treatments/{treatment_id}",
+    params(
+        ("treatment_id" = Uuid, Path, description = "Treatment UUID")
+    ),
+    responses(
+        (status = 200, description = "A result per well of the treatment's regions, by experiment", body = Vec<FreezingResult>),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "freezing_results",
+    summary = "Freezing results of a treatment",
+    description = "List the results of the wells in regions holding the treatment, across the processed experiments the user may read"
+)]
+pub async fn get_treatment_freezing_results(
+    State(state): State<AppState>,
+    token: Option<Extension<KeycloakToken<Role>>>,
+    labs: Option<Extension<Labs>>,
+    Path(treatment_id): Path<Uuid>,
+) -> Result<Json<Vec<FreezingResult>>, (StatusCode, String)> {
+    treatment_freezing_results(&state.db, &reader(token, labs), treatment_id)
+        .await
+        .map(Json)
+        .map_err(error_response)
+}
+
+pub fn router(state: &AppState) -> OpenApiRouter {
+    let mut router = OpenApiRouter::new()
+        .routes(routes!(get_experiment_freezing_results))
+        .routes(routes!(get_well_freezing_results))
+        .routes(routes!(get_treatment_freezing_results))
+        .with_state(state.clone());
+
+    if let Some(instance) = state.keycloak_auth_instance.clone() {
+        router = router
+            .layer(middleware::from_fn_with_state(
+                RouteAccess::PROJECT_RECORDS,
+                require_role,
+            ))
+            .layer(middleware::from_fn(redact_for_viewers))
+            .layer(middleware::from_fn_with_state(
+                (state.db.clone(), "freezing_results"),
+                accept_api_keys,
+            ))
+            .layer(middleware::from_fn_with_state(
+                (instance, PassthroughMode::Pass),
+                authenticate,
+            ));
+    } else if !state.config.tests_running {
+        println!("Warning: Freezing result routes are not protected");
+    }
+
+    router
+}
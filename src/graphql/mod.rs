@@ -0,0 +1,6 @@
+pub mod schema;
+pub mod types;
+pub mod views;
+
+#[cfg(test)]
+pub mod tests;
@@ -0,0 +1,331 @@
+//! GraphQL queries over experiments, samples and treatments.
+//!
+//! Visualization tools fetch an experiment with its regions, the treatments
+//! in them and its results summary in one round trip, instead of one REST
+//! request per record. Queries read records through the same services as
+//! the REST routes, and a user who is not an administrator reaches only the
+//! records of their projects and labs, as the REST routes decide it. Deleted
+//! records are left out. There are no mutations; changes go through REST.
+
+use super::types::{ExperimentNode, SampleNode, TreatmentNode};
+use crate::common::include::including;
+use crate::common::labs;
+use crate::common::labs::TenantResource;
+use crate::experiments::models::Experiment;
+use crate::projects::access::{self, ListScope, ScopedResource, in_list_scope};
+use crate::projects::sharing::Requester;
+use crate::samples::models::Sample;
+use crate::treatments::models::Treatment;
+use async_graphql::{
+    Context, EmptyMutation, EmptySubscription, Error, ErrorExtensions, Object, Result, Schema,
+};
+use async_trait::async_trait;
+use axum::http::{Method, StatusCode};
+use crudcrate::CRUDResource;
+use sea_orm::{
+    ColumnTrait, Condition, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter,
+    QueryOrder, QuerySelect,
+};
+use serde_json::Value;
+use uuid::Uuid;
+
+pub type SpiceSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+pub const DEFAULT_LIMIT: u64 = 100;
+pub const MAX_LIMIT: u64 = 1000;
+/// Deepest nesting of fields a query may select
+const MAX_DEPTH: usize = 10;
+
+pub fn build_schema(db: DatabaseConnection) -> SpiceSchema {
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .data(db)
+        .limit_depth(MAX_DEPTH)
+        .finish()
+}
+
+/// A refusal of the REST routes as a GraphQL error, with its status
+fn rejection((status, message): (StatusCode, String)) -> Error {
+    Error::new(message).extend_with(|_, extensions| extensions.set("status", status.as_u16()))
+}
+
+/// Who a query is run for, with what limits the REST routes would put on
+/// them. Without a user, when authentication is off or for
+/// administrators, every record is reached.
+#[derive(Clone, Debug, Default)]
+pub struct Viewer {
+    /// Username and Keycloak groups of a user who is not an administrator
+    pub member: Option<(String, Vec<String>)>,
+    /// Labs of a user who is not an administrator, when labs are on
+    pub labs: Option<Vec<String>>,
+}
+
+impl Viewer {
+    /// Check that the user may read a record
+    async fn check(
+        &self,
+        db: &DatabaseConnection,
+        resources: (ScopedResource, TenantResource),
+        id: Uuid,
+    ) -> Result<()> {
+        self.authorize(db, resources, &format!("/{id}")).await
+    }
+
+    /// Ask the REST routes' checks about a read
+    async fn authorize(
+        &self,
+        db: &DatabaseConnection,
+        (scoped, tenant): (ScopedResource, TenantResource),
+        path: &str,
+    ) -> Result<()> {
+        if let Some(labs) = &self.labs {
+            labs::authorize(db, labs, tenant, &Method::GET, path, None)
+                .await
+                .map_err(rejection)?;
+        }
+        if let Some((username, groups)) = &self.member {
+            let user = Requester { username, groups };
+            access::authorize(db, &user, scoped, &Method::GET, path, None)
+                .await
+                .map_err(rejection)?;
+        }
+        Ok(())
+    }
+
+    /// The scope of the user's lists of a resource, if they are limited
+    fn list_scope(&self, (scoped, tenant): (ScopedResource, TenantResource)) -> Option<ListScope> {
+        let labs = self.labs.as_ref().map(|labs| labs::lab_scope(tenant, labs));
+        let member = self.member.as_ref().map(|(username, groups)| {
+            access::list_scope(scoped, &Requester { username, groups }).0
+        });
+        match (labs, member) {
+            (None, None) => None,
+            (labs, member) => Some(ListScope(
+                labs.into_iter()
+                    .chain(member)
+                    .fold(Condition::all(), Condition::add),
+            )),
+        }
+    }
+}
+
+/// Column a resource marks its deleted records in, if it keeps them
+fn deleted_at_column<R: CRUDResource>() -> Option<R::ColumnType> {
+    R::filterable_columns()
+        .into_iter()
+        .find(|(name, _)| *name == "deleted_at")
+        .map(|(_, column)| column)
+}
+
+/// A record the user may read, read with the relations named, or `None`
+/// when there is no such record or it was deleted
+async fn find_one<R: CRUDResource>(
+    ctx: &Context<'_>,
+    resources: (ScopedResource, TenantResource),
+    relations: &[&str],
+    id: Uuid,
+) -> Result<Option<R>> {
+    let db = ctx.data::<DatabaseConnection>()?;
+    if let Some(column) = deleted_at_column::<R>() {
+        let deleted = R::EntityType::find_by_id(id)
+            .filter(column.is_not_null())
+            .one(db)
+            .await?
+            .is_some();
+        if deleted {
+            return Ok(None);
+        }
+    }
+    ctx.data::<Viewer>()?.check(db, resources, id).await?;
+    match including(relations, R::get_one(db, id)).await {
+        Ok(record) => Ok(Some(record)),
+        Err(DbErr::RecordNotFound(_)) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// A resource whose pages are read with the relations of all their records
+/// together
+#[async_trait]
+trait Listed: CRUDResource {
+    /// The records with the IDs, in no particular order
+    async fn get_many(db: &DatabaseConnection, ids: &[Uuid]) -> Result<Vec<Self>, DbErr>;
+
+    fn id(&self) -> Uuid;
+}
+
+#[async_trait]
+impl Listed for Experiment {
+    async fn get_many(db: &DatabaseConnection, ids: &[Uuid]) -> Result<Vec<Self>, DbErr> {
+        crate::experiments::models::get_experiments(db, ids).await
+    }
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+}
+
+#[async_trait]
+impl Listed for Sample {
+    async fn get_many(db: &DatabaseConnection, ids: &[Uuid]) -> Result<Vec<Self>, DbErr> {
+        crate::samples::models::get_samples(db, ids).await
+    }
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+}
+
+/// A page of the records the user may read, matching a filter as the REST
+/// lists take it, in the order of a sort as they take it
+async fn find_many<R: Listed>(
+    ctx: &Context<'_>,
+    resources: (ScopedResource, TenantResource),
+    relations: &[&str],
+    page: Page,
+) -> Result<Vec<R>> {
+    let db = ctx.data::<DatabaseConnection>()?;
+    let viewer = ctx.data::<Viewer>()?;
+    viewer.authorize(db, resources, "/").await?;
+    let filter = page.filter;
+    if let Some(filter) = &filter {
+        serde_json::from_str::<Value>(filter)
+            .map_err(|e| Error::new(format!("filter is not JSON: {e}")))?;
+    }
+    let mut condition = in_list_scope(
+        crudcrate::filter::apply_filters::<R>(
+            filter,
+            &R::filterable_columns(),
+            db.get_database_backend(),
+        ),
+        viewer.list_scope(resources).as_ref(),
+    );
+    if let Some(column) = deleted_at_column::<R>() {
+        condition = condition.add(column.is_null());
+    }
+    let (order_column, order) =
+        crudcrate::sort::generic_sort(page.sort, &R::sortable_columns(), R::default_index_column());
+    let ids: Vec<Uuid> = R::EntityType::find()
+        .select_only()
+        .column(R::ID_COLUMN)
+        .filter(condition)
+        .order_by(order_column, order)
+        .order_by_asc(R::ID_COLUMN)
+        .offset(page.offset.unwrap_or(0))
+        .limit(page.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT))
+        .into_tuple()
+        .all(db)
+        .await?;
+
+    let mut records = including(relations, R::get_many(db, &ids)).await?;
+    records.sort_by_key(|record| ids.iter().position(|id| *id == record.id()));
+    Ok(records)
+}
+
+/// Arguments of a list
+struct Page {
+    filter: Option<String>,
+    sort: Option<String>,
+    limit: Option<u64>,
+    offset: Option<u64>,
+}
+
+/// Relations of an experiment the query selects
+fn experiment_relations(ctx: &Context<'_>) -> Vec<&'static str> {
+    let selection = ctx.look_ahead();
+    let mut relations = vec![];
+    if selection.field("regions").exists() {
+        relations.push("regions");
+    }
+    if selection.field("summary").exists() || selection.field("results").exists() {
+        relations.push("results");
+    }
+    relations
+}
+
+/// Relations of a sample the query selects
+fn sample_relations(ctx: &Context<'_>) -> Vec<&'static str> {
+    let selection = ctx.look_ahead();
+    let mut relations = vec![];
+    if selection.field("treatments").field("statistics").exists() {
+        relations.push("statistics");
+    }
+    relations
+}
+
+const EXPERIMENTS: (ScopedResource, TenantResource) =
+    (ScopedResource::Experiments, TenantResource::Experiments);
+const SAMPLES: (ScopedResource, TenantResource) =
+    (ScopedResource::Samples, TenantResource::Samples);
+const TREATMENTS: (ScopedResource, TenantResource) =
+    (ScopedResource::Treatments, TenantResource::Treatments);
+
+pub struct QueryRoot;
+
+#[Object(rename_fields = "snake_case", rename_args = "snake_case")]
+impl QueryRoot {
+    async fn experiment(&self, ctx: &Context<'_>, id: Uuid) -> Result<Option<ExperimentNode>> {
+        let relations = experiment_relations(ctx);
+        Ok(find_one::<Experiment>(ctx, EXPERIMENTS, &relations, id)
+            .await?
+            .map(ExperimentNode))
+    }
+
+    /// Experiments matching a filter, such as `{"project_id": "..."}`,
+    /// sorted as `["performed_at", "DESC"]`; 100 by default and at most 1000
+    async fn experiments(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<String>,
+        sort: Option<String>,
+        limit: Option<u64>,
+        offset: Option<u64>,
+    ) -> Result<Vec<ExperimentNode>> {
+        let relations = experiment_relations(ctx);
+        let page = Page {
+            filter,
+            sort,
+            limit,
+            offset,
+        };
+        Ok(find_many::<Experiment>(ctx, EXPERIMENTS, &relations, page)
+            .await?
+            .into_iter()
+            .map(ExperimentNode)
+            .collect())
+    }
+
+    async fn sample(&self, ctx: &Context<'_>, id: Uuid) -> Result<Option<SampleNode>> {
+        Ok(find_one::<Sample>(ctx, SAMPLES, &[], id)
+            .await?
+            .map(SampleNode))
+    }
+
+    /// Samples matching a filter, sorted and paged as experiments are
+    async fn samples(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<String>,
+        sort: Option<String>,
+        limit: Option<u64>,
+        offset: Option<u64>,
+    ) -> Result<Vec<SampleNode>> {
+        let relations = sample_relations(ctx);
+        let page = Page {
+            filter,
+            sort,
+            limit,
+            offset,
+        };
+        Ok(find_many::<Sample>(ctx, SAMPLES, &relations, page)
+            .await?
+            .into_iter()
+            .map(SampleNode)
+            .collect())
+    }
+
+    async fn treatment(&self, ctx: &Context<'_>, id: Uuid) -> Result<Option<TreatmentNode>> {
+        Ok(find_one::<Treatment>(ctx, TREATMENTS, &[], id)
+            .await?
+            .map(TreatmentNode))
+    }
+}
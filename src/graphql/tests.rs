@@ -0,0 +1,177 @@
+use crate::config::test_helpers::{create, send_json, setup_test_app};
+use axum::Router;
+use axum::body::{Body, to_bytes};
+use axum::http::{Request, StatusCode};
+use serde_json::{Value, json};
+use tower::ServiceExt;
+
+async fn query(app: &Router, query: &str, variables: &Value) -> Value {
+    let (status, response) = send_json(
+        app,
+        "POST",
+        "/api/graphql",
+        Some(&json!({"query": query, "variables": variables})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{response}");
+    response
+}
+
+/// An experiment with a region of a treatment of a sample, and the
+/// experiment's and treatment's IDs
+async fn create_experiment(app: &Router) -> (String, String) {
+    let project_id = create(app, "/api/projects", &json!({"name": "GraphQL project"})).await;
+    let location_id = create(
+        app,
+        "/api/locations",
+        &json!({"name": "GraphQL station", "project_id": project_id}),
+    )
+    .await;
+    let sample_id = create(
+        app,
+        "/api/samples",
+        &json!({"name": "GraphQL filter", "type": "filter", "location_id": location_id}),
+    )
+    .await;
+    let treatment_id = create(
+        app,
+        "/api/treatments",
+        &json!({"name": "heat", "sample_id": sample_id}),
+    )
+    .await;
+    let experiment_id = create(
+        app,
+        "/api/experiments",
+        &json!({
+            "name": "GraphQL experiment",
+            "is_calibration": false,
+            "project_id": project_id,
+            "regions": [{
+                "treatment_id": treatment_id,
+                "name": "Heat treated",
+                "tray_id": 1,
+                "col_min": 0, "col_max": 3, "row_min": 0, "row_max": 7,
+                "dilution_factor": 1,
+                "is_background_key": false
+            }]
+        }),
+    )
+    .await;
+    (experiment_id, treatment_id)
+}
+
+#[tokio::test]
+async fn test_graphql_nested_experiment() {
+    let app = setup_test_app().await;
+    let (experiment_id, treatment_id) = create_experiment(&app).await;
+
+    let response = query(
+        &app,
+        "query($id: UUID!) {
+            experiment(id: $id) {
+                name
+                regions { name treatment { id name sample { name } } }
+                summary { total_wells frozen_wells }
+            }
+        }",
+        &json!({"id": experiment_id}),
+    )
+    .await;
+    assert!(response.get("errors").is_none(), "{response}");
+    let experiment = &response["data"]["experiment"];
+    assert_eq!(experiment["name"], "GraphQL experiment");
+    let region = &experiment["regions"][0];
+    assert_eq!(region["name"], "Heat treated");
+    assert_eq!(region["treatment"]["id"], treatment_id);
+    assert_eq!(region["treatment"]["name"], "heat");
+    assert_eq!(region["treatment"]["sample"]["name"], "GraphQL filter");
+    assert_eq!(experiment["summary"]["total_wells"], 0);
+
+    // Lists take filters and sorts as the REST lists do
+    let other_id = create(
+        &app,
+        "/api/experiments",
+        &json!({"name": "Calibration run", "is_calibration": false}),
+    )
+    .await;
+    let response = query(
+        &app,
+        r#"{
+            matching: experiments(filter: "{\"name\": \"GraphQL experiment\"}") {
+                id
+                regions { id }
+                summary { total_wells }
+            }
+            other: experiments(filter: "{\"name\": \"Another experiment\"}") { id }
+            sorted: experiments(sort: "[\"name\", \"DESC\"]") { name }
+            samples(limit: 5) { name treatments { name statistics } }
+        }"#,
+        &json!({}),
+    )
+    .await;
+    assert!(response.get("errors").is_none(), "{response}");
+    let data = &response["data"];
+    assert_eq!(data["matching"][0]["id"], experiment_id);
+    assert_eq!(data["matching"][0]["regions"].as_array().unwrap().len(), 1);
+    assert_eq!(data["matching"][0]["summary"]["total_wells"], 0);
+    assert_eq!(data["other"], json!([]));
+    assert_eq!(
+        data["sorted"],
+        json!([{"name": "GraphQL experiment"}, {"name": "Calibration run"}])
+    );
+    assert_eq!(data["samples"][0]["treatments"][0]["name"], "heat");
+
+    // Deleted records are not reached
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("DELETE")
+                .uri(format!("/api/experiments/{experiment_id}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    let response = query(
+        &app,
+        "query($id: UUID!) { experiment(id: $id) { name } experiments { id } }",
+        &json!({"id": experiment_id}),
+    )
+    .await;
+    assert_eq!(response["data"]["experiment"], Value::Null, "{response}");
+    assert_eq!(response["data"]["experiments"], json!([{"id": other_id}]));
+}
+
+#[tokio::test]
+async fn test_graphql_schema_and_errors() {
+    let app = setup_test_app().await;
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/graphql/schema")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let schema = String::from_utf8(bytes.to_vec()).unwrap();
+    assert!(schema.contains("type Experiment"));
+    assert!(schema.contains("total_wells"));
+
+    // There are no mutations, and bad filters are reported
+    let response = query(&app, "mutation { experiment }", &json!({})).await;
+    assert!(response["errors"].is_array());
+    let response = query(
+        &app,
+        r#"{ experiments(filter: "not json") { id } }"#,
+        &json!({}),
+    )
+    .await;
+    assert!(response["errors"].is_array(), "{response}");
+}
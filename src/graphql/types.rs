@@ -0,0 +1,321 @@
+//! Records as the GraphQL schema shows them.
+//!
+//! Each type wraps the record the REST routes return and names its fields
+//! as they do. Related records are read only when a query selects them.
+
+use crate::experiments::models::{
+    Experiment, ExperimentResultsResponse, ExperimentResultsSummaryCompact,
+};
+use crate::nucleation_events::models::NucleationStatistics;
+use crate::samples::models::Sample;
+use crate::tray_configurations::regions::models::Region;
+use crate::treatments::models::Treatment;
+use async_graphql::{Context, Json, Object, Result};
+use chrono::{DateTime, Utc};
+use crudcrate::CRUDResource;
+use rust_decimal::Decimal;
+use sea_orm::{DatabaseConnection, DbErr};
+use serde::Serialize;
+use uuid::Uuid;
+
+/// Value of an enumeration as the REST routes give it, e.g. `filter`
+fn label<T: Serialize>(value: &T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// A related record, or `None` when it no longer exists
+pub(super) async fn related<R: CRUDResource>(
+    ctx: &Context<'_>,
+    id: Option<Uuid>,
+) -> Result<Option<R>> {
+    let Some(id) = id else {
+        return Ok(None);
+    };
+    match R::get_one(ctx.data::<DatabaseConnection>()?, id).await {
+        Ok(record) => Ok(Some(record)),
+        Err(DbErr::RecordNotFound(_)) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub struct ExperimentNode(pub Experiment);
+
+#[Object(name = "Experiment", rename_fields = "snake_case")]
+impl ExperimentNode {
+    async fn id(&self) -> Uuid {
+        self.0.id
+    }
+
+    async fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    async fn username(&self) -> Option<&str> {
+        self.0.username.as_deref()
+    }
+
+    async fn performed_at(&self) -> Option<DateTime<Utc>> {
+        self.0.performed_at
+    }
+
+    async fn temperature_ramp(&self) -> Option<Decimal> {
+        self.0.temperature_ramp
+    }
+
+    async fn temperature_start(&self) -> Option<Decimal> {
+        self.0.temperature_start
+    }
+
+    async fn temperature_end(&self) -> Option<Decimal> {
+        self.0.temperature_end
+    }
+
+    async fn is_calibration(&self) -> bool {
+        self.0.is_calibration
+    }
+
+    async fn remarks(&self) -> Option<&str> {
+        self.0.remarks.as_deref()
+    }
+
+    async fn tray_configuration_id(&self) -> Option<Uuid> {
+        self.0.tray_configuration_id
+    }
+
+    async fn doi(&self) -> Option<&str> {
+        self.0.doi.as_deref()
+    }
+
+    async fn project_id(&self) -> Option<Uuid> {
+        self.0.project_id
+    }
+
+    async fn created_by(&self) -> Option<&str> {
+        self.0.created_by.as_deref()
+    }
+
+    async fn lab(&self) -> Option<&str> {
+        self.0.lab.as_deref()
+    }
+
+    async fn created_at(&self) -> DateTime<Utc> {
+        self.0.created_at
+    }
+
+    async fn last_updated(&self) -> DateTime<Utc> {
+        self.0.last_updated
+    }
+
+    /// Regions of the trays, with the treatments in their wells
+    async fn regions(&self) -> Vec<RegionNode> {
+        self.0.regions.iter().cloned().map(RegionNode).collect()
+    }
+
+    /// Counts of the experiment's wells and readings
+    async fn summary(&self) -> Option<ResultsSummaryNode> {
+        self.0
+            .results
+            .as_ref()
+            .map(|results| ResultsSummaryNode(results.summary.clone()))
+    }
+
+    /// Results of every well, tray by tray, as the REST routes give them
+    async fn results(&self) -> Option<Json<ExperimentResultsResponse>> {
+        self.0.results.clone().map(Json)
+    }
+}
+
+pub struct ResultsSummaryNode(pub ExperimentResultsSummaryCompact);
+
+#[Object(name = "ResultsSummary", rename_fields = "snake_case")]
+impl ResultsSummaryNode {
+    async fn total_time_points(&self) -> usize {
+        self.0.total_time_points
+    }
+
+    async fn first_timestamp(&self) -> Option<DateTime<Utc>> {
+        self.0.first_timestamp
+    }
+
+    async fn last_timestamp(&self) -> Option<DateTime<Utc>> {
+        self.0.last_timestamp
+    }
+
+    async fn total_wells(&self) -> usize {
+        self.0.total_wells
+    }
+
+    async fn excluded_wells(&self) -> usize {
+        self.0.excluded_wells
+    }
+
+    /// Wells that froze, excluded wells not counted
+    async fn frozen_wells(&self) -> usize {
+        self.0.frozen_wells
+    }
+
+    /// Share of the wells that froze, excluded wells not counted
+    async fn frozen_fraction(&self) -> Option<Decimal> {
+        self.0.frozen_fraction
+    }
+}
+
+pub struct RegionNode(pub Region);
+
+#[Object(name = "Region", rename_fields = "snake_case")]
+impl RegionNode {
+    async fn id(&self) -> Uuid {
+        self.0.id
+    }
+
+    async fn name(&self) -> Option<&str> {
+        self.0.name.as_deref()
+    }
+
+    async fn display_colour_hex(&self) -> Option<&str> {
+        self.0.display_colour_hex.as_deref()
+    }
+
+    async fn tray_id(&self) -> Option<i32> {
+        self.0.tray_id
+    }
+
+    async fn col_min(&self) -> Option<i32> {
+        self.0.col_min
+    }
+
+    async fn row_min(&self) -> Option<i32> {
+        self.0.row_min
+    }
+
+    async fn col_max(&self) -> Option<i32> {
+        self.0.col_max
+    }
+
+    async fn row_max(&self) -> Option<i32> {
+        self.0.row_max
+    }
+
+    async fn dilution_factor(&self) -> Option<i32> {
+        self.0.dilution_factor
+    }
+
+    async fn is_background_key(&self) -> bool {
+        self.0.is_background_key
+    }
+
+    async fn treatment(&self, ctx: &Context<'_>) -> Result<Option<TreatmentNode>> {
+        Ok(related::<Treatment>(ctx, self.0.treatment_id)
+            .await?
+            .map(TreatmentNode))
+    }
+}
+
+pub struct TreatmentNode(pub Treatment);
+
+#[Object(name = "Treatment", rename_fields = "snake_case")]
+impl TreatmentNode {
+    async fn id(&self) -> Uuid {
+        self.0.id
+    }
+
+    async fn name(&self) -> String {
+        label(&self.0.name)
+    }
+
+    async fn notes(&self) -> Option<&str> {
+        self.0.notes.as_deref()
+    }
+
+    async fn enzyme_volume_litres(&self) -> Option<Decimal> {
+        self.0.enzyme_volume_litres
+    }
+
+    async fn sample_id(&self) -> Option<Uuid> {
+        self.0.sample_id
+    }
+
+    /// Statistics of the nucleation events of the treatment's wells
+    async fn statistics(&self) -> Option<Json<NucleationStatistics>> {
+        self.0.statistics.clone().map(Json)
+    }
+
+    async fn sample(&self, ctx: &Context<'_>) -> Result<Option<SampleNode>> {
+        Ok(related::<Sample>(ctx, self.0.sample_id)
+            .await?
+            .map(SampleNode))
+    }
+}
+
+pub struct SampleNode(pub Sample);
+
+#[Object(name = "Sample", rename_fields = "snake_case")]
+impl SampleNode {
+    async fn id(&self) -> Uuid {
+        self.0.id
+    }
+
+    async fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    async fn r#type(&self) -> String {
+        label(&self.0.r#type)
+    }
+
+    async fn start_time(&self) -> Option<DateTime<Utc>> {
+        self.0.start_time
+    }
+
+    async fn stop_time(&self) -> Option<DateTime<Utc>> {
+        self.0.stop_time
+    }
+
+    async fn latitude(&self) -> Option<Decimal> {
+        self.0.latitude
+    }
+
+    async fn longitude(&self) -> Option<Decimal> {
+        self.0.longitude
+    }
+
+    async fn location_id(&self) -> Option<Uuid> {
+        self.0.location_id
+    }
+
+    async fn barcode(&self) -> Option<&str> {
+        self.0.barcode.as_deref()
+    }
+
+    async fn qc_status(&self) -> String {
+        label(&self.0.qc_status)
+    }
+
+    async fn remarks(&self) -> Option<&str> {
+        self.0.remarks.as_deref()
+    }
+
+    async fn created_by(&self) -> Option<&str> {
+        self.0.created_by.as_deref()
+    }
+
+    async fn created_at(&self) -> DateTime<Utc> {
+        self.0.created_at
+    }
+
+    async fn last_updated(&self) -> DateTime<Utc> {
+        self.0.last_updated
+    }
+
+    async fn treatments(&self) -> Vec<TreatmentNode> {
+        self.0
+            .treatments
+            .iter()
+            .cloned()
+            .map(TreatmentNode)
+            .collect()
+    }
+}
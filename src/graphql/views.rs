@@ -0,0 +1,103 @@
+use super::schema::{SpiceSchema, Viewer, build_schema};
+use crate::api_keys::services::accept_api_keys;
+use crate::common::auth::{Role, RouteAccess, require_role};
+use crate::common::keycloak::authenticate;
+use crate::common::labs::Labs;
+use crate::common::redaction::redact_for_viewers;
+use crate::common::state::AppState;
+use crate::projects::sharing::token_groups;
+use axum::{Extension, Json, extract::State, middleware};
+use axum_keycloak_auth::{PassthroughMode, decode::KeycloakToken};
+use serde::Deserialize;
+use serde_json::Value;
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+/// A GraphQL query, as posted by GraphQL clients
+#[derive(Deserialize, ToSchema)]
+#[allow(dead_code)]
+pub struct GraphQLRequest {
+    /// Query, such as `{ experiment(id: "...") { name regions { treatment { name } } } }`
+    query: String,
+    /// Values of the query's variables
+    variables: Option<Value>,
+    /// Operation to run, when the query names several
+    #[serde(rename = "operationName")]
+    operation_name: Option<String>,
+}
+
+/// Run a GraphQL query
+#[utoipa::path(
+    post,
+    path = "",
+    request_body = GraphQLRequest,
+    responses(
+        (status = 200, description = "The query's `data`, and its `errors` if any part of it failed", body = Value)
+    ),
+    tag = "graphql",
+    summary = "Run a GraphQL query",
+    description = "Read experiments, samples and treatments with the related records a query selects, such as an experiment's regions, the treatments and samples in them and its results summary, in one request. Records are reached as through the REST routes; the schema is at `/api/graphql/schema`"
+)]
+pub async fn post_query(
+    State(schema): State<SpiceSchema>,
+    token: Option<Extension<KeycloakToken<Role>>>,
+    labs: Option<Extension<Labs>>,
+    Json(request): Json<async_graphql::Request>,
+) -> Json<async_graphql::Response> {
+    let mut viewer = Viewer::default();
+    if let Some(Extension(token)) = token
+        && !token
+            .roles
+            .iter()
+            .any(|role| *role.role() == Role::Administrator)
+    {
+        let groups = token_groups(&token);
+        viewer.member = Some((token.extra.profile.preferred_username, groups));
+        viewer.labs = labs.map(|Extension(Labs(labs))| labs);
+    }
+    Json(schema.execute(request.data(viewer)).await)
+}
+
+/// Get the GraphQL schema
+#[utoipa::path(
+    get,
+    path = "/schema",
+    responses(
+        (status = 200, description = "The schema in GraphQL's schema language", body = String, content_type = "text/plain")
+    ),
+    tag = "graphql",
+    summary = "Get the GraphQL schema"
+)]
+pub async fn get_schema(State(schema): State<SpiceSchema>) -> String {
+    schema.sdl()
+}
+
+pub fn router(state: &AppState) -> OpenApiRouter {
+    let mut router = OpenApiRouter::new()
+        .routes(routes!(post_query))
+        .routes(routes!(get_schema))
+        .with_state(build_schema(state.db.clone()));
+
+    if let Some(instance) = state.keycloak_auth_instance.clone() {
+        // Queries only read, so viewers may run them; what they reach is
+        // limited by the schema as the REST routes limit it
+        router = router
+            .layer(middleware::from_fn_with_state(
+                RouteAccess::QUERIES,
+                require_role,
+            ))
+            .layer(middleware::from_fn(redact_for_viewers))
+            .layer(middleware::from_fn_with_state(
+                (state.db.clone(), "graphql"),
+                accept_api_keys,
+            ))
+            .layer(middleware::from_fn_with_state(
+                (instance, PassthroughMode::Pass),
+                authenticate,
+            ));
+    } else if !state.config.tests_running {
+        println!("Warning: GraphQL routes are not protected");
+    }
+
+    router
+}
@@ -0,0 +1,30 @@
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+
+/// A POST request made with an `Idempotency-Key`, with the response it got
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "idempotency_keys")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    /// User named by the request's token or API key, or `anonymous`
+    #[sea_orm(column_type = "Text")]
+    pub client: String,
+    #[sea_orm(column_type = "Text")]
+    pub key: String,
+    /// SHA-256 of the request's path, query and body
+    #[sea_orm(column_type = "Text")]
+    pub request_hash: String,
+    /// Status of the response, once the request has been handled
+    pub status_code: Option<i32>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub content_type: Option<String>,
+    pub response_body: Option<Vec<u8>>,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
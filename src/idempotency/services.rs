@@ -0,0 +1,249 @@
+//! Safe retries of POST requests with an `Idempotency-Key` header.
+//!
+//! Instrument scripts retry requests that time out, which could create an
+//! experiment twice. A POST made with an `Idempotency-Key` is stored with
+//! its response, and a retry with the same key by the same user gets that
+//! response again, marked `Idempotent-Replayed: true`, without being run.
+//! A key reused for another request is refused with 422, and one whose
+//! request is still running with 409. Responses of server errors and
+//! refused credentials are not stored, so those requests can be retried.
+//! Keys are kept for a day.
+//!
+//! Keys belong to the verified caller: the subject of a valid Keycloak token
+//! or the id of a valid API key, so no one can replay another's response by
+//! claiming their name. Requests with credentials that do not verify run
+//! without a key, and are refused by the routes as usual. Responses that
+//! show a secret once, such as a new API key, webhook secret or download
+//! token, are marked [`ShowsSecret`] by their handlers and never stored;
+//! those requests run each time. Neither are responses too large to keep or
+//! streamed without a known length.
+
+use super::models::{ActiveModel, Column, Entity as IdempotencyKeys, Model as IdempotencyKey};
+use crate::api_keys::services::{presented_key, verified_caller};
+use crate::assets::services::sha256_hex;
+use crate::common::keycloak::KeycloakAuth;
+use axum::{
+    body::{Body, Bytes, HttpBody, to_bytes},
+    extract::{Request, State},
+    http::{HeaderValue, Method, StatusCode, header::CONTENT_TYPE},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{Duration, Utc};
+use sea_orm::{ActiveValue::Set, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter};
+use std::sync::Arc;
+use uuid::Uuid;
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+pub const REPLAYED_HEADER: &str = "idempotent-replayed";
+const MAX_KEY_LENGTH: usize = 255;
+/// How long a key and its response are kept
+const RETENTION_HOURS: i64 = 24;
+/// How long a request may run before a retry is let through again, as when
+/// the server stopped while running it
+const RUNNING_TIMEOUT_MINUTES: i64 = 15;
+/// Largest request body read, as the routes accept
+const REQUEST_LIMIT: usize = 30 * 1024 * 1024;
+/// Largest response kept for retries
+const RESPONSE_LIMIT: u64 = 1024 * 1024;
+
+/// Response extension of handlers whose response shows a secret only this
+/// once, so that it is never kept for retries
+#[derive(Clone, Copy, Debug)]
+pub struct ShowsSecret;
+
+fn rejection(status: StatusCode, message: &str) -> Response {
+    (status, message.to_string()).into_response()
+}
+
+/// Whether a response is kept for retries: not when the server failed or
+/// the credentials were refused, so the request can be made again
+fn is_kept(status: StatusCode) -> bool {
+    !status.is_server_error()
+        && status != StatusCode::UNAUTHORIZED
+        && status != StatusCode::FORBIDDEN
+}
+
+/// The stored response, as it was first given
+fn replay(stored: &IdempotencyKey) -> Response {
+    let status = stored
+        .status_code
+        .and_then(|code| u16::try_from(code).ok())
+        .and_then(|code| StatusCode::from_u16(code).ok())
+        .unwrap_or(StatusCode::OK);
+    let mut response = Response::new(Body::from(stored.response_body.clone().unwrap_or_default()));
+    *response.status_mut() = status;
+    let headers = response.headers_mut();
+    if let Some(content_type) = stored
+        .content_type
+        .as_deref()
+        .and_then(|value| HeaderValue::from_str(value).ok())
+    {
+        headers.insert(CONTENT_TYPE, content_type);
+    }
+    headers.insert(REPLAYED_HEADER, HeaderValue::from_static("true"));
+    response
+}
+
+/// Claim a key for a request, or give the response to answer with instead
+async fn claim(
+    db: &DatabaseConnection,
+    client: &str,
+    key: &str,
+    request_hash: &str,
+) -> Result<Uuid, Response> {
+    let internal = |e: DbErr| rejection(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string());
+    let now = Utc::now();
+    // Forget the keys of past days
+    IdempotencyKeys::delete_many()
+        .filter(Column::CreatedAt.lt(now - Duration::hours(RETENTION_HOURS)))
+        .exec(db)
+        .await
+        .map_err(internal)?;
+
+    if let Some(stored) = IdempotencyKeys::find()
+        .filter(Column::Client.eq(client))
+        .filter(Column::Key.eq(key))
+        .one(db)
+        .await
+        .map_err(internal)?
+    {
+        if stored.request_hash != request_hash {
+            return Err(rejection(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "This Idempotency-Key was used for another request",
+            ));
+        }
+        if stored.status_code.is_some() {
+            return Err(replay(&stored));
+        }
+        if stored.created_at > now - Duration::minutes(RUNNING_TIMEOUT_MINUTES) {
+            return Err(rejection(
+                StatusCode::CONFLICT,
+                "A request with this Idempotency-Key is still running",
+            ));
+        }
+        IdempotencyKeys::delete_by_id(stored.id)
+            .exec(db)
+            .await
+            .map_err(internal)?;
+    }
+
+    let id = Uuid::new_v4();
+    let claimed = IdempotencyKeys::insert(ActiveModel {
+        id: Set(id),
+        client: Set(client.to_string()),
+        key: Set(key.to_string()),
+        request_hash: Set(request_hash.to_string()),
+        status_code: Set(None),
+        content_type: Set(None),
+        response_body: Set(None),
+        created_at: Set(now),
+        completed_at: Set(None),
+    })
+    .exec(db)
+    .await;
+    match claimed {
+        Ok(_) => Ok(id),
+        // Another retry claimed the key first
+        Err(_) => Err(rejection(
+            StatusCode::CONFLICT,
+            "A request with this Idempotency-Key is still running",
+        )),
+    }
+}
+
+/// Keep the response to a request for its retries, or let the key go
+async fn store(db: &DatabaseConnection, id: Uuid, response: Response) -> Response {
+    let status = response.status();
+    let small = response
+        .body()
+        .size_hint()
+        .exact()
+        .is_some_and(|length| length <= RESPONSE_LIMIT);
+    if !is_kept(status) || !small || response.extensions().get::<ShowsSecret>().is_some() {
+        let _ = IdempotencyKeys::delete_by_id(id).exec(db).await;
+        return response;
+    }
+    let (parts, body) = response.into_parts();
+    let Ok(bytes) = to_bytes(body, usize::try_from(RESPONSE_LIMIT).unwrap_or(usize::MAX)).await
+    else {
+        let _ = IdempotencyKeys::delete_by_id(id).exec(db).await;
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    let content_type = parts
+        .headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let stored = IdempotencyKeys::update(ActiveModel {
+        id: Set(id),
+        status_code: Set(Some(i32::from(status.as_u16()))),
+        content_type: Set(content_type),
+        response_body: Set(Some(bytes.to_vec())),
+        completed_at: Set(Some(Utc::now())),
+        ..Default::default()
+    })
+    .exec(db)
+    .await;
+    if let Err(e) = stored {
+        tracing::warn!("Failed to store the response of idempotent request: {e}");
+    }
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+fn request_hash(request: &Request, body: &Bytes) -> String {
+    let target = request
+        .uri()
+        .path_and_query()
+        .map_or("", |target| target.as_str());
+    let mut hashed = Vec::with_capacity(target.len() + 1 + body.len());
+    hashed.extend_from_slice(target.as_bytes());
+    hashed.push(b'\n');
+    hashed.extend_from_slice(body);
+    sha256_hex(&hashed)
+}
+
+/// Middleware answering retries of POST requests with an `Idempotency-Key`
+/// with the response the first request got
+pub async fn replay_idempotent_requests(
+    State((db, auth)): State<(DatabaseConnection, Option<Arc<KeycloakAuth>>)>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if request.method() != Method::POST {
+        return next.run(request).await;
+    }
+    let Some(key) = request.headers().get(IDEMPOTENCY_KEY_HEADER) else {
+        return next.run(request).await;
+    };
+    let key = match key.to_str() {
+        Ok(key) if !key.trim().is_empty() && key.len() <= MAX_KEY_LENGTH => key.trim().to_string(),
+        _ => {
+            return rejection(
+                StatusCode::BAD_REQUEST,
+                "Idempotency-Key must be 1 to 255 visible characters",
+            );
+        }
+    };
+    // Everyone is one caller when authentication is off
+    let client = match verified_caller(&db, auth.as_deref(), request.headers()).await {
+        Some(client) => client,
+        None if auth.is_none() && presented_key(request.headers()).is_none() => {
+            "anonymous".to_string()
+        }
+        None => return next.run(request).await,
+    };
+
+    let (parts, body) = request.into_parts();
+    let Ok(body) = to_bytes(body, REQUEST_LIMIT).await else {
+        return StatusCode::PAYLOAD_TOO_LARGE.into_response();
+    };
+    let request = Request::from_parts(parts, Body::from(body.clone()));
+    let hash = request_hash(&request, &body);
+
+    match claim(&db, &client, &key, &hash).await {
+        Ok(id) => store(&db, id, next.run(request).await).await,
+        Err(response) => response,
+    }
+}
@@ -0,0 +1,256 @@
+use super::models::Entity as IdempotencyKeys;
+use super::services::{IDEMPOTENCY_KEY_HEADER, REPLAYED_HEADER};
+use crate::api_keys::models::{ApiKeyCreate, ApiKeyRole};
+use crate::api_keys::services::create_api_key;
+use crate::common::keycloak::test_realm;
+use crate::config::test_helpers::{setup_authenticated_test_app, setup_test_app};
+use axum::Router;
+use axum::body::{Body, to_bytes};
+use axum::http::{HeaderMap, Request, StatusCode};
+use sea_orm::{EntityTrait, PaginatorTrait};
+use serde_json::{Value, json};
+use tower::ServiceExt;
+
+async fn post(
+    app: &Router,
+    uri: &str,
+    key: Option<&str>,
+    body: &Value,
+) -> (StatusCode, HeaderMap, Value) {
+    post_as(app, None, uri, key, body).await
+}
+
+/// `post` with the given bearer token or API key, if any
+async fn post_as(
+    app: &Router,
+    credential: Option<&str>,
+    uri: &str,
+    key: Option<&str>,
+    body: &Value,
+) -> (StatusCode, HeaderMap, Value) {
+    let mut request = Request::builder()
+        .method("POST")
+        .uri(uri)
+        .header("content-type", "application/json");
+    if let Some(credential) = credential {
+        request = request.header("authorization", format!("Bearer {credential}"));
+    }
+    if let Some(key) = key {
+        request = request.header(IDEMPOTENCY_KEY_HEADER, key);
+    }
+    let response = app
+        .clone()
+        .oneshot(request.body(Body::from(body.to_string())).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let headers = response.headers().clone();
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body = serde_json::from_slice(&bytes)
+        .unwrap_or_else(|_| json!({"error": String::from_utf8_lossy(&bytes)}));
+    (status, headers, body)
+}
+
+async fn count_projects(app: &Router) -> usize {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/projects")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let projects: Value = serde_json::from_slice(&bytes).unwrap();
+    projects.as_array().unwrap().len()
+}
+
+#[tokio::test]
+async fn test_retries_with_an_idempotency_key_are_replayed() {
+    let app = setup_test_app().await;
+    let project = json!({"name": "Idempotent project"});
+
+    let (status, headers, created) = post(&app, "/api/projects", Some("retry-1"), &project).await;
+    assert_eq!(status, StatusCode::CREATED, "{created}");
+    assert!(headers.get(REPLAYED_HEADER).is_none());
+
+    // A retry gets the first response, without creating the project again
+    let (status, headers, replayed) = post(&app, "/api/projects", Some("retry-1"), &project).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(headers[REPLAYED_HEADER], "true");
+    assert_eq!(headers["content-type"], "application/json");
+    assert_eq!(replayed, created);
+    assert_eq!(count_projects(&app).await, 1);
+
+    // The key cannot be reused for another request
+    let (status, _, _) = post(
+        &app,
+        "/api/projects",
+        Some("retry-1"),
+        &json!({"name": "Another project"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    // Requests without a key, or with a new one, are run
+    let (status, _, _) = post(&app, "/api/projects", None, &json!({"name": "Second"})).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, _, _) = post(
+        &app,
+        "/api/projects",
+        Some("retry-2"),
+        &json!({"name": "Third"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(count_projects(&app).await, 3);
+}
+
+#[tokio::test]
+async fn test_refused_requests_are_replayed() {
+    let app = setup_test_app().await;
+
+    // A refused request is kept and replayed like any other answer
+    let invalid = json!({"comment": "A location without a name"});
+    let (status, _, _) = post(&app, "/api/locations", Some("location-1"), &invalid).await;
+    assert!(status.is_client_error());
+    let (replayed_status, headers, _) =
+        post(&app, "/api/locations", Some("location-1"), &invalid).await;
+    assert_eq!(replayed_status, status);
+    assert_eq!(headers[REPLAYED_HEADER], "true");
+
+    let (status, _, _) = post(
+        &app,
+        "/api/projects",
+        Some(""),
+        &json!({"name": "Blank key"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_keys_belong_to_the_verified_caller() {
+    let (app, db) = setup_authenticated_test_app().await;
+    let ada = test_realm::token("ada", &["spice-admin"], &[]);
+    let grace = test_realm::token("grace", &["spice-admin"], &[]);
+    let project = json!({"name": "Shared key project"});
+
+    let (status, _, created) = post_as(
+        &app,
+        Some(&ada),
+        "/api/projects",
+        Some("shared-1"),
+        &project,
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{created}");
+    let (_, headers, replayed) = post_as(
+        &app,
+        Some(&ada),
+        "/api/projects",
+        Some("shared-1"),
+        &project,
+    )
+    .await;
+    assert_eq!(headers[REPLAYED_HEADER], "true");
+    assert_eq!(replayed, created);
+
+    // Another user's key of the same name is their own
+    let (status, headers, other) = post_as(
+        &app,
+        Some(&grace),
+        "/api/projects",
+        Some("shared-1"),
+        &json!({"name": "Grace's project"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{other}");
+    assert!(headers.get(REPLAYED_HEADER).is_none());
+    assert_ne!(other["id"], created["id"]);
+
+    // A token claiming to be ada without her signature replays nothing
+    let (unsigned, _) = ada.rsplit_once('.').unwrap();
+    let forged = format!("{unsigned}.AAAA");
+    let (status, headers, _) = post_as(
+        &app,
+        Some(&forged),
+        "/api/projects",
+        Some("shared-1"),
+        &project,
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert!(headers.get(REPLAYED_HEADER).is_none());
+
+    // API keys are callers of their own
+    let api_key = create_api_key(
+        &db,
+        ApiKeyCreate {
+            name: "Freezer PC".to_string(),
+            username: Some("ada".to_string()),
+            role: ApiKeyRole::Administrator,
+            scopes: vec!["projects".to_string()],
+            rate_limit_per_minute: None,
+            lab: None,
+            expires_at: None,
+        },
+        None,
+    )
+    .await
+    .unwrap();
+    let (status, headers, by_key) = post_as(
+        &app,
+        Some(&api_key.key),
+        "/api/projects",
+        Some("shared-1"),
+        &json!({"name": "Freezer PC project"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{by_key}");
+    assert!(headers.get(REPLAYED_HEADER).is_none());
+    assert_ne!(by_key["id"], created["id"]);
+}
+
+#[tokio::test]
+async fn test_secrets_are_not_stored() {
+    let (app, db) = setup_authenticated_test_app().await;
+    let ada = test_realm::token("ada", &["spice-admin"], &[]);
+    let input = json!({"name": "Freezer PC", "role": "editor", "scopes": ["samples"]});
+
+    let (status, _, first) =
+        post_as(&app, Some(&ada), "/api/api_keys", Some("key-1"), &input).await;
+    assert_eq!(status, StatusCode::CREATED, "{first}");
+    let (status, headers, second) =
+        post_as(&app, Some(&ada), "/api/api_keys", Some("key-1"), &input).await;
+    assert_eq!(status, StatusCode::CREATED, "{second}");
+    assert!(headers.get(REPLAYED_HEADER).is_none());
+    assert_ne!(first["key"], second["key"]);
+    assert_eq!(IdempotencyKeys::find().count(&db).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn test_download_tokens_are_not_stored() {
+    let app = setup_test_app().await;
+    let (status, _, experiment) = post(
+        &app,
+        "/api/experiments",
+        None,
+        &json!({"name": "Token experiment", "is_calibration": false}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{experiment}");
+    let uri = format!(
+        "/api/experiments/{}/download-token",
+        experiment["id"].as_str().unwrap()
+    );
+
+    let (status, _, first) = post(&app, &uri, Some("token-1"), &json!({})).await;
+    assert_eq!(status, StatusCode::OK, "{first}");
+    let (status, headers, second) = post(&app, &uri, Some("token-1"), &json!({})).await;
+    assert_eq!(status, StatusCode::OK, "{second}");
+    assert!(headers.get(REPLAYED_HEADER).is_none());
+    assert_ne!(first["token"], second["token"]);
+}
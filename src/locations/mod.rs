@@ -1,4 +1,5 @@
 pub mod models;
+pub mod site;
 pub mod views;
 
 #[cfg(test)]
@@ -1,8 +1,9 @@
 use crate::services::convex_hull_service;
 use chrono::{DateTime, Utc};
-use crudcrate::{CRUDResource, EntityToModels};
+use crudcrate::{CRUDResource, EntityToModels, traits::MergeIntoActiveModel};
+use rust_decimal::Decimal;
 use sea_orm::entity::prelude::*;
-use sea_orm::{QueryOrder, QuerySelect};
+use sea_orm::{ActiveModelTrait, IntoActiveModel, QueryOrder, QuerySelect};
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, EntityToModels)]
 #[sea_orm(table_name = "locations")]
@@ -14,6 +15,8 @@ use sea_orm::{QueryOrder, QuerySelect};
     description = "Locations represent physical places where experiments are conducted. Each location belongs to a project and can contain multiple samples and experiments.",
     fn_get_one = get_one_location,
     fn_get_all = get_all_locations,
+    fn_create = create_location,
+    fn_update = update_location,
 )]
 pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
@@ -27,6 +30,26 @@ pub struct Model {
     pub comment: Option<String>,
     #[crudcrate(sortable, filterable)]
     pub project_id: Option<Uuid>,
+    /// Reference point of the site, such as the station's inlet
+    #[sea_orm(column_type = "Decimal(Some((9, 6)))", nullable)]
+    #[crudcrate(sortable)]
+    pub latitude: Option<Decimal>,
+    #[sea_orm(column_type = "Decimal(Some((9, 6)))", nullable)]
+    #[crudcrate(sortable)]
+    pub longitude: Option<Decimal>,
+    /// Metres above sea level, looked up from the coordinates when not given
+    #[sea_orm(column_type = "Decimal(Some((8, 2)))", nullable)]
+    #[crudcrate(sortable, filterable)]
+    pub elevation_m: Option<Decimal>,
+    /// IANA time zone the site's local times are in, e.g. "Europe/Zurich",
+    /// looked up from the coordinates when not given
+    #[sea_orm(column_type = "Text", nullable)]
+    #[crudcrate(sortable, filterable)]
+    pub timezone: Option<String>,
+    /// Lab the location belongs to, when labs are on
+    #[sea_orm(column_type = "Text", nullable)]
+    #[crudcrate(sortable, filterable, create_model = false, update_model = false)]
+    pub lab: Option<String>,
     #[crudcrate(update_model = false, create_model = false, on_create = chrono::Utc::now(), sortable)]
     pub created_at: DateTime<Utc>,
     #[crudcrate(update_model = false, create_model = false, on_update = chrono::Utc::now(), on_create = chrono::Utc::now(), sortable)]
@@ -107,6 +130,58 @@ async fn get_one_location(db: &DatabaseConnection, id: Uuid) -> Result<Location,
     Ok(location)
 }
 
+async fn create_location(
+    db: &DatabaseConnection,
+    create_data: LocationCreate,
+) -> Result<Location, DbErr> {
+    super::site::validate_coordinates(create_data.latitude, create_data.longitude)?;
+    let elevation = create_data.elevation_m.is_none();
+    let timezone = create_data.timezone.is_none();
+
+    let mut active_model: ActiveModel = create_data.into();
+    active_model.lab = sea_orm::ActiveValue::Set(crate::common::labs::current_lab());
+    super::site::fill_site_details(
+        &super::site::providers(),
+        &mut active_model,
+        elevation,
+        timezone,
+    )
+    .await;
+    let inserted = active_model.insert(db).await?;
+    Location::get_one(db, inserted.id).await
+}
+
+async fn update_location(
+    db: &DatabaseConnection,
+    id: Uuid,
+    update_data: LocationUpdate,
+) -> Result<Location, DbErr> {
+    let existing = Entity::find_by_id(id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Location not found".to_string()))?;
+    let latitude = update_data.latitude.unwrap_or(existing.latitude);
+    let longitude = update_data.longitude.unwrap_or(existing.longitude);
+    super::site::validate_coordinates(latitude, longitude)?;
+
+    // Look up again what the move made stale or an earlier lookup missed,
+    // unless the update gives it
+    let moved = (latitude, longitude) != (existing.latitude, existing.longitude);
+    let elevation = update_data.elevation_m.is_none() && (moved || existing.elevation_m.is_none());
+    let timezone = update_data.timezone.is_none() && (moved || existing.timezone.is_none());
+
+    let mut active_model = update_data.merge_into_activemodel(existing.into_active_model())?;
+    super::site::fill_site_details(
+        &super::site::providers(),
+        &mut active_model,
+        elevation,
+        timezone,
+    )
+    .await;
+    active_model.update(db).await?;
+    Location::get_one(db, id).await
+}
+
 /// Custom `get_all` that includes area (convex hull) for each location
 async fn get_all_locations(
     db: &DatabaseConnection,
@@ -0,0 +1,98 @@
+//! Elevation and time zone of locations.
+//!
+//! A location given coordinates gets its elevation and IANA time zone from
+//! the providers configured with `ELEVATION_API_URL` and `TIMEZONE_API_URL`,
+//! so that sampling times can be read in local time. Values given with the
+//! location are kept, and they are looked up again when its coordinates
+//! change. A provider that fails leaves the value empty rather than
+//! rejecting the location.
+
+use super::models::ActiveModel;
+use crate::config::Config;
+use crate::external::site::{fetch_elevation, fetch_timezone};
+use rust_decimal::{Decimal, prelude::ToPrimitive};
+use sea_orm::{ActiveValue::Set, DbErr};
+use std::sync::RwLock;
+
+static PROVIDERS: RwLock<SiteProviders> = RwLock::new(SiteProviders {
+    elevation_api_url: None,
+    timezone_api_url: None,
+});
+
+#[derive(Clone, Debug, Default)]
+pub struct SiteProviders {
+    pub elevation_api_url: Option<String>,
+    pub timezone_api_url: Option<String>,
+}
+
+/// Use the providers of the configuration
+pub fn configure(config: &Config) {
+    *PROVIDERS
+        .write()
+        .unwrap_or_else(std::sync::PoisonError::into_inner) = SiteProviders {
+        elevation_api_url: config.elevation_api_url.clone(),
+        timezone_api_url: config.timezone_api_url.clone(),
+    };
+}
+
+/// The providers in use
+pub fn providers() -> SiteProviders {
+    PROVIDERS
+        .read()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .clone()
+}
+
+/// Check that coordinates are given together and on the globe
+pub fn validate_coordinates(
+    latitude: Option<Decimal>,
+    longitude: Option<Decimal>,
+) -> Result<(), DbErr> {
+    match (latitude, longitude) {
+        (None, None) => Ok(()),
+        (Some(latitude), Some(longitude))
+            if (Decimal::from(-90)..=Decimal::from(90)).contains(&latitude)
+                && (Decimal::from(-180)..=Decimal::from(180)).contains(&longitude) =>
+        {
+            Ok(())
+        }
+        (Some(_), Some(_)) => Err(DbErr::Custom(
+            "Latitude must be between -90 and 90 and longitude between -180 and 180".to_string(),
+        )),
+        _ => Err(DbErr::Custom(
+            "Latitude and longitude must be given together".to_string(),
+        )),
+    }
+}
+
+/// Look up what is asked for at the location's coordinates. Lookups that
+/// fail are logged and leave the value as it was.
+pub async fn fill_site_details(
+    providers: &SiteProviders,
+    location: &mut ActiveModel,
+    elevation: bool,
+    timezone: bool,
+) {
+    let (Some(Some(latitude)), Some(Some(longitude))) = (
+        location.latitude.try_as_ref().copied(),
+        location.longitude.try_as_ref().copied(),
+    ) else {
+        return;
+    };
+    let (Some(latitude), Some(longitude)) = (latitude.to_f64(), longitude.to_f64()) else {
+        return;
+    };
+
+    if elevation && let Some(url) = &providers.elevation_api_url {
+        match fetch_elevation(url, latitude, longitude).await {
+            Ok(elevation_m) => location.elevation_m = Set(Some(elevation_m)),
+            Err(e) => tracing::warn!("Could not look up the elevation of a location: {e}"),
+        }
+    }
+    if timezone && let Some(url) = &providers.timezone_api_url {
+        match fetch_timezone(url, latitude, longitude).await {
+            Ok(timezone) => location.timezone = Set(Some(timezone)),
+            Err(e) => tracing::warn!("Could not look up the time zone of a location: {e}"),
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use core::panic;
 
-use crate::config::test_helpers::setup_test_app;
+use crate::common::keycloak::test_realm;
+use crate::config::test_helpers::{send_json_as, setup_authenticated_test_app, setup_test_app};
 use axum::body::{Body, to_bytes};
 use axum::http::{Request, StatusCode};
 use serde_json::{Value, json};
@@ -296,6 +297,169 @@ async fn test_location_filtering_and_pagination() {
     );
 }
 
+#[tokio::test]
+async fn test_location_cursor_pagination() {
+    let app = setup_test_app().await;
+    let project_id = create_test_project(&app).await;
+    for i in 1..=5 {
+        let location_data = json!({
+            "name": format!("Cursor Location {i}"),
+            "project_id": project_id
+        });
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/locations")
+                    .header("content-type", "application/json")
+                    .body(Body::from(location_data.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+
+    // Pages of two, newest name first, walked with the cursor alone
+    let filter = format!("filter=%7B%22project_id%22%3A%22{project_id}%22%7D");
+    let mut uri = format!("/api/locations?{filter}&sort=%5B%22name%22%2C%22DESC%22%5D&page_size=2");
+    let mut names = Vec::new();
+    let mut pages = 0;
+    loop {
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri(&uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let headers = response.headers().clone();
+        let (status, body) = extract_response_body(response).await;
+        assert_eq!(status, StatusCode::OK, "Failed to page locations: {body:?}");
+        assert_eq!(headers["x-total-count"], "5");
+        pages += 1;
+        names.extend(
+            body.as_array()
+                .unwrap()
+                .iter()
+                .map(|location| location["name"].as_str().unwrap().to_string()),
+        );
+        let Some(cursor) = headers.get("x-next-cursor") else {
+            break;
+        };
+        uri = format!(
+            "/api/locations?{filter}&page_size=2&cursor={}",
+            cursor.to_str().unwrap()
+        );
+    }
+    assert_eq!(pages, 3);
+    assert_eq!(
+        names,
+        (1..=5)
+            .rev()
+            .map(|i| format!("Cursor Location {i}"))
+            .collect::<Vec<_>>()
+    );
+
+    // Lists paged by offset give the total too
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/locations?{filter}&range=%5B0%2C1%5D"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.headers()["x-total-count"], "5");
+
+    for uri in ["/api/locations?page_size=0", "/api/locations?cursor=nonsense"] {
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{uri}");
+    }
+}
+
+#[tokio::test]
+async fn test_location_cursor_after_deleted_record() {
+    let app = setup_test_app().await;
+    let project_id = create_test_project(&app).await;
+    for i in 1..=4 {
+        let location_data = json!({
+            "name": format!("Cursor Location {i}"),
+            "project_id": project_id
+        });
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/locations")
+                    .header("content-type", "application/json")
+                    .body(Body::from(location_data.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+
+    // Pages of two, newest name first
+    let filter = format!("filter=%7B%22project_id%22%3A%22{project_id}%22%7D");
+    let uri = format!("/api/locations?{filter}&sort=%5B%22name%22%2C%22DESC%22%5D&page_size=2");
+    let response = app
+        .clone()
+        .oneshot(Request::builder().uri(&uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let cursor = response.headers()["x-next-cursor"]
+        .to_str()
+        .unwrap()
+        .to_string();
+    let (_, first_page) = extract_response_body(response).await;
+
+    // The next page follows on from the last record, even once it is deleted
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("DELETE")
+                .uri(format!(
+                    "/api/locations/{}",
+                    first_page[1]["id"].as_str().unwrap()
+                ))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!(
+                    "/api/locations?{filter}&page_size=2&cursor={cursor}"
+                ))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (status, next_page) = extract_response_body(response).await;
+    assert_eq!(
+        status,
+        StatusCode::OK,
+        "Failed to follow the cursor: {next_page:?}"
+    );
+    assert_eq!(first_page[1]["name"], "Cursor Location 3");
+    assert_eq!(next_page[0]["name"], "Cursor Location 2");
+    assert_eq!(next_page[1]["name"], "Cursor Location 1");
+}
+
 // Helper function to create a test location
 async fn create_test_location(
     app: &axum::Router,
@@ -874,3 +1038,263 @@ async fn test_location_helper_functions_consistency() {
         Err(_error) => {}
     }
 }
+
+#[tokio::test]
+async fn test_location_site_details() {
+    let app = setup_test_app().await;
+    let project_id = create_test_project(&app).await;
+    let send = |method: &'static str, uri: String, data: Value| {
+        let app = app.clone();
+        async move {
+            let response = app
+                .oneshot(
+                    Request::builder()
+                        .method(method)
+                        .uri(uri)
+                        .header("content-type", "application/json")
+                        .body(Body::from(data.to_string()))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            extract_response_body(response).await
+        }
+    };
+
+    // Values given with the location are kept
+    let (status, location) = send(
+        "POST",
+        "/api/locations".to_string(),
+        json!({
+            "name": format!("Jungfraujoch {}", uuid::Uuid::new_v4()),
+            "project_id": project_id,
+            "latitude": "46.5475",
+            "longitude": "7.9851",
+            "elevation_m": "3571",
+            "timezone": "Europe/Zurich"
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{location:?}");
+    assert_eq!(location["latitude"], "46.5475");
+    assert_eq!(location["elevation_m"], "3571");
+    assert_eq!(location["timezone"], "Europe/Zurich");
+    let location_id = location["id"].as_str().unwrap().to_string();
+
+    let (status, location) = send(
+        "PUT",
+        format!("/api/locations/{location_id}"),
+        json!({"latitude": "46.8130", "longitude": "6.9440", "elevation_m": "491"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{location:?}");
+    assert_eq!(location["longitude"], "6.944");
+    assert_eq!(location["elevation_m"], "491");
+    assert_eq!(location["timezone"], "Europe/Zurich");
+
+    // Coordinates go together and on the globe
+    let (status, body) = send(
+        "PUT",
+        format!("/api/locations/{location_id}"),
+        json!({"latitude": null}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(body.to_string().contains("given together"), "{body}");
+    let (status, _) = send(
+        "PUT",
+        format!("/api/locations/{location_id}"),
+        json!({"latitude": "91"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let (status, _) = send(
+        "POST",
+        "/api/locations".to_string(),
+        json!({
+            "name": format!("Nowhere {}", uuid::Uuid::new_v4()),
+            "project_id": project_id,
+            "longitude": "7.9851"
+        }),
+    )
+    .await;
+    assert!(!status.is_success());
+}
+
+#[tokio::test]
+async fn test_fill_site_details() {
+    use crate::locations::models::ActiveModel;
+    use crate::locations::site::{SiteProviders, fill_site_details};
+    use rust_decimal::Decimal;
+    use sea_orm::ActiveValue::Set;
+    use std::str::FromStr;
+
+    let provider = axum::Router::new()
+        .route(
+            "/v1/elevation",
+            axum::routing::get(|| async { axum::Json(json!({"elevation": [3571.456]})) }),
+        )
+        .route(
+            "/v1/forecast",
+            axum::routing::get(|| async { axum::Json(json!({"timezone": "Europe/Zurich"})) }),
+        )
+        .route(
+            "/v1/abbreviation",
+            axum::routing::get(|| async { axum::Json(json!({"timezone": "CET"})) }),
+        );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, provider).await.unwrap() });
+
+    let located = || ActiveModel {
+        latitude: Set(Some(Decimal::from_str("46.5475").unwrap())),
+        longitude: Set(Some(Decimal::from_str("7.9851").unwrap())),
+        elevation_m: Set(None),
+        timezone: Set(None),
+        ..Default::default()
+    };
+    let providers = SiteProviders {
+        elevation_api_url: Some(format!("http://{address}/v1/elevation")),
+        timezone_api_url: Some(format!("http://{address}/v1/forecast")),
+    };
+
+    let mut location = located();
+    fill_site_details(&providers, &mut location, true, true).await;
+    assert_eq!(
+        location.elevation_m,
+        Set(Some(Decimal::from_str("3571.46").unwrap()))
+    );
+    assert_eq!(location.timezone, Set(Some("Europe/Zurich".to_string())));
+
+    // Only what is asked for is looked up
+    let mut location = located();
+    fill_site_details(&providers, &mut location, false, true).await;
+    assert_eq!(location.elevation_m, Set(None));
+    assert_eq!(location.timezone, Set(Some("Europe/Zurich".to_string())));
+
+    // Failed lookups leave the values alone
+    let providers = SiteProviders {
+        elevation_api_url: Some(format!("http://{address}/v1/missing")),
+        timezone_api_url: Some(format!("http://{address}/v1/abbreviation")),
+    };
+    let mut location = located();
+    fill_site_details(&providers, &mut location, true, true).await;
+    assert_eq!(location.elevation_m, Set(None));
+    assert_eq!(location.timezone, Set(None));
+
+    // Nothing is looked up without coordinates
+    let providers = SiteProviders {
+        elevation_api_url: Some(format!("http://{address}/v1/elevation")),
+        timezone_api_url: None,
+    };
+    let mut location = ActiveModel {
+        elevation_m: Set(None),
+        ..Default::default()
+    };
+    fill_site_details(&providers, &mut location, true, true).await;
+    assert_eq!(location.elevation_m, Set(None));
+}
+
+#[tokio::test]
+async fn test_location_access_by_role() {
+    let (app, _db) = setup_authenticated_test_app().await;
+    let admin = test_realm::token("ada", &["spice-admin"], &[]);
+    let editor = test_realm::token("eddie", &["spice-editor"], &[]);
+    let viewer = test_realm::token("vera", &["spice-viewer"], &[]);
+
+    let mut project_ids = Vec::new();
+    let mut location_ids = Vec::new();
+    for name in ["Member project", "Other project"] {
+        let (status, project) = send_json_as(
+            &app,
+            Some(&admin),
+            "POST",
+            "/api/projects",
+            Some(&json!({"name": name})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED, "{project}");
+        let project_id = project["id"].as_str().unwrap().to_string();
+        let (status, location) = send_json_as(
+            &app,
+            Some(&admin),
+            "POST",
+            "/api/locations",
+            Some(&json!({"name": format!("{name} site"), "project_id": project_id})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED, "{location}");
+        location_ids.push(location["id"].as_str().unwrap().to_string());
+        project_ids.push(project_id);
+    }
+    let (status, _) = send_json_as(
+        &app,
+        Some(&admin),
+        "PUT",
+        &format!("/api/projects/{}/members", project_ids[0]),
+        Some(&json!(["eddie", "vera"])),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = send_json_as(&app, None, "GET", "/api/locations", None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // Viewers read the locations of their projects, and change nothing
+    let (status, listed) = send_json_as(&app, Some(&viewer), "GET", "/api/locations", None).await;
+    assert_eq!(status, StatusCode::OK, "{listed}");
+    let listed: Vec<&str> = listed
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|location| location["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(listed, vec![location_ids[0].as_str()]);
+    for (location_id, expected) in location_ids
+        .iter()
+        .zip([StatusCode::OK, StatusCode::FORBIDDEN])
+    {
+        let uri = format!("/api/locations/{location_id}");
+        let (status, _) = send_json_as(&app, Some(&viewer), "GET", &uri, None).await;
+        assert_eq!(status, expected);
+    }
+    let new_location = json!({"name": "Viewer site", "project_id": project_ids[0]});
+    let (status, _) = send_json_as(
+        &app,
+        Some(&viewer),
+        "POST",
+        "/api/locations",
+        Some(&new_location),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Editors change them, within their projects
+    let (status, created) = send_json_as(
+        &app,
+        Some(&editor),
+        "POST",
+        "/api/locations",
+        Some(&json!({"name": "Editor site", "project_id": project_ids[0]})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{created}");
+    let (status, _) = send_json_as(
+        &app,
+        Some(&editor),
+        "POST",
+        "/api/locations",
+        Some(&json!({"name": "Elsewhere", "project_id": project_ids[1]})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = send_json_as(
+        &app,
+        Some(&editor),
+        "PATCH",
+        &format!("/api/locations/{}", location_ids[1]),
+        Some(&json!({"comment": "Not mine"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
@@ -1,18 +1,41 @@
-use super::models::{Location, router as crudrouter};
-use crate::common::auth::Role;
+use super::models::{Column, Location, LocationList, router as crudrouter};
+use crate::api_keys::services::accept_api_keys;
+use crate::audit::services::{AuditedResource, audit_changes};
+use crate::common::auth::{RouteAccess, require_role};
+use crate::common::etag::check_entity_tags;
+use crate::common::keycloak::authenticate;
+use crate::common::labs::{TenantResource, require_lab};
+use crate::common::pagination::paginate_by_cursor;
+use crate::common::patch::patch_one_handler;
+use crate::common::redaction::redact_for_viewers;
+use crate::common::spatial::{SpatialQuery, page, spatial_condition};
 use crate::common::state::AppState;
-use axum::extract::{Path, State};
+use crate::projects::access::{ScopedResource, require_project_access};
+use crate::projects::archiving::reject_archived_changes;
+use axum::extract::{Path, Query, State};
+use axum::middleware;
 use axum::response::Json;
-use axum::routing::get;
-use axum_keycloak_auth::{PassthroughMode, layer::KeycloakAuthLayer};
+use axum::routing::{get, patch};
+use axum_keycloak_auth::PassthroughMode;
 use crudcrate::CRUDResource;
-use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, Statement};
+use sea_orm::{ColumnTrait, ConnectionTrait, DbErr, EntityTrait, Order, QueryFilter, Statement};
 use serde_json::{Value, json};
+use utoipa::OpenApi;
 use utoipa_axum::router::OpenApiRouter;
 use uuid::Uuid;
 
+/// Routes beyond the CRUD routes, documented here as they are routed
+/// with `route`
+#[derive(OpenApi)]
+#[openapi(paths(get_location_samples, get_location_experiments, get_spatial_locations))]
+struct LocationsApi;
+
 pub fn router(state: &AppState) -> OpenApiRouter {
-    let mut mutating_router = crudrouter(&state.db.clone());
+    let mut mutating_router = crudrouter(&state.db.clone())
+        .route(
+            "/{id}",
+            patch(patch_one_handler::<Location>).with_state(state.db.clone()),
+        );
 
     // Add custom routes for fetching related data with OpenAPI documentation
     mutating_router = mutating_router
@@ -23,18 +46,63 @@ pub fn router(state: &AppState) -> OpenApiRouter {
         .route(
             "/{id}/experiments",
             get(get_location_experiments).with_state(state.clone()),
+        )
+        .route(
+            "/spatial",
+            get(get_spatial_locations).with_state(state.clone()),
         );
+    mutating_router
+        .get_openapi_mut()
+        .merge(LocationsApi::openapi());
+
+    mutating_router = mutating_router.layer(middleware::from_fn_with_state(
+        state.db.clone(),
+        paginate_by_cursor::<Location>,
+    ));
+
+    mutating_router = mutating_router.layer(middleware::from_fn_with_state(
+        (state.db.clone(), state.config.require_if_match),
+        check_entity_tags::<Location>,
+    ));
+
+    // Archived projects and their records are read-only, even to administrators
+    mutating_router = mutating_router.layer(middleware::from_fn_with_state(
+        (state.db.clone(), Some(ScopedResource::Locations)),
+        reject_archived_changes,
+    ));
+
+    // Changes are logged with the user who made them
+    mutating_router = mutating_router.layer(middleware::from_fn_with_state(
+        (state.db.clone(), AuditedResource::Locations),
+        audit_changes,
+    ));
 
     if let Some(instance) = state.keycloak_auth_instance.clone() {
-        mutating_router = mutating_router.layer(
-            KeycloakAuthLayer::<Role>::builder()
-                .instance(instance)
-                .passthrough_mode(PassthroughMode::Block)
-                .persist_raw_claims(false)
-                .expected_audiences(vec![String::from("account")])
-                .required_roles(vec![Role::Administrator])
-                .build(),
-        );
+        // Viewers read and editors change records; users who are not
+        // administrators are limited to the projects they are members of,
+        // within their labs
+        mutating_router = mutating_router
+            .layer(middleware::from_fn_with_state(
+                (state.db.clone(), ScopedResource::Locations),
+                require_project_access,
+            ))
+            .layer(middleware::from_fn_with_state(
+                (state.db.clone(), TenantResource::Locations),
+                require_lab,
+            ))
+            .layer(middleware::from_fn_with_state(
+                RouteAccess::PROJECT_RECORDS,
+                require_role,
+            ))
+            .layer(middleware::from_fn(redact_for_viewers))
+            .layer(middleware::from_fn_with_state(
+                (state.db.clone(), "locations"),
+                accept_api_keys,
+            ))
+            .layer(middleware::from_fn_with_state(
+                (instance, PassthroughMode::Pass),
+                authenticate,
+            ));
     } else if !state.config.tests_running {
         println!(
             "Warning: Mutating routes of {} router are not protected",
@@ -202,3 +270,37 @@ pub async fn get_location_experiments(
 
     Ok(Json(json!(experiments_data)))
 }
+
+/// Locations within an area, using the convex hull of their samples
+#[utoipa::path(
+    get,
+    path = "/locations/spatial",
+    params(SpatialQuery),
+    responses(
+        (status = 200, description = "Locations intersecting every given area, by name", body = Vec<LocationList>),
+        (status = 400, description = "Missing or invalid filter, or a database without PostGIS"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "locations",
+    summary = "Find locations within an area",
+    description = "Filter locations whose samples fall in a bounding box, within a distance from a point or in a polygon, in WGS 84 longitude and latitude. Combined filters must all match"
+)]
+pub async fn get_spatial_locations(
+    State(app_state): State<AppState>,
+    Query(query): Query<SpatialQuery>,
+) -> Result<Json<Vec<LocationList>>, (axum::http::StatusCode, String)> {
+    let db = &app_state.db;
+    let locations = async {
+        let condition = spatial_condition(
+            db.get_database_backend(),
+            "\"locations\".\"geom\"",
+            &query,
+        )?;
+        let (offset, limit) = page(&query)?;
+        Location::get_all(db, &condition, Column::Name, Order::Asc, offset, limit).await
+    };
+    locations.await.map(Json).map_err(|e| match e {
+        DbErr::Custom(message) => (axum::http::StatusCode::BAD_REQUEST, message),
+        e => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    })
+}
@@ -4,53 +4,150 @@ mod external;
 mod routes;
 mod services;
 
+mod api_keys;
 mod assets;
+mod audit;
+mod changes;
 mod experiments;
+mod exports;
+mod freezing_results;
+mod graphql;
+mod idempotency;
 mod locations;
+mod maintenance;
 mod nucleation_events;
+mod planned_experiments;
 mod projects;
 mod samples;
 mod tray_configurations;
 mod treatments;
+mod users;
+mod versions;
+mod webhooks;
 
 use crate::config::Config;
+use clap::{Arg, ArgAction, ArgMatches, Command};
 use migration::{Migrator, MigratorTrait};
 use sea_orm::{Database, DatabaseConnection};
 
+fn cli() -> Command {
+    Command::new("spice-api")
+        .about("API for managing SPICE lab data in the EERL lab")
+        .arg(
+            Arg::new("migration-status")
+                .long("migration-status")
+                .action(ArgAction::SetTrue)
+                .help("Print the applied and pending migrations, and exit"),
+        )
+        .arg(
+            Arg::new("migrate-down")
+                .long("migrate-down")
+                .value_name("STEPS")
+                .value_parser(clap::value_parser!(u32))
+                .requires("confirm")
+                .help("Revert the latest STEPS applied migrations, and exit"),
+        )
+        .arg(
+            Arg::new("seed-demo")
+                .long("seed-demo")
+                .action(ArgAction::SetTrue)
+                .help("Seed a demo project with a processed experiment, unless it exists"),
+        )
+        .arg(
+            Arg::new("confirm")
+                .long("confirm")
+                .value_name("MIGRATION")
+                .help("Name of the earliest migration --migrate-down reverts"),
+        )
+}
+
+/// Look at or revert migrations instead of serving, if asked to
+async fn run_migration_command(db: &DatabaseConnection, matches: &ArgMatches) -> bool {
+    let steps = matches.get_one::<u32>("migrate-down").copied();
+    if !matches.get_flag("migration-status") && steps.is_none() {
+        return false;
+    }
+    if let Some(steps) = steps {
+        let downgrade = maintenance::models::MigrationDowngrade {
+            steps,
+            confirm: matches
+                .get_one::<String>("confirm")
+                .cloned()
+                .unwrap_or_default(),
+        };
+        match maintenance::migrations::revert_migrations(db, downgrade).await {
+            Ok(reverted) => reverted.iter().for_each(|name| println!("Reverted {name}")),
+            Err(e) => {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+        }
+    }
+    let migrations = maintenance::migrations::migration_status(db)
+        .await
+        .expect("Failed to read the migrations");
+    for migration in migrations {
+        let applied_at = migration
+            .applied_at
+            .map(|at| at.to_rfc3339())
+            .unwrap_or_default();
+        let state = format!("{:?}", migration.state);
+        println!("{state:<8} {applied_at:<25} {}", migration.name);
+    }
+    true
+}
+
 #[tokio::main]
 async fn main() {
+    let matches = cli().get_matches();
     // Set up tracing/logging
     tracing_subscriber::fmt::init();
     println!("Starting server...");
 
     // Load configuration and environment variables to pass to the application
-    let config: Config = Config::from_env();
+    let config: Config = Config::load().unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(1);
+    });
+    // Reports panics and server errors for as long as it is kept
+    let _error_reporting = common::error_reporting::init(&config);
 
-    let db: DatabaseConnection = Database::connect(config.db_url.as_ref().unwrap())
+    // Dependencies are checked by the /healthz and /readyz probes from here on
+    let db: DatabaseConnection = Database::connect(config.connect_options())
         .await
-        .unwrap();
+        .expect("Failed to connect to the database");
 
-    if db.ping().await.is_ok() {
-        println!("Connected to the database");
-    } else {
-        println!("Could not connect to the database");
+    if run_migration_command(&db, &matches).await {
+        return;
     }
 
-    // Run migrations
+    // Run migrations; see --migrate-down to downgrade
     Migrator::up(&db, None)
         .await
         .expect("Failed to run migrations");
 
-    // Left commented here in case of need to downgrade
-    // Migrator::down(&db, Some(1)).await.expect("Failed to run downgrade migration");
-
     println!("DB migrations complete");
 
-    // Initialize S3 bucket if needed
+    if let Err(e) = experiments::temperatures::timescale::check_series(&db, &config).await {
+        eprintln!("{e}");
+        std::process::exit(1);
+    }
+
+    if matches.get_flag("seed-demo") {
+        match maintenance::demo::seed_demo(&db).await {
+            Ok(report) => println!("Demo data seeded in project {}", report.project_id),
+            Err(e) => println!("Demo data not seeded: {e}"),
+        }
+    }
+
+    // Initialize the storage bucket if needed
     if let Err(e) = external::s3::ensure_bucket_exists(&config).await {
-        eprintln!("Warning: Failed to initialize S3 bucket: {e}");
+        eprintln!(
+            "Warning: Failed to initialize {} storage: {e}",
+            config.storage_backend
+        );
     } else if !config.tests_running {
-        println!("S3 bucket '{}' ready", config.s3_bucket_id);
+        println!("{} storage ready", config.storage_backend);
     }
 
     println!(
@@ -70,7 +167,7 @@ async fn main() {
 
     axum::serve(
         tokio::net::TcpListener::bind(addr).await.unwrap(),
-        router.into_make_service(),
+        router.into_make_service_with_connect_info::<std::net::SocketAddr>(),
     )
     .await
     .unwrap();
@@ -78,6 +175,18 @@ async fn main() {
 
 #[cfg(test)]
 mod tests {
+    #[test]
+    fn test_migrate_down_needs_confirmation() {
+        let unconfirmed = super::cli().try_get_matches_from(["spice-api", "--migrate-down", "1"]);
+        assert!(unconfirmed.is_err());
+
+        let matches = super::cli()
+            .try_get_matches_from(["spice-api", "--migrate-down", "2", "--confirm", "m1"])
+            .unwrap();
+        assert_eq!(matches.get_one::<u32>("migrate-down"), Some(&2));
+        assert!(!matches.get_flag("migration-status"));
+    }
+
     #[test]
     fn test_socket_address_parsing() {
         // Test that the socket address used in main can be parsed
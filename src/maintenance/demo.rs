@@ -0,0 +1,317 @@
+//! Demo data for new deployments and frontend development.
+//!
+//! A project is created with a location, samples and their treatments, a tray
+//! configuration of two 96-well trays with their probes, and an experiment
+//! whose regions place the treatments on the trays. The experiment is then
+//! processed from a generated run, in the layout of the files of the
+//! acquisition software, so that it has readings, phase transitions and
+//! results like an uploaded one; no file is kept in storage. Records are
+//! created as the API creates them, and the demo is only seeded once.
+
+use super::models::DemoReport;
+use crate::experiments::models::Experiment;
+use crate::locations::models::Location;
+use crate::projects::models::{self as projects, Project};
+use crate::samples::models::Sample;
+use crate::services::processing::excel_processor::ExcelProcessor;
+use crate::tray_configurations::models::TrayConfiguration;
+use crate::treatments::models::TreatmentName;
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use crudcrate::CRUDResource;
+use rust_xlsxwriter::{Workbook, Worksheet, XlsxError};
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, PaginatorTrait, QueryFilter, RuntimeErr,
+};
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+const DEMO_PROJECT: &str = "SPICE demo project";
+
+const TRAYS: [&str; 2] = ["P1", "P2"];
+const ROWS: u8 = 8;
+const COLUMNS: u16 = 12;
+/// Data logger channels, four probes on each tray
+const PROBES: i32 = 8;
+const START_TEMPERATURE: f64 = 0.0;
+const END_TEMPERATURE: f64 = -25.0;
+/// Cooling in °C per minute
+const RAMP: f64 = 1.0;
+const SECONDS_PER_READING: u32 = 10;
+/// Readings after the first, over the 25 minutes of the ramp
+const READINGS: u32 = 150;
+
+fn create_data<T: DeserializeOwned>(data: Value) -> Result<T, DbErr> {
+    serde_json::from_value(data).map_err(|e| DbErr::Custom(format!("Invalid demo record: {e}")))
+}
+
+fn internal_error(message: String) -> DbErr {
+    DbErr::Exec(RuntimeErr::Internal(message))
+}
+
+/// Temperature at which a well of the demo freezes. Each half of a tray holds
+/// one region, whose wells freeze over a range of its own, in scattered order.
+fn freezing_temperature(tray: usize, row: u8, column: u16) -> f64 {
+    let (warmest, range) = match (tray, column < COLUMNS / 2) {
+        (0, true) => (-8.0, 8.0),
+        (0, false) => (-12.0, 7.0),
+        (1, true) => (-15.0, 6.0),
+        _ => (-20.0, 4.0),
+    };
+    let wells = u16::from(ROWS) * COLUMNS / 2;
+    let rank = (u16::from(row) * COLUMNS / 2 + column % (COLUMNS / 2)) * 29 % wells;
+    warmest - range * f64::from(rank) / f64::from(wells - 1)
+}
+
+fn write_run(worksheet: &mut Worksheet, started_at: DateTime<Utc>) -> Result<(), XlsxError> {
+    // Tray names in row 1, well coordinates in row 2 and headers in row 7
+    worksheet
+        .write_string(6, 0, "Date")?
+        .write_string(6, 1, "Time")?;
+    for channel in 1..=PROBES {
+        let column = u16::try_from(channel + 1).unwrap_or(u16::MAX);
+        worksheet.write_string(6, column, format!("Temperature {channel} (°C)"))?;
+    }
+    let well_start = u16::try_from(PROBES + 2).unwrap_or(u16::MAX);
+    let mut wells = Vec::new();
+    for (tray, name) in TRAYS.iter().enumerate() {
+        for row in 0..ROWS {
+            for column in 0..COLUMNS {
+                let sheet_column = well_start + u16::try_from(wells.len()).unwrap_or(u16::MAX);
+                let coordinate = format!("{}{}", char::from(b'A' + row), column + 1);
+                worksheet
+                    .write_string(0, sheet_column, *name)?
+                    .write_string(1, sheet_column, coordinate)?
+                    .write_string(6, sheet_column, "()")?;
+                wells.push((sheet_column, freezing_temperature(tray, row, column)));
+            }
+        }
+    }
+
+    for reading in 0..=READINGS {
+        let row = 7 + reading;
+        let seconds = reading * SECONDS_PER_READING;
+        let timestamp = started_at + Duration::seconds(i64::from(seconds));
+        let temperature = START_TEMPERATURE - RAMP * f64::from(seconds) / 60.0;
+        worksheet
+            .write_string(row, 0, timestamp.format("%Y-%m-%d").to_string())?
+            .write_string(row, 1, timestamp.format("%H:%M:%S").to_string())?;
+        // The probes across the trays read a slight gradient
+        for channel in 1..=PROBES {
+            let column = u16::try_from(channel + 1).unwrap_or(u16::MAX);
+            let offset = f64::from(channel - PROBES / 2) * 0.05;
+            worksheet.write_number(
+                row,
+                column,
+                ((temperature + offset) * 100.0).round() / 100.0,
+            )?;
+        }
+        for (column, freezes_at) in &wells {
+            worksheet.write_number(row, *column, u8::from(temperature <= *freezes_at))?;
+        }
+    }
+    Ok(())
+}
+
+/// Workbook of a demo run, cooling the trays from 0 °C to -25 °C
+fn demo_workbook(started_at: DateTime<Utc>) -> Result<Vec<u8>, DbErr> {
+    let mut workbook = Workbook::new();
+    write_run(workbook.add_worksheet(), started_at)
+        .and_then(|()| workbook.save_to_buffer())
+        .map_err(|e| internal_error(format!("Failed to write the demo run: {e}")))
+}
+
+fn tray(name: &str, order_sequence: i32, rotation_degrees: i32, channels: [i32; 4]) -> Value {
+    let positions = [(22.1, 77.6), (47.1, 20.0), (113.0, 19.5), (143.5, 79.5)];
+    json!({
+        "name": name,
+        "rotation_degrees": rotation_degrees,
+        "well_relative_diameter": 6.4,
+        "qty_cols": COLUMNS,
+        "qty_rows": ROWS,
+        "order_sequence": order_sequence,
+        "probe_locations": channels
+            .iter()
+            .zip(positions)
+            .map(|(channel, (x, y))| json!({
+                "data_column_index": channel,
+                "position_x": x,
+                "position_y": y,
+                "name": format!("Probe {channel}")
+            }))
+            .collect::<Vec<_>>()
+    })
+}
+
+fn treatment_id(sample: &Sample, name: &TreatmentName) -> Option<Uuid> {
+    sample
+        .treatments
+        .iter()
+        .find(|treatment| treatment.name == *name)
+        .map(|treatment| treatment.id)
+}
+
+/// Create the demo project and everything in it, unless it exists
+#[allow(clippy::too_many_lines)] // One record after the other
+pub async fn seed_demo(db: &DatabaseConnection) -> Result<DemoReport, DbErr> {
+    let existing = projects::Entity::find()
+        .filter(projects::Column::Name.eq(DEMO_PROJECT))
+        .count(db)
+        .await?;
+    if existing > 0 {
+        return Err(DbErr::Custom(format!(
+            "The demo data has already been seeded, in the project named {DEMO_PROJECT}"
+        )));
+    }
+    let performed_at = (Utc::now() - Duration::days(1))
+        .duration_trunc(Duration::hours(1))
+        .map_err(|e| internal_error(e.to_string()))?;
+    let day = performed_at.format("%Y-%m-%d");
+
+    let project = Project::create(
+        db,
+        create_data(json!({
+            "name": DEMO_PROJECT,
+            "colour": "#2563EB",
+            "note": "Demo data of a filter sampling campaign, seeded to try the API and its frontend"
+        }))?,
+    )
+    .await?;
+    let location = Location::create(
+        db,
+        create_data(json!({
+            "name": "Demo site: Jungfraujoch",
+            "comment": "High-altitude research station (46.5475°, 7.9853°)",
+            "project_id": project.id
+        }))?,
+    )
+    .await?;
+
+    let filter = Sample::create(
+        db,
+        create_data(json!({
+            "name": "Demo filter JFJ-001",
+            "type": "filter",
+            "location_id": location.id,
+            "start_time": format!("{day}T06:00:00Z"),
+            "stop_time": format!("{day}T08:00:00Z"),
+            "flow_litres_per_minute": 12.5,
+            "total_volume": 1500.0,
+            "filter_substrate": "Polycarbonate",
+            "suspension_volume_litres": 0.01,
+            "well_volume_litres": 0.00005,
+            "remarks": "Aerosol filter sampled over two hours",
+            "treatments": [
+                {"name": "none", "notes": "Untreated"},
+                {"name": "heat", "notes": "Heated at 95 °C for 20 minutes, removing heat-labile biological INPs"},
+                {"name": "h2o2", "notes": "Digested with hydrogen peroxide, removing organic INPs", "enzyme_volume_litres": 0.0002}
+            ]
+        }))?,
+    )
+    .await?;
+    let bulk = Sample::create(
+        db,
+        create_data(json!({
+            "name": "Demo snow JFJ-002",
+            "type": "bulk",
+            "location_id": location.id,
+            "start_time": format!("{day}T09:00:00Z"),
+            "latitude": 46.5475,
+            "longitude": 7.9853,
+            "suspension_volume_litres": 0.02,
+            "well_volume_litres": 0.00005,
+            "remarks": "Fresh snow, melted at room temperature",
+            "treatments": [{"name": "none"}, {"name": "heat"}]
+        }))?,
+    )
+    .await?;
+    let blank = Sample::create(
+        db,
+        create_data(json!({
+            "name": "Demo field blank",
+            "type": "blank",
+            "well_volume_litres": 0.00005,
+            "remarks": "Filter handled in the field without sampling",
+            "treatments": [{"name": "none"}]
+        }))?,
+    )
+    .await?;
+
+    let tray_configuration = TrayConfiguration::create(
+        db,
+        create_data(json!({
+            "name": "Demo freezing assay",
+            "experiment_default": false,
+            "trays": [
+                tray(TRAYS[0], 1, 90, [1, 2, 3, 4]),
+                tray(TRAYS[1], 2, 270, [5, 6, 7, 8])
+            ]
+        }))?,
+    )
+    .await?;
+
+    // Each half of a tray is a region, matching the freezing of its wells
+    let region = |name: &str, tray: i32, columns: (u16, u16), treatment: Option<Uuid>| {
+        json!({
+            "name": name,
+            "tray_id": tray,
+            "row_min": 0,
+            "row_max": ROWS - 1,
+            "col_min": columns.0,
+            "col_max": columns.1,
+            "treatment_id": treatment,
+            "dilution_factor": 1,
+            "is_background_key": false
+        })
+    };
+    let (left, right) = ((0, COLUMNS / 2 - 1), (COLUMNS / 2, COLUMNS - 1));
+    let mut blank_region = region(
+        "Field blank",
+        2,
+        right,
+        treatment_id(&blank, &TreatmentName::None),
+    );
+    blank_region["is_background_key"] = json!(true);
+    let experiment = Experiment::create(
+        db,
+        create_data(json!({
+            "name": "Demo freezing experiment",
+            "username": "demo@spice.local",
+            "performed_at": performed_at,
+            "temperature_ramp": -RAMP,
+            "temperature_start": START_TEMPERATURE,
+            "temperature_end": END_TEMPERATURE,
+            "is_calibration": false,
+            "remarks": "Processed from a generated run",
+            "project_id": project.id,
+            "tray_configuration_id": tray_configuration.id,
+            "regions": [
+                region("Untreated", 1, left, treatment_id(&filter, &TreatmentName::None)),
+                region("Heat treated", 1, right, treatment_id(&filter, &TreatmentName::Heat)),
+                region("H2O2 treated", 2, left, treatment_id(&filter, &TreatmentName::H2o2)),
+                blank_region
+            ]
+        }))?,
+    )
+    .await?;
+
+    let result = ExcelProcessor::new(db.clone())
+        .process_excel_file(experiment.id, demo_workbook(performed_at)?)
+        .await
+        .map_err(|e| internal_error(e.to_string()))?;
+    if let Some(error) = result.error {
+        return Err(internal_error(format!(
+            "Failed to process the demo experiment: {error}"
+        )));
+    }
+
+    Ok(DemoReport {
+        project_id: project.id,
+        location_id: location.id,
+        sample_ids: vec![filter.id, bulk.id, blank.id],
+        tray_configuration_id: tray_configuration.id,
+        experiment_id: experiment.id,
+        temperature_readings: result.temperature_readings_created,
+        phase_transitions: result.phase_transitions_created,
+    })
+}
@@ -0,0 +1,94 @@
+//! Status of the database migrations, and reverting the latest of them.
+//!
+//! The API applies pending migrations as it starts, so a migration reverted
+//! here is applied again by the next start of the same version. Revert them
+//! before deploying the version to go back to, whose migrations end before
+//! them. Reverting drops what the migrations added, data included, so the
+//! earliest migration to revert must be named to confirm it.
+
+use super::models::{MigrationDowngrade, MigrationInfo, MigrationState};
+use chrono::DateTime;
+use migration::{Migrator, MigratorTrait};
+use sea_orm::{ConnectionTrait, DatabaseConnection, DbErr};
+use std::collections::HashMap;
+
+/// Every migration of this version, oldest first, then those applied by a
+/// newer version
+pub async fn migration_status(db: &impl ConnectionTrait) -> Result<Vec<MigrationInfo>, DbErr> {
+    let mut applied: HashMap<String, i64> = Migrator::get_migration_models(db)
+        .await?
+        .into_iter()
+        .map(|model| (model.version, model.applied_at))
+        .collect();
+    let mut migrations: Vec<MigrationInfo> = Migrator::migrations()
+        .iter()
+        .map(|migration| {
+            let name = migration.name().to_string();
+            let applied_at = applied.remove(&name);
+            MigrationInfo {
+                state: if applied_at.is_some() {
+                    MigrationState::Applied
+                } else {
+                    MigrationState::Pending
+                },
+                applied_at: applied_at.and_then(|seconds| DateTime::from_timestamp(seconds, 0)),
+                name,
+            }
+        })
+        .collect();
+    let mut unknown: Vec<MigrationInfo> = applied
+        .into_iter()
+        .map(|(name, applied_at)| MigrationInfo {
+            name,
+            state: MigrationState::Unknown,
+            applied_at: DateTime::from_timestamp(applied_at, 0),
+        })
+        .collect();
+    unknown.sort_by(|a, b| a.name.cmp(&b.name));
+    migrations.extend(unknown);
+    Ok(migrations)
+}
+
+/// Revert the latest `steps` applied migrations, once `confirm` names the
+/// earliest of them
+pub async fn revert_migrations(
+    db: &DatabaseConnection,
+    downgrade: MigrationDowngrade,
+) -> Result<Vec<String>, DbErr> {
+    let status = migration_status(db).await?;
+    if let Some(unknown) = status
+        .iter()
+        .find(|migration| migration.state == MigrationState::Unknown)
+    {
+        return Err(DbErr::Custom(format!(
+            "Migration {} was applied by a newer version of the API, which must revert it",
+            unknown.name
+        )));
+    }
+    let applied: Vec<&str> = status
+        .iter()
+        .filter(|migration| migration.state == MigrationState::Applied)
+        .map(|migration| migration.name.as_str())
+        .collect();
+    let steps = usize::try_from(downgrade.steps).unwrap_or(usize::MAX);
+    if steps == 0 || steps > applied.len() {
+        return Err(DbErr::Custom(format!(
+            "Steps must be between 1 and the {} migrations applied",
+            applied.len()
+        )));
+    }
+    let reverted: Vec<String> = applied[applied.len() - steps..]
+        .iter()
+        .rev()
+        .map(|name| (*name).to_string())
+        .collect();
+    let earliest = reverted.last().map_or("", String::as_str);
+    if downgrade.confirm != earliest {
+        return Err(DbErr::Custom(format!(
+            "Reverting {steps} migrations reverts {earliest} last; give its name to confirm"
+        )));
+    }
+
+    Migrator::down(db, Some(downgrade.steps)).await?;
+    Ok(reverted)
+}
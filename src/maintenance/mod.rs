@@ -0,0 +1,8 @@
+pub mod demo;
+pub mod migrations;
+pub mod models;
+pub mod services;
+pub mod views;
+
+#[cfg(test)]
+pub mod tests;
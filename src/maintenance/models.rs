@@ -0,0 +1,125 @@
+use chrono::{DateTime, Utc};
+use sea_orm::FromQueryResult;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+/// Size of a table. Only row counts are known on databases other than
+/// `PostgreSQL`.
+#[derive(Clone, Debug, Serialize, ToSchema, FromQueryResult)]
+pub struct TableStats {
+    pub table: String,
+    /// Rows, as estimated by `PostgreSQL`'s statistics
+    pub rows: i64,
+    /// Rows deleted or updated away and not yet vacuumed
+    pub dead_rows: Option<i64>,
+    /// Bytes of the table, its indexes and its TOAST data
+    pub total_bytes: Option<i64>,
+    pub table_bytes: Option<i64>,
+    pub index_bytes: Option<i64>,
+    /// Last time the statistics of the table were gathered
+    pub last_analyzed: Option<DateTime<Utc>>,
+}
+
+/// Rows an experiment has in the tables that grow with it
+#[derive(Clone, Debug, Default, Serialize, ToSchema)]
+pub struct ExperimentRows {
+    pub experiment_id: Uuid,
+    pub name: String,
+    pub temperature_readings: i64,
+    /// One per probe per reading, the largest table
+    pub probe_temperature_readings: i64,
+    pub phase_transitions: i64,
+    pub assets: i64,
+}
+
+/// Size of an index and how much of it is estimated to be empty space
+#[derive(Clone, Debug, Serialize, ToSchema, FromQueryResult)]
+pub struct IndexStats {
+    pub index: String,
+    pub table: String,
+    pub bytes: i64,
+    /// Scans of the index since statistics were last reset; unused indexes
+    /// only slow writes
+    pub scans: i64,
+    /// Bytes beyond what the index would take freshly built, estimated from
+    /// the table's statistics; `REINDEX` reclaims them
+    pub estimated_bloat_bytes: i64,
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct AnalyzeReport {
+    pub duration_ms: u64,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, IntoParams)]
+pub struct RecomputeQuery {
+    /// Also recompute the experiments whose results summary is kept
+    #[serde(default)]
+    pub all: bool,
+}
+
+/// Results summaries recomputed, by experiment
+#[derive(Clone, Debug, Default, Serialize, ToSchema)]
+pub struct RecomputeReport {
+    pub recomputed: Vec<Uuid>,
+    /// Experiments without results, such as those with no tray configuration
+    pub without_results: Vec<Uuid>,
+    /// Experiments whose results could not be built, with why
+    pub failed: Vec<RecomputeFailure>,
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct RecomputeFailure {
+    pub experiment_id: Uuid,
+    pub error: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MigrationState {
+    Applied,
+    Pending,
+    /// Applied by a newer version of the API, which does not know it
+    Unknown,
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct MigrationInfo {
+    pub name: String,
+    pub state: MigrationState,
+    pub applied_at: Option<DateTime<Utc>>,
+}
+
+fn one_step() -> u32 {
+    1
+}
+
+#[derive(Clone, Debug, Deserialize, ToSchema)]
+pub struct MigrationDowngrade {
+    /// Latest applied migrations to revert
+    #[serde(default = "one_step")]
+    pub steps: u32,
+    /// Name of the earliest migration the downgrade reverts
+    pub confirm: String,
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct MigrationDowngradeReport {
+    /// Migrations reverted, latest first
+    pub reverted: Vec<String>,
+    pub migrations: Vec<MigrationInfo>,
+}
+
+/// Records created for the demo
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct DemoReport {
+    pub project_id: Uuid,
+    pub location_id: Uuid,
+    pub sample_ids: Vec<Uuid>,
+    pub tray_configuration_id: Uuid,
+    /// Experiment processed from a generated run
+    pub experiment_id: Uuid,
+    pub temperature_readings: usize,
+    pub phase_transitions: usize,
+}
@@ -0,0 +1,247 @@
+//! Database statistics and maintenance for operators.
+//!
+//! Sizes, dead rows and index bloat come from `PostgreSQL`'s own statistics,
+//! which are estimates kept up to date by autovacuum and by `ANALYZE`. On
+//! other databases, used in tests, tables are counted and index statistics
+//! are not available.
+
+use super::models::{
+    AnalyzeReport, ExperimentRows, IndexStats, RecomputeFailure, RecomputeQuery, RecomputeReport,
+    TableStats,
+};
+use crate::assets::models as assets;
+use crate::experiments::models as experiments;
+use crate::experiments::phase_transitions::models as phase_transitions;
+use crate::experiments::probe_temperature_readings::models as probe_temperature_readings;
+use crate::experiments::results_summaries::models as results_summaries;
+use crate::experiments::summaries::recompute_results_summary;
+use crate::experiments::temperatures::models as temperature_readings;
+use sea_orm::sea_query::{Expr, Query};
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, DatabaseConnection, DbBackend, DbErr, EntityTrait,
+    FromQueryResult, JoinType, QueryFilter, QuerySelect, RelationTrait, Select, Statement,
+};
+use std::collections::HashMap;
+use std::time::Instant;
+use uuid::Uuid;
+
+const TABLE_STATS_SQL: &str = r#"
+    SELECT s.relname AS "table",
+           s.n_live_tup AS rows,
+           s.n_dead_tup AS dead_rows,
+           pg_total_relation_size(s.relid) AS total_bytes,
+           pg_relation_size(s.relid) AS table_bytes,
+           pg_indexes_size(s.relid) AS index_bytes,
+           GREATEST(s.last_analyze, s.last_autoanalyze) AS last_analyzed
+    FROM pg_stat_user_tables s
+    WHERE s.schemaname = current_schema()
+    ORDER BY total_bytes DESC, s.relname
+"#;
+
+/// B-tree indexes with the pages they would take freshly built: each entry
+/// takes the average width of its columns, an 8 byte header and a 4 byte
+/// pointer, in pages filled to the default 90%, after the index's metapage
+const INDEX_STATS_SQL: &str = r#"
+    SELECT ic.relname AS "index",
+           tc.relname AS "table",
+           pg_relation_size(ic.oid) AS bytes,
+           COALESCE(s.idx_scan, 0) AS scans,
+           (GREATEST(
+               ic.relpages - 1 - CEIL(
+                   GREATEST(ic.reltuples, 0)::numeric * (12 + w.width)
+                   / (current_setting('block_size')::numeric * 0.9)
+               ),
+               0
+           ) * current_setting('block_size')::numeric)::bigint AS estimated_bloat_bytes
+    FROM pg_index i
+    JOIN pg_class ic ON ic.oid = i.indexrelid
+    JOIN pg_class tc ON tc.oid = i.indrelid
+    JOIN pg_namespace n ON n.oid = tc.relnamespace
+    JOIN pg_am am ON am.oid = ic.relam AND am.amname = 'btree'
+    LEFT JOIN pg_stat_user_indexes s ON s.indexrelid = i.indexrelid
+    CROSS JOIN LATERAL (
+        SELECT COALESCE(SUM(st.avg_width), 0) AS width
+        FROM pg_attribute a
+        JOIN pg_stats st
+          ON st.schemaname = n.nspname AND st.tablename = tc.relname AND st.attname = a.attname
+        WHERE a.attrelid = ic.oid AND a.attnum > 0
+    ) w
+    WHERE n.nspname = current_schema()
+    ORDER BY estimated_bloat_bytes DESC, bytes DESC, ic.relname
+"#;
+
+/// Size of each table, largest first
+pub async fn table_stats(db: &DatabaseConnection) -> Result<Vec<TableStats>, DbErr> {
+    let backend = db.get_database_backend();
+    if backend == DbBackend::Postgres {
+        return TableStats::find_by_statement(Statement::from_string(backend, TABLE_STATS_SQL))
+            .all(db)
+            .await;
+    }
+
+    let names: Vec<String> = db
+        .query_all(Statement::from_string(
+            backend,
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%'",
+        ))
+        .await?
+        .iter()
+        .map(|row| row.try_get("", "name"))
+        .collect::<Result<_, _>>()?;
+    let mut tables = Vec::with_capacity(names.len());
+    for table in names {
+        let count = format!(
+            "SELECT COUNT(*) AS rows FROM \"{}\"",
+            table.replace('"', "\"\"")
+        );
+        let rows = db
+            .query_one(Statement::from_string(backend, count))
+            .await?
+            .map_or(Ok(0), |row| row.try_get("", "rows"))?;
+        tables.push(TableStats {
+            table,
+            rows,
+            dead_rows: None,
+            total_bytes: None,
+            table_bytes: None,
+            index_bytes: None,
+            last_analyzed: None,
+        });
+    }
+    tables.sort_by(|a, b| b.rows.cmp(&a.rows).then_with(|| a.table.cmp(&b.table)));
+    Ok(tables)
+}
+
+/// Rows of each experiment, counted by the experiment column given
+async fn counts(
+    db: &DatabaseConnection,
+    select: Select<impl EntityTrait>,
+    experiment_id: impl ColumnTrait,
+) -> Result<HashMap<Uuid, i64>, DbErr> {
+    let counts: Vec<(Option<Uuid>, i64)> = select
+        .select_only()
+        .column(experiment_id)
+        .column_as(Expr::cust("COUNT(*)"), "rows")
+        .group_by(experiment_id)
+        .into_tuple()
+        .all(db)
+        .await?;
+    Ok(counts
+        .into_iter()
+        .filter_map(|(id, rows)| Some((id?, rows)))
+        .collect())
+}
+
+/// Rows of each experiment in the tables that grow with it, most readings
+/// first
+pub async fn experiment_rows(db: &DatabaseConnection) -> Result<Vec<ExperimentRows>, DbErr> {
+    let readings = counts(
+        db,
+        temperature_readings::Entity::find(),
+        temperature_readings::Column::ExperimentId,
+    )
+    .await?;
+    let probe_readings = counts(
+        db,
+        probe_temperature_readings::Entity::find().join(
+            JoinType::InnerJoin,
+            probe_temperature_readings::Relation::TemperatureReadings.def(),
+        ),
+        temperature_readings::Column::ExperimentId,
+    )
+    .await?;
+    let transitions = counts(
+        db,
+        phase_transitions::Entity::find(),
+        phase_transitions::Column::ExperimentId,
+    )
+    .await?;
+    let experiment_assets =
+        counts(db, assets::Entity::find(), assets::Column::ExperimentId).await?;
+
+    let names: Vec<(Uuid, String)> = experiments::Entity::find()
+        .select_only()
+        .column(experiments::Column::Id)
+        .column(experiments::Column::Name)
+        .into_tuple()
+        .all(db)
+        .await?;
+    let count = |counts: &HashMap<Uuid, i64>, id| counts.get(&id).copied().unwrap_or_default();
+    let mut rows: Vec<ExperimentRows> = names
+        .into_iter()
+        .map(|(id, name)| ExperimentRows {
+            experiment_id: id,
+            name,
+            temperature_readings: count(&readings, id),
+            probe_temperature_readings: count(&probe_readings, id),
+            phase_transitions: count(&transitions, id),
+            assets: count(&experiment_assets, id),
+        })
+        .collect();
+    rows.sort_by(|a, b| {
+        b.probe_temperature_readings
+            .cmp(&a.probe_temperature_readings)
+            .then_with(|| b.temperature_readings.cmp(&a.temperature_readings))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    Ok(rows)
+}
+
+/// Size and estimated bloat of each index, most bloated first
+pub async fn index_stats(db: &DatabaseConnection) -> Result<Vec<IndexStats>, DbErr> {
+    let backend = db.get_database_backend();
+    if backend != DbBackend::Postgres {
+        return Err(DbErr::Custom(
+            "Index statistics are only kept by PostgreSQL".to_string(),
+        ));
+    }
+    IndexStats::find_by_statement(Statement::from_string(backend, INDEX_STATS_SQL))
+        .all(db)
+        .await
+}
+
+/// Gather the statistics of every table, which the query planner and the
+/// estimates here rely on
+pub async fn analyze(db: &DatabaseConnection) -> Result<AnalyzeReport, DbErr> {
+    let started = Instant::now();
+    db.execute_unprepared("ANALYZE").await?;
+    Ok(AnalyzeReport {
+        duration_ms: u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX),
+    })
+}
+
+/// Build and keep the results summaries of the experiments without one, or
+/// of every experiment
+pub async fn recompute_summaries(
+    db: &DatabaseConnection,
+    query: RecomputeQuery,
+) -> Result<RecomputeReport, DbErr> {
+    let mut select = experiments::Entity::find()
+        .select_only()
+        .column(experiments::Column::Id);
+    if !query.all {
+        select = select.filter(
+            experiments::Column::Id.not_in_subquery(
+                Query::select()
+                    .column(results_summaries::Column::ExperimentId)
+                    .from(results_summaries::Entity)
+                    .to_owned(),
+            ),
+        );
+    }
+    let ids: Vec<Uuid> = select.into_tuple().all(db).await?;
+
+    let mut report = RecomputeReport::default();
+    for experiment_id in ids {
+        match recompute_results_summary(db, experiment_id, None).await {
+            Ok(_) => report.recomputed.push(experiment_id),
+            // The experiment exists, so it has no results to build
+            Err(DbErr::RecordNotFound(_)) => report.without_results.push(experiment_id),
+            Err(e) => report.failed.push(RecomputeFailure {
+                experiment_id,
+                error: e.to_string(),
+            }),
+        }
+    }
+    Ok(report)
+}
@@ -0,0 +1,167 @@
+use crate::config::test_helpers::{send_json, setup_test_app};
+use axum::http::StatusCode;
+use serde_json::{Value, json};
+
+#[tokio::test]
+async fn test_database_statistics() {
+    let app = setup_test_app().await;
+    let (status, experiment) = send_json(
+        &app,
+        "POST",
+        "/api/experiments",
+        Some(&json!({
+            "name": "Maintenance experiment",
+            "username": "test@example.com",
+            "performed_at": "2024-06-20T14:30:00Z",
+            "is_calibration": false
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{experiment}");
+    let id = experiment["id"].as_str().unwrap();
+
+    let (status, tables) = send_json(&app, "GET", "/api/maintenance/tables", None).await;
+    assert_eq!(status, StatusCode::OK, "{tables}");
+    let experiments = tables
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|table| table["table"] == "experiments")
+        .unwrap();
+    assert_eq!(experiments["rows"], 1);
+
+    let (status, rows) = send_json(&app, "GET", "/api/maintenance/experiments", None).await;
+    assert_eq!(status, StatusCode::OK, "{rows}");
+    assert_eq!(
+        rows,
+        json!([{
+            "experiment_id": id,
+            "name": "Maintenance experiment",
+            "temperature_readings": 0,
+            "probe_temperature_readings": 0,
+            "phase_transitions": 0,
+            "assets": 0
+        }])
+    );
+
+    // Index statistics are PostgreSQL's
+    let (status, _) = send_json(&app, "GET", "/api/maintenance/indexes", None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, report) = send_json(&app, "POST", "/api/maintenance/analyze", None).await;
+    assert_eq!(status, StatusCode::OK, "{report}");
+    assert!(report["duration_ms"].is_u64());
+}
+
+#[tokio::test]
+async fn test_summaries_are_recomputed() {
+    let app = setup_test_app().await;
+    let (status, experiment) = send_json(
+        &app,
+        "POST",
+        "/api/experiments",
+        Some(&json!({
+            "name": "Summarized experiment",
+            "username": "test@example.com",
+            "performed_at": "2024-06-20T14:30:00Z",
+            "is_calibration": false
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{experiment}");
+
+    let (status, report) =
+        send_json(&app, "POST", "/api/maintenance/summaries/recompute", None).await;
+    assert_eq!(status, StatusCode::OK, "{report}");
+    assert_eq!(report["recomputed"], json!([experiment["id"]]));
+    assert_eq!(report["failed"], json!([]));
+
+    // Only experiments without a summary, unless all are asked for
+    let (_, report) = send_json(&app, "POST", "/api/maintenance/summaries/recompute", None).await;
+    assert_eq!(report["recomputed"], json!([]));
+    let (_, report) = send_json(
+        &app,
+        "POST",
+        "/api/maintenance/summaries/recompute?all=true",
+        None,
+    )
+    .await;
+    assert_eq!(report["recomputed"], json!([experiment["id"]]));
+}
+
+#[tokio::test]
+async fn test_migrations_are_reverted_once_confirmed() {
+    let app = setup_test_app().await;
+    let latest = "m20251209_000001_create_probe_temperature_series";
+
+    let (status, migrations) = send_json(&app, "GET", "/api/maintenance/migrations", None).await;
+    assert_eq!(status, StatusCode::OK, "{migrations}");
+    let migrations = migrations.as_array().unwrap();
+    assert!(
+        migrations
+            .iter()
+            .all(|migration| migration["state"] == "applied")
+    );
+    assert_eq!(migrations.last().unwrap()["name"], latest);
+
+    // The earliest migration reverted must be named
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        "/api/maintenance/migrations/down",
+        Some(&json!({ "steps": 2, "confirm": latest })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        "/api/maintenance/migrations/down",
+        Some(&json!({ "steps": 1000, "confirm": latest })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, report) = send_json(
+        &app,
+        "POST",
+        "/api/maintenance/migrations/down",
+        Some(&json!({ "confirm": latest })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{report}");
+    assert_eq!(report["reverted"], json!([latest]));
+    let last = report["migrations"].as_array().unwrap().last().unwrap();
+    assert_eq!(last["name"], latest);
+    assert_eq!(last["state"], "pending");
+    assert_eq!(last["applied_at"], Value::Null);
+}
+
+#[tokio::test]
+async fn test_demo_is_seeded_once() {
+    let app = setup_test_app().await;
+
+    let (status, report) = send_json(&app, "POST", "/api/maintenance/demo", None).await;
+    assert_eq!(status, StatusCode::CREATED, "{report}");
+    assert_eq!(report["sample_ids"].as_array().unwrap().len(), 3);
+    assert_eq!(report["temperature_readings"], 151);
+    // Every well of the two trays freezes once
+    assert_eq!(report["phase_transitions"], 192);
+
+    let experiment_id = report["experiment_id"].as_str().unwrap();
+    let (status, experiment) = send_json(
+        &app,
+        "GET",
+        &format!("/api/experiments/{experiment_id}"),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{experiment}");
+    assert_eq!(experiment["project_id"], report["project_id"]);
+    assert_eq!(experiment["regions"].as_array().unwrap().len(), 4);
+    assert_eq!(experiment["results"]["summary"]["total_wells"], 192);
+    assert_eq!(experiment["results"]["summary"]["frozen_wells"], 192);
+
+    let (status, _) = send_json(&app, "POST", "/api/maintenance/demo", None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,249 @@
+use super::demo::seed_demo;
+use super::migrations::{migration_status, revert_migrations};
+use super::models::{
+    AnalyzeReport, DemoReport, ExperimentRows, IndexStats, MigrationDowngrade,
+    MigrationDowngradeReport, MigrationInfo, RecomputeQuery, RecomputeReport, TableStats,
+};
+use super::services::{analyze, experiment_rows, index_stats, recompute_summaries, table_stats};
+use crate::common::auth::{RouteAccess, require_role};
+use crate::common::keycloak::authenticate;
+use crate::common::state::AppState;
+use axum::{
+    Json,
+    extract::{Query, State},
+    http::StatusCode,
+    middleware,
+    routing::{get, post},
+};
+use axum_keycloak_auth::PassthroughMode;
+use sea_orm::DbErr;
+use utoipa::OpenApi;
+use utoipa_axum::router::OpenApiRouter;
+
+fn error_response(e: DbErr) -> (StatusCode, String) {
+    match e {
+        DbErr::Custom(message) => (StatusCode::BAD_REQUEST, message),
+        e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+/// Size of each table
+#[utoipa::path(
+    get,
+    path = "/tables",
+    responses(
+        (status = 200, description = "Tables, largest first", body = Vec<TableStats>),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "maintenance",
+    summary = "Table sizes",
+    description = "List each table with its rows, dead rows, and the bytes of its data and indexes, largest first. Rows are PostgreSQL's estimates, as of the last vacuum or `ANALYZE`"
+)]
+pub async fn get_table_stats(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<TableStats>>, (StatusCode, String)> {
+    table_stats(&state.db)
+        .await
+        .map(Json)
+        .map_err(error_response)
+}
+
+/// Rows of each experiment
+#[utoipa::path(
+    get,
+    path = "/experiments",
+    responses(
+        (status = 200, description = "Experiments, largest first", body = Vec<ExperimentRows>),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "maintenance",
+    summary = "Rows per experiment",
+    description = "Count the temperature readings, probe temperatures, phase transitions and assets of each experiment, the experiments with the most probe temperatures first"
+)]
+pub async fn get_experiment_rows(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<ExperimentRows>>, (StatusCode, String)> {
+    experiment_rows(&state.db)
+        .await
+        .map(Json)
+        .map_err(error_response)
+}
+
+/// Size and bloat of each index
+#[utoipa::path(
+    get,
+    path = "/indexes",
+    responses(
+        (status = 200, description = "Indexes, most bloated first", body = Vec<IndexStats>),
+        (status = 400, description = "The database is not PostgreSQL"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "maintenance",
+    summary = "Index sizes and bloat",
+    description = "List each B-tree index with its size, its scans and the bytes it takes beyond a freshly built index, estimated from the statistics of its table. Indexes with much bloat are worth a `REINDEX CONCURRENTLY`, and indexes never scanned only slow writes"
+)]
+pub async fn get_index_stats(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<IndexStats>>, (StatusCode, String)> {
+    index_stats(&state.db)
+        .await
+        .map(Json)
+        .map_err(error_response)
+}
+
+/// Gather table statistics
+#[utoipa::path(
+    post,
+    path = "/analyze",
+    responses(
+        (status = 200, description = "Statistics gathered", body = AnalyzeReport),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "maintenance",
+    summary = "Analyze the database",
+    description = "Run `ANALYZE` on every table, so that the query planner, and the row counts and bloat estimates reported here, follow large loads of data without waiting for autovacuum"
+)]
+pub async fn post_analyze(
+    State(state): State<AppState>,
+) -> Result<Json<AnalyzeReport>, (StatusCode, String)> {
+    analyze(&state.db).await.map(Json).map_err(error_response)
+}
+
+/// Recompute results summaries
+#[utoipa::path(
+    post,
+    path = "/summaries/recompute",
+    params(RecomputeQuery),
+    responses(
+        (status = 200, description = "Summaries recomputed", body = RecomputeReport),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "maintenance",
+    summary = "Recompute results summaries",
+    description = "Build and keep the results summary of each experiment without one, or with `all=true` of every experiment, one at a time. Experiments whose results cannot be built are listed with why, and do not stop the others"
+)]
+pub async fn post_recompute_summaries(
+    State(state): State<AppState>,
+    Query(query): Query<RecomputeQuery>,
+) -> Result<Json<RecomputeReport>, (StatusCode, String)> {
+    recompute_summaries(&state.db, query)
+        .await
+        .map(Json)
+        .map_err(error_response)
+}
+
+/// Applied and pending migrations
+#[utoipa::path(
+    get,
+    path = "/migrations",
+    responses(
+        (status = 200, description = "Migrations, oldest first", body = Vec<MigrationInfo>),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "maintenance",
+    summary = "Migration status",
+    description = "List the migrations of this version of the API, oldest first, each applied or pending, followed by those applied by a newer version"
+)]
+pub async fn get_migrations(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<MigrationInfo>>, (StatusCode, String)> {
+    migration_status(&state.db)
+        .await
+        .map(Json)
+        .map_err(error_response)
+}
+
+/// Revert the latest migrations
+#[utoipa::path(
+    post,
+    path = "/migrations/down",
+    request_body = MigrationDowngrade,
+    responses(
+        (status = 200, description = "Migrations reverted", body = MigrationDowngradeReport),
+        (status = 400, description = "Unconfirmed, or more steps than migrations applied"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "maintenance",
+    summary = "Revert migrations",
+    description = "Revert the latest `steps` applied migrations, dropping what they added, data included. `confirm` must name the earliest migration reverted. The API applies its pending migrations as it starts, so deploy the version to go back to before this one restarts"
+)]
+pub async fn post_migrations_down(
+    State(state): State<AppState>,
+    Json(downgrade): Json<MigrationDowngrade>,
+) -> Result<Json<MigrationDowngradeReport>, (StatusCode, String)> {
+    let reverted = revert_migrations(&state.db, downgrade)
+        .await
+        .map_err(error_response)?;
+    let migrations = migration_status(&state.db).await.map_err(error_response)?;
+    Ok(Json(MigrationDowngradeReport {
+        reverted,
+        migrations,
+    }))
+}
+
+/// Seed demo data
+#[utoipa::path(
+    post,
+    path = "/demo",
+    responses(
+        (status = 201, description = "Demo data seeded", body = DemoReport),
+        (status = 400, description = "The demo data has already been seeded"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "maintenance",
+    summary = "Seed demo data",
+    description = "Create a demo project with a location, samples and their treatments, a tray configuration and an experiment processed from a generated run, so that a new deployment has data to work with. The demo is seeded once; delete its project to seed it again"
+)]
+pub async fn post_demo(
+    State(state): State<AppState>,
+) -> Result<(StatusCode, Json<DemoReport>), (StatusCode, String)> {
+    seed_demo(&state.db)
+        .await
+        .map(|report| (StatusCode::CREATED, Json(report)))
+        .map_err(error_response)
+}
+
+/// Documentation of the routes, which are routed with `route`
+#[derive(OpenApi)]
+#[openapi(paths(
+    get_table_stats,
+    get_experiment_rows,
+    get_index_stats,
+    post_analyze,
+    post_recompute_summaries,
+    get_migrations,
+    post_migrations_down,
+    post_demo
+))]
+struct MaintenanceApi;
+
+pub fn router(state: &AppState) -> OpenApiRouter {
+    let mut router = OpenApiRouter::new()
+        .route("/tables", get(get_table_stats))
+        .route("/experiments", get(get_experiment_rows))
+        .route("/indexes", get(get_index_stats))
+        .route("/analyze", post(post_analyze))
+        .route("/summaries/recompute", post(post_recompute_summaries))
+        .route("/migrations", get(get_migrations))
+        .route("/migrations/down", post(post_migrations_down))
+        .route("/demo", post(post_demo))
+        .with_state(state.clone());
+    router.get_openapi_mut().merge(MaintenanceApi::openapi());
+
+    // Only signed-in administrators manage the database
+    if let Some(instance) = state.keycloak_auth_instance.clone() {
+        router = router
+            .layer(middleware::from_fn_with_state(
+                RouteAccess::ADMINISTRATION,
+                require_role,
+            ))
+            .layer(middleware::from_fn_with_state(
+                (instance, PassthroughMode::Block),
+                authenticate,
+            ));
+    } else if !state.config.tests_running {
+        println!("Warning: Maintenance routes are not protected");
+    }
+
+    router
+}
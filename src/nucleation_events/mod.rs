@@ -1,4 +1,6 @@
 pub mod models;
+pub mod services;
+pub mod views;
 
 #[cfg(test)]
-mod tests;
\ No newline at end of file
+mod tests;
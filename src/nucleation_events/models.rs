@@ -15,18 +15,29 @@ pub struct NucleationEvent {
     pub experiment_name: String,
     /// Date and time when the experiment was performed
     pub experiment_date: Option<DateTime<Utc>>,
+    /// Well the event occurred in
+    #[serde(default)]
+    pub well_id: Option<Uuid>,
     /// Well coordinate in standard format (e.g., "A1", "B2", "H12")
     pub well_coordinate: String,
     /// Name of the tray/plate (e.g., "P1", "P2")
     pub tray_name: Option<String>,
     /// Time from experiment start to nucleation in seconds
     pub nucleation_time_seconds: Option<i64>,
-    /// Average temperature across all temperature probes at nucleation event, in Celsius
+    /// Average temperature across all temperature probes at nucleation event, with each
+    /// probe's calibration applied, in Celsius
     pub nucleation_temperature_avg_celsius: Option<Decimal>,
     /// UI compatibility field - same as `nucleation_time_seconds`
     pub freezing_time_seconds: Option<i64>,
     /// UI compatibility field - same as `nucleation_temperature_avg_celsius`
     pub freezing_temperature_avg: Option<Decimal>,
+    /// Average of the probe readings at nucleation as logged, before calibration, in Celsius
+    #[serde(default)]
+    pub nucleation_temperature_raw_avg_celsius: Option<Decimal>,
+    /// Calibrated temperature at the well's position at nucleation, interpolated
+    /// from the probes of its tray, in Celsius
+    #[serde(default)]
+    pub nucleation_temperature_interpolated_celsius: Option<Decimal>,
     /// Dilution factor applied to the sample in this well
     pub dilution_factor: Option<i32>,
     /// Final state of the well: "frozen", "liquid", or "`no_data`"
@@ -35,9 +46,14 @@ pub struct NucleationEvent {
     pub treatment_id: Option<Uuid>,
     /// Name of the treatment applied to this sample
     pub treatment_name: Option<String>,
+    /// The well is excluded from the experiment's statistics; the event is
+    /// listed but not counted
+    #[serde(default)]
+    pub excluded: bool,
 }
 
-/// Summary statistics for nucleation events, used for sample and treatment analysis
+/// Summary statistics for nucleation events, used for sample and treatment analysis.
+/// Events of excluded wells are not counted.
 #[derive(ToSchema, Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
 pub struct NucleationStatistics {
     /// Total number of wells tested
@@ -66,6 +82,7 @@ pub struct DilutionSummary {
 impl NucleationStatistics {
     /// Calculate statistics from a collection of nucleation events
     pub fn from_events(events: &[NucleationEvent]) -> Option<Self> {
+        let events: Vec<&NucleationEvent> = events.iter().filter(|e| !e.excluded).collect();
         if events.is_empty() {
             return None;
         }
@@ -106,7 +123,7 @@ impl NucleationStatistics {
 
             if times.is_empty() {
                 None
-            } else if times.len() % 2 == 0 {
+            } else if times.len().is_multiple_of(2) {
                 let mid = times.len() / 2;
                 Some(i64::midpoint(times[mid - 1], times[mid]))
             } else {
@@ -133,7 +150,7 @@ impl NucleationStatistics {
         // Group events by dilution factor
         let mut dilution_groups: HashMap<i32, Vec<&NucleationEvent>> = HashMap::new();
 
-        for event in events {
+        for event in events.iter().filter(|e| !e.excluded) {
             let dilution = event.dilution_factor.unwrap_or(1);
             dilution_groups.entry(dilution).or_default().push(event);
         }
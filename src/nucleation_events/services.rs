@@ -0,0 +1,455 @@
+//! Nucleation events queried across experiments.
+//!
+//! Events are not stored: each is the freezing of a well, a phase transition
+//! from liquid to frozen, with the mean temperature of the probes at the
+//! reading it happened in and the treatment and dilution of the region the
+//! well is in. They follow the experiments' data as it is processed, so they
+//! are only read here; a well is left out of the statistics by excluding it.
+
+use super::models::{NucleationEvent, NucleationStatistics};
+use crate::changes::services::Reader;
+use crate::common::labs::TenantResource;
+use crate::experiments::exclusions::excluded_well_reasons;
+use crate::experiments::models as experiments;
+use crate::experiments::phase_transitions::models as phase_transitions;
+use crate::experiments::probe_temperature_readings::models::{
+    self as probe_temperature_readings, interpolated_temperature, mean_temperatures,
+};
+use crate::experiments::temperatures::models as temperature_readings;
+use crate::projects::access::ScopedResource;
+use crate::services::processing::structure::parse_well_coordinate;
+use crate::tray_configurations::{
+    probes::models as probes, regions::models as regions, trays::models as trays,
+    wells::models as wells,
+};
+use crate::treatments::models as treatments;
+use rust_decimal::Decimal;
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder,
+    QuerySelect, sea_query::Expr,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+const DEFAULT_LIMIT: u64 = 100;
+const MAX_LIMIT: u64 = 1000;
+/// Temperature readings looked up per query
+const READINGS_PER_QUERY: usize = 5000;
+
+#[derive(Clone, Debug, Default, Deserialize, IntoParams)]
+pub struct NucleationEventQuery {
+    pub experiment_id: Option<Uuid>,
+    pub well_id: Option<Uuid>,
+    /// Tray name, such as `P1`
+    pub tray_name: Option<String>,
+    /// Well coordinate, such as `A1`
+    pub well_coordinate: Option<String>,
+    /// Treatment of the region the well is in
+    pub treatment_id: Option<Uuid>,
+    /// Mean calibrated temperature at nucleation at or above, in °C
+    pub min_temperature: Option<Decimal>,
+    /// Mean calibrated temperature at nucleation at or below, in °C
+    pub max_temperature: Option<Decimal>,
+    /// Only the events of excluded wells, or only those counted
+    pub excluded: Option<bool>,
+    /// Most events to return, up to 1000 (default 100)
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct NucleationEventSearchResult {
+    /// Matching events before `limit` and `offset`
+    pub total: u64,
+    /// Statistics of all the matching events, excluded wells not counted
+    pub statistics: Option<NucleationStatistics>,
+    /// Oldest first
+    pub events: Vec<NucleationEvent>,
+}
+
+/// Experiment records an event refers to, loaded once per query
+#[derive(Default)]
+pub(crate) struct EventContext {
+    experiments: HashMap<Uuid, experiments::Model>,
+    started_at: HashMap<Uuid, chrono::DateTime<chrono::Utc>>,
+    regions: HashMap<Uuid, Vec<regions::Model>>,
+    treatments: HashMap<Uuid, treatments::Model>,
+    excluded_wells: HashSet<Uuid>,
+    trays: HashMap<Uuid, trays::Model>,
+    probes: HashMap<Uuid, probes::Model>,
+    probe_readings: HashMap<Uuid, Vec<probe_temperature_readings::Model>>,
+}
+
+/// Region of an experiment a well is in, by tray order and 0-based bounds
+fn region_of<'a>(
+    regions: &'a [regions::Model],
+    tray: &trays::Model,
+    well: &wells::Model,
+) -> Option<&'a regions::Model> {
+    let row = well
+        .row_letter
+        .chars()
+        .next()
+        .map_or(0, |c| c as i32 - 'A' as i32);
+    let column = well.column_number - 1;
+    regions.iter().find(|region| {
+        region.tray_id == Some(tray.order_sequence)
+            && matches!(
+                (region.row_min, region.row_max, region.col_min, region.col_max),
+                (Some(row_min), Some(row_max), Some(col_min), Some(col_max))
+                    if (row_min..=row_max).contains(&row)
+                        && (col_min..=col_max).contains(&column)
+            )
+    })
+}
+
+impl EventContext {
+    /// Region of the experiment the well is in
+    pub(crate) fn region(
+        &self,
+        experiment_id: Uuid,
+        well: &wells::Model,
+    ) -> Option<&regions::Model> {
+        let tray = self.trays.get(&well.tray_id)?;
+        region_of(
+            self.regions.get(&experiment_id).map_or(&[], Vec::as_slice),
+            tray,
+            well,
+        )
+    }
+
+    pub(crate) fn treatment(&self, treatment_id: Uuid) -> Option<&treatments::Model> {
+        self.treatments.get(&treatment_id)
+    }
+}
+
+/// Experiments the user may read, of those given
+pub(crate) async fn readable_experiments(
+    db: &DatabaseConnection,
+    reader: &Reader,
+    experiment_ids: HashSet<Uuid>,
+) -> HashSet<Uuid> {
+    let mut readable = HashSet::new();
+    for experiment_id in experiment_ids {
+        if reader
+            .may_read(
+                db,
+                (
+                    Some(ScopedResource::Experiments),
+                    TenantResource::Experiments,
+                ),
+                experiment_id,
+            )
+            .await
+        {
+            readable.insert(experiment_id);
+        }
+    }
+    readable
+}
+
+/// Probe readings at the events, and their probes
+async fn load_probe_readings(
+    db: &impl ConnectionTrait,
+    transitions: &[(phase_transitions::Model, wells::Model)],
+    context: &mut EventContext,
+) -> Result<(), DbErr> {
+    let reading_ids: Vec<Uuid> = transitions
+        .iter()
+        .map(|(transition, _)| transition.temperature_reading_id)
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    for chunk in reading_ids.chunks(READINGS_PER_QUERY) {
+        for reading in probe_temperature_readings::Entity::find()
+            .filter(
+                probe_temperature_readings::Column::TemperatureReadingId
+                    .is_in(chunk.iter().copied()),
+            )
+            .all(db)
+            .await?
+        {
+            context
+                .probe_readings
+                .entry(reading.temperature_reading_id)
+                .or_default()
+                .push(reading);
+        }
+    }
+    let probe_ids: HashSet<Uuid> = context
+        .probe_readings
+        .values()
+        .flatten()
+        .map(|reading| reading.probe_id)
+        .collect();
+    context.probes = probes::Entity::find()
+        .filter(probes::Column::Id.is_in(probe_ids))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|probe| (probe.id, probe))
+        .collect();
+    Ok(())
+}
+
+/// Records of the given experiments and trays, and the probe readings at
+/// the given transitions
+pub(crate) async fn load_context(
+    db: &impl ConnectionTrait,
+    experiment_ids: &HashSet<Uuid>,
+    tray_ids: HashSet<Uuid>,
+    transitions: &[(phase_transitions::Model, wells::Model)],
+) -> Result<EventContext, DbErr> {
+    let mut context = EventContext {
+        experiments: experiments::Entity::find()
+            .filter(experiments::Column::Id.is_in(experiment_ids.iter().copied()))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|experiment| (experiment.id, experiment))
+            .collect(),
+        trays: trays::Entity::find()
+            .filter(trays::Column::Id.is_in(tray_ids))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|tray| (tray.id, tray))
+            .collect(),
+        ..EventContext::default()
+    };
+
+    let started_at: Vec<(Uuid, Option<chrono::DateTime<chrono::Utc>>)> =
+        temperature_readings::Entity::find()
+            .select_only()
+            .column(temperature_readings::Column::ExperimentId)
+            .column_as(
+                Expr::col(temperature_readings::Column::Timestamp).min(),
+                "started_at",
+            )
+            .filter(
+                temperature_readings::Column::ExperimentId.is_in(experiment_ids.iter().copied()),
+            )
+            .group_by(temperature_readings::Column::ExperimentId)
+            .into_tuple()
+            .all(db)
+            .await?;
+    context.started_at = started_at
+        .into_iter()
+        .filter_map(|(experiment_id, started_at)| Some((experiment_id, started_at?)))
+        .collect();
+
+    for region in regions::Entity::find()
+        .filter(regions::Column::ExperimentId.is_in(experiment_ids.iter().copied()))
+        .all(db)
+        .await?
+    {
+        context
+            .regions
+            .entry(region.experiment_id)
+            .or_default()
+            .push(region);
+    }
+    let treatment_ids: HashSet<Uuid> = context
+        .regions
+        .values()
+        .flatten()
+        .filter_map(|region| region.treatment_id)
+        .collect();
+    context.treatments = treatments::Entity::find()
+        .filter(treatments::Column::Id.is_in(treatment_ids))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|treatment| (treatment.id, treatment))
+        .collect();
+    for experiment_id in experiment_ids {
+        context
+            .excluded_wells
+            .extend(excluded_well_reasons(db, *experiment_id).await?.into_keys());
+    }
+
+    load_probe_readings(db, transitions, &mut context).await?;
+    Ok(context)
+}
+
+/// Event of a well in an experiment, frozen at the given transition or else
+/// liquid throughout
+pub(crate) fn build_event(
+    context: &EventContext,
+    experiment_id: Uuid,
+    well: &wells::Model,
+    transition: Option<&phase_transitions::Model>,
+) -> Option<NucleationEvent> {
+    let experiment = context.experiments.get(&experiment_id)?;
+    let tray = context.trays.get(&well.tray_id);
+    let region = context.region(experiment_id, well);
+    let treatment = region
+        .and_then(|region| region.treatment_id)
+        .and_then(|id| context.treatment(id));
+
+    let readings: Vec<&probe_temperature_readings::Model> = transition
+        .and_then(|transition| {
+            context
+                .probe_readings
+                .get(&transition.temperature_reading_id)
+        })
+        .map(|readings| readings.iter().collect())
+        .unwrap_or_default();
+    let temperatures = mean_temperatures(&readings, &context.probes);
+    let temperature_avg = temperatures.map(|(calibrated, _)| calibrated);
+    let nucleation_time_seconds = transition.and_then(|transition| {
+        context
+            .started_at
+            .get(&experiment.id)
+            .map(|started_at| (transition.timestamp - *started_at).num_seconds())
+    });
+
+    Some(NucleationEvent {
+        experiment_id: experiment.id,
+        experiment_name: experiment.name.clone(),
+        experiment_date: experiment.performed_at,
+        well_id: Some(well.id),
+        well_coordinate: format!("{}{}", well.row_letter, well.column_number),
+        tray_name: tray.and_then(|tray| tray.name.clone()),
+        nucleation_time_seconds,
+        nucleation_temperature_avg_celsius: temperature_avg,
+        freezing_time_seconds: nucleation_time_seconds,
+        freezing_temperature_avg: temperature_avg,
+        nucleation_temperature_raw_avg_celsius: temperatures.map(|(_, raw)| raw),
+        nucleation_temperature_interpolated_celsius: interpolated_temperature(
+            &readings,
+            &context.probes,
+            well,
+        ),
+        dilution_factor: region.and_then(|region| region.dilution_factor),
+        final_state: if transition.is_some() {
+            "frozen"
+        } else {
+            "liquid"
+        }
+        .to_string(),
+        treatment_id: treatment.map(|treatment| treatment.id),
+        treatment_name: treatment.map(|treatment| format!("{:?}", treatment.name)),
+        excluded: context.excluded_wells.contains(&well.id),
+    })
+}
+
+/// Whether an event matches the filters that are only known once it is
+/// built
+fn matches(query: &NucleationEventQuery, event: &NucleationEvent) -> bool {
+    let temperature = event.nucleation_temperature_avg_celsius;
+    query
+        .tray_name
+        .as_ref()
+        .is_none_or(|name| event.tray_name.as_ref() == Some(name))
+        && query
+            .treatment_id
+            .is_none_or(|id| event.treatment_id == Some(id))
+        && query
+            .min_temperature
+            .is_none_or(|min| temperature.is_some_and(|t| t >= min))
+        && query
+            .max_temperature
+            .is_none_or(|max| temperature.is_some_and(|t| t <= max))
+        && query
+            .excluded
+            .is_none_or(|excluded| event.excluded == excluded)
+}
+
+/// Nucleation events matching every given filter, that the user may read.
+/// Invalid filters are returned as `DbErr::Custom`.
+pub async fn search_nucleation_events(
+    db: &DatabaseConnection,
+    reader: &Reader,
+    query: &NucleationEventQuery,
+) -> Result<NucleationEventSearchResult, DbErr> {
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return Err(DbErr::Custom(format!(
+            "limit must be between 1 and {MAX_LIMIT}"
+        )));
+    }
+    if let (Some(min), Some(max)) = (query.min_temperature, query.max_temperature)
+        && min > max
+    {
+        return Err(DbErr::Custom(
+            "min_temperature must not be above max_temperature".to_string(),
+        ));
+    }
+
+    let mut select = phase_transitions::Entity::find()
+        .filter(phase_transitions::Column::PreviousState.eq(0))
+        .filter(phase_transitions::Column::NewState.eq(1))
+        .inner_join(wells::Entity)
+        .select_also(wells::Entity);
+    if let Some(experiment_id) = query.experiment_id {
+        select = select.filter(phase_transitions::Column::ExperimentId.eq(experiment_id));
+    }
+    if let Some(well_id) = query.well_id {
+        select = select.filter(phase_transitions::Column::WellId.eq(well_id));
+    }
+    if let Some(coordinate) = &query.well_coordinate {
+        let (row_letter, column_number) = parse_well_coordinate(&coordinate.to_uppercase())
+            .map_err(|e| DbErr::Custom(e.to_string()))?;
+        select = select
+            .filter(wells::Column::RowLetter.eq(row_letter))
+            .filter(wells::Column::ColumnNumber.eq(column_number));
+    }
+    if let Some(treatment_id) = query.treatment_id {
+        let experiment_ids: Vec<Uuid> = regions::Entity::find()
+            .select_only()
+            .column(regions::Column::ExperimentId)
+            .filter(regions::Column::TreatmentId.eq(treatment_id))
+            .into_tuple()
+            .all(db)
+            .await?;
+        select = select.filter(phase_transitions::Column::ExperimentId.is_in(experiment_ids));
+    }
+    let transitions: Vec<(phase_transitions::Model, wells::Model)> = select
+        .order_by_asc(phase_transitions::Column::Timestamp)
+        .order_by_asc(wells::Column::RowLetter)
+        .order_by_asc(wells::Column::ColumnNumber)
+        .all(db)
+        .await?
+        .into_iter()
+        .filter_map(|(transition, well)| Some((transition, well?)))
+        .collect();
+
+    let readable = readable_experiments(
+        db,
+        reader,
+        transitions
+            .iter()
+            .map(|(transition, _)| transition.experiment_id)
+            .collect(),
+    )
+    .await;
+    let transitions: Vec<_> = transitions
+        .into_iter()
+        .filter(|(transition, _)| readable.contains(&transition.experiment_id))
+        .collect();
+    let context = load_context(
+        db,
+        &readable,
+        transitions.iter().map(|(_, well)| well.tray_id).collect(),
+        &transitions,
+    )
+    .await?;
+    let events: Vec<NucleationEvent> = transitions
+        .iter()
+        .filter_map(|(transition, well)| {
+            build_event(&context, transition.experiment_id, well, Some(transition))
+        })
+        .filter(|event| matches(query, event))
+        .collect();
+
+    let offset = usize::try_from(query.offset.unwrap_or(0)).unwrap_or(usize::MAX);
+    let limit = usize::try_from(limit).unwrap_or(usize::MAX);
+    Ok(NucleationEventSearchResult {
+        total: events.len() as u64,
+        statistics: NucleationStatistics::from_events(&events),
+        events: events.into_iter().skip(offset).take(limit).collect(),
+    })
+}
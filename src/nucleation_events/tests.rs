@@ -1,5 +1,8 @@
 use super::models::{NucleationEvent, NucleationStatistics};
+use crate::config::test_helpers::{send_json, setup_test_app};
+use axum::http::StatusCode;
 use rust_decimal::Decimal;
+use serde_json::Value;
 use uuid::Uuid;
 
 #[test]
@@ -9,46 +12,58 @@ fn test_nucleation_statistics_calculation() {
             experiment_id: Uuid::new_v4(),
             experiment_name: "Test".to_string(),
             experiment_date: None,
+            well_id: None,
             well_coordinate: "A1".to_string(),
             tray_name: Some("P1".to_string()),
             nucleation_time_seconds: Some(1000),
             nucleation_temperature_avg_celsius: Some(Decimal::new(-150, 1)), // -15.0
             freezing_time_seconds: Some(1000),                               // UI compatibility
             freezing_temperature_avg: Some(Decimal::new(-150, 1)),           // UI compatibility
+            nucleation_temperature_raw_avg_celsius: None,
+            nucleation_temperature_interpolated_celsius: None,
             dilution_factor: Some(100),
             final_state: "frozen".to_string(),
             treatment_id: None,
             treatment_name: None,
+            excluded: false,
         },
         NucleationEvent {
             experiment_id: Uuid::new_v4(),
             experiment_name: "Test".to_string(),
             experiment_date: None,
+            well_id: None,
             well_coordinate: "A2".to_string(),
             tray_name: Some("P1".to_string()),
             nucleation_time_seconds: Some(2000),
             nucleation_temperature_avg_celsius: Some(Decimal::new(-180, 1)), // -18.0
             freezing_time_seconds: Some(2000),                               // UI compatibility
             freezing_temperature_avg: Some(Decimal::new(-180, 1)),           // UI compatibility
+            nucleation_temperature_raw_avg_celsius: None,
+            nucleation_temperature_interpolated_celsius: None,
             dilution_factor: Some(100),
             final_state: "frozen".to_string(),
             treatment_id: None,
             treatment_name: None,
+            excluded: false,
         },
         NucleationEvent {
             experiment_id: Uuid::new_v4(),
             experiment_name: "Test".to_string(),
             experiment_date: None,
+            well_id: None,
             well_coordinate: "A3".to_string(),
             tray_name: Some("P1".to_string()),
             nucleation_time_seconds: None,
             nucleation_temperature_avg_celsius: None,
             freezing_time_seconds: None,    // UI compatibility
             freezing_temperature_avg: None, // UI compatibility
+            nucleation_temperature_raw_avg_celsius: None,
+            nucleation_temperature_interpolated_celsius: None,
             dilution_factor: Some(100),
             final_state: "liquid".to_string(),
             treatment_id: None,
             treatment_name: None,
+            excluded: false,
         },
     ];
 
@@ -70,4 +85,161 @@ fn test_nucleation_statistics_empty() {
 
     // Empty events should return None
     assert!(stats.is_none(), "Empty events should return None");
-}
\ No newline at end of file
+}
+#[test]
+fn test_nucleation_statistics_skip_excluded_wells() {
+    let event = |well_coordinate: &str, dilution_factor: i32, excluded: bool| NucleationEvent {
+        experiment_id: Uuid::nil(),
+        experiment_name: "Test".to_string(),
+        experiment_date: None,
+        well_id: None,
+        well_coordinate: well_coordinate.to_string(),
+        tray_name: Some("P1".to_string()),
+        nucleation_time_seconds: Some(1000),
+        nucleation_temperature_avg_celsius: Some(Decimal::new(-150, 1)),
+        freezing_time_seconds: Some(1000),
+        freezing_temperature_avg: Some(Decimal::new(-150, 1)),
+        nucleation_temperature_raw_avg_celsius: None,
+        nucleation_temperature_interpolated_celsius: None,
+        dilution_factor: Some(dilution_factor),
+        final_state: "frozen".to_string(),
+        treatment_id: None,
+        treatment_name: None,
+        excluded,
+    };
+    let events = vec![
+        event("A1", 1, false),
+        event("A2", 1, true),
+        event("B1", 10, true),
+    ];
+
+    let stats = NucleationStatistics::from_events(&events).unwrap();
+    assert_eq!(stats.total_wells, 1);
+    assert_eq!(stats.frozen_count, 1);
+
+    // A dilution with only excluded wells has no summary
+    let summaries = NucleationStatistics::dilution_summaries_from_events(&events);
+    assert_eq!(summaries.len(), 1);
+    assert_eq!(summaries[0].dilution_factor, 1);
+    assert_eq!(summaries[0].statistics.total_wells, 1);
+
+    assert!(NucleationStatistics::from_events(&events[1..]).is_none());
+}
+
+#[tokio::test]
+async fn test_nucleation_events_are_filtered() {
+    let app = setup_test_app().await;
+    let (status, demo) = send_json(&app, "POST", "/api/maintenance/demo", None).await;
+    assert_eq!(status, StatusCode::CREATED, "{demo}");
+    let experiment_id = demo["experiment_id"].as_str().unwrap();
+
+    let uri = format!("/api/nucleation_events?experiment_id={experiment_id}&limit=1000");
+    let (status, all) = send_json(&app, "GET", &uri, None).await;
+    assert_eq!(status, StatusCode::OK, "{all}");
+    assert_eq!(all["total"], 192);
+    assert_eq!(all["statistics"]["frozen_count"], 192);
+    let events = all["events"].as_array().unwrap();
+    assert_eq!(events.len(), 192);
+    assert!(events.iter().all(|event| event["final_state"] == "frozen"));
+
+    // A well, by coordinate or by ID
+    let (status, well) = send_json(
+        &app,
+        "GET",
+        &format!(
+            "/api/nucleation_events?experiment_id={experiment_id}&tray_name=P2&well_coordinate=h12"
+        ),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{well}");
+    assert_eq!(well["total"], 1);
+    let event = &well["events"][0];
+    assert_eq!(event["well_coordinate"], "H12");
+    assert_eq!(event["tray_name"], "P2");
+    let well_id = event["well_id"].as_str().unwrap();
+    let (_, by_id) = send_json(
+        &app,
+        "GET",
+        &format!("/api/nucleation_events?well_id={well_id}"),
+        None,
+    )
+    .await;
+    assert_eq!(by_id["events"], well["events"]);
+
+    // The heat treated half of the first tray
+    let (_, experiment) = send_json(
+        &app,
+        "GET",
+        &format!("/api/experiments/{experiment_id}"),
+        None,
+    )
+    .await;
+    let heat = experiment["regions"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|region| region["name"] == "Heat treated")
+        .unwrap();
+    let treatment_id = heat["treatment_id"].as_str().unwrap();
+    let (_, treated) = send_json(
+        &app,
+        "GET",
+        &format!("/api/nucleation_events?treatment_id={treatment_id}&limit=1000"),
+        None,
+    )
+    .await;
+    assert_eq!(treated["total"], 48);
+    assert!(
+        treated["events"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .all(|event| event["tray_name"] == "P1" && event["treatment_id"] == treatment_id)
+    );
+
+    let (_, warm) = send_json(
+        &app,
+        "GET",
+        "/api/nucleation_events?min_temperature=-10&max_temperature=-8&limit=1000",
+        None,
+    )
+    .await;
+    let warm = warm["events"].as_array().unwrap();
+    assert!(!warm.is_empty() && warm.len() < 192);
+    for event in warm {
+        let temperature: f64 = event["nucleation_temperature_avg_celsius"]
+            .as_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!((-10.0..=-8.0).contains(&temperature), "{event}");
+    }
+
+    let (_, page) = send_json(&app, "GET", &format!("{uri}&offset=190"), None).await;
+    assert_eq!(page["total"], 192);
+    assert_eq!(page["events"].as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_nucleation_event_filters_are_checked() {
+    let app = setup_test_app().await;
+    for query in [
+        "min_temperature=-5&max_temperature=-10",
+        "limit=0",
+        "well_coordinate=12",
+    ] {
+        let (status, _) = send_json(
+            &app,
+            "GET",
+            &format!("/api/nucleation_events?{query}"),
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{query}");
+    }
+    let (status, none) = send_json(&app, "GET", "/api/nucleation_events", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(none["total"], 0);
+    assert_eq!(none["statistics"], Value::Null);
+}
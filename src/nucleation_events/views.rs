@@ -0,0 +1,75 @@
+use super::services::{
+    NucleationEventQuery, NucleationEventSearchResult, search_nucleation_events,
+};
+use crate::api_keys::services::accept_api_keys;
+use crate::changes::views::reader;
+use crate::common::auth::{Role, RouteAccess, require_role};
+use crate::common::keycloak::authenticate;
+use crate::common::labs::Labs;
+use crate::common::redaction::redact_for_viewers;
+use crate::common::state::AppState;
+use axum::{
+    Extension, Json,
+    extract::{Query, State},
+    http::StatusCode,
+    middleware,
+};
+use axum_keycloak_auth::{PassthroughMode, decode::KeycloakToken};
+use sea_orm::DbErr;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+/// Query nucleation events
+#[utoipa::path(
+    get,
+    path = "",
+    params(NucleationEventQuery),
+    responses(
+        (status = 200, description = "Matching nucleation events, oldest first", body = NucleationEventSearchResult),
+        (status = 400, description = "Invalid filter"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "nucleation_events",
+    summary = "Query nucleation events",
+    description = "List the freezing of wells across experiments, with the temperature at nucleation and the treatment of the well's region, filtered by experiment, well, tray, treatment and temperature range. Events are derived from the processed phase transitions, so they are read-only; exclude a well to leave it out of the statistics. Users who are not administrators get only the events of experiments they may read"
+)]
+pub async fn get_nucleation_events(
+    State(state): State<AppState>,
+    token: Option<Extension<KeycloakToken<Role>>>,
+    labs: Option<Extension<Labs>>,
+    Query(query): Query<NucleationEventQuery>,
+) -> Result<Json<NucleationEventSearchResult>, (StatusCode, String)> {
+    search_nucleation_events(&state.db, &reader(token, labs), &query)
+        .await
+        .map(Json)
+        .map_err(|e| match e {
+            DbErr::Custom(message) => (StatusCode::BAD_REQUEST, message),
+            e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })
+}
+
+pub fn router(state: &AppState) -> OpenApiRouter {
+    let mut router = OpenApiRouter::new()
+        .routes(routes!(get_nucleation_events))
+        .with_state(state.clone());
+
+    if let Some(instance) = state.keycloak_auth_instance.clone() {
+        router = router
+            .layer(middleware::from_fn_with_state(
+                RouteAccess::PROJECT_RECORDS,
+                require_role,
+            ))
+            .layer(middleware::from_fn(redact_for_viewers))
+            .layer(middleware::from_fn_with_state(
+                (state.db.clone(), "nucleation_events"),
+                accept_api_keys,
+            ))
+            .layer(middleware::from_fn_with_state(
+                (instance, PassthroughMode::Pass),
+                authenticate,
+            ));
+    } else if !state.config.tests_running {
+        println!("Warning: Nucleation event routes are not protected");
+    }
+
+    router
+}
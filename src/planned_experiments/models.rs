@@ -0,0 +1,176 @@
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Deserializer, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Where a plan stands
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, ToSchema, Serialize, Deserialize,
+)]
+#[sea_orm(rs_type = "String", db_type = "Text")]
+#[serde(rename_all = "snake_case")]
+pub enum PlannedExperimentStatus {
+    /// Waiting for its day
+    #[sea_orm(string_value = "planned")]
+    Planned,
+    /// Its experiment has been created
+    #[sea_orm(string_value = "started")]
+    Started,
+    #[sea_orm(string_value = "cancelled")]
+    Cancelled,
+}
+
+/// An experiment planned for a day, with who runs it and what on
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "planned_experiments")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    #[sea_orm(column_type = "Text")]
+    pub name: String,
+    pub scheduled_for: DateTime<Utc>,
+    /// Username of who is to run the experiment
+    #[sea_orm(column_type = "Text", nullable)]
+    pub operator: Option<String>,
+    pub project_id: Option<Uuid>,
+    pub tray_configuration_id: Option<Uuid>,
+    /// Samples to be run
+    #[sea_orm(column_type = "JsonBinary")]
+    pub sample_ids: Json,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub notes: Option<String>,
+    pub status: PlannedExperimentStatus,
+    /// Experiment the plan was started as
+    pub experiment_id: Option<Uuid>,
+    /// Lab the plan belongs to, when labs are on
+    #[sea_orm(column_type = "Text", nullable)]
+    pub lab: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub created_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_updated: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "crate::projects::models::Entity",
+        from = "Column::ProjectId",
+        to = "crate::projects::models::Column::Id",
+        on_update = "NoAction",
+        on_delete = "SetNull"
+    )]
+    Projects,
+    #[sea_orm(
+        belongs_to = "crate::tray_configurations::models::Entity",
+        from = "Column::TrayConfigurationId",
+        to = "crate::tray_configurations::models::Column::Id",
+        on_update = "NoAction",
+        on_delete = "SetNull"
+    )]
+    TrayConfigurations,
+    #[sea_orm(
+        belongs_to = "crate::experiments::models::Entity",
+        from = "Column::ExperimentId",
+        to = "crate::experiments::models::Column::Id",
+        on_update = "NoAction",
+        on_delete = "SetNull"
+    )]
+    Experiments,
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    pub fn sample_id_list(&self) -> Vec<Uuid> {
+        serde_json::from_value(self.sample_ids.clone()).unwrap_or_default()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, ToSchema)]
+pub struct PlannedExperiment {
+    pub id: Uuid,
+    pub name: String,
+    pub scheduled_for: DateTime<Utc>,
+    pub operator: Option<String>,
+    pub project_id: Option<Uuid>,
+    pub tray_configuration_id: Option<Uuid>,
+    pub sample_ids: Vec<Uuid>,
+    pub notes: Option<String>,
+    pub status: PlannedExperimentStatus,
+    pub experiment_id: Option<Uuid>,
+    pub lab: Option<String>,
+    pub created_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_updated: DateTime<Utc>,
+}
+
+impl From<Model> for PlannedExperiment {
+    fn from(model: Model) -> Self {
+        Self {
+            sample_ids: model.sample_id_list(),
+            id: model.id,
+            name: model.name,
+            scheduled_for: model.scheduled_for,
+            operator: model.operator,
+            project_id: model.project_id,
+            tray_configuration_id: model.tray_configuration_id,
+            notes: model.notes,
+            status: model.status,
+            experiment_id: model.experiment_id,
+            lab: model.lab,
+            created_by: model.created_by,
+            created_at: model.created_at,
+            last_updated: model.last_updated,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, ToSchema)]
+pub struct PlannedExperimentCreate {
+    /// Name the experiment is created with when the plan is started
+    pub name: String,
+    pub scheduled_for: DateTime<Utc>,
+    /// Username of who is to run the experiment
+    pub operator: Option<String>,
+    pub project_id: Option<Uuid>,
+    pub tray_configuration_id: Option<Uuid>,
+    #[serde(default)]
+    pub sample_ids: Vec<Uuid>,
+    pub notes: Option<String>,
+}
+
+/// A field given in an update, possibly as null to clear it
+#[allow(clippy::option_option)]
+fn given<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::deserialize(deserializer).map(Some)
+}
+
+/// Changes to a plan; fields left out are kept, and null clears them
+#[allow(clippy::option_option)]
+#[derive(Clone, Debug, Default, Deserialize, ToSchema)]
+pub struct PlannedExperimentUpdate {
+    pub name: Option<String>,
+    pub scheduled_for: Option<DateTime<Utc>>,
+    #[serde(default, deserialize_with = "given")]
+    #[schema(value_type = Option<String>)]
+    pub operator: Option<Option<String>>,
+    #[serde(default, deserialize_with = "given")]
+    #[schema(value_type = Option<Uuid>)]
+    pub project_id: Option<Option<Uuid>>,
+    #[serde(default, deserialize_with = "given")]
+    #[schema(value_type = Option<Uuid>)]
+    pub tray_configuration_id: Option<Option<Uuid>>,
+    pub sample_ids: Option<Vec<Uuid>>,
+    #[serde(default, deserialize_with = "given")]
+    #[schema(value_type = Option<String>)]
+    pub notes: Option<Option<String>>,
+    /// `cancelled` to call the plan off, or `planned` to take it up again;
+    /// plans are started with the start route
+    pub status: Option<PlannedExperimentStatus>,
+}
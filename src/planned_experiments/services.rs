@@ -0,0 +1,470 @@
+//! Experiments planned ahead, kept beside those run.
+//!
+//! A plan names the day an experiment is to be run, who runs it, and the
+//! project, tray configuration and samples it is for. Starting a plan
+//! creates its experiment, after which the plan is kept as it was. The
+//! calendar gives the plans and the experiments performed in a time range
+//! together, so the lab's schedule and what came of it are read in one
+//! place. Users who are not administrators see and change the plans of
+//! the projects they are members of and those they created.
+
+use super::models::{
+    ActiveModel, Column, Entity, Model, PlannedExperiment, PlannedExperimentCreate,
+    PlannedExperimentStatus, PlannedExperimentUpdate,
+};
+use crate::changes::services::Reader;
+use crate::common::labs::{self, TenantResource};
+use crate::experiments::models::{self as experiments, Experiment};
+use crate::nucleation_events::services::readable_experiments;
+use crate::projects::access::member_projects;
+use crate::projects::models as projects;
+use crate::samples::models as samples;
+use crate::tray_configurations::models as tray_configurations;
+use chrono::{DateTime, Duration, Utc};
+use crudcrate::CRUDResource;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, Condition, ConnectionTrait,
+    DatabaseConnection, DbErr, EntityTrait, IntoActiveModel, PaginatorTrait, QueryFilter,
+    QueryOrder, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+/// Longest time range the calendar covers
+const MAX_CALENDAR_DAYS: i64 = 366;
+
+#[derive(Clone, Debug, Default, Deserialize, IntoParams)]
+pub struct PlannedExperimentQuery {
+    pub status: Option<PlannedExperimentStatus>,
+    /// Plans of this operator
+    pub operator: Option<String>,
+    /// Plans scheduled at or after this time
+    pub from: Option<DateTime<Utc>>,
+    /// Plans scheduled before this time
+    pub to: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Debug, Deserialize, IntoParams)]
+pub struct CalendarQuery {
+    /// Start of the range
+    pub from: DateTime<Utc>,
+    /// End of the range, at most 366 days after its start
+    pub to: DateTime<Utc>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CalendarEntryKind {
+    /// A plan not yet performed
+    Planned,
+    /// An experiment performed in the range
+    Performed,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, ToSchema)]
+pub struct CalendarEntry {
+    pub kind: CalendarEntryKind,
+    /// When the plan is scheduled for, or the experiment was performed
+    pub date: DateTime<Utc>,
+    pub name: String,
+    /// The plan, or the plan the experiment was started from
+    pub planned_experiment_id: Option<Uuid>,
+    pub experiment_id: Option<Uuid>,
+    /// Who is to run the plan, or ran the experiment
+    pub operator: Option<String>,
+    pub project_id: Option<Uuid>,
+    /// Status of the plan, if any
+    pub status: Option<PlannedExperimentStatus>,
+}
+
+fn not_found() -> DbErr {
+    DbErr::RecordNotFound("Planned experiment not found".to_string())
+}
+
+async fn find_plan(db: &impl ConnectionTrait, id: Uuid) -> Result<Model, DbErr> {
+    Entity::find_by_id(id).one(db).await?.ok_or_else(not_found)
+}
+
+/// Plans the reader may see: those of their labs and of no lab and, when
+/// they are not an administrator, those of the projects they are members
+/// of and those they created
+async fn visible_plans(db: &DatabaseConnection, reader: &Reader) -> Result<Condition, DbErr> {
+    let mut condition = Condition::all();
+    if let Some(labs) = &reader.labs {
+        condition = condition.add(labs::lab_scope(TenantResource::PlannedExperiments, labs));
+    }
+    if let Some((username, _)) = &reader.member {
+        condition = condition.add(
+            Condition::any()
+                .add(Column::ProjectId.is_in(member_projects(db, username).await?))
+                .add(Column::CreatedBy.eq(username.as_str())),
+        );
+    }
+    Ok(condition)
+}
+
+/// Whether the reader may see and change a plan of their labs: one of a
+/// project they are a member of, or that they created
+pub async fn may_reach(
+    db: &DatabaseConnection,
+    reader: &Reader,
+    plan: &PlannedExperiment,
+) -> Result<bool, DbErr> {
+    let Some((username, _)) = &reader.member else {
+        return Ok(true);
+    };
+    if plan.created_by.as_ref() == Some(username) {
+        return Ok(true);
+    }
+    may_plan_for(db, reader, plan.project_id).await
+}
+
+/// Whether the reader may plan for a project: one they are a member of,
+/// or none
+pub async fn may_plan_for(
+    db: &DatabaseConnection,
+    reader: &Reader,
+    project_id: Option<Uuid>,
+) -> Result<bool, DbErr> {
+    match (&reader.member, project_id) {
+        (Some((username, _)), Some(project_id)) => {
+            Ok(member_projects(db, username).await?.contains(&project_id))
+        }
+        (Some(_), None) | (None, _) => Ok(true),
+    }
+}
+
+/// Check that the records a plan names exist
+async fn check_references(
+    db: &DatabaseConnection,
+    project_id: Option<Uuid>,
+    tray_configuration_id: Option<Uuid>,
+    sample_ids: &[Uuid],
+) -> Result<(), DbErr> {
+    if let Some(project_id) = project_id
+        && projects::Entity::find_by_id(project_id)
+            .one(db)
+            .await?
+            .is_none()
+    {
+        return Err(DbErr::Custom(format!("Project {project_id} not found")));
+    }
+    if let Some(tray_configuration_id) = tray_configuration_id
+        && tray_configurations::Entity::find_by_id(tray_configuration_id)
+            .one(db)
+            .await?
+            .is_none()
+    {
+        return Err(DbErr::Custom(format!(
+            "Tray configuration {tray_configuration_id} not found"
+        )));
+    }
+    let found: HashSet<Uuid> = samples::Entity::find()
+        .filter(samples::Column::Id.is_in(sample_ids.iter().copied()))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|sample| sample.id)
+        .collect();
+    if let Some(missing) = sample_ids.iter().find(|id| !found.contains(id)) {
+        return Err(DbErr::Custom(format!("Sample {missing} not found")));
+    }
+    Ok(())
+}
+
+fn check_name(name: &str) -> Result<(), DbErr> {
+    if name.trim().is_empty() {
+        return Err(DbErr::Custom("name must not be empty".to_string()));
+    }
+    Ok(())
+}
+
+/// Plans the reader may see, soonest first
+pub async fn list_planned_experiments(
+    db: &DatabaseConnection,
+    reader: &Reader,
+    query: &PlannedExperimentQuery,
+) -> Result<Vec<PlannedExperiment>, DbErr> {
+    let mut select = Entity::find().filter(visible_plans(db, reader).await?);
+    if let Some(status) = query.status {
+        select = select.filter(Column::Status.eq(status));
+    }
+    if let Some(operator) = &query.operator {
+        select = select.filter(Column::Operator.eq(operator.as_str()));
+    }
+    if let Some(from) = query.from {
+        select = select.filter(Column::ScheduledFor.gte(from));
+    }
+    if let Some(to) = query.to {
+        select = select.filter(Column::ScheduledFor.lt(to));
+    }
+    Ok(select
+        .order_by_asc(Column::ScheduledFor)
+        .order_by_asc(Column::Name)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(PlannedExperiment::from)
+        .collect())
+}
+
+pub async fn get_planned_experiment(
+    db: &DatabaseConnection,
+    id: Uuid,
+) -> Result<PlannedExperiment, DbErr> {
+    find_plan(db, id).await.map(PlannedExperiment::from)
+}
+
+/// Plan an experiment. Problems are returned as `DbErr::Custom`.
+pub async fn create_planned_experiment(
+    db: &DatabaseConnection,
+    input: PlannedExperimentCreate,
+) -> Result<PlannedExperiment, DbErr> {
+    check_name(&input.name)?;
+    check_references(
+        db,
+        input.project_id,
+        input.tray_configuration_id,
+        &input.sample_ids,
+    )
+    .await?;
+
+    let now = Utc::now();
+    let plan = ActiveModel {
+        id: Set(Uuid::new_v4()),
+        name: Set(input.name),
+        scheduled_for: Set(input.scheduled_for),
+        operator: Set(input.operator),
+        project_id: Set(input.project_id),
+        tray_configuration_id: Set(input.tray_configuration_id),
+        sample_ids: Set(json!(input.sample_ids)),
+        notes: Set(input.notes),
+        status: Set(PlannedExperimentStatus::Planned),
+        experiment_id: Set(None),
+        lab: Set(labs::current_lab()),
+        created_by: Set(crate::common::auth::current_username()),
+        created_at: Set(now),
+        last_updated: Set(now),
+    }
+    .insert(db)
+    .await?;
+    Ok(plan.into())
+}
+
+/// Change a plan that has not been started. Problems are returned as
+/// `DbErr::Custom`.
+pub async fn update_planned_experiment(
+    db: &DatabaseConnection,
+    id: Uuid,
+    input: PlannedExperimentUpdate,
+) -> Result<PlannedExperiment, DbErr> {
+    let existing = find_plan(db, id).await?;
+    if existing.status == PlannedExperimentStatus::Started {
+        return Err(DbErr::Custom(
+            "The plan has been started; change its experiment instead".to_string(),
+        ));
+    }
+    if input.status == Some(PlannedExperimentStatus::Started) {
+        return Err(DbErr::Custom(
+            "Plans are started with the start route".to_string(),
+        ));
+    }
+    if let Some(name) = &input.name {
+        check_name(name)?;
+    }
+    check_references(
+        db,
+        input.project_id.flatten(),
+        input.tray_configuration_id.flatten(),
+        input.sample_ids.as_deref().unwrap_or_default(),
+    )
+    .await?;
+
+    let mut plan = existing.into_active_model();
+    if let Some(name) = input.name {
+        plan.name = Set(name);
+    }
+    if let Some(scheduled_for) = input.scheduled_for {
+        plan.scheduled_for = Set(scheduled_for);
+    }
+    if let Some(operator) = input.operator {
+        plan.operator = Set(operator);
+    }
+    if let Some(project_id) = input.project_id {
+        plan.project_id = Set(project_id);
+    }
+    if let Some(tray_configuration_id) = input.tray_configuration_id {
+        plan.tray_configuration_id = Set(tray_configuration_id);
+    }
+    if let Some(sample_ids) = input.sample_ids {
+        plan.sample_ids = Set(json!(sample_ids));
+    }
+    if let Some(notes) = input.notes {
+        plan.notes = Set(notes);
+    }
+    if let Some(status) = input.status {
+        plan.status = Set(status);
+    }
+    plan.last_updated = Set(Utc::now());
+    Ok(plan.update(db).await?.into())
+}
+
+/// Delete a plan, keeping any experiment it was started as
+pub async fn delete_planned_experiment(
+    db: &DatabaseConnection,
+    id: Uuid,
+) -> Result<PlannedExperiment, DbErr> {
+    let plan = find_plan(db, id).await?;
+    Entity::delete_by_id(id).exec(db).await?;
+    Ok(plan.into())
+}
+
+/// Create the plan's experiment, named as the plan and run by its
+/// operator, and mark the plan started. Problems are returned as
+/// `DbErr::Custom`.
+pub async fn start_planned_experiment(
+    db: &DatabaseConnection,
+    id: Uuid,
+) -> Result<Experiment, DbErr> {
+    let txn = db.begin().await?;
+    let plan = find_plan(&txn, id).await?;
+    if plan.status != PlannedExperimentStatus::Planned {
+        return Err(DbErr::Custom(
+            "Only plans still planned can be started".to_string(),
+        ));
+    }
+    if experiments::Entity::find()
+        .filter(experiments::Column::Name.eq(plan.name.as_str()))
+        .count(&txn)
+        .await?
+        > 0
+    {
+        return Err(DbErr::Custom(format!(
+            "An experiment named '{}' already exists",
+            plan.name
+        )));
+    }
+
+    let now = Utc::now();
+    let experiment = experiments::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        name: Set(plan.name.clone()),
+        username: Set(plan.operator.clone()),
+        performed_at: Set(None),
+        temperature_ramp: Set(None),
+        temperature_start: Set(None),
+        temperature_end: Set(None),
+        is_calibration: Set(false),
+        remarks: Set(plan.notes.clone()),
+        tray_configuration_id: Set(plan.tray_configuration_id),
+        doi: Set(None),
+        project_id: Set(plan.project_id),
+        created_by: Set(crate::common::auth::current_username()),
+        lab: Set(plan.lab.clone()),
+        deleted_at: Set(None),
+        created_at: Set(now),
+        last_updated: Set(now),
+    }
+    .insert(&txn)
+    .await?;
+
+    let mut plan = plan.into_active_model();
+    plan.status = Set(PlannedExperimentStatus::Started);
+    plan.experiment_id = Set(Some(experiment.id));
+    plan.last_updated = Set(now);
+    plan.update(&txn).await?;
+    txn.commit().await?;
+
+    Experiment::get_one(db, experiment.id).await
+}
+
+/// Plans and performed experiments in the range that the reader may see,
+/// by date. Plans that were not called off are given unless their
+/// experiment is given as performed. Invalid ranges are returned as
+/// `DbErr::Custom`.
+pub async fn calendar(
+    db: &DatabaseConnection,
+    reader: &Reader,
+    query: &CalendarQuery,
+) -> Result<Vec<CalendarEntry>, DbErr> {
+    if query.from >= query.to {
+        return Err(DbErr::Custom("from must be before to".to_string()));
+    }
+    if query.to - query.from > Duration::days(MAX_CALENDAR_DAYS) {
+        return Err(DbErr::Custom(format!(
+            "The calendar covers at most {MAX_CALENDAR_DAYS} days"
+        )));
+    }
+    let visible = visible_plans(db, reader).await?;
+
+    let performed = experiments::Entity::find()
+        .filter(experiments::Column::PerformedAt.gte(query.from))
+        .filter(experiments::Column::PerformedAt.lt(query.to))
+        .filter(experiments::Column::DeletedAt.is_null())
+        .all(db)
+        .await?;
+    let readable = readable_experiments(
+        db,
+        reader,
+        performed.iter().map(|experiment| experiment.id).collect(),
+    )
+    .await;
+    let performed: Vec<_> = performed
+        .into_iter()
+        .filter(|experiment| readable.contains(&experiment.id))
+        .collect();
+    let started: HashMap<Uuid, Uuid> = Entity::find()
+        .filter(Column::ExperimentId.is_in(performed.iter().map(|experiment| experiment.id)))
+        .filter(visible.clone())
+        .all(db)
+        .await?
+        .into_iter()
+        .filter_map(|plan| {
+            plan.experiment_id
+                .map(|experiment_id| (experiment_id, plan.id))
+        })
+        .collect();
+
+    let mut entries: Vec<CalendarEntry> = Entity::find()
+        .filter(Column::ScheduledFor.gte(query.from))
+        .filter(Column::ScheduledFor.lt(query.to))
+        .filter(Column::Status.ne(PlannedExperimentStatus::Cancelled))
+        .filter(visible)
+        .all(db)
+        .await?
+        .into_iter()
+        .filter(|plan| {
+            plan.experiment_id
+                .is_none_or(|experiment_id| !readable.contains(&experiment_id))
+        })
+        .map(|plan| CalendarEntry {
+            kind: CalendarEntryKind::Planned,
+            date: plan.scheduled_for,
+            name: plan.name,
+            planned_experiment_id: Some(plan.id),
+            experiment_id: plan.experiment_id,
+            operator: plan.operator,
+            project_id: plan.project_id,
+            status: Some(plan.status),
+        })
+        .collect();
+    entries.extend(performed.into_iter().filter_map(|experiment| {
+        Some(CalendarEntry {
+            kind: CalendarEntryKind::Performed,
+            date: experiment.performed_at?,
+            planned_experiment_id: started.get(&experiment.id).copied(),
+            status: started
+                .contains_key(&experiment.id)
+                .then_some(PlannedExperimentStatus::Started),
+            name: experiment.name,
+            experiment_id: Some(experiment.id),
+            operator: experiment.username,
+            project_id: experiment.project_id,
+        })
+    }));
+    entries.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.name.cmp(&b.name)));
+    Ok(entries)
+}
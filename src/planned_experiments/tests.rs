@@ -0,0 +1,292 @@
+use crate::common::keycloak::test_realm;
+use crate::config::test_helpers::{
+    send_json, send_json_as, setup_authenticated_test_app, setup_test_app,
+};
+use axum::http::StatusCode;
+use chrono::{Duration, Utc};
+use serde_json::json;
+
+fn calendar_uri(from: chrono::DateTime<Utc>, to: chrono::DateTime<Utc>) -> String {
+    format!(
+        "/api/planned_experiments/calendar?from={}&to={}",
+        from.format("%Y-%m-%dT%H:%M:%SZ"),
+        to.format("%Y-%m-%dT%H:%M:%SZ")
+    )
+}
+
+#[tokio::test]
+#[allow(clippy::too_many_lines)]
+async fn test_experiments_are_planned_and_started() {
+    let app = setup_test_app().await;
+    let (status, demo) = send_json(&app, "POST", "/api/maintenance/demo", None).await;
+    assert_eq!(status, StatusCode::CREATED, "{demo}");
+    let now = Utc::now();
+    let tomorrow = now + Duration::days(1);
+
+    // Plans must name records that exist
+    for invalid in [
+        json!({"name": " ", "scheduled_for": tomorrow}),
+        json!({"name": "Run", "scheduled_for": tomorrow, "sample_ids": [uuid::Uuid::new_v4()]}),
+        json!({"name": "Run", "scheduled_for": tomorrow, "tray_configuration_id": uuid::Uuid::new_v4()}),
+    ] {
+        let (status, _) = send_json(&app, "POST", "/api/planned_experiments", Some(&invalid)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{invalid}");
+    }
+
+    let (status, plan) = send_json(
+        &app,
+        "POST",
+        "/api/planned_experiments",
+        Some(&json!({
+            "name": "Repeat of the demo run",
+            "scheduled_for": tomorrow,
+            "operator": "alice",
+            "project_id": demo["project_id"],
+            "tray_configuration_id": demo["tray_configuration_id"],
+            "sample_ids": demo["sample_ids"],
+            "notes": "Bring the bulk sample up from the freezer",
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{plan}");
+    assert_eq!(plan["status"], "planned");
+    assert_eq!(plan["sample_ids"], demo["sample_ids"]);
+    let plan_uri = format!("/api/planned_experiments/{}", plan["id"].as_str().unwrap());
+    let (status, spare) = send_json(
+        &app,
+        "POST",
+        "/api/planned_experiments",
+        Some(&json!({"name": "Spare slot", "scheduled_for": now + Duration::days(2)})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{spare}");
+    let spare_uri = format!("/api/planned_experiments/{}", spare["id"].as_str().unwrap());
+
+    // The calendar gives what was performed and what is planned, by date
+    let week = calendar_uri(now - Duration::days(3), now + Duration::days(4));
+    let (status, entries) = send_json(&app, "GET", &week, None).await;
+    assert_eq!(status, StatusCode::OK, "{entries}");
+    let summary: Vec<(&str, &str)> = entries
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| {
+            (
+                entry["kind"].as_str().unwrap(),
+                entry["name"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary[1..],
+        [
+            ("planned", "Repeat of the demo run"),
+            ("planned", "Spare slot")
+        ]
+    );
+    assert_eq!(summary[0].0, "performed");
+    assert_eq!(entries[0]["experiment_id"], demo["experiment_id"]);
+    assert_eq!(entries[1]["operator"], "alice");
+
+    for invalid in [
+        calendar_uri(now, now - Duration::days(1)),
+        calendar_uri(now, now + Duration::days(400)),
+    ] {
+        let (status, _) = send_json(&app, "GET", &invalid, None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{invalid}");
+    }
+
+    // Cancelled plans leave the calendar and cannot be started
+    let (status, cancelled) = send_json(
+        &app,
+        "PATCH",
+        &spare_uri,
+        Some(&json!({"status": "cancelled", "notes": null})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{cancelled}");
+    assert_eq!(cancelled["status"], "cancelled");
+    let (status, _) = send_json(&app, "POST", &format!("{spare_uri}/start"), None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, planned) = send_json(
+        &app,
+        "GET",
+        "/api/planned_experiments?status=planned&operator=alice",
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{planned}");
+    assert_eq!(planned.as_array().unwrap().len(), 1);
+    assert_eq!(planned[0]["id"], plan["id"]);
+
+    // Starting a plan creates its experiment
+    let (status, experiment) = send_json(&app, "POST", &format!("{plan_uri}/start"), None).await;
+    assert_eq!(status, StatusCode::CREATED, "{experiment}");
+    assert_eq!(experiment["name"], "Repeat of the demo run");
+    assert_eq!(experiment["username"], "alice");
+    assert_eq!(experiment["project_id"], demo["project_id"]);
+    assert_eq!(
+        experiment["tray_configuration_id"],
+        demo["tray_configuration_id"]
+    );
+    assert_eq!(
+        experiment["remarks"],
+        "Bring the bulk sample up from the freezer"
+    );
+    let (status, started) = send_json(&app, "GET", &plan_uri, None).await;
+    assert_eq!(status, StatusCode::OK, "{started}");
+    assert_eq!(started["status"], "started");
+    assert_eq!(started["experiment_id"], experiment["id"]);
+
+    // Started plans are kept as they were
+    let (status, _) = send_json(&app, "POST", &format!("{plan_uri}/start"), None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = send_json(
+        &app,
+        "PATCH",
+        &plan_uri,
+        Some(&json!({"scheduled_for": now + Duration::days(3)})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Until it is performed, the started plan stays on its day
+    let (status, entries) = send_json(&app, "GET", &week, None).await;
+    assert_eq!(status, StatusCode::OK, "{entries}");
+    assert_eq!(entries.as_array().unwrap().len(), 2);
+    assert_eq!(entries[1]["status"], "started");
+    assert_eq!(entries[1]["experiment_id"], experiment["id"]);
+
+    let (status, _) = send_json(&app, "DELETE", &plan_uri, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send_json(&app, "GET", &plan_uri, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let experiment_uri = format!("/api/experiments/{}", experiment["id"].as_str().unwrap());
+    let (status, _) = send_json(&app, "GET", &experiment_uri, None).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+#[allow(clippy::too_many_lines)]
+async fn test_plans_are_kept_to_project_members() {
+    let (app, _db) = setup_authenticated_test_app().await;
+    let admin = test_realm::token("ada", &["spice-admin"], &[]);
+    let editor = test_realm::token("eddie", &["spice-editor"], &[]);
+    let viewer = test_realm::token("vera", &["spice-viewer"], &[]);
+    let tomorrow = Utc::now() + Duration::days(1);
+
+    let mut project_ids = Vec::new();
+    for name in ["Member project", "Other project"] {
+        let (_, project) = send_json_as(
+            &app,
+            Some(&admin),
+            "POST",
+            "/api/projects",
+            Some(&json!({"name": name})),
+        )
+        .await;
+        project_ids.push(project["id"].clone());
+    }
+    let (mine, other) = (&project_ids[0], &project_ids[1]);
+    let (status, _) = send_json_as(
+        &app,
+        Some(&admin),
+        "PUT",
+        &format!("/api/projects/{}/members", mine.as_str().unwrap()),
+        Some(&json!(["eddie", "vera"])),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Editors plan only for their projects, and are named as the planner
+    let (status, _) = send_json_as(
+        &app,
+        Some(&editor),
+        "POST",
+        "/api/planned_experiments",
+        Some(&json!({"name": "Elsewhere", "scheduled_for": tomorrow, "project_id": other})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, plan) = send_json_as(
+        &app,
+        Some(&editor),
+        "POST",
+        "/api/planned_experiments",
+        Some(&json!({
+            "name": "Member run",
+            "scheduled_for": tomorrow,
+            "operator": "eddie",
+            "project_id": mine
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{plan}");
+    assert_eq!(plan["created_by"], "eddie");
+    let plan_uri = format!("/api/planned_experiments/{}", plan["id"].as_str().unwrap());
+    let (status, other_plan) = send_json_as(
+        &app,
+        Some(&admin),
+        "POST",
+        "/api/planned_experiments",
+        Some(&json!({"name": "Other run", "scheduled_for": tomorrow, "project_id": other})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{other_plan}");
+    let other_uri = format!(
+        "/api/planned_experiments/{}",
+        other_plan["id"].as_str().unwrap()
+    );
+
+    // Viewers read the plans of their projects, without who planned or runs them
+    let (status, listed) =
+        send_json_as(&app, Some(&viewer), "GET", "/api/planned_experiments", None).await;
+    assert_eq!(status, StatusCode::OK, "{listed}");
+    assert_eq!(listed.as_array().unwrap().len(), 1);
+    assert_eq!(listed[0]["id"], plan["id"]);
+    assert!(listed[0]["created_by"].is_null());
+    assert!(listed[0]["operator"].is_null());
+    let (status, _) = send_json_as(&app, Some(&viewer), "GET", &other_uri, None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Plans of other projects are neither changed nor started, and plans
+    // are not moved to them
+    let (status, _) = send_json_as(
+        &app,
+        Some(&editor),
+        "PATCH",
+        &other_uri,
+        Some(&json!({"notes": "Mine now"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = send_json_as(
+        &app,
+        Some(&editor),
+        "POST",
+        &format!("{other_uri}/start"),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = send_json_as(
+        &app,
+        Some(&editor),
+        "PATCH",
+        &plan_uri,
+        Some(&json!({"project_id": other})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, experiment) = send_json_as(
+        &app,
+        Some(&editor),
+        "POST",
+        &format!("{plan_uri}/start"),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{experiment}");
+    assert_eq!(experiment["project_id"], *mine);
+}
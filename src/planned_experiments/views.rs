@@ -0,0 +1,383 @@
+use super::models::{PlannedExperiment, PlannedExperimentCreate, PlannedExperimentUpdate};
+use super::services::{
+    CalendarEntry, CalendarQuery, PlannedExperimentQuery, calendar, create_planned_experiment,
+    delete_planned_experiment, get_planned_experiment, list_planned_experiments, may_plan_for,
+    may_reach, start_planned_experiment, update_planned_experiment,
+};
+use crate::api_keys::services::accept_api_keys;
+use crate::changes::services::Reader;
+use crate::changes::views::reader;
+use crate::common::auth::{Role, RouteAccess, as_user, require_role};
+use crate::common::keycloak::authenticate;
+use crate::common::labs::{Labs, TenantResource, require_lab};
+use crate::common::redaction::redact_for_viewers;
+use crate::common::state::AppState;
+use crate::experiments::models::Experiment;
+use crate::projects::access::ScopedResource;
+use axum::{
+    Extension, Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    middleware,
+    routing::{get, post},
+};
+use axum_keycloak_auth::{PassthroughMode, decode::KeycloakToken};
+use sea_orm::{DatabaseConnection, DbErr};
+use utoipa::OpenApi;
+use utoipa_axum::router::OpenApiRouter;
+use uuid::Uuid;
+
+fn error_response(e: DbErr) -> (StatusCode, String) {
+    match e {
+        DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+        DbErr::Custom(message) => (StatusCode::BAD_REQUEST, message),
+        e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+/// Username of who makes the request, whom the records it creates name
+fn requester(token: Option<&Extension<KeycloakToken<Role>>>) -> Option<String> {
+    token.map(|Extension(token)| token.extra.profile.preferred_username.clone())
+}
+
+/// Run a change on behalf of the user making it, if any
+async fn on_behalf<F: Future>(username: Option<String>, future: F) -> F::Output {
+    match username {
+        Some(username) => as_user(username, future).await,
+        None => future.await,
+    }
+}
+
+/// Check that the samples a plan names are ones the user may read; the lab
+/// layer checks the other records it names
+async fn check_samples(
+    db: &DatabaseConnection,
+    reader: &Reader,
+    sample_ids: &[Uuid],
+) -> Result<(), (StatusCode, String)> {
+    for sample_id in sample_ids {
+        if !reader
+            .may_read(
+                db,
+                (Some(ScopedResource::Samples), TenantResource::Samples),
+                *sample_id,
+            )
+            .await
+        {
+            return Err((
+                StatusCode::FORBIDDEN,
+                "The sample_ids name a sample you may not read".to_string(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Check that the user may plan for the project, one they are a member of
+async fn check_project(
+    db: &DatabaseConnection,
+    reader: &Reader,
+    project_id: Option<Uuid>,
+) -> Result<(), (StatusCode, String)> {
+    if may_plan_for(db, reader, project_id)
+        .await
+        .map_err(error_response)?
+    {
+        Ok(())
+    } else {
+        Err((
+            StatusCode::FORBIDDEN,
+            "The project_id must be a project you are a member of".to_string(),
+        ))
+    }
+}
+
+/// Check that the user may see and change a plan
+async fn check_plan(
+    db: &DatabaseConnection,
+    reader: &Reader,
+    id: Uuid,
+) -> Result<PlannedExperiment, (StatusCode, String)> {
+    let plan = get_planned_experiment(db, id)
+        .await
+        .map_err(error_response)?;
+    if may_reach(db, reader, &plan).await.map_err(error_response)? {
+        Ok(plan)
+    } else {
+        Err((
+            StatusCode::FORBIDDEN,
+            "This plan is not in a project you are a member of".to_string(),
+        ))
+    }
+}
+
+/// List planned experiments
+#[utoipa::path(
+    get,
+    path = "",
+    params(PlannedExperimentQuery),
+    responses(
+        (status = 200, description = "Planned experiments, soonest first", body = Vec<PlannedExperiment>),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "planned_experiments",
+    summary = "List planned experiments",
+    description = "List the experiments planned ahead, filtered by status, operator and the time they are scheduled for"
+)]
+pub async fn get_planned_experiments(
+    State(state): State<AppState>,
+    token: Option<Extension<KeycloakToken<Role>>>,
+    labs: Option<Extension<Labs>>,
+    Query(query): Query<PlannedExperimentQuery>,
+) -> Result<Json<Vec<PlannedExperiment>>, (StatusCode, String)> {
+    list_planned_experiments(&state.db, &reader(token, labs), &query)
+        .await
+        .map(Json)
+        .map_err(error_response)
+}
+
+/// Plan an experiment
+#[utoipa::path(
+    post,
+    path = "",
+    request_body = PlannedExperimentCreate,
+    responses(
+        (status = 201, description = "The planned experiment", body = PlannedExperiment),
+        (status = 400, description = "Empty name, or a project, tray configuration or sample that does not exist"),
+        (status = 403, description = "A record named is of another lab, or a project or sample of others"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "planned_experiments",
+    summary = "Plan an experiment",
+    description = "Schedule an experiment for a day, with the operator to run it and the project, tray configuration and samples it is for"
+)]
+pub async fn post_planned_experiment(
+    State(state): State<AppState>,
+    token: Option<Extension<KeycloakToken<Role>>>,
+    labs: Option<Extension<Labs>>,
+    Json(input): Json<PlannedExperimentCreate>,
+) -> Result<(StatusCode, Json<PlannedExperiment>), (StatusCode, String)> {
+    let username = requester(token.as_ref());
+    let reader = reader(token, labs);
+    check_project(&state.db, &reader, input.project_id).await?;
+    check_samples(&state.db, &reader, &input.sample_ids).await?;
+    on_behalf(username, create_planned_experiment(&state.db, input))
+        .await
+        .map(|plan| (StatusCode::CREATED, Json(plan)))
+        .map_err(error_response)
+}
+
+/// Get a planned experiment
+#[utoipa::path(
+    get,
+    path = "/{id}",
+    params(
+        ("id" = Uuid, Path, description = "Planned experiment ID")
+    ),
+    responses(
+        (status = 200, description = "The planned experiment", body = PlannedExperiment),
+        (status = 403, description = "The plan is of a project of others"),
+        (status = 404, description = "Planned experiment not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "planned_experiments",
+    summary = "Get a planned experiment"
+)]
+pub async fn get_one_planned_experiment(
+    State(state): State<AppState>,
+    token: Option<Extension<KeycloakToken<Role>>>,
+    labs: Option<Extension<Labs>>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<PlannedExperiment>, (StatusCode, String)> {
+    check_plan(&state.db, &reader(token, labs), id)
+        .await
+        .map(Json)
+}
+
+/// Change a planned experiment
+#[utoipa::path(
+    patch,
+    path = "/{id}",
+    params(
+        ("id" = Uuid, Path, description = "Planned experiment ID")
+    ),
+    request_body = PlannedExperimentUpdate,
+    responses(
+        (status = 200, description = "The changed plan", body = PlannedExperiment),
+        (status = 400, description = "The plan has been started, or a record named does not exist"),
+        (status = 403, description = "The plan, or a record named, is of another lab or of a project of others"),
+        (status = 404, description = "Planned experiment not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "planned_experiments",
+    summary = "Change a planned experiment",
+    description = "Reschedule a plan, change what it is for, or call it off with the status `cancelled`. Fields left out are kept and null clears them. Started plans are kept as they were"
+)]
+pub async fn patch_planned_experiment(
+    State(state): State<AppState>,
+    token: Option<Extension<KeycloakToken<Role>>>,
+    labs: Option<Extension<Labs>>,
+    Path(id): Path<Uuid>,
+    Json(input): Json<PlannedExperimentUpdate>,
+) -> Result<Json<PlannedExperiment>, (StatusCode, String)> {
+    let reader = reader(token, labs);
+    check_plan(&state.db, &reader, id).await?;
+    if let Some(project_id) = input.project_id {
+        check_project(&state.db, &reader, project_id).await?;
+    }
+    if let Some(sample_ids) = &input.sample_ids {
+        check_samples(&state.db, &reader, sample_ids).await?;
+    }
+    update_planned_experiment(&state.db, id, input)
+        .await
+        .map(Json)
+        .map_err(error_response)
+}
+
+/// Delete a planned experiment
+#[utoipa::path(
+    delete,
+    path = "/{id}",
+    params(
+        ("id" = Uuid, Path, description = "Planned experiment ID")
+    ),
+    responses(
+        (status = 200, description = "The deleted plan", body = PlannedExperiment),
+        (status = 403, description = "The plan is of a project of others"),
+        (status = 404, description = "Planned experiment not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "planned_experiments",
+    summary = "Delete a planned experiment",
+    description = "Delete a plan. The experiment of a started plan is kept"
+)]
+pub async fn delete_one_planned_experiment(
+    State(state): State<AppState>,
+    token: Option<Extension<KeycloakToken<Role>>>,
+    labs: Option<Extension<Labs>>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<PlannedExperiment>, (StatusCode, String)> {
+    check_plan(&state.db, &reader(token, labs), id).await?;
+    delete_planned_experiment(&state.db, id)
+        .await
+        .map(Json)
+        .map_err(error_response)
+}
+
+/// Start a planned experiment
+#[utoipa::path(
+    post,
+    path = "/{id}/start",
+    params(
+        ("id" = Uuid, Path, description = "Planned experiment ID")
+    ),
+    responses(
+        (status = 201, description = "The experiment created for the plan", body = Experiment),
+        (status = 400, description = "The plan is not planned, or an experiment of its name exists"),
+        (status = 403, description = "The plan is of a project of others"),
+        (status = 404, description = "Planned experiment not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "planned_experiments",
+    summary = "Start a planned experiment",
+    description = "Create the plan's experiment, named as the plan, run by its operator and with its project, tray configuration and notes, and mark the plan started. Its data is uploaded to the experiment as usual"
+)]
+pub async fn post_start(
+    State(state): State<AppState>,
+    token: Option<Extension<KeycloakToken<Role>>>,
+    labs: Option<Extension<Labs>>,
+    Path(id): Path<Uuid>,
+) -> Result<(StatusCode, Json<Experiment>), (StatusCode, String)> {
+    let username = requester(token.as_ref());
+    check_plan(&state.db, &reader(token, labs), id).await?;
+    on_behalf(username, start_planned_experiment(&state.db, id))
+        .await
+        .map(|experiment| (StatusCode::CREATED, Json(experiment)))
+        .map_err(error_response)
+}
+
+/// Get the lab calendar
+#[utoipa::path(
+    get,
+    path = "/calendar",
+    params(CalendarQuery),
+    responses(
+        (status = 200, description = "Plans and performed experiments, by date", body = Vec<CalendarEntry>),
+        (status = 400, description = "Invalid time range"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "planned_experiments",
+    summary = "Get the lab calendar",
+    description = "List the plans scheduled and the experiments performed in a time range of at most 366 days, by date. Plans called off are left out, as are started plans whose experiment is listed as performed; performed experiments name the plan they were started from. Users who are not administrators get only what they may read"
+)]
+pub async fn get_calendar(
+    State(state): State<AppState>,
+    token: Option<Extension<KeycloakToken<Role>>>,
+    labs: Option<Extension<Labs>>,
+    Query(query): Query<CalendarQuery>,
+) -> Result<Json<Vec<CalendarEntry>>, (StatusCode, String)> {
+    calendar(&state.db, &reader(token, labs), &query)
+        .await
+        .map(Json)
+        .map_err(error_response)
+}
+
+/// Documentation of the routes, which are routed with `route`
+#[derive(OpenApi)]
+#[openapi(paths(
+    get_planned_experiments,
+    post_planned_experiment,
+    get_calendar,
+    get_one_planned_experiment,
+    patch_planned_experiment,
+    delete_one_planned_experiment,
+    post_start
+))]
+struct PlannedExperimentsApi;
+
+pub fn router(state: &AppState) -> OpenApiRouter {
+    let mut router = OpenApiRouter::new()
+        .route(
+            "/",
+            get(get_planned_experiments).post(post_planned_experiment),
+        )
+        .route("/calendar", get(get_calendar))
+        .route(
+            "/{id}",
+            get(get_one_planned_experiment)
+                .patch(patch_planned_experiment)
+                .delete(delete_one_planned_experiment),
+        )
+        .route("/{id}/start", post(post_start))
+        .with_state(state.clone());
+    router
+        .get_openapi_mut()
+        .merge(PlannedExperimentsApi::openapi());
+
+    // Viewers read and editors change plans, of their projects and within
+    // their labs
+    if let Some(instance) = state.keycloak_auth_instance.clone() {
+        router = router
+            .layer(middleware::from_fn_with_state(
+                (state.db.clone(), TenantResource::PlannedExperiments),
+                require_lab,
+            ))
+            .layer(middleware::from_fn_with_state(
+                RouteAccess::PROJECT_RECORDS,
+                require_role,
+            ))
+            .layer(middleware::from_fn(redact_for_viewers))
+            .layer(middleware::from_fn_with_state(
+                (state.db.clone(), "planned_experiments"),
+                accept_api_keys,
+            ))
+            .layer(middleware::from_fn_with_state(
+                (instance, PassthroughMode::Pass),
+                authenticate,
+            ));
+    } else if !state.config.tests_running {
+        println!("Warning: Planned experiment routes are not protected");
+    }
+
+    router
+}
@@ -0,0 +1,590 @@
+//! Project-scoped access for users who are not administrators.
+//!
+//! Administrators can do anything. Other users can read only the projects
+//! they are members of, and read, or as editors change, only their records:
+//! a location or an experiment through its project, a sample through its
+//! location, a treatment through its sample, a dilution through its
+//! treatment and an asset through its experiment. Lists are narrowed to
+//! those records, and other routes over a whole collection stay with
+//! administrators. Members are kept by Keycloak username. Records outside
+//! the user's projects that they created or that were shared with them are
+//! reached as `sharing` allows.
+
+use super::members::models::{self as members, ProjectMember};
+use super::models::{self as projects, Entity as Projects};
+use super::shares::models::SharePermission;
+use super::sharing::{RecordAccess, Requester, accessible, record_access, token_groups};
+use crate::common::auth::{Role, as_user};
+use crate::{
+    assets::models as assets, experiments::models as experiments, locations::models as locations,
+    samples::models as samples, treatments::dilutions::models as dilutions,
+    treatments::models as treatments,
+};
+use axum::{
+    Extension,
+    body::Body,
+    extract::{Request, State},
+    http::{Extensions, Method, StatusCode, request::Parts},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use axum_keycloak_auth::decode::KeycloakToken;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, Condition, DatabaseConnection, DbErr,
+    EntityTrait, QueryFilter, QueryOrder, QuerySelect, TransactionTrait, sea_query::Query,
+};
+use serde_json::Value;
+use uuid::Uuid;
+
+/// As the router's body limit
+const BODY_LIMIT: usize = 30 * 1024 * 1024;
+
+/// Resources whose records belong to projects
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScopedResource {
+    Projects,
+    Locations,
+    Samples,
+    Treatments,
+    Dilutions,
+    Experiments,
+    Assets,
+}
+
+impl ScopedResource {
+    fn singular(self) -> &'static str {
+        match self {
+            Self::Projects => "project",
+            Self::Locations => "location",
+            Self::Samples => "sample",
+            Self::Treatments => "treatment",
+            Self::Dilutions => "dilution",
+            Self::Experiments => "experiment",
+            Self::Assets => "asset",
+        }
+    }
+
+    /// Field of a record naming what it belongs to
+    pub(super) fn owner_field(self) -> &'static str {
+        match self {
+            Self::Projects => "id",
+            Self::Locations | Self::Experiments => "project_id",
+            Self::Samples => "location_id",
+            Self::Treatments => "sample_id",
+            Self::Dilutions => "treatment_id",
+            Self::Assets => "experiment_id",
+        }
+    }
+
+    /// Collection routes that reveal no records, open to every user
+    fn open_routes(self) -> &'static [&'static str] {
+        match self {
+            Self::Samples => &["type-rules", "validate"],
+            Self::Projects
+            | Self::Locations
+            | Self::Treatments
+            | Self::Dilutions
+            | Self::Experiments
+            | Self::Assets => &[],
+        }
+    }
+}
+
+fn forbidden(message: impl Into<String>) -> (StatusCode, String) {
+    (StatusCode::FORBIDDEN, message.into())
+}
+
+fn internal(error: &DbErr) -> (StatusCode, String) {
+    (StatusCode::INTERNAL_SERVER_ERROR, error.to_string())
+}
+
+/// Projects the user is a member of
+pub async fn member_projects(db: &DatabaseConnection, username: &str) -> Result<Vec<Uuid>, DbErr> {
+    members::Entity::find()
+        .select_only()
+        .column(members::Column::ProjectId)
+        .filter(members::Column::Username.eq(username))
+        .into_tuple()
+        .all(db)
+        .await
+}
+
+async fn location_project(db: &DatabaseConnection, id: Uuid) -> Result<Option<Uuid>, DbErr> {
+    Ok(locations::Entity::find_by_id(id)
+        .one(db)
+        .await?
+        .and_then(|location| location.project_id))
+}
+
+async fn sample_project(db: &DatabaseConnection, id: Uuid) -> Result<Option<Uuid>, DbErr> {
+    match samples::Entity::find_by_id(id)
+        .one(db)
+        .await?
+        .and_then(|sample| sample.location_id)
+    {
+        Some(location_id) => location_project(db, location_id).await,
+        None => Ok(None),
+    }
+}
+
+/// Project of whatever the owner field of a record points at
+pub(super) async fn owner_project(
+    db: &DatabaseConnection,
+    resource: ScopedResource,
+    owner_id: Uuid,
+) -> Result<Option<Uuid>, DbErr> {
+    Ok(match resource {
+        ScopedResource::Projects | ScopedResource::Locations | ScopedResource::Experiments => {
+            Some(owner_id)
+        }
+        ScopedResource::Samples => location_project(db, owner_id).await?,
+        ScopedResource::Treatments => sample_project(db, owner_id).await?,
+        ScopedResource::Dilutions => match treatments::Entity::find_by_id(owner_id)
+            .one(db)
+            .await?
+            .and_then(|treatment| treatment.sample_id)
+        {
+            Some(sample_id) => sample_project(db, sample_id).await?,
+            None => None,
+        },
+        ScopedResource::Assets => experiments::Entity::find_by_id(owner_id)
+            .one(db)
+            .await?
+            .and_then(|experiment| experiment.project_id),
+    })
+}
+
+/// What the owner field of a record points at, or `None` when there is no
+/// such record
+async fn record_owner(
+    db: &DatabaseConnection,
+    resource: ScopedResource,
+    id: Uuid,
+) -> Result<Option<Option<Uuid>>, DbErr> {
+    Ok(match resource {
+        ScopedResource::Projects => Projects::find_by_id(id)
+            .one(db)
+            .await?
+            .map(|project| Some(project.id)),
+        ScopedResource::Locations => locations::Entity::find_by_id(id)
+            .one(db)
+            .await?
+            .map(|location| location.project_id),
+        ScopedResource::Treatments => treatments::Entity::find_by_id(id)
+            .one(db)
+            .await?
+            .map(|treatment| treatment.sample_id),
+        ScopedResource::Dilutions => dilutions::Entity::find_by_id(id)
+            .one(db)
+            .await?
+            .map(|dilution| Some(dilution.treatment_id)),
+        ScopedResource::Experiments => experiments::Entity::find_by_id(id)
+            .one(db)
+            .await?
+            .map(|experiment| experiment.project_id),
+        ScopedResource::Samples => samples::Entity::find_by_id(id)
+            .one(db)
+            .await?
+            .map(|sample| sample.location_id),
+        ScopedResource::Assets => assets::Entity::find_by_id(id)
+            .one(db)
+            .await?
+            .map(|asset| asset.experiment_id),
+    })
+}
+
+/// Project of a record, or `None` when there is no such record
+pub(super) async fn record_project(
+    db: &DatabaseConnection,
+    resource: ScopedResource,
+    id: Uuid,
+) -> Result<Option<Option<Uuid>>, DbErr> {
+    match record_owner(db, resource, id).await? {
+        None => Ok(None),
+        Some(None) => Ok(Some(None)),
+        Some(Some(owner_id)) => Ok(Some(owner_project(db, resource, owner_id).await?)),
+    }
+}
+
+/// Records of the resource in the projects the user is a member of, as a
+/// condition on its table
+fn in_member_projects(resource: ScopedResource, username: &str) -> Condition {
+    let projects = Query::select()
+        .column(members::Column::ProjectId)
+        .from(members::Entity)
+        .and_where(members::Column::Username.eq(username))
+        .to_owned();
+    let location_ids = || {
+        Query::select()
+            .column(locations::Column::Id)
+            .from(locations::Entity)
+            .and_where(locations::Column::ProjectId.in_subquery(projects.clone()))
+            .to_owned()
+    };
+    let sample_ids = || {
+        Query::select()
+            .column(samples::Column::Id)
+            .from(samples::Entity)
+            .and_where(samples::Column::LocationId.in_subquery(location_ids()))
+            .to_owned()
+    };
+    let in_projects = match resource {
+        ScopedResource::Projects => projects::Column::Id.in_subquery(projects),
+        ScopedResource::Locations => locations::Column::ProjectId.in_subquery(projects),
+        ScopedResource::Samples => samples::Column::LocationId.in_subquery(location_ids()),
+        ScopedResource::Treatments => treatments::Column::SampleId.in_subquery(sample_ids()),
+        ScopedResource::Dilutions => dilutions::Column::TreatmentId.in_subquery(
+            Query::select()
+                .column(treatments::Column::Id)
+                .from(treatments::Entity)
+                .and_where(treatments::Column::SampleId.in_subquery(sample_ids()))
+                .to_owned(),
+        ),
+        ScopedResource::Experiments => experiments::Column::ProjectId.in_subquery(projects),
+        ScopedResource::Assets => assets::Column::ExperimentId.in_subquery(
+            Query::select()
+                .column(experiments::Column::Id)
+                .from(experiments::Entity)
+                .and_where(experiments::Column::ProjectId.in_subquery(projects))
+                .to_owned(),
+        ),
+    };
+    Condition::all().add(in_projects)
+}
+
+/// The records of a list a user who is not an administrator may read, as a
+/// condition on the resource's table. Lists and their summaries add it to
+/// their filter, in the database, however many records it reaches.
+#[derive(Clone, Debug)]
+pub struct ListScope(pub Condition);
+
+/// The scope of the user's lists: the records of their projects and those
+/// they reach outside them
+pub fn list_scope(resource: ScopedResource, user: &Requester<'_>) -> ListScope {
+    let mut scope = Condition::any().add(in_member_projects(resource, user.username));
+    if let Some(accessible) = accessible(resource, user) {
+        scope = scope.add(accessible);
+    }
+    ListScope(scope)
+}
+
+/// Narrow the scope of a request's list further, giving it one if it has
+/// none, as each access check adds its own
+pub fn narrow_list_scope(extensions: &mut Extensions, condition: Condition) {
+    let scope = match extensions.remove::<ListScope>() {
+        Some(ListScope(scope)) => Condition::all().add(scope).add(condition),
+        None => condition,
+    };
+    extensions.insert(ListScope(scope));
+}
+
+/// A condition narrowed to the scope of the request's list, if it has one
+pub fn in_list_scope(condition: Condition, scope: Option<&ListScope>) -> Condition {
+    match scope {
+        Some(ListScope(scope)) => condition.add(scope.clone()),
+        None => condition,
+    }
+}
+
+/// Check that the owner a create or update body gives is in the projects,
+/// or is an experiment the user may change for an asset. Creates must give
+/// one; updates may leave it unchanged.
+async fn check_owner(
+    db: &DatabaseConnection,
+    user: &Requester<'_>,
+    resource: ScopedResource,
+    projects: &[Uuid],
+    body: Option<&Value>,
+    creating: bool,
+) -> Result<(), (StatusCode, String)> {
+    let field = resource.owner_field();
+    let owner = body.and_then(|body| body.get(field));
+    if owner.is_none() && !creating {
+        return Ok(());
+    }
+    let owner_id = owner
+        .and_then(Value::as_str)
+        .and_then(|owner_id| Uuid::parse_str(owner_id).ok());
+    let project = match owner_id {
+        Some(owner_id) => owner_project(db, resource, owner_id)
+            .await
+            .map_err(|e| internal(&e))?,
+        None => None,
+    };
+    let changeable_experiment = match owner_id {
+        Some(owner_id) if resource == ScopedResource::Assets => matches!(
+            record_access(db, ScopedResource::Experiments, owner_id, user)
+                .await
+                .map_err(|e| internal(&e))?,
+            Some(RecordAccess::Creator | RecordAccess::Shared(SharePermission::Write))
+        ),
+        _ => false,
+    };
+    if project.is_some_and(|project| projects.contains(&project)) || changeable_experiment {
+        Ok(())
+    } else {
+        Err(forbidden(format!(
+            "The {field} of the {} must be in a project you are a member of",
+            resource.singular()
+        )))
+    }
+}
+
+pub(crate) fn segments(path: &str) -> Vec<&str> {
+    path.split('/').filter(|s| !s.is_empty()).collect()
+}
+
+/// Routes summarising a collection, narrowed as its list is
+const SUMMARY_ROUTES: &[&str] = &["count", "aggregate"];
+
+/// Whether a request reads a collection: its list, or a summary of it
+pub(crate) fn reads_collection(method: &Method, segments: &[&str]) -> bool {
+    *method == Method::GET
+        && match segments {
+            [] => true,
+            [route] => SUMMARY_ROUTES.contains(route),
+            _ => false,
+        }
+}
+
+/// The JSON body of a change to a collection or one of its records, which
+/// may name the record's owner. Deeper routes, such as uploads, stream
+/// through untouched.
+pub(crate) async fn read_record_body(
+    parts: &Parts,
+    body: Body,
+) -> Result<(Body, Option<Value>), Response> {
+    if matches!(parts.method, Method::GET | Method::HEAD) || segments(parts.uri.path()).len() > 1 {
+        return Ok((body, None));
+    }
+    let Ok(bytes) = axum::body::to_bytes(body, BODY_LIMIT).await else {
+        return Err(StatusCode::PAYLOAD_TOO_LARGE.into_response());
+    };
+    let json = serde_json::from_slice(&bytes).ok();
+    Ok((Body::from(bytes), json))
+}
+
+/// Decide whether a member may make a request, given the path within the
+/// resource's router. Lists are left to `list_scope`.
+pub async fn authorize(
+    db: &DatabaseConnection,
+    user: &Requester<'_>,
+    resource: ScopedResource,
+    method: &Method,
+    path: &str,
+    body: Option<&Value>,
+) -> Result<(), (StatusCode, String)> {
+    let segments = segments(path);
+    if reads_collection(method, &segments) {
+        return Ok(());
+    }
+    let projects = member_projects(db, user.username)
+        .await
+        .map_err(|e| internal(&e))?;
+    let Some(first) = segments.first() else {
+        return match *method {
+            Method::POST => check_owner(db, user, resource, &projects, body, true).await,
+            _ => Err(forbidden(
+                "Only administrators can change several records at once",
+            )),
+        };
+    };
+    let Ok(id) = Uuid::parse_str(first) else {
+        return if resource.open_routes().contains(first) {
+            Ok(())
+        } else {
+            Err(forbidden("Only administrators can use this endpoint"))
+        };
+    };
+
+    match record_project(db, resource, id)
+        .await
+        .map_err(|e| internal(&e))?
+    {
+        // Let the route report the missing record
+        None => Ok(()),
+        Some(Some(project)) if projects.contains(&project) => {
+            if segments.len() == 1 && matches!(*method, Method::PUT | Method::PATCH) {
+                check_owner(db, user, resource, &projects, body, false).await?;
+            }
+            Ok(())
+        }
+        Some(_) => {
+            authorize_outside_projects(db, user, resource, id, method, &segments, &projects, body)
+                .await
+        }
+    }
+}
+
+/// Decide on a request for a record outside the user's projects, which
+/// they may have created or been given
+#[allow(clippy::too_many_arguments)]
+async fn authorize_outside_projects(
+    db: &DatabaseConnection,
+    user: &Requester<'_>,
+    resource: ScopedResource,
+    id: Uuid,
+    method: &Method,
+    segments: &[&str],
+    projects: &[Uuid],
+    body: Option<&Value>,
+) -> Result<(), (StatusCode, String)> {
+    let singular = resource.singular();
+    let Some(access) = record_access(db, resource, id, user)
+        .await
+        .map_err(|e| internal(&e))?
+    else {
+        return Err(forbidden(format!(
+            "This {singular} is not in a project you are a member of"
+        )));
+    };
+
+    let whole_record = segments.len() == 1;
+    if let RecordAccess::Shared(permission) = access {
+        if segments.get(1) == Some(&"shares") {
+            return Err(forbidden(format!(
+                "Only members of its project and its creator can share this {singular}"
+            )));
+        }
+        if permission == SharePermission::Read && !matches!(*method, Method::GET | Method::HEAD) {
+            return Err(forbidden(format!(
+                "This {singular} is shared with you to read only"
+            )));
+        }
+        if whole_record && *method == Method::DELETE && resource.shared().is_some() {
+            return Err(forbidden(format!(
+                "Only members of its project and its creator can delete this {singular}"
+            )));
+        }
+    }
+
+    // The record may stay where it is, or move to one of the user's projects
+    if whole_record && matches!(*method, Method::PUT | Method::PATCH) {
+        let field = resource.owner_field();
+        let current = record_owner(db, resource, id)
+            .await
+            .map_err(|e| internal(&e))?
+            .flatten()
+            .map(|owner_id| owner_id.to_string());
+        let kept = match body.and_then(|body| body.get(field)) {
+            None => true,
+            Some(Value::Null) => current.is_none(),
+            Some(owner) => owner.as_str() == current.as_deref(),
+        };
+        if !kept {
+            check_owner(db, user, resource, projects, body, false).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Middleware limiting users who are not administrators to the records of
+/// their projects and those they reach outside them, and running requests
+/// on behalf of the user. Requests without a token, when authentication is
+/// off, pass unchanged.
+pub async fn require_project_access(
+    State((db, resource)): State<(DatabaseConnection, ScopedResource)>,
+    token: Option<Extension<KeycloakToken<Role>>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(Extension(token)) = token else {
+        return next.run(request).await;
+    };
+    let username = token.extra.profile.preferred_username.clone();
+    if token
+        .roles
+        .iter()
+        .any(|role| *role.role() == Role::Administrator)
+    {
+        return as_user(username, next.run(request)).await;
+    }
+    let groups = token_groups(&token);
+
+    let (mut parts, body) = request.into_parts();
+    let (body, json) = match read_record_body(&parts, body).await {
+        Ok(read) => read,
+        Err(response) => return response,
+    };
+
+    let user = Requester {
+        username: &username,
+        groups: &groups,
+    };
+    if let Err(rejection) = authorize(
+        &db,
+        &user,
+        resource,
+        &parts.method,
+        parts.uri.path(),
+        json.as_ref(),
+    )
+    .await
+    {
+        return rejection.into_response();
+    }
+    if reads_collection(&parts.method, &segments(parts.uri.path())) {
+        let ListScope(scope) = list_scope(resource, &user);
+        narrow_list_scope(&mut parts.extensions, scope);
+    }
+    as_user(username, next.run(Request::from_parts(parts, body))).await
+}
+
+/// Members of a project, by username
+pub async fn project_members(
+    db: &DatabaseConnection,
+    project_id: Uuid,
+) -> Result<Vec<ProjectMember>, DbErr> {
+    Projects::find_by_id(project_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Project not found".to_string()))?;
+    Ok(members::Entity::find()
+        .filter(members::Column::ProjectId.eq(project_id))
+        .order_by_asc(members::Column::Username)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(ProjectMember::from)
+        .collect())
+}
+
+/// Replace the members of a project. Blank and repeated usernames are
+/// dropped.
+pub async fn set_project_members(
+    db: &DatabaseConnection,
+    project_id: Uuid,
+    usernames: Vec<String>,
+) -> Result<Vec<ProjectMember>, DbErr> {
+    let mut usernames: Vec<String> = usernames
+        .into_iter()
+        .map(|username| username.trim().to_string())
+        .filter(|username| !username.is_empty())
+        .collect();
+    usernames.sort();
+    usernames.dedup();
+
+    Projects::find_by_id(project_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Project not found".to_string()))?;
+
+    let txn = db.begin().await?;
+    members::Entity::delete_many()
+        .filter(members::Column::ProjectId.eq(project_id))
+        .exec(&txn)
+        .await?;
+    for username in usernames {
+        members::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            project_id: Set(project_id),
+            username: Set(username),
+            created_at: Set(chrono::Utc::now()),
+        }
+        .insert(&txn)
+        .await?;
+    }
+    txn.commit().await?;
+
+    project_members(db, project_id).await
+}
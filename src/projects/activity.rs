@@ -0,0 +1,277 @@
+//! Recent activity in a project.
+//!
+//! There is no event log: activity is read from the timestamps records keep.
+//! Creating a location, sample or experiment, uploading an asset and the
+//! outcome of processing it each give an event, as does the latest edit of a
+//! record, so earlier edits of the same record are not listed.
+
+use super::models::Entity as Projects;
+use crate::assets::models as assets;
+use crate::experiments::models as experiments;
+use crate::locations::models as locations;
+use crate::samples::models as samples;
+use chrono::{DateTime, Duration, Utc};
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder, QuerySelect,
+    QueryTrait,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+pub const DEFAULT_LIMIT: u64 = 50;
+pub const MAX_LIMIT: u64 = 500;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityKind {
+    Created,
+    Edited,
+    Uploaded,
+    ProcessingCompleted,
+    ProcessingFailed,
+    Archived,
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct ActivityEvent {
+    pub occurred_at: DateTime<Utc>,
+    pub kind: ActivityKind,
+    /// `project`, `location`, `sample`, `experiment` or `asset`
+    pub resource: String,
+    pub resource_id: Uuid,
+    /// Name of the record, or file name of the asset
+    pub name: String,
+    /// Who uploaded the asset or archived the project, when known
+    pub username: Option<String>,
+    /// Message of the processing outcome
+    pub detail: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, IntoParams)]
+pub struct ActivityQuery {
+    /// Most events to return, up to 500 (default 50)
+    pub limit: Option<u64>,
+    /// Only events from this time on
+    pub since: Option<DateTime<Utc>>,
+}
+
+/// Saving a new record can set its last update a moment after its creation
+fn was_edited(created_at: DateTime<Utc>, last_updated: DateTime<Utc>) -> bool {
+    last_updated - created_at > Duration::seconds(1)
+}
+
+fn event(
+    occurred_at: DateTime<Utc>,
+    kind: ActivityKind,
+    resource: &str,
+    resource_id: Uuid,
+    name: &str,
+) -> ActivityEvent {
+    ActivityEvent {
+        occurred_at,
+        kind,
+        resource: resource.to_string(),
+        resource_id,
+        name: name.to_string(),
+        username: None,
+        detail: None,
+    }
+}
+
+/// Creation and latest edit of a record
+fn record_events(
+    resource: &str,
+    id: Uuid,
+    name: &str,
+    created_at: DateTime<Utc>,
+    last_updated: DateTime<Utc>,
+) -> Vec<ActivityEvent> {
+    let mut events = vec![event(created_at, ActivityKind::Created, resource, id, name)];
+    if was_edited(created_at, last_updated) {
+        events.push(event(
+            last_updated,
+            ActivityKind::Edited,
+            resource,
+            id,
+            name,
+        ));
+    }
+    events
+}
+
+/// Creation, latest edit and archiving of the project
+fn project_events(project: &super::models::Model) -> Vec<ActivityEvent> {
+    let mut events = record_events(
+        "project",
+        project.id,
+        &project.name,
+        project.created_at,
+        project.last_updated,
+    );
+    if let Some(archived_at) = project.archived_at {
+        // Archiving is the project's last update
+        events.retain(|event| event.occurred_at != archived_at);
+        events.push(ActivityEvent {
+            username: project.archived_by.clone(),
+            ..event(
+                archived_at,
+                ActivityKind::Archived,
+                "project",
+                project.id,
+                &project.name,
+            )
+        });
+    }
+    events
+}
+
+/// Uploads of the experiments' assets and the outcome of processing them
+async fn asset_events(
+    db: &DatabaseConnection,
+    experiment_ids: Vec<Uuid>,
+    query: &ActivityQuery,
+    limit: u64,
+) -> Result<Vec<ActivityEvent>, DbErr> {
+    let mut events = Vec::new();
+    for asset in assets::Entity::find()
+        .filter(assets::Column::ExperimentId.is_in(experiment_ids))
+        .filter(assets::Column::IsDeleted.eq(false))
+        .apply_if(query.since, |select, since| {
+            select.filter(assets::Column::LastUpdated.gte(since))
+        })
+        .order_by_desc(assets::Column::LastUpdated)
+        .limit(limit)
+        .all(db)
+        .await?
+    {
+        events.push(ActivityEvent {
+            username: asset.uploaded_by.clone(),
+            ..event(
+                asset.uploaded_at,
+                ActivityKind::Uploaded,
+                "asset",
+                asset.id,
+                &asset.original_filename,
+            )
+        });
+        let outcome = match asset.processing_status.as_deref() {
+            Some("completed") => Some(ActivityKind::ProcessingCompleted),
+            Some("error") => Some(ActivityKind::ProcessingFailed),
+            _ => None,
+        };
+        if let Some(kind) = outcome {
+            events.push(ActivityEvent {
+                detail: asset.processing_message.clone(),
+                ..event(
+                    asset.last_updated,
+                    kind,
+                    "asset",
+                    asset.id,
+                    &asset.original_filename,
+                )
+            });
+        }
+    }
+    Ok(events)
+}
+
+/// The project's most recent events, newest first. Each record gives at
+/// most one event after its last update, so the `limit` records updated
+/// last of each kind hold the `limit` most recent events.
+pub async fn project_activity(
+    db: &DatabaseConnection,
+    project_id: Uuid,
+    query: &ActivityQuery,
+) -> Result<Vec<ActivityEvent>, DbErr> {
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return Err(DbErr::Custom(format!(
+            "limit must be between 1 and {MAX_LIMIT}"
+        )));
+    }
+
+    let project = Projects::find_by_id(project_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Project not found".to_string()))?;
+    let mut events = project_events(&project);
+
+    let location_ids: Vec<Uuid> = locations::Entity::find()
+        .select_only()
+        .column(locations::Column::Id)
+        .filter(locations::Column::ProjectId.eq(project_id))
+        .into_tuple()
+        .all(db)
+        .await?;
+    for location in locations::Entity::find()
+        .filter(locations::Column::ProjectId.eq(project_id))
+        .apply_if(query.since, |select, since| {
+            select.filter(locations::Column::LastUpdated.gte(since))
+        })
+        .order_by_desc(locations::Column::LastUpdated)
+        .limit(limit)
+        .all(db)
+        .await?
+    {
+        events.extend(record_events(
+            "location",
+            location.id,
+            &location.name,
+            location.created_at,
+            location.last_updated,
+        ));
+    }
+    for sample in samples::Entity::find()
+        .filter(samples::Column::LocationId.is_in(location_ids))
+        .apply_if(query.since, |select, since| {
+            select.filter(samples::Column::LastUpdated.gte(since))
+        })
+        .order_by_desc(samples::Column::LastUpdated)
+        .limit(limit)
+        .all(db)
+        .await?
+    {
+        events.extend(record_events(
+            "sample",
+            sample.id,
+            &sample.name,
+            sample.created_at,
+            sample.last_updated,
+        ));
+    }
+
+    let experiment_ids: Vec<Uuid> = experiments::Entity::find()
+        .select_only()
+        .column(experiments::Column::Id)
+        .filter(experiments::Column::ProjectId.eq(project_id))
+        .into_tuple()
+        .all(db)
+        .await?;
+    for experiment in experiments::Entity::find()
+        .filter(experiments::Column::ProjectId.eq(project_id))
+        .apply_if(query.since, |select, since| {
+            select.filter(experiments::Column::LastUpdated.gte(since))
+        })
+        .order_by_desc(experiments::Column::LastUpdated)
+        .limit(limit)
+        .all(db)
+        .await?
+    {
+        events.extend(record_events(
+            "experiment",
+            experiment.id,
+            &experiment.name,
+            experiment.created_at,
+            experiment.last_updated,
+        ));
+    }
+    events.extend(asset_events(db, experiment_ids, query, limit).await?);
+
+    if let Some(since) = query.since {
+        events.retain(|event| event.occurred_at >= since);
+    }
+    events.sort_by_key(|event| std::cmp::Reverse(event.occurred_at));
+    events.truncate(usize::try_from(limit).unwrap_or(usize::MAX));
+    Ok(events)
+}
@@ -0,0 +1,192 @@
+//! Archived projects.
+//!
+//! Archiving a project makes it and everything in it read-only: its
+//! locations, their samples and treatments, and its experiments with their
+//! assets can still be read, but changes are rejected with 409 Conflict
+//! until an administrator unarchives the project.
+
+use super::access::{ScopedResource, owner_project, read_record_body, record_project, segments};
+use super::models::{ActiveModel, Column, Entity as Projects, Model, Project};
+use axum::{
+    extract::{Request, State},
+    http::{Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::Utc;
+use crudcrate::CRUDResource;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, DatabaseConnection, DbErr, EntityTrait,
+    IntoActiveModel, QueryFilter,
+};
+use serde_json::Value;
+use uuid::Uuid;
+
+/// Archive a project. `archived_by` is the authenticated user, when there
+/// is one.
+pub async fn archive_project(
+    db: &DatabaseConnection,
+    id: Uuid,
+    archived_by: Option<String>,
+) -> Result<Project, DbErr> {
+    let project = Projects::find_by_id(id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Project not found".to_string()))?;
+    if project.archived_at.is_some() {
+        return Err(DbErr::Custom(format!(
+            "Project '{}' is already archived",
+            project.name
+        )));
+    }
+
+    let now = Utc::now();
+    let mut active: ActiveModel = project.into_active_model();
+    active.archived_at = Set(Some(now));
+    active.archived_by = Set(archived_by);
+    active.last_updated = Set(now);
+    active.update(db).await?;
+
+    Project::get_one(db, id).await
+}
+
+/// Make an archived project and its records editable again
+pub async fn unarchive_project(db: &DatabaseConnection, id: Uuid) -> Result<Project, DbErr> {
+    let project = Projects::find_by_id(id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Project not found".to_string()))?;
+    if project.archived_at.is_none() {
+        return Err(DbErr::Custom(format!(
+            "Project '{}' is not archived",
+            project.name
+        )));
+    }
+
+    let mut active: ActiveModel = project.into_active_model();
+    active.archived_at = Set(None);
+    active.archived_by = Set(None);
+    active.last_updated = Set(Utc::now());
+    active.update(db).await?;
+
+    Project::get_one(db, id).await
+}
+
+/// Projects a change would touch: that of the record changed, that of the
+/// owner a create or update gives it, and those of records deleted or
+/// upserted at once
+async fn changed_projects(
+    db: &DatabaseConnection,
+    resource: Option<ScopedResource>,
+    method: &Method,
+    path: &str,
+    body: Option<&Value>,
+) -> Result<Vec<Uuid>, DbErr> {
+    let segments = segments(path);
+    let Some(resource) = resource else {
+        // Archiving, unarchiving and membership of a project stay open
+        return Ok(match segments[..] {
+            [id] => Uuid::parse_str(id).into_iter().collect(),
+            _ => Vec::new(),
+        });
+    };
+
+    let mut projects = Vec::new();
+    let record_ids: Vec<Uuid> = match segments[..] {
+        // Deleting several records at once
+        ["batch"] if *method == Method::DELETE => body
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|id| id.as_str().and_then(|id| Uuid::parse_str(id).ok()))
+            .collect(),
+        [first, ..] => Uuid::parse_str(first).into_iter().collect(),
+        [] => Vec::new(),
+    };
+    for id in record_ids {
+        if let Some(Some(project)) = record_project(db, resource, id).await? {
+            projects.push(project);
+        }
+    }
+    let bodies: Vec<&Value> = match segments[..] {
+        ["upsert"] if *method == Method::POST => body
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .collect(),
+        [] | [_] => body.into_iter().collect(),
+        _ => Vec::new(),
+    };
+    for body in bodies {
+        if let Some(owner_id) = body
+            .get(resource.owner_field())
+            .and_then(Value::as_str)
+            .and_then(|owner_id| Uuid::parse_str(owner_id).ok())
+            && let Some(project) = owner_project(db, resource, owner_id).await?
+        {
+            projects.push(project);
+        }
+    }
+    Ok(projects)
+}
+
+/// The archived project among those a change would touch, if any
+async fn archived_project(
+    db: &DatabaseConnection,
+    resource: Option<ScopedResource>,
+    method: &Method,
+    path: &str,
+    body: Option<&Value>,
+) -> Result<Option<Model>, DbErr> {
+    let projects = changed_projects(db, resource, method, path, body).await?;
+    if projects.is_empty() {
+        return Ok(None);
+    }
+    Projects::find()
+        .filter(Column::Id.is_in(projects))
+        .filter(Column::ArchivedAt.is_not_null())
+        .one(db)
+        .await
+}
+
+/// Middleware rejecting changes to archived projects and their records.
+/// `None` guards the projects themselves.
+pub async fn reject_archived_changes(
+    State((db, resource)): State<(DatabaseConnection, Option<ScopedResource>)>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS
+    ) {
+        return next.run(request).await;
+    }
+
+    let (parts, body) = request.into_parts();
+    let (body, json) = match read_record_body(&parts, body).await {
+        Ok(read) => read,
+        Err(response) => return response,
+    };
+
+    match archived_project(
+        &db,
+        resource,
+        &parts.method,
+        parts.uri.path(),
+        json.as_ref(),
+    )
+    .await
+    {
+        Ok(None) => next.run(Request::from_parts(parts, body)).await,
+        Ok(Some(project)) => (
+            StatusCode::CONFLICT,
+            format!(
+                "Project '{}' is archived and read-only; unarchive it to make changes",
+                project.name
+            ),
+        )
+            .into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
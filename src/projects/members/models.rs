@@ -0,0 +1,36 @@
+use chrono::{DateTime, Utc};
+use crudcrate::EntityToModels;
+use sea_orm::entity::prelude::*;
+use uuid::Uuid;
+
+/// A user who may work with the experiments, samples and assets of a project
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, EntityToModels)]
+#[sea_orm(table_name = "project_members")]
+#[crudcrate(api_struct = "ProjectMember")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    #[crudcrate(primary_key, update_model = false, create_model = false, on_create = Uuid::new_v4())]
+    pub id: Uuid,
+    #[crudcrate(sortable, filterable)]
+    pub project_id: Uuid,
+    /// Keycloak username of the member
+    #[sea_orm(column_type = "Text")]
+    #[crudcrate(sortable, filterable)]
+    pub username: String,
+    #[crudcrate(update_model = false, create_model = false, on_create = chrono::Utc::now(), sortable)]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "crate::projects::models::Entity",
+        from = "Column::ProjectId",
+        to = "crate::projects::models::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Project,
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -1,4 +1,11 @@
+pub mod access;
+pub mod activity;
+pub mod archiving;
+pub mod members;
 pub mod models;
+pub mod services;
+pub mod shares;
+pub mod sharing;
 #[cfg(test)]
 pub mod tests;
 pub mod views;
@@ -12,6 +12,7 @@ use sea_orm::entity::prelude::*;
     name_plural = "projects",
     description = "Projects provide a way to organise locations hierarchically. Each project can contain multiple locations and provides visual organization through color coding.",
     fn_get_one = get_one,
+    fn_create = create_project,
 )]
 pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
@@ -25,6 +26,16 @@ pub struct Model {
     pub note: Option<String>,
     #[crudcrate(sortable, filterable, fulltext)]
     pub colour: Option<String>,
+    /// When the project was archived, making its records read-only
+    #[crudcrate(sortable, filterable, update_model = false, create_model = false)]
+    pub archived_at: Option<DateTime<Utc>>,
+    #[sea_orm(column_type = "Text", nullable)]
+    #[crudcrate(update_model = false, create_model = false, list_model = false)]
+    pub archived_by: Option<String>,
+    /// Lab the project belongs to, when labs are on
+    #[sea_orm(column_type = "Text", nullable)]
+    #[crudcrate(sortable, filterable, create_model = false, update_model = false)]
+    pub lab: Option<String>,
     #[crudcrate(update_model = false, create_model = false, on_create = chrono::Utc::now(), sortable, list_model=false)]
     pub created_at: DateTime<Utc>,
     #[crudcrate(update_model = false, create_model = false, on_update = chrono::Utc::now(), on_create = chrono::Utc::now(), sortable, list_model=false)]
@@ -48,6 +59,13 @@ impl Related<crate::locations::models::Entity> for Entity {
 
 impl ActiveModelBehavior for ActiveModel {}
 
+async fn create_project(db: &DatabaseConnection, create_data: ProjectCreate) -> Result<Project, DbErr> {
+    let mut active_model: ActiveModel = create_data.into();
+    active_model.lab = sea_orm::ActiveValue::Set(crate::common::labs::current_lab());
+    let inserted = active_model.insert(db).await?;
+    Project::get_one(db, inserted.id).await
+}
+
 async fn get_one(db: &DatabaseConnection, id: Uuid) -> Result<Project, DbErr> {
     let model = Entity::find_by_id(id)
         .one(db)
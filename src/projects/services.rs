@@ -0,0 +1,173 @@
+use crate::assets::services::{ArchiveEntry, ArchiveSource, archive_path_component};
+use crate::{
+    experiments::models as experiments, locations::models as locations,
+    samples::models as samples, tray_configurations::regions::models as regions,
+    treatments::models as treatments,
+};
+use chrono::Utc;
+use sea_orm::{DatabaseConnection, EntityTrait, QueryOrder, entity::prelude::*};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+/// Records reachable from a project: its locations, their samples, the
+/// samples' treatments and the experiments that measured those treatments
+pub struct ProjectRecords {
+    pub locations: Vec<locations::Model>,
+    pub samples: Vec<samples::Model>,
+    pub treatments: Vec<treatments::Model>,
+    pub experiments: Vec<experiments::Model>,
+}
+
+pub async fn load_project_records(
+    db: &DatabaseConnection,
+    project_id: Uuid,
+) -> Result<ProjectRecords, DbErr> {
+    let project_locations = locations::Entity::find()
+        .filter(locations::Column::ProjectId.eq(project_id))
+        .order_by_asc(locations::Column::Name)
+        .all(db)
+        .await?;
+    let project_samples = if project_locations.is_empty() {
+        vec![]
+    } else {
+        samples::Entity::find()
+            .filter(
+                samples::Column::LocationId
+                    .is_in(project_locations.iter().map(|location| location.id)),
+            )
+            .order_by_asc(samples::Column::Name)
+            .all(db)
+            .await?
+    };
+    let project_treatments = if project_samples.is_empty() {
+        vec![]
+    } else {
+        treatments::Entity::find()
+            .filter(
+                treatments::Column::SampleId.is_in(project_samples.iter().map(|sample| sample.id)),
+            )
+            .all(db)
+            .await?
+    };
+    let experiment_ids: HashSet<Uuid> = if project_treatments.is_empty() {
+        HashSet::new()
+    } else {
+        regions::Entity::find()
+            .filter(
+                regions::Column::TreatmentId
+                    .is_in(project_treatments.iter().map(|treatment| treatment.id)),
+            )
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|region| region.experiment_id)
+            .collect()
+    };
+    let project_experiments = if experiment_ids.is_empty() {
+        vec![]
+    } else {
+        experiments::Entity::find()
+            .filter(experiments::Column::Id.is_in(experiment_ids))
+            .order_by_asc(experiments::Column::PerformedAt)
+            .all(db)
+            .await?
+    };
+
+    Ok(ProjectRecords {
+        locations: project_locations,
+        samples: project_samples,
+        treatments: project_treatments,
+        experiments: project_experiments,
+    })
+}
+
+/// Contents of a project `BagIt` bag, ready to be streamed
+pub struct ProjectBag {
+    pub name: String,
+    pub payload: Vec<ArchiveEntry>,
+    pub bag_info: Vec<(String, String)>,
+}
+
+fn json_bytes(value: &impl serde::Serialize) -> Result<Vec<u8>, DbErr> {
+    serde_json::to_vec_pretty(value)
+        .map_err(|e| DbErr::Custom(format!("Failed to serialise bag metadata: {e}")))
+}
+
+/// Gather the payload and bag metadata for archiving a project.
+///
+/// The payload holds `project.json` (project, locations, samples and
+/// treatments), `datacite.json`, and for every experiment of the project the
+/// same raw and derived files as the experiment archive, below
+/// `experiments/<experiment name>/`.
+pub async fn build_project_bag(
+    db: &DatabaseConnection,
+    project_id: Uuid,
+    publisher: &str,
+) -> Result<ProjectBag, DbErr> {
+    let project = crate::projects::models::Entity::find_by_id(project_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Project not found".to_string()))?;
+    let records = load_project_records(db, project_id).await?;
+    let datacite =
+        crate::services::datacite_service::project_metadata(db, project_id, publisher).await?;
+
+    let project_json = serde_json::json!({
+        "format_version": 1,
+        "generated_at": Utc::now(),
+        "project": crate::projects::models::Project::from(project.clone()),
+        "locations": records.locations.iter().cloned().map(locations::Location::from).collect::<Vec<_>>(),
+        "samples": records.samples.iter().cloned().map(samples::Sample::from).collect::<Vec<_>>(),
+        "treatments": records.treatments.iter().cloned().map(treatments::Treatment::from).collect::<Vec<_>>(),
+        "experiments": records.experiments.iter().map(|experiment| serde_json::json!({
+            "id": experiment.id,
+            "name": experiment.name,
+            "path": format!("experiments/{}/", archive_path_component(&experiment.name)),
+            "doi": experiment.doi,
+        })).collect::<Vec<_>>(),
+    });
+    let mut payload = vec![
+        ArchiveEntry {
+            path: "project.json".to_string(),
+            source: ArchiveSource::Inline(json_bytes(&project_json)?),
+        },
+        ArchiveEntry {
+            path: "datacite.json".to_string(),
+            source: ArchiveSource::Inline(json_bytes(&datacite)?),
+        },
+    ];
+
+    for experiment in &records.experiments {
+        let directory = archive_path_component(&experiment.name);
+        let entries =
+            crate::experiments::services::build_experiment_archive_entries(experiment.id, db)
+                .await?;
+        payload.extend(entries.into_iter().map(|entry| ArchiveEntry {
+            path: format!("experiments/{directory}/{}", entry.path),
+            source: entry.source,
+        }));
+    }
+
+    let mut bag_info = vec![
+        ("Source-Organization".to_string(), publisher.to_string()),
+        ("External-Identifier".to_string(), project.id.to_string()),
+        ("Internal-Sender-Identifier".to_string(), project.name.clone()),
+        (
+            "Bag-Software-Agent".to_string(),
+            format!("spice-api {}", env!("CARGO_PKG_VERSION")),
+        ),
+    ];
+    if let Some(note) = &project.note {
+        // Tag values are single-line unless continuation lines are indented
+        bag_info.push((
+            "External-Description".to_string(),
+            note.lines().collect::<Vec<_>>().join("\n  "),
+        ));
+    }
+
+    Ok(ProjectBag {
+        name: archive_path_component(&project.name),
+        payload,
+        bag_info,
+    })
+}
@@ -0,0 +1,77 @@
+use chrono::{DateTime, Utc};
+use crudcrate::EntityToModels;
+use sea_orm::entity::prelude::*;
+use uuid::Uuid;
+
+/// Records that can be shared outside their project
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, ToSchema, Serialize, Deserialize,
+)]
+#[sea_orm(rs_type = "String", db_type = "Text")]
+#[serde(rename_all = "snake_case")]
+pub enum SharedResource {
+    #[sea_orm(string_value = "experiment")]
+    Experiment,
+    #[sea_orm(string_value = "sample")]
+    Sample,
+}
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, ToSchema, Serialize, Deserialize,
+)]
+#[sea_orm(rs_type = "String", db_type = "Text")]
+#[serde(rename_all = "snake_case")]
+pub enum GranteeType {
+    /// A Keycloak username
+    #[sea_orm(string_value = "user")]
+    User,
+    /// A Keycloak group, reaching the users who hold the role of that name
+    #[sea_orm(string_value = "group")]
+    Group,
+}
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, ToSchema, Serialize, Deserialize,
+)]
+#[sea_orm(rs_type = "String", db_type = "Text")]
+#[serde(rename_all = "snake_case")]
+pub enum SharePermission {
+    #[sea_orm(string_value = "read")]
+    Read,
+    /// Read and change, but not delete or share further
+    #[sea_orm(string_value = "write")]
+    Write,
+}
+
+/// Access to one experiment or sample given to a user or group outside its
+/// project
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, EntityToModels)]
+#[sea_orm(table_name = "share_grants")]
+#[crudcrate(api_struct = "ShareGrant")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    #[crudcrate(primary_key, update_model = false, create_model = false, on_create = Uuid::new_v4())]
+    pub id: Uuid,
+    #[crudcrate(filterable, enum_field, create_model = false, update_model = false)]
+    pub resource_type: SharedResource,
+    #[crudcrate(filterable, create_model = false, update_model = false)]
+    pub resource_id: Uuid,
+    #[crudcrate(filterable, enum_field)]
+    pub grantee_type: GranteeType,
+    /// Username or group name
+    #[sea_orm(column_type = "Text")]
+    #[crudcrate(sortable, filterable)]
+    pub grantee: String,
+    #[crudcrate(filterable, enum_field)]
+    pub permission: SharePermission,
+    #[sea_orm(column_type = "Text", nullable)]
+    #[crudcrate(create_model = false, update_model = false)]
+    pub granted_by: Option<String>,
+    #[crudcrate(update_model = false, create_model = false, on_create = chrono::Utc::now(), sortable)]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,127 @@
+use super::models::{ShareGrant, ShareGrantCreate, SharedResource};
+use crate::common::auth::Role;
+use crate::projects::sharing::{grant_share, revoke_share, share_grants};
+use axum::{
+    Extension,
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+};
+use axum_keycloak_auth::decode::KeycloakToken;
+use sea_orm::{ActiveEnum, DatabaseConnection, DbErr};
+use utoipa::OpenApi;
+use uuid::Uuid;
+
+#[derive(OpenApi)]
+#[openapi(paths(get_shares, post_share, delete_share))]
+struct SharesApi;
+
+/// Documentation of a resource's sharing routes. Each shared resource
+/// routes the same handlers, so their operations are named after it.
+pub fn shares_api(resource: SharedResource) -> utoipa::openapi::OpenApi {
+    let mut api = SharesApi::openapi();
+    let name = resource.to_value();
+    for item in api.paths.paths.values_mut() {
+        for operation in [&mut item.get, &mut item.post, &mut item.delete]
+            .into_iter()
+            .flatten()
+        {
+            operation.operation_id = operation
+                .operation_id
+                .take()
+                .map(|id| format!("{id}_of_{name}"));
+        }
+    }
+    api
+}
+
+fn share_error(e: DbErr) -> (StatusCode, String) {
+    match e {
+        DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+        DbErr::Custom(message) => (StatusCode::UNPROCESSABLE_ENTITY, message),
+        e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+/// Users and groups an experiment or sample is shared with
+#[utoipa::path(
+    get,
+    path = "/{id}/shares",
+    params(
+        ("id" = Uuid, Path, description = "Experiment or sample ID")
+    ),
+    responses(
+        (status = 200, description = "Grants of the record, by grantee", body = Vec<ShareGrant>),
+        (status = 404, description = "Record not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "sharing",
+    summary = "List share grants",
+    description = "List the users and groups outside its project the record is shared with, and whether they may read or also change it"
+)]
+pub async fn get_shares(
+    State((db, resource)): State<(DatabaseConnection, SharedResource)>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<ShareGrant>>, (StatusCode, String)> {
+    share_grants(&db, resource, id)
+        .await
+        .map(Json)
+        .map_err(share_error)
+}
+
+/// Share an experiment or sample with a user or group
+#[utoipa::path(
+    post,
+    path = "/{id}/shares",
+    params(
+        ("id" = Uuid, Path, description = "Experiment or sample ID")
+    ),
+    request_body = ShareGrantCreate,
+    responses(
+        (status = 201, description = "The grant", body = ShareGrant),
+        (status = 404, description = "Record not found"),
+        (status = 422, description = "Blank grantee"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "sharing",
+    summary = "Share a record",
+    description = "Give a Keycloak user, or the users holding a Keycloak group's role, access to this one record without making them project members. A read grant lets them read it; a write grant also lets them change it, but not delete or share it. Granting again replaces the permission. The authenticated user is kept as granted_by"
+)]
+pub async fn post_share(
+    State((db, resource)): State<(DatabaseConnection, SharedResource)>,
+    Path(id): Path<Uuid>,
+    token: Option<Extension<KeycloakToken<Role>>>,
+    Json(grant): Json<ShareGrantCreate>,
+) -> Result<(StatusCode, Json<ShareGrant>), (StatusCode, String)> {
+    let granted_by = token.map(|Extension(token)| token.extra.profile.preferred_username);
+    grant_share(&db, resource, id, grant, granted_by)
+        .await
+        .map(|grant| (StatusCode::CREATED, Json(grant)))
+        .map_err(share_error)
+}
+
+/// Stop sharing an experiment or sample with a user or group
+#[utoipa::path(
+    delete,
+    path = "/{id}/shares/{grant_id}",
+    params(
+        ("id" = Uuid, Path, description = "Experiment or sample ID"),
+        ("grant_id" = Uuid, Path, description = "Share grant ID")
+    ),
+    responses(
+        (status = 204, description = "Grant withdrawn"),
+        (status = 404, description = "Grant not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "sharing",
+    summary = "Withdraw a share grant"
+)]
+pub async fn delete_share(
+    State((db, resource)): State<(DatabaseConnection, SharedResource)>,
+    Path((id, grant_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    revoke_share(&db, resource, id, grant_id)
+        .await
+        .map(|()| StatusCode::NO_CONTENT)
+        .map_err(share_error)
+}
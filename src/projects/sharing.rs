@@ -0,0 +1,278 @@
+//! Ownership and sharing of experiments and samples.
+//!
+//! Experiments and samples keep the user who created them, who can go on
+//! working with them like a member of their project. A member or the creator
+//! can also share one record with a user or a group outside the project, to
+//! read or to change, so that a collaborator can work on one experiment
+//! without joining the project. Access to a shared experiment reaches its
+//! assets. Groups are matched against the Keycloak roles of the user, which
+//! is how Keycloak hands group membership to tokens.
+
+use super::access::ScopedResource;
+use super::shares::models::{
+    self as shares, GranteeType, ShareGrant, ShareGrantCreate, SharePermission, SharedResource,
+};
+use crate::common::auth::Role;
+use crate::{
+    assets::models as assets, experiments::models as experiments, samples::models as samples,
+};
+use axum_keycloak_auth::decode::KeycloakToken;
+use chrono::Utc;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, Condition, DatabaseConnection, DbErr,
+    EntityTrait, IntoActiveModel, QueryFilter, QueryOrder, QuerySelect, sea_query::Query,
+};
+use uuid::Uuid;
+
+/// A user who is not an administrator, and the groups they are in
+#[derive(Clone, Copy, Debug)]
+pub struct Requester<'a> {
+    pub username: &'a str,
+    pub groups: &'a [String],
+}
+
+/// Groups grants reach a user in: the names of their Keycloak roles
+pub fn token_groups(token: &KeycloakToken<Role>) -> Vec<String> {
+    token.roles.iter().map(|role| role.role().name()).collect()
+}
+
+/// How a user outside its project reaches a record
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecordAccess {
+    Creator,
+    Shared(SharePermission),
+}
+
+impl ScopedResource {
+    /// The kind of record grants of the resource are given on
+    pub fn shared(self) -> Option<SharedResource> {
+        match self {
+            Self::Experiments => Some(SharedResource::Experiment),
+            Self::Samples => Some(SharedResource::Sample),
+            Self::Projects
+            | Self::Locations
+            | Self::Treatments
+            | Self::Dilutions
+            | Self::Assets => None,
+        }
+    }
+}
+
+/// Creator of a record, or `None` when there is no such record
+async fn record_creator(
+    db: &DatabaseConnection,
+    resource: SharedResource,
+    id: Uuid,
+) -> Result<Option<Option<String>>, DbErr> {
+    Ok(match resource {
+        SharedResource::Experiment => experiments::Entity::find_by_id(id)
+            .one(db)
+            .await?
+            .map(|experiment| experiment.created_by),
+        SharedResource::Sample => samples::Entity::find_by_id(id)
+            .one(db)
+            .await?
+            .map(|sample| sample.created_by),
+    })
+}
+
+fn granted_to(user: &Requester<'_>) -> Condition {
+    Condition::any()
+        .add(
+            Condition::all()
+                .add(shares::Column::GranteeType.eq(GranteeType::User))
+                .add(shares::Column::Grantee.eq(user.username)),
+        )
+        .add(
+            Condition::all()
+                .add(shares::Column::GranteeType.eq(GranteeType::Group))
+                .add(shares::Column::Grantee.is_in(user.groups.to_vec())),
+        )
+}
+
+async fn shared_record_access(
+    db: &DatabaseConnection,
+    resource: SharedResource,
+    id: Uuid,
+    user: &Requester<'_>,
+) -> Result<Option<RecordAccess>, DbErr> {
+    if record_creator(db, resource, id).await?.flatten().as_deref() == Some(user.username) {
+        return Ok(Some(RecordAccess::Creator));
+    }
+    let permissions: Vec<SharePermission> = shares::Entity::find()
+        .select_only()
+        .column(shares::Column::Permission)
+        .filter(shares::Column::ResourceType.eq(resource))
+        .filter(shares::Column::ResourceId.eq(id))
+        .filter(granted_to(user))
+        .into_tuple()
+        .all(db)
+        .await?;
+    Ok(if permissions.contains(&SharePermission::Write) {
+        Some(RecordAccess::Shared(SharePermission::Write))
+    } else if permissions.is_empty() {
+        None
+    } else {
+        Some(RecordAccess::Shared(SharePermission::Read))
+    })
+}
+
+/// How the user reaches a record outside the projects they are members of:
+/// experiments and samples they created or were given, and the assets of
+/// those experiments
+pub async fn record_access(
+    db: &DatabaseConnection,
+    resource: ScopedResource,
+    id: Uuid,
+    user: &Requester<'_>,
+) -> Result<Option<RecordAccess>, DbErr> {
+    if let Some(shared) = resource.shared() {
+        return shared_record_access(db, shared, id, user).await;
+    }
+    if resource == ScopedResource::Assets
+        && let Some(Some(experiment_id)) = assets::Entity::find_by_id(id)
+            .one(db)
+            .await?
+            .map(|asset| asset.experiment_id)
+    {
+        return shared_record_access(db, SharedResource::Experiment, experiment_id, user).await;
+    }
+    Ok(None)
+}
+
+/// Records of the resource the user created or was given, as a condition
+/// on its table
+fn accessible_records(resource: SharedResource, user: &Requester<'_>) -> Condition {
+    let granted = Query::select()
+        .column(shares::Column::ResourceId)
+        .from(shares::Entity)
+        .and_where(shares::Column::ResourceType.eq(resource))
+        .cond_where(granted_to(user))
+        .to_owned();
+    match resource {
+        SharedResource::Experiment => Condition::any()
+            .add(experiments::Column::CreatedBy.eq(user.username))
+            .add(experiments::Column::Id.in_subquery(granted)),
+        SharedResource::Sample => Condition::any()
+            .add(samples::Column::CreatedBy.eq(user.username))
+            .add(samples::Column::Id.in_subquery(granted)),
+    }
+}
+
+/// Records the user reaches outside their projects, as a condition on the
+/// resource's table, or `None` when there are none
+pub fn accessible(resource: ScopedResource, user: &Requester<'_>) -> Option<Condition> {
+    if let Some(shared) = resource.shared() {
+        return Some(accessible_records(shared, user));
+    }
+    if resource == ScopedResource::Assets {
+        let experiment_ids = Query::select()
+            .column(experiments::Column::Id)
+            .from(experiments::Entity)
+            .cond_where(accessible_records(SharedResource::Experiment, user))
+            .to_owned();
+        return Some(
+            Condition::all().add(assets::Column::ExperimentId.in_subquery(experiment_ids)),
+        );
+    }
+    None
+}
+
+async fn find_record(
+    db: &DatabaseConnection,
+    resource: SharedResource,
+    id: Uuid,
+) -> Result<(), DbErr> {
+    match record_creator(db, resource, id).await? {
+        Some(_) => Ok(()),
+        None => Err(DbErr::RecordNotFound(match resource {
+            SharedResource::Experiment => "Experiment not found".to_string(),
+            SharedResource::Sample => "Sample not found".to_string(),
+        })),
+    }
+}
+
+/// Grants of a record, by grantee
+pub async fn share_grants(
+    db: &DatabaseConnection,
+    resource: SharedResource,
+    id: Uuid,
+) -> Result<Vec<ShareGrant>, DbErr> {
+    find_record(db, resource, id).await?;
+    Ok(shares::Entity::find()
+        .filter(shares::Column::ResourceType.eq(resource))
+        .filter(shares::Column::ResourceId.eq(id))
+        .order_by_asc(shares::Column::GranteeType)
+        .order_by_asc(shares::Column::Grantee)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(ShareGrant::from)
+        .collect())
+}
+
+/// Share a record with a user or group, replacing the permission they had.
+/// Invalid input is returned as `DbErr::Custom`.
+pub async fn grant_share(
+    db: &DatabaseConnection,
+    resource: SharedResource,
+    id: Uuid,
+    input: ShareGrantCreate,
+    granted_by: Option<String>,
+) -> Result<ShareGrant, DbErr> {
+    let grantee = input.grantee.trim().to_string();
+    if grantee.is_empty() {
+        return Err(DbErr::Custom("grantee must not be blank".to_string()));
+    }
+    find_record(db, resource, id).await?;
+
+    let existing = shares::Entity::find()
+        .filter(shares::Column::ResourceType.eq(resource))
+        .filter(shares::Column::ResourceId.eq(id))
+        .filter(shares::Column::GranteeType.eq(input.grantee_type))
+        .filter(shares::Column::Grantee.eq(grantee.as_str()))
+        .one(db)
+        .await?;
+    let grant = match existing {
+        Some(existing) => {
+            let mut active = existing.into_active_model();
+            active.permission = Set(input.permission);
+            active.granted_by = Set(granted_by);
+            active.update(db).await?
+        }
+        None => {
+            shares::ActiveModel {
+                id: Set(Uuid::new_v4()),
+                resource_type: Set(resource),
+                resource_id: Set(id),
+                grantee_type: Set(input.grantee_type),
+                grantee: Set(grantee),
+                permission: Set(input.permission),
+                granted_by: Set(granted_by),
+                created_at: Set(Utc::now()),
+            }
+            .insert(db)
+            .await?
+        }
+    };
+    Ok(grant.into())
+}
+
+/// Withdraw a grant of a record
+pub async fn revoke_share(
+    db: &DatabaseConnection,
+    resource: SharedResource,
+    id: Uuid,
+    grant_id: Uuid,
+) -> Result<(), DbErr> {
+    let result = shares::Entity::delete_many()
+        .filter(shares::Column::Id.eq(grant_id))
+        .filter(shares::Column::ResourceType.eq(resource))
+        .filter(shares::Column::ResourceId.eq(id))
+        .exec(db)
+        .await?;
+    if result.rows_affected == 0 {
+        return Err(DbErr::RecordNotFound("Share grant not found".to_string()));
+    }
+    Ok(())
+}
@@ -1,6 +1,9 @@
 use core::panic;
 
-use crate::config::test_helpers::setup_test_app;
+use crate::common::keycloak::test_realm;
+use crate::config::test_helpers::{
+    create, send_json, send_json_as, setup_authenticated_test_app, setup_test_app,
+};
 use axum::body::{Body, to_bytes};
 use axum::http::{Request, StatusCode};
 use serde_json::{Value, json};
@@ -1028,3 +1031,1303 @@ async fn test_project_error_handling_with_helpers() {
     // Test deletion of non-existent project using helper
     let _delete_success = test_project_deletion(&app, &fake_project_id).await;
 }
+
+#[tokio::test]
+async fn test_project_datacite_metadata() {
+    let app = setup_test_app().await;
+    let project_name = format!("DataCite Project {}", uuid::Uuid::new_v4());
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/projects")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({"name": project_name, "note": "Arctic aerosol campaign"}).to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (status, project) = extract_response_body(response).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let project_id = project["id"].as_str().unwrap();
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/locations")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({
+                        "name": format!("DataCite Station {}", uuid::Uuid::new_v4()),
+                        "project_id": project_id
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (status, location) = extract_response_body(response).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/samples")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({
+                        "name": "DataCite bulk sample",
+                        "type": "bulk",
+                        "start_time": "2025-03-01T10:00:00Z",
+                        "latitude": "78.2232",
+                        "longitude": "15.6267",
+                        "location_id": location["id"]
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri(format!("/api/projects/{project_id}/datacite"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (status, metadata) = extract_response_body(response).await;
+    assert_eq!(status, StatusCode::OK, "DataCite request failed: {metadata}");
+
+    assert_eq!(metadata["titles"][0]["title"], project_name);
+    assert_eq!(metadata["descriptions"][0]["description"], "Arctic aerosol campaign");
+    // Without experiments there is no person to credit, so the publisher is the creator
+    assert_eq!(metadata["creators"][0]["nameType"], "Organizational");
+    assert_eq!(
+        metadata["geoLocations"][0]["geoLocationPoint"],
+        json!({"pointLatitude": "78.2232", "pointLongitude": "15.6267"})
+    );
+    assert!(
+        metadata["dates"]
+            .as_array()
+            .unwrap()
+            .contains(&json!({"date": "2025-03-01", "dateType": "Collected"}))
+    );
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri(format!("/api/projects/{}/datacite", uuid::Uuid::new_v4()))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+/// Create a project with one location holding a single geolocated bulk sample.
+/// Returns the project ID, project name and sample ID.
+async fn create_project_with_sample(app: &axum::Router) -> (String, String, String) {
+    let project_name = format!("Archived Project {}", uuid::Uuid::new_v4());
+    let project_id = create(
+        app,
+        "/api/projects",
+        &json!({"name": project_name, "note": "Arctic aerosol campaign"}),
+    )
+    .await;
+    let location_id = create(
+        app,
+        "/api/locations",
+        &json!({
+            "name": format!("Archive Station {}", uuid::Uuid::new_v4()),
+            "project_id": project_id
+        }),
+    )
+    .await;
+    let sample_id = create(
+        app,
+        "/api/samples",
+        &json!({
+            "name": "Archived bulk sample",
+            "type": "bulk",
+            "start_time": "2025-03-01T10:00:00Z",
+            "latitude": "78.2232",
+            "longitude": "15.6267",
+            "location_id": location_id
+        }),
+    )
+    .await;
+    (project_id, project_name, sample_id)
+}
+
+#[tokio::test]
+async fn test_project_bagit_export() {
+    use sha2::{Digest, Sha256};
+    use std::io::Read;
+
+    let app = setup_test_app().await;
+    let (project_id, _, sample_id) = create_project_with_sample(&app).await;
+
+    let treatment_id = create(
+        &app,
+        "/api/treatments",
+        &json!({"name": "heat", "sample_id": sample_id}),
+    )
+    .await;
+
+    let experiment_name = format!("Bagged Experiment {}", uuid::Uuid::new_v4());
+    let (status, experiment) = send_json(
+        &app,
+        "POST",
+        "/api/experiments",
+        Some(&json!({
+            "name": experiment_name,
+            "username": "archivist@example.com",
+            "is_calibration": false,
+            "regions": [{
+                "name": "Heat region",
+                "treatment_id": treatment_id,
+                "tray_id": 1,
+                "col_min": 0,
+                "col_max": 3,
+                "row_min": 0,
+                "row_max": 3,
+                "dilution_factor": 1,
+                "is_background_key": false
+            }]
+        })),
+    )
+    .await;
+    assert_eq!(
+        status,
+        StatusCode::CREATED,
+        "Failed to create experiment: {experiment}"
+    );
+
+    let uri = format!("/api/projects/{project_id}/bagit");
+    let request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes)).unwrap();
+    let bag_root = archive
+        .file_names()
+        .find_map(|name| name.strip_suffix("/bagit.txt"))
+        .expect("bagit.txt should be present")
+        .to_string();
+    let mut read = |name: &str| -> Vec<u8> {
+        let mut file = archive
+            .by_name(&format!("{bag_root}/{name}"))
+            .unwrap_or_else(|_| panic!("{name} should be in the bag"));
+        let mut content = Vec::new();
+        file.read_to_end(&mut content).unwrap();
+        content
+    };
+
+    assert_eq!(
+        String::from_utf8(read("bagit.txt")).unwrap(),
+        "BagIt-Version: 1.0\nTag-File-Character-Encoding: UTF-8\n"
+    );
+
+    // Every payload file is listed with a matching SHA-256 and counted in the Payload-Oxum
+    let manifest = String::from_utf8(read("manifest-sha256.txt")).unwrap();
+    let mut octets = 0;
+    let mut paths = Vec::new();
+    for line in manifest.lines() {
+        let (checksum, path) = line.split_once("  ").unwrap();
+        let content = read(path);
+        assert_eq!(
+            checksum,
+            format!("{:x}", Sha256::digest(&content)),
+            "{path}"
+        );
+        octets += content.len();
+        paths.push(path.to_string());
+    }
+    let experiment_dir = format!("data/experiments/{}", experiment_name.replace(' ', "_"));
+    assert!(paths.contains(&"data/project.json".to_string()));
+    assert!(paths.contains(&"data/datacite.json".to_string()));
+    assert!(paths.contains(&format!("{experiment_dir}/metadata.json")));
+    assert!(paths.contains(&format!("{experiment_dir}/derived/temperatures.csv")));
+
+    let bag_info = String::from_utf8(read("bag-info.txt")).unwrap();
+    assert!(bag_info.contains(&format!("Payload-Oxum: {octets}.{}", paths.len())));
+    assert!(bag_info.contains(&format!("External-Identifier: {project_id}")));
+    assert!(bag_info.contains("Source-Organization: SPICE Test Publisher"));
+
+    let tag_manifest = String::from_utf8(read("tagmanifest-sha256.txt")).unwrap();
+    for tag_file in ["bagit.txt", "bag-info.txt", "manifest-sha256.txt"] {
+        let checksum = format!("{:x}", Sha256::digest(read(tag_file)));
+        assert!(tag_manifest.contains(&format!("{checksum}  {tag_file}")));
+    }
+
+    let project_json: Value = serde_json::from_slice(&read("data/project.json")).unwrap();
+    assert_eq!(project_json["experiments"][0]["name"], experiment_name);
+    assert_eq!(project_json["samples"][0]["id"], sample_id);
+
+    let uri = format!("/api/projects/{}/bagit", uuid::Uuid::new_v4());
+    let (status, _) = send_json(&app, "GET", &uri, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+#[allow(clippy::too_many_lines)]
+async fn test_project_scoped_access() {
+    use crate::config::Config;
+    use crate::config::test_helpers::setup_test_db;
+    use crate::projects::access::{ScopedResource, authorize, list_scope};
+    use crate::projects::sharing::Requester;
+    use axum::http::Method;
+    use sea_orm::{EntityTrait, QueryFilter};
+
+    let db = setup_test_db().await;
+    let mut config = Config::for_tests();
+    config.keycloak_url = String::new();
+    let app = crate::routes::build_router(&db, &config);
+
+    let (project_id, _, sample_id) = create_project_with_sample(&app).await;
+    let (other_project_id, _, other_sample_id) = create_project_with_sample(&app).await;
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri(format!("/api/projects/{project_id}/members"))
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!(["bob", " alice ", "alice", ""]).to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (status, members) = extract_response_body(response).await;
+    assert_eq!(status, StatusCode::OK, "{members}");
+    let usernames: Vec<&str> = members
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|member| member["username"].as_str().unwrap())
+        .collect();
+    assert_eq!(usernames, vec!["alice", "bob"]);
+
+    let mut experiment_ids = Vec::new();
+    for (name, project) in [
+        ("Member run", &project_id),
+        ("Other run", &other_project_id),
+    ] {
+        let (status, experiment) = send_json(
+            &app,
+            "POST",
+            "/api/experiments",
+            Some(&json!({"name": name, "is_calibration": false, "project_id": project})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED, "{experiment}");
+        experiment_ids.push(experiment["id"].as_str().unwrap().to_string());
+    }
+
+    let check = |resource, method, path: String, body: Option<Value>| {
+        let db = db.clone();
+        async move {
+            authorize(
+                &db,
+                &Requester {
+                    username: "alice",
+                    groups: &[],
+                },
+                resource,
+                &method,
+                &path,
+                body.as_ref(),
+            )
+            .await
+        }
+    };
+
+    // Records of the member's project
+    for (resource, id) in [
+        (ScopedResource::Samples, &sample_id),
+        (ScopedResource::Experiments, &experiment_ids[0]),
+    ] {
+        assert_eq!(
+            check(resource, Method::GET, format!("/{id}"), None).await,
+            Ok(())
+        );
+        assert_eq!(
+            check(resource, Method::DELETE, format!("/{id}"), None).await,
+            Ok(())
+        );
+    }
+    assert_eq!(
+        check(
+            ScopedResource::Experiments,
+            Method::GET,
+            format!("/{}/results", experiment_ids[0]),
+            None
+        )
+        .await,
+        Ok(())
+    );
+
+    // Records of another project
+    for (resource, id) in [
+        (ScopedResource::Samples, &other_sample_id),
+        (ScopedResource::Experiments, &experiment_ids[1]),
+    ] {
+        let (status, message) = check(resource, Method::GET, format!("/{id}"), None)
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert!(message.contains("not in a project you are a member of"));
+    }
+
+    // Lists are narrowed to the member's records, searches included
+    let alice = Requester {
+        username: "alice",
+        groups: &[],
+    };
+    assert_eq!(
+        check(ScopedResource::Samples, Method::GET, "/".to_string(), None).await,
+        Ok(())
+    );
+    let samples = crate::samples::models::Entity::find()
+        .filter(list_scope(ScopedResource::Samples, &alice).0)
+        .all(&db)
+        .await
+        .unwrap();
+    assert_eq!(samples.len(), 1);
+    assert_eq!(samples[0].id.to_string(), sample_id);
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/samples?filter=%7B%22q%22%3A%22bulk%22%7D")
+                .extension(list_scope(ScopedResource::Samples, &alice))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.headers()["x-total-count"], "0");
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/samples")
+                .extension(list_scope(ScopedResource::Samples, &alice))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.headers()["x-total-count"], "1");
+    let (status, samples) = extract_response_body(response).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(samples.as_array().unwrap().len(), 1);
+    assert_eq!(samples[0]["id"], sample_id);
+
+    // Creates and updates must stay within the member's projects
+    assert_eq!(
+        check(
+            ScopedResource::Experiments,
+            Method::POST,
+            "/".to_string(),
+            Some(json!({"name": "New run", "project_id": project_id}))
+        )
+        .await,
+        Ok(())
+    );
+    for body in [
+        json!({"name": "New run"}),
+        json!({"name": "New run", "project_id": other_project_id}),
+    ] {
+        let (status, _) = check(
+            ScopedResource::Experiments,
+            Method::POST,
+            "/".to_string(),
+            Some(body),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+    assert_eq!(
+        check(
+            ScopedResource::Experiments,
+            Method::PATCH,
+            format!("/{}", experiment_ids[0]),
+            Some(json!({"remarks": "Repeated"}))
+        )
+        .await,
+        Ok(())
+    );
+    let (status, _) = check(
+        ScopedResource::Experiments,
+        Method::PATCH,
+        format!("/{}", experiment_ids[0]),
+        Some(json!({"project_id": other_project_id})),
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Collection-wide routes stay with administrators
+    let (status, _) = check(
+        ScopedResource::Samples,
+        Method::GET,
+        "/storage/freezers".to_string(),
+        None,
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(
+        check(
+            ScopedResource::Samples,
+            Method::GET,
+            "/type-rules".to_string(),
+            None
+        )
+        .await,
+        Ok(())
+    );
+
+    // A user of no project sees nothing
+    let mallory = Requester {
+        username: "mallory",
+        groups: &[],
+    };
+    let experiments = crate::experiments::models::Entity::find()
+        .filter(list_scope(ScopedResource::Experiments, &mallory).0)
+        .all(&db)
+        .await
+        .unwrap();
+    assert!(experiments.is_empty());
+}
+
+#[tokio::test]
+#[allow(clippy::too_many_lines)]
+async fn test_project_archiving() {
+    let app = setup_test_app().await;
+    let (project_id, project_name, sample_id) = create_project_with_sample(&app).await;
+    let (status, experiment) = send_json(
+        &app,
+        "POST",
+        "/api/experiments",
+        Some(&json!({"name": "Archived run", "is_calibration": false, "project_id": project_id})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{experiment}");
+
+    let (status, project) = send_json(
+        &app,
+        "POST",
+        &format!("/api/projects/{project_id}/archive"),
+        Some(&json!({})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{project}");
+    assert!(project["archived_at"].is_string());
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        &format!("/api/projects/{project_id}/archive"),
+        Some(&json!({})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    // Records stay readable but cannot change
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri(format!("/api/samples/{sample_id}"))
+                .header("content-type", "application/json")
+                .body(Body::from(json!({"remarks": "Too late"}).to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    let message = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    assert!(String::from_utf8_lossy(&message).contains(&project_name));
+    let (status, _) = send_json(
+        &app,
+        "GET",
+        &format!("/api/samples/{sample_id}"),
+        Some(&json!({})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send_json(
+        &app,
+        "DELETE",
+        "/api/samples/batch",
+        Some(&json!([sample_id])),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (status, _) = send_json(
+        &app,
+        "PUT",
+        &format!("/api/experiments/{}", experiment["id"].as_str().unwrap()),
+        Some(&json!({"name": "Renamed run"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        "/api/experiments",
+        Some(&json!({"name": "New run", "is_calibration": false, "project_id": project_id})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (status, _) = send_json(
+        &app,
+        "PUT",
+        &format!("/api/projects/{project_id}"),
+        Some(&json!({"note": "Edited"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (status, _) = send_json(
+        &app,
+        "DELETE",
+        &format!("/api/projects/{project_id}"),
+        Some(&json!({})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, project) = send_json(
+        &app,
+        "POST",
+        &format!("/api/projects/{project_id}/unarchive"),
+        Some(&json!({})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{project}");
+    assert!(project["archived_at"].is_null());
+    let (status, sample) = send_json(
+        &app,
+        "PUT",
+        &format!("/api/samples/{sample_id}"),
+        Some(&json!({"remarks": "Edited after unarchiving"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{sample}");
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        &format!("/api/projects/{project_id}/unarchive"),
+        Some(&json!({})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn test_project_activity() {
+    let app = setup_test_app().await;
+    let (project_id, project_name, sample_id) = create_project_with_sample(&app).await;
+    let (status, experiment) = send_json(
+        &app,
+        "POST",
+        "/api/experiments",
+        Some(&json!({"name": "Activity run", "is_calibration": false, "project_id": project_id})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{experiment}");
+
+    // Edits are told apart from creation by their time
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+    let (status, _) = send_json(
+        &app,
+        "PUT",
+        &format!("/api/samples/{sample_id}"),
+        Some(&json!({"remarks": "Filter torn"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        &format!("/api/projects/{project_id}/archive"),
+        Some(&json!({})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let uri = format!("/api/projects/{project_id}/activity");
+    let response = app
+        .clone()
+        .oneshot(Request::builder().uri(&uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let (status, events) = extract_response_body(response).await;
+    assert_eq!(status, StatusCode::OK, "{events}");
+    let events: Vec<(&str, &str)> = events
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|event| {
+            (
+                event["resource"].as_str().unwrap(),
+                event["kind"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(events[0], ("project", "archived"));
+    assert_eq!(events[1], ("sample", "edited"));
+    assert_eq!(events.len(), 6, "{events:?}");
+    for resource in ["project", "location", "sample", "experiment"] {
+        assert!(events.contains(&(resource, "created")), "{events:?}");
+    }
+    assert!(!events.contains(&("project", "edited")));
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("{uri}?limit=1"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (_, events) = extract_response_body(response).await;
+    assert_eq!(events.as_array().unwrap().len(), 1);
+    assert_eq!(events[0]["name"], project_name.as_str());
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("{uri}?since=2100-01-01T00:00:00Z"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (status, events) = extract_response_body(response).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(events.as_array().unwrap().is_empty());
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/projects/{}/activity", uuid::Uuid::new_v4()))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+#[allow(clippy::too_many_lines)]
+async fn test_sharing() {
+    use crate::common::auth::as_user;
+    use crate::config::Config;
+    use crate::config::test_helpers::setup_test_db;
+    use crate::projects::access::{ScopedResource, authorize, list_scope};
+    use crate::projects::sharing::Requester;
+    use axum::http::Method;
+    use sea_orm::{EntityTrait, QueryFilter};
+
+    let db = setup_test_db().await;
+    let mut config = Config::for_tests();
+    config.keycloak_url = String::new();
+    let app = crate::routes::build_router(&db, &config);
+
+    let (project_id, _, sample_id) = create_project_with_sample(&app).await;
+    let (other_project_id, _, _) = create_project_with_sample(&app).await;
+    let (status, experiment) = send_json(&app, "POST", "/api/experiments", Some(&json!({"name": format!("Shared run {}", uuid::Uuid::new_v4()), "is_calibration": false, "project_id": project_id})))
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{experiment}");
+    let experiment_id = experiment["id"].as_str().unwrap().to_string();
+    assert!(experiment["created_by"].is_null());
+
+    let groups = ["ice-lab".to_string()];
+    let bob = Requester {
+        username: "bob",
+        groups: &[],
+    };
+    let carol = Requester {
+        username: "carol",
+        groups: &groups,
+    };
+    let check = async |user: &Requester<'_>,
+                       resource,
+                       method: Method,
+                       path: String,
+                       body: Option<Value>| {
+        authorize(&db, user, resource, &method, &path, body.as_ref())
+            .await
+            .map_err(|(status, _)| status)
+    };
+    let experiment_path = format!("/{experiment_id}");
+
+    // Outside the project, nothing is reachable until it is shared
+    assert_eq!(
+        check(
+            &bob,
+            ScopedResource::Experiments,
+            Method::GET,
+            experiment_path.clone(),
+            None
+        )
+        .await,
+        Err(StatusCode::FORBIDDEN)
+    );
+
+    let shares_uri = format!("/api/experiments/{experiment_id}/shares");
+    let (status, grant) = send_json(
+        &app,
+        "POST",
+        &shares_uri,
+        Some(&json!({"grantee_type": "user", "grantee": " bob ", "permission": "read"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{grant}");
+    assert_eq!(grant["grantee"], "bob");
+    assert_eq!(grant["resource_type"], "experiment");
+
+    assert_eq!(
+        check(
+            &bob,
+            ScopedResource::Experiments,
+            Method::GET,
+            experiment_path.clone(),
+            None
+        )
+        .await,
+        Ok(())
+    );
+    assert_eq!(
+        check(
+            &bob,
+            ScopedResource::Experiments,
+            Method::PATCH,
+            experiment_path.clone(),
+            Some(json!({"remarks": "Mine now"}))
+        )
+        .await,
+        Err(StatusCode::FORBIDDEN)
+    );
+    let experiments = crate::experiments::models::Entity::find()
+        .filter(list_scope(ScopedResource::Experiments, &bob).0)
+        .all(&db)
+        .await
+        .unwrap();
+    assert!(
+        experiments
+            .iter()
+            .any(|experiment| experiment.id.to_string() == experiment_id)
+    );
+
+    // Granting again replaces the permission
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        &shares_uri,
+        Some(&json!({"grantee_type": "user", "grantee": "bob", "permission": "write"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, grants) = send_json(&app, "GET", &shares_uri, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(grants.as_array().unwrap().len(), 1);
+    assert_eq!(grants[0]["permission"], "write");
+    let grant_id = grants[0]["id"].as_str().unwrap().to_string();
+
+    for body in [
+        json!({"remarks": "Repeated"}),
+        json!({"remarks": "Repeated", "project_id": project_id}),
+    ] {
+        assert_eq!(
+            check(
+                &bob,
+                ScopedResource::Experiments,
+                Method::PATCH,
+                experiment_path.clone(),
+                Some(body)
+            )
+            .await,
+            Ok(())
+        );
+    }
+    // Writers cannot move, delete or share the record further
+    for (method, path, body) in [
+        (
+            Method::PATCH,
+            experiment_path.clone(),
+            Some(json!({"project_id": other_project_id})),
+        ),
+        (Method::DELETE, experiment_path.clone(), None),
+        (Method::GET, format!("{experiment_path}/shares"), None),
+    ] {
+        assert_eq!(
+            check(&bob, ScopedResource::Experiments, method, path, body).await,
+            Err(StatusCode::FORBIDDEN)
+        );
+    }
+
+    // Groups reach users holding the role of that name
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        &format!("/api/samples/{sample_id}/shares"),
+        Some(&json!({"grantee_type": "group", "grantee": "ice-lab", "permission": "read"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(
+        check(
+            &carol,
+            ScopedResource::Samples,
+            Method::GET,
+            format!("/{sample_id}"),
+            None
+        )
+        .await,
+        Ok(())
+    );
+    assert_eq!(
+        check(
+            &bob,
+            ScopedResource::Samples,
+            Method::GET,
+            format!("/{sample_id}"),
+            None
+        )
+        .await,
+        Err(StatusCode::FORBIDDEN)
+    );
+
+    // Withdrawing a grant takes the access away
+    let (status, _) = send_json(
+        &app,
+        "DELETE",
+        &format!("{shares_uri}/{grant_id}"),
+        Some(&json!({})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = send_json(
+        &app,
+        "DELETE",
+        &format!("{shares_uri}/{grant_id}"),
+        Some(&json!({})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(
+        check(
+            &bob,
+            ScopedResource::Experiments,
+            Method::GET,
+            experiment_path,
+            None
+        )
+        .await,
+        Err(StatusCode::FORBIDDEN)
+    );
+
+    // Creators keep working with what they created outside any project
+    let response = as_user(
+        "dave".to_string(),
+        app.clone().oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/experiments")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({"name": format!("Own run {}", uuid::Uuid::new_v4()), "is_calibration": false})
+                        .to_string(),
+                ))
+                .unwrap(),
+        ),
+    )
+    .await
+    .unwrap();
+    let (status, own) = extract_response_body(response).await;
+    assert_eq!(status, StatusCode::CREATED, "{own}");
+    assert_eq!(own["created_by"], "dave");
+    let dave = Requester {
+        username: "dave",
+        groups: &[],
+    };
+    for method in [Method::GET, Method::DELETE] {
+        assert_eq!(
+            check(
+                &dave,
+                ScopedResource::Experiments,
+                method,
+                format!("/{}", own["id"].as_str().unwrap()),
+                None
+            )
+            .await,
+            Ok(())
+        );
+    }
+
+    for (body, expected) in [
+        (
+            json!({"grantee_type": "user", "grantee": " ", "permission": "read"}),
+            StatusCode::UNPROCESSABLE_ENTITY,
+        ),
+        (
+            json!({"grantee_type": "team", "grantee": "bob", "permission": "read"}),
+            StatusCode::UNPROCESSABLE_ENTITY,
+        ),
+    ] {
+        let (status, _) = send_json(&app, "POST", &shares_uri, Some(&body)).await;
+        assert_eq!(status, expected, "{body}");
+    }
+    let (status, _) = send_json(
+        &app,
+        "GET",
+        &format!("/api/experiments/{}/shares", uuid::Uuid::new_v4()),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_projects_are_served_from_the_cache_while_unchanged() {
+    use crate::config::{Config, test_helpers::setup_test_db};
+    use crate::projects::models::{Column, Entity as Projects};
+    use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, sea_query::Expr};
+
+    let db = setup_test_db().await;
+    let mut config = Config::for_tests();
+    config.keycloak_url = String::new();
+    config.response_cache_seconds = Some(60);
+    let app = crate::routes::build_router(&db, &config);
+
+    let send = |method: &str, uri: String, body: Option<Value>| {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(body.map_or_else(Body::empty, |body| Body::from(body.to_string())))
+            .unwrap();
+        let app = app.clone();
+        async move { extract_response_body(app.oneshot(request).await.unwrap()).await }
+    };
+    let (status, project) = send(
+        "POST",
+        "/api/projects".to_string(),
+        Some(json!({"name": format!("Cached {}", uuid::Uuid::new_v4()), "note": "first"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{project}");
+    let id = project["id"].as_str().unwrap().to_string();
+    let uri = format!("/api/projects/{id}");
+    let project_id = uuid::Uuid::parse_str(&id).unwrap();
+    assert_eq!(send("GET", uri.clone(), None).await.1["note"], "first");
+
+    // A change that leaves the project's last_updated is not seen
+    Projects::update_many()
+        .col_expr(Column::Note, Expr::value("behind the API"))
+        .filter(Column::Id.eq(project_id))
+        .exec(&db)
+        .await
+        .unwrap();
+    assert_eq!(send("GET", uri.clone(), None).await.1["note"], "first");
+
+    // An update moves last_updated on
+    Projects::update_many()
+        .col_expr(Column::Note, Expr::value("updated"))
+        .col_expr(Column::LastUpdated, Expr::value(chrono::Utc::now()))
+        .filter(Column::Id.eq(project_id))
+        .exec(&db)
+        .await
+        .unwrap();
+    assert_eq!(send("GET", uri.clone(), None).await.1["note"], "updated");
+
+    // And any change through the API drops what was kept
+    Projects::update_many()
+        .col_expr(Column::Note, Expr::value("after a change"))
+        .filter(Column::Id.eq(project_id))
+        .exec(&db)
+        .await
+        .unwrap();
+    let (status, _) = send(
+        "POST",
+        "/api/projects".to_string(),
+        Some(json!({"name": format!("Other {}", uuid::Uuid::new_v4())})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(
+        send("GET", uri.clone(), None).await.1["note"],
+        "after a change"
+    );
+}
+
+#[tokio::test]
+async fn test_project_access_by_role() {
+    let (app, _db) = setup_authenticated_test_app().await;
+    let admin = test_realm::token("ada", &["spice-admin"], &[]);
+    let editor = test_realm::token("eddie", &["spice-editor"], &[]);
+    let viewer = test_realm::token("vera", &["spice-viewer"], &[]);
+
+    let mut project_ids = Vec::new();
+    for name in ["Member project", "Other project"] {
+        let (status, project) = send_json_as(
+            &app,
+            Some(&admin),
+            "POST",
+            "/api/projects",
+            Some(&json!({"name": name})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED, "{project}");
+        project_ids.push(project["id"].as_str().unwrap().to_string());
+    }
+    let (mine, other) = (&project_ids[0], &project_ids[1]);
+    let (status, _) = send_json_as(
+        &app,
+        Some(&admin),
+        "PUT",
+        &format!("/api/projects/{mine}/members"),
+        Some(&json!(["eddie", "vera"])),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = send_json_as(&app, None, "GET", "/api/projects", None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // Members read their projects only
+    for token in [&viewer, &editor] {
+        let (status, listed) = send_json_as(&app, Some(token), "GET", "/api/projects", None).await;
+        assert_eq!(status, StatusCode::OK, "{listed}");
+        assert_eq!(listed.as_array().unwrap().len(), 1);
+        assert_eq!(listed[0]["id"], mine.as_str());
+        for (uri, expected) in [
+            (format!("/api/projects/{mine}"), StatusCode::OK),
+            (format!("/api/projects/{mine}/members"), StatusCode::OK),
+            (format!("/api/projects/{other}"), StatusCode::FORBIDDEN),
+            (
+                format!("/api/projects/{other}/activity"),
+                StatusCode::FORBIDDEN,
+            ),
+        ] {
+            let (status, _) = send_json_as(&app, Some(token), "GET", &uri, None).await;
+            assert_eq!(status, expected, "{uri}");
+        }
+    }
+
+    // Viewers do not learn who the members are
+    let (_, members) = send_json_as(
+        &app,
+        Some(&viewer),
+        "GET",
+        &format!("/api/projects/{mine}/members"),
+        None,
+    )
+    .await;
+    assert!(members[0]["username"].is_null(), "{members}");
+
+    // Only administrators change projects
+    for token in [&viewer, &editor] {
+        let (status, _) = send_json_as(
+            &app,
+            Some(token),
+            "PATCH",
+            &format!("/api/projects/{mine}"),
+            Some(&json!({"note": "Renamed"})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = send_json_as(
+            &app,
+            Some(token),
+            "POST",
+            "/api/projects",
+            Some(&json!({"name": "Another"})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+    let (status, _) = send_json_as(
+        &app,
+        Some(&admin),
+        "PATCH",
+        &format!("/api/projects/{mine}"),
+        Some(&json!({"note": "Renamed"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_member_lists_through_router() {
+    let (app, _db) = setup_authenticated_test_app().await;
+    let admin = test_realm::token("ada", &["spice-admin"], &[]);
+    let viewer = test_realm::token("vera", &["spice-viewer"], &[]);
+
+    for (project_name, sites) in [
+        ("Listed project", ["Listed site", "Listed other site"]),
+        ("Hidden project", ["Listed hidden site", "Hidden site"]),
+    ] {
+        let (_, project) = send_json_as(
+            &app,
+            Some(&admin),
+            "POST",
+            "/api/projects",
+            Some(&json!({"name": project_name})),
+        )
+        .await;
+        for name in sites {
+            let (status, location) = send_json_as(
+                &app,
+                Some(&admin),
+                "POST",
+                "/api/locations",
+                Some(&json!({"name": name, "comment": "", "project_id": project["id"]})),
+            )
+            .await;
+            assert_eq!(status, StatusCode::CREATED, "{location}");
+        }
+        let (status, _) = send_json_as(
+            &app,
+            Some(&admin),
+            "POST",
+            "/api/experiments",
+            Some(&json!({
+                "name": format!("{project_name} run"),
+                "is_calibration": false,
+                "project_id": project["id"]
+            })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        if project_name == "Listed project" {
+            let (status, _) = send_json_as(
+                &app,
+                Some(&admin),
+                "PUT",
+                &format!("/api/projects/{}/members", project["id"].as_str().unwrap()),
+                Some(&json!(["vera"])),
+            )
+            .await;
+            assert_eq!(status, StatusCode::OK);
+        }
+    }
+
+    // Searches, pages and counts only see the member's records
+    for (query, listed, total) in [
+        ("", 2, "2"),
+        ("?filter=%7B%22q%22%3A%22Listed%22%7D", 2, "2"),
+        ("?filter=%7B%22q%22%3A%22hidden%22%7D", 0, "0"),
+        ("?range=%5B0%2C0%5D", 1, "2"),
+    ] {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/api/locations{query}"))
+                    .header("authorization", format!("Bearer {viewer}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.headers()["x-total-count"], total, "{query}");
+        let (status, locations) = extract_response_body(response).await;
+        assert_eq!(status, StatusCode::OK, "{locations}");
+        assert_eq!(locations.as_array().unwrap().len(), listed, "{query}");
+    }
+    let (status, counted) =
+        send_json_as(&app, Some(&viewer), "GET", "/api/experiments/count", None).await;
+    assert_eq!(status, StatusCode::OK, "{counted}");
+    assert_eq!(counted["count"], 1);
+}
+
+#[tokio::test]
+async fn test_group_shares_through_router() {
+    let (app, _db) = setup_authenticated_test_app().await;
+    let admin = test_realm::token("ada", &["spice-admin"], &[]);
+    // A group is reached by the users holding the role of its name
+    let in_group = test_realm::token("gina", &["spice-viewer", "field-team"], &[]);
+    let outside = test_realm::token("hank", &["spice-viewer"], &[]);
+
+    let (_, project) = send_json_as(
+        &app,
+        Some(&admin),
+        "POST",
+        "/api/projects",
+        Some(&json!({"name": "Shared by group"})),
+    )
+    .await;
+    let (status, experiment) = send_json_as(
+        &app,
+        Some(&admin),
+        "POST",
+        "/api/experiments",
+        Some(&json!({
+            "name": "Field run",
+            "is_calibration": false,
+            "project_id": project["id"]
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{experiment}");
+    let experiment_id = experiment["id"].as_str().unwrap();
+    let experiment_uri = format!("/api/experiments/{experiment_id}");
+
+    for token in [&in_group, &outside] {
+        let (status, _) = send_json_as(&app, Some(token), "GET", &experiment_uri, None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+    let (status, grant) = send_json_as(
+        &app,
+        Some(&admin),
+        "POST",
+        &format!("{experiment_uri}/shares"),
+        Some(&json!({"grantee_type": "group", "grantee": "field-team", "permission": "read"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{grant}");
+
+    let (status, _) = send_json_as(&app, Some(&in_group), "GET", &experiment_uri, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, listed) =
+        send_json_as(&app, Some(&in_group), "GET", "/api/experiments", None).await;
+    assert_eq!(status, StatusCode::OK, "{listed}");
+    assert_eq!(listed.as_array().unwrap().len(), 1);
+    assert_eq!(listed[0]["id"], experiment_id);
+    let (status, _) = send_json_as(&app, Some(&outside), "GET", &experiment_uri, None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
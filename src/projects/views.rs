@@ -1,23 +1,137 @@
+use super::access::{ScopedResource, project_members, require_project_access, set_project_members};
+use super::activity::{ActivityEvent, ActivityQuery, project_activity};
+use super::archiving::{archive_project, reject_archived_changes, unarchive_project};
+use super::members::models::ProjectMember;
 pub use super::models::{Project, router as crudrouter};
-use crate::common::auth::Role;
+use crate::api_keys::services::accept_api_keys;
+use crate::audit::services::{AuditedResource, audit_changes};
+use crate::common::auth::{Role, RouteAccess, require_role};
+use crate::common::cache::cache_records;
+use crate::common::etag::check_entity_tags;
+use crate::common::keycloak::authenticate;
+use crate::common::labs::{TenantResource, require_lab};
+use crate::common::pagination::paginate_by_cursor;
+use crate::common::patch::patch_one_handler;
+use crate::common::redaction::redact_for_viewers;
 use crate::common::state::AppState;
-use axum_keycloak_auth::{PassthroughMode, layer::KeycloakAuthLayer};
+use axum_keycloak_auth::{PassthroughMode, decode::KeycloakToken};
+use crate::services::datacite_service::DataCiteMetadata;
+use axum::{
+    Extension,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    middleware,
+    response::{Json, Response},
+    routing::{get, patch, post},
+};
 use crudcrate::CRUDResource;
+use sea_orm::DbErr;
+use utoipa::OpenApi;
 use utoipa_axum::router::OpenApiRouter;
+use uuid::Uuid;
+
+/// Routes beyond the CRUD routes, documented here as they are routed
+/// with `route`
+#[derive(OpenApi)]
+#[openapi(paths(
+    get_project_datacite,
+    export_project_bagit,
+    get_project_members,
+    put_project_members,
+    post_archive_project,
+    post_unarchive_project,
+    get_project_activity
+))]
+struct ProjectsApi;
 
 pub fn router(state: &AppState) -> OpenApiRouter {
-    let mut mutating_router = crudrouter(&state.db.clone());
+    let mut mutating_router = crudrouter(&state.db.clone())
+        .route(
+            "/{id}",
+            patch(patch_one_handler::<Project>).with_state(state.db.clone()),
+        )
+        .route(
+            "/{project_id}/datacite",
+            get(get_project_datacite).with_state(state.clone()),
+        )
+        .route(
+            "/{project_id}/bagit",
+            get(export_project_bagit).with_state(state.clone()),
+        )
+        .route(
+            "/{project_id}/members",
+            get(get_project_members)
+                .put(put_project_members)
+                .with_state(state.clone()),
+        )
+        .route(
+            "/{project_id}/activity",
+            get(get_project_activity).with_state(state.clone()),
+        )
+        .route(
+            "/{project_id}/archive",
+            post(post_archive_project).with_state(state.clone()),
+        )
+        .route(
+            "/{project_id}/unarchive",
+            post(post_unarchive_project).with_state(state.clone()),
+        );
+    mutating_router
+        .get_openapi_mut()
+        .merge(ProjectsApi::openapi());
+
+    // Records polled by dashboards are served from the cache while unchanged
+    mutating_router = mutating_router.layer(middleware::from_fn_with_state(
+        (state.db.clone(), state.response_cache.clone()),
+        cache_records::<Project>,
+    ));
+
+    mutating_router = mutating_router.layer(middleware::from_fn_with_state(
+        state.db.clone(),
+        paginate_by_cursor::<Project>,
+    ));
+
+    mutating_router = mutating_router.layer(middleware::from_fn_with_state(
+        (state.db.clone(), state.config.require_if_match),
+        check_entity_tags::<Project>,
+    ));
+
+    // Archived projects and their records are read-only, even to administrators
+    mutating_router = mutating_router.layer(middleware::from_fn_with_state(
+        (state.db.clone(), None),
+        reject_archived_changes,
+    ));
+
+    // Changes are logged with the user who made them
+    mutating_router = mutating_router.layer(middleware::from_fn_with_state(
+        (state.db.clone(), AuditedResource::Projects),
+        audit_changes,
+    ));
 
     if let Some(instance) = state.keycloak_auth_instance.clone() {
-        mutating_router = mutating_router.layer(
-            KeycloakAuthLayer::<Role>::builder()
-                .instance(instance)
-                .passthrough_mode(PassthroughMode::Block)
-                .persist_raw_claims(false)
-                .expected_audiences(vec![String::from("account")])
-                .required_roles(vec![Role::Administrator])
-                .build(),
-        );
+        // Members read their projects, which only administrators change
+        mutating_router = mutating_router
+            .layer(middleware::from_fn_with_state(
+                (state.db.clone(), ScopedResource::Projects),
+                require_project_access,
+            ))
+            .layer(middleware::from_fn_with_state(
+                (state.db.clone(), TenantResource::Projects),
+                require_lab,
+            ))
+            .layer(middleware::from_fn_with_state(
+                RouteAccess::SHARED,
+                require_role,
+            ))
+            .layer(middleware::from_fn(redact_for_viewers))
+            .layer(middleware::from_fn_with_state(
+                (state.db.clone(), "projects"),
+                accept_api_keys,
+            ))
+            .layer(middleware::from_fn_with_state(
+                (instance, PassthroughMode::Pass),
+                authenticate,
+            ));
     } else if !state.config.tests_running {
         println!(
             "Warning: Mutating routes of {} router are not protected",
@@ -27,3 +141,236 @@ pub fn router(state: &AppState) -> OpenApiRouter {
 
     mutating_router
 }
+
+#[utoipa::path(
+    get,
+    path = "/{project_id}/datacite",
+    params(
+        ("project_id" = Uuid, Path, description = "Project UUID")
+    ),
+    responses(
+        (status = 200, description = "DataCite metadata for the project", body = DataCiteMetadata),
+        (status = 404, description = "Project not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "projects",
+    summary = "Get DataCite metadata",
+    description = "Assemble DataCite (schema 4) metadata describing the project's samples and experiments as a dataset. Experiments with their own DOI are listed as related identifiers"
+)]
+pub async fn get_project_datacite(
+    State(state): State<AppState>,
+    Path(project_id): Path<Uuid>,
+) -> Result<Json<DataCiteMetadata>, (StatusCode, String)> {
+    crate::services::datacite_service::project_metadata(
+        &state.db,
+        project_id,
+        &state.config.datacite_publisher,
+    )
+    .await
+    .map(Json)
+    .map_err(|e| match e {
+        DbErr::RecordNotFound(_) => (StatusCode::NOT_FOUND, "Project not found".to_string()),
+        _ => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to build DataCite metadata: {e}"),
+        ),
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/{project_id}/bagit",
+    params(
+        ("project_id" = Uuid, Path, description = "Project UUID")
+    ),
+    responses(
+        (status = 200, description = "BagIt bag of the project as a ZIP archive", content_type = "application/zip"),
+        (status = 404, description = "Project not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "projects",
+    summary = "Export project as BagIt bag",
+    description = "Stream a BagIt 1.0 bag for long-term preservation. The payload contains the project metadata, DataCite metadata and, for each experiment, its uploaded files, images and derived CSVs; SHA-256 manifests and a tag manifest are written after the payload"
+)]
+pub async fn export_project_bagit(
+    State(state): State<AppState>,
+    Path(project_id): Path<Uuid>,
+) -> Result<Response, (StatusCode, String)> {
+    let bag = super::services::build_project_bag(
+        &state.db,
+        project_id,
+        &state.config.datacite_publisher,
+    )
+    .await
+    .map_err(|e| match e {
+        DbErr::RecordNotFound(_) => (StatusCode::NOT_FOUND, "Project not found".to_string()),
+        _ => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to build BagIt bag: {e}"),
+        ),
+    })?;
+
+    Ok(crate::assets::services::create_bagit_streaming_zip_response(
+        bag.payload,
+        bag.bag_info,
+        &bag.name,
+        &state.config,
+        &format!("project_{project_id}_bagit.zip"),
+    ))
+}
+
+/// Users who can work with the project's records
+#[utoipa::path(
+    get,
+    path = "/{project_id}/members",
+    params(
+        ("project_id" = Uuid, Path, description = "Project UUID")
+    ),
+    responses(
+        (status = 200, description = "Members of the project, by username", body = Vec<ProjectMember>),
+        (status = 404, description = "Project not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "projects",
+    summary = "List project members",
+    description = "List the users who, without being administrators, can read and change the experiments, samples and assets of the project"
+)]
+pub async fn get_project_members(
+    State(state): State<AppState>,
+    Path(project_id): Path<Uuid>,
+) -> Result<Json<Vec<ProjectMember>>, (StatusCode, String)> {
+    project_members(&state.db, project_id)
+        .await
+        .map(Json)
+        .map_err(|e| match e {
+            DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+            e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })
+}
+
+/// Replace the members of a project
+#[utoipa::path(
+    put,
+    path = "/{project_id}/members",
+    params(
+        ("project_id" = Uuid, Path, description = "Project UUID")
+    ),
+    request_body(content = Vec<String>, description = "Keycloak usernames of the members"),
+    responses(
+        (status = 200, description = "Members of the project, by username", body = Vec<ProjectMember>),
+        (status = 404, description = "Project not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "projects",
+    summary = "Set project members",
+    description = "Replace the members of the project with the given Keycloak usernames. Members who are not administrators only see and change the experiments, samples and assets of their projects: experiments by their project, samples by the project of their location and assets by their experiment"
+)]
+pub async fn put_project_members(
+    State(state): State<AppState>,
+    Path(project_id): Path<Uuid>,
+    Json(usernames): Json<Vec<String>>,
+) -> Result<Json<Vec<ProjectMember>>, (StatusCode, String)> {
+    set_project_members(&state.db, project_id, usernames)
+        .await
+        .map(Json)
+        .map_err(|e| match e {
+            DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+            e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })
+}
+
+/// Archive a project, making it read-only
+#[utoipa::path(
+    post,
+    path = "/{project_id}/archive",
+    params(
+        ("project_id" = Uuid, Path, description = "Project UUID")
+    ),
+    responses(
+        (status = 200, description = "The archived project", body = Project),
+        (status = 404, description = "Project not found"),
+        (status = 409, description = "Project already archived"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "projects",
+    summary = "Archive a project",
+    description = "Make the project, its locations, their samples and treatments, and its experiments and their assets read-only. They can still be read; changes are rejected with 409 Conflict until the project is unarchived. The authenticated user and the time are kept"
+)]
+pub async fn post_archive_project(
+    State(state): State<AppState>,
+    Path(project_id): Path<Uuid>,
+    token: Option<Extension<KeycloakToken<Role>>>,
+) -> Result<Json<Project>, (StatusCode, String)> {
+    let archived_by = token.map(|Extension(token)| token.extra.profile.preferred_username);
+    archive_project(&state.db, project_id, archived_by)
+        .await
+        .map(Json)
+        .map_err(|e| match e {
+            DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+            DbErr::Custom(message) => (StatusCode::CONFLICT, message),
+            e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })
+}
+
+/// Unarchive a project, making it editable again
+#[utoipa::path(
+    post,
+    path = "/{project_id}/unarchive",
+    params(
+        ("project_id" = Uuid, Path, description = "Project UUID")
+    ),
+    responses(
+        (status = 200, description = "The unarchived project", body = Project),
+        (status = 404, description = "Project not found"),
+        (status = 409, description = "Project not archived"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "projects",
+    summary = "Unarchive a project",
+    description = "Let the project and its records be changed again"
+)]
+pub async fn post_unarchive_project(
+    State(state): State<AppState>,
+    Path(project_id): Path<Uuid>,
+) -> Result<Json<Project>, (StatusCode, String)> {
+    unarchive_project(&state.db, project_id)
+        .await
+        .map(Json)
+        .map_err(|e| match e {
+            DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+            DbErr::Custom(message) => (StatusCode::CONFLICT, message),
+            e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })
+}
+
+#[utoipa::path(
+    get,
+    path = "/{project_id}/activity",
+    params(
+        ("project_id" = Uuid, Path, description = "Project UUID"),
+        ActivityQuery
+    ),
+    responses(
+        (status = 200, description = "The project's recent events, newest first", body = Vec<ActivityEvent>),
+        (status = 400, description = "Invalid limit"),
+        (status = 404, description = "Project not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "projects",
+    summary = "Get project activity",
+    description = "List what changed recently in the project: locations, samples and experiments created or edited, assets uploaded and the outcome of processing them, and the project's own creation, edits and archiving. Only the latest edit of each record is listed"
+)]
+pub async fn get_project_activity(
+    State(state): State<AppState>,
+    Path(project_id): Path<Uuid>,
+    Query(query): Query<ActivityQuery>,
+) -> Result<Json<Vec<ActivityEvent>>, (StatusCode, String)> {
+    project_activity(&state.db, project_id, &query)
+        .await
+        .map(Json)
+        .map_err(|e| match e {
+            DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+            DbErr::Custom(message) => (StatusCode::BAD_REQUEST, message),
+            e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })
+}
@@ -1,56 +1,87 @@
+use crate::common::auth;
+use crate::common::cache::clear_on_changes;
+use crate::common::error_reporting::report_server_errors;
+use crate::common::fields::select_fields;
+use crate::common::include::scope_includes;
+use crate::common::keycloak::KeycloakAuth;
+use crate::common::labs;
+use crate::common::rate_limit::{RateLimiter, limit_rate};
 use crate::common::state::AppState;
 use crate::config::Config;
-use crate::{assets, experiments, locations, projects, samples, tray_configurations, treatments};
-use axum::{Router, extract::DefaultBodyLimit};
-use axum_keycloak_auth::{Url, instance::KeycloakAuthInstance, instance::KeycloakConfig};
+use crate::{
+    api_keys, assets, audit, changes, experiments, exports, freezing_results, graphql, idempotency,
+    locations, maintenance, nucleation_events, planned_experiments, projects, samples,
+    tray_configurations, treatments, users, webhooks,
+};
+use axum::{Json, Router, extract::DefaultBodyLimit, middleware, routing::get};
 use sea_orm::DatabaseConnection;
 use std::sync::Arc;
+use tower_http::compression::CompressionLayer;
+use tower_http::compression::predicate::{DefaultPredicate, NotForContentType, Predicate};
 use utoipa::OpenApi;
 use utoipa_axum::router::OpenApiRouter;
 use utoipa_scalar::{Scalar, Servable};
 
-#[allow(clippy::needless_for_each)]
-pub fn build_router(db: &DatabaseConnection, config: &Config) -> Router {
-    #[derive(OpenApi)]
-    #[openapi(
-        modifiers(&SecurityAddon),
-        security(
-            ("bearerAuth" = [])
-        )
-    )]
-    struct ApiDoc;
+#[derive(OpenApi)]
+#[openapi(
+    modifiers(&SecurityAddon),
+    security(
+        ("bearerAuth" = []),
+        ("apiKey" = [])
+    )
+)]
+struct ApiDoc;
 
-    struct SecurityAddon;
+struct SecurityAddon;
 
-    impl utoipa::Modify for SecurityAddon {
-        fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
-            if let Some(components) = openapi.components.as_mut() {
-                components.add_security_scheme(
-                    "bearerAuth",
-                    utoipa::openapi::security::SecurityScheme::Http(
-                        utoipa::openapi::security::HttpBuilder::new()
-                            .scheme(utoipa::openapi::security::HttpAuthScheme::Bearer)
-                            .bearer_format("JWT")
-                            .build(),
+impl utoipa::Modify for SecurityAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "bearerAuth",
+                utoipa::openapi::security::SecurityScheme::Http(
+                    utoipa::openapi::security::HttpBuilder::new()
+                        .scheme(utoipa::openapi::security::HttpAuthScheme::Bearer)
+                        .bearer_format("JWT")
+                        .build(),
+                ),
+            );
+            components.add_security_scheme(
+                "apiKey",
+                utoipa::openapi::security::SecurityScheme::ApiKey(
+                    utoipa::openapi::security::ApiKey::Header(
+                        utoipa::openapi::security::ApiKeyValue::new("X-API-Key"),
                     ),
-                );
-            }
+                ),
+            );
         }
     }
+}
 
-    let keycloak_instance: Option<Arc<KeycloakAuthInstance>> = if config.keycloak_url.is_empty() {
+pub fn build_router(db: &DatabaseConnection, config: &Config) -> Router {
+    let keycloak_instance: Option<Arc<KeycloakAuth>> = if config.keycloak_url.is_empty() {
         // Skip Keycloak initialization for tests
         None
     } else {
-        Some(Arc::new(KeycloakAuthInstance::new(
-            KeycloakConfig::builder()
-                .server(Url::parse(&config.keycloak_url).unwrap())
-                .realm(String::from(&config.keycloak_realm))
-                .build(),
-        )))
+        Some(KeycloakAuth::start(config))
     };
+    build_router_with_auth(db, config, keycloak_instance)
+}
 
+/// Router validating tokens with the given Keycloak instance, if any
+pub fn build_router_with_auth(
+    db: &DatabaseConnection,
+    config: &Config,
+    keycloak_instance: Option<Arc<KeycloakAuth>>,
+) -> Router {
     let app_state: AppState = AppState::new(db.clone(), config.clone(), keycloak_instance);
+    assets::orphans::schedule_cleanups(&app_state);
+    webhooks::services::schedule_retries(&app_state);
+    experiments::region_validation::configure(config);
+    samples::metadata::configure(config);
+    locations::site::configure(config);
+    auth::configure(config);
+    labs::configure(config);
 
     // Build the router with OpenAPI documentation
     let (router, api) = OpenApiRouter::with_openapi(ApiDoc::openapi())
@@ -64,10 +95,84 @@ pub fn build_router(db: &DatabaseConnection, config: &Config) -> Router {
             "/api/tray_configurations",
             tray_configurations::views::router(&app_state),
         )
+        .nest(
+            "/api/trays",
+            tray_configurations::trays::views::router(&app_state),
+        )
         .nest("/api/treatments", treatments::views::router(&app_state))
+        .nest(
+            "/api/dilutions",
+            treatments::dilutions::views::router(&app_state),
+        )
+        .nest("/api/exports", exports::views::router(&app_state))
+        .nest("/api/api_keys", api_keys::views::router(&app_state))
+        .nest("/api/audit", audit::views::router(&app_state))
+        .nest("/api/users", users::views::router(&app_state))
+        .nest("/api/webhooks", webhooks::views::router(&app_state))
+        .nest("/api/graphql", graphql::views::router(&app_state))
+        .nest("/api/changes", changes::views::router(&app_state))
+        .nest(
+            "/api/nucleation_events",
+            nucleation_events::views::router(&app_state),
+        )
+        .nest(
+            "/api/freezing_results",
+            freezing_results::views::router(&app_state),
+        )
+        .nest(
+            "/api/planned_experiments",
+            planned_experiments::views::router(&app_state),
+        )
+        .nest("/api/maintenance", maintenance::views::router(&app_state))
         .split_for_parts();
 
+    // The document clients are generated from
+    let spec = api.clone();
     router
+        .route(
+            "/api/openapi.json",
+            get(move || {
+                let spec = spec.clone();
+                async move { Json(spec) }
+            }),
+        )
         .merge(Scalar::with_url("/api/docs", api))
+        .layer(compression(config))
         .layer(DefaultBodyLimit::max(30 * 1024 * 1024))
+        .layer(middleware::from_fn(select_fields))
+        .layer(middleware::from_fn(scope_includes))
+        .layer(middleware::from_fn_with_state(
+            app_state.response_cache.clone(),
+            clear_on_changes,
+        ))
+        .layer(middleware::from_fn_with_state(
+            (db.clone(), app_state.keycloak_auth_instance.clone()),
+            idempotency::services::replay_idempotent_requests,
+        ))
+        .layer(middleware::from_fn_with_state(
+            (
+                Arc::new(RateLimiter::new(config)),
+                db.clone(),
+                app_state.keycloak_auth_instance.clone(),
+            ),
+            limit_rate,
+        ))
+        .layer(middleware::from_fn(report_server_errors))
+        .layer(middleware::from_fn(audit::services::assign_request_id))
+}
+
+/// Results and exports run to megabytes of JSON; archives and workbooks are
+/// compressed already
+fn compression(config: &Config) -> CompressionLayer<impl Predicate + use<>> {
+    let encodings = &config.response_compression;
+    CompressionLayer::new()
+        .br(encodings.iter().any(|encoding| encoding == "br"))
+        .gzip(encodings.iter().any(|encoding| encoding == "gzip"))
+        .compress_when(
+            DefaultPredicate::new()
+                .and(NotForContentType::const_new("application/zip"))
+                .and(NotForContentType::const_new(
+                    "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+                )),
+        )
 }
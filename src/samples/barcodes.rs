@@ -0,0 +1,106 @@
+//! Barcodes on sample vials.
+//!
+//! A sample's barcode is whatever code the lab prints or sticks on its vial.
+//! Labels of samples without one encode the sample ID, which the lookup also
+//! accepts, so labels can be printed before codes are assigned.
+
+use super::models::{Column, Entity as Samples, Model, Sample};
+use crate::locations::models as locations;
+use crudcrate::CRUDResource;
+use sea_orm::{ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter};
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Content of a vial label
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct SampleLabel {
+    pub sample_id: Uuid,
+    /// Printed as a barcode or QR code: the sample's barcode, or its ID when
+    /// it has none
+    pub code: String,
+    pub title: String,
+    /// Text under the title
+    pub lines: Vec<String>,
+}
+
+/// Trimmed barcode, `None` when blank
+pub(super) fn normalise(barcode: Option<String>) -> Option<String> {
+    barcode
+        .map(|code| code.trim().to_string())
+        .filter(|code| !code.is_empty())
+}
+
+/// `DbErr::Custom` when another sample than `sample_id` has the barcode
+pub(super) async fn ensure_unused(
+    db: &DatabaseConnection,
+    barcode: Option<&str>,
+    sample_id: Option<Uuid>,
+) -> Result<(), DbErr> {
+    let Some(barcode) = barcode else {
+        return Ok(());
+    };
+    let mut query = Samples::find().filter(Column::Barcode.eq(barcode));
+    if let Some(sample_id) = sample_id {
+        query = query.filter(Column::Id.ne(sample_id));
+    }
+    match query.one(db).await? {
+        Some(other) => Err(DbErr::Custom(format!(
+            "Barcode {barcode} is already used by sample '{}'",
+            other.name
+        ))),
+        None => Ok(()),
+    }
+}
+
+async fn find_by_code(db: &DatabaseConnection, code: &str) -> Result<Model, DbErr> {
+    let code = code.trim();
+    if let Some(sample) = Samples::find()
+        .filter(Column::Barcode.eq(code))
+        .one(db)
+        .await?
+    {
+        return Ok(sample);
+    }
+    if let Ok(id) = Uuid::parse_str(code)
+        && let Some(sample) = Samples::find_by_id(id).one(db).await?
+    {
+        return Ok(sample);
+    }
+    Err(DbErr::RecordNotFound(format!(
+        "No sample has barcode {code}"
+    )))
+}
+
+/// The sample whose barcode, or ID, is `code`
+pub async fn sample_by_barcode(db: &DatabaseConnection, code: &str) -> Result<Sample, DbErr> {
+    let sample = find_by_code(db, code).await?;
+    Sample::get_one(db, sample.id).await
+}
+
+pub async fn sample_label(db: &DatabaseConnection, id: Uuid) -> Result<SampleLabel, DbErr> {
+    let sample = Samples::find_by_id(id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Sample not found".to_string()))?;
+
+    let mut lines = vec![match &sample.aliquot_label {
+        Some(aliquot_label) => format!("{:?}, aliquot {aliquot_label}", sample.r#type),
+        None => format!("{:?}", sample.r#type),
+    }];
+    if let Some(start_time) = sample.start_time {
+        lines.push(start_time.format("%Y-%m-%d %H:%M UTC").to_string());
+    }
+    if let Some(location_id) = sample.location_id
+        && let Some(location) = locations::Entity::find_by_id(location_id).one(db).await?
+    {
+        lines.push(location.name);
+    }
+
+    Ok(SampleLabel {
+        sample_id: sample.id,
+        code: sample.barcode.unwrap_or_else(|| sample.id.to_string()),
+        title: sample.name,
+        lines,
+    })
+}
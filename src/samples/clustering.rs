@@ -0,0 +1,217 @@
+//! Clusters of sampling points for the map.
+//!
+//! Points are grouped the way supercluster does: projected to Web Mercator
+//! pixels at the requested zoom, each point not yet clustered gathers those
+//! within the cluster radius around it. Clusters are placed at the mean
+//! position of their points, so the map only draws a marker per cluster.
+
+use super::models::{Column, Entity as Samples};
+use crate::common::spatial::bbox;
+use crate::locations::models::Column as LocationColumn;
+use rust_decimal::{Decimal, prelude::ToPrimitive};
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, JoinType, QueryFilter, QueryOrder,
+    QuerySelect, RelationTrait,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+pub const MAX_ZOOM: u8 = 22;
+pub const DEFAULT_RADIUS: u32 = 40;
+const TILE_SIZE: f64 = 256.0;
+/// Latitudes beyond which Web Mercator is not drawn
+const MAX_LATITUDE: f64 = 85.051_128_78;
+
+#[derive(Clone, Debug, Default, Deserialize, IntoParams)]
+pub struct ClusterQuery {
+    /// Zoom level of the map, 0 to 22
+    pub zoom: u8,
+    /// `min_lon,min_lat,max_lon,max_lat` of the visible area; everywhere when
+    /// omitted
+    pub within_bbox: Option<String>,
+    /// Only the samples of this project's locations
+    pub project_id: Option<Uuid>,
+    /// Radius of a cluster in pixels, 1 to 256 (default 40)
+    pub radius: Option<u32>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, ToSchema)]
+pub struct MapCluster {
+    pub longitude: f64,
+    pub latitude: f64,
+    /// Number of samples in the cluster
+    pub count: usize,
+    /// The sample, when the cluster is a single one
+    pub sample_id: Option<Uuid>,
+}
+
+/// A sample's position in Web Mercator, from 0 to 1 across the world
+#[derive(Clone, Copy, Debug)]
+struct Point {
+    id: Uuid,
+    x: f64,
+    y: f64,
+}
+
+fn project(longitude: f64, latitude: f64) -> (f64, f64) {
+    let sin = latitude
+        .clamp(-MAX_LATITUDE, MAX_LATITUDE)
+        .to_radians()
+        .sin();
+    let y = 0.5 - ((1.0 + sin) / (1.0 - sin)).ln() / (4.0 * PI);
+    (longitude / 360.0 + 0.5, y)
+}
+
+fn unproject(x: f64, y: f64) -> (f64, f64) {
+    let latitude = (PI * (1.0 - 2.0 * y)).sinh().atan().to_degrees();
+    ((x - 0.5) * 360.0, latitude)
+}
+
+/// Group points lying within `radius`, in the projection's units, of the
+/// first point of each cluster, visiting points in order
+fn cluster(points: &[Point], radius: f64) -> Vec<MapCluster> {
+    #[allow(clippy::cast_possible_truncation)]
+    let cell = |x: f64, y: f64| ((x / radius).floor() as i64, (y / radius).floor() as i64);
+    let mut grid: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+    for (i, point) in points.iter().enumerate() {
+        grid.entry(cell(point.x, point.y)).or_default().push(i);
+    }
+
+    let mut clustered = vec![false; points.len()];
+    let mut clusters = Vec::new();
+    for (i, point) in points.iter().enumerate() {
+        if clustered[i] {
+            continue;
+        }
+        let (column, row) = cell(point.x, point.y);
+        let mut members = Vec::new();
+        for neighbour_column in column - 1..=column + 1 {
+            for neighbour_row in row - 1..=row + 1 {
+                for &j in grid
+                    .get(&(neighbour_column, neighbour_row))
+                    .into_iter()
+                    .flatten()
+                {
+                    let other = points[j];
+                    if !clustered[j] && (other.x - point.x).hypot(other.y - point.y) <= radius {
+                        clustered[j] = true;
+                        members.push(other);
+                    }
+                }
+            }
+        }
+
+        #[allow(clippy::cast_precision_loss)]
+        let count = members.len() as f64;
+        let x = members.iter().map(|member| member.x).sum::<f64>() / count;
+        let y = members.iter().map(|member| member.y).sum::<f64>() / count;
+        let (longitude, latitude) = unproject(x, y);
+        clusters.push(MapCluster {
+            longitude,
+            latitude,
+            count: members.len(),
+            sample_id: (members.len() == 1).then_some(point.id),
+        });
+    }
+    clusters
+}
+
+/// Clusters of the samples with coordinates in the query's area, largest
+/// first. Invalid queries are returned as `DbErr::Custom`.
+pub async fn sample_clusters(
+    db: &DatabaseConnection,
+    query: &ClusterQuery,
+) -> Result<Vec<MapCluster>, DbErr> {
+    if query.zoom > MAX_ZOOM {
+        return Err(DbErr::Custom(format!(
+            "zoom must be between 0 and {MAX_ZOOM}"
+        )));
+    }
+    let radius = query.radius.unwrap_or(DEFAULT_RADIUS);
+    if !(1..=256).contains(&radius) {
+        return Err(DbErr::Custom(
+            "radius must be between 1 and 256 pixels".to_string(),
+        ));
+    }
+
+    let mut select = Samples::find()
+        .select_only()
+        .columns([Column::Id, Column::Longitude, Column::Latitude])
+        .filter(Column::Longitude.is_not_null())
+        .filter(Column::Latitude.is_not_null())
+        .order_by_asc(Column::Id);
+    if let Some(text) = &query.within_bbox {
+        let [min_lon, min_lat, max_lon, max_lat] = bbox(text)?;
+        let decimal = |value: f64| Decimal::try_from(value).unwrap_or_default();
+        select = select
+            .filter(Column::Longitude.between(decimal(min_lon), decimal(max_lon)))
+            .filter(Column::Latitude.between(decimal(min_lat), decimal(max_lat)));
+    }
+    if let Some(project_id) = query.project_id {
+        select = select
+            .join(
+                JoinType::InnerJoin,
+                super::models::Relation::Locations.def(),
+            )
+            .filter(LocationColumn::ProjectId.eq(project_id));
+    }
+
+    let points: Vec<Point> = select
+        .into_tuple::<(Uuid, Decimal, Decimal)>()
+        .all(db)
+        .await?
+        .into_iter()
+        .filter_map(|(id, longitude, latitude)| {
+            let (x, y) = project(longitude.to_f64()?, latitude.to_f64()?);
+            Some(Point { id, x, y })
+        })
+        .collect();
+
+    let world_size = TILE_SIZE * 2_f64.powi(i32::from(query.zoom));
+    let mut clusters = cluster(&points, f64::from(radius) / world_size);
+    clusters.sort_by_key(|cluster| std::cmp::Reverse(cluster.count));
+    Ok(clusters)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cluster() {
+        let point = |longitude, latitude| {
+            let (x, y) = project(longitude, latitude);
+            Point {
+                id: Uuid::new_v4(),
+                x,
+                y,
+            }
+        };
+        let (x, y) = project(7.9851, 46.5475);
+        let (longitude, latitude) = unproject(x, y);
+        assert!((longitude - 7.9851).abs() < 1e-9 && (latitude - 46.5475).abs() < 1e-9);
+
+        // Jungfraujoch and Payerne are 80 km apart, Ny-Ålesund far north
+        let points = [
+            point(7.9851, 46.5475),
+            point(7.9861, 46.5480),
+            point(6.9440, 46.8130),
+            point(11.9224, 78.9243),
+        ];
+        let radius = |zoom: i32| 40.0 / (TILE_SIZE * 2_f64.powi(zoom));
+
+        let clusters = cluster(&points, radius(3));
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[0].count, 3);
+        assert!(clusters[0].sample_id.is_none());
+        assert_eq!(clusters[1].sample_id, Some(points[3].id));
+
+        let clusters = cluster(&points, radius(9));
+        let counts: Vec<usize> = clusters.iter().map(|cluster| cluster.count).collect();
+        assert_eq!(counts, vec![2, 1, 1]);
+        assert!((clusters[0].longitude - 7.9856).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,73 @@
+//! Chain of custody of samples.
+//!
+//! Each step a sample goes through, from collection to processing, is
+//! appended to its log. Entries are never edited or deleted so the log can be
+//! audited; a mistake is corrected by logging another event.
+
+use super::custody_events::models::{self as custody_events, CustodyEventType, SampleCustodyEvent};
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, ConnectionTrait, DbErr, EntityTrait,
+    QueryFilter, QueryOrder,
+};
+use serde::Deserialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(Clone, Debug, Deserialize, ToSchema)]
+pub struct CustodyEventCreate {
+    pub event_type: CustodyEventType,
+    /// When the event took place; now when omitted
+    pub occurred_at: Option<DateTime<Utc>>,
+    /// Who handled the sample
+    pub username: Option<String>,
+    /// Where the sample was, e.g. a freezer, a lab or a courier
+    pub location: Option<String>,
+    pub notes: Option<String>,
+}
+
+/// The sample's custody log in the order the events took place
+pub async fn list_custody_events(
+    db: &impl ConnectionTrait,
+    sample_id: Uuid,
+) -> Result<Vec<SampleCustodyEvent>, DbErr> {
+    Ok(custody_events::Entity::find()
+        .filter(custody_events::Column::SampleId.eq(sample_id))
+        .order_by_asc(custody_events::Column::OccurredAt)
+        .order_by_asc(custody_events::Column::CreatedAt)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(SampleCustodyEvent::from)
+        .collect())
+}
+
+/// Append an event to the sample's log. `recorded_by` is the authenticated
+/// user, when there is one.
+pub async fn record_custody_event(
+    db: &impl ConnectionTrait,
+    sample_id: Uuid,
+    event: CustodyEventCreate,
+    recorded_by: Option<String>,
+) -> Result<SampleCustodyEvent, DbErr> {
+    super::models::Entity::find_by_id(sample_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Sample not found".to_string()))?;
+
+    let now = Utc::now();
+    let inserted = custody_events::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        sample_id: Set(sample_id),
+        event_type: Set(event.event_type),
+        occurred_at: Set(event.occurred_at.unwrap_or(now)),
+        username: Set(event.username),
+        location: Set(event.location),
+        notes: Set(event.notes),
+        recorded_by: Set(recorded_by),
+        created_at: Set(now),
+    }
+    .insert(db)
+    .await?;
+    Ok(inserted.into())
+}
@@ -0,0 +1,73 @@
+use chrono::{DateTime, Utc};
+use crudcrate::EntityToModels;
+use sea_orm::entity::prelude::*;
+use uuid::Uuid;
+
+#[derive(
+    Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, ToSchema, Serialize, Deserialize,
+)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "custody_event_type")]
+#[serde(rename_all = "snake_case")]
+pub enum CustodyEventType {
+    #[sea_orm(string_value = "collected")]
+    Collected,
+    #[sea_orm(string_value = "shipped")]
+    Shipped,
+    #[sea_orm(string_value = "received")]
+    Received,
+    #[sea_orm(string_value = "stored")]
+    Stored,
+    #[sea_orm(string_value = "processed")]
+    Processed,
+}
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, EntityToModels)]
+#[sea_orm(table_name = "sample_custody_events")]
+#[crudcrate(api_struct = "SampleCustodyEvent")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    #[crudcrate(primary_key, update_model = false, create_model = false, on_create = Uuid::new_v4())]
+    pub id: Uuid,
+    #[crudcrate(sortable, filterable)]
+    pub sample_id: Uuid,
+    #[crudcrate(sortable, filterable, enum_field)]
+    pub event_type: CustodyEventType,
+    #[crudcrate(sortable)]
+    pub occurred_at: DateTime<Utc>,
+    /// Who handled the sample
+    #[sea_orm(column_type = "Text", nullable)]
+    #[crudcrate(filterable)]
+    pub username: Option<String>,
+    /// Where the sample was, e.g. a freezer, a lab or a courier
+    #[sea_orm(column_type = "Text", nullable)]
+    #[crudcrate(filterable)]
+    pub location: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub notes: Option<String>,
+    /// Authenticated user who logged the event
+    #[sea_orm(column_type = "Text", nullable)]
+    #[crudcrate(filterable)]
+    pub recorded_by: Option<String>,
+    #[crudcrate(update_model = false, create_model = false, on_create = chrono::Utc::now(), sortable)]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "crate::samples::models::Entity",
+        from = "Column::SampleId",
+        to = "crate::samples::models::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Samples,
+}
+
+impl Related<crate::samples::models::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Samples.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,221 @@
+//! Aliquots of samples: a filter cut into pieces, or a suspension split into
+//! subsamples, each of which can be run and divided again.
+//!
+//! A sample's `parent_sample_id` points to the sample it was taken from, so
+//! the hierarchy is a tree under every top-level sample. Results of a sample
+//! roll up with those of all its aliquots.
+
+use super::models::{Column, Entity as Samples, Model, SampleType};
+use super::services::fetch_experimental_results_for_sample;
+use crate::nucleation_events::models::{DilutionSummary, NucleationEvent, NucleationStatistics};
+use sea_orm::{ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct SampleReference {
+    pub id: Uuid,
+    pub name: String,
+    pub r#type: SampleType,
+    pub aliquot_label: Option<String>,
+}
+
+/// A sample and, recursively, the aliquots taken from it
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct AliquotTree {
+    pub id: Uuid,
+    pub name: String,
+    pub r#type: SampleType,
+    pub aliquot_label: Option<String>,
+    #[schema(no_recursion)]
+    pub aliquots: Vec<AliquotTree>,
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct SampleHierarchy {
+    /// From the top-level sample down to the sample's parent
+    pub ancestors: Vec<SampleReference>,
+    pub sample: AliquotTree,
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct AliquotResults {
+    pub sample: SampleReference,
+    pub experimental_results: Vec<NucleationEvent>,
+}
+
+/// Results of a sample together with those of all its aliquots
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct SampleRollup {
+    pub sample_id: Uuid,
+    /// The sample first, then its aliquots depth first
+    pub samples: Vec<AliquotResults>,
+    /// Over the results of every sample listed
+    pub statistics: Option<NucleationStatistics>,
+    pub dilution_summaries: Vec<DilutionSummary>,
+}
+
+impl From<&Model> for SampleReference {
+    fn from(model: &Model) -> Self {
+        Self {
+            id: model.id,
+            name: model.name.clone(),
+            r#type: model.r#type.clone(),
+            aliquot_label: model.aliquot_label.clone(),
+        }
+    }
+}
+
+async fn find_sample(db: &DatabaseConnection, id: Uuid) -> Result<Model, DbErr> {
+    Samples::find_by_id(id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Sample not found".to_string()))
+}
+
+/// Every aliquot below `id`, at any depth, grouped by parent and sorted by
+/// label then name
+async fn aliquots_by_parent(
+    db: &DatabaseConnection,
+    id: Uuid,
+) -> Result<HashMap<Uuid, Vec<Model>>, DbErr> {
+    let mut by_parent: HashMap<Uuid, Vec<Model>> = HashMap::new();
+    let mut seen = HashSet::from([id]);
+    let mut level = vec![id];
+    while !level.is_empty() {
+        let children = Samples::find()
+            .filter(Column::ParentSampleId.is_in(level))
+            .all(db)
+            .await?;
+        level = Vec::new();
+        for child in children {
+            if !seen.insert(child.id) {
+                continue;
+            }
+            level.push(child.id);
+            if let Some(parent_id) = child.parent_sample_id {
+                by_parent.entry(parent_id).or_default().push(child);
+            }
+        }
+    }
+    for children in by_parent.values_mut() {
+        children.sort_by(|a, b| (&a.aliquot_label, &a.name).cmp(&(&b.aliquot_label, &b.name)));
+    }
+    Ok(by_parent)
+}
+
+fn tree(sample: &Model, by_parent: &HashMap<Uuid, Vec<Model>>) -> AliquotTree {
+    AliquotTree {
+        id: sample.id,
+        name: sample.name.clone(),
+        r#type: sample.r#type.clone(),
+        aliquot_label: sample.aliquot_label.clone(),
+        aliquots: by_parent
+            .get(&sample.id)
+            .map(|children| {
+                children
+                    .iter()
+                    .map(|child| tree(child, by_parent))
+                    .collect()
+            })
+            .unwrap_or_default(),
+    }
+}
+
+fn depth_first<'a>(
+    sample: &'a Model,
+    by_parent: &'a HashMap<Uuid, Vec<Model>>,
+    order: &mut Vec<&'a Model>,
+) {
+    order.push(sample);
+    for child in by_parent.get(&sample.id).into_iter().flatten() {
+        depth_first(child, by_parent, order);
+    }
+}
+
+/// Check that `parent_id` can be the parent of `sample_id`, or of a new
+/// sample when `None`. Problems are returned as `DbErr::Custom`.
+pub(super) async fn validate_parent(
+    db: &DatabaseConnection,
+    sample_id: Option<Uuid>,
+    parent_id: Option<Uuid>,
+) -> Result<(), DbErr> {
+    let Some(parent_id) = parent_id else {
+        return Ok(());
+    };
+    if Samples::find_by_id(parent_id).one(db).await?.is_none() {
+        return Err(DbErr::Custom(format!(
+            "Parent sample {parent_id} not found"
+        )));
+    }
+    if let Some(sample_id) = sample_id {
+        if sample_id == parent_id {
+            return Err(DbErr::Custom(
+                "A sample cannot be an aliquot of itself".to_string(),
+            ));
+        }
+        let is_descendant = aliquots_by_parent(db, sample_id)
+            .await?
+            .values()
+            .flatten()
+            .any(|aliquot| aliquot.id == parent_id);
+        if is_descendant {
+            return Err(DbErr::Custom(format!(
+                "Sample {parent_id} is an aliquot of this sample and cannot be its parent"
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// The samples above a sample and the tree of aliquots below it
+pub async fn sample_hierarchy(db: &DatabaseConnection, id: Uuid) -> Result<SampleHierarchy, DbErr> {
+    let sample = find_sample(db, id).await?;
+
+    let mut ancestors = Vec::new();
+    let mut seen = HashSet::from([id]);
+    let mut parent_id = sample.parent_sample_id;
+    while let Some(id) = parent_id.filter(|id| seen.insert(*id)) {
+        let Some(parent) = Samples::find_by_id(id).one(db).await? else {
+            break;
+        };
+        parent_id = parent.parent_sample_id;
+        ancestors.push(SampleReference::from(&parent));
+    }
+    ancestors.reverse();
+
+    let by_parent = aliquots_by_parent(db, id).await?;
+    Ok(SampleHierarchy {
+        ancestors,
+        sample: tree(&sample, &by_parent),
+    })
+}
+
+/// Results of a sample and all its aliquots, with statistics over all of them
+pub async fn sample_rollup(db: &DatabaseConnection, id: Uuid) -> Result<SampleRollup, DbErr> {
+    let sample = find_sample(db, id).await?;
+    let by_parent = aliquots_by_parent(db, id).await?;
+    let mut order = Vec::new();
+    depth_first(&sample, &by_parent, &mut order);
+
+    let mut samples = Vec::new();
+    for model in order {
+        samples.push(AliquotResults {
+            sample: SampleReference::from(model),
+            experimental_results: fetch_experimental_results_for_sample(db, model.id).await?,
+        });
+    }
+    let all_results: Vec<NucleationEvent> = samples
+        .iter()
+        .flat_map(|aliquot| aliquot.experimental_results.iter().cloned())
+        .collect();
+
+    Ok(SampleRollup {
+        sample_id: id,
+        samples,
+        statistics: NucleationStatistics::from_events(&all_results),
+        dilution_summaries: NucleationStatistics::dilution_summaries_from_events(&all_results),
+    })
+}
@@ -0,0 +1,309 @@
+//! Metadata each sample type needs.
+//!
+//! Rules are written like a JSON schema for each sample type, for example
+//! `{"filter": {"required": ["air_volume_litres"], "properties":
+//! {"air_volume_litres": {"exclusiveMinimum": 0}}}}`, and read at startup
+//! from the file at `SAMPLE_TYPE_RULES_PATH`. Without it the built-in rules
+//! apply, which only keep volumes, flows and concentrations from being
+//! negative. Samples are checked as saved, after an update is merged in.
+
+use super::models::{ActiveModel, Column, Model, SampleType};
+use crate::config::Config;
+use sea_orm::{
+    ActiveEnum, ActiveModelTrait, ActiveValue, DbErr, IntoActiveModel, Iterable,
+    sea_query::sea_value_to_json_value,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::RwLock;
+use utoipa::ToSchema;
+
+/// Rules by sample type, replacing the built-in ones when configured
+static RULES: RwLock<Option<SampleTypeRules>> = RwLock::new(None);
+
+/// Numeric fields the built-in rules keep from being negative
+const NON_NEGATIVE_FIELDS: [&str; 6] = [
+    "flow_litres_per_minute",
+    "total_volume",
+    "suspension_volume_litres",
+    "air_volume_litres",
+    "initial_concentration_gram_l",
+    "well_volume_litres",
+];
+
+pub type SampleTypeRules = BTreeMap<String, TypeSchema>;
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TypeSchema {
+    /// Fields that must have a value
+    #[serde(default)]
+    pub required: Vec<String>,
+    /// Bounds on numeric fields, checked when they have a value
+    #[serde(default)]
+    pub properties: BTreeMap<String, PropertySchema>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PropertySchema {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minimum: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exclusive_minimum: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maximum: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exclusive_maximum: Option<f64>,
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct MetadataProblem {
+    pub field: String,
+    pub message: String,
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct MetadataCheck {
+    pub valid: bool,
+    pub problems: Vec<MetadataProblem>,
+}
+
+fn type_name(sample_type: &SampleType) -> String {
+    sample_type.to_value()
+}
+
+fn default_rules() -> SampleTypeRules {
+    let schema = TypeSchema {
+        required: Vec::new(),
+        properties: NON_NEGATIVE_FIELDS
+            .iter()
+            .map(|field| {
+                (
+                    (*field).to_string(),
+                    PropertySchema {
+                        minimum: Some(0.0),
+                        ..PropertySchema::default()
+                    },
+                )
+            })
+            .collect(),
+    };
+    SampleType::iter()
+        .map(|sample_type| (type_name(&sample_type), schema.clone()))
+        .collect()
+}
+
+/// Parse rules, rejecting unknown sample types and fields
+pub fn parse_rules(json: &str) -> Result<SampleTypeRules, String> {
+    let rules: SampleTypeRules = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    let type_names: Vec<String> = SampleType::iter().map(|t| type_name(&t)).collect();
+    for (sample_type, schema) in &rules {
+        if !type_names.contains(sample_type) {
+            return Err(format!(
+                "Unknown sample type '{sample_type}', expected one of {}",
+                type_names.join(", ")
+            ));
+        }
+        for field in schema.required.iter().chain(schema.properties.keys()) {
+            if Column::from_str(field).is_err() {
+                return Err(format!(
+                    "Samples have no field '{field}' (in the rules for {sample_type} samples)"
+                ));
+            }
+        }
+    }
+    Ok(rules)
+}
+
+/// Load the rules file of the configuration, if any. An unreadable or
+/// invalid file stops the server rather than leaving samples unchecked.
+pub fn configure(config: &Config) {
+    let rules = config.sample_type_rules_path.as_ref().map(|path| {
+        let json = std::fs::read_to_string(path)
+            .unwrap_or_else(|e| panic!("Cannot read sample type rules from {path}: {e}"));
+        parse_rules(&json).unwrap_or_else(|e| panic!("Invalid sample type rules in {path}: {e}"))
+    });
+    *RULES
+        .write()
+        .unwrap_or_else(std::sync::PoisonError::into_inner) = rules;
+}
+
+/// The rules in force
+pub fn current_rules() -> SampleTypeRules {
+    RULES
+        .read()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .clone()
+        .unwrap_or_else(default_rules)
+}
+
+/// The sample's value of a field, `None` when unset or blank
+fn field_value(sample: &ActiveModel, field: &str) -> Option<Value> {
+    let column = Column::from_str(field).ok()?;
+    let value = sea_value_to_json_value(&sample.get(column).into_value()?);
+    match value {
+        Value::Null => None,
+        Value::String(text) if text.trim().is_empty() => None,
+        value => Some(value),
+    }
+}
+
+fn property_problems(field: &str, value: f64, property: &PropertySchema) -> Vec<String> {
+    let mut problems = Vec::new();
+    if let Some(minimum) = property.minimum
+        && value < minimum
+    {
+        problems.push(format!("{field} must be at least {minimum}"));
+    }
+    if let Some(minimum) = property.exclusive_minimum
+        && value <= minimum
+    {
+        problems.push(format!("{field} must be greater than {minimum}"));
+    }
+    if let Some(maximum) = property.maximum
+        && value > maximum
+    {
+        problems.push(format!("{field} must be at most {maximum}"));
+    }
+    if let Some(maximum) = property.exclusive_maximum
+        && value >= maximum
+    {
+        problems.push(format!("{field} must be less than {maximum}"));
+    }
+    problems
+}
+
+/// Problems of the sample against the rules of its type
+pub fn check_sample(rules: &SampleTypeRules, sample: &ActiveModel) -> Vec<MetadataProblem> {
+    let Some(sample_type) = sample.r#type.try_as_ref() else {
+        return Vec::new();
+    };
+    let sample_type = type_name(sample_type);
+    let Some(schema) = rules.get(&sample_type) else {
+        return Vec::new();
+    };
+
+    let mut problems: Vec<MetadataProblem> = schema
+        .required
+        .iter()
+        .filter(|field| field_value(sample, field).is_none())
+        .map(|field| MetadataProblem {
+            field: field.clone(),
+            message: format!("{field} is required for {sample_type} samples"),
+        })
+        .collect();
+    for (field, property) in &schema.properties {
+        let Some(value) = field_value(sample, field).as_ref().and_then(Value::as_f64) else {
+            continue;
+        };
+        problems.extend(
+            property_problems(field, value, property)
+                .into_iter()
+                .map(|message| MetadataProblem {
+                    field: field.clone(),
+                    message,
+                }),
+        );
+    }
+    problems
+}
+
+/// Check the sample against the rules in force, returning every problem in a
+/// single `DbErr::Custom`
+pub(super) fn validate(sample: &ActiveModel) -> Result<(), DbErr> {
+    let problems = check_sample(&current_rules(), sample);
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(DbErr::Custom(
+            problems
+                .into_iter()
+                .map(|problem| problem.message)
+                .collect::<Vec<_>>()
+                .join("; "),
+        ))
+    }
+}
+
+/// The saved sample with the fields an update sets, since an update leaves
+/// the fields it does not change unset
+pub(super) fn merged(existing: Model, changes: &ActiveModel) -> ActiveModel {
+    let mut sample = existing.into_active_model();
+    for column in Column::iter() {
+        if let ActiveValue::Set(value) = changes.get(column) {
+            sample.set(column, value);
+        }
+    }
+    sample
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+    use sea_orm::ActiveValue::Set;
+
+    #[test]
+    fn test_check_sample() {
+        let rules = parse_rules(
+            r#"{
+                "filter": {
+                    "required": ["air_volume_litres", "filter_substrate"],
+                    "properties": {"air_volume_litres": {"exclusiveMinimum": 0}}
+                },
+                "blank": {}
+            }"#,
+        )
+        .unwrap();
+
+        let mut filter = ActiveModel {
+            r#type: Set(SampleType::Filter),
+            filter_substrate: Set(Some("  ".to_string())),
+            ..Default::default()
+        };
+        let messages = |sample: &ActiveModel| {
+            check_sample(&rules, sample)
+                .into_iter()
+                .map(|problem| problem.message)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            messages(&filter),
+            vec![
+                "air_volume_litres is required for filter samples",
+                "filter_substrate is required for filter samples",
+            ]
+        );
+
+        filter.air_volume_litres = Set(Some(Decimal::ZERO));
+        filter.filter_substrate = Set(Some("Polycarbonate".to_string()));
+        assert_eq!(
+            messages(&filter),
+            vec!["air_volume_litres must be greater than 0"]
+        );
+        filter.air_volume_litres = Set(Some(Decimal::new(1500, 0)));
+        assert!(messages(&filter).is_empty());
+
+        // Types without rules are not checked
+        let bulk = ActiveModel {
+            r#type: Set(SampleType::Bulk),
+            ..Default::default()
+        };
+        assert!(messages(&bulk).is_empty());
+
+        assert!(
+            parse_rules(r#"{"water": {}}"#)
+                .unwrap_err()
+                .contains("water")
+        );
+        assert!(
+            parse_rules(r#"{"bulk": {"required": ["colour"]}}"#)
+                .unwrap_err()
+                .contains("colour")
+        );
+        assert!(parse_rules(r#"{"bulk": {"requires": []}}"#).is_err());
+    }
+}
@@ -0,0 +1,256 @@
+//! Weather over the collection period of samples.
+//!
+//! Hourly temperature, wind and boundary layer height at a sample's
+//! coordinates come from the configured reanalysis provider and are
+//! summarised over the hours the sample was collected, so that results can be
+//! correlated with them. They are cached with the sample and only fetched
+//! again once its coordinates or times change.
+
+use super::models::Model;
+use super::weather::models::{self as weather, SampleWeather};
+use crate::config::Config;
+use crate::external::weather::{WeatherHour, fetch_hourly};
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::{
+    Decimal,
+    prelude::{FromPrimitive, ToPrimitive},
+};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, DatabaseConnection, DbErr, EntityTrait,
+    QueryFilter, TransactionTrait,
+};
+use uuid::Uuid;
+
+/// Where and when a sample was collected
+#[derive(Clone, Debug, PartialEq)]
+pub struct CollectionPeriod {
+    pub latitude: Decimal,
+    pub longitude: Decimal,
+    pub start_time: DateTime<Utc>,
+    pub stop_time: Option<DateTime<Utc>>,
+}
+
+impl CollectionPeriod {
+    /// Whether an hour of weather overlaps the period, or is the hour the
+    /// sample was taken in when it has no stop time
+    fn covers(&self, hour: DateTime<Utc>) -> bool {
+        hour + Duration::hours(1) > self.start_time
+            && self
+                .stop_time
+                .map_or(hour <= self.start_time, |stop_time| hour < stop_time)
+    }
+}
+
+/// The sample's collection period, or a `DbErr::Custom` when it lacks the
+/// coordinates or times to look the weather up
+pub fn collection_period(sample: &Model) -> Result<CollectionPeriod, DbErr> {
+    let (Some(latitude), Some(longitude)) = (sample.latitude, sample.longitude) else {
+        return Err(DbErr::Custom(format!(
+            "Sample '{}' has no coordinates to look the weather up at",
+            sample.name
+        )));
+    };
+    let Some(start_time) = sample.start_time else {
+        return Err(DbErr::Custom(format!(
+            "Sample '{}' has no start time to look the weather up for",
+            sample.name
+        )));
+    };
+    if sample
+        .stop_time
+        .is_some_and(|stop_time| stop_time < start_time)
+    {
+        return Err(DbErr::Custom(format!(
+            "Sample '{}' stops before it starts",
+            sample.name
+        )));
+    }
+    Ok(CollectionPeriod {
+        latitude,
+        longitude,
+        start_time,
+        stop_time: sample.stop_time,
+    })
+}
+
+/// The cached weather of the sample, unless its coordinates or times changed
+/// since it was fetched
+pub async fn cached_weather(
+    db: &DatabaseConnection,
+    sample: &Model,
+) -> Result<Option<SampleWeather>, DbErr> {
+    let Ok(period) = collection_period(sample) else {
+        return Ok(None);
+    };
+    let cached = weather::Entity::find()
+        .filter(weather::Column::SampleId.eq(sample.id))
+        .one(db)
+        .await?;
+    Ok(cached
+        .filter(|cached| {
+            cached.latitude == period.latitude
+                && cached.longitude == period.longitude
+                && cached.start_time == period.start_time
+                && cached.stop_time == period.stop_time
+        })
+        .map(SampleWeather::from))
+}
+
+/// A summary value as stored, to the nearest thousandth
+fn rounded(value: f64) -> Option<Decimal> {
+    Decimal::from_f64(value).map(|value| value.round_dp(3).normalize())
+}
+
+/// Mean, minimum and maximum of the values present
+fn statistics(
+    values: impl Iterator<Item = Option<f64>>,
+) -> Option<(Option<Decimal>, Option<Decimal>, Option<Decimal>)> {
+    let values: Vec<f64> = values.flatten().collect();
+    if values.is_empty() {
+        return None;
+    }
+    #[allow(clippy::cast_precision_loss)]
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    Some((rounded(mean), rounded(min), rounded(max)))
+}
+
+/// Direction of the mean of unit vectors, so that 350° and 10° average to 0°
+fn mean_direction(directions: impl Iterator<Item = Option<f64>>) -> Option<Decimal> {
+    let (sin, cos) = directions
+        .flatten()
+        .map(f64::to_radians)
+        .fold((0.0, 0.0), |(sin, cos), angle| {
+            (sin + angle.sin(), cos + angle.cos())
+        });
+    if sin.abs() < 1e-9 && cos.abs() < 1e-9 {
+        return None;
+    }
+    // Rounded first so that -0.0001° is 0° rather than 360°
+    let degrees = rounded(sin.atan2(cos).to_degrees())?;
+    let full_turn = Decimal::from(360);
+    Some(((degrees + full_turn) % full_turn).normalize())
+}
+
+/// Summarise the hours of the period into the weather of a sample
+pub fn summarise(
+    sample_id: Uuid,
+    source: &str,
+    period: &CollectionPeriod,
+    hours: Vec<WeatherHour>,
+) -> weather::ActiveModel {
+    let hours: Vec<WeatherHour> = hours
+        .into_iter()
+        .filter(|hour| period.covers(hour.time))
+        .collect();
+    let temperature = statistics(hours.iter().map(|hour| hour.temperature_c));
+    let wind_speed = statistics(hours.iter().map(|hour| hour.wind_speed_m_s));
+    let boundary_layer_height = statistics(hours.iter().map(|hour| hour.boundary_layer_height_m));
+
+    weather::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        sample_id: Set(sample_id),
+        source: Set(source.to_string()),
+        latitude: Set(period.latitude),
+        longitude: Set(period.longitude),
+        start_time: Set(period.start_time),
+        stop_time: Set(period.stop_time),
+        temperature_mean_c: Set(temperature.and_then(|(mean, _, _)| mean)),
+        temperature_min_c: Set(temperature.and_then(|(_, min, _)| min)),
+        temperature_max_c: Set(temperature.and_then(|(_, _, max)| max)),
+        wind_speed_mean_m_s: Set(wind_speed.and_then(|(mean, _, _)| mean)),
+        wind_speed_max_m_s: Set(wind_speed.and_then(|(_, _, max)| max)),
+        wind_direction_mean_deg: Set(mean_direction(
+            hours.iter().map(|hour| hour.wind_direction_deg),
+        )),
+        boundary_layer_height_mean_m: Set(boundary_layer_height.and_then(|(mean, _, _)| mean)),
+        boundary_layer_height_min_m: Set(boundary_layer_height.and_then(|(_, min, _)| min)),
+        boundary_layer_height_max_m: Set(boundary_layer_height.and_then(|(_, _, max)| max)),
+        hourly: Set(serde_json::to_value(&hours).unwrap_or_default()),
+        fetched_at: Set(Utc::now()),
+    }
+}
+
+/// Fetch the weather of the period from the configured provider. Errors are
+/// those of the provider.
+pub async fn fetch_weather(
+    config: &Config,
+    period: &CollectionPeriod,
+) -> Result<Vec<WeatherHour>, String> {
+    let end_time = period.stop_time.unwrap_or(period.start_time);
+    fetch_hourly(
+        config,
+        period.latitude.to_f64().unwrap_or_default(),
+        period.longitude.to_f64().unwrap_or_default(),
+        period.start_time.date_naive(),
+        end_time.date_naive(),
+    )
+    .await
+}
+
+/// Replace the cached weather of the sample
+pub async fn store_weather(
+    db: &DatabaseConnection,
+    weather: weather::ActiveModel,
+) -> Result<SampleWeather, DbErr> {
+    let txn = db.begin().await?;
+    weather::Entity::delete_many()
+        .filter(weather::Column::SampleId.eq(weather.sample_id.clone().unwrap()))
+        .exec(&txn)
+        .await?;
+    let inserted = weather.insert(&txn).await?;
+    txn.commit().await?;
+    Ok(inserted.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hour(time: &str, temperature_c: f64, wind_direction_deg: f64) -> WeatherHour {
+        WeatherHour {
+            time: time.parse().unwrap(),
+            temperature_c: Some(temperature_c),
+            wind_speed_m_s: Some(temperature_c / 2.0),
+            wind_direction_deg: Some(wind_direction_deg),
+            boundary_layer_height_m: None,
+        }
+    }
+
+    #[test]
+    fn test_summarise() {
+        let hours = vec![
+            hour("2025-03-01T09:00:00Z", -8.0, 90.0),
+            hour("2025-03-01T10:00:00Z", -2.0, 350.0),
+            hour("2025-03-01T11:00:00Z", 4.0, 10.0),
+            hour("2025-03-01T12:00:00Z", 10.0, 90.0),
+        ];
+        let mut period = CollectionPeriod {
+            latitude: Decimal::new(782_232, 4),
+            longitude: Decimal::new(156_267, 4),
+            start_time: "2025-03-01T10:30:00Z".parse().unwrap(),
+            stop_time: Some("2025-03-01T12:00:00Z".parse().unwrap()),
+        };
+
+        let weather = summarise(Uuid::nil(), "test", &period, hours.clone());
+        assert_eq!(weather.temperature_mean_c.unwrap(), Some(Decimal::ONE));
+        assert_eq!(weather.temperature_min_c.unwrap(), Some(Decimal::from(-2)));
+        assert_eq!(weather.wind_speed_max_m_s.unwrap(), Some(Decimal::TWO));
+        assert_eq!(
+            weather.wind_direction_mean_deg.unwrap(),
+            Some(Decimal::ZERO)
+        );
+        assert_eq!(weather.boundary_layer_height_mean_m.unwrap(), None);
+        assert_eq!(weather.hourly.unwrap().as_array().unwrap().len(), 2);
+
+        // Without a stop time, the hour the sample was taken in
+        period.stop_time = None;
+        let weather = summarise(Uuid::nil(), "test", &period, hours);
+        assert_eq!(weather.temperature_max_c.unwrap(), Some(Decimal::from(-2)));
+        assert_eq!(
+            mean_direction([Some(200.0), Some(260.0)].into_iter()),
+            Some(Decimal::from(230))
+        );
+    }
+}
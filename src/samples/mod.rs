@@ -1,5 +1,18 @@
+pub mod barcodes;
+pub mod clustering;
+pub mod custody;
+pub mod custody_events;
+pub mod hierarchy;
+pub mod metadata;
+pub mod meteorology;
 pub mod models;
+pub mod pool_sources;
+pub mod pooling;
+pub mod qc;
+pub mod storage;
+pub mod tracks;
 pub mod views;
+pub mod weather;
 mod services;
 #[cfg(test)]
 pub mod tests;
@@ -1,3 +1,6 @@
+use crate::common::include::includes;
+use crate::common::soft_delete::{soft_delete, soft_delete_many};
+use crate::common::upsert::NaturalKey;
 use crate::treatments::models::TreatmentList;
 use chrono::{DateTime, Utc};
 use crudcrate::{CRUDResource, EntityToModels, traits::MergeIntoActiveModel};
@@ -23,6 +26,22 @@ pub enum SampleType {
     Blank,
 }
 
+#[derive(
+    Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, ToSchema, Serialize, Deserialize,
+)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "sample_qc_status")]
+#[serde(rename_all = "snake_case")]
+pub enum SampleQcStatus {
+    #[sea_orm(string_value = "pending")]
+    Pending,
+    #[sea_orm(string_value = "accepted")]
+    Accepted,
+    #[sea_orm(string_value = "rejected")]
+    Rejected,
+    #[sea_orm(string_value = "flagged")]
+    Flagged,
+}
+
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, EntityToModels)]
 #[sea_orm(table_name = "samples")]
 #[crudcrate(
@@ -32,6 +51,8 @@ pub enum SampleType {
     name_plural = "samples",
     description = "This resource manages samples associated with experiments.",
     fn_get_one = get_one_sample,
+    fn_delete = delete_sample,
+    fn_delete_many = delete_many_samples,
     fn_create = create_sample_with_treatments,
     fn_update = update_sample_with_treatments,
     fn_get_all = get_all_samples,
@@ -81,8 +102,64 @@ pub struct Model {
     #[sea_orm(column_type = "Decimal(Some((9, 6)))", nullable)]
     #[crudcrate(sortable)]
     pub latitude: Option<Decimal>,
+    /// Points of a transect the sample was collected along, as
+    /// `[{"time", "longitude", "latitude"}]` in time order
+    #[sea_orm(column_type = "JsonBinary", nullable)]
+    #[crudcrate(list_model = false)]
+    pub track: Option<serde_json::Value>,
     #[crudcrate(sortable, filterable)]
     pub location_id: Option<Uuid>,
+    /// The sample this one is an aliquot of
+    #[crudcrate(sortable, filterable)]
+    pub parent_sample_id: Option<Uuid>,
+    /// Identifies the aliquot among those of its parent, e.g. "1/4" of a filter
+    #[sea_orm(column_type = "Text", nullable)]
+    #[crudcrate(sortable, filterable, fulltext)]
+    pub aliquot_label: Option<String>,
+    /// Printed on the vial's label and scanned to find the sample
+    #[sea_orm(column_type = "Text", nullable)]
+    #[crudcrate(sortable, filterable, fulltext)]
+    pub barcode: Option<String>,
+    /// Freezer the sample is stored in
+    #[sea_orm(column_type = "Text", nullable)]
+    #[crudcrate(sortable, filterable)]
+    pub storage_freezer: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    #[crudcrate(sortable, filterable)]
+    pub storage_shelf: Option<String>,
+    /// Name of the box, unique within the freezer
+    #[sea_orm(column_type = "Text", nullable)]
+    #[crudcrate(sortable, filterable)]
+    pub storage_box: Option<String>,
+    /// Position in the box, e.g. "C7"
+    #[sea_orm(column_type = "Text", nullable)]
+    #[crudcrate(sortable, filterable)]
+    pub storage_position: Option<String>,
+    /// Outcome of quality control, set by a QC review. Rejected samples
+    /// cannot be assigned to new experiment regions.
+    #[crudcrate(sortable, filterable, enum_field, create_model = false, update_model = false, on_create = SampleQcStatus::Pending)]
+    pub qc_status: SampleQcStatus,
+    /// Why the sample was rejected or flagged
+    #[sea_orm(column_type = "Text", nullable)]
+    #[crudcrate(filterable, fulltext, create_model = false, update_model = false)]
+    pub qc_reason: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    #[crudcrate(filterable, create_model = false, update_model = false)]
+    pub qc_reviewed_by: Option<String>,
+    #[crudcrate(sortable, create_model = false, update_model = false)]
+    pub qc_reviewed_at: Option<DateTime<Utc>>,
+    /// Keycloak username of the user who created it, who can work with it
+    /// outside the project and share it
+    #[sea_orm(column_type = "Text", nullable)]
+    #[crudcrate(sortable, filterable, create_model = false, update_model = false)]
+    pub created_by: Option<String>,
+    /// Lab the sample belongs to, when labs are on
+    #[sea_orm(column_type = "Text", nullable)]
+    #[crudcrate(sortable, filterable, create_model = false, update_model = false)]
+    pub lab: Option<String>,
+    /// When the sample was deleted; deleted samples are kept until restored
+    #[crudcrate(sortable, filterable, create_model = false, update_model = false)]
+    pub deleted_at: Option<DateTime<Utc>>,
     #[crudcrate(update_model = false, create_model = false, on_create = chrono::Utc::now(), sortable, list_model=false)]
     pub created_at: DateTime<Utc>,
     #[crudcrate(update_model = false, create_model = false, on_update = chrono::Utc::now(), on_create = chrono::Utc::now(), sortable)]
@@ -93,6 +170,14 @@ pub struct Model {
     #[sea_orm(ignore)]
     #[crudcrate(non_db_attr = true, default = None, list_model=false)]
     pub location: Option<crate::locations::models::Location>,
+    /// Chain of custody, oldest event first
+    #[sea_orm(ignore)]
+    #[crudcrate(non_db_attr = true, default = vec![], list_model = false, create_model = false, update_model = false)]
+    pub custody_events: Vec<super::custody_events::models::SampleCustodyEvent>,
+    /// Weather over the collection period, when it has been fetched
+    #[sea_orm(ignore)]
+    #[crudcrate(non_db_attr = true, default = None, list_model = false, create_model = false, update_model = false)]
+    pub weather: Option<super::weather::models::SampleWeather>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -105,8 +190,18 @@ pub enum Relation {
         on_delete = "NoAction"
     )]
     Locations,
+    #[sea_orm(
+        belongs_to = "Entity",
+        from = "Column::ParentSampleId",
+        to = "Column::Id",
+        on_update = "NoAction",
+        on_delete = "SetNull"
+    )]
+    Parent,
     #[sea_orm(has_many = "crate::treatments::models::Entity")]
     Treatments,
+    #[sea_orm(has_many = "super::custody_events::models::Entity")]
+    CustodyEvents,
 }
 
 impl Related<crate::locations::models::Entity> for Entity {
@@ -121,8 +216,28 @@ impl Related<crate::treatments::models::Entity> for Entity {
     }
 }
 
+impl Related<super::custody_events::models::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::CustodyEvents.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
 
+/// Samples are identified by their name at their location
+impl NaturalKey for Sample {
+    const NATURAL_KEY: &'static [&'static str] = &["name", "location_id"];
+}
+
+/// Custom `delete` keeping the sample, marked deleted
+async fn delete_sample(db: &DatabaseConnection, id: Uuid) -> Result<Uuid, DbErr> {
+    soft_delete::<Entity>(db, Column::Id, Column::DeletedAt, id).await
+}
+
+async fn delete_many_samples(db: &DatabaseConnection, ids: Vec<Uuid>) -> Result<Vec<Uuid>, DbErr> {
+    soft_delete_many::<Entity>(db, Column::Id, Column::DeletedAt, ids).await
+}
+
 async fn get_one_sample(db: &DatabaseConnection, id: Uuid) -> Result<Sample, DbErr> {
     let model = Entity::find_by_id(id)
         .one(db)
@@ -149,12 +264,71 @@ async fn get_one_sample(db: &DatabaseConnection, id: Uuid) -> Result<Sample, DbE
         treatments_with_results.push(treatment);
     }
 
+    let weather = super::meteorology::cached_weather(db, &model).await?;
     let mut sample: Sample = model.into();
     sample.treatments = treatments_with_results;
+    sample.custody_events = super::custody::list_custody_events(db, id).await?;
+    sample.weather = weather;
 
     Ok(sample)
 }
 
+/// The samples with the IDs, in no particular order, with their treatments
+/// read for all of them together. The treatments' experimental results and
+/// statistics are worked out sample by sample, unless `statistics` is left
+/// out of the relations included. Custody events and weather are left out.
+pub(crate) async fn get_samples(
+    db: &DatabaseConnection,
+    ids: &[Uuid],
+) -> Result<Vec<Sample>, DbErr> {
+    let models = Entity::find()
+        .filter(Column::Id.is_in(ids.iter().copied()))
+        .all(db)
+        .await?;
+    let mut treatments_by_sample: std::collections::HashMap<
+        Uuid,
+        Vec<crate::treatments::models::Model>,
+    > = std::collections::HashMap::new();
+    for treatment in crate::treatments::models::Entity::find()
+        .filter(crate::treatments::models::Column::SampleId.is_in(ids.iter().copied()))
+        .all(db)
+        .await?
+    {
+        if let Some(sample_id) = treatment.sample_id {
+            treatments_by_sample
+                .entry(sample_id)
+                .or_default()
+                .push(treatment);
+        }
+    }
+
+    let mut samples = Vec::with_capacity(models.len());
+    for model in models {
+        let all_experimental_results = if includes("statistics", true) {
+            super::services::fetch_experimental_results_for_sample(db, model.id).await?
+        } else {
+            Vec::new()
+        };
+        let treatments = treatments_by_sample
+            .remove(&model.id)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|treatment| {
+                super::services::treatment_to_treatment_with_results(
+                    treatment,
+                    model.id,
+                    &all_experimental_results,
+                    db,
+                )
+            })
+            .collect();
+        let mut sample: Sample = model.into();
+        sample.treatments = treatments;
+        samples.push(sample);
+    }
+    Ok(samples)
+}
+
 async fn get_all_samples(
     db: &DatabaseConnection,
     condition: &sea_orm::Condition,
@@ -196,7 +370,7 @@ async fn get_all_samples(
 
 async fn create_sample_with_treatments(
     db: &DatabaseConnection,
-    create_data: SampleCreate,
+    mut create_data: SampleCreate,
 ) -> Result<Sample, DbErr> {
     // Extract treatments before creating sample
     let treatments_to_create = if create_data.treatments.is_empty() {
@@ -204,9 +378,33 @@ async fn create_sample_with_treatments(
     } else {
         Some(create_data.treatments.clone())
     };
+    super::hierarchy::validate_parent(db, None, create_data.parent_sample_id).await?;
+    super::tracks::validate(create_data.track.as_ref())?;
+    create_data.barcode = super::barcodes::normalise(create_data.barcode.take());
+    super::barcodes::ensure_unused(db, create_data.barcode.as_deref(), None).await?;
+    super::storage::normalise([
+        &mut create_data.storage_freezer,
+        &mut create_data.storage_shelf,
+        &mut create_data.storage_box,
+        &mut create_data.storage_position,
+    ]);
+    super::storage::validate_placement(
+        db,
+        None,
+        super::storage::Placement {
+            freezer: create_data.storage_freezer.as_deref(),
+            shelf: create_data.storage_shelf.as_deref(),
+            storage_box: create_data.storage_box.as_deref(),
+            position: create_data.storage_position.as_deref(),
+        },
+    )
+    .await?;
 
     // Use the auto-generated default create logic by creating ActiveModel directly
-    let active_model: ActiveModel = create_data.into();
+    let mut active_model: ActiveModel = create_data.into();
+    active_model.created_by = sea_orm::ActiveValue::Set(crate::common::auth::current_username());
+    active_model.lab = sea_orm::ActiveValue::Set(crate::common::labs::current_lab());
+    super::metadata::validate(&active_model)?;
     let inserted = active_model.insert(db).await?;
     let sample_id = inserted.id;
 
@@ -223,10 +421,11 @@ async fn create_sample_with_treatments(
     Sample::get_one(db, sample_id).await
 }
 
+#[allow(clippy::too_many_lines)]
 async fn update_sample_with_treatments(
     db: &DatabaseConnection,
     id: Uuid,
-    update_data: SampleUpdate,
+    mut update_data: SampleUpdate,
 ) -> Result<Sample, DbErr> {
     // Extract treatments before updating sample (always process treatments, even if empty to handle deletions)
     let treatments_to_update = Some(update_data.treatments.clone());
@@ -238,8 +437,61 @@ async fn update_sample_with_treatments(
         .await?
         .ok_or_else(|| DbErr::RecordNotFound("Sample not found".to_string()))?;
 
-    let existing_active: ActiveModel = existing_model.into_active_model();
+    if let Some(parent_sample_id) = update_data.parent_sample_id {
+        super::hierarchy::validate_parent(db, Some(id), parent_sample_id).await?;
+    }
+    if let Some(track) = &update_data.track {
+        super::tracks::validate(track.as_ref())?;
+    }
+    if let Some(barcode) = &mut update_data.barcode {
+        *barcode = super::barcodes::normalise(barcode.take());
+        super::barcodes::ensure_unused(db, barcode.as_deref(), Some(id)).await?;
+    }
+    super::storage::normalise(
+        [
+            update_data.storage_freezer.as_mut(),
+            update_data.storage_shelf.as_mut(),
+            update_data.storage_box.as_mut(),
+            update_data.storage_position.as_mut(),
+        ]
+        .into_iter()
+        .flatten(),
+    );
+    // Check the placement the sample ends up with
+    super::storage::validate_placement(
+        db,
+        Some(id),
+        super::storage::Placement {
+            freezer: update_data
+                .storage_freezer
+                .as_ref()
+                .unwrap_or(&existing_model.storage_freezer)
+                .as_deref(),
+            shelf: update_data
+                .storage_shelf
+                .as_ref()
+                .unwrap_or(&existing_model.storage_shelf)
+                .as_deref(),
+            storage_box: update_data
+                .storage_box
+                .as_ref()
+                .unwrap_or(&existing_model.storage_box)
+                .as_deref(),
+            position: update_data
+                .storage_position
+                .as_ref()
+                .unwrap_or(&existing_model.storage_position)
+                .as_deref(),
+        },
+    )
+    .await?;
+
+    let existing_active: ActiveModel = existing_model.clone().into_active_model();
     let updated_active_model = update_data.merge_into_activemodel(existing_active)?;
+    super::metadata::validate(&super::metadata::merged(
+        existing_model,
+        &updated_active_model,
+    ))?;
     let _updated_sample = updated_active_model.update(db).await?;
 
     // Handle complete treatment list replacement: create new, update existing, delete missing
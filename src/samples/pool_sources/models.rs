@@ -0,0 +1,47 @@
+use chrono::{DateTime, Utc};
+use crudcrate::EntityToModels;
+use rust_decimal::Decimal;
+use sea_orm::entity::prelude::*;
+use uuid::Uuid;
+
+/// A sample that went into a pooled sample
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, EntityToModels)]
+#[sea_orm(table_name = "sample_pool_sources")]
+#[crudcrate(api_struct = "SamplePoolSource")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    #[crudcrate(primary_key, update_model = false, create_model = false, on_create = Uuid::new_v4())]
+    pub id: Uuid,
+    #[crudcrate(sortable, filterable)]
+    pub pooled_sample_id: Uuid,
+    #[crudcrate(sortable, filterable)]
+    pub source_sample_id: Uuid,
+    /// Share of the source sample that went into the pool, in (0, 1]
+    #[sea_orm(column_type = "Decimal(Some((16, 10)))")]
+    #[crudcrate(sortable)]
+    pub fraction: Decimal,
+    #[crudcrate(update_model = false, create_model = false, on_create = chrono::Utc::now(), sortable)]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "crate::samples::models::Entity",
+        from = "Column::PooledSampleId",
+        to = "crate::samples::models::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    PooledSample,
+    #[sea_orm(
+        belongs_to = "crate::samples::models::Entity",
+        from = "Column::SourceSampleId",
+        to = "crate::samples::models::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    SourceSample,
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,234 @@
+//! Pooled samples, such as several filters extracted into one suspension.
+//!
+//! A pool lists its source samples with the fraction of each that went into
+//! it, so a filter cut in half can feed two pools. The air volume a pool
+//! represents is the sum of its sources' air volumes weighted by those
+//! fractions, which is what its results are normalised by.
+
+use super::hierarchy::SampleReference;
+use super::models::{Column, Entity as Samples, Model};
+use super::pool_sources::models as pool_sources;
+use rust_decimal::Decimal;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, DatabaseConnection, DbErr, EntityTrait,
+    QueryFilter, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(Clone, Debug, Deserialize, ToSchema)]
+pub struct PoolSourceInput {
+    pub source_sample_id: Uuid,
+    /// Share of the source sample that goes into the pool, in (0, 1]
+    pub fraction: Decimal,
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct PooledSource {
+    pub sample: SampleReference,
+    pub fraction: Decimal,
+    /// The fraction of the source's air volume
+    pub air_volume_litres: Option<Decimal>,
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct PoolMembership {
+    pub sample: SampleReference,
+    pub fraction: Decimal,
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct SamplePool {
+    pub sample_id: Uuid,
+    /// Empty when the sample is not a pool
+    pub sources: Vec<PooledSource>,
+    /// Air volume the pool represents, or `None` when the sample is not a
+    /// pool or a source has no air volume
+    pub equivalent_air_volume_litres: Option<Decimal>,
+    /// Pools the sample went into
+    pub pooled_into: Vec<PoolMembership>,
+}
+
+async fn find_sample(db: &DatabaseConnection, id: Uuid) -> Result<Model, DbErr> {
+    Samples::find_by_id(id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Sample not found".to_string()))
+}
+
+/// Every sample that went into any of `ids`, directly or through other pools
+async fn all_sources(db: &DatabaseConnection, ids: Vec<Uuid>) -> Result<HashSet<Uuid>, DbErr> {
+    let mut seen = HashSet::new();
+    let mut level = ids;
+    while !level.is_empty() {
+        level = pool_sources::Entity::find()
+            .filter(pool_sources::Column::PooledSampleId.is_in(level))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|source| source.source_sample_id)
+            .filter(|id| seen.insert(*id))
+            .collect();
+    }
+    Ok(seen)
+}
+
+/// The sources of a sample and the pools it went into
+pub async fn sample_pool(db: &DatabaseConnection, id: Uuid) -> Result<SamplePool, DbErr> {
+    find_sample(db, id).await?;
+    let links = pool_sources::Entity::find()
+        .filter(
+            pool_sources::Column::PooledSampleId
+                .eq(id)
+                .or(pool_sources::Column::SourceSampleId.eq(id)),
+        )
+        .all(db)
+        .await?;
+    let linked_ids: Vec<Uuid> = links
+        .iter()
+        .flat_map(|link| [link.pooled_sample_id, link.source_sample_id])
+        .collect();
+    let samples: HashMap<Uuid, Model> = Samples::find()
+        .filter(Column::Id.is_in(linked_ids))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|sample| (sample.id, sample))
+        .collect();
+
+    let mut sources = Vec::new();
+    let mut pooled_into = Vec::new();
+    for link in &links {
+        let fraction = link.fraction.normalize();
+        if link.pooled_sample_id == id {
+            let Some(source) = samples.get(&link.source_sample_id) else {
+                continue;
+            };
+            sources.push(PooledSource {
+                sample: SampleReference::from(source),
+                fraction,
+                air_volume_litres: source
+                    .air_volume_litres
+                    .map(|volume| (volume * link.fraction).normalize()),
+            });
+        } else if let Some(pool) = samples.get(&link.pooled_sample_id) {
+            pooled_into.push(PoolMembership {
+                sample: SampleReference::from(pool),
+                fraction,
+            });
+        }
+    }
+    sources.sort_by(|a, b| a.sample.name.cmp(&b.sample.name));
+    pooled_into.sort_by(|a, b| a.sample.name.cmp(&b.sample.name));
+
+    let equivalent_air_volume_litres = if sources.is_empty() {
+        None
+    } else {
+        sources
+            .iter()
+            .map(|source| source.air_volume_litres)
+            .sum::<Option<Decimal>>()
+            .map(|volume| volume.normalize())
+    };
+
+    Ok(SamplePool {
+        sample_id: id,
+        sources,
+        equivalent_air_volume_litres,
+        pooled_into,
+    })
+}
+
+/// Replace the sources of a pool; an empty list makes the sample a plain
+/// sample again. Problems are returned as `DbErr::Custom`.
+pub async fn set_pool_sources(
+    db: &DatabaseConnection,
+    id: Uuid,
+    sources: Vec<PoolSourceInput>,
+) -> Result<SamplePool, DbErr> {
+    find_sample(db, id).await?;
+
+    let mut source_ids = Vec::new();
+    for source in &sources {
+        if source.fraction <= Decimal::ZERO || source.fraction > Decimal::ONE {
+            return Err(DbErr::Custom(format!(
+                "The fraction of source {} must be greater than 0 and at most 1",
+                source.source_sample_id
+            )));
+        }
+        if source.source_sample_id == id {
+            return Err(DbErr::Custom(
+                "A sample cannot be one of its own sources".to_string(),
+            ));
+        }
+        if source_ids.contains(&source.source_sample_id) {
+            return Err(DbErr::Custom(format!(
+                "Sample {} is listed more than once",
+                source.source_sample_id
+            )));
+        }
+        source_ids.push(source.source_sample_id);
+    }
+
+    let models: HashMap<Uuid, Model> = Samples::find()
+        .filter(Column::Id.is_in(source_ids.clone()))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|sample| (sample.id, sample))
+        .collect();
+    if let Some(missing) = source_ids.iter().find(|id| !models.contains_key(id)) {
+        return Err(DbErr::Custom(format!("Source sample {missing} not found")));
+    }
+    if all_sources(db, source_ids.clone()).await?.contains(&id) {
+        return Err(DbErr::Custom(
+            "A sample cannot be a source of a pool it was made from".to_string(),
+        ));
+    }
+
+    // What other pools already took of each source
+    let mut used: HashMap<Uuid, Decimal> = HashMap::new();
+    for link in pool_sources::Entity::find()
+        .filter(pool_sources::Column::SourceSampleId.is_in(source_ids))
+        .filter(pool_sources::Column::PooledSampleId.ne(id))
+        .all(db)
+        .await?
+    {
+        *used.entry(link.source_sample_id).or_default() += link.fraction;
+    }
+    for source in &sources {
+        let used = used
+            .get(&source.source_sample_id)
+            .copied()
+            .unwrap_or_default();
+        if used + source.fraction > Decimal::ONE {
+            return Err(DbErr::Custom(format!(
+                "Only {} of sample '{}' is left to pool",
+                (Decimal::ONE - used).normalize(),
+                models[&source.source_sample_id].name
+            )));
+        }
+    }
+
+    let txn = db.begin().await?;
+    pool_sources::Entity::delete_many()
+        .filter(pool_sources::Column::PooledSampleId.eq(id))
+        .exec(&txn)
+        .await?;
+    for source in sources {
+        pool_sources::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            pooled_sample_id: Set(id),
+            source_sample_id: Set(source.source_sample_id),
+            fraction: Set(source.fraction),
+            created_at: Set(chrono::Utc::now()),
+        }
+        .insert(&txn)
+        .await?;
+    }
+    txn.commit().await?;
+
+    sample_pool(db, id).await
+}
@@ -0,0 +1,99 @@
+//! Quality control of samples.
+//!
+//! Samples start out pending. A reviewer accepts, rejects or flags them, giving
+//! a reason for the last two. Treatments of rejected samples cannot be placed
+//! in new experiment regions; regions that already use them are kept.
+
+use super::models::{ActiveModel, Column, Entity as Samples, Sample, SampleQcStatus};
+use crate::treatments::models as treatments;
+use chrono::Utc;
+use crudcrate::CRUDResource;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr,
+    EntityTrait, IntoActiveModel, QueryFilter,
+};
+use serde::Deserialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(Clone, Debug, Deserialize, ToSchema)]
+pub struct SampleQcReview {
+    pub status: SampleQcStatus,
+    /// Required when rejecting or flagging the sample
+    pub reason: Option<String>,
+}
+
+/// Record the outcome of a QC review. `reviewed_by` is the authenticated
+/// user, when there is one.
+pub async fn review_sample(
+    db: &DatabaseConnection,
+    id: Uuid,
+    review: SampleQcReview,
+    reviewed_by: Option<String>,
+) -> Result<Sample, DbErr> {
+    let sample = Samples::find_by_id(id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Sample not found".to_string()))?;
+
+    let reason = review
+        .reason
+        .map(|reason| reason.trim().to_string())
+        .filter(|reason| !reason.is_empty());
+    if reason.is_none()
+        && matches!(
+            review.status,
+            SampleQcStatus::Rejected | SampleQcStatus::Flagged
+        )
+    {
+        return Err(DbErr::Custom(
+            "A reason is required to reject or flag a sample".to_string(),
+        ));
+    }
+
+    let mut active: ActiveModel = sample.into_active_model();
+    active.qc_status = Set(review.status);
+    active.qc_reason = Set(reason);
+    active.qc_reviewed_by = Set(reviewed_by);
+    active.qc_reviewed_at = Set(Some(Utc::now()));
+    active.last_updated = Set(Utc::now());
+    active.update(db).await?;
+
+    Sample::get_one(db, id).await
+}
+
+/// `DbErr::Custom` naming the rejected samples among those of the treatments
+pub(crate) async fn ensure_not_rejected(
+    db: &impl ConnectionTrait,
+    treatment_ids: Vec<Uuid>,
+) -> Result<(), DbErr> {
+    if treatment_ids.is_empty() {
+        return Ok(());
+    }
+    let sample_ids: Vec<Uuid> = treatments::Entity::find()
+        .filter(treatments::Column::Id.is_in(treatment_ids))
+        .all(db)
+        .await?
+        .into_iter()
+        .filter_map(|treatment| treatment.sample_id)
+        .collect();
+    if sample_ids.is_empty() {
+        return Ok(());
+    }
+    let rejected: Vec<String> = Samples::find()
+        .filter(Column::Id.is_in(sample_ids))
+        .filter(Column::QcStatus.eq(SampleQcStatus::Rejected))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|sample| format!("'{}'", sample.name))
+        .collect();
+    if rejected.is_empty() {
+        Ok(())
+    } else {
+        Err(DbErr::Custom(format!(
+            "Rejected samples cannot be assigned to experiment regions: {}",
+            rejected.join(", ")
+        )))
+    }
+}
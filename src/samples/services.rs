@@ -12,7 +12,6 @@ use crate::{
     nucleation_events::models::{NucleationEvent, NucleationStatistics},
     treatments::views::Treatment,
 };
-use rust_decimal::Decimal;
 use sea_orm::{DatabaseConnection, EntityTrait, QueryOrder, entity::prelude::*};
 use uuid::Uuid;
 
@@ -59,6 +58,9 @@ pub(super) async fn fetch_experimental_results_for_sample(
                 .await?
                 .map(|tr| tr.timestamp);
 
+            let excluded_wells =
+                crate::experiments::exclusions::excluded_well_reasons(db, experiment.id).await?;
+
             let temp_reading_ids: Vec<Uuid> = phase_transitions_data
                 .iter()
                 .map(|(transition, _)| transition.temperature_reading_id)
@@ -84,6 +86,21 @@ pub(super) async fn fetch_experimental_results_for_sample(
                     .push(probe_reading);
             }
 
+            let probe_ids: std::collections::HashSet<Uuid> = probe_readings_data
+                .iter()
+                .map(|reading| reading.probe_id)
+                .collect();
+            let probe_map: std::collections::HashMap<
+                Uuid,
+                crate::tray_configurations::probes::models::Model,
+            > = crate::tray_configurations::probes::models::Entity::find()
+                .filter(crate::tray_configurations::probes::models::Column::Id.is_in(probe_ids))
+                .all(db)
+                .await?
+                .into_iter()
+                .map(|probe| (probe.id, probe))
+                .collect();
+
             let tray_ids: Vec<Uuid> = phase_transitions_data
                 .iter()
                 .filter_map(|(_, well_opt)| well_opt.as_ref().map(|w| w.tray_id))
@@ -158,18 +175,23 @@ pub(super) async fn fetch_experimental_results_for_sample(
                     }
 
                     // Get temperature data at nucleation time from probe readings
-                    let temperature_avg = probe_readings_by_temp_id
+                    let temperatures = probe_readings_by_temp_id
                         .get(&transition.temperature_reading_id)
                         .and_then(|probe_readings| {
-                            let temperature_values: Vec<Decimal> =
-                                probe_readings.iter().map(|pr| pr.temperature).collect();
-
-                            if temperature_values.is_empty() {
-                                None
-                            } else {
-                                let sum: Decimal = temperature_values.iter().sum();
-                                Some(sum / Decimal::from(temperature_values.len()))
-                            }
+                            crate::experiments::probe_temperature_readings::models::mean_temperatures(
+                                probe_readings,
+                                &probe_map,
+                            )
+                        });
+                    let temperature_avg = temperatures.map(|(calibrated, _)| calibrated);
+                    let temperature_interpolated = probe_readings_by_temp_id
+                        .get(&transition.temperature_reading_id)
+                        .and_then(|probe_readings| {
+                            crate::experiments::probe_temperature_readings::models::interpolated_temperature(
+                                probe_readings,
+                                &probe_map,
+                                well,
+                            )
                         });
 
                     let nucleation_time_seconds = experiment_start_time
@@ -187,16 +209,20 @@ pub(super) async fn fetch_experimental_results_for_sample(
                         experiment_id: experiment.id,
                         experiment_name: experiment.name.clone(),
                         experiment_date: experiment.performed_at,
+                        well_id: Some(well.id),
                         well_coordinate,
                         tray_name: Some(tray_name.clone()),
                         nucleation_time_seconds,
                         nucleation_temperature_avg_celsius: temperature_avg,
                         freezing_time_seconds: nucleation_time_seconds, // UI compatibility
                         freezing_temperature_avg: temperature_avg,      // UI compatibility
+                        nucleation_temperature_raw_avg_celsius: temperatures.map(|(_, raw)| raw),
+                        nucleation_temperature_interpolated_celsius: temperature_interpolated,
                         dilution_factor: region.dilution_factor,
                         final_state: "frozen".to_string(), // Since this is a 0→1 transition
                         treatment_id: treatment.map(|t| t.id),
                         treatment_name: treatment.map(|t| format!("{:?}", t.name)), // Convert enum to string
+                        excluded: excluded_wells.contains_key(&well.id),
                     };
 
                     nucleation_events.push(nucleation_event);
@@ -224,6 +250,7 @@ pub(super) fn treatment_to_treatment_with_results(
     Treatment {
         id: treatment.id,
         sample_id: Some(sample_id),
+        deleted_at: treatment.deleted_at,
         created_at: treatment.created_at,
         last_updated: treatment.last_updated,
         name: treatment.name,
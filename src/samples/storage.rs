@@ -0,0 +1,228 @@
+//! Where samples are stored in the lab.
+//!
+//! A stored sample is in a freezer and, usually, on a shelf in a box at a
+//! position such as "C7". Box names are only unique within a freezer, so a box
+//! is identified by its freezer and name, and a position holds one sample.
+
+use super::models::{Column, Entity as Samples, Model};
+use sea_orm::{ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// `(shelf, box)` of samples in a freezer
+type Shelving = (Option<String>, Option<String>);
+
+/// The storage fields of a sample
+#[derive(Clone, Copy, Debug, Default)]
+pub(super) struct Placement<'a> {
+    pub freezer: Option<&'a str>,
+    pub shelf: Option<&'a str>,
+    pub storage_box: Option<&'a str>,
+    pub position: Option<&'a str>,
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct StoredSample {
+    pub id: Uuid,
+    pub name: String,
+    pub barcode: Option<String>,
+    pub position: Option<String>,
+}
+
+/// A box and the samples in it
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct StorageBox {
+    pub freezer: String,
+    pub name: String,
+    /// Normally one; more when samples of the box were recorded on different
+    /// shelves
+    pub shelves: Vec<String>,
+    /// In position order
+    pub samples: Vec<StoredSample>,
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct BoxOccupancy {
+    pub shelf: Option<String>,
+    /// `None` for samples stored in the freezer outside a box
+    pub name: Option<String>,
+    pub sample_count: usize,
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct FreezerOccupancy {
+    pub freezer: String,
+    pub sample_count: usize,
+    /// Sorted by shelf then box
+    pub boxes: Vec<BoxOccupancy>,
+}
+
+/// Trim each field, clearing blank ones
+pub(super) fn normalise<'a>(fields: impl IntoIterator<Item = &'a mut Option<String>>) {
+    for field in fields {
+        *field = field
+            .take()
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty());
+    }
+}
+
+/// Check that `sample_id`, or a new sample when `None`, can be stored at
+/// `placement`. Problems are returned as `DbErr::Custom`.
+pub(super) async fn validate_placement(
+    db: &DatabaseConnection,
+    sample_id: Option<Uuid>,
+    placement: Placement<'_>,
+) -> Result<(), DbErr> {
+    let Some(freezer) = placement.freezer else {
+        if placement.shelf.is_some()
+            || placement.storage_box.is_some()
+            || placement.position.is_some()
+        {
+            return Err(DbErr::Custom(
+                "A sample stored on a shelf, in a box or at a position needs a freezer".to_string(),
+            ));
+        }
+        return Ok(());
+    };
+    let Some(position) = placement.position else {
+        return Ok(());
+    };
+    let Some(storage_box) = placement.storage_box else {
+        return Err(DbErr::Custom(
+            "A sample stored at a position needs a box".to_string(),
+        ));
+    };
+
+    let mut query = Samples::find()
+        .filter(Column::StorageFreezer.eq(freezer))
+        .filter(Column::StorageBox.eq(storage_box))
+        .filter(Column::StoragePosition.eq(position));
+    if let Some(sample_id) = sample_id {
+        query = query.filter(Column::Id.ne(sample_id));
+    }
+    match query.one(db).await? {
+        Some(other) => Err(DbErr::Custom(format!(
+            "Position {position} of box {storage_box} in freezer {freezer} already holds sample '{}'",
+            other.name
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Sort key putting "A2" before "A10"
+fn position_key(position: Option<&str>) -> (String, u64, String) {
+    let position = position.unwrap_or_default();
+    let digits_start = position
+        .find(|c: char| c.is_ascii_digit())
+        .unwrap_or(position.len());
+    let (prefix, rest) = position.split_at(digits_start);
+    let digits_end = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    let (number, suffix) = rest.split_at(digits_end);
+    (
+        prefix.to_uppercase(),
+        number.parse().unwrap_or_default(),
+        suffix.to_string(),
+    )
+}
+
+/// Every box named `name`, in any freezer, with its contents
+pub async fn find_box(db: &DatabaseConnection, name: &str) -> Result<Vec<StorageBox>, DbErr> {
+    let samples = Samples::find()
+        .filter(Column::StorageBox.eq(name.trim()))
+        .filter(Column::StorageFreezer.is_not_null())
+        .order_by_asc(Column::StorageFreezer)
+        .all(db)
+        .await?;
+
+    let mut by_freezer: BTreeMap<String, Vec<Model>> = BTreeMap::new();
+    for sample in samples {
+        if let Some(freezer) = sample.storage_freezer.clone() {
+            by_freezer.entry(freezer).or_default().push(sample);
+        }
+    }
+    if by_freezer.is_empty() {
+        return Err(DbErr::RecordNotFound(format!(
+            "No sample is stored in box {name}"
+        )));
+    }
+
+    Ok(by_freezer
+        .into_iter()
+        .map(|(freezer, mut samples)| {
+            samples.sort_by_cached_key(|sample| position_key(sample.storage_position.as_deref()));
+            let mut shelves: Vec<String> = samples
+                .iter()
+                .filter_map(|sample| sample.storage_shelf.clone())
+                .collect();
+            shelves.sort();
+            shelves.dedup();
+            StorageBox {
+                freezer,
+                name: name.trim().to_string(),
+                shelves,
+                samples: samples
+                    .into_iter()
+                    .map(|sample| StoredSample {
+                        id: sample.id,
+                        name: sample.name,
+                        barcode: sample.barcode,
+                        position: sample.storage_position,
+                    })
+                    .collect(),
+            }
+        })
+        .collect())
+}
+
+/// Number of samples in each freezer, and on each shelf and in each box of it
+pub async fn freezer_occupancy(db: &DatabaseConnection) -> Result<Vec<FreezerOccupancy>, DbErr> {
+    let samples = Samples::find()
+        .filter(Column::StorageFreezer.is_not_null())
+        .all(db)
+        .await?;
+
+    let mut counts: BTreeMap<String, BTreeMap<Shelving, usize>> = BTreeMap::new();
+    for sample in samples {
+        let Some(freezer) = sample.storage_freezer else {
+            continue;
+        };
+        *counts
+            .entry(freezer)
+            .or_default()
+            .entry((sample.storage_shelf, sample.storage_box))
+            .or_default() += 1;
+    }
+
+    Ok(counts
+        .into_iter()
+        .map(|(freezer, boxes)| FreezerOccupancy {
+            freezer,
+            sample_count: boxes.values().sum(),
+            boxes: boxes
+                .into_iter()
+                .map(|((shelf, name), sample_count)| BoxOccupancy {
+                    shelf,
+                    name,
+                    sample_count,
+                })
+                .collect(),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_position_key() {
+        let mut positions = vec!["B1", "A10", "a2", "A1", "A2b"];
+        positions.sort_by_cached_key(|position| position_key(Some(position)));
+        assert_eq!(positions, vec!["A1", "a2", "A2b", "A10", "B1"]);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::config::test_helpers::setup_test_app;
+use crate::config::test_helpers::{send_json, setup_test_app};
 use axum::body::{Body, to_bytes};
 use axum::http::{Request, StatusCode};
 use serde_json::{Value, json};
@@ -1524,7 +1524,6 @@ async fn test_sample_update_treatment_crud_comprehensive() {
         "Heat treatment should be deleted (not in update)"
     );
 
-
     // Step 4: Test edge case - empty treatments list should delete all treatments
     let empty_treatments_data = json!({
         "treatments": []
@@ -1675,3 +1674,1060 @@ async fn test_sample_update_treatment_validation() {
     );
 
 }
+
+async fn get_json(app: &axum::Router, uri: &str) -> (StatusCode, Value) {
+    let response = app
+        .clone()
+        .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    extract_response_body(response).await
+}
+
+#[tokio::test]
+#[allow(clippy::too_many_lines)]
+async fn test_sample_aliquot_hierarchy() {
+    let app = setup_test_app().await;
+    let create = |name: &str, parent: Option<&str>, label: Option<&str>| {
+        json!({
+            "name": name,
+            "type": "filter",
+            "parent_sample_id": parent,
+            "aliquot_label": label,
+        })
+    };
+
+    let (status, filter) = send_json(
+        &app,
+        "POST",
+        "/api/samples",
+        Some(&create("Filter", None, None)),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{filter}");
+    let filter_id = filter["id"].as_str().unwrap();
+    assert!(filter["parent_sample_id"].is_null());
+
+    let (status, half_b) = send_json(
+        &app,
+        "POST",
+        "/api/samples",
+        Some(&create("Filter half B", Some(filter_id), Some("B"))),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{half_b}");
+    let (status, half_a) = send_json(
+        &app,
+        "POST",
+        "/api/samples",
+        Some(&create("Filter half A", Some(filter_id), Some("A"))),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{half_a}");
+    assert_eq!(half_a["parent_sample_id"], filter_id);
+    assert_eq!(half_a["aliquot_label"], "A");
+    let half_a_id = half_a["id"].as_str().unwrap();
+    let (status, quarter) = send_json(
+        &app,
+        "POST",
+        "/api/samples",
+        Some(&create("Filter quarter A1", Some(half_a_id), Some("A1"))),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{quarter}");
+    let quarter_id = quarter["id"].as_str().unwrap();
+
+    // Aliquots are filterable by parent
+    let (status, children) = get_json(
+        &app,
+        &format!("/api/samples?filter=%7B%22parent_sample_id%22%3A%22{filter_id}%22%7D"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(children.as_array().unwrap().len(), 2);
+
+    let (status, hierarchy) = get_json(&app, &format!("/api/samples/{filter_id}/hierarchy")).await;
+    assert_eq!(status, StatusCode::OK, "{hierarchy}");
+    assert!(hierarchy["ancestors"].as_array().unwrap().is_empty());
+    let aliquots = hierarchy["sample"]["aliquots"].as_array().unwrap();
+    assert_eq!(aliquots.len(), 2);
+    assert_eq!(aliquots[0]["aliquot_label"], "A");
+    assert_eq!(aliquots[0]["aliquots"][0]["id"], quarter_id);
+    assert_eq!(aliquots[1]["aliquot_label"], "B");
+    assert!(aliquots[1]["aliquots"].as_array().unwrap().is_empty());
+
+    let (status, hierarchy) = get_json(&app, &format!("/api/samples/{quarter_id}/hierarchy")).await;
+    assert_eq!(status, StatusCode::OK);
+    let ancestors: Vec<&str> = hierarchy["ancestors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|sample| sample["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(ancestors, ["Filter", "Filter half A"]);
+
+    let (status, rollup) = get_json(&app, &format!("/api/samples/{filter_id}/rollup")).await;
+    assert_eq!(status, StatusCode::OK, "{rollup}");
+    let names: Vec<&str> = rollup["samples"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| entry["sample"]["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "Filter",
+            "Filter half A",
+            "Filter quarter A1",
+            "Filter half B"
+        ]
+    );
+    assert!(rollup["statistics"].is_null());
+
+    // A sample cannot be placed below its own aliquots
+    let (status, body) = send_json(
+        &app,
+        "PUT",
+        &format!("/api/samples/{filter_id}"),
+        Some(&json!({"parent_sample_id": quarter_id})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(
+        body.to_string().contains("is an aliquot of this sample"),
+        "{body}"
+    );
+    let (status, _) = send_json(
+        &app,
+        "PUT",
+        &format!("/api/samples/{filter_id}"),
+        Some(&json!({"parent_sample_id": filter_id})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        "/api/samples",
+        Some(&create("Orphan", Some(&Uuid::new_v4().to_string()), None)),
+    )
+    .await;
+    assert!(!status.is_success());
+
+    let (status, _) = get_json(&app, &format!("/api/samples/{}/hierarchy", Uuid::new_v4())).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_sample_barcode_lookup_and_label() {
+    let app = setup_test_app().await;
+    let location_id = create_test_location(&app).await;
+    let (status, vial) = send_json(
+        &app,
+        "POST",
+        "/api/samples",
+        Some(&json!({
+            "name": "Jungfraujoch filter 12",
+            "type": "filter",
+            "barcode": "  JFJ-0012 ",
+            "aliquot_label": "1/2",
+            "start_time": "2025-03-04T08:00:00Z",
+            "location_id": location_id,
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{vial}");
+    assert_eq!(vial["barcode"], "JFJ-0012");
+    let vial_id = vial["id"].as_str().unwrap();
+
+    let (status, found) = get_json(&app, "/api/samples/by-barcode/JFJ-0012").await;
+    assert_eq!(status, StatusCode::OK, "{found}");
+    assert_eq!(found["id"], vial_id);
+    assert!(found["treatments"].is_array());
+
+    let (status, label) = get_json(&app, &format!("/api/samples/{vial_id}/label")).await;
+    assert_eq!(status, StatusCode::OK, "{label}");
+    assert_eq!(label["code"], "JFJ-0012");
+    assert_eq!(label["title"], "Jungfraujoch filter 12");
+    assert_eq!(label["lines"][0], "Filter, aliquot 1/2");
+    assert_eq!(label["lines"][1], "2025-03-04 08:00 UTC");
+    assert_eq!(label["lines"].as_array().unwrap().len(), 3);
+
+    // Without a barcode the label encodes the sample ID, which resolves too
+    let (status, unlabelled) = send_json(
+        &app,
+        "POST",
+        "/api/samples",
+        Some(&json!({"name": "Unlabelled blank", "type": "blank"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let unlabelled_id = unlabelled["id"].as_str().unwrap();
+    let (_, label) = get_json(&app, &format!("/api/samples/{unlabelled_id}/label")).await;
+    assert_eq!(label["code"], unlabelled_id);
+    let (status, found) = get_json(&app, &format!("/api/samples/by-barcode/{unlabelled_id}")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(found["id"], unlabelled_id);
+
+    // Barcodes are unique
+    let (status, body) = send_json(
+        &app,
+        "PUT",
+        &format!("/api/samples/{unlabelled_id}"),
+        Some(&json!({"barcode": "JFJ-0012", "treatments": []})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(
+        body.to_string()
+            .contains("already used by sample 'Jungfraujoch filter 12'"),
+        "{body}"
+    );
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        "/api/samples",
+        Some(&json!({"name": "Copy", "type": "blank", "barcode": "JFJ-0012"})),
+    )
+    .await;
+    assert!(!status.is_success());
+
+    let (status, _) = get_json(&app, "/api/samples/by-barcode/UNKNOWN-1").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_sample_custody_log() {
+    let app = setup_test_app().await;
+    let (status, sample) = send_json(
+        &app,
+        "POST",
+        "/api/samples",
+        Some(&json!({"name": "Ny-Ålesund filter 3", "type": "filter"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let sample_id = sample["id"].as_str().unwrap();
+    assert!(sample["custody_events"].as_array().unwrap().is_empty());
+    let uri = format!("/api/samples/{sample_id}/custody-events");
+
+    // Logged out of order, listed in the order they took place
+    let (status, shipped) = send_json(
+        &app,
+        "POST",
+        &uri,
+        Some(&json!({
+            "event_type": "shipped",
+            "occurred_at": "2025-05-02T09:00:00Z",
+            "username": "field.team",
+            "location": "Courier, dry ice"
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{shipped}");
+    assert_eq!(shipped["event_type"], "shipped");
+    assert!(shipped["recorded_by"].is_null());
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        &uri,
+        Some(&json!({"event_type": "collected", "occurred_at": "2025-05-01T12:00:00Z"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, stored) = send_json(
+        &app,
+        "POST",
+        &uri,
+        Some(&json!({"event_type": "stored", "location": "Freezer B, rack 2", "notes": "-80 °C"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert!(stored["occurred_at"].is_string());
+
+    let (status, detail) = get_json(&app, &format!("/api/samples/{sample_id}")).await;
+    assert_eq!(status, StatusCode::OK);
+    let events: Vec<&str> = detail["custody_events"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|event| event["event_type"].as_str().unwrap())
+        .collect();
+    assert_eq!(events, ["collected", "shipped", "stored"]);
+    assert_eq!(detail["custody_events"][1]["location"], "Courier, dry ice");
+
+    let (status, _) = send_json(&app, "POST", &uri, Some(&json!({"event_type": "lost"}))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        &format!("/api/samples/{}/custody-events", Uuid::new_v4()),
+        Some(&json!({"event_type": "received"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // The log is append-only
+    let (status, _) = send_json(&app, "DELETE", &uri, Some(&json!({}))).await;
+    assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED);
+}
+
+#[tokio::test]
+#[allow(clippy::too_many_lines)]
+async fn test_sample_storage_inventory() {
+    let app = setup_test_app().await;
+    let freezer = format!("Freezer {}", &Uuid::new_v4().to_string()[..8]);
+    let mut ids = Vec::new();
+    for (name, position) in [("Vial A10", "A10"), ("Vial A2", "A2"), ("Vial B1", "B1")] {
+        let (status, sample) = send_json(
+            &app,
+            "POST",
+            "/api/samples",
+            Some(&json!({
+                "name": name,
+                "type": "bulk",
+                "storage_freezer": freezer,
+                "storage_shelf": "2",
+                "storage_box": " Box 7 ",
+                "storage_position": position,
+            })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED, "{sample}");
+        assert_eq!(sample["storage_box"], "Box 7");
+        ids.push(sample["id"].as_str().unwrap().to_string());
+    }
+    let (status, loose) = send_json(
+        &app,
+        "POST",
+        "/api/samples",
+        Some(&json!({"name": "Loose filter", "type": "filter", "storage_freezer": freezer, "storage_shelf": "1"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{loose}");
+    let loose_id = loose["id"].as_str().unwrap();
+
+    let (status, boxes) = get_json(&app, "/api/samples/storage/boxes/Box%207").await;
+    assert_eq!(status, StatusCode::OK, "{boxes}");
+    let found = boxes
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|storage_box| storage_box["freezer"] == freezer.as_str())
+        .expect("box in the freezer");
+    assert_eq!(found["shelves"], json!(["2"]));
+    let names: Vec<&str> = found["samples"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|sample| sample["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, vec!["Vial A2", "Vial A10", "Vial B1"]);
+
+    let (status, _) = get_json(&app, "/api/samples/storage/boxes/No%20such%20box").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, freezers) = get_json(&app, "/api/samples/storage/freezers").await;
+    assert_eq!(status, StatusCode::OK, "{freezers}");
+    let occupancy = freezers
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|occupancy| occupancy["freezer"] == freezer.as_str())
+        .expect("freezer occupancy");
+    assert_eq!(occupancy["sample_count"], 4);
+    assert_eq!(
+        occupancy["boxes"],
+        json!([
+            {"shelf": "1", "name": null, "sample_count": 1},
+            {"shelf": "2", "name": "Box 7", "sample_count": 3},
+        ])
+    );
+
+    // A position holds one sample
+    let (status, body) = send_json(
+        &app,
+        "PUT",
+        &format!("/api/samples/{loose_id}"),
+        Some(&json!({"storage_box": "Box 7", "storage_position": "A2", "treatments": []})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(
+        body.to_string().contains("already holds sample 'Vial A2'"),
+        "{body}"
+    );
+
+    // A position needs a box, and a box a freezer
+    let (status, _) = send_json(
+        &app,
+        "PUT",
+        &format!("/api/samples/{loose_id}"),
+        Some(&json!({"storage_position": "C3", "treatments": []})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let (status, _) = send_json(
+        &app,
+        "PUT",
+        &format!("/api/samples/{}", ids[0]),
+        Some(&json!({"storage_freezer": null, "treatments": []})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    // Moving a sample within its box keeps its other storage fields
+    let (status, moved) = send_json(
+        &app,
+        "PUT",
+        &format!("/api/samples/{}", ids[0]),
+        Some(&json!({"storage_position": "C1", "treatments": []})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{moved}");
+    assert_eq!(moved["storage_position"], "C1");
+    assert_eq!(moved["storage_box"], "Box 7");
+    assert_eq!(moved["storage_freezer"], freezer.as_str());
+}
+
+#[tokio::test]
+#[allow(clippy::too_many_lines)]
+async fn test_pooled_samples() {
+    let app = setup_test_app().await;
+    let mut filter_ids = Vec::new();
+    for (name, air_volume) in [("Pool source A", 1000), ("Pool source B", 3000)] {
+        let (status, sample) = send_json(
+            &app,
+            "POST",
+            "/api/samples",
+            Some(&json!({"name": name, "type": "filter", "air_volume_litres": air_volume})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED, "{sample}");
+        filter_ids.push(sample["id"].as_str().unwrap().to_string());
+    }
+    let (status, pool) = send_json(
+        &app,
+        "POST",
+        "/api/samples",
+        Some(&json!({"name": "Pooled suspension", "type": "bulk"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{pool}");
+    let pool_id = pool["id"].as_str().unwrap();
+
+    let (status, pooling) = send_json(
+        &app,
+        "PUT",
+        &format!("/api/samples/{pool_id}/pool"),
+        Some(&json!([
+            {"source_sample_id": filter_ids[0], "fraction": "0.5"},
+            {"source_sample_id": filter_ids[1], "fraction": "0.25"},
+        ])),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{pooling}");
+    assert_eq!(pooling["sources"][0]["sample"]["name"], "Pool source A");
+    assert_eq!(pooling["sources"][0]["fraction"], "0.5");
+    assert_eq!(pooling["sources"][0]["air_volume_litres"], "500");
+    assert_eq!(pooling["sources"][1]["air_volume_litres"], "750");
+    assert_eq!(pooling["equivalent_air_volume_litres"], "1250");
+
+    // Provenance is visible from the sources
+    let (status, source) = get_json(&app, &format!("/api/samples/{}/pool", filter_ids[0])).await;
+    assert_eq!(status, StatusCode::OK, "{source}");
+    assert!(source["sources"].as_array().unwrap().is_empty());
+    assert!(source["equivalent_air_volume_litres"].is_null());
+    assert_eq!(source["pooled_into"][0]["sample"]["id"], pool_id);
+
+    // A second pool can only take what is left of a source
+    let (_, second) = send_json(
+        &app,
+        "POST",
+        "/api/samples",
+        Some(&json!({"name": "Second pool", "type": "bulk"})),
+    )
+    .await;
+    let second_id = second["id"].as_str().unwrap();
+    let (status, body) = send_json(
+        &app,
+        "PUT",
+        &format!("/api/samples/{second_id}/pool"),
+        Some(&json!([{"source_sample_id": filter_ids[0], "fraction": "0.75"}])),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(
+        body.to_string()
+            .contains("Only 0.5 of sample 'Pool source A' is left to pool"),
+        "{body}"
+    );
+
+    // Pools cannot feed themselves, directly or through another pool
+    let (status, _) = send_json(
+        &app,
+        "PUT",
+        &format!("/api/samples/{second_id}/pool"),
+        Some(&json!([{"source_sample_id": pool_id, "fraction": "1"}])),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send_json(
+        &app,
+        "PUT",
+        &format!("/api/samples/{pool_id}/pool"),
+        Some(&json!([{"source_sample_id": second_id, "fraction": "1"}])),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    for fraction in ["0", "1.5"] {
+        let (status, _) = send_json(
+            &app,
+            "PUT",
+            &format!("/api/samples/{second_id}/pool"),
+            Some(&json!([{"source_sample_id": filter_ids[1], "fraction": fraction}])),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    // An empty list removes the pooling
+    let (status, cleared) = send_json(
+        &app,
+        "PUT",
+        &format!("/api/samples/{second_id}/pool"),
+        Some(&json!([])),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{cleared}");
+    assert!(cleared["sources"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_sample_metadata_rules() {
+    let app = setup_test_app().await;
+
+    let (status, rules) = get_json(&app, "/api/samples/type-rules").await;
+    assert_eq!(status, StatusCode::OK, "{rules}");
+    assert_eq!(
+        rules["filter"]["properties"]["air_volume_litres"]["minimum"],
+        0.0
+    );
+    assert!(rules["blank"]["required"].is_array());
+
+    let (status, check) = send_json(
+        &app,
+        "POST",
+        "/api/samples/validate",
+        Some(&json!({"name": "Leaky filter", "type": "filter", "air_volume_litres": -5, "flow_litres_per_minute": 10})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{check}");
+    assert_eq!(check["valid"], false);
+    assert_eq!(
+        check["problems"],
+        json!([{"field": "air_volume_litres", "message": "air_volume_litres must be at least 0"}])
+    );
+
+    let (status, sample) = send_json(
+        &app,
+        "POST",
+        "/api/samples",
+        Some(&json!({"name": "Checked filter", "type": "filter", "air_volume_litres": 1200})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{sample}");
+    let sample_id = sample["id"].as_str().unwrap();
+
+    // Updates are checked once merged into the saved sample
+    let (status, body) = send_json(
+        &app,
+        "PUT",
+        &format!("/api/samples/{sample_id}"),
+        Some(&json!({"well_volume_litres": -0.00005, "treatments": []})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(
+        body.to_string()
+            .contains("well_volume_litres must be at least 0"),
+        "{body}"
+    );
+
+    let (status, body) = send_json(
+        &app,
+        "POST",
+        "/api/samples",
+        Some(&json!({"name": "Negative bulk", "type": "bulk", "total_volume": -1})),
+    )
+    .await;
+    assert!(!status.is_success(), "{body}");
+}
+
+#[tokio::test]
+async fn test_spatial_filters() {
+    let app = setup_test_app().await;
+
+    for resource in ["samples", "locations"] {
+        let (status, body) = get_json(&app, &format!("/api/{resource}/spatial")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+        assert!(body["error"].as_str().unwrap().contains("within_bbox"));
+
+        let (status, body) = get_json(
+            &app,
+            &format!("/api/{resource}/spatial?within_radius=7.98,46.55,0"),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+        assert!(body["error"].as_str().unwrap().contains("positive distance"));
+
+        // The test database has no PostGIS
+        let (status, body) = get_json(
+            &app,
+            &format!("/api/{resource}/spatial?within_bbox=5.9,45.8,10.5,47.8"),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+        assert!(body["error"].as_str().unwrap().contains("PostGIS"));
+    }
+}
+
+#[tokio::test]
+#[allow(clippy::too_many_lines)]
+async fn test_sample_weather() {
+    use crate::config::Config;
+    use crate::config::test_helpers::setup_test_db;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // A provider answering like Open-Meteo's archive API and counting requests
+    let requests = Arc::new(AtomicUsize::new(0));
+    let provider = axum::Router::new().route(
+        "/v1/archive",
+        axum::routing::get({
+            let requests = requests.clone();
+            move |axum::extract::Query(query): axum::extract::Query<
+                std::collections::HashMap<String, String>,
+            >| async move {
+                requests.fetch_add(1, Ordering::SeqCst);
+                assert_eq!(query["start_date"], "2025-03-01");
+                assert_eq!(query["wind_speed_unit"], "ms");
+                axum::Json(json!({
+                    "hourly": {
+                        "time": ["2025-03-01T09:00", "2025-03-01T10:00", "2025-03-01T11:00"],
+                        "temperature_2m": [-9.5, -8.0, -6.0],
+                        "wind_speed_10m": [3.0, 4.0, 6.0],
+                        "wind_direction_10m": [180.0, 200.0, 260.0],
+                        "boundary_layer_height": [250.0, 300.0, null]
+                    }
+                }))
+            }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, provider).await.unwrap() });
+
+    let db = setup_test_db().await;
+    let mut config = Config::for_tests();
+    config.keycloak_url = String::new();
+    config.weather_api_url = Some(format!("http://{address}/v1/archive"));
+    let app = crate::routes::build_router(&db, &config);
+
+    let (status, sample) = send_json(
+        &app,
+        "POST",
+        "/api/samples",
+        Some(&json!({
+            "name": "Jungfraujoch filter 1",
+            "type": "filter",
+            "start_time": "2025-03-01T10:00:00Z",
+            "stop_time": "2025-03-01T12:00:00Z",
+            "latitude": "46.5475",
+            "longitude": "7.9851"
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{sample}");
+    let sample_id = sample["id"].as_str().unwrap();
+    assert!(sample["weather"].is_null());
+
+    let (status, weather) = get_json(&app, &format!("/api/samples/{sample_id}/weather")).await;
+    assert_eq!(status, StatusCode::OK, "{weather}");
+    assert_eq!(weather["temperature_mean_c"], "-7");
+    assert_eq!(weather["wind_speed_max_m_s"], "6");
+    assert_eq!(weather["wind_direction_mean_deg"], "230");
+    assert_eq!(weather["boundary_layer_height_max_m"], "300");
+    assert_eq!(weather["hourly"].as_array().unwrap().len(), 2);
+
+    // Cached with the sample until its times change
+    let (_, weather) = get_json(&app, &format!("/api/samples/{sample_id}/weather")).await;
+    assert_eq!(weather["temperature_mean_c"], "-7");
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+    let (_, sample) = get_json(&app, &format!("/api/samples/{sample_id}")).await;
+    assert_eq!(sample["weather"]["temperature_min_c"], "-8");
+
+    let (status, _) = send_json(
+        &app,
+        "PUT",
+        &format!("/api/samples/{sample_id}"),
+        Some(&json!({"stop_time": "2025-03-01T11:00:00Z"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, sample) = get_json(&app, &format!("/api/samples/{sample_id}")).await;
+    assert!(sample["weather"].is_null());
+    let (_, weather) = get_json(&app, &format!("/api/samples/{sample_id}/weather")).await;
+    assert_eq!(weather["temperature_mean_c"], "-8");
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+    // Samples without coordinates have no weather
+    let (_, blank) = send_json(
+        &app,
+        "POST",
+        "/api/samples",
+        Some(&json!({"name": "Field blank", "type": "blank"})),
+    )
+    .await;
+    let (status, _) = get_json(
+        &app,
+        &format!("/api/samples/{}/weather", blank["id"].as_str().unwrap()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    // Nothing is fetched without a provider
+    let app = setup_test_app().await;
+    let (_, sample) = send_json(
+        &app,
+        "POST",
+        "/api/samples",
+        Some(&json!({
+            "name": "Unenriched filter",
+            "type": "filter",
+            "start_time": "2025-03-01T10:00:00Z",
+            "latitude": "46.5475",
+            "longitude": "7.9851"
+        })),
+    )
+    .await;
+    let (status, _) = get_json(
+        &app,
+        &format!("/api/samples/{}/weather", sample["id"].as_str().unwrap()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+}
+
+#[tokio::test]
+async fn test_sample_clusters() {
+    let app = setup_test_app().await;
+    let (project_id, location_id) = create_test_project_and_location(&app, "clusters").await;
+    for (name, longitude, latitude, location) in [
+        ("Jungfraujoch 1", "7.9851", "46.5475", Some(location_id)),
+        ("Jungfraujoch 2", "7.9861", "46.5480", Some(location_id)),
+        ("Payerne", "6.9440", "46.8130", None),
+        ("Ny-Ålesund", "11.9224", "78.9243", Some(location_id)),
+    ] {
+        let (status, sample) = send_json(
+            &app,
+            "POST",
+            "/api/samples",
+            Some(&json!({
+                "name": name,
+                "type": "bulk",
+                "longitude": longitude,
+                "latitude": latitude,
+                "location_id": location
+            })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED, "{sample}");
+    }
+
+    let (status, clusters) = get_json(&app, "/api/samples/clusters?zoom=3").await;
+    assert_eq!(status, StatusCode::OK, "{clusters}");
+    let counts: Vec<u64> = clusters
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|cluster| cluster["count"].as_u64().unwrap())
+        .collect();
+    assert_eq!(counts, vec![3, 1]);
+    assert!(clusters[1]["sample_id"].is_string());
+
+    let (_, clusters) = get_json(
+        &app,
+        &format!("/api/samples/clusters?zoom=9&within_bbox=5,45,10,48&project_id={project_id}"),
+    )
+    .await;
+    assert_eq!(clusters.as_array().unwrap().len(), 1);
+    assert_eq!(clusters[0]["count"], 2);
+    assert!(clusters[0]["sample_id"].is_null());
+
+    let (status, _) = get_json(&app, "/api/samples/clusters?zoom=23").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = get_json(&app, "/api/samples/clusters?zoom=4&within_bbox=10,45,5,48").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_sample_track() {
+    let app = setup_test_app().await;
+    let track = json!([
+        {"time": "2025-06-01T00:00:00Z", "longitude": 0.0, "latitude": 0.0},
+        {"time": "2025-06-01T01:00:00Z", "longitude": 0.5, "latitude": 0.0},
+        {"time": "2025-06-01T02:00:00Z", "longitude": 1.0, "latitude": 0.01},
+        {"time": "2025-06-01T03:00:00Z", "longitude": 1.5, "latitude": 0.0},
+        {"time": "2025-06-01T04:00:00Z", "longitude": 2.0, "latitude": 0.0}
+    ]);
+    let (status, sample) = send_json(
+        &app,
+        "POST",
+        "/api/samples",
+        Some(&json!({
+            "name": "Transect filter",
+            "type": "filter",
+            "start_time": "2025-06-01T00:00:00Z",
+            "stop_time": "2025-06-01T04:00:00Z",
+            "track": track
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{sample}");
+    assert_eq!(sample["track"], track);
+    let sample_id = sample["id"].as_str().unwrap();
+
+    let (status, body) = get_json(&app, &format!("/api/samples/{sample_id}/track")).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["points"].as_array().unwrap().len(), 5);
+    assert_eq!(body["recorded_points"], 5);
+    let length_km = body["length_m"].as_f64().unwrap() / 1000.0;
+    assert!((length_km - 222.4).abs() < 0.1, "{length_km}");
+
+    let (_, body) = get_json(
+        &app,
+        &format!("/api/samples/{sample_id}/track?tolerance_m=600"),
+    )
+    .await;
+    let times: Vec<&str> = body["points"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|point| point["time"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        times,
+        vec![
+            "2025-06-01T00:00:00Z",
+            "2025-06-01T02:00:00Z",
+            "2025-06-01T04:00:00Z"
+        ]
+    );
+    assert_eq!(body["recorded_points"], 5);
+    let (status, _) = get_json(
+        &app,
+        &format!("/api/samples/{sample_id}/track?tolerance_m=-1"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, position) = get_json(
+        &app,
+        &format!("/api/samples/{sample_id}/track/position?time=2025-06-01T01:30:00Z"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{position}");
+    assert!((position["longitude"].as_f64().unwrap() - 0.75).abs() < 1e-9);
+    assert!((position["latitude"].as_f64().unwrap() - 0.005).abs() < 1e-9);
+    let (status, body) = get_json(
+        &app,
+        &format!("/api/samples/{sample_id}/track/position?time=2025-06-02T00:00:00Z"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body.to_string().contains("within the track"), "{body}");
+
+    // Tracks must be in time order
+    let (status, body) = send_json(
+        &app,
+        "PUT",
+        &format!("/api/samples/{sample_id}"),
+        Some(&json!({"track": [
+            {"time": "2025-06-01T01:00:00Z", "longitude": 0.0, "latitude": 0.0},
+            {"time": "2025-06-01T00:00:00Z", "longitude": 0.5, "latitude": 0.0}
+        ]})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(body.to_string().contains("must increase"), "{body}");
+
+    // Samples collected in one place have no track
+    let (status, _) = send_json(
+        &app,
+        "PUT",
+        &format!("/api/samples/{sample_id}"),
+        Some(&json!({"track": null})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, body) = get_json(&app, &format!("/api/samples/{sample_id}/track")).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert!(body.to_string().contains("no track"), "{body}");
+    let (status, _) = get_json(&app, &format!("/api/samples/{}/track", Uuid::new_v4())).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_sample_upsert_by_name_and_location() {
+    let app = setup_test_app().await;
+    let (_, location_id) = create_test_project_and_location(&app, "upsert").await;
+    let (_, other_location_id) = create_test_project_and_location(&app, "upsert other").await;
+    let samples = json!([
+        {
+            "name": "Upserted filter",
+            "type": "filter",
+            "location_id": location_id,
+            "remarks": "first run"
+        },
+        {"name": "Upserted filter", "type": "filter", "location_id": other_location_id},
+        {"name": "Upserted bulk", "type": "bulk", "location_id": location_id},
+    ]);
+
+    let (status, first) = send_json(&app, "POST", "/api/samples/upsert", Some(&samples)).await;
+    assert_eq!(status, StatusCode::OK, "{first}");
+    assert_eq!(first["created"], 3);
+    assert_eq!(first["updated"], 0);
+
+    // Running again updates the same samples
+    let mut samples = samples;
+    samples[0]["remarks"] = json!("second run");
+    samples
+        .as_array_mut()
+        .unwrap()
+        .push(json!({"type": "filter", "location_id": location_id}));
+    let (status, second) = send_json(&app, "POST", "/api/samples/upsert", Some(&samples)).await;
+    assert_eq!(status, StatusCode::OK, "{second}");
+    assert_eq!(second["created"], 0);
+    assert_eq!(second["updated"], 3);
+    assert_eq!(second["failed"], 1);
+    for index in 0..3 {
+        assert_eq!(second["results"][index]["action"], "updated");
+        assert_eq!(second["results"][index]["id"], first["results"][index]["id"]);
+    }
+    assert_eq!(second["results"][3]["action"], "failed");
+    assert!(second["results"][3]["error"].is_string());
+
+    let id = first["results"][0]["id"].as_str().unwrap();
+    let (_, sample) = get_json(&app, &format!("/api/samples/{id}")).await;
+    assert_eq!(sample["remarks"], "second run");
+    let (_, listed) = get_json(
+        &app,
+        &format!("/api/samples?filter=%7B%22location_id%22%3A%22{location_id}%22%7D"),
+    )
+    .await;
+    assert_eq!(listed.as_array().unwrap().len(), 2, "{listed}");
+
+    // Updates keep their versions, and each record is logged on its own
+    let (_, versions) = get_json(&app, &format!("/api/samples/{id}/versions")).await;
+    assert_eq!(versions.as_array().unwrap().len(), 1, "{versions}");
+    let (_, entries) =
+        get_json(&app, &format!("/api/audit?resource=samples&record_id={id}")).await;
+    let actions: Vec<&str> = entries
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| entry["action"].as_str().unwrap())
+        .collect();
+    assert_eq!(actions, vec!["update", "create"]);
+    assert_eq!(
+        entries[0]["changes"]["remarks"],
+        json!({"before": "first run", "after": "second run"})
+    );
+
+    // Deleted samples are not matched
+    let (status, _) = send_json(
+        &app,
+        "DELETE",
+        &format!("/api/samples/{id}"),
+        Some(&json!({})),
+    )
+    .await;
+    assert!(status.is_success());
+    let (_, third) = send_json(
+        &app,
+        "POST",
+        "/api/samples/upsert",
+        Some(&json!([samples[0]])),
+    )
+    .await;
+    assert_eq!(third["results"][0]["action"], "created", "{third}");
+    assert_ne!(third["results"][0]["id"], first["results"][0]["id"]);
+}
+
+#[tokio::test]
+async fn test_sample_count_and_aggregates() {
+    let app = setup_test_app().await;
+    let (_, location_id) = create_test_project_and_location(&app, "aggregates").await;
+    let (_, other_location_id) = create_test_project_and_location(&app, "aggregates other").await;
+    let mut ids = Vec::new();
+    for (name, location, volume) in [
+        ("Counted filter 1", location_id, 1.5),
+        ("Counted filter 2", location_id, 2.5),
+        ("Counted filter 3", other_location_id, 4.0),
+        ("Deleted filter", location_id, 100.0),
+    ] {
+        let (status, sample) = send_json(
+            &app,
+            "POST",
+            "/api/samples",
+            Some(&json!({
+                "name": name,
+                "type": "filter",
+                "location_id": location,
+                "total_volume": volume
+            })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED, "{sample}");
+        ids.push(sample["id"].as_str().unwrap().to_string());
+    }
+    let deleted = format!("/api/samples/{}", ids[3]);
+    let (status, _) = send_json(&app, "DELETE", &deleted, Some(&json!({}))).await;
+    assert!(status.is_success());
+
+    let (status, count) = get_json(&app, "/api/samples/count").await;
+    assert_eq!(status, StatusCode::OK, "{count}");
+    assert_eq!(count["count"], 3);
+    let (_, count) = get_json(
+        &app,
+        &format!("/api/samples/count?filter=%7B%22location_id%22%3A%22{location_id}%22%7D"),
+    )
+    .await;
+    assert_eq!(count["count"], 2, "{count}");
+
+    // Samples per location, largest group first
+    let (status, groups) = get_json(
+        &app,
+        "/api/samples/aggregate?group_by=location_id&field=total_volume",
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{groups}");
+    assert_eq!(groups.as_array().unwrap().len(), 2, "{groups}");
+    assert_eq!(groups[0]["key"], location_id.to_string());
+    assert_eq!(groups[0]["count"], 2);
+    assert!((groups[0]["avg"].as_f64().unwrap() - 2.0).abs() < 1e-9, "{groups}");
+    assert_eq!(groups[1]["count"], 1);
+
+    let (_, groups) = get_json(&app, "/api/samples/aggregate?group_by=type").await;
+    assert_eq!(groups[0]["key"], "filter", "{groups}");
+    assert_eq!(groups[0]["count"], 3);
+
+    let (status, _) = get_json(&app, "/api/samples/aggregate?group_by=track").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) =
+        get_json(&app, "/api/samples/aggregate?group_by=name&interval=month").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,326 @@
+//! Tracks of samples collected on the move.
+//!
+//! Samples collected along a ship or aircraft transect keep the track they
+//! were collected on: timestamped points in time order, stored with the
+//! sample as a JSON list of `{"time", "longitude", "latitude"}`. The track
+//! can be simplified for drawing, and the position along it read at any time
+//! it covers. Distances are taken on a plane around each segment, which is
+//! accurate over the short spans between recorded positions.
+
+use super::models::Entity as Samples;
+use chrono::{DateTime, Utc};
+use sea_orm::{DatabaseConnection, DbErr, EntityTrait, JsonValue};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+/// Mean radius of the Earth in metres
+const EARTH_RADIUS_M: f64 = 6_371_008.8;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct TrackPoint {
+    pub time: DateTime<Utc>,
+    pub longitude: f64,
+    pub latitude: f64,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, IntoParams)]
+pub struct TrackQuery {
+    /// Simplify the track, dropping points that lie within this many metres
+    /// of the line through those kept; every point is kept when omitted
+    pub tolerance_m: Option<f64>,
+}
+
+#[derive(Clone, Debug, Deserialize, IntoParams)]
+pub struct PositionQuery {
+    /// Time to give the position at, within the track
+    pub time: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct SampleTrack {
+    pub sample_id: Uuid,
+    pub points: Vec<TrackPoint>,
+    /// Number of points before simplification
+    pub recorded_points: usize,
+    /// Distance travelled along the recorded track
+    pub length_m: f64,
+}
+
+/// Points of a stored or submitted track, checked to be on the globe and in
+/// time order
+pub fn parse(track: &JsonValue) -> Result<Vec<TrackPoint>, DbErr> {
+    let points: Vec<TrackPoint> = serde_json::from_value(track.clone()).map_err(|_| {
+        DbErr::Custom(
+            "track must be a list of points with time, longitude and latitude".to_string(),
+        )
+    })?;
+    if points.len() < 2 {
+        return Err(DbErr::Custom(
+            "track must have at least two points".to_string(),
+        ));
+    }
+    if points.iter().any(|point| {
+        !(-180.0..=180.0).contains(&point.longitude) || !(-90.0..=90.0).contains(&point.latitude)
+    }) {
+        return Err(DbErr::Custom(
+            "track has a point outside longitudes -180 to 180 and latitudes -90 to 90".to_string(),
+        ));
+    }
+    if points.windows(2).any(|pair| pair[0].time >= pair[1].time) {
+        return Err(DbErr::Custom(
+            "track times must increase from point to point".to_string(),
+        ));
+    }
+    Ok(points)
+}
+
+/// Check a track given with a sample
+pub fn validate(track: Option<&JsonValue>) -> Result<(), DbErr> {
+    track.map_or(Ok(()), |track| parse(track).map(|_| ()))
+}
+
+/// Difference in longitude taking the shorter way round, across the
+/// antimeridian if need be
+fn longitude_delta(from: f64, to: f64) -> f64 {
+    let delta = to - from;
+    if delta > 180.0 {
+        delta - 360.0
+    } else if delta < -180.0 {
+        delta + 360.0
+    } else {
+        delta
+    }
+}
+
+/// Position of `to` east and north of `from`, in metres
+fn offset_m(from: &TrackPoint, to: &TrackPoint) -> (f64, f64) {
+    let mean_latitude = f64::midpoint(from.latitude, to.latitude).to_radians();
+    (
+        longitude_delta(from.longitude, to.longitude).to_radians()
+            * mean_latitude.cos()
+            * EARTH_RADIUS_M,
+        (to.latitude - from.latitude).to_radians() * EARTH_RADIUS_M,
+    )
+}
+
+fn distance_m(from: &TrackPoint, to: &TrackPoint) -> f64 {
+    let (east, north) = offset_m(from, to);
+    east.hypot(north)
+}
+
+/// Distance from `point` to the segment from `start` to `end`
+fn segment_distance_m(point: &TrackPoint, start: &TrackPoint, end: &TrackPoint) -> f64 {
+    let (end_east, end_north) = offset_m(start, end);
+    let (east, north) = offset_m(start, point);
+    let length_squared = end_east.powi(2) + end_north.powi(2);
+    if length_squared == 0.0 {
+        return east.hypot(north);
+    }
+    let along = ((east * end_east + north * end_north) / length_squared).clamp(0.0, 1.0);
+    (east - along * end_east).hypot(north - along * end_north)
+}
+
+/// Length of the track in metres
+pub fn length_m(points: &[TrackPoint]) -> f64 {
+    points
+        .windows(2)
+        .map(|pair| distance_m(&pair[0], &pair[1]))
+        .sum()
+}
+
+/// Douglas-Peucker simplification: keep the ends and, recursively, the
+/// point furthest from the line between those kept while it is further than
+/// the tolerance
+pub fn simplify(points: &[TrackPoint], tolerance_m: f64) -> Vec<TrackPoint> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+    let mut kept = vec![false; points.len()];
+    kept[0] = true;
+    kept[points.len() - 1] = true;
+
+    let mut spans = vec![(0, points.len() - 1)];
+    while let Some((start, end)) = spans.pop() {
+        let furthest = (start + 1..end)
+            .map(|i| {
+                (
+                    i,
+                    segment_distance_m(&points[i], &points[start], &points[end]),
+                )
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((i, distance)) = furthest
+            && distance > tolerance_m
+        {
+            kept[i] = true;
+            spans.push((start, i));
+            spans.push((i, end));
+        }
+    }
+    points
+        .iter()
+        .zip(kept)
+        .filter(|(_, kept)| *kept)
+        .map(|(point, _)| point.clone())
+        .collect()
+}
+
+/// Position at `time`, interpolated between the recorded points around it
+pub fn position_at(points: &[TrackPoint], time: DateTime<Utc>) -> Result<TrackPoint, DbErr> {
+    let (Some(first), Some(last)) = (points.first(), points.last()) else {
+        return Err(DbErr::Custom("track has no points".to_string()));
+    };
+    if time < first.time || time > last.time {
+        return Err(DbErr::Custom(format!(
+            "time must be within the track, from {} to {}",
+            first.time.to_rfc3339(),
+            last.time.to_rfc3339()
+        )));
+    }
+
+    // First point after `time`, or the last point
+    let next = points
+        .partition_point(|point| point.time <= time)
+        .clamp(1, points.len() - 1);
+    let (start, end) = (&points[next - 1], &points[next]);
+    #[allow(clippy::cast_precision_loss)]
+    let fraction = (time - start.time).num_milliseconds() as f64
+        / (end.time - start.time).num_milliseconds() as f64;
+
+    let mut longitude =
+        start.longitude + longitude_delta(start.longitude, end.longitude) * fraction;
+    if longitude > 180.0 {
+        longitude -= 360.0;
+    } else if longitude < -180.0 {
+        longitude += 360.0;
+    }
+    Ok(TrackPoint {
+        time,
+        longitude,
+        latitude: start.latitude + (end.latitude - start.latitude) * fraction,
+    })
+}
+
+async fn track_points(db: &DatabaseConnection, id: Uuid) -> Result<Vec<TrackPoint>, DbErr> {
+    let sample = Samples::find_by_id(id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Sample not found".to_string()))?;
+    let track = sample
+        .track
+        .ok_or_else(|| DbErr::RecordNotFound("Sample has no track".to_string()))?;
+    parse(&track)
+}
+
+/// The sample's track, simplified when the query gives a tolerance.
+/// Invalid queries are returned as `DbErr::Custom`.
+pub async fn sample_track(
+    db: &DatabaseConnection,
+    id: Uuid,
+    query: &TrackQuery,
+) -> Result<SampleTrack, DbErr> {
+    if let Some(tolerance_m) = query.tolerance_m
+        && !(tolerance_m.is_finite() && tolerance_m >= 0.0)
+    {
+        return Err(DbErr::Custom(
+            "tolerance_m must be a distance of 0 or more".to_string(),
+        ));
+    }
+
+    let points = track_points(db, id).await?;
+    Ok(SampleTrack {
+        sample_id: id,
+        recorded_points: points.len(),
+        length_m: length_m(&points),
+        points: match query.tolerance_m {
+            Some(tolerance_m) => simplify(&points, tolerance_m),
+            None => points,
+        },
+    })
+}
+
+/// Where the sample was being collected at a time within its track
+pub async fn track_position(
+    db: &DatabaseConnection,
+    id: Uuid,
+    time: DateTime<Utc>,
+) -> Result<TrackPoint, DbErr> {
+    position_at(&track_points(db, id).await?, time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn point(time: &str, longitude: f64, latitude: f64) -> TrackPoint {
+        TrackPoint {
+            time: time.parse().unwrap(),
+            longitude,
+            latitude,
+        }
+    }
+
+    #[test]
+    fn test_simplify_and_interpolate() {
+        // Along the equator, a degree is about 111 km; the third point is
+        // 1.1 km off the line
+        let points = [
+            point("2025-06-01T00:00:00Z", 0.0, 0.0),
+            point("2025-06-01T01:00:00Z", 0.5, 0.0),
+            point("2025-06-01T02:00:00Z", 1.0, 0.01),
+            point("2025-06-01T03:00:00Z", 1.5, 0.0),
+            point("2025-06-01T04:00:00Z", 2.0, 0.0),
+        ];
+        assert!((length_m(&points) / 1000.0 - 222.4).abs() < 0.1);
+        assert_eq!(simplify(&points, 2000.0).len(), 2);
+        let simplified = simplify(&points, 600.0);
+        assert_eq!(simplified.len(), 3);
+        assert_eq!(simplified[1], points[2]);
+        assert_eq!(simplify(&points, 0.0).len(), 5);
+
+        let position = position_at(&points, "2025-06-01T01:30:00Z".parse().unwrap()).unwrap();
+        assert!((position.longitude - 0.75).abs() < 1e-9);
+        assert!((position.latitude - 0.005).abs() < 1e-9);
+        let position = position_at(&points, points[4].time).unwrap();
+        assert!((position.longitude - 2.0).abs() < 1e-9);
+        assert!(position_at(&points, "2025-06-01T04:00:01Z".parse().unwrap()).is_err());
+
+        // Across the antimeridian
+        let crossing = [
+            point("2025-06-01T00:00:00Z", 179.5, -40.0),
+            point("2025-06-01T02:00:00Z", -179.5, -40.0),
+        ];
+        let position = position_at(&crossing, "2025-06-01T01:30:00Z".parse().unwrap()).unwrap();
+        assert!((position.longitude + 179.75).abs() < 1e-9);
+        assert!(length_m(&crossing) < 100_000.0);
+    }
+
+    #[test]
+    fn test_parse() {
+        let track = json!([
+            {"time": "2025-06-01T00:00:00Z", "longitude": 10.0, "latitude": 54.0},
+            {"time": "2025-06-01T01:00:00Z", "longitude": 10.5, "latitude": 54.2}
+        ]);
+        assert_eq!(parse(&track).unwrap().len(), 2);
+
+        for invalid in [
+            json!([{"time": "2025-06-01T00:00:00Z", "longitude": 10.0, "latitude": 54.0}]),
+            json!([
+                {"time": "2025-06-01T01:00:00Z", "longitude": 10.0, "latitude": 54.0},
+                {"time": "2025-06-01T00:00:00Z", "longitude": 10.5, "latitude": 54.2}
+            ]),
+            json!([
+                {"time": "2025-06-01T00:00:00Z", "longitude": 190.0, "latitude": 54.0},
+                {"time": "2025-06-01T01:00:00Z", "longitude": 10.5, "latitude": 54.2}
+            ]),
+            json!({"type": "LineString"}),
+        ] {
+            assert!(
+                matches!(parse(&invalid), Err(DbErr::Custom(_))),
+                "{invalid}"
+            );
+        }
+    }
+}
@@ -1,26 +1,785 @@
+use super::barcodes::{SampleLabel, sample_by_barcode, sample_label};
+use super::clustering::{ClusterQuery, MapCluster, sample_clusters};
+use super::custody::{CustodyEventCreate, record_custody_event};
+use super::custody_events::models::SampleCustodyEvent;
+use super::hierarchy::{SampleHierarchy, SampleRollup, sample_hierarchy, sample_rollup};
+use super::metadata::{MetadataCheck, SampleTypeRules, TypeSchema, check_sample, current_rules};
+use super::meteorology::{
+    cached_weather, collection_period, fetch_weather, store_weather, summarise,
+};
+use super::models::{ActiveModel, Column, SampleCreate, SampleList};
 pub use super::models::{Sample, router as crudrouter};
-use crate::common::auth::Role;
+use super::pooling::{PoolSourceInput, SamplePool, sample_pool, set_pool_sources};
+use super::qc::{SampleQcReview, review_sample};
+use super::storage::{FreezerOccupancy, StorageBox, find_box, freezer_occupancy};
+use super::tracks::{
+    PositionQuery, SampleTrack, TrackPoint, TrackQuery, sample_track, track_position,
+};
+use super::weather::models::SampleWeather;
+use crate::api_keys::services::accept_api_keys;
+use crate::audit::services::{AuditedResource, audit_changes};
+use crate::common::aggregate::{aggregate_handler, count_handler};
+use crate::common::auth::{Role, RouteAccess, require_role};
+use crate::common::etag::check_entity_tags;
+use crate::common::keycloak::authenticate;
+use crate::common::labs::{TenantResource, require_lab};
+use crate::common::pagination::paginate_by_cursor;
+use crate::common::patch::patch_one_handler;
+use crate::common::redaction::redact_for_viewers;
+use crate::common::soft_delete::{hide_deleted, restore_one_handler};
+use crate::common::spatial::{SpatialQuery, page, spatial_condition};
 use crate::common::state::AppState;
-use axum_keycloak_auth::{PassthroughMode, layer::KeycloakAuthLayer};
+use crate::common::upsert::upsert_handler;
+use crate::experiments::regions::sample_regions;
+use crate::projects::access::{ScopedResource, require_project_access};
+use crate::projects::archiving::reject_archived_changes;
+use crate::projects::shares::models::SharedResource;
+use crate::projects::shares::views::{delete_share, get_shares, post_share, shares_api};
+use crate::tray_configurations::regions::models::Region;
+use crate::versions::{self, services::keep_versions};
+use axum::{
+    Extension, Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    middleware,
+    routing::{delete, get, patch, post, put},
+};
+use axum_keycloak_auth::{PassthroughMode, decode::KeycloakToken};
 use crudcrate::CRUDResource;
+use sea_orm::{ConnectionTrait, DbErr, EntityTrait, Order};
+use utoipa::OpenApi;
 use utoipa_axum::router::OpenApiRouter;
+use uuid::Uuid;
 
+/// Find a sample by the code scanned from its vial
+#[utoipa::path(
+    get,
+    path = "/by-barcode/{code}",
+    params(
+        ("code" = String, Path, description = "Barcode of the sample, or its ID")
+    ),
+    responses(
+        (status = 200, description = "The sample with the barcode", body = Sample),
+        (status = 404, description = "No sample has the barcode"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "samples",
+    summary = "Get a sample by barcode",
+    description = "Look up the sample a scanned label belongs to. Labels of samples without a barcode encode the sample ID, which is accepted as well"
+)]
+pub async fn get_by_barcode(
+    State(state): State<AppState>,
+    Path(code): Path<String>,
+) -> Result<Json<Sample>, (StatusCode, String)> {
+    sample_by_barcode(&state.db, &code)
+        .await
+        .map(Json)
+        .map_err(|e| match e {
+            DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+            e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })
+}
+
+/// Content of a sample's vial label
+#[utoipa::path(
+    get,
+    path = "/{id}/label",
+    params(
+        ("id" = Uuid, Path, description = "Sample ID")
+    ),
+    responses(
+        (status = 200, description = "Code and text to print on the label", body = SampleLabel),
+        (status = 404, description = "Sample not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "samples",
+    summary = "Get the label of a sample",
+    description = "Give the code to print as a barcode or QR code, which resolves through the barcode lookup, with the sample's name, type, sampling time and location"
+)]
+pub async fn get_label(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<SampleLabel>, (StatusCode, String)> {
+    sample_label(&state.db, id)
+        .await
+        .map(Json)
+        .map_err(|e| match e {
+            DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+            e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })
+}
+
+/// Append an event to a sample's chain of custody
+#[utoipa::path(
+    post,
+    path = "/{id}/custody-events",
+    params(
+        ("id" = Uuid, Path, description = "Sample ID")
+    ),
+    request_body = CustodyEventCreate,
+    responses(
+        (status = 201, description = "The logged event", body = SampleCustodyEvent),
+        (status = 404, description = "Sample not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "samples",
+    summary = "Log a custody event",
+    description = "Record that the sample was collected, shipped, received, stored or processed, by whom and where. The log is append-only and listed in the sample's details; the authenticated user is kept as recorded_by"
+)]
+pub async fn post_custody_event(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    token: Option<Extension<KeycloakToken<Role>>>,
+    Json(event): Json<CustodyEventCreate>,
+) -> Result<(StatusCode, Json<SampleCustodyEvent>), (StatusCode, String)> {
+    let recorded_by = token.map(|Extension(token)| token.extra.profile.preferred_username);
+    record_custody_event(&state.db, id, event, recorded_by)
+        .await
+        .map(|event| (StatusCode::CREATED, Json(event)))
+        .map_err(|e| match e {
+            DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+            e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })
+}
+
+/// Record the QC review of a sample
+#[utoipa::path(
+    put,
+    path = "/{id}/qc",
+    params(
+        ("id" = Uuid, Path, description = "Sample ID")
+    ),
+    request_body = SampleQcReview,
+    responses(
+        (status = 200, description = "The reviewed sample", body = Sample),
+        (status = 400, description = "Rejected or flagged without a reason"),
+        (status = 404, description = "Sample not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "samples",
+    summary = "Review the quality of a sample",
+    description = "Set the QC status of the sample to pending, accepted, rejected or flagged. Rejecting or flagging needs a reason. The authenticated user and the time are kept with the review. Rejected samples cannot be assigned to new experiment regions"
+)]
+pub async fn put_qc_review(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    token: Option<Extension<KeycloakToken<Role>>>,
+    Json(review): Json<SampleQcReview>,
+) -> Result<Json<Sample>, (StatusCode, String)> {
+    let reviewed_by = token.map(|Extension(token)| token.extra.profile.preferred_username);
+    review_sample(&state.db, id, review, reviewed_by)
+        .await
+        .map(Json)
+        .map_err(|e| match e {
+            DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+            DbErr::Custom(message) => (StatusCode::BAD_REQUEST, message),
+            e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })
+}
+
+/// Sources of a pooled sample and the pools a sample went into
+#[utoipa::path(
+    get,
+    path = "/{id}/pool",
+    params(
+        ("id" = Uuid, Path, description = "Sample ID")
+    ),
+    responses(
+        (status = 200, description = "Pooling of the sample", body = SamplePool),
+        (status = 404, description = "Sample not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "samples",
+    summary = "Get the pooling of a sample",
+    description = "List the samples the sample was pooled from with the fraction of each, the air volume the pool represents, and the pools the sample itself went into"
+)]
+pub async fn get_pool(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<SamplePool>, (StatusCode, String)> {
+    sample_pool(&state.db, id)
+        .await
+        .map(Json)
+        .map_err(|e| match e {
+            DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+            e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })
+}
+
+/// Set the sources of a pooled sample
+#[utoipa::path(
+    put,
+    path = "/{id}/pool",
+    params(
+        ("id" = Uuid, Path, description = "Sample ID")
+    ),
+    request_body = Vec<PoolSourceInput>,
+    responses(
+        (status = 200, description = "Pooling of the sample", body = SamplePool),
+        (status = 400, description = "Invalid sources or fractions"),
+        (status = 404, description = "Sample not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "samples",
+    summary = "Set the sources of a pooled sample",
+    description = "Replace the samples the sample was pooled from. Each fraction is the share of the source that went into the pool; together the pools of a source cannot take more than all of it. An empty list removes the pooling"
+)]
+pub async fn put_pool(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(sources): Json<Vec<PoolSourceInput>>,
+) -> Result<Json<SamplePool>, (StatusCode, String)> {
+    set_pool_sources(&state.db, id, sources)
+        .await
+        .map(Json)
+        .map_err(|e| match e {
+            DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+            DbErr::Custom(message) => (StatusCode::BAD_REQUEST, message),
+            e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })
+}
+
+/// Metadata rules of each sample type
+#[utoipa::path(
+    get,
+    path = "/type-rules",
+    responses(
+        (status = 200, description = "Rules by sample type", body = std::collections::BTreeMap<String, TypeSchema>)
+    ),
+    tag = "samples",
+    summary = "Get the metadata rules of sample types",
+    description = "List, for each sample type, the fields a sample must have and the bounds of its numeric fields, written like a JSON schema"
+)]
+pub async fn get_type_rules() -> Json<SampleTypeRules> {
+    Json(current_rules())
+}
+
+/// Check a sample against the rules of its type without saving it
+#[utoipa::path(
+    post,
+    path = "/validate",
+    request_body = SampleCreate,
+    responses(
+        (status = 200, description = "Whether the sample can be saved and, if not, why", body = MetadataCheck)
+    ),
+    tag = "samples",
+    summary = "Validate sample metadata",
+    description = "Check a sample as it would be created against the metadata rules of its type, listing each missing or out of range field"
+)]
+pub async fn validate_sample(Json(sample): Json<SampleCreate>) -> Json<MetadataCheck> {
+    let active_model: ActiveModel = sample.into();
+    let problems = check_sample(&current_rules(), &active_model);
+    Json(MetadataCheck {
+        valid: problems.is_empty(),
+        problems,
+    })
+}
+
+/// Ancestors and aliquots of a sample
+#[utoipa::path(
+    get,
+    path = "/{id}/hierarchy",
+    params(
+        ("id" = Uuid, Path, description = "Sample ID")
+    ),
+    responses(
+        (status = 200, description = "Position of the sample in its aliquot hierarchy", body = SampleHierarchy),
+        (status = 404, description = "Sample not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "samples",
+    summary = "Get the aliquot hierarchy of a sample",
+    description = "List the samples the sample was taken from, from the top-level sample down, and the tree of aliquots taken from it"
+)]
+pub async fn get_hierarchy(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<SampleHierarchy>, (StatusCode, String)> {
+    sample_hierarchy(&state.db, id)
+        .await
+        .map(Json)
+        .map_err(|e| match e {
+            DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+            e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })
+}
+
+/// Results of a sample and all its aliquots
+#[utoipa::path(
+    get,
+    path = "/{id}/rollup",
+    params(
+        ("id" = Uuid, Path, description = "Sample ID")
+    ),
+    responses(
+        (status = 200, description = "Results of the sample and its aliquots", body = SampleRollup),
+        (status = 404, description = "Sample not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "samples",
+    summary = "Roll up the results of a sample's aliquots",
+    description = "Collect the nucleation events of the sample and of every aliquot below it, with statistics and dilution summaries over all of them"
+)]
+pub async fn get_rollup(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<SampleRollup>, (StatusCode, String)> {
+    sample_rollup(&state.db, id)
+        .await
+        .map(Json)
+        .map_err(|e| match e {
+            DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+            e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })
+}
+
+/// Regions of experiments holding the sample
+#[utoipa::path(
+    get,
+    path = "/{id}/regions",
+    params(
+        ("id" = Uuid, Path, description = "Sample ID")
+    ),
+    responses(
+        (status = 200, description = "Regions holding any treatment of the sample, by experiment", body = Vec<Region>),
+        (status = 404, description = "Sample not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "samples",
+    summary = "List the regions of a sample",
+    description = "List the regions of experiments that hold any of the sample's treatments, to find where the sample was run"
+)]
+pub async fn get_sample_regions(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<Region>>, (StatusCode, String)> {
+    sample_regions(&state.db, id)
+        .await
+        .map(Json)
+        .map_err(|e| match e {
+            DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+            e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })
+}
+
+/// Where a box is and what it holds
+#[utoipa::path(
+    get,
+    path = "/storage/boxes/{name}",
+    params(
+        ("name" = String, Path, description = "Name of the box")
+    ),
+    responses(
+        (status = 200, description = "The box in each freezer that has one of this name", body = Vec<StorageBox>),
+        (status = 404, description = "No sample is stored in the box"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "samples",
+    summary = "Find a storage box",
+    description = "Give the freezer and shelf of the box with its samples in position order. Box names are unique within a freezer, so the same name may be found in several freezers"
+)]
+pub async fn get_storage_box(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<Vec<StorageBox>>, (StatusCode, String)> {
+    find_box(&state.db, &name)
+        .await
+        .map(Json)
+        .map_err(|e| match e {
+            DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+            e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })
+}
+
+/// Number of samples stored in each freezer
+#[utoipa::path(
+    get,
+    path = "/storage/freezers",
+    responses(
+        (status = 200, description = "Occupancy of each freezer holding samples", body = Vec<FreezerOccupancy>),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "samples",
+    summary = "Report freezer occupancy",
+    description = "Count the samples stored in each freezer, broken down by shelf and box"
+)]
+pub async fn get_freezer_occupancy(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<FreezerOccupancy>>, (StatusCode, String)> {
+    freezer_occupancy(&state.db)
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// Samples within an area, using their `PostGIS` geometry
+#[utoipa::path(
+    get,
+    path = "/spatial",
+    params(SpatialQuery),
+    responses(
+        (status = 200, description = "Samples within every given area, by name", body = Vec<SampleList>),
+        (status = 400, description = "Missing or invalid filter, or a database without PostGIS"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "samples",
+    summary = "Find samples within an area",
+    description = "Filter samples by a bounding box, a distance from a point or a polygon, in WGS 84 longitude and latitude. Combined filters must all match"
+)]
+pub async fn get_spatial_samples(
+    State(state): State<AppState>,
+    Query(query): Query<SpatialQuery>,
+) -> Result<Json<Vec<SampleList>>, (StatusCode, String)> {
+    let samples = async {
+        let condition = spatial_condition(
+            state.db.get_database_backend(),
+            "\"samples\".\"geom\"",
+            &query,
+        )?;
+        let (offset, limit) = page(&query)?;
+        Sample::get_all(&state.db, &condition, Column::Name, Order::Asc, offset, limit).await
+    };
+    samples.await.map(Json).map_err(|e| match e {
+        DbErr::Custom(message) => (StatusCode::BAD_REQUEST, message),
+        e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    })
+}
+
+/// Clusters of sampling points for the map
+#[utoipa::path(
+    get,
+    path = "/clusters",
+    params(ClusterQuery),
+    responses(
+        (status = 200, description = "Clusters of the samples in the area, largest first", body = Vec<MapCluster>),
+        (status = 400, description = "Invalid zoom, radius or bounding box"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "samples",
+    summary = "Cluster samples for the map",
+    description = "Group the samples with coordinates into clusters of points within a radius in pixels of each other at the map's zoom level, giving the position and size of each cluster. A cluster of one sample gives its ID"
+)]
+pub async fn get_sample_clusters(
+    State(state): State<AppState>,
+    Query(query): Query<ClusterQuery>,
+) -> Result<Json<Vec<MapCluster>>, (StatusCode, String)> {
+    sample_clusters(&state.db, &query)
+        .await
+        .map(Json)
+        .map_err(|e| match e {
+            DbErr::Custom(message) => (StatusCode::BAD_REQUEST, message),
+            e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })
+}
+
+fn track_error(e: DbErr) -> (StatusCode, String) {
+    match e {
+        DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+        DbErr::Custom(message) => (StatusCode::BAD_REQUEST, message),
+        e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+/// Track of a sample collected along a transect
+#[utoipa::path(
+    get,
+    path = "/{id}/track",
+    params(
+        ("id" = Uuid, Path, description = "Sample ID"),
+        TrackQuery
+    ),
+    responses(
+        (status = 200, description = "Points of the track in time order", body = SampleTrack),
+        (status = 400, description = "Invalid tolerance"),
+        (status = 404, description = "Sample not found, or it has no track"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "samples",
+    summary = "Get the track of a sample",
+    description = "Give the timestamped points of the ship or aircraft transect the sample was collected along, with its length. With a tolerance, the track is simplified for drawing by dropping points within that many metres of the line through the points kept"
+)]
+pub async fn get_track(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<TrackQuery>,
+) -> Result<Json<SampleTrack>, (StatusCode, String)> {
+    sample_track(&state.db, id, &query)
+        .await
+        .map(Json)
+        .map_err(track_error)
+}
+
+/// Position of a sample's collection at a time
+#[utoipa::path(
+    get,
+    path = "/{id}/track/position",
+    params(
+        ("id" = Uuid, Path, description = "Sample ID"),
+        PositionQuery
+    ),
+    responses(
+        (status = 200, description = "Position along the track at the time", body = TrackPoint),
+        (status = 400, description = "The time is outside the track"),
+        (status = 404, description = "Sample not found, or it has no track"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "samples",
+    summary = "Get the position of a sample at a time",
+    description = "Give where the sample was being collected at a time within its track, interpolated between the recorded points before and after it"
+)]
+pub async fn get_track_position(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<PositionQuery>,
+) -> Result<Json<TrackPoint>, (StatusCode, String)> {
+    track_position(&state.db, id, query.time)
+        .await
+        .map(Json)
+        .map_err(track_error)
+}
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+pub struct WeatherQuery {
+    /// Fetch the weather again even when it is cached
+    #[serde(default)]
+    refresh: bool,
+}
+
+/// Weather over a sample's collection period
+#[utoipa::path(
+    get,
+    path = "/{id}/weather",
+    params(
+        ("id" = Uuid, Path, description = "Sample ID"),
+        WeatherQuery
+    ),
+    responses(
+        (status = 200, description = "Hourly weather over the collection period and its summary", body = SampleWeather),
+        (status = 404, description = "Sample not found"),
+        (status = 422, description = "The sample has no coordinates or start time"),
+        (status = 502, description = "The weather provider failed"),
+        (status = 503, description = "No weather provider is configured"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "samples",
+    summary = "Get the weather of a sample",
+    description = "Give the temperature, wind and boundary layer height at the sample's coordinates for each hour it was collected, with their means and extremes. The weather is fetched from the configured reanalysis provider the first time and cached with the sample until its coordinates or times change"
+)]
+pub async fn get_weather(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<WeatherQuery>,
+) -> Result<Json<SampleWeather>, (StatusCode, String)> {
+    let db_error = |e: DbErr| match e {
+        DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+        DbErr::Custom(message) => (StatusCode::UNPROCESSABLE_ENTITY, message),
+        e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    };
+    let sample = super::models::Entity::find_by_id(id)
+        .one(&state.db)
+        .await
+        .map_err(db_error)?
+        .ok_or((StatusCode::NOT_FOUND, "Sample not found".to_string()))?;
+
+    if !query.refresh
+        && let Some(weather) = cached_weather(&state.db, &sample).await.map_err(db_error)?
+    {
+        return Ok(Json(weather));
+    }
+    let period = collection_period(&sample).map_err(db_error)?;
+    let Some(source) = &state.config.weather_api_url else {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Weather provider is not configured".to_string(),
+        ));
+    };
+
+    let hours = fetch_weather(&state.config, &period)
+        .await
+        .map_err(|e| (StatusCode::BAD_GATEWAY, e))?;
+    store_weather(&state.db, summarise(id, source, &period, hours))
+        .await
+        .map(Json)
+        .map_err(db_error)
+}
+
+/// Routes beyond the CRUD routes, documented here as they are routed
+/// with `route`
+#[derive(OpenApi)]
+#[openapi(paths(
+    get_by_barcode,
+    get_label,
+    post_custody_event,
+    put_qc_review,
+    get_pool,
+    put_pool,
+    get_type_rules,
+    validate_sample,
+    get_hierarchy,
+    get_rollup,
+    get_sample_regions,
+    get_storage_box,
+    get_freezer_occupancy,
+    get_spatial_samples,
+    get_sample_clusters,
+    get_track,
+    get_track_position,
+    get_weather
+))]
+struct SamplesApi;
+
+#[allow(clippy::too_many_lines)] // One route per sample endpoint
 pub fn router(state: &AppState) -> OpenApiRouter
 where
     Sample: CRUDResource,
 {
-    let mut mutating_router = crudrouter(&state.db.clone());
+    let mut mutating_router = crudrouter(&state.db.clone())
+        .route(
+            "/{id}",
+            patch(patch_one_handler::<Sample>).with_state(state.db.clone()),
+        )
+        .route(
+            "/{id}/restore",
+            post(restore_one_handler::<Sample>).with_state(state.db.clone()),
+        )
+        .merge(versions::views::router::<Sample>(&state.db))
+        .route(
+            "/{id}/hierarchy",
+            get(get_hierarchy).with_state(state.clone()),
+        )
+        .route("/{id}/rollup", get(get_rollup).with_state(state.clone()))
+        .route(
+            "/{id}/regions",
+            get(get_sample_regions).with_state(state.clone()),
+        )
+        .route(
+            "/by-barcode/{code}",
+            get(get_by_barcode).with_state(state.clone()),
+        )
+        .route("/{id}/label", get(get_label).with_state(state.clone()))
+        .route(
+            "/{id}/custody-events",
+            post(post_custody_event).with_state(state.clone()),
+        )
+        .route("/{id}/qc", put(put_qc_review).with_state(state.clone()))
+        .route("/{id}/weather", get(get_weather).with_state(state.clone()))
+        .route("/{id}/track", get(get_track).with_state(state.clone()))
+        .route(
+            "/{id}/track/position",
+            get(get_track_position).with_state(state.clone()),
+        )
+        .route(
+            "/{id}/shares",
+            get(get_shares)
+                .post(post_share)
+                .with_state((state.db.clone(), SharedResource::Sample)),
+        )
+        .route(
+            "/{id}/shares/{grant_id}",
+            delete(delete_share).with_state((state.db.clone(), SharedResource::Sample)),
+        )
+        .route("/type-rules", get(get_type_rules))
+        .route("/validate", post(validate_sample))
+        .route(
+            "/count",
+            get(count_handler::<Sample>).with_state(state.db.clone()),
+        )
+        .route(
+            "/aggregate",
+            get(aggregate_handler::<Sample>).with_state(state.db.clone()),
+        )
+        .route(
+            "/upsert",
+            post(upsert_handler::<Sample>)
+                .with_state((state.db.clone(), AuditedResource::Samples)),
+        )
+        .route(
+            "/{id}/pool",
+            get(get_pool).put(put_pool).with_state(state.clone()),
+        )
+        .route(
+            "/storage/boxes/{name}",
+            get(get_storage_box).with_state(state.clone()),
+        )
+        .route(
+            "/storage/freezers",
+            get(get_freezer_occupancy).with_state(state.clone()),
+        )
+        .route(
+            "/spatial",
+            get(get_spatial_samples).with_state(state.clone()),
+        )
+        .route(
+            "/clusters",
+            get(get_sample_clusters).with_state(state.clone()),
+        );
+    mutating_router
+        .get_openapi_mut()
+        .merge(SamplesApi::openapi());
+    mutating_router
+        .get_openapi_mut()
+        .merge(shares_api(SharedResource::Sample));
+
+    // Each update keeps the version it replaces
+    mutating_router = mutating_router.layer(middleware::from_fn_with_state(
+        state.db.clone(),
+        keep_versions::<Sample>,
+    ));
+
+    mutating_router = mutating_router.layer(middleware::from_fn_with_state(
+        state.db.clone(),
+        paginate_by_cursor::<Sample>,
+    ));
+
+    mutating_router = mutating_router.layer(middleware::from_fn_with_state(
+        (state.db.clone(), state.config.require_if_match),
+        check_entity_tags::<Sample>,
+    ));
+
+    // Deleted records are kept, out of sight until restored
+    mutating_router = mutating_router.layer(middleware::from_fn_with_state(
+        state.db.clone(),
+        hide_deleted::<Sample>,
+    ));
+
+    // Archived projects and their records are read-only, even to administrators
+    mutating_router = mutating_router.layer(middleware::from_fn_with_state(
+        (state.db.clone(), Some(ScopedResource::Samples)),
+        reject_archived_changes,
+    ));
+
+    // Changes are logged with the user who made them
+    mutating_router = mutating_router.layer(middleware::from_fn_with_state(
+        (state.db.clone(), AuditedResource::Samples),
+        audit_changes,
+    ));
 
     if let Some(instance) = state.keycloak_auth_instance.clone() {
-        mutating_router = mutating_router.layer(
-            KeycloakAuthLayer::<Role>::builder()
-                .instance(instance)
-                .passthrough_mode(PassthroughMode::Block)
-                .persist_raw_claims(false)
-                .expected_audiences(vec![String::from("account")])
-                .required_roles(vec![Role::Administrator])
-                .build(),
-        );
+        // Viewers read and editors change records; users who are not
+        // administrators are limited to the projects they are members of and
+        // the records they created or were given, within their labs
+        mutating_router = mutating_router
+            .layer(middleware::from_fn_with_state(
+                (state.db.clone(), ScopedResource::Samples),
+                require_project_access,
+            ))
+            .layer(middleware::from_fn_with_state(
+                (state.db.clone(), TenantResource::Samples),
+                require_lab,
+            ))
+            .layer(middleware::from_fn_with_state(
+                RouteAccess::PROJECT_RECORDS,
+                require_role,
+            ))
+            .layer(middleware::from_fn(redact_for_viewers))
+            .layer(middleware::from_fn_with_state(
+                (state.db.clone(), "samples"),
+                accept_api_keys,
+            ))
+            .layer(middleware::from_fn_with_state(
+                (instance, PassthroughMode::Pass),
+                authenticate,
+            ));
     } else if !state.config.tests_running {
         println!(
             "Warning: Mutating routes of {} router are not protected",
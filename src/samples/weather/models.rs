@@ -0,0 +1,75 @@
+use chrono::{DateTime, Utc};
+use crudcrate::EntityToModels;
+use rust_decimal::Decimal;
+use sea_orm::entity::prelude::*;
+use uuid::Uuid;
+
+/// Weather over a sample's collection period, cached from the provider for
+/// the coordinates and times the sample had when it was fetched
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, EntityToModels)]
+#[sea_orm(table_name = "sample_weather")]
+#[crudcrate(api_struct = "SampleWeather")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    #[crudcrate(primary_key, update_model = false, create_model = false, on_create = Uuid::new_v4())]
+    pub id: Uuid,
+    #[sea_orm(unique)]
+    #[crudcrate(sortable, filterable)]
+    pub sample_id: Uuid,
+    /// API the weather was fetched from
+    #[sea_orm(column_type = "Text")]
+    pub source: String,
+    #[sea_orm(column_type = "Decimal(Some((9, 6)))")]
+    pub latitude: Decimal,
+    #[sea_orm(column_type = "Decimal(Some((9, 6)))")]
+    pub longitude: Decimal,
+    pub start_time: DateTime<Utc>,
+    pub stop_time: Option<DateTime<Utc>>,
+    #[sea_orm(column_type = "Decimal(Some((12, 3)))", nullable)]
+    #[crudcrate(sortable, filterable)]
+    pub temperature_mean_c: Option<Decimal>,
+    #[sea_orm(column_type = "Decimal(Some((12, 3)))", nullable)]
+    pub temperature_min_c: Option<Decimal>,
+    #[sea_orm(column_type = "Decimal(Some((12, 3)))", nullable)]
+    pub temperature_max_c: Option<Decimal>,
+    #[sea_orm(column_type = "Decimal(Some((12, 3)))", nullable)]
+    #[crudcrate(sortable, filterable)]
+    pub wind_speed_mean_m_s: Option<Decimal>,
+    #[sea_orm(column_type = "Decimal(Some((12, 3)))", nullable)]
+    pub wind_speed_max_m_s: Option<Decimal>,
+    /// Vector mean of the direction the wind comes from
+    #[sea_orm(column_type = "Decimal(Some((12, 3)))", nullable)]
+    pub wind_direction_mean_deg: Option<Decimal>,
+    #[sea_orm(column_type = "Decimal(Some((12, 3)))", nullable)]
+    #[crudcrate(sortable, filterable)]
+    pub boundary_layer_height_mean_m: Option<Decimal>,
+    #[sea_orm(column_type = "Decimal(Some((12, 3)))", nullable)]
+    pub boundary_layer_height_min_m: Option<Decimal>,
+    #[sea_orm(column_type = "Decimal(Some((12, 3)))", nullable)]
+    pub boundary_layer_height_max_m: Option<Decimal>,
+    /// `WeatherHour`s of the collection period
+    #[sea_orm(column_type = "JsonBinary")]
+    pub hourly: Json,
+    #[crudcrate(update_model = false, create_model = false, on_create = chrono::Utc::now(), sortable)]
+    pub fetched_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "crate::samples::models::Entity",
+        from = "Column::SampleId",
+        to = "crate::samples::models::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Samples,
+}
+
+impl Related<crate::samples::models::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Samples.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
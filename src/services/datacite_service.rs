@@ -0,0 +1,476 @@
+//! `DataCite` metadata for publishing experiments and projects as datasets
+//!
+//! The metadata follows the `DataCite` Metadata Schema 4 JSON representation so
+//! it can be submitted to a `DataCite` member or mapped onto a Zenodo deposition.
+
+use crate::{
+    experiments::models as experiments, locations::models as locations,
+    projects::models as projects, samples::models as samples,
+    tray_configurations::regions::models as regions, treatments::models as treatments,
+};
+use chrono::{Datelike, Utc};
+use sea_orm::{ActiveEnum, DatabaseConnection, EntityTrait, QueryOrder, entity::prelude::*};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+const SCHEMA_VERSION: &str = "http://datacite.org/schema/kernel-4";
+const BASE_SUBJECTS: [&str; 2] = ["Ice nucleation", "Ice-nucleating particles"];
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DataCiteMetadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub doi: Option<String>,
+    pub types: DataCiteTypes,
+    pub creators: Vec<DataCiteCreator>,
+    pub titles: Vec<DataCiteTitle>,
+    pub publisher: String,
+    pub publication_year: i32,
+    pub subjects: Vec<DataCiteSubject>,
+    pub dates: Vec<DataCiteDate>,
+    pub descriptions: Vec<DataCiteDescription>,
+    pub geo_locations: Vec<DataCiteGeoLocation>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub related_identifiers: Vec<DataCiteRelatedIdentifier>,
+    pub schema_version: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DataCiteTypes {
+    pub resource_type_general: String,
+    pub resource_type: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DataCiteCreator {
+    pub name: String,
+    pub name_type: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct DataCiteTitle {
+    pub title: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct DataCiteSubject {
+    pub subject: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DataCiteDate {
+    /// ISO 8601 date, or a `start/end` range
+    pub date: String,
+    pub date_type: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DataCiteDescription {
+    pub description: String,
+    pub description_type: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DataCiteGeoLocation {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub geo_location_place: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub geo_location_point: Option<DataCiteGeoLocationPoint>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct DataCiteGeoLocationPoint {
+    pub point_latitude: String,
+    pub point_longitude: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DataCiteRelatedIdentifier {
+    pub related_identifier: String,
+    pub related_identifier_type: String,
+    pub relation_type: String,
+}
+
+/// Samples (with their locations and treatments) that an experiment or project draws on
+#[derive(Default)]
+struct SampleContext {
+    samples: Vec<samples::Model>,
+    location_names: HashMap<Uuid, String>,
+    treatment_names: BTreeSet<String>,
+}
+
+impl SampleContext {
+    async fn load(
+        db: &DatabaseConnection,
+        sample_ids: Vec<Uuid>,
+        treatment_names: BTreeSet<String>,
+    ) -> Result<Self, DbErr> {
+        if sample_ids.is_empty() {
+            return Ok(Self {
+                treatment_names,
+                ..Self::default()
+            });
+        }
+
+        let samples = samples::Entity::find()
+            .filter(samples::Column::Id.is_in(sample_ids))
+            .order_by_asc(samples::Column::Name)
+            .all(db)
+            .await?;
+
+        let location_ids: HashSet<Uuid> = samples.iter().filter_map(|s| s.location_id).collect();
+        let location_names = if location_ids.is_empty() {
+            HashMap::new()
+        } else {
+            locations::Entity::find()
+                .filter(locations::Column::Id.is_in(location_ids))
+                .all(db)
+                .await?
+                .into_iter()
+                .map(|location| (location.id, location.name))
+                .collect()
+        };
+
+        Ok(Self {
+            samples,
+            location_names,
+            treatment_names,
+        })
+    }
+
+    fn subjects(&self, extra: &[&str]) -> Vec<DataCiteSubject> {
+        let mut subjects: BTreeSet<String> = self
+            .samples
+            .iter()
+            .map(|sample| format!("{} sample", sample.r#type.to_value()))
+            .collect();
+        subjects.extend(
+            self.treatment_names
+                .iter()
+                .filter(|name| name.as_str() != "none")
+                .map(|name| format!("{name} treatment")),
+        );
+
+        BASE_SUBJECTS
+            .iter()
+            .chain(extra)
+            .map(ToString::to_string)
+            .chain(subjects)
+            .map(|subject| DataCiteSubject { subject })
+            .collect()
+    }
+
+    /// Sampling periods as `DataCite` "Collected" dates
+    fn collected_dates(&self) -> Vec<DataCiteDate> {
+        let periods: BTreeSet<String> = self
+            .samples
+            .iter()
+            .filter_map(|sample| match (sample.start_time, sample.stop_time) {
+                (Some(start), Some(stop)) => Some(format!(
+                    "{}/{}",
+                    start.format("%Y-%m-%d"),
+                    stop.format("%Y-%m-%d")
+                )),
+                (Some(time), None) | (None, Some(time)) => {
+                    Some(time.format("%Y-%m-%d").to_string())
+                }
+                (None, None) => None,
+            })
+            .collect();
+
+        periods
+            .into_iter()
+            .map(|date| DataCiteDate {
+                date,
+                date_type: "Collected".to_string(),
+            })
+            .collect()
+    }
+
+    /// One entry per distinct sampling point, named after the sample's location
+    fn geo_locations(&self) -> Vec<DataCiteGeoLocation> {
+        let mut seen = HashSet::new();
+        let mut geo_locations = Vec::new();
+
+        for sample in &self.samples {
+            let place = sample
+                .location_id
+                .and_then(|id| self.location_names.get(&id).cloned());
+            let point = match (sample.latitude, sample.longitude) {
+                (Some(latitude), Some(longitude)) => Some(DataCiteGeoLocationPoint {
+                    point_latitude: latitude.normalize().to_string(),
+                    point_longitude: longitude.normalize().to_string(),
+                }),
+                _ => None,
+            };
+
+            if (place.is_none() && point.is_none()) || !seen.insert((place.clone(), point.clone()))
+            {
+                continue;
+            }
+
+            geo_locations.push(DataCiteGeoLocation {
+                geo_location_place: place,
+                geo_location_point: point,
+            });
+        }
+
+        geo_locations
+    }
+}
+
+fn creators_from_usernames<'a>(
+    usernames: impl Iterator<Item = &'a Option<String>>,
+    publisher: &str,
+) -> Vec<DataCiteCreator> {
+    let names: BTreeSet<&String> = usernames.flatten().collect();
+    if names.is_empty() {
+        return vec![DataCiteCreator {
+            name: publisher.to_string(),
+            name_type: "Organizational".to_string(),
+        }];
+    }
+
+    names
+        .into_iter()
+        .map(|name| DataCiteCreator {
+            name: name.clone(),
+            name_type: "Personal".to_string(),
+        })
+        .collect()
+}
+
+fn description(text: String, description_type: &str) -> DataCiteDescription {
+    DataCiteDescription {
+        description: text,
+        description_type: description_type.to_string(),
+    }
+}
+
+fn dataset_types(resource_type: &str) -> DataCiteTypes {
+    DataCiteTypes {
+        resource_type_general: "Dataset".to_string(),
+        resource_type: resource_type.to_string(),
+    }
+}
+
+/// Describe the freezing protocol of an experiment for the "Methods" description
+fn experiment_methods(experiment: &experiments::Model) -> Option<String> {
+    let mut parts = Vec::new();
+    if let Some(ramp) = experiment.temperature_ramp {
+        parts.push(format!("cooling rate {} °C/min", ramp.normalize()));
+    }
+    match (experiment.temperature_start, experiment.temperature_end) {
+        (Some(start), Some(end)) => parts.push(format!(
+            "from {} °C to {} °C",
+            start.normalize(),
+            end.normalize()
+        )),
+        (Some(start), None) => parts.push(format!("starting at {} °C", start.normalize())),
+        (None, Some(end)) => parts.push(format!("ending at {} °C", end.normalize())),
+        (None, None) => {}
+    }
+
+    if parts.is_empty() {
+        None
+    } else {
+        Some(format!("Droplet freezing assay with {}.", parts.join(", ")))
+    }
+}
+
+/// Build `DataCite` metadata for a single experiment
+pub async fn experiment_metadata(
+    db: &DatabaseConnection,
+    experiment_id: Uuid,
+    publisher: &str,
+) -> Result<DataCiteMetadata, DbErr> {
+    let experiment = experiments::Entity::find_by_id(experiment_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Experiment not found".to_string()))?;
+
+    let treatment_ids: HashSet<Uuid> = regions::Entity::find()
+        .filter(regions::Column::ExperimentId.eq(experiment_id))
+        .all(db)
+        .await?
+        .into_iter()
+        .filter_map(|region| region.treatment_id)
+        .collect();
+    let experiment_treatments = if treatment_ids.is_empty() {
+        vec![]
+    } else {
+        treatments::Entity::find()
+            .filter(treatments::Column::Id.is_in(treatment_ids))
+            .all(db)
+            .await?
+    };
+
+    let context = SampleContext::load(
+        db,
+        experiment_treatments
+            .iter()
+            .filter_map(|treatment| treatment.sample_id)
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect(),
+        experiment_treatments
+            .iter()
+            .map(|treatment| treatment.name.to_value())
+            .collect(),
+    )
+    .await?;
+
+    let mut dates = Vec::new();
+    if let Some(performed_at) = experiment.performed_at {
+        dates.push(DataCiteDate {
+            date: performed_at.format("%Y-%m-%d").to_string(),
+            date_type: "Created".to_string(),
+        });
+    }
+    dates.extend(context.collected_dates());
+
+    let mut descriptions = vec![description(
+        experiment.remarks.clone().unwrap_or_else(|| {
+            format!(
+                "Ice nucleation droplet freezing experiment \"{}\".",
+                experiment.name
+            )
+        }),
+        "Abstract",
+    )];
+    if let Some(methods) = experiment_methods(&experiment) {
+        descriptions.push(description(methods, "Methods"));
+    }
+
+    let extra_subjects: &[&str] = if experiment.is_calibration {
+        &["Calibration"]
+    } else {
+        &[]
+    };
+
+    Ok(DataCiteMetadata {
+        doi: experiment.doi.clone(),
+        types: dataset_types("Ice nucleation experiment"),
+        creators: creators_from_usernames(std::iter::once(&experiment.username), publisher),
+        titles: vec![DataCiteTitle {
+            title: experiment.name.clone(),
+        }],
+        publisher: publisher.to_string(),
+        publication_year: Utc::now().year(),
+        subjects: context.subjects(extra_subjects),
+        dates,
+        descriptions,
+        geo_locations: context.geo_locations(),
+        related_identifiers: vec![],
+        schema_version: SCHEMA_VERSION.to_string(),
+    })
+}
+
+/// Build `DataCite` metadata for a project, covering its samples and every
+/// experiment that measured them
+pub async fn project_metadata(
+    db: &DatabaseConnection,
+    project_id: Uuid,
+    publisher: &str,
+) -> Result<DataCiteMetadata, DbErr> {
+    let project = projects::Entity::find_by_id(project_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Project not found".to_string()))?;
+
+    let crate::projects::services::ProjectRecords {
+        samples: project_samples,
+        treatments: project_treatments,
+        experiments: project_experiments,
+        ..
+    } = crate::projects::services::load_project_records(db, project_id).await?;
+
+    let context = SampleContext::load(
+        db,
+        project_samples.iter().map(|sample| sample.id).collect(),
+        project_treatments
+            .iter()
+            .map(|treatment| treatment.name.to_value())
+            .collect(),
+    )
+    .await?;
+
+    let performed: Vec<_> = project_experiments
+        .iter()
+        .filter_map(|experiment| experiment.performed_at)
+        .collect();
+    let mut dates = Vec::new();
+    if let (Some(first), Some(last)) = (performed.iter().min(), performed.iter().max()) {
+        dates.push(DataCiteDate {
+            date: if first.date_naive() == last.date_naive() {
+                first.format("%Y-%m-%d").to_string()
+            } else {
+                format!("{}/{}", first.format("%Y-%m-%d"), last.format("%Y-%m-%d"))
+            },
+            date_type: "Created".to_string(),
+        });
+    }
+    dates.extend(context.collected_dates());
+
+    let mut descriptions = vec![description(
+        project.note.clone().unwrap_or_else(|| {
+            format!(
+                "Ice nucleation measurements of samples collected for the project \"{}\".",
+                project.name
+            )
+        }),
+        "Abstract",
+    )];
+    if !project_experiments.is_empty() {
+        descriptions.push(description(
+            format!(
+                "Contains {} droplet freezing experiment(s) on {} sample(s).",
+                project_experiments.len(),
+                context.samples.len()
+            ),
+            "TechnicalInfo",
+        ));
+    }
+
+    // Experiments that were published on their own are parts of this dataset
+    let related_identifiers = project_experiments
+        .iter()
+        .filter_map(|experiment| experiment.doi.clone())
+        .map(|doi| DataCiteRelatedIdentifier {
+            related_identifier: doi,
+            related_identifier_type: "DOI".to_string(),
+            relation_type: "HasPart".to_string(),
+        })
+        .collect();
+
+    Ok(DataCiteMetadata {
+        doi: None,
+        types: dataset_types("Ice nucleation project"),
+        creators: creators_from_usernames(
+            project_experiments
+                .iter()
+                .map(|experiment| &experiment.username),
+            publisher,
+        ),
+        titles: vec![DataCiteTitle {
+            title: project.name.clone(),
+        }],
+        publisher: publisher.to_string(),
+        publication_year: Utc::now().year(),
+        subjects: context.subjects(&[]),
+        dates,
+        descriptions,
+        geo_locations: context.geo_locations(),
+        related_identifiers,
+        schema_version: SCHEMA_VERSION.to_string(),
+    })
+}
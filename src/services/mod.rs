@@ -1,2 +1,4 @@
 pub mod convex_hull_service;
+pub mod datacite_service;
 pub mod processing;
+pub mod thumbnail_service;
@@ -16,7 +16,10 @@ use crate::{
     },
 };
 use anyhow::{Context, Result, anyhow};
-use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, Set};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait,
+    IntoActiveModel, Iterable, QueryFilter, Set,
+};
 use std::collections::HashMap;
 use uuid::Uuid;
 
@@ -71,8 +74,8 @@ impl DatabaseOperations {
 
         for well_key in structure.well_columns.keys() {
             // Parse well_key like "P1:A1"
-            if let Some((tray_name, well_coord)) = well_key.split_once(':') {
-                if let Some(&tray_id) = tray_name_to_id.get(tray_name) {
+            if let Some((tray_name, well_coord)) = well_key.split_once(':')
+                && let Some(&tray_id) = tray_name_to_id.get(tray_name) {
                     // Parse coordinate like "A1" -> row_letter="A", column_number=1
                     if let Ok((row_letter, column_number)) = parse_well_coordinate(well_coord) {
                         // Find the well in the database
@@ -95,15 +98,15 @@ impl DatabaseOperations {
                         tracing::warn!("Invalid coordinate: {well_coord}");
                     }
                 }
-            }
         }
 
         tracing::debug!("Loaded {} well mappings from database", well_mappings.len());
         Ok(well_mappings)
     }
 
-    /// Load probe mappings from database for the given experiment
-    pub async fn load_probe_mappings(&self, experiment_id: Uuid) -> Result<HashMap<usize, Uuid>> {
+    /// Load the probes of the experiment's trays by data logger channel
+    /// (`data_column_index`), however many the configuration has
+    pub async fn load_probe_mappings(&self, experiment_id: Uuid) -> Result<HashMap<i32, Uuid>> {
         // Get experiment's tray configuration
         let experiment = experiments::Entity::find_by_id(experiment_id)
             .one(&self.db)
@@ -126,22 +129,23 @@ impl DatabaseOperations {
             .context("Failed to query trays")?;
 
         // Load probes for all trays in the configuration
+        let probe_records = probes::Entity::find()
+            .filter(probes::Column::TrayId.is_in(tray_records.iter().map(|tray| tray.id)))
+            .all(&self.db)
+            .await
+            .context("Failed to query probes")?;
+
         let mut probe_mappings = HashMap::new();
-        for tray in &tray_records {
-            let probe_records = probes::Entity::find()
-                .filter(probes::Column::TrayId.eq(tray.id))
-                .all(&self.db)
-                .await
-                .context("Failed to query probes")?;
-
-            for probe in &probe_records {
-                // Map data_column_index to probe ID with proper cast handling
-                // Excel structure: Date(0), Time(1), Temp1(2), Temp2(3), ..., Temp8(9)
-                // But data_column_index is stored as 1-8 (user-friendly), so we need to add 1
-                #[allow(clippy::cast_sign_loss)]
-                // data_column_index is always positive in this context
-                let col_index = (probe.data_column_index + 1) as usize;
-                probe_mappings.insert(col_index, probe.id);
+        for probe in &probe_records {
+            if probe_mappings
+                .insert(probe.data_column_index, probe.id)
+                .is_some()
+            {
+                tracing::warn!(
+                    "Several probes read channel {}, using {}",
+                    probe.data_column_index,
+                    probe.name
+                );
             }
         }
 
@@ -212,9 +216,7 @@ impl DatabaseOperations {
 
         // Extract wells for this tray from the Excel structure
         let wells_for_tray: Vec<(&str, &str)> = structure
-            .well_columns
-            .iter()
-            .filter_map(|(well_key, _)| {
+            .well_columns.keys().filter_map(|well_key| {
                 // well_key format: "P1:A1"
                 let parts: Vec<&str> = well_key.split(':').collect();
                 if parts.len() == 2 && parts[0] == tray_name {
@@ -282,6 +284,13 @@ impl DatabaseOperations {
     }
 }
 
+/// Bind parameters a statement may have, the lower of the limits of the
+/// Postgres and `SQLite` drivers
+const MAX_BIND_PARAMETERS: usize = 32_766;
+
+/// Records buffered before they are written
+pub const FLUSH_RECORDS: usize = 20_000;
+
 /// Batch container for database operations
 #[derive(Debug, Default)]
 pub struct ProcessingBatches {
@@ -293,87 +302,37 @@ pub struct ProcessingBatches {
     pub phase_transitions_total: usize,
 }
 
+/// Insert records in as few statements as the bind parameter limit allows
+pub async fn insert_all<A>(db: &impl ConnectionTrait, records: Vec<A>) -> Result<(), DbErr>
+where
+    A: ActiveModelTrait + Send,
+    <A::Entity as EntityTrait>::Model: IntoActiveModel<A>,
+{
+    let per_statement = MAX_BIND_PARAMETERS / <A::Entity as EntityTrait>::Column::iter().count();
+    let mut records = records.into_iter().peekable();
+    while records.peek().is_some() {
+        <A::Entity as EntityTrait>::insert_many(records.by_ref().take(per_statement))
+            .exec_without_returning(db)
+            .await?;
+    }
+    Ok(())
+}
+
 impl ProcessingBatches {
     pub fn total_count(&self) -> usize {
         self.temp_readings.len() + self.probe_readings.len() + self.phase_transitions.len()
     }
 
     /// Flush all batches to the database
-    pub async fn flush(&mut self, db: &DatabaseConnection) -> Result<()> {
+    pub async fn flush(&mut self, db: &impl ConnectionTrait) -> Result<()> {
         // Update totals before draining
         self.temp_readings_total += self.temp_readings.len();
         self.probe_readings_total += self.probe_readings.len();
         self.phase_transitions_total += self.phase_transitions.len();
 
-        // Insert batches
-        if !self.temp_readings.is_empty() {
-            temperature_readings::Entity::insert_many(self.temp_readings.drain(..))
-                .exec(db)
-                .await?;
-        }
-        if !self.probe_readings.is_empty() {
-            probe_temperature_readings::Entity::insert_many(self.probe_readings.drain(..))
-                .exec(db)
-                .await?;
-        }
-        if !self.phase_transitions.is_empty() {
-            phase_transitions::Entity::insert_many(self.phase_transitions.drain(..))
-                .exec(db)
-                .await?;
-        }
+        insert_all(db, std::mem::take(&mut self.temp_readings)).await?;
+        insert_all(db, std::mem::take(&mut self.probe_readings)).await?;
+        insert_all(db, std::mem::take(&mut self.phase_transitions)).await?;
         Ok(())
     }
 }
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_probe_column_offset() {
-        // Test that the probe column mapping correctly handles the Excel column offset
-        // Excel structure: Date(0), Time(1), Temp1(2), Temp2(3), ..., Temp8(9)
-        // Database stores: data_column_index 1-8 (user-friendly)
-        // Mapping should convert: 1->2, 2->3, 3->4, 4->5, 5->6, 6->7, 7->8, 8->9
-        
-        struct MockProbe {
-            data_column_index: i32,
-            id: Uuid,
-        }
-        
-        let mock_probes = vec![
-            MockProbe { data_column_index: 1, id: Uuid::new_v4() },
-            MockProbe { data_column_index: 2, id: Uuid::new_v4() },
-            MockProbe { data_column_index: 3, id: Uuid::new_v4() },
-            MockProbe { data_column_index: 4, id: Uuid::new_v4() },
-            MockProbe { data_column_index: 5, id: Uuid::new_v4() },
-            MockProbe { data_column_index: 6, id: Uuid::new_v4() },
-            MockProbe { data_column_index: 7, id: Uuid::new_v4() },
-            MockProbe { data_column_index: 8, id: Uuid::new_v4() },
-        ];
-        
-        let mut probe_mappings = HashMap::new();
-        for probe in &mock_probes {
-            // Apply the same logic as in load_probe_mappings
-            #[allow(clippy::cast_sign_loss)]
-            let col_index = (probe.data_column_index + 1) as usize;
-            probe_mappings.insert(col_index, probe.id);
-        }
-        
-        // Verify mappings
-        assert_eq!(probe_mappings.len(), 8);
-        assert!(probe_mappings.contains_key(&2));  // Probe 1 -> Excel column 2
-        assert!(probe_mappings.contains_key(&3));  // Probe 2 -> Excel column 3
-        assert!(probe_mappings.contains_key(&4));  // Probe 3 -> Excel column 4
-        assert!(probe_mappings.contains_key(&5));  // Probe 4 -> Excel column 5
-        assert!(probe_mappings.contains_key(&6));  // Probe 5 -> Excel column 6
-        assert!(probe_mappings.contains_key(&7));  // Probe 6 -> Excel column 7
-        assert!(probe_mappings.contains_key(&8));  // Probe 7 -> Excel column 8
-        assert!(probe_mappings.contains_key(&9));  // Probe 8 -> Excel column 9
-        
-        // Verify incorrect mappings don't exist
-        assert!(!probe_mappings.contains_key(&0)); // No probe at column 0 (Date)
-        assert!(!probe_mappings.contains_key(&1)); // No probe at column 1 (Time)
-        assert!(!probe_mappings.contains_key(&10)); // No probe beyond column 9
-    }
-}
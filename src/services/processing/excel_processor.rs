@@ -7,12 +7,13 @@
 use crate::common::models::ProcessingStatus;
 use anyhow::Result;
 use chrono::Utc;
-use sea_orm::DatabaseConnection;
+use sea_orm::{ConnectionTrait, DatabaseConnection, TransactionTrait};
 use std::collections::HashMap;
 use uuid::Uuid;
 
 use super::{
-    database::{DatabaseOperations, ProcessingBatches},
+    database::{DatabaseOperations, FLUSH_RECORDS, ProcessingBatches},
+    progress::{ProcessingJobs, ProgressReporter},
     row_processing::{ProcessingResult, process_row},
     structure::parse_excel_structure,
     utils::load_excel,
@@ -20,7 +21,23 @@ use super::{
 
 /// Result of Excel file processing
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[schema(example = json!({
+    "job_id": "0c6f1d2e-3b4a-4f5e-8d7c-9a0b1c2d3e4f",
+    "status": "completed",
+    "success": true,
+    "temperature_readings_created": 6786,
+    "probe_temperature_readings_created": 54288,
+    "phase_transitions_created": 192,
+    "wells_tracked": 192,
+    "processing_time_ms": 5120,
+    "started_at": "2025-03-21T09:30:00Z",
+    "completed_at": "2025-03-21T09:30:05Z",
+    "error": null,
+    "errors": []
+}))]
 pub struct ExcelProcessingResult {
+    /// Job the progress of the processing was reported under
+    pub job_id: Uuid,
     pub status: ProcessingStatus,
     pub success: bool,
     pub temperature_readings_created: usize,
@@ -38,30 +55,39 @@ pub struct ExcelProcessingResult {
 #[derive(Clone)]
 pub struct ExcelProcessor {
     db: DatabaseConnection,
+    /// Progress of the files being processed and recently processed
+    pub jobs: ProcessingJobs,
 }
 
 impl ExcelProcessor {
     pub fn new(db: DatabaseConnection) -> Self {
-        Self { db }
+        Self {
+            db,
+            jobs: ProcessingJobs::default(),
+        }
     }
 
     /// Clear existing experimental data for an experiment before reprocessing
-    async fn clear_experiment_data(&self, experiment_id: Uuid) -> Result<()> {
+    async fn clear_experiment_data(db: &impl ConnectionTrait, experiment_id: Uuid) -> Result<()> {
         use sea_orm::{EntityTrait, QueryFilter, ColumnTrait};
 
         // Delete phase transitions for this experiment first
         crate::experiments::phase_transitions::models::Entity::delete_many()
             .filter(crate::experiments::phase_transitions::models::Column::ExperimentId.eq(experiment_id))
-            .exec(&self.db)
+            .exec(db)
             .await
-            .map_err(|e| anyhow::anyhow!("Failed to clear phase transitions: {}", e))?;
+            .map_err(|e| anyhow::anyhow!("Failed to clear phase transitions: {e}"))?;
 
-        // Delete temperature readings for this experiment (will cascade delete probe readings due to FK constraints)
+        // Delete temperature readings for this experiment (the temperature_reading_deleted trigger deletes their probe readings)
         crate::experiments::temperatures::models::Entity::delete_many()
             .filter(crate::experiments::temperatures::models::Column::ExperimentId.eq(experiment_id))
-            .exec(&self.db)
+            .exec(db)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to clear temperature readings: {e}"))?;
+
+        crate::experiments::summaries::discard_results_summary(db, experiment_id)
             .await
-            .map_err(|e| anyhow::anyhow!("Failed to clear temperature readings: {}", e))?;
+            .map_err(|e| anyhow::anyhow!("Failed to clear results summary: {e}"))?;
 
         Ok(())
     }
@@ -71,14 +97,40 @@ impl ExcelProcessor {
         &self,
         experiment_id: Uuid,
         file_data: Vec<u8>,
+    ) -> Result<ExcelProcessingResult> {
+        let job = self.jobs.start(experiment_id);
+        self.process_excel_file_in_job(&job, experiment_id, file_data)
+            .await
+    }
+
+    /// Process Excel file for an experiment, reporting progress under a job
+    /// started beforehand
+    pub async fn process_excel_file_in_job(
+        &self,
+        job: &ProgressReporter,
+        experiment_id: Uuid,
+        file_data: Vec<u8>,
     ) -> Result<ExcelProcessingResult> {
         let started_at = Utc::now();
 
-        match self
-            .process_excel_file_direct(file_data, experiment_id)
+        let outcome = self
+            .process_excel_file_direct(file_data, experiment_id, job)
+            .await;
+        // Results are built once for the new data and kept for reads
+        if outcome.is_ok()
+            && let Err(e) = crate::experiments::summaries::recompute_results_summary(
+                &self.db,
+                experiment_id,
+                Some(job.job_id()),
+            )
             .await
         {
-            Ok(result) => Ok(ExcelProcessingResult {
+            tracing::warn!("Failed to keep the results of experiment {experiment_id}: {e}");
+        }
+        job.finished(outcome.as_ref().err().map(ToString::to_string));
+        let result = match outcome {
+            Ok(result) => ExcelProcessingResult {
+                job_id: job.job_id(),
                 status: ProcessingStatus::Completed,
                 success: result.success,
                 temperature_readings_created: result.temperature_readings,
@@ -90,8 +142,9 @@ impl ExcelProcessor {
                 completed_at: Some(Utc::now()),
                 error: None,
                 errors: result.errors,
-            }),
-            Err(e) => Ok(ExcelProcessingResult {
+            },
+            Err(e) => ExcelProcessingResult {
+                job_id: job.job_id(),
                 status: ProcessingStatus::Failed,
                 success: false,
                 temperature_readings_created: 0,
@@ -103,8 +156,15 @@ impl ExcelProcessor {
                 completed_at: Some(Utc::now()),
                 error: Some(e.to_string()),
                 errors: vec![e.to_string()],
-            }),
-        }
+            },
+        };
+
+        // Downstream pipelines hear of the experiment's new data, or that it failed
+        let mut data = serde_json::to_value(&result).unwrap_or_default();
+        data["experiment_id"] = serde_json::json!(experiment_id);
+        crate::webhooks::services::emit(&self.db, "experiment.processed", data).await;
+
+        Ok(result)
     }
 
     /// Process Excel file for an experiment (internal implementation)
@@ -112,13 +172,11 @@ impl ExcelProcessor {
         &self,
         file_data: Vec<u8>,
         experiment_id: Uuid,
+        job: &ProgressReporter,
     ) -> Result<ProcessingResult> {
         let start_time = std::time::Instant::now();
         let mut errors = Vec::new();
 
-        // Clear existing experimental data before processing to avoid duplicates
-        self.clear_experiment_data(experiment_id).await?;
-
         // Load Excel data and parse structure
         let rows = load_excel(file_data)?;
         let structure = parse_excel_structure(&rows)?;
@@ -144,9 +202,15 @@ impl ExcelProcessor {
             return Err(anyhow::anyhow!("No wells found for experiment"));
         }
 
+        // The old data is replaced in one transaction, so a failed run
+        // leaves it as it was
+        let txn = self.db.begin().await?;
+        Self::clear_experiment_data(&txn, experiment_id).await?;
+
         // Process data in batches
         let mut batches = ProcessingBatches::default();
         let mut phase_states: HashMap<String, i32> = HashMap::new();
+        job.started(rows.len().saturating_sub(structure.data_start_row));
 
         for (row_idx, row) in rows.iter().skip(structure.data_start_row).enumerate() {
             match process_row(
@@ -164,9 +228,13 @@ impl ExcelProcessor {
                     batches.probe_readings.extend(probe_readings);
                     batches.phase_transitions.extend(transitions);
 
-                    // Batch insert every 500 records
-                    if batches.total_count() >= 500 {
-                        batches.flush(&self.db).await?;
+                    if batches.total_count() >= FLUSH_RECORDS {
+                        batches.flush(&txn).await?;
+                        job.advanced(
+                            row_idx + 1,
+                            batches.temp_readings_total,
+                            batches.phase_transitions_total,
+                        );
                     }
                 }
                 Err(e) => {
@@ -182,7 +250,16 @@ impl ExcelProcessor {
         }
 
         // Final flush
-        batches.flush(&self.db).await?;
+        batches.flush(&txn).await?;
+        job.advanced(
+            rows.len().saturating_sub(structure.data_start_row),
+            batches.temp_readings_total,
+            batches.phase_transitions_total,
+        );
+
+        // Readings were replaced, so camera images are linked to the new ones
+        crate::assets::capture::link_experiment_images(&txn, experiment_id).await?;
+        txn.commit().await?;
 
         let processing_time = start_time.elapsed().as_millis();
 
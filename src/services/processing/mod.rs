@@ -1,5 +1,6 @@
 pub mod database;
 pub mod excel_processor;
+pub mod progress;
 pub mod row_processing;
 pub mod structure;
 pub mod utils;
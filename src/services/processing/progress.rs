@@ -0,0 +1,183 @@
+//! Progress of the processing of experiment data files.
+//!
+//! Each run of the processor is a job, kept in memory for an hour after it
+//! ends. Its progress is published on a watch channel, so a client streaming
+//! it sees the latest counts without a backlog of stale ones.
+
+use crate::common::models::ProcessingStatus;
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use tokio::sync::watch;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// How long a finished job's progress is kept
+const JOB_RETENTION_HOURS: i64 = 1;
+
+/// Progress of a processing job
+#[derive(Clone, Debug, Serialize, ToSchema)]
+#[schema(example = json!({
+    "job_id": "0c6f1d2e-3b4a-4f5e-8d7c-9a0b1c2d3e4f",
+    "experiment_id": "8f3e2d1c-0b9a-4876-a543-210fedcba987",
+    "status": "in_progress",
+    "rows_total": 6786,
+    "rows_parsed": 3393,
+    "temperature_readings_written": 3393,
+    "phase_transitions_written": 91,
+    "percent_complete": 50,
+    "started_at": "2025-03-21T09:30:00Z",
+    "completed_at": null,
+    "error": null
+}))]
+pub struct ProcessingProgress {
+    pub job_id: Uuid,
+    pub experiment_id: Uuid,
+    pub status: ProcessingStatus,
+    /// Data rows in the file, once it has been read
+    pub rows_total: Option<usize>,
+    pub rows_parsed: usize,
+    pub temperature_readings_written: usize,
+    pub phase_transitions_written: usize,
+    /// Share of the rows parsed, from 0 to 100
+    pub percent_complete: u8,
+    pub started_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub error: Option<String>,
+}
+
+impl ProcessingProgress {
+    fn new(job_id: Uuid, experiment_id: Uuid) -> Self {
+        Self {
+            job_id,
+            experiment_id,
+            status: ProcessingStatus::Pending,
+            rows_total: None,
+            rows_parsed: 0,
+            temperature_readings_written: 0,
+            phase_transitions_written: 0,
+            percent_complete: 0,
+            started_at: Utc::now(),
+            completed_at: None,
+            error: None,
+        }
+    }
+
+    /// Whether the job has ended, for good or not
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self.status,
+            ProcessingStatus::Completed | ProcessingStatus::Failed
+        )
+    }
+}
+
+/// Handle a processing job reports its progress with
+#[derive(Clone, Debug)]
+pub struct ProgressReporter {
+    sender: watch::Sender<ProcessingProgress>,
+}
+
+impl ProgressReporter {
+    pub fn job_id(&self) -> Uuid {
+        self.sender.borrow().job_id
+    }
+
+    /// The data rows have been read from the file
+    pub fn started(&self, rows_total: usize) {
+        self.sender.send_modify(|progress| {
+            progress.status = ProcessingStatus::InProgress;
+            progress.rows_total = Some(rows_total);
+        });
+    }
+
+    /// Rows parsed so far, and the records written for them
+    pub fn advanced(
+        &self,
+        rows_parsed: usize,
+        readings_written: usize,
+        transitions_written: usize,
+    ) {
+        self.sender.send_modify(|progress| {
+            progress.rows_parsed = rows_parsed;
+            progress.temperature_readings_written = readings_written;
+            progress.phase_transitions_written = transitions_written;
+            progress.percent_complete = match progress.rows_total {
+                Some(0) | None => 0,
+                Some(total) => u8::try_from((rows_parsed.min(total) * 100) / total).unwrap_or(100),
+            };
+        });
+    }
+
+    /// The job has ended, with the error it failed with if any
+    pub fn finished(&self, error: Option<String>) {
+        self.sender.send_modify(|progress| {
+            progress.completed_at = Some(Utc::now());
+            if error.is_some() {
+                progress.status = ProcessingStatus::Failed;
+                progress.error = error;
+            } else {
+                progress.status = ProcessingStatus::Completed;
+                progress.percent_complete = 100;
+            }
+        });
+    }
+}
+
+/// Processing jobs, by job ID
+#[derive(Clone, Debug, Default)]
+pub struct ProcessingJobs {
+    jobs: Arc<Mutex<HashMap<Uuid, watch::Sender<ProcessingProgress>>>>,
+}
+
+impl ProcessingJobs {
+    /// Register a job processing an experiment's data file
+    pub fn start(&self, experiment_id: Uuid) -> ProgressReporter {
+        let job_id = Uuid::new_v4();
+        let (sender, _) = watch::channel(ProcessingProgress::new(job_id, experiment_id));
+        let cutoff = Utc::now() - Duration::hours(JOB_RETENTION_HOURS);
+        let mut jobs = self.jobs.lock().unwrap_or_else(PoisonError::into_inner);
+        // Forget the jobs that ended long ago
+        jobs.retain(|_, job| job.borrow().completed_at.is_none_or(|at| at >= cutoff));
+        jobs.insert(job_id, sender.clone());
+        ProgressReporter { sender }
+    }
+
+    /// Progress of a job, following its changes
+    pub fn subscribe(&self, job_id: Uuid) -> Option<watch::Receiver<ProcessingProgress>> {
+        self.jobs
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&job_id)
+            .map(watch::Sender::subscribe)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_is_reported() {
+        let jobs = ProcessingJobs::default();
+        let experiment_id = Uuid::new_v4();
+        let reporter = jobs.start(experiment_id);
+        let progress = jobs.subscribe(reporter.job_id()).unwrap();
+        assert_eq!(progress.borrow().status, ProcessingStatus::Pending);
+
+        reporter.started(200);
+        reporter.advanced(50, 48, 3);
+        {
+            let current = progress.borrow();
+            assert_eq!(current.status, ProcessingStatus::InProgress);
+            assert_eq!(current.percent_complete, 25);
+            assert_eq!(current.phase_transitions_written, 3);
+        }
+
+        reporter.finished(Some("No wells found for experiment".to_string()));
+        assert!(progress.borrow().is_finished());
+        assert_eq!(progress.borrow().status, ProcessingStatus::Failed);
+        assert!(jobs.subscribe(Uuid::new_v4()).is_none());
+    }
+}
@@ -26,7 +26,7 @@ pub fn process_row(
     structure: &ExcelStructure,
     experiment_id: Uuid,
     well_mappings: &HashMap<String, Uuid>,
-    probe_mappings: &HashMap<usize, Uuid>,
+    probe_mappings: &HashMap<i32, Uuid>,
     phase_states: &mut HashMap<String, i32>,
 ) -> Result<(
     Option<temperature_readings::ActiveModel>,
@@ -48,10 +48,9 @@ pub fn process_row(
 
     // Create probe readings
     let mut probe_readings = Vec::new();
-    for &probe_col in &structure.probe_columns {
-        if let (Some(cell), Some(&probe_id)) = (row.get(probe_col), probe_mappings.get(&probe_col))
-        {
-            if let Some(temp) = extract_decimal(cell) {
+    for (channel, &probe_col) in &structure.probe_columns {
+        if let (Some(cell), Some(&probe_id)) = (row.get(probe_col), probe_mappings.get(channel))
+            && let Some(temp) = extract_decimal(cell) {
                 probe_readings.push(probe_temperature_readings::ActiveModel {
                     id: Set(Uuid::new_v4()),
                     temperature_reading_id: Set(*temp_reading.id.as_ref()),
@@ -60,19 +59,18 @@ pub fn process_row(
                     created_at: Set(Utc::now()),
                 });
             }
-        }
     }
 
     // Process phase transitions
     let mut transitions = Vec::new();
     for (well_key, &col_idx) in &structure.well_columns {
-        if let Some(cell) = row.get(col_idx) {
-            if let Some(new_phase) = extract_integer(cell) {
+        if let Some(cell) = row.get(col_idx)
+            && let Some(new_phase) = extract_integer(cell) {
                 let previous = phase_states.get(well_key).copied().unwrap_or(0);
                 phase_states.insert(well_key.clone(), new_phase);
 
-                if previous != new_phase {
-                    if let Some(&well_id) = well_mappings.get(well_key) {
+                if previous != new_phase
+                    && let Some(&well_id) = well_mappings.get(well_key) {
                         transitions.push(phase_transitions::ActiveModel {
                             id: Set(Uuid::new_v4()),
                             well_id: Set(well_id),
@@ -84,9 +82,7 @@ pub fn process_row(
                             created_at: Set(Utc::now()),
                         });
                     }
-                }
             }
-        }
     }
 
     Ok((Some(temp_reading), probe_readings, transitions))
@@ -117,17 +113,18 @@ mod tests {
             time_col: 1,
             image_col: Some(2),
             well_columns: HashMap::new(),
-            probe_columns: vec![3],
+            probe_columns: HashMap::new(),
             data_start_row: 7,
         };
 
+        structure.probe_columns.insert(1, 3);
         structure.well_columns.insert("P1:A1".to_string(), 4);
 
         let mut well_mappings = HashMap::new();
         well_mappings.insert("P1:A1".to_string(), Uuid::new_v4());
 
         let mut probe_mappings = HashMap::new();
-        probe_mappings.insert(3, Uuid::new_v4());
+        probe_mappings.insert(1, Uuid::new_v4());
 
         let mut phase_states = HashMap::new();
 
@@ -14,7 +14,7 @@ pub struct ExcelStructure {
     pub time_col: usize,
     pub image_col: Option<usize>,
     pub well_columns: HashMap<String, usize>, // "TrayName:A1" -> column_index
-    pub probe_columns: Vec<usize>,
+    pub probe_columns: HashMap<i32, usize>,   // data logger channel -> column_index
     pub data_start_row: usize,
 }
 
@@ -29,7 +29,7 @@ pub fn parse_excel_structure(rows: &[Vec<Data>]) -> Result<ExcelStructure> {
     let header_row = &rows[6];
 
     let mut well_columns = HashMap::new();
-    let mut probe_columns = Vec::new();
+    let mut probe_columns = HashMap::new();
     let mut date_col = None;
     let mut time_col = None;
     let mut image_col = None;
@@ -41,7 +41,20 @@ pub fn parse_excel_structure(rows: &[Vec<Data>]) -> Result<ExcelStructure> {
                 "Date" => date_col = Some(col_idx),
                 "Time" => time_col = Some(col_idx),
                 h if h.contains(".jpg") => image_col = Some(col_idx),
-                h if h.starts_with("Temperature") => probe_columns.push(col_idx),
+                h if h.starts_with("Temperature") => {
+                    // Columns without a channel number are taken in order
+                    let channel = probe_channel(h).unwrap_or_else(|| {
+                        i32::try_from(probe_columns.len() + 1).unwrap_or(i32::MAX)
+                    });
+                    if let Some(previous) = probe_columns.insert(channel, col_idx) {
+                        return Err(anyhow!(
+                            "Probe columns {} ({}) and {} ({h}) are both channel {channel}",
+                            previous + 1,
+                            header_text(&header_row[previous]),
+                            col_idx + 1,
+                        ));
+                    }
+                }
                 "()" => {
                     // Well column - extract tray name and coordinate
                     if let Some(well_key) = extract_well_key(tray_row, coord_row, col_idx) {
@@ -63,6 +76,27 @@ pub fn parse_excel_structure(rows: &[Vec<Data>]) -> Result<ExcelStructure> {
     })
 }
 
+/// Data logger channel of a probe column header such as "Temperature 12 (°C)",
+/// matched against the probes' `data_column_index`
+fn probe_channel(header: &str) -> Option<i32> {
+    header
+        .trim_start_matches("Temperature")
+        .trim_start()
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect::<String>()
+        .parse()
+        .ok()
+}
+
+/// Text of a header cell
+fn header_text(cell: &Data) -> &str {
+    match cell {
+        Data::String(header) => header,
+        _ => "",
+    }
+}
+
 /// Extract well key (tray:coordinate) from tray and coordinate rows
 fn extract_well_key(tray_row: &[Data], coord_row: &[Data], col_idx: usize) -> Option<String> {
     let tray_name = extract_string_from_cell(tray_row.get(col_idx)?)?;
@@ -174,6 +208,72 @@ mod tests {
         assert_eq!(structure.data_start_row, 7);
     }
 
+    #[test]
+    fn test_probe_columns_by_channel() {
+        // A 16-channel logger, with the channels in any column order
+        let mut header = vec![
+            Data::String("Date".to_string()),
+            Data::String("Time".to_string()),
+            Data::String("INP Freezing".to_string()),
+        ];
+        header.extend(
+            (1..=16)
+                .rev()
+                .map(|channel| Data::String(format!("Temperature {channel} (°C)"))),
+        );
+        let mut rows = vec![vec![Data::Empty; header.len()]; 6];
+        rows.push(header);
+
+        let structure = parse_excel_structure(&rows).unwrap();
+        assert_eq!(structure.probe_columns.len(), 16);
+        assert_eq!(structure.probe_columns[&16], 3);
+        assert_eq!(structure.probe_columns[&1], 18);
+
+        assert_eq!(probe_channel("Temperature 8 (°C)"), Some(8));
+        assert_eq!(probe_channel("Temperature12"), Some(12));
+        assert_eq!(probe_channel("Temperature (°C)"), None);
+    }
+
+    #[test]
+    fn test_duplicate_probe_channels() {
+        let parse = |probes: &[&str]| {
+            let mut header = vec![
+                Data::String("Date".to_string()),
+                Data::String("Time".to_string()),
+            ];
+            header.extend(
+                probes
+                    .iter()
+                    .map(|probe| Data::String((*probe).to_string())),
+            );
+            let mut rows = vec![vec![Data::Empty; header.len()]; 6];
+            rows.push(header);
+            parse_excel_structure(&rows)
+        };
+
+        // The same channel twice
+        let error = parse(&[
+            "Temperature 1 (°C)",
+            "Temperature 2 (°C)",
+            "Temperature 2 (°C)",
+        ])
+        .unwrap_err()
+        .to_string();
+        assert_eq!(
+            error,
+            "Probe columns 4 (Temperature 2 (°C)) and 5 (Temperature 2 (°C)) are both channel 2"
+        );
+
+        // An unnumbered column taken in order onto a numbered channel
+        let error = parse(&["Temperature 2 (°C)", "Temperature (°C)"])
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            error,
+            "Probe columns 3 (Temperature 2 (°C)) and 4 (Temperature (°C)) are both channel 2"
+        );
+    }
+
     #[test]
     fn test_coordinate_validation() {
         assert!(is_valid_coordinate("A1"));
@@ -79,9 +79,9 @@ pub fn parse_timestamp(row: &[Data], structure: &ExcelStructure) -> Result<chron
                 #[allow(clippy::cast_possible_truncation)]
                 let timestamp_int = timestamp_secs as i64;
                 Ok(chrono::DateTime::from_timestamp(timestamp_int, 0)
-                    .ok_or_else(|| anyhow!("Invalid timestamp: {}", timestamp_secs))?)
+                    .ok_or_else(|| anyhow!("Invalid timestamp: {timestamp_secs}"))?)
             } else {
-                Err(anyhow!("Excel timestamp is not finite: {}", timestamp_secs))
+                Err(anyhow!("Excel timestamp is not finite: {timestamp_secs}"))
             }
         }
         (Data::Float(timestamp), _) => {
@@ -94,12 +94,11 @@ pub fn parse_timestamp(row: &[Data], structure: &ExcelStructure) -> Result<chron
                 #[allow(clippy::cast_possible_truncation)]
                 let timestamp_int = rounded_timestamp as i64;
                 Ok(chrono::DateTime::from_timestamp(timestamp_int, 0)
-                    .ok_or_else(|| anyhow!("Invalid timestamp: {}", rounded_timestamp))?
+                    .ok_or_else(|| anyhow!("Invalid timestamp: {rounded_timestamp}"))?
                     .with_timezone(&chrono::Utc))
             } else {
                 Err(anyhow!(
-                    "Float timestamp is not finite: {}",
-                    rounded_timestamp
+                    "Float timestamp is not finite: {rounded_timestamp}"
                 ))
             }
         }
@@ -165,7 +164,7 @@ mod tests {
             time_col: 1,
             image_col: Some(2),
             well_columns: std::collections::HashMap::new(),
-            probe_columns: Vec::new(),
+            probe_columns: std::collections::HashMap::new(),
             data_start_row: 7,
         };
 
@@ -0,0 +1,81 @@
+use crate::assets::models as s3_assets;
+use crate::config::Config;
+use crate::external::s3::{get_object_from_s3, put_object_to_s3};
+use image::codecs::jpeg::JpegEncoder;
+use sea_orm::{ActiveValue::Set, DatabaseConnection, EntityTrait};
+use uuid::Uuid;
+
+/// Longest edge of a generated thumbnail, in pixels
+pub const THUMBNAIL_SIZE: u32 = 256;
+const THUMBNAIL_JPEG_QUALITY: u8 = 80;
+
+/// Thumbnails live next to the original object
+pub fn thumbnail_key(s3_key: &str) -> String {
+    format!("{s3_key}.thumb.jpg")
+}
+
+/// Downscale an encoded image so its longest edge is `THUMBNAIL_SIZE`,
+/// keeping the aspect ratio, and encode it as JPEG
+pub fn render_thumbnail(image_bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let image =
+        image::load_from_memory(image_bytes).map_err(|e| format!("Failed to decode image: {e}"))?;
+    let thumbnail = image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE).into_rgb8();
+
+    let mut encoded = Vec::new();
+    thumbnail
+        .write_with_encoder(JpegEncoder::new_with_quality(
+            &mut encoded,
+            THUMBNAIL_JPEG_QUALITY,
+        ))
+        .map_err(|e| format!("Failed to encode thumbnail: {e}"))?;
+    Ok(encoded)
+}
+
+/// Render the thumbnail of an image asset, store it in S3 and record its key.
+/// Returns the thumbnail bytes.
+pub async fn generate_thumbnail(
+    db: &DatabaseConnection,
+    config: &Config,
+    asset: &s3_assets::Model,
+) -> Result<Vec<u8>, String> {
+    if asset.r#type != "image" {
+        return Err(format!("Asset {} is not an image", asset.id));
+    }
+
+    let original = get_object_from_s3(asset.storage_key(), config).await?;
+    // Decoding a full-resolution camera image is CPU-bound
+    let thumbnail = tokio::task::spawn_blocking(move || render_thumbnail(&original))
+        .await
+        .map_err(|e| format!("Thumbnail task failed: {e}"))??;
+
+    let key = thumbnail_key(&asset.s3_key);
+    put_object_to_s3(&key, thumbnail.clone(), config).await?;
+
+    s3_assets::Entity::update(s3_assets::ActiveModel {
+        id: Set(asset.id),
+        thumbnail_s3_key: Set(Some(key)),
+        ..Default::default()
+    })
+    .exec(db)
+    .await
+    .map_err(|e| format!("Failed to record thumbnail: {e}"))?;
+
+    Ok(thumbnail)
+}
+
+/// Generate the thumbnail of a newly uploaded image without delaying the upload
+pub fn spawn_thumbnail_generation(db: DatabaseConnection, config: Config, asset_id: Uuid) {
+    tokio::spawn(async move {
+        let asset = match s3_assets::Entity::find_by_id(asset_id).one(&db).await {
+            Ok(Some(asset)) => asset,
+            Ok(None) => return,
+            Err(e) => {
+                tracing::warn!("Failed to load asset {asset_id} for thumbnail: {e}");
+                return;
+            }
+        };
+        if let Err(e) = generate_thumbnail(&db, &config, &asset).await {
+            tracing::warn!("Thumbnail generation failed for asset {asset_id}: {e}");
+        }
+    });
+}
@@ -0,0 +1,71 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// A calibration of a probe's sensor, kept so its drift can be followed
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, ToSchema)]
+#[sea_orm(table_name = "probe_calibrations")]
+#[schema(as = ProbeCalibration)]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    pub probe_id: Uuid,
+    /// Calibration experiment the calibration was derived from
+    pub experiment_id: Option<Uuid>,
+    pub calibrated_at: DateTime<Utc>,
+    /// Factor applied to raw readings before the offset
+    pub calibration_slope: Decimal,
+    /// Added to raw readings after the slope, in °C
+    pub calibration_offset: Decimal,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub notes: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub created_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "crate::tray_configurations::probes::models::Entity",
+        from = "Column::ProbeId",
+        to = "crate::tray_configurations::probes::models::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Probes,
+    #[sea_orm(
+        belongs_to = "crate::experiments::models::Entity",
+        from = "Column::ExperimentId",
+        to = "crate::experiments::models::Column::Id",
+        on_update = "NoAction",
+        on_delete = "SetNull"
+    )]
+    Experiments,
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    /// Calibrated temperature of a raw reading: `raw * slope + offset`
+    pub fn calibrate(&self, raw: Decimal) -> Decimal {
+        raw * self.calibration_slope + self.calibration_offset
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, ToSchema)]
+pub struct ProbeCalibrationCreate {
+    /// When the sensor was calibrated (default: when the calibration
+    /// experiment was performed, or else now)
+    pub calibrated_at: Option<DateTime<Utc>>,
+    /// Factor applied to raw readings before the offset (default 1)
+    pub calibration_slope: Option<Decimal>,
+    /// Added to raw readings after the slope, in °C (default 0)
+    pub calibration_offset: Option<Decimal>,
+    /// Calibration experiment the calibration was derived from
+    pub experiment_id: Option<Uuid>,
+    pub notes: Option<String>,
+}
@@ -0,0 +1,345 @@
+//! Calibration history of the temperature probes, and the drift between
+//! calibrations.
+//!
+//! A probe is followed across revisions of its tray configuration by its
+//! sensor's serial number, so the history of a probe is that of every probe
+//! with the same serial number, or its own when it has none. Recording the
+//! latest calibration of a sensor also makes it the probe's current one,
+//! which readings are processed with.
+//!
+//! Drift is the change between consecutive calibrations in the correction of
+//! a reading at a reference temperature. An experiment is flagged for a
+//! probe that had no calibration before it was run, whose last calibration
+//! had expired by then, or that was found to have drifted beyond the
+//! tolerance at the calibration after it.
+
+use super::models::{ActiveModel, Column, Entity, Model, ProbeCalibrationCreate};
+use crate::experiments::models as experiments;
+use crate::tray_configurations::{
+    models as tray_configurations, probes::models as probes, trays::models as trays,
+};
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::Decimal;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, IntoActiveModel,
+    QueryFilter, QueryOrder, Set, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+const DEFAULT_TOLERANCE: Decimal = Decimal::from_parts(2, 0, 0, false, 1);
+const DEFAULT_MAX_AGE_DAYS: i64 = 365;
+const DEFAULT_REFERENCE_TEMPERATURE: Decimal = Decimal::from_parts(20, 0, 0, true, 0);
+
+#[derive(Clone, Debug, Default, Deserialize, IntoParams)]
+pub struct CalibrationDriftQuery {
+    /// Largest drift between calibrations in °C before a probe is out of
+    /// calibration (default 0.2)
+    pub tolerance: Option<Decimal>,
+    /// Days a calibration is valid for (default 365)
+    pub max_age_days: Option<i64>,
+    /// Temperature in °C drift is measured at (default -20)
+    pub reference_temperature: Option<Decimal>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CalibrationIssue {
+    /// The probe had not been calibrated before the run
+    Uncalibrated,
+    /// The probe's last calibration before the run had expired
+    Expired,
+    /// The calibration after the run found the probe had drifted
+    Drifted,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, ToSchema)]
+pub struct CalibrationDrift {
+    #[serde(flatten)]
+    pub calibration: Model,
+    /// Change in °C from the previous calibration at the reference
+    /// temperature, `None` for the first
+    pub drift: Option<Decimal>,
+    pub out_of_tolerance: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, ToSchema)]
+pub struct ProbeDrift {
+    pub probe_id: Uuid,
+    pub probe_name: String,
+    pub data_column_index: i32,
+    pub serial_number: Option<String>,
+    /// Calibrations of the probe's sensor, oldest first
+    pub calibrations: Vec<CalibrationDrift>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, ToSchema)]
+pub struct FlaggedExperiment {
+    pub experiment_id: Uuid,
+    pub experiment_name: String,
+    pub performed_at: DateTime<Utc>,
+    pub probe_id: Uuid,
+    pub probe_name: String,
+    pub issue: CalibrationIssue,
+    /// Drift found at the calibration after the run, for `drifted`
+    pub drift: Option<Decimal>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, ToSchema)]
+pub struct CalibrationDriftReport {
+    pub tolerance: Decimal,
+    pub max_age_days: i64,
+    pub reference_temperature: Decimal,
+    /// Probes of the configuration, by tray order and channel
+    pub probes: Vec<ProbeDrift>,
+    /// Experiments using the configuration run with a probe out of
+    /// calibration, by run time and channel
+    pub flagged_experiments: Vec<FlaggedExperiment>,
+}
+
+/// The probe, which must be on a tray of the configuration
+async fn configuration_probe(
+    db: &DatabaseConnection,
+    tray_configuration_id: Uuid,
+    probe_id: Uuid,
+) -> Result<probes::Model, DbErr> {
+    let (probe, tray) = probes::Entity::find_by_id(probe_id)
+        .find_also_related(trays::Entity)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Probe not found".to_string()))?;
+    if tray.is_none_or(|tray| tray.tray_configuration_id != tray_configuration_id) {
+        return Err(DbErr::RecordNotFound("Probe not found".to_string()));
+    }
+    Ok(probe)
+}
+
+/// Calibrations of the probe's sensor, oldest first
+async fn sensor_history(
+    db: &impl sea_orm::ConnectionTrait,
+    probe: &probes::Model,
+) -> Result<Vec<Model>, DbErr> {
+    let probe_ids: Vec<Uuid> = match &probe.serial_number {
+        Some(serial_number) => probes::Entity::find()
+            .filter(probes::Column::SerialNumber.eq(serial_number.as_str()))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|probe| probe.id)
+            .collect(),
+        None => vec![probe.id],
+    };
+    Entity::find()
+        .filter(Column::ProbeId.is_in(probe_ids))
+        .order_by_asc(Column::CalibratedAt)
+        .order_by_asc(Column::CreatedAt)
+        .all(db)
+        .await
+}
+
+/// Calibrations of the probe's sensor, oldest first
+pub async fn list_calibrations(
+    db: &DatabaseConnection,
+    tray_configuration_id: Uuid,
+    probe_id: Uuid,
+) -> Result<Vec<Model>, DbErr> {
+    let probe = configuration_probe(db, tray_configuration_id, probe_id).await?;
+    sensor_history(db, &probe).await
+}
+
+/// Record a calibration of the probe, which becomes its current one unless
+/// a later calibration of its sensor is recorded. Problems are returned as
+/// `DbErr::Custom`.
+pub async fn record_calibration(
+    db: &DatabaseConnection,
+    tray_configuration_id: Uuid,
+    probe_id: Uuid,
+    input: ProbeCalibrationCreate,
+) -> Result<Model, DbErr> {
+    let probe = configuration_probe(db, tray_configuration_id, probe_id).await?;
+    let slope = input.calibration_slope.unwrap_or(Decimal::ONE);
+    if slope <= Decimal::ZERO {
+        return Err(DbErr::Custom(
+            "calibration_slope must be positive".to_string(),
+        ));
+    }
+    let mut calibrated_at = input.calibrated_at;
+    if let Some(experiment_id) = input.experiment_id {
+        let experiment = experiments::Entity::find_by_id(experiment_id)
+            .one(db)
+            .await?
+            .ok_or_else(|| DbErr::Custom(format!("Experiment {experiment_id} not found")))?;
+        if !experiment.is_calibration {
+            return Err(DbErr::Custom(format!(
+                "Experiment '{}' is not a calibration experiment",
+                experiment.name
+            )));
+        }
+        calibrated_at = calibrated_at.or(experiment.performed_at);
+    }
+
+    let txn = db.begin().await?;
+    let now = Utc::now();
+    let calibration = ActiveModel {
+        id: Set(Uuid::new_v4()),
+        probe_id: Set(probe.id),
+        experiment_id: Set(input.experiment_id),
+        calibrated_at: Set(calibrated_at.unwrap_or(now)),
+        calibration_slope: Set(slope),
+        calibration_offset: Set(input.calibration_offset.unwrap_or_default()),
+        notes: Set(input.notes),
+        created_by: Set(crate::common::auth::current_username()),
+        created_at: Set(now),
+    }
+    .insert(&txn)
+    .await?;
+
+    let latest = sensor_history(&txn, &probe).await?.pop();
+    if latest.is_some_and(|latest| latest.id == calibration.id) {
+        let mut probe = probe.into_active_model();
+        probe.calibration_slope = Set(Some(calibration.calibration_slope));
+        probe.calibration_offset = Set(Some(calibration.calibration_offset));
+        probe.last_updated = Set(now);
+        probe.update(&txn).await?;
+    }
+    txn.commit().await?;
+    Ok(calibration)
+}
+
+/// The calibrations with the drift of each from the one before
+fn drifts(
+    history: Vec<Model>,
+    reference_temperature: Decimal,
+    tolerance: Decimal,
+) -> Vec<CalibrationDrift> {
+    let mut previous: Option<Decimal> = None;
+    history
+        .into_iter()
+        .map(|calibration| {
+            let corrected = calibration.calibrate(reference_temperature);
+            let drift = previous.map(|previous| (corrected - previous).round_dp(3));
+            previous = Some(corrected);
+            CalibrationDrift {
+                out_of_tolerance: drift.is_some_and(|drift| drift.abs() > tolerance),
+                calibration,
+                drift,
+            }
+        })
+        .collect()
+}
+
+/// What was wrong with the probe's calibration when an experiment was run
+fn issue_at(
+    calibrations: &[CalibrationDrift],
+    performed_at: DateTime<Utc>,
+    max_age: Duration,
+) -> Option<(CalibrationIssue, Option<Decimal>)> {
+    let before = calibrations
+        .iter()
+        .take_while(|drift| drift.calibration.calibrated_at <= performed_at)
+        .count();
+    let Some(last) = before.checked_sub(1).map(|index| &calibrations[index]) else {
+        return Some((CalibrationIssue::Uncalibrated, None));
+    };
+    if let Some(next) = calibrations.get(before)
+        && next.out_of_tolerance
+    {
+        return Some((CalibrationIssue::Drifted, next.drift));
+    }
+    (performed_at - last.calibration.calibrated_at > max_age)
+        .then_some((CalibrationIssue::Expired, None))
+}
+
+/// Drift of the probes of the configuration and the experiments run with
+/// probes out of calibration. Invalid queries are returned as
+/// `DbErr::Custom`.
+pub async fn calibration_drift(
+    db: &DatabaseConnection,
+    tray_configuration_id: Uuid,
+    query: &CalibrationDriftQuery,
+) -> Result<CalibrationDriftReport, DbErr> {
+    let tolerance = query.tolerance.unwrap_or(DEFAULT_TOLERANCE);
+    if tolerance < Decimal::ZERO {
+        return Err(DbErr::Custom("tolerance must not be negative".to_string()));
+    }
+    let max_age_days = query.max_age_days.unwrap_or(DEFAULT_MAX_AGE_DAYS);
+    if max_age_days < 1 {
+        return Err(DbErr::Custom("max_age_days must be at least 1".to_string()));
+    }
+    let reference_temperature = query
+        .reference_temperature
+        .unwrap_or(DEFAULT_REFERENCE_TEMPERATURE);
+    tray_configurations::Entity::find_by_id(tray_configuration_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("tray_configuration not found".to_string()))?;
+
+    let tray_models = trays::Entity::find()
+        .filter(trays::Column::TrayConfigurationId.eq(tray_configuration_id))
+        .all(db)
+        .await?;
+    let tray_order: HashMap<Uuid, i32> = tray_models
+        .iter()
+        .map(|tray| (tray.id, tray.order_sequence))
+        .collect();
+    let mut probe_models = probes::Entity::find()
+        .filter(probes::Column::TrayId.is_in(tray_models.iter().map(|tray| tray.id)))
+        .all(db)
+        .await?;
+    probe_models.sort_by_key(|probe| {
+        (
+            tray_order.get(&probe.tray_id).copied().unwrap_or_default(),
+            probe.data_column_index,
+        )
+    });
+    let mut probe_drifts = Vec::with_capacity(probe_models.len());
+    for probe in probe_models {
+        let history = sensor_history(db, &probe).await?;
+        probe_drifts.push(ProbeDrift {
+            calibrations: drifts(history, reference_temperature, tolerance),
+            probe_id: probe.id,
+            probe_name: probe.name,
+            data_column_index: probe.data_column_index,
+            serial_number: probe.serial_number,
+        });
+    }
+
+    let runs = experiments::Entity::find()
+        .filter(experiments::Column::TrayConfigurationId.eq(tray_configuration_id))
+        .filter(experiments::Column::IsCalibration.eq(false))
+        .filter(experiments::Column::DeletedAt.is_null())
+        .filter(experiments::Column::PerformedAt.is_not_null())
+        .order_by_asc(experiments::Column::PerformedAt)
+        .all(db)
+        .await?;
+    let max_age = Duration::days(max_age_days);
+    let mut flagged_experiments = Vec::new();
+    for experiment in runs {
+        let Some(performed_at) = experiment.performed_at else {
+            continue;
+        };
+        for probe in &probe_drifts {
+            if let Some((issue, drift)) = issue_at(&probe.calibrations, performed_at, max_age) {
+                flagged_experiments.push(FlaggedExperiment {
+                    experiment_id: experiment.id,
+                    experiment_name: experiment.name.clone(),
+                    performed_at,
+                    probe_id: probe.probe_id,
+                    probe_name: probe.probe_name.clone(),
+                    issue,
+                    drift,
+                });
+            }
+        }
+    }
+
+    Ok(CalibrationDriftReport {
+        tolerance,
+        max_age_days,
+        reference_temperature,
+        probes: probe_drifts,
+        flagged_experiments,
+    })
+}
@@ -0,0 +1,172 @@
+use crate::config::test_helpers::{send_json, setup_test_app};
+use axum::http::StatusCode;
+use chrono::{Duration, Utc};
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+/// Issues of the flagged experiments, by probe name
+fn issues(report: &Value) -> Vec<(String, String)> {
+    report["flagged_experiments"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|flag| {
+            (
+                flag["probe_name"].as_str().unwrap().to_string(),
+                flag["issue"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect()
+}
+
+#[tokio::test]
+#[allow(clippy::too_many_lines)]
+async fn test_calibration_drift_flags_experiments() {
+    let app = setup_test_app().await;
+    let (status, demo) = send_json(&app, "POST", "/api/maintenance/demo", None).await;
+    assert_eq!(status, StatusCode::CREATED, "{demo}");
+    let configuration = format!(
+        "/api/tray_configurations/{}",
+        demo["tray_configuration_id"].as_str().unwrap()
+    );
+    let (status, probes) = send_json(&app, "GET", &format!("{configuration}/probes"), None).await;
+    assert_eq!(status, StatusCode::OK, "{probes}");
+    let probes = probes.as_array().unwrap();
+    assert_eq!(probes.len(), 8);
+    let calibrations = |probe: &Value| {
+        format!(
+            "{configuration}/probes/{}/calibrations",
+            probe["probe_id"].as_str().unwrap()
+        )
+    };
+
+    // Before any calibration, the demo run is flagged for every probe
+    let drift_uri = format!("{configuration}/calibration_drift");
+    let (status, report) = send_json(&app, "GET", &drift_uri, None).await;
+    assert_eq!(status, StatusCode::OK, "{report}");
+    let flagged = issues(&report);
+    assert_eq!(flagged.len(), 8);
+    assert!(flagged.iter().all(|(_, issue)| issue == "uncalibrated"));
+    assert_eq!(
+        report["flagged_experiments"][0]["experiment_id"],
+        demo["experiment_id"]
+    );
+
+    // Calibrations come from calibration experiments
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        &calibrations(&probes[0]),
+        Some(&json!({"experiment_id": demo["experiment_id"]})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        &calibrations(&probes[0]),
+        Some(&json!({"calibration_slope": 0})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        &format!("{configuration}/probes/{}/calibrations", Uuid::new_v4()),
+        Some(&json!({})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, calibration_run) = send_json(
+        &app,
+        "POST",
+        "/api/experiments",
+        Some(&json!({
+            "name": "Probe calibration",
+            "is_calibration": true,
+            "performed_at": Utc::now() - Duration::days(30),
+            "tray_configuration_id": demo["tray_configuration_id"],
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{calibration_run}");
+    for probe in probes {
+        let (status, calibration) = send_json(
+            &app,
+            "POST",
+            &calibrations(probe),
+            Some(&json!({
+                "experiment_id": calibration_run["id"],
+                "calibration_offset": 0.1,
+            })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED, "{calibration}");
+        assert_eq!(
+            calibration["calibrated_at"],
+            calibration_run["performed_at"]
+        );
+    }
+    let (status, report) = send_json(&app, "GET", &drift_uri, None).await;
+    assert_eq!(status, StatusCode::OK, "{report}");
+    assert!(issues(&report).is_empty());
+
+    // The first probe was found to have drifted after the run; the second
+    // stayed within the tolerance
+    for (probe, offset) in [(&probes[0], 0.5), (&probes[1], 0.15)] {
+        let (status, calibration) = send_json(
+            &app,
+            "POST",
+            &calibrations(probe),
+            Some(&json!({"calibration_offset": offset})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED, "{calibration}");
+    }
+    let (status, report) = send_json(&app, "GET", &drift_uri, None).await;
+    assert_eq!(status, StatusCode::OK, "{report}");
+    let first = probes[0]["name"].as_str().unwrap().to_string();
+    assert_eq!(
+        issues(&report),
+        vec![(first.clone(), "drifted".to_string())]
+    );
+    assert_eq!(report["flagged_experiments"][0]["drift"], "0.4");
+    let history = &report["probes"][0]["calibrations"];
+    assert_eq!(history[1]["drift"], "0.4");
+    assert_eq!(history[1]["out_of_tolerance"], true);
+    assert_eq!(report["probes"][1]["calibrations"][1]["drift"], "0.05");
+
+    // Calibrations a month before the run have expired with a shorter validity
+    let (status, report) =
+        send_json(&app, "GET", &format!("{drift_uri}?max_age_days=10"), None).await;
+    assert_eq!(status, StatusCode::OK, "{report}");
+    let flagged = issues(&report);
+    assert_eq!(flagged.len(), 8);
+    assert_eq!(flagged[0], (first, "drifted".to_string()));
+    assert!(flagged[1..].iter().all(|(_, issue)| issue == "expired"));
+    let (status, _) = send_json(&app, "GET", &format!("{drift_uri}?tolerance=-1"), None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // The latest calibration is the probe's current one; older ones are
+    // only kept in its history
+    let (status, calibration) = send_json(
+        &app,
+        "POST",
+        &calibrations(&probes[1]),
+        Some(&json!({
+            "calibrated_at": Utc::now() - Duration::days(60),
+            "calibration_offset": 9,
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{calibration}");
+    let (status, hardware) = send_json(&app, "GET", &format!("{configuration}/probes"), None).await;
+    assert_eq!(status, StatusCode::OK, "{hardware}");
+    assert_eq!(hardware[0]["calibration_offset"], "0.5");
+    assert_eq!(hardware[1]["calibration_offset"], "0.15");
+    let (status, history) = send_json(&app, "GET", &calibrations(&probes[1]), None).await;
+    assert_eq!(status, StatusCode::OK, "{history}");
+    assert_eq!(history.as_array().unwrap().len(), 3);
+    assert_eq!(history[0]["calibration_offset"], "9");
+}
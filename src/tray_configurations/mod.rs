@@ -1,8 +1,12 @@
+pub mod calibrations;
 pub mod models;
+pub mod probe_hardware;
 pub mod probes;
 pub mod regions;
+pub mod revisions;
 #[cfg(test)]
 mod tests;
 pub mod trays;
 pub mod views;
+pub mod well_grid;
 pub mod wells;
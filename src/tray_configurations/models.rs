@@ -1,3 +1,4 @@
+use crate::common::soft_delete::{soft_delete, soft_delete_many};
 use chrono::{DateTime, Utc};
 use crudcrate::traits::MergeIntoActiveModel;
 use crudcrate::{CRUDResource, EntityToModels};
@@ -15,6 +16,8 @@ use uuid::Uuid;
     name_plural = "tray_configurations",
     description = "This endpoint manages tray configurations, which define the setup of trays used in experiments.",
     fn_get_one = get_one_tray_configuration,
+    fn_delete = delete_tray_configuration,
+    fn_delete_many = delete_many_tray_configurations,
     fn_get_all = get_all_tray_configurations,
     fn_create = create_tray_configuration,
     fn_update = update_tray_configuration,
@@ -29,6 +32,19 @@ pub struct Model {
     pub name: Option<String>,
     #[crudcrate(sortable, filterable)]
     pub experiment_default: bool,
+    /// Bumped each time the trays are changed after experiments used them
+    #[crudcrate(sortable, filterable, create_model = false, update_model = false, on_create = 1)]
+    pub revision: i32,
+    /// For an earlier revision, the configuration that superseded it
+    #[crudcrate(filterable, create_model = false, update_model = false)]
+    pub revision_of_id: Option<Uuid>,
+    /// Lab the tray configuration belongs to, when labs are on
+    #[sea_orm(column_type = "Text", nullable)]
+    #[crudcrate(sortable, filterable, create_model = false, update_model = false)]
+    pub lab: Option<String>,
+    /// When the tray configuration was deleted; deleted tray configurations are kept until restored
+    #[crudcrate(sortable, filterable, create_model = false, update_model = false)]
+    pub deleted_at: Option<DateTime<Utc>>,
     #[crudcrate(update_model = false, create_model = false, on_create = chrono::Utc::now(), sortable, list_model=false)]
     pub created_at: DateTime<Utc>,
     #[crudcrate(update_model = false, create_model = false, on_update = chrono::Utc::now(), on_create = chrono::Utc::now(), sortable, list_model=false)]
@@ -63,6 +79,15 @@ impl Related<crate::tray_configurations::trays::models::Entity> for Entity {
 
 impl ActiveModelBehavior for ActiveModel {}
 
+/// Custom `delete` keeping the tray configuration, marked deleted
+async fn delete_tray_configuration(db: &DatabaseConnection, id: Uuid) -> Result<Uuid, DbErr> {
+    soft_delete::<Entity>(db, Column::Id, Column::DeletedAt, id).await
+}
+
+async fn delete_many_tray_configurations(db: &DatabaseConnection, ids: Vec<Uuid>) -> Result<Vec<Uuid>, DbErr> {
+    soft_delete_many::<Entity>(db, Column::Id, Column::DeletedAt, ids).await
+}
+
 // Custom crudcrate function to load nested tray assignments and experiments data
 pub async fn get_one_tray_configuration(
     db: &DatabaseConnection,
@@ -199,6 +224,10 @@ pub async fn create_tray_configuration(
         id: Set(tray_config_id),
         name: Set(data.name.clone()),
         experiment_default: Set(data.experiment_default),
+        revision: Set(1),
+        revision_of_id: Set(None),
+        lab: Set(crate::common::labs::current_lab()),
+        deleted_at: Set(None),
         created_at: Set(now),
         last_updated: Set(now),
     };
@@ -234,6 +263,11 @@ pub async fn create_tray_configuration(
                 data_column_index: Set(probe_data.data_column_index),
                 position_x: Set(probe_data.position_x),
                 position_y: Set(probe_data.position_y),
+                calibration_slope: Set(probe_data.calibration_slope),
+                calibration_offset: Set(probe_data.calibration_offset),
+                serial_number: Set(probe_data.serial_number.clone()),
+                sensor_type: Set(probe_data.sensor_type.clone()),
+                calibration_certificate_asset_id: Set(probe_data.calibration_certificate_asset_id),
                 created_at: Set(now),
                 last_updated: Set(now),
             };
@@ -246,6 +280,7 @@ pub async fn create_tray_configuration(
 }
 
 // Much simpler update function - just add to DB directly
+#[allow(clippy::too_many_lines)] // Trays and probes are recreated field by field
 pub async fn update_tray_configuration(
     db: &DatabaseConnection,
     id: Uuid,
@@ -263,6 +298,9 @@ pub async fn update_tray_configuration(
             }
     }
 
+    // Experiments keep the trays they were run with
+    super::revisions::prepare_update(db, id, !update_data.trays.is_empty()).await?;
+
     // If being set as experiment default, unset all other defaults first
     if update_data.experiment_default == Some(Some(true)) {
         Entity::update_many()
@@ -346,6 +384,13 @@ pub async fn update_tray_configuration(
                             .position_y
                             .unwrap_or_default()
                             .unwrap_or_else(|| 0.into())),
+                        calibration_slope: Set(probe_data.calibration_slope.unwrap_or_default()),
+                        calibration_offset: Set(probe_data.calibration_offset.unwrap_or_default()),
+                        serial_number: Set(probe_data.serial_number.clone().unwrap_or_default()),
+                        sensor_type: Set(probe_data.sensor_type.clone().unwrap_or_default()),
+                        calibration_certificate_asset_id: Set(probe_data
+                            .calibration_certificate_asset_id
+                            .unwrap_or_default()),
                         created_at: Set(now),
                         last_updated: Set(now),
                     };
@@ -0,0 +1,159 @@
+//! Hardware records of the temperature probes of a tray configuration.
+//!
+//! Serial numbers, sensor types and calibration certificates describe the
+//! physical sensors rather than the layout, so they are edited without
+//! creating a new revision, and can still be completed on earlier revisions
+//! whose experiments need the certificate for traceability.
+
+use super::{
+    models::Entity as TrayConfigurations, probes::models as probes, trays::models as trays,
+};
+use crate::assets::models as s3_assets;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, IntoActiveModel,
+    QueryFilter, Set,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct CalibrationCertificate {
+    pub asset_id: Uuid,
+    pub original_filename: String,
+    pub uploaded_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct ProbeHardware {
+    pub probe_id: Uuid,
+    pub tray_id: Uuid,
+    pub tray_name: Option<String>,
+    pub name: String,
+    pub data_column_index: i32,
+    pub serial_number: Option<String>,
+    pub sensor_type: Option<String>,
+    pub calibration_slope: Option<Decimal>,
+    pub calibration_offset: Option<Decimal>,
+    /// `None` when no certificate is linked or its asset was deleted
+    pub calibration_certificate: Option<CalibrationCertificate>,
+}
+
+#[derive(Clone, Debug, Deserialize, ToSchema)]
+pub struct ProbeHardwareUpdate {
+    pub serial_number: Option<String>,
+    pub sensor_type: Option<String>,
+    /// An uploaded asset; `null` unlinks the current certificate
+    pub calibration_certificate_asset_id: Option<Uuid>,
+}
+
+/// Probes of the configuration's trays, by tray order and data column
+pub async fn list_probe_hardware(
+    db: &DatabaseConnection,
+    tray_configuration_id: Uuid,
+) -> Result<Vec<ProbeHardware>, DbErr> {
+    TrayConfigurations::find_by_id(tray_configuration_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("tray_configuration not found".to_string()))?;
+
+    let tray_models = trays::Entity::find()
+        .filter(trays::Column::TrayConfigurationId.eq(tray_configuration_id))
+        .all(db)
+        .await?;
+    let probe_models = probes::Entity::find()
+        .filter(probes::Column::TrayId.is_in(tray_models.iter().map(|tray| tray.id)))
+        .all(db)
+        .await?;
+    let certificate_ids: Vec<Uuid> = probe_models
+        .iter()
+        .filter_map(|probe| probe.calibration_certificate_asset_id)
+        .collect();
+    let certificates: HashMap<Uuid, s3_assets::Model> = s3_assets::Entity::find()
+        .filter(s3_assets::Column::Id.is_in(certificate_ids))
+        .filter(s3_assets::Column::IsDeleted.eq(false))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|asset| (asset.id, asset))
+        .collect();
+    let tray_map: HashMap<Uuid, &trays::Model> =
+        tray_models.iter().map(|tray| (tray.id, tray)).collect();
+
+    let mut hardware: Vec<(i32, ProbeHardware)> = probe_models
+        .into_iter()
+        .map(|probe| {
+            let tray = tray_map.get(&probe.tray_id);
+            let calibration_certificate = probe
+                .calibration_certificate_asset_id
+                .and_then(|id| certificates.get(&id))
+                .map(|asset| CalibrationCertificate {
+                    asset_id: asset.id,
+                    original_filename: asset.original_filename.clone(),
+                    uploaded_at: asset.uploaded_at,
+                });
+            (
+                tray.map_or(0, |tray| tray.order_sequence),
+                ProbeHardware {
+                    probe_id: probe.id,
+                    tray_id: probe.tray_id,
+                    tray_name: tray.and_then(|tray| tray.name.clone()),
+                    name: probe.name,
+                    data_column_index: probe.data_column_index,
+                    serial_number: probe.serial_number,
+                    sensor_type: probe.sensor_type,
+                    calibration_slope: probe.calibration_slope,
+                    calibration_offset: probe.calibration_offset,
+                    calibration_certificate,
+                },
+            )
+        })
+        .collect();
+    hardware.sort_by_key(|(order, probe)| (*order, probe.data_column_index));
+    Ok(hardware.into_iter().map(|(_, probe)| probe).collect())
+}
+
+/// Replace the hardware record of one of the configuration's probes. A
+/// certificate that is not an uploaded asset is returned as `DbErr::Custom`.
+pub async fn set_probe_hardware(
+    db: &DatabaseConnection,
+    tray_configuration_id: Uuid,
+    probe_id: Uuid,
+    update: &ProbeHardwareUpdate,
+) -> Result<Vec<ProbeHardware>, DbErr> {
+    let (probe, tray) = probes::Entity::find_by_id(probe_id)
+        .find_also_related(trays::Entity)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Probe not found".to_string()))?;
+    if tray.is_none_or(|tray| tray.tray_configuration_id != tray_configuration_id) {
+        return Err(DbErr::RecordNotFound("Probe not found".to_string()));
+    }
+
+    if let Some(asset_id) = update.calibration_certificate_asset_id {
+        s3_assets::Entity::find_by_id(asset_id)
+            .filter(s3_assets::Column::IsDeleted.eq(false))
+            .one(db)
+            .await?
+            .ok_or_else(|| DbErr::Custom(format!("Asset {asset_id} not found")))?;
+    }
+
+    let trimmed = |value: &Option<String>| {
+        value
+            .as_deref()
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+    };
+    let mut probe = probe.into_active_model();
+    probe.serial_number = Set(trimmed(&update.serial_number));
+    probe.sensor_type = Set(trimmed(&update.sensor_type));
+    probe.calibration_certificate_asset_id = Set(update.calibration_certificate_asset_id);
+    probe.last_updated = Set(Utc::now());
+    probe.update(db).await?;
+
+    list_probe_hardware(db, tray_configuration_id).await
+}
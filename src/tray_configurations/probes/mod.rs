@@ -1,3 +1,4 @@
 pub mod models;
+pub mod services;
 #[cfg(test)]
 mod tests;
@@ -21,6 +21,23 @@ pub struct Model {
     pub position_x: Decimal,
     #[crudcrate(sortable, filterable)]
     pub position_y: Decimal,
+    /// Factor applied to raw readings before the offset; 1 when unset
+    #[crudcrate(filterable)]
+    pub calibration_slope: Option<Decimal>,
+    /// Added to raw readings after the slope, in °C; 0 when unset
+    #[crudcrate(filterable)]
+    pub calibration_offset: Option<Decimal>,
+    /// Serial number of the sensor, to trace it across tray configurations
+    #[sea_orm(column_type = "Text", nullable)]
+    #[crudcrate(sortable, filterable, fulltext)]
+    pub serial_number: Option<String>,
+    /// Kind of sensor, e.g. "PT100" or "thermocouple type T"
+    #[sea_orm(column_type = "Text", nullable)]
+    #[crudcrate(sortable, filterable, fulltext)]
+    pub sensor_type: Option<String>,
+    /// Uploaded asset holding the sensor's current calibration certificate
+    #[crudcrate(filterable)]
+    pub calibration_certificate_asset_id: Option<Uuid>,
     #[crudcrate(update_model = false, create_model = false, on_create = chrono::Utc::now(), sortable, list_model=false)]
     pub created_at: DateTime<Utc>,
     #[crudcrate(update_model = false, create_model = false, on_update = chrono::Utc::now(), on_create = chrono::Utc::now(), sortable, list_model=false)]
@@ -39,6 +56,14 @@ pub enum Relation {
     Trays,
     #[sea_orm(has_many = "crate::experiments::probe_temperature_readings::models::Entity")]
     ProbeTemperatureReadings,
+    #[sea_orm(
+        belongs_to = "crate::assets::models::Entity",
+        from = "Column::CalibrationCertificateAssetId",
+        to = "crate::assets::models::Column::Id",
+        on_update = "NoAction",
+        on_delete = "SetNull"
+    )]
+    CalibrationCertificate,
 }
 
 impl Related<crate::tray_configurations::trays::models::Entity> for Entity {
@@ -53,4 +78,25 @@ impl Related<crate::experiments::probe_temperature_readings::models::Entity> for
     }
 }
 
+impl Related<crate::assets::models::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::CalibrationCertificate.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    /// Calibrated temperature of a raw reading: `raw * slope + offset`
+    pub fn calibrate(&self, raw: Decimal) -> Decimal {
+        raw * self.calibration_slope.unwrap_or(Decimal::ONE)
+            + self.calibration_offset.unwrap_or_default()
+    }
+
+    /// Sum of the calibrated temperatures of `readings` raw readings that
+    /// sum to `raw_total`
+    pub fn calibrate_sum(&self, raw_total: Decimal, readings: Decimal) -> Decimal {
+        raw_total * self.calibration_slope.unwrap_or(Decimal::ONE)
+            + readings * self.calibration_offset.unwrap_or_default()
+    }
+}
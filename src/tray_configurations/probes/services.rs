@@ -0,0 +1,169 @@
+//! Probes of a tray, managed one at a time.
+//!
+//! Each probe reads a channel of the data logger, its `data_column_index`,
+//! which processing matches to the temperature columns of an experiment's
+//! file, so channels are unique across the trays of a configuration. Once an
+//! experiment uses the configuration, its probes keep their channels and
+//! positions, which its readings were taken and interpolated with: names,
+//! calibrations and hardware records can still change, but probes are only
+//! added, moved or removed by replacing the trays, which makes a revision.
+
+use super::models::{ActiveModel, Column, Entity, Model, Probe, ProbeCreate, ProbeUpdate};
+use crate::experiments::models as experiments;
+use crate::tray_configurations::{models as tray_configurations, trays::models as trays};
+use crudcrate::traits::MergeIntoActiveModel;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, IntoActiveModel,
+    PaginatorTrait, QueryFilter, QueryOrder, Set,
+};
+use uuid::Uuid;
+
+/// The tray, which must be of the current revision of its configuration,
+/// and whether experiments use the configuration
+async fn editable_tray(
+    db: &DatabaseConnection,
+    tray_id: Uuid,
+) -> Result<(trays::Model, bool), DbErr> {
+    let tray = trays::Entity::find_by_id(tray_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Tray not found".to_string()))?;
+    let configuration = tray_configurations::Entity::find_by_id(tray.tray_configuration_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("tray_configuration not found".to_string()))?;
+    if configuration.revision_of_id.is_some() {
+        return Err(DbErr::Custom(
+            "Earlier revisions of a tray configuration cannot be edited".to_string(),
+        ));
+    }
+    let in_use = experiments::Entity::find()
+        .filter(experiments::Column::TrayConfigurationId.eq(configuration.id))
+        .count(db)
+        .await?
+        > 0;
+    Ok((tray, in_use))
+}
+
+fn refuse_in_use(change: &str) -> DbErr {
+    DbErr::Custom(format!(
+        "Experiments use this tray configuration, so its probes cannot be {change}; replace its trays to make a new revision"
+    ))
+}
+
+/// Check that the channel is positive and read by no other probe of the
+/// tray's configuration
+async fn validate_channel(
+    db: &DatabaseConnection,
+    tray: &trays::Model,
+    data_column_index: i32,
+    probe_id: Option<Uuid>,
+) -> Result<(), DbErr> {
+    if data_column_index < 1 {
+        return Err(DbErr::Custom(format!(
+            "data_column_index must be at least 1, got {data_column_index}"
+        )));
+    }
+    let mut others = Entity::find()
+        .inner_join(trays::Entity)
+        .filter(trays::Column::TrayConfigurationId.eq(tray.tray_configuration_id))
+        .filter(Column::DataColumnIndex.eq(data_column_index));
+    if let Some(probe_id) = probe_id {
+        others = others.filter(Column::Id.ne(probe_id));
+    }
+    if let Some(other) = others.one(db).await? {
+        return Err(DbErr::Custom(format!(
+            "Channel {data_column_index} is already read by probe {} of this tray configuration",
+            other.name
+        )));
+    }
+    Ok(())
+}
+
+async fn find_probe(
+    db: &DatabaseConnection,
+    tray_id: Uuid,
+    probe_id: Uuid,
+) -> Result<Model, DbErr> {
+    Entity::find_by_id(probe_id)
+        .filter(Column::TrayId.eq(tray_id))
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Probe not found".to_string()))
+}
+
+/// Probes of the tray, by channel
+pub async fn list_probes(db: &DatabaseConnection, tray_id: Uuid) -> Result<Vec<Probe>, DbErr> {
+    trays::Entity::find_by_id(tray_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Tray not found".to_string()))?;
+    Ok(Entity::find()
+        .filter(Column::TrayId.eq(tray_id))
+        .order_by_asc(Column::DataColumnIndex)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect())
+}
+
+/// Add a probe to the tray. Problems are returned as `DbErr::Custom`.
+pub async fn create_probe(
+    db: &DatabaseConnection,
+    tray_id: Uuid,
+    data: ProbeCreate,
+) -> Result<Probe, DbErr> {
+    let (tray, in_use) = editable_tray(db, tray_id).await?;
+    if in_use {
+        return Err(refuse_in_use("added"));
+    }
+    validate_channel(db, &tray, data.data_column_index, None).await?;
+
+    let mut probe: ActiveModel = data.into();
+    probe.tray_id = Set(tray_id);
+    Ok(probe.insert(db).await?.into())
+}
+
+/// Change a probe of the tray. Problems are returned as `DbErr::Custom`.
+pub async fn update_probe(
+    db: &DatabaseConnection,
+    tray_id: Uuid,
+    probe_id: Uuid,
+    data: ProbeUpdate,
+) -> Result<Probe, DbErr> {
+    let (tray, in_use) = editable_tray(db, tray_id).await?;
+    let existing = find_probe(db, tray_id, probe_id).await?;
+    let channel = data.data_column_index.flatten();
+    let moved = [data.position_x.flatten(), data.position_y.flatten()]
+        .iter()
+        .zip([existing.position_x, existing.position_y])
+        .any(|(new, old)| new.is_some_and(|new| new != old));
+    if in_use && (moved || channel.is_some_and(|channel| channel != existing.data_column_index)) {
+        return Err(refuse_in_use("moved to other channels or positions"));
+    }
+    if let Some(channel) = channel {
+        validate_channel(db, &tray, channel, Some(probe_id)).await?;
+    }
+
+    let updated = data
+        .merge_into_activemodel(existing.into_active_model())?
+        .update(db)
+        .await?;
+    Ok(updated.into())
+}
+
+/// Remove a probe of the tray. Problems are returned as `DbErr::Custom`.
+pub async fn delete_probe(
+    db: &DatabaseConnection,
+    tray_id: Uuid,
+    probe_id: Uuid,
+) -> Result<(), DbErr> {
+    let (_, in_use) = editable_tray(db, tray_id).await?;
+    let probe = find_probe(db, tray_id, probe_id).await?;
+    if in_use {
+        return Err(refuse_in_use("removed"));
+    }
+    Entity::delete_by_id(probe.id).exec(db).await?;
+    Ok(())
+}
@@ -1,4 +1,7 @@
 use super::models;
+use crate::config::test_helpers::{send_json, setup_test_app};
+use axum::http::StatusCode;
+use serde_json::{Value, json};
 use uuid::Uuid;
 
 #[test]
@@ -18,6 +21,11 @@ fn test_probe_model_compilation() {
         data_column_index: 1,
         position_x: rust_decimal::Decimal::new(45, 1), // 4.5
         position_y: rust_decimal::Decimal::new(135, 1), // 13.5
+        calibration_slope: None,
+        calibration_offset: None,
+        serial_number: None,
+        sensor_type: None,
+        calibration_certificate_asset_id: None,
         created_at: chrono::Utc::now(),
         last_updated: chrono::Utc::now(),
     };
@@ -69,7 +77,6 @@ fn test_probe_yaml_config_structure() {
             "Y position should be reasonable for {name}"
         );
     }
-
 }
 
 #[test]
@@ -97,6 +104,11 @@ fn test_probe_database_constraints() {
         data_column_index: 1, // Excel column mapping for processing
         position_x: rust_decimal::Decimal::new(45, 1), // 4.5 pixels from left
         position_y: rust_decimal::Decimal::new(135, 1), // 13.5 pixels from top
+        calibration_slope: None,
+        calibration_offset: None,
+        serial_number: None,
+        sensor_type: None,
+        calibration_certificate_asset_id: None,
         created_at: chrono::Utc::now(),
         last_updated: chrono::Utc::now(),
     };
@@ -107,3 +119,173 @@ fn test_probe_database_constraints() {
     assert!(!probe.name.is_empty(), "Probe name should not be empty");
 
 }
+
+#[test]
+fn test_probe_calibration() {
+    use rust_decimal::Decimal;
+
+    let mut probe = models::Model {
+        id: Uuid::new_v4(),
+        tray_id: Uuid::new_v4(),
+        name: "Probe 1".to_string(),
+        data_column_index: 1,
+        position_x: Decimal::ZERO,
+        position_y: Decimal::ZERO,
+        calibration_slope: None,
+        calibration_offset: None,
+        serial_number: None,
+        sensor_type: None,
+        calibration_certificate_asset_id: None,
+        created_at: chrono::Utc::now(),
+        last_updated: chrono::Utc::now(),
+    };
+    let raw = Decimal::new(-2000, 2); // -20.00
+
+    // Uncalibrated probes read as logged
+    assert_eq!(probe.calibrate(raw), raw);
+
+    probe.calibration_offset = Some(Decimal::new(-15, 2)); // -0.15
+    assert_eq!(probe.calibrate(raw), Decimal::new(-2015, 2));
+
+    probe.calibration_slope = Some(Decimal::new(101, 2)); // 1.01
+    assert_eq!(probe.calibrate(raw), Decimal::new(-2035, 2));
+}
+
+fn probe(name: &str, data_column_index: i32) -> Value {
+    json!({
+        "name": name,
+        "data_column_index": data_column_index,
+        "position_x": 10.0,
+        "position_y": 20.0
+    })
+}
+
+#[tokio::test]
+#[allow(clippy::too_many_lines)]
+async fn test_probes_are_managed_per_tray() {
+    let app = setup_test_app().await;
+    let (status, configuration) = send_json(
+        &app,
+        "POST",
+        "/api/tray_configurations",
+        Some(&json!({
+            "name": format!("Probe CRUD {}", Uuid::new_v4()),
+            "experiment_default": false,
+            "trays": [
+                {"order_sequence": 1, "rotation_degrees": 0, "name": "P1", "qty_cols": 12, "qty_rows": 8, "probe_locations": [probe("Probe 1", 1)]},
+                {"order_sequence": 2, "rotation_degrees": 0, "name": "P2", "qty_cols": 12, "qty_rows": 8}
+            ]
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{configuration}");
+    let tray = |name: &str| {
+        configuration["trays"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|tray| tray["name"] == name)
+            .unwrap()["id"]
+            .as_str()
+            .unwrap()
+            .to_string()
+    };
+    let (p1, p2) = (tray("P1"), tray("P2"));
+
+    let (status, probes) = send_json(&app, "GET", &format!("/api/trays/{p1}/probes"), None).await;
+    assert_eq!(status, StatusCode::OK, "{probes}");
+    assert_eq!(probes.as_array().unwrap().len(), 1);
+
+    // Channels are unique across the trays of the configuration
+    let uri = format!("/api/trays/{p2}/probes");
+    let (status, _) = send_json(&app, "POST", &uri, Some(&probe("Probe 5", 1))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = send_json(&app, "POST", &uri, Some(&probe("Probe 5", 0))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, created) = send_json(&app, "POST", &uri, Some(&probe("Probe 5", 5))).await;
+    assert_eq!(status, StatusCode::CREATED, "{created}");
+    assert_eq!(created["data_column_index"], 5);
+    let probe_uri = format!("{uri}/{}", created["id"].as_str().unwrap());
+
+    let (status, _) = send_json(
+        &app,
+        "PUT",
+        &probe_uri,
+        Some(&json!({"data_column_index": 1})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, updated) = send_json(
+        &app,
+        "PUT",
+        &probe_uri,
+        Some(&json!({"name": "Probe 6", "data_column_index": 6, "position_x": 30.0})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{updated}");
+    assert_eq!(updated["name"], "Probe 6");
+    assert_eq!(updated["data_column_index"], 6);
+    assert_eq!(updated["position_y"], created["position_y"]);
+    // Only probes of the tray
+    let (status, _) = send_json(
+        &app,
+        "PUT",
+        &format!("/api/trays/{p1}/probes/{}", created["id"].as_str().unwrap()),
+        Some(&json!({"name": "Elsewhere"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, _) = send_json(&app, "DELETE", &probe_uri, None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (_, probes) = send_json(&app, "GET", &uri, None).await;
+    assert_eq!(probes, json!([]));
+    let (status, _) = send_json(&app, "DELETE", &probe_uri, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = send_json(
+        &app,
+        "GET",
+        &format!("/api/trays/{}/probes", Uuid::new_v4()),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Once used, probes keep their channels and positions
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        "/api/experiments",
+        Some(&json!({
+            "name": "Probe CRUD experiment",
+            "username": "test@example.com",
+            "performed_at": "2024-06-20T14:30:00Z",
+            "is_calibration": false,
+            "tray_configuration_id": configuration["id"]
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, _) = send_json(&app, "POST", &uri, Some(&probe("Probe 5", 5))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let used_uri = format!("/api/trays/{p1}/probes/{}", probes_of(&app, &p1).await);
+    let (status, _) = send_json(&app, "PUT", &used_uri, Some(&json!({"position_x": 11.0}))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, recalibrated) = send_json(
+        &app,
+        "PUT",
+        &used_uri,
+        Some(&json!({"calibration_offset": -0.1, "position_x": 10.0})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{recalibrated}");
+    assert_eq!(recalibrated["calibration_offset"], "-0.1");
+    let (status, _) = send_json(&app, "DELETE", &used_uri, None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+/// ID of the tray's first probe
+async fn probes_of(app: &axum::Router, tray_id: &str) -> String {
+    let (_, probes) = send_json(app, "GET", &format!("/api/trays/{tray_id}/probes"), None).await;
+    probes[0]["id"].as_str().unwrap().to_string()
+}
@@ -38,8 +38,12 @@ pub struct Model {
     pub col_max: Option<i32>,
     #[crudcrate(sortable, filterable)]
     pub row_max: Option<i32>,
+    /// Taken from the dilution when `dilution_id` is set
     #[crudcrate(sortable, filterable)]
     pub dilution_factor: Option<i32>,
+    /// A dilution of the region's treatment
+    #[crudcrate(sortable, filterable)]
+    pub dilution_id: Option<Uuid>,
     #[crudcrate(filterable)]
     pub is_background_key: bool,
     #[crudcrate(update_model = false, create_model = false, on_create = chrono::Utc::now(), sortable, list_model=false)]
@@ -79,6 +83,14 @@ pub enum Relation {
         on_delete = "NoAction"
     )]
     Treatments,
+    #[sea_orm(
+        belongs_to = "crate::treatments::dilutions::models::Entity",
+        from = "Column::DilutionId",
+        to = "crate::treatments::dilutions::models::Column::Id",
+        on_update = "NoAction",
+        on_delete = "SetNull"
+    )]
+    Dilutions,
 }
 
 impl Related<crate::experiments::models::Entity> for Entity {
@@ -93,4 +105,10 @@ impl Related<crate::treatments::models::Entity> for Entity {
     }
 }
 
+impl Related<crate::treatments::dilutions::models::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Dilutions.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
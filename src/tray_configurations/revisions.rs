@@ -0,0 +1,154 @@
+//! Revisions of tray configurations.
+//!
+//! Once an experiment uses a configuration, its trays no longer change in
+//! place. Editing them first moves the current trays, with their wells and
+//! probes, to a new configuration row that records the earlier revision, and
+//! points the experiments at it. The original configuration keeps its ID and
+//! takes the edited trays under the next revision number.
+
+use super::models::{self as tray_configurations, ActiveModel, Column, Entity};
+use super::trays::models as trays;
+use crate::experiments::models as experiments;
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, PaginatorTrait,
+    QueryFilter, QueryOrder, Set, TransactionTrait, sea_query::Expr,
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct TrayConfigurationRevision {
+    pub id: Uuid,
+    pub name: Option<String>,
+    pub revision: i32,
+    /// The revision new experiments get when they pick this configuration
+    pub is_current: bool,
+    /// Experiments run with this revision
+    pub experiment_count: u64,
+    pub last_updated: DateTime<Utc>,
+}
+
+/// Check that the configuration can be edited, and when its trays are about
+/// to be replaced while experiments use them, move the current ones to an
+/// earlier revision first
+pub async fn prepare_update(
+    db: &DatabaseConnection,
+    id: Uuid,
+    replaces_trays: bool,
+) -> Result<(), DbErr> {
+    let current = Entity::find_by_id(id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("tray_configuration not found".to_string()))?;
+    if current.revision_of_id.is_some() {
+        return Err(DbErr::Custom(
+            "Earlier revisions of a tray configuration cannot be edited".to_string(),
+        ));
+    }
+    if replaces_trays && is_in_use(db, id).await? {
+        archive_revision(db, &current).await?;
+    }
+    Ok(())
+}
+
+/// Whether any experiment uses the configuration
+async fn is_in_use(db: &DatabaseConnection, id: Uuid) -> Result<bool, DbErr> {
+    Ok(experiments::Entity::find()
+        .filter(experiments::Column::TrayConfigurationId.eq(id))
+        .count(db)
+        .await?
+        > 0)
+}
+
+/// Move the configuration's trays and experiments to a new row recording the
+/// current revision, and bump the configuration's revision number
+async fn archive_revision(
+    db: &DatabaseConnection,
+    current: &tray_configurations::Model,
+) -> Result<Uuid, DbErr> {
+    let txn = db.begin().await?;
+    let archived_id = Uuid::new_v4();
+    let now = Utc::now();
+
+    ActiveModel {
+        id: Set(archived_id),
+        // Names are unique
+        name: Set(current
+            .name
+            .as_ref()
+            .map(|name| format!("{name} (revision {})", current.revision))),
+        experiment_default: Set(false),
+        revision: Set(current.revision),
+        revision_of_id: Set(Some(current.id)),
+        lab: Set(current.lab.clone()),
+        deleted_at: Set(None),
+        created_at: Set(current.created_at),
+        last_updated: Set(now),
+    }
+    .insert(&txn)
+    .await?;
+
+    trays::Entity::update_many()
+        .col_expr(trays::Column::TrayConfigurationId, Expr::value(archived_id))
+        .filter(trays::Column::TrayConfigurationId.eq(current.id))
+        .exec(&txn)
+        .await?;
+    experiments::Entity::update_many()
+        .col_expr(
+            experiments::Column::TrayConfigurationId,
+            Expr::value(archived_id),
+        )
+        .filter(experiments::Column::TrayConfigurationId.eq(current.id))
+        .exec(&txn)
+        .await?;
+    Entity::update_many()
+        .col_expr(Column::Revision, Expr::value(current.revision + 1))
+        .col_expr(Column::LastUpdated, Expr::value(now))
+        .filter(Column::Id.eq(current.id))
+        .exec(&txn)
+        .await?;
+
+    txn.commit().await?;
+    Ok(archived_id)
+}
+
+/// Every revision of the configuration `id` belongs to, newest first
+pub async fn list_revisions(
+    db: &DatabaseConnection,
+    id: Uuid,
+) -> Result<Vec<TrayConfigurationRevision>, DbErr> {
+    let config = Entity::find_by_id(id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("tray_configuration not found".to_string()))?;
+    let current_id = config.revision_of_id.unwrap_or(config.id);
+
+    let configs = Entity::find()
+        .filter(
+            Column::Id
+                .eq(current_id)
+                .or(Column::RevisionOfId.eq(current_id)),
+        )
+        .order_by_desc(Column::Revision)
+        .all(db)
+        .await?;
+
+    let mut revisions = Vec::with_capacity(configs.len());
+    for config in configs {
+        let experiment_count = experiments::Entity::find()
+            .filter(experiments::Column::TrayConfigurationId.eq(config.id))
+            .count(db)
+            .await?;
+        revisions.push(TrayConfigurationRevision {
+            id: config.id,
+            name: config.name,
+            revision: config.revision,
+            is_current: config.revision_of_id.is_none(),
+            experiment_count,
+            last_updated: config.last_updated,
+        });
+    }
+    Ok(revisions)
+}
@@ -535,6 +535,34 @@ async fn test_tray_configuration_crud_operations() {
         delete_status.is_success(),
         "Tray configuration delete failed with status: {delete_status}"
     );
+
+    // A search, which ignores other filters, still leaves deleted records out
+    let search =
+        serde_urlencoded::to_string([("filter", json!({ "q": body["name"] }).to_string())])
+            .unwrap();
+    for (query, expected) in [
+        (search.clone(), false),
+        (format!("{search}&deleted=true"), true),
+    ] {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/api/tray_configurations?{query}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let (status, listed) = extract_response_body(response).await;
+        assert_eq!(status, StatusCode::OK, "{listed}");
+        let found = listed
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|config| config["id"] == tray_config_id);
+        assert_eq!(found, expected, "{query}: {listed}");
+    }
 }
 
 #[tokio::test]
@@ -1688,3 +1716,508 @@ async fn test_tray_configurations_have_id_fields() {
         // Tray configuration has required id field
     }
 }
+
+#[tokio::test]
+#[allow(clippy::too_many_lines)]
+async fn test_well_grid_endpoints() {
+    let app = setup_test_app().await;
+
+    let post = |uri: &str, body: Value| {
+        app.clone().oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+    };
+    let get = |uri: String| {
+        app.clone()
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+    };
+
+    let response = post(
+        "/api/tray_configurations",
+        json!({
+            "name": format!("Well Grid Config {}", uuid::Uuid::new_v4()),
+            "experiment_default": false,
+            "trays": [
+                {
+                    "order_sequence": 1,
+                    "rotation_degrees": 90,
+                    "name": "P1",
+                    "qty_cols": 12,
+                    "qty_rows": 8,
+                    "well_relative_diameter": 6.4,
+                    "upper_left_corner_x": 416,
+                    "upper_left_corner_y": 75,
+                    "lower_right_corner_x": 135,
+                    "lower_right_corner_y": 542
+                },
+                {
+                    "order_sequence": 2,
+                    "rotation_degrees": 0,
+                    "name": "No corners",
+                    "qty_cols": 12,
+                    "qty_rows": 8
+                }
+            ]
+        }),
+    )
+    .await
+    .unwrap();
+    let (status, config) = extract_response_body(response).await;
+    assert_eq!(status, StatusCode::CREATED, "Failed to create: {config:?}");
+    let config_id = config["id"].as_str().unwrap();
+
+    let response = get(format!("/api/tray_configurations/{config_id}/well-grid"))
+        .await
+        .unwrap();
+    let (status, grid) = extract_response_body(response).await;
+    assert_eq!(status, StatusCode::OK, "Failed to get grid: {grid:?}");
+    let trays = grid["trays"].as_array().unwrap();
+    assert_eq!(trays.len(), 1);
+    assert_eq!(trays[0]["tray_name"], "P1");
+    assert_eq!(grid["unmapped_tray_ids"].as_array().unwrap().len(), 1);
+
+    let wells = trays[0]["wells"].as_array().unwrap();
+    assert_eq!(wells.len(), 96);
+    let well = |row: &str, column: i64| {
+        wells
+            .iter()
+            .find(|well| well["row_letter"] == row && well["column_number"] == column)
+            .unwrap()
+    };
+    assert_eq!(
+        (well("A", 1)["x"].as_f64(), well("A", 1)["y"].as_f64()),
+        (Some(416.0), Some(75.0))
+    );
+    assert_eq!(
+        (well("H", 12)["x"].as_f64(), well("H", 12)["y"].as_f64()),
+        (Some(135.0), Some(542.0))
+    );
+    assert!(trays[0]["well_radius"].as_f64().unwrap() > 10.0);
+
+    let response = get(format!(
+        "/api/tray_configurations/{}/well-grid",
+        uuid::Uuid::new_v4()
+    ))
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // Camera images use the configuration of their experiment
+    let response = post(
+        "/api/experiments",
+        json!({
+            "name": format!("Well Grid Experiment {}", uuid::Uuid::new_v4()),
+            "username": "test@example.com",
+            "performed_at": "2024-06-20T14:30:00Z",
+            "is_calibration": false,
+            "tray_configuration_id": config_id
+        }),
+    )
+    .await
+    .unwrap();
+    let (status, experiment) = extract_response_body(response).await;
+    assert_eq!(
+        status,
+        StatusCode::CREATED,
+        "Failed to create: {experiment:?}"
+    );
+    let response = post(
+        "/api/assets",
+        json!({
+            "original_filename": "INP_1.jpg",
+            "experiment_id": experiment["id"],
+            "s3_key": format!("test/well-grid/{}/INP_1.jpg", uuid::Uuid::new_v4()),
+            "type": "image",
+            "is_deleted": false
+        }),
+    )
+    .await
+    .unwrap();
+    let (status, asset) = extract_response_body(response).await;
+    assert_eq!(status, StatusCode::CREATED, "Failed to create: {asset:?}");
+
+    let response = get(format!(
+        "/api/assets/{}/well-grid",
+        asset["id"].as_str().unwrap()
+    ))
+    .await
+    .unwrap();
+    let (status, image_grid) = extract_response_body(response).await;
+    assert_eq!(status, StatusCode::OK, "Failed to get grid: {image_grid:?}");
+    assert_eq!(image_grid["tray_configuration_id"], config_id);
+    assert_eq!(image_grid["trays"], grid["trays"]);
+}
+
+#[tokio::test]
+#[allow(clippy::too_many_lines)]
+async fn test_tray_configuration_revisions() {
+    let app = setup_test_app().await;
+
+    let send = |method: &str, uri: String, body: Option<Value>| {
+        let request = Request::builder().method(method).uri(uri);
+        let request = match body {
+            Some(body) => request
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string())),
+            None => request.body(Body::empty()),
+        }
+        .unwrap();
+        let app = app.clone();
+        async move { extract_response_body(app.oneshot(request).await.unwrap()).await }
+    };
+    let tray = |order_sequence: i32, qty_cols: i32| {
+        json!({
+            "order_sequence": order_sequence,
+            "rotation_degrees": 0,
+            "name": format!("P{order_sequence}"),
+            "qty_cols": qty_cols,
+            "qty_rows": 8
+        })
+    };
+
+    let name = format!("Revisioned Config {}", uuid::Uuid::new_v4());
+    let (status, config) = send(
+        "POST",
+        "/api/tray_configurations".to_string(),
+        Some(json!({"name": name, "experiment_default": false, "trays": [tray(1, 12)]})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "Failed to create: {config:?}");
+    assert_eq!(config["revision"], 1);
+    let config_id = config["id"].as_str().unwrap().to_string();
+
+    // Unused configurations are edited in place
+    let (status, config) = send(
+        "PUT",
+        format!("/api/tray_configurations/{config_id}"),
+        Some(json!({"trays": [tray(1, 10)]})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "Failed to update: {config:?}");
+    assert_eq!(config["revision"], 1);
+
+    let (status, experiment) = send(
+        "POST",
+        "/api/experiments".to_string(),
+        Some(json!({
+            "name": format!("Revision Experiment {}", uuid::Uuid::new_v4()),
+            "is_calibration": false,
+            "tray_configuration_id": config_id
+        })),
+    )
+    .await;
+    assert_eq!(
+        status,
+        StatusCode::CREATED,
+        "Failed to create: {experiment:?}"
+    );
+    let experiment_id = experiment["id"].as_str().unwrap();
+
+    // Renaming does not touch the trays the experiment used
+    let renamed = format!("{name} renamed");
+    let (status, config) = send(
+        "PUT",
+        format!("/api/tray_configurations/{config_id}"),
+        Some(json!({"name": renamed})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "Failed to rename: {config:?}");
+    assert_eq!(config["revision"], 1);
+
+    let (status, config) = send(
+        "PUT",
+        format!("/api/tray_configurations/{config_id}"),
+        Some(json!({"trays": [tray(1, 6), tray(2, 6)]})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "Failed to update: {config:?}");
+    assert_eq!(config["id"], config_id.as_str());
+    assert_eq!(config["revision"], 2);
+    assert_eq!(config["trays"][0]["qty_cols"], 6);
+    assert_eq!(config["associated_experiments"], json!([]));
+
+    // The experiment stays on the trays it was run with
+    let (_, experiment) = send("GET", format!("/api/experiments/{experiment_id}"), None).await;
+    let archived_id = experiment["tray_configuration_id"].as_str().unwrap();
+    assert_ne!(archived_id, config_id);
+    let (status, archived) = send(
+        "GET",
+        format!("/api/tray_configurations/{archived_id}"),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(archived["revision"], 1);
+    assert_eq!(archived["revision_of_id"], config_id.as_str());
+    assert_eq!(archived["name"], format!("{renamed} (revision 1)"));
+    assert_eq!(archived["trays"].as_array().unwrap().len(), 1);
+    assert_eq!(archived["trays"][0]["qty_cols"], 10);
+
+    let (status, _) = send(
+        "PUT",
+        format!("/api/tray_configurations/{archived_id}"),
+        Some(json!({"trays": [tray(1, 12)]})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let (status, revisions) = send(
+        "GET",
+        format!("/api/tray_configurations/{archived_id}/revisions"),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{revisions:?}");
+    assert_eq!(revisions[0]["id"], config_id.as_str());
+    assert_eq!(revisions[0]["is_current"], true);
+    assert_eq!(revisions[0]["experiment_count"], 0);
+    assert_eq!(revisions[1]["id"], archived_id);
+    assert_eq!(revisions[1]["revision"], 1);
+    assert_eq!(revisions[1]["experiment_count"], 1);
+
+    let (status, _) = send(
+        "GET",
+        format!(
+            "/api/tray_configurations/{}/revisions",
+            uuid::Uuid::new_v4()
+        ),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+#[allow(clippy::too_many_lines)]
+async fn test_probe_hardware() {
+    let app = setup_test_app().await;
+
+    let send = |method: &str, uri: String, body: Option<Value>| {
+        let request = Request::builder().method(method).uri(uri);
+        let request = match body {
+            Some(body) => request
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string())),
+            None => request.body(Body::empty()),
+        }
+        .unwrap();
+        let app = app.clone();
+        async move { extract_response_body(app.oneshot(request).await.unwrap()).await }
+    };
+
+    let (status, config) = send(
+        "POST",
+        "/api/tray_configurations".to_string(),
+        Some(json!({
+            "name": format!("Probe Hardware Config {}", uuid::Uuid::new_v4()),
+            "experiment_default": false,
+            "trays": [{
+                "order_sequence": 1,
+                "rotation_degrees": 0,
+                "name": "P1",
+                "qty_cols": 12,
+                "qty_rows": 8,
+                "probe_locations": [
+                    {"name": "Probe 2", "data_column_index": 2, "position_x": 10, "position_y": 10},
+                    {
+                        "name": "Probe 1",
+                        "data_column_index": 1,
+                        "position_x": 5,
+                        "position_y": 5,
+                        "serial_number": "PT-0001",
+                        "sensor_type": "PT100"
+                    }
+                ]
+            }]
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "Failed to create: {config:?}");
+    let config_id = config["id"].as_str().unwrap().to_string();
+
+    let (status, probes) = send(
+        "GET",
+        format!("/api/tray_configurations/{config_id}/probes"),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{probes:?}");
+    assert_eq!(probes[0]["name"], "Probe 1");
+    assert_eq!(probes[0]["serial_number"], "PT-0001");
+    assert_eq!(probes[0]["sensor_type"], "PT100");
+    assert_eq!(probes[0]["tray_name"], "P1");
+    assert!(probes[0]["calibration_certificate"].is_null());
+    assert!(probes[1]["serial_number"].is_null());
+    let probe_id = probes[1]["probe_id"].as_str().unwrap().to_string();
+
+    let (status, asset) = send(
+        "POST",
+        "/api/assets".to_string(),
+        Some(json!({
+            "original_filename": "PT-0002 certificate.pdf",
+            "s3_key": format!("certificates/{}.pdf", uuid::Uuid::new_v4()),
+            "size_bytes": 2048,
+            "type": "document",
+            "is_deleted": false
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "Failed to create: {asset:?}");
+    let asset_id = asset["id"].as_str().unwrap();
+
+    let (status, probes) = send(
+        "PUT",
+        format!("/api/tray_configurations/{config_id}/probes/{probe_id}/hardware"),
+        Some(json!({
+            "serial_number": " PT-0002 ",
+            "sensor_type": "PT100",
+            "calibration_certificate_asset_id": asset_id
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{probes:?}");
+    assert_eq!(probes[1]["serial_number"], "PT-0002");
+    assert_eq!(probes[1]["calibration_certificate"]["asset_id"], asset_id);
+    assert_eq!(
+        probes[1]["calibration_certificate"]["original_filename"],
+        "PT-0002 certificate.pdf"
+    );
+
+    // The configuration stays on its revision
+    let (_, config) = send("GET", format!("/api/tray_configurations/{config_id}"), None).await;
+    assert_eq!(config["revision"], 1);
+    let probe_locations = config["trays"][0]["probe_locations"].as_array().unwrap();
+    assert!(
+        probe_locations
+            .iter()
+            .any(|probe| probe["calibration_certificate_asset_id"] == asset_id)
+    );
+
+    let (status, _) = send(
+        "PUT",
+        format!("/api/tray_configurations/{config_id}/probes/{probe_id}/hardware"),
+        Some(json!({"calibration_certificate_asset_id": uuid::Uuid::new_v4()})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, _) = send(
+        "PUT",
+        format!(
+            "/api/tray_configurations/{}/probes/{probe_id}/hardware",
+            uuid::Uuid::new_v4()
+        ),
+        Some(json!({"serial_number": "PT-0003"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_tray_layout_svg() {
+    let app = setup_test_app().await;
+
+    let get = |uri: String| {
+        let app = app.clone();
+        async move {
+            let response = app
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            let status = response.status();
+            let content_type = response
+                .headers()
+                .get("content-type")
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string);
+            let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            (
+                status,
+                content_type,
+                String::from_utf8_lossy(&bytes).to_string(),
+            )
+        }
+    };
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/tray_configurations")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({
+            "name": format!("Layout Config {}", uuid::Uuid::new_v4()),
+            "experiment_default": false,
+            "trays": [{
+                "order_sequence": 1,
+                "rotation_degrees": 90,
+                "name": "P1",
+                "qty_cols": 12,
+                "qty_rows": 8,
+                "well_relative_diameter": 6.4,
+                "probe_locations": [
+                    {"name": "Probe 1", "data_column_index": 1, "position_x": 22.1, "position_y": 77.6}
+                ]
+            }]
+        })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (status, config) = extract_response_body(response).await;
+    assert_eq!(status, StatusCode::CREATED, "Failed to create: {config:?}");
+    let tray_id = config["trays"][0]["id"].as_str().unwrap().to_string();
+
+    let (status, content_type, svg) = get(format!("/api/trays/{tray_id}/layout.svg")).await;
+    assert_eq!(status, StatusCode::OK, "{svg}");
+    assert_eq!(content_type.as_deref(), Some("image/svg+xml"));
+    assert!(svg.starts_with("<svg"));
+    assert!(svg.contains("<title>H12</title>"));
+    assert!(svg.contains("<title>Probe 1 (channel 1)</title>"));
+
+    let (status, _, _) = get(format!("/api/trays/{}/layout.svg", uuid::Uuid::new_v4())).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _, _) = get(format!(
+        "/api/trays/{tray_id}/layout.svg?experiment_id={}",
+        uuid::Uuid::new_v4()
+    ))
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Experiments of other configurations have no regions on this tray
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/experiments")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({
+                        "name": format!("Layout Experiment {}", uuid::Uuid::new_v4()),
+                        "is_calibration": false
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (status, experiment) = extract_response_body(response).await;
+    assert_eq!(status, StatusCode::CREATED, "{experiment:?}");
+    let (status, _, _) = get(format!(
+        "/api/trays/{tray_id}/layout.svg?experiment_id={}",
+        experiment["id"].as_str().unwrap()
+    ))
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
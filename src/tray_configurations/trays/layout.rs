@@ -0,0 +1,287 @@
+//! SVG drawing of a tray: its well grid, its probes and, for an experiment,
+//! the regions on it.
+//!
+//! The tray is drawn unrotated in millimetres, the frame of the probe
+//! positions, with row letters down the left and column numbers along the
+//! top. Regions are shaded in the colours of the camera image overlay.
+
+use super::models as trays;
+use crate::experiments::{
+    interpolation::{WELL_PITCH_MM, tray_size, well_position},
+    models as experiments,
+    overlay::experiment_region_colours,
+};
+use crate::tray_configurations::{probes::models as probes, regions::models as regions};
+use image::Rgb;
+use rust_decimal::prelude::ToPrimitive;
+use sea_orm::{ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder};
+use std::fmt::Write;
+use uuid::Uuid;
+
+/// Space around the tray and its probes
+const MARGIN_MM: f64 = 6.0;
+/// Well diameter for trays without `well_relative_diameter`
+const FALLBACK_WELL_DIAMETER_MM: f64 = 6.4;
+const PROBE_RADIUS_MM: f64 = 1.5;
+const FONT_SIZE_MM: f64 = 3.0;
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn hex(colour: Rgb<u8>) -> String {
+    let [r, g, b] = colour.0;
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+/// Shade each region over the wells it covers
+fn write_regions(svg: &mut String, tray_regions: &[(regions::Model, Rgb<u8>)]) {
+    svg.push_str(r#"<g class="regions">"#);
+    for (region, colour) in tray_regions {
+        let (Some(row_min), Some(row_max), Some(col_min), Some(col_max)) = (
+            region.row_min,
+            region.row_max,
+            region.col_min,
+            region.col_max,
+        ) else {
+            continue;
+        };
+        // Region bounds are 0-based and inclusive
+        let (left, top) = well_position("A", col_min + 1);
+        let top = top + f64::from(row_min) * WELL_PITCH_MM;
+        let half = WELL_PITCH_MM / 2.0;
+        let region_width = f64::from(col_max - col_min + 1) * WELL_PITCH_MM;
+        let region_height = f64::from(row_max - row_min + 1) * WELL_PITCH_MM;
+        let colour = hex(*colour);
+        let _ = write!(
+            svg,
+            r#"<rect x="{:.2}" y="{:.2}" width="{region_width:.2}" height="{region_height:.2}" fill="{colour}" fill-opacity="0.35" stroke="{colour}" stroke-width="0.4">"#,
+            left - half,
+            top - half,
+        );
+        if let Some(name) = &region.name {
+            let _ = write!(svg, "<title>{}</title>", escape(name));
+        }
+        svg.push_str("</rect>");
+    }
+    svg.push_str("</g>");
+}
+
+/// Draw a tray with its probes and the regions placed on it
+fn render_svg(
+    tray: &trays::Model,
+    tray_probes: &[probes::Model],
+    tray_regions: &[(regions::Model, Rgb<u8>)],
+) -> String {
+    let columns = tray.qty_cols.unwrap_or(0).max(0);
+    let rows = tray.qty_rows.unwrap_or(0).clamp(0, 26);
+    let (width, height) = tray_size(columns.max(1), rows.max(1));
+    let radius = tray
+        .well_relative_diameter
+        .and_then(|diameter| diameter.to_f64())
+        .unwrap_or(FALLBACK_WELL_DIAMETER_MM)
+        / 2.0;
+    let probe_points: Vec<(&probes::Model, (f64, f64))> = tray_probes
+        .iter()
+        .filter_map(|probe| {
+            Some((
+                probe,
+                (probe.position_x.to_f64()?, probe.position_y.to_f64()?),
+            ))
+        })
+        .collect();
+
+    // Probes may sit outside the tray
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (0.0_f64, 0.0_f64, width, height);
+    for (_, (x, y)) in &probe_points {
+        min_x = min_x.min(x - PROBE_RADIUS_MM);
+        min_y = min_y.min(y - PROBE_RADIUS_MM);
+        max_x = max_x.max(x + PROBE_RADIUS_MM);
+        max_y = max_y.max(y + PROBE_RADIUS_MM);
+    }
+    let (view_x, view_y) = (min_x - MARGIN_MM, min_y - MARGIN_MM);
+    let (view_width, view_height) = (
+        max_x - min_x + 2.0 * MARGIN_MM,
+        max_y - min_y + 2.0 * MARGIN_MM,
+    );
+
+    let mut svg = String::new();
+    // Writing to a String cannot fail
+    let _ = write!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{view_x:.2} {view_y:.2} {view_width:.2} {view_height:.2}" width="{view_width:.2}mm" height="{view_height:.2}mm" font-family="sans-serif" font-size="{FONT_SIZE_MM}">"#
+    );
+    let _ = write!(
+        svg,
+        "<title>{}</title>",
+        escape(tray.name.as_deref().unwrap_or("Tray"))
+    );
+    let _ = write!(
+        svg,
+        r##"<rect class="tray" x="0" y="0" width="{width:.2}" height="{height:.2}" rx="3" fill="#f8fafc" stroke="#334155" stroke-width="0.5"/>"##
+    );
+
+    write_regions(&mut svg, tray_regions);
+
+    svg.push_str(r##"<g class="wells" fill="#ffffff" fill-opacity="0.8" stroke="#64748b" stroke-width="0.3">"##);
+    for letter in ('A'..='Z').take(usize::try_from(rows).unwrap_or_default()) {
+        for column in 1..=columns {
+            let (x, y) = well_position(&letter.to_string(), column);
+            let _ = write!(
+                svg,
+                r#"<circle cx="{x:.2}" cy="{y:.2}" r="{radius:.2}"><title>{letter}{column}</title></circle>"#
+            );
+        }
+        let (x, y) = well_position(&letter.to_string(), 1);
+        let _ = write!(
+            svg,
+            r##"<text x="{:.2}" y="{y:.2}" fill="#334155" stroke="none" text-anchor="middle" dominant-baseline="central">{letter}</text>"##,
+            x - 0.75 * WELL_PITCH_MM,
+        );
+    }
+    for column in 1..=columns {
+        let (x, y) = well_position("A", column);
+        let _ = write!(
+            svg,
+            r##"<text x="{x:.2}" y="{:.2}" fill="#334155" stroke="none" text-anchor="middle" dominant-baseline="central">{column}</text>"##,
+            y - 0.75 * WELL_PITCH_MM,
+        );
+    }
+    svg.push_str("</g>");
+
+    svg.push_str(r##"<g class="probes" fill="#f59e0b" stroke="#92400e" stroke-width="0.3">"##);
+    for (probe, (x, y)) in &probe_points {
+        let name = escape(&probe.name);
+        let _ = write!(
+            svg,
+            r##"<circle cx="{x:.2}" cy="{y:.2}" r="{PROBE_RADIUS_MM}"><title>{name} (channel {})</title></circle><text x="{:.2}" y="{y:.2}" fill="#92400e" stroke="none" dominant-baseline="central">{name}</text>"##,
+            probe.data_column_index,
+            x + PROBE_RADIUS_MM + 0.5,
+        );
+    }
+    svg.push_str("</g></svg>");
+    svg
+}
+
+/// SVG layout of a tray, with the regions of `experiment_id` when given.
+///
+/// A missing tray or experiment is returned as `DbErr::RecordNotFound`, an
+/// experiment run with another tray configuration as `DbErr::Custom`.
+pub async fn tray_layout_svg(
+    db: &DatabaseConnection,
+    tray_id: Uuid,
+    experiment_id: Option<Uuid>,
+) -> Result<String, DbErr> {
+    let tray = trays::Entity::find_by_id(tray_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Tray not found".to_string()))?;
+    let tray_probes = probes::Entity::find()
+        .filter(probes::Column::TrayId.eq(tray_id))
+        .order_by_asc(probes::Column::DataColumnIndex)
+        .all(db)
+        .await?;
+
+    let tray_regions = match experiment_id {
+        Some(experiment_id) => {
+            let experiment = experiments::Entity::find_by_id(experiment_id)
+                .one(db)
+                .await?
+                .ok_or_else(|| DbErr::RecordNotFound("Experiment not found".to_string()))?;
+            if experiment.tray_configuration_id != Some(tray.tray_configuration_id) {
+                return Err(DbErr::Custom(
+                    "The experiment does not use this tray's configuration".to_string(),
+                ));
+            }
+            experiment_region_colours(db, experiment_id)
+                .await?
+                .into_iter()
+                .filter(|(region, _)| region.tray_id == Some(tray.order_sequence))
+                .collect()
+        }
+        None => Vec::new(),
+    };
+
+    Ok(render_svg(&tray, &tray_probes, &tray_regions))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+
+    #[test]
+    fn test_render_svg() {
+        let now = chrono::Utc::now();
+        let tray = trays::Model {
+            id: Uuid::new_v4(),
+            tray_configuration_id: Uuid::new_v4(),
+            order_sequence: 1,
+            rotation_degrees: 0,
+            name: Some("P<1>".to_string()),
+            qty_cols: Some(12),
+            qty_rows: Some(8),
+            well_relative_diameter: None,
+            upper_left_corner_x: None,
+            upper_left_corner_y: None,
+            lower_right_corner_x: None,
+            lower_right_corner_y: None,
+            created_at: now,
+            last_updated: now,
+            probe_locations: vec![],
+        };
+        let probe = probes::Model {
+            id: Uuid::new_v4(),
+            tray_id: tray.id,
+            name: "Probe 4".to_string(),
+            data_column_index: 4,
+            position_x: Decimal::new(1435, 1),
+            position_y: Decimal::new(795, 1),
+            calibration_slope: None,
+            calibration_offset: None,
+            serial_number: None,
+            sensor_type: None,
+            calibration_certificate_asset_id: None,
+            created_at: now,
+            last_updated: now,
+        };
+        let region = regions::Model {
+            id: Uuid::new_v4(),
+            experiment_id: Uuid::new_v4(),
+            treatment_id: None,
+            name: Some("Heat".to_string()),
+            display_colour_hex: None,
+            tray_id: Some(1),
+            col_min: Some(0),
+            row_min: Some(0),
+            col_max: Some(5),
+            row_max: Some(3),
+            dilution_factor: None,
+            dilution_id: None,
+            is_background_key: false,
+            created_at: now,
+            last_updated: now,
+            treatment: None,
+        };
+
+        let svg = render_svg(&tray, &[probe], &[(region, Rgb([239, 68, 68]))]);
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.ends_with("</svg>"));
+        assert_eq!(svg.matches("<title>A1</title>").count(), 1);
+        assert_eq!(svg.matches("<circle").count(), 97);
+        assert!(svg.contains("<title>H12</title>"));
+        assert!(svg.contains("<title>P&lt;1&gt;</title>"));
+        // Six columns by four rows from the edge of A1
+        assert!(
+            svg.contains(
+                r##"<rect x="9.88" y="6.74" width="54.00" height="36.00" fill="#ef4444""##
+            )
+        );
+        // The probe outside the tray widens the view
+        assert!(svg.contains(r#"viewBox="-6.00 -6.00 157.00 97.48""#));
+        assert!(svg.contains("<title>Probe 4 (channel 4)</title>"));
+    }
+}
@@ -1 +1,3 @@
+pub mod layout;
 pub mod models;
+pub mod views;
@@ -0,0 +1,257 @@
+use super::layout::tray_layout_svg;
+use crate::api_keys::services::accept_api_keys;
+use crate::common::auth::{RouteAccess, require_role};
+use crate::common::keycloak::authenticate;
+use crate::common::labs::{TenantResource, require_lab};
+use crate::common::redaction::redact_for_viewers;
+use crate::common::state::AppState;
+use crate::tray_configurations::probes::models::{Probe, ProbeCreate, ProbeUpdate};
+use crate::tray_configurations::probes::services::{
+    create_probe, delete_probe, list_probes, update_probe,
+};
+use crate::tray_configurations::wells::services::{WellLocation, tray_wells};
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::{StatusCode, header::CONTENT_TYPE},
+    middleware,
+    response::{IntoResponse, Response},
+    routing::{get, put},
+};
+use axum_keycloak_auth::PassthroughMode;
+use sea_orm::DbErr;
+use utoipa::OpenApi;
+use utoipa_axum::router::OpenApiRouter;
+use uuid::Uuid;
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+pub struct LayoutQuery {
+    /// Shade the regions of this experiment, which must use the tray's configuration
+    experiment_id: Option<Uuid>,
+}
+
+/// Draw a tray's well grid and probe positions as SVG
+#[utoipa::path(
+    get,
+    path = "/{id}/layout.svg",
+    params(
+        ("id" = Uuid, Path, description = "Tray ID"),
+        LayoutQuery
+    ),
+    responses(
+        (status = 200, description = "SVG drawing of the tray", content_type = "image/svg+xml"),
+        (status = 400, description = "The experiment does not use the tray's configuration"),
+        (status = 404, description = "Tray or experiment not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "tray_configurations",
+    summary = "Get the layout of a tray as SVG",
+    description = "Draw the tray unrotated in millimetres with its wells, row and column labels and probe positions, and optionally the regions of an experiment in their display or treatment colours"
+)]
+pub async fn get_tray_layout(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<LayoutQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    let svg = tray_layout_svg(&state.db, id, query.experiment_id)
+        .await
+        .map_err(error_response)?;
+    Ok(([(CONTENT_TYPE, "image/svg+xml")], svg).into_response())
+}
+
+fn error_response(e: DbErr) -> (StatusCode, String) {
+    match e {
+        DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+        DbErr::Custom(message) => (StatusCode::BAD_REQUEST, message),
+        e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+/// List the wells of a tray
+#[utoipa::path(
+    get,
+    path = "/{id}/wells",
+    params(
+        ("id" = Uuid, Path, description = "Tray ID")
+    ),
+    responses(
+        (status = 200, description = "Wells of the tray, by row and column", body = Vec<WellLocation>),
+        (status = 404, description = "Tray not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "tray_configurations",
+    summary = "List the wells of a tray",
+    description = "List the well records of the tray with their IDs and coordinates. Wells are recorded when an experiment's data are first processed"
+)]
+pub async fn get_tray_wells(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<WellLocation>>, (StatusCode, String)> {
+    tray_wells(&state.db, id)
+        .await
+        .map(Json)
+        .map_err(error_response)
+}
+
+/// List the probes of a tray
+#[utoipa::path(
+    get,
+    path = "/{id}/probes",
+    params(
+        ("id" = Uuid, Path, description = "Tray ID")
+    ),
+    responses(
+        (status = 200, description = "Probes of the tray, by channel", body = Vec<Probe>),
+        (status = 404, description = "Tray not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "tray_configurations",
+    summary = "List the probes of a tray",
+    description = "List the temperature probes of the tray by data logger channel, with their positions and calibrations"
+)]
+pub async fn get_probes(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<Probe>>, (StatusCode, String)> {
+    list_probes(&state.db, id)
+        .await
+        .map(Json)
+        .map_err(error_response)
+}
+
+/// Add a probe to a tray
+#[utoipa::path(
+    post,
+    path = "/{id}/probes",
+    params(
+        ("id" = Uuid, Path, description = "Tray ID")
+    ),
+    request_body = ProbeCreate,
+    responses(
+        (status = 201, description = "Probe added", body = Probe),
+        (status = 400, description = "The channel is taken, or experiments use the configuration"),
+        (status = 404, description = "Tray not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "tray_configurations",
+    summary = "Add a probe to a tray",
+    description = "Add a probe reading a data logger channel that no other probe of the tray configuration reads. Configurations that experiments use get new probes by replacing their trays, which makes a revision"
+)]
+pub async fn post_probe(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(data): Json<ProbeCreate>,
+) -> Result<(StatusCode, Json<Probe>), (StatusCode, String)> {
+    create_probe(&state.db, id, data)
+        .await
+        .map(|probe| (StatusCode::CREATED, Json(probe)))
+        .map_err(error_response)
+}
+
+/// Update a probe of a tray
+#[utoipa::path(
+    put,
+    path = "/{id}/probes/{probe_id}",
+    params(
+        ("id" = Uuid, Path, description = "Tray ID"),
+        ("probe_id" = Uuid, Path, description = "Probe ID")
+    ),
+    request_body = ProbeUpdate,
+    responses(
+        (status = 200, description = "Probe updated", body = Probe),
+        (status = 400, description = "The channel is taken, or experiments use the configuration"),
+        (status = 404, description = "Tray or probe not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "tray_configurations",
+    summary = "Update a probe of a tray",
+    description = "Change the fields given. Once experiments use the configuration, probes keep their channels and positions, but can be renamed and recalibrated"
+)]
+pub async fn put_probe(
+    State(state): State<AppState>,
+    Path((id, probe_id)): Path<(Uuid, Uuid)>,
+    Json(data): Json<ProbeUpdate>,
+) -> Result<Json<Probe>, (StatusCode, String)> {
+    update_probe(&state.db, id, probe_id, data)
+        .await
+        .map(Json)
+        .map_err(error_response)
+}
+
+/// Remove a probe from a tray
+#[utoipa::path(
+    delete,
+    path = "/{id}/probes/{probe_id}",
+    params(
+        ("id" = Uuid, Path, description = "Tray ID"),
+        ("probe_id" = Uuid, Path, description = "Probe ID")
+    ),
+    responses(
+        (status = 204, description = "Probe removed"),
+        (status = 400, description = "Experiments use the configuration"),
+        (status = 404, description = "Tray or probe not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "tray_configurations",
+    summary = "Remove a probe from a tray",
+    description = "Remove a probe of a configuration that no experiment uses"
+)]
+pub async fn delete_probe_handler(
+    State(state): State<AppState>,
+    Path((id, probe_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    delete_probe(&state.db, id, probe_id)
+        .await
+        .map(|()| StatusCode::NO_CONTENT)
+        .map_err(error_response)
+}
+
+/// Documentation of the routes, which are routed with `route`
+#[derive(OpenApi)]
+#[openapi(paths(
+    get_tray_layout,
+    get_tray_wells,
+    get_probes,
+    post_probe,
+    put_probe,
+    delete_probe_handler
+))]
+struct TraysApi;
+
+pub fn router(state: &AppState) -> OpenApiRouter {
+    let mut router = OpenApiRouter::new()
+        .route("/{id}/layout.svg", get(get_tray_layout))
+        .route("/{id}/wells", get(get_tray_wells))
+        .route("/{id}/probes", get(get_probes).post(post_probe))
+        .route(
+            "/{id}/probes/{probe_id}",
+            put(put_probe).delete(delete_probe_handler),
+        )
+        .with_state(state.clone());
+    router.get_openapi_mut().merge(TraysApi::openapi());
+
+    if let Some(instance) = state.keycloak_auth_instance.clone() {
+        router = router
+            .layer(middleware::from_fn_with_state(
+                (state.db.clone(), TenantResource::Trays),
+                require_lab,
+            ))
+            .layer(middleware::from_fn_with_state(
+                RouteAccess::SHARED,
+                require_role,
+            ))
+            .layer(middleware::from_fn(redact_for_viewers))
+            .layer(middleware::from_fn_with_state(
+                (state.db.clone(), "trays"),
+                accept_api_keys,
+            ))
+            .layer(middleware::from_fn_with_state(
+                (instance, PassthroughMode::Pass),
+                authenticate,
+            ));
+    } else if !state.config.tests_running {
+        println!("Warning: Tray routes are not protected");
+    }
+
+    router
+}
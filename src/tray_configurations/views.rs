@@ -1,26 +1,376 @@
+use super::calibrations::models::{Model as ProbeCalibration, ProbeCalibrationCreate};
+use super::calibrations::services::{
+    CalibrationDriftQuery, CalibrationDriftReport, calibration_drift, list_calibrations,
+    record_calibration,
+};
 pub use super::models::{TrayConfiguration, router as crudrouter};
-use crate::common::auth::Role;
+use super::probe_hardware::{
+    ProbeHardware, ProbeHardwareUpdate, list_probe_hardware, set_probe_hardware,
+};
+use super::revisions::{TrayConfigurationRevision, list_revisions};
+use super::well_grid::{WellGrid, tray_configuration_well_grid};
+use crate::api_keys::services::accept_api_keys;
+use crate::audit::services::{AuditedResource, audit_changes};
+use crate::common::auth::{RouteAccess, require_role};
+use crate::common::cache::cache_records;
+use crate::common::etag::check_entity_tags;
+use crate::common::keycloak::authenticate;
+use crate::common::labs::{TenantResource, require_lab};
+use crate::common::pagination::paginate_by_cursor;
+use crate::common::patch::patch_one_handler;
+use crate::common::redaction::redact_for_viewers;
+use crate::common::soft_delete::{hide_deleted, restore_one_handler};
 use crate::common::state::AppState;
-use axum_keycloak_auth::{PassthroughMode, layer::KeycloakAuthLayer};
+use crate::versions::{self, services::keep_versions};
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    middleware,
+    routing::{get, patch, post, put},
+};
+use axum_keycloak_auth::PassthroughMode;
 use crudcrate::CRUDResource;
+use sea_orm::DbErr;
+use utoipa::OpenApi;
 use utoipa_axum::router::OpenApiRouter;
+use uuid::Uuid;
+
+/// Pixel centroids of the wells of every tray in a configuration
+#[utoipa::path(
+    get,
+    path = "/{id}/well-grid",
+    params(
+        ("id" = Uuid, Path, description = "Tray configuration ID")
+    ),
+    responses(
+        (status = 200, description = "Well positions per tray", body = WellGrid),
+        (status = 404, description = "Tray configuration not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "tray_configurations",
+    summary = "Get the well grid of a tray configuration",
+    description = "Compute each well's centroid in camera image pixels from the trays' corner coordinates and rotation, so well outlines can be drawn over camera images"
+)]
+pub async fn get_well_grid(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<WellGrid>, (StatusCode, String)> {
+    tray_configuration_well_grid(&state.db, id)
+        .await
+        .map(Json)
+        .map_err(|e| match e {
+            DbErr::RecordNotFound(_) => (
+                StatusCode::NOT_FOUND,
+                "Tray configuration not found".to_string(),
+            ),
+            e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })
+}
+
+/// Revisions of a tray configuration, newest first
+#[utoipa::path(
+    get,
+    path = "/{id}/revisions",
+    params(
+        ("id" = Uuid, Path, description = "Tray configuration ID, current or of an earlier revision")
+    ),
+    responses(
+        (status = 200, description = "Revisions of the configuration", body = Vec<TrayConfigurationRevision>),
+        (status = 404, description = "Tray configuration not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "tray_configurations",
+    summary = "List the revisions of a tray configuration",
+    description = "Editing the trays of a configuration used by experiments keeps the earlier trays as a revision that those experiments stay pinned to. Earlier revisions cannot be edited"
+)]
+pub async fn get_revisions(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<TrayConfigurationRevision>>, (StatusCode, String)> {
+    list_revisions(&state.db, id)
+        .await
+        .map(Json)
+        .map_err(|e| match e {
+            DbErr::RecordNotFound(_) => (
+                StatusCode::NOT_FOUND,
+                "Tray configuration not found".to_string(),
+            ),
+            e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })
+}
+
+/// Hardware records of the probes of a tray configuration
+#[utoipa::path(
+    get,
+    path = "/{id}/probes",
+    params(
+        ("id" = Uuid, Path, description = "Tray configuration ID")
+    ),
+    responses(
+        (status = 200, description = "Probes by tray and data column", body = Vec<ProbeHardware>),
+        (status = 404, description = "Tray configuration not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "tray_configurations",
+    summary = "List probe hardware",
+    description = "Serial number, sensor type, calibration and calibration certificate of each temperature probe of the configuration"
+)]
+pub async fn get_probe_hardware(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<ProbeHardware>>, (StatusCode, String)> {
+    list_probe_hardware(&state.db, id)
+        .await
+        .map(Json)
+        .map_err(|e| match e {
+            DbErr::RecordNotFound(_) => (
+                StatusCode::NOT_FOUND,
+                "Tray configuration not found".to_string(),
+            ),
+            e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })
+}
+
+/// Replace the hardware record of a probe
+#[utoipa::path(
+    put,
+    path = "/{id}/probes/{probe_id}/hardware",
+    params(
+        ("id" = Uuid, Path, description = "Tray configuration ID"),
+        ("probe_id" = Uuid, Path, description = "Probe ID")
+    ),
+    request_body = ProbeHardwareUpdate,
+    responses(
+        (status = 200, description = "Probes of the configuration after the update", body = Vec<ProbeHardware>),
+        (status = 400, description = "The certificate is not an uploaded asset"),
+        (status = 404, description = "Probe not found in the tray configuration"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "tray_configurations",
+    summary = "Set probe hardware",
+    description = "Set the serial number, sensor type and calibration certificate asset of a probe. These describe the sensor rather than the layout, so earlier revisions can be completed too and no new revision is created"
+)]
+pub async fn put_probe_hardware(
+    State(state): State<AppState>,
+    Path((id, probe_id)): Path<(Uuid, Uuid)>,
+    Json(update): Json<ProbeHardwareUpdate>,
+) -> Result<Json<Vec<ProbeHardware>>, (StatusCode, String)> {
+    set_probe_hardware(&state.db, id, probe_id, &update)
+        .await
+        .map(Json)
+        .map_err(|e| match e {
+            DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+            DbErr::Custom(message) => (StatusCode::BAD_REQUEST, message),
+            e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })
+}
+
+fn calibration_error(e: DbErr) -> (StatusCode, String) {
+    match e {
+        DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+        DbErr::Custom(message) => (StatusCode::BAD_REQUEST, message),
+        e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+/// Calibration history of a probe
+#[utoipa::path(
+    get,
+    path = "/{id}/probes/{probe_id}/calibrations",
+    params(
+        ("id" = Uuid, Path, description = "Tray configuration ID"),
+        ("probe_id" = Uuid, Path, description = "Probe ID")
+    ),
+    responses(
+        (status = 200, description = "Calibrations of the probe's sensor, oldest first", body = Vec<ProbeCalibration>),
+        (status = 404, description = "Probe not found in the tray configuration"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "tray_configurations",
+    summary = "List the calibrations of a probe",
+    description = "List the calibrations recorded for the probe's sensor. Probes with a serial number share their history with the probes of the same serial number in other configurations and revisions"
+)]
+pub async fn get_probe_calibrations(
+    State(state): State<AppState>,
+    Path((id, probe_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<Vec<ProbeCalibration>>, (StatusCode, String)> {
+    list_calibrations(&state.db, id, probe_id)
+        .await
+        .map(Json)
+        .map_err(calibration_error)
+}
+
+/// Record a calibration of a probe
+#[utoipa::path(
+    post,
+    path = "/{id}/probes/{probe_id}/calibrations",
+    params(
+        ("id" = Uuid, Path, description = "Tray configuration ID"),
+        ("probe_id" = Uuid, Path, description = "Probe ID")
+    ),
+    request_body = ProbeCalibrationCreate,
+    responses(
+        (status = 201, description = "The recorded calibration", body = ProbeCalibration),
+        (status = 400, description = "The slope is not positive, or the experiment is not a calibration experiment"),
+        (status = 404, description = "Probe not found in the tray configuration"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "tray_configurations",
+    summary = "Record a calibration of a probe",
+    description = "Record the slope and offset found for the probe's sensor, optionally from a calibration experiment. The latest calibration of the sensor becomes the probe's current one, which data files are processed with; readings already processed are kept"
+)]
+pub async fn post_probe_calibration(
+    State(state): State<AppState>,
+    Path((id, probe_id)): Path<(Uuid, Uuid)>,
+    Json(input): Json<ProbeCalibrationCreate>,
+) -> Result<(StatusCode, Json<ProbeCalibration>), (StatusCode, String)> {
+    record_calibration(&state.db, id, probe_id, input)
+        .await
+        .map(|calibration| (StatusCode::CREATED, Json(calibration)))
+        .map_err(calibration_error)
+}
+
+/// Calibration drift of the probes of a tray configuration
+#[utoipa::path(
+    get,
+    path = "/{id}/calibration_drift",
+    params(
+        ("id" = Uuid, Path, description = "Tray configuration ID"),
+        CalibrationDriftQuery
+    ),
+    responses(
+        (status = 200, description = "Drift of each probe and the experiments run out of calibration", body = CalibrationDriftReport),
+        (status = 400, description = "Invalid tolerance or maximum age"),
+        (status = 404, description = "Tray configuration not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "tray_configurations",
+    summary = "Report calibration drift",
+    description = "Give the change between consecutive calibrations of each probe's sensor in the correction at a reference temperature, and flag the experiments using the configuration that were run with a probe not yet calibrated, whose calibration had expired, or that had drifted beyond the tolerance by its next calibration"
+)]
+pub async fn get_calibration_drift(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<CalibrationDriftQuery>,
+) -> Result<Json<CalibrationDriftReport>, (StatusCode, String)> {
+    calibration_drift(&state.db, id, &query)
+        .await
+        .map(Json)
+        .map_err(calibration_error)
+}
+
+/// Routes beyond the CRUD routes, documented here as they are routed
+/// with `route`
+#[derive(OpenApi)]
+#[openapi(paths(
+    get_well_grid,
+    get_revisions,
+    get_probe_hardware,
+    put_probe_hardware,
+    get_probe_calibrations,
+    post_probe_calibration,
+    get_calibration_drift
+))]
+struct TrayConfigurationsApi;
 
 pub fn router(state: &AppState) -> OpenApiRouter
 where
     TrayConfiguration: CRUDResource,
 {
-    let mut mutating_router = crudrouter(&state.db.clone());
+    let mut mutating_router = crudrouter(&state.db.clone())
+        .route(
+            "/{id}",
+            patch(patch_one_handler::<TrayConfiguration>).with_state(state.db.clone()),
+        )
+        .route(
+            "/{id}/restore",
+            post(restore_one_handler::<TrayConfiguration>).with_state(state.db.clone()),
+        )
+        .merge(versions::views::router::<TrayConfiguration>(&state.db))
+        .route(
+            "/{id}/well-grid",
+            get(get_well_grid).with_state(state.clone()),
+        )
+        .route(
+            "/{id}/revisions",
+            get(get_revisions).with_state(state.clone()),
+        )
+        .route(
+            "/{id}/probes",
+            get(get_probe_hardware).with_state(state.clone()),
+        )
+        .route(
+            "/{id}/probes/{probe_id}/hardware",
+            put(put_probe_hardware).with_state(state.clone()),
+        )
+        .route(
+            "/{id}/probes/{probe_id}/calibrations",
+            get(get_probe_calibrations)
+                .post(post_probe_calibration)
+                .with_state(state.clone()),
+        )
+        .route(
+            "/{id}/calibration_drift",
+            get(get_calibration_drift).with_state(state.clone()),
+        );
+    mutating_router
+        .get_openapi_mut()
+        .merge(TrayConfigurationsApi::openapi());
+
+    // Records polled by dashboards are served from the cache while unchanged
+    mutating_router = mutating_router.layer(middleware::from_fn_with_state(
+        (state.db.clone(), state.response_cache.clone()),
+        cache_records::<TrayConfiguration>,
+    ));
+
+    // Each update keeps the version it replaces
+    mutating_router = mutating_router.layer(middleware::from_fn_with_state(
+        state.db.clone(),
+        keep_versions::<TrayConfiguration>,
+    ));
+
+    mutating_router = mutating_router.layer(middleware::from_fn_with_state(
+        state.db.clone(),
+        paginate_by_cursor::<TrayConfiguration>,
+    ));
+
+    mutating_router = mutating_router.layer(middleware::from_fn_with_state(
+        (state.db.clone(), state.config.require_if_match),
+        check_entity_tags::<TrayConfiguration>,
+    ));
+
+    // Deleted records are kept, out of sight until restored
+    mutating_router = mutating_router.layer(middleware::from_fn_with_state(
+        state.db.clone(),
+        hide_deleted::<TrayConfiguration>,
+    ));
+
+    // Changes are logged with the user who made them
+    mutating_router = mutating_router.layer(middleware::from_fn_with_state(
+        (state.db.clone(), AuditedResource::TrayConfigurations),
+        audit_changes,
+    ));
 
     if let Some(instance) = state.keycloak_auth_instance.clone() {
-        mutating_router = mutating_router.layer(
-            KeycloakAuthLayer::<Role>::builder()
-                .instance(instance)
-                .passthrough_mode(PassthroughMode::Block)
-                .persist_raw_claims(false)
-                .expected_audiences(vec![String::from("account")])
-                .required_roles(vec![Role::Administrator])
-                .build(),
-        );
+        mutating_router = mutating_router
+            .layer(middleware::from_fn_with_state(
+                (state.db.clone(), TenantResource::TrayConfigurations),
+                require_lab,
+            ))
+            .layer(middleware::from_fn_with_state(
+                RouteAccess::SHARED,
+                require_role,
+            ))
+            .layer(middleware::from_fn(redact_for_viewers))
+            .layer(middleware::from_fn_with_state(
+                (state.db.clone(), "tray_configurations"),
+                accept_api_keys,
+            ))
+            .layer(middleware::from_fn_with_state(
+                (instance, PassthroughMode::Pass),
+                authenticate,
+            ));
     } else if !state.config.tests_running {
         println!(
             "Warning: Mutating routes of {} router are not protected",
@@ -0,0 +1,245 @@
+//! Pixel positions of wells on camera images
+//!
+//! A tray's `upper_left_corner_*` and `lower_right_corner_*` are the image
+//! coordinates of the centres of well A1 and of the last well, and
+//! `rotation_degrees` is the clockwise rotation of the tray in the image: at
+//! 0° columns run to the right and rows downwards.
+
+use super::trays::models as trays;
+use super::wells::models as wells;
+use rust_decimal::prelude::ToPrimitive;
+use sea_orm::{ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder};
+use serde::Serialize;
+use std::collections::HashMap;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Trays follow the 9 mm well pitch of standard microplates, the unit of
+/// `well_relative_diameter`
+const WELL_PITCH_MM: f64 = 9.0;
+
+#[derive(Clone, Debug, PartialEq, Serialize, ToSchema)]
+pub struct WellPosition {
+    /// `null` if the well has no record yet
+    pub well_id: Option<Uuid>,
+    pub row_letter: String,
+    pub column_number: i32,
+    /// Centroid in image pixels
+    pub x: f64,
+    pub y: f64,
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct TrayWellGrid {
+    pub tray_id: Uuid,
+    pub tray_name: Option<String>,
+    pub order_sequence: i32,
+    pub rotation_degrees: i32,
+    /// Well radius in pixels, `null` without `well_relative_diameter`
+    pub well_radius: Option<f64>,
+    pub wells: Vec<WellPosition>,
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct WellGrid {
+    pub tray_configuration_id: Uuid,
+    pub trays: Vec<TrayWellGrid>,
+    /// Trays without corner coordinates or dimensions, which cannot be placed
+    pub unmapped_tray_ids: Vec<Uuid>,
+}
+
+fn round_px(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+/// Well centroids of a tray, or `None` if its corners or dimensions are not
+/// set. Rows are limited to the letters A to Z.
+pub fn compute_tray_grid(tray: &trays::Model) -> Option<TrayWellGrid> {
+    let (Some(ul_x), Some(ul_y), Some(lr_x), Some(lr_y)) = (
+        tray.upper_left_corner_x,
+        tray.upper_left_corner_y,
+        tray.lower_right_corner_x,
+        tray.lower_right_corner_y,
+    ) else {
+        return None;
+    };
+    let rows = tray.qty_rows.filter(|rows| (1..=26).contains(rows))?;
+    let columns = tray.qty_cols.filter(|columns| *columns >= 1)?;
+
+    let (sin, cos) = f64::from(tray.rotation_degrees).to_radians().sin_cos();
+    let (ul_x, ul_y) = (f64::from(ul_x), f64::from(ul_y));
+    let (dx, dy) = (f64::from(lr_x) - ul_x, f64::from(lr_y) - ul_y);
+
+    // Project the diagonal onto the column and row directions of the rotated tray
+    let pitch = |along: f64, count: i32| {
+        if count > 1 {
+            along / f64::from(count - 1)
+        } else {
+            0.0
+        }
+    };
+    let column_pitch = pitch(dx * cos + dy * sin, columns);
+    let row_pitch = pitch(dy * cos - dx * sin, rows);
+
+    let mut positions = Vec::new();
+    for (row, letter) in (0..rows).zip('A'..='Z') {
+        for column in 0..columns {
+            let (c, r) = (f64::from(column) * column_pitch, f64::from(row) * row_pitch);
+            positions.push(WellPosition {
+                well_id: None,
+                row_letter: letter.to_string(),
+                column_number: column + 1,
+                x: round_px(ul_x + c * cos - r * sin),
+                y: round_px(ul_y + c * sin + r * cos),
+            });
+        }
+    }
+
+    let pitches: Vec<f64> = [column_pitch.abs(), row_pitch.abs()]
+        .into_iter()
+        .filter(|pitch| *pitch > 0.0)
+        .collect();
+    let well_radius = tray
+        .well_relative_diameter
+        .and_then(|diameter| diameter.to_f64())
+        .filter(|_| !pitches.is_empty())
+        .map(|diameter| {
+            #[allow(clippy::cast_precision_loss)] // At most two pitches
+            let pixel_pitch = pitches.iter().sum::<f64>() / pitches.len() as f64;
+            round_px(diameter / WELL_PITCH_MM * pixel_pitch / 2.0)
+        });
+
+    Some(TrayWellGrid {
+        tray_id: tray.id,
+        tray_name: tray.name.clone(),
+        order_sequence: tray.order_sequence,
+        rotation_degrees: tray.rotation_degrees,
+        well_radius,
+        wells: positions,
+    })
+}
+
+/// Well centroids of every tray of a configuration, linked to the well
+/// records that exist
+pub async fn tray_configuration_well_grid(
+    db: &DatabaseConnection,
+    tray_configuration_id: Uuid,
+) -> Result<WellGrid, DbErr> {
+    super::models::Entity::find_by_id(tray_configuration_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("tray_configuration not found".to_string()))?;
+
+    let tray_models = trays::Entity::find()
+        .filter(trays::Column::TrayConfigurationId.eq(tray_configuration_id))
+        .order_by_asc(trays::Column::OrderSequence)
+        .all(db)
+        .await?;
+    let well_ids: HashMap<(Uuid, String, i32), Uuid> = wells::Entity::find()
+        .filter(wells::Column::TrayId.is_in(tray_models.iter().map(|tray| tray.id)))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|well| ((well.tray_id, well.row_letter, well.column_number), well.id))
+        .collect();
+
+    let mut grid = WellGrid {
+        tray_configuration_id,
+        trays: Vec::new(),
+        unmapped_tray_ids: Vec::new(),
+    };
+    for tray in &tray_models {
+        let Some(mut tray_grid) = compute_tray_grid(tray) else {
+            grid.unmapped_tray_ids.push(tray.id);
+            continue;
+        };
+        for well in &mut tray_grid.wells {
+            well.well_id = well_ids
+                .get(&(tray.id, well.row_letter.clone(), well.column_number))
+                .copied();
+        }
+        grid.trays.push(tray_grid);
+    }
+    Ok(grid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+
+    fn tray(
+        rotation_degrees: i32,
+        upper_left: (i32, i32),
+        lower_right: (i32, i32),
+    ) -> trays::Model {
+        trays::Model {
+            id: Uuid::new_v4(),
+            tray_configuration_id: Uuid::new_v4(),
+            order_sequence: 1,
+            rotation_degrees,
+            name: Some("P1".to_string()),
+            qty_cols: Some(12),
+            qty_rows: Some(8),
+            well_relative_diameter: Some(Decimal::new(64, 1)),
+            upper_left_corner_x: Some(upper_left.0),
+            upper_left_corner_y: Some(upper_left.1),
+            lower_right_corner_x: Some(lower_right.0),
+            lower_right_corner_y: Some(lower_right.1),
+            created_at: chrono::Utc::now(),
+            last_updated: chrono::Utc::now(),
+            probe_locations: vec![],
+        }
+    }
+
+    fn xy(grid: &TrayWellGrid, row_letter: &str, column: i32) -> (f64, f64) {
+        let well = grid
+            .wells
+            .iter()
+            .find(|well| well.row_letter == row_letter && well.column_number == column)
+            .unwrap();
+        (well.x, well.y)
+    }
+
+    #[test]
+    fn test_unrotated_grid() {
+        let grid = compute_tray_grid(&tray(0, (100, 50), (210, 120))).unwrap();
+        assert_eq!(grid.wells.len(), 96);
+        assert_eq!(xy(&grid, "A", 1), (100.0, 50.0));
+        assert_eq!(xy(&grid, "A", 12), (210.0, 50.0));
+        assert_eq!(xy(&grid, "H", 1), (100.0, 120.0));
+        assert_eq!(xy(&grid, "H", 12), (210.0, 120.0));
+        // 10 px column pitch, 10 px row pitch, 6.4 mm wells on a 9 mm pitch
+        assert_eq!(grid.well_radius, Some(3.56));
+    }
+
+    #[test]
+    fn test_rotated_grids_follow_the_tray() {
+        // Seeded P1 tray: rotated 90°, columns run down and rows to the left
+        let grid = compute_tray_grid(&tray(90, (416, 75), (135, 542))).unwrap();
+        assert_eq!(xy(&grid, "A", 1), (416.0, 75.0));
+        assert_eq!(xy(&grid, "H", 12), (135.0, 542.0));
+        assert_eq!(xy(&grid, "A", 12), (416.0, 542.0));
+        assert_eq!(xy(&grid, "H", 1), (135.0, 75.0));
+
+        // Seeded P2 tray: rotated 270°, columns run up and rows to the right
+        let grid = compute_tray_grid(&tray(270, (536, 529), (823, 67))).unwrap();
+        assert_eq!(xy(&grid, "A", 12), (536.0, 67.0));
+        assert_eq!(xy(&grid, "H", 1), (823.0, 529.0));
+        assert_eq!(xy(&grid, "B", 1), (577.0, 529.0));
+    }
+
+    #[test]
+    fn test_incomplete_tray_is_not_mapped() {
+        let mut incomplete = tray(0, (0, 0), (110, 70));
+        incomplete.lower_right_corner_x = None;
+        assert!(compute_tray_grid(&incomplete).is_none());
+
+        let mut single_well = tray(0, (40, 30), (40, 30));
+        single_well.qty_cols = Some(1);
+        single_well.qty_rows = Some(1);
+        let grid = compute_tray_grid(&single_well).unwrap();
+        assert_eq!(grid.wells.len(), 1);
+        assert_eq!(grid.well_radius, None);
+    }
+}
@@ -0,0 +1,92 @@
+//! Wells with their trays and coordinates, so clients need not rebuild the
+//! grid to find a well's ID
+
+use super::models::{Column, Entity, Model};
+use crate::experiments::models as experiments;
+use crate::experiments::well_image::split_coordinate;
+use crate::tray_configurations::trays::models as trays;
+use sea_orm::{ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder};
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, ToSchema)]
+pub struct WellLocation {
+    pub id: Uuid,
+    pub tray_id: Uuid,
+    pub tray_name: Option<String>,
+    pub row_letter: String,
+    pub column_number: i32,
+    /// Well coordinate such as A1
+    pub coordinate: String,
+}
+
+fn locate(well: Model, tray: &trays::Model) -> WellLocation {
+    WellLocation {
+        coordinate: format!("{}{}", well.row_letter, well.column_number),
+        id: well.id,
+        tray_id: well.tray_id,
+        tray_name: tray.name.clone(),
+        row_letter: well.row_letter,
+        column_number: well.column_number,
+    }
+}
+
+/// Wells of the tray, by row and column
+pub async fn tray_wells(
+    db: &DatabaseConnection,
+    tray_id: Uuid,
+) -> Result<Vec<WellLocation>, DbErr> {
+    let tray = trays::Entity::find_by_id(tray_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Tray not found".to_string()))?;
+    let wells = Entity::find()
+        .filter(Column::TrayId.eq(tray_id))
+        .order_by_asc(Column::RowLetter)
+        .order_by_asc(Column::ColumnNumber)
+        .all(db)
+        .await?;
+    Ok(wells.into_iter().map(|well| locate(well, &tray)).collect())
+}
+
+/// Wells of the experiment's trays, in tray order, optionally only those at
+/// a coordinate such as `A1` or `P1:A1`. Without a tray name the well of
+/// every tray is given. An invalid coordinate is returned as
+/// `DbErr::Custom`.
+pub async fn experiment_wells(
+    db: &DatabaseConnection,
+    experiment_id: Uuid,
+    coordinate: Option<&str>,
+) -> Result<Vec<WellLocation>, DbErr> {
+    let experiment = experiments::Entity::find_by_id(experiment_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Experiment not found".to_string()))?;
+    let Some(tray_configuration_id) = experiment.tray_configuration_id else {
+        return Ok(Vec::new());
+    };
+
+    let mut query = Entity::find()
+        .find_also_related(trays::Entity)
+        .filter(trays::Column::TrayConfigurationId.eq(tray_configuration_id));
+    if let Some(coordinate) = coordinate {
+        let (tray_name, row_letter, column_number) = split_coordinate(coordinate.trim())?;
+        query = query
+            .filter(Column::RowLetter.eq(row_letter))
+            .filter(Column::ColumnNumber.eq(column_number));
+        if let Some(tray_name) = tray_name {
+            query = query.filter(trays::Column::Name.eq(tray_name));
+        }
+    }
+    let wells = query
+        .order_by_asc(trays::Column::OrderSequence)
+        .order_by_asc(Column::RowLetter)
+        .order_by_asc(Column::ColumnNumber)
+        .all(db)
+        .await?;
+    Ok(wells
+        .into_iter()
+        .filter_map(|(well, tray)| tray.map(|tray| locate(well, &tray)))
+        .collect())
+}
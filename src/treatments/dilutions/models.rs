@@ -0,0 +1,181 @@
+use crate::{samples::models as samples, treatments::models as treatments};
+use chrono::{DateTime, Utc};
+use crudcrate::{CRUDResource, EntityToModels, traits::MergeIntoActiveModel};
+use rust_decimal::Decimal;
+use sea_orm::{
+    ActiveModelTrait, ConnectionTrait, DatabaseConnection, EntityTrait, IntoActiveModel,
+    entity::prelude::*, sea_query::Expr,
+};
+// Import after EntityToModels to avoid conflicts
+use uuid::Uuid;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, EntityToModels)]
+#[sea_orm(table_name = "treatment_dilutions")]
+#[crudcrate(
+    generate_router,
+    api_struct = "Dilution",
+    name_singular = "dilution",
+    name_plural = "dilutions",
+    description = "Dilutions record how each dilution of a treated sample suspension was prepared. Regions that use a dilution take its factor.",
+    fn_get_one = get_one_dilution,
+    fn_create = create_dilution,
+    fn_update = update_dilution,
+)]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    #[crudcrate(primary_key, update_model = false, create_model = false, on_create = Uuid::new_v4())]
+    pub id: Uuid,
+    #[crudcrate(sortable, filterable)]
+    pub treatment_id: Uuid,
+    /// Volume of the final suspension over that of the treated suspension used
+    #[crudcrate(sortable, filterable)]
+    pub dilution_factor: i32,
+    #[sea_orm(column_type = "Decimal(Some((16, 10)))", nullable)]
+    #[crudcrate(sortable, filterable)]
+    pub prepared_volume_litres: Option<Decimal>,
+    #[crudcrate(sortable)]
+    pub prepared_at: Option<DateTime<Utc>>,
+    #[sea_orm(column_type = "Text", nullable)]
+    #[crudcrate(sortable, filterable, fulltext)]
+    pub operator: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    #[crudcrate(filterable, fulltext)]
+    pub notes: Option<String>,
+    #[crudcrate(update_model = false, create_model = false, on_create = chrono::Utc::now(), sortable, list_model=false)]
+    pub created_at: DateTime<Utc>,
+    #[crudcrate(update_model = false, create_model = false, on_update = chrono::Utc::now(), on_create = chrono::Utc::now(), sortable, list_model=false)]
+    pub last_updated: DateTime<Utc>,
+    /// The sample's initial concentration divided by the dilution factor
+    #[sea_orm(ignore)]
+    #[crudcrate(non_db_attr = true, default = None, list_model = false, create_model = false, update_model = false)]
+    pub concentration_gram_l: Option<Decimal>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "crate::treatments::models::Entity",
+        from = "Column::TreatmentId",
+        to = "crate::treatments::models::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Treatments,
+    #[sea_orm(has_many = "crate::tray_configurations::regions::models::Entity")]
+    Regions,
+}
+
+impl Related<crate::treatments::models::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Treatments.def()
+    }
+}
+
+impl Related<crate::tray_configurations::regions::models::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Regions.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+fn validate_factor(dilution_factor: i32) -> Result<(), DbErr> {
+    if dilution_factor < 1 {
+        return Err(DbErr::Custom(format!(
+            "Dilution factor must be at least 1, got {dilution_factor}"
+        )));
+    }
+    Ok(())
+}
+
+async fn get_one_dilution(db: &DatabaseConnection, id: Uuid) -> Result<Dilution, DbErr> {
+    let model = Entity::find_by_id(id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Dilution not found".to_string()))?;
+
+    let initial_concentration = treatments::Entity::find_by_id(model.treatment_id)
+        .find_also_related(samples::Entity)
+        .one(db)
+        .await?
+        .and_then(|(_, sample)| sample)
+        .and_then(|sample| sample.initial_concentration_gram_l);
+    let concentration_gram_l = initial_concentration
+        .and_then(|concentration| concentration.checked_div(Decimal::from(model.dilution_factor)))
+        .map(|concentration| concentration.normalize());
+
+    let mut dilution: Dilution = model.into();
+    dilution.concentration_gram_l = concentration_gram_l;
+    Ok(dilution)
+}
+
+async fn create_dilution(
+    db: &DatabaseConnection,
+    create_data: DilutionCreate,
+) -> Result<Dilution, DbErr> {
+    validate_factor(create_data.dilution_factor)?;
+    treatments::Entity::find_by_id(create_data.treatment_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| {
+            DbErr::Custom(format!("Treatment {} not found", create_data.treatment_id))
+        })?;
+
+    let active_model: ActiveModel = create_data.into();
+    let inserted = active_model.insert(db).await?;
+    Dilution::get_one(db, inserted.id).await
+}
+
+async fn update_dilution(
+    db: &DatabaseConnection,
+    id: Uuid,
+    update_data: DilutionUpdate,
+) -> Result<Dilution, DbErr> {
+    if let Some(Some(dilution_factor)) = update_data.dilution_factor {
+        validate_factor(dilution_factor)?;
+    }
+    let existing = Entity::find_by_id(id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Dilution not found".to_string()))?;
+    let updated = update_data
+        .merge_into_activemodel(existing.into_active_model())?
+        .update(db)
+        .await?;
+
+    // Regions keep the factor of the dilution they use
+    crate::tray_configurations::regions::models::Entity::update_many()
+        .col_expr(
+            crate::tray_configurations::regions::models::Column::DilutionFactor,
+            Expr::value(updated.dilution_factor),
+        )
+        .filter(crate::tray_configurations::regions::models::Column::DilutionId.eq(id))
+        .exec(db)
+        .await?;
+
+    Dilution::get_one(db, id).await
+}
+
+/// Dilution factor of a region: that of its dilution when it has one, which
+/// must be a dilution of the region's treatment, else the factor given.
+/// Problems are returned as `DbErr::Custom`.
+pub(crate) async fn region_dilution_factor(
+    db: &impl ConnectionTrait,
+    treatment_id: Option<Uuid>,
+    dilution_id: Option<Uuid>,
+    dilution_factor: Option<i32>,
+) -> Result<Option<i32>, DbErr> {
+    let Some(dilution_id) = dilution_id else {
+        return Ok(dilution_factor);
+    };
+    let dilution = Entity::find_by_id(dilution_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::Custom(format!("Dilution {dilution_id} not found")))?;
+    if treatment_id != Some(dilution.treatment_id) {
+        return Err(DbErr::Custom(format!(
+            "Dilution {dilution_id} is not a dilution of the region's treatment"
+        )));
+    }
+    Ok(Some(dilution.dilution_factor))
+}
@@ -0,0 +1,79 @@
+pub use super::models::{Dilution, router as crudrouter};
+use crate::api_keys::services::accept_api_keys;
+use crate::audit::services::{AuditedResource, audit_changes};
+use crate::common::auth::{RouteAccess, require_role};
+use crate::common::etag::check_entity_tags;
+use crate::common::keycloak::authenticate;
+use crate::common::labs::{TenantResource, require_lab};
+use crate::common::pagination::paginate_by_cursor;
+use crate::common::patch::patch_one_handler;
+use crate::common::redaction::redact_for_viewers;
+use crate::common::state::AppState;
+use crate::projects::access::{ScopedResource, require_project_access};
+use axum::middleware;
+use axum::routing::patch;
+use axum_keycloak_auth::PassthroughMode;
+use crudcrate::CRUDResource;
+
+use utoipa_axum::router::OpenApiRouter;
+
+pub fn router(state: &AppState) -> OpenApiRouter
+where
+    Dilution: CRUDResource,
+{
+    let mut mutating_router = crudrouter(&state.db.clone()).route(
+        "/{id}",
+        patch(patch_one_handler::<Dilution>).with_state(state.db.clone()),
+    );
+
+    mutating_router = mutating_router.layer(middleware::from_fn_with_state(
+        state.db.clone(),
+        paginate_by_cursor::<Dilution>,
+    ));
+
+    mutating_router = mutating_router.layer(middleware::from_fn_with_state(
+        (state.db.clone(), state.config.require_if_match),
+        check_entity_tags::<Dilution>,
+    ));
+
+    // Changes are logged with the user who made them
+    mutating_router = mutating_router.layer(middleware::from_fn_with_state(
+        (state.db.clone(), AuditedResource::Dilutions),
+        audit_changes,
+    ));
+
+    if let Some(instance) = state.keycloak_auth_instance.clone() {
+        // Viewers read and editors change records; users who are not
+        // administrators are limited to the projects they are members of,
+        // within their labs
+        mutating_router = mutating_router
+            .layer(middleware::from_fn_with_state(
+                (state.db.clone(), ScopedResource::Dilutions),
+                require_project_access,
+            ))
+            .layer(middleware::from_fn_with_state(
+                (state.db.clone(), TenantResource::Dilutions),
+                require_lab,
+            ))
+            .layer(middleware::from_fn_with_state(
+                RouteAccess::PROJECT_RECORDS,
+                require_role,
+            ))
+            .layer(middleware::from_fn(redact_for_viewers))
+            .layer(middleware::from_fn_with_state(
+                (state.db.clone(), "dilutions"),
+                accept_api_keys,
+            ))
+            .layer(middleware::from_fn_with_state(
+                (instance, PassthroughMode::Pass),
+                authenticate,
+            ));
+    } else if !state.config.tests_running {
+        println!(
+            "Warning: Mutating routes of {} router are not protected",
+            Dilution::RESOURCE_NAME_PLURAL
+        );
+    }
+
+    mutating_router
+}
@@ -1,3 +1,4 @@
+pub mod dilutions;
 pub mod models;
 pub mod views;
 
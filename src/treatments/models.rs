@@ -1,3 +1,4 @@
+use crate::common::soft_delete::{soft_delete, soft_delete_many};
 use crate::nucleation_events::models::{
     DilutionSummary, NucleationEvent, NucleationStatistics,
 };
@@ -9,7 +10,7 @@ use crate::{
     tray_configurations::{regions::models as regions, wells::models as wells},
 };
 use chrono::{DateTime, Utc};
-use crudcrate::{CRUDResource, EntityToModels};
+use crudcrate::{CRUDResource, EntityToModels, traits::MergeIntoActiveModel};
 use rust_decimal::Decimal;
 use sea_orm::{EntityTrait, entity::prelude::*};
 // Import after EntityToModels to avoid conflicts
@@ -23,6 +24,9 @@ use uuid::Uuid;
     name_plural = "treatments",
     description = "Treatments are applied to samples during experiments to study their effects on ice nucleation.",
     fn_get_one = get_one_treatment,
+    fn_delete = delete_treatment,
+    fn_delete_many = delete_many_treatments,
+    fn_update = update_treatment,
 )]
 pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
@@ -35,6 +39,9 @@ pub struct Model {
     pub notes: Option<String>,
     #[crudcrate(sortable, filterable, list_model = false)]
     pub sample_id: Option<Uuid>,
+    /// When the treatment was deleted; deleted treatments are kept until restored
+    #[crudcrate(sortable, filterable, create_model = false, update_model = false)]
+    pub deleted_at: Option<DateTime<Utc>>,
     #[crudcrate(update_model = false, create_model = false, on_create = chrono::Utc::now(), sortable, list_model=false)]
     pub created_at: DateTime<Utc>,
     #[crudcrate(update_model = false, create_model = false, on_update = chrono::Utc::now(), on_create = chrono::Utc::now(), sortable, list_model=false)]
@@ -59,6 +66,8 @@ pub struct Model {
 pub enum Relation {
     #[sea_orm(has_many = "crate::tray_configurations::regions::models::Entity")]
     Regions,
+    #[sea_orm(has_many = "super::dilutions::models::Entity")]
+    Dilutions,
     #[sea_orm(
         belongs_to = "crate::samples::models::Entity",
         from = "Column::SampleId",
@@ -75,6 +84,12 @@ impl Related<crate::tray_configurations::regions::models::Entity> for Entity {
     }
 }
 
+impl Related<super::dilutions::models::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Dilutions.def()
+    }
+}
+
 impl Related<crate::samples::models::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Samples.def()
@@ -83,6 +98,15 @@ impl Related<crate::samples::models::Entity> for Entity {
 
 impl ActiveModelBehavior for ActiveModel {}
 
+/// Custom `delete` keeping the treatment, marked deleted
+async fn delete_treatment(db: &DatabaseConnection, id: Uuid) -> Result<Uuid, DbErr> {
+    soft_delete::<Entity>(db, Column::Id, Column::DeletedAt, id).await
+}
+
+async fn delete_many_treatments(db: &DatabaseConnection, ids: Vec<Uuid>) -> Result<Vec<Uuid>, DbErr> {
+    soft_delete_many::<Entity>(db, Column::Id, Column::DeletedAt, ids).await
+}
+
 #[derive(
     Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, ToSchema, Serialize, Deserialize,
 )]
@@ -130,6 +154,9 @@ async fn fetch_experimental_results_for_treatment(
                 .all(db)
                 .await?;
 
+            let excluded_wells =
+                crate::experiments::exclusions::excluded_well_reasons(db, experiment.id).await?;
+
             // Get temperature readings for this experiment
             let temp_readings_data = temperature_readings::Entity::find()
                 .filter(temperature_readings::Column::ExperimentId.eq(experiment.id))
@@ -159,6 +186,21 @@ async fn fetch_experimental_results_for_treatment(
                     .push(probe_reading);
             }
 
+            let probe_ids: std::collections::HashSet<Uuid> = probe_readings_data
+                .iter()
+                .map(|reading| reading.probe_id)
+                .collect();
+            let probe_map: std::collections::HashMap<
+                Uuid,
+                crate::tray_configurations::probes::models::Model,
+            > = crate::tray_configurations::probes::models::Entity::find()
+                .filter(crate::tray_configurations::probes::models::Column::Id.is_in(probe_ids))
+                .all(db)
+                .await?
+                .into_iter()
+                .map(|probe| (probe.id, probe))
+                .collect();
+
             // Get tray information - region.tray_id is i32, but we need to find by sequence/order
             // For now, let's use the tray name from the region or a placeholder
             let tray_name = format!("P{}", region.tray_id.unwrap_or(1));
@@ -201,20 +243,23 @@ async fn fetch_experimental_results_for_treatment(
                     }
 
                     // Get temperature data at nucleation time from probe readings
-                    let temperature_avg = probe_readings_by_temp_id
+                    let temperatures = probe_readings_by_temp_id
                         .get(&transition.temperature_reading_id)
                         .and_then(|probe_readings| {
-                            let temperature_values: Vec<Decimal> = probe_readings
-                                .iter()
-                                .map(|pr| pr.temperature)
-                                .collect();
-                            
-                            if temperature_values.is_empty() {
-                                None
-                            } else {
-                                let sum: Decimal = temperature_values.iter().sum();
-                                Some(sum / Decimal::from(temperature_values.len()))
-                            }
+                            crate::experiments::probe_temperature_readings::models::mean_temperatures(
+                                probe_readings,
+                                &probe_map,
+                            )
+                        });
+                    let temperature_avg = temperatures.map(|(calibrated, _)| calibrated);
+                    let temperature_interpolated = probe_readings_by_temp_id
+                        .get(&transition.temperature_reading_id)
+                        .and_then(|probe_readings| {
+                            crate::experiments::probe_temperature_readings::models::interpolated_temperature(
+                                probe_readings,
+                                &probe_map,
+                                well,
+                            )
                         });
 
                     // Calculate time from experiment start
@@ -230,16 +275,20 @@ async fn fetch_experimental_results_for_treatment(
                         experiment_id: experiment.id,
                         experiment_name: experiment.name.clone(),
                         experiment_date: experiment.performed_at,
+                        well_id: Some(well.id),
                         well_coordinate,
                         tray_name: Some(tray_name.clone()),
                         nucleation_time_seconds,
                         nucleation_temperature_avg_celsius: temperature_avg,
                         freezing_time_seconds: nucleation_time_seconds, // UI compatibility
                         freezing_temperature_avg: temperature_avg,      // UI compatibility
+                        nucleation_temperature_raw_avg_celsius: temperatures.map(|(_, raw)| raw),
+                        nucleation_temperature_interpolated_celsius: temperature_interpolated,
                         dilution_factor: region.dilution_factor,
                         final_state: "frozen".to_string(), // Since this is a 0→1 transition
                         treatment_id: Some(treatment_id),
                         treatment_name: Some(format!("{:?}", treatment.name)), // Convert enum to string
+                        excluded: excluded_wells.contains_key(&well.id),
                     };
 
                     nucleation_events.push(nucleation_event);
@@ -251,6 +300,24 @@ async fn fetch_experimental_results_for_treatment(
     Ok(nucleation_events)
 }
 
+/// Custom `update` keeping the treatment's ID. The update model names the
+/// ID of each treatment a sample update changes, and leaves it unset here.
+async fn update_treatment(
+    db: &DatabaseConnection,
+    id: Uuid,
+    update_data: TreatmentUpdate,
+) -> Result<Treatment, DbErr> {
+    let existing: ActiveModel = Entity::find_by_id(id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Treatment not found".to_string()))?
+        .into();
+    let mut updated = update_data.merge_into_activemodel(existing)?;
+    updated.id = ActiveValue::Unchanged(id);
+    updated.update(db).await?;
+    get_one_treatment(db, id).await
+}
+
 /// Custom `get_one` that loads experimental results and statistics
 async fn get_one_treatment(db: &DatabaseConnection, id: Uuid) -> Result<Treatment, DbErr> {
     let model = Entity::find_by_id(id)
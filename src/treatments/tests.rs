@@ -1,4 +1,7 @@
-use crate::config::test_helpers::setup_test_app;
+use crate::common::keycloak::test_realm;
+use crate::config::test_helpers::{
+    send_json, send_json_as, setup_authenticated_test_app, setup_test_app,
+};
 use axum::body::{Body, to_bytes};
 use axum::http::{Request, StatusCode};
 use serde_json::{Value, json};
@@ -48,6 +51,7 @@ async fn create_test_sample(app: &axum::Router) -> String {
 }
 
 #[tokio::test]
+#[allow(clippy::too_many_lines)]
 async fn test_treatment_crud_operations() {
     let app = setup_test_app().await;
 
@@ -123,15 +127,11 @@ async fn test_treatment_crud_operations() {
     assert_eq!(get_body["id"], treatment_id);
     assert_eq!(get_body["name"], "heat");
 
-    // NOTE: Update and Delete operations are not fully implemented yet in the Treatment CRUDResource
-    // The routes exist but return Method Not Allowed. This should be fixed in the CRUDResource implementation.
-
-    // Test updating the treatment (currently returns 405 Method Not Allowed)
+    // Test updating the treatment
     let update_data = json!({
         "notes": "Updated heat treatment for 10 minutes",
         "enzyme_volume_litres": 0.002
     });
-
     let update_response = app
         .clone()
         .oneshot(
@@ -145,14 +145,34 @@ async fn test_treatment_crud_operations() {
         .await
         .unwrap();
 
-    let (update_status, _update_body) = extract_response_body(update_response).await;
+    let (update_status, update_body) = extract_response_body(update_response).await;
     assert_eq!(
         update_status,
-        StatusCode::METHOD_NOT_ALLOWED,
-        "Update not implemented yet"
+        StatusCode::OK,
+        "Failed to update treatment: {update_body:?}"
+    );
+    assert_eq!(update_body["name"], "heat");
+    assert_eq!(
+        update_body["notes"],
+        "Updated heat treatment for 10 minutes"
     );
 
-    // Test deleting the treatment (currently returns 405 Method Not Allowed)
+    // The new enzyme volume is kept
+    let uri = format!("/api/treatments/{treatment_id}");
+    let (_, get_body) = send_json(&app, "GET", &uri, None).await;
+    for body in [&update_body, &get_body] {
+        let enzyme_volume = body["enzyme_volume_litres"]
+            .as_str()
+            .unwrap()
+            .parse::<f64>()
+            .unwrap();
+        assert!(
+            (enzyme_volume - 0.002).abs() < f64::EPSILON,
+            "Expected 0.002, got {enzyme_volume}"
+        );
+    }
+
+    // Test deleting the treatment
     let delete_response = app
         .clone()
         .oneshot(
@@ -387,11 +407,10 @@ async fn test_treatment_not_found() {
         .unwrap();
 
     let (update_status, _) = extract_response_body(update_response).await;
-    // Currently returns Method Not Allowed because update is not implemented
     assert_eq!(
         update_status,
-        StatusCode::METHOD_NOT_ALLOWED,
-        "Update operations not implemented yet"
+        StatusCode::NOT_FOUND,
+        "Should return 404 when updating a non-existent treatment"
     );
 
     // Test deleting non-existent treatment
@@ -508,3 +527,272 @@ async fn test_treatment_filtering_and_sorting() {
     let (sort_status, _) = extract_response_body(sort_response).await;
     assert_eq!(sort_status, StatusCode::OK, "Sorting should work");
 }
+
+#[tokio::test]
+#[allow(clippy::too_many_lines)]
+async fn test_treatment_dilutions() {
+    let app = setup_test_app().await;
+    let (status, sample) = send_json(
+        &app,
+        "POST",
+        "/api/samples",
+        Some(&json!({
+            "name": "Suspension for dilutions",
+            "type": "bulk",
+            "initial_concentration_gram_l": 0.5,
+            "treatments": [{"name": "none"}, {"name": "heat"}]
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{sample}");
+    let treatment_id = sample["treatments"][0]["id"].as_str().unwrap();
+    let other_treatment_id = sample["treatments"][1]["id"].as_str().unwrap();
+
+    let (status, dilution) = send_json(
+        &app,
+        "POST",
+        "/api/dilutions",
+        Some(&json!({
+            "treatment_id": treatment_id,
+            "dilution_factor": 10,
+            "prepared_volume_litres": 0.002,
+            "prepared_at": "2025-08-26T09:30:00Z",
+            "operator": "jdoe"
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{dilution}");
+    assert_eq!(dilution["operator"], "jdoe");
+    assert_eq!(dilution["concentration_gram_l"], "0.05");
+    let dilution_id = dilution["id"].as_str().unwrap();
+
+    // One record per factor and treatment
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        "/api/dilutions",
+        Some(&json!({"treatment_id": treatment_id, "dilution_factor": 10})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        "/api/dilutions",
+        Some(&json!({"treatment_id": treatment_id, "dilution_factor": 0})),
+    )
+    .await;
+    assert!(!status.is_success());
+
+    // A region using the dilution takes its factor over the one given
+    let (status, experiment) = send_json(
+        &app,
+        "POST",
+        "/api/experiments",
+        Some(&json!({
+            "name": "Dilution series run",
+            "is_calibration": false,
+            "regions": [{
+                "name": "Diluted",
+                "tray_id": 1,
+                "row_min": 0,
+                "row_max": 1,
+                "col_min": 0,
+                "col_max": 1,
+                "treatment_id": treatment_id,
+                "dilution_id": dilution_id,
+                "dilution_factor": 1,
+                "is_background_key": false
+            }]
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{experiment}");
+    let experiment_id = experiment["id"].as_str().unwrap();
+    let region_factor = |experiment: &Value| experiment["regions"][0]["dilution_factor"].clone();
+
+    let get_experiment = || async {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/api/experiments/{experiment_id}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        extract_response_body(response).await.1
+    };
+    let experiment = get_experiment().await;
+    assert_eq!(experiment["regions"][0]["dilution_id"], dilution_id);
+    assert_eq!(region_factor(&experiment), 10);
+
+    // Correcting the recorded factor updates the regions using it
+    let (status, dilution) = send_json(
+        &app,
+        "PUT",
+        &format!("/api/dilutions/{dilution_id}"),
+        Some(&json!({"dilution_factor": 20})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{dilution}");
+    assert_eq!(dilution["concentration_gram_l"], "0.025");
+    assert_eq!(region_factor(&get_experiment().await), 20);
+
+    let (status, body) = send_json(
+        &app,
+        "PUT",
+        &format!("/api/experiments/{experiment_id}"),
+        Some(&json!({"regions": [{
+            "name": "Wrong treatment",
+            "tray_id": 1,
+            "row_min": 0,
+            "row_max": 1,
+            "col_min": 0,
+            "col_max": 1,
+            "treatment_id": other_treatment_id,
+            "dilution_id": dilution_id
+        }]})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(
+        body.to_string()
+            .contains("is not a dilution of the region's treatment"),
+        "{body}"
+    );
+
+    let (status, listed) = send_json(
+        &app,
+        "GET",
+        &format!("/api/dilutions?filter=%7B%22treatment_id%22%3A%22{treatment_id}%22%7D"),
+        Some(&json!({})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(listed.as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+#[allow(clippy::too_many_lines)]
+async fn test_treatment_and_dilution_access_by_role() {
+    let (app, _db) = setup_authenticated_test_app().await;
+    let admin = test_realm::token("ada", &["spice-admin"], &[]);
+    let editor = test_realm::token("eddie", &["spice-editor"], &[]);
+    let viewer = test_realm::token("vera", &["spice-viewer"], &[]);
+
+    // A sample with a treatment in a project of the editor and viewer, and
+    // one in another project
+    let mut treatment_ids = Vec::new();
+    for (name, members) in [
+        ("Member project", json!(["eddie", "vera"])),
+        ("Other project", json!([])),
+    ] {
+        let (_, project) = send_json_as(
+            &app,
+            Some(&admin),
+            "POST",
+            "/api/projects",
+            Some(&json!({"name": name})),
+        )
+        .await;
+        let project_id = project["id"].as_str().unwrap();
+        let (status, _) = send_json_as(
+            &app,
+            Some(&admin),
+            "PUT",
+            &format!("/api/projects/{project_id}/members"),
+            Some(&members),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, location) = send_json_as(
+            &app,
+            Some(&admin),
+            "POST",
+            "/api/locations",
+            Some(&json!({"name": format!("{name} site"), "project_id": project_id})),
+        )
+        .await;
+        let (status, sample) = send_json_as(
+            &app,
+            Some(&admin),
+            "POST",
+            "/api/samples",
+            Some(&json!({
+                "name": format!("{name} sample"),
+                "type": "bulk",
+                "location_id": location["id"],
+                "treatments": [{"name": "none"}]
+            })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED, "{sample}");
+        treatment_ids.push(sample["treatments"][0]["id"].as_str().unwrap().to_string());
+    }
+    let (mine, other) = (&treatment_ids[0], &treatment_ids[1]);
+
+    let (status, _) = send_json_as(&app, None, "GET", "/api/treatments", None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // Viewers read the treatments of their projects, and change nothing
+    let (status, listed) = send_json_as(&app, Some(&viewer), "GET", "/api/treatments", None).await;
+    assert_eq!(status, StatusCode::OK, "{listed}");
+    assert_eq!(listed.as_array().unwrap().len(), 1);
+    assert_eq!(listed[0]["id"], mine.as_str());
+    let (status, _) = send_json_as(
+        &app,
+        Some(&viewer),
+        "GET",
+        &format!("/api/treatments/{other}"),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let dilution = json!({"treatment_id": mine, "dilution_factor": 10});
+    let (status, _) = send_json_as(
+        &app,
+        Some(&viewer),
+        "POST",
+        "/api/dilutions",
+        Some(&dilution),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Editors change them, within their projects
+    let (status, created) = send_json_as(
+        &app,
+        Some(&editor),
+        "POST",
+        "/api/dilutions",
+        Some(&dilution),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{created}");
+    let (status, _) = send_json_as(
+        &app,
+        Some(&editor),
+        "POST",
+        "/api/dilutions",
+        Some(&json!({"treatment_id": other, "dilution_factor": 10})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = send_json_as(
+        &app,
+        Some(&editor),
+        "PATCH",
+        &format!("/api/treatments/{mine}"),
+        Some(&json!({"notes": "Filtered first"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // The viewer reads the dilution made
+    let (status, listed) = send_json_as(&app, Some(&viewer), "GET", "/api/dilutions", None).await;
+    assert_eq!(status, StatusCode::OK, "{listed}");
+    assert_eq!(listed.as_array().unwrap().len(), 1);
+    assert_eq!(listed[0]["id"], created["id"]);
+}
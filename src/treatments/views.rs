@@ -1,27 +1,142 @@
 pub use super::models::{Treatment, router as crudrouter};
-use crate::common::auth::Role;
+use crate::api_keys::services::accept_api_keys;
+use crate::audit::services::{AuditedResource, audit_changes};
+use crate::common::auth::{RouteAccess, require_role};
+use crate::common::etag::check_entity_tags;
+use crate::common::keycloak::authenticate;
+use crate::common::labs::{TenantResource, require_lab};
+use crate::common::pagination::paginate_by_cursor;
+use crate::common::patch::patch_one_handler;
+use crate::common::redaction::redact_for_viewers;
+use crate::common::soft_delete::{hide_deleted, restore_one_handler};
 use crate::common::state::AppState;
-use axum_keycloak_auth::{PassthroughMode, layer::KeycloakAuthLayer};
+use crate::experiments::regions::treatment_regions;
+use crate::projects::access::{ScopedResource, require_project_access};
+use crate::projects::archiving::reject_archived_changes;
+use crate::tray_configurations::regions::models::Region;
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+    middleware,
+    routing::{get, patch, post},
+};
+use axum_keycloak_auth::PassthroughMode;
 use crudcrate::CRUDResource;
-
+use sea_orm::DbErr;
+use utoipa::OpenApi;
 use utoipa_axum::router::OpenApiRouter;
+use uuid::Uuid;
+
+/// Regions of experiments holding the treatment
+#[utoipa::path(
+    get,
+    path = "/{id}/regions",
+    params(
+        ("id" = Uuid, Path, description = "Treatment ID")
+    ),
+    responses(
+        (status = 200, description = "Regions holding the treatment, by experiment", body = Vec<Region>),
+        (status = 404, description = "Treatment not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "treatments",
+    summary = "List the regions of a treatment",
+    description = "List the regions of experiments that hold the treatment"
+)]
+pub async fn get_treatment_regions(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<Region>>, (StatusCode, String)> {
+    treatment_regions(&state.db, id)
+        .await
+        .map(Json)
+        .map_err(|e| match e {
+            DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+            e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })
+}
+
+/// Documentation of the routes, which are routed with `route`
+#[derive(OpenApi)]
+#[openapi(paths(get_treatment_regions))]
+struct TreatmentsApi;
 
 pub fn router(state: &AppState) -> OpenApiRouter
 where
     Treatment: CRUDResource,
 {
-    let mut mutating_router = crudrouter(&state.db.clone());
+    let mut mutating_router = crudrouter(&state.db.clone())
+        .route(
+            "/{id}",
+            patch(patch_one_handler::<Treatment>).with_state(state.db.clone()),
+        )
+        .route(
+            "/{id}/restore",
+            post(restore_one_handler::<Treatment>).with_state(state.db.clone()),
+        )
+        .route(
+            "/{id}/regions",
+            get(get_treatment_regions).with_state(state.clone()),
+        );
+    mutating_router
+        .get_openapi_mut()
+        .merge(TreatmentsApi::openapi());
+
+    mutating_router = mutating_router.layer(middleware::from_fn_with_state(
+        state.db.clone(),
+        paginate_by_cursor::<Treatment>,
+    ));
+
+    mutating_router = mutating_router.layer(middleware::from_fn_with_state(
+        (state.db.clone(), state.config.require_if_match),
+        check_entity_tags::<Treatment>,
+    ));
+
+    // Deleted records are kept, out of sight until restored
+    mutating_router = mutating_router.layer(middleware::from_fn_with_state(
+        state.db.clone(),
+        hide_deleted::<Treatment>,
+    ));
+
+    // Archived projects and their records are read-only, even to administrators
+    mutating_router = mutating_router.layer(middleware::from_fn_with_state(
+        (state.db.clone(), Some(ScopedResource::Treatments)),
+        reject_archived_changes,
+    ));
+
+    // Changes are logged with the user who made them
+    mutating_router = mutating_router.layer(middleware::from_fn_with_state(
+        (state.db.clone(), AuditedResource::Treatments),
+        audit_changes,
+    ));
 
     if let Some(instance) = state.keycloak_auth_instance.clone() {
-        mutating_router = mutating_router.layer(
-            KeycloakAuthLayer::<Role>::builder()
-                .instance(instance)
-                .passthrough_mode(PassthroughMode::Block)
-                .persist_raw_claims(false)
-                .expected_audiences(vec![String::from("account")])
-                .required_roles(vec![Role::Administrator])
-                .build(),
-        );
+        // Viewers read and editors change records; users who are not
+        // administrators are limited to the projects they are members of,
+        // within their labs
+        mutating_router = mutating_router
+            .layer(middleware::from_fn_with_state(
+                (state.db.clone(), ScopedResource::Treatments),
+                require_project_access,
+            ))
+            .layer(middleware::from_fn_with_state(
+                (state.db.clone(), TenantResource::Treatments),
+                require_lab,
+            ))
+            .layer(middleware::from_fn_with_state(
+                RouteAccess::PROJECT_RECORDS,
+                require_role,
+            ))
+            .layer(middleware::from_fn(redact_for_viewers))
+            .layer(middleware::from_fn_with_state(
+                (state.db.clone(), "treatments"),
+                accept_api_keys,
+            ))
+            .layer(middleware::from_fn_with_state(
+                (instance, PassthroughMode::Pass),
+                authenticate,
+            ));
     } else if !state.config.tests_running {
         println!(
             "Warning: Mutating routes of {} router are not protected",
@@ -0,0 +1,6 @@
+pub mod models;
+pub mod services;
+pub mod views;
+
+#[cfg(test)]
+pub mod tests;
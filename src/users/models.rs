@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+
+/// What becomes of a departed user's identifiers
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PurgeMode {
+    /// Replace them with a pseudonym, the same one everywhere, so that the
+    /// records of the user can still be told apart from those of others
+    Anonymize,
+    /// Clear them, and use a pseudonym only where a user must be named
+    Remove,
+}
+
+#[derive(Clone, Debug, Deserialize, ToSchema)]
+pub struct UserPurge {
+    /// Keycloak username of the departed user, also the user of their API
+    /// keys
+    pub username: String,
+    pub mode: PurgeMode,
+}
+
+/// What a purge changed. The purged username is not repeated.
+#[derive(Clone, Debug, Default, Serialize, ToSchema)]
+pub struct PurgeReport {
+    /// Pseudonym that replaced the username
+    pub pseudonym: String,
+    /// Records changed, by table and field, e.g. `experiments.username`
+    pub updated: BTreeMap<String, u64>,
+    /// Project memberships and share grants of the user, which are removed
+    pub removed: BTreeMap<String, u64>,
+    /// API keys of the user, which are revoked
+    pub api_keys_revoked: u64,
+    /// Audit log entries whose records named the user
+    pub audit_entries_rewritten: u64,
+}
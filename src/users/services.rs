@@ -0,0 +1,287 @@
+//! Purging a departed user's personal identifiers.
+//!
+//! Records name the users who created, uploaded, reviewed or changed them.
+//! When a user leaves, an administrator can anonymize these names, replacing
+//! them with a pseudonym, or remove them, clearing every field that may be
+//! empty. Either way the records themselves and their scientific data are
+//! kept as they are. The user's project memberships and share grants are
+//! removed, their API keys revoked, and the records of the audit log and
+//! their saved versions are rewritten, without logging the username again.
+//! Comments mentioning the user mention the pseudonym instead, in either
+//! mode.
+
+use super::models::{PurgeMode, PurgeReport, UserPurge};
+use crate::api_keys::models as api_keys;
+use crate::audit::models as audit_log;
+use crate::experiments::comments::{models as experiment_comments, services::replace_mentions};
+use crate::projects::members::models as project_members;
+use crate::projects::shares::models::{self as share_grants, GranteeType};
+use crate::versions::models as record_versions;
+use chrono::Utc;
+use sea_orm::{
+    ActiveModelTrait,
+    ActiveValue::Set,
+    ColumnTrait, ConnectionTrait, DatabaseConnection, DatabaseTransaction, DbErr, EntityTrait,
+    IntoActiveModel, QueryFilter, TransactionTrait,
+    sea_query::{Alias, Condition, Expr, Query},
+};
+use serde_json::Value;
+use uuid::Uuid;
+
+/// Fields naming a user, by table, and whether they may be empty
+const USER_FIELDS: &[(&str, &str, bool)] = &[
+    ("api_keys", "created_by", true),
+    ("api_keys", "username", false),
+    ("audit_log", "username", true),
+    ("download_tokens", "created_by", true),
+    ("download_tokens", "revoked_by", true),
+    ("experiment_comments", "created_by", true),
+    ("experiments", "created_by", true),
+    ("experiments", "username", true),
+    ("planned_experiments", "created_by", true),
+    ("planned_experiments", "operator", true),
+    ("probe_calibrations", "created_by", true),
+    ("projects", "archived_by", true),
+    ("record_versions", "username", true),
+    ("s3_assets", "uploaded_by", true),
+    ("sample_custody_events", "recorded_by", true),
+    ("sample_custody_events", "username", true),
+    ("samples", "created_by", true),
+    ("samples", "qc_reviewed_by", true),
+    ("share_grants", "granted_by", true),
+    ("treatment_dilutions", "operator", true),
+    ("webhooks", "created_by", true),
+];
+
+/// Fields of the records kept in the audit log that name a user
+const AUDITED_USER_FIELDS: &[&str] = &[
+    "archived_by",
+    "created_by",
+    "grantee",
+    "granted_by",
+    "mentions",
+    "operator",
+    "qc_reviewed_by",
+    "recorded_by",
+    "revoked_by",
+    "uploaded_by",
+    "username",
+];
+
+/// Purge a user's identifiers in one transaction
+pub async fn purge_user(db: &DatabaseConnection, purge: UserPurge) -> Result<PurgeReport, DbErr> {
+    let username = purge.username.trim();
+    if username.is_empty() {
+        return Err(DbErr::Custom("username must not be blank".to_string()));
+    }
+    let mut report = PurgeReport {
+        pseudonym: format!("former-user-{}", &Uuid::new_v4().simple().to_string()[..12]),
+        ..PurgeReport::default()
+    };
+    let txn = db.begin().await?;
+
+    report.api_keys_revoked = api_keys::Entity::update_many()
+        .col_expr(api_keys::Column::RevokedAt, Expr::value(Utc::now()))
+        .filter(api_keys::Column::Username.eq(username))
+        .filter(api_keys::Column::RevokedAt.is_null())
+        .exec(&txn)
+        .await?
+        .rows_affected;
+    let memberships = project_members::Entity::delete_many()
+        .filter(project_members::Column::Username.eq(username))
+        .exec(&txn)
+        .await?
+        .rows_affected;
+    report
+        .removed
+        .insert("project_members".to_string(), memberships);
+    let grants = share_grants::Entity::delete_many()
+        .filter(share_grants::Column::GranteeType.eq(GranteeType::User))
+        .filter(share_grants::Column::Grantee.eq(username))
+        .exec(&txn)
+        .await?
+        .rows_affected;
+    report.removed.insert("share_grants".to_string(), grants);
+
+    // Audit entries are found by their user or their records before the
+    // user is cleared from them
+    let replacement = match purge.mode {
+        PurgeMode::Anonymize => Value::String(report.pseudonym.clone()),
+        PurgeMode::Remove => Value::Null,
+    };
+    report.audit_entries_rewritten = rewrite_audit_log(&txn, username, &replacement).await?;
+    let versions = rewrite_versions(&txn, username, &replacement).await?;
+    report
+        .updated
+        .insert("record_versions.snapshot".to_string(), versions);
+    let mentioning = rewrite_mentions(&txn, username, &report.pseudonym).await?;
+    report
+        .updated
+        .insert("experiment_comments.mentions".to_string(), mentioning);
+
+    for &(table, field, nullable) in USER_FIELDS {
+        let value = if purge.mode == PurgeMode::Remove && nullable {
+            None
+        } else {
+            Some(report.pseudonym.clone())
+        };
+        let update = Query::update()
+            .table(Alias::new(table))
+            .value(Alias::new(field), value)
+            .and_where(Expr::col(Alias::new(field)).eq(username))
+            .to_owned();
+        let updated = txn
+            .execute(txn.get_database_backend().build(&update))
+            .await?
+            .rows_affected();
+        report.updated.insert(format!("{table}.{field}"), updated);
+    }
+
+    txn.commit().await?;
+    Ok(report)
+}
+
+/// Replace the user in the saved versions of records. Returns the number of
+/// versions rewritten.
+async fn rewrite_versions(
+    txn: &DatabaseTransaction,
+    username: &str,
+    replacement: &Value,
+) -> Result<u64, DbErr> {
+    let quoted = Value::String(username.to_string()).to_string();
+    let versions = record_versions::Entity::find()
+        .filter(
+            Expr::col(record_versions::Column::Snapshot)
+                .cast_as(Alias::new("text"))
+                .like(format!("%{quoted}%")),
+        )
+        .all(txn)
+        .await?;
+    let mut rewritten = 0;
+    for version in versions {
+        let mut snapshot = version.snapshot.clone();
+        replace_user(&mut snapshot, username, replacement, false);
+        if snapshot == version.snapshot {
+            continue;
+        }
+        let mut active = version.into_active_model();
+        active.snapshot = Set(snapshot);
+        active.update(txn).await?;
+        rewritten += 1;
+    }
+    Ok(rewritten)
+}
+
+/// Mention the pseudonym in the comments mentioning the user. Returns the
+/// number of comments rewritten.
+async fn rewrite_mentions(
+    txn: &DatabaseTransaction,
+    username: &str,
+    pseudonym: &str,
+) -> Result<u64, DbErr> {
+    let quoted = Value::String(username.to_string()).to_string();
+    let comments = experiment_comments::Entity::find()
+        .filter(
+            Expr::col(experiment_comments::Column::Mentions)
+                .cast_as(Alias::new("text"))
+                .like(format!("%{quoted}%")),
+        )
+        .all(txn)
+        .await?;
+    let mut rewritten = 0;
+    for comment in comments {
+        let mentions = comment.mention_list();
+        if !mentions.iter().any(|mentioned| mentioned == username) {
+            continue;
+        }
+        let body = replace_mentions(&comment.body, username, pseudonym);
+        let mentions: Vec<String> = mentions
+            .into_iter()
+            .map(|mentioned| {
+                if mentioned == username {
+                    pseudonym.to_string()
+                } else {
+                    mentioned
+                }
+            })
+            .collect();
+        let mut active = comment.into_active_model();
+        active.body = Set(body);
+        active.mentions = Set(serde_json::json!(mentions));
+        active.update(txn).await?;
+        rewritten += 1;
+    }
+    Ok(rewritten)
+}
+
+/// Replace the user in the records of the audit log. Returns the number of
+/// entries rewritten.
+async fn rewrite_audit_log(
+    txn: &DatabaseTransaction,
+    username: &str,
+    replacement: &Value,
+) -> Result<u64, DbErr> {
+    // Narrowed in the database to entries mentioning the name anywhere
+    let quoted = Value::String(username.to_string()).to_string();
+    let pattern = format!("%{}%", &quoted[1..quoted.len() - 1]);
+    let mentioning = [
+        audit_log::Column::Before,
+        audit_log::Column::After,
+        audit_log::Column::Changes,
+    ]
+    .into_iter()
+    .fold(Condition::any(), |condition, column| {
+        condition.add(Expr::col(column).cast_as(Alias::new("text")).like(&pattern))
+    });
+    let entries = audit_log::Entity::find()
+        .filter(mentioning)
+        .all(txn)
+        .await?;
+
+    let mut rewritten = 0;
+    for entry in entries {
+        let rewrite = |json: &Option<Value>| {
+            json.clone().map(|mut json| {
+                replace_user(&mut json, username, replacement, false);
+                json
+            })
+        };
+        let (before, after, changes) = (
+            rewrite(&entry.before),
+            rewrite(&entry.after),
+            rewrite(&entry.changes),
+        );
+        if before == entry.before && after == entry.after && changes == entry.changes {
+            continue;
+        }
+        let mut active = entry.into_active_model();
+        active.before = Set(before);
+        active.after = Set(after);
+        active.changes = Set(changes);
+        active.update(txn).await?;
+        rewritten += 1;
+    }
+    Ok(rewritten)
+}
+
+/// Replace the user in the fields naming users, at any depth, so also in
+/// the before and after of a logged change
+fn replace_user(json: &mut Value, username: &str, replacement: &Value, naming_user: bool) {
+    match json {
+        Value::String(value) if naming_user && value == username => {
+            *json = replacement.clone();
+        }
+        Value::Array(values) => {
+            for value in values {
+                replace_user(value, username, replacement, naming_user);
+            }
+        }
+        Value::Object(fields) => {
+            for (field, value) in fields {
+                let naming_user = naming_user || AUDITED_USER_FIELDS.contains(&field.as_str());
+                replace_user(value, username, replacement, naming_user);
+            }
+        }
+        _ => {}
+    }
+}
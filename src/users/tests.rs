@@ -0,0 +1,471 @@
+use crate::api_keys::models::{ApiKeyCreate, ApiKeyRole, Entity as ApiKeys};
+use crate::api_keys::services::create_api_key;
+use crate::audit::models::{Column as AuditColumn, Entity as AuditLog};
+use crate::common::auth::as_user;
+use crate::config::Config;
+use crate::config::test_helpers::{send_json, setup_test_db};
+use crate::experiments::comments::models::{Entity as ExperimentComments, ExperimentCommentCreate};
+use crate::experiments::comments::services::create_comment;
+use crate::experiments::models::Entity as Experiments;
+use crate::planned_experiments::models::{
+    ActiveModel as PlannedExperimentActive, Entity as PlannedExperiments, PlannedExperimentStatus,
+};
+use crate::projects::members::models::Entity as ProjectMembers;
+use crate::tray_configurations::calibrations::models::{
+    ActiveModel as CalibrationActive, Entity as ProbeCalibrations,
+};
+use crate::versions::models::Entity as RecordVersions;
+use crate::versions::services::save_version;
+use crate::webhooks::models::{Entity as Webhooks, WebhookCreate};
+use crate::webhooks::services::create_webhook;
+use axum::body::{Body, to_bytes};
+use axum::http::{Request, StatusCode};
+use chrono::Utc;
+use rust_decimal::Decimal;
+use sea_orm::{ActiveModelTrait, ActiveValue::Set, ColumnTrait, EntityTrait, QueryFilter};
+use serde_json::{Value, json};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+#[tokio::test]
+#[allow(clippy::too_many_lines)]
+async fn test_user_purge() {
+    let db = setup_test_db().await;
+    let mut config = Config::for_tests();
+    config.keycloak_url = String::new();
+    let app = crate::routes::build_router(&db, &config);
+    let send = |method: &str, uri: String, body: &Value| {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let app = app.clone();
+        async move {
+            let response = app.oneshot(request).await.unwrap();
+            let status = response.status();
+            let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let body = serde_json::from_slice(&bytes)
+                .unwrap_or_else(|_| json!({"error": String::from_utf8_lossy(&bytes)}));
+            (status, body)
+        }
+    };
+
+    // Records of the departing user, and of another
+    let mut experiments = Vec::new();
+    for username in ["alice", "bob", "alice"] {
+        let (status, experiment) = send(
+            "POST",
+            "/api/experiments".to_string(),
+            &json!({
+                "name": format!("Purge experiment {}", Uuid::new_v4()),
+                "username": username,
+                "temperature_ramp": -1.0,
+                "is_calibration": false,
+            }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED, "{experiment}");
+        experiments.push(Uuid::parse_str(experiment["id"].as_str().unwrap()).unwrap());
+    }
+    let (status, project) = send(
+        "POST",
+        "/api/projects".to_string(),
+        &json!({"name": "Purge project"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{project}");
+    let (status, _) = send(
+        "PUT",
+        format!("/api/projects/{}/members", project["id"].as_str().unwrap()),
+        &json!(["alice", "bob"]),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let key = create_api_key(
+        &db,
+        ApiKeyCreate {
+            name: "Alice's laptop".to_string(),
+            username: Some("alice".to_string()),
+            role: ApiKeyRole::Editor,
+            scopes: vec!["experiments".to_string()],
+            rate_limit_per_minute: None,
+            lab: None,
+            expires_at: None,
+        },
+        Some("alice".to_string()),
+    )
+    .await
+    .unwrap();
+
+    // A blank username purges nothing
+    let (status, _) = send(
+        "POST",
+        "/api/users/purge".to_string(),
+        &json!({"username": " ", "mode": "anonymize"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let (status, report) = send(
+        "POST",
+        "/api/users/purge".to_string(),
+        &json!({"username": "alice", "mode": "anonymize"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{report}");
+    let pseudonym = report["pseudonym"].as_str().unwrap().to_string();
+    assert!(pseudonym.starts_with("former-user-"));
+    assert_eq!(report["updated"]["experiments.username"], 2);
+    assert_eq!(report["updated"]["api_keys.username"], 1);
+    assert_eq!(report["updated"]["api_keys.created_by"], 1);
+    assert_eq!(report["removed"]["project_members"], 1);
+    assert_eq!(report["api_keys_revoked"], 1);
+    assert_eq!(report["audit_entries_rewritten"], 2);
+    assert!(!report.to_string().contains("alice"));
+
+    // Records are kept with the pseudonym; others' are untouched
+    let usernames: Vec<Option<String>> = {
+        let mut usernames = Vec::new();
+        for id in &experiments {
+            let experiment = Experiments::find_by_id(*id)
+                .one(&db)
+                .await
+                .unwrap()
+                .unwrap();
+            assert!(experiment.temperature_ramp.is_some());
+            usernames.push(experiment.username);
+        }
+        usernames
+    };
+    assert_eq!(
+        usernames,
+        vec![
+            Some(pseudonym.clone()),
+            Some("bob".to_string()),
+            Some(pseudonym.clone())
+        ]
+    );
+    let key = ApiKeys::find_by_id(key.api_key.id)
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(key.revoked_at.is_some());
+    assert_eq!(key.username, pseudonym);
+    let members: Vec<String> = ProjectMembers::find()
+        .all(&db)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|member| member.username)
+        .collect();
+    assert_eq!(members, vec!["bob".to_string()]);
+
+    // The audit log no longer names the user, and logs the purge without
+    // them
+    for entry in AuditLog::find().all(&db).await.unwrap() {
+        let entry = serde_json::to_string(&entry).unwrap();
+        assert!(!entry.contains("alice"), "{entry}");
+    }
+    let purges = AuditLog::find()
+        .filter(AuditColumn::Resource.eq("users"))
+        .all(&db)
+        .await
+        .unwrap();
+    assert_eq!(purges.len(), 1);
+    assert_eq!(purges[0].path, "/api/users/purge");
+
+    // Removing clears what may be empty
+    let (status, report) = send(
+        "POST",
+        "/api/users/purge".to_string(),
+        &json!({"username": "bob", "mode": "remove"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{report}");
+    assert_eq!(report["updated"]["experiments.username"], 1);
+    let experiment = Experiments::find_by_id(experiments[1])
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(experiment.username, None);
+    let entry = AuditLog::find()
+        .filter(AuditColumn::RecordId.eq(experiments[1]))
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(entry.after.unwrap()["username"], Value::Null);
+}
+
+#[tokio::test]
+async fn test_purge_of_comments() {
+    let db = setup_test_db().await;
+    let mut config = Config::for_tests();
+    config.keycloak_url = String::new();
+    let app = crate::routes::build_router(&db, &config);
+    let (status, experiment) = send_json(
+        &app,
+        "POST",
+        "/api/experiments",
+        Some(&json!({"name": "Commented experiment", "is_calibration": false})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{experiment}");
+    let experiment_id = Uuid::parse_str(experiment["id"].as_str().unwrap()).unwrap();
+    let comment = |username: &str, body: &str| {
+        let input = ExperimentCommentCreate {
+            body: body.to_string(),
+            coordinate: None,
+            parent_id: None,
+        };
+        as_user(
+            username.to_string(),
+            create_comment(&db, experiment_id, input),
+        )
+    };
+    let by_alice = comment("alice", "Freezing looks early, @bob")
+        .await
+        .unwrap();
+    let mentioning = comment("bob", "@alice, @alice.smith and @bob: see P1:A1")
+        .await
+        .unwrap();
+
+    let (status, report) = send_json(
+        &app,
+        "POST",
+        "/api/users/purge",
+        Some(&json!({"username": "alice", "mode": "remove"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{report}");
+    let pseudonym = report["pseudonym"].as_str().unwrap();
+    assert_eq!(report["updated"]["experiment_comments.created_by"], 1);
+    assert_eq!(report["updated"]["experiment_comments.mentions"], 1);
+
+    let by_alice = ExperimentComments::find_by_id(by_alice.id)
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(by_alice.created_by, None);
+    assert_eq!(by_alice.body, "Freezing looks early, @bob");
+    let mentioning = ExperimentComments::find_by_id(mentioning.id)
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(mentioning.created_by.as_deref(), Some("bob"));
+    assert_eq!(
+        mentioning.body,
+        format!("@{pseudonym}, @alice.smith and @bob: see P1:A1")
+    );
+    assert_eq!(
+        mentioning.mention_list(),
+        vec![pseudonym, "alice.smith", "bob"]
+    );
+}
+
+#[tokio::test]
+async fn test_purge_of_plans() {
+    let db = setup_test_db().await;
+    let mut config = Config::for_tests();
+    config.keycloak_url = String::new();
+    let app = crate::routes::build_router(&db, &config);
+    let mut plans = Vec::new();
+    for (created_by, operator) in [("alice", "bob"), ("bob", "alice")] {
+        let plan = PlannedExperimentActive {
+            id: Set(Uuid::new_v4()),
+            name: Set(format!("Plan of {created_by}")),
+            scheduled_for: Set(Utc::now()),
+            operator: Set(Some(operator.to_string())),
+            project_id: Set(None),
+            tray_configuration_id: Set(None),
+            sample_ids: Set(json!([])),
+            notes: Set(None),
+            status: Set(PlannedExperimentStatus::Planned),
+            experiment_id: Set(None),
+            lab: Set(None),
+            created_by: Set(Some(created_by.to_string())),
+            created_at: Set(Utc::now()),
+            last_updated: Set(Utc::now()),
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+        plans.push(plan.id);
+    }
+
+    let (status, report) = send_json(
+        &app,
+        "POST",
+        "/api/users/purge",
+        Some(&json!({"username": "alice", "mode": "anonymize"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{report}");
+    let pseudonym = report["pseudonym"].as_str().unwrap();
+    assert_eq!(report["updated"]["planned_experiments.created_by"], 1);
+    assert_eq!(report["updated"]["planned_experiments.operator"], 1);
+
+    let mut named = Vec::new();
+    for id in plans {
+        let plan = PlannedExperiments::find_by_id(id)
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        named.push((plan.created_by.unwrap(), plan.operator.unwrap()));
+    }
+    assert_eq!(
+        named,
+        vec![
+            (pseudonym.to_string(), "bob".to_string()),
+            ("bob".to_string(), pseudonym.to_string()),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_purge_of_calibrations() {
+    let db = setup_test_db().await;
+    let mut config = Config::for_tests();
+    config.keycloak_url = String::new();
+    let app = crate::routes::build_router(&db, &config);
+    let (status, demo) = send_json(&app, "POST", "/api/maintenance/demo", None).await;
+    assert_eq!(status, StatusCode::CREATED, "{demo}");
+    let (status, probes) = send_json(
+        &app,
+        "GET",
+        &format!(
+            "/api/tray_configurations/{}/probes",
+            demo["tray_configuration_id"].as_str().unwrap()
+        ),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{probes}");
+    let probe_id = Uuid::parse_str(probes[0]["probe_id"].as_str().unwrap()).unwrap();
+    let calibration = CalibrationActive {
+        id: Set(Uuid::new_v4()),
+        probe_id: Set(probe_id),
+        experiment_id: Set(None),
+        calibrated_at: Set(Utc::now()),
+        calibration_slope: Set(Decimal::ONE),
+        calibration_offset: Set(Decimal::ZERO),
+        notes: Set(None),
+        created_by: Set(Some("alice".to_string())),
+        created_at: Set(Utc::now()),
+    }
+    .insert(&db)
+    .await
+    .unwrap();
+
+    let (status, report) = send_json(
+        &app,
+        "POST",
+        "/api/users/purge",
+        Some(&json!({"username": "alice", "mode": "remove"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{report}");
+    assert_eq!(report["updated"]["probe_calibrations.created_by"], 1);
+    let calibration = ProbeCalibrations::find_by_id(calibration.id)
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(calibration.created_by, None);
+    assert_eq!(calibration.calibration_slope, Decimal::ONE);
+}
+
+#[tokio::test]
+async fn test_purge_of_webhooks() {
+    let db = setup_test_db().await;
+    let mut config = Config::for_tests();
+    config.keycloak_url = String::new();
+    let app = crate::routes::build_router(&db, &config);
+    let webhook = create_webhook(
+        &db,
+        WebhookCreate {
+            url: "https://example.org/spice".to_string(),
+            event_types: vec!["*".to_string()],
+            secret: None,
+        },
+        Some("alice".to_string()),
+    )
+    .await
+    .unwrap();
+
+    let (status, report) = send_json(
+        &app,
+        "POST",
+        "/api/users/purge",
+        Some(&json!({"username": "alice", "mode": "anonymize"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{report}");
+    assert_eq!(report["updated"]["webhooks.created_by"], 1);
+    let webhook = Webhooks::find_by_id(webhook.webhook.id)
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(webhook.created_by.as_deref(), report["pseudonym"].as_str());
+    assert!(webhook.active);
+}
+
+#[tokio::test]
+async fn test_purge_of_record_versions() {
+    let db = setup_test_db().await;
+    let mut config = Config::for_tests();
+    config.keycloak_url = String::new();
+    let app = crate::routes::build_router(&db, &config);
+    let record_id = Uuid::new_v4();
+    let snapshot = |name: &str| json!({"name": name, "username": "alice", "created_by": "bob", "notes": "alice"});
+    let first = as_user(
+        "alice".to_string(),
+        save_version(&db, "experiments", record_id, snapshot("First")),
+    )
+    .await
+    .unwrap();
+    let second = as_user(
+        "bob".to_string(),
+        save_version(&db, "experiments", record_id, snapshot("Second")),
+    )
+    .await
+    .unwrap();
+
+    let (status, report) = send_json(
+        &app,
+        "POST",
+        "/api/users/purge",
+        Some(&json!({"username": "alice", "mode": "remove"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{report}");
+    assert_eq!(report["updated"]["record_versions.username"], 1);
+    assert_eq!(report["updated"]["record_versions.snapshot"], 2);
+
+    let first = RecordVersions::find_by_id(first.id)
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(first.username, None);
+    // Only the fields naming users are cleared
+    assert_eq!(
+        first.snapshot,
+        json!({"name": "First", "username": null, "created_by": "bob", "notes": "alice"})
+    );
+    let second = RecordVersions::find_by_id(second.id)
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(second.username.as_deref(), Some("bob"));
+    assert_eq!(second.snapshot["username"], Value::Null);
+}
@@ -0,0 +1,95 @@
+use super::models::{PurgeReport, UserPurge};
+use super::services::purge_user;
+use crate::audit::models::AuditAction;
+use crate::audit::services::{AuditedChange, AuditedResource, REQUEST_ID_HEADER, record_change};
+use crate::common::auth::{Role, RouteAccess, require_role};
+use crate::common::keycloak::authenticate;
+use crate::common::state::AppState;
+use axum::{
+    Extension, Json,
+    extract::{OriginalUri, State},
+    http::{HeaderMap, Method, StatusCode},
+    middleware,
+    routing::post,
+};
+use axum_keycloak_auth::{PassthroughMode, decode::KeycloakToken};
+use sea_orm::DbErr;
+use utoipa::OpenApi;
+use utoipa_axum::router::OpenApiRouter;
+
+/// Purge a departed user's identifiers
+#[utoipa::path(
+    post,
+    path = "/purge",
+    request_body = UserPurge,
+    responses(
+        (status = 200, description = "What was changed, by table and field", body = PurgeReport),
+        (status = 422, description = "Blank username"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "users",
+    summary = "Purge a departed user",
+    description = "Anonymize or remove the username of a departed user wherever records name them: experiments, samples and their custody events, dilutions, assets, projects, share grants, download tokens, API keys and the audit log. Records and their scientific data are kept. The user's project memberships and share grants are removed and their API keys revoked. The username is sent in the body so that it is not logged again"
+)]
+pub async fn post_user_purge(
+    State(state): State<AppState>,
+    token: Option<Extension<KeycloakToken<Role>>>,
+    OriginalUri(uri): OriginalUri,
+    headers: HeaderMap,
+    Json(purge): Json<UserPurge>,
+) -> Result<Json<PurgeReport>, (StatusCode, String)> {
+    let report = purge_user(&state.db, purge).await.map_err(|e| match e {
+        DbErr::Custom(message) => (StatusCode::UNPROCESSABLE_ENTITY, message),
+        e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    })?;
+
+    // Logged by who purged, without whom
+    let change = AuditedChange {
+        username: token.map(|Extension(token)| token.extra.profile.preferred_username),
+        action: AuditAction::Update,
+        resource: AuditedResource::Users,
+        record_id: None,
+        method: Method::POST.to_string(),
+        path: uri.path().to_string(),
+        status: StatusCode::OK,
+        request_id: headers
+            .get(REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string),
+        before: None,
+        after: None,
+    };
+    if let Err(e) = record_change(&state.db, change).await {
+        tracing::error!("Could not write to the audit log: {e}");
+    }
+    Ok(Json(report))
+}
+
+/// Documentation of the routes, which are routed with `route`
+#[derive(OpenApi)]
+#[openapi(paths(post_user_purge))]
+struct UsersApi;
+
+pub fn router(state: &AppState) -> OpenApiRouter {
+    let mut router = OpenApiRouter::new()
+        .route("/purge", post(post_user_purge))
+        .with_state(state.clone());
+    router.get_openapi_mut().merge(UsersApi::openapi());
+
+    // Users are purged by signed-in administrators, not with keys
+    if let Some(instance) = state.keycloak_auth_instance.clone() {
+        router = router
+            .layer(middleware::from_fn_with_state(
+                RouteAccess::ADMINISTRATION,
+                require_role,
+            ))
+            .layer(middleware::from_fn_with_state(
+                (instance, PassthroughMode::Block),
+                authenticate,
+            ));
+    } else if !state.config.tests_running {
+        println!("Warning: User routes are not protected");
+    }
+
+    router
+}
@@ -0,0 +1,33 @@
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// A record as it was before one of its updates
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, ToSchema)]
+#[sea_orm(table_name = "record_versions")]
+#[schema(as = RecordVersion)]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    /// Route group of the record, such as `experiments`
+    #[sea_orm(column_type = "Text")]
+    pub resource: String,
+    pub record_id: Uuid,
+    /// Number of the version, from 1 for the record as it was created
+    pub version: i32,
+    /// The record as the API showed it
+    #[sea_orm(column_type = "JsonBinary")]
+    pub snapshot: Json,
+    /// User whose update replaced this version
+    #[sea_orm(column_type = "Text", nullable)]
+    pub username: Option<String>,
+    /// When this version was replaced
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
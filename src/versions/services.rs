@@ -0,0 +1,213 @@
+//! Version history of records.
+//!
+//! Every successful update of an experiment, sample or tray configuration
+//! first keeps the record as it was, with the relations its update sets, as
+//! the record's next version. Versions can be compared with the record as it
+//! is now, and the record reverted to one of them. A revert is an update
+//! like any other, so the version it replaces is kept too.
+
+use super::models::{ActiveModel, Column, Entity, Model};
+use crate::audit::services::diff;
+use crate::common::auth::current_username;
+use crate::common::include::including;
+use crate::projects::access::segments;
+use axum::{
+    extract::{Request, State},
+    http::Method,
+    middleware::Next,
+    response::Response,
+};
+use chrono::{DateTime, Utc};
+use crudcrate::CRUDResource;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, DatabaseConnection, DbErr, EntityTrait,
+    QueryFilter, QueryOrder, QuerySelect,
+};
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::Value;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Relations kept in versions: those updates set, such as the regions of an
+/// experiment. Results and other derived relations are left out.
+pub const VERSIONED_RELATIONS: &[&str] = &["regions"];
+
+/// A version, without the record
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct RecordVersionSummary {
+    pub version: i32,
+    /// User whose update replaced this version
+    pub username: Option<String>,
+    /// When this version was replaced
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<Model> for RecordVersionSummary {
+    fn from(model: Model) -> Self {
+        Self {
+            version: model.version,
+            username: model.username,
+            created_at: model.created_at,
+        }
+    }
+}
+
+/// A version with the fields in which the record now differs from it
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct RecordVersionDiff {
+    #[serde(flatten)]
+    pub version: Model,
+    /// Fields that differ, each with its value in this version (`before`) and
+    /// now (`after`)
+    pub changes: Value,
+}
+
+/// The record as versions keep it, or `None` when there is no such record
+pub async fn snapshot<R>(db: &DatabaseConnection, id: Uuid) -> Result<Option<Value>, DbErr>
+where
+    R: CRUDResource + Serialize,
+{
+    match including(VERSIONED_RELATIONS, R::get_one(db, id)).await {
+        Ok(record) => serde_json::to_value(record)
+            .map(Some)
+            .map_err(|e| DbErr::Custom(e.to_string())),
+        Err(DbErr::RecordNotFound(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Keep a version of a record, numbered after its last one
+pub async fn save_version(
+    db: &DatabaseConnection,
+    resource: &str,
+    record_id: Uuid,
+    snapshot: Value,
+) -> Result<Model, DbErr> {
+    let last: Option<i32> = Entity::find()
+        .select_only()
+        .column_as(Column::Version.max(), "version")
+        .filter(Column::Resource.eq(resource))
+        .filter(Column::RecordId.eq(record_id))
+        .into_tuple()
+        .one(db)
+        .await?
+        .flatten();
+    ActiveModel {
+        id: Set(Uuid::new_v4()),
+        resource: Set(resource.to_string()),
+        record_id: Set(record_id),
+        version: Set(last.unwrap_or(0) + 1),
+        snapshot: Set(snapshot),
+        username: Set(current_username()),
+        created_at: Set(Utc::now()),
+    }
+    .insert(db)
+    .await
+}
+
+/// Versions of a record, newest first
+pub async fn list_versions(
+    db: &DatabaseConnection,
+    resource: &str,
+    record_id: Uuid,
+) -> Result<Vec<RecordVersionSummary>, DbErr> {
+    Ok(Entity::find()
+        .filter(Column::Resource.eq(resource))
+        .filter(Column::RecordId.eq(record_id))
+        .order_by_desc(Column::Version)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(RecordVersionSummary::from)
+        .collect())
+}
+
+async fn find_version(
+    db: &DatabaseConnection,
+    resource: &str,
+    record_id: Uuid,
+    version: i32,
+) -> Result<Model, DbErr> {
+    Entity::find()
+        .filter(Column::Resource.eq(resource))
+        .filter(Column::RecordId.eq(record_id))
+        .filter(Column::Version.eq(version))
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound(format!("Version {version} not found")))
+}
+
+/// A version of a record, compared with the record now
+pub async fn version_diff<R>(
+    db: &DatabaseConnection,
+    record_id: Uuid,
+    version: i32,
+) -> Result<RecordVersionDiff, DbErr>
+where
+    R: CRUDResource + Serialize,
+{
+    let version = find_version(db, R::RESOURCE_NAME_PLURAL, record_id, version).await?;
+    let current = snapshot::<R>(db, record_id).await?;
+    let changes = diff(Some(&version.snapshot), current.as_ref()).unwrap_or_default();
+    Ok(RecordVersionDiff { version, changes })
+}
+
+/// Update a record back to a version, keeping the version it replaces
+pub async fn revert_to_version<R>(
+    db: &DatabaseConnection,
+    record_id: Uuid,
+    version: i32,
+) -> Result<R, DbErr>
+where
+    R: CRUDResource + Serialize,
+    R::UpdateModel: DeserializeOwned,
+{
+    let resource = R::RESOURCE_NAME_PLURAL;
+    let version = find_version(db, resource, record_id, version).await?;
+    let current = snapshot::<R>(db, record_id)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound(format!("{} not found", R::RESOURCE_NAME_SINGULAR)))?;
+    let update: R::UpdateModel = serde_json::from_value(version.snapshot).map_err(|e| {
+        DbErr::Custom(format!(
+            "Version {} no longer fits the record: {e}",
+            version.version
+        ))
+    })?;
+    let reverted = R::update(db, record_id, update).await?;
+    save_version(db, resource, record_id, current).await?;
+    Ok(reverted)
+}
+
+/// Middleware of a resource's router, innermost, keeping the version each
+/// successful `PUT` or `PATCH` of a record replaces
+pub async fn keep_versions<R>(
+    State(db): State<DatabaseConnection>,
+    request: Request,
+    next: Next,
+) -> Response
+where
+    R: CRUDResource + Serialize,
+{
+    let id = match segments(request.uri().path()).as_slice() {
+        [id] if matches!(*request.method(), Method::PUT | Method::PATCH) => {
+            Uuid::parse_str(id).ok()
+        }
+        _ => None,
+    };
+    let Some(id) = id else {
+        return next.run(request).await;
+    };
+    // A record that cannot be read is left for the update to answer
+    let before = snapshot::<R>(&db, id).await.ok().flatten();
+    let response = next.run(request).await;
+    if response.status().is_success()
+        && let Some(before) = before
+        && let Err(e) = save_version(&db, R::RESOURCE_NAME_PLURAL, id, before).await
+    {
+        tracing::error!(
+            "Failed to keep a version of {} {id}: {e}",
+            R::RESOURCE_NAME_SINGULAR
+        );
+    }
+    response
+}
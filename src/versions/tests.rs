@@ -0,0 +1,59 @@
+use crate::config::test_helpers::{send_json, setup_test_app};
+use axum::http::StatusCode;
+use serde_json::json;
+
+#[tokio::test]
+async fn test_experiment_versions() {
+    let app = setup_test_app().await;
+    let (status, experiment) = send_json(
+        &app,
+        "POST",
+        "/api/experiments",
+        Some(&json!({
+            "name": "Versioned experiment",
+            "performed_at": "2024-06-20T14:30:00Z",
+            "remarks": "Original",
+            "is_calibration": false
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{experiment}");
+    let uri = format!("/api/experiments/{}", experiment["id"].as_str().unwrap());
+
+    for remarks in ["First edit", "Second edit"] {
+        let (status, _) = send_json(&app, "PATCH", &uri, Some(&json!({"remarks": remarks}))).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+    // Failed updates keep nothing
+    let (status, _) = send_json(&app, "PATCH", &uri, Some(&json!({"is_calibration": "no"}))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let (status, versions) = send_json(&app, "GET", &format!("{uri}/versions"), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let numbers: Vec<i64> = versions
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|version| version["version"].as_i64().unwrap())
+        .collect();
+    assert_eq!(numbers, vec![2, 1]);
+
+    let (status, version) = send_json(&app, "GET", &format!("{uri}/versions/1"), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(version["snapshot"]["remarks"], "Original");
+    assert_eq!(
+        version["changes"]["remarks"],
+        json!({"before": "Original", "after": "Second edit"})
+    );
+    assert!(version["changes"].get("name").is_none());
+    let (status, _) = send_json(&app, "GET", &format!("{uri}/versions/9"), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Reverting is an update, keeping the version it replaces
+    let (status, reverted) =
+        send_json(&app, "POST", &format!("{uri}/versions/1/revert"), None).await;
+    assert_eq!(status, StatusCode::OK, "{reverted}");
+    assert_eq!(reverted["remarks"], "Original");
+    let (_, version) = send_json(&app, "GET", &format!("{uri}/versions/3"), None).await;
+    assert_eq!(version["snapshot"]["remarks"], "Second edit");
+}
@@ -0,0 +1,75 @@
+use super::services::{
+    RecordVersionDiff, RecordVersionSummary, list_versions, revert_to_version, version_diff,
+};
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+    routing::{get, post},
+};
+use crudcrate::CRUDResource;
+use sea_orm::{DatabaseConnection, DbErr};
+use serde::{Serialize, de::DeserializeOwned};
+use utoipa_axum::router::OpenApiRouter;
+use uuid::Uuid;
+
+fn version_error(e: DbErr) -> (StatusCode, String) {
+    match e {
+        DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+        DbErr::Custom(message) => (StatusCode::UNPROCESSABLE_ENTITY, message),
+        e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+/// Versions of a record, newest first
+pub async fn get_versions<R: CRUDResource>(
+    State(db): State<DatabaseConnection>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<RecordVersionSummary>>, (StatusCode, String)> {
+    list_versions(&db, R::RESOURCE_NAME_PLURAL, id)
+        .await
+        .map(Json)
+        .map_err(version_error)
+}
+
+/// A version of a record, with the fields in which the record now differs
+pub async fn get_version<R>(
+    State(db): State<DatabaseConnection>,
+    Path((id, version)): Path<(Uuid, i32)>,
+) -> Result<Json<RecordVersionDiff>, (StatusCode, String)>
+where
+    R: CRUDResource + Serialize,
+{
+    version_diff::<R>(&db, id, version)
+        .await
+        .map(Json)
+        .map_err(version_error)
+}
+
+/// Update a record back to one of its versions
+pub async fn post_revert<R>(
+    State(db): State<DatabaseConnection>,
+    Path((id, version)): Path<(Uuid, i32)>,
+) -> Result<Json<R>, (StatusCode, String)>
+where
+    R: CRUDResource + Serialize,
+    R::UpdateModel: DeserializeOwned,
+{
+    revert_to_version::<R>(&db, id, version)
+        .await
+        .map(Json)
+        .map_err(version_error)
+}
+
+/// Routes of a resource's versions, to merge into its router
+pub fn router<R>(db: &DatabaseConnection) -> OpenApiRouter
+where
+    R: CRUDResource + Serialize + 'static,
+    R::UpdateModel: DeserializeOwned,
+{
+    OpenApiRouter::new()
+        .route("/{id}/versions", get(get_versions::<R>))
+        .route("/{id}/versions/{version}", get(get_version::<R>))
+        .route("/{id}/versions/{version}/revert", post(post_revert::<R>))
+        .with_state(db.clone())
+}
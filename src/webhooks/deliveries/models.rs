@@ -0,0 +1,64 @@
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, ToSchema, Serialize, Deserialize,
+)]
+#[sea_orm(rs_type = "String", db_type = "Text")]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    /// Waiting for its next attempt
+    #[sea_orm(string_value = "pending")]
+    Pending,
+    #[sea_orm(string_value = "delivered")]
+    Delivered,
+    /// Given up on after its last attempt
+    #[sea_orm(string_value = "failed")]
+    Failed,
+}
+
+/// An event sent, or to be sent, to a webhook
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, ToSchema)]
+#[sea_orm(table_name = "webhook_deliveries")]
+#[schema(as = WebhookDelivery)]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    pub webhook_id: Uuid,
+    #[sea_orm(column_type = "Text")]
+    pub event_type: String,
+    /// Body posted to the URL
+    #[sea_orm(column_type = "JsonBinary")]
+    pub payload: Json,
+    pub status: DeliveryStatus,
+    pub attempts: i32,
+    /// Status the URL answered the last attempt with
+    pub last_status_code: Option<i32>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub last_error: Option<String>,
+    /// When a pending delivery is tried next
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "crate::webhooks::models::Entity",
+        from = "Column::WebhookId",
+        to = "crate::webhooks::models::Column::Id"
+    )]
+    Webhooks,
+}
+
+impl Related<crate::webhooks::models::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Webhooks.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,7 @@
+pub mod deliveries;
+pub mod models;
+pub mod services;
+pub mod views;
+
+#[cfg(test)]
+pub mod tests;
@@ -0,0 +1,97 @@
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// A URL subscribed to events. The secret signs what is sent to it, so it
+/// is kept as given.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "webhooks")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    #[sea_orm(column_type = "Text")]
+    pub url: String,
+    #[sea_orm(column_type = "Text")]
+    pub secret: String,
+    /// Event types sent to the URL, `*` for all of them
+    #[sea_orm(column_type = "JsonBinary")]
+    pub event_types: Json,
+    pub active: bool,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub created_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(has_many = "super::deliveries::models::Entity")]
+    Deliveries,
+}
+
+impl Related<super::deliveries::models::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Deliveries.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    pub fn event_type_list(&self) -> Vec<String> {
+        serde_json::from_value(self.event_types.clone()).unwrap_or_default()
+    }
+
+    /// Whether events of a type are sent to the URL
+    pub fn subscribes_to(&self, event_type: &str) -> bool {
+        self.event_type_list()
+            .iter()
+            .any(|subscribed| subscribed == "*" || subscribed == event_type)
+    }
+}
+
+/// A webhook as shown to administrators, without its secret
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct Webhook {
+    pub id: Uuid,
+    pub url: String,
+    pub event_types: Vec<String>,
+    pub active: bool,
+    pub created_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<Model> for Webhook {
+    fn from(model: Model) -> Self {
+        Self {
+            event_types: model.event_type_list(),
+            id: model.id,
+            url: model.url,
+            active: model.active,
+            created_by: model.created_by,
+            created_at: model.created_at,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, ToSchema)]
+pub struct WebhookCreate {
+    /// HTTP or HTTPS URL events are posted to
+    pub url: String,
+    /// Event types to send, such as `["experiment.processed",
+    /// "asset.uploaded"]`, or `["*"]` for all of them
+    pub event_types: Vec<String>,
+    /// Secret deliveries are signed with, at least 16 characters; made up
+    /// when omitted
+    pub secret: Option<String>,
+}
+
+/// A new webhook, the only time its secret is shown
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct CreatedWebhook {
+    /// Key of the HMAC-SHA256 in `X-Spice-Signature`
+    pub secret: String,
+    #[serde(flatten)]
+    pub webhook: Webhook,
+}
@@ -0,0 +1,405 @@
+//! Webhooks for processing and data events.
+//!
+//! Downstream pipelines subscribe a URL to event types instead of polling.
+//! Each event is kept as a delivery to every active webhook subscribed to
+//! its type and posted to the URL as JSON, signed with the webhook's secret:
+//! `X-Spice-Signature` holds `sha256=` and the hex HMAC-SHA256 of the
+//! `X-Spice-Timestamp`, a dot and the body. A delivery the URL does not
+//! answer with 2xx is tried again after growing waits, up to `MAX_ATTEMPTS`
+//! times, then marked failed; it can still be sent again by hand.
+
+use super::deliveries::models::{
+    ActiveModel as DeliveryActiveModel, Column as DeliveryColumn, DeliveryStatus,
+    Entity as DeliveryEntity, Model as Delivery,
+};
+use super::models::{ActiveModel, Column, CreatedWebhook, Entity, Model, Webhook, WebhookCreate};
+use crate::audit::models::AuditAction;
+use crate::audit::services::AuditedResource;
+use crate::common::state::AppState;
+use chrono::{Duration, Utc};
+use openssl::{hash::MessageDigest, pkey::PKey, sign::Signer};
+use rand::Rng;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, DatabaseConnection, DbErr, EntityTrait,
+    IntoActiveModel, QueryFilter, QueryOrder, QuerySelect,
+};
+use serde_json::{Value, json};
+use std::fmt::Write;
+use uuid::Uuid;
+
+pub const SIGNATURE_HEADER: &str = "x-spice-signature";
+pub const TIMESTAMP_HEADER: &str = "x-spice-timestamp";
+pub const EVENT_HEADER: &str = "x-spice-event";
+pub const DELIVERY_HEADER: &str = "x-spice-delivery";
+
+/// Attempts made at a delivery before it is marked failed
+pub const MAX_ATTEMPTS: i32 = 6;
+/// Wait before the second attempt, doubled before each one after
+const RETRY_DELAY_SECONDS: i64 = 30;
+/// How often deliveries due for another attempt are looked for
+const RETRY_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+/// Longest a URL is waited for
+const REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+/// Time an attempt in progress is left to finish before the delivery is
+/// looked at again
+const ATTEMPT_WINDOW_SECONDS: i64 = 60;
+const MIN_SECRET_LENGTH: usize = 16;
+const SECRET_START: &str = "whsec_";
+
+/// Events of processing
+pub const PROCESSING_EVENTS: &[&str] = &["experiment.processed", "asset.uploaded"];
+/// What happens to records, each an event of every kind of record
+const RECORD_ACTIONS: &[&str] = &["created", "updated", "deleted"];
+
+/// Kind of record events are sent for, as in event types such as
+/// `sample.updated`
+fn record_kind(resource: AuditedResource) -> Option<&'static str> {
+    match resource {
+        AuditedResource::Assets => Some("asset"),
+        AuditedResource::Dilutions => Some("dilution"),
+        AuditedResource::Experiments => Some("experiment"),
+        AuditedResource::Locations => Some("location"),
+        AuditedResource::Projects => Some("project"),
+        AuditedResource::Samples => Some("sample"),
+        AuditedResource::TrayConfigurations => Some("tray_configuration"),
+        AuditedResource::Treatments => Some("treatment"),
+        AuditedResource::ApiKeys | AuditedResource::Users | AuditedResource::Webhooks => None,
+    }
+}
+
+/// Every event type a webhook can subscribe to
+pub fn event_types() -> Vec<String> {
+    let kinds = [
+        AuditedResource::Assets,
+        AuditedResource::Dilutions,
+        AuditedResource::Experiments,
+        AuditedResource::Locations,
+        AuditedResource::Projects,
+        AuditedResource::Samples,
+        AuditedResource::TrayConfigurations,
+        AuditedResource::Treatments,
+    ]
+    .into_iter()
+    .filter_map(record_kind);
+    let mut types: Vec<String> = PROCESSING_EVENTS.iter().map(ToString::to_string).collect();
+    for kind in kinds {
+        types.extend(
+            RECORD_ACTIONS
+                .iter()
+                .map(|action| format!("{kind}.{action}")),
+        );
+    }
+    types
+}
+
+/// Event type of a change to a record, if events are sent for its kind
+pub fn record_event(resource: AuditedResource, action: AuditAction) -> Option<String> {
+    let action = match action {
+        AuditAction::Create => "created",
+        AuditAction::Update => "updated",
+        AuditAction::Delete => "deleted",
+    };
+    record_kind(resource).map(|kind| format!("{kind}.{action}"))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    })
+}
+
+/// Signature of a body sent at a time, as given in `X-Spice-Signature`
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> Result<String, String> {
+    let sign = || {
+        let key = PKey::hmac(secret.as_bytes())?;
+        let mut signer = Signer::new(MessageDigest::sha256(), &key)?;
+        signer.update(timestamp.to_string().as_bytes())?;
+        signer.update(b".")?;
+        signer.update(body)?;
+        signer.sign_to_vec()
+    };
+    sign()
+        .map(|signature| format!("sha256={}", hex(&signature)))
+        .map_err(|e| format!("Failed to sign the delivery: {e}"))
+}
+
+/// Wait before the attempt after a number of failed ones
+fn retry_delay(attempts: i32) -> Duration {
+    let doublings = u32::try_from(attempts.saturating_sub(1))
+        .unwrap_or(0)
+        .min(16);
+    Duration::seconds(RETRY_DELAY_SECONDS * 2_i64.pow(doublings))
+}
+
+/// Create a webhook. Invalid input is returned as `DbErr::Custom`.
+pub async fn create_webhook(
+    db: &DatabaseConnection,
+    input: WebhookCreate,
+    created_by: Option<String>,
+) -> Result<CreatedWebhook, DbErr> {
+    let url = input.url.trim().to_string();
+    match reqwest::Url::parse(&url) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => {}
+        _ => {
+            return Err(DbErr::Custom(format!(
+                "'{url}' is not an HTTP or HTTPS URL"
+            )));
+        }
+    }
+
+    let mut subscribed = input.event_types;
+    subscribed.sort();
+    subscribed.dedup();
+    if subscribed.is_empty() {
+        return Err(DbErr::Custom("event_types must not be empty".to_string()));
+    }
+    let known = event_types();
+    if let Some(unknown) = subscribed
+        .iter()
+        .find(|event_type| *event_type != "*" && !known.contains(event_type))
+    {
+        return Err(DbErr::Custom(format!(
+            "Unknown event type '{unknown}'; event types are {}",
+            known.join(", ")
+        )));
+    }
+
+    let secret = match input.secret {
+        Some(secret) if secret.len() < MIN_SECRET_LENGTH => {
+            return Err(DbErr::Custom(format!(
+                "secret must be at least {MIN_SECRET_LENGTH} characters"
+            )));
+        }
+        Some(secret) => secret,
+        None => {
+            let bytes: [u8; 24] = rand::rng().random();
+            format!("{SECRET_START}{}", hex(&bytes))
+        }
+    };
+
+    let webhook = ActiveModel {
+        id: Set(Uuid::new_v4()),
+        url: Set(url),
+        secret: Set(secret.clone()),
+        event_types: Set(json!(subscribed)),
+        active: Set(true),
+        created_by: Set(created_by),
+        created_at: Set(Utc::now()),
+    }
+    .insert(db)
+    .await?;
+    Ok(CreatedWebhook {
+        secret,
+        webhook: webhook.into(),
+    })
+}
+
+/// Webhooks, newest first
+pub async fn list_webhooks(db: &DatabaseConnection) -> Result<Vec<Webhook>, DbErr> {
+    Ok(Entity::find()
+        .order_by_desc(Column::CreatedAt)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(Webhook::from)
+        .collect())
+}
+
+async fn find_webhook(db: &DatabaseConnection, id: Uuid) -> Result<Model, DbErr> {
+    Entity::find_by_id(id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Webhook not found".to_string()))
+}
+
+pub async fn get_webhook(db: &DatabaseConnection, id: Uuid) -> Result<Webhook, DbErr> {
+    find_webhook(db, id).await.map(Webhook::from)
+}
+
+/// Delete a webhook with its deliveries
+pub async fn delete_webhook(db: &DatabaseConnection, id: Uuid) -> Result<Webhook, DbErr> {
+    let webhook = find_webhook(db, id).await?;
+    Entity::delete_by_id(id).exec(db).await?;
+    Ok(webhook.into())
+}
+
+/// Deliveries of a webhook, newest first
+pub async fn list_deliveries(
+    db: &DatabaseConnection,
+    webhook_id: Uuid,
+    limit: u64,
+) -> Result<Vec<Delivery>, DbErr> {
+    find_webhook(db, webhook_id).await?;
+    DeliveryEntity::find()
+        .filter(DeliveryColumn::WebhookId.eq(webhook_id))
+        .order_by_desc(DeliveryColumn::CreatedAt)
+        .limit(limit)
+        .all(db)
+        .await
+}
+
+/// Send an event to the webhooks subscribed to its type, in the background
+pub async fn emit(db: &DatabaseConnection, event_type: &str, data: Value) {
+    let webhooks = match Entity::find().filter(Column::Active.eq(true)).all(db).await {
+        Ok(webhooks) => webhooks,
+        Err(e) => {
+            tracing::error!("Could not find the webhooks of a {event_type} event: {e}");
+            return;
+        }
+    };
+    let now = Utc::now();
+    for webhook in webhooks
+        .into_iter()
+        .filter(|webhook| webhook.subscribes_to(event_type))
+    {
+        let id = Uuid::new_v4();
+        let payload = json!({
+            "id": id,
+            "event": event_type,
+            "occurred_at": now,
+            "data": data,
+        });
+        let delivery = DeliveryActiveModel {
+            id: Set(id),
+            webhook_id: Set(webhook.id),
+            event_type: Set(event_type.to_string()),
+            payload: Set(payload),
+            status: Set(DeliveryStatus::Pending),
+            attempts: Set(0),
+            last_status_code: Set(None),
+            last_error: Set(None),
+            // The first attempt is made now; retries pick the delivery up
+            // only if it never finishes
+            next_attempt_at: Set(Some(now + Duration::seconds(ATTEMPT_WINDOW_SECONDS))),
+            delivered_at: Set(None),
+            created_at: Set(now),
+        }
+        .insert(db)
+        .await;
+        match delivery {
+            Ok(delivery) => spawn_attempt(db, delivery),
+            Err(e) => tracing::error!("Could not keep a {event_type} delivery: {e}"),
+        }
+    }
+}
+
+fn spawn_attempt(db: &DatabaseConnection, delivery: Delivery) {
+    let db = db.clone();
+    tokio::spawn(async move {
+        if let Err(e) = attempt_delivery(&db, delivery).await {
+            tracing::error!("Could not record a webhook delivery attempt: {e}");
+        }
+    });
+}
+
+/// Post a delivery to its webhook's URL, recording how it went
+pub async fn attempt_delivery(
+    db: &DatabaseConnection,
+    delivery: Delivery,
+) -> Result<Delivery, DbErr> {
+    let webhook = find_webhook(db, delivery.webhook_id).await?;
+    let body = serde_json::to_vec(&delivery.payload).map_err(|e| DbErr::Custom(e.to_string()))?;
+    let timestamp = Utc::now().timestamp();
+    let outcome = match sign(&webhook.secret, timestamp, &body) {
+        Ok(signature) => reqwest::Client::new()
+            .post(&webhook.url)
+            .timeout(REQUEST_TIMEOUT)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(SIGNATURE_HEADER, signature)
+            .header(TIMESTAMP_HEADER, timestamp)
+            .header(EVENT_HEADER, &delivery.event_type)
+            .header(DELIVERY_HEADER, delivery.id.to_string())
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| (None, format!("Failed to reach the webhook: {e}")))
+            .and_then(|response| {
+                let status = response.status();
+                if status.is_success() {
+                    Ok(status)
+                } else {
+                    Err((Some(status), format!("The webhook answered {status}")))
+                }
+            }),
+        Err(e) => Err((None, e)),
+    };
+
+    let now = Utc::now();
+    let attempts = delivery.attempts + 1;
+    let mut delivery = delivery.into_active_model();
+    delivery.attempts = Set(attempts);
+    match outcome {
+        Ok(status) => {
+            delivery.status = Set(DeliveryStatus::Delivered);
+            delivery.last_status_code = Set(Some(i32::from(status.as_u16())));
+            delivery.last_error = Set(None);
+            delivery.next_attempt_at = Set(None);
+            delivery.delivered_at = Set(Some(now));
+        }
+        Err((status, error)) => {
+            delivery.last_status_code = Set(status.map(|status| i32::from(status.as_u16())));
+            delivery.last_error = Set(Some(error));
+            if attempts >= MAX_ATTEMPTS {
+                delivery.status = Set(DeliveryStatus::Failed);
+                delivery.next_attempt_at = Set(None);
+            } else {
+                delivery.status = Set(DeliveryStatus::Pending);
+                delivery.next_attempt_at = Set(Some(now + retry_delay(attempts)));
+            }
+        }
+    }
+    delivery.update(db).await
+}
+
+/// Attempt the pending deliveries that are due, giving how many were
+pub async fn deliver_due(db: &DatabaseConnection) -> Result<usize, DbErr> {
+    let now = Utc::now();
+    let due = DeliveryEntity::find()
+        .filter(DeliveryColumn::Status.eq(DeliveryStatus::Pending))
+        .filter(DeliveryColumn::NextAttemptAt.lte(now))
+        .order_by_asc(DeliveryColumn::NextAttemptAt)
+        .all(db)
+        .await?;
+    let count = due.len();
+    for delivery in due {
+        attempt_delivery(db, delivery).await?;
+    }
+    Ok(count)
+}
+
+/// Send a delivery again now, whatever became of it
+pub async fn redeliver(
+    db: &DatabaseConnection,
+    webhook_id: Uuid,
+    delivery_id: Uuid,
+) -> Result<Delivery, DbErr> {
+    let delivery = DeliveryEntity::find_by_id(delivery_id)
+        .filter(DeliveryColumn::WebhookId.eq(webhook_id))
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Delivery not found".to_string()))?;
+    let mut delivery = delivery.into_active_model();
+    delivery.status = Set(DeliveryStatus::Pending);
+    delivery.next_attempt_at = Set(Some(Utc::now() + Duration::seconds(ATTEMPT_WINDOW_SECONDS)));
+    let delivery = delivery.update(db).await?;
+    attempt_delivery(db, delivery).await
+}
+
+/// Retry failed deliveries in the background while the server runs
+pub fn schedule_retries(state: &AppState) {
+    if state.config.tests_running {
+        return;
+    }
+    let db = state.db.clone();
+    tokio::spawn(async move {
+        let mut interval =
+            tokio::time::interval_at(tokio::time::Instant::now() + RETRY_INTERVAL, RETRY_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = deliver_due(&db).await {
+                tracing::error!("Could not retry webhook deliveries: {e}");
+            }
+        }
+    });
+}
@@ -0,0 +1,213 @@
+use super::deliveries::models::{ActiveModel as DeliveryActiveModel, Entity as Deliveries};
+use super::services::{
+    DELIVERY_HEADER, EVENT_HEADER, SIGNATURE_HEADER, TIMESTAMP_HEADER, deliver_due, sign,
+};
+use crate::config::test_helpers::send_json;
+use crate::config::{
+    Config,
+    test_helpers::{setup_test_app, setup_test_db},
+};
+use axum::body::Bytes;
+use axum::http::{HeaderMap, StatusCode};
+use axum::{Router, extract::State, routing::post};
+use chrono::{Duration, Utc};
+use sea_orm::{ActiveModelTrait, ActiveValue::Set, EntityTrait, IntoActiveModel};
+use serde_json::{Value, json};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+/// Requests a receiver got, and the status it answers with
+#[derive(Clone, Default)]
+struct Receiver {
+    received: Arc<Mutex<Vec<(HeaderMap, Bytes)>>>,
+    status: Arc<AtomicU16>,
+}
+
+async fn receive(State(receiver): State<Receiver>, headers: HeaderMap, body: Bytes) -> StatusCode {
+    receiver.received.lock().unwrap().push((headers, body));
+    StatusCode::from_u16(receiver.status.load(Ordering::SeqCst)).unwrap()
+}
+
+/// A receiver answering 200, listening on a local port
+async fn start_receiver() -> (Receiver, SocketAddr) {
+    let receiver = Receiver::default();
+    receiver.status.store(200, Ordering::SeqCst);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let receiving = Router::new()
+        .route("/hook", post(receive))
+        .with_state(receiver.clone());
+    tokio::spawn(async move { axum::serve(listener, receiving).await.unwrap() });
+    (receiver, address)
+}
+
+/// Deliveries of a webhook once `count` of them have been attempted
+async fn wait_for_attempts(app: &Router, webhook_id: &str, count: usize) -> Vec<Value> {
+    for _ in 0..100 {
+        let (status, deliveries) = send_json(
+            app,
+            "GET",
+            &format!("/api/webhooks/{webhook_id}/deliveries"),
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK, "{deliveries}");
+        let deliveries = deliveries.as_array().unwrap().clone();
+        if deliveries.iter().filter(|d| d["attempts"] != 0).count() >= count {
+            return deliveries;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    panic!("Webhook deliveries were not attempted");
+}
+
+#[tokio::test]
+async fn test_webhook_subscription_validation() {
+    let app = setup_test_app().await;
+
+    let (status, types) = send_json(&app, "GET", "/api/webhooks/event_types", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let types = types.as_array().unwrap();
+    assert!(types.contains(&json!("experiment.processed")));
+    assert!(types.contains(&json!("project.created")));
+
+    for invalid in [
+        json!({"url": "ftp://example.com", "event_types": ["project.created"]}),
+        json!({"url": "http://example.com", "event_types": ["project.exploded"]}),
+        json!({"url": "http://example.com", "event_types": ["*"], "secret": "short"}),
+    ] {
+        let (status, _) = send_json(&app, "POST", "/api/webhooks", Some(&invalid)).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{invalid}");
+    }
+
+    let (status, webhook) = send_json(
+        &app,
+        "POST",
+        "/api/webhooks",
+        Some(&json!({"url": "https://example.com/hook", "event_types": ["*"]})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{webhook}");
+    assert!(webhook["secret"].as_str().unwrap().starts_with("whsec_"));
+    let uri = format!("/api/webhooks/{}", webhook["id"].as_str().unwrap());
+    let (status, _) = send_json(&app, "DELETE", &uri, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send_json(&app, "GET", &format!("{uri}/deliveries"), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_webhook_signed_delivery_and_retry() {
+    let (receiver, address) = start_receiver().await;
+
+    let db = setup_test_db().await;
+    let mut config = Config::for_tests();
+    config.keycloak_url = String::new();
+    let app = crate::routes::build_router(&db, &config);
+
+    let (status, webhook) = send_json(
+        &app,
+        "POST",
+        "/api/webhooks",
+        Some(&json!({
+            "url": format!("http://{address}/hook"),
+            "event_types": ["project.created"]
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{webhook}");
+    let secret = webhook["secret"].as_str().unwrap().to_string();
+    let webhook_id = webhook["id"].as_str().unwrap().to_string();
+    let (_, listed) = send_json(&app, "GET", "/api/webhooks", None).await;
+    assert!(listed[0].get("secret").is_none());
+
+    // A subscribed event is posted, signed with the secret
+    let (status, project) = send_json(
+        &app,
+        "POST",
+        "/api/projects",
+        Some(&json!({"name": "Webhook project"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{project}");
+    let deliveries = wait_for_attempts(&app, &webhook_id, 1).await;
+    assert_eq!(deliveries[0]["status"], "delivered");
+    assert_eq!(deliveries[0]["last_status_code"], 200);
+    let (headers, body) = receiver.received.lock().unwrap()[0].clone();
+    assert_eq!(headers[EVENT_HEADER], "project.created");
+    assert_eq!(
+        headers[DELIVERY_HEADER].to_str().unwrap(),
+        deliveries[0]["id"].as_str().unwrap()
+    );
+    let timestamp: i64 = headers[TIMESTAMP_HEADER].to_str().unwrap().parse().unwrap();
+    assert_eq!(
+        headers[SIGNATURE_HEADER].to_str().unwrap(),
+        sign(&secret, timestamp, &body).unwrap()
+    );
+    let payload: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(payload["event"], "project.created");
+    assert_eq!(payload["data"]["id"], project["id"]);
+    assert_eq!(payload["data"]["record"]["name"], "Webhook project");
+
+    // Events of other types are not sent
+    let project_id = project["id"].as_str().unwrap();
+    let (status, _) = send_json(
+        &app,
+        "PATCH",
+        &format!("/api/projects/{project_id}"),
+        Some(&json!({"name": "Renamed project"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // A failed delivery is kept pending and retried later
+    receiver.status.store(503, Ordering::SeqCst);
+    send_json(
+        &app,
+        "POST",
+        "/api/projects",
+        Some(&json!({"name": "Second project"})),
+    )
+    .await;
+    let deliveries = wait_for_attempts(&app, &webhook_id, 2).await;
+    assert_eq!(deliveries.len(), 2);
+    let failed = &deliveries[0];
+    assert_eq!(failed["status"], "pending");
+    assert_eq!(failed["attempts"], 1);
+    assert_eq!(failed["last_status_code"], 503);
+    assert!(failed["next_attempt_at"].is_string());
+    assert_eq!(deliver_due(&db).await.unwrap(), 0);
+
+    receiver.status.store(200, Ordering::SeqCst);
+    let failed_id = Uuid::parse_str(failed["id"].as_str().unwrap()).unwrap();
+    let mut due: DeliveryActiveModel = Deliveries::find_by_id(failed_id)
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap()
+        .into_active_model();
+    due.next_attempt_at = Set(Some(Utc::now() - Duration::seconds(1)));
+    due.update(&db).await.unwrap();
+    assert_eq!(deliver_due(&db).await.unwrap(), 1);
+    let retried = Deliveries::find_by_id(failed_id)
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(retried.attempts, 2);
+    assert!(retried.delivered_at.is_some());
+    assert_eq!(receiver.received.lock().unwrap().len(), 3);
+
+    // A delivery can be sent again by hand
+    let (status, redelivered) = send_json(
+        &app,
+        "POST",
+        &format!("/api/webhooks/{webhook_id}/deliveries/{failed_id}/redeliver"),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{redelivered}");
+    assert_eq!(redelivered["attempts"], 3);
+}
@@ -0,0 +1,263 @@
+use super::deliveries::models::Model as WebhookDelivery;
+use super::models::{CreatedWebhook, Webhook, WebhookCreate};
+use super::services::{
+    create_webhook, delete_webhook, event_types, get_webhook, list_deliveries, list_webhooks,
+    redeliver,
+};
+use crate::audit::services::{AuditedResource, audit_changes};
+use crate::common::auth::{Role, RouteAccess, require_role};
+use crate::common::keycloak::authenticate;
+use crate::common::state::AppState;
+use crate::idempotency::services::ShowsSecret;
+use axum::{
+    Extension, Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    middleware,
+    routing::{get, post},
+};
+use axum_keycloak_auth::{PassthroughMode, decode::KeycloakToken};
+use sea_orm::DbErr;
+use serde::Deserialize;
+use utoipa::{IntoParams, OpenApi};
+use utoipa_axum::router::OpenApiRouter;
+use uuid::Uuid;
+
+const DEFAULT_DELIVERY_LIMIT: u64 = 100;
+const MAX_DELIVERY_LIMIT: u64 = 1000;
+
+fn error_response(e: DbErr) -> (StatusCode, String) {
+    match e {
+        DbErr::RecordNotFound(message) => (StatusCode::NOT_FOUND, message),
+        DbErr::Custom(message) => (StatusCode::UNPROCESSABLE_ENTITY, message),
+        e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, IntoParams)]
+pub struct DeliveryQuery {
+    /// Number of deliveries, newest first; 100 by default and at most 1000
+    pub limit: Option<u64>,
+}
+
+/// List webhooks
+#[utoipa::path(
+    get,
+    path = "",
+    responses(
+        (status = 200, description = "Webhooks, newest first, without their secrets", body = Vec<Webhook>),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "webhooks",
+    summary = "List webhooks",
+    description = "List the URLs subscribed to events, with the event types sent to each. Secrets are only shown when a webhook is created"
+)]
+pub async fn get_webhooks(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<Webhook>>, (StatusCode, String)> {
+    list_webhooks(&state.db)
+        .await
+        .map(Json)
+        .map_err(error_response)
+}
+
+/// List event types
+#[utoipa::path(
+    get,
+    path = "/event_types",
+    responses(
+        (status = 200, description = "Event types webhooks can subscribe to", body = Vec<String>)
+    ),
+    tag = "webhooks",
+    summary = "List event types",
+    description = "List the event types webhooks can subscribe to: `experiment.processed` when an experiment's data file has been processed, `asset.uploaded` when a file is uploaded to an experiment, and `<record>.created`, `.updated` and `.deleted` for changes to records"
+)]
+pub async fn get_event_types() -> Json<Vec<String>> {
+    Json(event_types())
+}
+
+/// Create a webhook
+#[utoipa::path(
+    post,
+    path = "",
+    request_body = WebhookCreate,
+    responses(
+        (status = 201, description = "The webhook with its secret, shown only this once", body = CreatedWebhook),
+        (status = 422, description = "Invalid URL, unknown event type, or short secret"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "webhooks",
+    summary = "Create a webhook",
+    description = "Subscribe a URL to event types. Each event is posted to it as JSON, signed in `X-Spice-Signature` with `sha256=` and the hex HMAC-SHA256, keyed with the secret, of the `X-Spice-Timestamp`, a dot and the body. Deliveries not answered with 2xx are retried with growing waits"
+)]
+pub async fn post_webhook(
+    State(state): State<AppState>,
+    token: Option<Extension<KeycloakToken<Role>>>,
+    Json(input): Json<WebhookCreate>,
+) -> Result<(StatusCode, Extension<ShowsSecret>, Json<CreatedWebhook>), (StatusCode, String)> {
+    let created_by = token.map(|Extension(token)| token.extra.profile.preferred_username);
+    create_webhook(&state.db, input, created_by)
+        .await
+        .map(|created| (StatusCode::CREATED, Extension(ShowsSecret), Json(created)))
+        .map_err(error_response)
+}
+
+/// Get a webhook
+#[utoipa::path(
+    get,
+    path = "/{id}",
+    params(
+        ("id" = Uuid, Path, description = "Webhook ID")
+    ),
+    responses(
+        (status = 200, description = "The webhook, without its secret", body = Webhook),
+        (status = 404, description = "Webhook not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "webhooks",
+    summary = "Get a webhook"
+)]
+pub async fn get_one_webhook(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Webhook>, (StatusCode, String)> {
+    get_webhook(&state.db, id)
+        .await
+        .map(Json)
+        .map_err(error_response)
+}
+
+/// Delete a webhook
+#[utoipa::path(
+    delete,
+    path = "/{id}",
+    params(
+        ("id" = Uuid, Path, description = "Webhook ID")
+    ),
+    responses(
+        (status = 200, description = "The deleted webhook", body = Webhook),
+        (status = 404, description = "Webhook not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "webhooks",
+    summary = "Delete a webhook",
+    description = "Stop sending events to the URL, forgetting its deliveries"
+)]
+pub async fn delete_one_webhook(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Webhook>, (StatusCode, String)> {
+    delete_webhook(&state.db, id)
+        .await
+        .map(Json)
+        .map_err(error_response)
+}
+
+/// List a webhook's deliveries
+#[utoipa::path(
+    get,
+    path = "/{id}/deliveries",
+    params(
+        ("id" = Uuid, Path, description = "Webhook ID"),
+        DeliveryQuery
+    ),
+    responses(
+        (status = 200, description = "Deliveries, newest first", body = Vec<WebhookDelivery>),
+        (status = 404, description = "Webhook not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "webhooks",
+    summary = "List a webhook's deliveries",
+    description = "List the events sent to a webhook with their payload, status, attempts, the last answer or error, and when a pending one is tried next"
+)]
+pub async fn get_deliveries(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<DeliveryQuery>,
+) -> Result<Json<Vec<WebhookDelivery>>, (StatusCode, String)> {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_DELIVERY_LIMIT)
+        .clamp(1, MAX_DELIVERY_LIMIT);
+    list_deliveries(&state.db, id, limit)
+        .await
+        .map(Json)
+        .map_err(error_response)
+}
+
+/// Send a delivery again
+#[utoipa::path(
+    post,
+    path = "/{id}/deliveries/{delivery_id}/redeliver",
+    params(
+        ("id" = Uuid, Path, description = "Webhook ID"),
+        ("delivery_id" = Uuid, Path, description = "Delivery ID")
+    ),
+    responses(
+        (status = 200, description = "The delivery after the attempt", body = WebhookDelivery),
+        (status = 404, description = "Delivery not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "webhooks",
+    summary = "Send a delivery again",
+    description = "Post a delivery to the webhook's URL now, such as one that failed while the receiver was down, and give how it went. A delivery that fails again is retried as before"
+)]
+pub async fn post_redelivery(
+    State(state): State<AppState>,
+    Path((id, delivery_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<WebhookDelivery>, (StatusCode, String)> {
+    redeliver(&state.db, id, delivery_id)
+        .await
+        .map(Json)
+        .map_err(error_response)
+}
+
+/// Documentation of the routes, which are routed with `route`
+#[derive(OpenApi)]
+#[openapi(paths(
+    get_webhooks,
+    get_event_types,
+    post_webhook,
+    get_one_webhook,
+    delete_one_webhook,
+    get_deliveries,
+    post_redelivery
+))]
+struct WebhooksApi;
+
+pub fn router(state: &AppState) -> OpenApiRouter {
+    let mut router = OpenApiRouter::new()
+        .route("/", get(get_webhooks).post(post_webhook))
+        .route("/event_types", get(get_event_types))
+        .route("/{id}", get(get_one_webhook).delete(delete_one_webhook))
+        .route("/{id}/deliveries", get(get_deliveries))
+        .route(
+            "/{id}/deliveries/{delivery_id}/redeliver",
+            post(post_redelivery),
+        )
+        .with_state(state.clone());
+    router.get_openapi_mut().merge(WebhooksApi::openapi());
+
+    // Changes are logged with the user who made them
+    router = router.layer(middleware::from_fn_with_state(
+        (state.db.clone(), AuditedResource::Webhooks),
+        audit_changes,
+    ));
+
+    // Webhooks are managed by signed-in administrators, not with keys
+    if let Some(instance) = state.keycloak_auth_instance.clone() {
+        router = router
+            .layer(middleware::from_fn_with_state(
+                RouteAccess::ADMINISTRATION,
+                require_role,
+            ))
+            .layer(middleware::from_fn_with_state(
+                (instance, PassthroughMode::Block),
+                authenticate,
+            ));
+    } else if !state.config.tests_running {
+        println!("Warning: Webhook routes are not protected");
+    }
+
+    router
+}